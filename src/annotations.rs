@@ -0,0 +1,778 @@
+//! Summaries and notes attached to files and symbols
+//!
+//! Summarizing a file or a symbol with an LLM is expensive, and an assistant that
+//! has done it once should not have to do it again next session. Annotations
+//! store such summaries in `annotations.json` in the database directory; they are
+//! written with `PUT /api/v1/annotations`, matched against search queries by
+//! their words, and returned alongside search results for the files and symbols
+//! they describe.
+//!
+//! The same file holds notes left by people: free text, tags and bookmarks on a
+//! symbol, a file, or a range of its lines ("this is the real entry point").
+//! Notes are returned with the search, caller and impact results they point at.
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::path_utils::paths_equivalent;
+
+/// File name of the annotation store inside a database directory
+pub const ANNOTATIONS_FILE: &str = "annotations.json";
+
+/// Upper bound on annotations returned alongside one search
+pub const MAX_ANNOTATIONS_PER_SEARCH: usize = 10;
+
+/// Upper bound on notes returned alongside one search or relationship query
+pub const MAX_NOTES_PER_RESULT: usize = 20;
+
+const MAX_SUMMARY_LEN: usize = 16 * 1024;
+const MAX_TARGET_LEN: usize = 1024;
+const MAX_SOURCE_LEN: usize = 128;
+const MAX_TAGS: usize = 16;
+const MAX_TAG_LEN: usize = 64;
+
+/// What an annotation describes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AnnotationKind {
+    /// A file, by its repository-relative path
+    File,
+    /// A symbol, by its name
+    Symbol,
+}
+
+/// A stored summary of one file or symbol
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Annotation {
+    pub kind: AnnotationKind,
+    /// File path or symbol name
+    pub target: String,
+    pub summary: String,
+    /// Who produced the summary (model or tool name), if given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    /// RFC 3339 time of the last write
+    pub updated_at: String,
+}
+
+impl Annotation {
+    fn describes(&self, kind: AnnotationKind, target: &str) -> bool {
+        self.kind == kind
+            && match kind {
+                AnnotationKind::File => paths_equivalent(&self.target, target),
+                AnnotationKind::Symbol => self.target == target,
+            }
+    }
+}
+
+/// Inclusive, 1-based range of lines a note points at
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LineRange {
+    pub start: u32,
+    pub end: u32,
+}
+
+impl LineRange {
+    fn overlaps(&self, other: &LineRange) -> bool {
+        self.start <= other.end && other.start <= self.end
+    }
+
+    /// Split a `path:start-end` or `path:line` target into the path and its lines
+    ///
+    /// Targets without a line suffix are returned whole.
+    pub fn split_target(target: &str) -> (&str, Option<LineRange>) {
+        let Some((path, suffix)) = target.rsplit_once(':') else {
+            return (target, None);
+        };
+        let (start, end) = suffix.split_once('-').unwrap_or((suffix, suffix));
+        match (start.parse(), end.parse()) {
+            (Ok(start), Ok(end)) if !path.is_empty() => (path, Some(LineRange { start, end })),
+            _ => (target, None),
+        }
+    }
+}
+
+/// A note, tag set or bookmark left on a file, a range of its lines, or a symbol
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Note {
+    pub id: String,
+    pub kind: AnnotationKind,
+    /// File path or symbol name
+    pub target: String,
+    /// Lines of the file the note is about; only file notes have them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lines: Option<LineRange>,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub text: String,
+    /// Lowercase tags without a leading `#`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub bookmark: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    /// RFC 3339 time the note was left
+    pub created_at: String,
+    /// RFC 3339 time of the last edit
+    pub updated_at: String,
+}
+
+impl Note {
+    /// Whether the note is about `path`, or the part of it `lines` cover
+    fn points_at_file(&self, path: &str, lines: Option<LineRange>) -> bool {
+        self.kind == AnnotationKind::File
+            && paths_equivalent(&self.target, path)
+            && match (self.lines, lines) {
+                (Some(noted), Some(lines)) => noted.overlaps(&lines),
+                _ => true,
+            }
+    }
+}
+
+/// A note to leave with [`AnnotationStore::add_note`]
+#[derive(Debug, Clone, Deserialize)]
+pub struct NewNote {
+    pub kind: AnnotationKind,
+    pub target: String,
+    #[serde(default)]
+    pub lines: Option<LineRange>,
+    #[serde(default)]
+    pub text: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub bookmark: bool,
+    #[serde(default)]
+    pub author: Option<String>,
+}
+
+/// Changes to a note, applied with [`AnnotationStore::update_note`]
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct NoteUpdate {
+    pub text: Option<String>,
+    pub tags: Option<Vec<String>>,
+    pub bookmark: Option<bool>,
+}
+
+/// Which notes [`AnnotationStore::notes`] lists; unset fields match every note
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct NoteFilter {
+    pub kind: Option<AnnotationKind>,
+    pub target: Option<String>,
+    pub tag: Option<String>,
+    pub bookmark: Option<bool>,
+}
+
+/// All annotations and notes of a database, ordered by kind and target
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AnnotationStore {
+    #[serde(default)]
+    pub annotations: Vec<Annotation>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub notes: Vec<Note>,
+}
+
+impl AnnotationStore {
+    /// Location of the store for a database directory
+    pub fn path_for(db_path: &Path) -> PathBuf {
+        db_path.join(ANNOTATIONS_FILE)
+    }
+
+    /// Directory of one tenant's store when several share a database
+    ///
+    /// `owner` is reduced to ASCII alphanumerics, `-` and `_`, so it cannot name a
+    /// directory outside `db_path/annotations`.
+    pub fn tenant_dir(db_path: &Path, owner: &str) -> PathBuf {
+        let owner: String = owner
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        db_path.join("annotations").join(owner)
+    }
+
+    /// Load the annotations of a database, or an empty store if none were saved
+    pub fn load(db_path: &Path) -> Result<Self> {
+        let path = Self::path_for(db_path);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read annotations: {}", path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse annotations: {}", path.display()))
+    }
+
+    /// Save the store atomically into a database directory
+    pub fn save(&self, db_path: &Path) -> Result<()> {
+        std::fs::create_dir_all(db_path)
+            .with_context(|| format!("Failed to create {}", db_path.display()))?;
+        let path = Self::path_for(db_path);
+        let tmp_path = path.with_extension("json.tmp");
+        std::fs::write(&tmp_path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write annotations: {}", tmp_path.display()))?;
+        std::fs::rename(&tmp_path, &path)
+            .with_context(|| format!("Failed to replace annotations: {}", path.display()))?;
+        Ok(())
+    }
+
+    /// Whether the store holds neither annotations nor notes
+    pub fn is_empty(&self) -> bool {
+        self.annotations.is_empty() && self.notes.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.annotations.len()
+    }
+
+    /// Store the summary of a file or symbol, replacing any previous one
+    ///
+    /// Returns the stored annotation and whether it is new.
+    pub fn put(
+        &mut self,
+        kind: AnnotationKind,
+        target: &str,
+        summary: &str,
+        source: Option<&str>,
+    ) -> Result<(Annotation, bool)> {
+        let target = normalize_target(kind, target)?;
+        let summary = summary.trim();
+        if summary.is_empty() {
+            bail!("Annotation summary cannot be empty");
+        }
+        if summary.len() > MAX_SUMMARY_LEN {
+            bail!("Annotation summaries are limited to {MAX_SUMMARY_LEN} bytes");
+        }
+        let source = source.map(str::trim).filter(|s| !s.is_empty());
+        if source.is_some_and(|s| s.len() > MAX_SOURCE_LEN) {
+            bail!("Annotation sources are limited to {MAX_SOURCE_LEN} bytes");
+        }
+
+        let annotation = Annotation {
+            kind,
+            target: target.clone(),
+            summary: summary.to_string(),
+            source: source.map(str::to_string),
+            updated_at: chrono::Utc::now().to_rfc3339(),
+        };
+        if let Some(existing) = self
+            .annotations
+            .iter_mut()
+            .find(|existing| existing.describes(kind, &target))
+        {
+            *existing = annotation.clone();
+            return Ok((annotation, false));
+        }
+        self.annotations.push(annotation.clone());
+        self.annotations
+            .sort_by(|a, b| (a.kind, &a.target).cmp(&(b.kind, &b.target)));
+        Ok((annotation, true))
+    }
+
+    /// The annotation of a file or symbol, if one was stored
+    pub fn get(&self, kind: AnnotationKind, target: &str) -> Option<&Annotation> {
+        let target = normalize_target(kind, target).ok()?;
+        self.annotations
+            .iter()
+            .find(|annotation| annotation.describes(kind, &target))
+    }
+
+    /// Remove the annotation of a file or symbol, returning whether one existed
+    pub fn remove(&mut self, kind: AnnotationKind, target: &str) -> Result<bool> {
+        let target = normalize_target(kind, target)?;
+        let before = self.annotations.len();
+        self.annotations
+            .retain(|annotation| !annotation.describes(kind, &target));
+        Ok(self.annotations.len() != before)
+    }
+
+    /// Annotations whose target or summary contains every word of `query`
+    ///
+    /// Matching is case-insensitive; annotations mentioning the words most often
+    /// come first.
+    pub fn search(&self, query: &str, limit: usize) -> Vec<&Annotation> {
+        let words: Vec<String> = query
+            .split_whitespace()
+            .map(|word| word.trim_matches('*').to_lowercase())
+            .filter(|word| !word.is_empty())
+            .collect();
+        if words.is_empty() {
+            return Vec::new();
+        }
+
+        let mut scored: Vec<(usize, &Annotation)> = self
+            .annotations
+            .iter()
+            .filter_map(|annotation| {
+                let text = format!("{}\n{}", annotation.target, annotation.summary).to_lowercase();
+                let mut hits = 0;
+                for word in &words {
+                    match text.matches(word.as_str()).count() {
+                        0 => return None,
+                        count => hits += count,
+                    }
+                }
+                Some((hits, annotation))
+            })
+            .collect();
+        scored.sort_by_key(|(hits, _)| std::cmp::Reverse(*hits));
+        scored
+            .into_iter()
+            .take(limit)
+            .map(|(_, annotation)| annotation)
+            .collect()
+    }
+
+    /// Annotations to return alongside a search for `query`
+    ///
+    /// Annotations of the result files and symbols come first, in result order,
+    /// followed by annotations matching the query itself.
+    pub fn for_results<'a>(
+        &self,
+        query: &str,
+        paths: impl IntoIterator<Item = &'a str>,
+        symbols: impl IntoIterator<Item = &'a str>,
+        limit: usize,
+    ) -> Vec<Annotation> {
+        let mut selected: Vec<&Annotation> = Vec::new();
+        let attached = paths
+            .into_iter()
+            .filter_map(|path| self.get(AnnotationKind::File, path))
+            .chain(
+                symbols
+                    .into_iter()
+                    .filter_map(|name| self.get(AnnotationKind::Symbol, name)),
+            );
+        for annotation in attached.chain(self.search(query, limit)) {
+            if selected.len() >= limit {
+                break;
+            }
+            if !selected.contains(&annotation) {
+                selected.push(annotation);
+            }
+        }
+        selected.into_iter().cloned().collect()
+    }
+
+    /// Leave a note; it needs text, a tag or a bookmark
+    pub fn add_note(&mut self, note: NewNote) -> Result<Note> {
+        let target = normalize_target(note.kind, &note.target)?;
+        if let Some(lines) = note.lines {
+            if note.kind != AnnotationKind::File {
+                bail!("Only file notes can point at lines");
+            }
+            if lines.start == 0 || lines.start > lines.end {
+                bail!("Note lines must be a 1-based range with start <= end");
+            }
+        }
+        let text = normalize_note_text(&note.text)?;
+        let tags = normalize_tags(&note.tags)?;
+        if text.is_empty() && tags.is_empty() && !note.bookmark {
+            bail!("A note needs text, a tag or a bookmark");
+        }
+        let author = note
+            .author
+            .as_deref()
+            .map(str::trim)
+            .filter(|author| !author.is_empty());
+        if author.is_some_and(|author| author.len() > MAX_SOURCE_LEN) {
+            bail!("Note authors are limited to {MAX_SOURCE_LEN} bytes");
+        }
+
+        let now = chrono::Utc::now().to_rfc3339();
+        let note = Note {
+            id: uuid::Uuid::new_v4().to_string(),
+            kind: note.kind,
+            target,
+            lines: note.lines,
+            text,
+            tags,
+            bookmark: note.bookmark,
+            author: author.map(str::to_string),
+            created_at: now.clone(),
+            updated_at: now,
+        };
+        self.notes.push(note.clone());
+        self.notes.sort_by(|a, b| {
+            (a.kind, &a.target, a.lines.map(|l| l.start)).cmp(&(
+                b.kind,
+                &b.target,
+                b.lines.map(|l| l.start),
+            ))
+        });
+        Ok(note)
+    }
+
+    /// Edit a note, returning it, or `None` if no note has `id`
+    pub fn update_note(&mut self, id: &str, update: NoteUpdate) -> Result<Option<Note>> {
+        let Some(note) = self.notes.iter_mut().find(|note| note.id == id) else {
+            return Ok(None);
+        };
+        let text = match &update.text {
+            Some(text) => normalize_note_text(text)?,
+            None => note.text.clone(),
+        };
+        let tags = match &update.tags {
+            Some(tags) => normalize_tags(tags)?,
+            None => note.tags.clone(),
+        };
+        let bookmark = update.bookmark.unwrap_or(note.bookmark);
+        if text.is_empty() && tags.is_empty() && !bookmark {
+            bail!("A note needs text, a tag or a bookmark");
+        }
+        note.text = text;
+        note.tags = tags;
+        note.bookmark = bookmark;
+        note.updated_at = chrono::Utc::now().to_rfc3339();
+        Ok(Some(note.clone()))
+    }
+
+    /// Remove a note, returning whether it existed
+    pub fn remove_note(&mut self, id: &str) -> bool {
+        let before = self.notes.len();
+        self.notes.retain(|note| note.id != id);
+        self.notes.len() != before
+    }
+
+    /// Notes matching every set field of `filter`
+    ///
+    /// A file `target` also matches the notes on ranges of that file.
+    pub fn notes(&self, filter: &NoteFilter) -> Vec<&Note> {
+        let tag = filter
+            .tag
+            .as_deref()
+            .map(|tag| tag.trim().trim_start_matches('#').to_lowercase());
+        self.notes
+            .iter()
+            .filter(|note| filter.kind.is_none_or(|kind| note.kind == kind))
+            .filter(|note| {
+                filter.target.as_deref().is_none_or(|target| {
+                    match normalize_target(note.kind, target) {
+                        Ok(target) => match note.kind {
+                            AnnotationKind::File => paths_equivalent(&note.target, &target),
+                            AnnotationKind::Symbol => note.target == target,
+                        },
+                        Err(_) => false,
+                    }
+                })
+            })
+            .filter(|note| tag.as_ref().is_none_or(|tag| note.tags.contains(tag)))
+            .filter(|note| {
+                filter
+                    .bookmark
+                    .is_none_or(|bookmark| note.bookmark == bookmark)
+            })
+            .collect()
+    }
+
+    /// Notes to return alongside results pointing at `files` and `symbols`
+    ///
+    /// A file result with lines (a symbol definition or call site) only picks up
+    /// the file's notes on overlapping lines and on the whole file. Notes come in
+    /// result order, each once.
+    pub fn notes_for_results<'a>(
+        &self,
+        files: impl IntoIterator<Item = (&'a str, Option<LineRange>)>,
+        symbols: impl IntoIterator<Item = &'a str>,
+        limit: usize,
+    ) -> Vec<Note> {
+        if self.notes.is_empty() {
+            return Vec::new();
+        }
+        let mut selected: Vec<&Note> = Vec::new();
+        let symbol_notes = symbols.into_iter().flat_map(|name| {
+            self.notes
+                .iter()
+                .filter(move |note| note.kind == AnnotationKind::Symbol && note.target == name)
+        });
+        let file_notes = files.into_iter().flat_map(|(path, lines)| {
+            self.notes
+                .iter()
+                .filter(move |note| note.points_at_file(path, lines))
+        });
+        for note in symbol_notes.chain(file_notes) {
+            if selected.len() >= limit {
+                break;
+            }
+            if !selected.iter().any(|selected| selected.id == note.id) {
+                selected.push(note);
+            }
+        }
+        selected.into_iter().cloned().collect()
+    }
+}
+
+fn normalize_target(kind: AnnotationKind, target: &str) -> Result<String> {
+    let mut target = target.trim().to_string();
+    if kind == AnnotationKind::File {
+        target = target.replace('\\', "/");
+        while let Some(stripped) = target.strip_prefix("./") {
+            target = stripped.to_string();
+        }
+    }
+    if target.is_empty() {
+        bail!("Annotation target cannot be empty");
+    }
+    if target.len() > MAX_TARGET_LEN {
+        bail!("Annotation targets are limited to {MAX_TARGET_LEN} bytes");
+    }
+    Ok(target)
+}
+
+fn normalize_note_text(text: &str) -> Result<String> {
+    let text = text.trim();
+    if text.len() > MAX_SUMMARY_LEN {
+        bail!("Note text is limited to {MAX_SUMMARY_LEN} bytes");
+    }
+    Ok(text.to_string())
+}
+
+fn normalize_tags(tags: &[String]) -> Result<Vec<String>> {
+    let mut normalized: Vec<String> = tags
+        .iter()
+        .map(|tag| tag.trim().trim_start_matches('#').to_lowercase())
+        .filter(|tag| !tag.is_empty())
+        .collect();
+    normalized.sort();
+    normalized.dedup();
+    if normalized.len() > MAX_TAGS {
+        bail!("Notes are limited to {MAX_TAGS} tags");
+    }
+    if let Some(tag) = normalized
+        .iter()
+        .find(|tag| tag.len() > MAX_TAG_LEN || tag.contains(char::is_whitespace))
+    {
+        bail!("Invalid tag '{tag}': tags are single words of up to {MAX_TAG_LEN} bytes");
+    }
+    Ok(normalized)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store() -> AnnotationStore {
+        let mut store = AnnotationStore::default();
+        store
+            .put(
+                AnnotationKind::File,
+                "./src/file_storage.rs",
+                "Page-based storage engine with a write-ahead log",
+                Some("assistant"),
+            )
+            .unwrap();
+        store
+            .put(
+                AnnotationKind::Symbol,
+                "open_storage_engine",
+                "Opens the storage engine, replaying the log after a crash",
+                None,
+            )
+            .unwrap();
+        store
+    }
+
+    #[test]
+    fn put_replaces_and_remove_deletes_by_target() {
+        let mut store = store();
+        let (annotation, created) = store
+            .put(
+                AnnotationKind::File,
+                "src/file_storage.rs",
+                "Crash-safe file storage",
+                None,
+            )
+            .unwrap();
+        assert!(!created);
+        assert_eq!(annotation.target, "src/file_storage.rs");
+        assert_eq!(store.len(), 2);
+        assert_eq!(
+            store
+                .get(AnnotationKind::File, "src/file_storage.rs")
+                .unwrap()
+                .summary,
+            "Crash-safe file storage"
+        );
+        assert!(store
+            .get(AnnotationKind::Symbol, "src/file_storage.rs")
+            .is_none());
+        assert!(store.put(AnnotationKind::Symbol, "x", "  ", None).is_err());
+
+        assert!(store
+            .remove(AnnotationKind::Symbol, "open_storage_engine")
+            .unwrap());
+        assert!(!store
+            .remove(AnnotationKind::Symbol, "open_storage_engine")
+            .unwrap());
+    }
+
+    #[test]
+    fn search_requires_every_word_and_results_come_first() {
+        let store = store();
+        let hits: Vec<&str> = store
+            .search("Storage LOG", 10)
+            .iter()
+            .map(|a| a.target.as_str())
+            .collect();
+        assert_eq!(hits, vec!["src/file_storage.rs", "open_storage_engine"]);
+        assert!(store.search("storage network", 10).is_empty());
+
+        let alongside = store.for_results("crash", ["src/file_storage.rs"], [], 10);
+        let targets: Vec<&str> = alongside.iter().map(|a| a.target.as_str()).collect();
+        assert_eq!(targets, vec!["src/file_storage.rs", "open_storage_engine"]);
+    }
+
+    fn note(kind: AnnotationKind, target: &str, lines: Option<(u32, u32)>, text: &str) -> NewNote {
+        NewNote {
+            kind,
+            target: target.to_string(),
+            lines: lines.map(|(start, end)| LineRange { start, end }),
+            text: text.to_string(),
+            tags: Vec::new(),
+            bookmark: false,
+            author: None,
+        }
+    }
+
+    #[test]
+    fn notes_attach_to_overlapping_lines_and_symbols() {
+        let mut store = AnnotationStore::default();
+        let entry = store
+            .add_note(NewNote {
+                tags: vec!["#Entry-Point".to_string(), "entry-point".to_string()],
+                bookmark: true,
+                ..note(
+                    AnnotationKind::File,
+                    "src/main.rs",
+                    Some((10, 20)),
+                    "This is the real entry point",
+                )
+            })
+            .unwrap();
+        assert_eq!(entry.tags, vec!["entry-point"]);
+        store
+            .add_note(note(
+                AnnotationKind::File,
+                "src/main.rs",
+                None,
+                "Binary crate",
+            ))
+            .unwrap();
+        store
+            .add_note(note(
+                AnnotationKind::Symbol,
+                "run",
+                None,
+                "Blocks until shutdown",
+            ))
+            .unwrap();
+
+        assert!(store
+            .add_note(note(AnnotationKind::Symbol, "run", Some((1, 2)), "x"))
+            .is_err());
+        assert!(store
+            .add_note(note(AnnotationKind::File, "src/main.rs", None, " "))
+            .is_err());
+
+        let texts =
+            |notes: Vec<Note>| -> Vec<String> { notes.into_iter().map(|n| n.text).collect() };
+        let range = |start, end| Some(LineRange { start, end });
+        assert_eq!(
+            texts(store.notes_for_results(
+                [("repos/app/files/src/main.rs", range(18, 30))],
+                ["run"],
+                10
+            )),
+            vec![
+                "Blocks until shutdown",
+                "Binary crate",
+                "This is the real entry point"
+            ]
+        );
+        assert_eq!(
+            texts(store.notes_for_results([("src/main.rs", range(30, 40))], [], 10)),
+            vec!["Binary crate"]
+        );
+
+        let bookmarked = store.notes(&NoteFilter {
+            tag: Some("#entry-point".to_string()),
+            bookmark: Some(true),
+            ..Default::default()
+        });
+        assert_eq!(bookmarked.len(), 1);
+        assert_eq!(bookmarked[0].id, entry.id);
+
+        let updated = store
+            .update_note(
+                &entry.id,
+                NoteUpdate {
+                    text: Some(String::new()),
+                    tags: Some(Vec::new()),
+                    ..Default::default()
+                },
+            )
+            .unwrap()
+            .unwrap();
+        assert!(updated.bookmark && updated.text.is_empty());
+        assert!(store
+            .update_note(
+                &entry.id,
+                NoteUpdate {
+                    bookmark: Some(false),
+                    ..Default::default()
+                }
+            )
+            .is_err());
+        assert!(store.remove_note(&entry.id));
+        assert!(store
+            .update_note(&entry.id, NoteUpdate::default())
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn split_target_reads_line_suffixes() {
+        assert_eq!(
+            LineRange::split_target("src/main.rs:10-20"),
+            ("src/main.rs", Some(LineRange { start: 10, end: 20 }))
+        );
+        assert_eq!(
+            LineRange::split_target("src/main.rs:7"),
+            ("src/main.rs", Some(LineRange { start: 7, end: 7 }))
+        );
+        assert_eq!(
+            LineRange::split_target("FileStorage::new"),
+            ("FileStorage::new", None)
+        );
+        assert_eq!(
+            LineRange::split_target("src/main.rs"),
+            ("src/main.rs", None)
+        );
+    }
+
+    #[test]
+    fn round_trips_through_the_database_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(AnnotationStore::load(dir.path()).unwrap().is_empty());
+
+        let store = store();
+        store.save(dir.path()).unwrap();
+        assert_eq!(AnnotationStore::load(dir.path()).unwrap(), store);
+
+        // Tenants get separate stores that owner names cannot escape
+        let tenant = AnnotationStore::tenant_dir(dir.path(), "../key-7");
+        assert_eq!(tenant, dir.path().join("annotations").join("___key-7"));
+        store.save(&tenant).unwrap();
+        assert!(
+            AnnotationStore::load(&AnnotationStore::tenant_dir(dir.path(), "key-8"))
+                .unwrap()
+                .is_empty()
+        );
+    }
+}
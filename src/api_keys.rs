@@ -14,6 +14,8 @@ use sqlx::{postgres::PgPoolOptions, PgPool};
 use std::time::Duration;
 use tracing::{info, instrument, warn};
 
+use crate::services::RedactionRules;
+
 /// API key prefix for easy identification
 const API_KEY_PREFIX: &str = "kdb_live_";
 
@@ -78,6 +80,8 @@ pub struct ApiKey {
     pub description: Option<String>,
     /// IP restrictions (JSON array of allowed IPs)
     pub allowed_ips: Option<serde_json::Value>,
+    /// Path patterns hidden from requests made with this key (JSON array)
+    pub redaction_rules: Option<serde_json::Value>,
 }
 
 /// Request to create a new API key
@@ -90,6 +94,8 @@ pub struct CreateApiKeyRequest {
     pub monthly_quota: Option<u64>,
     pub expires_at: Option<DateTime<Utc>>,
     pub allowed_ips: Option<Vec<String>>,
+    /// Gitignore-style path patterns whose documents and symbols this key never sees
+    pub redaction_rules: Option<Vec<String>>,
 }
 
 /// Response when creating a new API key
@@ -100,6 +106,9 @@ pub struct CreateApiKeyResponse {
     pub created_at: DateTime<Utc>,
     pub rate_limit: u32,
     pub monthly_quota: u64,
+    /// Path patterns hidden from requests made with the key
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub redaction_rules: Vec<String>,
 }
 
 /// API key validation result
@@ -112,6 +121,8 @@ pub struct ApiKeyValidation {
     pub remaining_quota: u64,
     pub is_valid: bool,
     pub rejection_reason: Option<String>,
+    /// Paths hidden from requests made with the key
+    pub redaction: RedactionRules,
 }
 
 /// Service for managing API keys
@@ -167,6 +178,9 @@ impl ApiKeyService {
             CREATE INDEX IF NOT EXISTS idx_kotadb_api_keys_is_active ON kotadb_api_keys(is_active);
             CREATE INDEX IF NOT EXISTS idx_kotadb_api_keys_expires_at ON kotadb_api_keys(expires_at);
             
+            -- Path patterns hidden from requests made with a key
+            ALTER TABLE kotadb_api_keys ADD COLUMN IF NOT EXISTS redaction_rules JSONB;
+            
             -- Table for tracking API key usage
             CREATE TABLE IF NOT EXISTS kotadb_api_key_usage (
                 id BIGSERIAL PRIMARY KEY,
@@ -248,13 +262,21 @@ impl ApiKeyService {
             .transpose()
             .context("Failed to serialize allowed IPs")?;
 
+        // Reject invalid patterns before the key exists, so it never runs unredacted
+        let redaction_rules = request.redaction_rules.unwrap_or_default();
+        RedactionRules::new(redaction_rules.iter().cloned())?;
+        let stored_redaction_rules = (!redaction_rules.is_empty())
+            .then(|| serde_json::to_value(&redaction_rules))
+            .transpose()
+            .context("Failed to serialize redaction rules")?;
+
         // Insert into database
         let result = sqlx::query_as::<_, (i64, DateTime<Utc>)>(
             r#"
             INSERT INTO kotadb_api_keys (
                 key_hash, user_email, user_id, description,
-                rate_limit, monthly_quota, expires_at, allowed_ips
-            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+                rate_limit, monthly_quota, expires_at, allowed_ips, redaction_rules
+            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
             RETURNING id, created_at
             "#,
         )
@@ -266,6 +288,7 @@ impl ApiKeyService {
         .bind(monthly_quota)
         .bind(request.expires_at)
         .bind(&allowed_ips)
+        .bind(&stored_redaction_rules)
         .fetch_one(&self.pool)
         .await
         .context("Failed to create API key")?;
@@ -281,6 +304,7 @@ impl ApiKeyService {
             created_at: result.1,
             rate_limit: rate_limit as u32,
             monthly_quota: monthly_quota as u64,
+            redaction_rules,
         })
     }
 
@@ -306,6 +330,36 @@ impl ApiKeyService {
         .await
         .context("Failed to query API key")?;
 
+        self.validate_key_record(key_data, ip_address).await
+    }
+
+    /// Validate the API key with id `key_id`, for requests authorized by a URL it signed
+    #[instrument(skip(self))]
+    pub async fn validate_api_key_id(
+        &self,
+        key_id: i64,
+        ip_address: Option<&str>,
+    ) -> Result<ApiKeyValidation> {
+        let key_data = sqlx::query_as::<_, ApiKey>(
+            r#"
+            SELECT * FROM kotadb_api_keys
+            WHERE id = $1 AND is_active = TRUE
+            "#,
+        )
+        .bind(key_id)
+        .fetch_optional(&self.pool)
+        .await
+        .context("Failed to query API key")?;
+
+        self.validate_key_record(key_data, ip_address).await
+    }
+
+    /// Check expiry, IP restrictions and quota of a looked-up key
+    async fn validate_key_record(
+        &self,
+        key_data: Option<ApiKey>,
+        ip_address: Option<&str>,
+    ) -> Result<ApiKeyValidation> {
         let key_data = match key_data {
             Some(k) => k,
             None => {
@@ -317,6 +371,7 @@ impl ApiKeyService {
                     remaining_quota: 0,
                     is_valid: false,
                     rejection_reason: Some("Invalid or inactive API key".to_string()),
+                    redaction: RedactionRules::default(),
                 });
             }
         };
@@ -332,6 +387,7 @@ impl ApiKeyService {
                     remaining_quota: 0,
                     is_valid: false,
                     rejection_reason: Some("API key has expired".to_string()),
+                    redaction: RedactionRules::default(),
                 });
             }
         }
@@ -355,6 +411,7 @@ impl ApiKeyService {
                             rejection_reason: Some(
                                 "IP address not allowed for this key".to_string(),
                             ),
+                            redaction: RedactionRules::default(),
                         });
                     }
                 }
@@ -372,9 +429,14 @@ impl ApiKeyService {
                 remaining_quota: 0,
                 is_valid: false,
                 rejection_reason: Some("Monthly quota exceeded".to_string()),
+                redaction: RedactionRules::default(),
             });
         }
 
+        // Stored rules that no longer parse fail validation instead of being dropped
+        let redaction = RedactionRules::from_json(key_data.redaction_rules.as_ref())
+            .context("Failed to read redaction rules of API key")?;
+
         // Update last used timestamp
         sqlx::query("UPDATE kotadb_api_keys SET last_used_at = NOW() WHERE id = $1")
             .bind(key_data.id)
@@ -390,6 +452,7 @@ impl ApiKeyService {
             remaining_quota,
             is_valid: true,
             rejection_reason: None,
+            redaction,
         })
     }
 
@@ -4,9 +4,11 @@
 //! enforces rate limits, and records usage metrics.
 
 use crate::api_keys::ApiKeyService;
+use crate::services::RedactionRules;
+use crate::signed_urls::{self, SignedUrlError, UrlSigner};
 use axum::{
     extract::{ConnectInfo, Request, State},
-    http::{header, HeaderMap, StatusCode},
+    http::{header, HeaderMap, Method, StatusCode, Uri},
     middleware::Next,
     response::{IntoResponse, Json, Response},
 };
@@ -51,6 +53,8 @@ pub struct AuthContext {
     pub user_id: Option<String>,
     pub rate_limit: u32,
     pub remaining_quota: u64,
+    /// Paths the services hide from this request
+    pub redaction: RedactionRules,
 }
 
 /// Extract API key from request headers
@@ -118,26 +122,40 @@ pub async fn auth_middleware(
         return Ok(next.run(request).await);
     }
 
-    // Extract API key from headers
-    let api_key = extract_api_key(&headers).ok_or_else(|| {
-        warn!("Missing API key for request to {}", path);
-        AuthError {
-            error: "missing_api_key".to_string(),
-            message:
-                "API key is required. Include it in X-API-Key header or Authorization: Bearer <key>"
-                    .to_string(),
-            status_code: 401,
-        }
-    })?;
+    // Signed artifact URLs stand in for the API key that signed them
+    let signed_key_id = if signed_urls::has_signature(request.uri().query()) {
+        Some(verify_signed_url(&request)?)
+    } else {
+        None
+    };
 
     // Extract client IP
     let ip_address = extract_ip_address(&headers, Some(addr));
 
     // Validate API key
-    let validation = api_key_service
-        .validate_api_key(&api_key, ip_address.as_deref())
-        .await
-        .map_err(|e| {
+    let validation = match signed_key_id {
+        Some(key_id) => {
+            api_key_service
+                .validate_api_key_id(key_id, ip_address.as_deref())
+                .await
+        }
+        None => {
+            // Extract API key from headers
+            let api_key = extract_api_key(&headers).ok_or_else(|| {
+                warn!("Missing API key for request to {}", path);
+                AuthError {
+                    error: "missing_api_key".to_string(),
+                    message: "API key is required. Include it in X-API-Key header or Authorization: Bearer <key>"
+                        .to_string(),
+                    status_code: 401,
+                }
+            })?;
+            api_key_service
+                .validate_api_key(&api_key, ip_address.as_deref())
+                .await
+        }
+    }
+    .map_err(|e| {
             let error_id = Uuid::new_v4();
             warn!("API key validation error [{}]: {}", error_id, e);
             AuthError {
@@ -207,6 +225,7 @@ pub async fn auth_middleware(
         user_id: validation.user_id.clone(),
         rate_limit: validation.rate_limit,
         remaining_quota: validation.remaining_quota,
+        redaction: validation.redaction.clone(),
     };
 
     debug!(
@@ -217,6 +236,9 @@ pub async fn auth_middleware(
     // Add auth context to request extensions
     let mut request = request;
     request.extensions_mut().insert(auth_context.clone());
+    if signed_key_id.is_some() {
+        *request.uri_mut() = without_signing_params(request.uri());
+    }
 
     // Execute the actual request
     let response = next.run(request).await;
@@ -261,6 +283,66 @@ pub async fn auth_middleware(
     Ok(response)
 }
 
+/// Key id a signed artifact URL was issued for
+fn verify_signed_url(request: &Request) -> Result<i64, AuthError> {
+    let rejected = |error: &str, message: String, status_code: u16| {
+        warn!(
+            "Rejected signed URL for {}: {}",
+            request.uri().path(),
+            message
+        );
+        AuthError {
+            error: error.to_string(),
+            message,
+            status_code,
+        }
+    };
+
+    let Some(signer) = UrlSigner::from_env() else {
+        return Err(rejected(
+            "signed_urls_disabled",
+            "Signed URLs are not enabled on this server".to_string(),
+            401,
+        ));
+    };
+    if request.method() != Method::GET {
+        return Err(rejected(
+            "invalid_signed_url",
+            "Signed URLs only grant GET requests".to_string(),
+            403,
+        ));
+    }
+    signer
+        .verify(
+            request.uri().path(),
+            request.uri().query().unwrap_or_default(),
+            chrono::Utc::now(),
+        )
+        .map_err(|e| match e {
+            SignedUrlError::Expired => rejected("signed_url_expired", e.to_string(), 401),
+            SignedUrlError::NotShareable => rejected("invalid_signed_url", e.to_string(), 403),
+            SignedUrlError::Malformed | SignedUrlError::BadSignature => {
+                rejected("invalid_signed_url", e.to_string(), 401)
+            }
+        })
+}
+
+/// `uri` without the signed URL parameters, so handlers see the query that was signed
+fn without_signing_params(uri: &Uri) -> Uri {
+    let path_and_query = match uri.query().and_then(signed_urls::strip_signing_params) {
+        Some(query) => format!("{}?{}", uri.path(), query),
+        None => uri.path().to_string(),
+    };
+    let mut parts = uri.clone().into_parts();
+    match path_and_query.parse() {
+        Ok(path_and_query) => {
+            parts.path_and_query = Some(path_and_query);
+            Uri::from_parts(parts).unwrap_or_else(|_| uri.clone())
+        }
+        Err(_) => uri.clone(),
+    }
+}
+
 /// Middleware for internal endpoints (requires different auth)
 #[instrument(skip_all)]
 pub async fn internal_auth_middleware(
@@ -317,6 +399,27 @@ mod tests {
         assert_eq!(key, Some("kdb_live_test123".to_string()));
     }
 
+    #[test]
+    fn signing_params_are_removed_before_handlers_run() {
+        let uri: Uri =
+            "/api/v1/overview/treemap?depth=2&kotadb_expires=1&kotadb_key=3&kotadb_signature=ab"
+                .parse()
+                .unwrap();
+        assert_eq!(
+            without_signing_params(&uri).to_string(),
+            "/api/v1/overview/treemap?depth=2"
+        );
+
+        let uri: Uri =
+            "/api/v1/codebase-overview?kotadb_expires=1&kotadb_key=3&kotadb_signature=ab"
+                .parse()
+                .unwrap();
+        assert_eq!(
+            without_signing_params(&uri).to_string(),
+            "/api/v1/codebase-overview"
+        );
+    }
+
     #[test]
     fn test_extract_api_key_from_bearer() {
         let mut headers = HeaderMap::new();
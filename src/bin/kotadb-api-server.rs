@@ -6,8 +6,8 @@
 use anyhow::{Context, Result};
 use clap::Parser;
 use kotadb::{
-    create_file_storage, create_primary_index, create_trigram_index, start_services_saas_server,
-    ApiKeyConfig,
+    create_file_storage, create_primary_index, create_trigram_index, start_services_demo_server,
+    start_services_saas_server, ApiKeyConfig, DemoConfig,
 };
 use std::path::PathBuf;
 use std::sync::Arc;
@@ -24,9 +24,9 @@ struct Args {
     #[arg(short = 'p', long, default_value = "8080", env = "PORT")]
     port: u16,
 
-    /// PostgreSQL database URL for API keys
-    #[arg(long, env = "DATABASE_URL")]
-    database_url: String,
+    /// PostgreSQL database URL for API keys (not needed in demo mode)
+    #[arg(long, env = "DATABASE_URL", required_unless_present = "demo")]
+    database_url: Option<String>,
 
     /// Maximum database connections
     #[arg(long, default_value = "10", env = "DATABASE_MAX_CONNECTIONS")]
@@ -47,6 +47,10 @@ struct Args {
     /// Enable quiet mode (minimal logging)
     #[arg(short = 'q', long, env = "QUIET_MODE")]
     quiet: bool,
+
+    /// Serve a read-only, rate-limited demo with anonymous access
+    #[arg(long, env = "KOTADB_DEMO")]
+    demo: bool,
 }
 
 #[tokio::main]
@@ -90,11 +94,31 @@ async fn main() -> Result<()> {
     info!("Version: {}", env!("CARGO_PKG_VERSION"));
     info!("Data directory: {}", args.data_dir.display());
     info!("Port: {}", args.port);
-    info!("Database URL configured: {}", !args.database_url.is_empty());
+    info!("Database URL configured: {}", args.database_url.is_some());
 
     info!("📁 Creating data directory...");
     std::fs::create_dir_all(&args.data_dir)
         .map_err(|e| anyhow::anyhow!("Failed to create data directory: {}", e))?;
+    kotadb::crash_report::install_panic_hook(
+        &args.data_dir,
+        format!(
+            "kotadb-api-server{}",
+            if args.demo { " --demo" } else { "" }
+        ),
+    );
+
+    // The job worker writes the data directory; a second server on it would corrupt it.
+    // The demo never writes, so demo replicas may share one
+    let _ownership = if args.demo {
+        None
+    } else {
+        Some(kotadb::db_lock::Ownership::claim(
+            &args.data_dir,
+            "kotadb-api-server",
+            true,
+            false,
+        )?)
+    };
 
     info!("💾 Initializing storage backend...");
     let storage_path = args.data_dir.join("storage");
@@ -125,9 +149,32 @@ async fn main() -> Result<()> {
     .context("Failed to create trigram index for SaaS server")?;
     let trigram_index = Arc::new(tokio::sync::Mutex::new(trigram_index));
 
+    if args.demo {
+        let demo_config = DemoConfig::from_env();
+        info!(
+            "🎪 Starting read-only demo server on port {} ({} requests/min per client)...",
+            args.port, demo_config.requests_per_minute
+        );
+        return start_services_demo_server(
+            storage,
+            primary_index,
+            trigram_index,
+            args.data_dir,
+            demo_config,
+            args.port,
+        )
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to start demo server: {}", e));
+    }
+
+    let database_url = args
+        .database_url
+        .clone()
+        .context("DATABASE_URL is required outside demo mode")?;
+
     info!("🔑 Configuring API key service...");
     let api_key_config = ApiKeyConfig {
-        database_url: args.database_url.clone(),
+        database_url: database_url.clone(),
         max_connections: args.max_connections,
         connect_timeout_seconds: args.connect_timeout,
         default_rate_limit: args.default_rate_limit,
@@ -135,7 +182,7 @@ async fn main() -> Result<()> {
     };
 
     info!("🔍 Testing database connectivity...");
-    info!("Database URL configured: {}", !args.database_url.is_empty());
+    info!("Database URL configured: {}", !database_url.is_empty());
     info!("Max connections: {}", args.max_connections);
     info!("Connect timeout: {}s", args.connect_timeout);
 
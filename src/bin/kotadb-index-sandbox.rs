@@ -0,0 +1,19 @@
+//! KotaDB ingestion sandbox
+//!
+//! Started by the SaaS job worker when `KOTADB_INDEX_SANDBOX_BIN` points at this
+//! binary: reads one ingestion request on stdin, confines itself and reports progress
+//! and the result as JSON lines on stdout. See `kotadb::ingestion_sandbox`.
+
+use anyhow::Result;
+
+fn main() -> Result<()> {
+    // stdout carries the protocol; logs go to stderr, which the worker inherits
+    let _ = tracing_subscriber::fmt()
+        .with_writer(std::io::stderr)
+        .with_env_filter(
+            tracing_subscriber::EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("warn")),
+        )
+        .try_init();
+    kotadb::ingestion_sandbox::run_child()
+}
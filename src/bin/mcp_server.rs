@@ -127,6 +127,15 @@ fn main() -> Result<()> {
     // Create data directory if it doesn't exist
     std::fs::create_dir_all(&config.database.data_dir)?;
 
+    // Refuse index files this build cannot read instead of failing on the first query
+    let mismatches = kotadb::compatibility::check(std::path::Path::new(&config.database.data_dir));
+    if !mismatches.is_empty() {
+        anyhow::bail!(
+            "{}",
+            kotadb::compatibility::guidance(&mismatches).trim_end()
+        );
+    }
+
     // Initialize the server with minimal async setup
     let rt = tokio::runtime::Runtime::new()?;
     let server = rt.block_on(async { init_mcp_server(config).await })?;
@@ -7,7 +7,7 @@
 use anyhow::{Context, Result};
 use petgraph::graph::DiGraph;
 use rayon::prelude::*;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 use tracing::{debug, info, instrument, warn};
@@ -16,10 +16,12 @@ use uuid::Uuid;
 
 use crate::{
     binary_symbols::BinarySymbolReader,
+    cargo_workspace::CrateMap,
     dependency_extractor::{
-        CodeReference, DependencyEdge, DependencyGraph, GraphStats, ReferenceType, SymbolNode,
+        CodeReference, DependencyEdge, DependencyGraph, GraphStats, ReferenceType,
+        SerializableDependencyGraph, SerializableEdge, SymbolNode,
     },
-    parsing::{SupportedLanguage, SymbolType},
+    parsing::{CodeParser, SupportedLanguage, SymbolType},
     types::RelationType,
 };
 
@@ -50,6 +52,8 @@ pub struct BinaryRelationshipBridge {
     config: RelationshipExtractionConfig,
     /// Parser pool for reuse across threads
     parser_pool: Arc<Mutex<Vec<Parser>>>,
+    /// Cargo crates used to qualify Rust symbols and prefer same-crate references
+    crates: CrateMap,
 }
 
 impl Default for BinaryRelationshipBridge {
@@ -69,9 +73,16 @@ impl BinaryRelationshipBridge {
         Self {
             config,
             parser_pool: Arc::new(Mutex::new(Vec::new())),
+            crates: CrateMap::default(),
         }
     }
 
+    /// Qualify Rust symbols by crate and resolve references within their crate first
+    pub fn with_crates(mut self, crates: CrateMap) -> Self {
+        self.crates = crates;
+        self
+    }
+
     /// Extract relationships from binary symbols and source files
     ///
     /// This is the main entry point that:
@@ -124,6 +135,227 @@ impl BinaryRelationshipBridge {
         Ok(graph)
     }
 
+    /// Replace the symbols and references of changed files in an existing graph
+    ///
+    /// Only the changed files are parsed: their nodes and outgoing edges are rebuilt,
+    /// edges from other files keep pointing at symbols that still exist under the same
+    /// qualified name, and edges to symbols that no longer exist are returned as
+    /// dangling. Files given without content are treated as deleted.
+    pub fn patch_graph(
+        &self,
+        graph: &SerializableDependencyGraph,
+        changes: &[(PathBuf, Option<Vec<u8>>)],
+    ) -> Result<GraphPatch> {
+        let changed: HashSet<&Path> = changes.iter().map(|(path, _)| path.as_path()).collect();
+
+        // Symbols of changed files that existed before, by qualified name in file order,
+        // so unchanged symbols keep their IDs
+        let mut previous: HashMap<&str, Vec<Uuid>> = HashMap::new();
+        for node in graph
+            .nodes
+            .iter()
+            .filter(|node| changed.contains(node.file_path.as_path()))
+        {
+            previous
+                .entry(node.qualified_name.as_str())
+                .or_default()
+                .push(node.symbol_id);
+        }
+        for ids in previous.values_mut() {
+            ids.reverse();
+        }
+
+        let mut nodes: Vec<SymbolNode> = graph
+            .nodes
+            .iter()
+            .filter(|node| !changed.contains(node.file_path.as_path()))
+            .cloned()
+            .collect();
+        let mut parsed_files = Vec::new();
+        let mut parser = CodeParser::new()?;
+        for (path, content) in changes {
+            let Some(content) = content else {
+                continue;
+            };
+            let Some(language) = path
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .and_then(SupportedLanguage::from_extension)
+            else {
+                continue;
+            };
+            let content = String::from_utf8_lossy(content);
+            let parsed = match parser.parse_content(&content, language) {
+                Ok(parsed) => parsed,
+                Err(e) => {
+                    warn!("Failed to parse {}: {}", path.display(), e);
+                    continue;
+                }
+            };
+            let mut symbols = Vec::new();
+            for symbol in parsed.symbols {
+                let qualified_name = self.crates.qualified_name(path, &symbol.name);
+                let id = previous
+                    .get_mut(qualified_name.as_str())
+                    .and_then(|ids| ids.pop())
+                    .unwrap_or_else(Uuid::new_v4);
+                // Same types the binary symbol format can represent
+                let symbol_type = match symbol.symbol_type {
+                    SymbolType::Function
+                    | SymbolType::Method
+                    | SymbolType::Class
+                    | SymbolType::Struct
+                    | SymbolType::Enum
+                    | SymbolType::Variable
+                    | SymbolType::Constant
+                    | SymbolType::Module => symbol.symbol_type.clone(),
+                    _ => SymbolType::Other("Unknown".to_string()),
+                };
+                nodes.push(SymbolNode {
+                    symbol_id: id,
+                    qualified_name: qualified_name.clone(),
+                    symbol_type: symbol_type.clone(),
+                    file_path: path.clone(),
+                    in_degree: 0,
+                    out_degree: 0,
+                });
+                symbols.push((
+                    id,
+                    SymbolInfo {
+                        id,
+                        name: symbol.name,
+                        qualified_name,
+                        symbol_type,
+                        file_path: path.clone(),
+                        start_line: symbol.start_line,
+                        end_line: symbol.end_line,
+                        parent_id: None,
+                    },
+                ));
+            }
+            parsed_files.push((path, language, content, symbols));
+        }
+
+        let node_ids: HashSet<Uuid> = nodes.iter().map(|node| node.symbol_id).collect();
+        let mut name_to_symbol: HashMap<String, Uuid> = graph
+            .name_to_symbol
+            .iter()
+            .filter(|(_, id)| node_ids.contains(id))
+            .map(|(name, id)| (name.clone(), *id))
+            .collect();
+        for (path, _, _, symbols) in &parsed_files {
+            for (id, info) in symbols {
+                name_to_symbol.insert(info.qualified_name.clone(), *id);
+                name_to_symbol.insert(format!("{}::{}", path.display(), info.name), *id);
+                name_to_symbol.insert(info.name.clone(), *id);
+            }
+        }
+        let crate_names = self.crate_local_names(nodes.iter().map(|node| {
+            (
+                node.file_path.as_path(),
+                simple_name(&node.qualified_name),
+                node.symbol_id,
+            )
+        }));
+
+        let source_paths: HashMap<Uuid, &Path> = graph
+            .nodes
+            .iter()
+            .map(|node| (node.symbol_id, node.file_path.as_path()))
+            .collect();
+        let mut edges = Vec::new();
+        let mut dangling_edges = Vec::new();
+        for edge in &graph.edges {
+            let from_changed = source_paths
+                .get(&edge.from_id)
+                .is_none_or(|path| changed.contains(path));
+            if from_changed {
+                continue;
+            }
+            if node_ids.contains(&edge.to_id) {
+                edges.push(edge.clone());
+            } else {
+                dangling_edges.push(edge.clone());
+            }
+        }
+
+        for (path, language, content, symbols) in &parsed_files {
+            let references =
+                match self.extract_file_references_with_recovery(path, content, *language) {
+                    ExtractionResult::Success(refs)
+                    | ExtractionResult::PartialSuccess {
+                        references: refs, ..
+                    } => refs.references,
+                    ExtractionResult::Failure(e) => {
+                        warn!("Failed to extract from {}: {}", path.display(), e);
+                        continue;
+                    }
+                };
+            let symbol_refs: Vec<(&Uuid, &SymbolInfo)> =
+                symbols.iter().map(|(id, info)| (id, info)).collect();
+            let hierarchy = SymbolHierarchy::build_from_symbols(&symbol_refs);
+            for reference in &references {
+                let Some(target_id) = self.resolve_reference_from(
+                    path,
+                    &reference.name,
+                    &crate_names,
+                    &name_to_symbol,
+                ) else {
+                    continue;
+                };
+                let Some(source_id) = hierarchy
+                    .iter()
+                    .find_map(|root| root.find_containing_symbol(reference.line))
+                else {
+                    continue;
+                };
+                if source_id != target_id {
+                    edges.push(SerializableEdge {
+                        from_id: source_id,
+                        to_id: target_id,
+                        edge: DependencyEdge {
+                            relation_type: self.ref_type_to_relation(&reference.ref_type),
+                            line_number: reference.line,
+                            column_number: reference.column,
+                            end_line_number: reference.end_line,
+                            end_column_number: reference.end_column,
+                            start_byte: reference.start_byte,
+                            end_byte: reference.end_byte,
+                            context: Some(reference.text.clone()),
+                        },
+                    });
+                }
+            }
+        }
+
+        let mut in_degree: HashMap<Uuid, usize> = HashMap::new();
+        let mut out_degree: HashMap<Uuid, usize> = HashMap::new();
+        for edge in &edges {
+            *out_degree.entry(edge.from_id).or_default() += 1;
+            *in_degree.entry(edge.to_id).or_default() += 1;
+        }
+        for node in &mut nodes {
+            node.in_degree = in_degree.get(&node.symbol_id).copied().unwrap_or(0);
+            node.out_degree = out_degree.get(&node.symbol_id).copied().unwrap_or(0);
+        }
+
+        let stats = GraphStats {
+            node_count: nodes.len(),
+            edge_count: edges.len(),
+            ..graph.stats.clone()
+        };
+        Ok(GraphPatch {
+            graph: SerializableDependencyGraph {
+                nodes,
+                edges,
+                name_to_symbol,
+                file_imports: graph.file_imports.clone(),
+                stats,
+            },
+            dangling_edges,
+        })
+    }
+
     /// Build lookup maps from binary symbols
     #[allow(clippy::type_complexity)]
     fn build_symbol_maps(
@@ -144,8 +376,8 @@ impl BinaryRelationshipBridge {
             let name = reader.get_symbol_name(&symbol)?;
             let file_path = PathBuf::from(reader.get_symbol_file_path(&symbol)?);
 
-            // Build qualified name (for now, use file:name pattern)
-            let qualified_name = format!("{}::{}", file_path.display(), name);
+            // Crate-qualified for Rust files in a Cargo crate, file:name pattern otherwise
+            let qualified_name = self.crates.qualified_name(&file_path, &name);
 
             let info = SymbolInfo {
                 id,
@@ -163,6 +395,7 @@ impl BinaryRelationshipBridge {
             };
 
             symbol_map.insert(id, info);
+            name_map.insert(format!("{}::{}", file_path.display(), name), id);
             name_map.insert(qualified_name, id);
 
             // Also index by simple name for reference resolution
@@ -299,7 +532,11 @@ impl BinaryRelationshipBridge {
                                 name,
                                 ref_type: ReferenceType::FunctionCall,
                                 line: line_num + 1,
-                                column: 1,
+                                column: 0,
+                                end_line: None,
+                                end_column: None,
+                                start_byte: None,
+                                end_byte: None,
                                 text: line.trim().to_string(),
                             });
                         }
@@ -529,7 +766,11 @@ impl BinaryRelationshipBridge {
                     name,
                     ref_type,
                     line: point.row + 1,
-                    column: point.column + 1,
+                    column: point.column,
+                    end_line: Some(node.end_position().row + 1),
+                    end_column: Some(node.end_position().column),
+                    start_byte: Some(node.start_byte()),
+                    end_byte: Some(node.end_byte()),
                     text: match node.utf8_text(content.as_bytes()) {
                         Ok(text) => text.to_string(),
                         Err(_) => {
@@ -547,6 +788,43 @@ impl BinaryRelationshipBridge {
         Ok(references)
     }
 
+    /// Symbols of each crate by simple name, for resolving references within their crate
+    fn crate_local_names<'a>(
+        &self,
+        symbols: impl Iterator<Item = (&'a Path, &'a str, Uuid)>,
+    ) -> HashMap<(String, String), Uuid> {
+        let mut names = HashMap::new();
+        if self.crates.is_empty() {
+            return names;
+        }
+        for (path, name, id) in symbols {
+            if let Some(krate) = self.crates.crate_of(&path.to_string_lossy()) {
+                names
+                    .entry((krate.name.clone(), name.to_string()))
+                    .or_insert(id);
+            }
+        }
+        names
+    }
+
+    /// Resolve a reference made in `from`, preferring a symbol of the same crate
+    fn resolve_reference_from(
+        &self,
+        from: &Path,
+        name: &str,
+        crate_names: &HashMap<(String, String), Uuid>,
+        name_map: &HashMap<String, Uuid>,
+    ) -> Option<Uuid> {
+        if !name.contains("::") {
+            if let Some(krate) = self.crates.crate_of(&from.to_string_lossy()) {
+                if let Some(&id) = crate_names.get(&(krate.name.clone(), name.to_string())) {
+                    return Some(id);
+                }
+            }
+        }
+        self.resolve_symbol_reference(name, name_map)
+    }
+
     /// Enhanced symbol reference resolution with suffix matching fallback
     fn resolve_symbol_reference(
         &self,
@@ -682,6 +960,12 @@ impl BinaryRelationshipBridge {
             file_hierarchies.insert(file_path.clone(), hierarchy);
         }
 
+        let crate_names = self.crate_local_names(
+            symbol_map
+                .iter()
+                .map(|(id, info)| (info.file_path.as_path(), info.name.as_str(), *id)),
+        );
+
         // Create edges from references
         for file_refs in &all_references {
             // Get the symbol hierarchy for this file
@@ -699,7 +983,12 @@ impl BinaryRelationshipBridge {
 
             for reference in &file_refs.references {
                 // Try to resolve the reference to a symbol with enhanced matching
-                if let Some(target_id) = self.resolve_symbol_reference(&reference.name, &name_map) {
+                if let Some(target_id) = self.resolve_reference_from(
+                    &file_refs.file_path,
+                    &reference.name,
+                    &crate_names,
+                    &name_map,
+                ) {
                     // Find which symbol in this file contains this reference using hierarchy
                     let source_id = hierarchy
                         .iter()
@@ -716,6 +1005,10 @@ impl BinaryRelationshipBridge {
                                     relation_type: self.ref_type_to_relation(&reference.ref_type),
                                     line_number: reference.line,
                                     column_number: reference.column,
+                                    end_line_number: reference.end_line,
+                                    end_column_number: reference.end_column,
+                                    start_byte: reference.start_byte,
+                                    end_byte: reference.end_byte,
                                     context: Some(reference.text.clone()),
                                 };
 
@@ -967,7 +1260,11 @@ impl BinaryRelationshipBridge {
                     name: symbol_name,
                     ref_type: reference_type,
                     line: node.start_position().row + 1,
-                    column: node.start_position().column + 1,
+                    column: node.start_position().column,
+                    end_line: Some(node.end_position().row + 1),
+                    end_column: Some(node.end_position().column),
+                    start_byte: Some(node.start_byte()),
+                    end_byte: Some(node.end_byte()),
                     text: symbol_text,
                 });
             }
@@ -1162,7 +1459,11 @@ impl BinaryRelationshipBridge {
                     name: symbol_name,
                     ref_type: reference_type,
                     line: node.start_position().row + 1,
-                    column: node.start_position().column + 1,
+                    column: node.start_position().column,
+                    end_line: Some(node.end_position().row + 1),
+                    end_column: Some(node.end_position().column),
+                    start_byte: Some(node.start_byte()),
+                    end_byte: Some(node.end_byte()),
                     text: symbol_text,
                 });
             }
@@ -1320,7 +1621,11 @@ impl BinaryRelationshipBridge {
                     name: symbol_name,
                     ref_type: reference_type,
                     line: node.start_position().row + 1,
-                    column: node.start_position().column + 1,
+                    column: node.start_position().column,
+                    end_line: Some(node.end_position().row + 1),
+                    end_column: Some(node.end_position().column),
+                    start_byte: Some(node.start_byte()),
+                    end_byte: Some(node.end_byte()),
                     text: symbol_text,
                 });
             }
@@ -1374,6 +1679,19 @@ impl BinaryRelationshipBridge {
     }
 }
 
+/// A dependency graph with the symbols and references of changed files replaced
+#[derive(Debug, Clone)]
+pub struct GraphPatch {
+    pub graph: SerializableDependencyGraph,
+    /// Edges from unchanged files to symbols the changed files no longer define
+    pub dangling_edges: Vec<SerializableEdge>,
+}
+
+/// Last segment of a qualified name
+fn simple_name(qualified_name: &str) -> &str {
+    qualified_name.rsplit("::").next().unwrap_or(qualified_name)
+}
+
 /// Information about a symbol from the binary database
 #[derive(Debug, Clone)]
 struct SymbolInfo {
@@ -1541,4 +1859,132 @@ fn process_data() {
         assert_eq!(graph.stats.node_count, 2);
         // Note: edge count might be 0 initially as reference resolution needs improvement
     }
+
+    #[test]
+    fn test_patch_graph_replaces_changed_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("test.symdb");
+
+        let mut writer = BinarySymbolWriter::new();
+        let helper_id = Uuid::new_v4();
+        let other_id = Uuid::new_v4();
+        let run_id = Uuid::new_v4();
+        writer.add_symbol(helper_id, "helper", 1, "src/lib.rs", 1, 3, None);
+        writer.add_symbol(other_id, "other", 1, "src/lib.rs", 5, 7, None);
+        writer.add_symbol(run_id, "run", 1, "src/main.rs", 1, 3, None);
+        writer.write_to_file(&db_path).unwrap();
+
+        let lib = "fn helper() -> u32 {\n    1\n}\n\nfn other() -> u32 {\n    2\n}\n";
+        let main = "fn run() -> u32 {\n    helper() + other()\n}\n";
+        let files = vec![
+            (PathBuf::from("src/lib.rs"), lib.as_bytes().to_vec()),
+            (PathBuf::from("src/main.rs"), main.as_bytes().to_vec()),
+        ];
+        let bridge = BinaryRelationshipBridge::new();
+        let graph = bridge
+            .extract_relationships(&db_path, temp_dir.path(), &files)
+            .unwrap()
+            .to_serializable();
+        assert_eq!(graph.edges.len(), 2);
+
+        // `other` is removed and `helper` now calls a new function
+        let lib = "fn helper() -> u32 {\n    added()\n}\n\nfn added() -> u32 {\n    2\n}\n";
+        let patch = bridge
+            .patch_graph(
+                &graph,
+                &[(PathBuf::from("src/lib.rs"), Some(lib.as_bytes().to_vec()))],
+            )
+            .unwrap();
+
+        let names: HashMap<Uuid, &str> = patch
+            .graph
+            .nodes
+            .iter()
+            .map(|node| (node.symbol_id, node.qualified_name.as_str()))
+            .collect();
+        assert_eq!(names.get(&helper_id), Some(&"src/lib.rs::helper"));
+        assert!(!names.contains_key(&other_id));
+        let edges: Vec<(&str, &str)> = patch
+            .graph
+            .edges
+            .iter()
+            .map(|edge| (names[&edge.from_id], names[&edge.to_id]))
+            .collect();
+        assert!(edges.contains(&("src/main.rs::run", "src/lib.rs::helper")));
+        assert!(edges.contains(&("src/lib.rs::helper", "src/lib.rs::added")));
+        assert_eq!(patch.dangling_edges.len(), 1);
+        assert_eq!(patch.dangling_edges[0].from_id, run_id);
+        assert_eq!(patch.dangling_edges[0].to_id, other_id);
+    }
+
+    #[test]
+    fn test_references_resolve_within_their_crate() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("test.symdb");
+
+        let mut writer = BinarySymbolWriter::new();
+        let (core_load, cli_load, core_run, cli_run) = (
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+        );
+        writer.add_symbol(core_load, "load", 1, "core/src/config.rs", 1, 3, None);
+        writer.add_symbol(core_run, "run", 1, "core/src/lib.rs", 1, 3, None);
+        writer.add_symbol(cli_load, "load", 1, "cli/src/config.rs", 1, 3, None);
+        writer.add_symbol(cli_run, "run", 1, "cli/src/main.rs", 1, 3, None);
+        writer.write_to_file(&db_path).unwrap();
+
+        let load = "fn load() -> u32 {\n    1\n}\n";
+        let run = "fn run() -> u32 {\n    load()\n}\n";
+        let files = vec![
+            (
+                PathBuf::from("core/Cargo.toml"),
+                b"[package]\nname = \"core\"\n".to_vec(),
+            ),
+            (
+                PathBuf::from("cli/Cargo.toml"),
+                b"[package]\nname = \"cli\"\n".to_vec(),
+            ),
+            (
+                PathBuf::from("core/src/config.rs"),
+                load.as_bytes().to_vec(),
+            ),
+            (PathBuf::from("core/src/lib.rs"), run.as_bytes().to_vec()),
+            (PathBuf::from("cli/src/config.rs"), load.as_bytes().to_vec()),
+            (PathBuf::from("cli/src/main.rs"), run.as_bytes().to_vec()),
+        ];
+        let crates = CrateMap::from_files(
+            files
+                .iter()
+                .map(|(path, content)| (path.as_path(), content.as_slice())),
+        );
+        let graph = BinaryRelationshipBridge::new()
+            .with_crates(crates)
+            .extract_relationships(&db_path, temp_dir.path(), &files)
+            .unwrap()
+            .to_serializable();
+
+        assert_eq!(
+            graph.name_to_symbol.get("core::config::load"),
+            Some(&core_load)
+        );
+        assert_eq!(
+            graph.name_to_symbol.get("cli::config::load"),
+            Some(&cli_load)
+        );
+        assert_eq!(
+            graph.name_to_symbol.get("cli/src/config.rs::load"),
+            Some(&cli_load)
+        );
+        let edges: HashSet<(Uuid, Uuid)> = graph
+            .edges
+            .iter()
+            .map(|edge| (edge.from_id, edge.to_id))
+            .collect();
+        assert_eq!(
+            edges,
+            HashSet::from([(core_run, core_load), (cli_run, cli_load)])
+        );
+    }
 }
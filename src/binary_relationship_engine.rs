@@ -4,7 +4,7 @@
 //! ensuring sub-10ms query latency while maintaining full API compatibility.
 
 use anyhow::{Context, Result};
-use std::collections::HashSet;
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::RwLock;
@@ -13,10 +13,12 @@ use uuid::Uuid;
 
 use crate::{
     binary_relationship_bridge::BinaryRelationshipBridge,
-    binary_symbols::BinarySymbolReader,
-    dependency_extractor::DependencyGraph,
+    binary_symbols::{BinarySymbolReader, PackedSymbol},
+    cargo_workspace::CrateMap,
+    dependency_extractor::{DependencyEdge, DependencyGraph},
     parsing::{SupportedLanguage, SymbolType},
-    path_utils::normalize_path_relative,
+    path_utils::{is_test_file, normalize_path_relative},
+    query_deadline::QueryDeadline,
     relationship_query::{
         RelationshipLocation, RelationshipMatch, RelationshipQueryConfig, RelationshipQueryResult,
         RelationshipQueryType, RelationshipStats,
@@ -176,25 +178,62 @@ impl BinaryRelationshipEngine {
         &self,
         query_type: RelationshipQueryType,
     ) -> Result<RelationshipQueryResult> {
+        self.execute_query_with_explain(query_type)
+            .await
+            .map(|(result, _)| result)
+    }
+
+    /// Execute a relationship query and return the query plan alongside the result
+    ///
+    /// The explanation records which data path served the query, how much of the
+    /// dependency graph was traversed and how long each phase took.
+    pub async fn execute_query_with_explain(
+        &self,
+        query_type: RelationshipQueryType,
+    ) -> Result<(RelationshipQueryResult, QueryExplanation)> {
+        self.execute_query_with_deadline(query_type, QueryDeadline::none())
+            .await
+    }
+
+    /// Execute a relationship query, stopping graph traversal once `deadline` expires
+    ///
+    /// Relationships found before the deadline are returned; the explanation and the
+    /// result stats are then marked as timed out and truncated.
+    #[instrument(skip(self))]
+    pub async fn execute_query_with_deadline(
+        &self,
+        query_type: RelationshipQueryType,
+        deadline: QueryDeadline,
+    ) -> Result<(RelationshipQueryResult, QueryExplanation)> {
         info!("Executing relationship query: {:?}", query_type);
         let start = std::time::Instant::now();
+        let cache_hits_before = self.cache_metadata.get_access_count();
+        let mut explain = QueryExplanation {
+            query: format!("{:?}", query_type),
+            ..Default::default()
+        };
 
         // First try binary symbols if available, even without dependency graph for basic queries
         let result = if self.symbol_reader.is_some() {
             debug!(
                 "Using binary symbol path for query (dependency graph available: {})",
-                self.dependency_graph
-                    .read()
-                    .map(|g| g.is_some())
-                    .unwrap_or(false)
+                self.has_cached_graph()
             );
-            self.execute_binary_query(query_type.clone()).await
+            explain.data_path = "binary_symbols".to_string();
+            self.execute_binary_query(query_type.clone(), &mut explain, deadline)
+                .await
         } else {
             debug!("Falling back to legacy symbol storage path");
+            explain.data_path = "legacy_fallback".to_string();
             self.execute_legacy_query(query_type.clone()).await
         };
 
         let elapsed = start.elapsed();
+        explain.total_time_us = elapsed.as_micros() as u64;
+        explain.cache_misses = self
+            .cache_metadata
+            .get_access_count()
+            .saturating_sub(cache_hits_before);
 
         match &result {
             Ok(r) => {
@@ -211,13 +250,23 @@ impl BinaryRelationshipEngine {
             }
         }
 
-        result
+        result.map(|r| (r, explain))
+    }
+
+    /// Check whether a dependency graph is currently held in memory
+    fn has_cached_graph(&self) -> bool {
+        self.dependency_graph
+            .read()
+            .map(|g| g.is_some())
+            .unwrap_or(false)
     }
 
     /// Execute query using binary symbols and optional dependency graph
     async fn execute_binary_query(
         &self,
         query_type: RelationshipQueryType,
+        explain: &mut QueryExplanation,
+        deadline: QueryDeadline,
     ) -> Result<RelationshipQueryResult> {
         let reader = self
             .symbol_reader
@@ -226,14 +275,16 @@ impl BinaryRelationshipEngine {
 
         match query_type.clone() {
             RelationshipQueryType::FindCallers { target } => {
-                self.execute_find_callers_query(query_type, &target).await
+                self.execute_find_callers_query(query_type, &target, explain, deadline)
+                    .await
             }
             RelationshipQueryType::ImpactAnalysis { target } => {
-                self.execute_impact_analysis_query(query_type, &target)
+                self.execute_impact_analysis_query(query_type, &target, explain, deadline)
                     .await
             }
             _ => {
                 // For other query types, fall back to legacy implementation
+                explain.data_path = "legacy_fallback".to_string();
                 self.execute_legacy_query(query_type).await
             }
         }
@@ -253,12 +304,16 @@ impl BinaryRelationshipEngine {
     }
 
     /// Find all symbols that transitively depend on the given symbol
+    ///
+    /// Stops with the dependents found so far once `deadline` expires.
     fn find_transitive_dependents(
         &self,
         graph: &DependencyGraph,
         target_id: Uuid,
         max_depth: usize,
-    ) -> Vec<(Uuid, RelationType)> {
+        explain: &mut QueryExplanation,
+        deadline: QueryDeadline,
+    ) -> Vec<(Uuid, RelationType, usize)> {
         use std::collections::{HashSet, VecDeque};
 
         let mut result = Vec::new();
@@ -269,16 +324,24 @@ impl BinaryRelationshipEngine {
         queue.push_back((target_id, 0));
         visited.insert(target_id);
 
+        let mut expanded = 0;
         while let Some((current_id, depth)) = queue.pop_front() {
             if depth >= max_depth {
                 continue;
             }
+            if deadline.expired_at(expanded) {
+                explain.timed_out = true;
+                break;
+            }
+            expanded += 1;
 
             let dependents = graph.find_dependents(current_id);
+            explain.nodes_visited += 1;
+            explain.edges_visited += dependents.len();
             for (dependent_id, relation_type) in dependents {
                 if !visited.contains(&dependent_id) {
                     visited.insert(dependent_id);
-                    result.push((dependent_id, relation_type.clone()));
+                    result.push((dependent_id, relation_type.clone(), depth + 1));
                     queue.push_back((dependent_id, depth + 1));
                 }
             }
@@ -292,6 +355,8 @@ impl BinaryRelationshipEngine {
         &self,
         query_type: RelationshipQueryType,
         target: &str,
+        explain: &mut QueryExplanation,
+        deadline: QueryDeadline,
     ) -> Result<RelationshipQueryResult> {
         let start = std::time::Instant::now();
 
@@ -301,9 +366,13 @@ impl BinaryRelationshipEngine {
             .ok_or_else(|| anyhow::anyhow!("Binary symbol reader not available"))?;
 
         // Ensure dependency graph is available, extracting on-demand if needed
+        let phase_start = std::time::Instant::now();
+        explain.record_graph_source(self.has_cached_graph());
         if let Err(e) = self.ensure_dependency_graph("find-callers query").await {
+            explain.graph_source = "unavailable".to_string();
             return self.create_extraction_failure_result(query_type, target, reader, e);
         }
+        explain.record_phase("load_dependency_graph", phase_start);
 
         // Now we should have a graph, get it safely
         let graph_ref = self.get_dependency_graph()?;
@@ -314,7 +383,14 @@ impl BinaryRelationshipEngine {
             "Looking for all symbols named '{}' in binary reader",
             target
         );
-        let all_symbols = self.find_all_symbols_by_name(reader, target);
+        let phase_start = std::time::Instant::now();
+        let mut all_symbols = self.find_all_symbols_by_name(reader, target);
+        if all_symbols.is_empty() {
+            all_symbols = Self::find_symbols_by_qualified_name(reader, graph, target);
+        }
+        explain.symbols_scanned = reader.symbol_count();
+        explain.target_instances = all_symbols.len();
+        explain.record_phase("resolve_target_symbols", phase_start);
 
         if all_symbols.is_empty() {
             return Err(anyhow::anyhow!("Symbol '{}' not found", target));
@@ -323,17 +399,28 @@ impl BinaryRelationshipEngine {
         debug!("Found {} symbols named '{}'", all_symbols.len(), target);
 
         // Try each symbol until we find one with relationships
+        let phase_start = std::time::Instant::now();
         let mut all_callers = Vec::new();
 
         for (_symbol, symbol_id) in &all_symbols {
+            if deadline.expired() {
+                explain.timed_out = true;
+                break;
+            }
             debug!("Checking symbol '{}' with UUID: {}", target, symbol_id);
 
             // Resolve symbol UUID with fallback to name-based lookup
             if let Some(effective_id) =
                 Self::resolve_symbol_uuid_with_fallback(graph, target, *symbol_id)
             {
-                // Find callers for this specific symbol instance
-                let callers = graph.find_dependents(effective_id);
+                // Find callers for this specific symbol instance, keeping edge details
+                let callers: Vec<(Uuid, DependencyEdge)> = graph
+                    .find_dependent_edges(effective_id)
+                    .into_iter()
+                    .map(|(id, edge)| (id, edge.clone()))
+                    .collect();
+                explain.nodes_visited += 1;
+                explain.edges_visited += callers.len();
                 if !callers.is_empty() {
                     debug!(
                         "Found {} callers for symbol '{}' (UUID: {})",
@@ -356,6 +443,8 @@ impl BinaryRelationshipEngine {
             }
         }
 
+        explain.record_phase("traverse_graph", phase_start);
+
         // If we found no callers from any symbol instance, return empty result
         if all_callers.is_empty() {
             return Ok(RelationshipQueryResult {
@@ -381,8 +470,10 @@ impl BinaryRelationshipEngine {
         let callers = all_callers;
 
         // Convert to relationship matches with caller-specific context
+        let phase_start = std::time::Instant::now();
         let direct_relationships =
             self.convert_caller_relationships_to_matches(reader, &callers, target);
+        explain.record_phase("build_matches", phase_start);
 
         let execution_time_ms = start.elapsed().as_millis() as u64;
         if execution_time_ms > QUERY_PERFORMANCE_THRESHOLD_MS {
@@ -401,7 +492,7 @@ impl BinaryRelationshipEngine {
                 indirect_count: 0,
                 symbols_analyzed: reader.symbol_count(),
                 execution_time_ms,
-                truncated: false,
+                truncated: explain.timed_out,
             },
             summary: format!("Found {} direct callers of '{}'", callers.len(), target),
         })
@@ -412,6 +503,8 @@ impl BinaryRelationshipEngine {
         &self,
         query_type: RelationshipQueryType,
         target: &str,
+        explain: &mut QueryExplanation,
+        deadline: QueryDeadline,
     ) -> Result<RelationshipQueryResult> {
         let start = std::time::Instant::now();
 
@@ -421,9 +514,13 @@ impl BinaryRelationshipEngine {
             .ok_or_else(|| anyhow::anyhow!("Binary symbol reader not available"))?;
 
         // Ensure dependency graph is available, extracting on-demand if needed
+        let phase_start = std::time::Instant::now();
+        explain.record_graph_source(self.has_cached_graph());
         if let Err(e) = self.ensure_dependency_graph("impact analysis").await {
+            explain.graph_source = "unavailable".to_string();
             return self.create_extraction_failure_result(query_type, target, reader, e);
         }
+        explain.record_phase("load_dependency_graph", phase_start);
 
         // Now we should have a graph, get it safely
         let graph_ref = self.get_dependency_graph()?;
@@ -434,7 +531,14 @@ impl BinaryRelationshipEngine {
             "Looking for all symbols named '{}' for impact analysis",
             target
         );
-        let all_symbols = self.find_all_symbols_by_name(reader, target);
+        let phase_start = std::time::Instant::now();
+        let mut all_symbols = self.find_all_symbols_by_name(reader, target);
+        if all_symbols.is_empty() {
+            all_symbols = Self::find_symbols_by_qualified_name(reader, graph, target);
+        }
+        explain.symbols_scanned = reader.symbol_count();
+        explain.target_instances = all_symbols.len();
+        explain.record_phase("resolve_target_symbols", phase_start);
 
         if all_symbols.is_empty() {
             return Ok(RelationshipQueryResult {
@@ -453,10 +557,15 @@ impl BinaryRelationshipEngine {
         }
 
         // Collect all impacted symbols from all instances of the target symbol
+        let phase_start = std::time::Instant::now();
         let mut all_impacted = Vec::new();
         let mut found_in_graph = false;
 
         for (_symbol, symbol_id) in &all_symbols {
+            if deadline.expired() {
+                explain.timed_out = true;
+                break;
+            }
             debug!(
                 "Checking symbol '{}' with UUID: {} for impact",
                 target, symbol_id
@@ -467,8 +576,13 @@ impl BinaryRelationshipEngine {
                 Self::resolve_symbol_uuid_with_fallback(graph, target, *symbol_id)
             {
                 found_in_graph = true;
-                let impacted =
-                    self.find_transitive_dependents(graph, effective_id, self.config.max_depth);
+                let impacted = self.find_transitive_dependents(
+                    graph,
+                    effective_id,
+                    self.config.max_depth,
+                    explain,
+                    deadline,
+                );
                 if !impacted.is_empty() {
                     debug!(
                         "Found {} impacted symbols for '{}' (UUID: {})",
@@ -486,6 +600,8 @@ impl BinaryRelationshipEngine {
             }
         }
 
+        explain.record_phase("traverse_graph", phase_start);
+
         // If none of the symbol instances were found in the graph
         if !found_in_graph {
             return Ok(RelationshipQueryResult {
@@ -507,19 +623,23 @@ impl BinaryRelationshipEngine {
             });
         }
 
-        // Deduplicate impacted symbols (a symbol might be impacted through multiple paths)
+        // Deduplicate impacted symbols (a symbol might be impacted through multiple paths),
+        // keeping the shortest distance at which each one was reached
+        all_impacted.sort_by_key(|(_, _, depth)| *depth);
         let mut unique_impacted = Vec::new();
         let mut seen = std::collections::HashSet::new();
-        for (id, rel_type) in all_impacted {
+        for (id, rel_type, depth) in all_impacted {
             if seen.insert(id) {
-                unique_impacted.push((id, rel_type));
+                unique_impacted.push((id, rel_type, depth));
             }
         }
         let impacted = unique_impacted;
 
         // Convert to relationship matches with impact-specific context
+        let phase_start = std::time::Instant::now();
         let direct_relationships =
             self.convert_impact_relationships_to_matches(reader, &impacted, target);
+        explain.record_phase("build_matches", phase_start);
 
         let execution_time_ms = start.elapsed().as_millis() as u64;
         if execution_time_ms > QUERY_PERFORMANCE_THRESHOLD_MS {
@@ -538,7 +658,7 @@ impl BinaryRelationshipEngine {
                 indirect_count: 0,
                 symbols_analyzed: reader.symbol_count(),
                 execution_time_ms,
-                truncated: false,
+                truncated: explain.timed_out,
             },
             summary: format!(
                 "{} symbols would be impacted by changes to '{}'",
@@ -561,22 +681,16 @@ impl BinaryRelationshipEngine {
 
         // Use spawn_blocking to handle the blocking serialization operation
         tokio::task::spawn_blocking(move || -> Result<()> {
-            use std::fs::File;
-            use std::io::BufWriter;
-
             // Create parent directory if it doesn't exist
             if let Some(parent) = path.parent() {
                 std::fs::create_dir_all(parent)
                     .with_context(|| format!("Failed to create directory: {:?}", parent))?;
             }
 
-            let file = File::create(&path)
-                .with_context(|| format!("Failed to create dependency graph file: {:?}", path))?;
-            let writer = BufWriter::new(file);
-
-            // Serialize using bincode for efficiency
-            bincode::serialize_into(writer, &serializable)
-                .context("Failed to serialize dependency graph")?;
+            // Versioned bincode payload so older files can be detected and migrated
+            let graph_binary = serializable.to_bytes()?;
+            std::fs::write(&path, graph_binary)
+                .with_context(|| format!("Failed to write dependency graph file: {:?}", path))?;
 
             info!("Successfully saved dependency graph to: {:?}", path);
             Ok(())
@@ -587,9 +701,6 @@ impl BinaryRelationshipEngine {
 
     /// Save dependency graph to binary file (legacy sync version for backward compatibility)
     pub fn save_dependency_graph(graph: &DependencyGraph, path: &Path) -> Result<()> {
-        use std::fs::File;
-        use std::io::BufWriter;
-
         info!(
             "Saving dependency graph with {} nodes to: {:?}",
             graph.graph.node_count(),
@@ -602,16 +713,10 @@ impl BinaryRelationshipEngine {
                 .with_context(|| format!("Failed to create directory: {:?}", parent))?;
         }
 
-        let file = File::create(path)
-            .with_context(|| format!("Failed to create dependency graph file: {:?}", path))?;
-        let writer = BufWriter::new(file);
-
-        // Convert to serializable format
-        let serializable = graph.to_serializable();
-
-        // Serialize using bincode for efficiency
-        bincode::serialize_into(writer, &serializable)
-            .context("Failed to serialize dependency graph")?;
+        // Convert to serializable format and encode as a versioned bincode payload
+        let graph_binary = graph.to_serializable().to_bytes()?;
+        std::fs::write(path, graph_binary)
+            .with_context(|| format!("Failed to write dependency graph file: {:?}", path))?;
 
         info!("Successfully saved dependency graph to: {:?}", path);
         Ok(())
@@ -619,24 +724,301 @@ impl BinaryRelationshipEngine {
 
     /// Load dependency graph from binary file
     fn load_dependency_graph(path: &Path) -> Result<DependencyGraph> {
-        use std::fs::File;
-        use std::io::BufReader;
-
         debug!("Loading dependency graph from: {:?}", path);
 
-        let file = File::open(path)
+        let graph_binary = std::fs::read(path)
             .with_context(|| format!("Failed to open dependency graph file: {:?}", path))?;
-        let reader = BufReader::new(file);
 
-        // Deserialize using bincode for efficiency
-        let serializable: crate::dependency_extractor::SerializableDependencyGraph =
-            bincode::deserialize_from(reader).context("Failed to deserialize dependency graph")?;
+        // Versioned bincode payload; unversioned files are migrated on load
+        let serializable =
+            crate::dependency_extractor::SerializableDependencyGraph::from_bytes(&graph_binary)?;
 
         // Convert from serializable format
         DependencyGraph::from_serializable(serializable)
             .context("Failed to reconstruct dependency graph from serialized data")
     }
 
+    /// Definition sites `(file path, start line)` of every symbol with the given name
+    pub fn symbol_locations(&self, name: &str) -> Vec<(String, u32)> {
+        let Some(reader) = self.symbol_reader.as_ref() else {
+            return Vec::new();
+        };
+        self.find_all_symbols_by_name(reader, name)
+            .into_iter()
+            .filter_map(|(symbol, _)| {
+                let file_path = reader.get_symbol_file_path(&symbol).ok()?;
+                Some((file_path, symbol.start_line))
+            })
+            .collect()
+    }
+
+    /// Symbols used by the callers of `target`, with the callers they share
+    ///
+    /// Every caller of any symbol named `target` is expanded to the other symbols it
+    /// depends on, answering "what else do the users of this symbol touch". Results
+    /// are ordered by the number of shared callers.
+    pub async fn find_shared_callees(&self, target: &str) -> Result<SharedCallees> {
+        let reader = self
+            .symbol_reader
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Binary symbol reader not available"))?;
+        let instances = self.find_all_symbols_by_name(reader, target);
+        if instances.is_empty() {
+            return Err(anyhow::anyhow!("Symbol '{}' not found", target));
+        }
+
+        self.ensure_dependency_graph("shared-callers query").await?;
+        let graph_ref = self.get_dependency_graph()?;
+        let graph = graph_ref
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Dependency graph unavailable"))?;
+
+        let target_ids: HashSet<Uuid> = instances
+            .iter()
+            .filter_map(|(_, id)| Self::resolve_symbol_uuid_with_fallback(graph, target, *id))
+            .collect();
+        let symbol_name = |id: Uuid| {
+            reader
+                .find_symbol(id)
+                .and_then(|symbol| reader.get_symbol_name(&symbol).ok())
+        };
+
+        let mut callers = BTreeSet::new();
+        let mut shared: HashMap<Uuid, BTreeSet<String>> = HashMap::new();
+        for target_id in &target_ids {
+            for (caller_id, _) in graph.find_dependents(*target_id) {
+                let caller =
+                    symbol_name(caller_id).unwrap_or_else(|| format!("symbol_{}", caller_id));
+                callers.insert(caller.clone());
+                for (callee_id, _) in graph.find_dependencies(caller_id) {
+                    if callee_id != caller_id && !target_ids.contains(&callee_id) {
+                        shared.entry(callee_id).or_default().insert(caller.clone());
+                    }
+                }
+            }
+        }
+
+        let mut callees: Vec<SharedCallee> = shared
+            .into_iter()
+            .filter_map(|(id, shared_by)| {
+                let symbol = reader.find_symbol(id)?;
+                let name = reader.get_symbol_name(&symbol).ok()?;
+                if name == target {
+                    return None;
+                }
+                Some(SharedCallee {
+                    name,
+                    file_path: reader.get_symbol_file_path(&symbol).ok()?,
+                    start_line: symbol.start_line,
+                    callers: shared_by.into_iter().collect(),
+                })
+            })
+            .collect();
+        callees.sort_by(|a, b| {
+            b.callers
+                .len()
+                .cmp(&a.callers.len())
+                .then_with(|| a.name.cmp(&b.name))
+                .then_with(|| a.file_path.cmp(&b.file_path))
+        });
+
+        Ok(SharedCallees {
+            total_callers: callers.len(),
+            callees,
+        })
+    }
+
+    /// Every symbol defined in `file_path` with its caller count and the tests calling it
+    ///
+    /// Meant for inline editor hints, so only the loaded dependency graph is read and no
+    /// relationship extraction is triggered. Imports and comments are left out; symbols
+    /// are ordered by position.
+    pub fn file_symbol_links(&self, file_path: &str) -> Result<Vec<SymbolLinks>> {
+        let reader = self
+            .symbol_reader
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Binary symbol reader not available"))?;
+        let graph_ref = self.get_dependency_graph()?;
+        let graph = graph_ref
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Dependency graph unavailable"))?;
+
+        let mut symbols = reader.read_symbols_for_file(file_path);
+        symbols.retain(|symbol| {
+            !matches!(
+                SymbolType::try_from(symbol.kind),
+                Ok(SymbolType::Import | SymbolType::Comment)
+            )
+        });
+        symbols.sort_by_key(|symbol| (symbol.start_line, symbol.start_column));
+
+        let mut links = Vec::with_capacity(symbols.len());
+        for symbol in symbols {
+            let id = Uuid::from_bytes(symbol.id);
+            let mut callers = HashSet::new();
+            let mut tests = Vec::new();
+            for (caller_id, _) in graph.find_dependents(id) {
+                if caller_id == id || !callers.insert(caller_id) {
+                    continue;
+                }
+                let Some(caller) = reader.find_symbol(caller_id) else {
+                    continue;
+                };
+                let (Ok(name), Ok(caller_path)) = (
+                    reader.get_symbol_name(&caller),
+                    reader.get_symbol_file_path(&caller),
+                ) else {
+                    continue;
+                };
+                if name.starts_with("test") || is_test_file(Path::new(&caller_path)) {
+                    tests.push(TestLink {
+                        name,
+                        file_path: caller_path,
+                        start_line: caller.start_line,
+                    });
+                }
+            }
+            tests.sort_by(|a, b| {
+                (&a.file_path, a.start_line, &a.name).cmp(&(&b.file_path, b.start_line, &b.name))
+            });
+            links.push(SymbolLinks {
+                name: reader.get_symbol_name(&symbol)?,
+                kind: Self::convert_symbol_type(symbol.kind).to_string(),
+                start_line: symbol.start_line,
+                end_line: symbol.end_line,
+                start_column: symbol.start_column,
+                end_column: symbol.end_column,
+                callers: callers.len(),
+                tests,
+            });
+        }
+        Ok(links)
+    }
+
+    /// Symbols within `depth` dependency hops of `target`, with the edges between them
+    ///
+    /// Dependents (callers) are followed upstream and dependencies downstream; each
+    /// node records its signed distance as its layer. Both directions are expanded
+    /// one hop at a time until `max_nodes` symbols are collected.
+    pub async fn symbol_neighborhood(
+        &self,
+        target: &str,
+        depth: usize,
+        max_nodes: usize,
+    ) -> Result<SymbolNeighborhood> {
+        let reader = self
+            .symbol_reader
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Binary symbol reader not available"))?;
+        let instances = self.find_all_symbols_by_name(reader, target);
+        if instances.is_empty() {
+            return Err(anyhow::anyhow!("Symbol '{}' not found", target));
+        }
+
+        self.ensure_dependency_graph("symbol graph query").await?;
+        let graph_ref = self.get_dependency_graph()?;
+        let graph = graph_ref
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Dependency graph unavailable"))?;
+
+        let mut order: Vec<Uuid> = Vec::new();
+        let mut layers: HashMap<Uuid, i32> = HashMap::new();
+        for (_, id) in &instances {
+            if let Some(id) = Self::resolve_symbol_uuid_with_fallback(graph, target, *id) {
+                if layers.insert(id, 0).is_none() {
+                    order.push(id);
+                }
+            }
+        }
+        if order.is_empty() {
+            return Err(anyhow::anyhow!(
+                "Symbol '{}' is not in the dependency graph",
+                target
+            ));
+        }
+
+        let mut truncated = false;
+        let mut upstream = order.clone();
+        let mut downstream = order.clone();
+        for distance in 1..=depth as i32 {
+            for (frontier, layer) in [(&mut upstream, -distance), (&mut downstream, distance)] {
+                let mut next = Vec::new();
+                for id in frontier.iter() {
+                    let neighbors = if layer < 0 {
+                        graph.find_dependents(*id)
+                    } else {
+                        graph.find_dependencies(*id)
+                    };
+                    for (neighbor, _) in neighbors {
+                        if layers.contains_key(&neighbor) {
+                            continue;
+                        }
+                        if order.len() >= max_nodes {
+                            truncated = true;
+                            break;
+                        }
+                        layers.insert(neighbor, layer);
+                        order.push(neighbor);
+                        next.push(neighbor);
+                    }
+                }
+                *frontier = next;
+            }
+        }
+
+        let index: HashMap<Uuid, usize> =
+            order.iter().enumerate().map(|(i, id)| (*id, i)).collect();
+        let nodes = order
+            .iter()
+            .filter_map(|id| {
+                let node = &graph.graph[*graph.symbol_to_node.get(id)?];
+                let symbol = reader.find_symbol(*id);
+                let name = symbol
+                    .and_then(|symbol| reader.get_symbol_name(&symbol).ok())
+                    .unwrap_or_else(|| {
+                        node.qualified_name
+                            .rsplit("::")
+                            .next()
+                            .unwrap_or(&node.qualified_name)
+                            .to_string()
+                    });
+                let file_path = symbol
+                    .and_then(|symbol| reader.get_symbol_file_path(&symbol).ok())
+                    .unwrap_or_else(|| node.file_path.to_string_lossy().into_owned());
+                Some(NeighborhoodNode {
+                    name,
+                    qualified_name: node.qualified_name.clone(),
+                    file_path,
+                    symbol_type: node.symbol_type.clone(),
+                    layer: layers[id],
+                })
+            })
+            .collect();
+
+        let mut seen = HashSet::new();
+        let mut edges = Vec::new();
+        for (from, id) in order.iter().enumerate() {
+            for (dependency, relation_type) in graph.find_dependencies(*id) {
+                let Some(&to) = index.get(&dependency) else {
+                    continue;
+                };
+                if from != to && seen.insert((from, to, relation_type.clone())) {
+                    edges.push(NeighborhoodEdge {
+                        from,
+                        to,
+                        relation_type,
+                    });
+                }
+            }
+        }
+
+        Ok(SymbolNeighborhood {
+            nodes,
+            edges,
+            truncated,
+        })
+    }
+
     /// Find all symbols with the given name (handles multiple symbols with same name)
     fn find_all_symbols_by_name(
         &self,
@@ -656,6 +1038,24 @@ impl BinaryRelationshipEngine {
             .collect()
     }
 
+    /// Symbol recorded in the graph under a qualified name, e.g. `app_core::config::load`
+    /// or `src/config.rs::load`
+    fn find_symbols_by_qualified_name(
+        reader: &BinarySymbolReader,
+        graph: &DependencyGraph,
+        name: &str,
+    ) -> Vec<(PackedSymbol, Uuid)> {
+        if !name.contains("::") {
+            return Vec::new();
+        }
+        graph
+            .name_to_symbol
+            .get(name)
+            .and_then(|&id| Some((reader.find_symbol(id)?, id)))
+            .into_iter()
+            .collect()
+    }
+
     /// Resolve symbol UUID with fallback to name-based lookup
     ///
     /// When binary symbols and dependency graphs are generated at different times,
@@ -772,14 +1172,18 @@ impl BinaryRelationshipEngine {
     // Removed generic implementation in favor of specific context-aware implementations
 
     /// Convert caller relationships to RelationshipMatch objects
+    ///
+    /// Locations come from the dependency edge (the call site itself); the caller's
+    /// start line is only used when the edge carries no position.
     fn convert_caller_relationships_to_matches(
         &self,
         reader: &BinarySymbolReader,
-        relationships: &[(Uuid, RelationType)],
+        relationships: &[(Uuid, DependencyEdge)],
         target: &str,
     ) -> Vec<RelationshipMatch> {
         let mut matches = Vec::new();
-        for (id, relation_type) in relationships.iter() {
+        for (id, edge) in relationships.iter() {
+            let relation_type = &edge.relation_type;
             if let Some(symbol) = reader.find_symbol(*id) {
                 let symbol_name = reader.get_symbol_name(&symbol).unwrap_or_else(|e| {
                     warn!("Failed to get symbol name for UUID {}: {}", id, e);
@@ -790,18 +1194,33 @@ impl BinaryRelationshipEngine {
                     "unknown".to_string()
                 });
 
+                let location = if edge.line_number > 0 {
+                    RelationshipLocation {
+                        line_number: edge.line_number,
+                        column_number: edge.column_number,
+                        end_line_number: edge.end_line_number.unwrap_or_default(),
+                        end_column_number: edge.end_column_number.unwrap_or_default(),
+                        start_byte: edge.start_byte.unwrap_or_default(),
+                        end_byte: edge.end_byte.unwrap_or_default(),
+                        file_path: file_path.clone(),
+                    }
+                } else {
+                    Self::symbol_location(&symbol, &file_path)
+                };
+                let line_number = location.line_number;
+
                 // Generate context based on relation type
                 let context = match relation_type {
                     RelationType::Calls => {
-                        format!("Calls {} at line {}", target, symbol.start_line)
+                        format!("Calls {} at line {}", target, line_number)
                     }
                     RelationType::References => {
-                        format!("References {} at line {}", target, symbol.start_line)
+                        format!("References {} at line {}", target, line_number)
                     }
                     RelationType::Implements => {
-                        format!("Implements {} at line {}", target, symbol.start_line)
+                        format!("Implements {} at line {}", target, line_number)
                     }
-                    _ => format!("Uses {} at line {}", target, symbol.start_line),
+                    _ => format!("Uses {} at line {}", target, line_number),
                 };
 
                 matches.push(RelationshipMatch {
@@ -811,27 +1230,38 @@ impl BinaryRelationshipEngine {
                     symbol_type: Self::convert_symbol_type(symbol.kind),
                     file_path: file_path.clone(),
                     relation_type: relation_type.clone(),
-                    location: RelationshipLocation {
-                        line_number: symbol.start_line as usize,
-                        column_number: 0,
-                        file_path: file_path.clone(),
-                    },
+                    location,
                     context,
+                    depth: 1,
+                    snippet: edge.context.clone(),
                 });
             }
         }
         matches
     }
 
+    /// Location spanning a symbol's definition, as recorded in the binary symbol database
+    fn symbol_location(symbol: &PackedSymbol, file_path: &str) -> RelationshipLocation {
+        RelationshipLocation {
+            line_number: symbol.start_line as usize,
+            column_number: symbol.start_column as usize,
+            end_line_number: symbol.end_line as usize,
+            end_column_number: symbol.end_column as usize,
+            start_byte: symbol.start_byte as usize,
+            end_byte: symbol.end_byte as usize,
+            file_path: file_path.to_string(),
+        }
+    }
+
     /// Convert impact relationships to RelationshipMatch objects
     fn convert_impact_relationships_to_matches(
         &self,
         reader: &BinarySymbolReader,
-        relationships: &[(Uuid, RelationType)],
+        relationships: &[(Uuid, RelationType, usize)],
         target: &str,
     ) -> Vec<RelationshipMatch> {
         let mut matches = Vec::new();
-        for (id, relation_type) in relationships.iter() {
+        for (id, relation_type, depth) in relationships.iter() {
             if let Some(symbol) = reader.find_symbol(*id) {
                 let symbol_name = reader.get_symbol_name(&symbol).unwrap_or_else(|e| {
                     warn!("Failed to get symbol name for UUID {}: {}", id, e);
@@ -849,12 +1279,10 @@ impl BinaryRelationshipEngine {
                     symbol_type: Self::convert_symbol_type(symbol.kind),
                     file_path: file_path.clone(),
                     relation_type: relation_type.clone(),
-                    location: RelationshipLocation {
-                        line_number: symbol.start_line as usize,
-                        column_number: 0,
-                        file_path: file_path.clone(),
-                    },
+                    location: Self::symbol_location(&symbol, &file_path),
                     context: format!("Would be impacted by changes to {}", target),
+                    depth: *depth,
+                    snippet: None,
                 });
             }
         }
@@ -968,7 +1396,8 @@ impl BinaryRelationshipEngine {
         }
 
         // Create relationship bridge and extract relationships using the actual repository path
-        let bridge = BinaryRelationshipBridge::new();
+        let crates = CrateMap::load(&self.db_path)?.unwrap_or_default();
+        let bridge = BinaryRelationshipBridge::new().with_crates(crates);
         let dependency_graph = bridge
             .extract_relationships(&symbol_db_path, &source_repo_path, &files)
             .with_context(|| "Failed to extract relationships from binary symbols")?;
@@ -1377,6 +1806,136 @@ impl BinaryRelationshipEngine {
     }
 }
 
+/// Query plan details captured while executing a relationship query
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct QueryExplanation {
+    /// The query that was executed
+    pub query: String,
+    /// Symbol data path used: "binary_symbols" or "legacy_fallback"
+    pub data_path: String,
+    /// Dependency graph origin: "cached", "extracted_on_demand" or "unavailable"
+    pub graph_source: String,
+    /// Number of binary symbols scanned while resolving the target
+    pub symbols_scanned: usize,
+    /// Number of symbols matching the target name
+    pub target_instances: usize,
+    /// Graph nodes whose incoming edges were expanded
+    pub nodes_visited: usize,
+    /// Graph edges followed during traversal
+    pub edges_visited: usize,
+    /// Queries served from the in-memory dependency graph
+    pub cache_hits: u64,
+    /// Queries that had to load or extract the dependency graph
+    pub cache_misses: u64,
+    /// Timing for each executed phase, in execution order
+    pub phases: Vec<QueryPhaseTiming>,
+    /// Total wall-clock time for the query in microseconds
+    pub total_time_us: u64,
+    /// Traversal stopped at the query deadline, so results are partial
+    #[serde(default)]
+    pub timed_out: bool,
+    /// Served from the relationship result cache; the plan is that of the original run
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub result_cache_hit: bool,
+}
+
+/// Timing of a single query execution phase
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct QueryPhaseTiming {
+    pub phase: String,
+    pub duration_us: u64,
+}
+
+impl QueryExplanation {
+    fn record_phase(&mut self, phase: &str, started: std::time::Instant) {
+        self.phases.push(QueryPhaseTiming {
+            phase: phase.to_string(),
+            duration_us: started.elapsed().as_micros() as u64,
+        });
+    }
+
+    fn record_graph_source(&mut self, cached: bool) {
+        if cached {
+            self.graph_source = "cached".to_string();
+            self.cache_hits += 1;
+        } else {
+            self.graph_source = "extracted_on_demand".to_string();
+        }
+    }
+}
+
+/// Symbols around a queried symbol in the dependency graph
+#[derive(Debug, Clone, Default)]
+pub struct SymbolNeighborhood {
+    /// Queried symbol instances first, then in order of discovery
+    pub nodes: Vec<NeighborhoodNode>,
+    pub edges: Vec<NeighborhoodEdge>,
+    /// Whether expansion stopped at the node limit
+    pub truncated: bool,
+}
+
+/// A symbol in a [`SymbolNeighborhood`]
+#[derive(Debug, Clone)]
+pub struct NeighborhoodNode {
+    pub name: String,
+    pub qualified_name: String,
+    pub file_path: String,
+    pub symbol_type: SymbolType,
+    /// Hops from the queried symbol: negative for dependents, positive for dependencies
+    pub layer: i32,
+}
+
+/// A dependency between two nodes of a [`SymbolNeighborhood`], by node index
+#[derive(Debug, Clone)]
+pub struct NeighborhoodEdge {
+    /// Index of the dependent node
+    pub from: usize,
+    /// Index of the node depended on
+    pub to: usize,
+    pub relation_type: RelationType,
+}
+
+/// Symbols that share callers with a queried symbol
+#[derive(Debug, Clone, Default)]
+pub struct SharedCallees {
+    /// Distinct callers of the queried symbol
+    pub total_callers: usize,
+    pub callees: Vec<SharedCallee>,
+}
+
+/// A symbol used by some of the callers of a queried symbol
+#[derive(Debug, Clone)]
+pub struct SharedCallee {
+    pub name: String,
+    pub file_path: String,
+    pub start_line: u32,
+    /// Names of the queried symbol's callers that also use this symbol
+    pub callers: Vec<String>,
+}
+
+/// A symbol with the code that depends on it, for inline editor hints
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct SymbolLinks {
+    pub name: String,
+    pub kind: String,
+    pub start_line: u32,
+    pub end_line: u32,
+    pub start_column: u32,
+    pub end_column: u32,
+    /// Distinct symbols that depend on this one
+    pub callers: usize,
+    /// Callers that are tests
+    pub tests: Vec<TestLink>,
+}
+
+/// A test that calls a symbol directly
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct TestLink {
+    pub name: String,
+    pub file_path: String,
+    pub start_line: u32,
+}
+
 /// Statistics about the binary engine
 #[derive(Debug, Clone)]
 pub struct BinaryEngineStats {
@@ -1431,7 +1990,7 @@ impl CacheMetadata {
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use super::*;
     use crate::binary_symbols::BinarySymbolWriter;
     use crate::dependency_extractor::{DependencyEdge, SymbolNode};
@@ -1997,6 +2556,10 @@ mod tests {
                 relation_type: RelationType::Calls,
                 line_number: 50,
                 column_number: 10,
+                end_line_number: None,
+                end_column_number: None,
+                start_byte: None,
+                end_byte: None,
                 context: Some("FileStorage::new()".to_string()),
             },
         );
@@ -2045,5 +2608,113 @@ mod tests {
         let caller = &result.direct_relationships[0];
         assert_eq!(caller.symbol_name, "main");
         assert_eq!(caller.file_path, "src/main.rs");
+
+        // The location and snippet come from the call-site edge, not the caller definition
+        assert_eq!(caller.location.line_number, 50);
+        assert_eq!(caller.location.column_number, 10);
+        assert_eq!(caller.snippet.as_deref(), Some("FileStorage::new()"));
+    }
+
+    /// Write symbols and a dependency graph in which each of `callers` (in
+    /// `src/main.rs`) calls `target` (in `src/lib.rs`)
+    pub(crate) fn write_target_with_callers(db_path: &Path, callers: &[&str]) {
+        let target_uuid = Uuid::new_v4();
+        let mut writer = BinarySymbolWriter::new();
+        writer.add_symbol(target_uuid, "target", 1, "src/lib.rs", 10, 20, None);
+
+        let mut graph = DiGraph::new();
+        let mut symbol_to_node = HashMap::new();
+        let target_idx = graph.add_node(SymbolNode {
+            symbol_id: target_uuid,
+            qualified_name: "src/lib.rs::target".to_string(),
+            symbol_type: SymbolType::Function,
+            file_path: PathBuf::from("src/lib.rs"),
+            in_degree: callers.len(),
+            out_degree: 0,
+        });
+        symbol_to_node.insert(target_uuid, target_idx);
+        for (i, caller) in callers.iter().enumerate() {
+            let caller_uuid = Uuid::new_v4();
+            let line = 30 + 10 * i as u32;
+            writer.add_symbol(caller_uuid, caller, 1, "src/main.rs", line, line + 10, None);
+            let caller_idx = graph.add_node(SymbolNode {
+                symbol_id: caller_uuid,
+                qualified_name: format!("src/main.rs::{caller}"),
+                symbol_type: SymbolType::Function,
+                file_path: PathBuf::from("src/main.rs"),
+                in_degree: 0,
+                out_degree: 1,
+            });
+            symbol_to_node.insert(caller_uuid, caller_idx);
+            graph.add_edge(
+                caller_idx,
+                target_idx,
+                DependencyEdge {
+                    relation_type: RelationType::Calls,
+                    line_number: line as usize + 5,
+                    column_number: 4,
+                    end_line_number: None,
+                    end_column_number: None,
+                    start_byte: None,
+                    end_byte: None,
+                    context: None,
+                },
+            );
+        }
+        writer
+            .write_to_file(&db_path.join("symbols.kota"))
+            .expect("Failed to save symbols");
+        let dependency_graph = DependencyGraph {
+            graph,
+            symbol_to_node,
+            name_to_symbol: HashMap::new(),
+            file_imports: HashMap::new(),
+            stats: Default::default(),
+        };
+        BinaryRelationshipEngine::save_dependency_graph(
+            &dependency_graph,
+            &db_path.join("dependency_graph.bin"),
+        )
+        .expect("Failed to save dependency graph");
+    }
+
+    #[tokio::test]
+    async fn test_execute_query_with_explain_reports_plan() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let db_path = temp_dir.path();
+        write_target_with_callers(db_path, &["caller"]);
+
+        let engine = BinaryRelationshipEngine::new(db_path, RelationshipQueryConfig::default())
+            .await
+            .expect("Failed to create engine");
+
+        let (result, explain) = engine
+            .execute_query_with_explain(RelationshipQueryType::ImpactAnalysis {
+                target: "target".to_string(),
+            })
+            .await
+            .expect("Query failed");
+
+        assert_eq!(result.stats.direct_count, 1);
+        assert_eq!(result.direct_relationships[0].depth, 1);
+        assert_eq!(explain.data_path, "binary_symbols");
+        assert_eq!(explain.graph_source, "cached");
+        assert_eq!(explain.symbols_scanned, 2);
+        assert_eq!(explain.target_instances, 1);
+        // Target and caller are both expanded; only the target has an incoming edge
+        assert_eq!(explain.nodes_visited, 2);
+        assert_eq!(explain.edges_visited, 1);
+        assert_eq!(explain.cache_hits, 1);
+        assert_eq!(explain.cache_misses, 0);
+        let phases: Vec<&str> = explain.phases.iter().map(|p| p.phase.as_str()).collect();
+        assert_eq!(
+            phases,
+            vec![
+                "load_dependency_graph",
+                "resolve_target_symbols",
+                "traverse_graph",
+                "build_matches"
+            ]
+        );
     }
 }
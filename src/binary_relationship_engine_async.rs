@@ -3,16 +3,30 @@
 //! This module provides an async-safe wrapper around BinaryRelationshipEngine that uses
 //! tokio::task::spawn_blocking to ensure CPU-intensive operations don't block the async runtime.
 //! This is critical for HTTP API integration where blocking operations would degrade performance.
+//!
+//! The wrapper also keeps an LRU cache of query results. Callers and impact queries on
+//! popular symbols are asked again and again between re-indexes, so results are cached
+//! by symbol, operation, query options and graph generation: a fingerprint of the symbol
+//! database and dependency graph files. When re-indexing changes those files, the engine
+//! is reloaded and the cache is cleared. Timed-out and failed queries are never cached.
 
 use anyhow::{Context, Result};
 use once_cell::sync::Lazy;
-use std::path::Path;
-use std::sync::Arc;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
 use tokio::runtime::Runtime;
-use tracing::{debug, info, instrument};
+use tokio::sync::RwLock;
+use tracing::{debug, info, instrument, warn};
 
 use crate::{
-    binary_relationship_engine::{BinaryRelationshipEngine, ExtractionConfig},
+    binary_relationship_engine::{
+        BinaryEngineStats, BinaryRelationshipEngine, ExtractionConfig, QueryExplanation,
+    },
+    query_deadline::QueryDeadline,
     relationship_query::{RelationshipQueryConfig, RelationshipQueryResult, RelationshipQueryType},
 };
 
@@ -27,25 +41,305 @@ static BLOCKING_RUNTIME: Lazy<Runtime> = Lazy::new(|| {
         .expect("Failed to create blocking runtime")
 });
 
+/// Query results kept by the result cache unless configured otherwise
+pub const DEFAULT_RESULT_CACHE_CAPACITY: usize = 256;
+
+/// Environment variable holding the result cache capacity; 0 disables caching
+pub const RESULT_CACHE_SIZE_ENV: &str = "KOTADB_RELATIONSHIP_CACHE_SIZE";
+
+/// Files whose size and modification time make up the graph generation
+const GRAPH_ARTIFACTS: [&str; 2] = ["symbols.kota", "dependency_graph.bin"];
+
+/// Fingerprint of the symbol database and dependency graph files under `db_path`
+///
+/// Changes whenever re-indexing rewrites either file, including from another process.
+pub fn graph_generation(db_path: &Path) -> u64 {
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    for artifact in GRAPH_ARTIFACTS {
+        match std::fs::metadata(db_path.join(artifact)) {
+            Ok(meta) => {
+                meta.len().hash(&mut hasher);
+                meta.modified().ok().hash(&mut hasher);
+            }
+            Err(_) => 0u8.hash(&mut hasher),
+        }
+    }
+    hasher.finish()
+}
+
+/// Identity of a cached query result
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct QueryCacheKey {
+    pub symbol: String,
+    pub operation: &'static str,
+    /// Query parameters besides the symbol, e.g. the other end of a call chain
+    pub options: String,
+    pub generation: u64,
+}
+
+impl QueryCacheKey {
+    pub fn new(query_type: &RelationshipQueryType, generation: u64) -> Self {
+        let (operation, symbol, options) = match query_type {
+            RelationshipQueryType::FindCallers { target } => {
+                ("find_callers", target.clone(), String::new())
+            }
+            RelationshipQueryType::FindCallees { target } => {
+                ("find_callees", target.clone(), String::new())
+            }
+            RelationshipQueryType::ImpactAnalysis { target } => {
+                ("impact_analysis", target.clone(), String::new())
+            }
+            RelationshipQueryType::CallChain { from, to } => {
+                ("call_chain", from.clone(), format!("to={to}"))
+            }
+            RelationshipQueryType::CircularDependencies { target } => (
+                "circular_dependencies",
+                target.clone().unwrap_or_default(),
+                String::new(),
+            ),
+            RelationshipQueryType::UnusedSymbols { symbol_type } => (
+                "unused_symbols",
+                String::new(),
+                format!("symbol_type={symbol_type:?}"),
+            ),
+            RelationshipQueryType::HotPaths { limit } => {
+                ("hot_paths", String::new(), format!("limit={limit:?}"))
+            }
+            RelationshipQueryType::DependenciesByType {
+                target,
+                relation_type,
+            } => (
+                "dependencies_by_type",
+                target.clone(),
+                format!("relation_type={relation_type:?}"),
+            ),
+        };
+        Self {
+            symbol,
+            operation,
+            options,
+            generation,
+        }
+    }
+}
+
+type CachedQuery = Arc<(RelationshipQueryResult, QueryExplanation)>;
+
+/// Hit, miss and eviction counts of the result cache
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ResultCacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+    pub entries: usize,
+    pub capacity: usize,
+}
+
+impl ResultCacheStats {
+    /// Share of lookups served from the cache; 0 before the first lookup
+    pub fn hit_rate(&self) -> f64 {
+        let lookups = self.hits + self.misses;
+        if lookups == 0 {
+            0.0
+        } else {
+            self.hits as f64 / lookups as f64
+        }
+    }
+
+    /// Prometheus exposition of the cache counters
+    pub fn to_prometheus(&self) -> String {
+        let families = [
+            (
+                "kotadb_relationship_cache_hits_total",
+                "counter",
+                "Relationship queries served from the result cache",
+                self.hits.to_string(),
+            ),
+            (
+                "kotadb_relationship_cache_misses_total",
+                "counter",
+                "Relationship queries computed because no cached result matched",
+                self.misses.to_string(),
+            ),
+            (
+                "kotadb_relationship_cache_evictions_total",
+                "counter",
+                "Cached relationship results evicted as least recently used",
+                self.evictions.to_string(),
+            ),
+            (
+                "kotadb_relationship_cache_entries",
+                "gauge",
+                "Relationship results currently cached",
+                self.entries.to_string(),
+            ),
+            (
+                "kotadb_relationship_cache_hit_ratio",
+                "gauge",
+                "Share of relationship queries served from the result cache",
+                self.hit_rate().to_string(),
+            ),
+        ];
+        let mut prometheus = String::new();
+        for (name, kind, help, value) in families {
+            prometheus.push_str(&format!(
+                "# HELP {} {}\n# TYPE {} {}\n{} {}\n",
+                name, help, name, kind, name, value
+            ));
+        }
+        prometheus
+    }
+}
+
+/// Least-recently-used map from query to result
+#[derive(Default)]
+struct LruEntries {
+    /// Result and last-use tick of every cached query
+    entries: HashMap<QueryCacheKey, (u64, CachedQuery)>,
+    /// Cached queries by last-use tick, oldest first
+    recency: BTreeMap<u64, QueryCacheKey>,
+    tick: u64,
+}
+
+impl LruEntries {
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+
+    fn get(&mut self, key: &QueryCacheKey) -> Option<CachedQuery> {
+        let tick = self.next_tick();
+        let (used, value) = self.entries.get_mut(key)?;
+        self.recency.remove(used);
+        *used = tick;
+        self.recency.insert(tick, key.clone());
+        Some(value.clone())
+    }
+
+    /// Insert `value`, returning how many entries were evicted to stay within `capacity`
+    fn insert(&mut self, key: QueryCacheKey, value: CachedQuery, capacity: usize) -> u64 {
+        let tick = self.next_tick();
+        if let Some((used, _)) = self.entries.insert(key.clone(), (tick, value)) {
+            self.recency.remove(&used);
+        }
+        self.recency.insert(tick, key);
+        let mut evicted = 0;
+        while self.entries.len() > capacity {
+            let Some((_, oldest)) = self.recency.pop_first() else {
+                break;
+            };
+            self.entries.remove(&oldest);
+            evicted += 1;
+        }
+        evicted
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+        self.recency.clear();
+    }
+}
+
+/// LRU cache of relationship query results with hit-rate counters
+struct ResultCache {
+    capacity: usize,
+    entries: Mutex<LruEntries>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    evictions: AtomicU64,
+}
+
+impl ResultCache {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: Mutex::new(LruEntries::default()),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            evictions: AtomicU64::new(0),
+        }
+    }
+
+    /// Capacity from [`RESULT_CACHE_SIZE_ENV`], else [`DEFAULT_RESULT_CACHE_CAPACITY`]
+    fn from_env() -> Self {
+        let capacity = std::env::var(RESULT_CACHE_SIZE_ENV)
+            .ok()
+            .and_then(|value| value.trim().parse().ok())
+            .unwrap_or(DEFAULT_RESULT_CACHE_CAPACITY);
+        Self::new(capacity)
+    }
+
+    fn entries(&self) -> std::sync::MutexGuard<'_, LruEntries> {
+        self.entries
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn get(&self, key: &QueryCacheKey) -> Option<CachedQuery> {
+        if self.capacity == 0 {
+            return None;
+        }
+        let cached = self.entries().get(key);
+        let counter = if cached.is_some() {
+            &self.hits
+        } else {
+            &self.misses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        cached
+    }
+
+    fn insert(&self, key: QueryCacheKey, value: CachedQuery) {
+        if self.capacity == 0 {
+            return;
+        }
+        let evicted = self.entries().insert(key, value, self.capacity);
+        self.evictions.fetch_add(evicted, Ordering::Relaxed);
+    }
+
+    fn clear(&self) {
+        self.entries().clear();
+    }
+
+    fn stats(&self) -> ResultCacheStats {
+        ResultCacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            evictions: self.evictions.load(Ordering::Relaxed),
+            entries: self.entries().entries.len(),
+            capacity: self.capacity,
+        }
+    }
+}
+
+/// Engine loaded from the database files of one graph generation
+struct LoadedEngine {
+    engine: Arc<BinaryRelationshipEngine>,
+    generation: u64,
+}
+
 /// Thread-safe async wrapper for BinaryRelationshipEngine
 ///
 /// This wrapper ensures all potentially blocking operations are executed
 /// on a dedicated thread pool via spawn_blocking, preventing them from
 /// blocking the tokio runtime when called from async contexts like HTTP handlers.
+/// Clones share the engine and its result cache.
 #[derive(Clone)]
 pub struct AsyncBinaryRelationshipEngine {
-    /// The underlying engine wrapped in Arc for thread-safe sharing
-    engine: Arc<BinaryRelationshipEngine>,
+    /// The underlying engine, replaced when the graph generation changes
+    loaded: Arc<RwLock<LoadedEngine>>,
+    /// Results of recent queries
+    cache: Arc<ResultCache>,
+    db_path: PathBuf,
+    config: RelationshipQueryConfig,
+    extraction_config: ExtractionConfig,
 }
 
 impl AsyncBinaryRelationshipEngine {
     /// Create a new async binary engine from database paths
     #[instrument]
     pub async fn new(db_path: &Path, config: RelationshipQueryConfig) -> Result<Self> {
-        let engine = BinaryRelationshipEngine::new(db_path, config).await?;
-        Ok(Self {
-            engine: Arc::new(engine),
-        })
+        Self::with_extraction_config(db_path, config, ExtractionConfig::default()).await
     }
 
     /// Create a new async binary engine with custom extraction configuration
@@ -55,14 +349,75 @@ impl AsyncBinaryRelationshipEngine {
         config: RelationshipQueryConfig,
         extraction_config: ExtractionConfig,
     ) -> Result<Self> {
-        let engine =
-            BinaryRelationshipEngine::with_extraction_config(db_path, config, extraction_config)
-                .await?;
+        let generation = graph_generation(db_path);
+        let engine = BinaryRelationshipEngine::with_extraction_config(
+            db_path,
+            config.clone(),
+            extraction_config.clone(),
+        )
+        .await?;
         Ok(Self {
-            engine: Arc::new(engine),
+            loaded: Arc::new(RwLock::new(LoadedEngine {
+                engine: Arc::new(engine),
+                generation,
+            })),
+            cache: Arc::new(ResultCache::from_env()),
+            db_path: db_path.to_path_buf(),
+            config,
+            extraction_config,
         })
     }
 
+    /// Keep at most `capacity` query results; 0 disables the cache
+    pub fn with_result_cache_capacity(mut self, capacity: usize) -> Self {
+        self.cache = Arc::new(ResultCache::new(capacity));
+        self
+    }
+
+    /// Hit, miss and eviction counts of the result cache
+    pub fn cache_stats(&self) -> ResultCacheStats {
+        self.cache.stats()
+    }
+
+    /// Statistics of the currently loaded engine
+    pub async fn engine_stats(&self) -> BinaryEngineStats {
+        self.current_engine().await.0.get_stats()
+    }
+
+    /// The engine for the current graph generation, reloading it if the files changed
+    ///
+    /// A failed reload keeps serving the previous engine.
+    async fn current_engine(&self) -> (Arc<BinaryRelationshipEngine>, u64) {
+        let generation = graph_generation(&self.db_path);
+        {
+            let loaded = self.loaded.read().await;
+            if loaded.generation == generation {
+                return (loaded.engine.clone(), generation);
+            }
+        }
+        let mut loaded = self.loaded.write().await;
+        if loaded.generation != generation {
+            match BinaryRelationshipEngine::with_extraction_config(
+                &self.db_path,
+                self.config.clone(),
+                self.extraction_config.clone(),
+            )
+            .await
+            {
+                Ok(engine) => {
+                    info!("Graph generation changed, reloaded relationship engine");
+                    *loaded = LoadedEngine {
+                        engine: Arc::new(engine),
+                        generation,
+                    };
+                    self.cache.clear();
+                }
+                Err(e) => warn!("Keeping previous relationship engine, reload failed: {e:#}"),
+            }
+        }
+        (loaded.engine.clone(), loaded.generation)
+    }
+
     /// Execute a relationship query with proper async/sync boundary handling
     ///
     /// This method uses spawn_blocking to ensure the potentially CPU-intensive
@@ -73,23 +428,53 @@ impl AsyncBinaryRelationshipEngine {
         &self,
         query_type: RelationshipQueryType,
     ) -> Result<RelationshipQueryResult> {
-        info!("Executing async relationship query: {:?}", query_type);
+        self.execute_query_with_deadline(query_type, QueryDeadline::none())
+            .await
+            .map(|(result, _)| result)
+    }
+
+    /// Execute a relationship query with its plan, answering from the result cache
+    /// when the same query already ran at the current graph generation
+    ///
+    /// Traversal stops at `deadline` (see
+    /// [`BinaryRelationshipEngine::execute_query_with_deadline`]).
+    #[instrument(skip(self))]
+    pub async fn execute_query_with_deadline(
+        &self,
+        query_type: RelationshipQueryType,
+        deadline: QueryDeadline,
+    ) -> Result<(RelationshipQueryResult, QueryExplanation)> {
+        let (engine, generation) = self.current_engine().await;
+        let key = QueryCacheKey::new(&query_type, generation);
+        if let Some(cached) = self.cache.get(&key) {
+            debug!("Relationship query served from cache: {:?}", query_type);
+            let (result, mut explanation) = (*cached).clone();
+            explanation.result_cache_hit = true;
+            return Ok((result, explanation));
+        }
 
-        // Clone the Arc to move into the spawn_blocking closure
-        let engine = self.engine.clone();
+        info!("Executing async relationship query: {:?}", query_type);
 
         // Use spawn_blocking to run the query on a dedicated thread pool
         // This prevents blocking the tokio runtime
-        let result = tokio::task::spawn_blocking(move || {
+        let (result, explanation) = tokio::task::spawn_blocking(move || {
             // Use the shared runtime pool instead of creating a new one each time
             // This eliminates the ~1-2ms overhead of runtime creation
-            BLOCKING_RUNTIME.block_on(async move { engine.execute_query(query_type).await })
+            BLOCKING_RUNTIME.block_on(async move {
+                engine
+                    .execute_query_with_deadline(query_type, deadline)
+                    .await
+            })
         })
         .await
-        .context("Task join error")?;
+        .context("Task join error")??;
 
         debug!("Async query execution completed");
-        result
+        if !explanation.timed_out {
+            self.cache
+                .insert(key, Arc::new((result.clone(), explanation.clone())));
+        }
+        Ok((result, explanation))
     }
 
     /// Execute a find callers query with async safety
@@ -190,6 +575,7 @@ impl AsyncBinaryRelationshipEngine {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::binary_relationship_engine::tests::write_target_with_callers;
     use tempfile::TempDir;
 
     #[tokio::test]
@@ -291,4 +677,70 @@ mod tests {
 
         // If we get here without panicking, the test passes
     }
+
+    #[tokio::test]
+    async fn test_results_are_cached_per_graph_generation() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let db_path = temp_dir.path();
+        write_target_with_callers(db_path, &["caller"]);
+        let engine =
+            AsyncBinaryRelationshipEngine::new(db_path, RelationshipQueryConfig::default())
+                .await
+                .expect("Failed to create engine")
+                .with_result_cache_capacity(8);
+        let callers = || RelationshipQueryType::FindCallers {
+            target: "target".to_string(),
+        };
+
+        let (result, explanation) = engine
+            .execute_query_with_deadline(callers(), QueryDeadline::none())
+            .await
+            .expect("Query failed");
+        assert_eq!(result.stats.direct_count, 1);
+        assert!(!explanation.result_cache_hit);
+        let (cached, explanation) = engine
+            .execute_query_with_deadline(callers(), QueryDeadline::none())
+            .await
+            .expect("Query failed");
+        assert!(explanation.result_cache_hit);
+        assert_eq!(cached.stats.direct_count, 1);
+        // Another operation on the same symbol is a different query
+        engine.analyze_impact("target").await.expect("Query failed");
+        let stats = engine.cache_stats();
+        assert_eq!((stats.hits, stats.misses, stats.entries), (1, 2, 2));
+        assert!((stats.hit_rate() - 1.0 / 3.0).abs() < f64::EPSILON);
+
+        // Re-indexing changes the graph generation, so the engine is reloaded
+        write_target_with_callers(db_path, &["caller", "other_caller"]);
+        let result = engine.find_callers("target").await.expect("Query failed");
+        assert_eq!(result.stats.direct_count, 2);
+        assert_eq!(engine.cache_stats().entries, 1);
+    }
+
+    #[tokio::test]
+    async fn test_least_recently_used_results_are_evicted() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let db_path = temp_dir.path();
+        write_target_with_callers(db_path, &["caller"]);
+        let engine =
+            AsyncBinaryRelationshipEngine::new(db_path, RelationshipQueryConfig::default())
+                .await
+                .expect("Failed to create engine")
+                .with_result_cache_capacity(2);
+
+        engine.find_callers("target").await.expect("Query failed");
+        engine.analyze_impact("target").await.expect("Query failed");
+        // Using the callers result makes the impact result the least recently used
+        engine.find_callers("target").await.expect("Query failed");
+        engine.find_callers("caller").await.expect("Query failed");
+        engine.find_callers("target").await.expect("Query failed");
+        engine.analyze_impact("target").await.expect("Query failed");
+
+        let stats = engine.cache_stats();
+        assert_eq!((stats.hits, stats.misses), (2, 4));
+        assert_eq!((stats.entries, stats.evictions), (2, 2));
+        assert!(stats
+            .to_prometheus()
+            .contains("kotadb_relationship_cache_hits_total 2\n"));
+    }
 }
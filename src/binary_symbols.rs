@@ -2,21 +2,42 @@
 //!
 //! This module provides a zero-dependency, high-performance storage format
 //! for code symbols using memory-mapped files and fixed-size structs.
+//!
+//! Symbol name patterns are answered from a [`SymbolNameIndex`]: the distinct search
+//! keys of symbol names (lowercase, Unicode-normalized and optionally transliterated,
+//! see [`identifier_search_key`]) sharded by first character and sorted within each
+//! shard. Patterns with a
+//! literal prefix (`handle_*`) binary-search the one shard that can hold them, while
+//! substring and leading-wildcard patterns scan all shards in parallel. Servers keep
+//! the reader and its index across queries with a [`SharedSymbolReader`].
 
 use anyhow::{Context, Result};
 use memmap2::{Mmap, MmapOptions};
+use rayon::prelude::*;
 use std::collections::HashMap;
 use std::fs::{File, OpenOptions};
 use std::io::{BufWriter, Write};
 use std::mem;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 use tracing::info;
 
+use crate::identifier_tokenization::{identifier_search_key, identifier_transliteration_enabled};
+
 /// Magic bytes to identify our file format
 const KOTA_MAGIC: &[u8; 4] = b"KOTA";
 
 /// Current version of the binary format
-const FORMAT_VERSION: u32 = 1;
+///
+/// Version 2 appends column and byte ranges to each symbol record. Version 1
+/// databases are still readable; their ranges read back as zero.
+pub(crate) const FORMAT_VERSION: u32 = 2;
+
+/// Oldest binary format version this build reads
+pub(crate) const OLDEST_READABLE_VERSION: u32 = 1;
+
+/// Size of a version 1 symbol record (before column/byte ranges were added)
+const V1_SYMBOL_SIZE: usize = 56;
 
 /// Platform endianness marker (1 = little-endian, 2 = big-endian)
 /// TODO: Store this in header reserved bytes in v2 for cross-platform support
@@ -52,6 +73,23 @@ pub struct PackedSymbol {
     pub parent_id: [u8; 16],
     /// Reserved for future use
     pub _reserved: [u8; 3],
+    /// Start column (0-based, in bytes from the start of the line)
+    pub start_column: u32,
+    /// End column (0-based, exclusive)
+    pub end_column: u32,
+    /// Byte offset of the symbol start within its file
+    pub start_byte: u32,
+    /// Byte offset just past the symbol end within its file
+    pub end_byte: u32,
+}
+
+/// Column and byte range of a symbol within its source file
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SymbolRange {
+    pub start_column: u32,
+    pub end_column: u32,
+    pub start_byte: u32,
+    pub end_byte: u32,
 }
 
 impl PackedSymbol {
@@ -79,6 +117,21 @@ impl PackedSymbol {
         // input bytes array has exactly the right size
         unsafe { mem::transmute(bytes) }
     }
+
+    /// Column and byte range recorded for this symbol
+    pub fn range(&self) -> SymbolRange {
+        SymbolRange {
+            start_column: self.start_column,
+            end_column: self.end_column,
+            start_byte: self.start_byte,
+            end_byte: self.end_byte,
+        }
+    }
+
+    /// Whether a byte range was recorded (false for version 1 databases)
+    pub fn has_byte_range(&self) -> bool {
+        self.end_byte > self.start_byte
+    }
 }
 
 /// Header for the symbol database file
@@ -153,6 +206,31 @@ impl BinarySymbolWriter {
         start_line: u32,
         end_line: u32,
         parent_id: Option<uuid::Uuid>,
+    ) {
+        self.add_symbol_with_range(
+            id,
+            name,
+            kind,
+            file_path,
+            start_line,
+            end_line,
+            SymbolRange::default(),
+            parent_id,
+        );
+    }
+
+    /// Add a symbol together with its column and byte range
+    #[allow(clippy::too_many_arguments)]
+    pub fn add_symbol_with_range(
+        &mut self,
+        id: uuid::Uuid,
+        name: &str,
+        kind: u8,
+        file_path: &str,
+        start_line: u32,
+        end_line: u32,
+        range: SymbolRange,
+        parent_id: Option<uuid::Uuid>,
     ) {
         let name_offset = self.intern_string(name);
         let file_path_offset = self.intern_string(file_path);
@@ -166,6 +244,10 @@ impl BinarySymbolWriter {
             end_line,
             parent_id: parent_id.map_or([0u8; 16], |pid| *pid.as_bytes()),
             _reserved: [0; 3],
+            start_column: range.start_column,
+            end_column: range.end_column,
+            start_byte: range.start_byte,
+            end_byte: range.end_byte,
         };
 
         self.symbols.push(packed);
@@ -225,6 +307,8 @@ impl Default for BinarySymbolWriter {
 pub struct BinarySymbolReader {
     mmap: Mmap,
     header: SymbolDatabaseHeader,
+    /// On-disk size of one symbol record (depends on the format version)
+    record_size: usize,
     /// Fast UUID → index mapping for O(1) lookups
     ///
     /// Trade-off: This consumes ~24 bytes per symbol (UUID + usize) for O(1) performance.
@@ -233,6 +317,8 @@ pub struct BinarySymbolReader {
     uuid_index: std::collections::HashMap<uuid::Uuid, usize>,
     /// Lazily-built file path -> symbol indices index for fast file-scoped queries
     file_index: once_cell::sync::OnceCell<std::collections::HashMap<String, Vec<usize>>>,
+    /// Lazily-built symbol name index for pattern searches
+    name_index: once_cell::sync::OnceCell<SymbolNameIndex>,
 }
 
 impl BinarySymbolReader {
@@ -262,12 +348,19 @@ impl BinarySymbolReader {
                 String::from_utf8_lossy(&header.magic)
             );
         }
-        if header.version != FORMAT_VERSION {
-            anyhow::bail!(
+        let record_size = match header.version {
+            OLDEST_READABLE_VERSION => V1_SYMBOL_SIZE,
+            FORMAT_VERSION => PackedSymbol::SIZE,
+            other => anyhow::bail!(
                 "Unsupported symbol database version: {} (expected {})",
-                header.version,
+                other,
                 FORMAT_VERSION
-            );
+            ),
+        };
+        let symbols_end =
+            header.symbols_offset as usize + header.symbol_count as usize * record_size;
+        if mmap.len() < symbols_end {
+            anyhow::bail!("Symbol database truncated: symbol table extends past end of file");
         }
 
         // Check endianness (stored in reserved bytes for now)
@@ -288,12 +381,11 @@ impl BinarySymbolReader {
         // 2. Bloom filter for existence checks before lookup
         // 3. LRU cache for frequently accessed symbols
         for i in 0..symbol_count {
-            let offset = header.symbols_offset as usize + i * PackedSymbol::SIZE;
-            let mut symbol_bytes = [0u8; PackedSymbol::SIZE];
-            symbol_bytes.copy_from_slice(&mmap[offset..offset + PackedSymbol::SIZE]);
-            let symbol = PackedSymbol::from_bytes(symbol_bytes);
-            let uuid = uuid::Uuid::from_bytes(symbol.id);
-            uuid_index.insert(uuid, i);
+            // The UUID is the first field of every record version
+            let offset = header.symbols_offset as usize + i * record_size;
+            let mut id = [0u8; 16];
+            id.copy_from_slice(&mmap[offset..offset + 16]);
+            uuid_index.insert(uuid::Uuid::from_bytes(id), i);
         }
 
         let index_memory = symbol_count * std::mem::size_of::<(uuid::Uuid, usize)>();
@@ -306,8 +398,10 @@ impl BinarySymbolReader {
         Ok(Self {
             mmap,
             header,
+            record_size,
             uuid_index,
             file_index: once_cell::sync::OnceCell::new(),
+            name_index: once_cell::sync::OnceCell::new(),
         })
     }
 
@@ -322,9 +416,11 @@ impl BinarySymbolReader {
             return None;
         }
 
-        let offset = self.header.symbols_offset as usize + index * PackedSymbol::SIZE;
+        // Older records are a prefix of the current layout; missing fields stay zero
+        let offset = self.header.symbols_offset as usize + index * self.record_size;
         let mut symbol_bytes = [0u8; PackedSymbol::SIZE];
-        symbol_bytes.copy_from_slice(&self.mmap[offset..offset + PackedSymbol::SIZE]);
+        symbol_bytes[..self.record_size]
+            .copy_from_slice(&self.mmap[offset..offset + self.record_size]);
 
         Some(PackedSymbol::from_bytes(symbol_bytes))
     }
@@ -396,6 +492,33 @@ impl BinarySymbolReader {
         })
     }
 
+    /// Name index for pattern searches, built on first use
+    pub fn name_index(&self) -> &SymbolNameIndex {
+        self.name_index.get_or_init(|| SymbolNameIndex::build(self))
+    }
+
+    /// Indices of symbols whose name matches a pattern, in database order
+    ///
+    /// Names and pattern are compared by [`identifier_search_key`]. Uses the name index
+    /// if it was built; otherwise scans the symbol table once, which is cheaper than
+    /// building the index for a single query.
+    pub fn matching_symbols(&self, pattern: &str) -> Vec<usize> {
+        if let Some(index) = self.name_index.get() {
+            return index.matching_symbols(pattern);
+        }
+        let transliterate = identifier_transliteration_enabled();
+        let pattern_key = identifier_search_key(pattern, transliterate);
+        (0..self.symbol_count())
+            .filter(|&i| {
+                self.get_symbol(i)
+                    .and_then(|symbol| self.get_symbol_name(&symbol).ok())
+                    .is_some_and(|name| {
+                        name_matches(&identifier_search_key(&name, transliterate), &pattern_key)
+                    })
+            })
+            .collect()
+    }
+
     /// Read all symbols for a given file path efficiently using the cached index
     pub fn read_symbols_for_file(&self, file_path: &str) -> Vec<PackedSymbol> {
         let idx = self.get_or_build_file_index();
@@ -413,6 +536,300 @@ impl BinarySymbolReader {
     }
 }
 
+/// A function or method's name, file and 1-based line range
+#[derive(Debug, Clone)]
+struct FunctionRange {
+    name: String,
+    file_path: String,
+    start_line: usize,
+    end_line: usize,
+}
+
+/// Line ranges of functions and methods, for attributing source lines to the function
+/// containing them
+#[derive(Debug, Clone, Default)]
+pub struct FunctionRanges {
+    by_file_name: HashMap<String, Vec<FunctionRange>>,
+}
+
+impl FunctionRanges {
+    /// Functions and methods of a symbol database; empty when the database doesn't exist
+    pub fn load(symbol_db_path: &Path) -> Result<Self> {
+        use crate::parsing::SymbolType;
+
+        if !symbol_db_path.exists() {
+            return Ok(Self::default());
+        }
+        let reader = BinarySymbolReader::open(symbol_db_path)?;
+        let ranges = reader.iter_symbols().filter_map(|symbol| {
+            if !matches!(
+                SymbolType::try_from(symbol.kind),
+                Ok(SymbolType::Function | SymbolType::Method)
+            ) {
+                return None;
+            }
+            Some((
+                reader.get_symbol_name(&symbol).ok()?,
+                reader.get_symbol_file_path(&symbol).ok()?,
+                symbol.start_line as usize,
+                symbol.end_line as usize,
+            ))
+        });
+        Ok(Self::from_ranges(ranges))
+    }
+
+    /// Ranges from `(name, file path, start line, end line)`
+    pub fn from_ranges(ranges: impl IntoIterator<Item = (String, String, usize, usize)>) -> Self {
+        let mut by_file_name: HashMap<String, Vec<FunctionRange>> = HashMap::new();
+        for (name, file_path, start_line, end_line) in ranges {
+            by_file_name
+                .entry(file_name(&file_path).to_string())
+                .or_default()
+                .push(FunctionRange {
+                    name,
+                    file_path,
+                    start_line,
+                    end_line,
+                });
+        }
+        Self { by_file_name }
+    }
+
+    /// Innermost function or method of `file_path` containing the 1-based `line`
+    pub fn enclosing(&self, file_path: &str, line: usize) -> Option<&str> {
+        self.by_file_name
+            .get(file_name(file_path))?
+            .iter()
+            .filter(|f| f.start_line <= line && line <= f.end_line)
+            .filter(|f| crate::path_utils::paths_equivalent(&f.file_path, file_path))
+            .min_by_key(|f| f.end_line - f.start_line)
+            .map(|f| f.name.as_str())
+    }
+
+    /// Functions and methods of `file_path` overlapping the 1-based inclusive line range, as
+    /// `(name, start line, end line)`
+    pub fn overlapping(
+        &self,
+        file_path: &str,
+        start_line: usize,
+        end_line: usize,
+    ) -> Vec<(&str, usize, usize)> {
+        self.by_file_name
+            .get(file_name(file_path))
+            .into_iter()
+            .flatten()
+            .filter(|f| f.start_line <= end_line && start_line <= f.end_line)
+            .filter(|f| crate::path_utils::paths_equivalent(&f.file_path, file_path))
+            .map(|f| (f.name.as_str(), f.start_line, f.end_line))
+            .collect()
+    }
+}
+
+fn file_name(path: &str) -> &str {
+    path.rsplit(['/', '\\']).next().unwrap_or(path)
+}
+
+/// Whether the search key of a symbol name matches the search key of a pattern: `*`
+/// wildcards when the pattern has any, otherwise a substring match
+pub fn name_matches(name_key: &str, pattern_key: &str) -> bool {
+    if pattern_key.contains('*') {
+        crate::services::search_service::matches_wildcard_pattern(name_key, pattern_key)
+    } else {
+        name_key.contains(pattern_key)
+    }
+}
+
+/// Distinct name keys starting with one character, sorted, with their symbols
+#[derive(Debug)]
+struct NameShard {
+    first: char,
+    names: Vec<Box<str>>,
+    /// Symbol indices of each name, parallel to `names`
+    symbols: Vec<Vec<u32>>,
+}
+
+impl NameShard {
+    fn matching<'a>(
+        &'a self,
+        names: std::ops::Range<usize>,
+        pattern_key: &'a str,
+    ) -> impl Iterator<Item = usize> + 'a {
+        self.names[names.clone()]
+            .iter()
+            .zip(&self.symbols[names])
+            .filter(move |(name, _)| name_matches(name, pattern_key))
+            .flat_map(|(_, symbols)| symbols.iter().map(|&i| i as usize))
+    }
+
+    /// Range of names starting with `prefix`
+    fn prefix_range(&self, prefix: &str) -> std::ops::Range<usize> {
+        let start = self.names.partition_point(|name| &**name < prefix);
+        let len = self.names[start..].partition_point(|name| name.starts_with(prefix));
+        start..start + len
+    }
+}
+
+/// Symbol names sharded by first character for pattern searches
+///
+/// Each shard holds the distinct name keys sharing a first character in sorted order, so a pattern with a literal prefix only binary-searches one shard. Patterns
+/// that can match anywhere in a name scan the shards in parallel.
+#[derive(Debug)]
+pub struct SymbolNameIndex {
+    /// Shards ordered by first character
+    shards: Vec<NameShard>,
+    name_count: usize,
+    /// Keys are ASCII transliterations (see [`identifier_search_key`])
+    transliterate: bool,
+}
+
+impl SymbolNameIndex {
+    /// Index the names of every symbol of `reader`, transliterated when
+    /// [`identifier_transliteration_enabled`]
+    pub fn build(reader: &BinarySymbolReader) -> Self {
+        let transliterate = identifier_transliteration_enabled();
+        let mut entries: Vec<(String, u32)> = (0..reader.symbol_count())
+            .into_par_iter()
+            .filter_map(|i| {
+                let symbol = reader.get_symbol(i)?;
+                let name = reader.get_symbol_name(&symbol).ok()?;
+                Some((identifier_search_key(&name, transliterate), i as u32))
+            })
+            .collect();
+        entries.par_sort_unstable();
+
+        let mut shards: Vec<NameShard> = Vec::new();
+        let mut name_count = 0;
+        for (name, index) in entries {
+            let first = name.chars().next().unwrap_or_default();
+            if shards.last().is_none_or(|shard| shard.first != first) {
+                shards.push(NameShard {
+                    first,
+                    names: Vec::new(),
+                    symbols: Vec::new(),
+                });
+            }
+            let shard = shards.last_mut().expect("shard was just pushed");
+            if shard.names.last().is_some_and(|last| **last == *name) {
+                shard
+                    .symbols
+                    .last_mut()
+                    .expect("names and symbols are parallel")
+                    .push(index);
+            } else {
+                shard.names.push(name.into_boxed_str());
+                shard.symbols.push(vec![index]);
+                name_count += 1;
+            }
+        }
+
+        Self {
+            shards,
+            name_count,
+            transliterate,
+        }
+    }
+
+    /// Number of distinct name keys
+    pub fn name_count(&self) -> usize {
+        self.name_count
+    }
+
+    pub fn shard_count(&self) -> usize {
+        self.shards.len()
+    }
+
+    /// Indices of symbols whose name matches a pattern (see [`name_matches`]), in
+    /// database order
+    pub fn matching_symbols(&self, pattern: &str) -> Vec<usize> {
+        let pattern_key = identifier_search_key(pattern, self.transliterate);
+        let pattern_key = pattern_key.as_str();
+        // Only wildcard patterns are anchored at the start of the name
+        let prefix = match pattern_key.find('*') {
+            Some(star) => &pattern_key[..star],
+            None => "",
+        };
+
+        let mut matches: Vec<usize> = match prefix.chars().next() {
+            Some(first) => self
+                .shards
+                .binary_search_by(|shard| shard.first.cmp(&first))
+                .map(|i| {
+                    let shard = &self.shards[i];
+                    shard
+                        .matching(shard.prefix_range(prefix), pattern_key)
+                        .collect()
+                })
+                .unwrap_or_default(),
+            None => self
+                .shards
+                .par_iter()
+                .flat_map_iter(|shard| shard.matching(0..shard.names.len(), pattern_key))
+                .collect(),
+        };
+        matches.par_sort_unstable();
+        matches
+    }
+}
+
+/// Identity of a symbol database file, changing whenever it is rewritten
+fn file_generation(path: &Path) -> Option<(u64, std::time::SystemTime)> {
+    let metadata = std::fs::metadata(path).ok()?;
+    Some((metadata.len(), metadata.modified().ok()?))
+}
+
+/// A symbol database reader shared across queries, with its name index
+///
+/// The database is reopened, and its name index rebuilt, when re-indexing rewrites the
+/// file. Cloning shares the reader.
+#[derive(Clone)]
+pub struct SharedSymbolReader {
+    path: PathBuf,
+    current: Arc<Mutex<Option<SharedReaderState>>>,
+}
+
+type SharedReaderState = (
+    Option<(u64, std::time::SystemTime)>,
+    Arc<BinarySymbolReader>,
+);
+
+impl SharedSymbolReader {
+    /// Share the symbol database at `path`, opened on first use
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            current: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The reader of the current database file, with its name index built
+    pub fn get(&self) -> Result<Arc<BinarySymbolReader>> {
+        let generation = file_generation(&self.path);
+        let mut current = self
+            .current
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Some((opened, reader)) = current.as_ref() {
+            if *opened == generation {
+                return Ok(reader.clone());
+            }
+        }
+
+        let reader = Arc::new(BinarySymbolReader::open(&self.path)?);
+        let index = reader.name_index();
+        info!(
+            "Indexed {} symbol names in {} shards",
+            index.name_count(),
+            index.shard_count()
+        );
+        *current = Some((generation, reader.clone()));
+        Ok(reader)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -491,6 +908,61 @@ mod tests {
         assert!(err_msg.contains("Unsupported symbol database version"));
     }
 
+    #[test]
+    fn test_symbol_range_roundtrip() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("ranges.symdb");
+
+        let mut writer = BinarySymbolWriter::new();
+        let range = SymbolRange {
+            start_column: 4,
+            end_column: 5,
+            start_byte: 120,
+            end_byte: 188,
+        };
+        writer.add_symbol_with_range(Uuid::new_v4(), "ranged", 1, "src/lib.rs", 3, 6, range, None);
+        writer.write_to_file(&db_path).unwrap();
+
+        let reader = BinarySymbolReader::open(&db_path).unwrap();
+        let symbol = reader.get_symbol(0).unwrap();
+        assert_eq!(symbol.range(), range);
+        assert!(symbol.has_byte_range());
+    }
+
+    #[test]
+    fn test_reads_version_1_database() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("v1.symdb");
+
+        let id = Uuid::new_v4();
+        let mut writer = BinarySymbolWriter::new();
+        writer.add_symbol(id, "legacy", 1, "src/old.rs", 7, 9, None);
+
+        // Rebuild the file using the shorter version 1 record layout
+        let symbols_offset = SymbolDatabaseHeader::SIZE as u64;
+        let header = SymbolDatabaseHeader {
+            magic: *KOTA_MAGIC,
+            version: 1,
+            symbol_count: 1,
+            string_table_offset: symbols_offset + V1_SYMBOL_SIZE as u64,
+            string_table_size: writer.string_table.len() as u64,
+            symbols_offset,
+            _reserved: [0; 32],
+        };
+        let mut bytes = header.to_bytes().to_vec();
+        bytes.extend_from_slice(&writer.symbols[0].to_bytes()[..V1_SYMBOL_SIZE]);
+        bytes.extend_from_slice(&writer.string_table);
+        std::fs::write(&db_path, bytes).unwrap();
+
+        let reader = BinarySymbolReader::open(&db_path).unwrap();
+        let symbol = reader.find_symbol(id).unwrap();
+        assert_eq!(reader.get_symbol_name(&symbol).unwrap(), "legacy");
+        assert_eq!(symbol.start_line, 7);
+        assert_eq!(symbol.end_line, 9);
+        assert_eq!(symbol.range(), SymbolRange::default());
+        assert!(!symbol.has_byte_range());
+    }
+
     #[test]
     fn test_empty_database() {
         let temp_dir = TempDir::new().unwrap();
@@ -503,4 +975,115 @@ mod tests {
         assert_eq!(reader.symbol_count(), 0);
         assert!(reader.get_symbol(0).is_none());
     }
+
+    #[test]
+    fn test_name_index_matches_linear_scan() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("names.symdb");
+
+        let mut writer = BinarySymbolWriter::new();
+        let names = [
+            "handle_request",
+            "HandleResponse",
+            "handle_request",
+            "parse_config",
+            "ConfigParser",
+            "_private",
+            "zeta",
+        ];
+        for (line, name) in names.iter().enumerate() {
+            let line = line as u32 + 1;
+            writer.add_symbol(Uuid::new_v4(), name, 1, "src/lib.rs", line, line, None);
+        }
+        writer.write_to_file(&db_path).unwrap();
+
+        let reader = BinarySymbolReader::open(&db_path).unwrap();
+        let patterns = [
+            "handle*",
+            "handle_*st",
+            "*config",
+            "*pars*",
+            "config",
+            "_",
+            "",
+            "*",
+            "q*",
+            "z",
+        ];
+        let scanned: Vec<_> = patterns
+            .iter()
+            .map(|p| reader.matching_symbols(p))
+            .collect();
+
+        let index = reader.name_index();
+        assert_eq!(index.name_count(), 6);
+        assert_eq!(index.shard_count(), 5);
+        for (pattern, expected) in patterns.iter().zip(&scanned) {
+            assert_eq!(
+                &index.matching_symbols(pattern),
+                expected,
+                "pattern {pattern:?}"
+            );
+            assert_eq!(&reader.matching_symbols(pattern), expected);
+        }
+        assert_eq!(scanned[0], vec![0, 1, 2]);
+        assert_eq!(scanned[2], vec![3]);
+        assert_eq!(scanned[4], vec![3, 4]);
+        assert!(scanned[8].is_empty());
+    }
+
+    #[test]
+    fn test_name_index_ignores_unicode_normalization_form() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("names.symdb");
+
+        let mut writer = BinarySymbolWriter::new();
+        // Composed and decomposed spellings of "é"
+        for (line, name) in ["caf\u{e9}", "Cafe\u{301}Total", "cafeteria"]
+            .iter()
+            .enumerate()
+        {
+            let line = line as u32 + 1;
+            writer.add_symbol(Uuid::new_v4(), name, 1, "src/menu.py", line, line, None);
+        }
+        writer.write_to_file(&db_path).unwrap();
+
+        let reader = BinarySymbolReader::open(&db_path).unwrap();
+        for pattern in ["CAFE\u{301}*", "caf\u{e9}*", "f\u{e9}"] {
+            assert_eq!(reader.matching_symbols(pattern), vec![0, 1], "{pattern:?}");
+            assert_eq!(
+                reader.name_index().matching_symbols(pattern),
+                vec![0, 1],
+                "{pattern:?}"
+            );
+        }
+        assert_eq!(reader.name_index().name_count(), 3);
+        // Names keep the spelling of the source
+        let stored = reader.get_symbol(1).unwrap();
+        assert_eq!(reader.get_symbol_name(&stored).unwrap(), "Cafe\u{301}Total");
+    }
+
+    #[test]
+    fn test_shared_reader_reopens_rewritten_database() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("symbols.kota");
+
+        let mut writer = BinarySymbolWriter::new();
+        writer.add_symbol(Uuid::new_v4(), "alpha", 1, "src/a.rs", 1, 2, None);
+        writer.write_to_file(&db_path).unwrap();
+
+        let shared = SharedSymbolReader::new(&db_path);
+        let first = shared.get().unwrap();
+        assert!(Arc::ptr_eq(&first, &shared.clone().get().unwrap()));
+        assert_eq!(first.matching_symbols("alp*"), vec![0]);
+
+        let mut writer = BinarySymbolWriter::new();
+        writer.add_symbol(Uuid::new_v4(), "beta", 1, "src/b.rs", 1, 2, None);
+        writer.add_symbol(Uuid::new_v4(), "alphabet", 1, "src/b.rs", 3, 4, None);
+        writer.write_to_file(&db_path).unwrap();
+
+        let second = shared.get().unwrap();
+        assert!(!Arc::ptr_eq(&first, &second));
+        assert_eq!(second.matching_symbols("alp*"), vec![1]);
+    }
 }
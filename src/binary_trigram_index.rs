@@ -1,5 +1,11 @@
 // Binary Trigram Index - High-Performance Full-Text Search Engine
 // Uses bincode for efficient serialization and memory-mapped files for zero-copy access
+//
+// Postings live in a memory-mapped segment plus an in-memory delta of changes since the
+// last compaction. Deleting or re-inserting a document removes its delta postings and
+// tombstones its segment postings, so updates stay searchable without a rebuild. Flushing
+// persists the delta on its own until enough changes pile up, then compacts segment and
+// delta into a new segment.
 
 use anyhow::{bail, Result};
 use async_trait::async_trait;
@@ -8,15 +14,26 @@ use memmap2::{Mmap, MmapOptions};
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use tokio::fs;
 use tokio::sync::RwLock;
+use uuid::Uuid;
 
-use crate::contracts::{Index, Query};
+use crate::contracts::{ContentTokenizer, Index, Query, TokenizedDocument};
+use crate::identifier_tokenization;
 use crate::types::{ValidatedDocumentId, ValidatedPath};
 use crate::validation;
 
 /// Binary format version for compatibility checking
-const BINARY_FORMAT_VERSION: u32 = 2;
+pub(crate) const BINARY_FORMAT_VERSION: u32 = 2;
+
+/// Header flag recording that identifier subwords were indexed
+const FLAG_IDENTIFIER_SUBWORDS: u32 = 1;
+
+/// Changed documents the delta holds before flushing compacts it into the segment
+const COMPACTION_MIN_CHANGES: usize = 500;
+/// Compaction also waits for changes to reach this fraction (1/n) of indexed documents
+const COMPACTION_DOCUMENT_DIVISOR: usize = 5;
 
 /// High-performance binary trigram index
 pub struct BinaryTrigramIndex {
@@ -24,12 +41,20 @@ pub struct BinaryTrigramIndex {
     index_path: PathBuf,
     /// Memory-mapped trigram index for zero-copy access
     trigram_mmap: RwLock<Option<TrigramMmap>>,
-    /// In-memory cache for hot trigrams (LRU-style)
+    /// Postings added since the last compaction, searched alongside the segment
     hot_cache: RwLock<HashMap<String, HashSet<ValidatedDocumentId>>>,
+    /// Documents whose segment postings are stale because they were deleted or re-inserted
+    tombstones: RwLock<HashSet<ValidatedDocumentId>>,
+    /// Documents inserted or deleted since the last compaction
+    pending_changes: AtomicUsize,
     /// Document metadata for ranking (compact binary format)
     document_meta: RwLock<HashMap<ValidatedDocumentId, CompactDocMeta>>,
     /// Index statistics for optimization
     stats: RwLock<IndexStats>,
+    /// Index identifier subwords (camelCase/snake_case) alongside the raw text
+    ///
+    /// Fixed when the index is created and persisted in the header flags.
+    identifier_tokenization: bool,
 }
 
 /// Memory-mapped trigram index structure
@@ -40,6 +65,29 @@ struct TrigramMmap {
     offset_table: HashMap<String, (usize, usize)>, // trigram -> (offset, length)
 }
 
+impl TrigramMmap {
+    /// Documents listed for `trigram` in the segment, tombstoned ones included
+    fn postings<'a>(&'a self, trigram: &str) -> impl Iterator<Item = ValidatedDocumentId> + 'a {
+        self.offset_table
+            .get(trigram)
+            .into_iter()
+            .flat_map(move |&(offset, size)| {
+                let (uuids, _) = self.mmap[offset..offset + size].as_chunks::<16>();
+                uuids.iter().filter_map(|bytes| {
+                    ValidatedDocumentId::from_uuid(Uuid::from_bytes(*bytes)).ok()
+                })
+            })
+    }
+}
+
+/// Changes since the last compaction, persisted next to the segment
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct DeltaSegment {
+    postings: HashMap<String, Vec<Uuid>>,
+    tombstones: Vec<Uuid>,
+    pending_changes: usize,
+}
+
 /// Compact document metadata (optimized for size)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct CompactDocMeta {
@@ -79,10 +127,12 @@ impl BinaryTrigramIndex {
         fs::create_dir_all(&index_path).await?;
         fs::create_dir_all(index_path.join("binary")).await?;
 
-        let index = Self {
+        let mut index = Self {
             index_path,
             trigram_mmap: RwLock::new(None),
             hot_cache: RwLock::new(HashMap::with_capacity(1000)), // Pre-size hot cache
+            tombstones: RwLock::new(HashSet::new()),
+            pending_changes: AtomicUsize::new(0),
             document_meta: RwLock::new(HashMap::new()),
             stats: RwLock::new(IndexStats {
                 version: BINARY_FORMAT_VERSION,
@@ -92,23 +142,32 @@ impl BinaryTrigramIndex {
                 index_size_bytes: 0,
                 last_compaction: chrono::Utc::now().timestamp(),
             }),
+            identifier_tokenization: identifier_tokenization::identifier_tokenization_enabled(),
         };
 
-        // Try to load existing index
-        if let Err(e) = index.load_binary_index().await {
-            tracing::warn!("Failed to load existing binary index: {e}, starting fresh");
+        // Try to load existing index, which keeps the tokenization it was built with
+        match index.load_binary_index().await {
+            Ok(Some(flags)) => {
+                index.identifier_tokenization = flags & FLAG_IDENTIFIER_SUBWORDS != 0;
+            }
+            Ok(None) => {}
+            Err(e) => {
+                tracing::warn!("Failed to load existing binary index: {e}, starting fresh");
+            }
         }
 
         Ok(index)
     }
 
     /// Load binary index from disk using memory mapping
-    async fn load_binary_index(&self) -> Result<()> {
+    ///
+    /// Returns the header flags of the loaded index, or `None` when none exists yet.
+    async fn load_binary_index(&self) -> Result<Option<u32>> {
         let index_path = self.index_path.join("binary").join("trigrams.bin");
         let meta_path = self.index_path.join("binary").join("metadata.bin");
 
         if !index_path.exists() || !meta_path.exists() {
-            return Ok(());
+            return Ok(None);
         }
 
         // Load and verify header
@@ -173,7 +232,27 @@ impl BinaryTrigramIndex {
             *self.stats.write().await = stats;
         }
 
-        Ok(())
+        // Restore changes flushed since the last compaction
+        let delta_path = self.index_path.join("binary").join("delta.bin");
+        if delta_path.exists() {
+            let delta: DeltaSegment = bincode::deserialize(&tokio::fs::read(&delta_path).await?)?;
+            let ids = |uuids: Vec<Uuid>| -> HashSet<ValidatedDocumentId> {
+                uuids
+                    .into_iter()
+                    .filter_map(|uuid| ValidatedDocumentId::from_uuid(uuid).ok())
+                    .collect()
+            };
+            *self.hot_cache.write().await = delta
+                .postings
+                .into_iter()
+                .map(|(trigram, uuids)| (trigram, ids(uuids)))
+                .collect();
+            *self.tombstones.write().await = ids(delta.tombstones);
+            self.pending_changes
+                .store(delta.pending_changes, Ordering::Relaxed);
+        }
+
+        Ok(Some(header.flags))
     }
 
     /// Build offset table for O(1) trigram lookups
@@ -228,11 +307,51 @@ impl BinaryTrigramIndex {
         Ok(offset_table)
     }
 
-    /// Save index in optimized binary format
+    /// Persist the index
+    ///
+    /// Writes only the delta while it is small relative to the index, and compacts the
+    /// delta into a new segment otherwise.
     async fn save_binary_index(&self) -> Result<()> {
+        if self.trigram_mmap.read().await.is_none() || self.should_compact().await {
+            self.compact().await?;
+        } else {
+            self.save_delta().await?;
+        }
+        self.save_metadata().await
+    }
+
+    /// Whether enough documents changed since the last compaction to compact
+    async fn should_compact(&self) -> bool {
+        let documents = self.stats.read().await.document_count;
+        self.pending_changes.load(Ordering::Relaxed)
+            >= COMPACTION_MIN_CHANGES.max(documents / COMPACTION_DOCUMENT_DIVISOR)
+    }
+
+    /// Merge the live segment postings with the delta into a new segment
+    async fn compact(&self) -> Result<()> {
         let index_path = self.index_path.join("binary").join("trigrams.bin");
-        let meta_path = self.index_path.join("binary").join("metadata.bin");
-        let stats_path = self.index_path.join("binary").join("stats.bin");
+
+        let mut postings: HashMap<String, HashSet<ValidatedDocumentId>> = HashMap::new();
+        {
+            let tombstones = self.tombstones.read().await;
+            if let Some(segment) = self.trigram_mmap.read().await.as_ref() {
+                for trigram in segment.offset_table.keys() {
+                    let live: HashSet<_> = segment
+                        .postings(trigram)
+                        .filter(|doc_id| !tombstones.contains(doc_id))
+                        .collect();
+                    if !live.is_empty() {
+                        postings.insert(trigram.clone(), live);
+                    }
+                }
+            }
+        }
+        for (trigram, doc_ids) in self.hot_cache.read().await.iter() {
+            postings
+                .entry(trigram.clone())
+                .or_default()
+                .extend(doc_ids.iter().copied());
+        }
 
         // Build the binary index
         let mut index_data = Vec::with_capacity(1024 * 1024); // Pre-allocate 1MB
@@ -241,7 +360,11 @@ impl BinaryTrigramIndex {
         let mut header = IndexHeader {
             magic: *b"KTRI",
             version: BINARY_FORMAT_VERSION,
-            flags: 0,
+            flags: if self.identifier_tokenization {
+                FLAG_IDENTIFIER_SUBWORDS
+            } else {
+                0
+            },
             created: chrono::Utc::now().timestamp(),
             checksum: 0, // Will be calculated after building index
         };
@@ -250,14 +373,11 @@ impl BinaryTrigramIndex {
         let header_size = std::mem::size_of::<IndexHeader>();
         index_data.resize(header_size, 0);
 
-        // Get trigram data from hot cache or existing mmap
-        let hot_cache = self.hot_cache.read().await;
-
         // Write number of trigrams
-        index_data.extend_from_slice(&(hot_cache.len() as u32).to_le_bytes());
+        index_data.extend_from_slice(&(postings.len() as u32).to_le_bytes());
 
         // Write each trigram and its document list
-        for (trigram, doc_ids) in hot_cache.iter() {
+        for (trigram, doc_ids) in postings.iter() {
             // Write trigram length and data
             let trigram_bytes = trigram.as_bytes();
             index_data.extend_from_slice(&(trigram_bytes.len() as u16).to_le_bytes());
@@ -281,28 +401,215 @@ impl BinaryTrigramIndex {
         let header_bytes = bincode::serialize(&header)?;
         index_data[..header_size].copy_from_slice(&header_bytes[..header_size]);
 
-        // Write index file
-        tokio::fs::write(&index_path, &index_data).await?;
+        // Replace the segment by renaming, so the current mapping stays valid until dropped
+        let tmp_path = index_path.with_extension("bin.tmp");
+        tokio::fs::write(&tmp_path, &index_data).await?;
+        tokio::fs::rename(&tmp_path, &index_path).await?;
+        let file = std::fs::File::open(&index_path)?;
+        let mmap = unsafe { MmapOptions::new().map(&file)? };
+        let offset_table = Self::build_offset_table(&mmap)?;
+        *self.trigram_mmap.write().await = Some(TrigramMmap { mmap, offset_table });
+
+        // The new segment holds every live posting
+        self.hot_cache.write().await.clear();
+        self.tombstones.write().await.clear();
+        self.pending_changes.store(0, Ordering::Relaxed);
+        let delta_path = self.index_path.join("binary").join("delta.bin");
+        if delta_path.exists() {
+            tokio::fs::remove_file(&delta_path).await?;
+        }
+
+        let mut stats = self.stats.write().await;
+        stats.index_size_bytes = index_data.len() as u64;
+        stats.unique_trigrams = postings.len();
+        stats.last_compaction = chrono::Utc::now().timestamp();
+        Ok(())
+    }
+
+    /// Write the postings and tombstones added since the last compaction
+    async fn save_delta(&self) -> Result<()> {
+        let delta = DeltaSegment {
+            postings: self
+                .hot_cache
+                .read()
+                .await
+                .iter()
+                .map(|(trigram, doc_ids)| {
+                    let uuids = doc_ids.iter().map(|doc_id| doc_id.as_uuid()).collect();
+                    (trigram.clone(), uuids)
+                })
+                .collect(),
+            tombstones: self
+                .tombstones
+                .read()
+                .await
+                .iter()
+                .map(|doc_id| doc_id.as_uuid())
+                .collect(),
+            pending_changes: self.pending_changes.load(Ordering::Relaxed),
+        };
+        let delta_path = self.index_path.join("binary").join("delta.bin");
+        tokio::fs::write(&delta_path, bincode::serialize(&delta)?).await?;
+        Ok(())
+    }
+
+    /// Save document metadata and statistics
+    async fn save_metadata(&self) -> Result<()> {
+        let meta_path = self.index_path.join("binary").join("metadata.bin");
+        let stats_path = self.index_path.join("binary").join("stats.bin");
 
-        // Save document metadata
         let doc_meta = self.document_meta.read().await;
         let serializable_meta: HashMap<String, CompactDocMeta> = doc_meta
             .iter()
             .map(|(id, meta)| (id.as_uuid().to_string(), meta.clone()))
             .collect();
+        drop(doc_meta);
         let meta_data = bincode::serialize(&serializable_meta)?;
         tokio::fs::write(&meta_path, &meta_data).await?;
 
-        // Save statistics and update index size (avoid deadlock by doing both in single lock)
+        // Serialize under the lock, write after releasing it
+        let stats_data = bincode::serialize(&*self.stats.read().await)?;
+        tokio::fs::write(&stats_path, &stats_data).await?;
+
+        Ok(())
+    }
+
+    /// Drop the postings of a document
+    ///
+    /// Delta postings are removed right away; segment postings are tombstoned until the
+    /// next compaction. Returns whether the document was indexed.
+    async fn remove_document(&self, doc_id: &ValidatedDocumentId) -> bool {
+        // Every indexed document has metadata, so unknown documents need no scan
+        if self.document_meta.write().await.remove(doc_id).is_none() {
+            return false;
+        }
+        self.hot_cache.write().await.retain(|_, docs| {
+            docs.remove(doc_id);
+            !docs.is_empty()
+        });
+        if self.trigram_mmap.read().await.is_some() {
+            self.tombstones.write().await.insert(*doc_id);
+        }
+        self.pending_changes.fetch_add(1, Ordering::Relaxed);
+        true
+    }
+
+    /// Enable or disable identifier-aware tokenization
+    ///
+    /// New indices default to [`identifier_tokenization::identifier_tokenization_enabled`];
+    /// an index that already holds documents keeps the mode it was built with.
+    pub fn set_identifier_tokenization(&mut self, enabled: bool) {
+        if !self.document_meta.get_mut().is_empty() {
+            if self.identifier_tokenization != enabled {
+                tracing::warn!(
+                    "Ignoring identifier tokenization change for a non-empty binary trigram index; rebuild the index to switch modes"
+                );
+            }
+            return;
+        }
+        self.identifier_tokenization = enabled;
+    }
+
+    /// Documents passing the minimum-match threshold for one search term, with match counts
+    async fn term_matches(&self, term: &str) -> HashMap<ValidatedDocumentId, u16> {
+        let all_query_trigrams = if self.identifier_tokenization {
+            Self::extract_trigrams_optimized(&identifier_tokenization::normalize_query_term(term))
+        } else {
+            Self::extract_trigrams_optimized(term)
+        };
+        if all_query_trigrams.is_empty() {
+            return HashMap::new();
+        }
+
+        // Use unique trigram set to avoid duplicate inflation when counting matches
+        let query_trigram_set: std::collections::HashSet<String> =
+            all_query_trigrams.into_iter().collect();
+        let query_trigram_count = query_trigram_set.len();
+
+        // Compute minimum-match threshold (tiered policy)
+        // Align threshold policy with established trigram_index behavior by default.
+        // Optionally, an aggressive policy can be enabled via feature flag.
+        let base_min_match_threshold: usize = if query_trigram_count <= 3 {
+            // 100%
+            query_trigram_count
+        } else if query_trigram_count <= 6 {
+            // ~80%
+            std::cmp::max(
+                (query_trigram_count * 8).div_ceil(10), // ceil(0.8 * N)
+                query_trigram_count.saturating_sub(1),
+            )
+        } else {
+            // ~60%
+            let sixty = (query_trigram_count * 6).div_ceil(10); // ceil(0.6 * N)
+            std::cmp::max(3, sixty)
+        };
+
+        // Heuristic: long/digit token → stricter threshold
+        let mut min_match_threshold = base_min_match_threshold;
+        let has_digits = term.chars().any(|c| c.is_ascii_digit());
+        let long_token = term.chars().count() >= 12;
+        if has_digits || long_token {
+            // For numeric-heavy or very long single tokens, require ~90%
+            let ninety = (query_trigram_count * 9).div_ceil(10); // ceil(0.9*N)
+            min_match_threshold = std::cmp::max(min_match_threshold, ninety);
+        }
+
+        // Count each document once per trigram across the delta and the live segment
+        let mut doc_scores: HashMap<ValidatedDocumentId, u16> = HashMap::new();
         {
-            let mut stats = self.stats.write().await;
-            stats.index_size_bytes = index_data.len() as u64;
-            let stats_data = bincode::serialize(&*stats)?;
-            drop(stats); // Release lock before async I/O
-            tokio::fs::write(&stats_path, &stats_data).await?;
+            let cache = self.hot_cache.read().await;
+            let tombstones = self.tombstones.read().await;
+            let segment = self.trigram_mmap.read().await;
+            for trigram in &query_trigram_set {
+                let delta = cache.get(trigram);
+                if let Some(doc_ids) = delta {
+                    for doc_id in doc_ids {
+                        let entry = doc_scores.entry(*doc_id).or_insert(0);
+                        *entry = entry.saturating_add(1);
+                    }
+                }
+                if let Some(segment) = segment.as_ref() {
+                    for doc_id in segment.postings(trigram) {
+                        if tombstones.contains(&doc_id)
+                            || delta.is_some_and(|doc_ids| doc_ids.contains(&doc_id))
+                        {
+                            continue;
+                        }
+                        let entry = doc_scores.entry(doc_id).or_insert(0);
+                        *entry = entry.saturating_add(1);
+                    }
+                }
+            }
         }
 
-        Ok(())
+        // Apply minimum-match threshold to reduce false positives
+        doc_scores
+            .into_iter()
+            .filter(|(_id, count)| *count as usize >= min_match_threshold)
+            .collect()
+    }
+
+    /// Trigrams indexed for a document: its path and content, plus identifier subwords
+    /// when `identifier_subwords` is set
+    pub fn content_trigrams(
+        path: &ValidatedPath,
+        content: &[u8],
+        identifier_subwords: bool,
+    ) -> Vec<String> {
+        // Use path as pseudo-title since we don't have document title here
+        let searchable_text = format!("{} {}", path.as_str(), String::from_utf8_lossy(content));
+        let mut trigrams = Self::extract_trigrams_optimized(&searchable_text);
+        if identifier_subwords {
+            let subwords = identifier_tokenization::identifier_subwords(&searchable_text);
+            trigrams.extend(Self::extract_trigrams_optimized(&subwords));
+        }
+        trigrams
+    }
+
+    /// Words of the indexed text of a document
+    fn word_count(path: &ValidatedPath, content: &[u8]) -> usize {
+        path.as_str().split_whitespace().count()
+            + String::from_utf8_lossy(content).split_whitespace().count()
     }
 
     /// Extract trigrams with optimized algorithm
@@ -368,17 +675,38 @@ impl Index for BinaryTrigramIndex {
         path: ValidatedPath,
         content: &[u8],
     ) -> Result<()> {
-        // Extract text for indexing
-        // Use path as pseudo-title since we don't have document title here
-        let searchable_text = format!("{} {}", path.as_str(), String::from_utf8_lossy(content));
+        let tokenizer = ContentTokenizer::BinaryTrigram {
+            identifier_subwords: self.identifier_tokenization,
+        };
+        self.insert_tokenized(tokenizer.tokenize(doc_id, path, content.to_vec()))
+            .await
+    }
+
+    async fn content_tokenizer(&self) -> Option<ContentTokenizer> {
+        Some(ContentTokenizer::BinaryTrigram {
+            identifier_subwords: self.identifier_tokenization,
+        })
+    }
+
+    async fn insert_tokenized(&mut self, document: TokenizedDocument) -> Result<()> {
+        let TokenizedDocument {
+            id: doc_id,
+            path,
+            content,
+            trigrams,
+        } = document;
 
-        // Extract trigrams with optimized algorithm
-        let trigrams = Self::extract_trigrams_optimized(&searchable_text);
+        // Re-inserting a document replaces its previous postings
+        let replaced = self.remove_document(&doc_id).await;
         if trigrams.is_empty() {
+            if replaced {
+                let mut stats = self.stats.write().await;
+                stats.document_count = stats.document_count.saturating_sub(1);
+            }
             return Ok(());
         }
 
-        // Update hot cache
+        // Update the delta
         let unique_trigrams: HashSet<String> = trigrams.iter().cloned().collect();
         {
             let mut cache = self.hot_cache.write().await;
@@ -394,25 +722,24 @@ impl Index for BinaryTrigramIndex {
         let meta = CompactDocMeta {
             title_hash: xxhash_rust::xxh3::xxh3_64(path.as_str().as_bytes()),
             trigram_freqs: Vec::new(), // TODO: Implement sparse frequency vector
-            packed_stats: ((searchable_text.split_whitespace().count() as u32) & 0xFFFF)
+            packed_stats: ((Self::word_count(&path, &content) as u32) & 0xFFFF)
                 | ((unique_trigrams.len() as u32 & 0xFFFF) << 16),
         };
 
         self.document_meta.write().await.insert(doc_id, meta);
 
-        // Update statistics (avoid deadlock by getting cache length first)
-        let cache_len = self.hot_cache.read().await.len();
         {
             let mut stats = self.stats.write().await;
-            stats.document_count += 1;
-            stats.unique_trigrams = cache_len;
+            if !replaced {
+                stats.document_count += 1;
+            }
             stats.total_trigrams += trigrams.len();
         }
+        self.pending_changes.fetch_add(1, Ordering::Relaxed);
 
-        // Save to disk very rarely during bulk indexing for maximum performance
-        // Only save every 500 documents during initial indexing to minimize I/O overhead
-        // Relies on external callers to call flush() to ensure data persistence
-        if self.stats.read().await.document_count % 500 == 0 {
+        // Compact during bulk indexing once enough changes piled up; otherwise relies on
+        // external callers to call flush() to ensure data persistence
+        if self.should_compact().await {
             self.save_binary_index().await?;
         }
 
@@ -437,26 +764,12 @@ impl Index for BinaryTrigramIndex {
     }
 
     async fn delete(&mut self, doc_id: &ValidatedDocumentId) -> Result<bool> {
-        // Remove from hot cache
-        let mut removed = false;
-        {
-            let mut cache = self.hot_cache.write().await;
-            cache.retain(|_, docs| {
-                if docs.remove(doc_id) {
-                    removed = true;
-                }
-                !docs.is_empty()
-            });
-        }
-
-        // Remove metadata
-        if self.document_meta.write().await.remove(doc_id).is_some() {
-            removed = true;
-        }
+        let removed = self.remove_document(doc_id).await;
 
         // Update statistics
         if removed {
-            self.stats.write().await.document_count -= 1;
+            let mut stats = self.stats.write().await;
+            stats.document_count = stats.document_count.saturating_sub(1);
         }
 
         Ok(removed)
@@ -469,94 +782,17 @@ impl Index for BinaryTrigramIndex {
             return Ok(meta.keys().copied().collect());
         }
 
-        // Extract query trigrams
-        let mut all_query_trigrams = Vec::new();
+        // Several search terms are alternatives (OR): a document matches when it passes
+        // the threshold for any of them and keeps its best match count
+        let mut results: HashMap<ValidatedDocumentId, u16> = HashMap::new();
         for term in &query.search_terms {
-            all_query_trigrams.extend(Self::extract_trigrams_optimized(term.as_str()));
-        }
-
-        if all_query_trigrams.is_empty() {
-            return Ok(Vec::new());
-        }
-
-        // Use unique trigram set to avoid duplicate inflation when counting matches
-        let query_trigram_set: std::collections::HashSet<String> =
-            all_query_trigrams.into_iter().collect();
-        let query_trigram_count = query_trigram_set.len();
-
-        // Compute minimum-match threshold (tiered policy)
-        // Align threshold policy with established trigram_index behavior by default.
-        // Optionally, an aggressive policy can be enabled via feature flag.
-        let base_min_match_threshold: usize = if query_trigram_count <= 3 {
-            // 100%
-            query_trigram_count
-        } else if query_trigram_count <= 6 {
-            // ~80%
-            std::cmp::max(
-                (query_trigram_count * 8).div_ceil(10), // ceil(0.8 * N)
-                query_trigram_count.saturating_sub(1),
-            )
-        } else {
-            // ~60%
-            let sixty = (query_trigram_count * 6).div_ceil(10); // ceil(0.6 * N)
-            std::cmp::max(3, sixty)
-        };
-
-        // Heuristic: single long/digit token → stricter threshold
-        let mut min_match_threshold = base_min_match_threshold;
-        if query.search_terms.len() == 1 {
-            let term = query.search_terms[0].as_str();
-            let has_digits = term.chars().any(|c| c.is_ascii_digit());
-            let long_token = term.chars().count() >= 12;
-            if has_digits || long_token {
-                // For numeric-heavy or very long single tokens, require ~90%
-                let ninety = (query_trigram_count * 9).div_ceil(10); // ceil(0.9*N)
-                min_match_threshold = std::cmp::max(min_match_threshold, ninety);
+            for (doc_id, count) in self.term_matches(term.as_str()).await {
+                let best = results.entry(doc_id).or_insert(0);
+                *best = (*best).max(count);
             }
         }
+        let mut results: Vec<_> = results.into_iter().collect();
 
-        // Use hot cache first, then fall back to mmap
-        let mut doc_scores: HashMap<ValidatedDocumentId, u16> = HashMap::new();
-
-        // Check hot cache first
-        {
-            let cache = self.hot_cache.read().await;
-            for trigram in &query_trigram_set {
-                if let Some(doc_ids) = cache.get(trigram) {
-                    for doc_id in doc_ids {
-                        let entry = doc_scores.entry(*doc_id).or_insert(0);
-                        *entry = entry.saturating_add(1);
-                    }
-                }
-            }
-        }
-
-        // If no results in hot cache, check mmap data
-        if doc_scores.is_empty() {
-            if let Some(trigram_mmap) = self.trigram_mmap.read().await.as_ref() {
-                for trigram in &query_trigram_set {
-                    if let Some((offset, size)) = trigram_mmap.offset_table.get(trigram) {
-                        // Read document IDs from mmap
-                        let mmap_data = &trigram_mmap.mmap[*offset..*offset + *size];
-                        for chunk in mmap_data.chunks_exact(16) {
-                            if let Ok(uuid_bytes) = <[u8; 16]>::try_from(chunk) {
-                                let uuid = uuid::Uuid::from_bytes(uuid_bytes);
-                                if let Ok(doc_id) = ValidatedDocumentId::from_uuid(uuid) {
-                                    let entry = doc_scores.entry(doc_id).or_insert(0);
-                                    *entry = entry.saturating_add(1);
-                                }
-                            }
-                        }
-                    }
-                }
-            }
-        }
-
-        // Apply minimum-match threshold to reduce false positives
-        let mut results: Vec<_> = doc_scores
-            .into_iter()
-            .filter(|(_id, count)| *count as usize >= min_match_threshold)
-            .collect();
         // Sort by match count descending, then by ID for stability
         results.sort_by(|a, b| {
             b.1.cmp(&a.1)
@@ -643,4 +879,96 @@ mod tests {
         let trigrams = BinaryTrigramIndex::extract_trigrams_optimized(unicode_text);
         assert_eq!(trigrams.len(), 2); // "测试中" and "试中文"
     }
+
+    #[tokio::test]
+    async fn test_identifier_tokenization_is_persisted_with_the_index() -> Result<()> {
+        let temp_dir = tempfile::TempDir::new()?;
+        let query = Query {
+            search_terms: vec![crate::types::ValidatedSearchQuery::new("get user", 1)?],
+            limit: crate::types::ValidatedLimit::new(10, 100_000)?,
+            ..Default::default()
+        };
+        let content = b"pub fn getUserById(id: u64) -> Option<User>";
+
+        let mut index = BinaryTrigramIndex::new(temp_dir.path().join("enabled")).await?;
+        index.set_identifier_tokenization(true);
+        let doc_id = ValidatedDocumentId::new();
+        index
+            .insert_with_content(doc_id, ValidatedPath::new("src/users.rs")?, content)
+            .await?;
+        assert_eq!(index.search(&query).await?, vec![doc_id]);
+        index.flush().await?;
+
+        // Reopening restores the mode from the header, not the setter or environment
+        let mut reopened = BinaryTrigramIndex::new(temp_dir.path().join("enabled")).await?;
+        reopened.set_identifier_tokenization(false);
+        assert!(reopened.identifier_tokenization);
+
+        let mut plain = BinaryTrigramIndex::new(temp_dir.path().join("disabled")).await?;
+        plain.set_identifier_tokenization(false);
+        plain
+            .insert_with_content(
+                ValidatedDocumentId::new(),
+                ValidatedPath::new("src/users.rs")?,
+                content,
+            )
+            .await?;
+        assert!(plain.search(&query).await?.is_empty());
+        plain.flush().await?;
+        let reopened_plain = BinaryTrigramIndex::new(temp_dir.path().join("disabled")).await?;
+        assert!(!reopened_plain.identifier_tokenization);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_updates_and_deletes_stay_searchable_without_rebuild() -> Result<()> {
+        let temp_dir = tempfile::TempDir::new()?;
+        let path = temp_dir.path().join("index");
+        let query = |term: &str| -> Result<Query> {
+            Ok(Query {
+                search_terms: vec![crate::types::ValidatedSearchQuery::new(term, 1)?],
+                limit: crate::types::ValidatedLimit::new(10, 100_000)?,
+                ..Default::default()
+            })
+        };
+        let (first, second) = (ValidatedDocumentId::new(), ValidatedDocumentId::new());
+        let file = ValidatedPath::new("src/lib.rs")?;
+
+        let mut index = BinaryTrigramIndex::new(path.clone()).await?;
+        index
+            .insert_with_content(first, file.clone(), b"alpha marker")
+            .await?;
+        index
+            .insert_with_content(second, ValidatedPath::new("src/io.rs")?, b"bravo marker")
+            .await?;
+        // The first flush compacts everything into the segment
+        index.flush().await?;
+
+        index
+            .update_with_content(first, file.clone(), b"charlie marker")
+            .await?;
+        assert!(index.delete(&second).await?);
+        assert!(index.search(&query("alpha")?).await?.is_empty());
+        assert!(index.search(&query("bravo")?).await?.is_empty());
+        assert_eq!(index.search(&query("charlie")?).await?, vec![first]);
+        assert_eq!(index.search(&query("marker")?).await?, vec![first]);
+
+        // Small changes are flushed as a delta and restored on reopen
+        index.flush().await?;
+        assert!(path.join("binary").join("delta.bin").exists());
+        let reopened = BinaryTrigramIndex::new(path.clone()).await?;
+        assert!(reopened.search(&query("alpha")?).await?.is_empty());
+        assert_eq!(reopened.search(&query("charlie")?).await?, vec![first]);
+        assert_eq!(reopened.tombstones.read().await.len(), 2);
+
+        // Compaction drops the tombstoned postings from the segment
+        reopened.compact().await?;
+        assert!(!path.join("binary").join("delta.bin").exists());
+        assert!(reopened.tombstones.read().await.is_empty());
+        assert_eq!(reopened.search(&query("marker")?).await?, vec![first]);
+        assert!(reopened.search(&query("bravo")?).await?.is_empty());
+
+        Ok(())
+    }
 }
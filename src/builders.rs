@@ -2,7 +2,8 @@
 // This module provides fluent builder APIs for constructing complex objects
 // with sensible defaults and compile-time validation.
 
-use crate::contracts::{Document, Query, StorageMetrics};
+use crate::contracts::{Document, DocumentFilters, Query, StorageMetrics};
+use crate::query_deadline::QueryDeadline;
 use crate::types::*;
 use anyhow::{bail, ensure, Result};
 use chrono::{DateTime, Utc};
@@ -140,6 +141,8 @@ pub struct QueryBuilder {
     tags: Vec<ValidatedTag>,
     date_range: Option<(ValidatedTimestamp, ValidatedTimestamp)>,
     limit: Option<ValidatedLimit>,
+    filters: DocumentFilters,
+    deadline: QueryDeadline,
 }
 
 impl QueryBuilder {
@@ -150,6 +153,8 @@ impl QueryBuilder {
             tags: Vec::new(),
             date_range: None,
             limit: None,
+            filters: DocumentFilters::default(),
+            deadline: QueryDeadline::none(),
         }
     }
 
@@ -227,6 +232,18 @@ impl QueryBuilder {
         Ok(self)
     }
 
+    /// Restrict results by extension, directory depth and size
+    pub fn with_filters(mut self, filters: DocumentFilters) -> Self {
+        self.filters = filters;
+        self
+    }
+
+    /// Stop index traversals at `deadline` with the matches found so far
+    pub fn with_deadline(mut self, deadline: QueryDeadline) -> Self {
+        self.deadline = deadline;
+        self
+    }
+
     /// Build the query
     pub fn build(self) -> Result<Query> {
         let tags = if self.tags.is_empty() {
@@ -274,6 +291,8 @@ impl QueryBuilder {
         if let Some(pattern) = path_pattern {
             query.path_pattern = Some(pattern);
         }
+        query.filters = self.filters;
+        query.deadline = self.deadline;
 
         Ok(query)
     }
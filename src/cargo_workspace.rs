@@ -0,0 +1,267 @@
+//! Cargo crate boundaries within an indexed repository
+//!
+//! Workspaces commonly hold several crates with the same module layout, so two
+//! `config::load` functions are only told apart by the crate they belong to. The crate
+//! map is read from the `Cargo.toml` manifests found while indexing and recorded as
+//! `crates.json` in the database directory. Rust symbols get qualified names of the
+//! form `<crate>::<module path>::<name>` from it, references are resolved within their
+//! own crate first, and symbol and relationship queries can be limited to one crate.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// A crate and the repository-relative directory holding its manifest
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CargoCrate {
+    /// Crate name as used in paths (`-` replaced by `_`)
+    pub name: String,
+    /// Directory of `Cargo.toml`; empty for a manifest at the repository root
+    pub root: String,
+}
+
+impl CargoCrate {
+    /// Whether the repository-relative `path` lies inside the crate
+    pub fn contains(&self, path: &str) -> bool {
+        let path = normalize(path);
+        self.root.is_empty()
+            || path
+                .strip_prefix(self.root.as_str())
+                .is_some_and(|rest| rest.starts_with('/'))
+    }
+
+    /// Rust module path of a file in the crate, e.g. `config::loader` for
+    /// `src/config/loader.rs`; empty for the crate root
+    pub fn module_path(&self, path: &str) -> String {
+        let path = normalize(path);
+        let relative = path
+            .strip_prefix(self.root.as_str())
+            .unwrap_or(path)
+            .trim_start_matches('/');
+        let relative = relative.strip_suffix(".rs").unwrap_or(relative);
+        let relative = relative.strip_prefix("src/").unwrap_or(relative);
+        let mut segments: Vec<&str> = relative.split('/').collect();
+        if matches!(segments.as_slice(), ["lib" | "main"]) || segments.last() == Some(&"mod") {
+            segments.pop();
+        }
+        segments.join("::")
+    }
+}
+
+/// Crates of a repository, as recorded in `crates.json`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CrateMap {
+    pub crates: Vec<CargoCrate>,
+}
+
+impl CrateMap {
+    pub fn path_for(db_path: &Path) -> PathBuf {
+        db_path.join("crates.json")
+    }
+
+    /// Load the recorded crates; `None` when the index has no crate map
+    pub fn load(db_path: &Path) -> Result<Option<Self>> {
+        let path = Self::path_for(db_path);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read crate map: {}", path.display()))?;
+        let map = serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse crate map: {}", path.display()))?;
+        Ok(Some(map))
+    }
+
+    /// Write the crate map atomically
+    pub fn save(&self, db_path: &Path) -> Result<()> {
+        let path = Self::path_for(db_path);
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_vec_pretty(self)?)
+            .with_context(|| format!("Failed to write crate map: {}", tmp.display()))?;
+        std::fs::rename(&tmp, &path)
+            .with_context(|| format!("Failed to replace crate map: {}", path.display()))?;
+        Ok(())
+    }
+
+    /// Crates declared by the `Cargo.toml` files among `(path, content)` pairs
+    ///
+    /// Workspace-only manifests and manifests that fail to parse are skipped. A `[lib]`
+    /// name takes precedence over the package name, as it does for `use` paths.
+    pub fn from_files<'a>(files: impl IntoIterator<Item = (&'a Path, &'a [u8])>) -> Self {
+        let mut crates = Vec::new();
+        for (path, content) in files {
+            if path.file_name().and_then(|name| name.to_str()) != Some("Cargo.toml") {
+                continue;
+            }
+            let Some(name) = manifest_crate_name(&String::from_utf8_lossy(content)) else {
+                continue;
+            };
+            let root = path
+                .parent()
+                .map(|parent| normalize(&parent.to_string_lossy()).to_string())
+                .unwrap_or_default();
+            crates.push(CargoCrate { name, root });
+        }
+        crates.sort_by(|a, b| a.root.cmp(&b.root));
+        Self { crates }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.crates.is_empty()
+    }
+
+    /// Innermost crate containing the repository-relative `path`
+    pub fn crate_of(&self, path: &str) -> Option<&CargoCrate> {
+        self.crates
+            .iter()
+            .filter(|krate| krate.contains(path))
+            .max_by_key(|krate| krate.root.len())
+    }
+
+    /// Crate called `name` (`-` and `_` are interchangeable)
+    pub fn find(&self, name: &str) -> Option<&CargoCrate> {
+        let name = name.replace('-', "_");
+        self.crates.iter().find(|krate| krate.name == name)
+    }
+
+    /// Qualified name of `name` defined in `path`: `<crate>::<module path>::<name>` for
+    /// Rust files inside a crate, `<path>::<name>` otherwise
+    pub fn qualified_name(&self, path: &Path, name: &str) -> String {
+        let display = path.to_string_lossy();
+        let krate = (path.extension().and_then(|ext| ext.to_str()) == Some("rs"))
+            .then(|| self.crate_of(&display))
+            .flatten();
+        let Some(krate) = krate else {
+            return format!("{}::{}", display, name);
+        };
+        let module = krate.module_path(&display);
+        if module.is_empty() {
+            format!("{}::{}", krate.name, name)
+        } else {
+            format!("{}::{}::{}", krate.name, module, name)
+        }
+    }
+
+    /// Resolve a `--crate` argument against the crate map recorded for `db_path`
+    pub fn resolve(db_path: &Path, name: &str) -> Result<CargoCrate> {
+        let Some(map) = Self::load(db_path)? else {
+            anyhow::bail!(
+                "No Cargo crates recorded for this index. Re-index the codebase:\n\
+                 kotadb index-codebase /path/to/repo"
+            );
+        };
+        if let Some(krate) = map.find(name) {
+            return Ok(krate.clone());
+        }
+        let known: Vec<&str> = map.crates.iter().map(|k| k.name.as_str()).collect();
+        anyhow::bail!(
+            "Unknown crate '{}'. Indexed crates: {}",
+            name,
+            if known.is_empty() {
+                "none".to_string()
+            } else {
+                known.join(", ")
+            }
+        )
+    }
+}
+
+fn normalize(path: &str) -> &str {
+    path.trim_start_matches("./").trim_matches('/')
+}
+
+fn manifest_crate_name(manifest: &str) -> Option<String> {
+    let table: toml::Table = toml::from_str(manifest).ok()?;
+    let lib_name = table
+        .get("lib")
+        .and_then(|lib| lib.get("name"))
+        .and_then(|name| name.as_str());
+    let package_name = table
+        .get("package")
+        .and_then(|package| package.get("name"))
+        .and_then(|name| name.as_str());
+    Some(lib_name.or(package_name)?.replace('-', "_"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn workspace() -> CrateMap {
+        let files: Vec<(&Path, &[u8])> = vec![
+            (
+                Path::new("Cargo.toml"),
+                b"[workspace]\nmembers = [\"crates/*\"]\n",
+            ),
+            (
+                Path::new("crates/app-core/Cargo.toml"),
+                b"[package]\nname = \"app-core\"\n",
+            ),
+            (
+                Path::new("crates/cli/Cargo.toml"),
+                b"[package]\nname = \"app-cli\"\n\n[lib]\nname = \"cli\"\n",
+            ),
+            (Path::new("crates/cli/src/main.rs"), b"fn main() {}\n"),
+        ];
+        CrateMap::from_files(files)
+    }
+
+    #[test]
+    fn qualifies_rust_symbols_with_crate_and_module() {
+        let map = workspace();
+        assert_eq!(map.crates.len(), 2);
+        assert_eq!(
+            map.qualified_name(Path::new("crates/app-core/src/config.rs"), "load"),
+            "app_core::config::load"
+        );
+        assert_eq!(
+            map.qualified_name(Path::new("crates/cli/src/config/mod.rs"), "load"),
+            "cli::config::load"
+        );
+        assert_eq!(
+            map.qualified_name(Path::new("crates/cli/src/lib.rs"), "run"),
+            "cli::run"
+        );
+        assert_eq!(
+            map.qualified_name(Path::new("crates/cli/tests/smoke.rs"), "works"),
+            "cli::tests::smoke::works"
+        );
+        assert_eq!(
+            map.qualified_name(Path::new("scripts/build.py"), "main"),
+            "scripts/build.py::main"
+        );
+        assert_eq!(
+            map.qualified_name(Path::new("crates/app-core/web/app.ts"), "start"),
+            "crates/app-core/web/app.ts::start"
+        );
+    }
+
+    #[test]
+    fn finds_innermost_crate_and_resolves_names() {
+        let mut map = workspace();
+        map.crates.push(CargoCrate {
+            name: "root".to_string(),
+            root: String::new(),
+        });
+        assert_eq!(
+            map.crate_of("crates/app-core/src/lib.rs")
+                .map(|k| k.name.as_str()),
+            Some("app_core")
+        );
+        assert_eq!(
+            map.crate_of("./crates/cli/src/main.rs")
+                .map(|k| k.name.as_str()),
+            Some("cli")
+        );
+        assert_eq!(
+            map.crate_of("crates/cli-extra/src/lib.rs")
+                .map(|k| k.name.as_str()),
+            Some("root")
+        );
+        assert_eq!(
+            map.find("app-core").map(|k| k.root.as_str()),
+            Some("crates/app-core")
+        );
+        assert!(map.find("missing").is_none());
+    }
+}
@@ -20,6 +20,7 @@ use std::time::Instant;
 use tokio::sync::RwLock;
 use tracing::{info, instrument, warn};
 
+use crate::metrics::performance::{LockedResource, TimedLock};
 use crate::{
     binary_relationship_engine_async::AsyncBinaryRelationshipEngine,
     contracts::Index,
@@ -602,7 +603,7 @@ pub async fn index_repository(
         let ingester = RepositoryIngester::new(ingestion_config);
 
         // Lock storage for ingestion
-        let mut storage_guard = storage.lock().await;
+        let mut storage_guard = storage.lock_timed(LockedResource::Storage).await;
         let ingest_result = ingester.ingest(&repo_path, &mut *storage_guard).await?;
         drop(storage_guard);
 
@@ -622,7 +623,7 @@ pub async fn index_repository(
         let mut trigram_guard = state.trigram_index.write().await;
         if trigram_guard.is_some() {
             // Re-index with new documents
-            let storage_guard = storage.lock().await;
+            let storage_guard = storage.lock_timed(LockedResource::Storage).await;
             let all_docs = storage_guard.list_all().await?;
             drop(storage_guard);
 
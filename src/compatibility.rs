@@ -0,0 +1,167 @@
+//! Format compatibility between a database directory and the running binary
+//!
+//! Binary index files start with magic bytes and a format version. A build rejects
+//! versions it does not know with a deserialization error deep inside the first
+//! query, which says nothing about what to do. [`check`] reads the headers up front
+//! (without opening the database) and [`guidance`] explains the way out: upgrading
+//! kotadb when a newer build wrote the files, re-indexing when they predate the
+//! formats this build still reads.
+
+use std::path::Path;
+
+use crate::crash_report::DatabaseSummary;
+
+/// A binary index file in a format this build cannot read
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormatMismatch {
+    /// Path relative to the database directory
+    pub file: String,
+    /// Version in the file header
+    pub found: u32,
+    /// Version this build writes
+    pub expected: u32,
+}
+
+impl FormatMismatch {
+    /// Whether the file was written by a newer kotadb than this one
+    pub fn written_by_newer(&self) -> bool {
+        self.found > self.expected
+    }
+}
+
+/// Magic bytes and the oldest format version this build still reads, by file name
+fn readable_formats(file_name: &str) -> Option<(&'static str, u32)> {
+    match file_name {
+        "trigrams.bin" => Some(("KTRI", crate::binary_trigram_index::BINARY_FORMAT_VERSION)),
+        crate::leaf_pages::LEAF_PAGES_FILE => Some(("KOTALEAF", crate::leaf_pages::VERSION)),
+        #[cfg(feature = "tree-sitter-parsing")]
+        "symbols.kota" => Some(("KOTA", crate::binary_symbols::OLDEST_READABLE_VERSION)),
+        #[cfg(feature = "tree-sitter-parsing")]
+        "dependency_graph.bin" => Some(("KDGR", crate::dependency_extractor::GRAPH_FILE_VERSION)),
+        _ => None,
+    }
+}
+
+/// Index files in `db_path` whose format this build cannot read
+///
+/// Files without a recognised header are left alone: older unversioned files are
+/// migrated when they are loaded.
+pub fn check(db_path: &Path) -> Vec<FormatMismatch> {
+    DatabaseSummary::collect(db_path)
+        .formats
+        .into_iter()
+        .filter_map(|format| {
+            let file_name = Path::new(&format.file).file_name()?.to_str()?;
+            let (magic, oldest) = readable_formats(file_name)?;
+            let (found, expected) = (format.version?, format.expected_version?);
+            if format.magic.as_deref() != Some(magic) || (oldest..=expected).contains(&found) {
+                return None;
+            }
+            Some(FormatMismatch {
+                file: format.file,
+                found,
+                expected,
+            })
+        })
+        .collect()
+}
+
+/// What to do about `mismatches`, for printing instead of running the command
+pub fn guidance(mismatches: &[FormatMismatch]) -> String {
+    let version = env!("CARGO_PKG_VERSION");
+    let (newer, older): (Vec<_>, Vec<_>) = mismatches
+        .iter()
+        .partition(|mismatch| mismatch.written_by_newer());
+    let mut output = String::new();
+    if !newer.is_empty() {
+        output.push_str(&format!(
+            "This database was written by a newer kotadb than this one ({version}):\n"
+        ));
+        for mismatch in &newer {
+            output.push_str(&format!(
+                "  {}: format {}, this build reads up to {}\n",
+                mismatch.file, mismatch.found, mismatch.expected
+            ));
+        }
+        output.push_str("Run `kotadb self-update` to upgrade, or use the kotadb that wrote it.\n");
+    }
+    if !older.is_empty() {
+        if !output.is_empty() {
+            output.push('\n');
+        }
+        output.push_str(&format!(
+            "This database has index files in formats this kotadb ({version}) no longer reads:\n"
+        ));
+        for mismatch in &older {
+            output.push_str(&format!(
+                "  {}: format {}, this build writes {}\n",
+                mismatch.file, mismatch.found, mismatch.expected
+            ));
+        }
+        output.push_str(
+            "Re-index to rebuild them: `kotadb index-codebase /path/to/repo`. \
+             Documents are kept; only the indices are rewritten.\n",
+        );
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binary_trigram_index::BINARY_FORMAT_VERSION;
+    use tempfile::TempDir;
+
+    fn write_header(db_path: &Path, file: &str, magic: &[u8], version: u32) {
+        let path = db_path.join(file);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        let mut bytes = magic.to_vec();
+        bytes.extend_from_slice(&version.to_le_bytes());
+        bytes.extend_from_slice(&[0; 16]);
+        std::fs::write(path, bytes).unwrap();
+    }
+
+    #[test]
+    fn reports_newer_and_outdated_index_files() {
+        let dir = TempDir::new().unwrap();
+        let db_path = dir.path();
+        write_header(
+            db_path,
+            "trigram_index/binary/trigrams.bin",
+            b"KTRI",
+            BINARY_FORMAT_VERSION,
+        );
+        write_header(db_path, "other/unknown.bin", b"ABCD", 99);
+        assert!(check(db_path).is_empty());
+
+        write_header(
+            db_path,
+            "trigram_index/binary/trigrams.bin",
+            b"KTRI",
+            BINARY_FORMAT_VERSION + 1,
+        );
+        write_header(
+            db_path,
+            crate::leaf_pages::LEAF_PAGES_FILE,
+            b"KOTALEAF",
+            crate::leaf_pages::VERSION - 1,
+        );
+        let mismatches = check(db_path);
+        assert_eq!(mismatches.len(), 2);
+        assert!(!mismatches[0].written_by_newer());
+        assert!(mismatches[1].written_by_newer());
+        assert_eq!(mismatches[1].file, "trigram_index/binary/trigrams.bin");
+
+        let text = guidance(&mismatches);
+        assert!(text.contains("kotadb self-update"));
+        assert!(text.contains("kotadb index-codebase"));
+    }
+
+    #[cfg(feature = "tree-sitter-parsing")]
+    #[test]
+    fn accepts_older_formats_that_are_still_read() {
+        let dir = TempDir::new().unwrap();
+        write_header(dir.path(), "symbols.kota", b"KOTA", 1);
+        assert!(check(dir.path()).is_empty());
+    }
+}
@@ -0,0 +1,613 @@
+//! Configuration key usage index
+//!
+//! `index-codebase` records where configuration keys are set and read in
+//! `config_usages.json` in the database directory:
+//!
+//! - Set: keys of TOML, YAML and `.env` files, and fields of deserialized Rust config
+//!   structs (`#[derive(Deserialize)]` structs named `*Config`, `*Settings` or `*Conf`,
+//!   honoring `#[serde(rename = "..")]`).
+//! - Read: environment variable reads, lookups like `config.get("key")` or
+//!   `settings["key"]`, and field accesses like `self.config.database_url`.
+//!
+//! Keys match regardless of case and `_`, `-` or `.` separators, so `DATABASE_URL`
+//! finds `database_url` fields and a `[database] url` TOML entry; a dotted key also
+//! matches its last segment.
+
+use anyhow::{Context, Result};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::binary_symbols::FunctionRanges;
+use crate::contracts::Document;
+use crate::error_flows::mask_comments_and_strings;
+use crate::feature_flags::{LineStarts, ENV_READS};
+use crate::pure::metadata::strip_frontmatter;
+
+/// File name of the configuration key index inside a database directory
+pub const CONFIG_INDEX_FILE: &str = "config_usages.json";
+
+/// Manifests and lock files whose keys are dependencies, not settings
+const NON_CONFIG_FILES: &[&str] = &[
+    "Cargo.toml",
+    "Cargo.lock",
+    "pnpm-lock.yaml",
+    "yarn.lock",
+    "poetry.lock",
+];
+
+static CONFIG_LOOKUP: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r#"(?i)\b\w*(?:config|settings|cfg|conf)\w*(?:\(\))?\s*(?:\.\s*get\w*(?:::<[^>]*>)?\(|\[)\s*["']([\w.\-]+)["']"#,
+    )
+    .expect("valid lookup pattern")
+});
+static CONFIG_FIELD: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)\b\w*(?:config|settings|cfg|conf)(?:\(\))?((?:\s*\.\s*[a-z_]\w*\b)+)")
+        .expect("valid field pattern")
+});
+static CONFIG_STRUCT: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"\bstruct\s+(\w*(?:Config|Settings|Configuration|Conf))\s*(?:<[^>{]*>)?\s*\{")
+        .expect("valid struct pattern")
+});
+static STRUCT_FIELD: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^\s*(?:pub(?:\([^)]*\))?\s+)?([A-Za-z_]\w*)\s*:").expect("valid field pattern")
+});
+static SERDE_RENAME: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"\brename\s*=\s*"([^"]+)""#).expect("valid rename pattern"));
+static SERDE_SKIP: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"\b(?:flatten|skip|skip_deserializing)\b").expect("valid skip pattern")
+});
+static TOML_TABLE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^\s*\[\[?\s*([^\]]+?)\s*\]\]?\s*$").expect("valid table pattern"));
+static TOML_KEY: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"^\s*("[^"]+"|[A-Za-z0-9_.\-]+)\s*="#).expect("valid key pattern"));
+static YAML_KEY: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"^(\s*)(?:-\s+)?("[^"]+"|'[^']+'|[A-Za-z0-9_.\-]+)\s*:(?:\s|$)"#)
+        .expect("valid key pattern")
+});
+static DOTENV_KEY: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^\s*(?:export\s+)?([A-Za-z_][A-Za-z0-9_]*)\s*=").expect("valid key pattern")
+});
+
+/// How a configuration key appears at a site
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfigSiteKind {
+    /// Key set in a TOML, YAML or `.env` file
+    ConfigFile,
+    /// Field of a deserialized config struct
+    StructField,
+    /// Environment variable read
+    EnvRead,
+    /// Lookup by key, e.g. `config.get("key")`
+    Lookup,
+    /// Field access on a config value, e.g. `config.key`
+    FieldAccess,
+}
+
+impl ConfigSiteKind {
+    /// Whether the site reads the key rather than setting or declaring it
+    pub fn is_read(&self) -> bool {
+        matches!(
+            self,
+            ConfigSiteKind::EnvRead | ConfigSiteKind::Lookup | ConfigSiteKind::FieldAccess
+        )
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            ConfigSiteKind::ConfigFile => "config file",
+            ConfigSiteKind::StructField => "struct field",
+            ConfigSiteKind::EnvRead => "env",
+            ConfigSiteKind::Lookup => "lookup",
+            ConfigSiteKind::FieldAccess => "field access",
+        }
+    }
+}
+
+/// One place a configuration key is set or read
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConfigSite {
+    /// Key as spelled at the site; dotted for nested config file entries
+    pub key: String,
+    pub kind: ConfigSiteKind,
+    pub file_path: String,
+    /// 1-based line
+    pub line: usize,
+    /// Function containing a read, or the struct declaring a field
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub symbol: Option<String>,
+    /// Trimmed source line
+    pub snippet: String,
+}
+
+/// Where one configuration key is set and read
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ConfigUsages {
+    pub key: String,
+    /// Config file entries and config struct fields
+    pub definitions: Vec<ConfigSite>,
+    /// Environment reads, lookups and field accesses
+    pub reads: Vec<ConfigSite>,
+}
+
+impl ConfigUsages {
+    /// Keep at most `limit` sites per section
+    pub fn truncate(&mut self, limit: usize) {
+        self.definitions.truncate(limit);
+        self.reads.truncate(limit);
+    }
+
+    /// Sections for definitions and read sites
+    pub fn to_markdown(&self) -> String {
+        let mut output = format!("# Usages of `{}`\n", self.key);
+        if self.definitions.is_empty() && self.reads.is_empty() {
+            output.push_str("\nThe key is not set or read in the indexed code.\n");
+            return output;
+        }
+        for (title, sites) in [("Set", &self.definitions), ("Read", &self.reads)] {
+            if sites.is_empty() {
+                continue;
+            }
+            output.push_str(&format!("\n## {} ({})\n", title, sites.len()));
+            for site in sites {
+                output.push_str(&format!(
+                    "- {}:{}{} [{}] `{}`\n",
+                    site.file_path,
+                    site.line,
+                    site.symbol
+                        .as_ref()
+                        .map(|symbol| format!(" in `{}`", symbol))
+                        .unwrap_or_default(),
+                    site.kind.as_str(),
+                    site.snippet
+                ));
+            }
+        }
+        output
+    }
+}
+
+/// Configuration key sites of the indexed codebase
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ConfigIndex {
+    /// RFC 3339 time the index was built
+    pub indexed_at: String,
+    pub sites: Vec<ConfigSite>,
+}
+
+impl ConfigIndex {
+    /// Find configuration keys set in config files and read in source files
+    pub fn build<'a>(
+        files: impl IntoIterator<Item = (&'a str, &'a str)>,
+        functions: &FunctionRanges,
+    ) -> Self {
+        let mut sites = Vec::new();
+        for (path, content) in files {
+            let file_name = path.rsplit('/').next().unwrap_or(path);
+            if NON_CONFIG_FILES.contains(&file_name) {
+                continue;
+            }
+            if file_name.starts_with(".env") || file_name.ends_with(".env") {
+                sites.extend(dotenv_keys(path, content));
+            } else if file_name.ends_with(".toml") {
+                sites.extend(toml_keys(path, content));
+            } else if file_name.ends_with(".yaml") || file_name.ends_with(".yml") {
+                sites.extend(yaml_keys(path, content));
+            } else if is_source_file(file_name) {
+                sites.extend(source_sites(path, content, functions));
+            }
+        }
+        sites.sort_by(|a, b| (&a.file_path, a.line, &a.key).cmp(&(&b.file_path, b.line, &b.key)));
+        sites.dedup();
+        Self {
+            indexed_at: chrono::Utc::now().to_rfc3339(),
+            sites,
+        }
+    }
+
+    /// Location of the configuration index for a database directory
+    pub fn path_for(db_path: &Path) -> PathBuf {
+        db_path.join(CONFIG_INDEX_FILE)
+    }
+
+    /// The configuration index of a database, if one was built
+    pub fn load(db_path: &Path) -> Result<Option<Self>> {
+        let path = Self::path_for(db_path);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read config usages: {}", path.display()))?;
+        let index = serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse config usages: {}", path.display()))?;
+        Ok(Some(index))
+    }
+
+    /// Save the index atomically into a database directory
+    pub fn save(&self, db_path: &Path) -> Result<()> {
+        std::fs::create_dir_all(db_path)
+            .with_context(|| format!("Failed to create {}", db_path.display()))?;
+        let path = Self::path_for(db_path);
+        let tmp_path = path.with_extension("json.tmp");
+        std::fs::write(&tmp_path, serde_json::to_string(self)?)
+            .with_context(|| format!("Failed to write config usages: {}", tmp_path.display()))?;
+        std::fs::rename(&tmp_path, &path)
+            .with_context(|| format!("Failed to replace config usages: {}", path.display()))?;
+        Ok(())
+    }
+
+    /// Sites setting or reading `key`
+    pub fn usages(&self, key: &str) -> ConfigUsages {
+        let mut usages = ConfigUsages {
+            key: key.to_string(),
+            ..ConfigUsages::default()
+        };
+        for site in self.sites.iter().filter(|site| keys_match(&site.key, key)) {
+            if site.kind.is_read() {
+                usages.reads.push(site.clone());
+            } else {
+                usages.definitions.push(site.clone());
+            }
+        }
+        usages
+    }
+}
+
+/// Build and save the configuration index from stored documents
+pub fn index_config_usages(db_path: &Path, documents: &[Document]) -> Result<ConfigIndex> {
+    let functions = FunctionRanges::load(&db_path.join("symbols.kota"))?;
+    let contents: Vec<(&str, String)> = documents
+        .iter()
+        .filter(|d| !d.tags.iter().any(|tag| tag.as_str() == "commit"))
+        .map(|d| {
+            (
+                d.path.as_str(),
+                strip_frontmatter(&String::from_utf8_lossy(&d.content)).to_string(),
+            )
+        })
+        .collect();
+    let index = ConfigIndex::build(
+        contents
+            .iter()
+            .map(|(path, content)| (*path, content.as_str())),
+        &functions,
+    );
+    index.save(db_path)?;
+    Ok(index)
+}
+
+/// Key without case or separators, so spellings across env, files and code compare equal
+fn normalize_key(key: &str) -> String {
+    key.chars()
+        .filter(|c| !matches!(c, '_' | '-' | '.'))
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Whether two spellings name the same key; a dotted key also matches its last segment
+fn keys_match(a: &str, b: &str) -> bool {
+    if normalize_key(a) == normalize_key(b) {
+        return true;
+    }
+    let last = |key: &str| key.rsplit('.').next().map(normalize_key);
+    (a.contains('.') && !b.contains('.') && last(a) == Some(normalize_key(b)))
+        || (b.contains('.') && !a.contains('.') && last(b) == Some(normalize_key(a)))
+}
+
+fn is_source_file(file_name: &str) -> bool {
+    crate::trends::is_source_language(crate::path_utils::detect_language_from_extension(
+        Path::new(file_name),
+    ))
+}
+
+fn site(key: &str, kind: ConfigSiteKind, path: &str, line: usize, text: &str) -> ConfigSite {
+    ConfigSite {
+        key: key.trim_matches(|c| c == '"' || c == '\'').to_string(),
+        kind,
+        file_path: path.to_string(),
+        line,
+        symbol: None,
+        snippet: text.trim().chars().take(160).collect(),
+    }
+}
+
+fn dotenv_keys(path: &str, content: &str) -> Vec<ConfigSite> {
+    content
+        .lines()
+        .enumerate()
+        .filter_map(|(i, text)| {
+            let key = DOTENV_KEY.captures(text)?;
+            Some(site(&key[1], ConfigSiteKind::ConfigFile, path, i + 1, text))
+        })
+        .collect()
+}
+
+fn toml_keys(path: &str, content: &str) -> Vec<ConfigSite> {
+    let mut table = String::new();
+    let mut sites = Vec::new();
+    for (i, text) in content.lines().enumerate() {
+        if let Some(header) = TOML_TABLE.captures(text) {
+            table = header[1].replace('"', "");
+        } else if let Some(key) = TOML_KEY.captures(text) {
+            let key = key[1].trim_matches('"');
+            let key = if table.is_empty() {
+                key.to_string()
+            } else {
+                format!("{}.{}", table, key)
+            };
+            sites.push(site(&key, ConfigSiteKind::ConfigFile, path, i + 1, text));
+        }
+    }
+    sites
+}
+
+fn yaml_keys(path: &str, content: &str) -> Vec<ConfigSite> {
+    // (indent, key) of the mappings enclosing the current line
+    let mut parents: Vec<(usize, String)> = Vec::new();
+    let mut sites = Vec::new();
+    for (i, text) in content.lines().enumerate() {
+        let Some(key) = YAML_KEY.captures(text) else {
+            continue;
+        };
+        let indent = key[1].len();
+        while parents.last().is_some_and(|(level, _)| *level >= indent) {
+            parents.pop();
+        }
+        let name = key[2].trim_matches(|c| c == '"' || c == '\'').to_string();
+        let full: Vec<&str> = parents
+            .iter()
+            .map(|(_, parent)| parent.as_str())
+            .chain(std::iter::once(name.as_str()))
+            .collect();
+        sites.push(site(
+            &full.join("."),
+            ConfigSiteKind::ConfigFile,
+            path,
+            i + 1,
+            text,
+        ));
+        parents.push((indent, name));
+    }
+    sites
+}
+
+fn source_sites(path: &str, content: &str, functions: &FunctionRanges) -> Vec<ConfigSite> {
+    let is_rust = path.ends_with(".rs");
+    let code = if is_rust {
+        mask_comments_and_strings(content)
+    } else {
+        content.to_string()
+    };
+    let lines = LineStarts::new(content);
+    let source_lines: Vec<&str> = content.lines().collect();
+    let in_code = |offset: usize| code.as_bytes()[offset] == content.as_bytes()[offset];
+    let read = |key: &str, kind: ConfigSiteKind, offset: usize| {
+        let line = lines.line_of(offset);
+        let mut read = site(
+            key,
+            kind,
+            path,
+            line,
+            source_lines.get(line - 1).copied().unwrap_or_default(),
+        );
+        read.symbol = functions.enclosing(path, line).map(str::to_string);
+        read
+    };
+
+    let mut sites = Vec::new();
+    for pattern in ENV_READS.iter() {
+        for env in pattern.captures_iter(content) {
+            let start = env.get(0).expect("match").start();
+            if in_code(start) {
+                sites.push(read(&env[1], ConfigSiteKind::EnvRead, start));
+            }
+        }
+    }
+    for lookup in CONFIG_LOOKUP.captures_iter(content) {
+        let start = lookup.get(0).expect("match").start();
+        if in_code(start) {
+            sites.push(read(&lookup[1], ConfigSiteKind::Lookup, start));
+        }
+    }
+    // Field chains are matched in masked code and recorded dotted (`config.database.url`
+    // reads `database.url`); the chain ends before a method call
+    for chain in CONFIG_FIELD.captures_iter(&code) {
+        let chain = chain.get(1).expect("group");
+        let mut key: Vec<&str> = Vec::new();
+        let mut segments = chain.as_str().split('.');
+        let mut segment_end = chain.start() + segments.next().unwrap_or_default().len();
+        for segment in segments {
+            segment_end += segment.len() + 1;
+            if code[segment_end..].trim_start().starts_with(['(', ':']) {
+                break;
+            }
+            key.push(segment.trim());
+        }
+        if !key.is_empty() {
+            let start = chain.start() + chain.as_str().find('.').expect("chain starts with a dot");
+            sites.push(read(&key.join("."), ConfigSiteKind::FieldAccess, start + 1));
+        }
+    }
+    if is_rust {
+        sites.extend(config_struct_fields(path, content, &code, &lines));
+    }
+    sites
+}
+
+/// Fields of deserialized config structs, by serde key
+fn config_struct_fields(
+    path: &str,
+    content: &str,
+    code: &str,
+    lines: &LineStarts,
+) -> Vec<ConfigSite> {
+    let mut sites = Vec::new();
+    for item in CONFIG_STRUCT.captures_iter(code) {
+        let whole = item.get(0).expect("match");
+        // Attributes between the previous item and this struct
+        let preceding = &code[..whole.start()];
+        let attributes_start = preceding.rfind(['}', ';']).map_or(0, |i| i + 1);
+        if !code[attributes_start..whole.start()].contains("Deserialize") {
+            continue;
+        }
+
+        let body_start = whole.end();
+        let mut depth = 0usize;
+        let mut field_start = body_start;
+        for (offset, byte) in code[body_start..].bytes().enumerate() {
+            let offset = body_start + offset;
+            match byte {
+                b'(' | b'[' | b'<' | b'{' => depth += 1,
+                b')' | b']' | b'>' if depth > 0 => depth -= 1,
+                b'}' if depth > 0 => depth -= 1,
+                b',' | b'}' if depth == 0 => {
+                    if let Some(field) =
+                        struct_field(path, content, code, field_start..offset, lines)
+                    {
+                        sites.push(ConfigSite {
+                            symbol: Some(item[1].to_string()),
+                            ..field
+                        });
+                    }
+                    field_start = offset + 1;
+                    if byte == b'}' {
+                        break;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    sites
+}
+
+fn struct_field(
+    path: &str,
+    content: &str,
+    code: &str,
+    range: std::ops::Range<usize>,
+    lines: &LineStarts,
+) -> Option<ConfigSite> {
+    // Attributes come first; the declaration starts after the last one
+    let chunk = &code[range.clone()];
+    let declaration_start = chunk.rfind("#[").map_or(0, |i| {
+        chunk[i..].find(']').map_or(chunk.len(), |end| i + end + 1)
+    });
+    let attributes = &content[range.start..range.start + declaration_start];
+    if SERDE_SKIP.is_match(attributes) {
+        return None;
+    }
+    let field = STRUCT_FIELD.captures(&chunk[declaration_start..])?;
+    let name = field.get(1).expect("group");
+    let key = attributes
+        .lines()
+        .filter(|attribute| attribute.contains("serde"))
+        .find_map(|attribute| SERDE_RENAME.captures(attribute))
+        .map_or(name.as_str().to_string(), |rename| rename[1].to_string());
+    let line = lines.line_of(range.start + declaration_start + name.start());
+    Some(site(
+        &key,
+        ConfigSiteKind::StructField,
+        path,
+        line,
+        content.lines().nth(line - 1).unwrap_or_default(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SETTINGS: &str = r#"
+#[derive(Debug, Deserialize)]
+pub struct AppConfig {
+    /// Connection string
+    pub database_url: String,
+    #[serde(rename = "port")]
+    pub listen_port: u16,
+    #[serde(flatten)]
+    pub extra: HashMap<String, String>,
+}
+
+pub fn connect(config: &AppConfig) -> Pool {
+    // config.database_url is required
+    let url = std::env::var("DATABASE_URL").unwrap_or(config.database_url.clone());
+    let timeout = settings.get::<u64>("database.timeout");
+    Pool::new(url, config.listen_port, config.pool.size.max(1))
+}
+"#;
+
+    fn build() -> ConfigIndex {
+        ConfigIndex::build(
+            [
+                ("src/config.rs", SETTINGS),
+                (
+                    "config/app.toml",
+                    "[database]\nurl = \"postgres://\"\ntimeout = 5\n",
+                ),
+                ("deploy/values.yaml", "server:\n  port: 8080\n"),
+                (".env", "export DATABASE_URL=postgres://local\n"),
+                ("Cargo.toml", "[dependencies]\nurl = \"2\"\n"),
+            ],
+            &FunctionRanges::from_ranges([(
+                "connect".to_string(),
+                "src/config.rs".to_string(),
+                12,
+                17,
+            )]),
+        )
+    }
+
+    #[test]
+    fn key_spellings_across_env_files_and_structs_match() {
+        let usages = build().usages("DATABASE_URL");
+        let defined: Vec<(&str, usize)> = usages
+            .definitions
+            .iter()
+            .map(|site| (site.file_path.as_str(), site.line))
+            .collect();
+        assert_eq!(
+            defined,
+            vec![(".env", 1), ("config/app.toml", 2), ("src/config.rs", 5)]
+        );
+        assert_eq!(usages.definitions[2].symbol.as_deref(), Some("AppConfig"));
+
+        let read: Vec<(ConfigSiteKind, usize)> = usages
+            .reads
+            .iter()
+            .map(|site| (site.kind, site.line))
+            .collect();
+        assert_eq!(
+            read,
+            vec![
+                (ConfigSiteKind::EnvRead, 14),
+                (ConfigSiteKind::FieldAccess, 14)
+            ]
+        );
+        assert_eq!(usages.reads[0].symbol.as_deref(), Some("connect"));
+    }
+
+    #[test]
+    fn renamed_fields_lookups_and_nested_keys() {
+        let index = build();
+        let port = index.usages("port");
+        assert_eq!(port.definitions.len(), 2);
+        assert!(port
+            .definitions
+            .iter()
+            .any(|site| site.key == "server.port"));
+        assert!(index.usages("listen_port").definitions.is_empty());
+        assert_eq!(index.usages("listen_port").reads.len(), 1);
+
+        let timeout = index.usages("database.timeout");
+        assert_eq!(timeout.reads[0].kind, ConfigSiteKind::Lookup);
+        assert_eq!(timeout.definitions[0].file_path, "config/app.toml");
+        assert!(index.usages("extra").definitions.is_empty());
+        assert_eq!(index.usages("pool.size").reads[0].key, "pool.size");
+        assert_eq!(index.usages("size").reads.len(), 1);
+        assert!(index.usages("max").reads.is_empty());
+    }
+}
@@ -48,6 +48,53 @@ pub trait Storage: Send + Sync {
     /// Get a document by ID
     async fn get(&self, id: &ValidatedDocumentId) -> Result<Option<Document>>;
 
+    /// Get several documents at once, in the order of `ids`
+    ///
+    /// Missing documents are `None`. Default implementation calls `get` for each ID;
+    /// storages that can batch lookups or reads should override this.
+    async fn get_many(&self, ids: &[ValidatedDocumentId]) -> Result<Vec<Option<Document>>> {
+        let mut documents = Vec::with_capacity(ids.len());
+        for id in ids {
+            documents.push(self.get(id).await?);
+        }
+        Ok(documents)
+    }
+
+    /// Path and size of a stored document, without reading its content
+    ///
+    /// Default implementation loads the document. Storages that keep document
+    /// metadata in memory should override this.
+    async fn summary(&self, id: &ValidatedDocumentId) -> Result<Option<DocumentSummary>> {
+        Ok(self
+            .get(id)
+            .await?
+            .map(|doc| DocumentSummary::of_document(&doc)))
+    }
+
+    /// Stored attributes of a document, without reading its content
+    ///
+    /// Default implementation loads the document. Storages that keep document
+    /// metadata in memory should override this.
+    async fn get_metadata(&self, id: &ValidatedDocumentId) -> Result<Option<DocumentMetadata>> {
+        Ok(self
+            .get(id)
+            .await?
+            .map(|doc| DocumentMetadata::of_document(&doc)))
+    }
+
+    /// Stored attributes of all documents, without reading their content
+    ///
+    /// Default implementation loads every document. Storages that keep document
+    /// metadata in memory should override this.
+    async fn list_metadata(&self) -> Result<Vec<DocumentMetadata>> {
+        Ok(self
+            .list_all()
+            .await?
+            .iter()
+            .map(DocumentMetadata::of_document)
+            .collect())
+    }
+
     /// Update an existing document
     async fn update(&mut self, document: Document) -> Result<()>;
 
@@ -57,6 +104,28 @@ pub trait Storage: Send + Sync {
     /// List all documents
     async fn list_all(&self) -> Result<Vec<Document>>;
 
+    /// List documents in ID order, one page at a time
+    ///
+    /// Returns up to `limit` documents with IDs after `after` (from the start when
+    /// `None`). Pass the returned `next` cursor to get the following page, until it is
+    /// `None`. Only one page is held in memory, so callers can walk databases larger
+    /// than RAM. Default implementation lists all documents and slices them; storages
+    /// should override this.
+    async fn list_page(
+        &self,
+        after: Option<ValidatedDocumentId>,
+        limit: usize,
+    ) -> Result<DocumentPage> {
+        let mut documents = self.list_all().await?;
+        documents.sort_by_key(|doc| doc.id);
+        let documents: Vec<Document> = documents
+            .into_iter()
+            .filter(|doc| after.is_none_or(|after| doc.id > after))
+            .take(limit)
+            .collect();
+        Ok(DocumentPage::new(documents, limit))
+    }
+
     /// Sync changes to persistent storage
     async fn sync(&mut self) -> Result<()>;
 
@@ -109,6 +178,22 @@ pub trait Index: Send + Sync {
         self.update(id, path).await
     }
 
+    /// Tokenizer that prepares documents for [`Index::insert_tokenized`]
+    ///
+    /// Lets callers tokenize content without holding the index, e.g. on worker threads.
+    /// Default implementation returns `None` for indices that don't tokenize content.
+    async fn content_tokenizer(&self) -> Option<ContentTokenizer> {
+        None
+    }
+
+    /// Insert a document tokenized by this index's [`ContentTokenizer`]
+    ///
+    /// Default implementation delegates to insert_with_content().
+    async fn insert_tokenized(&mut self, document: TokenizedDocument) -> Result<()> {
+        self.insert_with_content(document.id, document.path, &document.content)
+            .await
+    }
+
     /// Delete an entry from the index
     async fn delete(&mut self, id: &ValidatedDocumentId) -> Result<bool>;
 
@@ -123,6 +208,166 @@ pub trait Index: Send + Sync {
 
     /// Close the index instance
     async fn close(self) -> Result<()>;
+
+    /// Structure of the B+ tree backing the index
+    ///
+    /// Default implementation returns `None` for indices that aren't backed by a tree.
+    async fn tree_structure(&self) -> Result<Option<TreeStructureMetrics>> {
+        Ok(None)
+    }
+
+    /// Rebuild the backing tree balanced and with densely packed nodes
+    ///
+    /// Returns `false` when the index isn't backed by a tree.
+    async fn rebuild_tree(&mut self) -> Result<bool> {
+        Ok(false)
+    }
+
+    /// Copy of the B+ tree backing the index, for rebuilding without holding the index
+    ///
+    /// Default implementation returns `None` for indices that aren't backed by a tree.
+    async fn tree_snapshot(&self) -> Result<Option<crate::pure::btree::BTreeRoot>> {
+        Ok(None)
+    }
+
+    /// Replace the backing tree with one rebuilt from [`Index::tree_snapshot`]
+    ///
+    /// The caller ensures no writes happened since the snapshot. Returns `false` when the
+    /// index isn't backed by a tree.
+    async fn replace_tree(&mut self, _tree: crate::pure::btree::BTreeRoot) -> Result<bool> {
+        Ok(false)
+    }
+}
+
+/// Path and size of a stored document, as kept in storage metadata
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DocumentSummary {
+    pub path: ValidatedPath,
+    pub size: usize,
+    /// Estimated tokens of the content, when the storage recorded it at insert time
+    pub tokens: Option<u64>,
+}
+
+impl DocumentSummary {
+    /// Summary of a document held in memory
+    pub fn of_document(doc: &Document) -> Self {
+        Self {
+            path: doc.path.clone(),
+            size: doc.size,
+            tokens: Some(content_tokens(&doc.content)),
+        }
+    }
+}
+
+/// Stored attributes of a document, without its content
+#[derive(Debug, Clone, PartialEq)]
+pub struct DocumentMetadata {
+    pub id: ValidatedDocumentId,
+    pub path: ValidatedPath,
+    pub title: ValidatedTitle,
+    pub tags: Vec<ValidatedTag>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    /// Content size in bytes
+    pub size: usize,
+    /// Estimated tokens of the content, when the storage recorded it at insert time
+    pub tokens: Option<u64>,
+}
+
+impl DocumentMetadata {
+    /// Metadata of a document held in memory
+    pub fn of_document(doc: &Document) -> Self {
+        Self {
+            id: doc.id,
+            path: doc.path.clone(),
+            title: doc.title.clone(),
+            tags: doc.tags.clone(),
+            created_at: doc.created_at,
+            updated_at: doc.updated_at,
+            size: doc.size,
+            tokens: Some(content_tokens(&doc.content)),
+        }
+    }
+}
+
+/// One page of documents from [`Storage::list_page`]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DocumentPage {
+    pub documents: Vec<Document>,
+    /// Cursor for the following page; `None` once every document has been listed
+    pub next: Option<ValidatedDocumentId>,
+}
+
+impl DocumentPage {
+    /// Page of `documents`, in ID order, listed with a page size of `limit`
+    ///
+    /// A full page may be followed by more documents, so it continues after its last one.
+    pub fn new(documents: Vec<Document>, limit: usize) -> Self {
+        let next = if documents.len() >= limit {
+            documents.last().map(|doc| doc.id)
+        } else {
+            None
+        };
+        Self { documents, next }
+    }
+}
+
+/// How a content-aware index splits documents into trigrams
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContentTokenizer {
+    /// File name and content, as indexed by [`crate::trigram_index::TrigramIndex`]
+    Trigram { identifier_subwords: bool },
+    /// Full path and content, as indexed by
+    /// [`crate::binary_trigram_index::BinaryTrigramIndex`]
+    BinaryTrigram { identifier_subwords: bool },
+}
+
+impl ContentTokenizer {
+    /// Extract the trigrams of a document
+    pub fn tokenize(
+        &self,
+        id: ValidatedDocumentId,
+        path: ValidatedPath,
+        content: Vec<u8>,
+    ) -> TokenizedDocument {
+        let trigrams = match *self {
+            Self::Trigram {
+                identifier_subwords,
+            } => crate::trigram_index::TrigramIndex::content_trigrams(
+                &path,
+                &content,
+                identifier_subwords,
+            ),
+            Self::BinaryTrigram {
+                identifier_subwords,
+            } => crate::binary_trigram_index::BinaryTrigramIndex::content_trigrams(
+                &path,
+                &content,
+                identifier_subwords,
+            ),
+        };
+        TokenizedDocument {
+            id,
+            path,
+            content,
+            trigrams,
+        }
+    }
+}
+
+/// A document with the trigrams extracted by a [`ContentTokenizer`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenizedDocument {
+    pub id: ValidatedDocumentId,
+    pub path: ValidatedPath,
+    pub content: Vec<u8>,
+    /// Every trigram in text order, including duplicates
+    pub trigrams: Vec<String>,
+}
+
+/// Estimated tokens `content` occupies in an assistant's context
+pub fn content_tokens(content: &[u8]) -> u64 {
+    crate::llm_search::estimate_tokens(&String::from_utf8_lossy(content)) as u64
 }
 
 /// Document representation
@@ -195,11 +440,94 @@ impl Document {
 /// Query representation
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Query {
+    /// Alternative search terms: content indices match documents that match any of them
     pub search_terms: Vec<ValidatedSearchQuery>,
     pub tags: Vec<ValidatedTag>,
     pub path_pattern: Option<String>,
     pub limit: ValidatedLimit,
     pub offset: ValidatedPageId,
+    /// Extension, depth and size restrictions on the documents returned
+    #[serde(default)]
+    pub filters: DocumentFilters,
+    /// Point after which index traversals stop and return what they matched so far
+    #[serde(skip)]
+    pub deadline: crate::query_deadline::QueryDeadline,
+}
+
+/// Restrictions on which documents a query returns
+///
+/// Extension and depth are read from the document path, so indices that keep paths
+/// check them while they traverse their entries. Size bounds need the stored document
+/// metadata and are checked against [`DocumentSummary::size`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DocumentFilters {
+    /// File extensions without the dot, compared case-insensitively; empty allows all
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extensions: Vec<String>,
+    /// Most directories above the file in its path (`src/lib.rs` has depth 1)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_depth: Option<usize>,
+    /// Smallest document size in bytes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_size: Option<u64>,
+    /// Largest document size in bytes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_size: Option<u64>,
+}
+
+impl DocumentFilters {
+    /// Only allow documents with one of `extensions` (leading dots are ignored)
+    pub fn with_extensions(
+        mut self,
+        extensions: impl IntoIterator<Item = impl AsRef<str>>,
+    ) -> Self {
+        self.extensions = extensions
+            .into_iter()
+            .map(|ext| ext.as_ref().trim().trim_start_matches('.').to_lowercase())
+            .filter(|ext| !ext.is_empty())
+            .collect();
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        !self.filters_paths() && !self.filters_sizes()
+    }
+
+    /// Whether an extension or depth restriction is set
+    pub fn filters_paths(&self) -> bool {
+        !self.extensions.is_empty() || self.max_depth.is_some()
+    }
+
+    /// Whether a size bound is set
+    pub fn filters_sizes(&self) -> bool {
+        self.min_size.is_some() || self.max_size.is_some()
+    }
+
+    /// Whether a document at `path` passes the extension and depth restrictions
+    pub fn matches_path(&self, path: &str) -> bool {
+        let path = path.trim_start_matches("./");
+        if let Some(max_depth) = self.max_depth {
+            if path.matches('/').count() > max_depth {
+                return false;
+            }
+        }
+        if self.extensions.is_empty() {
+            return true;
+        }
+        let file_name = path.rsplit('/').next().unwrap_or(path);
+        file_name.rsplit_once('.').is_some_and(|(stem, ext)| {
+            !stem.is_empty()
+                && self
+                    .extensions
+                    .iter()
+                    .any(|allowed| allowed.eq_ignore_ascii_case(ext))
+        })
+    }
+
+    /// Whether a document of `size` bytes passes the size bounds
+    pub fn matches_size(&self, size: u64) -> bool {
+        self.min_size.is_none_or(|min| size >= min) && self.max_size.is_none_or(|max| size <= max)
+    }
 }
 
 impl Query {
@@ -228,6 +556,8 @@ impl Query {
             path_pattern,
             limit: ValidatedLimit::new(limit, 100_000)?, // Increased from 1000 to handle large repositories
             offset: ValidatedPageId::new(1)?,
+            filters: DocumentFilters::default(),
+            deadline: Default::default(),
         })
     }
 
@@ -239,6 +569,8 @@ impl Query {
             path_pattern: None,
             limit: ValidatedLimit::new(10, 100_000).expect("Default limit values are valid"),
             offset: ValidatedPageId::new(1).expect("Default page ID is valid"),
+            filters: DocumentFilters::default(),
+            deadline: Default::default(),
         }
     }
 }
@@ -8,6 +8,7 @@ use tokio::sync::Mutex;
 use tracing::{debug, error, info, warn};
 
 use crate::contracts::{Index, Storage};
+use crate::metrics::performance::{LockedResource, TimedLock};
 use crate::types::ValidatedDocumentId;
 
 /// Service that coordinates deletion across storage and indices
@@ -56,7 +57,7 @@ impl CoordinatedDeletionService {
         // Step 1: Retrieve document for potential rollback
         debug!("Retrieving document for rollback: {}", doc_id.as_uuid());
         let document_backup = {
-            let storage = self.storage.lock().await;
+            let storage = self.storage.lock_timed(LockedResource::Storage).await;
             storage.get(doc_id).await?
         };
 
@@ -72,7 +73,7 @@ impl CoordinatedDeletionService {
         // Step 2: Delete from storage
         debug!("Deleting document from storage: {}", doc_id.as_uuid());
         let deleted_from_storage = {
-            let mut storage = self.storage.lock().await;
+            let mut storage = self.storage.lock_timed(LockedResource::Storage).await;
             storage.delete(doc_id).await?
         };
 
@@ -93,7 +94,10 @@ impl CoordinatedDeletionService {
         // Step 2: Update primary index
         debug!("Deleting document from primary index: {}", doc_id.as_uuid());
         let primary_result = {
-            let mut primary_index = self.primary_index.lock().await;
+            let mut primary_index = self
+                .primary_index
+                .lock_timed(LockedResource::PrimaryIndex)
+                .await;
             primary_index.delete(doc_id).await
         };
 
@@ -120,7 +124,7 @@ impl CoordinatedDeletionService {
                 );
 
                 // Attempt to restore the document
-                let mut storage = self.storage.lock().await;
+                let mut storage = self.storage.lock_timed(LockedResource::Storage).await;
                 match storage.insert(document_backup.clone()).await {
                     Ok(_) => {
                         info!(
@@ -151,7 +155,10 @@ impl CoordinatedDeletionService {
         // Step 3: Update trigram index
         debug!("Deleting document from trigram index: {}", doc_id.as_uuid());
         let trigram_result = {
-            let mut trigram_index = self.trigram_index.lock().await;
+            let mut trigram_index = self
+                .trigram_index
+                .lock_timed(LockedResource::TrigramIndex)
+                .await;
             trigram_index.delete(doc_id).await
         };
 
@@ -178,11 +185,14 @@ impl CoordinatedDeletionService {
                 );
 
                 // First restore to storage
-                let mut storage = self.storage.lock().await;
+                let mut storage = self.storage.lock_timed(LockedResource::Storage).await;
                 let storage_rollback = storage.insert(document_backup.clone()).await;
 
                 // Then restore to primary index
-                let mut primary_index = self.primary_index.lock().await;
+                let mut primary_index = self
+                    .primary_index
+                    .lock_timed(LockedResource::PrimaryIndex)
+                    .await;
                 let primary_rollback = primary_index
                     .insert(*doc_id, document_backup.path.clone())
                     .await;
@@ -287,27 +297,41 @@ mod tests {
         let content = doc.content.clone();
 
         // Insert into all systems
-        storage.lock().await.insert(doc).await?;
+        storage
+            .lock_timed(LockedResource::Storage)
+            .await
+            .insert(doc)
+            .await?;
         primary_index
-            .lock()
+            .lock_timed(LockedResource::PrimaryIndex)
             .await
             .insert(doc_id, doc_path.clone())
             .await?;
         trigram_index
-            .lock()
+            .lock_timed(LockedResource::TrigramIndex)
             .await
             .insert_with_content(doc_id, doc_path, &content)
             .await?;
 
         // Verify document exists in all systems
-        assert!(storage.lock().await.get(&doc_id).await?.is_some());
+        assert!(storage
+            .lock_timed(LockedResource::Storage)
+            .await
+            .get(&doc_id)
+            .await?
+            .is_some());
 
         // Use coordinated deletion
         let deleted = deletion_service.delete_document(&doc_id).await?;
         assert!(deleted, "Document should have been deleted");
 
         // Verify document is gone from all systems
-        assert!(storage.lock().await.get(&doc_id).await?.is_none());
+        assert!(storage
+            .lock_timed(LockedResource::Storage)
+            .await
+            .get(&doc_id)
+            .await?
+            .is_none());
 
         // Note: We can't easily verify index deletion without more complex test setup,
         // but the coordinated deletion service ensures they're called
@@ -0,0 +1,698 @@
+//! Non-code corpora searched alongside code
+//!
+//! Design docs, RFCs or support tickets are loaded in bulk into a named corpus. Each
+//! corpus declares a lightweight schema in `corpora.json` in the database directory: its
+//! fields, whether they hold prose (`text`) or exact values (`keyword`), and which keyword
+//! fields are facets. Documents are stored next to the indexed code under
+//! `corpora/<corpus>/<id>.md`, with the corpus, id and keyword fields in YAML frontmatter
+//! and the text fields as markdown sections, so the trigram index covers them like any
+//! other file. A `corpus:<name>` term in a search query keeps results to one corpus
+//! (`corpus:code` to the code), and `<facet>:<value>` terms filter on its facets.
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::metrics::performance::{LockedResource, TimedLock};
+use crate::pure::metadata::parse_frontmatter;
+use crate::services::{summary_by_path, DatabaseAccess};
+use crate::DocumentBuilder;
+
+/// File name of the corpus registry inside a database directory
+pub const CORPORA_FILE: &str = "corpora.json";
+
+/// Leading directory of corpus documents in storage
+pub const CORPORA_DIR: &str = "corpora";
+
+/// Corpus name selecting the indexed code in `corpus:` filters
+pub const CODE_CORPUS: &str = "code";
+
+/// Upper bound on documents loaded by one request
+pub const MAX_CORPUS_DOCUMENTS_PER_REQUEST: usize = 1_000;
+
+const MAX_NAME_LEN: usize = 64;
+const MAX_ID_LEN: usize = 128;
+const MAX_FIELDS: usize = 32;
+const MAX_DESCRIPTION_LEN: usize = 1024;
+
+/// What a field holds
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FieldKind {
+    /// Prose, searched as content
+    Text,
+    /// An exact value or list of values, e.g. a status or a team
+    Keyword,
+}
+
+/// A field of a corpus schema
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CorpusField {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub kind: FieldKind,
+    /// Whether `<name>:<value>` query terms filter on the field (keyword fields only)
+    #[serde(default)]
+    pub facet: bool,
+    /// Whether every document must set the field
+    #[serde(default)]
+    pub required: bool,
+}
+
+/// Schema of one corpus
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CorpusSchema {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub fields: Vec<CorpusField>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A document to load into a corpus
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CorpusDocument {
+    /// Identifier within the corpus; loading the same id again replaces the document
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub body: String,
+    /// Values of schema fields: a string, or a list of strings for keyword fields
+    #[serde(default)]
+    pub fields: BTreeMap<String, serde_json::Value>,
+}
+
+impl CorpusSchema {
+    /// Check the corpus name and field declarations
+    pub fn validate(&self) -> Result<()> {
+        validate_name("Corpus name", &self.name)?;
+        if self.name == CODE_CORPUS {
+            bail!(
+                "Corpus name '{}' is reserved for the indexed code",
+                CODE_CORPUS
+            );
+        }
+        if self
+            .description
+            .as_ref()
+            .is_some_and(|description| description.len() > MAX_DESCRIPTION_LEN)
+        {
+            bail!(
+                "Corpus description must be at most {} bytes",
+                MAX_DESCRIPTION_LEN
+            );
+        }
+        if self.fields.len() > MAX_FIELDS {
+            bail!("A corpus declares at most {} fields", MAX_FIELDS);
+        }
+        for (i, field) in self.fields.iter().enumerate() {
+            validate_name("Field name", &field.name)?;
+            if matches!(field.name.as_str(), "corpus" | "id" | "title") {
+                bail!("Field name '{}' is reserved", field.name);
+            }
+            if self.fields[..i]
+                .iter()
+                .any(|other| other.name == field.name)
+            {
+                bail!("Field '{}' is declared twice", field.name);
+            }
+            if field.facet && field.kind != FieldKind::Keyword {
+                bail!(
+                    "Field '{}' must be a keyword field to be a facet",
+                    field.name
+                );
+            }
+        }
+        Ok(())
+    }
+
+    pub fn field(&self, name: &str) -> Option<&CorpusField> {
+        self.fields.iter().find(|field| field.name == name)
+    }
+
+    /// Storage path of the document `id`
+    pub fn document_path(&self, id: &str) -> String {
+        format!("{}/{}/{}.md", CORPORA_DIR, self.name, id)
+    }
+
+    /// Check a document against the schema
+    pub fn validate_document(&self, document: &CorpusDocument) -> Result<()> {
+        let id = &document.id;
+        if id.is_empty()
+            || id.len() > MAX_ID_LEN
+            || id.starts_with('.')
+            || !id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        {
+            bail!(
+                "Document id '{}' must be 1-{} characters of letters, digits, '-', '_' or '.'",
+                id,
+                MAX_ID_LEN
+            );
+        }
+        for (name, value) in &document.fields {
+            let Some(field) = self.field(name) else {
+                bail!("Document '{}': unknown field '{}'", id, name);
+            };
+            let valid = match (field.kind, value) {
+                (_, serde_json::Value::String(_)) => true,
+                (FieldKind::Keyword, serde_json::Value::Array(values)) => {
+                    values.iter().all(serde_json::Value::is_string)
+                }
+                _ => false,
+            };
+            if !valid {
+                bail!(
+                    "Document '{}': field '{}' must be {}",
+                    id,
+                    name,
+                    match field.kind {
+                        FieldKind::Text => "a string",
+                        FieldKind::Keyword => "a string or a list of strings",
+                    }
+                );
+            }
+        }
+        if let Some(missing) = self
+            .fields
+            .iter()
+            .find(|field| field.required && !document.fields.contains_key(&field.name))
+        {
+            bail!(
+                "Document '{}': required field '{}' is missing",
+                id,
+                missing.name
+            );
+        }
+        Ok(())
+    }
+
+    /// Check a batch of documents loaded by one request
+    pub fn validate_batch(&self, documents: &[CorpusDocument]) -> Result<()> {
+        if documents.len() > MAX_CORPUS_DOCUMENTS_PER_REQUEST {
+            bail!(
+                "At most {} documents can be loaded per request",
+                MAX_CORPUS_DOCUMENTS_PER_REQUEST
+            );
+        }
+        for (i, document) in documents.iter().enumerate() {
+            if documents[..i].iter().any(|other| other.id == document.id) {
+                bail!("Document '{}' appears twice in the request", document.id);
+            }
+            self.validate_document(document)?;
+        }
+        Ok(())
+    }
+
+    /// Stored content of a document: frontmatter with the keyword fields, then the
+    /// body, then one section per text field
+    pub fn render(&self, document: &CorpusDocument) -> Result<String> {
+        let mut frontmatter = serde_yaml::Mapping::new();
+        frontmatter.insert("corpus".into(), self.name.clone().into());
+        frontmatter.insert("id".into(), document.id.clone().into());
+        let mut sections = String::new();
+        for field in &self.fields {
+            let Some(value) = document.fields.get(&field.name) else {
+                continue;
+            };
+            match field.kind {
+                FieldKind::Keyword => {
+                    frontmatter.insert(field.name.clone().into(), serde_yaml::to_value(value)?);
+                }
+                FieldKind::Text => {
+                    let text = value.as_str().unwrap_or_default();
+                    sections.push_str(&format!("\n## {}\n\n{}\n", field.name, text.trim_end()));
+                }
+            }
+        }
+        let mut content = format!("---\n{}---\n", serde_yaml::to_string(&frontmatter)?);
+        if let Some(title) = &document.title {
+            content.push_str(&format!("# {}\n\n", title));
+        }
+        content.push_str(document.body.trim_end());
+        content.push('\n');
+        content.push_str(&sections);
+        Ok(content)
+    }
+}
+
+/// Corpora defined for a database, as recorded in `corpora.json`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CorpusRegistry {
+    pub corpora: Vec<CorpusSchema>,
+}
+
+impl CorpusRegistry {
+    pub fn path_for(db_path: &Path) -> PathBuf {
+        db_path.join(CORPORA_FILE)
+    }
+
+    /// Load the registry; empty when no corpus has been defined
+    pub fn load(db_path: &Path) -> Result<Self> {
+        let path = Self::path_for(db_path);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read corpus registry: {}", path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse corpus registry: {}", path.display()))
+    }
+
+    /// Write the registry atomically
+    pub fn save(&self, db_path: &Path) -> Result<()> {
+        let path = Self::path_for(db_path);
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_vec_pretty(self)?)
+            .with_context(|| format!("Failed to write corpus registry: {}", tmp.display()))?;
+        std::fs::rename(&tmp, &path)
+            .with_context(|| format!("Failed to replace corpus registry: {}", path.display()))?;
+        Ok(())
+    }
+
+    pub fn get(&self, name: &str) -> Option<&CorpusSchema> {
+        self.corpora.iter().find(|schema| schema.name == name)
+    }
+
+    /// Add or replace the schema of a corpus; `true` when the corpus is new
+    pub fn upsert(&mut self, schema: CorpusSchema) -> Result<bool> {
+        schema.validate()?;
+        match self.corpora.iter_mut().find(|s| s.name == schema.name) {
+            Some(existing) => {
+                *existing = schema;
+                Ok(false)
+            }
+            None => {
+                self.corpora.push(schema);
+                self.corpora.sort_by(|a, b| a.name.cmp(&b.name));
+                Ok(true)
+            }
+        }
+    }
+}
+
+/// Corpus a stored document path belongs to; `None` for code
+pub fn corpus_of(path: &str) -> Option<&str> {
+    path.trim_start_matches("./")
+        .strip_prefix(CORPORA_DIR)?
+        .strip_prefix('/')?
+        .split('/')
+        .next()
+}
+
+/// Which documents a `corpus:` filter selects
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CorpusSelection {
+    /// The indexed code, i.e. everything outside `corpora/`
+    Code,
+    /// One named corpus
+    Corpus(String),
+}
+
+/// `corpus:` and facet terms taken from a search query
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CorpusFilter {
+    pub selection: CorpusSelection,
+    /// `(field, value)` pairs the document's keyword fields must contain
+    pub facets: Vec<(String, String)>,
+}
+
+impl CorpusFilter {
+    /// Split the `corpus:<name>` and `<facet>:<value>` terms off `query`
+    ///
+    /// Returns the remaining query and the filter, if the query selects a corpus. Facet
+    /// terms are only recognized for facets declared by the selected corpus; other
+    /// `word:word` terms stay in the query.
+    pub fn extract(query: &str, registry: &CorpusRegistry) -> Result<(String, Option<Self>)> {
+        let mut selection = None;
+        let mut rest = Vec::new();
+        for term in query.split_whitespace() {
+            match term.strip_prefix("corpus:") {
+                Some(name) => {
+                    if selection.is_some() {
+                        bail!("A query selects at most one corpus");
+                    }
+                    selection = Some(match name {
+                        CODE_CORPUS => CorpusSelection::Code,
+                        _ if registry.get(name).is_some() => {
+                            CorpusSelection::Corpus(name.to_string())
+                        }
+                        _ => {
+                            let known: Vec<&str> =
+                                registry.corpora.iter().map(|s| s.name.as_str()).collect();
+                            bail!(
+                                "Unknown corpus '{}'. Defined corpora: {}",
+                                name,
+                                if known.is_empty() {
+                                    "none".to_string()
+                                } else {
+                                    known.join(", ")
+                                }
+                            );
+                        }
+                    });
+                }
+                None => rest.push(term),
+            }
+        }
+        let Some(selection) = selection else {
+            return Ok((query.to_string(), None));
+        };
+
+        let schema = match &selection {
+            CorpusSelection::Corpus(name) => registry.get(name),
+            CorpusSelection::Code => None,
+        };
+        let mut facets = Vec::new();
+        rest.retain(|term| {
+            let facet = term.split_once(':').filter(|(field, value)| {
+                !value.is_empty()
+                    && schema
+                        .and_then(|schema| schema.field(field))
+                        .is_some_and(|field| field.facet)
+            });
+            match facet {
+                Some((field, value)) => {
+                    facets.push((field.to_string(), value.to_string()));
+                    false
+                }
+                None => true,
+            }
+        });
+        Ok((rest.join(" "), Some(Self { selection, facets })))
+    }
+
+    /// [`CorpusFilter::extract`] against the registry of the database at `db_path`,
+    /// which is only read when the query has a `corpus:` term
+    pub fn from_query(db_path: &Path, query: &str) -> Result<(String, Option<Self>)> {
+        if !query
+            .split_whitespace()
+            .any(|term| term.starts_with("corpus:"))
+        {
+            return Ok((query.to_string(), None));
+        }
+        Self::extract(query, &CorpusRegistry::load(db_path)?)
+    }
+
+    pub fn has_facets(&self) -> bool {
+        !self.facets.is_empty()
+    }
+
+    /// Whether a stored document path belongs to the selected corpus
+    pub fn matches_path(&self, path: &str) -> bool {
+        match &self.selection {
+            CorpusSelection::Code => corpus_of(path).is_none(),
+            CorpusSelection::Corpus(name) => corpus_of(path) == Some(name.as_str()),
+        }
+    }
+
+    /// Whether a stored document belongs to the selected corpus and has every facet value
+    pub fn matches_document(&self, path: &str, content: &[u8]) -> bool {
+        if !self.matches_path(path) {
+            return false;
+        }
+        if self.facets.is_empty() {
+            return true;
+        }
+        let Some(frontmatter) = parse_frontmatter(&String::from_utf8_lossy(content)) else {
+            return false;
+        };
+        self.facets.iter().all(|(field, wanted)| {
+            let matches = |value: &serde_yaml::Value| {
+                value
+                    .as_str()
+                    .is_some_and(|value| value.eq_ignore_ascii_case(wanted))
+            };
+            match frontmatter.get(field) {
+                Some(serde_yaml::Value::Sequence(values)) => values.iter().any(matches),
+                Some(value) => matches(value),
+                None => false,
+            }
+        })
+    }
+}
+
+/// Documents written by [`load_documents`]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct CorpusLoadResult {
+    pub corpus: String,
+    pub created: usize,
+    pub updated: usize,
+}
+
+/// Validate `documents` against `schema` and write them to storage and both indices
+///
+/// Nothing is written when any document is invalid. A document whose id is already
+/// stored in the corpus replaces the stored one.
+pub async fn load_documents(
+    database: &dyn DatabaseAccess,
+    schema: &CorpusSchema,
+    documents: &[CorpusDocument],
+) -> Result<CorpusLoadResult> {
+    schema.validate_batch(documents)?;
+
+    let (storage, primary_index, trigram_index, path_cache) = (
+        database.storage(),
+        database.primary_index(),
+        database.trigram_index(),
+        database.path_cache(),
+    );
+    let mut result = CorpusLoadResult {
+        corpus: schema.name.clone(),
+        ..CorpusLoadResult::default()
+    };
+    for document in documents {
+        let path = schema.document_path(&document.id);
+        let content = schema.render(document)?;
+        let existing = summary_by_path(&storage, &primary_index, &path_cache, &path)
+            .await?
+            .filter(|(_, summary)| summary.path.as_str() == path)
+            .map(|(id, _)| id);
+        // Replaced documents are removed and stored afresh, so a document reloaded
+        // within the same second doesn't trip the update timestamp check
+        if let Some(old_id) = existing {
+            storage
+                .lock_timed(LockedResource::Storage)
+                .await
+                .delete(&old_id)
+                .await?;
+            primary_index
+                .lock_timed(LockedResource::PrimaryIndex)
+                .await
+                .delete(&old_id)
+                .await?;
+            trigram_index
+                .lock_timed(LockedResource::TrigramIndex)
+                .await
+                .delete(&old_id)
+                .await?;
+            result.updated += 1;
+        } else {
+            result.created += 1;
+        }
+        let stored = DocumentBuilder::new()
+            .path(&path)?
+            .title(
+                document
+                    .title
+                    .clone()
+                    .unwrap_or_else(|| document.id.clone()),
+            )?
+            .content(content.as_bytes())
+            .build()?;
+        let (id, stored_path) = (stored.id, stored.path.clone());
+        storage
+            .lock_timed(LockedResource::Storage)
+            .await
+            .insert(stored)
+            .await?;
+        primary_index
+            .lock_timed(LockedResource::PrimaryIndex)
+            .await
+            .insert(id, stored_path.clone())
+            .await?;
+        trigram_index
+            .lock_timed(LockedResource::TrigramIndex)
+            .await
+            .insert_with_content(id, stored_path, content.as_bytes())
+            .await?;
+        path_cache.write().await.insert(path, id);
+    }
+    storage
+        .lock_timed(LockedResource::Storage)
+        .await
+        .flush()
+        .await?;
+    primary_index
+        .lock_timed(LockedResource::PrimaryIndex)
+        .await
+        .flush()
+        .await?;
+    trigram_index
+        .lock_timed(LockedResource::TrigramIndex)
+        .await
+        .flush()
+        .await?;
+    Ok(result)
+}
+
+fn validate_name(what: &str, name: &str) -> Result<()> {
+    if name.is_empty()
+        || name.len() > MAX_NAME_LEN
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || matches!(c, '-' | '_'))
+    {
+        bail!(
+            "{} '{}' must be 1-{} characters of lowercase letters, digits, '-' or '_'",
+            what,
+            name,
+            MAX_NAME_LEN
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn rfcs() -> CorpusSchema {
+        CorpusSchema {
+            name: "rfcs".to_string(),
+            description: None,
+            fields: vec![
+                CorpusField {
+                    name: "status".to_string(),
+                    kind: FieldKind::Keyword,
+                    facet: true,
+                    required: true,
+                },
+                CorpusField {
+                    name: "teams".to_string(),
+                    kind: FieldKind::Keyword,
+                    facet: true,
+                    required: false,
+                },
+                CorpusField {
+                    name: "motivation".to_string(),
+                    kind: FieldKind::Text,
+                    facet: false,
+                    required: false,
+                },
+            ],
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn rfc(id: &str, fields: serde_json::Value) -> CorpusDocument {
+        CorpusDocument {
+            id: id.to_string(),
+            title: Some("Streaming exports".to_string()),
+            body: "Export the symbol table as NDJSON.".to_string(),
+            fields: serde_json::from_value(fields).unwrap(),
+        }
+    }
+
+    #[test]
+    fn validates_schemas_and_documents() {
+        let mut registry = CorpusRegistry::default();
+        assert!(registry.upsert(rfcs()).unwrap());
+        assert!(!registry.upsert(rfcs()).unwrap());
+
+        let mut reserved = rfcs();
+        reserved.name = "code".to_string();
+        assert!(registry.upsert(reserved).is_err());
+        let mut text_facet = rfcs();
+        text_facet.fields[2].facet = true;
+        assert!(text_facet.validate().is_err());
+
+        let schema = rfcs();
+        assert!(schema
+            .validate_document(&rfc(
+                "0042",
+                json!({"status": "accepted", "teams": ["storage"]})
+            ))
+            .is_ok());
+        assert!(schema
+            .validate_document(&rfc("0042", json!({"teams": "storage"})))
+            .is_err());
+        assert!(schema
+            .validate_document(&rfc("0042", json!({"status": "draft", "owner": "kim"})))
+            .is_err());
+        assert!(schema
+            .validate_document(&rfc(
+                "0042",
+                json!({"status": "draft", "motivation": ["a"]})
+            ))
+            .is_err());
+        assert!(schema
+            .validate_document(&rfc("../x", json!({"status": "draft"})))
+            .is_err());
+    }
+
+    #[test]
+    fn extracts_corpus_and_facet_terms() {
+        let registry = CorpusRegistry {
+            corpora: vec![rfcs()],
+        };
+        let (query, filter) =
+            CorpusFilter::extract("export corpus:rfcs status:accepted std::io", &registry).unwrap();
+        let filter = filter.unwrap();
+        assert_eq!(query, "export std::io");
+        assert_eq!(
+            filter.selection,
+            CorpusSelection::Corpus("rfcs".to_string())
+        );
+        assert_eq!(
+            filter.facets,
+            [("status".to_string(), "accepted".to_string())]
+        );
+
+        let (query, filter) = CorpusFilter::extract("status:draft", &registry).unwrap();
+        assert_eq!(query, "status:draft");
+        assert!(filter.is_none());
+
+        let (_, filter) = CorpusFilter::extract("parse corpus:code", &registry).unwrap();
+        let filter = filter.unwrap();
+        assert!(filter.matches_path("repos/app/files/src/lib.rs"));
+        assert!(!filter.matches_path("corpora/rfcs/0042.md"));
+
+        let error = CorpusFilter::extract("corpus:tickets", &registry).unwrap_err();
+        assert!(error.to_string().contains("Defined corpora: rfcs"));
+    }
+
+    #[test]
+    fn rendered_documents_match_facets() {
+        let schema = rfcs();
+        let document = rfc(
+            "0042",
+            json!({"status": "Accepted", "teams": ["storage", "api"], "motivation": "Mirrors."}),
+        );
+        let content = schema.render(&document).unwrap();
+        assert!(content.starts_with("---\n"));
+        assert!(content.contains("# Streaming exports\n\nExport the symbol table"));
+        assert!(content.contains("\n## motivation\n\nMirrors.\n"));
+
+        let path = schema.document_path("0042");
+        assert_eq!(corpus_of(&path), Some("rfcs"));
+        let filter = |facets: &[(&str, &str)]| CorpusFilter {
+            selection: CorpusSelection::Corpus("rfcs".to_string()),
+            facets: facets
+                .iter()
+                .map(|(f, v)| (f.to_string(), v.to_string()))
+                .collect(),
+        };
+        let bytes = content.as_bytes();
+        assert!(filter(&[]).matches_document(&path, bytes));
+        assert!(filter(&[("status", "accepted"), ("teams", "api")]).matches_document(&path, bytes));
+        assert!(!filter(&[("teams", "web")]).matches_document(&path, bytes));
+        assert!(!filter(&[]).matches_document("corpora/tickets/1.md", bytes));
+    }
+}
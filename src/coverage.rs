@@ -0,0 +1,573 @@
+//! Symbol-level test coverage
+//!
+//! `kotadb import-coverage` reads an lcov or Cobertura report, maps its line hits onto
+//! the line ranges of the functions and methods in the symbol database and stores the
+//! result as `symbol_coverage.json` in the database directory. Impact analysis reports
+//! how many impacted symbols no test executes, and the codebase overview shows the
+//! measured coverage.
+//!
+//! Report paths rarely match indexed paths exactly (CI checkouts are absolute, indexes are
+//! often relative), so a report file matches a symbol's file when either path ends with the
+//! other at a component boundary.
+
+use anyhow::{bail, Context, Result};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// File name of the imported coverage inside a database directory
+pub const COVERAGE_FILE: &str = "symbol_coverage.json";
+
+static XML_ELEMENT: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"<(class|line)\s([^>]*)>").expect("valid element pattern"));
+static XML_ATTRIBUTE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"([\w-]+)\s*=\s*"([^"]*)""#).expect("valid attribute pattern"));
+
+/// Format of a coverage report
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReportFormat {
+    #[default]
+    Lcov,
+    Cobertura,
+}
+
+impl ReportFormat {
+    /// Cobertura reports are XML; anything else is read as lcov tracefile data
+    pub fn detect(content: &str) -> Self {
+        if content.trim_start().starts_with('<') {
+            ReportFormat::Cobertura
+        } else {
+            ReportFormat::Lcov
+        }
+    }
+}
+
+impl FromStr for ReportFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "lcov" => Ok(ReportFormat::Lcov),
+            "cobertura" | "xml" => Ok(ReportFormat::Cobertura),
+            other => bail!(
+                "Unknown coverage format '{}': expected lcov or cobertura",
+                other
+            ),
+        }
+    }
+}
+
+/// Hit counts per instrumented line of each file in a report
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LineCoverage {
+    pub files: BTreeMap<String, BTreeMap<u32, u64>>,
+}
+
+impl LineCoverage {
+    pub fn parse(content: &str, format: ReportFormat) -> Result<Self> {
+        let coverage = match format {
+            ReportFormat::Lcov => Self::parse_lcov(content),
+            ReportFormat::Cobertura => Self::parse_cobertura(content),
+        };
+        if coverage.files.is_empty() {
+            bail!("Coverage report contains no line data");
+        }
+        Ok(coverage)
+    }
+
+    /// `SF:` starts a file record and `DA:<line>,<hits>` records a line
+    fn parse_lcov(content: &str) -> Self {
+        let mut coverage = Self::default();
+        let mut current: Option<String> = None;
+        for line in content.lines().map(str::trim) {
+            if let Some(path) = line.strip_prefix("SF:") {
+                current = Some(normalize(path));
+            } else if line == "end_of_record" {
+                current = None;
+            } else if let (Some(path), Some(data)) = (&current, line.strip_prefix("DA:")) {
+                let mut fields = data.split(',');
+                let line_number = fields.next().and_then(|v| v.trim().parse::<u32>().ok());
+                let hits = fields.next().and_then(|v| v.trim().parse::<u64>().ok());
+                if let (Some(line_number), Some(hits)) = (line_number, hits) {
+                    coverage.record(path, line_number, hits);
+                }
+            }
+        }
+        coverage
+    }
+
+    /// `<line number=".." hits=".."/>` elements belong to the enclosing `<class filename="..">`
+    fn parse_cobertura(content: &str) -> Self {
+        let mut coverage = Self::default();
+        let mut current: Option<String> = None;
+        for element in XML_ELEMENT.captures_iter(content) {
+            let attributes: HashMap<&str, &str> = XML_ATTRIBUTE
+                .captures_iter(&element[2])
+                .filter_map(|a| Some((a.get(1)?.as_str(), a.get(2)?.as_str())))
+                .collect();
+            match &element[1] {
+                "class" => current = attributes.get("filename").map(|path| normalize(path)),
+                _ => {
+                    let line_number = attributes.get("number").and_then(|v| v.parse().ok());
+                    let hits = attributes.get("hits").and_then(|v| v.parse().ok());
+                    if let (Some(path), Some(line_number), Some(hits)) =
+                        (&current, line_number, hits)
+                    {
+                        coverage.record(path, line_number, hits);
+                    }
+                }
+            }
+        }
+        coverage
+    }
+
+    /// Lines listed twice (e.g. under a Cobertura class and its method) keep the higher count
+    fn record(&mut self, path: &str, line_number: u32, hits: u64) {
+        let lines = self.files.entry(path.to_string()).or_default();
+        let entry = lines.entry(line_number).or_insert(0);
+        *entry = (*entry).max(hits);
+    }
+}
+
+/// A function or method whose line range is measured
+#[derive(Debug, Clone, Copy)]
+pub struct CoverableSymbol<'a> {
+    pub name: &'a str,
+    pub file_path: &'a str,
+    pub start_line: u32,
+    pub end_line: u32,
+}
+
+/// Instrumented and executed lines of one symbol
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SymbolCoverage {
+    pub name: String,
+    pub file_path: String,
+    pub start_line: u32,
+    pub end_line: u32,
+    pub lines_total: u32,
+    pub lines_covered: u32,
+}
+
+impl SymbolCoverage {
+    /// Whether any test executed the symbol
+    pub fn is_tested(&self) -> bool {
+        self.lines_covered > 0
+    }
+
+    /// Covered share of the symbol's instrumented lines, in percent
+    pub fn percent(&self) -> f64 {
+        percentage(self.lines_covered as usize, self.lines_total as usize)
+    }
+}
+
+/// How many of a set of symbols tests execute
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct CoverageSummary {
+    /// Symbols with coverage data
+    pub measured: usize,
+    pub tested: usize,
+    pub untested: usize,
+    /// Symbols of the set missing from the imported coverage
+    pub unmeasured: usize,
+    /// Untested share of the measured symbols, in percent
+    pub untested_percent: f64,
+    /// Covered share of the measured symbols' instrumented lines, in percent
+    pub line_percent: f64,
+}
+
+impl CoverageSummary {
+    fn from_symbols<'a>(
+        symbols: impl IntoIterator<Item = &'a SymbolCoverage>,
+        unmeasured: usize,
+    ) -> Self {
+        let mut summary = Self {
+            unmeasured,
+            ..Self::default()
+        };
+        let (mut lines_total, mut lines_covered) = (0usize, 0usize);
+        for symbol in symbols {
+            summary.measured += 1;
+            if symbol.is_tested() {
+                summary.tested += 1;
+            } else {
+                summary.untested += 1;
+            }
+            lines_total += symbol.lines_total as usize;
+            lines_covered += symbol.lines_covered as usize;
+        }
+        summary.untested_percent = percentage(summary.untested, summary.measured);
+        summary.line_percent = percentage(lines_covered, lines_total);
+        summary
+    }
+}
+
+/// Per-symbol coverage imported from a report
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CoverageStore {
+    /// RFC 3339 time of the import
+    pub imported_at: String,
+    /// Report the coverage was read from
+    pub source: String,
+    pub format: ReportFormat,
+    pub files_in_report: usize,
+    /// Report files matched to indexed symbols
+    pub files_matched: usize,
+    pub symbols: Vec<SymbolCoverage>,
+    #[serde(skip)]
+    by_name: HashMap<String, Vec<usize>>,
+}
+
+impl CoverageStore {
+    /// Measure `symbols` against the line hits of a report
+    ///
+    /// Symbols whose file is not in the report, or whose range holds no instrumented
+    /// line, are left out.
+    pub fn build<'a>(
+        report: &LineCoverage,
+        symbols: impl IntoIterator<Item = CoverableSymbol<'a>>,
+        source: impl Into<String>,
+        format: ReportFormat,
+    ) -> Self {
+        let matcher = PathMatcher::new(report.files.keys().map(String::as_str));
+        let mut matched_files = HashSet::new();
+        let mut measured = Vec::new();
+        for symbol in symbols {
+            let Some(report_path) = matcher.find(&normalize(symbol.file_path)) else {
+                continue;
+            };
+            let lines = &report.files[report_path];
+            let range = symbol.start_line..=symbol.end_line.max(symbol.start_line);
+            let (total, covered) = lines
+                .range(range)
+                .fold((0u32, 0u32), |(total, covered), (_, hits)| {
+                    (total + 1, covered + u32::from(*hits > 0))
+                });
+            if total == 0 {
+                continue;
+            }
+            matched_files.insert(report_path);
+            measured.push(SymbolCoverage {
+                name: symbol.name.to_string(),
+                file_path: symbol.file_path.to_string(),
+                start_line: symbol.start_line,
+                end_line: symbol.end_line,
+                lines_total: total,
+                lines_covered: covered,
+            });
+        }
+
+        let mut store = Self {
+            imported_at: chrono::Utc::now().to_rfc3339(),
+            source: source.into(),
+            format,
+            files_in_report: report.files.len(),
+            files_matched: matched_files.len(),
+            symbols: measured,
+            by_name: HashMap::new(),
+        };
+        store.index();
+        store
+    }
+
+    /// Location of the imported coverage for a database directory
+    pub fn path_for(db_path: &Path) -> PathBuf {
+        db_path.join(COVERAGE_FILE)
+    }
+
+    /// The imported coverage of a database, if any was imported
+    pub fn load(db_path: &Path) -> Result<Option<Self>> {
+        let path = Self::path_for(db_path);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read coverage: {}", path.display()))?;
+        let mut store: Self = serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse coverage: {}", path.display()))?;
+        store.index();
+        Ok(Some(store))
+    }
+
+    /// Save the coverage atomically into a database directory, replacing earlier imports
+    pub fn save(&self, db_path: &Path) -> Result<()> {
+        std::fs::create_dir_all(db_path)
+            .with_context(|| format!("Failed to create {}", db_path.display()))?;
+        let path = Self::path_for(db_path);
+        let tmp_path = path.with_extension("json.tmp");
+        std::fs::write(&tmp_path, serde_json::to_string(self)?)
+            .with_context(|| format!("Failed to write coverage: {}", tmp_path.display()))?;
+        std::fs::rename(&tmp_path, &path)
+            .with_context(|| format!("Failed to replace coverage: {}", path.display()))?;
+        Ok(())
+    }
+
+    /// Coverage of the symbol `name` in `file_path`, preferring the one spanning `line`
+    pub fn lookup(
+        &self,
+        name: &str,
+        file_path: &str,
+        line: Option<u32>,
+    ) -> Option<&SymbolCoverage> {
+        let file_path = normalize(file_path);
+        let mut candidates = self
+            .by_name
+            .get(name)?
+            .iter()
+            .map(|&i| &self.symbols[i])
+            .filter(|symbol| same_file(&normalize(&symbol.file_path), &file_path))
+            .peekable();
+        let first = *candidates.peek()?;
+        Some(
+            line.and_then(|line| candidates.find(|s| (s.start_line..=s.end_line).contains(&line)))
+                .unwrap_or(first),
+        )
+    }
+
+    /// Coverage of every measured symbol
+    pub fn summary(&self) -> CoverageSummary {
+        CoverageSummary::from_symbols(&self.symbols, 0)
+    }
+
+    /// Coverage of the symbols at `sites`, given as `(name, file, line)`
+    pub fn summarize<'s>(
+        &self,
+        sites: impl IntoIterator<Item = (&'s str, &'s str, Option<u32>)>,
+    ) -> CoverageSummary {
+        let mut seen = HashSet::new();
+        let mut unmeasured = HashSet::new();
+        let mut measured = Vec::new();
+        for (name, file_path, line) in sites {
+            match self.lookup(name, file_path, line) {
+                Some(symbol) => {
+                    if seen.insert((symbol.file_path.as_str(), symbol.start_line, name)) {
+                        measured.push(symbol);
+                    }
+                }
+                None => {
+                    unmeasured.insert((name, file_path));
+                }
+            }
+        }
+        CoverageSummary::from_symbols(measured, unmeasured.len())
+    }
+
+    fn index(&mut self) {
+        self.by_name.clear();
+        for (i, symbol) in self.symbols.iter().enumerate() {
+            self.by_name.entry(symbol.name.clone()).or_default().push(i);
+        }
+    }
+}
+
+/// Matches indexed file paths to report paths
+struct PathMatcher<'a> {
+    /// Every component-boundary suffix of the report paths; `None` when several share it
+    suffixes: HashMap<&'a str, Option<&'a str>>,
+    paths: HashSet<&'a str>,
+}
+
+impl<'a> PathMatcher<'a> {
+    fn new(paths: impl Iterator<Item = &'a str>) -> Self {
+        let mut matcher = Self {
+            suffixes: HashMap::new(),
+            paths: HashSet::new(),
+        };
+        for path in paths {
+            matcher.paths.insert(path);
+            for suffix in suffixes(path) {
+                matcher
+                    .suffixes
+                    .entry(suffix)
+                    .and_modify(|owner| {
+                        if *owner != Some(path) {
+                            *owner = None;
+                        }
+                    })
+                    .or_insert(Some(path));
+            }
+        }
+        matcher
+    }
+
+    /// Report path naming the same file as `path`
+    fn find(&self, path: &str) -> Option<&'a str> {
+        if let Some(owner) = self.suffixes.get(path) {
+            return *owner;
+        }
+        suffixes(path).find_map(|suffix| self.paths.get(suffix).copied())
+    }
+}
+
+/// `path` and its suffixes starting after each `/`, longest first
+fn suffixes(path: &str) -> impl Iterator<Item = &str> {
+    std::iter::once(path).chain(
+        path.match_indices('/')
+            .map(move |(i, _)| &path[i + 1..])
+            .filter(|suffix| !suffix.is_empty()),
+    )
+}
+
+fn same_file(a: &str, b: &str) -> bool {
+    a == b || a.ends_with(&format!("/{}", b)) || b.ends_with(&format!("/{}", a))
+}
+
+fn normalize(path: &str) -> String {
+    let path = path.trim().replace('\\', "/");
+    path.trim_start_matches("./")
+        .trim_start_matches('/')
+        .to_string()
+}
+
+fn percentage(part: usize, whole: usize) -> f64 {
+    if whole == 0 {
+        0.0
+    } else {
+        (part as f64 * 1000.0 / whole as f64).round() / 10.0
+    }
+}
+
+/// Import a report into a database, measuring the functions and methods of its symbol database
+#[cfg(feature = "tree-sitter-parsing")]
+pub fn import_report(
+    db_path: &Path,
+    report_path: &Path,
+    format: Option<ReportFormat>,
+) -> Result<CoverageStore> {
+    use crate::binary_symbols::BinarySymbolReader;
+    use crate::parsing::SymbolType;
+
+    let content = std::fs::read_to_string(report_path)
+        .with_context(|| format!("Failed to read coverage report: {}", report_path.display()))?;
+    let format = format.unwrap_or_else(|| ReportFormat::detect(&content));
+    let report = LineCoverage::parse(&content, format)?;
+
+    let symbol_db_path = db_path.join("symbols.kota");
+    if !symbol_db_path.exists() {
+        bail!("No symbol database found; index a codebase with symbol extraction first");
+    }
+    let reader = BinarySymbolReader::open(&symbol_db_path)?;
+    let mut symbols = Vec::new();
+    for symbol in reader.iter_symbols() {
+        if !matches!(
+            SymbolType::try_from(symbol.kind),
+            Ok(SymbolType::Function | SymbolType::Method)
+        ) {
+            continue;
+        }
+        let (Ok(name), Ok(file_path)) = (
+            reader.get_symbol_name(&symbol),
+            reader.get_symbol_file_path(&symbol),
+        ) else {
+            continue;
+        };
+        symbols.push((name, file_path, symbol.start_line, symbol.end_line));
+    }
+
+    let store = CoverageStore::build(
+        &report,
+        symbols
+            .iter()
+            .map(|(name, file_path, start_line, end_line)| CoverableSymbol {
+                name,
+                file_path,
+                start_line: *start_line,
+                end_line: *end_line,
+            }),
+        report_path.display().to_string(),
+        format,
+    );
+    store.save(db_path)?;
+    Ok(store)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LCOV: &str = "TN:\nSF:/ci/checkout/src/lib.rs\nDA:1,1\nDA:2,4\nDA:3,0\nDA:10,0\nDA:11,0\nend_of_record\n";
+
+    fn symbols() -> Vec<CoverableSymbol<'static>> {
+        vec![
+            CoverableSymbol {
+                name: "parse",
+                file_path: "src/lib.rs",
+                start_line: 1,
+                end_line: 5,
+            },
+            CoverableSymbol {
+                name: "render",
+                file_path: "src/lib.rs",
+                start_line: 9,
+                end_line: 12,
+            },
+            CoverableSymbol {
+                name: "main",
+                file_path: "src/main.rs",
+                start_line: 1,
+                end_line: 3,
+            },
+        ]
+    }
+
+    #[test]
+    fn line_hits_map_onto_symbol_ranges() -> Result<()> {
+        let report = LineCoverage::parse(LCOV, ReportFormat::detect(LCOV))?;
+        let store = CoverageStore::build(&report, symbols(), "lcov.info", ReportFormat::Lcov);
+
+        assert_eq!(store.files_matched, 1);
+        let parse = store.lookup("parse", "./src/lib.rs", None).unwrap();
+        assert_eq!((parse.lines_covered, parse.lines_total), (2, 3));
+        assert!(!store
+            .lookup("render", "src/lib.rs", Some(10))
+            .unwrap()
+            .is_tested());
+        assert!(store.lookup("main", "src/main.rs", None).is_none());
+
+        let impacted = store.summarize([
+            ("parse", "src/lib.rs", Some(2)),
+            ("render", "src/lib.rs", Some(10)),
+            ("main", "src/main.rs", Some(1)),
+        ]);
+        assert_eq!((impacted.measured, impacted.untested), (2, 1));
+        assert_eq!(impacted.unmeasured, 1);
+        assert_eq!(impacted.untested_percent, 50.0);
+        assert_eq!(store.summary().line_percent, 40.0);
+        Ok(())
+    }
+
+    #[test]
+    fn cobertura_reports_are_read_per_class() -> Result<()> {
+        let xml = r#"<?xml version="1.0" ?>
+<coverage line-rate="0.5">
+  <sources><source>/ci/checkout</source></sources>
+  <packages><package name="src"><classes>
+    <class name="lib" filename="src/lib.rs" line-rate="0.5">
+      <methods><method name="parse"><lines><line number="1" hits="3"/></lines></method></methods>
+      <lines>
+        <line number="1" hits="3"/>
+        <line number="2" hits="0" branch="false"/>
+      </lines>
+    </class>
+  </classes></package></packages>
+</coverage>"#;
+        let report = LineCoverage::parse(xml, ReportFormat::detect(xml))?;
+        assert_eq!(report.files["src/lib.rs"], BTreeMap::from([(1, 3), (2, 0)]));
+        assert!(LineCoverage::parse("TN:\n", ReportFormat::Lcov).is_err());
+        assert!("jacoco".parse::<ReportFormat>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn ambiguous_file_names_do_not_match() {
+        let matcher = PathMatcher::new(["a/lib.rs", "b/lib.rs", "/abs/src/main.rs"].into_iter());
+        assert_eq!(matcher.find("lib.rs"), None);
+        assert_eq!(matcher.find("repo/a/lib.rs"), Some("a/lib.rs"));
+        assert_eq!(matcher.find("src/main.rs"), Some("/abs/src/main.rs"));
+        assert_eq!(matcher.find("c/lib.rs"), None);
+    }
+}
@@ -0,0 +1,346 @@
+//! Crash reports for panics in the CLI and servers
+//!
+//! [`install_panic_hook`] chains a panic hook that, on the first panic of the process,
+//! writes a JSON report to `crash-reports/` in the database directory and tells the
+//! user how to attach it to an issue. A report holds what is needed to reproduce a
+//! panic or corruption bug without access to the user's code: the backtrace, the
+//! command with its argument values left out, file counts and sizes of each database
+//! component, and the format versions found in the headers of the binary index files
+//! next to the versions this build expects. The home directory is replaced by `~` in
+//! messages and backtraces, and document contents and names are never included.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Directory inside the database directory that holds crash reports
+pub const CRASH_REPORTS_DIR: &str = "crash-reports";
+
+/// Where users are asked to file crash reports
+pub const ISSUES_URL: &str = "https://github.com/jayminwest/kota-db/issues/new";
+
+/// Longest panic message kept in a report
+const MAX_MESSAGE_LEN: usize = 4096;
+
+/// Directory levels below the database directory searched for index files
+const MAX_SCAN_DEPTH: usize = 4;
+
+/// Directories left out of the component statistics and format scan
+const SKIPPED_DIRS: &[&str] = &[CRASH_REPORTS_DIR, "snapshots"];
+
+/// Set once a report was written, so a panic storm in a server yields one report
+static REPORTED: AtomicBool = AtomicBool::new(false);
+
+/// Everything recorded about one panic
+#[derive(Debug, Clone, Serialize)]
+pub struct CrashReport {
+    pub created_at: DateTime<Utc>,
+    pub kotadb_version: String,
+    pub os: String,
+    pub arch: String,
+    /// Subcommand and flag names; argument values are left out
+    pub command: String,
+    pub thread: Option<String>,
+    pub message: String,
+    /// `file:line:column` of the panic
+    pub location: Option<String>,
+    pub backtrace: String,
+    pub database: DatabaseSummary,
+}
+
+/// Shape of the database at the time of the crash
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DatabaseSummary {
+    pub exists: bool,
+    /// Files and bytes per top-level entry of the database directory
+    pub components: BTreeMap<String, ComponentStats>,
+    /// Versioned binary files and the format versions in their headers
+    pub formats: Vec<FileFormat>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ComponentStats {
+    pub files: u64,
+    pub bytes: u64,
+}
+
+/// Header of a binary index file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FileFormat {
+    /// Path relative to the database directory
+    pub file: String,
+    pub bytes: u64,
+    /// Magic bytes, when the header starts with them
+    pub magic: Option<String>,
+    /// Version stored after the magic bytes
+    pub version: Option<u32>,
+    /// Version this build writes, for files it knows
+    pub expected_version: Option<u32>,
+}
+
+/// Format versions this build writes, by file name
+fn expected_version(file_name: &str) -> Option<u32> {
+    match file_name {
+        "trigrams.bin" => Some(crate::binary_trigram_index::BINARY_FORMAT_VERSION),
+        crate::leaf_pages::LEAF_PAGES_FILE => Some(crate::leaf_pages::VERSION),
+        #[cfg(feature = "tree-sitter-parsing")]
+        "symbols.kota" => Some(crate::binary_symbols::FORMAT_VERSION),
+        #[cfg(feature = "tree-sitter-parsing")]
+        "dependency_graph.bin" => Some(crate::dependency_extractor::GRAPH_FILE_VERSION),
+        _ => None,
+    }
+}
+
+impl CrashReport {
+    /// Report a panic; `command` should already be stripped of argument values
+    pub fn capture(db_path: &Path, command: &str, info: &std::panic::PanicHookInfo) -> Self {
+        let message = panic_message(info);
+        let backtrace = std::backtrace::Backtrace::force_capture().to_string();
+        Self {
+            created_at: Utc::now(),
+            kotadb_version: env!("CARGO_PKG_VERSION").to_string(),
+            os: std::env::consts::OS.to_string(),
+            arch: std::env::consts::ARCH.to_string(),
+            command: command.to_string(),
+            thread: std::thread::current().name().map(str::to_string),
+            message: truncate(&sanitize(&message), MAX_MESSAGE_LEN),
+            location: info
+                .location()
+                .map(|l| format!("{}:{}:{}", l.file(), l.line(), l.column())),
+            backtrace: sanitize(&backtrace),
+            database: DatabaseSummary::collect(db_path),
+        }
+    }
+
+    /// Write the report to `crash-reports/` of the database directory
+    pub fn save(&self, db_path: &Path) -> Result<PathBuf> {
+        let dir = db_path.join(CRASH_REPORTS_DIR);
+        std::fs::create_dir_all(&dir)
+            .with_context(|| format!("Failed to create {}", dir.display()))?;
+        let path = dir.join(format!(
+            "crash-{}-{}.json",
+            self.created_at.format("%Y%m%dT%H%M%SZ"),
+            std::process::id()
+        ));
+        std::fs::write(&path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write crash report: {}", path.display()))?;
+        Ok(path)
+    }
+}
+
+impl DatabaseSummary {
+    /// Summarize the database directory without opening the database
+    pub fn collect(db_path: &Path) -> Self {
+        let mut summary = Self {
+            exists: db_path.is_dir(),
+            ..Self::default()
+        };
+        let Ok(entries) = std::fs::read_dir(db_path) else {
+            return summary;
+        };
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if SKIPPED_DIRS.contains(&name.as_str()) {
+                continue;
+            }
+            let stats = summary.components.entry(name).or_default();
+            scan(db_path, &entry.path(), 0, stats, &mut summary.formats);
+        }
+        summary.formats.sort_by(|a, b| a.file.cmp(&b.file));
+        summary
+    }
+}
+
+/// Count files below `path` and record the headers of binary index files
+fn scan(
+    db_path: &Path,
+    path: &Path,
+    depth: usize,
+    stats: &mut ComponentStats,
+    formats: &mut Vec<FileFormat>,
+) {
+    let Ok(metadata) = std::fs::symlink_metadata(path) else {
+        return;
+    };
+    if metadata.is_dir() {
+        if depth >= MAX_SCAN_DEPTH {
+            return;
+        }
+        if let Ok(entries) = std::fs::read_dir(path) {
+            for entry in entries.flatten() {
+                scan(db_path, &entry.path(), depth + 1, stats, formats);
+            }
+        }
+        return;
+    }
+    stats.files += 1;
+    stats.bytes += metadata.len();
+    if matches!(
+        path.extension().and_then(|e| e.to_str()),
+        Some("bin" | "kota")
+    ) {
+        formats.push(read_format(db_path, path, metadata.len()));
+    }
+}
+
+/// Read the magic bytes and version from a file header
+///
+/// Index files start with 4 or 8 ASCII magic bytes followed by a little-endian `u32`
+/// version; files that don't are reported with their size only.
+fn read_format(db_path: &Path, path: &Path, bytes: u64) -> FileFormat {
+    let mut header = Vec::with_capacity(12);
+    if let Ok(file) = std::fs::File::open(path) {
+        let _ = file.take(12).read_to_end(&mut header);
+    }
+    let magic_len = [8, 4].into_iter().find(|&len| {
+        header.len() >= len + 4 && header[..len].iter().all(|b| b.is_ascii_alphanumeric())
+    });
+    let file_name = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or_default();
+    FileFormat {
+        file: path
+            .strip_prefix(db_path)
+            .unwrap_or(path)
+            .to_string_lossy()
+            .into_owned(),
+        bytes,
+        magic: magic_len.map(|len| String::from_utf8_lossy(&header[..len]).into_owned()),
+        version: magic_len.map(|len| {
+            u32::from_le_bytes([
+                header[len],
+                header[len + 1],
+                header[len + 2],
+                header[len + 3],
+            ])
+        }),
+        expected_version: expected_version(file_name),
+    }
+}
+
+fn panic_message(info: &std::panic::PanicHookInfo) -> String {
+    info.payload()
+        .downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| info.payload().downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "<non-string panic payload>".to_string())
+}
+
+/// Whether a panic comes from writing to a closed pipe, e.g. `kotadb ... | head`
+fn is_broken_pipe(message: &str) -> bool {
+    message.starts_with("failed printing to std") && message.contains("Broken pipe")
+}
+
+/// Replace the home directory with `~`
+fn sanitize(text: &str) -> String {
+    match std::env::var("HOME") {
+        Ok(home) if home.len() > 1 => text.replace(&home, "~"),
+        _ => text.to_string(),
+    }
+}
+
+fn truncate(text: &str, max_len: usize) -> String {
+    if text.len() <= max_len {
+        return text.to_string();
+    }
+    let mut end = max_len;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}… ({} bytes omitted)", &text[..end], text.len() - end)
+}
+
+/// Write a crash report on the first panic of the process, after the previous hook ran
+///
+/// `command` is recorded as given, so callers pass subcommand and flag names only.
+pub fn install_panic_hook(db_path: &Path, command: String) {
+    let db_path = db_path.to_path_buf();
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        previous(info);
+        if is_broken_pipe(&panic_message(info)) || REPORTED.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        let report = CrashReport::capture(&db_path, &command, info);
+        match report.save(&db_path) {
+            Ok(path) => eprintln!(
+                "\nKotaDB crashed. A crash report was saved to:\n  {}\n\
+                 It contains the backtrace, the command without its arguments and the sizes \
+                 and format versions of the database files, but no file contents.\n\
+                 Please review it and attach it to an issue at {}",
+                path.display(),
+                ISSUES_URL
+            ),
+            Err(e) => eprintln!("\nKotaDB crashed and the crash report could not be saved: {e}"),
+        }
+    }));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn summarizes_components_and_index_headers() -> Result<()> {
+        let dir = TempDir::new()?;
+        let binary = dir.path().join("trigram_index").join("binary");
+        std::fs::create_dir_all(&binary)?;
+        let mut trigrams = b"KTRI".to_vec();
+        trigrams.extend_from_slice(&2u32.to_le_bytes());
+        trigrams.extend_from_slice(&[0; 16]);
+        std::fs::write(binary.join("trigrams.bin"), &trigrams)?;
+        std::fs::write(binary.join("stats.bin"), [0xff, 0x01])?;
+        std::fs::create_dir_all(dir.path().join("storage").join("documents"))?;
+        std::fs::write(dir.path().join("storage/documents/a.json"), b"{}")?;
+        std::fs::create_dir_all(dir.path().join(CRASH_REPORTS_DIR))?;
+        std::fs::write(dir.path().join(CRASH_REPORTS_DIR).join("old.bin"), b"x")?;
+
+        let summary = DatabaseSummary::collect(dir.path());
+        assert!(summary.exists);
+        assert_eq!(
+            summary.components["trigram_index"],
+            ComponentStats {
+                files: 2,
+                bytes: trigrams.len() as u64 + 2
+            }
+        );
+        assert_eq!(summary.components["storage"].files, 1);
+        assert!(!summary.components.contains_key(CRASH_REPORTS_DIR));
+
+        assert_eq!(summary.formats.len(), 2);
+        let stats = &summary.formats[0];
+        assert_eq!((stats.magic.as_deref(), stats.version), (None, None));
+        let trigram = &summary.formats[1];
+        assert_eq!(trigram.file, "trigram_index/binary/trigrams.bin");
+        assert_eq!(trigram.magic.as_deref(), Some("KTRI"));
+        assert_eq!(trigram.version, Some(2));
+        assert_eq!(
+            trigram.expected_version,
+            Some(crate::binary_trigram_index::BINARY_FORMAT_VERSION)
+        );
+
+        assert!(!DatabaseSummary::collect(&dir.path().join("missing")).exists);
+        Ok(())
+    }
+
+    #[test]
+    fn ignores_closed_output_pipes() {
+        assert!(is_broken_pipe(
+            "failed printing to stdout: Broken pipe (os error 32)"
+        ));
+        assert!(!is_broken_pipe("index out of bounds: the len is 0"));
+    }
+
+    #[test]
+    fn truncates_long_messages_on_char_boundaries() {
+        assert_eq!(truncate("short", 10), "short");
+        let long = "é".repeat(10);
+        assert_eq!(truncate(&long, 5), "éé… (16 bytes omitted)");
+    }
+}
@@ -10,6 +10,7 @@ use std::path::Path;
 use std::sync::Arc;
 use tokio::sync::{Mutex, RwLock};
 
+use crate::metrics::performance::{LockedResource, TimedLock};
 use crate::{
     create_binary_trigram_index, create_file_storage, create_primary_index, create_trigram_index,
     create_wrapped_storage,
@@ -97,7 +98,12 @@ impl Database {
 
     /// Get database statistics (document count and total size)
     pub async fn stats(&self) -> Result<(usize, usize)> {
-        let all_docs = self.storage.lock().await.list_all().await?;
+        let all_docs = self
+            .storage
+            .lock_timed(LockedResource::Storage)
+            .await
+            .list_metadata()
+            .await?;
         let doc_count = all_docs.len();
         let total_size: usize = all_docs.iter().map(|d| d.size).sum();
         Ok((doc_count, total_size))
@@ -128,6 +134,14 @@ impl AnalysisServiceDatabase for Database {
     fn storage(&self) -> Arc<Mutex<dyn Storage>> {
         self.storage.clone()
     }
+
+    fn primary_index(&self) -> Arc<Mutex<dyn Index>> {
+        self.primary_index.clone()
+    }
+
+    fn path_cache(&self) -> Arc<RwLock<HashMap<String, ValidatedDocumentId>>> {
+        self.path_cache.clone()
+    }
 }
 
 #[cfg(test)]
@@ -162,6 +176,8 @@ mod tests {
             path_pattern: None,
             limit: ValidatedLimit::new(100, 1000).unwrap(),
             offset: ValidatedPageId::new(1).unwrap(), // Page IDs must be > 0
+            filters: Default::default(),
+            deadline: Default::default(),
         }
     }
 
@@ -178,11 +194,17 @@ mod tests {
 
         let db = database.unwrap();
         // Verify all components are present
-        assert!(db.storage.lock().await.list_all().await.is_ok());
+        assert!(db
+            .storage
+            .lock_timed(LockedResource::Storage)
+            .await
+            .list_all()
+            .await
+            .is_ok());
         let wildcard_query = create_test_query("*");
         assert!(db
             .primary_index
-            .lock()
+            .lock_timed(LockedResource::PrimaryIndex)
             .await
             .search(&wildcard_query)
             .await
@@ -190,7 +212,7 @@ mod tests {
         let test_query = create_test_query("test");
         assert!(db
             .trigram_index
-            .lock()
+            .lock_timed(LockedResource::TrigramIndex)
             .await
             .search(&test_query)
             .await
@@ -213,11 +235,17 @@ mod tests {
 
         let db = database.unwrap();
         // Verify all components are present
-        assert!(db.storage.lock().await.list_all().await.is_ok());
+        assert!(db
+            .storage
+            .lock_timed(LockedResource::Storage)
+            .await
+            .list_all()
+            .await
+            .is_ok());
         let wildcard_query = create_test_query("*");
         assert!(db
             .primary_index
-            .lock()
+            .lock_timed(LockedResource::PrimaryIndex)
             .await
             .search(&wildcard_query)
             .await
@@ -225,7 +253,7 @@ mod tests {
         let test_query = create_test_query("test");
         assert!(db
             .trigram_index
-            .lock()
+            .lock_timed(LockedResource::TrigramIndex)
             .await
             .search(&test_query)
             .await
@@ -264,14 +292,14 @@ mod tests {
 
         database
             .storage
-            .lock()
+            .lock_timed(LockedResource::Storage)
             .await
             .insert(doc1.clone())
             .await
             .expect("Failed to insert doc1");
         database
             .storage
-            .lock()
+            .lock_timed(LockedResource::Storage)
             .await
             .insert(doc2.clone())
             .await
@@ -298,7 +326,12 @@ mod tests {
 
         // Test DatabaseAccess trait method
         let storage = DatabaseAccess::storage(&database);
-        assert!(storage.lock().await.list_all().await.is_ok());
+        assert!(storage
+            .lock_timed(LockedResource::Storage)
+            .await
+            .list_all()
+            .await
+            .is_ok());
     }
 
     #[tokio::test]
@@ -311,9 +344,14 @@ mod tests {
             .expect("Failed to create database");
 
         // Test DatabaseAccess trait method
-        let primary_index = database.primary_index();
+        let primary_index = DatabaseAccess::primary_index(&database);
         let query = create_test_query("*");
-        assert!(primary_index.lock().await.search(&query).await.is_ok());
+        assert!(primary_index
+            .lock_timed(LockedResource::PrimaryIndex)
+            .await
+            .search(&query)
+            .await
+            .is_ok());
     }
 
     #[tokio::test]
@@ -328,7 +366,12 @@ mod tests {
         // Test DatabaseAccess trait method
         let trigram_index = database.trigram_index();
         let query = create_test_query("test");
-        assert!(trigram_index.lock().await.search(&query).await.is_ok());
+        assert!(trigram_index
+            .lock_timed(LockedResource::TrigramIndex)
+            .await
+            .search(&query)
+            .await
+            .is_ok());
     }
 
     #[tokio::test]
@@ -341,7 +384,7 @@ mod tests {
             .expect("Failed to create database");
 
         // Test DatabaseAccess trait method
-        let path_cache = database.path_cache();
+        let path_cache = DatabaseAccess::path_cache(&database);
         assert_eq!(path_cache.read().await.len(), 0);
 
         // Test cache modification
@@ -364,7 +407,12 @@ mod tests {
 
         // Test AnalysisServiceDatabase trait method
         let storage: Arc<Mutex<dyn Storage>> = AnalysisServiceDatabase::storage(&database);
-        assert!(storage.lock().await.list_all().await.is_ok());
+        assert!(storage
+            .lock_timed(LockedResource::Storage)
+            .await
+            .list_all()
+            .await
+            .is_ok());
     }
 
     #[tokio::test]
@@ -418,7 +466,7 @@ mod tests {
                 let db = database.clone();
                 async move {
                     db.storage
-                        .lock()
+                        .lock_timed(LockedResource::Storage)
                         .await
                         .list_all()
                         .await
@@ -430,7 +478,7 @@ mod tests {
                 async move {
                     let query = create_test_query("*");
                     db.primary_index
-                        .lock()
+                        .lock_timed(LockedResource::PrimaryIndex)
                         .await
                         .search(&query)
                         .await
@@ -442,7 +490,7 @@ mod tests {
                 async move {
                     let query = create_test_query("test");
                     db.trigram_index
-                        .lock()
+                        .lock_timed(LockedResource::TrigramIndex)
                         .await
                         .search(&query)
                         .await
@@ -478,7 +526,7 @@ mod tests {
         let doc = create_test_document("Hello world test content", "example.md");
         database
             .storage
-            .lock()
+            .lock_timed(LockedResource::Storage)
             .await
             .insert(doc.clone())
             .await
@@ -492,7 +540,7 @@ mod tests {
         // Verify document can be retrieved
         let retrieved = database
             .storage
-            .lock()
+            .lock_timed(LockedResource::Storage)
             .await
             .get(&doc.id)
             .await
@@ -529,14 +577,14 @@ mod tests {
         let test_query = create_test_query("test");
         assert!(binary_db
             .trigram_index
-            .lock()
+            .lock_timed(LockedResource::TrigramIndex)
             .await
             .search(&test_query)
             .await
             .is_ok());
         assert!(text_db
             .trigram_index
-            .lock()
+            .lock_timed(LockedResource::TrigramIndex)
             .await
             .search(&test_query)
             .await
@@ -546,14 +594,14 @@ mod tests {
         let nonexistent_query = create_test_query("nonexistent");
         let binary_results = binary_db
             .trigram_index
-            .lock()
+            .lock_timed(LockedResource::TrigramIndex)
             .await
             .search(&nonexistent_query)
             .await
             .expect("Search should work");
         let text_results = text_db
             .trigram_index
-            .lock()
+            .lock_timed(LockedResource::TrigramIndex)
             .await
             .search(&nonexistent_query)
             .await
@@ -582,21 +630,21 @@ mod tests {
 
         database
             .storage
-            .lock()
+            .lock_timed(LockedResource::Storage)
             .await
             .insert(small_doc.clone())
             .await
             .expect("Failed to insert small doc");
         database
             .storage
-            .lock()
+            .lock_timed(LockedResource::Storage)
             .await
             .insert(medium_doc.clone())
             .await
             .expect("Failed to insert medium doc");
         database
             .storage
-            .lock()
+            .lock_timed(LockedResource::Storage)
             .await
             .insert(large_doc.clone())
             .await
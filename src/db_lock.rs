@@ -0,0 +1,536 @@
+//! Ownership of a database directory
+//!
+//! Storage, indices and the symbol files keep in-memory state that they write back
+//! wholesale, so two processes writing one `db_path` silently overwrite each other's
+//! changes. The writer of a directory holds a lease in [`LOCK_FILE`]: a JSON record of
+//! who holds it ([`LockHolder`]) that a background thread renews while the process
+//! runs. Another process finding a live lease either fails with a message naming the
+//! holder or, for commands that only read, opens the database read-only. Leases whose
+//! holder stopped renewing them, or whose process is gone, are taken over. Reading the
+//! lease and replacing it happen under an exclusive lock on [`GUARD_FILE`], so two
+//! processes never both take over one stale lease, and a holder whose lease was taken
+//! over stops writing instead of renewing it.
+//!
+//! Replicas that intentionally share a directory (for example read replicas on a
+//! shared volume that is only written by an external sync) set [`ALLOW_SHARED_ENV`]
+//! or pass `--allow-shared-db` to skip the lease entirely.
+
+use std::fs::OpenOptions;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::{error, info, warn};
+
+/// Acquisition times of the leases this process holds
+static HELD_LEASES: Mutex<Vec<DateTime<Utc>>> = Mutex::new(Vec::new());
+
+/// Lease file in the database directory
+pub const LOCK_FILE: &str = "kotadb.lock";
+
+/// File locked while the lease file is read and replaced
+pub const GUARD_FILE: &str = "kotadb.lock.guard";
+
+/// Exit code of a process that lost its lease with [`DbLock::exit_on_takeover`] set
+pub const LEASE_LOST_EXIT_CODE: i32 = 75;
+
+/// Set to `1` or `true` to open a database without taking its lease
+pub const ALLOW_SHARED_ENV: &str = "KOTADB_ALLOW_SHARED_DB";
+
+/// How long a lease stays valid without being renewed
+pub const DEFAULT_LEASE: Duration = Duration::from_secs(30);
+
+/// Who holds the lease on a database directory
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockHolder {
+    pub pid: u32,
+    pub hostname: String,
+    /// Command line of the holder without argument values, e.g. `kotadb serve`
+    pub command: String,
+    pub acquired_at: DateTime<Utc>,
+    pub renewed_at: DateTime<Utc>,
+    pub lease_secs: u64,
+}
+
+impl LockHolder {
+    fn current(command: &str, lease: Duration) -> Self {
+        let now = Utc::now();
+        Self {
+            pid: std::process::id(),
+            hostname: hostname(),
+            command: command.to_string(),
+            acquired_at: now,
+            renewed_at: now,
+            lease_secs: lease.as_secs().max(1),
+        }
+    }
+
+    /// Whether the holder stopped renewing the lease or its process no longer exists
+    pub fn is_stale(&self, now: DateTime<Utc>) -> bool {
+        let expires = self.renewed_at + chrono::Duration::seconds(self.lease_secs as i64);
+        if now > expires {
+            return true;
+        }
+        if self.hostname != hostname() {
+            return false;
+        }
+        // A restarted container runs as the same pid as the process that crashed
+        if self.pid == std::process::id() {
+            return !held_here(self);
+        }
+        !process_exists(self.pid)
+    }
+
+    /// Same lease as `other`, ignoring renewals
+    fn same_lease(&self, other: &LockHolder) -> bool {
+        self.pid == other.pid
+            && self.hostname == other.hostname
+            && self.acquired_at == other.acquired_at
+    }
+}
+
+impl std::fmt::Display for LockHolder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "`{}` (pid {} on {}), holding it since {}",
+            self.command,
+            self.pid,
+            self.hostname,
+            self.acquired_at.format("%Y-%m-%d %H:%M:%S UTC")
+        )
+    }
+}
+
+/// Why a lease could not be acquired
+#[derive(Debug, thiserror::Error)]
+pub enum DbLockError {
+    #[error(
+        "Database {} is in use by {holder}.\n\
+         Stop that process or point this one at another --db-path. Replicas that \
+         intentionally share a directory can pass --allow-shared-db (or set {}=1).",
+        .path.display(),
+        ALLOW_SHARED_ENV
+    )]
+    Held { path: PathBuf, holder: LockHolder },
+    #[error("Failed to access lock file {}: {source}", .path.display())]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+}
+
+/// Lease on a database directory, renewed in the background and released on drop
+pub struct DbLock {
+    path: PathBuf,
+    holder: LockHolder,
+    stop: Arc<AtomicBool>,
+    lost: Arc<AtomicBool>,
+    exit_on_takeover: Arc<AtomicBool>,
+    renewer: Option<JoinHandle<()>>,
+}
+
+/// Outcome of one attempt to take the lease
+enum Attempt {
+    Acquired,
+    Held(LockHolder),
+    Unreadable,
+}
+
+impl DbLock {
+    /// Take the lease on `db_path`, waiting up to `wait` for a live holder to release it
+    pub fn acquire(db_path: &Path, command: &str, wait: Duration) -> Result<Self, DbLockError> {
+        Self::acquire_with_lease(db_path, command, wait, DEFAULT_LEASE)
+    }
+
+    pub fn acquire_with_lease(
+        db_path: &Path,
+        command: &str,
+        wait: Duration,
+        lease: Duration,
+    ) -> Result<Self, DbLockError> {
+        let path = db_path.join(LOCK_FILE);
+        let io_error = |source| DbLockError::Io {
+            path: path.clone(),
+            source,
+        };
+        std::fs::create_dir_all(db_path).map_err(io_error)?;
+        let deadline = std::time::Instant::now() + wait;
+        loop {
+            let holder = LockHolder::current(command, lease);
+            let attempt = with_guard(&path, || {
+                let stale = match read_holder(&path) {
+                    None if !path.exists() => None,
+                    Some(current) if !current.is_stale(Utc::now()) => {
+                        return Ok(Attempt::Held(current));
+                    }
+                    Some(stale) => Some(stale),
+                    // Unreadable: written by hand or by a version that wrote it in place
+                    None if lock_file_age(&path) > lease => {
+                        warn!("Replacing unreadable lock file {}", path.display());
+                        None
+                    }
+                    None => return Ok(Attempt::Unreadable),
+                };
+                if let Some(stale) = stale {
+                    warn!("Taking over stale database lease held by {}", stale);
+                }
+                // Registered before it is written, so other threads of this process never
+                // see the new lease as left behind by a crashed process
+                if let Ok(mut held) = HELD_LEASES.lock() {
+                    held.push(holder.acquired_at);
+                }
+                if let Err(e) = write_holder(&path, &holder) {
+                    forget_held(&holder);
+                    return Err(e);
+                }
+                Ok(Attempt::Acquired)
+            })
+            .map_err(io_error)?;
+            match attempt {
+                Attempt::Acquired => return Ok(Self::start(path.clone(), holder)),
+                Attempt::Held(current) => {
+                    if std::time::Instant::now() >= deadline {
+                        return Err(DbLockError::Held {
+                            path: db_path.to_path_buf(),
+                            holder: current,
+                        });
+                    }
+                    std::thread::sleep(Duration::from_millis(250));
+                }
+                Attempt::Unreadable => std::thread::sleep(Duration::from_millis(50)),
+            }
+        }
+    }
+
+    fn start(path: PathBuf, holder: LockHolder) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let lost = Arc::new(AtomicBool::new(false));
+        let exit_on_takeover = Arc::new(AtomicBool::new(false));
+        let renewer = {
+            let (path, mut holder, stop) = (path.clone(), holder.clone(), stop.clone());
+            let (lost, exit_on_takeover) = (lost.clone(), exit_on_takeover.clone());
+            let interval = Duration::from_secs(holder.lease_secs).div_f32(3.0);
+            std::thread::Builder::new()
+                .name("kotadb-lease".to_string())
+                .spawn(move || {
+                    while !stop.load(Ordering::Relaxed) {
+                        std::thread::park_timeout(interval);
+                        if stop.load(Ordering::Relaxed) {
+                            break;
+                        }
+                        let renewed = with_guard(&path, || match read_holder(&path) {
+                            Some(current) if current.same_lease(&holder) => {
+                                holder.renewed_at = Utc::now();
+                                write_holder(&path, &holder).map(|()| None)
+                            }
+                            other => Ok(Some(other)),
+                        });
+                        match renewed {
+                            Ok(None) => {}
+                            Ok(Some(other)) => {
+                                lost.store(true, Ordering::SeqCst);
+                                error!(
+                                    "Database lease {} was taken over{}; refusing further writes",
+                                    path.display(),
+                                    other.map(|h| format!(" by {}", h)).unwrap_or_default()
+                                );
+                                if exit_on_takeover.load(Ordering::SeqCst) {
+                                    std::process::exit(LEASE_LOST_EXIT_CODE);
+                                }
+                                break;
+                            }
+                            Err(e) => warn!("Failed to renew database lease: {}", e),
+                        }
+                    }
+                })
+                .ok()
+        };
+        info!("Acquired database lease {}", path.display());
+        Self {
+            path,
+            holder,
+            stop,
+            lost,
+            exit_on_takeover,
+            renewer,
+        }
+    }
+
+    pub fn holder(&self) -> &LockHolder {
+        &self.holder
+    }
+
+    /// Whether another process took the lease over; the holder must not write after that
+    pub fn is_lost(&self) -> bool {
+        self.lost.load(Ordering::SeqCst)
+    }
+
+    /// Exit the process with [`LEASE_LOST_EXIT_CODE`] as soon as the lease is taken over,
+    /// for processes whose writers can't check [`DbLock::is_lost`] themselves
+    pub fn exit_on_takeover(self) -> Self {
+        self.exit_on_takeover.store(true, Ordering::SeqCst);
+        self
+    }
+}
+
+impl Drop for DbLock {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(renewer) = self.renewer.take() {
+            renewer.thread().unpark();
+            let _ = renewer.join();
+        }
+        if let Err(e) = with_guard(&self.path, || remove_if_held_by(&self.path, &self.holder)) {
+            warn!("Failed to release database lease: {}", e);
+        }
+        forget_held(&self.holder);
+    }
+}
+
+/// How a process may use a database directory
+pub enum Ownership {
+    /// This process holds the lease and may write
+    Exclusive(DbLock),
+    /// Another process holds the lease; this one must not write
+    ReadOnly(LockHolder),
+    /// The lease was skipped on request; the caller coordinates writers itself
+    Shared,
+}
+
+impl Ownership {
+    pub fn is_read_only(&self) -> bool {
+        matches!(self, Ownership::ReadOnly(_))
+    }
+
+    /// Take the lease on `db_path`, falling back to read-only access when another
+    /// process holds it and `writes` is false
+    pub fn claim(
+        db_path: &Path,
+        command: &str,
+        writes: bool,
+        allow_shared: bool,
+    ) -> Result<Self, DbLockError> {
+        if allow_shared || shared_from_env() {
+            warn!(
+                "Opening {} without a lease; other processes may write to it",
+                db_path.display()
+            );
+            return Ok(Ownership::Shared);
+        }
+        // Writers give short-lived commands of another process a moment to finish
+        let wait = if writes {
+            Duration::from_secs(10)
+        } else {
+            Duration::ZERO
+        };
+        // Storage and indices write back in-memory state, so a process that lost its
+        // lease exits rather than overwrite the new holder's changes
+        match DbLock::acquire(db_path, command, wait) {
+            Ok(lock) => Ok(Ownership::Exclusive(lock.exit_on_takeover())),
+            Err(DbLockError::Held { holder, .. }) if !writes => Ok(Ownership::ReadOnly(holder)),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+fn held_here(holder: &LockHolder) -> bool {
+    HELD_LEASES
+        .lock()
+        .map(|held| held.contains(&holder.acquired_at))
+        .unwrap_or(true)
+}
+
+fn forget_held(holder: &LockHolder) {
+    if let Ok(mut held) = HELD_LEASES.lock() {
+        held.retain(|acquired_at| *acquired_at != holder.acquired_at);
+    }
+}
+
+fn shared_from_env() -> bool {
+    std::env::var(ALLOW_SHARED_ENV)
+        .map(|value| matches!(value.trim(), "1" | "true" | "yes"))
+        .unwrap_or(false)
+}
+
+/// Run `f` holding the exclusive lock on the guard file next to the lease file `path`
+fn with_guard<T>(path: &Path, f: impl FnOnce() -> std::io::Result<T>) -> std::io::Result<T> {
+    let guard = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(path.with_file_name(GUARD_FILE))?;
+    guard.lock()?;
+    let result = f();
+    // Closing the file releases the lock as well
+    let _ = guard.unlock();
+    result
+}
+
+fn read_holder(path: &Path) -> Option<LockHolder> {
+    serde_json::from_slice(&std::fs::read(path).ok()?).ok()
+}
+
+/// Rename the holder into place, so readers never see a partial holder
+fn write_holder(path: &Path, holder: &LockHolder) -> std::io::Result<()> {
+    let temp = path.with_extension(format!("lock.{}", std::process::id()));
+    std::fs::write(&temp, serde_json::to_vec_pretty(holder)?)?;
+    std::fs::rename(&temp, path)
+}
+
+fn remove_if_held_by(path: &Path, holder: &LockHolder) -> std::io::Result<()> {
+    match read_holder(path) {
+        Some(current) if current.same_lease(holder) => {
+            std::fs::remove_file(path).or_else(ignore_not_found)
+        }
+        _ => Ok(()),
+    }
+}
+
+fn lock_file_age(path: &Path) -> Duration {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| modified.elapsed().ok())
+        .unwrap_or_default()
+}
+
+fn ignore_not_found(e: std::io::Error) -> std::io::Result<()> {
+    if e.kind() == std::io::ErrorKind::NotFound {
+        Ok(())
+    } else {
+        Err(e)
+    }
+}
+
+#[cfg(unix)]
+fn process_exists(pid: u32) -> bool {
+    // SAFETY: signal 0 only checks whether the process exists
+    let result = unsafe { libc::kill(pid as libc::pid_t, 0) };
+    result == 0 || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+#[cfg(not(unix))]
+fn process_exists(_pid: u32) -> bool {
+    true
+}
+
+fn hostname() -> String {
+    #[cfg(unix)]
+    {
+        let mut buffer = [0u8; 256];
+        // SAFETY: the buffer is valid for its length and gethostname NUL-terminates
+        // within it on success
+        if unsafe { libc::gethostname(buffer.as_mut_ptr().cast(), buffer.len()) } == 0 {
+            let end = buffer.iter().position(|&b| b == 0).unwrap_or(buffer.len());
+            if let Ok(name) = std::str::from_utf8(&buffer[..end]) {
+                return name.to_string();
+            }
+        }
+    }
+    std::env::var("HOSTNAME")
+        .or_else(|_| std::env::var("COMPUTERNAME"))
+        .unwrap_or_else(|_| "unknown".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn second_writer_is_refused_and_reader_falls_back_to_read_only() {
+        let dir = TempDir::new().unwrap();
+        let first = DbLock::acquire(dir.path(), "kotadb serve", Duration::ZERO).unwrap();
+        assert_eq!(first.holder().pid, std::process::id());
+
+        let err = match DbLock::acquire(dir.path(), "kotadb index-codebase", Duration::ZERO) {
+            Err(e) => e,
+            Ok(_) => panic!("second lease acquired"),
+        };
+        let message = err.to_string();
+        assert!(message.contains("kotadb serve"), "{message}");
+        assert!(message.contains("--allow-shared-db"), "{message}");
+
+        let reader = Ownership::claim(dir.path(), "kotadb search-code", false, false).unwrap();
+        assert!(reader.is_read_only());
+        assert!(matches!(
+            Ownership::claim(dir.path(), "kotadb stats", false, true).unwrap(),
+            Ownership::Shared
+        ));
+
+        drop(first);
+        assert!(!dir.path().join(LOCK_FILE).exists());
+        let writer = Ownership::claim(dir.path(), "kotadb index-codebase", true, false).unwrap();
+        assert!(matches!(writer, Ownership::Exclusive(_)));
+    }
+
+    #[test]
+    fn expired_leases_are_taken_over() {
+        let dir = TempDir::new().unwrap();
+        let mut stale = LockHolder::current("kotadb serve", DEFAULT_LEASE);
+        stale.hostname = "other-node".to_string();
+        stale.renewed_at = Utc::now() - chrono::Duration::minutes(5);
+        write_holder(&dir.path().join(LOCK_FILE), &stale).unwrap();
+        assert!(stale.is_stale(Utc::now()));
+
+        // Left behind by an earlier process that ran as this pid, as in a restarted container
+        let crashed = LockHolder::current("kotadb serve", DEFAULT_LEASE);
+        assert!(crashed.is_stale(Utc::now()));
+
+        let lock = DbLock::acquire(dir.path(), "kotadb index-codebase", Duration::ZERO).unwrap();
+        assert_eq!(
+            read_holder(&dir.path().join(LOCK_FILE)).as_ref(),
+            Some(lock.holder())
+        );
+    }
+
+    #[test]
+    fn one_process_takes_over_a_stale_lease() {
+        let dir = TempDir::new().unwrap();
+        let mut stale = LockHolder::current("kotadb serve", DEFAULT_LEASE);
+        stale.hostname = "other-node".to_string();
+        stale.renewed_at = Utc::now() - chrono::Duration::minutes(5);
+        write_holder(&dir.path().join(LOCK_FILE), &stale).unwrap();
+
+        let takers: Vec<_> = (0..8)
+            .map(|_| {
+                let path = dir.path().to_path_buf();
+                std::thread::spawn(move || DbLock::acquire(&path, "kotadb index", Duration::ZERO))
+            })
+            .collect();
+        let locks: Vec<_> = takers
+            .into_iter()
+            .filter_map(|taker| taker.join().unwrap().ok())
+            .collect();
+        assert_eq!(locks.len(), 1);
+    }
+
+    #[test]
+    fn takeover_is_reported_to_the_holder() {
+        let dir = TempDir::new().unwrap();
+        let lock = DbLock::acquire_with_lease(
+            dir.path(),
+            "kotadb serve",
+            Duration::ZERO,
+            Duration::from_secs(1),
+        )
+        .unwrap();
+        assert!(!lock.is_lost());
+
+        let mut other = LockHolder::current("kotadb serve", DEFAULT_LEASE);
+        other.hostname = "other-node".to_string();
+        write_holder(&dir.path().join(LOCK_FILE), &other).unwrap();
+        std::thread::sleep(Duration::from_millis(800));
+        assert!(lock.is_lost());
+
+        // Releasing a lost lease leaves the new holder's file alone
+        drop(lock);
+        assert_eq!(read_holder(&dir.path().join(LOCK_FILE)), Some(other));
+    }
+}
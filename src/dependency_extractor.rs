@@ -59,6 +59,107 @@ pub struct SerializableEdge {
     pub edge: DependencyEdge,
 }
 
+/// Magic bytes at the start of a versioned `dependency_graph.bin` file
+const GRAPH_FILE_MAGIC: &[u8; 4] = b"KDGR";
+
+/// Current on-disk format version of `dependency_graph.bin`
+pub const GRAPH_FILE_VERSION: u32 = 2;
+
+impl SerializableDependencyGraph {
+    /// Encode for `dependency_graph.bin`: magic bytes, format version, then the bincode payload
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut bytes = Vec::with_capacity(GRAPH_FILE_MAGIC.len() + 4);
+        bytes.extend_from_slice(GRAPH_FILE_MAGIC);
+        bytes.extend_from_slice(&GRAPH_FILE_VERSION.to_le_bytes());
+        bincode::serialize_into(&mut bytes, self)
+            .context("Failed to serialize dependency graph")?;
+        Ok(bytes)
+    }
+
+    /// Decode a `dependency_graph.bin` file.
+    ///
+    /// Files written before the format was versioned carry no header and no edge
+    /// end positions or byte offsets; they are migrated in memory.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let Some(payload) = bytes.strip_prefix(GRAPH_FILE_MAGIC.as_slice()) else {
+            let legacy: LegacyDependencyGraphV1 = bincode::deserialize(bytes)
+                .context("Failed to deserialize unversioned dependency graph")?;
+            return Ok(legacy.into());
+        };
+
+        let (version, payload) = payload
+            .split_first_chunk::<4>()
+            .context("Dependency graph file header is truncated")?;
+        let version = u32::from_le_bytes(*version);
+        if version != GRAPH_FILE_VERSION {
+            anyhow::bail!(
+                "Unsupported dependency graph format version {} (expected {}); re-index to rebuild",
+                version,
+                GRAPH_FILE_VERSION
+            );
+        }
+        bincode::deserialize(payload).context("Failed to deserialize dependency graph")
+    }
+}
+
+/// Edge layout of unversioned (format 1) dependency graph files
+#[derive(Deserialize)]
+struct LegacyDependencyEdgeV1 {
+    relation_type: RelationType,
+    line_number: usize,
+    column_number: usize,
+    context: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct LegacySerializableEdgeV1 {
+    from_id: Uuid,
+    to_id: Uuid,
+    edge: LegacyDependencyEdgeV1,
+}
+
+/// Layout of unversioned (format 1) dependency graph files
+#[derive(Deserialize)]
+struct LegacyDependencyGraphV1 {
+    nodes: Vec<SymbolNode>,
+    edges: Vec<LegacySerializableEdgeV1>,
+    name_to_symbol: HashMap<String, Uuid>,
+    file_imports: HashMap<PathBuf, Vec<ImportStatement>>,
+    stats: GraphStats,
+}
+
+impl From<LegacyDependencyGraphV1> for SerializableDependencyGraph {
+    fn from(legacy: LegacyDependencyGraphV1) -> Self {
+        let edges = legacy
+            .edges
+            .into_iter()
+            .map(|edge| SerializableEdge {
+                from_id: edge.from_id,
+                to_id: edge.to_id,
+                edge: DependencyEdge {
+                    relation_type: edge.edge.relation_type,
+                    line_number: edge.edge.line_number,
+                    // Format 1 files were written by the relationship bridge with 1-based columns
+                    column_number: edge.edge.column_number.saturating_sub(1),
+                    end_line_number: None,
+                    end_column_number: None,
+                    start_byte: None,
+                    end_byte: None,
+                    context: edge.edge.context,
+                },
+            })
+            .collect();
+
+        SerializableDependencyGraph {
+            nodes: legacy.nodes,
+            edges,
+            name_to_symbol: legacy.name_to_symbol,
+            file_imports: legacy.file_imports,
+            stats: legacy.stats,
+        }
+    }
+}
+
 /// Node in the dependency graph representing a symbol
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SymbolNode {
@@ -83,8 +184,16 @@ pub struct DependencyEdge {
     pub relation_type: RelationType,
     /// Line number where the reference occurs
     pub line_number: usize,
-    /// Column number where the reference occurs
+    /// Column number where the reference occurs (0-based)
     pub column_number: usize,
+    /// Line number where the reference ends
+    pub end_line_number: Option<usize>,
+    /// Column number where the reference ends (0-based, exclusive)
+    pub end_column_number: Option<usize>,
+    /// Byte offset of the reference within the source file
+    pub start_byte: Option<usize>,
+    /// Byte offset just past the end of the reference
+    pub end_byte: Option<usize>,
     /// Context snippet around the reference
     pub context: Option<String>,
 }
@@ -133,6 +242,12 @@ pub struct CodeReference {
     /// Location in source
     pub line: usize,
     pub column: usize,
+    /// End of the reference in source, when the parser reported it
+    pub end_line: Option<usize>,
+    pub end_column: Option<usize>,
+    /// Byte range of the reference in source, when the parser reported it
+    pub start_byte: Option<usize>,
+    pub end_byte: Option<usize>,
     /// Full text of the reference
     pub text: String,
 }
@@ -843,6 +958,10 @@ impl DependencyExtractor {
                             ref_type: ref_type.clone(),
                             line: pos.row + 1,
                             column: pos.column,
+                            end_line: Some(node.end_position().row + 1),
+                            end_column: Some(node.end_position().column),
+                            start_byte: Some(node.start_byte()),
+                            end_byte: Some(node.end_byte()),
                             text: text.to_string(),
                         });
                     }
@@ -1035,6 +1154,10 @@ impl DependencyExtractor {
                                         .to_descriptive_relation_type(),
                                     line_number: reference.line,
                                     column_number: reference.column,
+                                    end_line_number: reference.end_line,
+                                    end_column_number: reference.end_column,
+                                    start_byte: reference.start_byte,
+                                    end_byte: reference.end_byte,
                                     context: Some(reference.text.clone()),
                                 };
 
@@ -1411,6 +1534,19 @@ impl DependencyGraph {
         }
     }
 
+    /// Find all symbols that depend on a given symbol, along with the edge describing
+    /// where the dependency occurs
+    pub fn find_dependent_edges(&self, symbol_id: Uuid) -> Vec<(Uuid, &DependencyEdge)> {
+        if let Some(&node_idx) = self.symbol_to_node.get(&symbol_id) {
+            self.graph
+                .edges_directed(node_idx, petgraph::Direction::Incoming)
+                .map(|edge| (self.graph[edge.source()].symbol_id, edge.weight()))
+                .collect()
+        } else {
+            Vec::new()
+        }
+    }
+
     /// Find all symbols that depend on a given symbol
     pub fn find_dependents(&self, symbol_id: Uuid) -> Vec<(Uuid, RelationType)> {
         if let Some(&node_idx) = self.symbol_to_node.get(&symbol_id) {
@@ -1612,6 +1748,10 @@ fn validate_result(value: i32) -> i32 {
                 relation_type: RelationType::Calls,
                 line_number: 10,
                 column_number: 5,
+                end_line_number: None,
+                end_column_number: None,
+                start_byte: None,
+                end_byte: None,
                 context: Some("A calls B".to_string()),
             },
         );
@@ -1623,6 +1763,10 @@ fn validate_result(value: i32) -> i32 {
                 relation_type: RelationType::Calls,
                 line_number: 20,
                 column_number: 5,
+                end_line_number: None,
+                end_column_number: None,
+                start_byte: None,
+                end_byte: None,
                 context: Some("B calls C".to_string()),
             },
         );
@@ -1634,6 +1778,10 @@ fn validate_result(value: i32) -> i32 {
                 relation_type: RelationType::Calls,
                 line_number: 30,
                 column_number: 5,
+                end_line_number: None,
+                end_column_number: None,
+                start_byte: None,
+                end_byte: None,
                 context: Some("C calls A".to_string()),
             },
         );
@@ -1811,4 +1959,78 @@ impl DataStore {
         assert_eq!(reconstructed.graph.node_count(), 1);
         assert_eq!(reconstructed.graph.edge_count(), 0);
     }
+
+    #[test]
+    fn test_unversioned_graph_file_is_migrated_on_load() {
+        // Mirror of the format 1 on-disk layout, before edges carried end positions
+        #[derive(Serialize)]
+        struct EdgeV1 {
+            relation_type: RelationType,
+            line_number: usize,
+            column_number: usize,
+            context: Option<String>,
+        }
+        #[derive(Serialize)]
+        struct SerializableEdgeV1 {
+            from_id: Uuid,
+            to_id: Uuid,
+            edge: EdgeV1,
+        }
+        #[derive(Serialize)]
+        struct GraphV1 {
+            nodes: Vec<SymbolNode>,
+            edges: Vec<SerializableEdgeV1>,
+            name_to_symbol: HashMap<String, Uuid>,
+            file_imports: HashMap<PathBuf, Vec<ImportStatement>>,
+            stats: GraphStats,
+        }
+
+        let caller = Uuid::new_v4();
+        let callee = Uuid::new_v4();
+        let node = |id, name: &str| SymbolNode {
+            symbol_id: id,
+            qualified_name: name.to_string(),
+            symbol_type: SymbolType::Function,
+            file_path: PathBuf::from("src/lib.rs"),
+            in_degree: 0,
+            out_degree: 0,
+        };
+        let fixture = bincode::serialize(&GraphV1 {
+            nodes: vec![node(caller, "main"), node(callee, "helper")],
+            edges: vec![SerializableEdgeV1 {
+                from_id: caller,
+                to_id: callee,
+                edge: EdgeV1 {
+                    relation_type: RelationType::Calls,
+                    line_number: 3,
+                    column_number: 5,
+                    context: Some("helper()".to_string()),
+                },
+            }],
+            name_to_symbol: HashMap::from([("helper".to_string(), callee)]),
+            file_imports: HashMap::new(),
+            stats: GraphStats::default(),
+        })
+        .unwrap();
+
+        let migrated = SerializableDependencyGraph::from_bytes(&fixture).unwrap();
+        assert_eq!(migrated.nodes.len(), 2);
+        let edge = &migrated.edges[0].edge;
+        assert_eq!(edge.line_number, 3);
+        assert_eq!(edge.column_number, 4, "format 1 columns were 1-based");
+        assert_eq!(edge.end_line_number, None);
+        assert_eq!(edge.start_byte, None);
+        assert_eq!(edge.context.as_deref(), Some("helper()"));
+
+        // Re-encoding writes the current versioned format, which round-trips
+        let encoded = migrated.to_bytes().unwrap();
+        assert!(encoded.starts_with(GRAPH_FILE_MAGIC));
+        let reloaded = SerializableDependencyGraph::from_bytes(&encoded).unwrap();
+        assert_eq!(reloaded.edges[0].edge.column_number, 4);
+
+        // Unknown future versions are rejected rather than misread
+        let mut future = encoded.clone();
+        future[4..8].copy_from_slice(&(GRAPH_FILE_VERSION + 1).to_le_bytes());
+        assert!(SerializableDependencyGraph::from_bytes(&future).is_err());
+    }
 }
@@ -0,0 +1,237 @@
+//! "Did you mean" suggestions for queries that found nothing
+//!
+//! A search that returns no results is most often a typo (`StorgaeEngine`) or a
+//! remembered-wrong name. [`Suggester`] collects the symbol names and document
+//! paths of a database and offers the ones closest to the query by edit
+//! distance, so the no-results message can name something that does exist.
+
+use crate::identifier_tokenization::split_identifier;
+use std::collections::BTreeSet;
+
+/// Upper bound on suggestions returned for one query
+pub const MAX_SUGGESTIONS: usize = 5;
+
+/// Shortest word considered for correction; shorter words are too ambiguous
+const MIN_WORD_LEN: usize = 3;
+
+/// Optimal-string-alignment edit distance between two strings, by character
+///
+/// Insertions, deletions, substitutions and swaps of adjacent characters each
+/// cost one.
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    if a.is_empty() || b.is_empty() {
+        return a.len().max(b.len());
+    }
+
+    let width = b.len() + 1;
+    let mut rows = vec![0usize; (a.len() + 1) * width];
+    for (j, cell) in rows.iter_mut().take(width).enumerate() {
+        *cell = j;
+    }
+    for i in 1..=a.len() {
+        rows[i * width] = i;
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut best = (rows[(i - 1) * width + j] + 1)
+                .min(rows[i * width + j - 1] + 1)
+                .min(rows[(i - 1) * width + j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                best = best.min(rows[(i - 2) * width + j - 2] + 1);
+            }
+            rows[i * width + j] = best;
+        }
+    }
+    rows[a.len() * width + b.len()]
+}
+
+/// Largest edit distance still treated as a typo of a word with `len` characters
+pub fn max_typo_distance(len: usize) -> usize {
+    match len {
+        0..=2 => 0,
+        3..=5 => 1,
+        6..=9 => 2,
+        _ => 3,
+    }
+}
+
+/// Dictionary of known identifiers and paths to suggest from
+#[derive(Debug, Default, Clone)]
+pub struct Suggester {
+    identifiers: BTreeSet<String>,
+    paths: BTreeSet<String>,
+}
+
+impl Suggester {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_identifier(&mut self, name: &str) {
+        if !name.is_empty() {
+            self.identifiers.insert(name.to_string());
+        }
+    }
+
+    pub fn add_path(&mut self, path: &str) {
+        if !path.is_empty() {
+            self.paths.insert(path.to_string());
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.identifiers.is_empty() && self.paths.is_empty()
+    }
+
+    /// Known identifiers or paths close to `query`, nearest first
+    ///
+    /// Single-word queries are compared with whole identifiers, file names and
+    /// stems or, when the query contains `/`, whole paths. Multi-word queries have each
+    /// unknown word corrected against the subwords of known identifiers and
+    /// produce at most one corrected query. Wildcards are ignored while comparing.
+    pub fn suggest(&self, query: &str, limit: usize) -> Vec<String> {
+        let query = query.trim().replace('*', "");
+        if query.chars().count() < MIN_WORD_LEN {
+            return Vec::new();
+        }
+        if query.contains(char::is_whitespace) {
+            return self.correct_words(&query).into_iter().collect();
+        }
+
+        let query_lower = query.to_lowercase();
+        let budget = max_typo_distance(query_lower.chars().count());
+        let mut scored: Vec<(usize, &str)> = Vec::new();
+        for identifier in &self.identifiers {
+            let distance = edit_distance(&query_lower, &identifier.to_lowercase());
+            if distance > 0 && distance <= budget {
+                scored.push((distance, identifier));
+            }
+        }
+        for path in &self.paths {
+            let distance = if query.contains('/') {
+                edit_distance(&query_lower, &path.to_lowercase())
+            } else {
+                let name = path.rsplit('/').next().unwrap_or(path).to_lowercase();
+                let stem = name.split('.').next().unwrap_or(&name);
+                edit_distance(&query_lower, &name).min(edit_distance(&query_lower, stem))
+            };
+            if distance > 0 && distance <= budget {
+                scored.push((distance, path));
+            }
+        }
+
+        scored.sort_by(|a, b| a.0.cmp(&b.0).then_with(|| a.1.cmp(b.1)));
+        let mut suggestions: Vec<String> = Vec::new();
+        for (_, candidate) in scored {
+            if !suggestions.iter().any(|existing| existing == candidate) {
+                suggestions.push(candidate.to_string());
+            }
+            if suggestions.len() >= limit {
+                break;
+            }
+        }
+        suggestions
+    }
+
+    /// The query with each misspelled word replaced by its nearest known subword
+    fn correct_words(&self, query: &str) -> Option<String> {
+        let vocabulary: BTreeSet<String> = self
+            .identifiers
+            .iter()
+            .map(String::as_str)
+            .chain(self.paths.iter().filter_map(|path| {
+                let name = path.rsplit('/').next().unwrap_or(path);
+                name.split('.').next()
+            }))
+            .flat_map(split_identifier)
+            .filter(|word| word.chars().count() >= MIN_WORD_LEN)
+            .collect();
+
+        let mut changed = false;
+        let corrected: Vec<String> = query
+            .split_whitespace()
+            .map(|word| {
+                let lower = word.to_lowercase();
+                if lower.chars().count() < MIN_WORD_LEN || vocabulary.contains(&lower) {
+                    return word.to_string();
+                }
+                let budget = max_typo_distance(lower.chars().count());
+                let nearest = vocabulary
+                    .iter()
+                    .map(|candidate| (edit_distance(&lower, candidate), candidate))
+                    .filter(|(distance, _)| *distance <= budget)
+                    .min();
+                match nearest {
+                    Some((_, candidate)) => {
+                        changed = true;
+                        candidate.clone()
+                    }
+                    None => word.to_string(),
+                }
+            })
+            .collect();
+
+        changed.then(|| corrected.join(" "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn suggester() -> Suggester {
+        let mut suggester = Suggester::new();
+        suggester.add_identifier("StorageEngine");
+        suggester.add_identifier("open_storage_engine");
+        suggester.add_identifier("FileStorage");
+        suggester.add_path("src/file_storage.rs");
+        suggester.add_path("src/engine.rs");
+        suggester
+    }
+
+    #[test]
+    fn edit_distance_counts_adjacent_swaps_once() {
+        assert_eq!(edit_distance("storage", "storage"), 0);
+        assert_eq!(edit_distance("storgae", "storage"), 1);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("", "abc"), 3);
+    }
+
+    #[test]
+    fn suggests_identifiers_and_paths_for_single_words() {
+        let suggester = suggester();
+        assert_eq!(
+            suggester.suggest("StorgaeEngine", MAX_SUGGESTIONS),
+            vec!["StorageEngine"]
+        );
+        assert_eq!(
+            suggester.suggest("engnie.rs", MAX_SUGGESTIONS),
+            vec!["src/engine.rs"]
+        );
+        assert_eq!(
+            suggester.suggest("FileStorag", MAX_SUGGESTIONS),
+            vec!["FileStorage", "src/file_storage.rs"]
+        );
+        assert_eq!(
+            suggester.suggest("src/file_storag.rs", MAX_SUGGESTIONS),
+            vec!["src/file_storage.rs"]
+        );
+        assert!(suggester
+            .suggest("StorageEngine", MAX_SUGGESTIONS)
+            .is_empty());
+        assert!(suggester.suggest("network", MAX_SUGGESTIONS).is_empty());
+    }
+
+    #[test]
+    fn corrects_misspelled_words_of_phrases() {
+        let suggester = suggester();
+        assert_eq!(
+            suggester.suggest("open storgae engin", MAX_SUGGESTIONS),
+            vec!["open storage engine"]
+        );
+        assert!(suggester
+            .suggest("storage engine", MAX_SUGGESTIONS)
+            .is_empty());
+    }
+}
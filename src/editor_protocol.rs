@@ -0,0 +1,415 @@
+// Editor protocol module - Per-symbol impact hints for editor extensions over stdio
+//
+// `kotadb editor-server` reads one JSON request per line on stdin and answers with one JSON line
+// on stdout. It is much smaller than LSP on purpose: an extension asks for the metrics of the
+// symbols in a file (callers, churn, tests calling them) and renders them as code lenses next to
+// the definitions, while navigation and diagnostics stay with the language server. Answers come
+// from the binary relationship engine held in memory, so each request takes milliseconds.
+//
+//   -> {"id": 1, "method": "initialize", "params": {"root": "/home/me/project"}}
+//   -> {"id": 2, "method": "fileMetrics", "params": {"file": "src/lib.rs"}}
+//   -> {"id": 3, "method": "symbolAt", "params": {"file": "src/lib.rs", "line": 41, "character": 4}}
+//   -> {"id": 4, "method": "reload"}
+//   -> {"id": 5, "method": "shutdown"}
+//   <- {"id": 2, "result": {"file": "src/lib.rs", "churn": 7, "symbols": [...]}}
+//   <- {"id": 9, "error": {"code": -32602, "message": "..."}}
+//
+// Lines are 0-based as in editor APIs; characters are byte offsets within the line.
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use serde_json::{json, Value as JsonValue};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::sync::Mutex;
+
+use crate::binary_relationship_engine::{BinaryRelationshipEngine, SymbolLinks};
+use crate::contracts::Storage;
+use crate::metrics::performance::{LockedResource, TimedLock};
+use crate::relationship_query::RelationshipQueryConfig;
+use crate::services::churn_by_file;
+
+/// Version of the request and response shapes, reported by `initialize`
+pub const EDITOR_PROTOCOL_VERSION: u32 = 1;
+
+const PARSE_ERROR: i64 = -32700;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const SERVER_ERROR: i64 = -32000;
+
+const METHODS: [&str; 5] = [
+    "initialize",
+    "fileMetrics",
+    "symbolAt",
+    "reload",
+    "shutdown",
+];
+
+#[derive(Debug, Deserialize)]
+struct Request {
+    #[serde(default)]
+    id: JsonValue,
+    method: String,
+    #[serde(default)]
+    params: JsonValue,
+}
+
+#[derive(Debug, Deserialize)]
+struct InitializeParams {
+    root: Option<PathBuf>,
+}
+
+#[derive(Debug, Deserialize)]
+struct FileParams {
+    file: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct PositionParams {
+    file: String,
+    line: u32,
+    #[serde(default)]
+    character: u32,
+}
+
+/// Failure reported to the editor as `{"code", "message"}`
+struct RequestError {
+    code: i64,
+    message: String,
+}
+
+impl RequestError {
+    fn invalid_params(e: impl std::fmt::Display) -> Self {
+        Self {
+            code: INVALID_PARAMS,
+            message: e.to_string(),
+        }
+    }
+
+    fn server(e: impl std::fmt::Display) -> Self {
+        Self {
+            code: SERVER_ERROR,
+            message: e.to_string(),
+        }
+    }
+}
+
+/// State behind one editor connection
+pub struct EditorServer {
+    db_path: PathBuf,
+    root: PathBuf,
+    storage: Arc<Mutex<dyn Storage>>,
+    engine: BinaryRelationshipEngine,
+    churn: HashMap<String, usize>,
+    commits: usize,
+}
+
+impl EditorServer {
+    /// Load the symbol database, dependency graph and commit history of `db_path`
+    ///
+    /// `root` is the checkout absolute file paths in requests are made relative to; an
+    /// `initialize` request may replace it.
+    pub async fn open(
+        db_path: &Path,
+        root: &Path,
+        storage: Arc<Mutex<dyn Storage>>,
+    ) -> Result<Self> {
+        let engine = BinaryRelationshipEngine::new(db_path, RelationshipQueryConfig::default())
+            .await
+            .context("Failed to load the relationship engine")?;
+        let mut server = Self {
+            db_path: db_path.to_path_buf(),
+            root: root.to_path_buf(),
+            storage,
+            engine,
+            churn: HashMap::new(),
+            commits: 0,
+        };
+        server.load_churn().await?;
+        Ok(server)
+    }
+
+    async fn load_churn(&mut self) -> Result<()> {
+        let documents = self
+            .storage
+            .lock_timed(LockedResource::Storage)
+            .await
+            .list_all()
+            .await?;
+        (self.churn, self.commits) = churn_by_file(&documents);
+        Ok(())
+    }
+
+    /// Answer requests from `reader` on `writer` until `shutdown` or end of input
+    pub async fn serve<R, W>(&mut self, reader: R, mut writer: W) -> Result<()>
+    where
+        R: AsyncBufRead + Unpin,
+        W: AsyncWrite + Unpin,
+    {
+        let mut lines = reader.lines();
+        while let Some(line) = lines.next_line().await? {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let (response, shutdown) = self.handle_line(&line).await;
+            let mut encoded = serde_json::to_vec(&response)?;
+            encoded.push(b'\n');
+            writer.write_all(&encoded).await?;
+            writer.flush().await?;
+            if shutdown {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Response to one request line, and whether the connection should close
+    pub async fn handle_line(&mut self, line: &str) -> (JsonValue, bool) {
+        let request: Request = match serde_json::from_str(line) {
+            Ok(request) => request,
+            Err(e) => {
+                let error = RequestError {
+                    code: PARSE_ERROR,
+                    message: format!("Invalid request: {}", e),
+                };
+                return (error_response(JsonValue::Null, error), false);
+            }
+        };
+        let shutdown = request.method == "shutdown";
+        let response = match self.dispatch(&request.method, request.params).await {
+            Ok(result) => json!({ "id": request.id, "result": result }),
+            Err(error) => error_response(request.id, error),
+        };
+        (response, shutdown)
+    }
+
+    async fn dispatch(
+        &mut self,
+        method: &str,
+        params: JsonValue,
+    ) -> std::result::Result<JsonValue, RequestError> {
+        match method {
+            "initialize" => {
+                let params: InitializeParams = parse_params(params)?;
+                if let Some(root) = params.root {
+                    self.root = root;
+                }
+                let stats = self.engine.get_stats();
+                Ok(json!({
+                    "name": "kotadb",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "protocolVersion": EDITOR_PROTOCOL_VERSION,
+                    "methods": METHODS,
+                    "symbols": stats.binary_symbols_loaded,
+                    "graphNodes": stats.graph_nodes_loaded,
+                    "commits": self.commits,
+                }))
+            }
+            "fileMetrics" => {
+                let params: FileParams = parse_params(params)?;
+                let start = Instant::now();
+                let file = self.relative_path(&params.file);
+                let symbols = self
+                    .engine
+                    .file_symbol_links(&file)
+                    .map_err(RequestError::server)?;
+                let churn = self.churn.get(&file).copied().unwrap_or(0);
+                let symbols: Vec<JsonValue> = symbols
+                    .iter()
+                    .map(|symbol| symbol_json(symbol, churn))
+                    .collect();
+                Ok(json!({
+                    "file": file,
+                    "churn": churn,
+                    "symbols": symbols,
+                    "elapsedMs": start.elapsed().as_secs_f64() * 1000.0,
+                }))
+            }
+            "symbolAt" => {
+                let params: PositionParams = parse_params(params)?;
+                let file = self.relative_path(&params.file);
+                let symbols = self
+                    .engine
+                    .file_symbol_links(&file)
+                    .map_err(RequestError::server)?;
+                let churn = self.churn.get(&file).copied().unwrap_or(0);
+                // Innermost symbol whose range contains the position
+                let line = params.line + 1;
+                let symbol = symbols
+                    .iter()
+                    .filter(|symbol| {
+                        (symbol.start_line, symbol.start_column) <= (line, params.character)
+                            && (line, params.character) <= (symbol.end_line, symbol.end_column)
+                    })
+                    .min_by_key(|symbol| symbol.end_line.saturating_sub(symbol.start_line));
+                Ok(symbol.map_or(JsonValue::Null, |symbol| symbol_json(symbol, churn)))
+            }
+            "reload" => {
+                self.engine = BinaryRelationshipEngine::new(
+                    &self.db_path,
+                    RelationshipQueryConfig::default(),
+                )
+                .await
+                .map_err(RequestError::server)?;
+                self.load_churn().await.map_err(RequestError::server)?;
+                Ok(json!({ "symbols": self.engine.get_stats().binary_symbols_loaded }))
+            }
+            "shutdown" => Ok(JsonValue::Null),
+            other => Err(RequestError {
+                code: METHOD_NOT_FOUND,
+                message: format!("Unknown method '{}'", other),
+            }),
+        }
+    }
+
+    /// Path as stored in the index: relative to the root, `/`-separated, without `./`
+    fn relative_path(&self, file: &str) -> String {
+        let path = Path::new(file);
+        let relative = path.strip_prefix(&self.root).unwrap_or(path);
+        relative
+            .to_string_lossy()
+            .replace('\\', "/")
+            .trim_start_matches("./")
+            .to_string()
+    }
+}
+
+fn parse_params<T: serde::de::DeserializeOwned>(
+    params: JsonValue,
+) -> std::result::Result<T, RequestError> {
+    let params = if params.is_null() { json!({}) } else { params };
+    serde_json::from_value(params).map_err(RequestError::invalid_params)
+}
+
+fn error_response(id: JsonValue, error: RequestError) -> JsonValue {
+    json!({ "id": id, "error": { "code": error.code, "message": error.message } })
+}
+
+fn symbol_json(symbol: &SymbolLinks, churn: usize) -> JsonValue {
+    let tests: Vec<JsonValue> = symbol
+        .tests
+        .iter()
+        .map(|test| {
+            json!({
+                "name": test.name,
+                "file": test.file_path,
+                "line": test.start_line.saturating_sub(1),
+            })
+        })
+        .collect();
+    json!({
+        "name": symbol.name,
+        "kind": symbol.kind,
+        "range": {
+            "start": { "line": symbol.start_line.saturating_sub(1), "character": symbol.start_column },
+            "end": { "line": symbol.end_line.saturating_sub(1), "character": symbol.end_column },
+        },
+        "callers": symbol.callers,
+        "churn": churn,
+        "tests": tests,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binary_relationship_bridge::BinaryRelationshipBridge;
+    use crate::binary_symbols::BinarySymbolWriter;
+    use uuid::Uuid;
+
+    async fn server(dir: &Path) -> EditorServer {
+        let mut writer = BinarySymbolWriter::new();
+        writer.add_symbol(Uuid::new_v4(), "helper", 1, "src/lib.rs", 1, 3, None);
+        writer.add_symbol(Uuid::new_v4(), "run", 1, "src/main.rs", 1, 3, None);
+        writer.add_symbol(Uuid::new_v4(), "test_helper", 1, "tests/lib.rs", 1, 3, None);
+        writer.write_to_file(&dir.join("symbols.kota")).unwrap();
+        let files = vec![
+            (
+                PathBuf::from("src/lib.rs"),
+                b"fn helper() -> u32 {\n    1\n}\n".to_vec(),
+            ),
+            (
+                PathBuf::from("src/main.rs"),
+                b"fn run() -> u32 {\n    helper()\n}\n".to_vec(),
+            ),
+            (
+                PathBuf::from("tests/lib.rs"),
+                b"fn test_helper() {\n    helper();\n}\n".to_vec(),
+            ),
+        ];
+        let graph = BinaryRelationshipBridge::new()
+            .extract_relationships(dir.join("symbols.kota"), dir, &files)
+            .unwrap();
+        BinaryRelationshipEngine::save_dependency_graph(&graph, &dir.join("dependency_graph.bin"))
+            .unwrap();
+
+        let storage = crate::file_storage::create_file_storage(
+            dir.join("storage").to_str().unwrap(),
+            Some(10),
+        )
+        .await
+        .unwrap();
+        EditorServer::open(dir, Path::new("/work"), Arc::new(Mutex::new(storage)))
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn file_metrics_report_callers_and_tests_of_each_symbol() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let mut server = server(dir.path()).await;
+
+        let input = concat!(
+            r#"{"id": 1, "method": "fileMetrics", "params": {"file": "/work/src/lib.rs"}}"#,
+            "\n",
+            r#"{"id": 2, "method": "symbolAt", "params": {"file": "src/main.rs", "line": 1}}"#,
+            "\n",
+            r#"{"id": 3, "method": "shutdown"}"#,
+            "\n",
+            r#"{"id": 4, "method": "fileMetrics", "params": {"file": "src/lib.rs"}}"#,
+            "\n",
+        );
+        let mut output = Vec::new();
+        server.serve(input.as_bytes(), &mut output).await.unwrap();
+        let responses: Vec<JsonValue> = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+
+        // Requests after shutdown are not answered
+        assert_eq!(responses.len(), 3);
+        let metrics = &responses[0]["result"];
+        assert_eq!(metrics["file"], "src/lib.rs");
+        let helper = &metrics["symbols"][0];
+        assert_eq!(helper["name"], "helper");
+        assert_eq!(helper["range"]["start"]["line"], 0);
+        assert_eq!(helper["callers"], 2);
+        assert_eq!(helper["tests"][0]["name"], "test_helper");
+        assert_eq!(helper["tests"][0]["file"], "tests/lib.rs");
+
+        let run = &responses[1]["result"];
+        assert_eq!(run["name"], "run");
+        assert_eq!(run["callers"], 0);
+        assert_eq!(responses[2]["result"], JsonValue::Null);
+    }
+
+    #[tokio::test]
+    async fn malformed_requests_get_error_responses() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let mut server = server(dir.path()).await;
+
+        let (response, _) = server.handle_line("{not json").await;
+        assert_eq!(response["error"]["code"], PARSE_ERROR);
+        let (response, _) = server.handle_line(r#"{"id": 7, "method": "hover"}"#).await;
+        assert_eq!(response["id"], 7);
+        assert_eq!(response["error"]["code"], METHOD_NOT_FOUND);
+        let (response, _) = server
+            .handle_line(r#"{"id": 8, "method": "fileMetrics", "params": {}}"#)
+            .await;
+        assert_eq!(response["error"]["code"], INVALID_PARAMS);
+    }
+}
@@ -687,6 +687,31 @@ impl EmbeddingService {
     }
 }
 
+/// Environment variable holding the OpenAI API key for CLI commands that embed text
+pub const OPENAI_API_KEY_ENV: &str = "OPENAI_API_KEY";
+
+/// Embedding configuration taken from the environment, if any
+///
+/// Uses OpenAI `text-embedding-3-small` when `OPENAI_API_KEY` is set.
+pub fn config_from_env() -> Option<EmbeddingConfig> {
+    match std::env::var(OPENAI_API_KEY_ENV) {
+        Ok(api_key) if !api_key.trim().is_empty() => Some(models::openai_text_embedding_3_small(
+            api_key.trim().to_string(),
+        )),
+        _ => None,
+    }
+}
+
+/// Embedding provider configured through the environment, if any
+///
+/// See [`config_from_env`].
+pub fn provider_from_env() -> Result<Option<Box<dyn EmbeddingProvider>>> {
+    match config_from_env() {
+        Some(config) => Ok(Some(Box::new(OpenAIEmbeddingProvider::new(config)?))),
+        None => Ok(None),
+    }
+}
+
 /// Utility functions for common embedding models
 pub mod models {
     use super::*;
@@ -0,0 +1,663 @@
+//! Error type flow analysis for Rust sources
+//!
+//! `kotadb error-flows <ErrorType>` follows one error type through the indexed code:
+//! where it is defined and constructed (`ErrorType::Variant(..)`, `ErrorType::new(..)`,
+//! struct literals, `.map_err(ErrorType::from)`), what converts into and out of it
+//! (`impl From<_>` and thiserror's `#[from]`), where it is handled (match arms, `if let`,
+//! `matches!`, `downcast_ref::<ErrorType>()`), and which public functions let it escape
+//! through their return type, directly or through a `type Result<T> = ...` alias used in
+//! the declaring module or imported from it.
+//!
+//! The analysis reads source text rather than resolved types, so two error types sharing
+//! a name are reported together; the definitions list shows when that happens.
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Serialize;
+
+use crate::binary_symbols::FunctionRanges;
+
+/// Longest source excerpt kept per site
+const MAX_CODE_CHARS: usize = 160;
+
+static FROM_IMPL: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"\bimpl\s*(?:<[^{]*?>)?\s*(?:[\w:]+::)?From<(.+?)>\s+for\s+([\w:]+)")
+        .expect("valid From impl pattern")
+});
+static FROM_ATTRIBUTE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"#\[from\]\s*(?:#\[[^\]]*\]\s*)*(?:\w+\s*:\s+)?([\w:]+(?:<[^>]*>)?)")
+        .expect("valid #[from] pattern")
+});
+static PUBLIC_FN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"\bpub\s+(?:const\s+)?(?:async\s+)?(?:unsafe\s+)?(?:extern\s+\S+\s+)?fn\s+(\w+)")
+        .expect("valid public fn pattern")
+});
+static USE_DECLARATION: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\buse\s+([^;]+);").expect("valid use pattern"));
+static TYPE_ALIAS: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"\btype\s+(\w+)\s*(?:<[^=]*>)?\s*=([^;]*);").expect("valid type alias pattern")
+});
+
+/// A line of code involving the error type
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ErrorSite {
+    pub file_path: String,
+    /// 1-based line
+    pub line: usize,
+    /// Function or method containing the site
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub symbol: Option<String>,
+    /// The source line, trimmed
+    pub code: String,
+}
+
+/// How a conversion between error types is declared
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConversionKind {
+    /// `impl From<A> for B`
+    FromImpl,
+    /// `#[from]` on a variant field (thiserror)
+    FromAttribute,
+}
+
+/// A conversion from one error type into another, as used by `?`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ErrorConversion {
+    pub from: String,
+    pub to: String,
+    pub kind: ConversionKind,
+    pub file_path: String,
+    pub line: usize,
+}
+
+/// A public function whose return type carries the error type out of its crate
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ErrorEscape {
+    pub function: String,
+    pub file_path: String,
+    pub line: usize,
+    /// Return type as written
+    pub returns: String,
+}
+
+/// Where an error type is defined, constructed, converted, handled and escapes
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ErrorFlows {
+    pub error_type: String,
+    pub definitions: Vec<ErrorSite>,
+    pub constructions: Vec<ErrorSite>,
+    /// Conversions producing the error type
+    pub conversions_in: Vec<ErrorConversion>,
+    /// Conversions turning the error type into another
+    pub conversions_out: Vec<ErrorConversion>,
+    pub handlers: Vec<ErrorSite>,
+    pub escapes: Vec<ErrorEscape>,
+}
+
+impl ErrorFlows {
+    /// Trace `error_type` (a plain or path-qualified type name) through Rust source files
+    pub fn analyze<'a>(
+        error_type: &str,
+        files: impl IntoIterator<Item = (&'a str, &'a str)>,
+        functions: &FunctionRanges,
+    ) -> Self {
+        let name = last_segment(error_type).to_string();
+        let mut flows = Self {
+            error_type: name.clone(),
+            ..Self::default()
+        };
+        if name.is_empty() {
+            return flows;
+        }
+        let escaped = regex::escape(&name);
+        let definition = Regex::new(&format!(r"\b(?:enum|struct)\s+{}\b", escaped))
+            .expect("escaped definition pattern");
+        let path_use = Regex::new(&format!(r"\b{}::(\w+)", escaped)).expect("escaped path pattern");
+        let literal =
+            Regex::new(&format!(r"\b{}\s*\{{", escaped)).expect("escaped struct literal pattern");
+        let downcast = Regex::new(&format!(r"\bdowncast(?:_ref|_mut)?::<\s*{}\s*>", escaped))
+            .expect("escaped downcast pattern");
+        let mentions = Regex::new(&format!(r"\b{}\b", escaped)).expect("escaped name pattern");
+
+        let files: Vec<(&str, String, &str)> = files
+            .into_iter()
+            .filter(|(path, _)| path.ends_with(".rs"))
+            .map(|(path, content)| (path, mask_comments_and_strings(content), content))
+            .collect();
+
+        // `type Result<T> = std::result::Result<T, Error>;` carries the error type into the
+        // declaring module and modules importing the alias from it
+        let aliases: Vec<(String, &str, String)> = files
+            .iter()
+            .filter(|(_, code, _)| code.contains(name.as_str()))
+            .flat_map(|(path, code, _)| {
+                TYPE_ALIAS
+                    .captures_iter(code)
+                    .filter(|alias| mentions.is_match(&alias[2]))
+                    .map(|alias| (alias[1].to_string(), *path, module_name(path)))
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+
+        for (path, code, content) in &files {
+            let local_aliases: Vec<&str> = aliases
+                .iter()
+                .filter(|(alias, defined_in, module)| {
+                    defined_in == path
+                        || USE_DECLARATION.captures_iter(code).any(|declaration| {
+                            let words: Vec<&str> = declaration[1]
+                                .split(|c: char| !c.is_alphanumeric() && c != '_')
+                                .collect();
+                            words.contains(&alias.as_str()) && words.contains(&module.as_str())
+                        })
+                })
+                .map(|(alias, _, _)| alias.as_str())
+                .collect();
+            if !code.contains(name.as_str()) && local_aliases.is_empty() {
+                continue;
+            }
+
+            let lines = LineIndex::new(content);
+            let site = |offset: usize| {
+                let line = lines.line_of(offset);
+                ErrorSite {
+                    file_path: path.to_string(),
+                    line,
+                    symbol: functions.enclosing(path, line).map(str::to_string),
+                    code: lines.text(line),
+                }
+            };
+
+            for m in definition.find_iter(code) {
+                flows.definitions.push(site(m.start()));
+                if code[m.start()..].starts_with("enum") {
+                    let body = block_after(code, m.end());
+                    for from in FROM_ATTRIBUTE.captures_iter(&code[body.clone()]) {
+                        flows.conversions_in.push(ErrorConversion {
+                            from: from[1].to_string(),
+                            to: name.clone(),
+                            kind: ConversionKind::FromAttribute,
+                            file_path: path.to_string(),
+                            line: lines.line_of(body.start + from.get(0).expect("match").start()),
+                        });
+                    }
+                }
+            }
+
+            for from in FROM_IMPL.captures_iter(code) {
+                let (source, target) = (from[1].trim(), &from[2]);
+                let line = lines.line_of(from.get(0).expect("match").start());
+                let conversion = |from: &str, to: &str| ErrorConversion {
+                    from: from.to_string(),
+                    to: to.to_string(),
+                    kind: ConversionKind::FromImpl,
+                    file_path: path.to_string(),
+                    line,
+                };
+                if last_segment(target) == name {
+                    flows.conversions_in.push(conversion(source, &name));
+                } else if last_segment(source) == name {
+                    flows.conversions_out.push(conversion(&name, target));
+                }
+            }
+
+            for m in path_use.find_iter(code) {
+                if is_use_declaration(code, m.start()) {
+                    continue;
+                }
+                if is_pattern(code, m.start(), m.end()) {
+                    flows.handlers.push(site(m.start()));
+                } else {
+                    flows.constructions.push(site(m.start()));
+                }
+            }
+            for m in literal.find_iter(code) {
+                let before = code[..m.start()].trim_end();
+                let is_declaration = ["struct", "enum", "union", "for", "impl", "->", "fn"]
+                    .iter()
+                    .any(|keyword| before.ends_with(keyword))
+                    || before.ends_with('>');
+                if is_declaration || is_use_declaration(code, m.start()) {
+                    continue;
+                }
+                if is_pattern(code, m.start(), m.end() - 1) {
+                    flows.handlers.push(site(m.start()));
+                } else {
+                    flows.constructions.push(site(m.start()));
+                }
+            }
+            for m in downcast.find_iter(code) {
+                flows.handlers.push(site(m.start()));
+            }
+
+            for function in PUBLIC_FN.captures_iter(code) {
+                let header = function.get(0).expect("match");
+                let signature_end = code[header.end()..]
+                    .find(['{', ';'])
+                    .map_or(code.len(), |i| header.end() + i);
+                let signature = &code[header.end()..signature_end];
+                let Some(arrow) = signature.find("->") else {
+                    continue;
+                };
+                let returns = signature[arrow + 2..]
+                    .split(" where")
+                    .next()
+                    .unwrap_or_default()
+                    .split_whitespace()
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                let escapes = mentions.is_match(&returns)
+                    || returns
+                        .split(|c: char| !c.is_alphanumeric() && c != '_')
+                        .any(|word| local_aliases.contains(&word));
+                if escapes {
+                    flows.escapes.push(ErrorEscape {
+                        function: function[1].to_string(),
+                        file_path: path.to_string(),
+                        line: lines.line_of(header.start()),
+                        returns,
+                    });
+                }
+            }
+        }
+
+        flows
+            .constructions
+            .sort_by(|a, b| (&a.file_path, a.line).cmp(&(&b.file_path, b.line)));
+        flows.constructions.dedup();
+        flows
+            .handlers
+            .sort_by(|a, b| (&a.file_path, a.line).cmp(&(&b.file_path, b.line)));
+        flows.handlers.dedup();
+        flows
+    }
+
+    /// Keep at most `limit` entries per section
+    pub fn truncate(&mut self, limit: usize) {
+        self.definitions.truncate(limit);
+        self.constructions.truncate(limit);
+        self.conversions_in.truncate(limit);
+        self.conversions_out.truncate(limit);
+        self.handlers.truncate(limit);
+        self.escapes.truncate(limit);
+    }
+
+    /// Sections for definitions, constructions, conversions, handlers and escapes
+    pub fn to_markdown(&self) -> String {
+        let mut output = format!("# Error flows of `{}`\n", self.error_type);
+        if self.definitions.is_empty() && self.constructions.is_empty() {
+            output.push_str("\nNo definition or construction found in indexed Rust files.\n");
+        }
+
+        let sites = |output: &mut String, title: &str, sites: &[ErrorSite]| {
+            if sites.is_empty() {
+                return;
+            }
+            output.push_str(&format!("\n## {} ({})\n", title, sites.len()));
+            for site in sites {
+                output.push_str(&format!(
+                    "- {}:{}{}: `{}`\n",
+                    site.file_path,
+                    site.line,
+                    site.symbol
+                        .as_ref()
+                        .map(|symbol| format!(" in `{}`", symbol))
+                        .unwrap_or_default(),
+                    site.code
+                ));
+            }
+        };
+        let conversions = |output: &mut String, title: &str, conversions: &[ErrorConversion]| {
+            if conversions.is_empty() {
+                return;
+            }
+            output.push_str(&format!("\n## {} ({})\n", title, conversions.len()));
+            for conversion in conversions {
+                let via = match conversion.kind {
+                    ConversionKind::FromImpl => "impl From",
+                    ConversionKind::FromAttribute => "#[from]",
+                };
+                output.push_str(&format!(
+                    "- `{}` -> `{}` ({}) at {}:{}\n",
+                    conversion.from, conversion.to, via, conversion.file_path, conversion.line
+                ));
+            }
+        };
+
+        sites(&mut output, "Definitions", &self.definitions);
+        sites(&mut output, "Constructed", &self.constructions);
+        conversions(&mut output, "Converted from", &self.conversions_in);
+        conversions(&mut output, "Converted into", &self.conversions_out);
+        sites(&mut output, "Handled", &self.handlers);
+        if !self.escapes.is_empty() {
+            output.push_str(&format!(
+                "\n## Escapes public APIs ({})\n",
+                self.escapes.len()
+            ));
+            for escape in &self.escapes {
+                output.push_str(&format!(
+                    "- `{}` -> `{}` at {}:{}\n",
+                    escape.function, escape.returns, escape.file_path, escape.line
+                ));
+            }
+        }
+        output
+    }
+}
+
+/// Word naming a file's module in `use` paths: its stem, the directory of a `mod.rs`, or
+/// `crate` for a crate root
+fn module_name(path: &str) -> String {
+    let mut components = path.rsplit(['/', '\\']);
+    let stem = components
+        .next()
+        .unwrap_or_default()
+        .trim_end_matches(".rs");
+    match stem {
+        "lib" | "main" => "crate".to_string(),
+        "mod" => components.next().unwrap_or("crate").to_string(),
+        stem => stem.to_string(),
+    }
+}
+
+fn last_segment(path: &str) -> &str {
+    let path = path.split('<').next().unwrap_or(path).trim();
+    path.rsplit("::").next().unwrap_or(path)
+}
+
+/// Whether the statement containing `offset` is a `use` declaration
+fn is_use_declaration(code: &str, offset: usize) -> bool {
+    let statement_start = code[..offset].rfind([';', '{', '}']).map_or(0, |i| i + 1);
+    let statement = code[statement_start..offset].trim_start();
+    statement.starts_with("use ")
+        || statement.starts_with("pub use ")
+        || statement.starts_with("pub(crate) use ")
+}
+
+/// Whether the type path spanning `start..end` is matched against rather than built:
+/// a match arm, an `if let`/`while let` pattern or a `matches!` pattern
+fn is_pattern(code: &str, start: usize, end: usize) -> bool {
+    let line_start = code[..start].rfind('\n').map_or(0, |i| i + 1);
+    let prefix = &code[line_start..start];
+    if prefix.contains("matches!(") {
+        return true;
+    }
+    let binding = prefix.contains("if let ")
+        || prefix.contains("while let ")
+        || prefix.trim_start().starts_with("let ");
+    // `if let PATTERN = value` and `let PATTERN = value else` put the pattern before the `=`;
+    // `let name = VALUE` puts the construction after it
+    if binding && !prefix.replace("==", "").replace("=>", "").contains('=') {
+        return true;
+    }
+
+    let mut rest = code[end..].trim_start();
+    if rest.starts_with('(') || rest.starts_with('{') {
+        let group = block_after(rest, 0);
+        rest = rest[group.end.min(rest.len())..].trim_start();
+        rest = rest.strip_prefix(['}', ')']).unwrap_or(rest).trim_start();
+    }
+    // Variant patterns nested in `Err(..)` or tuples close their parentheses first
+    let rest = rest.trim_start_matches(|c: char| c == ')' || c.is_whitespace());
+    rest.starts_with("=>")
+        || rest.starts_with("if ")
+        || (rest.starts_with('|') && !rest.starts_with("||"))
+}
+
+/// Byte range inside the `{..}`, `(..)` or `[..]` group opening at or after `from`
+fn block_after(code: &str, from: usize) -> std::ops::Range<usize> {
+    let Some(open) = code[from..].find(['{', '(', '[']).map(|i| from + i) else {
+        return from..from;
+    };
+    let mut depth = 0usize;
+    for (i, byte) in code.bytes().enumerate().skip(open) {
+        match byte {
+            b'{' | b'(' | b'[' => depth += 1,
+            b'}' | b')' | b']' => {
+                depth -= 1;
+                if depth == 0 {
+                    return open + 1..i;
+                }
+            }
+            _ => {}
+        }
+    }
+    open + 1..code.len()
+}
+
+/// Offsets of line starts, for turning byte offsets into 1-based lines
+struct LineIndex<'a> {
+    content: &'a str,
+    starts: Vec<usize>,
+}
+
+impl<'a> LineIndex<'a> {
+    fn new(content: &'a str) -> Self {
+        let starts = std::iter::once(0)
+            .chain(content.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        Self { content, starts }
+    }
+
+    fn line_of(&self, offset: usize) -> usize {
+        self.starts.partition_point(|start| *start <= offset)
+    }
+
+    fn text(&self, line: usize) -> String {
+        let text = self
+            .content
+            .lines()
+            .nth(line - 1)
+            .unwrap_or_default()
+            .trim();
+        match text.char_indices().nth(MAX_CODE_CHARS) {
+            Some((end, _)) => format!("{}...", &text[..end]),
+            None => text.to_string(),
+        }
+    }
+}
+
+/// Source with comments and string contents blanked out, byte for byte, so patterns
+/// don't match inside them and offsets still line up
+pub(crate) fn mask_comments_and_strings(source: &str) -> String {
+    let bytes = source.as_bytes();
+    let mut masked = bytes.to_vec();
+    let blank = |masked: &mut Vec<u8>, range: std::ops::Range<usize>| {
+        for byte in &mut masked[range] {
+            if *byte != b'\n' {
+                *byte = b' ';
+            }
+        }
+    };
+
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'/' if bytes.get(i + 1) == Some(&b'/') => {
+                let end = source[i..].find('\n').map_or(bytes.len(), |n| i + n);
+                blank(&mut masked, i..end);
+                i = end;
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                let mut depth = 0usize;
+                let mut j = i;
+                while j < bytes.len() {
+                    if bytes[j..].starts_with(b"/*") {
+                        depth += 1;
+                        j += 2;
+                    } else if bytes[j..].starts_with(b"*/") {
+                        depth -= 1;
+                        j += 2;
+                        if depth == 0 {
+                            break;
+                        }
+                    } else {
+                        j += 1;
+                    }
+                }
+                blank(&mut masked, i..j);
+                i = j;
+            }
+            b'r' if (i == 0 || !is_ident_byte(bytes[i - 1]))
+                && matches!(bytes.get(i + 1), Some(b'"' | b'#')) =>
+            {
+                let hashes = bytes[i + 1..].iter().take_while(|b| **b == b'#').count();
+                if bytes.get(i + 1 + hashes) != Some(&b'"') {
+                    i += 1;
+                    continue;
+                }
+                let body = i + 2 + hashes;
+                let terminator = format!("\"{}", "#".repeat(hashes));
+                let end = source[body..]
+                    .find(&terminator)
+                    .map_or(bytes.len(), |n| body + n);
+                blank(&mut masked, body..end);
+                i = end + terminator.len();
+            }
+            b'"' => {
+                let mut j = i + 1;
+                while j < bytes.len() && bytes[j] != b'"' {
+                    j += if bytes[j] == b'\\' { 2 } else { 1 };
+                }
+                blank(&mut masked, i + 1..j.min(bytes.len()));
+                i = j + 1;
+            }
+            b'\'' => {
+                // Char literals; a lifetime has no closing quote right after one character
+                let close = if bytes.get(i + 1) == Some(&b'\\') {
+                    source[i + 2..].find('\'').map(|n| i + 2 + n)
+                } else {
+                    source[i + 1..]
+                        .char_indices()
+                        .nth(1)
+                        .filter(|(_, c)| *c == '\'')
+                        .map(|(n, _)| i + 1 + n)
+                };
+                match close {
+                    Some(close) => {
+                        blank(&mut masked, i + 1..close);
+                        i = close + 1;
+                    }
+                    None => i += 1,
+                }
+            }
+            _ => i += 1,
+        }
+    }
+    String::from_utf8(masked).unwrap_or_else(|_| source.to_string())
+}
+
+fn is_ident_byte(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || byte == b'_'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ERRORS: &str = r#"
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum StorageError {
+    #[error("document {0} not found")]
+    NotFound(String),
+    #[error("io: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+pub type Result<T> = std::result::Result<T, StorageError>;
+
+impl From<StorageError> for ApiError {
+    fn from(e: StorageError) -> Self {
+        ApiError::Internal(e.to_string())
+    }
+}
+"#;
+
+    const STORE: &str = r#"
+use crate::errors::{Result, StorageError};
+
+pub fn get(id: &str) -> Result<Doc> {
+    // StorageError::NotFound is returned for unknown ids
+    lookup(id).ok_or_else(|| StorageError::NotFound(id.to_string()))
+}
+
+pub(crate) fn load(path: &Path) -> std::result::Result<Vec<u8>, StorageError> {
+    std::fs::read(path).map_err(StorageError::from)
+}
+
+fn serve(id: &str) -> Response {
+    match get(id) {
+        Ok(doc) => Response::ok(doc),
+        Err(StorageError::NotFound(_)) => Response::not_found("StorageError::Io"),
+        Err(e) => Response::error(e),
+    }
+}
+"#;
+
+    fn flows() -> ErrorFlows {
+        ErrorFlows::analyze(
+            "crate::errors::StorageError",
+            [("src/errors.rs", ERRORS), ("src/store.rs", STORE)],
+            &FunctionRanges::from_ranges([
+                ("get".to_string(), "src/store.rs".to_string(), 4, 7),
+                ("serve".to_string(), "src/store.rs".to_string(), 13, 19),
+            ]),
+        )
+    }
+
+    #[test]
+    fn finds_constructions_handlers_and_conversions() {
+        let flows = flows();
+        assert_eq!(flows.error_type, "StorageError");
+        assert_eq!(flows.definitions.len(), 1);
+        assert_eq!(flows.definitions[0].line, 5);
+
+        let constructions: Vec<(usize, Option<&str>)> = flows
+            .constructions
+            .iter()
+            .map(|site| (site.line, site.symbol.as_deref()))
+            .collect();
+        assert_eq!(constructions, vec![(6, Some("get")), (10, None)]);
+        let handlers: Vec<(usize, Option<&str>)> = flows
+            .handlers
+            .iter()
+            .map(|site| (site.line, site.symbol.as_deref()))
+            .collect();
+        assert_eq!(handlers, vec![(16, Some("serve"))]);
+
+        assert_eq!(flows.conversions_in.len(), 1);
+        assert_eq!(flows.conversions_in[0].from, "std::io::Error");
+        assert_eq!(flows.conversions_in[0].kind, ConversionKind::FromAttribute);
+        assert_eq!(flows.conversions_out.len(), 1);
+        assert_eq!(flows.conversions_out[0].to, "ApiError");
+        assert_eq!(flows.conversions_out[0].line, 14);
+    }
+
+    #[test]
+    fn public_functions_returning_the_error_escape() {
+        let flows = flows();
+        let escapes: Vec<(&str, &str)> = flows
+            .escapes
+            .iter()
+            .map(|escape| (escape.function.as_str(), escape.returns.as_str()))
+            .collect();
+        // `get` through the imported alias; `load` is crate-private
+        assert_eq!(escapes, vec![("get", "Result<Doc>")]);
+
+        // Without the import, `Result` is std's
+        let unrelated = STORE.replace("use crate::errors::{Result, StorageError};", "");
+        let flows = ErrorFlows::analyze(
+            "StorageError",
+            [
+                ("src/errors.rs", ERRORS),
+                ("src/store.rs", unrelated.as_str()),
+            ],
+            &FunctionRanges::default(),
+        );
+        assert!(flows.escapes.is_empty());
+        assert!(flows.to_markdown().contains("## Constructed (2)"));
+    }
+}
@@ -0,0 +1,553 @@
+//! Failure injection for storage and indices
+//!
+//! [`FailingStorage`] and [`FailingIndex`] wrap a real implementation and, following a
+//! [`FailureSchedule`], fail operations with I/O errors, complete writes only partially
+//! and add latency. Schedules are driven by a seeded RNG, so a failing run replays
+//! exactly with the same seed, and clones share one sequence so a storage and an index
+//! under the same schedule see faults in a reproducible interleaving.
+//!
+//! A partial write reaches the wrapped implementation and is still reported as failed:
+//! storages receive the document with a truncated content, indices apply the change in
+//! full. This is what a crash between writing and acknowledging looks like to callers.
+//!
+//! Only built with the `failure-injection` feature; integration tests use it to check
+//! the crash-consistency of the wrappers, the WAL and the indexing pipeline.
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::contracts::{
+    ContentTokenizer, Document, DocumentMetadata, DocumentPage, DocumentSummary, Index, Query,
+    Storage, TokenizedDocument, TreeStructureMetrics,
+};
+use crate::types::{ValidatedDocumentId, ValidatedPath};
+
+/// Kind of operation a fault is drawn for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Operation {
+    Read,
+    Write,
+    Flush,
+}
+
+/// Failure injected into one operation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+    /// Fail without touching the wrapped implementation
+    IoError,
+    /// Write to the wrapped implementation, then fail
+    PartialWrite,
+}
+
+/// What happens to one operation: an optional delay, then an optional fault
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Injection {
+    pub delay: Duration,
+    pub fault: Option<Fault>,
+}
+
+/// Faults injected so far
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FailureStats {
+    pub operations: u64,
+    pub io_errors: u64,
+    pub partial_writes: u64,
+    pub delays: u64,
+}
+
+impl FailureStats {
+    /// Operations that were reported as failed
+    pub fn failures(&self) -> u64 {
+        self.io_errors + self.partial_writes
+    }
+}
+
+struct ScheduleState {
+    rng: StdRng,
+    enabled: bool,
+    io_error_rate: f64,
+    partial_write_rate: f64,
+    latency_rate: f64,
+    latency: (Duration, Duration),
+    crash_after: Option<u64>,
+    operations: Option<Vec<Operation>>,
+    stats: FailureStats,
+}
+
+/// Seeded plan of the faults to inject
+///
+/// Clones share the RNG and the statistics.
+#[derive(Clone)]
+pub struct FailureSchedule {
+    seed: u64,
+    state: Arc<Mutex<ScheduleState>>,
+}
+
+impl FailureSchedule {
+    /// Schedule that injects nothing until rates are configured
+    pub fn seeded(seed: u64) -> Self {
+        Self {
+            seed,
+            state: Arc::new(Mutex::new(ScheduleState {
+                rng: StdRng::seed_from_u64(seed),
+                enabled: true,
+                io_error_rate: 0.0,
+                partial_write_rate: 0.0,
+                latency_rate: 0.0,
+                latency: (Duration::ZERO, Duration::ZERO),
+                crash_after: None,
+                operations: None,
+                stats: FailureStats::default(),
+            })),
+        }
+    }
+
+    /// Fail this fraction of operations with an I/O error
+    pub fn with_io_errors(self, rate: f64) -> Self {
+        self.configure(|state| state.io_error_rate = rate.clamp(0.0, 1.0))
+    }
+
+    /// Complete this fraction of writes partially
+    pub fn with_partial_writes(self, rate: f64) -> Self {
+        self.configure(|state| state.partial_write_rate = rate.clamp(0.0, 1.0))
+    }
+
+    /// Delay this fraction of operations by a duration between `min` and `max`
+    pub fn with_latency(self, rate: f64, min: Duration, max: Duration) -> Self {
+        self.configure(|state| {
+            state.latency_rate = rate.clamp(0.0, 1.0);
+            state.latency = (min, max.max(min));
+        })
+    }
+
+    /// Let the first `operations` operations through, then fail every one after them,
+    /// as if the process had crashed
+    pub fn crash_after(self, operations: u64) -> Self {
+        self.configure(|state| state.crash_after = Some(operations))
+    }
+
+    /// Inject faults into these kinds of operations only
+    pub fn only(self, operations: &[Operation]) -> Self {
+        let operations = operations.to_vec();
+        self.configure(|state| state.operations = Some(operations))
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Stop or resume injecting; operations pass through untouched while disabled
+    pub fn set_enabled(&self, enabled: bool) {
+        self.with_state(|state| state.enabled = enabled);
+    }
+
+    pub fn stats(&self) -> FailureStats {
+        self.with_state(|state| state.stats)
+    }
+
+    /// Draw what happens to the next operation of kind `operation`
+    pub fn next(&self, operation: Operation) -> Injection {
+        self.with_state(|state| {
+            if !state.enabled {
+                return Injection::default();
+            }
+            state.stats.operations += 1;
+            if let Some(crash_after) = state.crash_after {
+                if state.stats.operations > crash_after {
+                    state.stats.io_errors += 1;
+                    return Injection {
+                        delay: Duration::ZERO,
+                        fault: Some(Fault::IoError),
+                    };
+                }
+            }
+            if let Some(operations) = &state.operations {
+                if !operations.contains(&operation) {
+                    return Injection::default();
+                }
+            }
+
+            // Always draw both values so the sequence doesn't depend on the rates
+            let roll: f64 = state.rng.gen();
+            let latency_roll: f64 = state.rng.gen();
+            let fault = if roll < state.io_error_rate {
+                state.stats.io_errors += 1;
+                Some(Fault::IoError)
+            } else if operation == Operation::Write
+                && roll < state.io_error_rate + state.partial_write_rate
+            {
+                state.stats.partial_writes += 1;
+                Some(Fault::PartialWrite)
+            } else {
+                None
+            };
+            let delay = if latency_roll < state.latency_rate {
+                state.stats.delays += 1;
+                let (min, max) = state.latency;
+                if max > min {
+                    state.rng.gen_range(min..=max)
+                } else {
+                    min
+                }
+            } else {
+                Duration::ZERO
+            };
+            Injection { delay, fault }
+        })
+    }
+
+    /// Length of the prefix of `len` bytes that a partial write keeps
+    fn partial_length(&self, len: usize) -> usize {
+        self.with_state(|state| {
+            if len == 0 {
+                0
+            } else {
+                state.rng.gen_range(0..len)
+            }
+        })
+    }
+
+    fn configure(self, apply: impl FnOnce(&mut ScheduleState)) -> Self {
+        self.with_state(apply);
+        self
+    }
+
+    fn with_state<T>(&self, apply: impl FnOnce(&mut ScheduleState) -> T) -> T {
+        let mut state = self
+            .state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        apply(&mut state)
+    }
+}
+
+/// Error returned for an injected fault
+fn injected_error(operation: &str, fault: Fault) -> anyhow::Error {
+    let kind = match fault {
+        Fault::IoError => "I/O error",
+        Fault::PartialWrite => "partial write",
+    };
+    std::io::Error::other(format!("injected {} during {}", kind, operation)).into()
+}
+
+/// Delay `injection` and fail it when it is an I/O error; partial writes are left to
+/// the caller
+async fn begin(injection: Injection, operation: &str) -> Result<Option<Fault>> {
+    if !injection.delay.is_zero() {
+        tokio::time::sleep(injection.delay).await;
+    }
+    match injection.fault {
+        Some(Fault::IoError) => Err(injected_error(operation, Fault::IoError)),
+        fault => Ok(fault),
+    }
+}
+
+/// Storage wrapper that injects faults following a [`FailureSchedule`]
+pub struct FailingStorage<S: Storage> {
+    inner: S,
+    schedule: FailureSchedule,
+}
+
+impl<S: Storage> FailingStorage<S> {
+    pub fn new(inner: S, schedule: FailureSchedule) -> Self {
+        Self { inner, schedule }
+    }
+
+    pub fn schedule(&self) -> &FailureSchedule {
+        &self.schedule
+    }
+
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+
+    async fn read(&self, operation: &str) -> Result<()> {
+        begin(self.schedule.next(Operation::Read), operation).await?;
+        Ok(())
+    }
+
+    /// `document` with its content cut to a prefix, as left by an interrupted write
+    fn truncated(&self, mut document: Document) -> Document {
+        let len = self.schedule.partial_length(document.content.len());
+        document.content.truncate(len);
+        document.size = len;
+        document
+    }
+}
+
+#[async_trait]
+impl<S: Storage> Storage for FailingStorage<S> {
+    async fn open(path: &str) -> Result<Self>
+    where
+        Self: Sized,
+    {
+        Ok(Self::new(S::open(path).await?, FailureSchedule::seeded(0)))
+    }
+
+    async fn insert(&mut self, document: Document) -> Result<()> {
+        match begin(self.schedule.next(Operation::Write), "insert").await? {
+            Some(fault) => {
+                let document = self.truncated(document);
+                self.inner.insert(document).await?;
+                Err(injected_error("insert", fault))
+            }
+            None => self.inner.insert(document).await,
+        }
+    }
+
+    async fn get(&self, id: &ValidatedDocumentId) -> Result<Option<Document>> {
+        self.read("get").await?;
+        self.inner.get(id).await
+    }
+
+    async fn get_many(&self, ids: &[ValidatedDocumentId]) -> Result<Vec<Option<Document>>> {
+        self.read("get_many").await?;
+        self.inner.get_many(ids).await
+    }
+
+    async fn summary(&self, id: &ValidatedDocumentId) -> Result<Option<DocumentSummary>> {
+        self.read("summary").await?;
+        self.inner.summary(id).await
+    }
+
+    async fn get_metadata(&self, id: &ValidatedDocumentId) -> Result<Option<DocumentMetadata>> {
+        self.read("get_metadata").await?;
+        self.inner.get_metadata(id).await
+    }
+
+    async fn list_metadata(&self) -> Result<Vec<DocumentMetadata>> {
+        self.read("list_metadata").await?;
+        self.inner.list_metadata().await
+    }
+
+    async fn update(&mut self, document: Document) -> Result<()> {
+        match begin(self.schedule.next(Operation::Write), "update").await? {
+            Some(fault) => {
+                let document = self.truncated(document);
+                self.inner.update(document).await?;
+                Err(injected_error("update", fault))
+            }
+            None => self.inner.update(document).await,
+        }
+    }
+
+    async fn delete(&mut self, id: &ValidatedDocumentId) -> Result<bool> {
+        match begin(self.schedule.next(Operation::Write), "delete").await? {
+            Some(fault) => {
+                self.inner.delete(id).await?;
+                Err(injected_error("delete", fault))
+            }
+            None => self.inner.delete(id).await,
+        }
+    }
+
+    async fn list_all(&self) -> Result<Vec<Document>> {
+        self.read("list_all").await?;
+        self.inner.list_all().await
+    }
+
+    async fn list_page(
+        &self,
+        after: Option<ValidatedDocumentId>,
+        limit: usize,
+    ) -> Result<DocumentPage> {
+        self.read("list_page").await?;
+        self.inner.list_page(after, limit).await
+    }
+
+    async fn sync(&mut self) -> Result<()> {
+        begin(self.schedule.next(Operation::Flush), "sync").await?;
+        self.inner.sync().await
+    }
+
+    async fn flush(&mut self) -> Result<()> {
+        begin(self.schedule.next(Operation::Flush), "flush").await?;
+        self.inner.flush().await
+    }
+
+    async fn close(self) -> Result<()> {
+        self.inner.close().await
+    }
+}
+
+/// Index wrapper that injects faults following a [`FailureSchedule`]
+pub struct FailingIndex<I: Index> {
+    inner: I,
+    schedule: FailureSchedule,
+}
+
+impl<I: Index> FailingIndex<I> {
+    pub fn new(inner: I, schedule: FailureSchedule) -> Self {
+        Self { inner, schedule }
+    }
+
+    pub fn schedule(&self) -> &FailureSchedule {
+        &self.schedule
+    }
+
+    pub fn into_inner(self) -> I {
+        self.inner
+    }
+
+    /// Draw a write fault; `Some` means the write must be applied and then failed
+    async fn write(&self, operation: &str) -> Result<Option<Fault>> {
+        begin(self.schedule.next(Operation::Write), operation).await
+    }
+}
+
+/// Turn the result of a write applied despite `fault` into the injected error
+fn after_partial<T>(result: Result<T>, operation: &str, fault: Option<Fault>) -> Result<T> {
+    match fault {
+        Some(fault) => result.and_then(|_| Err(injected_error(operation, fault))),
+        None => result,
+    }
+}
+
+#[async_trait]
+impl<I: Index> Index for FailingIndex<I> {
+    async fn open(path: &str) -> Result<Self>
+    where
+        Self: Sized,
+    {
+        Ok(Self::new(I::open(path).await?, FailureSchedule::seeded(0)))
+    }
+
+    async fn insert(&mut self, id: ValidatedDocumentId, path: ValidatedPath) -> Result<()> {
+        let fault = self.write("insert").await?;
+        after_partial(self.inner.insert(id, path).await, "insert", fault)
+    }
+
+    async fn insert_with_content(
+        &mut self,
+        id: ValidatedDocumentId,
+        path: ValidatedPath,
+        content: &[u8],
+    ) -> Result<()> {
+        let fault = self.write("insert_with_content").await?;
+        let result = self.inner.insert_with_content(id, path, content).await;
+        after_partial(result, "insert_with_content", fault)
+    }
+
+    async fn update(&mut self, id: ValidatedDocumentId, path: ValidatedPath) -> Result<()> {
+        let fault = self.write("update").await?;
+        after_partial(self.inner.update(id, path).await, "update", fault)
+    }
+
+    async fn update_with_content(
+        &mut self,
+        id: ValidatedDocumentId,
+        path: ValidatedPath,
+        content: &[u8],
+    ) -> Result<()> {
+        let fault = self.write("update_with_content").await?;
+        let result = self.inner.update_with_content(id, path, content).await;
+        after_partial(result, "update_with_content", fault)
+    }
+
+    async fn content_tokenizer(&self) -> Option<ContentTokenizer> {
+        self.inner.content_tokenizer().await
+    }
+
+    async fn insert_tokenized(&mut self, document: TokenizedDocument) -> Result<()> {
+        let fault = self.write("insert_tokenized").await?;
+        after_partial(
+            self.inner.insert_tokenized(document).await,
+            "insert_tokenized",
+            fault,
+        )
+    }
+
+    async fn delete(&mut self, id: &ValidatedDocumentId) -> Result<bool> {
+        let fault = self.write("delete").await?;
+        after_partial(self.inner.delete(id).await, "delete", fault)
+    }
+
+    async fn search(&self, query: &Query) -> Result<Vec<ValidatedDocumentId>> {
+        begin(self.schedule.next(Operation::Read), "search").await?;
+        self.inner.search(query).await
+    }
+
+    async fn sync(&mut self) -> Result<()> {
+        begin(self.schedule.next(Operation::Flush), "sync").await?;
+        self.inner.sync().await
+    }
+
+    async fn flush(&mut self) -> Result<()> {
+        begin(self.schedule.next(Operation::Flush), "flush").await?;
+        self.inner.flush().await
+    }
+
+    async fn close(self) -> Result<()> {
+        self.inner.close().await
+    }
+
+    async fn tree_structure(&self) -> Result<Option<TreeStructureMetrics>> {
+        self.inner.tree_structure().await
+    }
+
+    async fn rebuild_tree(&mut self) -> Result<bool> {
+        let fault = self.write("rebuild_tree").await?;
+        after_partial(self.inner.rebuild_tree().await, "rebuild_tree", fault)
+    }
+
+    async fn tree_snapshot(&self) -> Result<Option<crate::pure::btree::BTreeRoot>> {
+        self.inner.tree_snapshot().await
+    }
+
+    async fn replace_tree(&mut self, tree: crate::pure::btree::BTreeRoot) -> Result<bool> {
+        let fault = self.write("replace_tree").await?;
+        after_partial(self.inner.replace_tree(tree).await, "replace_tree", fault)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn faults(schedule: &FailureSchedule, count: usize) -> Vec<Injection> {
+        (0..count)
+            .map(|_| schedule.next(Operation::Write))
+            .collect()
+    }
+
+    #[test]
+    fn same_seed_replays_the_same_faults() {
+        let build = || {
+            FailureSchedule::seeded(42)
+                .with_io_errors(0.2)
+                .with_partial_writes(0.2)
+                .with_latency(0.5, Duration::from_millis(1), Duration::from_millis(5))
+        };
+        let (first, second) = (build(), build());
+        let injected = faults(&first, 200);
+        assert_eq!(injected, faults(&second, 200));
+        assert_ne!(
+            injected,
+            faults(&FailureSchedule::seeded(7).with_io_errors(0.2), 200)
+        );
+
+        let stats = first.stats();
+        assert_eq!(stats.operations, 200);
+        assert!(stats.io_errors > 0 && stats.partial_writes > 0 && stats.delays > 0);
+        assert_eq!(stats, second.stats());
+    }
+
+    #[test]
+    fn crash_fails_everything_after_the_cut_until_disabled() {
+        let schedule = FailureSchedule::seeded(1).crash_after(3);
+        let injected = faults(&schedule, 5);
+        assert!(injected[..3].iter().all(|i| i.fault.is_none()));
+        assert!(injected[3..]
+            .iter()
+            .all(|i| i.fault == Some(Fault::IoError)));
+
+        schedule.set_enabled(false);
+        assert_eq!(schedule.next(Operation::Write), Injection::default());
+        assert_eq!(schedule.stats().operations, 5);
+    }
+}
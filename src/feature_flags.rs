@@ -0,0 +1,747 @@
+//! Feature flag usage map
+//!
+//! After `index-codebase`, every place the indexed code consults a flag is recorded in
+//! `feature_flags.json` in the database directory:
+//!
+//! - Cargo features in `#[cfg(...)]`, `#[cfg_attr(...)]` and `cfg!(...)`. Attributes gate
+//!   the item they precede; `#[cfg(feature = "x")] mod m;` and `#![cfg(feature = "x")]`
+//!   gate whole files.
+//! - Environment variable reads (`std::env::var`, `os.getenv`, `process.env.X`,
+//!   `os.Getenv`, `System.getenv`).
+//! - Flag SDK calls such as `is_enabled("x")`, `isEnabled("x")` or `variation("x", ..)`,
+//!   plus patterns from `kotadb-flags.yml` in the repository root:
+//!
+//! ```yaml
+//! # Regexes whose first capture group is the flag name
+//! patterns:
+//!   - 'flags\.get\("([^"]+)"\)'
+//! # Leave out environment variable reads (default: true)
+//! env: false
+//! ```
+//!
+//! `kotadb flags` lists the flags with usage counts and `kotadb flag-impact <name>` shows
+//! the files, items and runtime checks a flag controls.
+
+use anyhow::{Context, Result};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::path::{Path, PathBuf};
+
+use crate::binary_symbols::FunctionRanges;
+use crate::contracts::Document;
+use crate::error_flows::mask_comments_and_strings;
+use crate::pure::metadata::strip_frontmatter;
+
+/// File name of the flag usage index inside a database directory
+pub const FLAG_INDEX_FILE: &str = "feature_flags.json";
+
+/// Pattern config read from the root of an indexed repository
+pub const FLAG_PATTERNS_FILE: &str = "kotadb-flags.yml";
+
+static CFG_ATTRIBUTE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"#(!?)\[\s*cfg(?:_attr)?\s*\(|\bcfg!\s*\(").expect("valid cfg pattern")
+});
+static CFG_FEATURE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"\bfeature\s*=\s*"([^"]+)""#).expect("valid feature pattern"));
+static ITEM_HEADER: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"^(?:pub(?:\([^)]*\))?\s+)?(?:(?:async|const|unsafe|extern\s+\S+)\s+)*(fn|struct|enum|mod|trait|const|static|type|use|impl|macro_rules!)\b\s*(?:<[^>]*>\s*)?([\w:]*)",
+    )
+    .expect("valid item pattern")
+});
+/// Environment variable reads across languages; the first capture group is the variable
+pub(crate) static ENV_READS: Lazy<Vec<Regex>> = Lazy::new(|| {
+    [
+        r#"\benv::var(?:_os)?\(\s*"([^"]+)""#,
+        r#"\b(?:option_)?env!\(\s*"([^"]+)""#,
+        r#"\bos\.(?:environ\.get|getenv)\(\s*["']([^"']+)["']"#,
+        r#"\bos\.environ\[\s*["']([^"']+)["']\s*\]"#,
+        r"\bprocess\.env\.([A-Za-z_][A-Za-z0-9_]*)",
+        r#"\bprocess\.env\[\s*["']([^"']+)["']\s*\]"#,
+        r#"\bos\.(?:Getenv|LookupEnv)\(\s*"([^"]+)""#,
+        r#"\bSystem\.getenv\(\s*"([^"]+)""#,
+    ]
+    .iter()
+    .map(|pattern| Regex::new(pattern).expect("valid env pattern"))
+    .collect()
+});
+static SDK_CALL: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r#"\b(?:is_enabled|isEnabled|is_feature_enabled|isFeatureEnabled|feature_enabled|bool_variation|boolVariation|variation|get_feature_value|getFeatureValue)\(\s*["']([\w.:/-]+)["']"#,
+    )
+    .expect("valid SDK pattern")
+});
+
+/// How code consults a flag
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FlagKind {
+    /// Cargo feature in `cfg`
+    Cfg,
+    /// Environment variable read
+    Env,
+    /// Flag SDK call or configured pattern
+    Sdk,
+}
+
+impl FlagKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FlagKind::Cfg => "cfg",
+            FlagKind::Env => "env",
+            FlagKind::Sdk => "sdk",
+        }
+    }
+}
+
+/// One place a flag is consulted
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FlagUsage {
+    pub flag: String,
+    pub kind: FlagKind,
+    pub file_path: String,
+    /// 1-based line of the check
+    pub line: usize,
+    /// Item the check gates (e.g. `fn parse`, `mod git`), or the function containing a
+    /// runtime check
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub symbol: Option<String>,
+    /// Lines gated by a `cfg` attribute, inclusive
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gated_lines: Option<(usize, usize)>,
+    /// File compiled only with (or, when negated, without) the flag
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gated_file: Option<String>,
+    /// Code that applies when the flag is off, as in `cfg(not(feature = "x"))`
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub negated: bool,
+}
+
+/// Patterns configured in [`FLAG_PATTERNS_FILE`]
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FlagPatterns {
+    /// Regexes whose first capture group is the flag name
+    #[serde(default)]
+    pub patterns: Vec<String>,
+    /// Whether environment variable reads count as flags
+    #[serde(default = "default_true")]
+    pub env: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Default for FlagPatterns {
+    fn default() -> Self {
+        Self {
+            patterns: Vec::new(),
+            env: true,
+        }
+    }
+}
+
+impl FlagPatterns {
+    /// Patterns from [`FLAG_PATTERNS_FILE`] in `repo_path`, or the defaults without one
+    pub fn load(repo_path: &Path) -> Result<Self> {
+        let path = repo_path.join(FLAG_PATTERNS_FILE);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let raw = std::fs::read_to_string(&path)
+            .with_context(|| format!("failed to read flag patterns {}", path.display()))?;
+        let patterns: Self = serde_yaml::from_str(&raw)
+            .with_context(|| format!("invalid flag patterns {}", path.display()))?;
+        patterns.compile()?;
+        Ok(patterns)
+    }
+
+    fn compile(&self) -> Result<Vec<Regex>> {
+        self.patterns
+            .iter()
+            .map(|pattern| {
+                let regex = Regex::new(pattern)
+                    .with_context(|| format!("invalid flag pattern '{}'", pattern))?;
+                anyhow::ensure!(
+                    regex.captures_len() > 1,
+                    "flag pattern '{}' needs a capture group for the flag name",
+                    pattern
+                );
+                Ok(regex)
+            })
+            .collect()
+    }
+}
+
+/// A flag and how widely it is used
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FlagSummary {
+    pub flag: String,
+    pub kinds: Vec<FlagKind>,
+    pub usages: usize,
+    pub files: usize,
+    /// Whole files the flag gates
+    pub gated_files: usize,
+}
+
+/// The code one flag controls
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct FlagImpact {
+    pub flag: String,
+    /// Files compiled only with the flag (or only without it, when negated)
+    pub gated_files: Vec<FlagUsage>,
+    /// Items gated by `cfg` attributes
+    pub gated_items: Vec<FlagUsage>,
+    /// `cfg!`, environment and SDK checks evaluated at runtime
+    pub runtime_checks: Vec<FlagUsage>,
+}
+
+impl FlagImpact {
+    /// Sections for gated files, gated items and runtime checks
+    pub fn to_markdown(&self) -> String {
+        let mut output = format!("# Code controlled by `{}`\n", self.flag);
+        if self.gated_files.is_empty()
+            && self.gated_items.is_empty()
+            && self.runtime_checks.is_empty()
+        {
+            output.push_str("\nThe flag is not used in the indexed code.\n");
+            return output;
+        }
+        let section = |output: &mut String, title: &str, usages: &[FlagUsage]| {
+            if usages.is_empty() {
+                return;
+            }
+            output.push_str(&format!("\n## {} ({})\n", title, usages.len()));
+            for usage in usages {
+                let mut line = format!("- {}:{}", usage.file_path, usage.line);
+                if let Some((start, end)) = usage.gated_lines {
+                    line = format!("- {}:{}-{}", usage.file_path, start, end);
+                }
+                if let Some(file) = &usage.gated_file {
+                    line.push_str(&format!(" gates `{}`", file));
+                } else if let Some(symbol) = &usage.symbol {
+                    line.push_str(&format!(" `{}`", symbol));
+                }
+                if usage.kind != FlagKind::Cfg {
+                    line.push_str(&format!(" [{}]", usage.kind.as_str()));
+                }
+                if usage.negated {
+                    line.push_str(" (when disabled)");
+                }
+                output.push_str(&line);
+                output.push('\n');
+            }
+        };
+        section(&mut output, "Gated files", &self.gated_files);
+        section(&mut output, "Gated items", &self.gated_items);
+        section(&mut output, "Runtime checks", &self.runtime_checks);
+        output
+    }
+}
+
+/// Flag usages of the indexed codebase
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FlagIndex {
+    /// RFC 3339 time the index was built
+    pub indexed_at: String,
+    pub usages: Vec<FlagUsage>,
+}
+
+impl FlagIndex {
+    /// Find flag checks in source files, attributing runtime checks to the function
+    /// containing them
+    pub fn build<'a>(
+        files: impl IntoIterator<Item = (&'a str, &'a str)>,
+        functions: &FunctionRanges,
+        patterns: &FlagPatterns,
+    ) -> Result<Self> {
+        let custom = patterns.compile()?;
+        let files: Vec<(&str, &str)> = files.into_iter().collect();
+        let paths: HashSet<&str> = files.iter().map(|(path, _)| *path).collect();
+
+        let mut usages = Vec::new();
+        for (path, content) in &files {
+            let is_rust = path.ends_with(".rs");
+            let code = if is_rust {
+                mask_comments_and_strings(content)
+            } else {
+                content.to_string()
+            };
+            let lines = LineStarts::new(content);
+            // Checks inside comments (blanked in `code`) don't count
+            let in_code = |offset: usize| code.as_bytes()[offset] == content.as_bytes()[offset];
+            let runtime = |flag: &str, kind: FlagKind, offset: usize| {
+                let line = lines.line_of(offset);
+                FlagUsage {
+                    flag: flag.to_string(),
+                    kind,
+                    file_path: path.to_string(),
+                    line,
+                    symbol: functions.enclosing(path, line).map(str::to_string),
+                    gated_lines: None,
+                    gated_file: None,
+                    negated: false,
+                }
+            };
+
+            if is_rust {
+                for cfg in CFG_ATTRIBUTE.captures_iter(content) {
+                    let whole = cfg.get(0).expect("match");
+                    if !in_code(whole.start()) {
+                        continue;
+                    }
+                    let predicate_end = closing_paren(content, whole.end());
+                    let predicate = &content[whole.end()..predicate_end];
+                    let features: Vec<(String, bool)> = CFG_FEATURE
+                        .captures_iter(predicate)
+                        .map(|feature| {
+                            let start = feature.get(0).expect("match").start();
+                            (feature[1].to_string(), is_negated(&predicate[..start]))
+                        })
+                        .collect();
+                    if features.is_empty() {
+                        continue;
+                    }
+
+                    let line = lines.line_of(whole.start());
+                    let (symbol, gated_lines, gated_file) = if whole.as_str().starts_with("cfg!") {
+                        (
+                            functions.enclosing(path, line).map(str::to_string),
+                            None,
+                            None,
+                        )
+                    } else if &cfg[1] == "!" {
+                        (None, None, Some(path.to_string()))
+                    } else {
+                        let attribute_end = content[predicate_end..]
+                            .find(']')
+                            .map_or(content.len(), |i| predicate_end + i + 1);
+                        let item = gated_item(&code, attribute_end);
+                        let gated_file = item
+                            .module
+                            .as_deref()
+                            .and_then(|module| module_file(path, module, &paths));
+                        (
+                            item.name,
+                            Some((line, lines.line_of(item.end.saturating_sub(1)))),
+                            gated_file,
+                        )
+                    };
+                    for (flag, negated) in features {
+                        usages.push(FlagUsage {
+                            flag,
+                            kind: FlagKind::Cfg,
+                            file_path: path.to_string(),
+                            line,
+                            symbol: symbol.clone(),
+                            gated_lines,
+                            gated_file: gated_file.clone(),
+                            negated,
+                        });
+                    }
+                }
+            }
+
+            if patterns.env {
+                for pattern in ENV_READS.iter() {
+                    for read in pattern.captures_iter(content) {
+                        let start = read.get(0).expect("match").start();
+                        if in_code(start) {
+                            usages.push(runtime(&read[1], FlagKind::Env, start));
+                        }
+                    }
+                }
+            }
+            for pattern in std::iter::once(&*SDK_CALL).chain(custom.iter()) {
+                for call in pattern.captures_iter(content) {
+                    let start = call.get(0).expect("match").start();
+                    if let (true, Some(flag)) = (in_code(start), call.get(1)) {
+                        usages.push(runtime(flag.as_str(), FlagKind::Sdk, start));
+                    }
+                }
+            }
+        }
+
+        usages.sort_by(|a, b| {
+            (&a.flag, &a.file_path, a.line, a.kind).cmp(&(&b.flag, &b.file_path, b.line, b.kind))
+        });
+        usages.dedup();
+        Ok(Self {
+            indexed_at: chrono::Utc::now().to_rfc3339(),
+            usages,
+        })
+    }
+
+    /// Location of the flag index for a database directory
+    pub fn path_for(db_path: &Path) -> PathBuf {
+        db_path.join(FLAG_INDEX_FILE)
+    }
+
+    /// The flag index of a database, if one was built
+    pub fn load(db_path: &Path) -> Result<Option<Self>> {
+        let path = Self::path_for(db_path);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read feature flags: {}", path.display()))?;
+        let index = serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse feature flags: {}", path.display()))?;
+        Ok(Some(index))
+    }
+
+    /// Save the index atomically into a database directory
+    pub fn save(&self, db_path: &Path) -> Result<()> {
+        std::fs::create_dir_all(db_path)
+            .with_context(|| format!("Failed to create {}", db_path.display()))?;
+        let path = Self::path_for(db_path);
+        let tmp_path = path.with_extension("json.tmp");
+        std::fs::write(&tmp_path, serde_json::to_string(self)?)
+            .with_context(|| format!("Failed to write feature flags: {}", tmp_path.display()))?;
+        std::fs::rename(&tmp_path, &path)
+            .with_context(|| format!("Failed to replace feature flags: {}", path.display()))?;
+        Ok(())
+    }
+
+    /// Flags by descending usage count
+    pub fn summaries(&self) -> Vec<FlagSummary> {
+        let mut by_flag: BTreeMap<&str, Vec<&FlagUsage>> = BTreeMap::new();
+        for usage in &self.usages {
+            by_flag.entry(usage.flag.as_str()).or_default().push(usage);
+        }
+        let mut summaries: Vec<FlagSummary> = by_flag
+            .into_iter()
+            .map(|(flag, usages)| FlagSummary {
+                flag: flag.to_string(),
+                kinds: usages
+                    .iter()
+                    .map(|u| u.kind)
+                    .collect::<BTreeSet<_>>()
+                    .into_iter()
+                    .collect(),
+                usages: usages.len(),
+                files: usages
+                    .iter()
+                    .map(|u| u.file_path.as_str())
+                    .collect::<HashSet<_>>()
+                    .len(),
+                gated_files: usages.iter().filter(|u| u.gated_file.is_some()).count(),
+            })
+            .collect();
+        summaries.sort_by(|a, b| b.usages.cmp(&a.usages).then(a.flag.cmp(&b.flag)));
+        summaries
+    }
+
+    /// Files, items and runtime checks controlled by `flag`
+    pub fn impact(&self, flag: &str) -> FlagImpact {
+        let mut impact = FlagImpact {
+            flag: flag.to_string(),
+            ..FlagImpact::default()
+        };
+        for usage in self.usages.iter().filter(|u| u.flag == flag) {
+            if usage.gated_file.is_some() {
+                impact.gated_files.push(usage.clone());
+            } else if usage.gated_lines.is_some() {
+                impact.gated_items.push(usage.clone());
+            } else {
+                impact.runtime_checks.push(usage.clone());
+            }
+        }
+        impact
+    }
+}
+
+/// Build and save the flag index from stored documents, with patterns from the repository
+pub fn index_feature_flags(
+    db_path: &Path,
+    repo_path: &Path,
+    documents: &[Document],
+) -> Result<FlagIndex> {
+    let patterns = FlagPatterns::load(repo_path)?;
+    let functions = FunctionRanges::load(&db_path.join("symbols.kota"))?;
+    let contents: Vec<(&str, String)> = documents
+        .iter()
+        .filter(|d| !d.tags.iter().any(|tag| tag.as_str() == "commit"))
+        .filter(|d| {
+            crate::trends::is_source_language(crate::path_utils::detect_language_from_extension(
+                Path::new(d.path.as_str()),
+            ))
+        })
+        .map(|d| {
+            (
+                d.path.as_str(),
+                strip_frontmatter(&String::from_utf8_lossy(&d.content)).to_string(),
+            )
+        })
+        .collect();
+    let index = FlagIndex::build(
+        contents
+            .iter()
+            .map(|(path, content)| (*path, content.as_str())),
+        &functions,
+        &patterns,
+    )?;
+    index.save(db_path)?;
+    Ok(index)
+}
+
+/// Whether the feature at the end of `predicate_prefix` sits inside `not(..)`
+fn is_negated(predicate_prefix: &str) -> bool {
+    let mut open: Vec<bool> = Vec::new();
+    let mut word = String::new();
+    for c in predicate_prefix.chars() {
+        match c {
+            '(' => {
+                open.push(word.trim() == "not");
+                word.clear();
+            }
+            ')' => {
+                open.pop();
+                word.clear();
+            }
+            ',' => word.clear(),
+            c => word.push(c),
+        }
+    }
+    open.iter().filter(|negates| **negates).count() % 2 == 1
+}
+
+/// Offset of the `)` closing the parenthesis opened just before `from`
+fn closing_paren(source: &str, from: usize) -> usize {
+    let mut depth = 1usize;
+    for (i, byte) in source.bytes().enumerate().skip(from) {
+        match byte {
+            b'(' => depth += 1,
+            b')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return i;
+                }
+            }
+            _ => {}
+        }
+    }
+    source.len()
+}
+
+/// The item following a `cfg` attribute
+struct GatedItem {
+    /// e.g. `fn parse` or `impl Display for Error`
+    name: Option<String>,
+    /// Module name of a `mod m;` declaration
+    module: Option<String>,
+    /// Offset just past the item
+    end: usize,
+}
+
+fn gated_item(code: &str, from: usize) -> GatedItem {
+    // Skip further attributes stacked on the item
+    let mut start = from;
+    loop {
+        let rest = code[start..].trim_start();
+        start = code.len() - rest.len();
+        if rest.starts_with("#[") {
+            start = code[start..]
+                .find(']')
+                .map_or(code.len(), |i| start + i + 1);
+        } else {
+            break;
+        }
+    }
+
+    let rest = &code[start..];
+    let header_end = if ITEM_HEADER.is_match(rest) {
+        rest.find(['{', ';']).unwrap_or(rest.len())
+    } else {
+        statement_end(rest)
+    };
+    let end = match rest.as_bytes().get(header_end) {
+        Some(b'{') => start + header_end + closing_brace(&rest[header_end..]) + 1,
+        // Last field or match arm before the enclosing block closes
+        Some(b'}') => start + header_end,
+        _ => (start + header_end + 1).min(code.len()),
+    };
+    let header = rest[..header_end]
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    let (name, module) = match ITEM_HEADER.captures(&header) {
+        Some(item) if &item[1] == "impl" => (Some(header.clone()), None),
+        Some(item) => {
+            let keyword = item[1].trim_end_matches('!');
+            let module = (keyword == "mod" && rest[header_end..].starts_with(';'))
+                .then(|| item[2].to_string());
+            (Some(format!("{} {}", keyword, &item[2])), module)
+        }
+        None => (None, None),
+    };
+    GatedItem { name, module, end }
+}
+
+/// Offset of the `,`, `;`, `{` or `}` ending a field, variant, match arm or statement
+fn statement_end(source: &str) -> usize {
+    let mut depth = 0usize;
+    for (i, byte) in source.bytes().enumerate() {
+        match byte {
+            b'(' | b'[' => depth += 1,
+            b')' | b']' => depth = depth.saturating_sub(1),
+            b',' | b';' | b'{' | b'}' if depth == 0 => return i,
+            _ => {}
+        }
+    }
+    source.len()
+}
+
+/// Offset of the `}` closing the block that opens at the start of `block`
+fn closing_brace(block: &str) -> usize {
+    let mut depth = 0usize;
+    for (i, byte) in block.bytes().enumerate() {
+        match byte {
+            b'{' => depth += 1,
+            b'}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return i;
+                }
+            }
+            _ => {}
+        }
+    }
+    block.len().saturating_sub(1)
+}
+
+/// Indexed file of module `module` declared in `declaring_file`
+fn module_file(declaring_file: &str, module: &str, paths: &HashSet<&str>) -> Option<String> {
+    let (dir, file) = declaring_file
+        .rsplit_once('/')
+        .unwrap_or(("", declaring_file));
+    let stem = file.trim_end_matches(".rs");
+    let base = if matches!(stem, "lib" | "main" | "mod") {
+        dir.to_string()
+    } else {
+        format!("{}/{}", dir, stem)
+    };
+    let base = base.trim_start_matches('/');
+    [
+        format!("{}/{}.rs", base, module),
+        format!("{}/{}/mod.rs", base, module),
+    ]
+    .into_iter()
+    .map(|candidate| candidate.trim_start_matches('/').to_string())
+    .find(|candidate| paths.contains(candidate.as_str()))
+}
+
+/// Offsets of line starts, for turning byte offsets into 1-based lines
+pub(crate) struct LineStarts(Vec<usize>);
+
+impl LineStarts {
+    pub(crate) fn new(content: &str) -> Self {
+        Self(
+            std::iter::once(0)
+                .chain(content.match_indices('\n').map(|(i, _)| i + 1))
+                .collect(),
+        )
+    }
+
+    pub(crate) fn line_of(&self, offset: usize) -> usize {
+        self.0.partition_point(|start| *start <= offset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIB: &str = r#"
+pub mod core;
+
+#[cfg(feature = "git-integration")]
+pub mod git;
+
+#[cfg(all(feature = "mcp-server", not(feature = "embedded")))]
+#[allow(dead_code)]
+pub fn serve() {
+    start();
+}
+
+pub fn run() {
+    // cfg!(feature = "commented")
+    if cfg!(feature = "git-integration") {
+        sync();
+    }
+    let url = std::env::var("KOTADB_URL").unwrap_or_default();
+    if flags.is_enabled("new-ranking") {
+        rank();
+    }
+}
+
+pub struct Config {
+    #[cfg(feature = "embedded")]
+    path: PathBuf,
+    name: String,
+}
+"#;
+
+    const GIT: &str = "pub fn clone() {}\n";
+
+    fn build(patterns: &FlagPatterns) -> FlagIndex {
+        FlagIndex::build(
+            [
+                ("src/lib.rs", LIB),
+                ("src/git.rs", GIT),
+                (
+                    "web/app.ts",
+                    "const u = process.env.API_URL;\nif (client.isEnabled('new-ranking')) {}\n",
+                ),
+            ],
+            &FunctionRanges::from_ranges([("run".to_string(), "src/lib.rs".to_string(), 13, 22)]),
+            patterns,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn cfg_attributes_gate_items_and_module_files() {
+        let index = build(&FlagPatterns::default());
+        let impact = index.impact("git-integration");
+        assert_eq!(impact.gated_files.len(), 1);
+        assert_eq!(
+            impact.gated_files[0].gated_file.as_deref(),
+            Some("src/git.rs")
+        );
+        assert_eq!(impact.gated_files[0].symbol.as_deref(), Some("mod git"));
+        assert_eq!(impact.runtime_checks.len(), 1);
+        assert_eq!(impact.runtime_checks[0].line, 15);
+        assert_eq!(impact.runtime_checks[0].symbol.as_deref(), Some("run"));
+
+        let serve = &index.impact("mcp-server").gated_items[0];
+        assert_eq!(serve.symbol.as_deref(), Some("fn serve"));
+        assert_eq!(serve.gated_lines, Some((7, 11)));
+        assert!(!serve.negated);
+        let embedded = index.impact("embedded").gated_items;
+        assert!(embedded[0].negated);
+        assert_eq!(embedded[1].gated_lines, Some((25, 26)));
+        assert!(index.impact("commented").runtime_checks.is_empty());
+    }
+
+    #[test]
+    fn env_reads_and_sdk_calls_are_counted() {
+        let index = build(&FlagPatterns::default());
+        let summaries = index.summaries();
+        let ranking = summaries.iter().find(|s| s.flag == "new-ranking").unwrap();
+        assert_eq!((ranking.usages, ranking.files), (2, 2));
+        assert_eq!(ranking.kinds, vec![FlagKind::Sdk]);
+        assert!(summaries.iter().any(|s| s.flag == "KOTADB_URL"));
+        assert!(summaries.iter().any(|s| s.flag == "API_URL"));
+
+        let patterns: FlagPatterns =
+            serde_yaml::from_str("patterns: ['\\b(rank)\\(\\)']\nenv: false").unwrap();
+        assert!(patterns.compile().is_ok());
+        let index = build(&patterns);
+        assert!(!index.summaries().iter().any(|s| s.flag == "KOTADB_URL"));
+        assert!(serde_yaml::from_str::<FlagPatterns>("pattern: []").is_err());
+    }
+}
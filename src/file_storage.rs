@@ -4,17 +4,22 @@
 
 use anyhow::{Context, Result};
 use async_trait::async_trait;
-use std::collections::HashMap;
+use std::collections::BTreeMap;
+use std::ops::Bound;
 use std::path::PathBuf;
 use tokio::fs;
 use tokio::sync::{Mutex, RwLock};
 use uuid::Uuid;
 
-use crate::contracts::{Document, Storage};
+use crate::contracts::{content_tokens, Document, DocumentPage, DocumentSummary, Storage};
 use crate::types::{ValidatedDocumentId, ValidatedPath, ValidatedTag, ValidatedTitle};
 use crate::validation;
 use crate::wrappers::create_wrapped_storage;
 use chrono::{DateTime, Utc};
+use futures::stream::{self, StreamExt, TryStreamExt};
+
+/// Document files read concurrently by `get_many` and `list_page`
+const MAX_CONCURRENT_READS: usize = 32;
 
 /// Simple file-based storage implementation
 ///
@@ -24,8 +29,8 @@ use chrono::{DateTime, Utc};
 pub struct FileStorage {
     /// Root directory for the database
     db_path: PathBuf,
-    /// In-memory document metadata for fast lookups
-    documents: RwLock<HashMap<Uuid, DocumentMetadata>>,
+    /// In-memory document metadata for fast lookups, ordered by ID for paging
+    documents: RwLock<BTreeMap<Uuid, DocumentMetadata>>,
     /// Write-ahead log for crash recovery
     wal_writer: Mutex<Option<tokio::fs::File>>,
 }
@@ -42,6 +47,8 @@ struct DocumentMetadata {
     updated: i64,
     hash: [u8; 32],
     embedding: Option<Vec<f32>>, // Vector embedding for semantic search
+    tokens: Option<u64>,         // Estimated content tokens, computed on write
+    tags: Option<Vec<String>>,   // Frontmatter tags, recorded on write
 }
 
 impl FileStorage {
@@ -155,18 +162,10 @@ impl FileStorage {
         })?;
 
         // Parse frontmatter for tags (title is now stored in metadata)
-        let content_str = String::from_utf8_lossy(&content);
-
-        // Parse frontmatter for tags
-        let tags = if let Some(frontmatter) = crate::pure::metadata::parse_frontmatter(&content_str)
-        {
-            crate::pure::metadata::extract_tags(&frontmatter)
-                .into_iter()
-                .filter_map(|tag| ValidatedTag::new(&tag).ok())
-                .collect()
-        } else {
-            Vec::new()
-        };
+        let tags = frontmatter_tags(&content)
+            .iter()
+            .filter_map(|tag| ValidatedTag::new(tag).ok())
+            .collect();
 
         Ok(Document {
             id: ValidatedDocumentId::from_uuid(metadata.id)?,
@@ -182,6 +181,59 @@ impl FileStorage {
             embedding: metadata.embedding.clone(),
         })
     }
+
+    /// Convert stored metadata to the metadata returned by `get_metadata`
+    fn public_metadata(metadata: &DocumentMetadata) -> Result<crate::contracts::DocumentMetadata> {
+        Ok(crate::contracts::DocumentMetadata {
+            id: ValidatedDocumentId::from_uuid(metadata.id)?,
+            path: ValidatedPath::new(&metadata.original_path)?,
+            title: ValidatedTitle::new(&metadata.title)?,
+            tags: metadata
+                .tags
+                .iter()
+                .flatten()
+                .filter_map(|tag| ValidatedTag::new(tag).ok())
+                .collect(),
+            created_at: DateTime::<Utc>::from_timestamp(metadata.created, 0)
+                .ok_or_else(|| anyhow::anyhow!("Invalid created timestamp"))?,
+            updated_at: DateTime::<Utc>::from_timestamp(metadata.updated, 0)
+                .ok_or_else(|| anyhow::anyhow!("Invalid updated timestamp"))?,
+            size: metadata.size as usize,
+            tokens: metadata.tokens,
+        })
+    }
+
+    /// Record tags for documents whose metadata was written before tags were
+    ///
+    /// The content of each such document is read once and its metadata file
+    /// rewritten, so later metadata reads don't touch content again.
+    async fn backfill_tags(&self, ids: &[Uuid]) -> Result<()> {
+        for id in ids {
+            let content_path = self.document_file_path(id);
+            let Ok(content) = fs::read(&content_path).await else {
+                continue;
+            };
+            let tags = frontmatter_tags(&content);
+
+            let metadata = {
+                let mut documents = self.documents.write().await;
+                let Some(metadata) = documents.get_mut(id) else {
+                    continue;
+                };
+                metadata.tags = Some(tags);
+                metadata.clone()
+            };
+            self.save_metadata(&metadata).await?;
+        }
+        Ok(())
+    }
+}
+
+/// Tags in the YAML frontmatter of stored content
+fn frontmatter_tags(content: &[u8]) -> Vec<String> {
+    crate::pure::metadata::parse_frontmatter(&String::from_utf8_lossy(content))
+        .map(|frontmatter| crate::pure::metadata::extract_tags(&frontmatter))
+        .unwrap_or_default()
 }
 
 #[async_trait]
@@ -196,7 +248,7 @@ impl Storage for FileStorage {
         let db_path = PathBuf::from(path);
         let storage = Self {
             db_path,
-            documents: RwLock::new(HashMap::new()),
+            documents: RwLock::new(BTreeMap::new()),
             wal_writer: Mutex::new(None),
         };
 
@@ -285,6 +337,8 @@ impl Storage for FileStorage {
             updated: doc.updated_at.timestamp(),
             hash,
             embedding: doc.embedding.clone(),
+            tokens: Some(content_tokens(&doc.content)),
+            tags: Some(frontmatter_tags(content_to_write.as_bytes())),
         };
 
         // Save metadata to disk
@@ -313,6 +367,85 @@ impl Storage for FileStorage {
         }
     }
 
+    async fn get_many(&self, ids: &[ValidatedDocumentId]) -> Result<Vec<Option<Document>>> {
+        let metadata: Vec<Option<DocumentMetadata>> = {
+            let documents = self.documents.read().await;
+            ids.iter()
+                .map(|id| documents.get(&id.as_uuid()).cloned())
+                .collect()
+        };
+
+        // Content files are read concurrently; `buffered` keeps results in input order
+        stream::iter(metadata)
+            .map(|metadata| async move {
+                match metadata {
+                    Some(metadata) => self.metadata_to_document(&metadata).await.map(Some),
+                    None => Ok(None),
+                }
+            })
+            .buffered(MAX_CONCURRENT_READS)
+            .try_collect()
+            .await
+    }
+
+    async fn summary(&self, id: &ValidatedDocumentId) -> Result<Option<DocumentSummary>> {
+        let documents = self.documents.read().await;
+        documents
+            .get(&id.as_uuid())
+            .map(|metadata| {
+                Ok(DocumentSummary {
+                    path: ValidatedPath::new(&metadata.original_path)?,
+                    size: metadata.size as usize,
+                    tokens: metadata.tokens,
+                })
+            })
+            .transpose()
+    }
+
+    async fn get_metadata(
+        &self,
+        id: &ValidatedDocumentId,
+    ) -> Result<Option<crate::contracts::DocumentMetadata>> {
+        {
+            let documents = self.documents.read().await;
+            match documents.get(&id.as_uuid()) {
+                None => return Ok(None),
+                Some(metadata) if metadata.tags.is_some() => {
+                    return Self::public_metadata(metadata).map(Some)
+                }
+                Some(_) => {}
+            }
+        }
+        self.backfill_tags(&[id.as_uuid()]).await?;
+
+        let documents = self.documents.read().await;
+        documents
+            .get(&id.as_uuid())
+            .map(Self::public_metadata)
+            .transpose()
+    }
+
+    async fn list_metadata(&self) -> Result<Vec<crate::contracts::DocumentMetadata>> {
+        let untagged: Vec<Uuid> = {
+            let documents = self.documents.read().await;
+            documents
+                .values()
+                .filter(|metadata| metadata.tags.is_none())
+                .map(|metadata| metadata.id)
+                .collect()
+        };
+        if !untagged.is_empty() {
+            self.backfill_tags(&untagged).await?;
+        }
+
+        // Like `list_all`, entries that can't be converted are skipped
+        let documents = self.documents.read().await;
+        Ok(documents
+            .values()
+            .filter_map(|metadata| Self::public_metadata(metadata).ok())
+            .collect())
+    }
+
     async fn update(&mut self, doc: Document) -> Result<()> {
         // Check if document exists
         let doc_uuid = doc.id.as_uuid();
@@ -340,6 +473,8 @@ impl Storage for FileStorage {
         metadata.updated = doc.updated_at.timestamp();
         metadata.hash = hash;
         metadata.embedding = doc.embedding.clone();
+        metadata.tokens = Some(content_tokens(&doc.content));
+        metadata.tags = Some(frontmatter_tags(&doc.content));
 
         // Save metadata
         self.save_metadata(&metadata).await?;
@@ -418,6 +553,40 @@ impl Storage for FileStorage {
         Ok(result)
     }
 
+    async fn list_page(
+        &self,
+        after: Option<ValidatedDocumentId>,
+        limit: usize,
+    ) -> Result<DocumentPage> {
+        let metadata_page: Vec<DocumentMetadata> = {
+            let documents = self.documents.read().await;
+            let start = after.map_or(Bound::Unbounded, |id| Bound::Excluded(id.as_uuid()));
+            documents
+                .range((start, Bound::Unbounded))
+                .take(limit)
+                .map(|(_, metadata)| metadata.clone())
+                .collect()
+        };
+
+        // The cursor advances past documents that can't be read, which `list_all` skips too
+        let next = match metadata_page.last() {
+            Some(last) if metadata_page.len() >= limit => {
+                Some(ValidatedDocumentId::from_uuid(last.id)?)
+            }
+            _ => None,
+        };
+        let documents: Vec<Option<Document>> = stream::iter(metadata_page)
+            .map(|metadata| async move { self.metadata_to_document(&metadata).await.ok() })
+            .buffered(MAX_CONCURRENT_READS)
+            .collect()
+            .await;
+
+        Ok(DocumentPage {
+            documents: documents.into_iter().flatten().collect(),
+            next,
+        })
+    }
+
     async fn flush(&mut self) -> Result<()> {
         // For file-based storage, flush is similar to sync
         // Ensure all buffered writes are persisted to disk
@@ -456,7 +625,7 @@ impl serde::Serialize for DocumentMetadata {
         S: serde::Serializer,
     {
         use serde::ser::SerializeStruct;
-        let mut state = serializer.serialize_struct("DocumentMetadata", 9)?;
+        let mut state = serializer.serialize_struct("DocumentMetadata", 11)?;
         state.serialize_field("id", &self.id)?;
         state.serialize_field("file_path", &self.file_path)?;
         state.serialize_field("original_path", &self.original_path)?;
@@ -466,6 +635,8 @@ impl serde::Serialize for DocumentMetadata {
         state.serialize_field("updated", &self.updated)?;
         state.serialize_field("hash", &self.hash)?;
         state.serialize_field("embedding", &self.embedding)?;
+        state.serialize_field("tokens", &self.tokens)?;
+        state.serialize_field("tags", &self.tags)?;
         state.end()
     }
 }
@@ -486,6 +657,10 @@ impl<'de> serde::Deserialize<'de> for DocumentMetadata {
             updated: i64,
             hash: [u8; 32],
             embedding: Option<Vec<f32>>, // Optional for backward compatibility
+            #[serde(default)]
+            tokens: Option<u64>, // Absent in metadata written before token counts
+            #[serde(default)]
+            tags: Option<Vec<String>>, // Absent in metadata written before tag records
         }
 
         let helper = DocumentMetadataHelper::deserialize(deserializer)?;
@@ -503,6 +678,8 @@ impl<'de> serde::Deserialize<'de> for DocumentMetadata {
             updated: helper.updated,
             hash: helper.hash,
             embedding: helper.embedding,
+            tokens: helper.tokens,
+            tags: helper.tags,
         })
     }
 }
@@ -610,6 +787,80 @@ mod tests {
         assert_eq!(retrieved_doc.embedding, doc.embedding);
     }
 
+    #[tokio::test]
+    async fn test_summary_matches_stored_document() {
+        let (mut storage, _temp_dir) = create_test_storage().await;
+        let doc = create_test_document_no_tags("Hello, world!");
+        storage
+            .insert(doc.clone())
+            .await
+            .expect("Failed to insert document");
+
+        let summary = storage
+            .summary(&doc.id)
+            .await
+            .expect("Failed to read summary")
+            .expect("Summary for stored document");
+        assert_eq!(summary.path, doc.path);
+        assert_eq!(summary.size, doc.size);
+        assert_eq!(summary.tokens, Some(content_tokens(&doc.content)));
+
+        let unknown = ValidatedDocumentId::from_uuid(Uuid::new_v4()).unwrap();
+        assert!(storage.summary(&unknown).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_get_many_preserves_order_and_missing_documents() {
+        let (mut storage, _temp_dir) = create_test_storage().await;
+        let first = create_test_document("First document");
+        let second = create_test_document_no_tags("Second document");
+        for doc in [&first, &second] {
+            storage
+                .insert(doc.clone())
+                .await
+                .expect("Failed to insert document");
+        }
+
+        let unknown = ValidatedDocumentId::from_uuid(Uuid::new_v4()).unwrap();
+        let documents = storage
+            .get_many(&[second.id, unknown, first.id])
+            .await
+            .expect("Failed to get documents");
+        let ids: Vec<Option<ValidatedDocumentId>> = documents
+            .iter()
+            .map(|doc| doc.as_ref().map(|d| d.id))
+            .collect();
+        assert_eq!(ids, [Some(second.id), None, Some(first.id)]);
+        assert_eq!(documents[2].as_ref().unwrap().tags, first.tags);
+        assert!(storage.get_many(&[]).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_list_page_walks_documents_in_id_order() {
+        let (mut storage, _temp_dir) = create_test_storage().await;
+        let mut ids = Vec::new();
+        for i in 0..5 {
+            let doc = create_test_document(&format!("Document {i}"));
+            ids.push(doc.id);
+            storage
+                .insert(doc)
+                .await
+                .expect("Failed to insert document");
+        }
+        ids.sort();
+
+        let first = storage.list_page(None, 2).await.unwrap();
+        let first_ids: Vec<_> = first.documents.iter().map(|d| d.id).collect();
+        assert_eq!(first_ids, ids[..2]);
+        assert_eq!(first.next, Some(ids[1]));
+
+        let rest = storage.list_page(first.next, 10).await.unwrap();
+        let rest_ids: Vec<_> = rest.documents.iter().map(|d| d.id).collect();
+        assert_eq!(rest_ids, ids[2..]);
+        assert_eq!(rest.next, None);
+        assert!(rest.documents.iter().all(|d| !d.content.is_empty()));
+    }
+
     #[tokio::test]
     async fn test_document_update() {
         let (mut storage, _temp_dir) = create_test_storage().await;
@@ -870,6 +1121,55 @@ mod tests {
         assert!(parsed.get("hash").is_some());
     }
 
+    #[tokio::test]
+    async fn test_metadata_without_content() {
+        let (mut storage, temp_dir) = create_test_storage().await;
+        let doc = create_test_document("Tagged content");
+        storage
+            .insert(doc.clone())
+            .await
+            .expect("Failed to insert document");
+
+        // Metadata is served from memory even when the content file is gone
+        fs::remove_file(storage.document_file_path(&doc.id.as_uuid()))
+            .await
+            .expect("Failed to remove content");
+        let metadata = storage
+            .get_metadata(&doc.id)
+            .await
+            .expect("Failed to read metadata")
+            .expect("Metadata for stored document");
+        assert_eq!(metadata.path, doc.path);
+        assert_eq!(metadata.title, doc.title);
+        assert_eq!(metadata.tags, doc.tags);
+        assert_eq!(metadata.size, doc.size);
+        assert_eq!(storage.list_metadata().await.unwrap(), vec![metadata]);
+
+        // Metadata written before tags were recorded is backfilled from content once
+        let legacy = create_test_document("Legacy content");
+        storage
+            .insert(legacy.clone())
+            .await
+            .expect("Failed to insert document");
+        let metadata_path = storage.metadata_file_path(&legacy.id.as_uuid());
+        let mut json: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&metadata_path).await.unwrap()).unwrap();
+        json.as_object_mut().unwrap().remove("tags");
+        fs::write(&metadata_path, json.to_string()).await.unwrap();
+        drop(storage);
+
+        let storage = FileStorage::open(temp_dir.path().to_str().unwrap())
+            .await
+            .expect("Failed to reopen storage");
+        let listed = storage.list_metadata().await.unwrap();
+        assert_eq!(listed.len(), 2);
+        let backfilled = listed.iter().find(|m| m.id == legacy.id).unwrap();
+        assert_eq!(backfilled.tags, legacy.tags);
+        let json: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&metadata_path).await.unwrap()).unwrap();
+        assert_eq!(json["tags"], serde_json::json!(["test"]));
+    }
+
     #[tokio::test]
     async fn test_invalid_path_rejection() {
         // Test path validation - these should fail due to directory traversal
@@ -0,0 +1,1146 @@
+//! Deterministic fixture repositories for `kotadb generate-fixture`
+//!
+//! A fixture is a small multi-language repository whose analysis results are known in
+//! advance. Each language gets an entry point that calls into a pipeline of modules:
+//! every function calls the next one, the last function of a module calls into the next
+//! module, and some modules additionally enter a ring of mutually recursive functions.
+//! Each module also has a class or struct whose method calls into the pipeline, and a
+//! function nothing calls.
+//!
+//! Names are drawn from fixed word lists with a seeded generator, so the same options
+//! always produce the same files. Next to the sources, `kotadb-fixture.json` lists every
+//! symbol, call site, cycle and dead function with its exact file and line, so the
+//! crate's integration tests and users validating a deployment can compare
+//! `find-callers`, cycle detection and unused-symbol reports against it.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+/// File listing the expected analysis results at the fixture root
+pub const EXPECTED_FILE: &str = "kotadb-fixture.json";
+
+/// Seed used when `--seed` is not given
+pub const DEFAULT_SEED: u64 = 42;
+
+const VERBS: [&str; 30] = [
+    "load", "parse", "merge", "score", "route", "cache", "index", "encode", "decode", "filter",
+    "rank", "sync", "flush", "queue", "hash", "split", "bundle", "track", "verify", "render",
+    "compact", "resolve", "stage", "emit", "fetch", "sample", "prune", "seal", "trace", "batch",
+];
+
+const NOUNS: [&str; 40] = [
+    "ledger", "invoice", "order", "cart", "token", "session", "widget", "report", "schedule",
+    "profile", "account", "payment", "shipment", "catalog", "review", "coupon", "message",
+    "channel", "ticket", "upload", "region", "tenant", "metric", "quota", "snapshot", "manifest",
+    "segment", "badge", "voucher", "parcel", "lease", "permit", "sensor", "beacon", "rating",
+    "digest", "archive", "bucket", "cursor", "journal",
+];
+
+/// A language a fixture can contain
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FixtureLanguage {
+    Rust,
+    Python,
+    TypeScript,
+}
+
+impl FixtureLanguage {
+    pub fn name(&self) -> &'static str {
+        match self {
+            FixtureLanguage::Rust => "rust",
+            FixtureLanguage::Python => "python",
+            FixtureLanguage::TypeScript => "typescript",
+        }
+    }
+
+    /// Parse a comma-separated list such as `rust,python,ts`, keeping the first
+    /// occurrence of each language
+    pub fn parse_list(list: &str) -> Result<Vec<Self>> {
+        let mut languages = Vec::new();
+        for item in list.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            let language = item.parse()?;
+            if !languages.contains(&language) {
+                languages.push(language);
+            }
+        }
+        if languages.is_empty() {
+            bail!("No fixture languages given; use any of rust, python, ts");
+        }
+        Ok(languages)
+    }
+
+    /// Directory of the language's sources inside the fixture
+    fn source_dir(&self) -> &'static str {
+        match self {
+            FixtureLanguage::Rust => "rust/src",
+            FixtureLanguage::Python => "python",
+            FixtureLanguage::TypeScript => "typescript/src",
+        }
+    }
+
+    fn extension(&self) -> &'static str {
+        match self {
+            FixtureLanguage::Rust => "rs",
+            FixtureLanguage::Python => "py",
+            FixtureLanguage::TypeScript => "ts",
+        }
+    }
+
+    /// File holding the entry point and, for Rust, the module declarations
+    fn entry_file(&self) -> &'static str {
+        match self {
+            FixtureLanguage::Rust => "lib",
+            FixtureLanguage::Python => "main",
+            FixtureLanguage::TypeScript => "index",
+        }
+    }
+
+    /// Function identifier from lowercase words
+    fn function_name(&self, words: &[&str]) -> String {
+        match self {
+            FixtureLanguage::TypeScript => words
+                .iter()
+                .enumerate()
+                .map(|(i, word)| {
+                    if i == 0 {
+                        word.to_string()
+                    } else {
+                        capitalize(word)
+                    }
+                })
+                .collect(),
+            _ => words.join("_"),
+        }
+    }
+}
+
+impl FromStr for FixtureLanguage {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "rust" | "rs" => Ok(FixtureLanguage::Rust),
+            "python" | "py" => Ok(FixtureLanguage::Python),
+            "typescript" | "ts" => Ok(FixtureLanguage::TypeScript),
+            other => bail!("Unsupported fixture language '{other}'; use rust, python or ts"),
+        }
+    }
+}
+
+/// How much code a fixture contains per language
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FixtureSize {
+    Small,
+    Medium,
+    Large,
+}
+
+impl FixtureSize {
+    fn modules(&self) -> usize {
+        match self {
+            FixtureSize::Small => 2,
+            FixtureSize::Medium => 4,
+            FixtureSize::Large => 12,
+        }
+    }
+
+    /// Pipeline functions per module
+    fn pipeline_length(&self) -> usize {
+        match self {
+            FixtureSize::Small => 3,
+            FixtureSize::Medium => 5,
+            FixtureSize::Large => 8,
+        }
+    }
+
+    fn cycles(&self) -> usize {
+        match self {
+            FixtureSize::Small => 1,
+            FixtureSize::Medium => 2,
+            FixtureSize::Large => 4,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            FixtureSize::Small => "small",
+            FixtureSize::Medium => "medium",
+            FixtureSize::Large => "large",
+        }
+    }
+}
+
+impl FromStr for FixtureSize {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "small" => Ok(FixtureSize::Small),
+            "medium" => Ok(FixtureSize::Medium),
+            "large" => Ok(FixtureSize::Large),
+            other => bail!("Unsupported fixture size '{other}'; use small, medium or large"),
+        }
+    }
+}
+
+/// What to generate
+#[derive(Debug, Clone)]
+pub struct FixtureOptions {
+    pub languages: Vec<FixtureLanguage>,
+    pub size: FixtureSize,
+    pub seed: u64,
+}
+
+impl Default for FixtureOptions {
+    fn default() -> Self {
+        Self {
+            languages: vec![
+                FixtureLanguage::Rust,
+                FixtureLanguage::Python,
+                FixtureLanguage::TypeScript,
+            ],
+            size: FixtureSize::Medium,
+            seed: DEFAULT_SEED,
+        }
+    }
+}
+
+/// A symbol defined in the fixture
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExpectedSymbol {
+    pub name: String,
+    /// `function`, `method`, `struct` or `class`
+    pub kind: String,
+    pub language: FixtureLanguage,
+    pub file: String,
+    /// Line of the definition, 1-based
+    pub line: usize,
+}
+
+/// A call from one fixture function to another
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExpectedCall {
+    pub caller: String,
+    pub callee: String,
+    /// File of the call site
+    pub file: String,
+    /// Line of the call site, 1-based
+    pub line: usize,
+}
+
+/// The analysis results a fixture is built to produce
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExpectedAnalysis {
+    pub seed: u64,
+    pub size: FixtureSize,
+    pub languages: Vec<FixtureLanguage>,
+    pub symbols: Vec<ExpectedSymbol>,
+    pub calls: Vec<ExpectedCall>,
+    /// Rings of mutually recursive functions, each listed from the function the
+    /// pipeline enters it through
+    pub cycles: Vec<Vec<String>>,
+    /// Functions nothing calls that are not entry points
+    pub dead_code: Vec<String>,
+    /// Functions and methods nothing in the fixture calls by design
+    pub entry_points: Vec<String>,
+}
+
+impl ExpectedAnalysis {
+    /// Calls of `callee`, in file and line order
+    pub fn callers_of(&self, callee: &str) -> Vec<&ExpectedCall> {
+        self.calls
+            .iter()
+            .filter(|call| call.callee == callee)
+            .collect()
+    }
+
+    /// Functions and methods some other fixture function calls
+    pub fn called_symbols(&self) -> Vec<&str> {
+        let mut called: Vec<&str> = self.calls.iter().map(|call| call.callee.as_str()).collect();
+        called.sort_unstable();
+        called.dedup();
+        called
+    }
+}
+
+/// A generated repository: file contents by relative path, and what analyzing it yields
+#[derive(Debug, Clone)]
+pub struct Fixture {
+    pub files: BTreeMap<String, String>,
+    pub expected: ExpectedAnalysis,
+}
+
+/// Outcome of writing a fixture to disk
+#[derive(Debug, Clone, Serialize)]
+pub struct FixtureReport {
+    pub path: PathBuf,
+    pub languages: Vec<FixtureLanguage>,
+    pub size: FixtureSize,
+    pub seed: u64,
+    pub files: usize,
+    pub symbols: usize,
+    pub calls: usize,
+    pub cycles: usize,
+    pub dead_code: usize,
+    /// Commit holding the fixture, when it was committed to a fresh repository
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub commit: Option<String>,
+}
+
+impl FixtureReport {
+    pub fn to_text(&self) -> String {
+        let languages: Vec<&str> = self.languages.iter().map(|l| l.name()).collect();
+        let mut out = format!(
+            "Generated {} fixture ({}, seed {}) in {}\n",
+            self.size.name(),
+            languages.join(", "),
+            self.seed,
+            self.path.display()
+        );
+        let _ = writeln!(
+            out,
+            "  {} files, {} symbols, {} calls, {} cycles, {} dead functions",
+            self.files, self.symbols, self.calls, self.cycles, self.dead_code
+        );
+        if let Some(commit) = &self.commit {
+            let _ = writeln!(out, "  committed as {}", &commit[..commit.len().min(12)]);
+        }
+        let _ = writeln!(
+            out,
+            "Expected analysis results: {}",
+            self.path.join(EXPECTED_FILE).display()
+        );
+        let _ = writeln!(
+            out,
+            "Index it with: kotadb index-codebase {}",
+            self.path.display()
+        );
+        out
+    }
+}
+
+impl Fixture {
+    /// Write the fixture and its expected results under `dir` and, with git integration,
+    /// commit them to a fresh repository so `kotadb index-codebase` can ingest it
+    ///
+    /// Refuses a non-empty directory unless `force` is set, in which case existing files
+    /// with the same paths are replaced and the fixture is committed on top.
+    pub fn write(&self, dir: &Path, force: bool) -> Result<FixtureReport> {
+        let occupied = dir
+            .read_dir()
+            .map(|mut entries| entries.next().is_some())
+            .unwrap_or(false);
+        if occupied && !force {
+            bail!(
+                "{} is not empty; choose another --output or pass --force",
+                dir.display()
+            );
+        }
+
+        for (path, content) in &self.files {
+            let target = dir.join(path);
+            if let Some(parent) = target.parent() {
+                std::fs::create_dir_all(parent)
+                    .with_context(|| format!("Failed to create {}", parent.display()))?;
+            }
+            std::fs::write(&target, content)
+                .with_context(|| format!("Failed to write {}", target.display()))?;
+        }
+        let manifest = serde_json::to_string_pretty(&self.expected)?;
+        std::fs::write(dir.join(EXPECTED_FILE), manifest + "\n")
+            .with_context(|| format!("Failed to write {}", dir.join(EXPECTED_FILE).display()))?;
+
+        #[cfg(feature = "git-integration")]
+        let commit = Some(self.commit(dir)?);
+        #[cfg(not(feature = "git-integration"))]
+        let commit = None;
+
+        Ok(FixtureReport {
+            path: dir.to_path_buf(),
+            languages: self.expected.languages.clone(),
+            size: self.expected.size,
+            seed: self.expected.seed,
+            files: self.files.len() + 1,
+            symbols: self.expected.symbols.len(),
+            calls: self.expected.calls.len(),
+            cycles: self.expected.cycles.len(),
+            dead_code: self.expected.dead_code.len(),
+            commit,
+        })
+    }
+
+    /// Commit the fixture with a fixed author and time so its commit id is reproducible
+    #[cfg(feature = "git-integration")]
+    fn commit(&self, dir: &Path) -> Result<String> {
+        let repo = git2::Repository::init(dir)
+            .with_context(|| format!("Failed to initialise a repository in {}", dir.display()))?;
+        let mut index = repo.index()?;
+        for path in self.files.keys().map(String::as_str).chain([EXPECTED_FILE]) {
+            index.add_path(Path::new(path))?;
+        }
+        index.write()?;
+
+        let tree = repo.find_tree(index.write_tree()?)?;
+        let signature = git2::Signature::new("kotadb", "kotadb@localhost", &git2::Time::new(0, 0))?;
+        let parent = repo.head().ok().and_then(|head| head.peel_to_commit().ok());
+        let message = format!(
+            "KotaDB {} fixture (seed {})",
+            self.expected.size.name(),
+            self.expected.seed
+        );
+        let id = repo.commit(
+            Some("HEAD"),
+            &signature,
+            &signature,
+            &message,
+            &tree,
+            &parent.iter().collect::<Vec<_>>(),
+        )?;
+        Ok(id.to_string())
+    }
+}
+
+/// Generate the fixture described by `options`
+pub fn generate(options: &FixtureOptions) -> Fixture {
+    let mut names = NamePool::new(options.seed);
+    let mut files = BTreeMap::new();
+    let mut expected = ExpectedAnalysis {
+        seed: options.seed,
+        size: options.size,
+        languages: options.languages.clone(),
+        symbols: Vec::new(),
+        calls: Vec::new(),
+        cycles: Vec::new(),
+        dead_code: Vec::new(),
+        entry_points: Vec::new(),
+    };
+
+    for &language in &options.languages {
+        let plan = plan_language(language, options.size, &mut names);
+        for module in &plan.modules {
+            for ring in &module.cycles {
+                expected.cycles.push(ring.clone());
+            }
+            expected.dead_code.push(module.dead.clone());
+            expected.entry_points.push(module.method.name.clone());
+        }
+        expected.entry_points.push(plan.entry.name.clone());
+        emit_language(&plan, &mut files, &mut expected);
+    }
+
+    let readme = readme(options, &expected);
+    files.insert("README.md".to_string(), readme);
+    Fixture { files, expected }
+}
+
+/// Words drawn without repetition by a seeded generator
+struct NamePool {
+    state: u64,
+    used_pairs: HashSet<(usize, usize)>,
+    used_nouns: HashSet<usize>,
+}
+
+impl NamePool {
+    fn new(seed: u64) -> Self {
+        Self {
+            state: seed,
+            used_pairs: HashSet::new(),
+            used_nouns: HashSet::new(),
+        }
+    }
+
+    /// SplitMix64
+    fn next(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// A noun no module has been named after yet
+    fn module_noun(&mut self) -> &'static str {
+        let start = (self.next() % NOUNS.len() as u64) as usize;
+        let index = (0..NOUNS.len())
+            .map(|offset| (start + offset) % NOUNS.len())
+            .find(|index| !self.used_nouns.contains(index))
+            .expect("more modules requested than nouns available");
+        self.used_nouns.insert(index);
+        NOUNS[index]
+    }
+
+    /// A verb-noun pair no function has been named after yet
+    fn function_words(&mut self) -> [&'static str; 2] {
+        let total = VERBS.len() * NOUNS.len();
+        let start = (self.next() % total as u64) as usize;
+        let index = (0..total)
+            .map(|offset| (start + offset) % total)
+            .find(|index| {
+                !self
+                    .used_pairs
+                    .contains(&(index / NOUNS.len(), index % NOUNS.len()))
+            })
+            .expect("more functions requested than names available");
+        let pair = (index / NOUNS.len(), index % NOUNS.len());
+        self.used_pairs.insert(pair);
+        [VERBS[pair.0], NOUNS[pair.1]]
+    }
+
+    fn function(&mut self, language: FixtureLanguage) -> String {
+        language.function_name(&self.function_words())
+    }
+}
+
+/// A function to emit and the functions it calls, by name
+struct FunctionPlan {
+    name: String,
+    calls: Vec<String>,
+    /// Return early on a zero input, for functions in a recursive ring
+    guard: bool,
+    doc: String,
+}
+
+struct ModulePlan {
+    noun: &'static str,
+    functions: Vec<FunctionPlan>,
+    cycles: Vec<Vec<String>>,
+    dead: String,
+    class: String,
+    method: FunctionPlan,
+}
+
+struct LanguagePlan {
+    language: FixtureLanguage,
+    entry: FunctionPlan,
+    modules: Vec<ModulePlan>,
+}
+
+fn plan_language(
+    language: FixtureLanguage,
+    size: FixtureSize,
+    names: &mut NamePool,
+) -> LanguagePlan {
+    let module_count = size.modules();
+    let mut modules: Vec<ModulePlan> = (0..module_count)
+        .map(|_| {
+            let noun = names.module_noun();
+            let pipeline: Vec<String> = (0..size.pipeline_length())
+                .map(|_| names.function(language))
+                .collect();
+            let functions = pipeline
+                .iter()
+                .enumerate()
+                .map(|(i, name)| FunctionPlan {
+                    name: name.clone(),
+                    calls: pipeline.get(i + 1).cloned().into_iter().collect(),
+                    guard: false,
+                    doc: format!("Step {} of the {noun} pipeline", i + 1),
+                })
+                .collect();
+            let dead = names.function(language);
+            let method = FunctionPlan {
+                name: names.function(language),
+                calls: vec![pipeline[0].clone()],
+                guard: false,
+                doc: format!("Run the {noun} pipeline"),
+            };
+            ModulePlan {
+                noun,
+                functions,
+                cycles: Vec::new(),
+                class: format!("{}Service", capitalize(noun)),
+                dead,
+                method,
+            }
+        })
+        .collect();
+
+    // The last step of each module hands over to the next module
+    for i in 0..module_count.saturating_sub(1) {
+        let next = modules[i + 1].functions[0].name.clone();
+        if let Some(last) = modules[i].functions.last_mut() {
+            last.calls.push(next);
+        }
+    }
+
+    for c in 0..size.cycles() {
+        let module = &mut modules[c % module_count];
+        let ring: Vec<String> = (0..2 + c % 2).map(|_| names.function(language)).collect();
+        for (i, name) in ring.iter().enumerate() {
+            module.functions.push(FunctionPlan {
+                name: name.clone(),
+                calls: vec![ring[(i + 1) % ring.len()].clone()],
+                guard: true,
+                doc: format!("Part of a recursive ring in the {} module", module.noun),
+            });
+        }
+        // Enter the ring from the module's last pipeline step
+        module.functions[size.pipeline_length() - 1]
+            .calls
+            .push(ring[0].clone());
+        module.cycles.push(ring);
+    }
+
+    for module in &mut modules {
+        module.functions.push(FunctionPlan {
+            name: module.dead.clone(),
+            calls: vec![module.functions[0].name.clone()],
+            guard: false,
+            doc: "Superseded helper; nothing calls it".to_string(),
+        });
+    }
+
+    let entry_name = match language {
+        FixtureLanguage::TypeScript => "runTypescriptFixture".to_string(),
+        _ => format!("run_{}_fixture", language.name()),
+    };
+    LanguagePlan {
+        language,
+        entry: FunctionPlan {
+            name: entry_name,
+            calls: vec![modules[0].functions[0].name.clone()],
+            guard: false,
+            doc: "Entry point of the fixture".to_string(),
+        },
+        modules,
+    }
+}
+
+/// Lines of one source file, tracking the line each symbol and call lands on
+struct SourceFile {
+    path: String,
+    lines: Vec<String>,
+}
+
+impl SourceFile {
+    fn new(path: String) -> Self {
+        Self {
+            path,
+            lines: Vec::new(),
+        }
+    }
+
+    /// Append a line and return its 1-based number
+    fn push(&mut self, line: impl Into<String>) -> usize {
+        self.lines.push(line.into());
+        self.lines.len()
+    }
+
+    fn finish(self) -> (String, String) {
+        let mut content = self.lines.join("\n");
+        content.push('\n');
+        (self.path, content)
+    }
+}
+
+fn emit_language(
+    plan: &LanguagePlan,
+    files: &mut BTreeMap<String, String>,
+    expected: &mut ExpectedAnalysis,
+) {
+    let language = plan.language;
+    let module_of: HashMap<&str, &str> = plan
+        .modules
+        .iter()
+        .flat_map(|module| {
+            module
+                .functions
+                .iter()
+                .map(move |function| (function.name.as_str(), module.noun))
+        })
+        .collect();
+    let path_of = |file: &str| {
+        format!(
+            "{}/{}.{}",
+            language.source_dir(),
+            file,
+            language.extension()
+        )
+    };
+
+    // Entry file
+    let entry_path = path_of(language.entry_file());
+    let mut entry = SourceFile::new(entry_path);
+    match language {
+        FixtureLanguage::Rust => {
+            entry.push("//! Generated by `kotadb generate-fixture`");
+            entry.push("");
+            for module in &plan.modules {
+                entry.push(format!("pub mod {};", module.noun));
+            }
+            entry.push("");
+        }
+        FixtureLanguage::Python => {
+            entry.push("\"\"\"Generated by `kotadb generate-fixture`.\"\"\"");
+            entry.push("");
+            let first = &plan.modules[0];
+            entry.push(format!(
+                "from {} import {}",
+                first.noun, first.functions[0].name
+            ));
+            entry.push("");
+            entry.push("");
+        }
+        FixtureLanguage::TypeScript => {
+            entry.push("// Generated by `kotadb generate-fixture`");
+            entry.push("");
+            let first = &plan.modules[0];
+            entry.push(format!(
+                "import {{ {} }} from \"./{}\";",
+                first.functions[0].name, first.noun
+            ));
+            entry.push("");
+        }
+    }
+    emit_function(
+        language,
+        &mut entry,
+        &plan.entry,
+        None,
+        &module_of,
+        "function",
+        expected,
+    );
+    let (path, content) = entry.finish();
+    files.insert(path, content);
+
+    for module in &plan.modules {
+        let mut file = SourceFile::new(path_of(module.noun));
+        emit_header(language, &mut file, module, &module_of);
+        for function in &module.functions {
+            emit_function(
+                language,
+                &mut file,
+                function,
+                Some(module.noun),
+                &module_of,
+                "function",
+                expected,
+            );
+            file.push("");
+            if language == FixtureLanguage::Python {
+                file.push("");
+            }
+        }
+        emit_class(language, &mut file, module, &module_of, expected);
+        let (path, content) = file.finish();
+        files.insert(path, content);
+    }
+
+    if language == FixtureLanguage::Rust {
+        files.insert(
+            "rust/Cargo.toml".to_string(),
+            "[package]\nname = \"kotadb-fixture\"\nversion = \"0.1.0\"\nedition = \"2021\"\n"
+                .to_string(),
+        );
+    }
+}
+
+/// Imports of the functions a module calls in other modules
+fn emit_header(
+    language: FixtureLanguage,
+    file: &mut SourceFile,
+    module: &ModulePlan,
+    module_of: &HashMap<&str, &str>,
+) {
+    let mut imports: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for function in &module.functions {
+        for callee in &function.calls {
+            let home = module_of[callee.as_str()];
+            if home != module.noun {
+                imports.entry(home).or_default().push(callee);
+            }
+        }
+    }
+    match language {
+        FixtureLanguage::Rust => {
+            file.push(format!("//! The {} module", module.noun));
+        }
+        FixtureLanguage::Python => {
+            file.push(format!("\"\"\"The {} module.\"\"\"", module.noun));
+            if !imports.is_empty() {
+                file.push("");
+            }
+            for (home, names) in &imports {
+                file.push(format!("from {home} import {}", names.join(", ")));
+            }
+            file.push("");
+        }
+        FixtureLanguage::TypeScript => {
+            file.push(format!("// The {} module", module.noun));
+            if !imports.is_empty() {
+                file.push("");
+            }
+            for (home, names) in &imports {
+                file.push(format!(
+                    "import {{ {} }} from \"./{home}\";",
+                    names.join(", ")
+                ));
+            }
+        }
+    }
+    file.push("");
+}
+
+fn emit_function(
+    language: FixtureLanguage,
+    file: &mut SourceFile,
+    function: &FunctionPlan,
+    module: Option<&str>,
+    module_of: &HashMap<&str, &str>,
+    kind: &str,
+    expected: &mut ExpectedAnalysis,
+) {
+    let indent = if kind == "method" {
+        match language {
+            FixtureLanguage::TypeScript => "  ",
+            _ => "    ",
+        }
+    } else {
+        ""
+    };
+    let body = match language {
+        FixtureLanguage::TypeScript => format!("{indent}  "),
+        _ => format!("{indent}    "),
+    };
+    // Rust calls into other modules by path; Python and TypeScript import the callee
+    let callee_path = |callee: &str| {
+        let home = module_of.get(callee).copied();
+        match (language, home) {
+            (FixtureLanguage::Rust, Some(home)) if Some(home) != module => {
+                format!("crate::{home}::{callee}")
+            }
+            _ => callee.to_string(),
+        }
+    };
+
+    let definition_line = match language {
+        FixtureLanguage::Rust => {
+            file.push(format!("{indent}/// {}", function.doc));
+            if kind == "method" {
+                file.push(format!(
+                    "{indent}pub fn {}(&mut self, input: u64) -> u64 {{",
+                    function.name
+                ))
+            } else {
+                file.push(format!(
+                    "{indent}pub fn {}(input: u64) -> u64 {{",
+                    function.name
+                ))
+            }
+        }
+        FixtureLanguage::Python => {
+            let line = if kind == "method" {
+                file.push(format!("{indent}def {}(self, value):", function.name))
+            } else {
+                file.push(format!("{indent}def {}(value):", function.name))
+            };
+            file.push(format!("{body}\"\"\"{}.\"\"\"", function.doc));
+            line
+        }
+        FixtureLanguage::TypeScript => {
+            file.push(format!("{indent}/** {} */", function.doc));
+            let prefix = if kind == "method" {
+                ""
+            } else {
+                "export function "
+            };
+            file.push(format!(
+                "{indent}{prefix}{}(input: number): number {{",
+                function.name
+            ))
+        }
+    };
+    expected.symbols.push(ExpectedSymbol {
+        name: function.name.clone(),
+        kind: kind.to_string(),
+        language,
+        file: file.path.clone(),
+        line: definition_line,
+    });
+
+    if function.guard {
+        match language {
+            FixtureLanguage::Rust => {
+                file.push(format!("{body}if input == 0 {{"));
+                file.push(format!("{body}    return 0;"));
+                file.push(format!("{body}}}"));
+            }
+            FixtureLanguage::Python => {
+                file.push(format!("{body}if value <= 0:"));
+                file.push(format!("{body}    return 0"));
+            }
+            FixtureLanguage::TypeScript => {
+                file.push(format!("{body}if (input <= 0) {{"));
+                file.push(format!("{body}  return 0;"));
+                file.push(format!("{body}}}"));
+            }
+        }
+    }
+    // Ring functions halve their input so the recursion stays shallow
+    let weight = function.name.len() % 7 + 2;
+    let computed = match (language, function.guard) {
+        (FixtureLanguage::Rust, true) => "input / 2".to_string(),
+        (FixtureLanguage::Rust, false) => {
+            format!("input.wrapping_mul({weight}).wrapping_add(1)")
+        }
+        (FixtureLanguage::Python, true) => "value // 2".to_string(),
+        (FixtureLanguage::Python, false) => format!("value * {weight} + 1"),
+        (FixtureLanguage::TypeScript, true) => "Math.floor(input / 2)".to_string(),
+        (FixtureLanguage::TypeScript, false) => format!("input * {weight} + 1"),
+    };
+    if language == FixtureLanguage::Rust && kind == "method" {
+        file.push(format!("{body}self.calls += 1;"));
+    }
+    // A function without calls returns its computed value directly
+    let text = match (language, function.calls.is_empty()) {
+        (FixtureLanguage::Rust, true) => computed,
+        (FixtureLanguage::Rust, false) => format!("let value = {computed};"),
+        (FixtureLanguage::Python, true) => format!("return {computed}"),
+        (FixtureLanguage::Python, false) => format!("value = {computed}"),
+        (FixtureLanguage::TypeScript, true) => format!("return {computed};"),
+        (FixtureLanguage::TypeScript, false) => format!("const value = {computed};"),
+    };
+    file.push(format!("{body}{text}"));
+
+    // Calls are terms of the returned expression, one per line, so each is attributed
+    // to the function rather than to a local binding
+    let single = function.calls.len() == 1;
+    let wrapped = function.calls.len() > 1 && language != FixtureLanguage::Rust;
+    if wrapped {
+        file.push(format!("{body}return ("));
+    }
+    for (i, callee) in function.calls.iter().enumerate() {
+        let call = format!("{}(value)", callee_path(callee));
+        let last = i + 1 == function.calls.len();
+        let text = match language {
+            FixtureLanguage::Rust if i == 0 => call,
+            FixtureLanguage::Rust => format!("    .wrapping_add({call})"),
+            FixtureLanguage::Python if single => format!("return {call}"),
+            FixtureLanguage::Python if i == 0 => format!("    {call}"),
+            FixtureLanguage::Python => format!("    + {call}"),
+            FixtureLanguage::TypeScript if single => format!("return {call};"),
+            FixtureLanguage::TypeScript if last => format!("  {call}"),
+            FixtureLanguage::TypeScript => format!("  {call} +"),
+        };
+        let line = file.push(format!("{body}{text}"));
+        expected.calls.push(ExpectedCall {
+            caller: function.name.clone(),
+            callee: callee.clone(),
+            file: file.path.clone(),
+            line,
+        });
+    }
+    if wrapped {
+        let close = if language == FixtureLanguage::Python {
+            ")"
+        } else {
+            ");"
+        };
+        file.push(format!("{body}{close}"));
+    }
+
+    if language != FixtureLanguage::Python {
+        file.push(format!("{indent}}}"));
+    }
+}
+
+fn emit_class(
+    language: FixtureLanguage,
+    file: &mut SourceFile,
+    module: &ModulePlan,
+    module_of: &HashMap<&str, &str>,
+    expected: &mut ExpectedAnalysis,
+) {
+    let (kind, line) = match language {
+        FixtureLanguage::Rust => {
+            file.push(format!(
+                "/// Runs the {} pipeline and counts runs",
+                module.noun
+            ));
+            let line = file.push(format!("pub struct {} {{", module.class));
+            file.push("    pub calls: u64,");
+            file.push("}");
+            file.push("");
+            file.push(format!("impl {} {{", module.class));
+            ("struct", line)
+        }
+        FixtureLanguage::Python => {
+            let line = file.push(format!("class {}:", module.class));
+            file.push(format!(
+                "    \"\"\"Runs the {} pipeline.\"\"\"",
+                module.noun
+            ));
+            file.push("");
+            ("class", line)
+        }
+        FixtureLanguage::TypeScript => {
+            file.push(format!("/** Runs the {} pipeline */", module.noun));
+            let line = file.push(format!("export class {} {{", module.class));
+            ("class", line)
+        }
+    };
+    expected.symbols.push(ExpectedSymbol {
+        name: module.class.clone(),
+        kind: kind.to_string(),
+        language,
+        file: file.path.clone(),
+        line,
+    });
+    emit_function(
+        language,
+        file,
+        &module.method,
+        Some(module.noun),
+        module_of,
+        "method",
+        expected,
+    );
+    if language != FixtureLanguage::Python {
+        file.push("}");
+    }
+}
+
+fn readme(options: &FixtureOptions, expected: &ExpectedAnalysis) -> String {
+    let languages: Vec<&str> = options.languages.iter().map(|l| l.name()).collect();
+    let mut out = String::from("# KotaDB fixture repository\n\n");
+    let _ = writeln!(
+        out,
+        "Generated by `kotadb generate-fixture --languages {} --size {} --seed {}`. \
+         Generating again with the same options reproduces these files exactly.\n",
+        languages.join(","),
+        options.size.name(),
+        options.seed
+    );
+    let _ = writeln!(
+        out,
+        "It defines {} symbols with {} calls, {} recursive rings and {} functions nothing \
+         calls. `{EXPECTED_FILE}` lists each of them with its file and line, to compare \
+         against `kotadb find-callers`, `kotadb gate` and the MCP analysis tools after \
+         `kotadb index-codebase`.",
+        expected.symbols.len(),
+        expected.calls.len(),
+        expected.cycles.len(),
+        expected.dead_code.len()
+    );
+    out
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_ascii_uppercase().to_string() + chars.as_str(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(size: FixtureSize, seed: u64) -> Fixture {
+        generate(&FixtureOptions {
+            size,
+            seed,
+            ..FixtureOptions::default()
+        })
+    }
+
+    #[test]
+    fn same_options_produce_the_same_fixture() {
+        let first = fixture(FixtureSize::Medium, 7);
+        let second = fixture(FixtureSize::Medium, 7);
+        assert_eq!(first.files, second.files);
+        assert_eq!(first.expected, second.expected);
+        assert_ne!(first.files, fixture(FixtureSize::Medium, 8).files);
+    }
+
+    #[test]
+    fn expected_locations_match_the_generated_sources() {
+        for size in [FixtureSize::Small, FixtureSize::Medium, FixtureSize::Large] {
+            let fixture = fixture(size, DEFAULT_SEED);
+            let line_of = |file: &str, line: usize| -> &str {
+                fixture.files[file].lines().nth(line - 1).unwrap()
+            };
+            for symbol in &fixture.expected.symbols {
+                assert!(
+                    line_of(&symbol.file, symbol.line).contains(&symbol.name),
+                    "{} not defined at {}:{}",
+                    symbol.name,
+                    symbol.file,
+                    symbol.line
+                );
+            }
+            for call in &fixture.expected.calls {
+                assert!(
+                    line_of(&call.file, call.line).contains(&format!("{}(", call.callee)),
+                    "{} not called at {}:{}",
+                    call.callee,
+                    call.file,
+                    call.line
+                );
+            }
+
+            let names: HashSet<&str> = fixture
+                .expected
+                .symbols
+                .iter()
+                .filter(|s| s.kind == "function" || s.kind == "method")
+                .map(|s| s.name.as_str())
+                .collect();
+            let functions = fixture
+                .expected
+                .symbols
+                .iter()
+                .filter(|s| s.kind == "function" || s.kind == "method")
+                .count();
+            assert_eq!(names.len(), functions, "function names must be unique");
+        }
+    }
+
+    #[test]
+    fn dead_code_and_cycles_follow_the_call_graph() {
+        let fixture = fixture(FixtureSize::Medium, DEFAULT_SEED);
+        let expected = &fixture.expected;
+        let called = expected.called_symbols();
+        assert_eq!(expected.dead_code.len(), 3 * FixtureSize::Medium.modules());
+        for name in expected.dead_code.iter().chain(&expected.entry_points) {
+            assert!(!called.contains(&name.as_str()), "{name} is called");
+        }
+
+        assert_eq!(expected.cycles.len(), 3 * FixtureSize::Medium.cycles());
+        for ring in &expected.cycles {
+            for (i, name) in ring.iter().enumerate() {
+                let next = &ring[(i + 1) % ring.len()];
+                assert!(expected
+                    .callers_of(next)
+                    .iter()
+                    .any(|call| &call.caller == name));
+            }
+            // The pipeline enters the ring, so it is not dead code
+            assert_eq!(expected.callers_of(&ring[0]).len(), 2);
+        }
+    }
+
+    #[test]
+    fn parses_language_lists_and_sizes() {
+        assert_eq!(
+            FixtureLanguage::parse_list("rust, ts,python,rs").unwrap(),
+            vec![
+                FixtureLanguage::Rust,
+                FixtureLanguage::TypeScript,
+                FixtureLanguage::Python
+            ]
+        );
+        assert!(FixtureLanguage::parse_list("rust,go").is_err());
+        assert!(FixtureLanguage::parse_list("").is_err());
+        assert_eq!("Large".parse::<FixtureSize>().unwrap(), FixtureSize::Large);
+    }
+}
@@ -0,0 +1,455 @@
+//! Function-level embedding index for code clone search
+//!
+//! Document embeddings describe whole files, which is too coarse to answer
+//! "which other functions do the same thing as this one". [`FunctionVectorIndex`]
+//! embeds the body of every function and method in `symbols.kota` separately and
+//! keeps those vectors in their own [`VectorIndex`] in the database directory, so
+//! a search only ever returns function-level chunks. Chunks are keyed by a hash of
+//! their body: unchanged functions are not embedded again when the index is
+//! refreshed, and identical copies share one vector.
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+use crate::binary_symbols::BinarySymbolReader;
+use crate::contracts::Document;
+use crate::embeddings::EmbeddingProvider;
+use crate::path_utils::paths_equivalent;
+use crate::types::ValidatedDocumentId;
+use crate::vector_index::{DistanceMetric, VectorIndex};
+
+/// File name of the function vector index inside a database directory
+pub const FUNCTION_VECTORS_FILE: &str = "function_vectors.idx";
+
+/// File name of the chunk manifest inside a database directory
+pub const FUNCTION_MANIFEST_FILE: &str = "function_vectors.json";
+
+/// Longest function body embedded; longer bodies are cut to their beginning
+const MAX_CHUNK_CHARS: usize = 8_000;
+
+/// Binary symbol kinds of functions and methods
+const FUNCTION_KINDS: [u8; 2] = [1, 2];
+
+/// Where a function is defined
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FunctionLocation {
+    pub name: String,
+    pub file_path: String,
+    /// First line of the definition (1-based)
+    pub start_line: u32,
+    /// Last line of the definition (1-based)
+    pub end_line: u32,
+}
+
+/// The source of one function or method, as embedded
+#[derive(Debug, Clone)]
+pub struct FunctionChunk {
+    pub location: FunctionLocation,
+    pub text: String,
+}
+
+/// Function and method bodies of a symbol database, cut from the stored documents
+///
+/// Symbols whose file is not stored, or whose lines fall outside it, are skipped.
+pub fn function_chunks(reader: &BinarySymbolReader, documents: &[Document]) -> Vec<FunctionChunk> {
+    let mut lines_by_path: HashMap<String, Option<Vec<String>>> = HashMap::new();
+    let mut chunks = Vec::new();
+    for symbol in reader.iter_symbols() {
+        if !FUNCTION_KINDS.contains(&symbol.kind) {
+            continue;
+        }
+        let (Ok(name), Ok(file_path)) = (
+            reader.get_symbol_name(&symbol),
+            reader.get_symbol_file_path(&symbol),
+        ) else {
+            continue;
+        };
+        let lines = lines_by_path
+            .entry(file_path.clone())
+            .or_insert_with(|| {
+                documents
+                    .iter()
+                    .find(|doc| paths_equivalent(doc.path.as_str(), &file_path))
+                    .map(|doc| {
+                        String::from_utf8_lossy(&doc.content)
+                            .lines()
+                            .map(str::to_string)
+                            .collect()
+                    })
+            })
+            .as_ref();
+        let Some(lines) = lines else {
+            continue;
+        };
+
+        let start = symbol.start_line as usize;
+        let end = (symbol.end_line as usize).min(lines.len());
+        if start == 0 || start > end {
+            continue;
+        }
+        let mut text = lines[start - 1..end].join("\n");
+        if text.len() > MAX_CHUNK_CHARS {
+            let mut cut = MAX_CHUNK_CHARS;
+            while !text.is_char_boundary(cut) {
+                cut -= 1;
+            }
+            text.truncate(cut);
+        }
+        if text.trim().is_empty() {
+            continue;
+        }
+        chunks.push(FunctionChunk {
+            location: FunctionLocation {
+                name,
+                file_path,
+                start_line: symbol.start_line,
+                end_line: end as u32,
+            },
+            text,
+        });
+    }
+    chunks
+}
+
+/// Vector index id of a function body
+fn chunk_id(text: &str) -> Result<ValidatedDocumentId> {
+    let digest = Sha256::digest(text.as_bytes());
+    let mut bytes = [0u8; 16];
+    bytes.copy_from_slice(&digest[..16]);
+    ValidatedDocumentId::from_uuid(Uuid::from_bytes(bytes))
+}
+
+/// Locations of every embedded body, keyed by chunk id
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct FunctionManifest {
+    model: String,
+    dimension: usize,
+    chunks: BTreeMap<String, Vec<FunctionLocation>>,
+}
+
+/// A function-level search hit
+#[derive(Debug, Clone)]
+pub struct FunctionMatch<'a> {
+    /// Cosine similarity to the query vector (0-1)
+    pub similarity: f32,
+    /// Every function with this body
+    pub locations: &'a [FunctionLocation],
+}
+
+/// Embeddings of function bodies, persisted next to the other indices
+pub struct FunctionVectorIndex {
+    manifest_path: PathBuf,
+    index: VectorIndex,
+    manifest: FunctionManifest,
+}
+
+impl FunctionVectorIndex {
+    /// Open the function index of a database for embeddings from `model`
+    ///
+    /// An index built with another model or dimension is discarded.
+    pub async fn open(db_path: &Path, model: &str, dimension: usize) -> Result<Self> {
+        let manifest_path = db_path.join(FUNCTION_MANIFEST_FILE);
+        let index_path = db_path.join(FUNCTION_VECTORS_FILE);
+
+        let saved = if manifest_path.exists() {
+            let content = std::fs::read_to_string(&manifest_path).with_context(|| {
+                format!("Failed to read function index: {}", manifest_path.display())
+            })?;
+            serde_json::from_str::<FunctionManifest>(&content).ok()
+        } else {
+            None
+        };
+        let reusable =
+            saved.filter(|m| m.model == model && m.dimension == dimension && index_path.exists());
+
+        let (index, manifest) = match reusable {
+            Some(manifest) => (
+                VectorIndex::load(&index_path, DistanceMetric::Cosine, dimension).await?,
+                manifest,
+            ),
+            None => (
+                VectorIndex::new(&index_path, DistanceMetric::Cosine, dimension).await?,
+                FunctionManifest {
+                    model: model.to_string(),
+                    dimension,
+                    chunks: BTreeMap::new(),
+                },
+            ),
+        };
+        Ok(Self {
+            manifest_path,
+            index,
+            manifest,
+        })
+    }
+
+    /// Number of distinct function bodies embedded
+    pub fn len(&self) -> usize {
+        self.manifest.chunks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.manifest.chunks.is_empty()
+    }
+
+    /// Bring the index in line with `chunks`, returning how many bodies were embedded
+    ///
+    /// Bodies already in the index keep their vectors; bodies no longer present are
+    /// removed.
+    pub async fn refresh(
+        &mut self,
+        chunks: &[FunctionChunk],
+        provider: &dyn EmbeddingProvider,
+    ) -> Result<usize> {
+        let mut current: BTreeMap<String, (ValidatedDocumentId, &str, Vec<FunctionLocation>)> =
+            BTreeMap::new();
+        for chunk in chunks {
+            let id = chunk_id(&chunk.text)?;
+            current
+                .entry(id.to_string())
+                .or_insert_with(|| (id, chunk.text.as_str(), Vec::new()))
+                .2
+                .push(chunk.location.clone());
+        }
+
+        let mut stale = Vec::new();
+        for key in self.manifest.chunks.keys() {
+            if !current.contains_key(key) {
+                stale.push(ValidatedDocumentId::parse(key)?);
+            }
+        }
+        if !stale.is_empty() {
+            self.index.remove_vectors(&stale).await?;
+        }
+
+        let missing: Vec<(ValidatedDocumentId, String)> = current
+            .iter()
+            .filter(|(key, _)| !self.manifest.chunks.contains_key(*key))
+            .map(|(_, (id, text, _))| (*id, text.to_string()))
+            .collect();
+        let mut vectors = Vec::with_capacity(missing.len());
+        for batch in missing.chunks(provider.max_batch_size().max(1)) {
+            let texts: Vec<String> = batch.iter().map(|(_, text)| text.clone()).collect();
+            let result = provider.embed_texts(&texts).await?;
+            if result.embeddings.len() != batch.len() {
+                bail!(
+                    "Embedding provider returned {} vectors for {} function bodies",
+                    result.embeddings.len(),
+                    batch.len()
+                );
+            }
+            vectors.extend(batch.iter().map(|(id, _)| *id).zip(result.embeddings));
+        }
+        self.index.insert_vectors(vectors).await?;
+
+        self.manifest.chunks = current
+            .into_iter()
+            .map(|(key, (_, _, locations))| (key, locations))
+            .collect();
+        self.save_manifest()?;
+        Ok(missing.len())
+    }
+
+    /// Function bodies closest to `vector`, most similar first
+    pub async fn search(&self, vector: &[f32], k: usize) -> Result<Vec<FunctionMatch<'_>>> {
+        let hits = self.index.search_knn(vector, k, None).await?;
+        Ok(hits
+            .into_iter()
+            .filter_map(|(id, distance)| {
+                let locations = self.manifest.chunks.get(&id.to_string())?;
+                Some(FunctionMatch {
+                    similarity: (1.0 - distance).clamp(0.0, 1.0),
+                    locations,
+                })
+            })
+            .collect())
+    }
+
+    fn save_manifest(&self) -> Result<()> {
+        let tmp_path = self.manifest_path.with_extension("json.tmp");
+        std::fs::write(&tmp_path, serde_json::to_string(&self.manifest)?)
+            .with_context(|| format!("Failed to write function index: {}", tmp_path.display()))?;
+        std::fs::rename(&tmp_path, &self.manifest_path).with_context(|| {
+            format!(
+                "Failed to replace function index: {}",
+                self.manifest_path.display()
+            )
+        })?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binary_symbols::BinarySymbolWriter;
+    use crate::builders::DocumentBuilder;
+    use crate::embeddings::EmbeddingResult;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    const DIMENSION: usize = 64;
+
+    /// Bag-of-words vectors, counting the texts it embeds
+    #[derive(Default)]
+    struct WordBagProvider {
+        embedded: AtomicUsize,
+    }
+
+    #[async_trait::async_trait]
+    impl EmbeddingProvider for WordBagProvider {
+        async fn embed_texts(&self, texts: &[String]) -> Result<EmbeddingResult> {
+            self.embedded.fetch_add(texts.len(), Ordering::SeqCst);
+            let embeddings = texts
+                .iter()
+                .map(|text| {
+                    let mut vector = vec![0.0f32; DIMENSION];
+                    for word in text.split(|c: char| !c.is_alphanumeric()) {
+                        if !word.is_empty() {
+                            let slot = word.bytes().map(usize::from).sum::<usize>() % DIMENSION;
+                            vector[slot] += 1.0;
+                        }
+                    }
+                    vector
+                })
+                .collect();
+            Ok(EmbeddingResult {
+                embeddings,
+                model_used: "word-bag".to_string(),
+                tokens_used: None,
+            })
+        }
+
+        fn dimension(&self) -> usize {
+            DIMENSION
+        }
+
+        fn model_name(&self) -> &str {
+            "word-bag"
+        }
+
+        fn max_batch_size(&self) -> usize {
+            2
+        }
+    }
+
+    const SOURCE: &str = "\
+fn total(items: &[u32]) -> u32 {
+    items.iter().sum()
+}
+fn sum_all(values: &[u32]) -> u32 {
+    values.iter().sum()
+}
+fn greet(name: &str) -> String {
+    format!(\"hello {name}\")
+}
+struct Config;
+fn total_copy(items: &[u32]) -> u32 {
+    items.iter().sum()
+}";
+
+    fn chunks_for(dir: &Path, source: &str) -> Vec<FunctionChunk> {
+        let mut writer = BinarySymbolWriter::new();
+        for (name, kind, start, end) in [
+            ("total", 1, 1, 3),
+            ("sum_all", 1, 4, 6),
+            ("greet", 2, 7, 9),
+            ("Config", 4, 10, 10),
+            ("total_copy", 1, 11, 13),
+        ] {
+            writer.add_symbol(Uuid::new_v4(), name, kind, "src/lib.rs", start, end, None);
+        }
+        let symbols_path = dir.join("symbols.kota");
+        writer.write_to_file(&symbols_path).unwrap();
+        let reader = BinarySymbolReader::open(&symbols_path).unwrap();
+        let document = DocumentBuilder::new()
+            .path("repos/demo/files/src/lib.rs")
+            .unwrap()
+            .title("lib.rs")
+            .unwrap()
+            .content(source.as_bytes())
+            .build()
+            .unwrap();
+        function_chunks(&reader, &[document])
+    }
+
+    #[test]
+    fn chunks_cover_functions_and_methods_only() {
+        let dir = tempfile::tempdir().unwrap();
+        let chunks = chunks_for(dir.path(), SOURCE);
+        let mut names: Vec<&str> = chunks.iter().map(|c| c.location.name.as_str()).collect();
+        names.sort();
+        assert_eq!(names, vec!["greet", "sum_all", "total", "total_copy"]);
+        let sum_all = chunks
+            .iter()
+            .find(|c| c.location.name == "sum_all")
+            .unwrap();
+        assert_eq!(
+            sum_all.text,
+            "fn sum_all(values: &[u32]) -> u32 {\n    values.iter().sum()\n}"
+        );
+        assert_eq!(sum_all.location.start_line, 4);
+        assert_eq!(sum_all.location.end_line, 6);
+    }
+
+    #[tokio::test]
+    async fn refresh_embeds_each_body_once_and_search_ranks_clones_first() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut chunks = chunks_for(dir.path(), SOURCE);
+        let total = chunks
+            .iter()
+            .find(|c| c.location.name == "total")
+            .unwrap()
+            .clone();
+        let mut copied = total.clone();
+        copied.location.file_path = "src/other.rs".to_string();
+        chunks.push(copied);
+        let provider = WordBagProvider::default();
+
+        let mut index = FunctionVectorIndex::open(dir.path(), "word-bag", DIMENSION)
+            .await
+            .unwrap();
+        // The verbatim copy of total shares its vector
+        assert_eq!(index.refresh(&chunks, &provider).await.unwrap(), 4);
+        assert_eq!(index.len(), 4);
+
+        let query = provider.embed_text(&total.text).await.unwrap();
+        let matches = index.search(&query, 4).await.unwrap();
+        let files: Vec<&str> = matches[0]
+            .locations
+            .iter()
+            .map(|l| l.file_path.as_str())
+            .collect();
+        assert_eq!(files, vec!["src/lib.rs", "src/other.rs"]);
+        assert!((matches[0].similarity - 1.0).abs() < 1e-6);
+        let names: Vec<&str> = matches[1..]
+            .iter()
+            .map(|m| m.locations[0].name.as_str())
+            .collect();
+        assert_eq!(names, vec!["total_copy", "sum_all", "greet"]);
+        assert!(matches[1].similarity > matches[2].similarity);
+
+        // Reopening keeps existing vectors; only changed bodies are embedded again
+        drop(index);
+        let mut index = FunctionVectorIndex::open(dir.path(), "word-bag", DIMENSION)
+            .await
+            .unwrap();
+        assert_eq!(index.refresh(&chunks, &provider).await.unwrap(), 0);
+        let changed = chunks_for(
+            dir.path(),
+            &SOURCE.replace("hello {name}", "goodbye {name}"),
+        );
+        assert_eq!(index.refresh(&changed, &provider).await.unwrap(), 1);
+        assert_eq!(index.len(), 4);
+        assert_eq!(provider.embedded.load(Ordering::SeqCst), 6);
+
+        // Another model starts over
+        let index = FunctionVectorIndex::open(dir.path(), "other-model", DIMENSION)
+            .await
+            .unwrap();
+        assert!(index.is_empty());
+    }
+}
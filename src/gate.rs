@@ -0,0 +1,247 @@
+// Gate module - Codebase health thresholds enforced in CI
+//
+// A gate compares the current health metrics of a database (dependency cycles, unused symbols,
+// test-to-code ratio, average complexity) with limits kept in a YAML file and/or given on the
+// command line. `kotadb gate` exits nonzero when any limit is exceeded, so a pipeline can block
+// changes that make the codebase measurably worse.
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt::Write as _;
+use std::path::Path;
+
+use crate::trends::MetricsSnapshot;
+
+/// Config file `kotadb gate` reads from the working directory when `--config` is not given
+pub const DEFAULT_GATE_CONFIG: &str = "kotadb-gate.yml";
+
+/// Limits a codebase must stay within; unset limits are not checked
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GateThresholds {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_cycles: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_unused: Option<usize>,
+    /// Lowest acceptable number of test files per source file
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_test_ratio: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_avg_complexity: Option<f64>,
+}
+
+impl GateThresholds {
+    pub fn load(path: &Path) -> Result<Self> {
+        let raw = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read gate config {}", path.display()))?;
+        Self::parse(&raw).with_context(|| format!("invalid gate config {}", path.display()))
+    }
+
+    pub fn parse(raw: &str) -> Result<Self> {
+        let thresholds: GateThresholds = serde_yaml::from_str(raw)?;
+        thresholds.validate()?;
+        Ok(thresholds)
+    }
+
+    /// Limits given as flags, completed from `config` or, when no config is given,
+    /// from [`DEFAULT_GATE_CONFIG`] in the working directory if it exists
+    pub fn resolve(flags: GateThresholds, config: Option<&Path>) -> Result<Self> {
+        flags.validate()?;
+        let default = Path::new(DEFAULT_GATE_CONFIG);
+        let config = config.or_else(|| default.exists().then_some(default));
+        let thresholds = match config {
+            Some(path) => flags.or(Self::load(path)?),
+            None => flags,
+        };
+        if thresholds.is_empty() {
+            bail!(
+                "no thresholds configured: pass --max-cycles, --max-unused, --min-test-ratio or \
+                 --max-avg-complexity, or create {}",
+                DEFAULT_GATE_CONFIG
+            );
+        }
+        Ok(thresholds)
+    }
+
+    /// Fill unset limits from `defaults`
+    pub fn or(self, defaults: GateThresholds) -> GateThresholds {
+        GateThresholds {
+            max_cycles: self.max_cycles.or(defaults.max_cycles),
+            max_unused: self.max_unused.or(defaults.max_unused),
+            min_test_ratio: self.min_test_ratio.or(defaults.min_test_ratio),
+            max_avg_complexity: self.max_avg_complexity.or(defaults.max_avg_complexity),
+        }
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.min_test_ratio.is_some_and(|ratio| ratio < 0.0) {
+            bail!("min_test_ratio must not be negative");
+        }
+        if self.max_avg_complexity.is_some_and(|limit| limit < 0.0) {
+            bail!("max_avg_complexity must not be negative");
+        }
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == GateThresholds::default()
+    }
+}
+
+/// One metric compared with its limit
+#[derive(Debug, Clone, Serialize)]
+pub struct GateCheck {
+    pub metric: &'static str,
+    pub actual: f64,
+    pub limit: f64,
+    /// `max` when `actual` must not exceed `limit`, `min` when it must reach it
+    pub bound: &'static str,
+    pub passed: bool,
+}
+
+/// Outcome of evaluating a gate
+#[derive(Debug, Clone, Serialize)]
+pub struct GateReport {
+    pub metrics: MetricsSnapshot,
+    pub checks: Vec<GateCheck>,
+}
+
+impl GateReport {
+    pub fn is_success(&self) -> bool {
+        self.checks.iter().all(|check| check.passed)
+    }
+
+    pub fn failed(&self) -> usize {
+        self.checks.iter().filter(|check| !check.passed).count()
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "# Quality gate\n");
+        for check in &self.checks {
+            let status = if check.passed { "✅" } else { "❌" };
+            let _ = writeln!(
+                out,
+                "{} {}: {} ({} {})",
+                status,
+                check.metric,
+                format_value(check.actual),
+                check.bound,
+                format_value(check.limit)
+            );
+        }
+        let _ = writeln!(
+            out,
+            "\n**{} passed, {} failed**",
+            self.checks.len() - self.failed(),
+            self.failed()
+        );
+        out
+    }
+}
+
+fn format_value(value: f64) -> String {
+    if value.fract() == 0.0 {
+        format!("{}", value)
+    } else {
+        format!("{:.2}", value)
+    }
+}
+
+/// Compare `metrics` with every limit set in `thresholds`
+pub fn evaluate(metrics: MetricsSnapshot, thresholds: &GateThresholds) -> GateReport {
+    let mut checks = Vec::new();
+    let mut max = |metric, actual: f64, limit: Option<f64>| {
+        if let Some(limit) = limit {
+            checks.push(GateCheck {
+                metric,
+                actual,
+                limit,
+                bound: "max",
+                passed: actual <= limit,
+            });
+        }
+    };
+    max(
+        "cycles",
+        metrics.cycles as f64,
+        thresholds.max_cycles.map(|limit| limit as f64),
+    );
+    max(
+        "unused_symbols",
+        metrics.unused_symbols as f64,
+        thresholds.max_unused.map(|limit| limit as f64),
+    );
+    max(
+        "avg_complexity",
+        metrics.avg_complexity,
+        thresholds.max_avg_complexity,
+    );
+    if let Some(limit) = thresholds.min_test_ratio {
+        checks.push(GateCheck {
+            metric: "test_ratio",
+            actual: metrics.test_to_code_ratio,
+            limit,
+            bound: "min",
+            passed: metrics.test_to_code_ratio >= limit,
+        });
+    }
+    GateReport { metrics, checks }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metrics() -> MetricsSnapshot {
+        MetricsSnapshot {
+            cycles: 1,
+            unused_symbols: 40,
+            test_to_code_ratio: 0.25,
+            avg_complexity: 3.5,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn evaluate_checks_only_the_limits_that_are_set() {
+        let report = evaluate(
+            metrics(),
+            &GateThresholds {
+                max_cycles: Some(0),
+                max_unused: Some(50),
+                min_test_ratio: Some(0.3),
+                max_avg_complexity: None,
+            },
+        );
+        let failed: Vec<&str> = report
+            .checks
+            .iter()
+            .filter(|check| !check.passed)
+            .map(|check| check.metric)
+            .collect();
+        assert_eq!(report.checks.len(), 3);
+        assert_eq!(failed, vec!["cycles", "test_ratio"]);
+        assert!(!report.is_success());
+        assert!(report
+            .to_markdown()
+            .contains("❌ test_ratio: 0.25 (min 0.30)"));
+
+        assert!(evaluate(metrics(), &GateThresholds::default()).is_success());
+    }
+
+    #[test]
+    fn config_values_are_overridden_by_flags() {
+        let config = GateThresholds::parse("max_cycles: 2\nmin_test_ratio: 0.5\n").unwrap();
+        let flags = GateThresholds {
+            max_cycles: Some(0),
+            ..Default::default()
+        };
+        let merged = flags.or(config);
+        assert_eq!(merged.max_cycles, Some(0));
+        assert_eq!(merged.min_test_ratio, Some(0.5));
+
+        assert!(GateThresholds::parse("max_cycle: 2\n").is_err());
+        assert!(GateThresholds::parse("min_test_ratio: -1\n").is_err());
+    }
+}
@@ -150,7 +150,7 @@ impl GitDocument {
 }
 
 /// Repository organization configuration
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RepositoryOrganizationConfig {
     /// Base data directory for repository analysis
     pub base_data_dir: String,
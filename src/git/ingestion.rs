@@ -1,6 +1,7 @@
 //! Git repository ingestion into KotaDB
 
 use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
 use std::path::Path;
 use tracing::{info, instrument, warn};
@@ -23,7 +24,7 @@ use tokio::sync::Mutex;
 #[cfg(feature = "tree-sitter-parsing")]
 use crate::binary_relationship_bridge::BinaryRelationshipBridge;
 #[cfg(feature = "tree-sitter-parsing")]
-use crate::binary_symbols::BinarySymbolWriter;
+use crate::binary_symbols::{BinarySymbolWriter, SymbolRange};
 #[cfg(feature = "tree-sitter-parsing")]
 use crate::parsing::{CodeParser, SupportedLanguage};
 #[cfg(feature = "tree-sitter-parsing")]
@@ -33,7 +34,7 @@ use crate::symbol_storage::SymbolStorage;
 pub type ProgressCallback = Box<dyn Fn(&str) + Send + Sync>;
 
 /// Configuration for repository ingestion
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct IngestionConfig {
     /// Prefix for document paths in KotaDB
     pub path_prefix: String,
@@ -204,7 +205,10 @@ impl RepositoryIngester {
             metadata.name, metadata.commit_count
         );
 
-        let mut result = IngestResult::default();
+        let mut result = IngestResult {
+            document_prefix: self.document_prefix(&safe_repo_name),
+            ..IngestResult::default()
+        };
 
         // Ingest files first
         report_progress("Discovering repository files...");
@@ -320,13 +324,19 @@ impl RepositoryIngester {
                         // 2. Maintaining a name->UUID map during processing
                         let parent_id: Option<uuid::Uuid> = None;
 
-                        writer.add_symbol(
+                        writer.add_symbol_with_range(
                             uuid::Uuid::new_v4(),
                             &symbol.name,
                             kind,
                             file_path,
                             symbol.start_line as u32,
                             symbol.end_line as u32,
+                            SymbolRange {
+                                start_column: symbol.start_column as u32,
+                                end_column: symbol.end_column as u32,
+                                start_byte: symbol.start_byte as u32,
+                                end_byte: symbol.end_byte as u32,
+                            },
                             parent_id,
                         );
 
@@ -354,6 +364,22 @@ impl RepositoryIngester {
             }
         }
 
+        // Ingest commit history
+        if self.config.options.include_commit_history {
+            report_progress("Loading commit history...");
+            let commits = repo
+                .get_commits(None)
+                .context("Failed to get repository commits")?;
+            self.ingest_commit_history(
+                commits,
+                &safe_repo_name,
+                storage,
+                &mut result,
+                &report_progress,
+            )
+            .await?;
+        }
+
         info!(
             "Binary ingestion complete: {} documents, {} symbols",
             result.documents_created, result.symbols_extracted
@@ -362,6 +388,37 @@ impl RepositoryIngester {
         Ok(result)
     }
 
+    /// Ingest a repository for indexing: documents into `storage` and, with
+    /// `extract_symbols`, `symbols.kota` and `dependency_graph.bin` into `db_path`
+    pub async fn ingest_codebase<S: Storage + ?Sized>(
+        &self,
+        repo_path: &Path,
+        storage: &mut S,
+        db_path: &Path,
+        extract_symbols: bool,
+        progress_callback: Option<ProgressCallback>,
+    ) -> Result<IngestResult> {
+        #[cfg(feature = "tree-sitter-parsing")]
+        if extract_symbols {
+            // Use binary symbol storage with relationship extraction for complete analysis
+            return self
+                .ingest_with_binary_symbols_and_relationships(
+                    repo_path,
+                    storage,
+                    db_path.join("symbols.kota"),
+                    db_path.join("dependency_graph.bin"),
+                    progress_callback,
+                )
+                .await
+                .context("Failed to ingest repository with symbol and relationship extraction");
+        }
+
+        let _ = (db_path, extract_symbols);
+        self.ingest_with_progress(repo_path, storage, progress_callback)
+            .await
+            .context("Failed to ingest repository without symbol extraction")
+    }
+
     /// Ingest a git repository with binary symbols and relationships (complete hybrid solution)
     #[cfg(feature = "tree-sitter-parsing")]
     pub async fn ingest_with_binary_symbols_and_relationships<S: Storage + ?Sized>(
@@ -416,7 +473,15 @@ impl RepositoryIngester {
             "Analyzing {} source files for dependencies...",
             file_contents.len()
         ));
-        let bridge = BinaryRelationshipBridge::new();
+        let crates = crate::cargo_workspace::CrateMap::from_files(
+            file_contents
+                .iter()
+                .map(|(path, content)| (path.as_path(), content.as_slice())),
+        );
+        if let Some(db_dir) = graph_db_path.as_ref().parent() {
+            crates.save(db_dir)?;
+        }
+        let bridge = BinaryRelationshipBridge::new().with_crates(crates);
         match bridge.extract_relationships(symbol_db_path.as_ref(), repo_path, &file_contents) {
             Ok(dependency_graph) => {
                 result.relationships_extracted = dependency_graph.stats.edge_count;
@@ -425,11 +490,10 @@ impl RepositoryIngester {
                     result.relationships_extracted
                 ));
 
-                // Serialize and save the dependency graph (using bincode for compatibility)
+                // Serialize and save the dependency graph (versioned bincode payload)
                 report_progress("Persisting dependency graph to disk...");
                 let serializable = dependency_graph.to_serializable();
-                let graph_binary = bincode::serialize(&serializable)
-                    .context("Failed to serialize dependency graph")?;
+                let graph_binary = serializable.to_bytes()?;
                 std::fs::write(graph_db_path.as_ref(), graph_binary)
                     .context("Failed to write dependency graph to disk")?;
 
@@ -680,7 +744,10 @@ impl RepositoryIngester {
             metadata.name, metadata.commit_count, safe_repo_name
         );
 
-        let mut result = IngestResult::default();
+        let mut result = IngestResult {
+            document_prefix: self.document_prefix(&safe_repo_name),
+            ..IngestResult::default()
+        };
 
         // Initialize file organization manager if configured
         let _file_org_manager = self
@@ -1033,49 +1100,14 @@ impl RepositoryIngester {
             let commits = repo
                 .get_commits(None)
                 .context("Failed to get repository commits")?;
-
-            info!("Processing {} commits", commits.len());
-
-            if !commits.is_empty() {
-                report_progress(&format!("Processing {} commits...", commits.len()));
-
-                let mut last_progress_time = std::time::Instant::now();
-                let progress_throttle = std::time::Duration::from_millis(250); // Update every 250ms max
-
-                for (index, commit) in commits.iter().enumerate() {
-                    let now = std::time::Instant::now();
-                    let should_report = index % 20 == 0 || // Every 20 commits  
-                        index + 1 == commits.len() || // Last commit
-                        now.duration_since(last_progress_time) >= progress_throttle; // Time-based throttle
-
-                    if should_report {
-                        let progress = ((index + 1) as f64 / commits.len() as f64 * 100.0) as u32;
-                        report_progress(&format!(
-                            "Processing commits: {}/{} ({}%)",
-                            index + 1,
-                            commits.len(),
-                            progress
-                        ));
-                        last_progress_time = now;
-                    }
-
-                    match self.create_commit_document(&safe_repo_name, commit) {
-                        Ok(doc) => {
-                            if let Err(e) = storage.insert(doc).await {
-                                warn!("Failed to insert commit document {}: {}", commit.sha, e);
-                                result.errors += 1;
-                            } else {
-                                result.documents_created += 1;
-                                result.commits_ingested += 1;
-                            }
-                        }
-                        Err(e) => {
-                            warn!("Failed to create document for commit {}: {}", commit.sha, e);
-                            result.errors += 1;
-                        }
-                    }
-                }
-            }
+            self.ingest_commit_history(
+                commits,
+                &safe_repo_name,
+                storage,
+                &mut result,
+                &report_progress,
+            )
+            .await?;
         }
 
         report_progress("Finalizing ingestion...");
@@ -1159,7 +1191,10 @@ impl RepositoryIngester {
             metadata.name, metadata.commit_count, safe_repo_name
         );
 
-        let mut result = IngestResult::default();
+        let mut result = IngestResult {
+            document_prefix: self.document_prefix(&safe_repo_name),
+            ..IngestResult::default()
+        };
 
         // Initialize file organization manager if configured
         let _file_org_manager = self
@@ -1265,49 +1300,14 @@ impl RepositoryIngester {
             let commits = repo
                 .get_commits(None)
                 .context("Failed to get repository commits")?;
-
-            info!("Processing {} commits", commits.len());
-
-            if !commits.is_empty() {
-                report_progress(&format!("Processing {} commits...", commits.len()));
-
-                let mut last_progress_time = std::time::Instant::now();
-                let progress_throttle = std::time::Duration::from_millis(250); // Update every 250ms max
-
-                for (index, commit) in commits.iter().enumerate() {
-                    let now = std::time::Instant::now();
-                    let should_report = index % 20 == 0 || // Every 20 commits  
-                        index + 1 == commits.len() || // Last commit
-                        now.duration_since(last_progress_time) >= progress_throttle; // Time-based throttle
-
-                    if should_report {
-                        let progress = ((index + 1) as f64 / commits.len() as f64 * 100.0) as u32;
-                        report_progress(&format!(
-                            "Processing commits: {}/{} ({}%)",
-                            index + 1,
-                            commits.len(),
-                            progress
-                        ));
-                        last_progress_time = now;
-                    }
-
-                    match self.create_commit_document(&safe_repo_name, commit) {
-                        Ok(doc) => {
-                            if let Err(e) = storage.insert(doc).await {
-                                warn!("Failed to insert commit document {}: {}", commit.sha, e);
-                                result.errors += 1;
-                            } else {
-                                result.documents_created += 1;
-                                result.commits_ingested += 1;
-                            }
-                        }
-                        Err(e) => {
-                            warn!("Failed to create document for commit {}: {}", commit.sha, e);
-                            result.errors += 1;
-                        }
-                    }
-                }
-            }
+            self.ingest_commit_history(
+                commits,
+                &safe_repo_name,
+                storage,
+                &mut result,
+                &report_progress,
+            )
+            .await?;
         }
 
         report_progress("Finalizing ingestion...");
@@ -1321,6 +1321,27 @@ impl RepositoryIngester {
         Ok(result)
     }
 
+    /// Remote URL, HEAD commit and document root of a repository, for permalinks to the
+    /// files this ingester stores
+    pub fn repository_link(
+        &self,
+        repo_path: impl AsRef<Path>,
+    ) -> Result<crate::repository_links::RepositoryLink> {
+        let repo = GitRepository::open(repo_path.as_ref(), self.config.options.clone())
+            .context("Failed to open git repository")?;
+        let metadata = repo
+            .metadata()
+            .context("Failed to get repository metadata")?;
+        let safe_repo_name = Self::sanitize_name(&metadata.name);
+        let prefix = self.config.path_prefix.trim_start_matches('/');
+        Ok(crate::repository_links::RepositoryLink {
+            document_root: format!("{}/{}/files", prefix, safe_repo_name),
+            name: metadata.name,
+            remote_url: metadata.url,
+            commit: metadata.head_commit,
+        })
+    }
+
     fn create_index_document(
         &self,
         metadata: &crate::git::types::RepositoryMetadata,
@@ -1357,10 +1378,15 @@ impl RepositoryIngester {
         builder.build()
     }
 
-    fn create_file_document(&self, repo_name: &str, file: &FileEntry) -> Result<Document> {
+    /// Storage prefix of the documents of repository `repo_name`
+    fn document_prefix(&self, repo_name: &str) -> String {
         // Remove leading slash if present to create relative path
         let prefix = self.config.path_prefix.trim_start_matches('/');
-        let doc_path = format!("{}/{}/files/{}", prefix, repo_name, file.path);
+        format!("{}/{}/", prefix, repo_name)
+    }
+
+    fn create_file_document(&self, repo_name: &str, file: &FileEntry) -> Result<Document> {
+        let doc_path = format!("{}files/{}", self.document_prefix(repo_name), file.path);
 
         let title = format!("File: {}", file.path);
 
@@ -1393,6 +1419,61 @@ impl RepositoryIngester {
         builder.build()
     }
 
+    /// Store one document per commit, listing the files it changed
+    async fn ingest_commit_history<S: Storage + ?Sized>(
+        &self,
+        commits: Vec<CommitInfo>,
+        safe_repo_name: &str,
+        storage: &mut S,
+        result: &mut IngestResult,
+        report_progress: &(dyn Fn(&str) + Sync),
+    ) -> Result<()> {
+        info!("Processing {} commits", commits.len());
+
+        if !commits.is_empty() {
+            report_progress(&format!("Processing {} commits...", commits.len()));
+
+            let mut last_progress_time = std::time::Instant::now();
+            let progress_throttle = std::time::Duration::from_millis(250); // Update every 250ms max
+
+            for (index, commit) in commits.iter().enumerate() {
+                let now = std::time::Instant::now();
+                let should_report = index % 20 == 0 || // Every 20 commits  
+                    index + 1 == commits.len() || // Last commit
+                    now.duration_since(last_progress_time) >= progress_throttle; // Time-based throttle
+
+                if should_report {
+                    let progress = ((index + 1) as f64 / commits.len() as f64 * 100.0) as u32;
+                    report_progress(&format!(
+                        "Processing commits: {}/{} ({}%)",
+                        index + 1,
+                        commits.len(),
+                        progress
+                    ));
+                    last_progress_time = now;
+                }
+
+                match self.create_commit_document(safe_repo_name, commit) {
+                    Ok(doc) => {
+                        if let Err(e) = storage.insert(doc).await {
+                            warn!("Failed to insert commit document {}: {}", commit.sha, e);
+                            result.errors += 1;
+                        } else {
+                            result.documents_created += 1;
+                            result.commits_ingested += 1;
+                        }
+                    }
+                    Err(e) => {
+                        warn!("Failed to create document for commit {}: {}", commit.sha, e);
+                        result.errors += 1;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
     fn create_commit_document(&self, repo_name: &str, commit: &CommitInfo) -> Result<Document> {
         // Remove leading slash if present to create relative path
         let prefix = self.config.path_prefix.trim_start_matches('/');
@@ -1628,7 +1709,7 @@ impl RepositoryIngester {
 }
 
 /// Result of repository ingestion
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Serialize, Deserialize)]
 pub struct IngestResult {
     /// Number of documents created
     pub documents_created: usize,
@@ -1644,6 +1725,9 @@ pub struct IngestResult {
     pub relationships_extracted: usize,
     /// Number of errors encountered
     pub errors: usize,
+    /// Storage prefix of the repository's documents, e.g. `repos/widgets/`
+    #[serde(default)]
+    pub document_prefix: String,
 }
 
 #[cfg(test)]
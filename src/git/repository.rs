@@ -2,10 +2,12 @@
 
 use anyhow::{Context, Result};
 use chrono::{TimeZone, Utc};
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use tracing::{debug, info};
 
 use crate::git::types::{CommitInfo, FileEntry, IngestionOptions, RepositoryMetadata};
+use crate::project_init::IgnoreRules;
 
 /// Wrapper around a git repository for reading and analysis
 pub struct GitRepository {
@@ -14,6 +16,8 @@ pub struct GitRepository {
     path: PathBuf,
     #[allow(dead_code)]
     options: IngestionOptions,
+    #[allow(dead_code)]
+    ignore: IgnoreRules,
 }
 
 impl GitRepository {
@@ -26,11 +30,13 @@ impl GitRepository {
             info!("Opening git repository at: {:?}", path);
             let repo = git2::Repository::open(&path)
                 .with_context(|| format!("Failed to open git repository at {:?}", path))?;
+            let ignore = IgnoreRules::new(&options.ignore_patterns)?;
 
             Ok(Self {
                 repo,
                 path,
                 options,
+                ignore,
             })
         }
 
@@ -49,6 +55,10 @@ impl GitRepository {
             let head = self.repo.head().context("Failed to get repository HEAD")?;
 
             let branch_name = head.shorthand().unwrap_or("HEAD").to_string();
+            let head_commit = head
+                .peel_to_commit()
+                .ok()
+                .map(|commit| commit.id().to_string());
 
             // Count commits
             let mut revwalk = self.repo.revwalk()?;
@@ -70,6 +80,7 @@ impl GitRepository {
                 url,
                 path: self.path.clone(),
                 default_branch: branch_name,
+                head_commit,
                 commit_count,
                 created_at: None, // Would need to find first commit
                 updated_at: Utc::now(),
@@ -281,6 +292,7 @@ impl GitRepository {
             .exclude_patterns
             .iter()
             .any(|pattern| path.contains(pattern))
+            || self.ignore.is_ignored(path)
     }
 
     #[cfg(feature = "git-integration")]
@@ -301,6 +313,66 @@ impl GitRepository {
         }
     }
 
+    /// Lines of `file_path` at HEAD last changed by each author (`Name <email>`), counted
+    /// within the given 1-based inclusive line ranges
+    pub fn blame_lines(
+        &self,
+        file_path: &str,
+        ranges: &[(usize, usize)],
+    ) -> Result<HashMap<String, usize>> {
+        #[cfg(feature = "git-integration")]
+        {
+            let blame = self
+                .repo
+                .blame_file(Path::new(file_path), None)
+                .with_context(|| format!("Failed to blame {}", file_path))?;
+            // Ranges may overlap; each line counts once
+            let lines: std::collections::BTreeSet<usize> = ranges
+                .iter()
+                .flat_map(|&(start, end)| start..=end)
+                .collect();
+            let mut lines_by_author: HashMap<String, usize> = HashMap::new();
+            for line in lines {
+                let Some(hunk) = blame.get_line(line) else {
+                    continue;
+                };
+                let signature = hunk.final_signature();
+                let author = format!(
+                    "{} <{}>",
+                    signature.name().unwrap_or("Unknown"),
+                    signature.email().unwrap_or("")
+                );
+                *lines_by_author.entry(author).or_insert(0) += 1;
+            }
+            Ok(lines_by_author)
+        }
+
+        #[cfg(not(feature = "git-integration"))]
+        {
+            let _ = (file_path, ranges);
+            anyhow::bail!("Git integration feature not enabled");
+        }
+    }
+
+    /// Commit a revision (sha, branch, tag or expression like `HEAD~3`) resolves to
+    pub fn resolve_commit(&self, revision: &str) -> Result<CommitInfo> {
+        #[cfg(feature = "git-integration")]
+        {
+            let commit = self
+                .repo
+                .revparse_single(revision)
+                .and_then(|object| object.peel_to_commit())
+                .with_context(|| format!("Failed to resolve revision {}", revision))?;
+            self.commit_to_info(&commit)
+        }
+
+        #[cfg(not(feature = "git-integration"))]
+        {
+            let _ = revision;
+            anyhow::bail!("Git integration feature not enabled");
+        }
+    }
+
     /// Get recent commits from the repository
     pub fn get_commits(&self, limit: Option<usize>) -> Result<Vec<CommitInfo>> {
         #[cfg(feature = "git-integration")]
@@ -15,6 +15,9 @@ pub struct RepositoryMetadata {
     pub path: PathBuf,
     /// Default branch name
     pub default_branch: String,
+    /// SHA of the commit checked out at HEAD
+    #[serde(default)]
+    pub head_commit: Option<String>,
     /// Total number of commits
     pub commit_count: usize,
     /// Repository creation timestamp
@@ -92,6 +95,9 @@ pub struct IngestionOptions {
     pub memory_limits: Option<crate::memory::MemoryLimitsConfig>,
     /// Explicit list of repository-relative paths to ingest (None = all files)
     pub include_paths: Option<Vec<String>>,
+    /// `.kotaignore` patterns of paths to leave out
+    #[serde(default)]
+    pub ignore_patterns: Vec<String>,
 }
 
 impl Default for IngestionOptions {
@@ -123,6 +129,7 @@ impl Default for IngestionOptions {
             max_history_depth: Some(1000),
             memory_limits: None, // Default to no memory limits for backward compatibility
             include_paths: None,
+            ignore_patterns: vec![],
         }
     }
 }
@@ -148,6 +155,7 @@ mod tests {
             url: Some("https://github.com/test/repo".to_string()),
             path: PathBuf::from("/tmp/test-repo"),
             default_branch: "main".to_string(),
+            head_commit: Some("0a1b2c3d".to_string()),
             commit_count: 42,
             created_at: Some(Utc::now()),
             updated_at: Utc::now(),
@@ -0,0 +1,549 @@
+// Git hooks module - Summarise what a commit or push changes before it happens
+//
+// `kotadb hook install` writes `pre-commit` and `pre-push` scripts that run `kotadb hook run`.
+// A run does not re-index: it parses only the changed files, patches their symbols and
+// references into the dependency graph of the local index and compares the result with the
+// indexed graph. The summary names the code depending on the change, dependency cycles the change
+// introduces and symbols it leaves unused. Hooks only inform; they never reject a commit.
+
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+use crate::binary_relationship_bridge::BinaryRelationshipBridge;
+use crate::cargo_workspace::CrateMap;
+use crate::dependency_extractor::{DependencyGraph, SerializableDependencyGraph};
+use crate::parsing::SupportedLanguage;
+use crate::services::analysis_service::unused_nodes;
+
+/// Second line of every hook written by `kotadb hook install`
+pub const HOOK_MARKER: &str = "# Installed by `kotadb hook install`";
+
+/// Time a hook run may spend reading and parsing changed files
+pub const DEFAULT_HOOK_BUDGET_MS: u64 = 800;
+
+/// Findings of each kind listed in the summary before it only gives a count
+const SUMMARY_LIST_LIMIT: usize = 5;
+
+/// Git hooks `kotadb hook install` can write
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookKind {
+    PreCommit,
+    PrePush,
+}
+
+impl HookKind {
+    pub const ALL: [HookKind; 2] = [HookKind::PreCommit, HookKind::PrePush];
+
+    /// File name in the hooks directory, also used on the command line
+    pub fn name(self) -> &'static str {
+        match self {
+            HookKind::PreCommit => "pre-commit",
+            HookKind::PrePush => "pre-push",
+        }
+    }
+
+    pub fn parse(name: &str) -> Result<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|kind| kind.name() == name)
+            .with_context(|| format!("unknown hook '{}': use pre-commit or pre-push", name))
+    }
+}
+
+/// Script installed as `kind`, running `kotadb` against the database at `db_path`
+pub fn hook_script(kind: HookKind, kotadb: &Path, db_path: &Path, budget_ms: u64) -> String {
+    format!(
+        "#!/bin/sh\n{}; delete this file to remove it\n{} --db-path {} hook run {} --budget-ms {} || true\n",
+        HOOK_MARKER,
+        shell_quote(&kotadb.to_string_lossy()),
+        shell_quote(&db_path.to_string_lossy()),
+        kind.name(),
+        budget_ms
+    )
+}
+
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+/// Directory git runs hooks from, honouring `core.hooksPath` and worktrees
+pub fn hooks_dir(repo_root: &Path) -> Result<PathBuf> {
+    let output = git(repo_root, &["rev-parse", "--git-path", "hooks"])?;
+    let dir = PathBuf::from(String::from_utf8_lossy(&output).trim());
+    Ok(if dir.is_absolute() {
+        dir
+    } else {
+        repo_root.join(dir)
+    })
+}
+
+/// Write the scripts of `kinds`, refusing to replace hooks kotadb did not install unless `force`
+pub fn install(
+    repo_root: &Path,
+    kinds: &[HookKind],
+    kotadb: &Path,
+    db_path: &Path,
+    budget_ms: u64,
+    force: bool,
+) -> Result<Vec<PathBuf>> {
+    let dir = hooks_dir(repo_root)?;
+    std::fs::create_dir_all(&dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+
+    let paths: Vec<PathBuf> = kinds.iter().map(|kind| dir.join(kind.name())).collect();
+    for path in &paths {
+        if !force && path.exists() && !is_kotadb_hook(path) {
+            bail!(
+                "{} exists and was not installed by kotadb; pass --force to replace it",
+                path.display()
+            );
+        }
+    }
+    for (kind, path) in kinds.iter().zip(&paths) {
+        std::fs::write(path, hook_script(*kind, kotadb, db_path, budget_ms))
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755))
+                .with_context(|| format!("Failed to make {} executable", path.display()))?;
+        }
+    }
+    Ok(paths)
+}
+
+/// Remove the hooks of `kinds` that kotadb installed, leaving any others in place
+pub fn uninstall(repo_root: &Path, kinds: &[HookKind]) -> Result<Vec<PathBuf>> {
+    let dir = hooks_dir(repo_root)?;
+    let mut removed = Vec::new();
+    for kind in kinds {
+        let path = dir.join(kind.name());
+        if path.exists() && is_kotadb_hook(&path) {
+            std::fs::remove_file(&path)
+                .with_context(|| format!("Failed to remove {}", path.display()))?;
+            removed.push(path);
+        }
+    }
+    Ok(removed)
+}
+
+fn is_kotadb_hook(path: &Path) -> bool {
+    std::fs::read_to_string(path).is_ok_and(|script| script.contains(HOOK_MARKER))
+}
+
+/// A file a commit or push adds, modifies or deletes
+#[derive(Debug, Clone)]
+pub struct ChangedFile {
+    /// Path relative to the repository root
+    pub path: String,
+    /// Where the new content is read from: `:path` for the index, `<commit>:path` otherwise;
+    /// `None` when the file is deleted
+    pub object: Option<String>,
+}
+
+/// Files whose changes are staged for the next commit
+pub fn staged_changes(repo_root: &Path) -> Result<Vec<ChangedFile>> {
+    let output = git(
+        repo_root,
+        &["diff", "--cached", "--name-status", "--no-renames", "-z"],
+    )?;
+    Ok(parse_name_status(&output, ""))
+}
+
+/// Files changed by the ref updates git passes to a pre-push hook on standard input
+/// (`<local ref> <local sha> <remote ref> <remote sha>` per line)
+pub fn pushed_changes(repo_root: &Path, updates: &str) -> Result<Vec<ChangedFile>> {
+    let is_zero = |sha: &str| sha.chars().all(|c| c == '0');
+    let mut changes: Vec<ChangedFile> = Vec::new();
+    for line in updates.lines() {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let [_, local_sha, _, remote_sha] = fields[..] else {
+            continue;
+        };
+        // Deleting a remote branch changes no files
+        if is_zero(local_sha) {
+            continue;
+        }
+        let output = if is_zero(remote_sha) {
+            // New remote branch: only the pushed commit itself is known to be new
+            git(
+                repo_root,
+                &[
+                    "diff-tree",
+                    "-r",
+                    "--root",
+                    "--no-commit-id",
+                    "--name-status",
+                    "--no-renames",
+                    "-z",
+                    local_sha,
+                ],
+            )?
+        } else {
+            git(
+                repo_root,
+                &[
+                    "diff",
+                    "--name-status",
+                    "--no-renames",
+                    "-z",
+                    remote_sha,
+                    local_sha,
+                ],
+            )?
+        };
+        for change in parse_name_status(&output, local_sha) {
+            changes.retain(|existing| existing.path != change.path);
+            changes.push(change);
+        }
+    }
+    Ok(changes)
+}
+
+/// Parse `--name-status -z` output, reading new content from `revision` (empty for the index)
+fn parse_name_status(output: &[u8], revision: &str) -> Vec<ChangedFile> {
+    let fields: Vec<String> = output
+        .split(|&byte| byte == 0)
+        .filter(|field| !field.is_empty())
+        .map(|field| String::from_utf8_lossy(field).into_owned())
+        .collect();
+    let (pairs, _) = fields.as_chunks::<2>();
+    pairs
+        .iter()
+        .map(|[status, path]| ChangedFile {
+            path: path.clone(),
+            object: (!status.starts_with('D')).then(|| format!("{}:{}", revision, path)),
+        })
+        .collect()
+}
+
+fn git(repo_root: &Path, args: &[&str]) -> Result<Vec<u8>> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(repo_root)
+        .args(args)
+        .output()
+        .context("Failed to run git")?;
+    if !output.status.success() {
+        bail!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(output.stdout)
+}
+
+/// Code in unchanged files that used a symbol the change removes
+#[derive(Debug, Clone, Serialize)]
+pub struct BrokenReference {
+    pub symbol: String,
+    pub file_path: String,
+    pub line: usize,
+    pub removed: String,
+}
+
+/// What a change does to the dependency graph of the index
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ChangeSummary {
+    pub files_changed: usize,
+    /// Changed source files read and parsed within the budget
+    pub files_analyzed: usize,
+    /// Whether the budget ran out before every changed source file was parsed
+    pub truncated: bool,
+    /// Symbols in unchanged files that use the changed code directly
+    pub direct_dependents: usize,
+    /// Symbols in unchanged files that use the changed code directly or transitively
+    pub impacted_symbols: usize,
+    pub impacted_files: Vec<String>,
+    pub broken_references: Vec<BrokenReference>,
+    /// Dependency cycles only present after the change, as qualified names
+    pub new_cycles: Vec<Vec<String>>,
+    /// Symbols nothing depends on any more after the change
+    pub newly_unused: Vec<String>,
+    pub elapsed_ms: u64,
+}
+
+impl ChangeSummary {
+    /// A few lines for the terminal the commit or push runs in
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        let _ = write!(
+            out,
+            "kotadb: {} changed file(s), {} analyzed in {}ms",
+            self.files_changed, self.files_analyzed, self.elapsed_ms
+        );
+        if self.truncated {
+            let _ = write!(out, " (budget exhausted, remaining files skipped)");
+        }
+        let _ = writeln!(out);
+
+        let _ = writeln!(
+            out,
+            "  impact: {} direct dependent(s), {} symbol(s) in {} other file(s){}",
+            self.direct_dependents,
+            self.impacted_symbols,
+            self.impacted_files.len(),
+            list_suffix(&self.impacted_files)
+        );
+        if !self.broken_references.is_empty() {
+            let _ = writeln!(
+                out,
+                "  removed but still used: {}",
+                self.broken_references.len()
+            );
+            for broken in self.broken_references.iter().take(SUMMARY_LIST_LIMIT) {
+                let _ = writeln!(
+                    out,
+                    "    {} ({}:{}) uses {}",
+                    broken.symbol, broken.file_path, broken.line, broken.removed
+                );
+            }
+        }
+        if !self.new_cycles.is_empty() {
+            let _ = writeln!(out, "  new dependency cycles: {}", self.new_cycles.len());
+            for cycle in self.new_cycles.iter().take(SUMMARY_LIST_LIMIT) {
+                let _ = writeln!(out, "    {}", cycle.join(" <-> "));
+            }
+        }
+        if !self.newly_unused.is_empty() {
+            let _ = writeln!(
+                out,
+                "  left unused: {}{}",
+                self.newly_unused.len(),
+                list_suffix(&self.newly_unused)
+            );
+        }
+        out
+    }
+}
+
+fn list_suffix(items: &[String]) -> String {
+    if items.is_empty() {
+        return String::new();
+    }
+    let shown: Vec<&str> = items
+        .iter()
+        .take(SUMMARY_LIST_LIMIT)
+        .map(String::as_str)
+        .collect();
+    let more = items.len().saturating_sub(SUMMARY_LIST_LIMIT);
+    if more > 0 {
+        format!(" ({}, +{} more)", shown.join(", "), more)
+    } else {
+        format!(" ({})", shown.join(", "))
+    }
+}
+
+/// Compare the indexed dependency graph with the graph after `changes`
+///
+/// Reading and parsing changed files stops once `budget` is spent; files left over are
+/// treated as unchanged and the summary is marked as truncated.
+pub fn analyze_changes(
+    db_path: &Path,
+    repo_root: &Path,
+    changes: &[ChangedFile],
+    budget: Duration,
+) -> Result<ChangeSummary> {
+    let start = Instant::now();
+    let graph_path = db_path.join("dependency_graph.bin");
+    let bytes = std::fs::read(&graph_path).with_context(|| {
+        format!(
+            "Failed to read dependency graph {}; index the codebase with symbol extraction first",
+            graph_path.display()
+        )
+    })?;
+    let before = SerializableDependencyGraph::from_bytes(&bytes)?;
+
+    let mut summary = ChangeSummary {
+        files_changed: changes.len(),
+        ..Default::default()
+    };
+    let mut files: Vec<(PathBuf, Option<Vec<u8>>)> = Vec::new();
+    for change in changes {
+        let is_source = Path::new(&change.path)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .and_then(SupportedLanguage::from_extension)
+            .is_some();
+        if !is_source {
+            continue;
+        }
+        let Some(object) = &change.object else {
+            files.push((PathBuf::from(&change.path), None));
+            continue;
+        };
+        if start.elapsed() >= budget {
+            summary.truncated = true;
+            break;
+        }
+        let content = git(repo_root, &["cat-file", "blob", object])?;
+        files.push((PathBuf::from(&change.path), Some(content)));
+        summary.files_analyzed += 1;
+    }
+    if files.is_empty() {
+        summary.elapsed_ms = start.elapsed().as_millis() as u64;
+        return Ok(summary);
+    }
+
+    let crates = CrateMap::load(db_path)?.unwrap_or_default();
+    let patch = BinaryRelationshipBridge::new()
+        .with_crates(crates)
+        .patch_graph(&before, &files)?;
+    let after = &patch.graph;
+    let changed: HashSet<&Path> = files.iter().map(|(path, _)| path.as_path()).collect();
+    let names: HashMap<Uuid, (&str, &Path)> = before
+        .nodes
+        .iter()
+        .chain(&after.nodes)
+        .map(|node| {
+            (
+                node.symbol_id,
+                (node.qualified_name.as_str(), node.file_path.as_path()),
+            )
+        })
+        .collect();
+
+    // Dependents of the changed code, walking incoming edges from symbols in changed files
+    let mut dependents: HashMap<Uuid, Vec<Uuid>> = HashMap::new();
+    for edge in before.edges.iter().chain(&after.edges) {
+        dependents.entry(edge.to_id).or_default().push(edge.from_id);
+    }
+    let mut visited: HashSet<Uuid> = HashSet::new();
+    let mut queue: VecDeque<(Uuid, usize)> = before
+        .nodes
+        .iter()
+        .chain(&after.nodes)
+        .filter(|node| changed.contains(node.file_path.as_path()))
+        .map(|node| (node.symbol_id, 0))
+        .collect();
+    let mut impacted_files = BTreeSet::new();
+    while let Some((id, depth)) = queue.pop_front() {
+        for &dependent in dependents.get(&id).into_iter().flatten() {
+            let Some((_, path)) = names.get(&dependent) else {
+                continue;
+            };
+            if changed.contains(path) || !visited.insert(dependent) {
+                continue;
+            }
+            if depth == 0 {
+                summary.direct_dependents += 1;
+            }
+            impacted_files.insert(path.display().to_string());
+            queue.push_back((dependent, depth + 1));
+        }
+    }
+    summary.impacted_symbols = visited.len();
+    summary.impacted_files = impacted_files.into_iter().collect();
+
+    summary.broken_references = patch
+        .dangling_edges
+        .iter()
+        .filter_map(|edge| {
+            let (symbol, path) = names.get(&edge.from_id)?;
+            let (removed, _) = names.get(&edge.to_id)?;
+            Some(BrokenReference {
+                symbol: symbol.to_string(),
+                file_path: path.display().to_string(),
+                line: edge.edge.line_number,
+                removed: removed.to_string(),
+            })
+        })
+        .collect();
+
+    let cycles = |graph: &SerializableDependencyGraph| -> Result<BTreeSet<Vec<String>>> {
+        let graph = DependencyGraph::from_serializable(graph.clone())?;
+        Ok(graph
+            .find_circular_dependencies()
+            .into_iter()
+            .map(|cycle| {
+                let mut members: Vec<String> = cycle
+                    .iter()
+                    .filter_map(|id| names.get(id).map(|(name, _)| name.to_string()))
+                    .collect();
+                members.sort();
+                members
+            })
+            .collect())
+    };
+    let cycles_before = cycles(&before)?;
+    summary.new_cycles = cycles(after)?
+        .into_iter()
+        .filter(|cycle| !cycles_before.contains(cycle))
+        .collect();
+
+    let unused_before: HashSet<&str> = unused_nodes(&before)
+        .into_iter()
+        .map(|node| node.qualified_name.as_str())
+        .collect();
+    let mut newly_unused: Vec<String> = unused_nodes(after)
+        .into_iter()
+        .map(|node| node.qualified_name.as_str())
+        .filter(|name| !unused_before.contains(name))
+        .map(str::to_string)
+        .collect();
+    newly_unused.sort();
+    newly_unused.dedup();
+    summary.newly_unused = newly_unused;
+
+    summary.elapsed_ms = start.elapsed().as_millis() as u64;
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn name_status_output_reads_new_content_from_the_revision() {
+        let output = b"M\0src/lib.rs\0D\0src/old.rs\0A\0src/it's.rs\0";
+        let changes = parse_name_status(output, "abc123");
+        let objects: Vec<(&str, Option<&str>)> = changes
+            .iter()
+            .map(|change| (change.path.as_str(), change.object.as_deref()))
+            .collect();
+        assert_eq!(
+            objects,
+            vec![
+                ("src/lib.rs", Some("abc123:src/lib.rs")),
+                ("src/old.rs", None),
+                ("src/it's.rs", Some("abc123:src/it's.rs")),
+            ]
+        );
+        assert_eq!(
+            parse_name_status(b"M\0a.rs\0", "")[0].object.as_deref(),
+            Some(":a.rs")
+        );
+    }
+
+    #[test]
+    fn install_keeps_foreign_hooks_unless_forced() {
+        let repo = tempfile::TempDir::new().unwrap();
+        git(repo.path(), &["init", "-q"]).unwrap();
+        let kotadb = Path::new("/opt/kota db/kotadb");
+        let db = Path::new("/tmp/db");
+
+        let installed = install(repo.path(), &HookKind::ALL, kotadb, db, 500, false).unwrap();
+        assert_eq!(installed.len(), 2);
+        let script = std::fs::read_to_string(&installed[0]).unwrap();
+        assert!(script.contains("'/opt/kota db/kotadb' --db-path '/tmp/db' hook run pre-commit"));
+        // Reinstalling over our own hooks needs no --force
+        install(repo.path(), &HookKind::ALL, kotadb, db, 500, false).unwrap();
+
+        let pre_push = hooks_dir(repo.path()).unwrap().join("pre-push");
+        std::fs::write(&pre_push, "#!/bin/sh\nmake lint\n").unwrap();
+        assert!(install(repo.path(), &[HookKind::PrePush], kotadb, db, 500, false).is_err());
+        assert_eq!(
+            uninstall(repo.path(), &HookKind::ALL).unwrap(),
+            vec![installed[0].clone()]
+        );
+        assert!(pre_push.exists());
+        install(repo.path(), &[HookKind::PrePush], kotadb, db, 500, true).unwrap();
+        assert!(is_kotadb_hook(&pre_push));
+    }
+}
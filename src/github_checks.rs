@@ -0,0 +1,418 @@
+// GitHub Checks module - Publish analysis results as Check Runs on a commit
+//
+// Quality gate, dead-code and impact results become GitHub Check Runs on the head commit of a
+// pull request. Findings tied to a location are attached as annotations, which GitHub shows
+// inline on the lines of the PR diff they point at, so reviewers see them next to the change.
+
+use anyhow::{bail, Context, Result};
+use reqwest::Client;
+use serde::Serialize;
+use serde_json::{json, Value as JsonValue};
+use std::fmt::Write as _;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::gate::GateReport;
+use crate::services::{ImpactResult, UnusedSymbol};
+
+const GITHUB_API_URL: &str = "https://api.github.com";
+const GITHUB_USER_AGENT: &str = "kotadb-checks/1.0";
+
+/// GitHub accepts at most this many annotations per create or update request
+const MAX_ANNOTATIONS_PER_REQUEST: usize = 50;
+/// Annotations beyond this are left out and mentioned in the summary instead
+pub const MAX_ANNOTATIONS: usize = 500;
+/// GitHub rejects check run summaries longer than 65535 characters
+const MAX_SUMMARY_LEN: usize = 60_000;
+
+/// Outcome GitHub shows for a completed check run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckConclusion {
+    Success,
+    Failure,
+    Neutral,
+}
+
+/// Severity of an annotation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AnnotationLevel {
+    Notice,
+    Warning,
+    Failure,
+}
+
+/// A finding attached to lines of a file in the repository
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CheckAnnotation {
+    /// Repository-relative path
+    pub path: String,
+    pub start_line: u32,
+    pub end_line: u32,
+    pub annotation_level: AnnotationLevel,
+    pub title: String,
+    pub message: String,
+}
+
+/// A completed check run ready to publish
+#[derive(Debug, Clone, Serialize)]
+pub struct CheckRunReport {
+    pub name: String,
+    pub conclusion: CheckConclusion,
+    pub title: String,
+    /// Markdown shown on the check run page
+    pub summary: String,
+    pub annotations: Vec<CheckAnnotation>,
+}
+
+impl CheckRunReport {
+    /// Gate thresholds as a check that fails when any of them is exceeded
+    pub fn from_gate(report: &GateReport) -> Self {
+        let failed = report.failed();
+        Self {
+            name: "KotaDB quality gate".to_string(),
+            conclusion: if report.is_success() {
+                CheckConclusion::Success
+            } else {
+                CheckConclusion::Failure
+            },
+            title: if failed == 0 {
+                format!("All {} checks passed", report.checks.len())
+            } else {
+                format!("{} of {} checks failed", failed, report.checks.len())
+            },
+            summary: report.to_markdown(),
+            annotations: Vec::new(),
+        }
+    }
+
+    /// Unused symbols as warnings on their definitions
+    ///
+    /// Paths are made relative to `repo_root` so GitHub can place them in the diff.
+    pub fn from_unused(unused: &[UnusedSymbol], repo_root: &Path) -> Self {
+        let mut summary = String::new();
+        let _ = writeln!(
+            summary,
+            "{} symbols have no dependents and are not entry points or tests.",
+            unused.len()
+        );
+        let annotations = unused
+            .iter()
+            .filter_map(|symbol| {
+                let line = symbol.start_line?;
+                Some(CheckAnnotation {
+                    path: repo_relative(&symbol.file_path, repo_root),
+                    start_line: line,
+                    end_line: line,
+                    annotation_level: AnnotationLevel::Warning,
+                    title: format!("Unused {}", symbol.kind.to_lowercase()),
+                    message: format!(
+                        "Nothing depends on `{}`. Remove it if it is dead code.",
+                        symbol.qualified_name
+                    ),
+                })
+            })
+            .collect();
+        Self {
+            name: "KotaDB dead code".to_string(),
+            conclusion: if unused.is_empty() {
+                CheckConclusion::Success
+            } else {
+                CheckConclusion::Neutral
+            },
+            title: format!("{} unused symbols", unused.len()),
+            summary,
+            annotations,
+        }
+    }
+
+    /// Dependents of a changed symbol as notices where they use it
+    pub fn from_impact(target: &str, impact: &ImpactResult, repo_root: &Path) -> Self {
+        let annotations = impact
+            .impacts
+            .iter()
+            .filter_map(|site| {
+                let line = site.line_number?;
+                Some(CheckAnnotation {
+                    path: repo_relative(&site.file_path, repo_root),
+                    start_line: line,
+                    end_line: line,
+                    annotation_level: AnnotationLevel::Notice,
+                    title: format!("Affected by changes to {}", target),
+                    message: format!(
+                        "`{}` {} `{}` ({}, {} hop{} away)",
+                        site.qualified_name,
+                        site.relation_type.to_lowercase(),
+                        target,
+                        site.impact_type,
+                        site.depth,
+                        if site.depth == 1 { "" } else { "s" }
+                    ),
+                })
+            })
+            .collect();
+        Self {
+            name: format!("KotaDB impact: {}", target),
+            conclusion: CheckConclusion::Neutral,
+            title: impact.summary.clone(),
+            summary: impact.markdown.clone(),
+            annotations,
+        }
+    }
+
+    /// Output payloads for the create request and any follow-up updates
+    fn outputs(&self) -> Vec<JsonValue> {
+        let annotations = &self.annotations[..self.annotations.len().min(MAX_ANNOTATIONS)];
+        let mut summary = self.summary.clone();
+        if self.annotations.len() > MAX_ANNOTATIONS {
+            let _ = write!(
+                summary,
+                "\n\n_Showing {} of {} annotations._",
+                MAX_ANNOTATIONS,
+                self.annotations.len()
+            );
+        }
+        if summary.len() > MAX_SUMMARY_LEN {
+            let mut end = MAX_SUMMARY_LEN;
+            while !summary.is_char_boundary(end) {
+                end -= 1;
+            }
+            summary.truncate(end);
+            summary.push_str("\n\n_Summary truncated._");
+        }
+
+        let output = |annotations: &[CheckAnnotation]| {
+            json!({
+                "title": self.title,
+                "summary": summary,
+                "annotations": annotations,
+            })
+        };
+        if annotations.is_empty() {
+            return vec![output(&[])];
+        }
+        annotations
+            .chunks(MAX_ANNOTATIONS_PER_REQUEST)
+            .map(output)
+            .collect()
+    }
+}
+
+/// Path GitHub expects in annotations: relative to the repository root, `/`-separated
+fn repo_relative(path: &str, repo_root: &Path) -> String {
+    let path = Path::new(path);
+    let relative = path.strip_prefix(repo_root).unwrap_or(path);
+    relative
+        .to_string_lossy()
+        .replace('\\', "/")
+        .trim_start_matches("./")
+        .to_string()
+}
+
+/// Creates check runs in one GitHub repository
+pub struct CheckRunPublisher {
+    client: Client,
+    api_url: String,
+    token: String,
+    owner: String,
+    repo: String,
+}
+
+impl CheckRunPublisher {
+    /// Publisher for `owner/repo` authenticated with `token`, which needs the `checks:write`
+    /// permission (e.g. `GITHUB_TOKEN` in GitHub Actions)
+    pub fn new(token: &str, repository: &str) -> Result<Self> {
+        let (owner, repo) = repository
+            .split_once('/')
+            .filter(|(owner, repo)| !owner.is_empty() && !repo.is_empty() && !repo.contains('/'))
+            .with_context(|| {
+                format!("repository must be given as owner/name, got '{repository}'")
+            })?;
+        if token.trim().is_empty() {
+            bail!("a GitHub token is required to publish check runs");
+        }
+        let client = Client::builder()
+            .timeout(Duration::from_secs(30))
+            .user_agent(GITHUB_USER_AGENT)
+            .build()
+            .context("failed to build GitHub client")?;
+        Ok(Self {
+            client,
+            api_url: GITHUB_API_URL.to_string(),
+            token: token.trim().to_string(),
+            owner: owner.to_string(),
+            repo: repo.to_string(),
+        })
+    }
+
+    /// Send requests to another API root, e.g. GitHub Enterprise Server
+    pub fn with_api_url(mut self, api_url: &str) -> Self {
+        self.api_url = api_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Publish `report` as a completed check run on `head_sha` and return its URL
+    ///
+    /// The first batch of annotations is sent with the run; the rest are appended with
+    /// updates, since GitHub accepts a limited number per request.
+    pub async fn publish(&self, head_sha: &str, report: &CheckRunReport) -> Result<String> {
+        let mut outputs = report.outputs().into_iter();
+        let first = outputs.next().unwrap_or_else(|| json!({}));
+        let endpoint = format!(
+            "{}/repos/{}/{}/check-runs",
+            self.api_url, self.owner, self.repo
+        );
+        let created = self
+            .send(
+                self.client.post(&endpoint).json(&json!({
+                    "name": report.name,
+                    "head_sha": head_sha,
+                    "status": "completed",
+                    "conclusion": report.conclusion,
+                    "output": first,
+                })),
+                "create check run",
+            )
+            .await?;
+        let id = created
+            .get("id")
+            .and_then(JsonValue::as_i64)
+            .context("GitHub check run response missing id field")?;
+
+        for output in outputs {
+            self.send(
+                self.client
+                    .patch(format!("{endpoint}/{id}"))
+                    .json(&json!({ "output": output })),
+                "add check run annotations",
+            )
+            .await?;
+        }
+
+        Ok(created
+            .get("html_url")
+            .and_then(JsonValue::as_str)
+            .map(str::to_string)
+            .unwrap_or_else(|| format!("{endpoint}/{id}")))
+    }
+
+    async fn send(&self, request: reqwest::RequestBuilder, action: &str) -> Result<JsonValue> {
+        let response = request
+            .header("Authorization", format!("token {}", self.token))
+            .header("Accept", "application/vnd.github+json")
+            .send()
+            .await
+            .context("failed to contact GitHub API")?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            bail!("GitHub API refused to {action} ({status}): {body}");
+        }
+        response
+            .json()
+            .await
+            .with_context(|| format!("failed to parse GitHub response to {action}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{extract::State, routing::post, Json, Router};
+    use std::sync::{Arc, Mutex};
+
+    fn unused(count: u32) -> Vec<UnusedSymbol> {
+        (1..=count)
+            .map(|line| UnusedSymbol {
+                qualified_name: format!("crate::helper_{line}"),
+                kind: "Function".to_string(),
+                file_path: format!("/work/repo/src/helpers_{}.rs", line % 2),
+                start_line: Some(line),
+                end_line: Some(line + 2),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn unused_symbols_become_warnings_on_repo_relative_paths() {
+        let report = CheckRunReport::from_unused(&unused(2), Path::new("/work/repo"));
+        assert_eq!(report.conclusion, CheckConclusion::Neutral);
+        assert_eq!(report.annotations.len(), 2);
+        let annotation = &report.annotations[0];
+        assert_eq!(annotation.path, "src/helpers_1.rs");
+        assert_eq!(annotation.start_line, 1);
+        assert_eq!(annotation.annotation_level, AnnotationLevel::Warning);
+        assert!(annotation.message.contains("crate::helper_1"));
+
+        let clean = CheckRunReport::from_unused(&[], Path::new("/work/repo"));
+        assert_eq!(clean.conclusion, CheckConclusion::Success);
+    }
+
+    #[test]
+    fn repository_must_be_owner_and_name() {
+        assert!(CheckRunPublisher::new("token", "kotadb/kota-db").is_ok());
+        for repository in ["kota-db", "/kota-db", "a/b/c"] {
+            assert!(CheckRunPublisher::new("token", repository).is_err());
+        }
+        assert!(CheckRunPublisher::new(" ", "kotadb/kota-db").is_err());
+    }
+
+    type Requests = Arc<Mutex<Vec<(String, JsonValue)>>>;
+
+    #[tokio::test]
+    async fn annotations_are_sent_in_batches() -> Result<()> {
+        let requests: Requests = Arc::default();
+        let app = Router::new()
+            .route(
+                "/repos/kotadb/kota-db/check-runs",
+                post(
+                    |State(requests): State<Requests>, Json(body): Json<JsonValue>| async move {
+                        requests.lock().unwrap().push(("create".to_string(), body));
+                        Json(json!({"id": 7, "html_url": "https://github.test/runs/7"}))
+                    },
+                ),
+            )
+            .route(
+                "/repos/kotadb/kota-db/check-runs/7",
+                axum::routing::patch(
+                    |State(requests): State<Requests>, Json(body): Json<JsonValue>| async move {
+                        requests.lock().unwrap().push(("update".to_string(), body));
+                        Json(json!({"id": 7}))
+                    },
+                ),
+            )
+            .with_state(requests.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let api_url = format!("http://{}", listener.local_addr()?);
+        let server = tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let publisher = CheckRunPublisher::new("secret", "kotadb/kota-db")?.with_api_url(&api_url);
+        let report = CheckRunReport::from_unused(&unused(120), Path::new("/work/repo"));
+        let url = publisher.publish("abc123", &report).await?;
+        server.abort();
+
+        assert_eq!(url, "https://github.test/runs/7");
+        let requests = requests.lock().unwrap();
+        let batches: Vec<(&str, usize)> = requests
+            .iter()
+            .map(|(kind, body)| {
+                let output = body.get("output").unwrap();
+                (
+                    kind.as_str(),
+                    output["annotations"].as_array().unwrap().len(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            batches,
+            vec![("create", 50), ("update", 50), ("update", 20)]
+        );
+        let create = &requests[0].1;
+        assert_eq!(create["head_sha"], "abc123");
+        assert_eq!(create["status"], "completed");
+        assert_eq!(create["conclusion"], "neutral");
+        assert_eq!(create["output"]["title"], "120 unused symbols");
+        Ok(())
+    }
+}
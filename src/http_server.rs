@@ -20,6 +20,7 @@ use tower_http::{cors::CorsLayer, trace::TraceLayer};
 use tracing::{info, warn};
 use uuid::Uuid;
 
+use crate::metrics::performance::{LockedResource, TimedLock};
 use crate::{
     api_keys::{ApiKeyConfig, ApiKeyService, CreateApiKeyRequest, CreateApiKeyResponse},
     auth_middleware::{auth_middleware, internal_auth_middleware},
@@ -874,7 +875,12 @@ async fn create_document(
         let doc = builder.build()?;
 
         // Store document
-        state.storage.lock().await.insert(doc.clone()).await?;
+        state
+            .storage
+            .lock_timed(LockedResource::Storage)
+            .await
+            .insert(doc.clone())
+            .await?;
 
         Ok(DocumentResponse::from(doc))
     })
@@ -941,7 +947,12 @@ async fn get_document(
     };
 
     let result = with_trace_id("get_document", async move {
-        state.storage.lock().await.get(&validated_id).await
+        state
+            .storage
+            .lock_timed(LockedResource::Storage)
+            .await
+            .get(&validated_id)
+            .await
     })
     .await;
 
@@ -1019,7 +1030,13 @@ async fn update_document(
 
     let result = with_trace_id("update_document", async move {
         // Get existing document
-        let doc = match state.storage.lock().await.get(&validated_id).await? {
+        let doc = match state
+            .storage
+            .lock_timed(LockedResource::Storage)
+            .await
+            .get(&validated_id)
+            .await?
+        {
             Some(doc) => doc,
             None => return Err(anyhow::anyhow!("Document not found")),
         };
@@ -1071,7 +1088,7 @@ async fn update_document(
         // Update the document
         state
             .storage
-            .lock()
+            .lock_timed(LockedResource::Storage)
             .await
             .update(updated_doc.clone())
             .await?;
@@ -1147,7 +1164,7 @@ async fn delete_document(
 
     let result = with_trace_id("delete_document", async move {
         // Check if document exists first
-        let mut storage = state.storage.lock().await;
+        let mut storage = state.storage.lock_timed(LockedResource::Storage).await;
         match storage.get(&validated_id).await? {
             Some(_) => {
                 storage.delete(&validated_id).await?;
@@ -1207,7 +1224,12 @@ async fn search_documents(
         let _offset = params.offset.unwrap_or(0) as usize;
 
         // Get all documents and filter by search query if provided
-        let all_docs = state.storage.lock().await.list_all().await?;
+        let all_docs = state
+            .storage
+            .lock_timed(LockedResource::Storage)
+            .await
+            .list_all()
+            .await?;
         let mut filtered_docs = Vec::new();
 
         // Prepare tag filter - support both 'tag' and 'tags' parameters
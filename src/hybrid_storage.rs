@@ -12,7 +12,7 @@ use std::sync::Arc;
 use tokio::sync::RwLock;
 use uuid::Uuid;
 
-use crate::contracts::{Document, Storage};
+use crate::contracts::{Document, DocumentMetadata, DocumentPage, Storage};
 use crate::file_storage::FileStorage;
 use crate::graph_storage::{GraphEdge, GraphNode, GraphStorage, GraphStorageConfig};
 use crate::native_graph_storage::NativeGraphStorage;
@@ -348,6 +348,21 @@ impl Storage for HybridStorage {
         storage.get(id).await
     }
 
+    async fn get_many(&self, ids: &[ValidatedDocumentId]) -> Result<Vec<Option<Document>>> {
+        let storage = self.document_storage.read().await;
+        storage.get_many(ids).await
+    }
+
+    async fn get_metadata(&self, id: &ValidatedDocumentId) -> Result<Option<DocumentMetadata>> {
+        let storage = self.document_storage.read().await;
+        storage.get_metadata(id).await
+    }
+
+    async fn list_metadata(&self) -> Result<Vec<DocumentMetadata>> {
+        let storage = self.document_storage.read().await;
+        storage.list_metadata().await
+    }
+
     async fn update(&mut self, document: Document) -> Result<()> {
         let path = document.path.as_str();
         let storage_type = self.route_operation(path).await;
@@ -424,6 +439,15 @@ impl Storage for HybridStorage {
         storage.list_all().await
     }
 
+    async fn list_page(
+        &self,
+        after: Option<ValidatedDocumentId>,
+        limit: usize,
+    ) -> Result<DocumentPage> {
+        let storage = self.document_storage.read().await;
+        storage.list_page(after, limit).await
+    }
+
     async fn sync(&mut self) -> Result<()> {
         // Sync both storages
         let mut doc_storage = self.document_storage.write().await;
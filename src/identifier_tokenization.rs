@@ -0,0 +1,240 @@
+//! Identifier-aware tokenization for code search
+//!
+//! Source code glues words together into identifiers (`StorageConfigBuilder`,
+//! `max_retry_count`, `content-type`), so a natural-language query such as
+//! "storage config" shares few trigrams with the code it describes. The trigram
+//! indices use these helpers to index the lowercase subwords of every compound
+//! identifier next to the raw text, and to rewrite identifier-shaped query words
+//! the same way, so both spellings meet in the middle.
+//!
+//! Non-ASCII identifiers can be spelled with composed (`é`) or decomposed (`e` +
+//! combining accent) characters depending on the editor that wrote them. Symbol
+//! search compares names and patterns by [`identifier_search_key`], which brings
+//! both to Unicode normalization form C, so either spelling finds the symbol;
+//! optional transliteration also lets ASCII queries find accented names.
+
+use std::borrow::Cow;
+use std::collections::HashSet;
+
+use unicode_normalization::{is_nfc_quick, IsNormalized, UnicodeNormalization};
+
+/// Environment variable toggling identifier tokenization (`0`, `false`, `off` or `no` disable it)
+pub const IDENTIFIER_TOKENIZATION_ENV: &str = "KOTADB_IDENTIFIER_TOKENIZATION";
+
+/// Whether identifier tokenization is enabled for newly opened indices
+///
+/// Enabled by default. Existing indices must be rebuilt after toggling the flag,
+/// since documents indexed without subwords cannot match subword queries.
+pub fn identifier_tokenization_enabled() -> bool {
+    match std::env::var(IDENTIFIER_TOKENIZATION_ENV) {
+        Ok(value) => !matches!(
+            value.trim().to_ascii_lowercase().as_str(),
+            "0" | "false" | "off" | "no"
+        ),
+        Err(_) => true,
+    }
+}
+
+/// Environment variable enabling ASCII transliteration of symbol names in symbol search
+/// (`1`, `true`, `on` or `yes` enable it)
+pub const IDENTIFIER_TRANSLITERATION_ENV: &str = "KOTADB_TRANSLITERATE_IDENTIFIERS";
+
+/// Whether symbol search matches names by their ASCII transliteration
+///
+/// Disabled by default, because transliteration also merges names that differ only
+/// in accents. Read when a symbol name index is built.
+pub fn identifier_transliteration_enabled() -> bool {
+    std::env::var(IDENTIFIER_TRANSLITERATION_ENV).is_ok_and(|value| {
+        matches!(
+            value.trim().to_ascii_lowercase().as_str(),
+            "1" | "true" | "on" | "yes"
+        )
+    })
+}
+
+/// An identifier in Unicode normalization form C
+pub fn normalize_identifier(identifier: &str) -> Cow<'_, str> {
+    if identifier.is_ascii() || is_nfc_quick(identifier.chars()) == IsNormalized::Yes {
+        Cow::Borrowed(identifier)
+    } else {
+        Cow::Owned(identifier.nfc().collect())
+    }
+}
+
+/// Key under which symbol names and search patterns are compared
+///
+/// The NFC-normalized lowercase identifier, or with `transliterate` its ASCII
+/// transliteration (`Größe` → `grosse`). ASCII characters, including `*`
+/// wildcards, are kept as they are.
+pub fn identifier_search_key(identifier: &str, transliterate: bool) -> String {
+    if identifier.is_ascii() {
+        return identifier.to_ascii_lowercase();
+    }
+    let lower: String = normalize_identifier(identifier)
+        .to_lowercase()
+        .nfc()
+        .collect();
+    if !transliterate {
+        return lower;
+    }
+    let mut key = String::with_capacity(lower.len());
+    for c in lower.chars() {
+        match deunicode::deunicode_char(c).filter(|_| !c.is_ascii()) {
+            // Transliterations of CJK characters end in a word-separating space
+            Some(ascii) => key.push_str(&ascii.trim().to_ascii_lowercase()),
+            None => key.push(c),
+        }
+    }
+    key
+}
+
+/// Split an identifier into lowercase subwords
+///
+/// Boundaries are `_` and `-` separators, lower-to-upper case changes
+/// (`storageConfig`), the end of an acronym (`HTTPServer` → `http`, `server`)
+/// and letter/digit changes (`sha256` → `sha`, `256`).
+pub fn split_identifier(identifier: &str) -> Vec<String> {
+    let chars: Vec<char> = identifier.chars().collect();
+    let mut subwords = Vec::new();
+    let mut current = String::new();
+
+    for (i, &c) in chars.iter().enumerate() {
+        if !c.is_alphanumeric() {
+            if !current.is_empty() {
+                subwords.push(std::mem::take(&mut current));
+            }
+            continue;
+        }
+
+        if let Some(&prev) = i.checked_sub(1).and_then(|p| chars.get(p)) {
+            let next = chars.get(i + 1).copied();
+            let boundary = prev.is_alphanumeric()
+                && ((prev.is_lowercase() && c.is_uppercase())
+                    || (prev.is_uppercase()
+                        && c.is_uppercase()
+                        && next.is_some_and(|n| n.is_lowercase()))
+                    || (prev.is_numeric() != c.is_numeric()));
+            if boundary && !current.is_empty() {
+                subwords.push(std::mem::take(&mut current));
+            }
+        }
+
+        current.extend(c.to_lowercase());
+    }
+
+    if !current.is_empty() {
+        subwords.push(current);
+    }
+    subwords
+}
+
+fn is_identifier_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '-'
+}
+
+/// Build the subword text indexed alongside a document
+///
+/// Every distinct compound identifier in `text` contributes one line of
+/// space-separated subwords; identifiers that are already a single word add
+/// nothing. Returns an empty string when there is nothing to add.
+pub fn identifier_subwords(text: &str) -> String {
+    let mut seen = HashSet::new();
+    let mut lines = Vec::new();
+
+    for word in text.split(|c: char| !is_identifier_char(c)) {
+        if word.is_empty() || !seen.insert(word) {
+            continue;
+        }
+        let subwords = split_identifier(word);
+        if subwords.len() > 1 {
+            lines.push(subwords.join(" "));
+        }
+    }
+
+    lines.join("\n")
+}
+
+/// Rewrite identifier-shaped words in a query term into their subwords
+///
+/// `StorageConfig` and `storage_config` both become `storage config`. Words with
+/// other punctuation (`Foo::new`, `fn(`) are left as typed, because they are
+/// matched against the raw text rather than the subword expansion.
+pub fn normalize_query_term(term: &str) -> String {
+    term.split_whitespace()
+        .map(|word| {
+            if word.chars().all(is_identifier_char) {
+                let subwords = split_identifier(word);
+                if subwords.len() > 1 {
+                    return subwords.join(" ");
+                }
+            }
+            word.to_string()
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn search_keys_ignore_normalization_form() {
+        let composed = "caf\u{e9}_total";
+        let decomposed = "cafe\u{301}_total";
+        assert_eq!(normalize_identifier(decomposed), composed);
+        assert!(matches!(normalize_identifier(composed), Cow::Borrowed(_)));
+        assert_eq!(
+            identifier_search_key("Cafe\u{301}_Total", false),
+            identifier_search_key(composed, false)
+        );
+        assert_eq!(identifier_search_key("Handle_*", false), "handle_*");
+
+        assert_eq!(identifier_search_key(composed, true), "cafe_total");
+        assert_eq!(identifier_search_key("Größe*", true), "grosse*");
+        assert_eq!(identifier_search_key("数据", true), "shuju");
+        assert_eq!(identifier_search_key("Größe", false), "größe");
+    }
+
+    #[test]
+    fn splits_camel_snake_kebab_and_acronyms() {
+        assert_eq!(
+            split_identifier("StorageConfigBuilder"),
+            vec!["storage", "config", "builder"]
+        );
+        assert_eq!(
+            split_identifier("max_retry_count"),
+            vec!["max", "retry", "count"]
+        );
+        assert_eq!(split_identifier("content-type"), vec!["content", "type"]);
+        assert_eq!(split_identifier("HTTPServer"), vec!["http", "server"]);
+        assert_eq!(split_identifier("parseJSON"), vec!["parse", "json"]);
+        assert_eq!(
+            split_identifier("sha256_digest"),
+            vec!["sha", "256", "digest"]
+        );
+        assert_eq!(split_identifier("storage"), vec!["storage"]);
+        assert!(split_identifier("__").is_empty());
+    }
+
+    #[test]
+    fn subword_text_covers_each_compound_identifier_once() {
+        let text = "let builder = StorageConfigBuilder::new(); builder.max_retries(3); StorageConfigBuilder";
+        assert_eq!(
+            identifier_subwords(text),
+            "storage config builder\nmax retries"
+        );
+        assert_eq!(identifier_subwords("plain words only"), "");
+    }
+
+    #[test]
+    fn query_terms_keep_punctuated_words_verbatim() {
+        assert_eq!(normalize_query_term("StorageConfig"), "storage config");
+        assert_eq!(
+            normalize_query_term("find storage_config"),
+            "find storage config"
+        );
+        assert_eq!(normalize_query_term("FileStorage::new"), "FileStorage::new");
+        assert_eq!(normalize_query_term("storage config"), "storage config");
+    }
+}
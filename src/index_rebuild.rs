@@ -0,0 +1,394 @@
+//! Parallel rebuild of the primary and trigram indices from storage
+//!
+//! A rebuild runs as a pipeline of stages connected by bounded channels: a storage reader
+//! walks the documents one page at a time, tokenizer workers split page contents into
+//! trigrams on blocking threads, and one writer per index inserts the results. Each index
+//! lock is only taken by its own writer, so reading, tokenizing and both inserts overlap
+//! while the channel bounds keep a handful of pages in memory. The report records the
+//! documents handled and the busy time of every stage, from which per-stage throughput is
+//! derived.
+
+use anyhow::{Context, Result};
+use futures::StreamExt;
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, Mutex};
+use tokio_stream::wrappers::ReceiverStream;
+
+use crate::contracts::{ContentTokenizer, Document, Index, Storage, TokenizedDocument};
+use crate::metrics::performance::{LockedResource, TimedLock};
+use crate::types::{ValidatedDocumentId, ValidatedPath};
+
+/// Documents read from storage per page
+pub const REBUILD_PAGE_SIZE: usize = 100;
+/// Pages buffered between two stages
+const CHANNEL_DEPTH: usize = 4;
+/// Documents inserted between periodic index flushes
+const FLUSH_INTERVAL: usize = 500;
+
+type Errors = Arc<StdMutex<Vec<String>>>;
+
+/// Work done by one pipeline stage
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StageStats {
+    pub documents: usize,
+    /// Time spent working rather than waiting on other stages; summed over workers
+    pub busy: Duration,
+}
+
+impl StageStats {
+    /// Documents per second of busy time
+    pub fn throughput(&self) -> f64 {
+        let secs = self.busy.as_secs_f64();
+        if secs > 0.0 {
+            self.documents as f64 / secs
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Outcome of [`rebuild_indices`]
+#[derive(Debug, Clone, Default)]
+pub struct RebuildReport {
+    pub read: StageStats,
+    pub tokenize: StageStats,
+    pub primary: StageStats,
+    pub trigram: StageStats,
+    /// Tokenizer workers run in parallel
+    pub workers: usize,
+    pub elapsed: Duration,
+    /// Problems that didn't stop the rebuild, e.g. documents an index rejected
+    pub errors: Vec<String>,
+}
+
+impl RebuildReport {
+    /// Documents inserted into the trigram index
+    pub fn processed(&self) -> usize {
+        self.trigram.documents
+    }
+
+    /// One line per stage with its documents and throughput
+    pub fn stage_summary(&self) -> String {
+        [
+            ("read", &self.read),
+            ("tokenize", &self.tokenize),
+            ("primary index", &self.primary),
+            ("trigram index", &self.trigram),
+        ]
+        .iter()
+        .map(|(name, stats)| {
+            format!(
+                "{}: {} docs in {:.2}s ({:.0} docs/s)",
+                name,
+                stats.documents,
+                stats.busy.as_secs_f64(),
+                stats.throughput()
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+    }
+}
+
+/// Progress reported after each page reaches the trigram index
+#[derive(Debug, Clone, PartialEq)]
+pub struct RebuildProgress {
+    pub processed: usize,
+    pub batch_size: usize,
+    pub errors: usize,
+}
+
+/// Rebuild both indices from every document in `storage`
+///
+/// Failures on individual documents are collected in the report rather than aborting the
+/// rebuild; only a panicking stage is an error.
+pub async fn rebuild_indices(
+    storage: Arc<Mutex<dyn Storage>>,
+    primary_index: Arc<Mutex<dyn Index>>,
+    trigram_index: Arc<Mutex<dyn Index>>,
+    progress: impl Fn(&RebuildProgress) + Sync,
+) -> Result<RebuildReport> {
+    let started = Instant::now();
+    let errors: Errors = Arc::default();
+    let tokenizer = trigram_index
+        .lock_timed(LockedResource::TrigramIndex)
+        .await
+        .content_tokenizer()
+        .await;
+    let workers = num_cpus::get().max(1);
+
+    let (page_tx, page_rx) = mpsc::channel(CHANNEL_DEPTH);
+    let (path_tx, path_rx) = mpsc::channel(CHANNEL_DEPTH);
+    let (tokenized_tx, tokenized_rx) = mpsc::channel(CHANNEL_DEPTH);
+
+    let reader = tokio::spawn(read_pages(storage, page_tx, path_tx, errors.clone()));
+    let tokenizers = tokio::spawn(tokenize_pages(tokenizer, workers, page_rx, tokenized_tx));
+    let primary = tokio::spawn(write_primary(primary_index, path_rx, errors.clone()));
+    let trigram = write_trigram(
+        trigram_index,
+        tokenizer.is_some(),
+        tokenized_rx,
+        &errors,
+        &progress,
+    )
+    .await;
+
+    let read = reader.await.context("Index rebuild reader panicked")?;
+    let tokenize = tokenizers
+        .await
+        .context("Index rebuild tokenizer panicked")??;
+    let primary = primary
+        .await
+        .context("Index rebuild primary index writer panicked")?;
+    let errors = std::mem::take(&mut *errors.lock().unwrap());
+
+    Ok(RebuildReport {
+        read,
+        tokenize,
+        primary,
+        trigram,
+        workers,
+        elapsed: started.elapsed(),
+        errors,
+    })
+}
+
+/// Walk storage page by page, feeding the tokenizers and the primary index writer
+async fn read_pages(
+    storage: Arc<Mutex<dyn Storage>>,
+    pages: mpsc::Sender<Vec<Document>>,
+    paths: mpsc::Sender<Vec<(ValidatedDocumentId, ValidatedPath)>>,
+    errors: Errors,
+) -> StageStats {
+    let mut stats = StageStats::default();
+    let mut cursor = None;
+    loop {
+        let start = Instant::now();
+        let page = storage
+            .lock_timed(LockedResource::Storage)
+            .await
+            .list_page(cursor, REBUILD_PAGE_SIZE)
+            .await;
+        stats.busy += start.elapsed();
+
+        let page = match page {
+            Ok(page) => page,
+            Err(e) => {
+                errors
+                    .lock()
+                    .unwrap()
+                    .push(format!("Failed to list documents for index rebuild: {}", e));
+                break;
+            }
+        };
+        stats.documents += page.documents.len();
+
+        if !page.documents.is_empty() {
+            let entries = page
+                .documents
+                .iter()
+                .map(|doc| (doc.id, doc.path.clone()))
+                .collect();
+            if paths.send(entries).await.is_err() || pages.send(page.documents).await.is_err() {
+                break;
+            }
+        }
+
+        match page.next {
+            Some(next) => cursor = Some(next),
+            None => break,
+        }
+    }
+    stats
+}
+
+/// Tokenize pages on up to `workers` blocking threads, forwarding them in storage order
+async fn tokenize_pages(
+    tokenizer: Option<ContentTokenizer>,
+    workers: usize,
+    pages: mpsc::Receiver<Vec<Document>>,
+    tokenized: mpsc::Sender<Vec<TokenizedDocument>>,
+) -> Result<StageStats> {
+    let mut stats = StageStats::default();
+    let mut batches = ReceiverStream::new(pages)
+        .map(|page| {
+            tokio::task::spawn_blocking(move || {
+                let start = Instant::now();
+                let documents: Vec<_> = page
+                    .into_iter()
+                    .map(|doc| match tokenizer {
+                        Some(tokenizer) => tokenizer.tokenize(doc.id, doc.path, doc.content),
+                        // The index tokenizes on insert
+                        None => TokenizedDocument {
+                            id: doc.id,
+                            path: doc.path,
+                            content: doc.content,
+                            trigrams: Vec::new(),
+                        },
+                    })
+                    .collect();
+                (documents, start.elapsed())
+            })
+        })
+        .buffered(workers);
+
+    while let Some(batch) = batches.next().await {
+        let (documents, busy) = batch?;
+        stats.documents += documents.len();
+        stats.busy += busy;
+        if tokenized.send(documents).await.is_err() {
+            break;
+        }
+    }
+    Ok(stats)
+}
+
+async fn write_primary(
+    index: Arc<Mutex<dyn Index>>,
+    mut batches: mpsc::Receiver<Vec<(ValidatedDocumentId, ValidatedPath)>>,
+    errors: Errors,
+) -> StageStats {
+    let mut stats = StageStats::default();
+    while let Some(batch) = batches.recv().await {
+        let start = Instant::now();
+        let mut index = index.lock().await;
+        for (id, path) in batch {
+            if let Err(e) = index.insert(id, path).await {
+                errors.lock().unwrap().push(format!(
+                    "Failed to insert document into primary index: {}",
+                    e
+                ));
+            }
+            stats.documents += 1;
+            if stats.documents % FLUSH_INTERVAL == 0 {
+                flush(&mut *index, "primary", &errors).await;
+            }
+        }
+        stats.busy += start.elapsed();
+    }
+    if stats.documents > 0 {
+        let start = Instant::now();
+        flush(&mut *index.lock().await, "primary", &errors).await;
+        stats.busy += start.elapsed();
+    }
+    stats
+}
+
+async fn write_trigram(
+    index: Arc<Mutex<dyn Index>>,
+    pretokenized: bool,
+    mut batches: mpsc::Receiver<Vec<TokenizedDocument>>,
+    errors: &Errors,
+    progress: &(impl Fn(&RebuildProgress) + Sync),
+) -> StageStats {
+    let mut stats = StageStats::default();
+    while let Some(batch) = batches.recv().await {
+        let start = Instant::now();
+        let batch_size = batch.len();
+        {
+            let mut index = index.lock().await;
+            for document in batch {
+                let result = if pretokenized {
+                    index.insert_tokenized(document).await
+                } else {
+                    index
+                        .insert_with_content(document.id, document.path, &document.content)
+                        .await
+                };
+                if let Err(e) = result {
+                    errors.lock().unwrap().push(format!(
+                        "Failed to insert document into trigram index: {}",
+                        e
+                    ));
+                }
+                stats.documents += 1;
+                if stats.documents % FLUSH_INTERVAL == 0 {
+                    flush(&mut *index, "trigram", errors).await;
+                }
+            }
+        }
+        stats.busy += start.elapsed();
+
+        let errors = errors.lock().unwrap().len();
+        progress(&RebuildProgress {
+            processed: stats.documents,
+            batch_size,
+            errors,
+        });
+    }
+    if stats.documents > 0 {
+        let start = Instant::now();
+        flush(&mut *index.lock().await, "trigram", errors).await;
+        stats.busy += start.elapsed();
+    }
+    stats
+}
+
+async fn flush(index: &mut dyn Index, name: &str, errors: &Errors) {
+    if let Err(e) = index.flush().await {
+        errors
+            .lock()
+            .unwrap()
+            .push(format!("Failed to flush {} index: {}", name, e));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builders::DocumentBuilder;
+    use crate::{create_file_storage, create_primary_index, create_trigram_index, QueryBuilder};
+    use tempfile::TempDir;
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn rebuilds_both_indices_through_the_pipeline() -> Result<()> {
+        let dir = TempDir::new()?;
+        let path = |name: &str| dir.path().join(name).to_string_lossy().to_string();
+        let mut storage = create_file_storage(&path("storage"), Some(10)).await?;
+        let count = REBUILD_PAGE_SIZE * 2 + 7;
+        for i in 0..count {
+            let doc = DocumentBuilder::new()
+                .path(format!("src/module_{i}.rs"))?
+                .title(format!("Module {i}"))?
+                .content(format!("fn handler_{i}() {{ pipeline_marker(); }}").as_bytes())
+                .build()?;
+            storage.insert(doc).await?;
+        }
+
+        let storage: Arc<Mutex<dyn Storage>> = Arc::new(Mutex::new(storage));
+        let primary: Arc<Mutex<dyn Index>> = Arc::new(Mutex::new(
+            create_primary_index(&path("primary"), Some(100)).await?,
+        ));
+        let trigram: Arc<Mutex<dyn Index>> = Arc::new(Mutex::new(
+            create_trigram_index(&path("trigram"), Some(100)).await?,
+        ));
+
+        let updates = StdMutex::new(Vec::new());
+        let report = rebuild_indices(storage, primary.clone(), trigram.clone(), |p| {
+            updates.lock().unwrap().push(p.processed)
+        })
+        .await?;
+
+        assert!(report.errors.is_empty(), "{:?}", report.errors);
+        for stage in [
+            &report.read,
+            &report.tokenize,
+            &report.primary,
+            &report.trigram,
+        ] {
+            assert_eq!(stage.documents, count);
+        }
+        assert_eq!(updates.into_inner().unwrap().last(), Some(&count));
+        assert_eq!(report.stage_summary().lines().count(), 4);
+
+        let query = QueryBuilder::new()
+            .with_text("pipeline_marker")?
+            .with_limit(1000)?
+            .build()?;
+        assert_eq!(trigram.lock().await.search(&query).await?.len(), count);
+        let query = QueryBuilder::new().with_limit(1000)?.build()?;
+        assert_eq!(primary.lock().await.search(&query).await?.len(), count);
+        Ok(())
+    }
+}
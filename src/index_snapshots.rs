@@ -0,0 +1,436 @@
+//! Index snapshots addressed by commit
+//!
+//! `kotadb index-codebase <repo> --snapshot` copies the database directory, once indexing
+//! has finished, to `snapshots/<commit>/` and records the repository's HEAD commit and its
+//! date in `snapshots/manifest.json`. `--as-of <commit|date>` on `search-code`,
+//! `find-callers` and `codebase-overview` then answers from the snapshot of that commit, or
+//! from the newest snapshot committed at or before the date, so the codebase can be
+//! questioned as it was without checking out an old tree. A snapshot is only opened when a
+//! query asks for it.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Directory holding the snapshots inside a database directory
+pub const SNAPSHOTS_DIR: &str = "snapshots";
+
+/// Manifest of the snapshots inside [`SNAPSHOTS_DIR`]
+const MANIFEST_FILE: &str = "manifest.json";
+
+/// Shortest commit prefix matched against snapshot commits
+const MIN_COMMIT_PREFIX: usize = 4;
+
+/// Copy of a database taken after indexing one commit
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IndexSnapshot {
+    /// Full SHA of the indexed commit
+    pub commit: String,
+    pub committed_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+    /// Repository the commit was indexed from
+    pub repository: String,
+    /// Directory of the snapshot below [`SNAPSHOTS_DIR`]
+    pub dir: String,
+}
+
+/// Snapshots of a database, oldest commit first
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SnapshotManifest {
+    pub snapshots: Vec<IndexSnapshot>,
+}
+
+impl SnapshotManifest {
+    /// Location of the manifest for a database directory
+    pub fn path_for(db_path: &Path) -> PathBuf {
+        db_path.join(SNAPSHOTS_DIR).join(MANIFEST_FILE)
+    }
+
+    /// Snapshots recorded for a database; empty when none were taken
+    pub fn load(db_path: &Path) -> Result<Self> {
+        let path = Self::path_for(db_path);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read snapshot manifest: {}", path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse snapshot manifest: {}", path.display()))
+    }
+
+    /// Write the manifest atomically
+    pub fn save(&self, db_path: &Path) -> Result<()> {
+        let path = Self::path_for(db_path);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        let temp_path = path.with_extension("json.tmp");
+        std::fs::write(&temp_path, serde_json::to_string_pretty(self)?).with_context(|| {
+            format!("Failed to write snapshot manifest: {}", temp_path.display())
+        })?;
+        std::fs::rename(&temp_path, &path)
+            .with_context(|| format!("Failed to write snapshot manifest: {}", path.display()))
+    }
+
+    /// Newest snapshot of a commit made at or before `time`
+    pub fn at_or_before(&self, time: DateTime<Utc>) -> Option<&IndexSnapshot> {
+        self.snapshots
+            .iter()
+            .filter(|snapshot| snapshot.committed_at <= time)
+            .max_by_key(|snapshot| snapshot.committed_at)
+    }
+
+    /// Snapshot answering `--as-of`: a date or time, a commit SHA or prefix of a snapshot,
+    /// or any revision of the indexed repository, which selects the newest snapshot at or
+    /// before that commit
+    pub fn resolve(&self, as_of: &str) -> Result<&IndexSnapshot> {
+        let as_of = as_of.trim();
+        if self.snapshots.is_empty() {
+            anyhow::bail!(
+                "No index snapshots recorded. Index with snapshots first:\n\
+                 kotadb index-codebase /path/to/repo --snapshot"
+            );
+        }
+        if let Some(time) = parse_as_of_date(as_of) {
+            return self
+                .at_or_before(time)
+                .ok_or_else(|| self.none_before(as_of));
+        }
+
+        let prefix = as_of.to_ascii_lowercase();
+        if prefix.len() >= MIN_COMMIT_PREFIX && prefix.chars().all(|c| c.is_ascii_hexdigit()) {
+            let matches: Vec<&IndexSnapshot> = self
+                .snapshots
+                .iter()
+                .filter(|snapshot| snapshot.commit.starts_with(&prefix))
+                .collect();
+            match matches.as_slice() {
+                [snapshot] => return Ok(snapshot),
+                [] => {}
+                _ => anyhow::bail!(
+                    "Commit prefix {} matches {} snapshots; use a longer prefix",
+                    as_of,
+                    matches.len()
+                ),
+            }
+        }
+
+        self.resolve_revision(as_of)
+    }
+
+    /// Newest snapshot at or before the commit a revision of the indexed repository names
+    #[cfg(feature = "git-integration")]
+    fn resolve_revision(&self, revision: &str) -> Result<&IndexSnapshot> {
+        use crate::git::{GitRepository, IngestionOptions};
+
+        let newest = self
+            .snapshots
+            .iter()
+            .max_by_key(|snapshot| snapshot.committed_at)
+            .context("No index snapshots recorded")?;
+        let repository = GitRepository::open(&newest.repository, IngestionOptions::default())
+            .with_context(|| format!("{} is not a date or a snapshot commit", revision))?;
+        let commit = repository.resolve_commit(revision)?;
+        if let Some(snapshot) = self.snapshots.iter().find(|s| s.commit == commit.sha) {
+            return Ok(snapshot);
+        }
+        self.at_or_before(commit.timestamp)
+            .ok_or_else(|| self.none_before(revision))
+    }
+
+    #[cfg(not(feature = "git-integration"))]
+    fn resolve_revision(&self, revision: &str) -> Result<&IndexSnapshot> {
+        anyhow::bail!(
+            "{} is not a date or the commit of an index snapshot",
+            revision
+        )
+    }
+
+    fn none_before(&self, as_of: &str) -> anyhow::Error {
+        let oldest = self
+            .snapshots
+            .iter()
+            .map(|snapshot| snapshot.committed_at)
+            .min()
+            .map(|time| time.format("%Y-%m-%d %H:%M:%S UTC").to_string())
+            .unwrap_or_default();
+        anyhow::anyhow!(
+            "No index snapshot as of {}; the oldest snapshot is of a commit from {}",
+            as_of,
+            oldest
+        )
+    }
+
+    /// Format the snapshots as markdown
+    pub fn to_markdown(&self) -> String {
+        if self.snapshots.is_empty() {
+            return "No index snapshots recorded. Take one with:\n  kotadb index-codebase /path/to/repo --snapshot\n".to_string();
+        }
+        let mut md =
+            String::from("| Commit | Committed | Indexed | Repository |\n|---|---|---|---|\n");
+        for snapshot in &self.snapshots {
+            md.push_str(&format!(
+                "| {} | {} | {} | {} |\n",
+                &snapshot.commit[..snapshot.commit.len().min(12)],
+                snapshot.committed_at.format("%Y-%m-%d %H:%M"),
+                snapshot.created_at.format("%Y-%m-%d %H:%M"),
+                snapshot.repository
+            ));
+        }
+        md
+    }
+}
+
+/// `--as-of` dates: RFC 3339, `YYYY-MM-DD HH:MM:SS` (UTC) or `YYYY-MM-DD`, meaning the end
+/// of that day
+pub fn parse_as_of_date(text: &str) -> Option<DateTime<Utc>> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(text) {
+        return Some(time.with_timezone(&Utc));
+    }
+    if let Ok(time) = NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M:%S") {
+        return Some(time.and_utc());
+    }
+    NaiveDate::parse_from_str(text, "%Y-%m-%d")
+        .ok()?
+        .and_hms_opt(23, 59, 59)
+        .map(|time| time.and_utc())
+}
+
+/// Database directory a query reads: the snapshot `as_of` selects, or `db_path` itself
+pub fn database_path_as_of(db_path: &Path, as_of: Option<&str>) -> Result<PathBuf> {
+    let Some(as_of) = as_of else {
+        return Ok(db_path.to_path_buf());
+    };
+    let manifest = SnapshotManifest::load(db_path)?;
+    let snapshot = manifest.resolve(as_of)?;
+    Ok(db_path.join(SNAPSHOTS_DIR).join(&snapshot.dir))
+}
+
+/// Copy the database to a snapshot of the repository's HEAD commit, replacing an earlier
+/// snapshot of the same commit
+#[cfg(feature = "git-integration")]
+pub fn record_snapshot(db_path: &Path, repo_path: &Path) -> Result<IndexSnapshot> {
+    use crate::git::{GitRepository, IngestionOptions};
+
+    let head =
+        GitRepository::open(repo_path, IngestionOptions::default())?.resolve_commit("HEAD")?;
+    let snapshot = IndexSnapshot {
+        dir: head.sha[..head.sha.len().min(12)].to_string(),
+        commit: head.sha,
+        committed_at: head.timestamp,
+        created_at: Utc::now(),
+        repository: repo_path
+            .canonicalize()
+            .unwrap_or_else(|_| repo_path.to_path_buf())
+            .display()
+            .to_string(),
+    };
+
+    let snapshots_dir = db_path.join(SNAPSHOTS_DIR);
+    let target = snapshots_dir.join(&snapshot.dir);
+    let staging = snapshots_dir.join(format!("{}.tmp", snapshot.dir));
+    if staging.exists() {
+        std::fs::remove_dir_all(&staging)
+            .with_context(|| format!("Failed to remove {}", staging.display()))?;
+    }
+    copy_database(db_path, &staging)?;
+    if target.exists() {
+        std::fs::remove_dir_all(&target)
+            .with_context(|| format!("Failed to remove {}", target.display()))?;
+    }
+    std::fs::rename(&staging, &target)
+        .with_context(|| format!("Failed to create snapshot {}", target.display()))?;
+
+    let mut manifest = SnapshotManifest::load(db_path)?;
+    manifest.snapshots.retain(|s| s.commit != snapshot.commit);
+    manifest.snapshots.push(snapshot.clone());
+    manifest.snapshots.sort_by_key(|s| s.committed_at);
+    manifest.save(db_path)?;
+    Ok(snapshot)
+}
+
+/// Remove all but the `keep` newest snapshots (by commit date), returning the removed ones
+pub fn prune_snapshots(db_path: &Path, keep: usize) -> Result<Vec<IndexSnapshot>> {
+    let mut manifest = SnapshotManifest::load(db_path)?;
+    if manifest.snapshots.len() <= keep {
+        return Ok(Vec::new());
+    }
+    manifest.snapshots.sort_by_key(|s| s.committed_at);
+    let removed: Vec<IndexSnapshot> = manifest
+        .snapshots
+        .drain(..manifest.snapshots.len() - keep)
+        .collect();
+    // The manifest is written first so queries never resolve to a removed directory
+    manifest.save(db_path)?;
+    for snapshot in &removed {
+        let dir = db_path.join(SNAPSHOTS_DIR).join(&snapshot.dir);
+        if dir.exists() {
+            std::fs::remove_dir_all(&dir)
+                .with_context(|| format!("Failed to remove snapshot {}", dir.display()))?;
+        }
+    }
+    Ok(removed)
+}
+
+/// Entries of a database directory that belong to it rather than to its contents
+const UNCOPIED_ENTRIES: &[&str] = &[
+    SNAPSHOTS_DIR,
+    crate::db_lock::LOCK_FILE,
+    crate::db_lock::GUARD_FILE,
+];
+
+/// Copy a database directory, leaving out its snapshots and the lease of its writer
+fn copy_database(db_path: &Path, target: &Path) -> Result<()> {
+    std::fs::create_dir_all(target)
+        .with_context(|| format!("Failed to create {}", target.display()))?;
+    for entry in std::fs::read_dir(db_path)
+        .with_context(|| format!("Failed to read {}", db_path.display()))?
+    {
+        let entry = entry?;
+        if UNCOPIED_ENTRIES
+            .iter()
+            .any(|name| entry.file_name() == *name)
+        {
+            continue;
+        }
+        copy_entry(&entry.path(), &target.join(entry.file_name()))?;
+    }
+    Ok(())
+}
+
+fn copy_entry(source: &Path, target: &Path) -> Result<()> {
+    if source.is_dir() {
+        std::fs::create_dir_all(target)
+            .with_context(|| format!("Failed to create {}", target.display()))?;
+        for entry in std::fs::read_dir(source)
+            .with_context(|| format!("Failed to read {}", source.display()))?
+        {
+            let entry = entry?;
+            copy_entry(&entry.path(), &target.join(entry.file_name()))?;
+        }
+    } else {
+        std::fs::copy(source, target)
+            .with_context(|| format!("Failed to copy {}", source.display()))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn snapshot(commit: &str, committed_at: &str) -> IndexSnapshot {
+        IndexSnapshot {
+            commit: commit.to_string(),
+            committed_at: parse_as_of_date(committed_at).unwrap(),
+            created_at: Utc::now(),
+            repository: "/nonexistent/repo".to_string(),
+            dir: commit[..12].to_string(),
+        }
+    }
+
+    #[test]
+    fn resolves_commit_prefixes_and_dates() {
+        let manifest = SnapshotManifest {
+            snapshots: vec![
+                snapshot(
+                    "a1b2c3d4e5f60718293a4b5c6d7e8f9012345678",
+                    "2024-03-01 12:00:00",
+                ),
+                snapshot(
+                    "a1b2ffff00001111222233334444555566667777",
+                    "2024-04-01 12:00:00",
+                ),
+                snapshot(
+                    "0c0ffee000000000000000000000000000000000",
+                    "2024-05-01 12:00:00",
+                ),
+            ],
+        };
+
+        let commit = |as_of: &str| manifest.resolve(as_of).map(|s| s.commit[..4].to_string());
+        assert_eq!(commit("0C0FFEE").unwrap(), "0c0f");
+        assert_eq!(commit("a1b2c3").unwrap(), "a1b2");
+        assert!(commit("a1b2")
+            .unwrap_err()
+            .to_string()
+            .contains("matches 2 snapshots"));
+        // A date includes the whole day
+        assert_eq!(commit("2024-05-01").unwrap(), "0c0f");
+        assert_eq!(commit("2024-04-30").unwrap(), "a1b2");
+        assert_eq!(commit("2024-04-01T11:00:00Z").unwrap(), "a1b2");
+        assert!(commit("2024-02-01")
+            .unwrap_err()
+            .to_string()
+            .contains("oldest snapshot is of a commit from 2024-03-01"));
+        assert!(SnapshotManifest::default().resolve("2024-05-01").is_err());
+    }
+
+    #[test]
+    fn snapshots_copy_the_database_without_earlier_snapshots() -> Result<()> {
+        let db = TempDir::new()?;
+        std::fs::write(db.path().join("symbols.kota"), b"symbols")?;
+        std::fs::create_dir_all(db.path().join("storage/pages"))?;
+        std::fs::write(db.path().join("storage/pages/0001.page"), b"page")?;
+        let manifest = SnapshotManifest {
+            snapshots: vec![snapshot(
+                "0c0ffee000000000000000000000000000000000",
+                "2024-05-01",
+            )],
+        };
+        manifest.save(db.path())?;
+        std::fs::write(db.path().join(crate::db_lock::LOCK_FILE), b"{}")?;
+        std::fs::write(db.path().join(crate::db_lock::GUARD_FILE), b"")?;
+
+        let target = db.path().join(SNAPSHOTS_DIR).join("copy");
+        copy_database(db.path(), &target)?;
+        assert_eq!(std::fs::read(target.join("symbols.kota"))?, b"symbols");
+        assert_eq!(
+            std::fs::read(target.join("storage/pages/0001.page"))?,
+            b"page"
+        );
+        assert!(!target.join(SNAPSHOTS_DIR).exists());
+        assert!(!target.join(crate::db_lock::LOCK_FILE).exists());
+        assert!(!target.join(crate::db_lock::GUARD_FILE).exists());
+
+        assert_eq!(
+            database_path_as_of(db.path(), Some("0c0ffee"))?,
+            db.path().join(SNAPSHOTS_DIR).join("0c0ffee00000")
+        );
+        assert_eq!(database_path_as_of(db.path(), None)?, db.path());
+        Ok(())
+    }
+
+    #[test]
+    fn pruning_keeps_the_newest_snapshots() -> Result<()> {
+        let db = TempDir::new()?;
+        let manifest = SnapshotManifest {
+            snapshots: vec![
+                snapshot("cccccccccccccccccccccccccccccccccccccccc", "2024-03-01"),
+                snapshot("aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa", "2024-01-01"),
+                snapshot("bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb", "2024-02-01"),
+            ],
+        };
+        manifest.save(db.path())?;
+        for snapshot in &manifest.snapshots {
+            std::fs::create_dir_all(db.path().join(SNAPSHOTS_DIR).join(&snapshot.dir))?;
+        }
+
+        let removed = prune_snapshots(db.path(), 2)?;
+        assert_eq!(removed.len(), 1);
+        assert_eq!(removed[0].dir, "aaaaaaaaaaaa");
+        assert!(!db.path().join(SNAPSHOTS_DIR).join("aaaaaaaaaaaa").exists());
+        assert!(db.path().join(SNAPSHOTS_DIR).join("bbbbbbbbbbbb").exists());
+        let dirs: Vec<String> = SnapshotManifest::load(db.path())?
+            .snapshots
+            .into_iter()
+            .map(|s| s.dir)
+            .collect();
+        assert_eq!(dirs, ["bbbbbbbbbbbb", "cccccccccccc"]);
+        assert!(prune_snapshots(db.path(), 2)?.is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,916 @@
+//! Repository ingestion in a sandboxed subprocess
+//!
+//! Ingestion runs tree-sitter, a C library, over every file of the repository being
+//! indexed. For the SaaS worker those are untrusted customer files, and a parser crash
+//! or exploit inside the API server takes down (or takes over) every tenant's
+//! requests. With an [`IngestionSandbox`], `IndexingService` hands ingestion to the
+//! `kotadb-index-sandbox` binary instead:
+//!
+//! 1. The parent writes a [`SandboxRequest`] to the child's stdin.
+//! 2. The child confines itself before touching the repository: resource limits,
+//!    `no_new_privs`, a Landlock ruleset that lets it read only the repository and
+//!    the loader and shared libraries and write only its output directory, and a
+//!    seccomp filter that refuses networking, process execution and other syscalls
+//!    ingestion never needs.
+//! 3. It ingests into a scratch database in the output directory and reports progress
+//!    and the result as JSON lines ([`SandboxMessage`]) on stdout.
+//! 4. The parent checks the output before it touches the database: every document must
+//!    lie under the configured path prefix, and the symbol database, dependency graph
+//!    and crate map must parse and record only repository-relative paths. It then
+//!    copies the documents into its storage and writes its own encoding of the
+//!    artifacts into place. A child that crashes, is killed by a limit, runs past the
+//!    timeout or produces output that fails these checks fails the job and leaves the
+//!    database untouched.
+//!
+//! Confinement is Linux-only; elsewhere the child refuses to run.
+
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tracing::{debug, info, warn};
+
+#[cfg(feature = "tree-sitter-parsing")]
+use crate::binary_symbols::{BinarySymbolReader, BinarySymbolWriter};
+use crate::cargo_workspace::CrateMap;
+#[cfg(feature = "tree-sitter-parsing")]
+use crate::dependency_extractor::SerializableDependencyGraph;
+use crate::git::{IngestResult, IngestionConfig, ProgressCallback, RepositoryIngester};
+use crate::Storage;
+
+/// Path of the `kotadb-index-sandbox` binary; sandboxing is off when unset
+pub const SANDBOX_BINARY_ENV: &str = "KOTADB_INDEX_SANDBOX_BIN";
+
+/// Address-space limit of the sandbox in MiB
+pub const SANDBOX_MEMORY_MB_ENV: &str = "KOTADB_INDEX_SANDBOX_MEMORY_MB";
+
+/// Wall-clock limit of one sandboxed ingestion in seconds
+pub const SANDBOX_TIMEOUT_SECS_ENV: &str = "KOTADB_INDEX_SANDBOX_TIMEOUT_SECS";
+
+/// Symbol database and dependency graph the sandbox writes, under the names they have in
+/// the database directory
+#[cfg(feature = "tree-sitter-parsing")]
+const SYMBOLS_FILE: &str = "symbols.kota";
+#[cfg(feature = "tree-sitter-parsing")]
+const GRAPH_FILE: &str = "dependency_graph.bin";
+
+/// Documents read per page of the sandbox's scratch storage
+const IMPORT_PAGE_SIZE: usize = 256;
+
+/// Bytes of stdout read from the child, so a compromised child cannot exhaust memory
+const MAX_IPC_BYTES: u64 = 64 * 1024 * 1024;
+
+/// Resource limits of the sandboxed process
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SandboxLimits {
+    /// Address space, in MiB
+    pub memory_mb: u64,
+    /// CPU time, in seconds
+    pub cpu_seconds: u64,
+    /// Largest file the sandbox may write, in MiB
+    pub max_file_size_mb: u64,
+    /// Open file descriptors
+    pub max_open_files: u64,
+    /// Wall-clock time before the parent kills the sandbox, in seconds
+    pub timeout_secs: u64,
+}
+
+impl Default for SandboxLimits {
+    fn default() -> Self {
+        Self {
+            memory_mb: 4096,
+            cpu_seconds: 1800,
+            max_file_size_mb: 2048,
+            max_open_files: 1024,
+            timeout_secs: 1800,
+        }
+    }
+}
+
+/// What the parent asks the sandbox to ingest
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SandboxRequest {
+    pub repo_path: PathBuf,
+    /// The only directory the sandbox may write to
+    pub output_dir: PathBuf,
+    pub config: IngestionConfig,
+    pub extract_symbols: bool,
+    pub limits: SandboxLimits,
+}
+
+/// One line of the sandbox's stdout
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SandboxMessage {
+    Progress { message: String },
+    Done { result: IngestResult },
+    Failed { error: String },
+}
+
+/// Runs ingestion in `kotadb-index-sandbox`
+#[derive(Debug, Clone)]
+pub struct IngestionSandbox {
+    binary: PathBuf,
+    limits: SandboxLimits,
+}
+
+impl IngestionSandbox {
+    pub fn new(binary: impl Into<PathBuf>) -> Self {
+        Self {
+            binary: binary.into(),
+            limits: SandboxLimits::default(),
+        }
+    }
+
+    pub fn with_limits(mut self, limits: SandboxLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Sandbox described by the environment, or `None` when no sandbox binary is configured
+    pub fn from_env() -> Result<Option<Self>> {
+        let Some(binary) = non_empty_env(SANDBOX_BINARY_ENV) else {
+            return Ok(None);
+        };
+        let binary = PathBuf::from(binary);
+        if !binary.is_file() {
+            bail!(
+                "{} points to {}, which is not a file",
+                SANDBOX_BINARY_ENV,
+                binary.display()
+            );
+        }
+        let mut limits = SandboxLimits::default();
+        if let Some(memory_mb) = non_empty_env(SANDBOX_MEMORY_MB_ENV) {
+            limits.memory_mb = memory_mb
+                .parse()
+                .with_context(|| format!("invalid {}", SANDBOX_MEMORY_MB_ENV))?;
+        }
+        if let Some(timeout_secs) = non_empty_env(SANDBOX_TIMEOUT_SECS_ENV) {
+            limits.timeout_secs = timeout_secs
+                .parse()
+                .with_context(|| format!("invalid {}", SANDBOX_TIMEOUT_SECS_ENV))?;
+            limits.cpu_seconds = limits.timeout_secs;
+        }
+        Ok(Some(Self::new(binary).with_limits(limits)))
+    }
+
+    /// Ingest `repo_path` in the sandbox, then copy its documents into `storage` and its
+    /// symbol database and dependency graph into `db_path`
+    pub async fn ingest<S: Storage + ?Sized>(
+        &self,
+        config: &IngestionConfig,
+        repo_path: &Path,
+        storage: &mut S,
+        db_path: &Path,
+        extract_symbols: bool,
+        progress_callback: Option<ProgressCallback>,
+    ) -> Result<IngestResult> {
+        // Inside the database directory so artifacts can be renamed into place
+        std::fs::create_dir_all(db_path)?;
+        let output = tempfile::Builder::new()
+            .prefix(".ingestion-sandbox-")
+            .tempdir_in(db_path)
+            .context("Failed to create sandbox output directory")?;
+        let request = SandboxRequest {
+            repo_path: std::path::absolute(repo_path)?,
+            output_dir: output.path().to_path_buf(),
+            config: config.clone(),
+            extract_symbols,
+            limits: self.limits.clone(),
+        };
+
+        let timeout = Duration::from_secs(self.limits.timeout_secs);
+        let result = tokio::time::timeout(timeout, self.run(&request, progress_callback))
+            .await
+            .map_err(|_| {
+                anyhow::anyhow!(
+                    "Sandboxed ingestion exceeded {}s and was killed",
+                    timeout.as_secs()
+                )
+            })??;
+
+        import_output(output.path(), &config.path_prefix, storage, db_path).await?;
+        Ok(result)
+    }
+
+    async fn run(
+        &self,
+        request: &SandboxRequest,
+        progress_callback: Option<ProgressCallback>,
+    ) -> Result<IngestResult> {
+        let mut child = tokio::process::Command::new(&self.binary)
+            .env_clear()
+            .env("RUST_LOG", std::env::var("RUST_LOG").unwrap_or_default())
+            .current_dir(&request.output_dir)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .kill_on_drop(true)
+            .spawn()
+            .with_context(|| format!("Failed to start {}", self.binary.display()))?;
+        info!(pid = child.id(), repo = %request.repo_path.display(), "Started ingestion sandbox");
+
+        let mut stdin = child.stdin.take().context("Sandbox stdin unavailable")?;
+        stdin.write_all(&serde_json::to_vec(request)?).await?;
+        drop(stdin);
+
+        let stdout = child.stdout.take().context("Sandbox stdout unavailable")?;
+        let mut lines = BufReader::new(stdout.take(MAX_IPC_BYTES)).lines();
+        let mut outcome = None;
+        while let Some(line) = lines.next_line().await? {
+            match serde_json::from_str::<SandboxMessage>(&line) {
+                Ok(SandboxMessage::Progress { message }) => {
+                    if let Some(callback) = &progress_callback {
+                        callback(&message);
+                    }
+                }
+                Ok(message) => outcome = Some(message),
+                Err(e) => debug!("Ignoring sandbox output line: {}", e),
+            }
+        }
+
+        let status = child.wait().await?;
+        match outcome {
+            Some(SandboxMessage::Done { result }) if status.success() => Ok(result),
+            Some(SandboxMessage::Failed { error }) => {
+                bail!("Sandboxed ingestion failed: {}", error)
+            }
+            _ => bail!("Sandboxed ingestion exited abnormally ({})", status),
+        }
+    }
+}
+
+/// Copy the documents of the sandbox's scratch storage into `storage` and install its
+/// artifacts in `db_path`
+///
+/// The child is untrusted: nothing is imported unless every document lies under
+/// `path_prefix` and every artifact passes [`SandboxArtifacts::load`].
+async fn import_output<S: Storage + ?Sized>(
+    output_dir: &Path,
+    path_prefix: &str,
+    storage: &mut S,
+    db_path: &Path,
+) -> Result<()> {
+    let scratch_path = output_dir.join("storage");
+    let scratch = crate::file_storage::create_file_storage(
+        scratch_path.to_str().context("Non-UTF-8 sandbox path")?,
+        None,
+    )
+    .await
+    .context("Failed to open sandbox storage")?;
+    for metadata in scratch.list_metadata().await? {
+        let path = metadata.path.as_str();
+        if !within_prefix(path, path_prefix) {
+            bail!(
+                "Sandboxed ingestion produced document {} outside prefix {:?}; nothing was imported",
+                path,
+                path_prefix
+            );
+        }
+    }
+    let artifacts = SandboxArtifacts::load(output_dir)?;
+
+    let mut count = 0;
+    let mut cursor = None;
+    loop {
+        let page = scratch.list_page(cursor, IMPORT_PAGE_SIZE).await?;
+        for document in page.documents {
+            let path = document.path.to_string();
+            if let Err(e) = storage.insert(document).await {
+                warn!("Failed to import sandboxed document {}: {}", path, e);
+            }
+            count += 1;
+        }
+        match page.next {
+            Some(next) => cursor = Some(next),
+            None => break,
+        }
+    }
+
+    artifacts.install(db_path)?;
+    info!(documents = count, "Imported sandboxed ingestion output");
+    Ok(())
+}
+
+/// Whether the document `path` lies under the ingestion's path prefix
+fn within_prefix(path: &str, path_prefix: &str) -> bool {
+    let path = path.trim_start_matches('/');
+    let prefix = path_prefix.trim_matches('/');
+    is_relative_path(path)
+        && (prefix.is_empty()
+            || path
+                .strip_prefix(prefix)
+                .is_some_and(|rest| rest.starts_with('/')))
+}
+
+/// Whether `path` is relative and made only of plain components, without `..`
+fn is_relative_path(path: &str) -> bool {
+    !path.is_empty()
+        && Path::new(path)
+            .components()
+            .all(|component| matches!(component, std::path::Component::Normal(_)))
+}
+
+/// Artifacts of a sandboxed ingestion, decoded and checked
+///
+/// Only symbol extraction writes artifacts, so without tree-sitter there is only ever
+/// the crate map.
+#[derive(Default)]
+struct SandboxArtifacts {
+    #[cfg(feature = "tree-sitter-parsing")]
+    symbols: Option<BinarySymbolWriter>,
+    #[cfg(feature = "tree-sitter-parsing")]
+    graph: Option<SerializableDependencyGraph>,
+    crates: Option<CrateMap>,
+}
+
+impl SandboxArtifacts {
+    /// Decode the artifacts in `output_dir`, failing unless each one parses and records
+    /// only repository-relative file paths
+    fn load(output_dir: &Path) -> Result<Self> {
+        let crates = CrateMap::load(output_dir).context("Sandbox wrote an invalid crate map")?;
+        if let Some(krate) = crates
+            .iter()
+            .flat_map(|crates| &crates.crates)
+            .find(|krate| !krate.root.is_empty() && !is_relative_path(&krate.root))
+        {
+            bail!(
+                "Sandbox wrote crate {} at {:?}, outside the repository",
+                krate.name,
+                krate.root
+            );
+        }
+        Ok(Self {
+            #[cfg(feature = "tree-sitter-parsing")]
+            symbols: load_symbols(&output_dir.join(SYMBOLS_FILE))?,
+            #[cfg(feature = "tree-sitter-parsing")]
+            graph: load_graph(&output_dir.join(GRAPH_FILE))?,
+            crates,
+        })
+    }
+
+    /// Write the artifacts into `db_path`, each replacing its file in one rename
+    fn install(&self, db_path: &Path) -> Result<()> {
+        #[cfg(feature = "tree-sitter-parsing")]
+        {
+            if let Some(symbols) = &self.symbols {
+                let path = db_path.join(SYMBOLS_FILE);
+                let tmp = path.with_extension("kota.tmp");
+                symbols.write_to_file(&tmp)?;
+                std::fs::rename(&tmp, &path)
+                    .with_context(|| format!("Failed to move {} into place", SYMBOLS_FILE))?;
+            }
+            if let Some(graph) = &self.graph {
+                let path = db_path.join(GRAPH_FILE);
+                let tmp = path.with_extension("bin.tmp");
+                std::fs::write(&tmp, graph.to_bytes()?)?;
+                std::fs::rename(&tmp, &path)
+                    .with_context(|| format!("Failed to move {} into place", GRAPH_FILE))?;
+            }
+        }
+        if let Some(crates) = &self.crates {
+            crates.save(db_path)?;
+        }
+        Ok(())
+    }
+}
+
+/// Re-encode the sandbox's symbol database, so only symbols that decode are installed
+#[cfg(feature = "tree-sitter-parsing")]
+fn load_symbols(path: &Path) -> Result<Option<BinarySymbolWriter>> {
+    if !path.is_file() {
+        return Ok(None);
+    }
+    let reader =
+        BinarySymbolReader::open(path).context("Sandbox wrote an invalid symbol database")?;
+    let mut writer = BinarySymbolWriter::new();
+    for symbol in reader.iter_symbols() {
+        let name = reader.get_symbol_name(&symbol)?;
+        let file_path = reader.get_symbol_file_path(&symbol)?;
+        if !is_relative_path(&file_path) {
+            bail!(
+                "Sandbox wrote a symbol in {:?}, outside the repository",
+                file_path
+            );
+        }
+        let parent_id =
+            (symbol.parent_id != [0u8; 16]).then(|| uuid::Uuid::from_bytes(symbol.parent_id));
+        writer.add_symbol_with_range(
+            uuid::Uuid::from_bytes(symbol.id),
+            &name,
+            symbol.kind,
+            &file_path,
+            symbol.start_line,
+            symbol.end_line,
+            symbol.range(),
+            parent_id,
+        );
+    }
+    Ok(Some(writer))
+}
+
+#[cfg(feature = "tree-sitter-parsing")]
+fn load_graph(path: &Path) -> Result<Option<SerializableDependencyGraph>> {
+    if !path.is_file() {
+        return Ok(None);
+    }
+    let graph = SerializableDependencyGraph::from_bytes(&std::fs::read(path)?)
+        .context("Sandbox wrote an invalid dependency graph")?;
+    let paths = graph
+        .nodes
+        .iter()
+        .map(|node| &node.file_path)
+        .chain(graph.file_imports.keys());
+    for path in paths {
+        if !path.to_str().is_some_and(is_relative_path) {
+            bail!(
+                "Sandbox wrote a dependency graph entry for {}, outside the repository",
+                path.display()
+            );
+        }
+    }
+    Ok(Some(graph))
+}
+
+/// Entry point of `kotadb-index-sandbox`: read a request from stdin, confine the
+/// process and ingest
+pub fn run_child() -> Result<()> {
+    let mut input = String::new();
+    std::io::Read::read_to_string(&mut std::io::stdin(), &mut input)?;
+    let request: SandboxRequest =
+        serde_json::from_str(&input).context("Invalid sandbox request")?;
+
+    // Built before confining: the runtime's drivers need syscalls the filter denies
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+    let outcome = confine(&request.repo_path, &request.output_dir, &request.limits)
+        .and_then(|()| runtime.block_on(ingest_in_sandbox(&request)));
+    let message = match outcome {
+        Ok(result) => SandboxMessage::Done { result },
+        Err(e) => SandboxMessage::Failed {
+            error: format!("{:#}", e),
+        },
+    };
+    let failed = matches!(message, SandboxMessage::Failed { .. });
+    emit(&message);
+    if failed {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+async fn ingest_in_sandbox(request: &SandboxRequest) -> Result<IngestResult> {
+    let scratch_path = request.output_dir.join("storage");
+    let mut storage = crate::file_storage::create_file_storage(
+        scratch_path.to_str().context("Non-UTF-8 sandbox path")?,
+        None,
+    )
+    .await?;
+    let progress: ProgressCallback = Box::new(|message: &str| {
+        emit(&SandboxMessage::Progress {
+            message: message.to_string(),
+        })
+    });
+    let result = RepositoryIngester::new(request.config.clone())
+        .ingest_codebase(
+            &request.repo_path,
+            &mut storage,
+            &request.output_dir,
+            request.extract_symbols,
+            Some(progress),
+        )
+        .await?;
+    storage.flush().await?;
+    Ok(result)
+}
+
+fn emit(message: &SandboxMessage) {
+    use std::io::Write;
+    if let Ok(line) = serde_json::to_string(message) {
+        let mut stdout = std::io::stdout().lock();
+        let _ = writeln!(stdout, "{}", line);
+        let _ = stdout.flush();
+    }
+}
+
+fn non_empty_env(name: &str) -> Option<String> {
+    std::env::var(name)
+        .ok()
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+}
+
+/// Restrict the current process to what ingestion needs
+#[cfg(target_os = "linux")]
+pub fn confine(repo_path: &Path, output_dir: &Path, limits: &SandboxLimits) -> Result<()> {
+    linux::set_limits(limits)?;
+    // SAFETY: PR_SET_NO_NEW_PRIVS takes integer arguments only
+    if unsafe { libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) } != 0 {
+        bail!(
+            "Failed to set no_new_privs: {}",
+            std::io::Error::last_os_error()
+        );
+    }
+    linux::restrict_filesystem(repo_path, output_dir)?;
+    linux::install_seccomp_filter()
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn confine(_repo_path: &Path, _output_dir: &Path, _limits: &SandboxLimits) -> Result<()> {
+    bail!("Sandboxed ingestion is only supported on Linux")
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::Path;
+
+    use anyhow::{bail, Context, Result};
+
+    use super::SandboxLimits;
+
+    const MIB: u64 = 1024 * 1024;
+
+    pub(super) fn set_limits(limits: &SandboxLimits) -> Result<()> {
+        let rlimits = [
+            (libc::RLIMIT_AS, limits.memory_mb * MIB, "address space"),
+            (libc::RLIMIT_CPU, limits.cpu_seconds, "CPU time"),
+            (
+                libc::RLIMIT_FSIZE,
+                limits.max_file_size_mb * MIB,
+                "file size",
+            ),
+            (libc::RLIMIT_NOFILE, limits.max_open_files, "open files"),
+            (libc::RLIMIT_CORE, 0, "core dumps"),
+        ];
+        for (resource, value, name) in rlimits {
+            let limit = libc::rlimit {
+                rlim_cur: value as libc::rlim_t,
+                rlim_max: value as libc::rlim_t,
+            };
+            // SAFETY: `limit` is a valid rlimit that outlives the call
+            if unsafe { libc::setrlimit(resource, &limit) } != 0 {
+                bail!(
+                    "Failed to limit {}: {}",
+                    name,
+                    std::io::Error::last_os_error()
+                );
+            }
+        }
+        Ok(())
+    }
+
+    // Landlock ABI (linux/landlock.h)
+    const LANDLOCK_CREATE_RULESET_VERSION: u32 = 1;
+    const LANDLOCK_RULE_PATH_BENEATH: libc::c_int = 1;
+    const ACCESS_EXECUTE: u64 = 1 << 0;
+    const ACCESS_READ_FILE: u64 = 1 << 2;
+    const ACCESS_READ_DIR: u64 = 1 << 3;
+    /// Every filesystem access of Landlock ABI 1
+    const ACCESS_ABI_1: u64 = (1 << 13) - 1;
+    /// Truncation became controllable with ABI 3
+    const ACCESS_TRUNCATE: u64 = 1 << 14;
+
+    /// Where the dynamic loader and shared libraries live; missing ones are skipped
+    const RUNTIME_PATHS: &[&str] = &["/lib", "/lib64", "/usr/lib", "/usr/lib64"];
+
+    #[repr(C)]
+    struct RulesetAttr {
+        handled_access_fs: u64,
+    }
+
+    #[repr(C, packed)]
+    struct PathBeneathAttr {
+        allowed_access: u64,
+        parent_fd: i32,
+    }
+
+    /// Allow reading `repo_path` and the runtime libraries, reading and writing below
+    /// `output_dir`, and nothing else
+    pub(super) fn restrict_filesystem(repo_path: &Path, output_dir: &Path) -> Result<()> {
+        // SAFETY: a null attribute with size 0 and the version flag queries the ABI
+        let abi = unsafe {
+            libc::syscall(
+                libc::SYS_landlock_create_ruleset,
+                std::ptr::null::<RulesetAttr>(),
+                0usize,
+                LANDLOCK_CREATE_RULESET_VERSION,
+            )
+        };
+        if abi < 1 {
+            bail!(
+                "Landlock is unavailable ({}); sandboxed ingestion needs Linux 5.13+ with Landlock enabled",
+                std::io::Error::last_os_error()
+            );
+        }
+        let handled = if abi >= 3 {
+            ACCESS_ABI_1 | ACCESS_TRUNCATE
+        } else {
+            ACCESS_ABI_1
+        };
+        let attr = RulesetAttr {
+            handled_access_fs: handled,
+        };
+        // SAFETY: `attr` is a valid ruleset attribute of the given size
+        let ruleset = unsafe {
+            libc::syscall(
+                libc::SYS_landlock_create_ruleset,
+                &attr as *const RulesetAttr,
+                std::mem::size_of::<RulesetAttr>(),
+                0u32,
+            )
+        };
+        if ruleset < 0 {
+            bail!(
+                "Failed to create Landlock ruleset: {}",
+                std::io::Error::last_os_error()
+            );
+        }
+        let ruleset = ruleset as libc::c_int;
+
+        let result = RUNTIME_PATHS
+            .iter()
+            .map(Path::new)
+            .filter(|path| path.exists())
+            .try_for_each(|path| {
+                add_path_rule(
+                    ruleset,
+                    path,
+                    ACCESS_EXECUTE | ACCESS_READ_FILE | ACCESS_READ_DIR,
+                )
+            })
+            .and_then(|()| add_path_rule(ruleset, repo_path, ACCESS_READ_FILE | ACCESS_READ_DIR))
+            .and_then(|()| add_path_rule(ruleset, output_dir, handled))
+            .and_then(|()| {
+                // SAFETY: restricting ourselves with a ruleset fd we own
+                if unsafe { libc::syscall(libc::SYS_landlock_restrict_self, ruleset, 0u32) } != 0 {
+                    bail!(
+                        "Failed to enforce Landlock ruleset: {}",
+                        std::io::Error::last_os_error()
+                    );
+                }
+                Ok(())
+            });
+        // SAFETY: closing the ruleset fd created above
+        unsafe { libc::close(ruleset) };
+        result
+    }
+
+    fn add_path_rule(ruleset: libc::c_int, path: &Path, access: u64) -> Result<()> {
+        let c_path = CString::new(path.as_os_str().as_bytes())?;
+        // SAFETY: `c_path` is a valid NUL-terminated string
+        let fd = unsafe { libc::open(c_path.as_ptr(), libc::O_PATH | libc::O_CLOEXEC) };
+        if fd < 0 {
+            return Err(std::io::Error::last_os_error())
+                .with_context(|| format!("Failed to open {}", path.display()));
+        }
+        let rule = PathBeneathAttr {
+            allowed_access: access,
+            parent_fd: fd,
+        };
+        // SAFETY: `rule` is a valid path-beneath attribute that outlives the call
+        let added = unsafe {
+            libc::syscall(
+                libc::SYS_landlock_add_rule,
+                ruleset,
+                LANDLOCK_RULE_PATH_BENEATH,
+                &rule as *const PathBeneathAttr,
+                0u32,
+            )
+        };
+        let error = std::io::Error::last_os_error();
+        // SAFETY: closing the fd opened above
+        unsafe { libc::close(fd) };
+        if added != 0 {
+            return Err(error)
+                .with_context(|| format!("Failed to add Landlock rule for {}", path.display()));
+        }
+        Ok(())
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    const AUDIT_ARCH: u32 = 0xC000_003E;
+    #[cfg(target_arch = "aarch64")]
+    const AUDIT_ARCH: u32 = 0xC000_00B7;
+
+    /// Syscalls refused with `EPERM`: networking, running other programs, inspecting
+    /// other processes and changing the kernel or mount state
+    const DENIED_SYSCALLS: &[libc::c_long] = &[
+        libc::SYS_socket,
+        libc::SYS_socketpair,
+        libc::SYS_connect,
+        libc::SYS_bind,
+        libc::SYS_listen,
+        libc::SYS_accept,
+        libc::SYS_accept4,
+        libc::SYS_execve,
+        libc::SYS_execveat,
+        libc::SYS_ptrace,
+        libc::SYS_process_vm_readv,
+        libc::SYS_process_vm_writev,
+        libc::SYS_mount,
+        libc::SYS_umount2,
+        libc::SYS_pivot_root,
+        libc::SYS_chroot,
+        libc::SYS_unshare,
+        libc::SYS_setns,
+        libc::SYS_bpf,
+        libc::SYS_perf_event_open,
+        libc::SYS_userfaultfd,
+        libc::SYS_keyctl,
+        libc::SYS_add_key,
+        libc::SYS_request_key,
+        libc::SYS_init_module,
+        libc::SYS_finit_module,
+        libc::SYS_delete_module,
+        libc::SYS_kexec_load,
+        libc::SYS_reboot,
+        libc::SYS_swapon,
+        libc::SYS_swapoff,
+        libc::SYS_io_uring_setup,
+    ];
+
+    fn statement(code: u32, k: u32) -> libc::sock_filter {
+        libc::sock_filter {
+            code: code as u16,
+            jt: 0,
+            jf: 0,
+            k,
+        }
+    }
+
+    fn jump(k: u32, jt: u8, jf: u8) -> libc::sock_filter {
+        libc::sock_filter {
+            code: (libc::BPF_JMP | libc::BPF_JEQ | libc::BPF_K) as u16,
+            jt,
+            jf,
+            k,
+        }
+    }
+
+    #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+    pub(super) fn install_seccomp_filter() -> Result<()> {
+        // Offsets into struct seccomp_data
+        const NR_OFFSET: u32 = 0;
+        const ARCH_OFFSET: u32 = 4;
+        let load = libc::BPF_LD | libc::BPF_W | libc::BPF_ABS;
+        let ret = libc::BPF_RET | libc::BPF_K;
+
+        let mut program = vec![
+            statement(load, ARCH_OFFSET),
+            // Syscalls of another ABI would bypass the numbers below
+            jump(AUDIT_ARCH, 1, 0),
+            statement(ret, libc::SECCOMP_RET_KILL_PROCESS),
+            statement(load, NR_OFFSET),
+        ];
+        #[cfg(target_arch = "x86_64")]
+        {
+            // x32 syscalls share the architecture but set bit 30 of the number
+            program.push(libc::sock_filter {
+                code: (libc::BPF_JMP | libc::BPF_JGE | libc::BPF_K) as u16,
+                jt: 0,
+                jf: 1,
+                k: 0x4000_0000,
+            });
+            program.push(statement(ret, libc::SECCOMP_RET_KILL_PROCESS));
+        }
+        for &syscall in DENIED_SYSCALLS {
+            program.push(jump(syscall as u32, 0, 1));
+            program.push(statement(
+                ret,
+                libc::SECCOMP_RET_ERRNO | (libc::EPERM as u32 & libc::SECCOMP_RET_DATA),
+            ));
+        }
+        program.push(statement(ret, libc::SECCOMP_RET_ALLOW));
+
+        let fprog = libc::sock_fprog {
+            len: program.len() as u16,
+            filter: program.as_mut_ptr(),
+        };
+        // SAFETY: `fprog` points at `program`, which outlives the call; the kernel copies it
+        let installed = unsafe {
+            libc::syscall(
+                libc::SYS_seccomp,
+                libc::SECCOMP_SET_MODE_FILTER,
+                libc::SECCOMP_FILTER_FLAG_TSYNC,
+                &fprog as *const libc::sock_fprog,
+            )
+        };
+        if installed != 0 {
+            bail!(
+                "Failed to install seccomp filter: {}",
+                std::io::Error::last_os_error()
+            );
+        }
+        Ok(())
+    }
+
+    #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+    pub(super) fn install_seccomp_filter() -> Result<()> {
+        bail!("Sandboxed ingestion supports x86_64 and aarch64 only")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn messages_round_trip_as_tagged_json_lines() -> Result<()> {
+        let line = serde_json::to_string(&SandboxMessage::Done {
+            result: IngestResult {
+                files_ingested: 3,
+                symbols_extracted: 12,
+                ..IngestResult::default()
+            },
+        })?;
+        assert!(line.starts_with(r#"{"type":"done""#));
+        let SandboxMessage::Done { result } = serde_json::from_str(&line)? else {
+            panic!("expected done message");
+        };
+        assert_eq!((result.files_ingested, result.symbols_extracted), (3, 12));
+
+        let request = SandboxRequest {
+            repo_path: PathBuf::from("/work/repo"),
+            output_dir: PathBuf::from("/db/.ingestion-sandbox-x"),
+            config: IngestionConfig::default(),
+            extract_symbols: true,
+            limits: SandboxLimits::default(),
+        };
+        let decoded: SandboxRequest = serde_json::from_str(&serde_json::to_string(&request)?)?;
+        assert_eq!(decoded.limits, request.limits);
+        assert_eq!(decoded.config.path_prefix, "repos");
+        Ok(())
+    }
+
+    #[test]
+    fn documents_must_lie_under_the_prefix() {
+        assert!(within_prefix("repos/app/files/src/lib.rs", "repos"));
+        assert!(within_prefix("/repos/app/index.md", "/repos/"));
+        assert!(!within_prefix("reposx/app/index.md", "repos"));
+        assert!(!within_prefix("repos/../other/files/a.rs", "repos"));
+        assert!(!within_prefix("other/app/index.md", "repos"));
+        assert!(within_prefix("app/index.md", ""));
+    }
+
+    #[cfg(feature = "tree-sitter-parsing")]
+    #[tokio::test]
+    async fn import_is_rejected_when_any_output_fails_its_checks() -> Result<()> {
+        let output = tempfile::tempdir()?;
+        let db = tempfile::tempdir()?;
+        let mut scratch = crate::file_storage::create_file_storage(
+            output.path().join("storage").to_str().unwrap(),
+            None,
+        )
+        .await?;
+        for path in ["repos/app/files/src/lib.rs", "other/app/files/src/lib.rs"] {
+            scratch
+                .insert(
+                    crate::DocumentBuilder::new()
+                        .path(path)?
+                        .title("lib.rs")?
+                        .content(b"pub fn a() {}".to_vec())
+                        .build()?,
+                )
+                .await?;
+        }
+        scratch.flush().await?;
+        drop(scratch);
+        let mut storage = crate::file_storage::create_file_storage(
+            db.path().join("storage").to_str().unwrap(),
+            None,
+        )
+        .await?;
+
+        let error = import_output(output.path(), "repos", &mut storage, db.path())
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("other/app/files/src/lib.rs"));
+        assert!(storage.list_all().await?.is_empty());
+
+        // Every document is under an empty prefix, but the symbols point outside the repository
+        let mut symbols = BinarySymbolWriter::new();
+        symbols.add_symbol(uuid::Uuid::new_v4(), "a", 1, "/etc/passwd", 1, 1, None);
+        symbols.write_to_file(&output.path().join(SYMBOLS_FILE))?;
+        assert!(import_output(output.path(), "", &mut storage, db.path())
+            .await
+            .is_err());
+        assert!(storage.list_all().await?.is_empty());
+        assert!(!db.path().join(SYMBOLS_FILE).exists());
+
+        let mut symbols = BinarySymbolWriter::new();
+        symbols.add_symbol(uuid::Uuid::new_v4(), "a", 1, "src/lib.rs", 1, 1, None);
+        symbols.write_to_file(&output.path().join(SYMBOLS_FILE))?;
+        import_output(output.path(), "", &mut storage, db.path()).await?;
+        assert_eq!(storage.list_all().await?.len(), 2);
+        let installed = BinarySymbolReader::open(&db.path().join(SYMBOLS_FILE))?;
+        assert_eq!(installed.symbol_count(), 1);
+        Ok(())
+    }
+}
@@ -0,0 +1,222 @@
+//! On-disk leaf pages of the primary index
+//!
+//! `data/btree_leaves.bin` stores the entries of the primary index B+ tree as the leaf
+//! level of a bulk-loaded tree: fixed-size pages of up to `btree::MAX_KEYS` entries,
+//! written back to back in key order so sibling leaves are contiguous on disk, followed
+//! by the paths of all entries in the same order. Pages are padded to whole cache lines.
+//! Loading reads the file front to back (with sequential read-ahead requested from the
+//! kernel) and rebuilds the tree bottom-up from the already sorted entries, which also
+//! allocates sibling leaves next to each other in memory.
+
+use anyhow::{bail, ensure, Context, Result};
+use std::path::Path;
+use uuid::Uuid;
+
+use crate::pure::btree::MAX_KEYS;
+use crate::types::{ValidatedDocumentId, ValidatedPath};
+
+/// File name of the leaf pages within the index `data` directory
+pub const LEAF_PAGES_FILE: &str = "btree_leaves.bin";
+
+const MAGIC: &[u8; 8] = b"KOTALEAF";
+pub(crate) const VERSION: u32 = 1;
+const CACHE_LINE: usize = 64;
+const HEADER_SIZE: usize = CACHE_LINE;
+const PAGE_HEADER_SIZE: usize = 8;
+/// Key (16 bytes), path offset and path length (4 bytes each)
+const ENTRY_SIZE: usize = 24;
+
+/// Size of a page holding `entries_per_page` entries, rounded up to whole cache lines
+fn page_size(entries_per_page: usize) -> usize {
+    (PAGE_HEADER_SIZE + entries_per_page * ENTRY_SIZE).div_ceil(CACHE_LINE) * CACHE_LINE
+}
+
+/// Encode entries, sorted by key, as leaf pages
+pub fn encode(pairs: &[(ValidatedDocumentId, ValidatedPath)]) -> Result<Vec<u8>> {
+    let page_size = page_size(MAX_KEYS);
+    let page_count = pairs.len().div_ceil(MAX_KEYS);
+    let paths_offset = HEADER_SIZE + page_count * page_size;
+    let paths_len: usize = pairs.iter().map(|(_, path)| path.as_str().len()).sum();
+    ensure!(
+        paths_len <= u32::MAX as usize,
+        "Primary index paths exceed the leaf page format limit"
+    );
+
+    let mut bytes = Vec::with_capacity(paths_offset + paths_len);
+    bytes.extend_from_slice(MAGIC);
+    bytes.extend_from_slice(&VERSION.to_le_bytes());
+    bytes.extend_from_slice(&(MAX_KEYS as u32).to_le_bytes());
+    bytes.extend_from_slice(&(page_size as u32).to_le_bytes());
+    bytes.extend_from_slice(&[0u8; 4]);
+    bytes.extend_from_slice(&(page_count as u64).to_le_bytes());
+    bytes.extend_from_slice(&(pairs.len() as u64).to_le_bytes());
+    bytes.extend_from_slice(&(paths_offset as u64).to_le_bytes());
+    bytes.extend_from_slice(&(paths_len as u64).to_le_bytes());
+    bytes.resize(HEADER_SIZE, 0);
+
+    let mut path_offset = 0u32;
+    for page in pairs.chunks(MAX_KEYS) {
+        let page_start = bytes.len();
+        bytes.extend_from_slice(&(page.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&[0u8; 4]);
+        for (id, path) in page {
+            let len = path.as_str().len() as u32;
+            bytes.extend_from_slice(id.as_uuid().as_bytes());
+            bytes.extend_from_slice(&path_offset.to_le_bytes());
+            bytes.extend_from_slice(&len.to_le_bytes());
+            path_offset += len;
+        }
+        bytes.resize(page_start + page_size, 0);
+    }
+
+    for (_, path) in pairs {
+        bytes.extend_from_slice(path.as_str().as_bytes());
+    }
+    Ok(bytes)
+}
+
+/// Decode leaf pages into entries in key order
+pub fn decode(bytes: &[u8]) -> Result<Vec<(ValidatedDocumentId, ValidatedPath)>> {
+    ensure!(
+        bytes.len() >= HEADER_SIZE && &bytes[..8] == MAGIC,
+        "Invalid B+ tree leaf pages: missing header"
+    );
+    let u32_at = |at: usize| u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap()) as usize;
+    let u64_at = |at: usize| u64::from_le_bytes(bytes[at..at + 8].try_into().unwrap()) as usize;
+
+    let version = u32_at(8) as u32;
+    if version != VERSION {
+        bail!("Unsupported B+ tree leaf page version {version}");
+    }
+    let entries_per_page = u32_at(12);
+    let page_size = u32_at(16);
+    let (page_count, entry_count) = (u64_at(24), u64_at(32));
+    let (paths_offset, paths_len) = (u64_at(40), u64_at(48));
+    ensure!(
+        page_size == self::page_size(entries_per_page)
+            && page_count
+                .checked_mul(page_size)
+                .and_then(|pages| pages.checked_add(HEADER_SIZE))
+                == Some(paths_offset)
+            && paths_offset.checked_add(paths_len) == Some(bytes.len()),
+        "Invalid B+ tree leaf pages: inconsistent header"
+    );
+    let paths = &bytes[paths_offset..];
+
+    let mut pairs = Vec::with_capacity(entry_count);
+    for page in bytes[HEADER_SIZE..paths_offset].chunks_exact(page_size) {
+        let count = u32::from_le_bytes(page[..4].try_into().unwrap()) as usize;
+        ensure!(
+            count <= entries_per_page,
+            "Invalid B+ tree leaf pages: page holds {count} entries"
+        );
+        let (entries, _) = page[PAGE_HEADER_SIZE..].as_chunks::<ENTRY_SIZE>();
+        for entry in entries.iter().take(count) {
+            let uuid = Uuid::from_slice(&entry[..16])?;
+            let offset = u32::from_le_bytes(entry[16..20].try_into().unwrap()) as usize;
+            let len = u32::from_le_bytes(entry[20..24].try_into().unwrap()) as usize;
+            let path = paths
+                .get(offset..offset + len)
+                .context("Invalid B+ tree leaf pages: path out of bounds")?;
+            let path = std::str::from_utf8(path)
+                .with_context(|| format!("Invalid path in B+ tree leaf pages for {uuid}"))?;
+
+            let id = ValidatedDocumentId::from_uuid(uuid)
+                .with_context(|| format!("Invalid document ID in B+ tree leaf pages: {uuid}"))?;
+            if let Some((previous, _)) = pairs.last() {
+                ensure!(
+                    *previous < id,
+                    "Invalid B+ tree leaf pages: keys out of order at {uuid}"
+                );
+            }
+            let path = ValidatedPath::new(path)
+                .with_context(|| format!("Invalid path in B+ tree leaf pages: {path}"))?;
+            pairs.push((id, path));
+        }
+    }
+    ensure!(
+        pairs.len() == entry_count,
+        "Invalid B+ tree leaf pages: expected {entry_count} entries, found {}",
+        pairs.len()
+    );
+    Ok(pairs)
+}
+
+/// Read the leaf pages at `path`
+///
+/// The file is memory-mapped and the kernel is asked to read it ahead sequentially, so
+/// the pages stream in while earlier ones are decoded.
+pub fn read(path: &Path) -> Result<Vec<(ValidatedDocumentId, ValidatedPath)>> {
+    let file = std::fs::File::open(path)
+        .with_context(|| format!("Failed to open B+ tree leaf pages: {}", path.display()))?;
+    let mmap = unsafe {
+        memmap2::MmapOptions::new()
+            .map(&file)
+            .with_context(|| format!("Failed to map B+ tree leaf pages: {}", path.display()))?
+    };
+    #[cfg(unix)]
+    {
+        // Read-ahead hints only; decoding works the same without them
+        let _ = mmap.advise(memmap2::Advice::Sequential);
+        let _ = mmap.advise(memmap2::Advice::WillNeed);
+    }
+    decode(&mmap)
+}
+
+/// Write entries, sorted by key, as leaf pages at `path`, replacing it atomically
+pub fn write(path: &Path, pairs: &[(ValidatedDocumentId, ValidatedPath)]) -> Result<()> {
+    let tmp = path.with_extension("bin.tmp");
+    std::fs::write(&tmp, encode(pairs)?)
+        .with_context(|| format!("Failed to write B+ tree leaf pages: {}", tmp.display()))?;
+    std::fs::rename(&tmp, path)
+        .with_context(|| format!("Failed to replace B+ tree leaf pages: {}", path.display()))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pairs(count: usize) -> Vec<(ValidatedDocumentId, ValidatedPath)> {
+        let mut pairs: Vec<_> = (0..count)
+            .map(|i| {
+                (
+                    ValidatedDocumentId::from_uuid(Uuid::new_v4()).unwrap(),
+                    ValidatedPath::new(format!("src/module_{i}.rs")).unwrap(),
+                )
+            })
+            .collect();
+        pairs.sort_by_key(|(id, _)| *id);
+        pairs
+    }
+
+    #[test]
+    fn round_trips_entries_in_cache_line_pages() -> Result<()> {
+        let pairs = pairs(23);
+        let bytes = encode(&pairs)?;
+
+        let page_size = page_size(MAX_KEYS);
+        assert_eq!(page_size % CACHE_LINE, 0);
+        let paths_len: usize = pairs.iter().map(|(_, p)| p.as_str().len()).sum();
+        assert_eq!(
+            bytes.len(),
+            HEADER_SIZE + 23usize.div_ceil(MAX_KEYS) * page_size + paths_len
+        );
+        assert_eq!(decode(&bytes)?, pairs);
+        assert!(decode(&encode(&[])?)?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_truncated_and_unordered_pages() -> Result<()> {
+        let mut pairs = pairs(8);
+        let bytes = encode(&pairs)?;
+        let error = decode(&bytes[..bytes.len() - 1]).unwrap_err();
+        assert!(error.to_string().contains("inconsistent header"), "{error}");
+
+        pairs.swap(0, 1);
+        let error = decode(&encode(&pairs)?).unwrap_err();
+        assert!(error.to_string().contains("keys out of order"), "{error}");
+        Ok(())
+    }
+}
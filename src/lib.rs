@@ -1,25 +1,45 @@
 // KotaDB - A Custom Database for Distributed Cognition
 // Root library module
 
+pub mod annotations;
 pub mod api_keys;
 pub mod auth_middleware;
 pub mod binary_trigram_index;
 pub mod builders;
+pub mod cargo_workspace;
 pub mod codebase_intelligence_api;
+pub mod compatibility;
 pub mod connection_pool;
 pub mod contracts;
 pub mod coordinated_deletion;
+pub mod corpora;
+pub mod coverage;
+pub mod crash_report;
 pub mod database;
+pub mod db_lock;
+pub mod did_you_mean;
 pub mod documentation_verification;
 pub mod embedding_transformer;
 pub mod embeddings;
+#[cfg(feature = "failure-injection")]
+pub mod failure_injection;
 pub mod file_storage;
+pub mod fixture_generator;
+pub mod function_similarity;
+pub mod gate;
+pub mod github_checks;
 pub mod graph_storage;
 pub mod http_server;
 pub mod http_types;
 pub mod hybrid_storage;
+pub mod identifier_tokenization;
+pub mod index_rebuild;
+pub mod index_snapshots;
+pub mod ingestion_sandbox;
 pub mod intent_mcp_server;
+pub mod leaf_pages;
 pub mod llm_search;
+pub mod maintenance;
 #[cfg(feature = "mcp-server")]
 pub mod mcp_api_mapper;
 pub mod mcp_http_bridge;
@@ -28,17 +48,30 @@ pub mod metrics;
 pub mod native_graph_storage;
 pub mod observability;
 pub mod primary_index;
+pub mod project_init;
+pub mod project_metadata;
 pub mod pure;
+pub mod query_deadline;
 pub mod query_sanitization;
+pub mod regression;
+pub mod replay;
+pub mod repository_links;
+pub mod response_limits;
 pub mod search_validation;
+pub mod self_update;
 pub mod semantic_search;
 pub mod services;
 pub mod services_http_server;
+pub mod signed_urls;
 pub mod supabase_repository;
+pub mod synonyms;
+pub mod telemetry;
+pub mod trends;
 pub mod trigram_index;
 pub mod types;
 pub mod validation;
 pub mod vector_index;
+pub mod warmup;
 pub mod wrappers;
 
 // Git integration module
@@ -64,10 +97,54 @@ pub mod binary_relationship_bridge;
 #[cfg(feature = "tree-sitter-parsing")]
 pub mod symbol_index;
 
+// Error type construction, conversion, handling and escape analysis
+#[cfg(feature = "tree-sitter-parsing")]
+pub mod error_flows;
+
+// Embedded SQL statements and the tables and columns they use
+#[cfg(feature = "tree-sitter-parsing")]
+pub mod sql_usages;
+
+// Configuration keys set in config files and read in code
+#[cfg(feature = "tree-sitter-parsing")]
+pub mod config_usages;
+
+// Feature flag usage map: cfg features, environment reads and flag SDK calls
+#[cfg(feature = "tree-sitter-parsing")]
+pub mod feature_flags;
+
+// Log macro format strings linked to their emitting symbols
+#[cfg(feature = "tree-sitter-parsing")]
+pub mod log_statements;
+
+// Reviewer suggestions for a diff from CODEOWNERS, commit history and blame
+#[cfg(feature = "tree-sitter-parsing")]
+pub mod review_routing;
+
+// Symbol size, complexity and callers across index snapshots
+#[cfg(feature = "tree-sitter-parsing")]
+pub mod symbol_evolution;
+
+// Streaming export of the symbol table for external mirrors
+#[cfg(feature = "tree-sitter-parsing")]
+pub mod symbol_export;
+
+// Definition, callers and source of one symbol in a single token-budgeted brief
+#[cfg(feature = "tree-sitter-parsing")]
+pub mod symbol_brief;
+
+// Locations a symbol rename has to edit, with mentions that cannot be verified
+#[cfg(feature = "tree-sitter-parsing")]
+pub mod rename_preview;
+
 // Dependency extraction and call graph building
 #[cfg(feature = "tree-sitter-parsing")]
 pub mod dependency_extractor;
 
+// Observed call counts from runtime traces, attached to the dependency graph
+#[cfg(feature = "tree-sitter-parsing")]
+pub mod runtime_traces;
+
 // Relationship query interface for dependency graph navigation
 #[cfg(feature = "tree-sitter-parsing")]
 pub mod relationship_query;
@@ -87,13 +164,24 @@ pub mod path_utils;
 #[cfg(feature = "tree-sitter-parsing")]
 pub mod factory;
 
+// Git hooks summarising what commits and pushes change in the dependency graph
+#[cfg(feature = "tree-sitter-parsing")]
+pub mod git_hooks;
+
+// JSON-over-stdio protocol serving per-symbol impact hints to editor extensions
+#[cfg(feature = "tree-sitter-parsing")]
+pub mod editor_protocol;
+
 // Re-export key types
 pub use observability::{
     init_logging, init_logging_with_level, log_operation, record_metric, with_trace_id, MetricType,
     Operation,
 };
 
-pub use contracts::{Document, Index, PageId, Query, Storage, StorageMetrics, Transaction};
+pub use contracts::{
+    ContentTokenizer, Document, DocumentFilters, DocumentMetadata, DocumentPage, DocumentSummary,
+    Index, PageId, Query, Storage, StorageMetrics, TokenizedDocument, Transaction,
+};
 
 // Re-export validated types
 pub use types::{
@@ -108,14 +196,14 @@ pub use builders::{
 
 // Re-export wrappers
 pub use wrappers::{
-    create_wrapped_storage, CachedStorage, MeteredIndex, RetryableStorage, TracedStorage,
-    ValidatedStorage,
+    create_wrapped_storage, CachedStorage, ErrorClass, MeteredIndex, ReadOnlyIndex,
+    ReadOnlyStorage, RetryPolicy, RetryStats, RetryableStorage, TracedStorage, ValidatedStorage,
 };
 
 // Re-export optimization wrappers
 pub use wrappers::optimization::{
-    create_optimized_index, create_optimized_index_with_defaults, OptimizationConfig,
-    OptimizationReport, OptimizedIndex,
+    create_optimized_index, create_optimized_index_with_defaults, AppliedOptimization,
+    OptimizationConfig, OptimizationReport, OptimizedIndex,
 };
 
 // Re-export storage implementations
@@ -179,8 +267,8 @@ pub use http_server::start_saas_server;
 
 // Re-export services HTTP server (clean architecture - preferred for new usage)
 pub use services_http_server::{
-    create_services_saas_server, create_services_server, start_services_saas_server,
-    start_services_server,
+    create_services_demo_server, create_services_saas_server, create_services_server,
+    start_services_demo_server, start_services_saas_server, start_services_server, DemoConfig,
 };
 
 // Re-export shared HTTP types
@@ -232,6 +320,7 @@ pub use llm_search::{
 // Re-export bulk operations
 pub use pure::{
     analyze_tree_structure, bulk_delete_from_tree, bulk_insert_into_tree, count_entries,
+    rebuild_tree,
 };
 
 // Re-export contracts
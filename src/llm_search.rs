@@ -161,6 +161,10 @@ pub struct LLMSearchResult {
     /// Context and metadata
     pub context_info: ContextInfo,
     pub metadata: HashMap<String, serde_json::Value>,
+
+    /// Commit-pinned link to the file on its forge, when the repository's remote is known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub web_url: Option<String>,
 }
 
 /// Token usage and optimization statistics
@@ -231,6 +235,17 @@ pub struct LLMSearchEngine {
     context_config: ContextConfig,
 }
 
+/// Approximate how many LLM tokens `content` occupies
+pub fn estimate_tokens(content: &str) -> usize {
+    // Better approximation using word count and punctuation
+    // Average English word ≈ 1.3 tokens, punctuation adds tokens
+    let word_count = content.split_whitespace().count();
+    let punctuation_count = content.chars().filter(|c| c.is_ascii_punctuation()).count();
+
+    // Formula: words * 1.3 + punctuation * 0.3
+    ((word_count as f32 * 1.3) + (punctuation_count as f32 * 0.3)) as usize
+}
+
 impl LLMSearchEngine {
     /// Create a new LLM search engine with default configuration
     pub fn new() -> Self {
@@ -407,6 +422,7 @@ impl LLMSearchEngine {
             estimated_tokens,
             context_info,
             metadata: HashMap::new(),
+            web_url: None,
         })
     }
 
@@ -980,13 +996,7 @@ impl LLMSearchEngine {
 
     /// Estimate token count for content (improved approximation)
     fn estimate_token_count(&self, content: &str) -> usize {
-        // Better approximation using word count and punctuation
-        // Average English word ≈ 1.3 tokens, punctuation adds tokens
-        let word_count = content.split_whitespace().count();
-        let punctuation_count = content.chars().filter(|c| c.is_ascii_punctuation()).count();
-
-        // Formula: words * 1.3 + punctuation * 0.3
-        ((word_count as f32 * 1.3) + (punctuation_count as f32 * 0.3)) as usize
+        estimate_tokens(content)
     }
 
     /// Optimize results for context window constraints
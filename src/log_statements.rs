@@ -0,0 +1,436 @@
+//! Log statements linked to the code that emits them
+//!
+//! After `index-codebase` extracts symbols, the message format strings of `log` and
+//! `tracing` macros (`info!`, `warn!`, `tracing::error!`, `event!`, ...) in indexed Rust
+//! files are stored with their file, line, level and enclosing function as
+//! `log_statements.json` in the database directory. `kotadb locate-log` matches an observed
+//! log line against them: the literal parts of a format string must appear in the line in
+//! order, so timestamps, targets and structured fields around the message, and the values
+//! substituted for `{}` placeholders, don't get in the way.
+
+use anyhow::{Context, Result};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::binary_symbols::FunctionRanges;
+use crate::contracts::Document;
+use crate::pure::metadata::strip_frontmatter;
+
+/// File name of the log statement index inside a database directory
+pub const LOG_INDEX_FILE: &str = "log_statements.json";
+
+/// Fewest literal characters a format string needs to be matched; `"{}"` or `"{:?}"` alone
+/// would match any line
+const MIN_LITERAL_CHARS: usize = 4;
+
+static LOG_MACRO: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"\b(?:(?:log|tracing)::)?(trace|debug|info|warn|error|event)!\s*[(\[{]")
+        .expect("valid log macro pattern")
+});
+static EVENT_LEVEL: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"Level::([A-Z]+)").expect("valid level pattern"));
+
+/// A log macro invocation and the message it formats
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LogStatement {
+    pub file_path: String,
+    /// 1-based line of the macro
+    pub line: usize,
+    /// trace, debug, info, warn or error
+    pub level: String,
+    /// Message format string, unescaped
+    pub format: String,
+    /// Function or method containing the statement
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub symbol: Option<String>,
+}
+
+impl LogStatement {
+    /// Literal parts of the format string between placeholders, unescaping `{{` and `}}`
+    pub fn literals(&self) -> Vec<String> {
+        let mut literals = vec![String::new()];
+        let mut chars = self.format.chars().peekable();
+        while let Some(c) = chars.next() {
+            match (c, chars.peek()) {
+                ('{', Some('{')) | ('}', Some('}')) => {
+                    chars.next();
+                    literals.last_mut().expect("never empty").push(c);
+                }
+                ('{', _) => {
+                    for c in chars.by_ref() {
+                        if c == '}' {
+                            break;
+                        }
+                    }
+                    literals.push(String::new());
+                }
+                _ => literals.last_mut().expect("never empty").push(c),
+            }
+        }
+        literals.retain(|literal| !literal.is_empty());
+        literals
+    }
+
+    /// Literal characters of the format string found in `message`, in order, or `None`
+    /// when a literal part is missing
+    pub fn match_len(&self, message: &str) -> Option<usize> {
+        let mut rest = message;
+        let mut matched = 0;
+        for literal in self.literals() {
+            let start = rest.find(&literal)?;
+            rest = &rest[start + literal.len()..];
+            matched += literal.chars().count();
+        }
+        (matched >= MIN_LITERAL_CHARS).then_some(matched)
+    }
+}
+
+/// A log statement that could have emitted an observed line
+#[derive(Debug, Clone, Serialize)]
+pub struct LogMatch {
+    #[serde(flatten)]
+    pub statement: LogStatement,
+    /// Characters of the observed line matched by the format string's literal parts
+    pub matched_chars: usize,
+}
+
+/// Log statements of the indexed codebase
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LogIndex {
+    /// RFC 3339 time the index was built
+    pub indexed_at: String,
+    pub statements: Vec<LogStatement>,
+}
+
+impl LogIndex {
+    /// Extract log statements from Rust source files, attributing each to the function
+    /// containing it
+    pub fn build<'a>(
+        files: impl IntoIterator<Item = (&'a str, &'a str)>,
+        functions: &FunctionRanges,
+    ) -> Self {
+        let mut statements = Vec::new();
+        for (path, content) in files {
+            if !path.ends_with(".rs") {
+                continue;
+            }
+            for (line, level, format) in extract_rust(content) {
+                statements.push(LogStatement {
+                    file_path: path.to_string(),
+                    line,
+                    level,
+                    format,
+                    symbol: functions.enclosing(path, line).map(str::to_string),
+                });
+            }
+        }
+
+        Self {
+            indexed_at: chrono::Utc::now().to_rfc3339(),
+            statements,
+        }
+    }
+
+    /// Location of the log statement index for a database directory
+    pub fn path_for(db_path: &Path) -> PathBuf {
+        db_path.join(LOG_INDEX_FILE)
+    }
+
+    /// The log statement index of a database, if one was built
+    pub fn load(db_path: &Path) -> Result<Option<Self>> {
+        let path = Self::path_for(db_path);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read log statements: {}", path.display()))?;
+        let index = serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse log statements: {}", path.display()))?;
+        Ok(Some(index))
+    }
+
+    /// Save the index atomically into a database directory
+    pub fn save(&self, db_path: &Path) -> Result<()> {
+        std::fs::create_dir_all(db_path)
+            .with_context(|| format!("Failed to create {}", db_path.display()))?;
+        let path = Self::path_for(db_path);
+        let tmp_path = path.with_extension("json.tmp");
+        std::fs::write(&tmp_path, serde_json::to_string(self)?)
+            .with_context(|| format!("Failed to write log statements: {}", tmp_path.display()))?;
+        std::fs::rename(&tmp_path, &path)
+            .with_context(|| format!("Failed to replace log statements: {}", path.display()))?;
+        Ok(())
+    }
+
+    /// Statements that could have emitted `message`, most literal characters matched
+    /// first; statements at the line's level win ties
+    pub fn locate(&self, message: &str, limit: usize) -> Vec<LogMatch> {
+        let message = message.trim();
+        let line_level = observed_level(message);
+        let mut matches: Vec<LogMatch> = self
+            .statements
+            .iter()
+            .filter_map(|statement| {
+                Some(LogMatch {
+                    matched_chars: statement.match_len(message)?,
+                    statement: statement.clone(),
+                })
+            })
+            .collect();
+        matches.sort_by(|a, b| {
+            let level_match = |m: &LogMatch| Some(m.statement.level.as_str()) == line_level;
+            b.matched_chars
+                .cmp(&a.matched_chars)
+                .then_with(|| level_match(b).cmp(&level_match(a)))
+                .then_with(|| a.statement.file_path.cmp(&b.statement.file_path))
+                .then_with(|| a.statement.line.cmp(&b.statement.line))
+        });
+        matches.truncate(limit);
+        matches
+    }
+}
+
+/// Build and save the log statement index from stored documents and the symbol database
+pub fn index_log_statements(db_path: &Path, documents: &[Document]) -> Result<LogIndex> {
+    let functions = FunctionRanges::load(&db_path.join("symbols.kota"))?;
+    let contents: Vec<(&str, String)> = documents
+        .iter()
+        .filter(|d| d.path.as_str().ends_with(".rs"))
+        .map(|d| {
+            (
+                d.path.as_str(),
+                strip_frontmatter(&String::from_utf8_lossy(&d.content)).to_string(),
+            )
+        })
+        .collect();
+    let index = LogIndex::build(
+        contents
+            .iter()
+            .map(|(path, content)| (*path, content.as_str())),
+        &functions,
+    );
+    index.save(db_path)?;
+    Ok(index)
+}
+
+/// Level named in an observed line, e.g. `WARN` or `[error]`
+fn observed_level(message: &str) -> Option<&'static str> {
+    message
+        .split(|c: char| !c.is_ascii_alphabetic())
+        .find_map(|word| match word.to_ascii_lowercase().as_str() {
+            "trace" => Some("trace"),
+            "debug" => Some("debug"),
+            "info" => Some("info"),
+            "warn" | "warning" => Some("warn"),
+            "error" => Some("error"),
+            _ => None,
+        })
+}
+
+/// `(line, level, format)` of each log macro with a message in Rust source
+fn extract_rust(content: &str) -> Vec<(usize, String, String)> {
+    let mut statements = Vec::new();
+    for captures in LOG_MACRO.captures_iter(content) {
+        let whole = captures.get(0).expect("match");
+        let line_start = content[..whole.start()].rfind('\n').map_or(0, |i| i + 1);
+        if content[line_start..whole.start()].contains("//") {
+            continue;
+        }
+        let Some((format, args)) = message_literal(&content[whole.end()..]) else {
+            continue;
+        };
+        let level = match &captures[1] {
+            "event" => match EVENT_LEVEL.captures(args) {
+                Some(level) => level[1].to_lowercase(),
+                None => continue,
+            },
+            level => level.to_string(),
+        };
+        let line = content[..whole.start()].matches('\n').count() + 1;
+        statements.push((line, level, format));
+    }
+    statements
+}
+
+/// First string literal among a macro's arguments that is neither a `target:` nor a
+/// `field = "value"`, with the arguments preceding it
+fn message_literal(args: &str) -> Option<(String, &str)> {
+    let bytes = args.as_bytes();
+    let mut depth = 0usize;
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'(' | b'[' | b'{' => depth += 1,
+            b')' | b']' | b'}' if depth == 0 => return None,
+            b')' | b']' | b'}' => depth -= 1,
+            b'\'' => {
+                // Char literals; lifetimes have no closing quote within three bytes
+                if bytes.get(i + 1) == Some(&b'\\') {
+                    i += 3;
+                } else if bytes.get(i + 2) == Some(&b'\'') {
+                    i += 2;
+                }
+            }
+            b'"' | b'r' if is_literal_start(bytes, i) => {
+                let (literal, end) = read_literal(args, i)?;
+                let before = args[..i].trim_end();
+                let is_value = before.ends_with('=') || before.ends_with("target:");
+                if depth == 0 && !is_value {
+                    return Some((literal, &args[..i]));
+                }
+                i = end;
+                continue;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    None
+}
+
+fn is_literal_start(bytes: &[u8], i: usize) -> bool {
+    if bytes[i] == b'"' {
+        return true;
+    }
+    // r"..." or r#"..."#, not the end of an identifier
+    let preceded_by_ident = i > 0 && (bytes[i - 1].is_ascii_alphanumeric() || bytes[i - 1] == b'_');
+    let mut j = i + 1;
+    while bytes.get(j) == Some(&b'#') {
+        j += 1;
+    }
+    !preceded_by_ident && bytes.get(j) == Some(&b'"')
+}
+
+/// Unescaped contents of the literal starting at `start`, and the index just past it
+fn read_literal(source: &str, start: usize) -> Option<(String, usize)> {
+    let bytes = source.as_bytes();
+    if bytes[start] == b'r' {
+        let hashes = bytes[start + 1..]
+            .iter()
+            .take_while(|b| **b == b'#')
+            .count();
+        let body_start = start + 1 + hashes + 1;
+        let terminator = format!("\"{}", "#".repeat(hashes));
+        let len = source[body_start..].find(&terminator)?;
+        return Some((
+            source[body_start..body_start + len].to_string(),
+            body_start + len + terminator.len(),
+        ));
+    }
+
+    let mut literal = String::new();
+    let mut chars = source[start + 1..].char_indices();
+    while let Some((offset, c)) = chars.next() {
+        match c {
+            '"' => return Some((literal, start + 1 + offset + 1)),
+            '\\' => match chars.next()?.1 {
+                'n' => literal.push('\n'),
+                't' => literal.push('\t'),
+                'r' => literal.push('\r'),
+                '0' => literal.push('\0'),
+                '\n' | '\r' => {
+                    // Line continuation skips the next line's indentation
+                    let rest = chars.as_str();
+                    let skipped = rest.len() - rest.trim_start().len();
+                    for _ in rest[..skipped].chars() {
+                        chars.next();
+                    }
+                }
+                escaped => literal.push(escaped),
+            },
+            _ => literal.push(c),
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = r##"
+fn connect(addr: &str) {
+    // info!("commented out {}", addr);
+    tracing::warn!(
+        target: "net",
+        attempt = 3,
+        peer = "primary",
+        "Connection to {} failed after {attempt} attempts, \
+         retrying in {:?}",
+        addr,
+        delay
+    );
+    info!("Connected to {addr} ({{pool}})");
+}
+
+fn shutdown() {
+    event!(Level::ERROR, code = 2, r#"Shutdown "forced""#);
+    debug!("{}", state);
+}
+"##;
+
+    fn index() -> LogIndex {
+        LogIndex::build(
+            [("src/net.rs", SOURCE)],
+            &FunctionRanges::from_ranges([
+                ("connect".to_string(), "./src/net.rs".to_string(), 2, 14),
+                ("shutdown".to_string(), "src/net.rs".to_string(), 16, 19),
+            ]),
+        )
+    }
+
+    #[test]
+    fn extracts_messages_of_log_and_tracing_macros() {
+        let statements = index().statements;
+        let summary: Vec<(usize, &str, &str, Option<&str>)> = statements
+            .iter()
+            .map(|s| {
+                (
+                    s.line,
+                    s.level.as_str(),
+                    s.format.as_str(),
+                    s.symbol.as_deref(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (
+                    4,
+                    "warn",
+                    "Connection to {} failed after {attempt} attempts, retrying in {:?}",
+                    Some("connect")
+                ),
+                (
+                    13,
+                    "info",
+                    "Connected to {addr} ({{pool}})",
+                    Some("connect")
+                ),
+                (17, "error", "Shutdown \"forced\"", Some("shutdown")),
+                (18, "debug", "{}", Some("shutdown")),
+            ]
+        );
+    }
+
+    #[test]
+    fn locates_observed_lines_around_substituted_values() {
+        let index = index();
+        let observed =
+            "2026-10-15T08:12:01Z  WARN net: Connection to 10.0.0.7:5432 failed after 3 \
+                        attempts, retrying in 2s attempt=3 peer=\"primary\"";
+        let matches = index.locate(observed, 5);
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].statement.line, 4);
+
+        let matches = index.locate("INFO Connected to db:5432 ({pool})", 5);
+        assert_eq!(matches[0].statement.line, 13);
+        // Placeholder-only formats never match, and literals must appear in order
+        assert!(index
+            .locate("retrying in 2s, Connection to x failed after", 5)
+            .is_empty());
+    }
+}
@@ -1,6 +1,6 @@
 // KotaDB CLI - Codebase intelligence platform for distributed human-AI cognition
-use anyhow::Result;
-use clap::{Parser, Subcommand};
+use anyhow::{Context, Result};
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand};
 
 // Macro for conditional printing based on quiet flag
 macro_rules! qprintln {
@@ -11,16 +11,26 @@ macro_rules! qprintln {
     };
 }
 use kotadb::{
+    cargo_workspace::CrateMap,
+    corpora::CorpusFilter,
     create_binary_trigram_index, create_file_storage, create_primary_index, create_trigram_index,
+    index_snapshots::database_path_as_of,
     init_logging_with_level,
+    metrics::performance::{LockedResource, TimedLock},
+    query_deadline::QueryDeadline,
+    replay::{ReplayOutcome, ReplayQuery, ReplaySession, ReplayStep},
     services::{
         AnalysisService, AnalysisServiceDatabase, BenchmarkOptions, BenchmarkService,
-        CallersOptions, DatabaseAccess, ImpactOptions, IndexCodebaseOptions, IndexingService,
-        OverviewOptions, SearchOptions, SearchResult, SearchService, SearchType, StatsOptions,
-        StatsService, SymbolResult, SymbolSearchOptions, ValidationOptions, ValidationService,
+        CallersOptions, ChangedFiles, ConfigUsagesOptions, DatabaseAccess, ErrorFlowOptions,
+        FlagImpactOptions, FlagsOptions, FusedSearchOptions, HotPathsOptions, ImpactOptions,
+        IndexCodebaseOptions, IndexingService, LocateLogOptions, OverviewOptions,
+        RelationshipSortBy, RenamePreviewOptions, ResultGrouping, SearchGrouping, SearchOptions,
+        SearchResult, SearchService, SearchType, SimilarOptions, StatsOptions, StatsService,
+        SuggestReviewersOptions, SymbolResult, SymbolSearchOptions, TableUsagesOptions,
+        TrendsOptions, ValidationOptions, ValidationService,
     },
-    with_trace_id, Document, DocumentBuilder, Index, QueryBuilder, Storage, ValidatedDocumentId,
-    ValidatedPath,
+    with_trace_id, Document, DocumentBuilder, Index, QueryBuilder, ReadOnlyIndex, ReadOnlyStorage,
+    Storage, ValidatedDocumentId,
 };
 
 use std::collections::HashMap;
@@ -45,6 +55,7 @@ EXAMPLES:
   kotadb index-codebase ./my-project
   kotadb search-code 'database query'
   kotadb search-symbols 'FileStorage'
+  kotadb search 'storage engine'
   
   # Analyze code relationships
   kotadb find-callers FileStorage
@@ -53,7 +64,16 @@ EXAMPLES:
 
   # System management
   kotadb stats
-  kotadb serve --port 8080"
+  kotadb serve --port 8080
+
+  # Record queries and replay them against another database
+  kotadb --record session.json search-code 'storage'
+  kotadb -d ./other-db replay run session.json
+  kotadb compare --before ./snap-a --after ./snap-b --query 'storage'
+  kotadb regression run golden-queries.yaml
+
+  # Expand abbreviations in code search queries
+  kotadb synonyms add cfg config configuration"
 )]
 struct Cli {
     /// Set verbosity level (quiet, normal, verbose, debug)
@@ -83,6 +103,14 @@ struct Cli {
     #[arg(long, global = true, default_value = "true")]
     binary_index: bool,
 
+    /// Append search and analysis queries to a replay session file
+    #[arg(long, global = true, value_name = "SESSION")]
+    record: Option<PathBuf>,
+
+    /// Open the database without taking its lease, for replicas that share a directory
+    #[arg(long, global = true, env = "KOTADB_ALLOW_SHARED_DB")]
+    allow_shared_db: bool,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -121,6 +149,54 @@ enum Commands {
             value_parser = ["none", "minimal", "medium", "full"]
         )]
         context: String,
+        /// Answer from the index snapshot of a commit or date (see `kotadb snapshots`)
+        #[arg(long)]
+        as_of: Option<String>,
+        /// Return one result per file (best match first) or one per match
+        #[arg(long, default_value = "file", value_parser = ["file", "match"])]
+        group_by: String,
+        /// Only return results from files changed since a commit SHA, duration (7d, 12h) or
+        /// date, according to ingested git history
+        #[arg(long, value_name = "REF|DURATION")]
+        changed_since: Option<String>,
+        /// Only return files with one of these comma-separated extensions (e.g. "rs,toml")
+        #[arg(long, value_name = "EXTS")]
+        ext: Option<String>,
+        /// Only return files at most this many directories deep (0 = top level)
+        #[arg(long, value_name = "N")]
+        max_depth: Option<usize>,
+        /// Only return files of at least this many bytes
+        #[arg(long, value_name = "BYTES")]
+        min_size: Option<u64>,
+        /// Only return files of at most this many bytes
+        #[arg(long, value_name = "BYTES")]
+        max_size: Option<u64>,
+        /// Stop after this many milliseconds and print what was found so far
+        #[arg(long, value_name = "MS")]
+        timeout_ms: Option<u64>,
+    },
+
+    /// Search content, symbol names and embeddings at once, fused into one ranking
+    ///
+    /// Runs trigram, symbol-name and (when configured) vector search concurrently and
+    /// merges them with reciprocal-rank fusion, so natural-language and identifier
+    /// queries both work without choosing a command.
+    Search {
+        /// Search query; `corpus:<name>` and `<facet>:<value>` terms limit it to one corpus
+        query: String,
+        /// Maximum number of results to return
+        #[arg(short, long, default_value = "10")]
+        limit: usize,
+        /// Output format (human, json)
+        #[arg(short = 'f', long, default_value = "human", value_parser = ["human", "json"])]
+        format: String,
+        /// Only return results from files changed since a commit SHA, duration (7d, 12h) or
+        /// date, according to ingested git history
+        #[arg(long, value_name = "REF|DURATION")]
+        changed_since: Option<String>,
+        /// Stop after this many milliseconds and print what was found so far
+        #[arg(long, value_name = "MS")]
+        timeout_ms: Option<u64>,
     },
 
     /// Show comprehensive database statistics (documents, symbols, relationships)
@@ -134,6 +210,12 @@ enum Commands {
         /// Show relationship and dependency data
         #[arg(long, help = "Show relationship graph and dependency analysis")]
         relationships: bool,
+        /// Also report wait times on the storage and index locks
+        #[arg(long, help = "Include lock contention on the storage and index locks")]
+        detailed: bool,
+        /// Show how files, symbols and relationships changed since the previous index run
+        #[arg(long)]
+        compare_previous: bool,
     },
 
     /// Validate search functionality
@@ -142,6 +224,24 @@ enum Commands {
     /// Verify documentation accuracy against implementation
     VerifyDocs,
 
+    /// Set up a repository: write .kotadb/config.toml and .kotaignore, optionally install
+    /// git hooks, then run the first index
+    #[cfg(feature = "git-integration")]
+    Init {
+        /// Root of the git repository
+        #[arg(default_value = ".")]
+        path: PathBuf,
+        /// Also install the pre-commit and pre-push hooks
+        #[arg(long)]
+        hooks: bool,
+        /// Write the project files without indexing
+        #[arg(long)]
+        no_index: bool,
+        /// Replace an existing config, .kotaignore and hooks
+        #[arg(long)]
+        force: bool,
+    },
+
     /// Index a codebase for intelligent analysis
     #[cfg(feature = "git-integration")]
     IndexCodebase {
@@ -177,6 +277,10 @@ enum Commands {
         #[cfg(feature = "tree-sitter-parsing")]
         #[arg(long, conflicts_with = "extract_symbols")]
         no_symbols: bool,
+        /// Keep a copy of the finished index as a snapshot of the repository's HEAD commit,
+        /// for --as-of queries
+        #[arg(long)]
+        snapshot: bool,
     },
 
     /// Search for symbols (functions, classes, variables) by name or pattern
@@ -195,6 +299,16 @@ enum Commands {
         /// Show only specific symbol types (function, class, variable, etc.)
         #[arg(short = 't', long)]
         symbol_type: Option<String>,
+        /// Only report results from the files of this Cargo crate
+        #[arg(long = "crate", value_name = "CRATE")]
+        krate: Option<String>,
+        /// Only return results from files changed since a commit SHA, duration (7d, 12h) or
+        /// date, according to ingested git history
+        #[arg(long, value_name = "REF|DURATION")]
+        changed_since: Option<String>,
+        /// Stop after this many milliseconds and print what was found so far
+        #[arg(long, value_name = "MS")]
+        timeout_ms: Option<u64>,
     },
 
     /// Find all places where a symbol is referenced (includes function calls, type usage, struct instantiations)
@@ -210,6 +324,24 @@ enum Commands {
             help = "Control number of results (default: unlimited, use -l 50 to limit)"
         )]
         limit: Option<usize>,
+        /// Print the query plan (data paths, nodes/edges visited, cache hits, phase timings) as JSON
+        #[arg(long)]
+        explain: bool,
+        /// Output format (markdown, json)
+        #[arg(short = 'f', long, default_value = "markdown", value_parser = ["markdown", "json"])]
+        format: String,
+        /// Include this many lines of surrounding source with each result (JSON output)
+        #[arg(long)]
+        context_lines: Option<usize>,
+        /// Answer from the index snapshot of a commit or date (see `kotadb snapshots`)
+        #[arg(long)]
+        as_of: Option<String>,
+        /// Only report results from the files of this Cargo crate
+        #[arg(long = "crate", value_name = "CRATE")]
+        krate: Option<String>,
+        /// Stop after this many milliseconds and print what was found so far
+        #[arg(long, value_name = "MS")]
+        timeout_ms: Option<u64>,
     },
 
     /// Analyze impact: what would break if you change a symbol
@@ -224,6 +356,57 @@ enum Commands {
             help = "Control number of results (default: unlimited, use -l 50 to limit)"
         )]
         limit: Option<usize>,
+        /// Print the query plan (data paths, nodes/edges visited, cache hits, phase timings) as JSON
+        #[arg(long)]
+        explain: bool,
+        /// Output format (markdown, json)
+        #[arg(short = 'f', long, default_value = "markdown", value_parser = ["markdown", "json"])]
+        format: String,
+        /// Include this many lines of surrounding source with each result (JSON output)
+        #[arg(long)]
+        context_lines: Option<usize>,
+        /// Only report results from the files of this Cargo crate
+        #[arg(long = "crate", value_name = "CRATE")]
+        krate: Option<String>,
+        /// Stop after this many milliseconds and print what was found so far
+        #[arg(long, value_name = "MS")]
+        timeout_ms: Option<u64>,
+    },
+
+    /// Preview a rename: every definition and reference to edit, with exact ranges
+    ///
+    /// Uses the references behind find-callers and reads the indexed source to confirm
+    /// the name at each location. Mentions it cannot verify (string literals that may
+    /// name the symbol dynamically, comments, code without a recorded relationship) are
+    /// listed separately so they can be checked by hand.
+    #[cfg(feature = "tree-sitter-parsing")]
+    PreviewRename {
+        /// Name or qualified name of the symbol to rename (e.g., 'FileStorage::insert')
+        symbol: String,
+        /// New name for the symbol (the bare identifier only)
+        new_name: String,
+        /// Output format (human, json)
+        #[arg(short = 'f', long, default_value = "human", value_parser = ["human", "json"])]
+        format: String,
+        /// Only report locations in the files of this Cargo crate
+        #[arg(long = "crate", value_name = "CRATE")]
+        krate: Option<String>,
+    },
+
+    /// Find functions that do the same thing as a function, even under different names
+    ///
+    /// Function bodies are embedded into a function-level vector index on first use
+    /// (requires OPENAI_API_KEY); later runs only embed new or changed functions.
+    #[cfg(feature = "tree-sitter-parsing")]
+    Similar {
+        /// Name of the function or method to compare against
+        symbol: String,
+        /// Maximum number of similar functions to show
+        #[arg(short, long, default_value = "10")]
+        limit: usize,
+        /// Output format (markdown, json)
+        #[arg(short = 'f', long, default_value = "markdown", value_parser = ["markdown", "json"])]
+        format: String,
     },
 
     /// Run performance benchmarks on database operations
@@ -234,7 +417,7 @@ enum Commands {
         /// Number of operations to perform
         #[arg(short, long, default_value = "10000")]
         operations: usize,
-        /// Run only specific benchmark types (storage, index, query, all)
+        /// Run only specific benchmark types (storage, index, query, search, scan, concurrency, all)
         #[arg(short = 't', long, default_value = "all")]
         benchmark_type: String,
         /// Output format (human, json, csv)
@@ -247,6 +430,9 @@ enum Commands {
             help = "Limit search operations to prevent excessive runtime"
         )]
         max_search_queries: usize,
+        /// Number of searches the concurrency benchmark keeps in flight
+        #[arg(long, default_value = "8")]
+        concurrency: usize,
     },
 
     /// Generate comprehensive codebase overview for AI assistants
@@ -257,8 +443,13 @@ enum Commands {
     /// counts, locations, and relationships.
     #[cfg(feature = "tree-sitter-parsing")]
     CodebaseOverview {
-        /// Output format (human, json)
-        #[arg(short = 'f', long, default_value = "human", value_parser = ["human", "json"])]
+        /// Output format (human, json, markdown, yaml)
+        #[arg(
+            short = 'f',
+            long,
+            default_value = "human",
+            value_parser = ["human", "json", "markdown", "yaml"]
+        )]
         format: String,
         /// Limit number of top symbols shown
         #[arg(long, default_value = "10")]
@@ -266,159 +457,737 @@ enum Commands {
         /// Limit number of entry points shown
         #[arg(long, default_value = "10")]
         entry_points_limit: usize,
+        /// Answer from the index snapshot of a commit or date (see `kotadb snapshots`)
+        #[arg(long)]
+        as_of: Option<String>,
     },
-}
 
-struct Database {
-    storage: Arc<Mutex<dyn Storage>>,
-    primary_index: Arc<Mutex<dyn Index>>,
-    trigram_index: Arc<Mutex<dyn Index>>,
-    // Cache for path -> document ID lookups (built lazily)
-    path_cache: Arc<RwLock<HashMap<String, ValidatedDocumentId>>>,
-}
+    /// Check codebase health metrics against thresholds and fail when one is exceeded
+    ///
+    /// Limits are read from --config (default: kotadb-gate.yml in the working
+    /// directory, if present); flags override the file.
+    #[cfg(feature = "tree-sitter-parsing")]
+    Gate {
+        /// YAML file with max_cycles, max_unused, min_test_ratio and max_avg_complexity
+        #[arg(short, long)]
+        config: Option<PathBuf>,
+        /// Most dependency cycles allowed
+        #[arg(long)]
+        max_cycles: Option<usize>,
+        /// Most unused symbols allowed
+        #[arg(long)]
+        max_unused: Option<usize>,
+        /// Fewest test files per source file allowed
+        #[arg(long)]
+        min_test_ratio: Option<f64>,
+        /// Highest average decision points per source file allowed
+        #[arg(long)]
+        max_avg_complexity: Option<f64>,
+        /// Report format (markdown, json)
+        #[arg(short = 'f', long, default_value = "markdown", value_parser = ["markdown", "json"])]
+        format: String,
+    },
 
-impl Database {
-    async fn new(db_path: &Path, use_binary_index: bool) -> Result<Self> {
-        let storage_path = db_path.join("storage");
-        let primary_index_path = db_path.join("primary_index");
-        let trigram_index_path = db_path.join("trigram_index");
+    /// Publish quality gate, dead-code and impact results as GitHub Check Runs
+    ///
+    /// Each selected analysis becomes one check run on --sha, with findings annotated
+    /// on the lines they concern. Exits with an error when the gate fails.
+    #[cfg(feature = "tree-sitter-parsing")]
+    PublishChecks {
+        /// Repository as owner/name
+        #[arg(long, env = "GITHUB_REPOSITORY")]
+        repo: String,
+        /// Commit the check runs are attached to (the PR head)
+        #[arg(long, env = "GITHUB_SHA")]
+        sha: String,
+        /// Token with the checks:write permission
+        #[arg(long, env = "GITHUB_TOKEN", hide_env_values = true)]
+        token: String,
+        /// GitHub API root (for GitHub Enterprise Server)
+        #[arg(long, env = "GITHUB_API_URL", default_value = "https://api.github.com")]
+        api_url: String,
+        /// Checkout the indexed paths are made relative to
+        #[arg(long, default_value = ".")]
+        repo_root: PathBuf,
+        /// Publish the quality gate, with limits from --gate-config or kotadb-gate.yml
+        #[arg(long)]
+        gate: bool,
+        /// YAML file with gate limits
+        #[arg(long)]
+        gate_config: Option<PathBuf>,
+        /// Publish unused symbols as warnings on their definitions
+        #[arg(long)]
+        dead_code: bool,
+        /// Publish the impact of changing a symbol (repeatable)
+        #[arg(long = "impact")]
+        impact: Vec<String>,
+        /// Most impacted symbols annotated per --impact
+        #[arg(long, default_value = "100")]
+        impact_limit: usize,
+    },
 
-        // Create directories if they don't exist
-        std::fs::create_dir_all(&storage_path)?;
-        std::fs::create_dir_all(&primary_index_path)?;
-        std::fs::create_dir_all(&trigram_index_path)?;
+    /// Show how codebase metrics evolved over recent indexing runs
+    ///
+    /// Every successful `index-codebase` run records file, line and symbol counts,
+    /// dependency cycles, unused symbols and average complexity.
+    #[cfg(feature = "tree-sitter-parsing")]
+    Trends {
+        /// Number of most recent runs shown
+        #[arg(short, long, default_value = "30")]
+        limit: usize,
+        /// Output format (human, json)
+        #[arg(short = 'f', long, default_value = "human", value_parser = ["human", "json"])]
+        format: String,
+    },
 
-        let storage = create_file_storage(
-            storage_path
-                .to_str()
-                .ok_or_else(|| anyhow::anyhow!("Invalid storage path: {:?}", storage_path))?,
-            Some(100), // Cache size
-        )
-        .await?;
+    /// List the index snapshots --as-of queries can answer from
+    ///
+    /// `index-codebase --snapshot` records one per indexed commit.
+    Snapshots {
+        /// Output format (human, json)
+        #[arg(short = 'f', long, default_value = "human", value_parser = ["human", "json"])]
+        format: String,
+    },
 
-        let primary_index = create_primary_index(
-            primary_index_path.to_str().ok_or_else(|| {
-                anyhow::anyhow!("Invalid primary index path: {:?}", primary_index_path)
-            })?,
-            Some(1000),
-        )
-        .await?;
-        let trigram_index_arc: Arc<Mutex<dyn Index>> = if use_binary_index {
-            tracing::info!("Using binary trigram index for optimized performance");
-            Arc::new(Mutex::new(
-                create_binary_trigram_index(
-                    trigram_index_path.to_str().ok_or_else(|| {
-                        anyhow::anyhow!("Invalid trigram index path: {:?}", trigram_index_path)
-                    })?,
-                    Some(1000),
-                )
-                .await?,
-            ))
-        } else {
-            Arc::new(Mutex::new(
-                create_trigram_index(
-                    trigram_index_path.to_str().ok_or_else(|| {
-                        anyhow::anyhow!("Invalid trigram index path: {:?}", trigram_index_path)
-                    })?,
-                    Some(1000),
-                )
-                .await?,
-            ))
-        };
+    /// Show when a symbol appeared, how it changed and when it was removed across snapshots
+    ///
+    /// Reports the symbol's files, lines, decision points and callers in every index
+    /// snapshot, oldest commit first, and in the current index.
+    #[cfg(feature = "tree-sitter-parsing")]
+    SymbolEvolution {
+        /// Symbol name, optionally path-qualified (e.g. 'FileStorage::insert')
+        name: String,
+        /// Output format (human, json)
+        #[arg(short = 'f', long, default_value = "human", value_parser = ["human", "json"])]
+        format: String,
+    },
 
-        let storage_arc: Arc<Mutex<dyn Storage>> = Arc::new(Mutex::new(storage));
-        let primary_index_arc: Arc<Mutex<dyn Index>> = Arc::new(Mutex::new(primary_index));
+    /// Import an lcov or Cobertura coverage report as per-symbol test coverage
+    ///
+    /// Line hits are mapped onto the ranges of indexed functions and methods. Impact
+    /// analysis then reports untested impacted symbols and the overview shows measured
+    /// coverage. Each import replaces the previous one.
+    #[cfg(feature = "tree-sitter-parsing")]
+    ImportCoverage {
+        /// Coverage report (e.g. lcov.info or cobertura.xml)
+        report: PathBuf,
+        /// Report format; detected from the content when omitted
+        #[arg(long, value_parser = ["lcov", "cobertura"])]
+        format: Option<String>,
+    },
 
-        let db = Self {
-            storage: storage_arc,
-            primary_index: primary_index_arc,
-            trigram_index: trigram_index_arc,
-            path_cache: Arc::new(RwLock::new(HashMap::new())),
-        };
+    /// Import runtime traces as observed call counts on the dependency graph
+    ///
+    /// Accepts OpenTelemetry spans exported as OTLP JSON and folded stacks from perf or
+    /// flamegraph tooling. Hot paths are then ranked by observed calls, and risk-sorted
+    /// callers and impact results put the most exercised code first. Each import replaces
+    /// the previous one.
+    #[cfg(feature = "tree-sitter-parsing")]
+    ImportTraces {
+        /// Trace export (e.g. spans.json or out.folded)
+        trace: PathBuf,
+        /// Trace format; detected from the content when omitted
+        #[arg(long, value_parser = ["otel", "folded"])]
+        format: Option<String>,
+    },
 
-        // Skip path cache rebuild for read-only operations like search
-        // The cache will be built lazily when needed for path-based lookups
-        // This significantly improves startup time for search operations
-        // from ~300ms to ~5ms (see issue #274)
+    /// List the most used symbols, by observed calls when traces were imported
+    #[cfg(feature = "tree-sitter-parsing")]
+    HotPaths {
+        /// Number of symbols shown
+        #[arg(short, long, default_value = "20")]
+        limit: usize,
+        /// Output format (markdown, json)
+        #[arg(short = 'f', long, default_value = "markdown", value_parser = ["markdown", "json"])]
+        format: String,
+    },
 
-        Ok(db)
-    }
+    /// Find the log statement that emitted an observed log line
+    ///
+    /// Matches the line against the format strings of `log` and `tracing` macros recorded
+    /// by `index-codebase`; timestamps, levels, fields and substituted values may remain.
+    #[cfg(feature = "tree-sitter-parsing")]
+    LocateLog {
+        /// Log line as observed in production
+        message: String,
+        /// Most candidate statements shown
+        #[arg(short, long, default_value = "5")]
+        limit: usize,
+        /// Output format (human, json)
+        #[arg(short = 'f', long, default_value = "human", value_parser = ["human", "json"])]
+        format: String,
+    },
 
-    /// Rebuild the path cache from current storage
-    #[allow(dead_code)]
-    async fn rebuild_path_cache(&self) -> Result<()> {
-        let mut cache = self.path_cache.write().await;
-        cache.clear();
+    /// Trace where a Rust error type is constructed, converted, handled and escapes
+    ///
+    /// Lists construction sites, `From`/`#[from]` conversions into and out of the type,
+    /// handling sites, and public functions whose return type carries it.
+    #[cfg(feature = "tree-sitter-parsing")]
+    ErrorFlows {
+        /// Error type name, e.g. StorageError or crate::errors::StorageError
+        error_type: String,
+        /// Most entries shown per section
+        #[arg(short, long, default_value = "50")]
+        limit: usize,
+        /// Output format (human, json)
+        #[arg(short = 'f', long, default_value = "human", value_parser = ["human", "json"])]
+        format: String,
+    },
 
-        // Get all documents to build the cache
-        let all_docs = self.storage.lock().await.list_all().await?;
-        for doc in all_docs {
-            cache.insert(doc.path.to_string(), doc.id);
-        }
+    /// List feature flags with usage counts
+    ///
+    /// Flags are Cargo features in `cfg`, environment variable reads and flag SDK calls,
+    /// plus patterns configured in `kotadb-flags.yml` at the repository root.
+    #[cfg(feature = "tree-sitter-parsing")]
+    Flags {
+        /// Most flags shown
+        #[arg(short, long, default_value = "50")]
+        limit: usize,
+        /// Output format (human, json)
+        #[arg(short = 'f', long, default_value = "human", value_parser = ["human", "json"])]
+        format: String,
+    },
 
-        Ok(())
-    }
+    /// Show the files, items and runtime checks a feature flag controls
+    #[cfg(feature = "tree-sitter-parsing")]
+    FlagImpact {
+        /// Cargo feature, environment variable or SDK flag key
+        name: String,
+        /// Output format (human, json)
+        #[arg(short = 'f', long, default_value = "human", value_parser = ["human", "json"])]
+        format: String,
+    },
 
-    /// Rebuild all indices from current storage
-    /// This is needed after bulk operations like git ingestion
-    #[allow(dead_code)]
-    async fn rebuild_indices(&self) -> Result<()> {
-        // Get all documents from storage
-        let all_docs = self.storage.lock().await.list_all().await?;
-        let total_docs = all_docs.len();
+    /// List where a configuration key is set and read
+    ///
+    /// Covers TOML, YAML and `.env` entries, deserialized config struct fields,
+    /// environment variable reads, `config.get("key")` lookups and `config.key` accesses.
+    /// Keys match regardless of case and `_`, `-` or `.` separators.
+    #[cfg(feature = "tree-sitter-parsing")]
+    ConfigUsages {
+        /// Configuration key, e.g. DATABASE_URL
+        key: String,
+        /// Most sites shown per section
+        #[arg(short, long, default_value = "50")]
+        limit: usize,
+        /// Output format (human, json)
+        #[arg(short = 'f', long, default_value = "human", value_parser = ["human", "json"])]
+        format: String,
+    },
 
-        if total_docs == 0 {
-            return Ok(());
-        }
+    /// List the SQL statements that define, write and read a table
+    ///
+    /// Statements come from `.sql` files and SQL string literals in the code (sqlx
+    /// queries, raw query strings); columns are listed per statement.
+    #[cfg(feature = "tree-sitter-parsing")]
+    TableUsages {
+        /// Table name, e.g. repositories or public.repositories
+        table: String,
+        /// Most statements shown per section
+        #[arg(short, long, default_value = "50")]
+        limit: usize,
+        /// Output format (human, json)
+        #[arg(short = 'f', long, default_value = "human", value_parser = ["human", "json"])]
+        format: String,
+    },
 
-        // Process documents in batches for better performance
-        const BATCH_SIZE: usize = 100;
-        let mut processed = 0;
+    /// Suggest reviewers for a diff, ranked by familiarity with the changed code
+    ///
+    /// Combines CODEOWNERS, ingested commits to the changed files and to files of impacted
+    /// symbols, and with --repo git blame of the changed functions. Prints JSON for CI bots.
+    #[cfg(feature = "tree-sitter-parsing")]
+    SuggestReviewers {
+        /// Unified diff of the change, e.g. from git diff main...HEAD
+        #[arg(long)]
+        diff: PathBuf,
+        /// Maximum number of reviewers
+        #[arg(short, long, default_value = "5")]
+        limit: usize,
+        /// Reviewer to leave out, such as the change author (repeatable)
+        #[arg(long)]
+        exclude: Vec<String>,
+        /// Repository checkout to read CODEOWNERS from and blame changed lines in
+        #[arg(long)]
+        repo: Option<PathBuf>,
+        /// CODEOWNERS file to use instead of the repository's
+        #[arg(long)]
+        codeowners: Option<PathBuf>,
+        /// Output format (json, human)
+        #[arg(short = 'f', long, default_value = "json", value_parser = ["json", "human"])]
+        format: String,
+    },
 
-        // Process in chunks to reduce lock contention and prevent OOM
-        for chunk in all_docs.chunks(BATCH_SIZE) {
-            // Collect document data for this batch (including content for trigram indexing)
-            let mut batch_entries = Vec::with_capacity(chunk.len());
-            for doc in chunk {
-                let doc_id = doc.id;
-                let doc_path = ValidatedPath::new(doc.path.to_string())?;
-                batch_entries.push((doc_id, doc_path, doc.content.clone()));
-            }
+    /// Run the same queries on two database snapshots and report result differences
+    ///
+    /// Useful for validating index format changes and ranking tweaks before release.
+    Compare {
+        /// Database directory of the baseline snapshot
+        #[arg(long)]
+        before: PathBuf,
+        /// Database directory of the snapshot under test
+        #[arg(long)]
+        after: PathBuf,
+        /// Query to run on both snapshots (repeatable)
+        #[arg(long = "query", required_unless_present = "session")]
+        queries: Vec<String>,
+        /// Tool each --query is run with
+        #[arg(
+            short,
+            long,
+            default_value = "search-code",
+            value_parser = ["search-code", "search-symbols", "find-callers", "analyze-impact"]
+        )]
+        tool: String,
+        /// Also run every query recorded in a replay session
+        #[arg(long)]
+        session: Option<PathBuf>,
+        /// Maximum number of results compared per query
+        #[arg(short, long, default_value = "10")]
+        limit: usize,
+        /// Context level for search-code (medium/full report relevance scores)
+        #[arg(short = 'c', long, default_value = "medium", value_parser = ["none", "minimal", "medium", "full"])]
+        context: String,
+        /// Report format (markdown, json)
+        #[arg(short = 'f', long, default_value = "markdown", value_parser = ["markdown", "json"])]
+        format: String,
+        /// Exit with an error when any query differs
+        #[arg(long)]
+        fail_on_diff: bool,
+    },
 
-            // Insert batch into primary index (path-based)
-            {
-                let mut primary_index = self.primary_index.lock().await;
-                for (doc_id, doc_path, _) in &batch_entries {
-                    primary_index.insert(*doc_id, doc_path.clone()).await?;
-                }
-            }
+    /// Write a deterministic multi-language repository with known symbols, calls, cycles
+    /// and dead code, plus the analysis results expected from it
+    GenerateFixture {
+        /// Languages to include, comma-separated (rust, python, ts)
+        #[arg(long, default_value = "rust,python,ts")]
+        languages: String,
+        /// Amount of code per language
+        #[arg(long, default_value = "medium", value_parser = ["small", "medium", "large"])]
+        size: String,
+        /// Seed for symbol names; the same seed reproduces the same files
+        #[arg(long, default_value_t = kotadb::fixture_generator::DEFAULT_SEED)]
+        seed: u64,
+        /// Directory to write the fixture to
+        #[arg(short, long, default_value = "kotadb-fixture")]
+        output: PathBuf,
+        /// Write into a non-empty directory, replacing files with the same paths
+        #[arg(long)]
+        force: bool,
+        /// Output format (human, json)
+        #[arg(short = 'f', long, default_value = "human", value_parser = ["human", "json"])]
+        format: String,
+    },
 
-            // Insert batch into trigram index with content for proper full-text search
-            {
-                let mut trigram_index = self.trigram_index.lock().await;
-                for (doc_id, doc_path, content) in &batch_entries {
-                    // Use the new content-aware method for proper trigram indexing
-                    trigram_index
-                        .insert_with_content(*doc_id, doc_path.clone(), content)
-                        .await?;
-                }
-            }
+    /// Replay recorded query sessions and compare results, latency, and token usage
+    Replay {
+        #[command(subcommand)]
+        action: ReplayAction,
+    },
 
-            processed += chunk.len();
+    /// Check golden queries against the database to gate index and ranking changes
+    Regression {
+        #[command(subcommand)]
+        action: RegressionAction,
+    },
 
-            // Periodic flush for large datasets
-            if processed % 500 == 0 || processed == total_docs {
-                self.primary_index.lock().await.flush().await?;
-                self.trigram_index.lock().await.flush().await?;
-            }
-        }
+    /// Manage the synonym dictionary used to expand search-code queries
+    Synonyms {
+        #[command(subcommand)]
+        action: SynonymsAction,
+    },
 
-        Ok(())
-    }
+    /// Download the latest release, verify its SHA-256 and replace this binary
+    SelfUpdate {
+        /// Release to install instead of the latest, e.g. 0.7.0
+        #[arg(long, value_name = "VERSION")]
+        to: Option<String>,
+        /// Only report whether a newer release is available
+        #[arg(long)]
+        check: bool,
+    },
 
-    #[allow(dead_code)]
-    async fn search(
-        &self,
+    /// Opt in to or out of anonymous usage telemetry (off by default)
+    Telemetry {
+        #[command(subcommand)]
+        action: TelemetryAction,
+    },
+
+    /// Load index files, symbols, the dependency graph and the path cache ahead of the
+    /// first query, e.g. at container start
+    Warmup {
+        /// Also compute the default codebase overview
+        #[arg(long)]
+        overview: bool,
+        /// Output format (human, json)
+        #[arg(short = 'f', long, default_value = "human", value_parser = ["human", "json"])]
+        format: String,
+    },
+
+    /// Inspect the background maintenance `kotadb serve` runs in its configured window
+    Maintenance {
+        #[command(subcommand)]
+        action: MaintenanceAction,
+    },
+
+    /// Leave notes, tags and bookmarks on files and symbols for teammates and assistants
+    Notes {
+        #[command(subcommand)]
+        action: NotesAction,
+    },
+
+    /// Install git hooks that summarise the impact of each commit or push
+    #[cfg(feature = "tree-sitter-parsing")]
+    Hook {
+        #[command(subcommand)]
+        action: HookAction,
+    },
+
+    /// Serve per-symbol callers, churn and test links to an editor extension over stdio
+    ///
+    /// Reads one JSON request per line on stdin and writes one JSON response per line
+    /// on stdout (methods: initialize, fileMetrics, symbolAt, reload, shutdown).
+    #[cfg(feature = "tree-sitter-parsing")]
+    EditorServer {
+        /// Checkout that absolute file paths in requests are relative to
+        #[arg(long, default_value = ".")]
+        root: PathBuf,
+    },
+}
+
+#[cfg(feature = "tree-sitter-parsing")]
+#[derive(Subcommand)]
+enum HookAction {
+    /// Write pre-commit and pre-push hooks into the repository in the working directory
+    Install {
+        /// Hooks to install (default: all)
+        #[arg(value_parser = ["pre-commit", "pre-push"])]
+        hooks: Vec<String>,
+        /// Replace hooks that were not installed by kotadb
+        #[arg(long)]
+        force: bool,
+        /// Time each run may spend reading and parsing changed files
+        #[arg(long, default_value_t = kotadb::git_hooks::DEFAULT_HOOK_BUDGET_MS)]
+        budget_ms: u64,
+    },
+    /// Remove hooks installed by kotadb
+    Uninstall {
+        /// Hooks to remove (default: all)
+        #[arg(value_parser = ["pre-commit", "pre-push"])]
+        hooks: Vec<String>,
+    },
+    /// Summarise staged changes (pre-commit) or pushed commits (pre-push, ref updates on stdin)
+    Run {
+        #[arg(value_parser = ["pre-commit", "pre-push"])]
+        hook: String,
+        /// Time spent reading and parsing changed files before the rest are skipped
+        #[arg(long, default_value_t = kotadb::git_hooks::DEFAULT_HOOK_BUDGET_MS)]
+        budget_ms: u64,
+        /// Output format (human, json)
+        #[arg(short = 'f', long, default_value = "human", value_parser = ["human", "json"])]
+        format: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum NotesAction {
+    /// Leave a note (e.g. `kotadb notes add --file src/main.rs:10-20 "The real entry point"`)
+    Add {
+        /// Symbol name, or with --file a path optionally followed by `:start-end`
+        target: String,
+        /// Note text
+        text: Option<String>,
+        /// The target is a file path rather than a symbol name
+        #[arg(long)]
+        file: bool,
+        /// Tag the target (repeatable)
+        #[arg(short = 't', long = "tag")]
+        tags: Vec<String>,
+        /// Bookmark the target
+        #[arg(short = 'b', long)]
+        bookmark: bool,
+        /// Who left the note
+        #[arg(long)]
+        author: Option<String>,
+    },
+    /// List notes, optionally of one target, tag or only bookmarks
+    List {
+        /// Symbol name or file path
+        target: Option<String>,
+        /// Only notes with this tag
+        #[arg(short = 't', long)]
+        tag: Option<String>,
+        /// Only bookmarks
+        #[arg(short = 'b', long)]
+        bookmarks: bool,
+        /// Output format (human, json)
+        #[arg(short = 'f', long, default_value = "human", value_parser = ["human", "json"])]
+        format: String,
+    },
+    /// Remove a note by its id
+    Remove {
+        /// Note id, as shown by `kotadb notes list`
+        id: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum MaintenanceAction {
+    /// Show the maintenance window and the last run of each task
+    Status {
+        /// Output format (human, json)
+        #[arg(short = 'f', long, default_value = "human", value_parser = ["human", "json"])]
+        format: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum SynonymsAction {
+    /// Add synonyms for a term (e.g. `kotadb synonyms add cfg config configuration`)
+    Add {
+        /// Term to expand
+        term: String,
+        /// Words searched in place of the term, and vice versa
+        #[arg(required = true)]
+        synonyms: Vec<String>,
+    },
+    /// Remove synonyms of a term, or the whole entry when none are given
+    Remove {
+        /// Term to edit
+        term: String,
+        /// Synonyms to remove
+        synonyms: Vec<String>,
+    },
+    /// List the dictionary
+    List {
+        /// Output format (human, json)
+        #[arg(short = 'f', long, default_value = "human", value_parser = ["human", "json"])]
+        format: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum TelemetryAction {
+    /// Show whether telemetry is enabled, where reports go and what is queued
+    Status {
+        /// Output format (human, json)
+        #[arg(short = 'f', long, default_value = "human", value_parser = ["human", "json"])]
+        format: String,
+    },
+    /// Start recording command names, durations and a bucketed database size
+    Enable {
+        /// URL aggregated reports are posted to; events stay queued locally without one
+        #[arg(long)]
+        endpoint: Option<String>,
+    },
+    /// Stop recording and delete queued events
+    Disable,
+}
+
+#[derive(Subcommand)]
+enum RegressionAction {
+    /// Run a golden-query suite and fail when results deviate beyond tolerances
+    Run {
+        /// YAML suite of queries with expected top-N results
+        suite: PathBuf,
+        /// Report format (markdown, json)
+        #[arg(short = 'f', long, default_value = "markdown", value_parser = ["markdown", "json"])]
+        format: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum ReplayAction {
+    /// Re-run a recorded session against --db-path and compare it with the recording
+    Run {
+        /// Session file written with --record
+        session: PathBuf,
+        /// Save the replayed session for later comparisons
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// Report format (markdown, json)
+        #[arg(short = 'f', long, default_value = "markdown", value_parser = ["markdown", "json"])]
+        format: String,
+    },
+    /// Compare two recorded sessions without re-running them
+    Compare {
+        /// Session used as the baseline
+        baseline: PathBuf,
+        /// Session compared against the baseline
+        candidate: PathBuf,
+        /// Report format (markdown, json)
+        #[arg(short = 'f', long, default_value = "markdown", value_parser = ["markdown", "json"])]
+        format: String,
+    },
+}
+
+/// Append a step to the session given by `--record`, if any
+fn record_step(
+    record: &Option<PathBuf>,
+    db_path: &Path,
+    query: ReplayQuery,
+    elapsed: std::time::Duration,
+    outcome: ReplayOutcome<'_>,
+) -> Result<()> {
+    if let Some(session_path) = record {
+        let step = ReplayStep::record(query, elapsed, outcome);
+        ReplaySession::append_to_file(session_path, db_path, step)?;
+    }
+    Ok(())
+}
+
+/// Log a warning when a query stopped at its `--timeout-ms` deadline
+fn warn_if_timed_out(timed_out: bool, timeout_ms: Option<u64>) {
+    if let (true, Some(timeout_ms)) = (timed_out, timeout_ms) {
+        tracing::warn!("Query stopped after its {timeout_ms} ms timeout; results are partial");
+    }
+}
+
+/// Share an index, wrapped to refuse writes when the database is opened read-only
+fn shared_index<I: Index + 'static>(index: I, read_only: Option<&str>) -> Arc<Mutex<dyn Index>> {
+    match read_only {
+        Some(reason) => Arc::new(Mutex::new(ReadOnlyIndex::new(index, reason))),
+        None => Arc::new(Mutex::new(index)),
+    }
+}
+
+struct Database {
+    storage: Arc<Mutex<dyn Storage>>,
+    primary_index: Arc<Mutex<dyn Index>>,
+    trigram_index: Arc<Mutex<dyn Index>>,
+    // Cache for path -> document ID lookups (built lazily)
+    path_cache: Arc<RwLock<HashMap<String, ValidatedDocumentId>>>,
+}
+
+impl Database {
+    async fn new(db_path: &Path, use_binary_index: bool) -> Result<Self> {
+        Self::open(db_path, use_binary_index, None).await
+    }
+
+    /// Open the database with storage and indices that fail writes with `reason`
+    async fn new_read_only(db_path: &Path, use_binary_index: bool, reason: &str) -> Result<Self> {
+        Self::open(db_path, use_binary_index, Some(reason)).await
+    }
+
+    async fn open(db_path: &Path, use_binary_index: bool, read_only: Option<&str>) -> Result<Self> {
+        let storage_path = db_path.join("storage");
+        let primary_index_path = db_path.join("primary_index");
+        let trigram_index_path = db_path.join("trigram_index");
+
+        // Create directories if they don't exist
+        std::fs::create_dir_all(&storage_path)?;
+        std::fs::create_dir_all(&primary_index_path)?;
+        std::fs::create_dir_all(&trigram_index_path)?;
+
+        let storage = create_file_storage(
+            storage_path
+                .to_str()
+                .ok_or_else(|| anyhow::anyhow!("Invalid storage path: {:?}", storage_path))?,
+            Some(100), // Cache size
+        )
+        .await?;
+
+        let primary_index = create_primary_index(
+            primary_index_path.to_str().ok_or_else(|| {
+                anyhow::anyhow!("Invalid primary index path: {:?}", primary_index_path)
+            })?,
+            Some(1000),
+        )
+        .await?;
+        let trigram_index_arc: Arc<Mutex<dyn Index>> = if use_binary_index {
+            tracing::info!("Using binary trigram index for optimized performance");
+            shared_index(
+                create_binary_trigram_index(
+                    trigram_index_path.to_str().ok_or_else(|| {
+                        anyhow::anyhow!("Invalid trigram index path: {:?}", trigram_index_path)
+                    })?,
+                    Some(1000),
+                )
+                .await?,
+                read_only,
+            )
+        } else {
+            shared_index(
+                create_trigram_index(
+                    trigram_index_path.to_str().ok_or_else(|| {
+                        anyhow::anyhow!("Invalid trigram index path: {:?}", trigram_index_path)
+                    })?,
+                    Some(1000),
+                )
+                .await?,
+                read_only,
+            )
+        };
+
+        let storage_arc: Arc<Mutex<dyn Storage>> = match read_only {
+            Some(reason) => Arc::new(Mutex::new(ReadOnlyStorage::new(storage, reason))),
+            None => Arc::new(Mutex::new(storage)),
+        };
+        let primary_index_arc = shared_index(primary_index, read_only);
+
+        let db = Self {
+            storage: storage_arc,
+            primary_index: primary_index_arc,
+            trigram_index: trigram_index_arc,
+            path_cache: Arc::new(RwLock::new(HashMap::new())),
+        };
+
+        // Skip path cache rebuild for read-only operations like search
+        // The cache will be built lazily when needed for path-based lookups
+        // This significantly improves startup time for search operations
+        // from ~300ms to ~5ms (see issue #274)
+
+        Ok(db)
+    }
+
+    /// Rebuild the path cache from current storage
+    #[allow(dead_code)]
+    async fn rebuild_path_cache(&self) -> Result<()> {
+        let mut cache = self.path_cache.write().await;
+        cache.clear();
+
+        // Document metadata is enough to build the cache
+        let all_docs = self
+            .storage
+            .lock_timed(LockedResource::Storage)
+            .await
+            .list_metadata()
+            .await?;
+        for doc in all_docs {
+            cache.insert(doc.path.to_string(), doc.id);
+        }
+
+        Ok(())
+    }
+
+    /// Rebuild all indices from current storage
+    /// This is needed after bulk operations like git ingestion
+    #[allow(dead_code)]
+    async fn rebuild_indices(&self) -> Result<()> {
+        // Read, tokenize and insert concurrently, one storage page at a time
+        let report = kotadb::index_rebuild::rebuild_indices(
+            self.storage.clone(),
+            self.primary_index.clone(),
+            self.trigram_index.clone(),
+            |_| {},
+        )
+        .await?;
+        if let Some(error) = report.errors.first() {
+            anyhow::bail!("{}", error);
+        }
+        tracing::info!(
+            "Rebuilt indices from {} documents in {:.2}s with {} tokenizer workers\n{}",
+            report.processed(),
+            report.elapsed.as_secs_f64(),
+            report.workers,
+            report.stage_summary()
+        );
+
+        Ok(())
+    }
+
+    #[allow(dead_code)]
+    async fn search(
+        &self,
         query_text: &str,
         tags: Option<Vec<String>>,
         limit: usize,
@@ -460,31 +1229,45 @@ impl Database {
         // search and don't handle wildcard patterns. Empty queries are handled above and return nothing.
         let doc_ids = if query_text.contains('*') {
             // Use Primary Index for wildcard/pattern queries
-            self.primary_index.lock().await.search(&query).await?
+            self.primary_index
+                .lock_timed(LockedResource::PrimaryIndex)
+                .await
+                .search(&query)
+                .await?
         } else {
             // Use Trigram Index for full-text search queries
-            self.trigram_index.lock().await.search(&query).await?
+            self.trigram_index
+                .lock_timed(LockedResource::TrigramIndex)
+                .await
+                .search(&query)
+                .await?
         };
 
         // Store total count before limiting
         let total_count = doc_ids.len();
 
-        // Retrieve documents from storage
+        // Retrieve documents from storage in one batch
         let doc_ids_limited: Vec<_> = doc_ids.into_iter().take(limit).collect();
-        let mut documents = Vec::with_capacity(doc_ids_limited.len());
-        let storage = self.storage.lock().await;
-
-        for doc_id in doc_ids_limited {
-            if let Some(doc) = storage.get(&doc_id).await? {
-                documents.push(doc);
-            }
-        }
+        let documents: Vec<_> = self
+            .storage
+            .lock_timed(LockedResource::Storage)
+            .await
+            .get_many(&doc_ids_limited)
+            .await?
+            .into_iter()
+            .flatten()
+            .collect();
 
         Ok((documents, total_count))
     }
 
     async fn stats(&self) -> Result<(usize, usize)> {
-        let all_docs = self.storage.lock().await.list_all().await?;
+        let all_docs = self
+            .storage
+            .lock_timed(LockedResource::Storage)
+            .await
+            .list_metadata()
+            .await?;
         let doc_count = all_docs.len();
         let total_size: usize = all_docs.iter().map(|d| d.size).sum();
         Ok((doc_count, total_size))
@@ -515,6 +1298,14 @@ impl AnalysisServiceDatabase for Database {
     fn storage(&self) -> Arc<Mutex<dyn Storage>> {
         self.storage.clone()
     }
+
+    fn primary_index(&self) -> Arc<Mutex<dyn Index>> {
+        self.primary_index.clone()
+    }
+
+    fn path_cache(&self) -> Arc<RwLock<HashMap<String, ValidatedDocumentId>>> {
+        self.path_cache.clone()
+    }
 }
 
 /// Format SearchResult to maintain identical CLI output
@@ -529,7 +1320,9 @@ fn format_search_result(result: &SearchResult, options: &SearchOptions) -> Strin
                     // Always show helpful messages for no-results case, even in quiet mode
                     // This addresses UX issue #3 - users need feedback when searches find nothing
                     output.push_str(&format!("No code found matching '{}'\n", options.query));
-                    if !options.quiet {
+                    if !result.suggestions.is_empty() {
+                        output.push_str(&format_did_you_mean(&result.suggestions));
+                    } else if !options.quiet {
                         output.push_str("Try:\n");
                         output.push_str("  • Different search terms or patterns\n");
                         output.push_str("  • Wildcard search with '*' for broader results\n");
@@ -642,7 +1435,9 @@ fn format_search_result(result: &SearchResult, options: &SearchOptions) -> Strin
                 // This addresses UX issue #3 - users need feedback when searches find nothing
                 output.push_str(&format!("No code found matching '{}'\n", options.query));
                 if options.query != "*" {
-                    if !options.quiet {
+                    if !result.suggestions.is_empty() {
+                        output.push_str(&format_did_you_mean(&result.suggestions));
+                    } else if !options.quiet {
                         output.push_str("Try:\n");
                         output.push_str("  • Different search terms or patterns\n");
                         output.push_str("  • Wildcard search with '*' for broader results\n");
@@ -681,6 +1476,12 @@ fn format_search_result(result: &SearchResult, options: &SearchOptions) -> Strin
     output
 }
 
+/// "Did you mean" line for the no-results messages
+fn format_did_you_mean(suggestions: &[String]) -> String {
+    let quoted: Vec<String> = suggestions.iter().map(|s| format!("'{}'", s)).collect();
+    format!("Did you mean: {}?\n", quoted.join(", "))
+}
+
 /// Format SymbolResult to maintain identical CLI output
 fn format_symbol_result(
     result: &SymbolResult,
@@ -703,7 +1504,9 @@ fn format_symbol_result(
             ));
         }
         if result.total_symbols > 0 {
-            if !options.quiet {
+            if !result.suggestions.is_empty() {
+                output.push_str(&format_did_you_mean(&result.suggestions));
+            } else if !options.quiet {
                 output.push_str("Try:\n");
                 output.push_str("  • Different symbol patterns or wildcards (e.g., '*Storage*')\n");
                 output.push_str("  • Removing type filters to see all symbol types\n");
@@ -786,11 +1589,21 @@ async fn run_benchmarks(
                 .build()?;
 
             let op_start = Instant::now();
-            database.storage.lock().await.insert(doc.clone()).await?;
+            database
+                .storage
+                .lock_timed(LockedResource::Storage)
+                .await
+                .insert(doc.clone())
+                .await?;
             durations.push(op_start.elapsed());
 
             // Also test retrieval
-            let _ = database.storage.lock().await.get(&doc.id).await?;
+            let _ = database
+                .storage
+                .lock_timed(LockedResource::Storage)
+                .await
+                .get(&doc.id)
+                .await?;
         }
 
         let total_duration = start.elapsed();
@@ -865,7 +1678,12 @@ async fn run_benchmarks(
         qprintln!(quiet, "\n🔍 Search Benchmarks");
 
         // Ensure we have documents and indices
-        let all_docs = database.storage.lock().await.list_all().await?;
+        let all_docs = database
+            .storage
+            .lock_timed(LockedResource::Storage)
+            .await
+            .list_all()
+            .await?;
         if all_docs.is_empty() {
             qprintln!(
                 quiet,
@@ -880,7 +1698,12 @@ async fn run_benchmarks(
                         format!("Benchmark document {} content with some test data", i).as_bytes(),
                     )
                     .build()?;
-                database.storage.lock().await.insert(doc).await?;
+                database
+                    .storage
+                    .lock_timed(LockedResource::Storage)
+                    .await
+                    .insert(doc)
+                    .await?;
             }
         }
 
@@ -1113,7 +1936,12 @@ mod tests {
 
         // Verify all documents are indexed
         let query = QueryBuilder::new().with_limit(200)?.build()?;
-        let results = db.primary_index.lock().await.search(&query).await?;
+        let results = db
+            .primary_index
+            .lock_timed(LockedResource::PrimaryIndex)
+            .await
+            .search(&query)
+            .await?;
         assert!(
             results.len() >= 150,
             "Expected at least 150 documents, got {}",
@@ -1184,6 +2012,18 @@ async fn generate_codebase_overview(
     overview_data.insert("total_files", json!(doc_count));
     overview_data.insert("total_size_bytes", json!(total_size));
 
+    // Language breakdown by bytes and well-known project files
+    let documents = db
+        .storage
+        .lock_timed(LockedResource::Storage)
+        .await
+        .list_metadata()
+        .await?;
+    let project = kotadb::project_metadata::ProjectMetadata::from_metadata(&documents);
+    overview_data.insert("languages", json!(project.languages));
+    overview_data.insert("primary_language", json!(project.primary_language));
+    overview_data.insert("project_files", json!(project.project_files));
+
     // 2. Symbol analysis (if available)
     let symbol_db_path = db_path.join("symbols.kota");
     let mut symbols_by_type: HashMap<String, usize> = HashMap::new();
@@ -1235,9 +2075,8 @@ async fn generate_codebase_overview(
     let graph_db_path = db_path.join("dependency_graph.bin");
     if graph_db_path.exists() {
         if let Ok(graph_binary) = std::fs::read(&graph_db_path) {
-            if let Ok(serializable) = bincode::deserialize::<
-                kotadb::dependency_extractor::SerializableDependencyGraph,
-            >(&graph_binary)
+            if let Ok(serializable) =
+                kotadb::dependency_extractor::SerializableDependencyGraph::from_bytes(&graph_binary)
             {
                 total_relationships = serializable.stats.edge_count;
                 connected_symbols = serializable.stats.node_count;
@@ -1349,6 +2188,27 @@ async fn generate_codebase_overview(
         json!(format!("{:.2}", test_to_code_ratio)),
     );
 
+    // 6. Measured test coverage, when a report was imported
+    let coverage = match kotadb::coverage::CoverageStore::load(db_path) {
+        Ok(store) => store.map(|store| (store.summary(), store)),
+        Err(e) => {
+            tracing::warn!("Ignoring unreadable coverage: {e:#}");
+            None
+        }
+    };
+    overview_data.insert(
+        "test_coverage",
+        match &coverage {
+            Some((summary, store)) => {
+                let mut value = json!(summary);
+                value["source"] = json!(store.source);
+                value["imported_at"] = json!(store.imported_at);
+                value
+            }
+            None => serde_json::Value::Null,
+        },
+    );
+
     // Output in requested format
     match format {
         "json" => {
@@ -1386,6 +2246,35 @@ async fn generate_codebase_overview(
                 }
             }
 
+            if !project.languages.is_empty() {
+                println!();
+                println!("Languages by Size:");
+                for share in &project.languages {
+                    println!(
+                        "- {}: {}% ({} bytes in {} files)",
+                        share.language, share.percentage, share.bytes, share.files
+                    );
+                }
+            }
+
+            let files = &project.project_files;
+            println!();
+            println!("Project Files:");
+            for (label, location) in [
+                ("README", &files.readme),
+                ("License", &files.license),
+                ("Contributing guide", &files.contributing),
+            ] {
+                println!(
+                    "- {}: {}",
+                    label,
+                    location.as_deref().unwrap_or("not found")
+                );
+            }
+            if !files.build_files.is_empty() {
+                println!("- Build files: {}", files.build_files.join(", "));
+            }
+
             if total_relationships > 0 {
                 println!();
                 println!("Relationships:");
@@ -1425,25 +2314,17 @@ async fn generate_codebase_overview(
             println!("Test Coverage Indicators:");
             println!("- Test-to-code ratio: {:.2}", test_to_code_ratio);
 
-            if source_files > 0 {
-                // Calculate estimated test coverage based on test-to-code ratio
-                // This is a general heuristic that works across different project types:
-                // - Some projects have many small test files (high ratio)
-                // - Others have fewer but comprehensive test suites (lower ratio)
-                // - We use a sigmoid-like curve that plateaus around 90%
-
-                // Using tanh for a smooth S-curve that's project-agnostic
-                // This maps: 0 -> 0%, 0.5 -> ~45%, 1.0 -> ~70%, 2.0 -> ~85%, 3.0+ -> ~90%
-                let coverage_estimate = 90.0 * (test_to_code_ratio * 0.8).tanh();
-
-                // Add a small baseline for projects with any tests at all
-                let final_estimate = if test_files > 0 {
-                    (coverage_estimate + 10.0).min(90.0) // Cap at 90% as 100% is rare
-                } else {
-                    0.0
-                };
-
-                println!("- Estimated test coverage: {:.0}%", final_estimate);
+            match &coverage {
+                Some((summary, _)) => println!(
+                    "- Measured coverage: {}% of lines in {} functions; {} functions untested ({}%)",
+                    summary.line_percent,
+                    summary.measured,
+                    summary.untested,
+                    summary.untested_percent
+                ),
+                None => println!(
+                    "- Measured coverage: not imported (run `kotadb import-coverage <report>`)"
+                ),
             }
         }
     }
@@ -1535,10 +2416,9 @@ async fn show_relationship_statistics(db_path: &std::path::Path, _quiet: bool) -
     if graph_db_path.exists() {
         match std::fs::read(&graph_db_path) {
             Ok(graph_binary) => {
-                match bincode::deserialize::<
-                    kotadb::dependency_extractor::SerializableDependencyGraph,
-                >(&graph_binary)
-                {
+                match kotadb::dependency_extractor::SerializableDependencyGraph::from_bytes(
+                    &graph_binary,
+                ) {
                     Ok(serializable) => {
                         println!("   Database path: {:?}", graph_db_path);
                         println!("   Total relationships: {}", serializable.stats.edge_count);
@@ -1564,16 +2444,146 @@ async fn show_relationship_statistics(db_path: &std::path::Path, _quiet: bool) -
     Ok(())
 }
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    // Parse CLI args first to get verbosity settings
-    let cli = Cli::parse();
+/// Index a repository, then flush storage and rebuild the indices so it is searchable
+#[cfg(feature = "git-integration")]
+async fn index_codebase(
+    db: &Database,
+    db_path: &Path,
+    options: IndexCodebaseOptions,
+) -> Result<()> {
+    let quiet = options.quiet;
+    let indexing_result = IndexingService::new(db, db_path.to_path_buf())
+        .index_codebase(options)
+        .await?;
 
-    // Determine effective verbosity level, supporting legacy flags
-    let verbosity_level = if cli.verbose {
-        "verbose"
-    } else if cli.quiet {
-        "quiet"
+    // Print the formatted output from the service
+    if !indexing_result.formatted_output.is_empty() {
+        print!("{}", indexing_result.formatted_output);
+    }
+
+    // Exit with error if indexing failed
+    if !indexing_result.success {
+        if !indexing_result.errors.is_empty() {
+            eprintln!("Indexing errors:");
+            for error in &indexing_result.errors {
+                eprintln!("  - {}", error);
+            }
+        }
+        return Err(anyhow::anyhow!("Codebase indexing failed"));
+    }
+
+    // CRITICAL: Flush storage buffer to ensure all documents are persisted
+    // This fixes issue #553 where documents were buffered but not flushed for small repositories
+    if !quiet {
+        println!("💾 Flushing storage buffer...");
+    }
+    // The storage wrapper may be buffering writes for performance, so we need to flush
+    // This is especially important for small repositories that don't reach the buffer threshold
+    {
+        let mut storage = db.storage.lock_timed(LockedResource::Storage).await;
+        if let Err(e) = storage.flush().await {
+            eprintln!("Warning: Failed to flush storage: {}", e);
+        }
+    }
+
+    // CRITICAL: Rebuild indices after successful codebase indexing
+    // This populates the Primary Index with document paths, enabling wildcard searches
+    if !quiet {
+        println!("🔄 Rebuilding indices to enable search functionality...");
+    }
+    db.rebuild_indices().await?;
+
+    if !quiet {
+        println!("✅ Index rebuild completed. Search functionality is now available.");
+    }
+
+    Ok(())
+}
+
+/// Subcommands and the flags given on the command line, without their values
+fn sanitized_command(matches: &clap::ArgMatches) -> String {
+    let mut parts = vec!["kotadb".to_string()];
+    let mut current = Some(matches);
+    while let Some(matches) = current {
+        parts.extend(
+            matches
+                .ids()
+                .filter(|id| {
+                    matches.value_source(id.as_str())
+                        == Some(clap::parser::ValueSource::CommandLine)
+                })
+                .map(|id| format!("--{}", id.as_str().replace('_', "-"))),
+        );
+        current = matches.subcommand().map(|(name, sub)| {
+            parts.push(name.to_string());
+            sub
+        });
+    }
+    parts.join(" ")
+}
+
+/// Whether the command in `matches` writes to the database and so needs its lease
+///
+/// `serve` counts as a writer: its indexing, notes and corpus routes write through the
+/// storage it opens at startup for as long as it runs.
+fn writes_database(matches: &clap::ArgMatches) -> bool {
+    match matches.subcommand() {
+        Some(("init" | "index-codebase" | "maintenance" | "serve" | "benchmark", _)) => true,
+        Some(("notes" | "synonyms", action)) => {
+            matches!(action.subcommand_name(), Some("add" | "remove"))
+        }
+        _ => false,
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    // Parse CLI args first to get verbosity settings
+    let matches = Cli::command().get_matches();
+    let mut cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    // Without --db-path, use the database of the initialized repository we are in
+    let db_path_given =
+        matches.value_source("db_path") == Some(clap::parser::ValueSource::CommandLine);
+    if !db_path_given {
+        #[cfg(feature = "git-integration")]
+        let init_root = match &cli.command {
+            Commands::Init { path, .. } => Some(path.clone()),
+            _ => None,
+        };
+        #[cfg(not(feature = "git-integration"))]
+        let init_root: Option<PathBuf> = None;
+        if let Some(root) = init_root {
+            let config = kotadb::project_init::ProjectConfig::load(&root)?.unwrap_or_default();
+            cli.db_path = config.resolved_db_path(&root);
+        } else if let Some((root, config)) =
+            kotadb::project_init::ProjectConfig::discover(&std::env::current_dir()?)?
+        {
+            cli.db_path = config.resolved_db_path(&root);
+        }
+    }
+    let command_name = matches.subcommand_name().unwrap_or_default().to_string();
+    let db_path = cli.db_path.clone();
+    let started = std::time::Instant::now();
+    kotadb::crash_report::install_panic_hook(&db_path, sanitized_command(&matches));
+
+    // Index files written by another kotadb would otherwise fail to deserialize on first
+    // use; commands that rewrite the indices or replace the binary still run
+    if !matches!(
+        command_name.as_str(),
+        "init" | "index-codebase" | "self-update" | "generate-fixture"
+    ) {
+        let mismatches = kotadb::compatibility::check(&db_path);
+        if !mismatches.is_empty() {
+            eprint!("{}", kotadb::compatibility::guidance(&mismatches));
+            std::process::exit(1);
+        }
+    }
+
+    // Determine effective verbosity level, supporting legacy flags
+    let verbosity_level = if cli.verbose {
+        "verbose"
+    } else if cli.quiet {
+        "quiet"
     } else {
         cli.verbosity.as_str()
     };
@@ -1597,10 +2607,46 @@ async fn main() -> Result<()> {
     // Store quiet flag for use in output (quiet for "quiet" level only)
     let quiet = verbosity_level == "quiet";
 
+    // One process writes a database directory at a time. Commands that only read open
+    // it read-only while another process holds the lease; commands that write fail
+    let ownership = match kotadb::db_lock::Ownership::claim(
+        &db_path,
+        &sanitized_command(&matches),
+        writes_database(&matches),
+        cli.allow_shared_db,
+    ) {
+        Ok(ownership) => ownership,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    let read_only_holder = match &ownership {
+        kotadb::db_lock::Ownership::ReadOnly(holder) => Some(holder.clone()),
+        _ => None,
+    };
+
     // Run everything within trace context
-    with_trace_id("kotadb-cli", async move {
+    let result = with_trace_id("kotadb-cli", async move {
         // Initialize database
-        let db = Database::new(&cli.db_path, cli.binary_index).await?;
+        let db = match &read_only_holder {
+            Some(holder) => {
+                if !quiet {
+                    eprintln!(
+                        "Note: {} is in use by {}; opening it read-only",
+                        cli.db_path.display(),
+                        holder
+                    );
+                }
+                Database::new_read_only(
+                    &cli.db_path,
+                    cli.binary_index,
+                    &format!("in use by {}", holder),
+                )
+                .await?
+            }
+            None => Database::new(&cli.db_path, cli.binary_index).await?,
+        };
 
         match cli.command {
             Commands::Serve { port } => {
@@ -1632,7 +2678,21 @@ async fn main() -> Result<()> {
             }
 
 
-            Commands::SearchCode { query, limit, tags, context } => {
+            Commands::SearchCode {
+                query,
+                limit,
+                tags,
+                context,
+                as_of,
+                group_by,
+                changed_since,
+                ext,
+                max_depth,
+                min_size,
+                max_size,
+                timeout_ms,
+            } => {
+                let group_by: SearchGrouping = group_by.parse()?;
                 // Handle empty query explicitly - return nothing with informative message
                 if query.is_empty() {
                     println!("Empty search query provided. Please specify a search term.");
@@ -1640,8 +2700,32 @@ async fn main() -> Result<()> {
                     return Ok(());
                 }
 
+                // Snapshots are only opened when a query asks for one
+                let query_db_path = database_path_as_of(&cli.db_path, as_of.as_deref())?;
+                let snapshot_db = match as_of {
+                    Some(_) => Some(Database::new(&query_db_path, cli.binary_index).await?),
+                    None => None,
+                };
+                let db = snapshot_db.as_ref().unwrap_or(&db);
+
                 // Create SearchService and use it for the search
-                let search_service = SearchService::new(&db, cli.db_path.clone());
+                let mut search_service = SearchService::new(db, query_db_path)
+                    .with_deadline(QueryDeadline::from_millis(timeout_ms));
+                if let Some(changed_since) = &changed_since {
+                    let changed = ChangedFiles::load(&DatabaseAccess::storage(db), changed_since).await?;
+                    qprintln!(quiet, "Limited to {}", changed.describe());
+                    search_service = search_service.with_changed_files(changed);
+                }
+                let filters = kotadb::DocumentFilters {
+                    max_depth,
+                    min_size,
+                    max_size,
+                    ..Default::default()
+                }
+                .with_extensions(ext.as_deref().unwrap_or_default().split(','));
+                if !filters.is_empty() {
+                    search_service = search_service.with_document_filters(filters);
+                }
                 let processed_tags = tags.as_ref().map(|t| t.split(',').map(String::from).collect());
                 let search_options = SearchOptions {
                     query: query.clone(),
@@ -1649,22 +2733,96 @@ async fn main() -> Result<()> {
                     tags: processed_tags.clone(),
                     context: context.clone(),
                     quiet,
+                    group_by,
                 };
 
+                let started = std::time::Instant::now();
                 let result = search_service.search_content(search_options).await?;
+                warn_if_timed_out(result.timed_out, timeout_ms);
+                record_step(
+                    &cli.record,
+                    &cli.db_path,
+                    ReplayQuery::SearchCode {
+                        query: query.clone(),
+                        limit,
+                        tags: processed_tags.clone(),
+                        context: context.clone(),
+                        group_by,
+                    },
+                    started.elapsed(),
+                    ReplayOutcome::Search(&result),
+                )?;
                 let output = format_search_result(&result, &SearchOptions {
                     query: query.clone(),
                     limit,
                     tags: processed_tags,
                     context: context.clone(),
                     quiet,
+                    group_by,
                 });
 
                 print!("{}", output);
+                if !result.expanded_queries.is_empty() {
+                    qprintln!(quiet, "Also searched synonyms: {}", result.expanded_queries.join(", "));
+                }
             }
 
 
-            Commands::Stats { basic, symbols, relationships } => {
+            Commands::Search { query, limit, format, changed_since, timeout_ms } => {
+                let mut search_service = SearchService::new(&db, cli.db_path.clone())
+                    .with_deadline(QueryDeadline::from_millis(timeout_ms));
+                match kotadb::SemanticSearchEngine::for_database(&cli.db_path).await {
+                    Ok(Some(engine)) => {
+                        search_service = search_service.with_semantic_engine(Arc::new(Mutex::new(engine)));
+                    }
+                    Ok(None) => {}
+                    Err(e) => qprintln!(quiet, "Warning: vector search unavailable: {:#}", e),
+                }
+                let (search_query, corpus) = CorpusFilter::from_query(&cli.db_path, &query)?;
+                if let Some(corpus) = corpus {
+                    search_service = search_service.with_corpus_filter(corpus);
+                }
+                if let Some(changed_since) = &changed_since {
+                    let changed = ChangedFiles::load(&DatabaseAccess::storage(&db), changed_since).await?;
+                    if format != "json" {
+                        qprintln!(quiet, "Limited to {}", changed.describe());
+                    }
+                    search_service = search_service.with_changed_files(changed);
+                }
+                let result = search_service
+                    .search_fused(FusedSearchOptions { query: search_query, limit, quiet })
+                    .await?;
+                warn_if_timed_out(result.timed_out, timeout_ms);
+
+                if format == "json" {
+                    println!("{}", serde_json::to_string_pretty(&result)?);
+                    return Ok(());
+                }
+                for error in &result.source_errors {
+                    qprintln!(quiet, "Warning: {} search failed", error);
+                }
+                if result.results.is_empty() {
+                    println!("No results found for '{}' (routed as {})", query, result.route.as_str());
+                    if !result.suggestions.is_empty() {
+                        print!("{}", format_did_you_mean(&result.suggestions));
+                    }
+                    return Ok(());
+                }
+                println!("Route: {}", result.route.as_str());
+                for (position, hit) in result.results.iter().enumerate() {
+                    let provenance: Vec<String> = hit
+                        .sources
+                        .iter()
+                        .map(|rank| format!("{} #{}", rank.source.as_str(), rank.rank))
+                        .collect();
+                    println!("{}. {} (score {:.4}; {})", position + 1, hit.path, hit.score, provenance.join(", "));
+                    for symbol in &hit.symbols {
+                        println!("   {} ({}:{})", symbol.name, symbol.file_path, symbol.start_line);
+                    }
+                }
+            }
+
+            Commands::Stats { basic, symbols, relationships, detailed, compare_previous } => {
                 // Use StatsService for comprehensive database statistics
                 let stats_service = StatsService::new(&db, cli.db_path.clone());
 
@@ -1672,7 +2830,8 @@ async fn main() -> Result<()> {
                     basic,
                     symbols,
                     relationships,
-                    detailed: false, // Could be added as CLI flag if needed
+                    detailed,
+                    compare_previous,
                     quiet,
                 };
 
@@ -1753,255 +2912,1258 @@ async fn main() -> Result<()> {
                     println!();
                 }
 
-                // Show critical issues
-                if !report.critical_issues.is_empty() {
-                    println!("🚨 Critical Issues Found:");
-                    for issue in &report.critical_issues {
-                        println!("   - {}", issue);
-                    }
-                    println!();
+                // Show critical issues
+                if !report.critical_issues.is_empty() {
+                    println!("🚨 Critical Issues Found:");
+                    for issue in &report.critical_issues {
+                        println!("   - {}", issue);
+                    }
+                    println!();
+                }
+
+                // Show recommendations
+                if !report.recommendations.is_empty() {
+                    println!("💡 Recommendations:");
+                    for rec in &report.recommendations {
+                        println!("   • {}", rec);
+                    }
+                    println!();
+                }
+
+                // Exit with error code if documentation is unacceptable
+                if !report.is_acceptable() {
+                    return Err(anyhow::anyhow!(
+                        "Documentation verification failed. {} critical issues found.",
+                        report.critical_issues.len()
+                    ));
+                }
+
+                println!("✨ Documentation verification completed successfully!");
+            }
+
+            #[cfg(feature = "git-integration")]
+            Commands::Init {
+                path,
+                hooks,
+                no_index,
+                force,
+            } => {
+                let db_path = db_path_given.then_some(cli.db_path.as_path());
+                let report = kotadb::project_init::scaffold(&path, db_path, force)?;
+                print!("{}", report.to_text());
+
+                if hooks {
+                    #[cfg(feature = "tree-sitter-parsing")]
+                    {
+                        use kotadb::git_hooks::{self, HookKind, DEFAULT_HOOK_BUDGET_MS};
+                        let installed = git_hooks::install(
+                            &path,
+                            &HookKind::ALL,
+                            &std::env::current_exe()?,
+                            &std::path::absolute(&cli.db_path)?,
+                            DEFAULT_HOOK_BUDGET_MS,
+                            force,
+                        )?;
+                        for hook in installed {
+                            println!("  Installed {}", hook.display());
+                        }
+                    }
+                    #[cfg(not(feature = "tree-sitter-parsing"))]
+                    return Err(anyhow::anyhow!(
+                        "Git hooks require the tree-sitter-parsing feature"
+                    ));
+                }
+
+                if no_index {
+                    println!("Run `kotadb index-codebase {}` to index it.", path.display());
+                } else {
+                    let indexing_options = IndexCodebaseOptions {
+                        repo_path: path,
+                        quiet,
+                        ..Default::default()
+                    };
+                    index_codebase(&db, &cli.db_path, indexing_options).await?;
+                }
+            }
+
+            #[cfg(feature = "git-integration")]
+            Commands::IndexCodebase {
+                repo_path,
+                prefix,
+                include_files,
+                include_commits,
+                max_file_size_mb,
+                max_memory_mb,
+                max_parallel_files,
+                enable_chunking,
+                #[cfg(feature = "tree-sitter-parsing")]
+                extract_symbols,
+                #[cfg(feature = "tree-sitter-parsing")]
+                no_symbols,
+                snapshot,
+            } => {
+                let indexing_options = IndexCodebaseOptions {
+                    repo_path: repo_path.clone(),
+                    prefix,
+                    include_files,
+                    include_commits,
+                    max_file_size_mb,
+                    max_memory_mb,
+                    max_parallel_files,
+                    enable_chunking,
+                    #[cfg(feature = "tree-sitter-parsing")]
+                    extract_symbols,
+                    #[cfg(not(feature = "tree-sitter-parsing"))]
+                    extract_symbols: None,
+                    no_symbols,
+                    quiet,
+                    include_paths: None,
+                    create_index: true,
+                };
+
+                index_codebase(&db, &cli.db_path, indexing_options).await?;
+
+                if snapshot {
+                    let snapshot = kotadb::index_snapshots::record_snapshot(&cli.db_path, &repo_path)?;
+                    qprintln!(
+                        quiet,
+                        "📸 Saved index snapshot of commit {} ({})",
+                        snapshot.dir,
+                        snapshot.committed_at.format("%Y-%m-%d %H:%M:%S UTC")
+                    );
+                }
+            }
+
+            #[cfg(feature = "tree-sitter-parsing")]
+            Commands::SearchSymbols {
+                pattern,
+                limit,
+                symbol_type,
+                krate,
+                changed_since,
+                timeout_ms,
+            } => {
+                // Check if symbols database exists - early exit with helpful message
+                let symbol_db_path = cli.db_path.join("symbols.kota");
+                if !symbol_db_path.exists() {
+                    println!("❌ No symbols found in database.");
+                    println!("   Required steps:");
+                    println!("   1. Index a codebase: kotadb index-codebase /path/to/repo");
+                    println!("   2. Verify indexing: kotadb symbol-stats");
+                    println!("   3. Then search: kotadb search-symbols 'pattern'");
+                    return Ok(());
+                }
+
+                // Create SearchService and use it for symbol search
+                let mut search_service = SearchService::new(&db, cli.db_path.clone())
+                    .with_deadline(QueryDeadline::from_millis(timeout_ms));
+                if let Some(krate) = &krate {
+                    search_service =
+                        search_service.with_crate(CrateMap::resolve(&cli.db_path, krate)?);
+                }
+                if let Some(changed_since) = &changed_since {
+                    let changed = ChangedFiles::load(&DatabaseAccess::storage(&db), changed_since).await?;
+                    qprintln!(quiet, "Limited to {}", changed.describe());
+                    search_service = search_service.with_changed_files(changed);
+                }
+                let symbol_options = SymbolSearchOptions {
+                    pattern: pattern.clone(),
+                    limit,
+                    symbol_type: symbol_type.clone(),
+                    quiet,
+                };
+
+                let started = std::time::Instant::now();
+                let result = search_service.search_symbols(symbol_options).await?;
+                warn_if_timed_out(result.timed_out, timeout_ms);
+                record_step(
+                    &cli.record,
+                    &cli.db_path,
+                    ReplayQuery::SearchSymbols {
+                        pattern: pattern.clone(),
+                        limit,
+                        symbol_type: symbol_type.clone(),
+                    },
+                    started.elapsed(),
+                    ReplayOutcome::Symbols(&result),
+                )?;
+
+                // Handle the case where database exists but has no symbols
+                if result.total_symbols == 0 {
+                    println!("No symbols in database. Index a codebase first with: kotadb index-codebase /path/to/repo");
+                    return Ok(());
+                }
+
+                let output = format_symbol_result(&result, &SymbolSearchOptions {
+                    pattern: pattern.clone(),
+                    limit,
+                    symbol_type: symbol_type.clone(),
+                    quiet,
+                }, &pattern);
+
+                print!("{}", output);
+            }
+
+            #[cfg(feature = "tree-sitter-parsing")]
+            Commands::FindCallers {
+                target,
+                limit,
+                explain,
+                format,
+                context_lines,
+                as_of,
+                krate,
+                timeout_ms,
+            } => {
+                let query_db_path = database_path_as_of(&cli.db_path, as_of.as_deref())?;
+                let db = Database::new(&query_db_path, true).await?;
+                let mut analysis_service = AnalysisService::new(&db, query_db_path.clone())
+                    .with_deadline(QueryDeadline::from_millis(timeout_ms));
+                if let Some(krate) = &krate {
+                    analysis_service =
+                        analysis_service.with_crate(CrateMap::resolve(&query_db_path, krate)?);
+                }
+                let options = CallersOptions {
+                    target: target.clone(),
+                    limit,
+                    offset: 0,
+                    sort_by: RelationshipSortBy::default(),
+                    group_by: ResultGrouping::default(),
+                    quiet,
+                    explain,
+                    context_lines,
+                };
+
+                let started = std::time::Instant::now();
+                let result = analysis_service.find_callers(options).await?;
+                warn_if_timed_out(result.timed_out, timeout_ms);
+                record_step(
+                    &cli.record,
+                    &cli.db_path,
+                    ReplayQuery::FindCallers { target: target.clone(), limit },
+                    started.elapsed(),
+                    ReplayOutcome::Callers(&result),
+                )?;
+
+                if let Some(explanation) = &result.explain {
+                    eprintln!("{}", serde_json::to_string_pretty(explanation)?);
+                }
+
+                if format == "json" {
+                    println!("{}", serde_json::to_string_pretty(&result)?);
+                } else if quiet {
+                    // In quiet mode, output one caller per line
+                    for site in &result.callers {
+                        println!(
+                            "{} {}:{}",
+                            site.caller,
+                            site.file_path,
+                            site.line_number.unwrap_or(0)
+                        );
+                    }
+                } else {
+                    println!("{}", result.markdown);
+                }
+            }
+
+            #[cfg(feature = "tree-sitter-parsing")]
+            Commands::AnalyzeImpact {
+                target,
+                limit,
+                explain,
+                format,
+                context_lines,
+                krate,
+                timeout_ms,
+            } => {
+                let db = Database::new(&cli.db_path, true).await?;
+                let mut analysis_service = AnalysisService::new(&db, cli.db_path.clone())
+                    .with_deadline(QueryDeadline::from_millis(timeout_ms));
+                if let Some(krate) = &krate {
+                    analysis_service =
+                        analysis_service.with_crate(CrateMap::resolve(&cli.db_path, krate)?);
+                }
+                let options = ImpactOptions {
+                    target: target.clone(),
+                    limit,
+                    offset: 0,
+                    sort_by: RelationshipSortBy::default(),
+                    group_by: ResultGrouping::default(),
+                    quiet,
+                    explain,
+                    context_lines,
+                };
+
+                let started = std::time::Instant::now();
+                let result = analysis_service.analyze_impact(options).await?;
+                warn_if_timed_out(result.timed_out, timeout_ms);
+                record_step(
+                    &cli.record,
+                    &cli.db_path,
+                    ReplayQuery::AnalyzeImpact { target: target.clone(), limit },
+                    started.elapsed(),
+                    ReplayOutcome::Impact(&result),
+                )?;
+
+                if let Some(explanation) = &result.explain {
+                    eprintln!("{}", serde_json::to_string_pretty(explanation)?);
+                }
+
+                if format == "json" {
+                    println!("{}", serde_json::to_string_pretty(&result)?);
+                } else if quiet {
+                    // In quiet mode, output one impacted symbol per line
+                    for site in &result.impacts {
+                        println!(
+                            "{} {}:{}",
+                            site.affected_symbol,
+                            site.file_path,
+                            site.line_number.unwrap_or(0)
+                        );
+                    }
+                } else {
+                    println!("{}", result.markdown);
+                }
+            }
+
+            #[cfg(feature = "tree-sitter-parsing")]
+            Commands::PreviewRename {
+                symbol,
+                new_name,
+                format,
+                krate,
+            } => {
+                let db = Database::new(&cli.db_path, true).await?;
+                let mut analysis_service = AnalysisService::new(&db, cli.db_path.clone());
+                if let Some(krate) = &krate {
+                    analysis_service =
+                        analysis_service.with_crate(CrateMap::resolve(&cli.db_path, krate)?);
+                }
+                let preview = analysis_service
+                    .preview_rename(RenamePreviewOptions {
+                        target: symbol,
+                        new_name,
+                    })
+                    .await?;
+                if format == "json" {
+                    println!("{}", serde_json::to_string_pretty(&preview)?);
+                } else {
+                    print!("{}", preview.to_markdown());
+                }
+            }
+
+            #[cfg(feature = "tree-sitter-parsing")]
+            Commands::Similar {
+                symbol,
+                limit,
+                format,
+            } => {
+                let provider = kotadb::embeddings::provider_from_env()?.ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "Similar-function search needs an embedding provider. \
+                         Set {} to embed function bodies with OpenAI.",
+                        kotadb::embeddings::OPENAI_API_KEY_ENV
+                    )
+                })?;
+                let db = Database::new(&cli.db_path, true).await?;
+                let mut analysis_service = AnalysisService::new(&db, cli.db_path.clone())
+                    .with_embedding_provider(std::sync::Arc::from(provider));
+                let result = analysis_service
+                    .find_similar_functions(SimilarOptions {
+                        target: symbol,
+                        limit,
+                        quiet,
+                    })
+                    .await?;
+
+                if format == "json" {
+                    println!("{}", serde_json::to_string_pretty(&result)?);
+                } else if quiet {
+                    for function in &result.matches {
+                        println!(
+                            "{:.3} {} {}:{}",
+                            function.similarity,
+                            function.name,
+                            function.file_path,
+                            function.start_line
+                        );
+                    }
+                } else {
+                    println!(
+                        "Functions similar to {} ({}:{}-{})",
+                        result.symbol, result.file_path, result.start_line, result.end_line
+                    );
+                    if result.newly_embedded > 0 {
+                        println!(
+                            "Embedded {} new or changed function bodies ({} indexed)",
+                            result.newly_embedded, result.functions_indexed
+                        );
+                    }
+                    if result.matches.is_empty() {
+                        println!("No similar functions found");
+                    }
+                    for function in &result.matches {
+                        println!(
+                            "  {:>5.1}%  {}  {}:{}-{}",
+                            function.similarity * 100.0,
+                            function.name,
+                            function.file_path,
+                            function.start_line,
+                            function.end_line
+                        );
+                    }
+                }
+            }
+
+            Commands::Benchmark {
+                operations,
+                benchmark_type,
+                format,
+                max_search_queries,
+                concurrency,
+            } => {
+                // Use BenchmarkService for comprehensive performance testing
+                let benchmark_service = BenchmarkService::new(&db, cli.db_path.clone());
+
+                let benchmark_options = BenchmarkOptions {
+                    operations,
+                    benchmark_type,
+                    format,
+                    max_search_queries,
+                    quiet,
+                    warm_up_operations: Some(100),
+                    concurrent_operations: Some(concurrency),
+                };
+
+                let benchmark_result = benchmark_service.run_benchmark(benchmark_options).await?;
+
+                // Print the formatted output from the service
+                if !benchmark_result.formatted_output.is_empty() {
+                    print!("{}", benchmark_result.formatted_output);
+                }
+            }
+
+            #[cfg(feature = "tree-sitter-parsing")]
+            Commands::CodebaseOverview {
+                format,
+                top_symbols_limit,
+                entry_points_limit,
+                as_of,
+            } => {
+                let query_db_path = database_path_as_of(&cli.db_path, as_of.as_deref())?;
+                let db = Database::new(&query_db_path, true).await?;
+                let analysis_service = AnalysisService::new(&db, query_db_path);
+                let options = OverviewOptions {
+                    format: format.clone(),
+                    top_symbols_limit,
+                    entry_points_limit,
+                    quiet,
+                };
+
+                let result = analysis_service.generate_overview(options).await?;
+                println!("{}", result.formatted_output);
+            }
+
+            #[cfg(feature = "tree-sitter-parsing")]
+            Commands::Gate {
+                config,
+                max_cycles,
+                max_unused,
+                min_test_ratio,
+                max_avg_complexity,
+                format,
+            } => {
+                use kotadb::gate::{evaluate, GateThresholds};
+
+                let flags = GateThresholds {
+                    max_cycles,
+                    max_unused,
+                    min_test_ratio,
+                    max_avg_complexity,
+                };
+                let thresholds = GateThresholds::resolve(flags, config.as_deref())?;
+
+                let db = Database::new(&cli.db_path, true).await?;
+                let report = evaluate(
+                    kotadb::services::storage_index_metrics(&cli.db_path, &db.storage).await?,
+                    &thresholds,
+                );
+                if format == "json" {
+                    println!("{}", serde_json::to_string_pretty(&report)?);
+                } else {
+                    print!("{}", report.to_markdown());
+                }
+                if !report.is_success() {
+                    return Err(anyhow::anyhow!(
+                        "{} of {} quality gate checks failed",
+                        report.failed(),
+                        report.checks.len()
+                    ));
+                }
+            }
+
+            #[cfg(feature = "tree-sitter-parsing")]
+            Commands::PublishChecks {
+                repo,
+                sha,
+                token,
+                api_url,
+                repo_root,
+                gate,
+                gate_config,
+                dead_code,
+                impact,
+                impact_limit,
+            } => {
+                use kotadb::gate::{evaluate, GateThresholds};
+                use kotadb::github_checks::{CheckRunPublisher, CheckRunReport};
+
+                if !gate && !dead_code && impact.is_empty() {
+                    return Err(anyhow::anyhow!(
+                        "Nothing to publish: pass --gate, --dead-code or --impact <SYMBOL>"
+                    ));
+                }
+                let publisher = CheckRunPublisher::new(&token, &repo)?.with_api_url(&api_url);
+                let repo_root = repo_root.canonicalize().unwrap_or(repo_root);
+                let db = Database::new(&cli.db_path, true).await?;
+
+                let mut gate_failed = None;
+                let mut reports = Vec::new();
+                if gate {
+                    let thresholds =
+                        GateThresholds::resolve(GateThresholds::default(), gate_config.as_deref())?;
+                    let report = evaluate(
+                        kotadb::services::storage_index_metrics(&cli.db_path, &db.storage)
+                            .await?,
+                        &thresholds,
+                    );
+                    if !report.is_success() {
+                        gate_failed = Some((report.failed(), report.checks.len()));
+                    }
+                    reports.push(CheckRunReport::from_gate(&report));
+                }
+                if dead_code {
+                    let unused = kotadb::services::find_unused_symbols(&cli.db_path)?;
+                    reports.push(CheckRunReport::from_unused(&unused, &repo_root));
+                }
+                for target in impact {
+                    let mut analysis_service = AnalysisService::new(&db, cli.db_path.clone());
+                    let result = analysis_service
+                        .analyze_impact(ImpactOptions {
+                            target: target.clone(),
+                            limit: Some(impact_limit),
+                            offset: 0,
+                            sort_by: RelationshipSortBy::default(),
+                            group_by: ResultGrouping::default(),
+                            quiet: true,
+                            explain: false,
+                            context_lines: None,
+                        })
+                        .await?;
+                    reports.push(CheckRunReport::from_impact(&target, &result, &repo_root));
+                }
+
+                for report in &reports {
+                    let url = publisher.publish(&sha, report).await?;
+                    println!(
+                        "{}: {} ({} annotations) {}",
+                        report.name,
+                        report.title,
+                        report.annotations.len(),
+                        url
+                    );
+                }
+                if let Some((failed, total)) = gate_failed {
+                    return Err(anyhow::anyhow!(
+                        "{} of {} quality gate checks failed",
+                        failed,
+                        total
+                    ));
+                }
+            }
+
+            #[cfg(feature = "tree-sitter-parsing")]
+            Commands::Trends { limit, format } => {
+                let db = Database::new(&cli.db_path, true).await?;
+                let analysis_service = AnalysisService::new(&db, cli.db_path.clone());
+                let result = analysis_service.trends(TrendsOptions { limit })?;
+                if format == "json" {
+                    println!("{}", serde_json::to_string_pretty(&result)?);
+                } else {
+                    print!("{}", result.to_markdown());
+                }
+            }
+
+            Commands::Snapshots { format } => {
+                let manifest = kotadb::index_snapshots::SnapshotManifest::load(&cli.db_path)?;
+                if format == "json" {
+                    println!("{}", serde_json::to_string_pretty(&manifest.snapshots)?);
+                } else {
+                    print!("{}", manifest.to_markdown());
+                }
+            }
+
+            #[cfg(feature = "tree-sitter-parsing")]
+            Commands::SymbolEvolution { name, format } => {
+                let evolution = kotadb::symbol_evolution::trace_symbol(&cli.db_path, &name).await?;
+                if format == "json" {
+                    println!("{}", serde_json::to_string_pretty(&evolution)?);
+                } else {
+                    print!("{}", evolution.to_markdown());
+                }
+            }
+
+            #[cfg(feature = "tree-sitter-parsing")]
+            Commands::ImportCoverage { report, format } => {
+                let format = format
+                    .map(|f| f.parse::<kotadb::coverage::ReportFormat>())
+                    .transpose()?;
+                let store = kotadb::coverage::import_report(&cli.db_path, &report, format)?;
+                let summary = store.summary();
+                if !quiet {
+                    println!(
+                        "Imported coverage for {} functions from {} of {} report files",
+                        summary.measured, store.files_matched, store.files_in_report
+                    );
+                    println!(
+                        "- {}% of their lines covered; {} functions untested ({}%)",
+                        summary.line_percent, summary.untested, summary.untested_percent
+                    );
+                }
+            }
+
+            #[cfg(feature = "tree-sitter-parsing")]
+            Commands::ImportTraces { trace, format } => {
+                let format = format
+                    .map(|f| f.parse::<kotadb::runtime_traces::TraceFormat>())
+                    .transpose()?;
+                let profile = kotadb::runtime_traces::import_traces(&cli.db_path, &trace, format)?;
+                if !quiet {
+                    let unmatched = profile.edges.iter().filter(|e| !e.in_graph).count();
+                    println!(
+                        "Imported {} observations: {} symbols and {} call edges observed",
+                        profile.observations,
+                        profile.symbols.len(),
+                        profile.edges.len()
+                    );
+                    println!(
+                        "- {} edges not found by static analysis; {} frame names unresolved",
+                        unmatched, profile.unresolved_frames
+                    );
+                }
+            }
+
+            #[cfg(feature = "tree-sitter-parsing")]
+            Commands::HotPaths { limit, format } => {
+                let db = Database::new(&cli.db_path, true).await?;
+                let analysis_service = AnalysisService::new(&db, cli.db_path.clone());
+                let result = analysis_service.hot_paths(HotPathsOptions { limit })?;
+                if format == "json" {
+                    println!("{}", serde_json::to_string_pretty(&result)?);
+                } else {
+                    print!("{}", result.to_markdown());
+                }
+            }
+
+            #[cfg(feature = "tree-sitter-parsing")]
+            Commands::LocateLog {
+                message,
+                limit,
+                format,
+            } => {
+                let db = Database::new(&cli.db_path, true).await?;
+                let analysis_service = AnalysisService::new(&db, cli.db_path.clone());
+                let result = analysis_service.locate_log(LocateLogOptions { message, limit })?;
+                if format == "json" {
+                    println!("{}", serde_json::to_string_pretty(&result)?);
+                } else {
+                    print!("{}", result.to_markdown());
+                }
+            }
+
+            #[cfg(feature = "tree-sitter-parsing")]
+            Commands::ErrorFlows {
+                error_type,
+                limit,
+                format,
+            } => {
+                let db = Database::new(&cli.db_path, true).await?;
+                let analysis_service = AnalysisService::new(&db, cli.db_path.clone());
+                let flows = analysis_service
+                    .error_flows(ErrorFlowOptions { error_type, limit })
+                    .await?;
+                if format == "json" {
+                    println!("{}", serde_json::to_string_pretty(&flows)?);
+                } else {
+                    print!("{}", flows.to_markdown());
+                }
+            }
+
+            #[cfg(feature = "tree-sitter-parsing")]
+            Commands::Flags { limit, format } => {
+                let db = Database::new(&cli.db_path, true).await?;
+                let analysis_service = AnalysisService::new(&db, cli.db_path.clone());
+                let result = analysis_service.feature_flags(FlagsOptions { limit })?;
+                if format == "json" {
+                    println!("{}", serde_json::to_string_pretty(&result)?);
+                } else {
+                    print!("{}", result.to_markdown());
+                }
+            }
+
+            #[cfg(feature = "tree-sitter-parsing")]
+            Commands::FlagImpact { name, format } => {
+                let db = Database::new(&cli.db_path, true).await?;
+                let analysis_service = AnalysisService::new(&db, cli.db_path.clone());
+                let impact = analysis_service.flag_impact(FlagImpactOptions { flag: name })?;
+                if format == "json" {
+                    println!("{}", serde_json::to_string_pretty(&impact)?);
+                } else {
+                    print!("{}", impact.to_markdown());
+                }
+            }
+
+            #[cfg(feature = "tree-sitter-parsing")]
+            Commands::ConfigUsages { key, limit, format } => {
+                let db = Database::new(&cli.db_path, true).await?;
+                let analysis_service = AnalysisService::new(&db, cli.db_path.clone());
+                let usages = analysis_service.config_usages(ConfigUsagesOptions { key, limit })?;
+                if format == "json" {
+                    println!("{}", serde_json::to_string_pretty(&usages)?);
+                } else {
+                    print!("{}", usages.to_markdown());
+                }
+            }
+
+            #[cfg(feature = "tree-sitter-parsing")]
+            Commands::TableUsages {
+                table,
+                limit,
+                format,
+            } => {
+                let db = Database::new(&cli.db_path, true).await?;
+                let analysis_service = AnalysisService::new(&db, cli.db_path.clone());
+                let usages = analysis_service.table_usages(TableUsagesOptions { table, limit })?;
+                if format == "json" {
+                    println!("{}", serde_json::to_string_pretty(&usages)?);
+                } else {
+                    print!("{}", usages.to_markdown());
+                }
+            }
+
+            #[cfg(feature = "tree-sitter-parsing")]
+            Commands::SuggestReviewers {
+                diff,
+                limit,
+                exclude,
+                repo,
+                codeowners,
+                format,
+            } => {
+                let diff = std::fs::read_to_string(&diff)
+                    .with_context(|| format!("Failed to read diff {}", diff.display()))?;
+                let codeowners = codeowners
+                    .map(|path| {
+                        std::fs::read_to_string(&path)
+                            .with_context(|| format!("Failed to read {}", path.display()))
+                    })
+                    .transpose()?;
+                let db = Database::new(&cli.db_path, true).await?;
+                let mut analysis_service = AnalysisService::new(&db, cli.db_path.clone());
+                let suggestions = analysis_service
+                    .suggest_reviewers(SuggestReviewersOptions {
+                        diff,
+                        limit,
+                        exclude,
+                        repo_path: repo,
+                        codeowners,
+                    })
+                    .await?;
+                if format == "json" {
+                    println!("{}", serde_json::to_string_pretty(&suggestions)?);
+                } else {
+                    print!("{}", suggestions.to_markdown());
                 }
+            }
 
-                // Show recommendations
-                if !report.recommendations.is_empty() {
-                    println!("💡 Recommendations:");
-                    for rec in &report.recommendations {
-                        println!("   • {}", rec);
+            Commands::Compare {
+                before,
+                after,
+                queries,
+                tool,
+                session,
+                limit,
+                context,
+                format,
+                fail_on_diff,
+            } => {
+                for snapshot in [&before, &after] {
+                    if !snapshot.exists() {
+                        return Err(anyhow::anyhow!(
+                            "Snapshot {} does not exist",
+                            snapshot.display()
+                        ));
                     }
-                    println!();
                 }
 
-                // Exit with error code if documentation is unacceptable
-                if !report.is_acceptable() {
-                    return Err(anyhow::anyhow!(
-                        "Documentation verification failed. {} critical issues found.",
-                        report.critical_issues.len()
-                    ));
+                let mut replay_queries: Vec<ReplayQuery> = queries
+                    .into_iter()
+                    .map(|query| match tool.as_str() {
+                        "search-symbols" => ReplayQuery::SearchSymbols {
+                            pattern: query,
+                            limit,
+                            symbol_type: None,
+                        },
+                        "find-callers" => ReplayQuery::FindCallers {
+                            target: query,
+                            limit: Some(limit),
+                        },
+                        "analyze-impact" => ReplayQuery::AnalyzeImpact {
+                            target: query,
+                            limit: Some(limit),
+                        },
+                        _ => ReplayQuery::SearchCode {
+                            query,
+                            limit,
+                            tags: None,
+                            context: context.clone(),
+                            group_by: SearchGrouping::default(),
+                        },
+                    })
+                    .collect();
+                if let Some(session) = session {
+                    replay_queries.extend(
+                        ReplaySession::load(&session)?
+                            .steps
+                            .into_iter()
+                            .map(|step| step.query),
+                    );
                 }
 
-                println!("✨ Documentation verification completed successfully!");
+                let before_db = kotadb::database::Database::new(&before, cli.binary_index).await?;
+                let after_db = kotadb::database::Database::new(&after, cli.binary_index).await?;
+                let report = kotadb::replay::diff_snapshots(
+                    (&before_db, before.as_path()),
+                    (&after_db, after.as_path()),
+                    &replay_queries,
+                )
+                .await?;
+
+                if format == "json" {
+                    println!("{}", serde_json::to_string_pretty(&report)?);
+                } else {
+                    print!("{}", report.to_markdown());
+                }
+                if fail_on_diff && report.has_differences() {
+                    return Err(anyhow::anyhow!("Snapshots returned different results"));
+                }
             }
 
-            #[cfg(feature = "git-integration")]
-            Commands::IndexCodebase {
-                repo_path,
-                prefix,
-                include_files,
-                include_commits,
-                max_file_size_mb,
-                max_memory_mb,
-                max_parallel_files,
-                enable_chunking,
-                #[cfg(feature = "tree-sitter-parsing")]
-                extract_symbols,
-                #[cfg(feature = "tree-sitter-parsing")]
-                no_symbols,
+            Commands::GenerateFixture {
+                languages,
+                size,
+                seed,
+                output,
+                force,
+                format,
             } => {
-                // Use IndexingService for codebase indexing operations
-                let indexing_service = IndexingService::new(&db, cli.db_path.clone());
+                use kotadb::fixture_generator::{generate, FixtureLanguage, FixtureOptions};
 
-                let indexing_options = IndexCodebaseOptions {
-                    repo_path,
-                    prefix,
-                    include_files,
-                    include_commits,
-                    max_file_size_mb,
-                    max_memory_mb,
-                    max_parallel_files,
-                    enable_chunking,
-                    #[cfg(feature = "tree-sitter-parsing")]
-                    extract_symbols,
-                    #[cfg(not(feature = "tree-sitter-parsing"))]
-                    extract_symbols: None,
-                    no_symbols,
-                    quiet,
-                    include_paths: None,
-                    create_index: true,
+                let options = FixtureOptions {
+                    languages: FixtureLanguage::parse_list(&languages)?,
+                    size: size.parse()?,
+                    seed,
                 };
+                let report = generate(&options).write(&output, force)?;
+                if format == "json" {
+                    println!("{}", serde_json::to_string_pretty(&report)?);
+                } else {
+                    print!("{}", report.to_text());
+                }
+            }
 
-                let indexing_result = indexing_service.index_codebase(indexing_options).await?;
+            Commands::Regression {
+                action: RegressionAction::Run { suite, format },
+            } => {
+                let suite = kotadb::regression::RegressionSuite::load(&suite)?;
+                let report = kotadb::regression::run_suite(&db, &cli.db_path, &suite).await?;
 
-                // Print the formatted output from the service
-                if !indexing_result.formatted_output.is_empty() {
-                    print!("{}", indexing_result.formatted_output);
+                if format == "json" {
+                    println!("{}", serde_json::to_string_pretty(&report)?);
+                } else {
+                    print!("{}", report.to_markdown());
+                }
+                if !report.is_success() {
+                    return Err(anyhow::anyhow!(
+                        "{} of {} golden queries failed",
+                        report.failed,
+                        report.queries.len()
+                    ));
                 }
+            }
 
-                // Exit with error if indexing failed
-                if !indexing_result.success {
-                    if !indexing_result.errors.is_empty() {
-                        eprintln!("Indexing errors:");
-                        for error in &indexing_result.errors {
-                            eprintln!("  - {}", error);
+            Commands::Warmup { overview, format } => {
+                let started = std::time::Instant::now();
+                let steps = kotadb::warmup::warm_up(&db, &cli.db_path, overview).await?;
+                if format == "json" {
+                    println!("{}", serde_json::to_string_pretty(&steps)?);
+                } else {
+                    for step in &steps {
+                        if step.skipped {
+                            println!("{:<18} skipped ({})", step.step, step.detail);
+                        } else {
+                            println!(
+                                "{:<18} {:>6} ms  {}",
+                                step.step, step.duration_ms, step.detail
+                            );
                         }
                     }
-                    return Err(anyhow::anyhow!("Codebase indexing failed"));
+                    println!("Warm-up finished in {} ms", started.elapsed().as_millis());
                 }
+            }
 
-                // CRITICAL: Flush storage buffer to ensure all documents are persisted
-                // This fixes issue #553 where documents were buffered but not flushed for small repositories
-                if !quiet {
-                    println!("💾 Flushing storage buffer...");
-                }
-                // The storage wrapper may be buffering writes for performance, so we need to flush
-                // This is especially important for small repositories that don't reach the buffer threshold
-                {
-                    let mut storage = db.storage.lock().await;
-                    if let Err(e) = storage.flush().await {
-                        eprintln!("Warning: Failed to flush storage: {}", e);
+            Commands::Maintenance { action } => match action {
+                MaintenanceAction::Status { format } => {
+                    let status = kotadb::maintenance::MaintenanceStatus::load(&cli.db_path)?;
+                    if format == "json" {
+                        println!("{}", serde_json::to_string_pretty(&status)?);
+                    } else {
+                        print!("{}", status.to_markdown());
                     }
                 }
+            },
 
-                // CRITICAL: Rebuild indices after successful codebase indexing
-                // This populates the Primary Index with document paths, enabling wildcard searches
-                if !quiet {
-                    println!("🔄 Rebuilding indices to enable search functionality...");
-                }
-                db.rebuild_indices().await?;
-
-                if !quiet {
-                    println!("✅ Index rebuild completed. Search functionality is now available.");
+            Commands::Synonyms { action } => {
+                use kotadb::synonyms::SynonymDictionary;
+
+                let mut dictionary = SynonymDictionary::load(&cli.db_path)?;
+                match action {
+                    SynonymsAction::Add { term, synonyms } => {
+                        let added = dictionary.add(&term, &synonyms)?;
+                        dictionary.save(&cli.db_path)?;
+                        let entry = dictionary.entries.get(&term.trim().to_lowercase());
+                        println!(
+                            "Added {} synonym(s): {} → {}",
+                            added,
+                            term.trim().to_lowercase(),
+                            entry.map(|list| list.join(", ")).unwrap_or_default()
+                        );
+                    }
+                    SynonymsAction::Remove { term, synonyms } => {
+                        if !dictionary.remove(&term, &synonyms)? {
+                            return Err(anyhow::anyhow!("No matching synonyms for '{}'", term));
+                        }
+                        dictionary.save(&cli.db_path)?;
+                        println!("Removed synonyms of '{}'", term.trim().to_lowercase());
+                    }
+                    SynonymsAction::List { format } => {
+                        if format == "json" {
+                            println!("{}", serde_json::to_string_pretty(&dictionary)?);
+                        } else if dictionary.is_empty() {
+                            println!("No synonyms defined. Add one with: kotadb synonyms add <term> <synonym>...");
+                        } else {
+                            for (term, synonyms) in &dictionary.entries {
+                                println!("{} → {}", term, synonyms.join(", "));
+                            }
+                        }
+                    }
                 }
             }
 
-            #[cfg(feature = "tree-sitter-parsing")]
-            Commands::SearchSymbols { pattern, limit, symbol_type } => {
-                // Check if symbols database exists - early exit with helpful message
-                let symbol_db_path = cli.db_path.join("symbols.kota");
-                if !symbol_db_path.exists() {
-                    println!("❌ No symbols found in database.");
-                    println!("   Required steps:");
-                    println!("   1. Index a codebase: kotadb index-codebase /path/to/repo");
-                    println!("   2. Verify indexing: kotadb symbol-stats");
-                    println!("   3. Then search: kotadb search-symbols 'pattern'");
-                    return Ok(());
-                }
-
-                // Create SearchService and use it for symbol search
-                let search_service = SearchService::new(&db, cli.db_path.clone());
-                let symbol_options = SymbolSearchOptions {
-                    pattern: pattern.clone(),
-                    limit,
-                    symbol_type: symbol_type.clone(),
-                    quiet,
-                };
-
-                let result = search_service.search_symbols(symbol_options).await?;
+            Commands::SelfUpdate { to, check } => {
+                use kotadb::self_update::{is_newer, Updater, CURRENT_VERSION};
 
-                // Handle the case where database exists but has no symbols
-                if result.total_symbols == 0 {
-                    println!("No symbols in database. Index a codebase first with: kotadb index-codebase /path/to/repo");
-                    return Ok(());
+                let updater = Updater::new()?;
+                let release = updater.release(to.as_deref()).await?;
+                let version = release.version();
+                if to.is_none() && !is_newer(version, CURRENT_VERSION) {
+                    println!("kotadb {CURRENT_VERSION} is up to date");
+                } else if check {
+                    println!(
+                        "kotadb {version} is available (running {CURRENT_VERSION}); \
+                         run `kotadb self-update` to install it"
+                    );
+                } else {
+                    let target = std::env::current_exe()?;
+                    updater.install(&release, &target).await?;
+                    println!(
+                        "Updated kotadb {CURRENT_VERSION} -> {version} at {}",
+                        target.display()
+                    );
                 }
-
-                let output = format_symbol_result(&result, &SymbolSearchOptions {
-                    pattern: pattern.clone(),
-                    limit,
-                    symbol_type: symbol_type.clone(),
-                    quiet,
-                }, &pattern);
-
-                print!("{}", output);
             }
 
-            #[cfg(feature = "tree-sitter-parsing")]
-            Commands::FindCallers { target, limit } => {
-                let db = Database::new(&cli.db_path, true).await?;
-                let mut analysis_service = AnalysisService::new(&db, cli.db_path.clone());
-                let options = CallersOptions {
-                    target: target.clone(),
-                    limit,
-                    quiet,
-                };
-
-                let result = analysis_service.find_callers(options).await?;
-
-                if quiet {
-                    // In quiet mode, output minimal information
-                    for line in result.markdown.lines() {
-                        if line.starts_with("- ") {
-                            println!("{}", line.trim_start_matches("- "));
+            Commands::Telemetry { action } => {
+                use kotadb::telemetry::{self, TelemetrySettings};
+
+                let mut settings = TelemetrySettings::load(&cli.db_path)?;
+                match action {
+                    TelemetryAction::Status { format } => {
+                        let queued = telemetry::queued_events(&cli.db_path)?.len();
+                        let endpoint = settings.effective_endpoint();
+                        if format == "json" {
+                            let status = serde_json::json!({
+                                "enabled": settings.enabled,
+                                "collecting": settings.collecting(),
+                                "installation_id": settings.installation_id,
+                                "endpoint": endpoint,
+                                "enabled_at": settings.enabled_at,
+                                "queued_events": queued,
+                            });
+                            println!("{}", serde_json::to_string_pretty(&status)?);
+                        } else {
+                            let state = match (settings.enabled, settings.collecting()) {
+                                (true, true) => "enabled",
+                                (true, false) => {
+                                    "enabled, paused by DO_NOT_TRACK or KOTADB_TELEMETRY_DISABLED"
+                                }
+                                _ => "disabled (nothing is collected)",
+                            };
+                            println!("Telemetry: {}", state);
+                            if settings.enabled {
+                                println!(
+                                    "Endpoint: {}",
+                                    endpoint
+                                        .as_deref()
+                                        .unwrap_or("none (events stay queued locally)")
+                                );
+                                println!("Queued events: {}", queued);
+                                println!(
+                                    "Collected: command names (no arguments), durations, success, \
+                                     database size class, KotaDB version and OS"
+                                );
+                            }
                         }
                     }
-                } else {
-                    println!("{}", result.markdown);
+                    TelemetryAction::Enable { endpoint } => {
+                        settings.enable(endpoint)?;
+                        settings.save(&cli.db_path)?;
+                        println!(
+                            "Telemetry enabled. Reports are sent after every {} commands{}.",
+                            telemetry::BATCH_SIZE,
+                            settings
+                                .effective_endpoint()
+                                .map(|endpoint| format!(" to {}", endpoint))
+                                .unwrap_or_else(|| {
+                                    ", once an endpoint is set with --endpoint".to_string()
+                                })
+                        );
+                    }
+                    TelemetryAction::Disable => {
+                        settings.disable();
+                        settings.save(&cli.db_path)?;
+                        telemetry::clear_queue(&cli.db_path)?;
+                        println!("Telemetry disabled and queued events deleted.");
+                    }
                 }
             }
 
             #[cfg(feature = "tree-sitter-parsing")]
-            Commands::AnalyzeImpact { target, limit } => {
-                let db = Database::new(&cli.db_path, true).await?;
-                let mut analysis_service = AnalysisService::new(&db, cli.db_path.clone());
-                let options = ImpactOptions {
-                    target: target.clone(),
-                    limit,
-                    quiet,
-                };
-
-                let result = analysis_service.analyze_impact(options).await?;
+            Commands::Hook { action } => {
+                use kotadb::git_hooks::{self, HookKind};
 
-                if quiet {
-                    // In quiet mode, output minimal information
-                    for line in result.markdown.lines() {
-                        if line.starts_with("- ") {
-                            println!("{}", line.trim_start_matches("- "));
+                let repo_root = Path::new(".");
+                let kinds = |names: &[String]| -> Result<Vec<HookKind>> {
+                    if names.is_empty() {
+                        return Ok(HookKind::ALL.to_vec());
+                    }
+                    names.iter().map(|name| HookKind::parse(name)).collect()
+                };
+                match action {
+                    HookAction::Install {
+                        hooks,
+                        force,
+                        budget_ms,
+                    } => {
+                        let kotadb = std::env::current_exe()?;
+                        let db_path = std::path::absolute(&cli.db_path)?;
+                        let installed = git_hooks::install(
+                            repo_root,
+                            &kinds(&hooks)?,
+                            &kotadb,
+                            &db_path,
+                            budget_ms,
+                            force,
+                        )?;
+                        for path in installed {
+                            println!("Installed {}", path.display());
+                        }
+                    }
+                    HookAction::Uninstall { hooks } => {
+                        let removed = git_hooks::uninstall(repo_root, &kinds(&hooks)?)?;
+                        if removed.is_empty() {
+                            println!("No kotadb hooks installed");
+                        }
+                        for path in removed {
+                            println!("Removed {}", path.display());
+                        }
+                    }
+                    HookAction::Run {
+                        hook,
+                        budget_ms,
+                        format,
+                    } => {
+                        let changes = match HookKind::parse(&hook)? {
+                            HookKind::PreCommit => git_hooks::staged_changes(repo_root)?,
+                            HookKind::PrePush => {
+                                use std::io::{IsTerminal, Read};
+
+                                let mut updates = String::new();
+                                if !std::io::stdin().is_terminal() {
+                                    std::io::stdin().read_to_string(&mut updates)?;
+                                }
+                                git_hooks::pushed_changes(repo_root, &updates)?
+                            }
+                        };
+                        let summary = git_hooks::analyze_changes(
+                            &cli.db_path,
+                            repo_root,
+                            &changes,
+                            std::time::Duration::from_millis(budget_ms),
+                        )?;
+                        if format == "json" {
+                            println!("{}", serde_json::to_string_pretty(&summary)?);
+                        } else {
+                            print!("{}", summary.to_text());
                         }
                     }
-                } else {
-                    println!("{}", result.markdown);
                 }
             }
 
+            #[cfg(feature = "tree-sitter-parsing")]
+            Commands::EditorServer { root } => {
+                use kotadb::editor_protocol::EditorServer;
 
-            Commands::Benchmark {
-                operations,
-                benchmark_type,
-                format,
-                max_search_queries,
-            } => {
-                // Use BenchmarkService for comprehensive performance testing
-                let benchmark_service = BenchmarkService::new(&db, cli.db_path.clone());
+                let db = Database::new(&cli.db_path, true).await?;
+                let root = std::path::absolute(&root)?;
+                let mut server = EditorServer::open(&cli.db_path, &root, db.storage.clone()).await?;
+                server
+                    .serve(
+                        tokio::io::BufReader::new(tokio::io::stdin()),
+                        tokio::io::stdout(),
+                    )
+                    .await?;
+            }
 
-                let benchmark_options = BenchmarkOptions {
-                    operations,
-                    benchmark_type,
-                    format,
-                    max_search_queries,
-                    quiet,
-                    warm_up_operations: Some(100),
-                    concurrent_operations: Some(1),
+            Commands::Notes { action } => {
+                use kotadb::annotations::{
+                    AnnotationKind, AnnotationStore, LineRange, NewNote, NoteFilter,
                 };
 
-                let benchmark_result = benchmark_service.run_benchmark(benchmark_options).await?;
-
-                // Print the formatted output from the service
-                if !benchmark_result.formatted_output.is_empty() {
-                    print!("{}", benchmark_result.formatted_output);
+                let mut store = AnnotationStore::load(&cli.db_path)?;
+                match action {
+                    NotesAction::Add { target, text, file, tags, bookmark, author } => {
+                        let (kind, target, lines) = if file {
+                            let (path, lines) = LineRange::split_target(&target);
+                            (AnnotationKind::File, path.to_string(), lines)
+                        } else {
+                            (AnnotationKind::Symbol, target, None)
+                        };
+                        let note = store.add_note(NewNote {
+                            kind,
+                            target,
+                            lines,
+                            text: text.unwrap_or_default(),
+                            tags,
+                            bookmark,
+                            author,
+                        })?;
+                        store.save(&cli.db_path)?;
+                        println!("Added note {} on {}", note.id, note.target);
+                    }
+                    NotesAction::List { target, tag, bookmarks, format } => {
+                        let notes = store.notes(&NoteFilter {
+                            kind: None,
+                            target,
+                            tag,
+                            bookmark: bookmarks.then_some(true),
+                        });
+                        if format == "json" {
+                            println!("{}", serde_json::to_string_pretty(&notes)?);
+                        } else if notes.is_empty() {
+                            println!("No notes found. Leave one with: kotadb notes add <symbol> <text>");
+                        } else {
+                            for note in notes {
+                                let lines = note
+                                    .lines
+                                    .map(|l| format!(":{}-{}", l.start, l.end))
+                                    .unwrap_or_default();
+                                let tags: String =
+                                    note.tags.iter().map(|tag| format!(" #{tag}")).collect();
+                                let text = if note.text.is_empty() {
+                                    String::new()
+                                } else {
+                                    format!(" — {}", note.text)
+                                };
+                                println!(
+                                    "{}{}{}{}{}  [{}]",
+                                    if note.bookmark { "★ " } else { "" },
+                                    note.target,
+                                    lines,
+                                    tags,
+                                    text,
+                                    note.id
+                                );
+                            }
+                        }
+                    }
+                    NotesAction::Remove { id } => {
+                        if !store.remove_note(&id) {
+                            return Err(anyhow::anyhow!("No note with id '{}'", id));
+                        }
+                        store.save(&cli.db_path)?;
+                        println!("Removed note {}", id);
+                    }
                 }
             }
 
-            #[cfg(feature = "tree-sitter-parsing")]
-            Commands::CodebaseOverview {
-                format,
-                top_symbols_limit,
-                entry_points_limit,
-            } => {
-                let db = Database::new(&cli.db_path, true).await?;
-                let analysis_service = AnalysisService::new(&db, cli.db_path.clone());
-                let options = OverviewOptions {
-                    format: format.clone(),
-                    top_symbols_limit,
-                    entry_points_limit,
-                    quiet,
+            Commands::Replay { action } => {
+                let (baseline, candidate, format) = match action {
+                    ReplayAction::Run { session, output, format } => {
+                        let baseline = ReplaySession::load(&session)?;
+                        let candidate = kotadb::replay::replay(&db, &cli.db_path, &baseline).await?;
+                        if let Some(output) = output {
+                            candidate.save(&output)?;
+                            qprintln!(quiet, "Saved replayed session to {}", output.display());
+                        }
+                        (baseline, candidate, format)
+                    }
+                    ReplayAction::Compare { baseline, candidate, format } => (
+                        ReplaySession::load(&baseline)?,
+                        ReplaySession::load(&candidate)?,
+                        format,
+                    ),
                 };
 
-                let result = analysis_service.generate_overview(options).await?;
-                println!("{}", result.formatted_output);
+                let report = kotadb::replay::compare(&baseline, &candidate);
+                if format == "json" {
+                    println!("{}", serde_json::to_string_pretty(&report)?);
+                } else {
+                    print!("{}", report.to_markdown());
+                }
             }
         }
 
         Ok::<(), anyhow::Error>(())
     })
-    .await
+    .await;
+
+    // Telemetry is opt-in; record_command does nothing unless it was enabled
+    if command_name != "telemetry" {
+        if let Err(e) = kotadb::telemetry::record_command(
+            &db_path,
+            &command_name,
+            started.elapsed(),
+            result.is_ok(),
+        )
+        .await
+        {
+            tracing::debug!("Telemetry not recorded: {}", e);
+        }
+    }
+
+    drop(ownership);
+    result
 }
 
 #[cfg(test)]
@@ -2221,7 +4383,7 @@ mod codebase_overview_tests {
             .expect("Failed to build document");
 
         db.storage
-            .lock()
+            .lock_timed(LockedResource::Storage)
             .await
             .insert(doc)
             .await
@@ -2307,7 +4469,7 @@ mod codebase_overview_tests {
                 .expect("Failed to build document");
 
             db.storage
-                .lock()
+                .lock_timed(LockedResource::Storage)
                 .await
                 .insert(doc)
                 .await
@@ -2384,7 +4546,7 @@ mod codebase_overview_tests {
                 .expect("Failed to build document");
 
             db.storage
-                .lock()
+                .lock_timed(LockedResource::Storage)
                 .await
                 .insert(doc)
                 .await
@@ -2451,6 +4613,10 @@ mod codebase_overview_tests {
                     relation_type: RelationType::Calls,
                     line_number: 1,
                     column_number: 10,
+                    end_line_number: None,
+                    end_column_number: None,
+                    start_byte: None,
+                    end_byte: None,
                     context: Some("helper()".to_string()),
                 },
             },
@@ -2461,6 +4627,10 @@ mod codebase_overview_tests {
                     relation_type: RelationType::Calls,
                     line_number: 1,
                     column_number: 5,
+                    end_line_number: None,
+                    end_column_number: None,
+                    start_byte: None,
+                    end_byte: None,
                     context: Some("helper()".to_string()),
                 },
             },
@@ -2485,7 +4655,7 @@ mod codebase_overview_tests {
             },
         };
 
-        let graph_binary = bincode::serialize(&graph).expect("Failed to serialize graph");
+        let graph_binary = graph.to_bytes().expect("Failed to serialize graph");
         fs::write(&graph_path, graph_binary).expect("Failed to write graph");
 
         // Generate overview and capture output
@@ -2501,8 +4671,8 @@ mod codebase_overview_tests {
 
         // Verify dependency graph can be read back
         let graph_data = fs::read(&graph_path).expect("Failed to read graph");
-        let deserialized: SerializableDependencyGraph =
-            bincode::deserialize(&graph_data).expect("Failed to deserialize graph");
+        let deserialized = SerializableDependencyGraph::from_bytes(&graph_data)
+            .expect("Failed to deserialize graph");
         assert_eq!(deserialized.edges.len(), 2, "Should have 2 edges");
         assert_eq!(deserialized.nodes.len(), 7, "Should have 7 nodes");
 
@@ -2512,4 +4682,59 @@ mod codebase_overview_tests {
         // - 2 languages detected (Rust and Python)
         // - Test file identified
     }
+
+    #[tokio::test]
+    async fn test_codebase_overview_markdown_and_yaml_formats() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let db_path = temp_dir.path();
+        let db = Database::new(db_path, true)
+            .await
+            .expect("Failed to create database");
+        let doc = DocumentBuilder::new()
+            .path("src/main.rs")
+            .expect("Failed to set path")
+            .title("main")
+            .expect("Failed to set title")
+            .content(b"fn main() {}")
+            .build()
+            .expect("Failed to build document");
+        db.storage
+            .lock_timed(LockedResource::Storage)
+            .await
+            .insert(doc)
+            .await
+            .expect("Failed to insert document");
+
+        let analysis_service = AnalysisService::new(&db, db_path.to_path_buf());
+        let overview = |format: &str| OverviewOptions {
+            format: format.to_string(),
+            top_symbols_limit: 10,
+            entry_points_limit: 10,
+            quiet: true,
+        };
+
+        let markdown = analysis_service
+            .generate_overview(overview("markdown"))
+            .await
+            .expect("Failed to generate markdown overview")
+            .formatted_output;
+        assert!(markdown.starts_with("# Codebase Overview\n\n## Scale\n"));
+        assert!(markdown.contains("| Total files | 1 |"));
+        assert!(markdown.contains("| Rust | 100.0% | 12 | 1 |"));
+        assert!(markdown.contains("## Test Coverage"));
+
+        let yaml = analysis_service
+            .generate_overview(overview("yaml"))
+            .await
+            .expect("Failed to generate YAML overview")
+            .formatted_output;
+        let parsed: serde_json::Value = serde_yaml::from_str(&yaml).expect("Invalid YAML");
+        assert_eq!(parsed["total_files"], 1);
+        assert_eq!(parsed["primary_language"], "Rust");
+
+        assert!(analysis_service
+            .generate_overview(overview("xml"))
+            .await
+            .is_err());
+    }
 }
@@ -0,0 +1,568 @@
+//! Scheduled background maintenance
+//!
+//! `kotadb serve` runs housekeeping tasks during a configured low-traffic window instead
+//! of on the request path: flushing storage and indices to disk (compaction), filling the
+//! path cache (cache warmup), precomputing the default codebase overview (overview
+//! refresh) and removing old index snapshots (snapshot pruning). Each enabled task runs
+//! once per occurrence of the window. The outcome, duration and amount of work of the last
+//! run of every task is recorded in `maintenance.json` in the database directory, which
+//! `kotadb maintenance status` reads.
+//!
+//! Configured from the environment:
+//! - `KOTADB_MAINTENANCE_WINDOW`: daily window in UTC, e.g. `02:00-05:00` (may wrap past
+//!   midnight); maintenance is off when unset
+//! - `KOTADB_MAINTENANCE_TASKS`: comma-separated tasks to run (default: all)
+//! - `KOTADB_MAINTENANCE_KEEP_SNAPSHOTS`: snapshots kept by pruning (default 20)
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Duration, NaiveTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use tracing::warn;
+
+use crate::index_snapshots::prune_snapshots;
+use crate::metrics::performance::{LockedResource, TimedLock};
+use crate::observability::{record_metric, MetricType};
+use crate::services::DatabaseAccess;
+use crate::QueryBuilder;
+
+/// File name of the maintenance status inside a database directory
+pub const MAINTENANCE_FILE: &str = "maintenance.json";
+
+/// How often the scheduler checks whether a task is due
+pub const MAINTENANCE_CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// Snapshots kept by pruning unless configured otherwise
+pub const DEFAULT_KEEP_SNAPSHOTS: usize = 20;
+
+/// Documents whose paths are loaded into the path cache by warmup
+const WARMUP_DOCUMENT_LIMIT: usize = 100_000;
+
+/// A housekeeping task run by the scheduler
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MaintenanceTask {
+    /// Flush storage and both indices so their files are rewritten from memory
+    Compaction,
+    /// Load every document path into the shared path cache
+    CacheWarmup,
+    /// Precompute the default codebase overview
+    OverviewRefresh,
+    /// Remove all but the newest index snapshots
+    SnapshotPruning,
+}
+
+impl MaintenanceTask {
+    pub const ALL: [MaintenanceTask; 4] = [
+        MaintenanceTask::Compaction,
+        MaintenanceTask::CacheWarmup,
+        MaintenanceTask::OverviewRefresh,
+        MaintenanceTask::SnapshotPruning,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MaintenanceTask::Compaction => "compaction",
+            MaintenanceTask::CacheWarmup => "cache_warmup",
+            MaintenanceTask::OverviewRefresh => "overview_refresh",
+            MaintenanceTask::SnapshotPruning => "snapshot_pruning",
+        }
+    }
+
+    fn metric_name(&self) -> &'static str {
+        match self {
+            MaintenanceTask::Compaction => "maintenance.compaction",
+            MaintenanceTask::CacheWarmup => "maintenance.cache_warmup",
+            MaintenanceTask::OverviewRefresh => "maintenance.overview_refresh",
+            MaintenanceTask::SnapshotPruning => "maintenance.snapshot_pruning",
+        }
+    }
+}
+
+impl std::str::FromStr for MaintenanceTask {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let name = s.trim().replace('-', "_");
+        MaintenanceTask::ALL
+            .into_iter()
+            .find(|task| task.as_str() == name)
+            .with_context(|| {
+                let known: Vec<&str> = MaintenanceTask::ALL.iter().map(|t| t.as_str()).collect();
+                format!(
+                    "Unknown maintenance task '{}'. Tasks: {}",
+                    s,
+                    known.join(", ")
+                )
+            })
+    }
+}
+
+/// Daily time window in UTC; ends before it starts when it spans midnight
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MaintenanceWindow {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+impl MaintenanceWindow {
+    /// Parse `HH:MM-HH:MM`
+    pub fn parse(text: &str) -> Result<Self> {
+        let Some((start, end)) = text.trim().split_once('-') else {
+            bail!("Maintenance window '{}' must look like 02:00-05:00", text);
+        };
+        let time = |part: &str| {
+            NaiveTime::parse_from_str(part.trim(), "%H:%M")
+                .with_context(|| format!("Invalid time '{}' in maintenance window", part.trim()))
+        };
+        Ok(Self {
+            start: time(start)?,
+            end: time(end)?,
+        })
+    }
+
+    fn length(&self) -> Duration {
+        let length = self.end.signed_duration_since(self.start);
+        if length <= Duration::zero() {
+            length + Duration::days(1)
+        } else {
+            length
+        }
+    }
+
+    /// Start of the occurrence of the window that `now` falls in, if any
+    pub fn current_start(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let today = now.date_naive().and_time(self.start).and_utc();
+        let latest = if today <= now {
+            today
+        } else {
+            today - Duration::days(1)
+        };
+        (now < latest + self.length()).then_some(latest)
+    }
+
+    /// Start of the next occurrence of the window after `now`
+    pub fn next_start(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        let today = now.date_naive().and_time(self.start).and_utc();
+        if today > now {
+            today
+        } else {
+            today + Duration::days(1)
+        }
+    }
+}
+
+impl std::fmt::Display for MaintenanceWindow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}-{} UTC",
+            self.start.format("%H:%M"),
+            self.end.format("%H:%M")
+        )
+    }
+}
+
+/// When and what the scheduler runs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MaintenanceConfig {
+    /// Window tasks run in; `None` disables maintenance
+    pub window: Option<MaintenanceWindow>,
+    pub tasks: Vec<MaintenanceTask>,
+    /// Snapshots kept by snapshot pruning
+    pub keep_snapshots: usize,
+}
+
+impl Default for MaintenanceConfig {
+    fn default() -> Self {
+        Self {
+            window: None,
+            tasks: MaintenanceTask::ALL.to_vec(),
+            keep_snapshots: DEFAULT_KEEP_SNAPSHOTS,
+        }
+    }
+}
+
+impl MaintenanceConfig {
+    /// Read the configuration from the environment; invalid values are logged and ignored
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let window = std::env::var("KOTADB_MAINTENANCE_WINDOW")
+            .ok()
+            .filter(|raw| !raw.trim().is_empty())
+            .and_then(|raw| match MaintenanceWindow::parse(&raw) {
+                Ok(window) => Some(window),
+                Err(e) => {
+                    warn!("Maintenance disabled: {e:#}");
+                    None
+                }
+            });
+        let tasks = match std::env::var("KOTADB_MAINTENANCE_TASKS") {
+            Ok(raw) => raw
+                .split(',')
+                .filter(|name| !name.trim().is_empty())
+                .filter_map(|name| match name.parse() {
+                    Ok(task) => Some(task),
+                    Err(e) => {
+                        warn!("Ignoring maintenance task: {e:#}");
+                        None
+                    }
+                })
+                .collect(),
+            Err(_) => defaults.tasks,
+        };
+        let keep_snapshots = match std::env::var("KOTADB_MAINTENANCE_KEEP_SNAPSHOTS") {
+            Ok(raw) => raw.trim().parse().unwrap_or_else(|_| {
+                warn!(
+                    "Ignoring invalid KOTADB_MAINTENANCE_KEEP_SNAPSHOTS '{}'; using {}",
+                    raw, defaults.keep_snapshots
+                );
+                defaults.keep_snapshots
+            }),
+            Err(_) => defaults.keep_snapshots,
+        };
+        Self {
+            window,
+            tasks,
+            keep_snapshots,
+        }
+    }
+
+    /// Enabled tasks that haven't run yet in the window occurrence `now` falls in
+    pub fn due_tasks(
+        &self,
+        status: &MaintenanceStatus,
+        now: DateTime<Utc>,
+    ) -> Vec<MaintenanceTask> {
+        let Some(window_start) = self.window.and_then(|window| window.current_start(now)) else {
+            return Vec::new();
+        };
+        self.tasks
+            .iter()
+            .copied()
+            .filter(|task| {
+                status
+                    .last_runs
+                    .get(task)
+                    .is_none_or(|run| run.started_at < window_start)
+            })
+            .collect()
+    }
+}
+
+/// How a task run ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RunOutcome {
+    Succeeded,
+    Failed,
+}
+
+/// Work done by a successful task run
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TaskReport {
+    /// Items processed: paths cached, snapshots removed, ...
+    pub items: u64,
+    pub detail: String,
+}
+
+/// The last run of a task
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TaskRun {
+    pub task: MaintenanceTask,
+    pub started_at: DateTime<Utc>,
+    pub duration_ms: u64,
+    pub outcome: RunOutcome,
+    pub items: u64,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub detail: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Scheduler configuration and last runs, as recorded in `maintenance.json`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MaintenanceStatus {
+    /// Configured window, e.g. `02:00-05:00 UTC`; `None` when maintenance is off
+    pub window: Option<String>,
+    #[serde(default)]
+    pub tasks: Vec<MaintenanceTask>,
+    /// Start of the next window, as of the last scheduler check
+    pub next_window: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub last_runs: BTreeMap<MaintenanceTask, TaskRun>,
+}
+
+impl MaintenanceStatus {
+    pub fn path_for(db_path: &Path) -> PathBuf {
+        db_path.join(MAINTENANCE_FILE)
+    }
+
+    /// Load the recorded status; empty when the scheduler never ran for this database
+    pub fn load(db_path: &Path) -> Result<Self> {
+        let path = Self::path_for(db_path);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read maintenance status: {}", path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse maintenance status: {}", path.display()))
+    }
+
+    /// Write the status atomically
+    pub fn save(&self, db_path: &Path) -> Result<()> {
+        let path = Self::path_for(db_path);
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_vec_pretty(self)?)
+            .with_context(|| format!("Failed to write maintenance status: {}", tmp.display()))?;
+        std::fs::rename(&tmp, &path)
+            .with_context(|| format!("Failed to replace maintenance status: {}", path.display()))?;
+        Ok(())
+    }
+
+    /// Record the configuration the scheduler runs with
+    pub fn configure(&mut self, config: &MaintenanceConfig, now: DateTime<Utc>) {
+        self.window = config.window.map(|window| window.to_string());
+        self.tasks = config.tasks.clone();
+        self.next_window = config.window.map(|window| window.next_start(now));
+    }
+
+    /// Format the status as markdown
+    pub fn to_markdown(&self) -> String {
+        let Some(window) = &self.window else {
+            return "Maintenance is not scheduled. Set KOTADB_MAINTENANCE_WINDOW (e.g. 02:00-05:00) for `kotadb serve`.\n".to_string();
+        };
+        let tasks: Vec<&str> = self.tasks.iter().map(|t| t.as_str()).collect();
+        let mut md = format!("Window: {}\nTasks: {}\n", window, tasks.join(", "));
+        if let Some(next) = self.next_window {
+            md.push_str(&format!(
+                "Next window: {}\n",
+                next.format("%Y-%m-%d %H:%M UTC")
+            ));
+        }
+        md.push('\n');
+        if self.last_runs.is_empty() {
+            md.push_str("No task has run yet.\n");
+            return md;
+        }
+        md.push_str("| Task | Last run | Duration | Outcome | Items | Details |\n");
+        md.push_str("|---|---|---|---|---|---|\n");
+        for run in self.last_runs.values() {
+            let outcome = match run.outcome {
+                RunOutcome::Succeeded => "ok",
+                RunOutcome::Failed => "failed",
+            };
+            md.push_str(&format!(
+                "| {} | {} | {} ms | {} | {} | {} |\n",
+                run.task.as_str(),
+                run.started_at.format("%Y-%m-%d %H:%M"),
+                run.duration_ms,
+                outcome,
+                run.items,
+                run.error.as_deref().unwrap_or(&run.detail)
+            ));
+        }
+        md
+    }
+}
+
+/// Run one task, timing it and recording a metric
+pub async fn run_task<F>(task: MaintenanceTask, work: F) -> TaskRun
+where
+    F: Future<Output = Result<TaskReport>>,
+{
+    let started_at = Utc::now();
+    let timer = Instant::now();
+    let result = work.await;
+    let elapsed = timer.elapsed();
+    record_metric(MetricType::Timer {
+        name: task.metric_name(),
+        duration: elapsed,
+    });
+    let (outcome, report, error) = match result {
+        Ok(report) => (RunOutcome::Succeeded, report, None),
+        Err(e) => {
+            warn!("Maintenance task {} failed: {e:#}", task.as_str());
+            (
+                RunOutcome::Failed,
+                TaskReport::default(),
+                Some(format!("{e:#}")),
+            )
+        }
+    };
+    TaskRun {
+        task,
+        started_at,
+        duration_ms: elapsed.as_millis() as u64,
+        outcome,
+        items: report.items,
+        detail: report.detail,
+        error,
+    }
+}
+
+/// Compaction: persist storage and rewrite both index files from memory
+pub async fn compact(database: &dyn DatabaseAccess) -> Result<TaskReport> {
+    {
+        let storage = database.storage();
+        let mut storage = storage.lock_timed(LockedResource::Storage).await;
+        storage.sync().await?;
+        storage.flush().await?;
+    }
+    database
+        .primary_index()
+        .lock_timed(LockedResource::PrimaryIndex)
+        .await
+        .flush()
+        .await?;
+    database
+        .trigram_index()
+        .lock_timed(LockedResource::TrigramIndex)
+        .await
+        .flush()
+        .await?;
+    Ok(TaskReport {
+        items: 3,
+        detail: "storage, primary and trigram index flushed".to_string(),
+    })
+}
+
+/// Cache warmup: resolve every document's path into the path cache
+pub async fn warm_path_cache(database: &dyn DatabaseAccess) -> Result<TaskReport> {
+    let query = QueryBuilder::new()
+        .with_limit(WARMUP_DOCUMENT_LIMIT)?
+        .build()?;
+    let ids = database
+        .primary_index()
+        .lock_timed(LockedResource::PrimaryIndex)
+        .await
+        .search(&query)
+        .await?;
+    let mut paths = Vec::with_capacity(ids.len());
+    {
+        let storage = database.storage();
+        let storage = storage.lock_timed(LockedResource::Storage).await;
+        for id in ids {
+            if let Some(summary) = storage.summary(&id).await? {
+                paths.push((summary.path.to_string(), id));
+            }
+        }
+    }
+    let cached = paths.len() as u64;
+    let path_cache = database.path_cache();
+    path_cache.write().await.extend(paths);
+    Ok(TaskReport {
+        items: cached,
+        detail: format!("{} document paths cached", cached),
+    })
+}
+
+/// Snapshot pruning: keep the newest `keep` snapshots
+pub fn prune(db_path: &Path, keep: usize) -> Result<TaskReport> {
+    let removed = prune_snapshots(db_path, keep)?;
+    let dirs: Vec<&str> = removed.iter().map(|s| s.dir.as_str()).collect();
+    Ok(TaskReport {
+        items: removed.len() as u64,
+        detail: if dirs.is_empty() {
+            format!("at most {} snapshots recorded", keep)
+        } else {
+            format!("removed {}", dirs.join(", "))
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(text: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(text)
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    #[test]
+    fn windows_may_span_midnight() {
+        let night = MaintenanceWindow::parse("23:30-02:00").unwrap();
+        assert_eq!(
+            night.current_start(at("2024-05-02T01:00:00Z")),
+            Some(at("2024-05-01T23:30:00Z"))
+        );
+        assert_eq!(
+            night.current_start(at("2024-05-01T23:45:00Z")),
+            Some(at("2024-05-01T23:30:00Z"))
+        );
+        assert_eq!(night.current_start(at("2024-05-02T02:00:00Z")), None);
+        assert_eq!(
+            night.next_start(at("2024-05-02T01:00:00Z")),
+            at("2024-05-02T23:30:00Z")
+        );
+        assert_eq!(night.to_string(), "23:30-02:00 UTC");
+
+        let early = MaintenanceWindow::parse("02:00-05:00").unwrap();
+        assert_eq!(early.current_start(at("2024-05-02T01:59:00Z")), None);
+        assert!(MaintenanceWindow::parse("2am").is_err());
+        assert!(MaintenanceWindow::parse("02:00-25:00").is_err());
+    }
+
+    #[test]
+    fn tasks_run_once_per_window() {
+        let config = MaintenanceConfig {
+            window: Some(MaintenanceWindow::parse("02:00-05:00").unwrap()),
+            tasks: vec![
+                MaintenanceTask::Compaction,
+                MaintenanceTask::SnapshotPruning,
+            ],
+            keep_snapshots: 5,
+        };
+        let mut status = MaintenanceStatus::default();
+        assert!(config
+            .due_tasks(&status, at("2024-05-02T12:00:00Z"))
+            .is_empty());
+        assert_eq!(
+            config.due_tasks(&status, at("2024-05-02T02:10:00Z")),
+            [
+                MaintenanceTask::Compaction,
+                MaintenanceTask::SnapshotPruning
+            ]
+        );
+
+        status.last_runs.insert(
+            MaintenanceTask::Compaction,
+            TaskRun {
+                task: MaintenanceTask::Compaction,
+                started_at: at("2024-05-02T02:10:00Z"),
+                duration_ms: 12,
+                outcome: RunOutcome::Succeeded,
+                items: 3,
+                detail: String::new(),
+                error: None,
+            },
+        );
+        assert_eq!(
+            config.due_tasks(&status, at("2024-05-02T04:00:00Z")),
+            [MaintenanceTask::SnapshotPruning]
+        );
+        assert_eq!(
+            config.due_tasks(&status, at("2024-05-03T02:00:00Z")),
+            [
+                MaintenanceTask::Compaction,
+                MaintenanceTask::SnapshotPruning
+            ]
+        );
+
+        status.configure(&config, at("2024-05-02T04:00:00Z"));
+        let markdown = status.to_markdown();
+        assert!(markdown.contains("Window: 02:00-05:00 UTC"));
+        assert!(markdown.contains("Next window: 2024-05-03 02:00 UTC"));
+        assert!(markdown.contains("| compaction | 2024-05-02 02:10 | 12 ms | ok | 3 |"));
+        assert_eq!(
+            "cache-warmup".parse::<MaintenanceTask>().unwrap(),
+            MaintenanceTask::CacheWarmup
+        );
+    }
+}
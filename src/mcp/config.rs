@@ -38,6 +38,42 @@ pub struct MCPProtocolConfig {
     pub enable_document_tools: bool,
     pub enable_search_tools: bool,
     pub enable_relationship_tools: bool,
+    /// Session file served tool calls are recorded to for `kotadb replay`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub record_session: Option<String>,
+    /// How long `kotadb://session_remember` keeps a working set after its last update
+    #[serde(default = "default_session_memory_ttl", with = "duration_string")]
+    pub session_memory_ttl: Duration,
+    /// Prefetch likely follow-up tool calls in streamable HTTP sessions
+    #[serde(default)]
+    pub prefetch: bool,
+    /// How long a prefetched response waits for the call it anticipates
+    #[serde(default = "default_prefetch_ttl", with = "duration_string")]
+    pub prefetch_ttl: Duration,
+    /// Least share of a tool's calls a follow-up must have to be prefetched
+    #[serde(default = "default_prefetch_min_probability")]
+    pub prefetch_min_probability: f64,
+}
+
+/// How long a prefetched response is kept when `prefetch_ttl` is not configured
+pub const DEFAULT_PREFETCH_TTL: Duration = Duration::from_secs(120);
+
+/// Prefetch threshold when `prefetch_min_probability` is not configured
+pub const DEFAULT_PREFETCH_MIN_PROBABILITY: f64 = 0.3;
+
+/// Environment variable that turns prefetching on (`1` or `true`)
+pub const PREFETCH_ENV: &str = "KOTADB_MCP_PREFETCH";
+
+fn default_session_memory_ttl() -> Duration {
+    crate::memory::DEFAULT_SESSION_TTL
+}
+
+fn default_prefetch_ttl() -> Duration {
+    DEFAULT_PREFETCH_TTL
+}
+
+fn default_prefetch_min_probability() -> f64 {
+    DEFAULT_PREFETCH_MIN_PROBABILITY
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -87,6 +123,11 @@ impl Default for MCPConfig {
                 enable_document_tools: false, // Disabled per issue #401 - pure codebase intelligence
                 enable_search_tools: true,
                 enable_relationship_tools: true,
+                record_session: None,
+                session_memory_ttl: default_session_memory_ttl(),
+                prefetch: false,
+                prefetch_ttl: DEFAULT_PREFETCH_TTL,
+                prefetch_min_probability: DEFAULT_PREFETCH_MIN_PROBABILITY,
             },
             logging: LoggingConfig {
                 level: "info".to_string(),
@@ -130,6 +171,13 @@ impl MCPConfig {
         if let Ok(data_dir) = std::env::var("KOTADB_DATA_DIR") {
             config.database.data_dir = data_dir;
         }
+        if let Ok(session) = std::env::var(crate::replay::RECORD_SESSION_ENV) {
+            config.mcp.record_session = Some(session).filter(|s| !s.trim().is_empty());
+        }
+        if let Ok(prefetch) = std::env::var(PREFETCH_ENV) {
+            config.mcp.prefetch =
+                matches!(prefetch.trim().to_ascii_lowercase().as_str(), "1" | "true");
+        }
 
         Ok(config)
     }
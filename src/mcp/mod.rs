@@ -3,6 +3,8 @@
 /// This module provides a JSON-RPC server that exposes KotaDB functionality
 /// through the Model Context Protocol, enabling seamless LLM integration.
 pub mod config;
+#[cfg(feature = "tree-sitter-parsing")]
+pub mod prefetch;
 pub mod resources;
 pub mod server;
 pub mod services_tools;
@@ -0,0 +1,555 @@
+//! Speculative prefetch of likely follow-up tool calls in MCP sessions
+//!
+//! Agents explore code in chains: they look a symbol up, ask for its callers, then read
+//! its definition and source. [`QueryPrefetcher`] learns how often one tool follows
+//! another on the same symbol within a session and, after a call is answered, runs the
+//! likely follow-ups in the background so the agent's next call is served from memory.
+//! Until a tool has been seen [`MIN_OBSERVATIONS`] times, the common chain
+//! `symbol_search` → `find_callers` → `symbol_brief` is assumed.
+//!
+//! Prefetched responses are used once, expire after a TTL and are dropped when
+//! re-indexing changes the graph generation. [`PrefetchStats`] reports hits, misses and
+//! wasted prefetches per transition so the threshold can be tuned.
+
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::binary_relationship_engine_async::graph_generation;
+use crate::mcp::config::{DEFAULT_PREFETCH_MIN_PROBABILITY, DEFAULT_PREFETCH_TTL};
+
+/// Calls of a tool observed before its learned transitions replace the default chain
+pub const MIN_OBSERVATIONS: u64 = 5;
+
+/// Follow-ups prefetched after one call
+const MAX_FOLLOW_UPS: usize = 2;
+
+/// Prefetched or running responses kept at once
+const MAX_ENTRIES: usize = 256;
+
+/// Tools that can be prefetched; each is called with the symbol as its only parameter
+const PREFETCHABLE: [&str; 3] = [
+    "kotadb://find_callers",
+    "kotadb://symbol_brief",
+    "kotadb://impact_analysis",
+];
+
+/// Follow-up probabilities assumed before a tool has been observed often enough
+const DEFAULT_CHAIN: [(&str, &str, f64); 2] = [
+    ("kotadb://symbol_search", "kotadb://find_callers", 0.5),
+    ("kotadb://find_callers", "kotadb://symbol_brief", 0.5),
+];
+
+/// Tuning of a [`QueryPrefetcher`]
+#[derive(Debug, Clone)]
+pub struct PrefetchConfig {
+    /// How long a prefetched response is kept, and how long a session's last call
+    /// still counts as the predecessor of the next
+    pub ttl: Duration,
+    /// Least probability of a follow-up for it to be prefetched
+    pub min_probability: f64,
+}
+
+impl Default for PrefetchConfig {
+    fn default() -> Self {
+        Self {
+            ttl: DEFAULT_PREFETCH_TTL,
+            min_probability: DEFAULT_PREFETCH_MIN_PROBABILITY,
+        }
+    }
+}
+
+/// A follow-up tool call to run ahead of the agent
+#[derive(Debug, Clone, PartialEq)]
+pub struct Prefetch {
+    pub method: String,
+    pub params: Value,
+    /// Tool whose call predicted this one
+    pub after: String,
+}
+
+/// Counters of one tool-to-tool transition
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct TransitionStats {
+    pub from: String,
+    pub to: String,
+    /// Times the agent called `to` on the symbol it had just passed to `from`
+    pub observed: u64,
+    /// Current estimate that a call of `from` is followed by `to`
+    pub probability: f64,
+    pub prefetched: u64,
+    pub hits: u64,
+    /// Prefetched responses that expired, were evicted or went stale unused
+    pub wasted: u64,
+}
+
+/// Prefetch counters since the server started
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct PrefetchStats {
+    /// Follow-up calls started in the background
+    pub issued: u64,
+    /// Calls answered from a prefetched response
+    pub hits: u64,
+    /// Calls of prefetchable tools with no prefetched response ready
+    pub misses: u64,
+    pub wasted: u64,
+    /// Follow-up calls that returned an error
+    pub failed: u64,
+    /// Responses prefetched or still running
+    pub entries: usize,
+    /// Share of prefetchable calls answered from a prefetched response
+    pub hit_rate: f64,
+    /// Share of issued prefetches that were used
+    pub precision: f64,
+    /// Observed transitions, most observed first
+    pub transitions: Vec<TransitionStats>,
+}
+
+/// The last symbol call of a session
+struct SessionTrail {
+    method: String,
+    target: String,
+    at: Instant,
+}
+
+/// A prefetched response, or `None` while its call is running
+struct Entry {
+    response: Option<Value>,
+    after: String,
+    stored_at: Instant,
+    generation: u64,
+}
+
+#[derive(Default)]
+struct TransitionCounts {
+    observed: u64,
+    prefetched: u64,
+    hits: u64,
+    wasted: u64,
+}
+
+#[derive(Default)]
+struct PrefetchState {
+    sessions: HashMap<String, SessionTrail>,
+    /// Symbol calls per tool
+    calls: HashMap<String, u64>,
+    transitions: HashMap<(String, String), TransitionCounts>,
+    entries: HashMap<String, Entry>,
+    issued: u64,
+    hits: u64,
+    misses: u64,
+    wasted: u64,
+    failed: u64,
+}
+
+impl PrefetchState {
+    /// Estimated probability that a call of `from` is followed by `to` on the same symbol
+    fn probability(&self, from: &str, to: &str) -> f64 {
+        let calls = self.calls.get(from).copied().unwrap_or(0);
+        if calls < MIN_OBSERVATIONS {
+            return DEFAULT_CHAIN
+                .iter()
+                .find(|(f, t, _)| *f == from && *t == to)
+                .map_or(0.0, |(_, _, p)| *p);
+        }
+        let observed = self
+            .transitions
+            .get(&(from.to_string(), to.to_string()))
+            .map_or(0, |t| t.observed);
+        observed as f64 / calls as f64
+    }
+
+    fn transition(&mut self, from: &str, to: &str) -> &mut TransitionCounts {
+        self.transitions
+            .entry((from.to_string(), to.to_string()))
+            .or_default()
+    }
+
+    /// Remove `key`, counting a finished but unused response as wasted
+    fn discard(&mut self, key: &str) {
+        if let Some(entry) = self.entries.remove(key) {
+            if entry.response.is_some() {
+                self.wasted += 1;
+                let to = key.split_once(' ').map_or(key, |(method, _)| method);
+                self.transition(&entry.after, to).wasted += 1;
+            }
+        }
+    }
+
+    /// Drop expired responses, then the oldest ones beyond [`MAX_ENTRIES`]
+    fn evict(&mut self, now: Instant, ttl: Duration) {
+        let expired: Vec<String> = self
+            .entries
+            .iter()
+            .filter(|(_, e)| e.response.is_some() && now.duration_since(e.stored_at) >= ttl)
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in expired {
+            self.discard(&key);
+        }
+        while self.entries.len() > MAX_ENTRIES {
+            let Some(oldest) = self
+                .entries
+                .iter()
+                .min_by_key(|(_, e)| e.stored_at)
+                .map(|(key, _)| key.clone())
+            else {
+                break;
+            };
+            self.discard(&oldest);
+        }
+    }
+}
+
+/// Learns which tool calls follow which in MCP sessions and keeps the responses of
+/// likely follow-ups ready
+pub struct QueryPrefetcher {
+    db_path: PathBuf,
+    config: PrefetchConfig,
+    state: Mutex<PrefetchState>,
+}
+
+impl QueryPrefetcher {
+    pub fn new(db_path: PathBuf, config: PrefetchConfig) -> Self {
+        Self {
+            db_path,
+            config,
+            state: Mutex::new(PrefetchState::default()),
+        }
+    }
+
+    /// The prefetched response to `method` with `params`, if one is ready and current
+    ///
+    /// A response is handed out once. Calls of prefetchable tools without one, including
+    /// calls whose prefetch is still running, count as misses.
+    pub fn take(&self, method: &str, params: &Value) -> Option<Value> {
+        if !PREFETCHABLE.contains(&method) {
+            return None;
+        }
+        let key = cache_key(method, params);
+        let generation = graph_generation(&self.db_path);
+        let now = Instant::now();
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let ready = state.entries.get(&key).is_some_and(|entry| {
+            entry.response.is_some()
+                && entry.generation == generation
+                && now.duration_since(entry.stored_at) < self.config.ttl
+        });
+        if !ready {
+            if state
+                .entries
+                .get(&key)
+                .is_some_and(|entry| entry.response.is_some())
+            {
+                state.discard(&key);
+            }
+            state.misses += 1;
+            return None;
+        }
+
+        let entry = state.entries.remove(&key)?;
+        state.hits += 1;
+        state.transition(&entry.after, method).hits += 1;
+        entry.response
+    }
+
+    /// Record a call answered in `session_id` and return the follow-ups to prefetch
+    ///
+    /// A call is a transition from the session's previous call when both name the same
+    /// symbol within the TTL. Follow-ups already prefetched or running are not returned
+    /// again.
+    pub fn observe(&self, session_id: &str, method: &str, params: &Value) -> Vec<Prefetch> {
+        let Some(target) = target_of(method, params) else {
+            return Vec::new();
+        };
+        let now = Instant::now();
+        let ttl = self.config.ttl;
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state
+            .sessions
+            .retain(|_, trail| now.duration_since(trail.at) < ttl);
+
+        *state.calls.entry(method.to_string()).or_default() += 1;
+        let previous = state.sessions.insert(
+            session_id.to_string(),
+            SessionTrail {
+                method: method.to_string(),
+                target: target.clone(),
+                at: now,
+            },
+        );
+        if let Some(previous) = previous.filter(|p| p.target == target && p.method != method) {
+            state.transition(&previous.method, method).observed += 1;
+        }
+        if state.sessions.len() > crate::memory::MAX_SESSIONS {
+            if let Some(oldest) = state
+                .sessions
+                .iter()
+                .min_by_key(|(_, trail)| trail.at)
+                .map(|(id, _)| id.clone())
+            {
+                state.sessions.remove(&oldest);
+            }
+        }
+
+        let mut follow_ups: Vec<(&str, f64)> = PREFETCHABLE
+            .iter()
+            .filter(|next| **next != method)
+            .map(|next| (*next, state.probability(method, next)))
+            .filter(|(_, p)| *p > 0.0 && *p >= self.config.min_probability)
+            .collect();
+        follow_ups.sort_by(|a, b| b.1.total_cmp(&a.1));
+        follow_ups.truncate(MAX_FOLLOW_UPS);
+
+        state.evict(now, ttl);
+        let mut prefetches = Vec::new();
+        for (next, _) in follow_ups {
+            let params = serde_json::json!({ "target": target });
+            let key = cache_key(next, &params);
+            if state.entries.contains_key(&key) {
+                continue;
+            }
+            state.entries.insert(
+                key,
+                Entry {
+                    response: None,
+                    after: method.to_string(),
+                    stored_at: now,
+                    generation: 0,
+                },
+            );
+            state.issued += 1;
+            state.transition(method, next).prefetched += 1;
+            prefetches.push(Prefetch {
+                method: next.to_string(),
+                params,
+                after: method.to_string(),
+            });
+        }
+        prefetches
+    }
+
+    /// Keep the response of a finished prefetch, or forget it if the call failed
+    pub fn complete(&self, prefetch: &Prefetch, result: anyhow::Result<Value>) {
+        let key = cache_key(&prefetch.method, &prefetch.params);
+        let generation = graph_generation(&self.db_path);
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        match result {
+            Ok(response) => {
+                if let Some(entry) = state.entries.get_mut(&key) {
+                    entry.response = Some(response);
+                    entry.stored_at = Instant::now();
+                    entry.generation = generation;
+                }
+            }
+            Err(e) => {
+                tracing::debug!("Prefetching {} failed: {e:#}", key);
+                state.entries.remove(&key);
+                state.failed += 1;
+            }
+        }
+    }
+
+    pub fn stats(&self) -> PrefetchStats {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let mut transitions: Vec<TransitionStats> = state
+            .transitions
+            .iter()
+            .map(|((from, to), counts)| TransitionStats {
+                from: from.clone(),
+                to: to.clone(),
+                observed: counts.observed,
+                probability: state.probability(from, to),
+                prefetched: counts.prefetched,
+                hits: counts.hits,
+                wasted: counts.wasted,
+            })
+            .collect();
+        transitions.sort_by(|a, b| {
+            b.observed
+                .cmp(&a.observed)
+                .then_with(|| (&a.from, &a.to).cmp(&(&b.from, &b.to)))
+        });
+        let ratio = |part: u64, whole: u64| {
+            if whole == 0 {
+                0.0
+            } else {
+                part as f64 / whole as f64
+            }
+        };
+        PrefetchStats {
+            issued: state.issued,
+            hits: state.hits,
+            misses: state.misses,
+            wasted: state.wasted,
+            failed: state.failed,
+            entries: state.entries.len(),
+            hit_rate: ratio(state.hits, state.hits + state.misses),
+            precision: ratio(state.hits, state.issued),
+            transitions,
+        }
+    }
+}
+
+/// The symbol a call is about: the `target` of relationship tools, or an exact
+/// (wildcard-free) `symbol_search` pattern
+fn target_of(method: &str, params: &Value) -> Option<String> {
+    let field = if method == "kotadb://symbol_search" {
+        "pattern"
+    } else {
+        "target"
+    };
+    params
+        .get(field)
+        .and_then(Value::as_str)
+        .map(str::trim)
+        .filter(|t| !t.is_empty() && !t.contains(['*', '?']))
+        .map(str::to_string)
+}
+
+/// Identity of a call: its method and parameters
+fn cache_key(method: &str, params: &Value) -> String {
+    format!("{method} {params}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    const SEARCH: &str = "kotadb://symbol_search";
+    const CALLERS: &str = "kotadb://find_callers";
+    const BRIEF: &str = "kotadb://symbol_brief";
+    const IMPACT: &str = "kotadb://impact_analysis";
+
+    fn prefetcher(dir: &TempDir) -> QueryPrefetcher {
+        QueryPrefetcher::new(dir.path().to_path_buf(), PrefetchConfig::default())
+    }
+
+    fn methods(prefetches: &[Prefetch]) -> Vec<&str> {
+        prefetches.iter().map(|p| p.method.as_str()).collect()
+    }
+
+    #[test]
+    fn follows_the_default_chain_before_learning() {
+        let dir = TempDir::new().unwrap();
+        let prefetcher = prefetcher(&dir);
+
+        let after_search = prefetcher.observe("s1", SEARCH, &json!({"pattern": "Storage"}));
+        assert_eq!(methods(&after_search), vec![CALLERS]);
+        assert_eq!(after_search[0].params, json!({"target": "Storage"}));
+        assert!(prefetcher
+            .observe("s1", SEARCH, &json!({"pattern": "Stor*"}))
+            .is_empty());
+
+        // A prefetch already running is not issued twice
+        assert!(prefetcher
+            .observe("s2", SEARCH, &json!({"pattern": "Storage"}))
+            .is_empty());
+        assert_eq!(prefetcher.stats().issued, 1);
+    }
+
+    #[test]
+    fn serves_a_prefetched_response_once() {
+        let dir = TempDir::new().unwrap();
+        let prefetcher = prefetcher(&dir);
+        let prefetch = prefetcher
+            .observe("s1", SEARCH, &json!({"pattern": "Storage"}))
+            .remove(0);
+
+        // Still running: the agent's call is a miss
+        assert_eq!(prefetcher.take(CALLERS, &prefetch.params), None);
+        prefetcher.complete(&prefetch, Ok(json!({"callers": ["main"]})));
+        assert_eq!(
+            prefetcher.take(CALLERS, &json!({"target": "Storage"})),
+            Some(json!({"callers": ["main"]}))
+        );
+        assert_eq!(prefetcher.take(CALLERS, &prefetch.params), None);
+        assert_eq!(
+            prefetcher.take(SEARCH, &json!({"pattern": "Storage"})),
+            None
+        );
+
+        let stats = prefetcher.stats();
+        assert_eq!((stats.hits, stats.misses), (1, 2));
+        assert_eq!(stats.precision, 1.0);
+        assert_eq!(stats.entries, 0);
+    }
+
+    #[test]
+    fn learns_transitions_from_session_chains() {
+        let dir = TempDir::new().unwrap();
+        let prefetcher = prefetcher(&dir);
+        for i in 0..MIN_OBSERVATIONS {
+            let session = format!("s{i}");
+            let target = json!({"target": format!("symbol_{i}")});
+            prefetcher.observe(&session, CALLERS, &target);
+            prefetcher.observe(&session, IMPACT, &target);
+        }
+
+        let stats = prefetcher.stats();
+        let learned = &stats.transitions[0];
+        assert_eq!(
+            (learned.from.as_str(), learned.to.as_str()),
+            (CALLERS, IMPACT)
+        );
+        assert_eq!(learned.observed, MIN_OBSERVATIONS);
+        assert_eq!(learned.probability, 1.0);
+
+        // Learned transitions replace the default chain
+        let prefetches = prefetcher.observe("next", CALLERS, &json!({"target": "Config"}));
+        assert_eq!(methods(&prefetches), vec![IMPACT]);
+
+        // Calls on another symbol are not transitions
+        prefetcher.observe("other", CALLERS, &json!({"target": "a"}));
+        prefetcher.observe("other", BRIEF, &json!({"target": "b"}));
+        assert!(prefetcher
+            .stats()
+            .transitions
+            .iter()
+            .all(|t| !(t.from == CALLERS && t.to == BRIEF && t.observed > 0)));
+    }
+
+    #[test]
+    fn drops_expired_and_failed_prefetches() {
+        let dir = TempDir::new().unwrap();
+        let prefetcher = QueryPrefetcher::new(
+            dir.path().to_path_buf(),
+            PrefetchConfig {
+                ttl: Duration::ZERO,
+                ..PrefetchConfig::default()
+            },
+        );
+        let prefetch = prefetcher
+            .observe("s1", SEARCH, &json!({"pattern": "Storage"}))
+            .remove(0);
+        prefetcher.complete(&prefetch, Ok(json!({})));
+        assert_eq!(prefetcher.take(CALLERS, &prefetch.params), None);
+        assert_eq!(prefetcher.stats().wasted, 1);
+
+        let prefetch = prefetcher
+            .observe("s1", SEARCH, &json!({"pattern": "Config"}))
+            .remove(0);
+        prefetcher.complete(&prefetch, Err(anyhow::anyhow!("No symbols found")));
+        let stats = prefetcher.stats();
+        assert_eq!((stats.failed, stats.entries), (1, 0));
+    }
+
+    #[test]
+    fn reindexing_invalidates_prefetched_responses() {
+        let dir = TempDir::new().unwrap();
+        let prefetcher = prefetcher(&dir);
+        let prefetch = prefetcher
+            .observe("s1", SEARCH, &json!({"pattern": "Storage"}))
+            .remove(0);
+        prefetcher.complete(&prefetch, Ok(json!({})));
+
+        std::fs::write(dir.path().join("symbols.kota"), b"reindexed").unwrap();
+        assert_eq!(prefetcher.take(CALLERS, &prefetch.params), None);
+        assert_eq!(prefetcher.stats().wasted, 1);
+    }
+}
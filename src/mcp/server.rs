@@ -150,22 +150,37 @@ impl MCPServer {
         if config.mcp.enable_relationship_tools {
             use crate::mcp::tools::relationship_tools::RelationshipTools;
             use crate::services::AnalysisServiceDatabase;
+            use crate::ValidatedDocumentId;
+            use std::collections::HashMap;
             use std::path::PathBuf;
+            use tokio::sync::RwLock;
 
             // Create database access wrapper for AnalysisService
             struct AnalysisServiceDatabaseImpl {
                 storage: Arc<Mutex<dyn Storage>>,
+                primary_index: Arc<Mutex<dyn Index>>,
+                path_cache: Arc<RwLock<HashMap<String, ValidatedDocumentId>>>,
             }
 
             impl AnalysisServiceDatabase for AnalysisServiceDatabaseImpl {
                 fn storage(&self) -> Arc<Mutex<dyn Storage>> {
                     self.storage.clone()
                 }
+
+                fn primary_index(&self) -> Arc<Mutex<dyn Index>> {
+                    self.primary_index.clone()
+                }
+
+                fn path_cache(&self) -> Arc<RwLock<HashMap<String, ValidatedDocumentId>>> {
+                    self.path_cache.clone()
+                }
             }
 
             let database_access: Arc<dyn AnalysisServiceDatabase> =
                 Arc::new(AnalysisServiceDatabaseImpl {
                     storage: storage.clone(),
+                    primary_index: primary_index.clone(),
+                    path_cache: Arc::new(RwLock::new(HashMap::new())),
                 });
 
             let db_path = PathBuf::from(&config.database.data_dir);
@@ -188,6 +203,37 @@ impl MCPServer {
             tool_registry = tool_registry.with_symbol_tools(symbol_tools);
         }
 
+        // Session memory tools need no database access and are always available
+        tool_registry = tool_registry.with_session_tools(Arc::new(
+            crate::mcp::tools::session_tools::SessionTools::new(Arc::new(
+                crate::memory::SessionMemory::new(config.mcp.session_memory_ttl),
+            )),
+        ));
+
+        if let Some(session) = &config.mcp.record_session {
+            tracing::info!("Recording MCP tool calls to {}", session);
+            tool_registry = tool_registry.with_session_recorder(Arc::new(
+                crate::replay::SessionRecorder::new(session, &config.database.data_dir),
+            ));
+        }
+
+        #[cfg(feature = "tree-sitter-parsing")]
+        if config.mcp.prefetch && config.mcp.enable_relationship_tools {
+            use crate::mcp::prefetch::{PrefetchConfig, QueryPrefetcher};
+
+            tracing::info!(
+                "Prefetching likely follow-up tool calls (min probability {})",
+                config.mcp.prefetch_min_probability
+            );
+            tool_registry = tool_registry.with_prefetcher(Arc::new(QueryPrefetcher::new(
+                std::path::PathBuf::from(&config.database.data_dir),
+                PrefetchConfig {
+                    ttl: config.mcp.prefetch_ttl,
+                    min_probability: config.mcp.prefetch_min_probability,
+                },
+            )));
+        }
+
         let tool_registry = Arc::new(tool_registry);
         let start_time = Instant::now();
         let streamable_state =
@@ -500,4 +546,47 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_session_tools_recall_remembered_working_set() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut config = MCPConfig::default();
+        config.database.data_dir = temp_dir.path().to_string_lossy().to_string();
+        config.mcp.enable_search_tools = false;
+
+        let server = MCPServer::new(config).await?;
+        let registry = &server.tool_registry;
+        registry
+            .handle_tool_call(
+                "kotadb://session_remember",
+                serde_json::json!({"session_id": "review-42", "symbols": ["Storage::get"]}),
+            )
+            .await?;
+        registry
+            .handle_tool_call(
+                "kotadb://session_remember",
+                serde_json::json!({"session_id": "review-42", "notes": ["get is cached"]}),
+            )
+            .await?;
+
+        let recalled = registry
+            .handle_tool_call(
+                "kotadb://session_recall",
+                serde_json::json!({"session_id": "review-42"}),
+            )
+            .await?;
+        assert_eq!(recalled["found"], true);
+        assert_eq!(recalled["symbols"], serde_json::json!(["Storage::get"]));
+        assert_eq!(recalled["notes"], serde_json::json!(["get is cached"]));
+
+        let unknown = registry
+            .handle_tool_call(
+                "kotadb://session_recall",
+                serde_json::json!({"session_id": "other"}),
+            )
+            .await?;
+        assert_eq!(unknown["found"], false);
+
+        Ok(())
+    }
 }
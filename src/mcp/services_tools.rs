@@ -110,6 +110,7 @@ impl ServicesMCPTools {
             symbols,
             relationships,
             detailed: false,
+            compare_previous: false,
             quiet: true, // MCP output should be structured
         };
 
@@ -135,7 +136,7 @@ impl ServicesMCPTools {
                     "benchmark_type": {
                         "type": "string",
                         "description": "Type of benchmark to run",
-                        "enum": ["storage", "index", "query", "all"],
+                        "enum": ["storage", "index", "query", "scan", "all"],
                         "default": "all"
                     }
                 },
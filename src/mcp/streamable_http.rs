@@ -395,7 +395,7 @@ async fn process_method_call(
             let session_id = state.require_session_header(&headers)?;
             let _session = session_manager.require_session(session_id).await?;
             let params = call.params.clone();
-            handle_tool_call(&registry, params).await
+            handle_tool_call(&registry, session_id, params).await
         }
         "resources/list" => {
             let _session = session_manager
@@ -452,6 +452,7 @@ async fn process_method_call(
 /// Helper to invoke tool registry for tools/call.
 async fn handle_tool_call(
     registry: &Arc<MCPToolRegistry>,
+    session_id: &str,
     params: Params,
 ) -> Result<Value, McpHttpError> {
     let params: Value = params.parse().map_err(|_| {
@@ -469,7 +470,7 @@ async fn handle_tool_call(
 
     let method = name.to_string();
     let response = registry
-        .handle_tool_call(&method, arguments)
+        .handle_session_tool_call(session_id, &method, arguments)
         .await
         .map_err(|err| {
             tracing::error!("MCP tool call failed for {}: {}", method, err);
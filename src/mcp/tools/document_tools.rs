@@ -1,5 +1,6 @@
 use crate::mcp::tools::MCPToolHandler;
 use crate::mcp::types::*;
+use crate::metrics::performance::{LockedResource, TimedLock};
 use crate::types::{ValidatedDocumentId, ValidatedPath, ValidatedTag, ValidatedTitle};
 use crate::DocumentBuilder;
 use anyhow::Result;
@@ -204,7 +205,7 @@ impl DocumentTools {
 
         // Store the document using the wrapped storage
         let storage = self.storage.clone();
-        let mut storage_guard = storage.lock().await;
+        let mut storage_guard = storage.lock_timed(LockedResource::Storage).await;
         storage_guard.insert(document).await?;
 
         let response = DocumentCreateResponse {
@@ -232,7 +233,7 @@ impl DocumentTools {
             .map_err(|e| anyhow::anyhow!("Invalid document ID: {}", e))?;
 
         let storage = self.storage.clone();
-        let storage_guard = storage.lock().await;
+        let storage_guard = storage.lock_timed(LockedResource::Storage).await;
 
         let document = storage_guard
             .get(&doc_id)
@@ -273,7 +274,7 @@ impl DocumentTools {
             .map_err(|e| anyhow::anyhow!("Invalid document ID: {}", e))?;
 
         let storage = self.storage.clone();
-        let mut storage_guard = storage.lock().await;
+        let mut storage_guard = storage.lock_timed(LockedResource::Storage).await;
 
         // Get existing document
         let mut document = storage_guard
@@ -336,7 +337,7 @@ impl DocumentTools {
             .map_err(|e| anyhow::anyhow!("Invalid document ID: {}", e))?;
 
         let storage = self.storage.clone();
-        let mut storage_guard = storage.lock().await;
+        let mut storage_guard = storage.lock_timed(LockedResource::Storage).await;
 
         let deleted = storage_guard.delete(&doc_id).await?;
 
@@ -362,7 +363,7 @@ impl DocumentTools {
         let start_time = std::time::Instant::now();
 
         let storage = self.storage.clone();
-        let storage_guard = storage.lock().await;
+        let storage_guard = storage.lock_timed(LockedResource::Storage).await;
 
         // For now, implement a simple list - in production this would use pagination/filtering
         let all_documents = storage_guard.list_all().await?;
@@ -17,9 +17,16 @@ pub mod symbol_tools;
 /// Lightweight text search (no embeddings) via MCP
 pub mod text_search_tools;
 
+/// Session memory tools - assistant working sets kept across calls
+pub mod session_tools;
+
+#[cfg(feature = "tree-sitter-parsing")]
+use crate::mcp::prefetch::QueryPrefetcher;
 use crate::mcp::types::*;
+use crate::replay::SessionRecorder;
 use anyhow::Result;
 use std::sync::Arc;
+use std::time::Instant;
 
 /// Trait for MCP tool handlers
 #[async_trait::async_trait]
@@ -41,6 +48,12 @@ pub struct MCPToolRegistry {
     pub relationship_tools: Option<Arc<relationship_tools::RelationshipTools>>,
     #[cfg(feature = "tree-sitter-parsing")]
     pub symbol_tools: Option<Arc<symbol_tools::SymbolTools>>,
+    pub session_tools: Option<Arc<session_tools::SessionTools>>,
+    /// Records served tool calls for `kotadb replay`
+    pub session_recorder: Option<Arc<SessionRecorder>>,
+    /// Prefetches likely follow-up calls in sessions
+    #[cfg(feature = "tree-sitter-parsing")]
+    pub prefetcher: Option<Arc<QueryPrefetcher>>,
 }
 
 impl Default for MCPToolRegistry {
@@ -58,6 +71,10 @@ impl MCPToolRegistry {
             relationship_tools: None,
             #[cfg(feature = "tree-sitter-parsing")]
             symbol_tools: None,
+            session_tools: None,
+            session_recorder: None,
+            #[cfg(feature = "tree-sitter-parsing")]
+            prefetcher: None,
         }
     }
 
@@ -90,6 +107,25 @@ impl MCPToolRegistry {
         self
     }
 
+    /// Register session memory tools
+    pub fn with_session_tools(mut self, tools: Arc<session_tools::SessionTools>) -> Self {
+        self.session_tools = Some(tools);
+        self
+    }
+
+    /// Record every replayable tool call to a session file
+    pub fn with_session_recorder(mut self, recorder: Arc<SessionRecorder>) -> Self {
+        self.session_recorder = Some(recorder);
+        self
+    }
+
+    /// Prefetch likely follow-up calls after each call in a session
+    #[cfg(feature = "tree-sitter-parsing")]
+    pub fn with_prefetcher(mut self, prefetcher: Arc<QueryPrefetcher>) -> Self {
+        self.prefetcher = Some(prefetcher);
+        self
+    }
+
     /// Get all available tool definitions
     pub fn get_all_tool_definitions(&self) -> Vec<ToolDefinition> {
         let mut definitions = Vec::new();
@@ -108,6 +144,23 @@ impl MCPToolRegistry {
         if let Some(tools) = &self.symbol_tools {
             definitions.extend(tools.get_tool_definitions());
         }
+        if let Some(tools) = &self.session_tools {
+            definitions.extend(tools.get_tool_definitions());
+        }
+        #[cfg(feature = "tree-sitter-parsing")]
+        if self.prefetcher.is_some() {
+            definitions.push(ToolDefinition {
+                name: "kotadb://prefetch_stats".to_string(),
+                description:
+                    "Show how often speculatively prefetched follow-up queries were used, \
+                              per observed tool-to-tool transition"
+                        .to_string(),
+                input_schema: serde_json::json!({
+                    "type": "object",
+                    "properties": {}
+                }),
+            });
+        }
 
         definitions
     }
@@ -120,6 +173,82 @@ impl MCPToolRegistry {
     ) -> Result<serde_json::Value> {
         tracing::debug!("Handling tool call: {}", method);
 
+        if self.session_recorder.is_none() {
+            return self.dispatch_tool_call(method, params).await;
+        }
+        let recorded_params = params.clone();
+        let started = Instant::now();
+        let response = self.dispatch_tool_call(method, params).await?;
+        self.record(method, &recorded_params, started, &response)
+            .await;
+        Ok(response)
+    }
+
+    /// Handle a tool call made in an MCP session
+    ///
+    /// With a prefetcher registered, the call is answered from a prefetched response
+    /// when one is ready, and the likely follow-ups are then started in the background.
+    pub async fn handle_session_tool_call(
+        self: &Arc<Self>,
+        session_id: &str,
+        method: &str,
+        params: serde_json::Value,
+    ) -> Result<serde_json::Value> {
+        #[cfg(feature = "tree-sitter-parsing")]
+        if let Some(prefetcher) = &self.prefetcher {
+            let started = Instant::now();
+            let response = match prefetcher.take(method, &params) {
+                Some(response) => {
+                    tracing::debug!("Serving {} from prefetch", method);
+                    self.record(method, &params, started, &response).await;
+                    response
+                }
+                None => self.handle_tool_call(method, params.clone()).await?,
+            };
+            for prefetch in prefetcher.observe(session_id, method, &params) {
+                let registry = Arc::clone(self);
+                let prefetcher = Arc::clone(prefetcher);
+                tokio::spawn(async move {
+                    let result = registry
+                        .dispatch_tool_call(&prefetch.method, prefetch.params.clone())
+                        .await;
+                    prefetcher.complete(&prefetch, result);
+                });
+            }
+            return Ok(response);
+        }
+        let _ = session_id;
+        self.handle_tool_call(method, params).await
+    }
+
+    async fn record(
+        &self,
+        method: &str,
+        params: &serde_json::Value,
+        started: Instant,
+        response: &serde_json::Value,
+    ) {
+        let Some(recorder) = &self.session_recorder else {
+            return;
+        };
+        if let Err(e) = recorder
+            .record_tool_call(method, params, started.elapsed(), response)
+            .await
+        {
+            tracing::warn!(
+                "Failed to record {} to {}: {}",
+                method,
+                recorder.path().display(),
+                e
+            );
+        }
+    }
+
+    async fn dispatch_tool_call(
+        &self,
+        method: &str,
+        params: serde_json::Value,
+    ) -> Result<serde_json::Value> {
         // Route to appropriate tool handler based on method prefix
         // Note: Document tools removed per issue #401
         match method {
@@ -157,7 +286,8 @@ impl MCPToolRegistry {
                 || m.starts_with("kotadb://unused_symbols")
                 || m.starts_with("kotadb://hot_paths")
                 || m.starts_with("kotadb://relationship_query")
-                || m.starts_with("kotadb://codebase_overview") =>
+                || m.starts_with("kotadb://codebase_overview")
+                || m.starts_with("kotadb://symbol_brief") =>
             {
                 if let Some(tools) = &self.relationship_tools {
                     tools.handle_call(method, params).await
@@ -173,6 +303,21 @@ impl MCPToolRegistry {
                     Err(anyhow::anyhow!("Symbol tools not enabled"))
                 }
             }
+            #[cfg(feature = "tree-sitter-parsing")]
+            "kotadb://prefetch_stats" => {
+                if let Some(prefetcher) = &self.prefetcher {
+                    Ok(serde_json::to_value(prefetcher.stats())?)
+                } else {
+                    Err(anyhow::anyhow!("Prefetching not enabled"))
+                }
+            }
+            m if m.starts_with("kotadb://session_") => {
+                if let Some(tools) = &self.session_tools {
+                    tools.handle_call(method, params).await
+                } else {
+                    Err(anyhow::anyhow!("Session tools not enabled"))
+                }
+            }
             _ => Err(anyhow::anyhow!("Unknown method: {}", method)),
         }
     }
@@ -1,6 +1,9 @@
 use crate::mcp::tools::MCPToolHandler;
 use crate::mcp::types::*;
-use crate::services::{AnalysisService, AnalysisServiceDatabase, CallersOptions, ImpactOptions};
+use crate::services::{
+    AnalysisService, AnalysisServiceDatabase, CallersOptions, ImpactOptions, RelationshipSortBy,
+    ResultGrouping,
+};
 use anyhow::Result;
 use std::path::PathBuf;
 use std::sync::Arc;
@@ -63,6 +66,10 @@ impl MCPToolHandler for RelationshipTools {
                 let request: CodebaseOverviewRequest = serde_json::from_value(params)?;
                 self.codebase_overview(request).await
             }
+            "kotadb://symbol_brief" => {
+                let request: SymbolBriefRequest = serde_json::from_value(params)?;
+                self.symbol_brief(request).await
+            }
             _ => Err(anyhow::anyhow!("Unknown relationship method: {}", method)),
         }
     }
@@ -159,7 +166,7 @@ impl MCPToolHandler for RelationshipTools {
             },
             ToolDefinition {
                 name: "kotadb://hot_paths".to_string(),
-                description: "Find the most frequently called symbols - identify performance bottlenecks".to_string(),
+                description: "Find the most frequently called symbols, by calls observed in imported runtime traces when available - identify performance bottlenecks".to_string(),
                 input_schema: serde_json::json!({
                     "type": "object",
                     "properties": {
@@ -194,8 +201,8 @@ impl MCPToolHandler for RelationshipTools {
                     "properties": {
                         "format": {
                             "type": "string",
-                            "description": "Output format: 'human' for readable text or 'json' for structured data",
-                            "enum": ["human", "json"],
+                            "description": "Output format: 'human' for readable text, 'markdown' for headings and tables, 'json' or 'yaml' for structured data",
+                            "enum": ["human", "json", "markdown", "yaml"],
                             "default": "human"
                         },
                         "top_symbols_limit": {
@@ -215,6 +222,34 @@ impl MCPToolHandler for RelationshipTools {
                     }
                 }),
             },
+            ToolDefinition {
+                name: "kotadb://symbol_brief".to_string(),
+                description: "Definition location, signature, doc comment, top callers and source of a symbol in one response, cut to a token budget - use instead of separate symbol search, find_callers and file reads".to_string(),
+                input_schema: serde_json::json!({
+                    "type": "object",
+                    "properties": {
+                        "target": {
+                            "type": "string",
+                            "description": "Symbol name, optionally qualified (e.g., 'FileStorage::insert')"
+                        },
+                        "max_callers": {
+                            "type": "integer",
+                            "description": "Number of callers to list, most at risk first (default: 5, max: 50)",
+                            "minimum": 0,
+                            "maximum": 50,
+                            "default": 5
+                        },
+                        "token_budget": {
+                            "type": "integer",
+                            "description": "Approximate tokens the brief may use (default: 1500)",
+                            "minimum": 100,
+                            "maximum": 20000,
+                            "default": 1500
+                        }
+                    },
+                    "required": ["target"]
+                }),
+            },
         ]
     }
 }
@@ -228,7 +263,12 @@ impl RelationshipTools {
         let options = CallersOptions {
             target: request.target.clone(),
             limit: None,
+            offset: 0,
+            sort_by: RelationshipSortBy::default(),
+            group_by: ResultGrouping::default(),
             quiet: false,
+            explain: false,
+            context_lines: None,
         };
 
         let result = analysis_service.find_callers(options).await?;
@@ -247,7 +287,8 @@ impl RelationshipTools {
             "target": request.target,
             "callers": result.callers,
             "total_count": result.total_count,
-            "markdown": result.markdown
+            "markdown": result.markdown,
+            "notes": result.notes
         }))
     }
 
@@ -267,7 +308,12 @@ impl RelationshipTools {
         let options = ImpactOptions {
             target: request.target.clone(),
             limit: None,
+            offset: 0,
+            sort_by: RelationshipSortBy::default(),
+            group_by: ResultGrouping::default(),
             quiet: false,
+            explain: false,
+            context_lines: None,
         };
 
         let result = analysis_service.analyze_impact(options).await?;
@@ -286,7 +332,8 @@ impl RelationshipTools {
             "target": request.target,
             "impacts": result.impacts,
             "total_count": result.total_count,
-            "markdown": result.markdown
+            "markdown": result.markdown,
+            "notes": result.notes
         }))
     }
 
@@ -317,10 +364,23 @@ impl RelationshipTools {
     }
 
     async fn hot_paths(&self, request: HotPathsRequest) -> Result<serde_json::Value> {
-        // TODO: Implement hot_paths in AnalysisService for Phase 3
-        Err(anyhow::anyhow!(
-            "Hot paths analysis is not yet implemented in Phase 2. Available in Phase 3."
-        ))
+        use crate::services::HotPathsOptions;
+
+        let analysis_service = AnalysisService::new(self.database.as_ref(), self.db_path.clone());
+        let options = HotPathsOptions {
+            limit: request.limit.unwrap_or(10).clamp(1, 50),
+        };
+
+        let result = analysis_service.hot_paths(options)?;
+
+        Ok(serde_json::json!({
+            "success": true,
+            "query_type": "hot_paths",
+            "ranked_by": result.ranked_by,
+            "profile_source": result.profile_source,
+            "symbols": result.symbols,
+            "markdown": result.to_markdown()
+        }))
     }
 
     async fn natural_language_relationship_query(
@@ -359,6 +419,34 @@ impl RelationshipTools {
             "formatted_output": result.formatted_output
         }))
     }
+
+    async fn symbol_brief(&self, request: SymbolBriefRequest) -> Result<serde_json::Value> {
+        use crate::services::SymbolBriefOptions;
+
+        let mut analysis_service =
+            AnalysisService::new(self.database.as_ref(), self.db_path.clone());
+        let defaults = SymbolBriefOptions::default();
+        let options = SymbolBriefOptions {
+            target: request.target.clone(),
+            max_callers: request.max_callers.unwrap_or(defaults.max_callers).min(50),
+            token_budget: request
+                .token_budget
+                .unwrap_or(defaults.token_budget)
+                .clamp(100, 20_000),
+        };
+
+        let brief = analysis_service
+            .symbol_brief(options)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("No definition of '{}' found", request.target))?;
+
+        Ok(serde_json::json!({
+            "success": true,
+            "query_type": "symbol_brief",
+            "markdown": brief.to_markdown(),
+            "brief": brief
+        }))
+    }
 }
 
 // Request types for MCP tools
@@ -396,7 +484,6 @@ struct UnusedSymbolsRequest {
 }
 
 #[derive(Debug, Clone, serde::Deserialize)]
-#[allow(dead_code)] // Phase 3 feature
 struct HotPathsRequest {
     limit: Option<usize>,
 }
@@ -413,6 +500,13 @@ struct CodebaseOverviewRequest {
     entry_points_limit: Option<usize>,
 }
 
+#[derive(Debug, Clone, serde::Deserialize)]
+struct SymbolBriefRequest {
+    target: String,
+    max_callers: Option<usize>,
+    token_budget: Option<usize>,
+}
+
 #[cfg(test)]
 mod tests {
     // NOTE: Relationship tools tests disabled pending real implementation
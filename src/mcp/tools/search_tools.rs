@@ -2,6 +2,7 @@ use crate::contracts::{Index, Query, Storage};
 use crate::llm_search::{ContextConfig, LLMSearchEngine, RelevanceConfig};
 use crate::mcp::tools::MCPToolHandler;
 use crate::mcp::types::*;
+use crate::metrics::performance::{LockedResource, TimedLock};
 use crate::semantic_search::{ScoredDocument, SemanticSearchEngine};
 use crate::types::*;
 use anyhow::Result;
@@ -262,7 +263,7 @@ impl SearchTools {
         // Fetch actual documents from storage
         let mut results = Vec::new();
         let storage = self.storage.clone();
-        let storage_guard = storage.lock().await;
+        let storage_guard = storage.lock_timed(LockedResource::Storage).await;
 
         for (idx, doc_id) in paginated_ids.into_iter().enumerate() {
             match storage_guard.get(&doc_id).await {
@@ -532,8 +533,11 @@ impl SearchTools {
             LLMSearchEngine::with_config(RelevanceConfig::default(), context_config);
 
         // Perform the LLM-optimized search by borrowing the guards directly
-        let storage_guard = self.storage.lock().await;
-        let index_guard = self.trigram_index.lock().await;
+        let storage_guard = self.storage.lock_timed(LockedResource::Storage).await;
+        let index_guard = self
+            .trigram_index
+            .lock_timed(LockedResource::TrigramIndex)
+            .await;
 
         let llm_response = search_engine
             .search_optimized(&request.query, &*storage_guard, &*index_guard, Some(limit))
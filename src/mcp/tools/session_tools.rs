@@ -0,0 +1,167 @@
+use crate::mcp::tools::MCPToolHandler;
+use crate::mcp::types::ToolDefinition;
+use crate::memory::{SessionMemory, SessionWorkingSet, WorkingSet};
+use anyhow::Result;
+use serde::Deserialize;
+use std::sync::Arc;
+
+/// Longest accepted session ID
+const MAX_SESSION_ID_LEN: usize = 128;
+
+/// Session memory tools - keep an assistant's working set between tool calls
+pub struct SessionTools {
+    memory: Arc<SessionMemory>,
+}
+
+impl SessionTools {
+    pub fn new(memory: Arc<SessionMemory>) -> Self {
+        Self { memory }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct SessionRememberRequest {
+    session_id: String,
+    #[serde(default)]
+    symbols: Vec<String>,
+    #[serde(default)]
+    notes: Vec<String>,
+    #[serde(default)]
+    replace: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct SessionRecallRequest {
+    session_id: String,
+}
+
+#[async_trait::async_trait]
+impl MCPToolHandler for SessionTools {
+    async fn handle_call(
+        &self,
+        method: &str,
+        params: serde_json::Value,
+    ) -> Result<serde_json::Value> {
+        match method {
+            "kotadb://session_remember" => {
+                let request: SessionRememberRequest = serde_json::from_value(params)?;
+                self.session_remember(request)
+            }
+            "kotadb://session_recall" => {
+                let request: SessionRecallRequest = serde_json::from_value(params)?;
+                self.session_recall(request)
+            }
+            _ => Err(anyhow::anyhow!("Unknown session method: {}", method)),
+        }
+    }
+
+    fn get_tool_definitions(&self) -> Vec<ToolDefinition> {
+        vec![
+            ToolDefinition {
+                name: "kotadb://session_remember".to_string(),
+                description: format!(
+                    "Remember the symbols and notes you are working with under a session ID, \
+                     so later calls can recall them instead of re-deriving context. \
+                     Kept for {}s after the last update.",
+                    self.memory.ttl().as_secs()
+                ),
+                input_schema: serde_json::json!({
+                    "type": "object",
+                    "properties": {
+                        "session_id": {
+                            "type": "string",
+                            "description": "Identifier chosen by the assistant for its session"
+                        },
+                        "symbols": {
+                            "type": "array",
+                            "items": {"type": "string"},
+                            "description": "Qualified names of symbols under investigation"
+                        },
+                        "notes": {
+                            "type": "array",
+                            "items": {"type": "string"}
+                        },
+                        "replace": {
+                            "type": "boolean",
+                            "description": "Replace the working set instead of adding to it",
+                            "default": false
+                        }
+                    },
+                    "required": ["session_id"]
+                }),
+            },
+            ToolDefinition {
+                name: "kotadb://session_recall".to_string(),
+                description: "Recall the working set remembered under a session ID".to_string(),
+                input_schema: serde_json::json!({
+                    "type": "object",
+                    "properties": {
+                        "session_id": {"type": "string"}
+                    },
+                    "required": ["session_id"]
+                }),
+            },
+        ]
+    }
+}
+
+impl SessionTools {
+    fn session_remember(&self, request: SessionRememberRequest) -> Result<serde_json::Value> {
+        validate_session_id(&request.session_id)?;
+        let update = WorkingSet {
+            symbols: non_empty(request.symbols),
+            notes: non_empty(request.notes),
+        };
+        let remembered = self
+            .memory
+            .remember(&request.session_id, update, request.replace);
+        Ok(working_set_response(&request.session_id, Some(remembered)))
+    }
+
+    fn session_recall(&self, request: SessionRecallRequest) -> Result<serde_json::Value> {
+        validate_session_id(&request.session_id)?;
+        let recalled = self.memory.recall(&request.session_id);
+        Ok(working_set_response(&request.session_id, recalled))
+    }
+}
+
+fn validate_session_id(session_id: &str) -> Result<()> {
+    if session_id.trim().is_empty() {
+        return Err(anyhow::anyhow!("session_id must not be empty"));
+    }
+    if session_id.len() > MAX_SESSION_ID_LEN {
+        return Err(anyhow::anyhow!(
+            "session_id must be at most {} bytes",
+            MAX_SESSION_ID_LEN
+        ));
+    }
+    Ok(())
+}
+
+fn non_empty(items: Vec<String>) -> Vec<String> {
+    items
+        .into_iter()
+        .map(|item| item.trim().to_string())
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+
+fn working_set_response(session_id: &str, session: Option<SessionWorkingSet>) -> serde_json::Value {
+    match session {
+        Some(session) => serde_json::json!({
+            "success": true,
+            "session_id": session_id,
+            "found": true,
+            "symbols": session.working_set.symbols,
+            "notes": session.working_set.notes,
+            "expires_in_secs": session.expires_in.as_secs()
+        }),
+        None => serde_json::json!({
+            "success": true,
+            "session_id": session_id,
+            "found": false,
+            "symbols": [],
+            "notes": []
+        }),
+    }
+}
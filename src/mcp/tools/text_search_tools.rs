@@ -1,6 +1,7 @@
 use crate::contracts::{Index, Query, Storage};
 use crate::mcp::tools::MCPToolHandler;
 use crate::mcp::types::ToolDefinition;
+use crate::metrics::performance::{LockedResource, TimedLock};
 use anyhow::Result;
 use serde::Deserialize;
 use std::collections::HashMap;
@@ -87,7 +88,10 @@ impl TextSearchTools {
 
         // Search via trigram index
         let doc_ids = {
-            let idx = self.trigram_index.lock().await;
+            let idx = self
+                .trigram_index
+                .lock_timed(LockedResource::TrigramIndex)
+                .await;
             idx.search(&query).await?
         };
 
@@ -99,7 +103,7 @@ impl TextSearchTools {
         let mut meta = HashMap::new();
         meta.insert("source".to_string(), "trigram".to_string());
 
-        let storage = self.storage.lock().await;
+        let storage = self.storage.lock_timed(LockedResource::Storage).await;
         for doc_id in ids_page {
             if let Some(doc) = storage.get(&doc_id).await? {
                 let content = String::from_utf8_lossy(&doc.content);
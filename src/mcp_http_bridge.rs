@@ -491,6 +491,8 @@ fn map_tool_name_to_mcp_method(tool: &str) -> Option<String> {
         "find_callers" => Some("kotadb://find_callers".to_string()),
         #[cfg(feature = "tree-sitter-parsing")]
         "analyze_impact" | "impact_analysis" => Some("kotadb://impact_analysis".to_string()),
+        #[cfg(feature = "tree-sitter-parsing")]
+        "symbol_brief" => Some("kotadb://symbol_brief".to_string()),
         _ => None,
     }
 }
@@ -2,10 +2,15 @@
 //!
 //! This module provides utilities for tracking memory usage, enforcing limits,
 //! and managing resource consumption during large repository ingestion operations.
+//! It also holds [`SessionMemory`], the per-session working sets assistants keep
+//! across MCP tool calls.
 
 use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use tracing::{debug, info, warn};
 
 /// Memory usage tracking and limits
@@ -235,6 +240,157 @@ impl MemoryLimitsConfig {
     }
 }
 
+/// Default time a session's working set is kept after its last update
+pub const DEFAULT_SESSION_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// Sessions kept at once; the session closest to expiry is evicted beyond this
+pub const MAX_SESSIONS: usize = 1024;
+
+/// Symbols and notes kept per session; the oldest entries are dropped beyond this
+pub const MAX_WORKING_SET_ITEMS: usize = 256;
+
+/// What an assistant is currently investigating
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorkingSet {
+    /// Qualified names of the symbols under investigation
+    #[serde(default)]
+    pub symbols: Vec<String>,
+    /// Free-form notes
+    #[serde(default)]
+    pub notes: Vec<String>,
+}
+
+impl WorkingSet {
+    /// Add `other`'s symbols and notes that are not already present
+    fn merge(&mut self, other: WorkingSet) {
+        for symbol in other.symbols {
+            if !self.symbols.contains(&symbol) {
+                self.symbols.push(symbol);
+            }
+        }
+        for note in other.notes {
+            if !self.notes.contains(&note) {
+                self.notes.push(note);
+            }
+        }
+    }
+
+    fn truncate_oldest(&mut self, max_items: usize) {
+        for items in [&mut self.symbols, &mut self.notes] {
+            let excess = items.len().saturating_sub(max_items);
+            items.drain(..excess);
+        }
+    }
+}
+
+/// A session's working set and how long it is kept
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SessionWorkingSet {
+    pub working_set: WorkingSet,
+    /// Time left until the session expires
+    pub expires_in: Duration,
+}
+
+struct SessionEntry {
+    working_set: WorkingSet,
+    expires_at: Instant,
+}
+
+/// Working sets keyed by session ID, each expiring a TTL after its last update
+///
+/// Held in memory only: working sets do not survive a server restart.
+pub struct SessionMemory {
+    ttl: Duration,
+    sessions: Mutex<HashMap<String, SessionEntry>>,
+}
+
+impl Default for SessionMemory {
+    fn default() -> Self {
+        Self::new(DEFAULT_SESSION_TTL)
+    }
+}
+
+impl SessionMemory {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            sessions: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// Store `update` for `session_id` and restart its TTL
+    ///
+    /// `update` is merged into the session's working set unless `replace` is set.
+    pub fn remember(
+        &self,
+        session_id: &str,
+        update: WorkingSet,
+        replace: bool,
+    ) -> SessionWorkingSet {
+        let now = Instant::now();
+        let mut sessions = self.sessions.lock().unwrap_or_else(|e| e.into_inner());
+        sessions.retain(|_, entry| entry.expires_at > now);
+
+        let entry = sessions
+            .entry(session_id.to_string())
+            .or_insert_with(|| SessionEntry {
+                working_set: WorkingSet::default(),
+                expires_at: now,
+            });
+        if replace {
+            entry.working_set = update;
+        } else {
+            entry.working_set.merge(update);
+        }
+        entry.working_set.truncate_oldest(MAX_WORKING_SET_ITEMS);
+        entry.expires_at = now + self.ttl;
+        let remembered = SessionWorkingSet {
+            working_set: entry.working_set.clone(),
+            expires_in: self.ttl,
+        };
+
+        if sessions.len() > MAX_SESSIONS {
+            if let Some(oldest) = sessions
+                .iter()
+                .filter(|(id, _)| id.as_str() != session_id)
+                .min_by_key(|(_, entry)| entry.expires_at)
+                .map(|(id, _)| id.clone())
+            {
+                sessions.remove(&oldest);
+            }
+        }
+
+        remembered
+    }
+
+    /// The working set of `session_id`, unless it is unknown or has expired
+    pub fn recall(&self, session_id: &str) -> Option<SessionWorkingSet> {
+        let now = Instant::now();
+        let mut sessions = self.sessions.lock().unwrap_or_else(|e| e.into_inner());
+        match sessions.get(session_id) {
+            Some(entry) if entry.expires_at > now => Some(SessionWorkingSet {
+                working_set: entry.working_set.clone(),
+                expires_in: entry.expires_at - now,
+            }),
+            Some(_) => {
+                sessions.remove(session_id);
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// Drop the working set of `session_id`, returning whether one was stored
+    pub fn forget(&self, session_id: &str) -> bool {
+        let mut sessions = self.sessions.lock().unwrap_or_else(|e| e.into_inner());
+        sessions.remove(session_id).is_some()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -347,4 +503,63 @@ mod tests {
         assert_eq!(test.max_total_memory_mb, Some(100));
         assert_eq!(test.chunk_size, 10);
     }
+
+    fn working_set(symbols: &[&str], notes: &[&str]) -> WorkingSet {
+        WorkingSet {
+            symbols: symbols.iter().map(|s| s.to_string()).collect(),
+            notes: notes.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_session_memory_merges_and_replaces_working_sets() {
+        let memory = SessionMemory::default();
+        assert_eq!(memory.recall("session-1"), None);
+
+        memory.remember("session-1", working_set(&["Storage::get"], &[]), false);
+        let merged = memory.remember(
+            "session-1",
+            working_set(&["Storage::get", "FileStorage"], &["get is hot"]),
+            false,
+        );
+        assert_eq!(
+            merged.working_set,
+            working_set(&["Storage::get", "FileStorage"], &["get is hot"])
+        );
+        assert_eq!(
+            memory.recall("session-1").unwrap().working_set,
+            merged.working_set
+        );
+        assert_eq!(memory.recall("session-2"), None);
+
+        let replaced = memory.remember("session-1", working_set(&["Index"], &[]), true);
+        assert_eq!(replaced.working_set, working_set(&["Index"], &[]));
+
+        assert!(memory.forget("session-1"));
+        assert_eq!(memory.recall("session-1"), None);
+    }
+
+    #[test]
+    fn test_session_memory_expires_working_sets() {
+        let memory = SessionMemory::new(Duration::ZERO);
+        memory.remember("session-1", working_set(&["Storage::get"], &[]), false);
+        assert_eq!(memory.recall("session-1"), None);
+    }
+
+    #[test]
+    fn test_session_memory_keeps_the_newest_items() {
+        let memory = SessionMemory::default();
+        let symbols: Vec<String> = (0..MAX_WORKING_SET_ITEMS + 2)
+            .map(|i| format!("symbol_{i}"))
+            .collect();
+        let remembered = memory.remember(
+            "session-1",
+            WorkingSet {
+                symbols: symbols.clone(),
+                notes: Vec::new(),
+            },
+            false,
+        );
+        assert_eq!(remembered.working_set.symbols, symbols[2..]);
+    }
 }
@@ -6,6 +6,8 @@ use crate::pure::performance::{GrowthAnalysis, PerformanceStats};
 use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant, SystemTime};
 
@@ -445,6 +447,218 @@ impl PerformanceCollector {
     }
 }
 
+/// Shared resource guarded by one of the database's async mutexes
+///
+/// Storage and each index sit behind a single mutex, so every reader and writer queues
+/// on it; wait times on these locks show when that design is the bottleneck.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LockedResource {
+    Storage,
+    PrimaryIndex,
+    TrigramIndex,
+}
+
+impl LockedResource {
+    pub const ALL: [LockedResource; 3] = [
+        LockedResource::Storage,
+        LockedResource::PrimaryIndex,
+        LockedResource::TrigramIndex,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LockedResource::Storage => "storage",
+            LockedResource::PrimaryIndex => "primary_index",
+            LockedResource::TrigramIndex => "trigram_index",
+        }
+    }
+}
+
+/// Lock acquisitions and wait times of one resource since startup (or the last reset)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LockContentionStats {
+    pub resource: LockedResource,
+    pub acquisitions: u64,
+    /// Acquisitions that found the lock held and had to wait
+    pub contended: u64,
+    pub total_wait: Duration,
+    pub max_wait: Duration,
+}
+
+impl LockContentionStats {
+    /// Fraction of acquisitions that had to wait
+    pub fn contention_ratio(&self) -> f64 {
+        if self.acquisitions == 0 {
+            0.0
+        } else {
+            self.contended as f64 / self.acquisitions as f64
+        }
+    }
+
+    /// Counters accumulated since the `earlier` snapshot of the same resource
+    ///
+    /// The longest wait is not windowed and stays the overall maximum.
+    pub fn since(&self, earlier: &Self) -> Self {
+        Self {
+            resource: self.resource,
+            acquisitions: self.acquisitions.saturating_sub(earlier.acquisitions),
+            contended: self.contended.saturating_sub(earlier.contended),
+            total_wait: self.total_wait.saturating_sub(earlier.total_wait),
+            max_wait: self.max_wait,
+        }
+    }
+
+    /// Average wait of the acquisitions that had to wait
+    pub fn average_contended_wait(&self) -> Duration {
+        if self.contended == 0 {
+            Duration::ZERO
+        } else {
+            self.total_wait.div_f64(self.contended as f64)
+        }
+    }
+}
+
+struct LockCounters {
+    acquisitions: AtomicU64,
+    contended: AtomicU64,
+    wait_micros: AtomicU64,
+    max_wait_micros: AtomicU64,
+}
+
+static LOCK_COUNTERS: [LockCounters; 3] = [
+    LockCounters::new(),
+    LockCounters::new(),
+    LockCounters::new(),
+];
+
+impl LockCounters {
+    const fn new() -> Self {
+        Self {
+            acquisitions: AtomicU64::new(0),
+            contended: AtomicU64::new(0),
+            wait_micros: AtomicU64::new(0),
+            max_wait_micros: AtomicU64::new(0),
+        }
+    }
+
+    fn of(resource: LockedResource) -> &'static LockCounters {
+        &LOCK_COUNTERS[resource as usize]
+    }
+}
+
+/// Record one acquisition of `resource`'s lock and how long it waited
+pub fn record_lock_wait(resource: LockedResource, wait: Option<Duration>) {
+    let counters = LockCounters::of(resource);
+    counters.acquisitions.fetch_add(1, Ordering::Relaxed);
+    if let Some(wait) = wait {
+        let micros = wait.as_micros() as u64;
+        counters.contended.fetch_add(1, Ordering::Relaxed);
+        counters.wait_micros.fetch_add(micros, Ordering::Relaxed);
+        counters
+            .max_wait_micros
+            .fetch_max(micros, Ordering::Relaxed);
+    }
+}
+
+/// Contention recorded so far for every resource
+pub fn lock_contention() -> Vec<LockContentionStats> {
+    LockedResource::ALL
+        .iter()
+        .map(|&resource| {
+            let counters = LockCounters::of(resource);
+            LockContentionStats {
+                resource,
+                acquisitions: counters.acquisitions.load(Ordering::Relaxed),
+                contended: counters.contended.load(Ordering::Relaxed),
+                total_wait: Duration::from_micros(counters.wait_micros.load(Ordering::Relaxed)),
+                max_wait: Duration::from_micros(counters.max_wait_micros.load(Ordering::Relaxed)),
+            }
+        })
+        .collect()
+}
+
+/// Metric name, type, help text and per-resource value of one exported family
+type LockMetricFamily = (
+    &'static str,
+    &'static str,
+    &'static str,
+    fn(&LockContentionStats) -> String,
+);
+
+/// Export lock contention in Prometheus text format
+pub fn export_lock_contention_prometheus() -> String {
+    let stats = lock_contention();
+    let mut prometheus = String::new();
+    let families: [LockMetricFamily; 4] = [
+        (
+            "kotadb_lock_acquisitions_total",
+            "counter",
+            "Acquisitions of the storage and index locks",
+            |s| s.acquisitions.to_string(),
+        ),
+        (
+            "kotadb_lock_contended_total",
+            "counter",
+            "Lock acquisitions that had to wait for another holder",
+            |s| s.contended.to_string(),
+        ),
+        (
+            "kotadb_lock_wait_seconds_total",
+            "counter",
+            "Time spent waiting for the storage and index locks",
+            |s| s.total_wait.as_secs_f64().to_string(),
+        ),
+        (
+            "kotadb_lock_wait_seconds_max",
+            "gauge",
+            "Longest single wait for the storage and index locks",
+            |s| s.max_wait.as_secs_f64().to_string(),
+        ),
+    ];
+    for (name, kind, help, value) in families {
+        prometheus.push_str(&format!(
+            "# HELP {} {}\n# TYPE {} {}\n",
+            name, help, name, kind
+        ));
+        for resource in &stats {
+            prometheus.push_str(&format!(
+                "{}{{resource=\"{}\"}} {}\n",
+                name,
+                resource.resource.as_str(),
+                value(resource)
+            ));
+        }
+    }
+    prometheus
+}
+
+/// Acquire a database mutex while recording how long the caller waited for it
+pub trait TimedLock<T: ?Sized> {
+    fn lock_timed<'a>(
+        &'a self,
+        resource: LockedResource,
+    ) -> impl Future<Output = tokio::sync::MutexGuard<'a, T>> + Send
+    where
+        T: 'a;
+}
+
+impl<T: ?Sized + Send> TimedLock<T> for tokio::sync::Mutex<T> {
+    async fn lock_timed<'a>(&'a self, resource: LockedResource) -> tokio::sync::MutexGuard<'a, T>
+    where
+        T: 'a,
+    {
+        if let Ok(guard) = self.try_lock() {
+            record_lock_wait(resource, None);
+            return guard;
+        }
+        let start = Instant::now();
+        let guard = self.lock().await;
+        record_lock_wait(resource, Some(start.elapsed()));
+        guard
+    }
+}
+
 /// Performance monitoring helper macros
 #[macro_export]
 macro_rules! measure_performance {
@@ -528,4 +742,37 @@ mod tests {
         let prometheus_export = collector.export_prometheus();
         assert!(prometheus_export.contains("kotadb_"));
     }
+
+    #[tokio::test]
+    async fn test_lock_timed_records_contended_waits() {
+        let counted = |resource: LockedResource| {
+            lock_contention()
+                .into_iter()
+                .find(|stats| stats.resource == resource)
+                .unwrap()
+        };
+        let before = counted(LockedResource::TrigramIndex);
+
+        let mutex = Arc::new(tokio::sync::Mutex::new(0));
+        let held = mutex.clone().lock_owned().await;
+        let waiter = {
+            let mutex = mutex.clone();
+            tokio::spawn(async move {
+                *mutex.lock_timed(LockedResource::TrigramIndex).await += 1;
+            })
+        };
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        drop(held);
+        waiter.await.unwrap();
+        drop(mutex.lock_timed(LockedResource::TrigramIndex).await);
+
+        // Other tests may take the same lock concurrently, so compare lower bounds
+        let after = counted(LockedResource::TrigramIndex);
+        assert!(after.acquisitions >= before.acquisitions + 2);
+        assert!(after.contended > before.contended);
+        assert!(after.total_wait >= before.total_wait + Duration::from_millis(10));
+        assert!(after.max_wait >= Duration::from_millis(10));
+        assert!(export_lock_contention_prometheus()
+            .contains("kotadb_lock_contended_total{resource=\"trigram_index\"}"));
+    }
 }
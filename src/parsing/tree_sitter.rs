@@ -373,6 +373,12 @@ pub struct ParsedSymbol {
     pub start_column: usize,
     /// Column where symbol ends (0-based)
     pub end_column: usize,
+    /// Byte offset where symbol starts
+    #[serde(default)]
+    pub start_byte: usize,
+    /// Byte offset just past the end of the symbol
+    #[serde(default)]
+    pub end_byte: usize,
     /// Full text of the symbol
     pub text: String,
     /// Documentation/comments associated with symbol
@@ -562,7 +568,11 @@ impl CodeParser {
 
         // Check if this node represents a symbol we care about
         // Using optimized HashSet lookups for O(1) performance
-        let symbol_type = if get_function_nodes().contains(node_type) {
+        // Keyword tokens share kinds with named nodes (the `function` in a TypeScript
+        // function declaration is a `function` token), so only named nodes are symbols
+        let symbol_type = if !node.is_named() {
+            None
+        } else if get_function_nodes().contains(node_type) {
             // Check if this is inside a trait/impl/class block (making it a method)
             if self.is_inside_method_container(node) {
                 Some(SymbolType::Method)
@@ -635,6 +645,8 @@ impl CodeParser {
                 end_line: end_pos.row + 1,
                 start_column: start_pos.column,
                 end_column: end_pos.column,
+                start_byte: node.start_byte(),
+                end_byte: node.end_byte(),
                 text: node.utf8_text(content.as_bytes()).unwrap_or("").to_string(),
                 documentation: None, // Will be enhanced to extract doc comments
             };
@@ -1576,4 +1588,25 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_typescript_function_keyword_is_not_a_symbol() -> Result<()> {
+        let mut parser = CodeParser::new()?;
+        let ts_code = r#"
+export function hashPassword(value: string): string {
+  return value;
+}
+"#;
+
+        let parsed = parser.parse_content(ts_code, SupportedLanguage::TypeScript)?;
+        let functions: Vec<&str> = parsed
+            .symbols
+            .iter()
+            .filter(|s| s.symbol_type == SymbolType::Function)
+            .map(|s| s.name.as_str())
+            .collect();
+        assert_eq!(functions, vec!["hashPassword"]);
+
+        Ok(())
+    }
 }
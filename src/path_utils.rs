@@ -457,14 +457,16 @@ pub fn detect_language_from_extension(path: &Path) -> &'static str {
 /// Uses common patterns across different languages and frameworks
 /// to identify test files.
 pub fn is_test_file(path: &Path) -> bool {
-    let path_str = path.to_string_lossy();
-
-    // Check path components for test directories
-    if path_str.contains("/test/")
-        || path_str.contains("/tests/")
-        || path_str.contains("/spec/")
-        || path_str.contains("/__tests__/")
-    {
+    // Check path components for test directories (also at the start of relative paths)
+    let in_test_dir = path.parent().is_some_and(|dir| {
+        dir.components().any(|component| {
+            matches!(
+                component.as_os_str().to_str(),
+                Some("test" | "tests" | "spec" | "__tests__")
+            )
+        })
+    });
+    if in_test_dir {
         return true;
     }
 
@@ -893,12 +895,15 @@ mod tests {
             ("MyClassTests.cs", true),
             ("component.test.ts", true),
             ("feature.spec.js", true),
+            ("tests/common/mod.rs", true),
+            ("/repo/test/fixtures.py", true),
             // Non-test files
             ("src/main.rs", false),
             ("lib/helper.py", false),
             ("app/component.js", false),
             ("README.md", false),
             ("build.gradle", false),
+            ("src/tests.rs", false),
         ];
 
         for (path_str, expected) in test_cases {
@@ -13,8 +13,13 @@ use tokio::io::{AsyncBufReadExt, AsyncSeekExt, AsyncWriteExt, BufReader};
 use tokio::sync::RwLock;
 use uuid::Uuid;
 
+use crate::contracts::optimization::TreeStructureMetrics;
 use crate::contracts::{Index, Query};
-use crate::pure::{btree, extract_all_pairs, traverse_pairs_until};
+use crate::leaf_pages;
+use crate::pure::{
+    analyze_tree_structure, btree, bulk_insert_into_tree, extract_all_pairs, rebuild_tree,
+    traverse_pairs_until,
+};
 use crate::types::{ValidatedDocumentId, ValidatedPath};
 use crate::validation;
 use crate::wrappers::MeteredIndex;
@@ -263,19 +268,24 @@ impl PrimaryIndex {
             *self.metadata.write().await = metadata;
         }
 
-        // Load B+ tree data
+        // Load B+ tree data: leaf pages, or the JSON mappings written by older versions
+        let leaves_path = data_dir.join(leaf_pages::LEAF_PAGES_FILE);
         let btree_path = data_dir.join("btree_data.json");
-        if btree_path.exists() {
+        let pairs = if leaves_path.exists() {
+            Some(
+                tokio::task::spawn_blocking(move || leaf_pages::read(&leaves_path))
+                    .await
+                    .context("B+ tree leaf page reader panicked")??,
+            )
+        } else if btree_path.exists() {
             let btree_content = fs::read_to_string(&btree_path).await.with_context(|| {
                 format!("Failed to read B+ tree data: {}", btree_path.display())
             })?;
 
-            // For now, rebuild tree from key-value pairs (future optimization: serialize tree structure)
             let raw_mappings: HashMap<String, String> = serde_json::from_str(&btree_content)
                 .context("Failed to deserialize B+ tree data")?;
 
-            // Rebuild B+ tree from stored key-value pairs
-            let mut btree_root = btree::create_empty_tree();
+            let mut pairs = Vec::with_capacity(raw_mappings.len());
             for (id_str, path_str) in raw_mappings {
                 let uuid = Uuid::parse_str(&id_str)
                     .with_context(|| format!("Invalid UUID in B+ tree data: {id_str}"))?;
@@ -286,12 +296,17 @@ impl PrimaryIndex {
                 let validated_path = ValidatedPath::new(&path_str)
                     .with_context(|| format!("Invalid path in B+ tree data: {path_str}"))?;
 
-                btree_root = btree::insert_into_tree(btree_root, doc_id, validated_path)
-                    .with_context(|| {
-                        format!("Failed to insert into B+ tree: {id_str} -> {path_str}")
-                    })?;
+                pairs.push((doc_id, validated_path));
             }
+            Some(pairs)
+        } else {
+            None
+        };
 
+        // Build the tree bottom-up so sibling leaves are allocated next to each other
+        if let Some(pairs) = pairs {
+            let btree_root = bulk_insert_into_tree(btree::create_empty_tree(), pairs)
+                .context("Failed to build B+ tree from stored entries")?;
             *self.btree_root.write().await = btree_root;
         }
 
@@ -452,27 +467,28 @@ impl PrimaryIndex {
         Ok(())
     }
 
-    /// Save B+ tree data to disk
+    /// Save B+ tree data to disk as leaf pages in key order
     async fn save_mappings(&self) -> Result<()> {
-        let btree_path = self.index_path.join("data").join("btree_data.json");
-        let btree_root = self.btree_root.read().await;
-
-        // Extract all key-value pairs from B+ tree for serialization
-        // Future optimization: serialize the tree structure directly
-        let all_pairs = extract_all_pairs(&btree_root)?;
-
-        // Convert to serializable format
-        let raw_mappings: HashMap<String, String> = all_pairs
-            .iter()
-            .map(|(doc_id, path)| (doc_id.as_uuid().to_string(), path.to_string()))
-            .collect();
+        let data_dir = self.index_path.join("data");
+        let leaves_path = data_dir.join(leaf_pages::LEAF_PAGES_FILE);
 
-        let content = serde_json::to_string_pretty(&raw_mappings)
-            .context("Failed to serialize B+ tree data")?;
+        // In-order traversal yields the entries sorted by key, i.e. in leaf order
+        let all_pairs = extract_all_pairs(&*self.btree_root.read().await)?;
 
-        fs::write(&btree_path, content)
+        tokio::task::spawn_blocking(move || leaf_pages::write(&leaves_path, &all_pairs))
             .await
-            .with_context(|| format!("Failed to write B+ tree data: {}", btree_path.display()))?;
+            .context("B+ tree leaf page writer panicked")??;
+
+        // The leaf pages supersede the JSON mappings of older versions
+        let legacy_path = data_dir.join("btree_data.json");
+        if legacy_path.exists() {
+            fs::remove_file(&legacy_path).await.with_context(|| {
+                format!(
+                    "Failed to remove legacy B+ tree data: {}",
+                    legacy_path.display()
+                )
+            })?;
+        }
 
         Ok(())
     }
@@ -752,16 +768,26 @@ impl Index for PrimaryIndex {
             return Ok(Vec::new());
         };
 
-        // Filter results based on wildcard pattern if present
+        // Filter results based on wildcard pattern if present. Extension and depth
+        // filters are checked during the traversal, so the limit counts only documents
+        // that pass them.
         let limit_value = query.limit.get();
+        let filters = &query.filters;
         let mut matched_ids: Vec<Uuid> = Vec::new();
+        // Paths visited; past the query deadline the traversal stops with what it matched
+        let mut visited = 0usize;
 
         if let Some(pattern) = wildcard_pattern {
             tracing::debug!("Filtering documents with pattern: '{}'", pattern);
 
             traverse_pairs_until(&btree_root, |doc_id, path| {
+                visited += 1;
+                if query.deadline.expired_at(visited) {
+                    return false;
+                }
                 let path_str = path.as_str();
-                let matches = Self::matches_wildcard_pattern(path_str, &pattern);
+                let matches = filters.matches_path(path_str)
+                    && Self::matches_wildcard_pattern(path_str, &pattern);
                 if matches {
                     tracing::debug!("Pattern '{}' MATCHES path: '{}'", pattern, path_str);
                     matched_ids.push(doc_id.as_uuid());
@@ -774,8 +800,14 @@ impl Index for PrimaryIndex {
                 limit_value
             );
 
-            traverse_pairs_until(&btree_root, |doc_id, _| {
-                matched_ids.push(doc_id.as_uuid());
+            traverse_pairs_until(&btree_root, |doc_id, path| {
+                visited += 1;
+                if query.deadline.expired_at(visited) {
+                    return false;
+                }
+                if filters.matches_path(path.as_str()) {
+                    matched_ids.push(doc_id.as_uuid());
+                }
                 matched_ids.len() < limit_value
             });
         }
@@ -783,6 +815,12 @@ impl Index for PrimaryIndex {
         if matched_ids.len() > limit_value {
             matched_ids.truncate(limit_value);
         }
+        if query.deadline.expired() {
+            tracing::debug!(
+                "Primary Index search stopped at its deadline after {} paths",
+                visited
+            );
+        }
 
         let mut results = Vec::with_capacity(matched_ids.len());
         for uuid in matched_ids {
@@ -847,6 +885,35 @@ impl Index for PrimaryIndex {
         drop(self.wal_writer);
         Ok(())
     }
+
+    /// Structure of the loaded B+ tree
+    async fn tree_structure(&self) -> Result<Option<TreeStructureMetrics>> {
+        self.ensure_loaded().await?;
+        let btree_root = self.btree_root.read().await;
+        analyze_tree_structure(&btree_root).map(Some)
+    }
+
+    /// Rebuild the B+ tree from its entries
+    ///
+    /// Only the in-memory shape changes; the persisted entries stay as they are.
+    async fn rebuild_tree(&mut self) -> Result<bool> {
+        self.ensure_loaded().await?;
+        let mut btree_root = self.btree_root.write().await;
+        *btree_root = rebuild_tree(&btree_root)?;
+        Ok(true)
+    }
+
+    async fn tree_snapshot(&self) -> Result<Option<btree::BTreeRoot>> {
+        self.ensure_loaded().await?;
+        Ok(Some(self.btree_root.read().await.clone()))
+    }
+
+    /// Swap in a rebuilt tree; like [`Index::rebuild_tree`], only the in-memory shape changes
+    async fn replace_tree(&mut self, tree: btree::BTreeRoot) -> Result<bool> {
+        self.ensure_loaded().await?;
+        *self.btree_root.write().await = tree;
+        Ok(true)
+    }
 }
 
 /// Create a fully wrapped PrimaryIndex with all Stage 6 components
@@ -998,6 +1065,79 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_primary_index_applies_path_filters_before_limit() -> Result<()> {
+        let test_dir = format!("test_data/primary_filters_{}", uuid::Uuid::new_v4());
+        std::fs::create_dir_all(&test_dir)?;
+
+        let mut index = create_primary_index_for_tests(&test_dir).await?;
+        let mut ids = HashMap::new();
+        for path in [
+            "README.md",
+            "src/deep/nested/mod.rs",
+            "src/lib.rs",
+            "src/main.RS",
+            "docs/guide.md",
+            "build.rs",
+        ] {
+            let id = ValidatedDocumentId::new();
+            index.insert(id, ValidatedPath::new(path)?).await?;
+            ids.insert(id, path);
+        }
+
+        let filters = crate::contracts::DocumentFilters {
+            max_depth: Some(1),
+            ..Default::default()
+        }
+        .with_extensions([".rs"]);
+        let mut query = Query::new(Some("*".to_string()), None, None, 10)?;
+        query.filters = filters.clone();
+        let mut found: Vec<_> = index
+            .search(&query)
+            .await?
+            .iter()
+            .map(|id| ids[id])
+            .collect();
+        found.sort();
+        assert_eq!(found, vec!["build.rs", "src/lib.rs", "src/main.RS"]);
+
+        // The limit counts documents that passed the filters
+        let mut query = Query::new(Some("*".to_string()), None, None, 3)?;
+        query.filters = filters;
+        assert_eq!(index.search(&query).await?.len(), 3);
+
+        let mut query = Query::new(None, None, Some("src/*".to_string()), 10)?;
+        query.filters = crate::contracts::DocumentFilters::default().with_extensions(["rs"]);
+        assert_eq!(index.search(&query).await?.len(), 3);
+
+        let _ = std::fs::remove_dir_all(&test_dir);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_primary_index_search_stops_at_deadline() -> Result<()> {
+        let test_dir = format!("test_data/primary_deadline_{}", uuid::Uuid::new_v4());
+        std::fs::create_dir_all(&test_dir)?;
+
+        let mut index = create_primary_index_for_tests(&test_dir).await?;
+        let total = 3 * crate::query_deadline::CHECK_INTERVAL;
+        for i in 0..total {
+            let path = ValidatedPath::new(format!("src/file_{i}.rs"))?;
+            index.insert(ValidatedDocumentId::new(), path).await?;
+        }
+
+        let mut query = Query::new(None, None, Some("src/*".to_string()), total)?;
+        assert_eq!(index.search(&query).await?.len(), total);
+
+        // An expired deadline ends the traversal at its first check with the matches so far
+        query.deadline = crate::query_deadline::QueryDeadline::after(std::time::Duration::ZERO);
+        let partial = index.search(&query).await?;
+        assert_eq!(partial.len(), crate::query_deadline::CHECK_INTERVAL - 1);
+
+        let _ = std::fs::remove_dir_all(&test_dir);
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_primary_index_metadata_management() -> Result<()> {
         let test_dir = format!("test_data/primary_metadata_{}", uuid::Uuid::new_v4());
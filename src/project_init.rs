@@ -0,0 +1,481 @@
+//! Project scaffolding for `kotadb init`
+//!
+//! Setting up a repository used to take several steps: choosing a database directory,
+//! passing it to every command, excluding build output and dependencies from the index
+//! and indexing. `kotadb init` writes `.kotadb/config.toml` with the database location
+//! and a `.kotaignore` with the excludes recommended for the languages found in the
+//! tree, then installs git hooks if asked and runs the first index.
+//!
+//! Commands run anywhere inside an initialized repository find its config and use its
+//! database unless `--db-path` is given. Indexing honors `.kotaignore` whether or not
+//! the repository was initialized.
+
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::path_utils::detect_language_from_extension;
+use crate::services::redaction::path_pattern;
+use crate::trends::is_source_language;
+
+/// Directory holding the project config and, by default, the database
+pub const PROJECT_DIR: &str = ".kotadb";
+
+/// Project config file inside [`PROJECT_DIR`]
+pub const CONFIG_FILE: &str = "config.toml";
+
+/// File at the repository root listing paths to leave out of the index
+pub const IGNORE_FILE: &str = ".kotaignore";
+
+/// Files looked at when detecting the languages of a repository
+const MAX_SCANNED_FILES: usize = 20_000;
+
+/// Directories never descended into when detecting languages
+const SKIPPED_DIRS: &[&str] = &[
+    "node_modules",
+    "target",
+    "vendor",
+    "build",
+    "dist",
+    "__pycache__",
+    "venv",
+];
+
+/// Excludes written for every repository
+const COMMON_EXCLUDES: &[&str] = &[".kotadb/"];
+
+/// Build output, dependencies and lock files of each language, as `.kotaignore` patterns
+const LANGUAGE_EXCLUDES: &[(&str, &[&str])] = &[
+    ("Rust", &["target/", "Cargo.lock"]),
+    (
+        "JavaScript",
+        &[
+            "node_modules/",
+            "dist/",
+            "build/",
+            "coverage/",
+            "*.min.js",
+            "*.map",
+            "package-lock.json",
+            "yarn.lock",
+            "pnpm-lock.yaml",
+        ],
+    ),
+    (
+        "TypeScript",
+        &[
+            "node_modules/",
+            "dist/",
+            "build/",
+            "coverage/",
+            "*.d.ts.map",
+            "package-lock.json",
+            "yarn.lock",
+            "pnpm-lock.yaml",
+        ],
+    ),
+    (
+        "Python",
+        &[
+            "__pycache__/",
+            "*.pyc",
+            ".venv/",
+            "venv/",
+            ".tox/",
+            ".mypy_cache/",
+            ".pytest_cache/",
+            "*.egg-info/",
+            "poetry.lock",
+        ],
+    ),
+    ("Go", &["vendor/", "go.sum"]),
+    ("Java", &["build/", "target/", "out/", ".gradle/"]),
+    ("Kotlin", &["build/", "out/", ".gradle/"]),
+    ("Scala", &["target/", ".bloop/", ".metals/"]),
+    ("C", &["build/", "cmake-build-*/", "*.o"]),
+    ("C++", &["build/", "cmake-build-*/", "*.o"]),
+    ("C#", &["bin/", "obj/"]),
+    ("Ruby", &["vendor/bundle/", ".bundle/", "Gemfile.lock"]),
+    ("PHP", &["vendor/", "composer.lock"]),
+    ("Swift", &[".build/", "DerivedData/", "Pods/"]),
+    ("Dart", &[".dart_tool/", "build/", "pubspec.lock"]),
+    ("Elixir", &["_build/", "deps/", "mix.lock"]),
+];
+
+/// Settings in `.kotadb/config.toml`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProjectConfig {
+    /// Database directory, relative to the repository root unless absolute
+    pub db_path: PathBuf,
+}
+
+impl Default for ProjectConfig {
+    fn default() -> Self {
+        Self {
+            db_path: Path::new(PROJECT_DIR).join("db"),
+        }
+    }
+}
+
+impl ProjectConfig {
+    /// Config of the repository at `root`, if it was initialized
+    pub fn load(root: &Path) -> Result<Option<Self>> {
+        let path = config_path(root);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let text = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let config =
+            toml::from_str(&text).with_context(|| format!("Invalid {}", path.display()))?;
+        Ok(Some(config))
+    }
+
+    /// Root and config of the nearest initialized repository at or above `dir`
+    pub fn discover(dir: &Path) -> Result<Option<(PathBuf, Self)>> {
+        for root in dir.ancestors() {
+            if let Some(config) = Self::load(root)? {
+                return Ok(Some((root.to_path_buf(), config)));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Database directory of the repository at `root`
+    pub fn resolved_db_path(&self, root: &Path) -> PathBuf {
+        root.join(&self.db_path)
+    }
+
+    fn to_toml(&self) -> Result<String> {
+        Ok(format!(
+            "# KotaDB project settings, written by `kotadb init`.\n\
+             # Commands run inside this repository use this database unless --db-path is given.\n\n{}",
+            toml::to_string(self)?
+        ))
+    }
+}
+
+fn config_path(root: &Path) -> PathBuf {
+    root.join(PROJECT_DIR).join(CONFIG_FILE)
+}
+
+/// Paths left out of the index, from `.kotaignore`
+///
+/// Patterns use `.gitignore` syntax without negation: `*` and `?` stay within one path
+/// segment, `**` spans directories, a pattern with a `/` is anchored at the repository
+/// root and a matched directory excludes everything below it.
+#[derive(Debug, Clone, Default)]
+pub struct IgnoreRules {
+    patterns: Vec<String>,
+    compiled: Vec<Regex>,
+}
+
+impl IgnoreRules {
+    pub fn new<I, S>(patterns: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut rules = Self::default();
+        for pattern in patterns {
+            let pattern = pattern.as_ref().trim();
+            if pattern.starts_with('!') {
+                bail!("Negated pattern '{}' is not supported", pattern);
+            }
+            let Some(compiled) = path_pattern(pattern) else {
+                bail!("Invalid ignore pattern '{}'", pattern);
+            };
+            rules.patterns.push(pattern.to_string());
+            rules.compiled.push(compiled);
+        }
+        Ok(rules)
+    }
+
+    /// Rules of a `.kotaignore` file, skipping blank lines and `#` comments
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut rules = Self::default();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let rule = Self::new([line]).with_context(|| format!("Line {}", number + 1))?;
+            rules.patterns.extend(rule.patterns);
+            rules.compiled.extend(rule.compiled);
+        }
+        Ok(rules)
+    }
+
+    /// Rules of the `.kotaignore` at the root of a repository; none if it has no such file
+    pub fn load(repo_root: &Path) -> Result<Self> {
+        let path = repo_root.join(IGNORE_FILE);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let text = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        Self::parse(&text).with_context(|| format!("Invalid {}", path.display()))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.patterns.is_empty()
+    }
+
+    pub fn patterns(&self) -> &[String] {
+        &self.patterns
+    }
+
+    /// Whether the repository-relative `path` is left out of the index
+    pub fn is_ignored(&self, path: &str) -> bool {
+        let path = path.trim_start_matches("./").trim_start_matches('/');
+        self.compiled.iter().any(|pattern| pattern.is_match(path))
+    }
+}
+
+/// Source languages of a repository by number of files, most files first
+///
+/// Hidden directories and common dependency and build directories are skipped, and at
+/// most [`MAX_SCANNED_FILES`] files are looked at.
+pub fn detect_languages(root: &Path) -> Vec<String> {
+    let mut counts: BTreeMap<&'static str, usize> = BTreeMap::new();
+    let mut pending = vec![root.to_path_buf()];
+    let mut scanned = 0;
+    while let Some(dir) = pending.pop() {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            if file_type.is_dir() {
+                if !name.starts_with('.') && !SKIPPED_DIRS.contains(&name.as_ref()) {
+                    pending.push(entry.path());
+                }
+                continue;
+            }
+            scanned += 1;
+            if scanned > MAX_SCANNED_FILES {
+                break;
+            }
+            let language = detect_language_from_extension(&entry.path());
+            if is_source_language(language) {
+                *counts.entry(language).or_default() += 1;
+            }
+        }
+        if scanned > MAX_SCANNED_FILES {
+            break;
+        }
+    }
+
+    let mut languages: Vec<(&str, usize)> = counts.into_iter().collect();
+    languages.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    languages
+        .into_iter()
+        .map(|(language, _)| language.to_string())
+        .collect()
+}
+
+/// Default `.kotaignore` for a repository written in `languages`
+///
+/// Each pattern appears once, under the first language recommending it.
+pub fn default_ignore_file(languages: &[String]) -> String {
+    let mut text = String::from(
+        "# Paths kotadb leaves out of the index, in .gitignore syntax (without `!`).\n\
+         # Written by `kotadb init`; edit freely. Changes apply on the next index.\n\n# KotaDB\n",
+    );
+    let mut seen: HashSet<&str> = HashSet::new();
+    for pattern in COMMON_EXCLUDES {
+        seen.insert(pattern);
+        text.push_str(pattern);
+        text.push('\n');
+    }
+    for language in languages {
+        let Some((_, excludes)) = LANGUAGE_EXCLUDES.iter().find(|(name, _)| name == language)
+        else {
+            continue;
+        };
+        let new: Vec<&str> = excludes
+            .iter()
+            .copied()
+            .filter(|pattern| seen.insert(pattern))
+            .collect();
+        if !new.is_empty() {
+            text.push_str(&format!("\n# {}\n{}\n", language, new.join("\n")));
+        }
+    }
+    text
+}
+
+/// Files written or kept by [`scaffold`]
+#[derive(Debug, Clone)]
+pub struct InitReport {
+    pub root: PathBuf,
+    pub languages: Vec<String>,
+    pub config: ProjectConfig,
+    /// Files written, relative to the root
+    pub written: Vec<PathBuf>,
+    /// Files that already existed and were left as they were, relative to the root
+    pub kept: Vec<PathBuf>,
+}
+
+impl InitReport {
+    pub fn to_text(&self) -> String {
+        let mut output = format!("Initialized KotaDB in {}\n", self.root.display());
+        if self.languages.is_empty() {
+            output.push_str("  Languages: none detected\n");
+        } else {
+            output.push_str(&format!("  Languages: {}\n", self.languages.join(", ")));
+        }
+        for path in &self.written {
+            output.push_str(&format!("  Wrote {}\n", path.display()));
+        }
+        for path in &self.kept {
+            output.push_str(&format!(
+                "  Kept {} (already exists; --force replaces it)\n",
+                path.display()
+            ));
+        }
+        output.push_str(&format!("  Database: {}\n", self.config.db_path.display()));
+        output
+    }
+}
+
+/// Write the project config, `.kotadb/.gitignore` and `.kotaignore` of the repository at
+/// `root`, keeping existing files unless `force`
+///
+/// The config records `db_path` when given, otherwise the default `.kotadb/db`.
+pub fn scaffold(root: &Path, db_path: Option<&Path>, force: bool) -> Result<InitReport> {
+    if !root.is_dir() {
+        bail!("{} is not a directory", root.display());
+    }
+    let languages = detect_languages(root);
+    let mut config = ProjectConfig::default();
+    if let Some(db_path) = db_path {
+        config.db_path = std::path::absolute(db_path)?;
+    }
+    if !force {
+        if let Some(existing) = ProjectConfig::load(root)? {
+            config = existing;
+        }
+    }
+
+    std::fs::create_dir_all(root.join(PROJECT_DIR))
+        .with_context(|| format!("Failed to create {}", root.join(PROJECT_DIR).display()))?;
+    let files = [
+        (Path::new(PROJECT_DIR).join(CONFIG_FILE), config.to_toml()?),
+        // The database is local state, not something to commit
+        (
+            Path::new(PROJECT_DIR).join(".gitignore"),
+            "/db/\n".to_string(),
+        ),
+        (PathBuf::from(IGNORE_FILE), default_ignore_file(&languages)),
+    ];
+
+    let mut written = Vec::new();
+    let mut kept = Vec::new();
+    for (relative, contents) in files {
+        let path = root.join(&relative);
+        if path.exists() && !force {
+            kept.push(relative);
+            continue;
+        }
+        std::fs::write(&path, contents)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        written.push(relative);
+    }
+
+    Ok(InitReport {
+        root: std::path::absolute(root)?,
+        languages,
+        config,
+        written,
+        kept,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn write(root: &Path, path: &str, contents: &str) {
+        let path = root.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, contents).unwrap();
+    }
+
+    #[test]
+    fn ignore_rules_follow_gitignore_syntax() -> Result<()> {
+        let rules = IgnoreRules::parse("# build output\n\ntarget/\n*.min.js\n/docs/generated\n")?;
+        assert_eq!(rules.patterns(), ["target/", "*.min.js", "/docs/generated"]);
+        assert!(rules.is_ignored("target"));
+        assert!(rules.is_ignored("crates/core/target/debug/build.rs"));
+        assert!(rules.is_ignored("web/static/app.min.js"));
+        assert!(rules.is_ignored("docs/generated/api.md"));
+        assert!(!rules.is_ignored("src/docs/generated/api.md"));
+        assert!(!rules.is_ignored("src/target.rs"));
+
+        let error = IgnoreRules::parse("target/\n!target/keep.rs\n").unwrap_err();
+        assert!(format!("{error:#}").contains("Line 2"));
+        Ok(())
+    }
+
+    #[test]
+    fn scaffold_writes_config_and_language_excludes() -> Result<()> {
+        let dir = TempDir::new()?;
+        let root = dir.path();
+        write(root, "src/main.rs", "fn main() {}");
+        write(root, "src/lib.rs", "");
+        write(root, "scripts/gen.py", "");
+        write(root, "node_modules/left-pad/index.js", "");
+        write(root, "Cargo.toml", "");
+
+        let report = scaffold(root, None, false)?;
+        assert_eq!(report.languages, ["Rust", "Python"]);
+        assert_eq!(report.written.len(), 3);
+        assert!(report.kept.is_empty());
+
+        let rules = IgnoreRules::load(root)?;
+        assert!(rules.is_ignored(".kotadb/db/storage/wal"));
+        assert!(rules.is_ignored("target/debug/kotadb"));
+        assert!(rules.is_ignored("tools/__pycache__/gen.cpython-312.pyc"));
+        assert!(!rules.is_ignored("node_modules/left-pad/index.js"));
+        assert!(!rules.is_ignored("src/main.rs"));
+
+        let (found_root, config) = ProjectConfig::discover(&root.join("src"))?.unwrap();
+        assert_eq!(found_root, root.join("src").parent().unwrap());
+        assert_eq!(config, ProjectConfig::default());
+        assert_eq!(config.resolved_db_path(root), root.join(".kotadb/db"));
+        Ok(())
+    }
+
+    #[test]
+    fn scaffold_keeps_existing_files_unless_forced() -> Result<()> {
+        let dir = TempDir::new()?;
+        let root = dir.path();
+        write(root, IGNORE_FILE, "custom/\n");
+        let db_path = root.join("elsewhere");
+
+        let report = scaffold(root, Some(&db_path), false)?;
+        assert_eq!(report.kept, [PathBuf::from(IGNORE_FILE)]);
+        assert_eq!(IgnoreRules::load(root)?.patterns(), ["custom/"]);
+        assert_eq!(ProjectConfig::load(root)?.unwrap().db_path, db_path);
+
+        // A second run keeps the recorded database
+        let report = scaffold(root, None, false)?;
+        assert_eq!(report.config.db_path, db_path);
+        assert_eq!(report.kept.len(), 3);
+
+        let report = scaffold(root, None, true)?;
+        assert_eq!(report.written.len(), 3);
+        assert_eq!(report.config, ProjectConfig::default());
+        assert!(IgnoreRules::load(root)?.is_ignored(".kotadb/db"));
+        Ok(())
+    }
+}
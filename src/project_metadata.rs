@@ -0,0 +1,219 @@
+//! Project orientation metadata for codebase overviews
+//!
+//! Summarizes what an assistant would otherwise glob for: how the indexed source splits
+//! across languages by bytes, and where the README, license, contributing guide and build
+//! files live.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::contracts::DocumentMetadata;
+use crate::path_utils::detect_language_from_extension;
+use crate::trends::is_source_language;
+
+/// Most build files listed in an overview
+const MAX_BUILD_FILES: usize = 20;
+
+/// File names of build manifests, compared case-insensitively
+const BUILD_FILE_NAMES: &[&str] = &[
+    "build.gradle",
+    "build.gradle.kts",
+    "build.sbt",
+    "build.zig",
+    "cargo.toml",
+    "cmakelists.txt",
+    "composer.json",
+    "deno.json",
+    "dockerfile",
+    "gemfile",
+    "go.mod",
+    "justfile",
+    "makefile",
+    "meson.build",
+    "mix.exs",
+    "package.json",
+    "pom.xml",
+    "pyproject.toml",
+    "requirements.txt",
+    "setup.py",
+];
+
+/// Share of the indexed source written in one language
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LanguageShare {
+    pub language: String,
+    pub files: usize,
+    pub bytes: usize,
+    /// Percentage of all source bytes, rounded to one decimal
+    pub percentage: f64,
+}
+
+/// Locations of well-known project files, shallowest first
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ProjectFiles {
+    pub readme: Option<String>,
+    pub license: Option<String>,
+    pub contributing: Option<String>,
+    pub build_files: Vec<String>,
+}
+
+/// Languages and project files detected among stored documents
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ProjectMetadata {
+    /// Languages by descending byte count; data formats and unknown files are left out
+    pub languages: Vec<LanguageShare>,
+    pub primary_language: Option<String>,
+    pub project_files: ProjectFiles,
+}
+
+impl ProjectMetadata {
+    /// Detect metadata from stored document metadata, ignoring ingested commits
+    pub fn from_metadata(documents: &[DocumentMetadata]) -> Self {
+        Self::from_files(
+            documents
+                .iter()
+                .filter(|d| !d.tags.iter().any(|tag| tag.as_str() == "commit"))
+                .map(|d| (d.path.as_str(), d.size)),
+        )
+    }
+
+    /// Detect metadata from `(path, size in bytes)` pairs
+    pub fn from_files<'a>(files: impl IntoIterator<Item = (&'a str, usize)>) -> Self {
+        let mut by_language: HashMap<&'static str, (usize, usize)> = HashMap::new();
+        let mut readme = Vec::new();
+        let mut license = Vec::new();
+        let mut contributing = Vec::new();
+        let mut build_files = Vec::new();
+
+        for (path, size) in files {
+            let language = detect_language_from_extension(Path::new(path));
+            if is_source_language(language) {
+                let entry = by_language.entry(language).or_insert((0, 0));
+                entry.0 += 1;
+                entry.1 += size;
+            }
+
+            let name = path.rsplit('/').next().unwrap_or(path).to_lowercase();
+            let stem = name.split('.').next().unwrap_or_default();
+            if stem == "readme" {
+                readme.push(path);
+            } else if matches!(stem, "license" | "licence" | "copying")
+                || name.starts_with("license-")
+            {
+                license.push(path);
+            } else if stem == "contributing" {
+                contributing.push(path);
+            } else if BUILD_FILE_NAMES.contains(&name.as_str()) {
+                build_files.push(path);
+            }
+        }
+
+        let total_bytes: usize = by_language.values().map(|(_, bytes)| bytes).sum();
+        let mut languages: Vec<LanguageShare> = by_language
+            .into_iter()
+            .map(|(language, (files, bytes))| LanguageShare {
+                language: language.to_string(),
+                files,
+                bytes,
+                percentage: if total_bytes > 0 {
+                    (bytes as f64 * 1000.0 / total_bytes as f64).round() / 10.0
+                } else {
+                    0.0
+                },
+            })
+            .collect();
+        languages.sort_by(|a, b| b.bytes.cmp(&a.bytes).then(a.language.cmp(&b.language)));
+
+        sort_shallowest_first(&mut build_files);
+        build_files.truncate(MAX_BUILD_FILES);
+        Self {
+            primary_language: languages.first().map(|share| share.language.clone()),
+            languages,
+            project_files: ProjectFiles {
+                readme: shallowest(readme),
+                license: shallowest(license),
+                contributing: shallowest(contributing),
+                build_files: build_files.into_iter().map(str::to_string).collect(),
+            },
+        }
+    }
+}
+
+fn sort_shallowest_first(paths: &mut [&str]) {
+    paths.sort_by(|a, b| {
+        a.matches('/')
+            .count()
+            .cmp(&b.matches('/').count())
+            .then(a.cmp(b))
+    });
+}
+
+fn shallowest(mut paths: Vec<&str>) -> Option<String> {
+    sort_shallowest_first(&mut paths);
+    paths.first().map(|path| path.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn languages_are_weighted_by_bytes() {
+        let metadata = ProjectMetadata::from_files([
+            ("src/lib.rs", 600),
+            ("src/main.rs", 200),
+            ("web/app.ts", 200),
+            ("Cargo.toml", 5_000),
+            ("docs/guide.md", 9_000),
+        ]);
+
+        assert_eq!(metadata.primary_language.as_deref(), Some("Rust"));
+        assert_eq!(
+            metadata.languages,
+            vec![
+                LanguageShare {
+                    language: "Rust".to_string(),
+                    files: 2,
+                    bytes: 800,
+                    percentage: 80.0,
+                },
+                LanguageShare {
+                    language: "TypeScript".to_string(),
+                    files: 1,
+                    bytes: 200,
+                    percentage: 20.0,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn project_files_prefer_the_shallowest_location() {
+        let metadata = ProjectMetadata::from_files([
+            ("crates/core/README.md", 10),
+            ("README.md", 10),
+            ("LICENSE-MIT", 10),
+            (".github/CONTRIBUTING.md", 10),
+            ("crates/core/Cargo.toml", 10),
+            ("Cargo.toml", 10),
+            ("web/package.json", 10),
+        ]);
+
+        assert_eq!(
+            metadata.project_files,
+            ProjectFiles {
+                readme: Some("README.md".to_string()),
+                license: Some("LICENSE-MIT".to_string()),
+                contributing: Some(".github/CONTRIBUTING.md".to_string()),
+                build_files: vec![
+                    "Cargo.toml".to_string(),
+                    "web/package.json".to_string(),
+                    "crates/core/Cargo.toml".to_string(),
+                ],
+            }
+        );
+        assert!(ProjectMetadata::from_files([]).primary_language.is_none());
+    }
+}
@@ -17,6 +17,14 @@ pub fn parse_frontmatter(content: &str) -> Option<HashMap<String, serde_yaml::Va
     serde_yaml::from_str(yaml_content).ok()
 }
 
+/// Content after the YAML frontmatter block, so line numbers match the original source
+pub fn strip_frontmatter(content: &str) -> &str {
+    content
+        .strip_prefix("---\n")
+        .and_then(|rest| rest.find("\n---\n").map(|end| &rest[end + 5..]))
+        .unwrap_or(content)
+}
+
 /// Extract tags from frontmatter
 pub fn extract_tags(frontmatter: &HashMap<String, serde_yaml::Value>) -> Vec<String> {
     if let Some(serde_yaml::Value::Sequence(tags)) = frontmatter.get("tags") {
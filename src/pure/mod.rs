@@ -144,6 +144,17 @@ fn count_entries_recursive(node: &BTreeNode) -> usize {
     }
 }
 
+/// Rebuild a tree bottom-up from its entries
+///
+/// The result holds the same entries with every leaf at the same depth and the leaves
+/// packed full.
+///
+/// Time Complexity: O(n)
+/// Space Complexity: O(n)
+pub fn rebuild_tree(tree: &BTreeRoot) -> Result<BTreeRoot> {
+    build_balanced_tree_from_sorted(extract_all_pairs(tree)?)
+}
+
 /// Analyze tree structure for optimization insights
 ///
 /// Time Complexity: O(n) - single tree traversal
@@ -334,14 +345,22 @@ where
 {
     match node {
         BTreeNode::Leaf { keys, values, .. } => {
-            for (key, value) in keys.iter().zip(values.iter()) {
+            for (i, (key, value)) in keys.iter().zip(values.iter()).enumerate() {
+                // The visitor usually reads the path, which lives in its own allocation
+                if let Some(next) = values.get(i + 1) {
+                    prefetch(next.as_str().as_ptr());
+                }
                 if !visitor(key, value) {
                     return false;
                 }
             }
         }
         BTreeNode::Internal { children, .. } => {
-            for child in children {
+            for (i, child) in children.iter().enumerate() {
+                // Start loading the next sibling while this subtree is visited
+                if let Some(next) = children.get(i + 1) {
+                    prefetch(next.as_ref() as *const BTreeNode);
+                }
                 if !traverse_pairs_recursive(child, visitor) {
                     return false;
                 }
@@ -352,6 +371,19 @@ where
     true
 }
 
+/// Hint the CPU to load the cache line at `ptr` ahead of its use
+#[inline(always)]
+fn prefetch<T>(ptr: *const T) {
+    #[cfg(target_arch = "x86_64")]
+    // SAFETY: prefetching is a hint without architectural effects; it never faults,
+    // whatever the address
+    unsafe {
+        std::arch::x86_64::_mm_prefetch::<{ std::arch::x86_64::_MM_HINT_T0 }>(ptr as *const i8);
+    }
+    #[cfg(not(target_arch = "x86_64"))]
+    let _ = ptr;
+}
+
 fn extract_pairs_recursive(
     node: &BTreeNode,
     pairs: &mut Vec<(ValidatedDocumentId, ValidatedPath)>,
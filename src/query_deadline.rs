@@ -0,0 +1,119 @@
+//! Per-query deadlines with cooperative cancellation
+//!
+//! A pathological wildcard, a query matching most of the index or a deep impact
+//! traversal can otherwise run unbounded and hold index locks while it does. Query
+//! paths take a [`QueryDeadline`] and check it between units of work: loops over
+//! symbols, candidates or graph nodes stop once it expired, and awaited index
+//! searches are abandoned when it passes. What was found so far is returned, and the
+//! result is marked `timed_out` so callers can tell partial results from complete ones.
+
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+/// Loop iterations between clock reads in [`QueryDeadline::expired_at`]
+pub const CHECK_INTERVAL: usize = 256;
+
+/// Environment variable holding the server-wide query timeout in milliseconds
+pub const QUERY_TIMEOUT_ENV: &str = "KOTADB_QUERY_TIMEOUT_MS";
+
+/// Query timeout of the HTTP server unless configured otherwise
+pub const DEFAULT_SERVER_QUERY_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Point in time after which a query should stop and return what it has
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct QueryDeadline {
+    expires_at: Option<Instant>,
+}
+
+impl QueryDeadline {
+    /// A deadline that never expires
+    pub fn none() -> Self {
+        Self::default()
+    }
+
+    /// Expire `timeout` from now
+    pub fn after(timeout: Duration) -> Self {
+        Self {
+            expires_at: Instant::now().checked_add(timeout),
+        }
+    }
+
+    /// Expire `timeout_ms` milliseconds from now, or never when not given
+    pub fn from_millis(timeout_ms: Option<u64>) -> Self {
+        timeout_ms.map_or_else(Self::none, |ms| Self::after(Duration::from_millis(ms)))
+    }
+
+    /// Server deadline: `timeout_ms` from the request, else [`QUERY_TIMEOUT_ENV`], else
+    /// [`DEFAULT_SERVER_QUERY_TIMEOUT`]; a timeout of 0 disables it
+    pub fn for_server_request(timeout_ms: Option<u64>) -> Self {
+        let timeout = timeout_ms
+            .or_else(|| {
+                std::env::var(QUERY_TIMEOUT_ENV)
+                    .ok()
+                    .and_then(|value| value.trim().parse().ok())
+            })
+            .map_or(DEFAULT_SERVER_QUERY_TIMEOUT, Duration::from_millis);
+        if timeout.is_zero() {
+            Self::none()
+        } else {
+            Self::after(timeout)
+        }
+    }
+
+    pub fn is_set(&self) -> bool {
+        self.expires_at.is_some()
+    }
+
+    pub fn expired(&self) -> bool {
+        self.expires_at
+            .is_some_and(|expires_at| Instant::now() >= expires_at)
+    }
+
+    /// Whether the deadline expired, reading the clock only every [`CHECK_INTERVAL`]
+    /// iterations of a loop
+    pub fn expired_at(&self, iteration: usize) -> bool {
+        iteration.is_multiple_of(CHECK_INTERVAL) && self.expired()
+    }
+
+    /// Time left before the deadline; `None` when there is no deadline
+    pub fn remaining(&self) -> Option<Duration> {
+        self.expires_at
+            .map(|expires_at| expires_at.saturating_duration_since(Instant::now()))
+    }
+
+    /// Run `future` until the deadline; `None` if it had to be abandoned
+    ///
+    /// Abandoning drops the future, releasing any locks it held.
+    pub async fn run<F: Future>(&self, future: F) -> Option<F::Output> {
+        match self.remaining() {
+            Some(remaining) => tokio::time::timeout(remaining, future).await.ok(),
+            None => Some(future.await),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn expires_and_abandons_slow_work() {
+        let unbounded = QueryDeadline::none();
+        assert!(!unbounded.is_set() && !unbounded.expired());
+        assert_eq!(unbounded.run(async { 7 }).await, Some(7));
+
+        let deadline = QueryDeadline::after(Duration::from_millis(20));
+        assert!(!deadline.expired());
+        let slow = deadline
+            .run(tokio::time::sleep(Duration::from_secs(5)))
+            .await;
+        assert!(slow.is_none());
+        assert!(deadline.expired());
+        assert!(deadline.expired_at(0) && !deadline.expired_at(1));
+        assert_eq!(deadline.remaining(), Some(Duration::ZERO));
+
+        assert!(!QueryDeadline::for_server_request(Some(0)).is_set());
+        assert!(QueryDeadline::for_server_request(Some(1_000)).is_set());
+        assert_eq!(QueryDeadline::from_millis(None), QueryDeadline::none());
+    }
+}
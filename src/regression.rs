@@ -0,0 +1,375 @@
+// Regression module - Golden-query suites that gate index and ranking changes
+//
+// A suite is a user-maintained YAML file listing queries together with the results they are
+// expected to return in their top N. Running the suite executes every query against the current
+// database and fails when results deviate beyond the configured tolerances, so CI can block
+// index or ranking changes that silently degrade retrieval quality.
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt::Write as _;
+use std::path::Path;
+
+use crate::replay::{execute, ReplayQuery, ReplayStep};
+use crate::services::{AnalysisServiceDatabase, DatabaseAccess};
+
+/// Acceptable deviation from a query's expected results
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Tolerance {
+    /// Fraction of expected results that must appear in the top N (default 1.0)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_overlap: Option<f64>,
+    /// How far an expected result may move from its listed position when `ordered` (default 0)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_rank_shift: Option<usize>,
+    /// Slowest acceptable execution time; unset disables the latency check
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_latency_ms: Option<f64>,
+}
+
+impl Tolerance {
+    /// Fill unset fields from `defaults`
+    fn or(self, defaults: Tolerance) -> Tolerance {
+        Tolerance {
+            min_overlap: self.min_overlap.or(defaults.min_overlap),
+            max_rank_shift: self.max_rank_shift.or(defaults.max_rank_shift),
+            max_latency_ms: self.max_latency_ms.or(defaults.max_latency_ms),
+        }
+    }
+}
+
+/// A query and the results it must keep returning
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GoldenQuery {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(flatten)]
+    pub query: ReplayQuery,
+    /// Expected results: file paths, symbol names, or `name@path:line` keys
+    pub expect: Vec<String>,
+    /// Whether `expect` is listed in ranking order
+    #[serde(default)]
+    pub ordered: bool,
+    /// Number of leading results inspected; defaults to the query's limit
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_n: Option<usize>,
+    #[serde(default)]
+    pub tolerance: Tolerance,
+}
+
+impl GoldenQuery {
+    pub fn label(&self) -> String {
+        self.name
+            .clone()
+            .unwrap_or_else(|| format!("{} '{}'", self.query.tool(), self.query.subject()))
+    }
+
+    fn top_n(&self) -> usize {
+        self.top_n.unwrap_or(match &self.query {
+            ReplayQuery::SearchCode { limit, .. } | ReplayQuery::SearchSymbols { limit, .. } => {
+                *limit
+            }
+            ReplayQuery::FindCallers { limit, .. } | ReplayQuery::AnalyzeImpact { limit, .. } => {
+                limit.unwrap_or(usize::MAX)
+            }
+        })
+    }
+}
+
+/// A golden-query suite loaded from YAML
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegressionSuite {
+    /// Tolerances applied to queries that do not override them
+    #[serde(default)]
+    pub defaults: Tolerance,
+    pub queries: Vec<GoldenQuery>,
+}
+
+impl RegressionSuite {
+    pub fn load(path: &Path) -> Result<Self> {
+        let raw = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read regression suite {}", path.display()))?;
+        Self::parse(&raw).with_context(|| format!("invalid regression suite {}", path.display()))
+    }
+
+    pub fn parse(raw: &str) -> Result<Self> {
+        let suite: RegressionSuite = serde_yaml::from_str(raw)?;
+        if suite.queries.is_empty() {
+            bail!("suite defines no queries");
+        }
+        for query in &suite.queries {
+            let tolerance = query.tolerance.or(suite.defaults);
+            if let Some(overlap) = tolerance.min_overlap {
+                if !(0.0..=1.0).contains(&overlap) {
+                    bail!("{}: min_overlap must be between 0.0 and 1.0", query.label());
+                }
+            }
+            if query.top_n == Some(0) {
+                bail!("{}: top_n must be at least 1", query.label());
+            }
+        }
+        Ok(suite)
+    }
+}
+
+/// Expected entries match a result key exactly, as a path suffix, by the symbol name before
+/// `@` (or its trailing `::` segments), or by the file path after `@`
+fn matches_expected(key: &str, expected: &str) -> bool {
+    let expected = expected.trim_start_matches('/');
+    let path_matches = |path: &str| path == expected || path.ends_with(&format!("/{}", expected));
+    if key == expected || path_matches(key) {
+        return true;
+    }
+    match key.split_once('@') {
+        Some((name, location)) => {
+            let path = location.rsplit_once(':').map_or(location, |(path, _)| path);
+            name == expected || name.ends_with(&format!("::{}", expected)) || path_matches(path)
+        }
+        None => false,
+    }
+}
+
+/// Outcome of one golden query
+#[derive(Debug, Clone, Serialize)]
+pub struct QueryOutcome {
+    pub name: String,
+    pub passed: bool,
+    /// Fraction of expected results found in the top N
+    pub overlap: f64,
+    pub missing: Vec<String>,
+    /// Largest distance between an expected result's listed and actual position
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_rank_shift: Option<usize>,
+    pub duration_ms: f64,
+    pub failures: Vec<String>,
+    /// Leading results actually returned, for updating the suite
+    pub top_results: Vec<String>,
+}
+
+/// Check a step's results against a golden query
+pub fn evaluate(golden: &GoldenQuery, defaults: Tolerance, step: &ReplayStep) -> QueryOutcome {
+    let tolerance = golden.tolerance.or(defaults);
+    let top: Vec<&String> = step.results.iter().take(golden.top_n()).collect();
+
+    let mut missing = Vec::new();
+    let mut worst_shift = None;
+    for (expected_rank, expected) in golden.expect.iter().enumerate() {
+        match top.iter().position(|key| matches_expected(key, expected)) {
+            Some(actual_rank) => {
+                if golden.ordered {
+                    let shift = actual_rank.abs_diff(expected_rank);
+                    worst_shift = Some(worst_shift.map_or(shift, |worst: usize| worst.max(shift)));
+                }
+            }
+            None => missing.push(expected.clone()),
+        }
+    }
+    let overlap = if golden.expect.is_empty() {
+        1.0
+    } else {
+        (golden.expect.len() - missing.len()) as f64 / golden.expect.len() as f64
+    };
+
+    let mut failures = Vec::new();
+    let min_overlap = tolerance.min_overlap.unwrap_or(1.0);
+    if overlap < min_overlap {
+        failures.push(format!(
+            "overlap {:.0}% below required {:.0}% (missing: {})",
+            overlap * 100.0,
+            min_overlap * 100.0,
+            missing.join(", ")
+        ));
+    }
+    if let Some(shift) = worst_shift {
+        let allowed = tolerance.max_rank_shift.unwrap_or(0);
+        if shift > allowed {
+            failures.push(format!(
+                "expected result moved {} position(s), allowed {}",
+                shift, allowed
+            ));
+        }
+    }
+    if let Some(max_latency) = tolerance.max_latency_ms {
+        if step.duration_ms > max_latency {
+            failures.push(format!(
+                "took {:.1} ms, limit {:.1} ms",
+                step.duration_ms, max_latency
+            ));
+        }
+    }
+
+    QueryOutcome {
+        name: golden.label(),
+        passed: failures.is_empty(),
+        overlap,
+        missing,
+        max_rank_shift: worst_shift,
+        duration_ms: step.duration_ms,
+        failures,
+        top_results: top.into_iter().cloned().collect(),
+    }
+}
+
+/// Results of running a suite
+#[derive(Debug, Clone, Serialize)]
+pub struct RegressionReport {
+    pub passed: usize,
+    pub failed: usize,
+    pub queries: Vec<QueryOutcome>,
+}
+
+impl RegressionReport {
+    pub fn is_success(&self) -> bool {
+        self.failed == 0
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "# Regression suite\n");
+        for outcome in &self.queries {
+            let status = if outcome.passed { "✅" } else { "❌" };
+            let _ = writeln!(
+                out,
+                "{} {} ({:.0}% overlap, {:.1} ms)",
+                status,
+                outcome.name,
+                outcome.overlap * 100.0,
+                outcome.duration_ms
+            );
+            for failure in &outcome.failures {
+                let _ = writeln!(out, "   - {}", failure);
+            }
+            if !outcome.passed {
+                let _ = writeln!(out, "   - top results: {}", outcome.top_results.join(", "));
+            }
+        }
+        let _ = writeln!(out, "\n**{} passed, {} failed**", self.passed, self.failed);
+        out
+    }
+}
+
+/// Execute every query in `suite` against `db` and evaluate it
+pub async fn run_suite<D>(
+    db: &D,
+    db_path: &Path,
+    suite: &RegressionSuite,
+) -> Result<RegressionReport>
+where
+    D: DatabaseAccess + AnalysisServiceDatabase,
+{
+    let mut queries = Vec::with_capacity(suite.queries.len());
+    for golden in &suite.queries {
+        let step = execute(db, db_path, &golden.query)
+            .await
+            .with_context(|| format!("{} failed", golden.label()))?;
+        queries.push(evaluate(golden, suite.defaults, &step));
+    }
+    let passed = queries.iter().filter(|q| q.passed).count();
+    Ok(RegressionReport {
+        passed,
+        failed: queries.len() - passed,
+        queries,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SUITE: &str = r#"
+defaults:
+  min_overlap: 0.5
+queries:
+  - name: storage search
+    tool: search_code
+    query: storage
+    expect: [src/file_storage.rs, src/wrappers.rs]
+    ordered: true
+    tolerance:
+      max_rank_shift: 1
+  - tool: find_callers
+    target: FileStorage
+    expect: [create_file_storage, src/main.rs]
+"#;
+
+    fn step(results: &[&str], duration_ms: f64) -> ReplayStep {
+        ReplayStep {
+            query: ReplayQuery::SearchCode {
+                query: "storage".into(),
+                limit: 10,
+                tags: None,
+                context: "minimal".into(),
+                group_by: Default::default(),
+            },
+            duration_ms,
+            estimated_tokens: 0,
+            result_count: results.len(),
+            results: results.iter().map(|r| r.to_string()).collect(),
+            scores: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn parses_suite_with_defaults() {
+        let suite = RegressionSuite::parse(SUITE).unwrap();
+        assert_eq!(suite.queries.len(), 2);
+        assert_eq!(suite.queries[0].label(), "storage search");
+        assert_eq!(suite.queries[1].label(), "find_callers 'FileStorage'");
+        assert!(matches!(
+            &suite.queries[0].query,
+            ReplayQuery::SearchCode { limit: 10, context, .. } if context == "minimal"
+        ));
+
+        assert!(RegressionSuite::parse("queries: []").is_err());
+        assert!(RegressionSuite::parse(
+            "queries:\n  - tool: search_code\n    query: x\n    expect: []\n    tolerance:\n      min_overlap: 2\n"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn evaluates_overlap_rank_shift_and_latency() {
+        let suite = RegressionSuite::parse(SUITE).unwrap();
+        let golden = &suite.queries[0];
+
+        let good = step(
+            &[
+                "repos/r/files/src/wrappers.rs",
+                "repos/r/files/src/file_storage.rs",
+            ],
+            3.0,
+        );
+        let outcome = evaluate(golden, suite.defaults, &good);
+        assert!(outcome.passed, "{:?}", outcome.failures);
+        assert_eq!(outcome.max_rank_shift, Some(1));
+
+        let shifted = step(
+            &["a.rs", "b.rs", "src/wrappers.rs", "src/file_storage.rs"],
+            3.0,
+        );
+        let outcome = evaluate(golden, suite.defaults, &shifted);
+        assert!(!outcome.passed);
+        assert_eq!(outcome.max_rank_shift, Some(3));
+
+        // Half the expected results satisfies the suite-wide 50% overlap
+        let partial = step(&["src/file_storage.rs"], 3.0);
+        let outcome = evaluate(golden, suite.defaults, &partial);
+        assert!(outcome.passed);
+        assert_eq!(outcome.missing, vec!["src/wrappers.rs"]);
+
+        let mut slow = golden.clone();
+        slow.tolerance.max_latency_ms = Some(1.0);
+        assert!(!evaluate(&slow, suite.defaults, &good).passed);
+    }
+
+    #[test]
+    fn expected_entries_match_names_and_paths() {
+        assert!(matches_expected("repos/r/files/src/lib.rs", "src/lib.rs"));
+        assert!(!matches_expected("repos/r/files/src/mylib.rs", "lib.rs"));
+        assert!(matches_expected("crate::run@src/main.rs:12", "crate::run"));
+        assert!(matches_expected("crate::run@src/main.rs:12", "src/main.rs"));
+        assert!(matches_expected("src/main.rs::run@src/main.rs:12", "run"));
+        assert!(!matches_expected("crate::run@src/main.rs:12", "un"));
+    }
+}
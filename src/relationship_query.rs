@@ -112,6 +112,16 @@ pub struct RelationshipMatch {
     pub location: RelationshipLocation,
     /// Context around the relationship
     pub context: String,
+    /// Graph distance from the query target (1 = direct relationship)
+    #[serde(default = "default_relationship_depth")]
+    pub depth: usize,
+    /// Source text at the relationship site, when known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snippet: Option<String>,
+}
+
+fn default_relationship_depth() -> usize {
+    1
 }
 
 /// Location information for a relationship
@@ -121,10 +131,56 @@ pub struct RelationshipLocation {
     pub line_number: usize,
     /// Column number
     pub column_number: usize,
+    /// Line number where the relationship ends (0 when unknown)
+    #[serde(default)]
+    pub end_line_number: usize,
+    /// Column number where the relationship ends
+    #[serde(default)]
+    pub end_column_number: usize,
+    /// Byte offset of the relationship within the source file
+    #[serde(default)]
+    pub start_byte: usize,
+    /// Byte offset just past the end of the relationship (0 when unknown)
+    #[serde(default)]
+    pub end_byte: usize,
     /// Source file path
     pub file_path: String,
 }
 
+impl RelationshipLocation {
+    /// Exact source range, when byte offsets were recorded during indexing
+    pub fn range(&self) -> Option<SourceRange> {
+        if self.end_byte <= self.start_byte {
+            return None;
+        }
+        Some(SourceRange {
+            start_line: self.line_number,
+            start_column: self.column_number,
+            end_line: self.end_line_number,
+            end_column: self.end_column_number,
+            start_byte: self.start_byte,
+            end_byte: self.end_byte,
+        })
+    }
+}
+
+/// Exact range of a symbol or reference in a source file, for editor highlighting
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourceRange {
+    /// Start line (1-based)
+    pub start_line: usize,
+    /// Start column (0-based byte column)
+    pub start_column: usize,
+    /// End line (1-based)
+    pub end_line: usize,
+    /// End column (0-based byte column, exclusive)
+    pub end_column: usize,
+    /// Byte offset of the start within the file
+    pub start_byte: usize,
+    /// Byte offset just past the end within the file
+    pub end_byte: usize,
+}
+
 /// A call path between two symbols
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CallPath {
@@ -544,6 +600,10 @@ impl RelationshipQueryEngine {
                 let location = RelationshipLocation {
                     line_number: 0, // We don't have this info readily available
                     column_number: 0,
+                    end_line_number: 0,
+                    end_column_number: 0,
+                    start_byte: 0,
+                    end_byte: 0,
                     file_path: node.file_path.display().to_string(),
                 };
 
@@ -561,6 +621,8 @@ impl RelationshipQueryEngine {
                     relation_type: RelationType::Custom("unused".to_string()),
                     location,
                     context: "Symbol has no incoming dependencies".to_string(),
+                    depth: 1,
+                    snippet: None,
                 };
 
                 unused_relationships.push(relationship);
@@ -623,6 +685,10 @@ impl RelationshipQueryEngine {
             let location = RelationshipLocation {
                 line_number: 0,
                 column_number: 0,
+                end_line_number: 0,
+                end_column_number: 0,
+                start_byte: 0,
+                end_byte: 0,
                 file_path: node.file_path.display().to_string(),
             };
 
@@ -640,6 +706,8 @@ impl RelationshipQueryEngine {
                 relation_type: RelationType::Custom("hot_path".to_string()),
                 location,
                 context: format!("Called by {} other symbols", in_degree),
+                depth: 1,
+                snippet: None,
             };
 
             hot_relationships.push(relationship);
@@ -839,6 +907,8 @@ impl RelationshipQueryEngine {
         let mut edge_context = "No context available".to_string();
         let mut line_number = 0;
         let mut column_number = 0;
+        let mut end_position = (0, 0);
+        let mut byte_range = (0, 0);
 
         if let Some(target_node_idx) = self.dependency_graph.symbol_to_node.get(target_id) {
             for edge in self.dependency_graph.graph.edges(*source_node_idx.unwrap()) {
@@ -846,6 +916,14 @@ impl RelationshipQueryEngine {
                     let edge_data = edge.weight();
                     line_number = edge_data.line_number;
                     column_number = edge_data.column_number;
+                    end_position = (
+                        edge_data.end_line_number.unwrap_or_default(),
+                        edge_data.end_column_number.unwrap_or_default(),
+                    );
+                    byte_range = (
+                        edge_data.start_byte.unwrap_or_default(),
+                        edge_data.end_byte.unwrap_or_default(),
+                    );
                     if let Some(ctx) = &edge_data.context {
                         edge_context = ctx.clone();
                     }
@@ -857,6 +935,10 @@ impl RelationshipQueryEngine {
         let location = RelationshipLocation {
             line_number,
             column_number,
+            end_line_number: end_position.0,
+            end_column_number: end_position.1,
+            start_byte: byte_range.0,
+            end_byte: byte_range.1,
             file_path: source_node.file_path.display().to_string(),
         };
 
@@ -874,6 +956,8 @@ impl RelationshipQueryEngine {
             relation_type,
             location,
             context: edge_context,
+            depth: 1,
+            snippet: None,
         }))
     }
 
@@ -1006,9 +1090,15 @@ impl RelationshipQueryResult {
                     "   - **Location:** {}:{}\n",
                     rel.location.line_number, rel.location.column_number
                 ));
+                if rel.depth > 1 {
+                    output.push_str(&format!("   - **Distance:** {}\n", rel.depth));
+                }
                 if !rel.context.is_empty() {
                     output.push_str(&format!("   - **Context:** {}\n", rel.context));
                 }
+                if let Some(snippet) = &rel.snippet {
+                    output.push_str(&format!("   - **Source:** `{}`\n", snippet.trim()));
+                }
                 output.push('\n');
             }
         }
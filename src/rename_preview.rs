@@ -0,0 +1,768 @@
+//! Rename previews
+//!
+//! Before a large rename it helps to see every place that has to change. A
+//! [`RenamePreview`] lists the definitions of a symbol and the references recorded in
+//! the dependency graph (the data behind `find-callers`), grouped by file with the exact
+//! range of the name at each, and separately flags mentions it cannot verify: string
+//! literals that may reach the symbol dynamically (reflection, `getattr`, serialization
+//! keys, route tables), comments, mentions in code with no recorded relationship, and
+//! recorded references whose location no longer holds the name.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::path_utils::detect_language_from_extension;
+use crate::relationship_query::SourceRange;
+
+/// Location of a name on one line
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct EditRange {
+    /// Line (1-based)
+    pub line: usize,
+    /// Start column (0-based byte column)
+    pub start_column: usize,
+    /// End column (0-based byte column, exclusive)
+    pub end_column: usize,
+}
+
+/// Whether an edit renames the symbol itself or a use of it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EditKind {
+    Definition,
+    Reference,
+}
+
+/// A verified occurrence of the name that the rename has to change
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RenameEdit {
+    pub kind: EditKind,
+    pub range: EditRange,
+    /// Symbol kind of a definition, or the referencing symbol of a reference
+    pub symbol: String,
+    /// The edited line, trimmed
+    pub text: String,
+}
+
+/// Why a mention of the name could not be confirmed as an edit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UnverifiedReason {
+    /// Inside a string literal, where it may name the symbol dynamically
+    StringLiteral,
+    /// Inside a comment
+    Comment,
+    /// In code, but no relationship to the symbol was recorded there; it may be a
+    /// shadowing local, a macro-generated use or a reference the parser missed
+    Unrecorded,
+    /// A recorded definition or reference whose location does not hold the name
+    NotAtRecordedLocation,
+    /// A recorded reference without a source location
+    NoLocation,
+    /// The file of a recorded definition or reference is not in storage
+    SourceUnavailable,
+}
+
+/// A mention of the name, or a recorded location, the preview cannot verify
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct UnverifiedReference {
+    pub reason: UnverifiedReason,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub range: Option<EditRange>,
+    /// Line of a recorded location the name was not found at
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+    /// Referencing symbol of a recorded reference
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub symbol: Option<String>,
+    /// The line holding the mention, trimmed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+}
+
+/// Edits and unverified mentions in one file, in line order
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FileRenames {
+    pub file_path: String,
+    pub edits: Vec<RenameEdit>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub unverified: Vec<UnverifiedReference>,
+}
+
+/// Definition of the symbol recorded in the symbol index
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordedDefinition {
+    pub kind: String,
+    /// 1-based, inclusive line range of the definition
+    pub start_line: usize,
+    pub end_line: usize,
+}
+
+/// Reference to the symbol recorded in the dependency graph
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordedReference {
+    /// Symbol the reference is made from
+    pub symbol: String,
+    pub line: Option<u32>,
+    pub column: Option<u32>,
+    pub range: Option<SourceRange>,
+}
+
+/// Every location a rename of one symbol touches
+#[derive(Debug, Clone, Serialize)]
+pub struct RenamePreview {
+    pub symbol: String,
+    pub new_name: String,
+    /// Files with edits or unverified mentions, by path
+    pub files: Vec<FileRenames>,
+    pub edit_count: usize,
+    pub unverified_count: usize,
+    /// Existing definitions of the new name as `path:line`, which the rename would
+    /// collide with or shadow
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub conflicts: Vec<String>,
+}
+
+impl RenamePreview {
+    /// Preview of renaming `symbol` to `new_name` from per-file findings
+    pub fn new(
+        symbol: &str,
+        new_name: &str,
+        mut files: Vec<FileRenames>,
+        conflicts: Vec<String>,
+    ) -> Self {
+        files.retain(|f| !f.edits.is_empty() || !f.unverified.is_empty());
+        files.sort_by(|a, b| a.file_path.cmp(&b.file_path));
+        Self {
+            symbol: symbol.to_string(),
+            new_name: new_name.to_string(),
+            edit_count: files.iter().map(|f| f.edits.len()).sum(),
+            unverified_count: files.iter().map(|f| f.unverified.len()).sum(),
+            files,
+            conflicts,
+        }
+    }
+
+    /// Edits per file with `line:start-end` ranges, then mentions to check by hand
+    pub fn to_markdown(&self) -> String {
+        let file_count = self.files.iter().filter(|f| !f.edits.is_empty()).count();
+        let mut output = format!(
+            "Renaming `{}` to `{}`: {} edit{} in {} file{}",
+            self.symbol,
+            self.new_name,
+            self.edit_count,
+            plural(self.edit_count),
+            file_count,
+            plural(file_count)
+        );
+        if self.unverified_count > 0 {
+            output.push_str(&format!(
+                ", {} mention{} to check by hand",
+                self.unverified_count,
+                plural(self.unverified_count)
+            ));
+        }
+        output.push_str("\n\n");
+        if !self.conflicts.is_empty() {
+            output.push_str(&format!(
+                "**Warning:** `{}` is already defined at {}\n\n",
+                self.new_name,
+                self.conflicts.join(", ")
+            ));
+        }
+
+        for file in self.files.iter().filter(|f| !f.edits.is_empty()) {
+            output.push_str(&format!("## {}\n", file.file_path));
+            for edit in &file.edits {
+                let kind = match edit.kind {
+                    EditKind::Definition => "definition",
+                    EditKind::Reference => "reference",
+                };
+                output.push_str(&format!(
+                    "- {}:{}-{} {} ({}): `{}`\n",
+                    edit.range.line,
+                    edit.range.start_column,
+                    edit.range.end_column,
+                    kind,
+                    edit.symbol,
+                    edit.text
+                ));
+            }
+            output.push('\n');
+        }
+
+        if self.unverified_count > 0 {
+            output.push_str("## Unverified\n");
+            for file in &self.files {
+                for mention in &file.unverified {
+                    let location = match (mention.range, mention.line) {
+                        (Some(range), _) => format!(
+                            "{}:{}:{}-{}",
+                            file.file_path, range.line, range.start_column, range.end_column
+                        ),
+                        (None, Some(line)) => format!("{}:{}", file.file_path, line),
+                        (None, None) => file.file_path.clone(),
+                    };
+                    let reason = match mention.reason {
+                        UnverifiedReason::StringLiteral => "string literal",
+                        UnverifiedReason::Comment => "comment",
+                        UnverifiedReason::Unrecorded => "no recorded relationship",
+                        UnverifiedReason::NotAtRecordedLocation => "name not at recorded location",
+                        UnverifiedReason::NoLocation => "recorded without a location",
+                        UnverifiedReason::SourceUnavailable => "source not in the index",
+                    };
+                    output.push_str(&format!("- {} [{}]", location, reason));
+                    if let Some(symbol) = &mention.symbol {
+                        output.push_str(&format!(" in `{}`", symbol));
+                    }
+                    if let Some(text) = &mention.text {
+                        output.push_str(&format!(": `{}`", text));
+                    }
+                    output.push('\n');
+                }
+            }
+        }
+        output
+    }
+}
+
+fn plural(count: usize) -> &'static str {
+    if count == 1 {
+        ""
+    } else {
+        "s"
+    }
+}
+
+/// Last segment of a `::` or `.` qualified symbol name, the text a rename replaces
+pub fn bare_name(symbol: &str) -> &str {
+    symbol
+        .rsplit("::")
+        .next()
+        .and_then(|name| name.rsplit('.').next())
+        .unwrap_or(symbol)
+}
+
+/// Check that `new_name` can replace the bare name of `symbol`
+pub fn validate_new_name(symbol: &str, new_name: &str) -> Result<()> {
+    let mut chars = new_name.chars();
+    let valid = chars.next().is_some_and(|c| c == '_' || c.is_alphabetic())
+        && chars.all(|c| c == '_' || c.is_alphanumeric());
+    if !valid {
+        anyhow::bail!(
+            "'{}' is not an identifier. Give only the new name, without a path or module",
+            new_name
+        );
+    }
+    if new_name == bare_name(symbol) {
+        anyhow::bail!("'{}' already has that name", symbol);
+    }
+    Ok(())
+}
+
+/// Edits and unverified mentions of `name` in one file
+///
+/// Each recorded definition claims the first occurrence of the name in code within
+/// its lines, and each recorded reference the first unclaimed occurrence inside its
+/// range, or on its line when no range was recorded. Every other occurrence is
+/// reported as unverified. Files without recorded definitions or references only
+/// report string literals, since a bare name in their code or comments is usually an
+/// unrelated symbol.
+pub fn preview_file(
+    file_path: &str,
+    content: &str,
+    name: &str,
+    definitions: &[RecordedDefinition],
+    references: &[RecordedReference],
+) -> FileRenames {
+    let lines: Vec<&str> = content.lines().collect();
+    let regions = classify_regions(content, Path::new(file_path));
+    let mut occurrences: Vec<(EditRange, Region, bool)> = Vec::new();
+    let mut line_start = 0;
+    for (index, line) in content.split('\n').enumerate() {
+        for column in word_occurrences(line, name) {
+            let region = regions[line_start + column];
+            let range = EditRange {
+                line: index + 1,
+                start_column: column,
+                end_column: column + name.len(),
+            };
+            occurrences.push((range, region, false));
+        }
+        line_start += line.len() + 1;
+    }
+    let text_at = |line: usize| lines.get(line - 1).map(|l| l.trim().to_string());
+
+    let mut edits = Vec::new();
+    let mut unverified = Vec::new();
+    for definition in definitions {
+        let found = occurrences.iter_mut().find(|(range, region, claimed)| {
+            !*claimed
+                && *region == Region::Code
+                && (definition.start_line..=definition.end_line.max(definition.start_line))
+                    .contains(&range.line)
+        });
+        match found {
+            Some((range, _, claimed)) => {
+                *claimed = true;
+                edits.push(RenameEdit {
+                    kind: EditKind::Definition,
+                    range: *range,
+                    symbol: definition.kind.clone(),
+                    text: text_at(range.line).unwrap_or_default(),
+                });
+            }
+            None => unverified.push(UnverifiedReference {
+                reason: UnverifiedReason::NotAtRecordedLocation,
+                range: None,
+                line: Some(definition.start_line),
+                symbol: Some(definition.kind.clone()),
+                text: text_at(definition.start_line),
+            }),
+        }
+    }
+
+    for reference in references {
+        let Some(line) = reference.line.map(|line| line as usize) else {
+            unverified.push(UnverifiedReference {
+                reason: UnverifiedReason::NoLocation,
+                range: None,
+                line: None,
+                symbol: Some(reference.symbol.clone()),
+                text: None,
+            });
+            continue;
+        };
+        let within = |range: &EditRange| match &reference.range {
+            Some(recorded) => {
+                (recorded.start_line, recorded.start_column) <= (range.line, range.start_column)
+                    && (range.line, range.end_column) <= (recorded.end_line, recorded.end_column)
+            }
+            None => {
+                range.line == line
+                    && reference
+                        .column
+                        .is_none_or(|column| range.end_column > column as usize)
+            }
+        };
+        let found = occurrences
+            .iter_mut()
+            .find(|(range, region, claimed)| !*claimed && *region == Region::Code && within(range));
+        match found {
+            Some((range, _, claimed)) => {
+                *claimed = true;
+                edits.push(RenameEdit {
+                    kind: EditKind::Reference,
+                    range: *range,
+                    symbol: reference.symbol.clone(),
+                    text: text_at(range.line).unwrap_or_default(),
+                });
+            }
+            None => unverified.push(UnverifiedReference {
+                reason: UnverifiedReason::NotAtRecordedLocation,
+                range: None,
+                line: Some(line),
+                symbol: Some(reference.symbol.clone()),
+                text: text_at(line),
+            }),
+        }
+    }
+
+    let recorded = !definitions.is_empty() || !references.is_empty();
+    for (range, region, claimed) in occurrences {
+        let reason = match region {
+            _ if claimed => continue,
+            Region::String => UnverifiedReason::StringLiteral,
+            Region::Comment if recorded => UnverifiedReason::Comment,
+            Region::Code if recorded => UnverifiedReason::Unrecorded,
+            _ => continue,
+        };
+        unverified.push(UnverifiedReference {
+            reason,
+            range: Some(range),
+            line: None,
+            symbol: None,
+            text: text_at(range.line),
+        });
+    }
+
+    edits.sort_by_key(|edit| edit.range);
+    unverified.sort_by_key(|mention| {
+        (
+            mention.range.map(|r| r.line).or(mention.line),
+            mention.range,
+        )
+    });
+    FileRenames {
+        file_path: file_path.to_string(),
+        edits,
+        unverified,
+    }
+}
+
+/// Recorded definitions and references of a file that is not in storage
+pub fn unavailable_file(
+    file_path: &str,
+    definitions: &[RecordedDefinition],
+    references: &[RecordedReference],
+) -> FileRenames {
+    let unverified = definitions
+        .iter()
+        .map(|d| (Some(d.start_line), d.kind.clone()))
+        .chain(
+            references
+                .iter()
+                .map(|r| (r.line.map(|line| line as usize), r.symbol.clone())),
+        )
+        .map(|(line, symbol)| UnverifiedReference {
+            reason: UnverifiedReason::SourceUnavailable,
+            range: None,
+            line,
+            symbol: Some(symbol),
+            text: None,
+        })
+        .collect();
+    FileRenames {
+        file_path: file_path.to_string(),
+        edits: Vec::new(),
+        unverified,
+    }
+}
+
+/// Group recorded definitions and references by file
+pub fn group_by_file(
+    definitions: impl IntoIterator<Item = (String, RecordedDefinition)>,
+    references: impl IntoIterator<Item = (String, RecordedReference)>,
+) -> BTreeMap<String, (Vec<RecordedDefinition>, Vec<RecordedReference>)> {
+    let mut files: BTreeMap<String, (Vec<RecordedDefinition>, Vec<RecordedReference>)> =
+        BTreeMap::new();
+    for (path, definition) in definitions {
+        files.entry(path).or_default().0.push(definition);
+    }
+    for (path, reference) in references {
+        files.entry(path).or_default().1.push(reference);
+    }
+    files
+}
+
+/// Byte columns of whole-word occurrences of `name` in `line`
+fn word_occurrences(line: &str, name: &str) -> Vec<usize> {
+    if name.is_empty() {
+        return Vec::new();
+    }
+    let bytes = line.as_bytes();
+    line.match_indices(name)
+        .map(|(start, _)| start)
+        .filter(|&start| {
+            let end = start + name.len();
+            (start == 0 || !is_word_byte(bytes[start - 1]))
+                && bytes.get(end).is_none_or(|b| !is_word_byte(*b))
+        })
+        .collect()
+}
+
+/// Identifier bytes, counting every byte of a non-ASCII character
+fn is_word_byte(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || byte == b'_' || !byte.is_ascii()
+}
+
+/// Part of a source file a byte belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Region {
+    Code,
+    Comment,
+    String,
+}
+
+/// Region of every byte of `source`, with comment and string syntax chosen by the
+/// file's language
+fn classify_regions(source: &str, path: &Path) -> Vec<Region> {
+    let language = detect_language_from_extension(path);
+    let hash_comments = matches!(
+        language,
+        "Python" | "Ruby" | "Perl" | "R" | "Julia" | "Elixir" | "Nim"
+    ) || path
+        .extension()
+        .is_some_and(|ext| matches!(ext.to_str(), Some("sh" | "bash" | "yml" | "yaml" | "toml")));
+    let slash_comments = !hash_comments;
+    let quote_strings = language != "Rust";
+    let backtick_strings = matches!(language, "JavaScript" | "TypeScript" | "Go");
+
+    let bytes = source.as_bytes();
+    let mut regions = vec![Region::Code; bytes.len()];
+    let mut i = 0;
+    while i < bytes.len() {
+        let rest = &bytes[i..];
+        let (region, end) =
+            if (hash_comments && rest[0] == b'#') || (slash_comments && rest.starts_with(b"//")) {
+                (Region::Comment, line_end(source, i))
+            } else if slash_comments && rest.starts_with(b"/*") {
+                let end = source[i + 2..]
+                    .find("*/")
+                    .map_or(bytes.len(), |n| i + n + 4);
+                (Region::Comment, end)
+            } else if hash_comments && (rest.starts_with(b"\"\"\"") || rest.starts_with(b"'''")) {
+                let delimiter = &source[i..i + 3];
+                let end = source[i + 3..]
+                    .find(delimiter)
+                    .map_or(bytes.len(), |n| i + n + 6);
+                (Region::String, end)
+            } else if language == "Rust"
+                && rest[0] == b'r'
+                && (i == 0 || !is_word_byte(bytes[i - 1]))
+                && matches!(rest.get(1), Some(b'"' | b'#'))
+            {
+                let hashes = rest[1..].iter().take_while(|b| **b == b'#').count();
+                if rest.get(1 + hashes) != Some(&b'"') {
+                    i += 1;
+                    continue;
+                }
+                let terminator = format!("\"{}", "#".repeat(hashes));
+                let body = i + 2 + hashes;
+                let end = source[body..]
+                    .find(&terminator)
+                    .map_or(bytes.len(), |n| body + n + terminator.len());
+                (Region::String, end)
+            } else if language == "Rust" && rest[0] == b'\'' {
+                // Char literals; a lifetime has no closing quote right after one character
+                let close = if rest.get(1) == Some(&b'\\') {
+                    source[i + 2..].find('\'').map(|n| i + 2 + n)
+                } else {
+                    source[i + 1..]
+                        .char_indices()
+                        .nth(1)
+                        .filter(|(_, c)| *c == '\'')
+                        .map(|(n, _)| i + 1 + n)
+                };
+                match close {
+                    Some(close) => (Region::String, close + 1),
+                    None => {
+                        i += 1;
+                        continue;
+                    }
+                }
+            } else if rest[0] == b'"'
+                || (quote_strings && rest[0] == b'\'')
+                || (backtick_strings && rest[0] == b'`')
+            {
+                let quote = rest[0];
+                let mut j = i + 1;
+                while j < bytes.len() && bytes[j] != quote {
+                    // Only backticks and Rust strings span lines
+                    if bytes[j] == b'\n' && quote != b'`' && language != "Rust" {
+                        break;
+                    }
+                    j += if bytes[j] == b'\\' { 2 } else { 1 };
+                }
+                (Region::String, (j + 1).min(bytes.len()))
+            } else {
+                i += 1;
+                continue;
+            };
+        for slot in &mut regions[i..end] {
+            *slot = region;
+        }
+        i = end;
+    }
+    regions
+}
+
+fn line_end(source: &str, from: usize) -> usize {
+    source[from..].find('\n').map_or(source.len(), |n| from + n)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RUST: &str = r#"/// Loads the configuration; see `load_config` callers.
+pub fn load_config(path: &str) -> Config {
+    parse(path)
+}
+
+fn main() {
+    let config = load_config("app.toml");
+    let handler = registry.get("load_config");
+    let load_config_v2 = load_config(load_config("nested"));
+}
+"#;
+
+    fn reference(symbol: &str, line: u32, start: usize, end: usize) -> RecordedReference {
+        RecordedReference {
+            symbol: symbol.to_string(),
+            line: Some(line),
+            column: Some(start as u32),
+            range: Some(SourceRange {
+                start_line: line as usize,
+                start_column: start,
+                end_line: line as usize,
+                end_column: end,
+                start_byte: 0,
+                end_byte: 0,
+            }),
+        }
+    }
+
+    fn definition() -> RecordedDefinition {
+        RecordedDefinition {
+            kind: "function".to_string(),
+            start_line: 2,
+            end_line: 4,
+        }
+    }
+
+    #[test]
+    fn claims_exact_ranges_of_recorded_definitions_and_references() {
+        let file = preview_file(
+            "src/main.rs",
+            RUST,
+            "load_config",
+            &[definition()],
+            &[
+                reference("main", 7, 17, 41),
+                reference("main", 9, 25, 60),
+                reference("main", 9, 37, 59),
+            ],
+        );
+
+        let ranges: Vec<(EditKind, usize, usize, usize)> = file
+            .edits
+            .iter()
+            .map(|e| {
+                (
+                    e.kind,
+                    e.range.line,
+                    e.range.start_column,
+                    e.range.end_column,
+                )
+            })
+            .collect();
+        assert_eq!(
+            ranges,
+            vec![
+                (EditKind::Definition, 2, 7, 18),
+                (EditKind::Reference, 7, 17, 28),
+                (EditKind::Reference, 9, 25, 36),
+                (EditKind::Reference, 9, 37, 48),
+            ]
+        );
+        assert_eq!(file.edits[0].symbol, "function");
+        assert_eq!(
+            file.edits[1].text,
+            r#"let config = load_config("app.toml");"#
+        );
+    }
+
+    #[test]
+    fn flags_string_literals_and_comments_it_cannot_verify() {
+        let file = preview_file(
+            "src/main.rs",
+            RUST,
+            "load_config",
+            &[definition()],
+            &[reference("main", 7, 17, 41)],
+        );
+
+        let reasons: Vec<(UnverifiedReason, usize)> = file
+            .unverified
+            .iter()
+            .map(|m| (m.reason, m.range.map_or(0, |r| r.line)))
+            .collect();
+        assert_eq!(
+            reasons,
+            vec![
+                (UnverifiedReason::Comment, 1),
+                (UnverifiedReason::StringLiteral, 8),
+                (UnverifiedReason::Unrecorded, 9),
+                (UnverifiedReason::Unrecorded, 9),
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_recorded_locations_without_the_name() {
+        let mut moved = reference("main", 3, 4, 15);
+        let missing = RecordedReference {
+            line: None,
+            column: None,
+            range: None,
+            ..moved.clone()
+        };
+        moved.symbol = "load".to_string();
+
+        let file = preview_file("src/main.rs", RUST, "load_config", &[], &[moved, missing]);
+        assert!(file.edits.is_empty());
+        let reasons: Vec<UnverifiedReason> = file.unverified.iter().map(|m| m.reason).collect();
+        assert!(reasons.contains(&UnverifiedReason::NotAtRecordedLocation));
+        assert!(reasons.contains(&UnverifiedReason::NoLocation));
+    }
+
+    #[test]
+    fn untouched_files_only_report_dynamic_string_references() {
+        let python = "# load_config is looked up by name\nhandler = getattr(module, 'load_config')\nload_config = None\n";
+        let file = preview_file("app/hooks.py", python, "load_config", &[], &[]);
+        assert!(file.edits.is_empty());
+        assert_eq!(file.unverified.len(), 1);
+        assert_eq!(file.unverified[0].reason, UnverifiedReason::StringLiteral);
+        assert_eq!(
+            file.unverified[0].range,
+            Some(EditRange {
+                line: 2,
+                start_column: 27,
+                end_column: 38,
+            })
+        );
+    }
+
+    #[test]
+    fn rust_lifetimes_are_not_strings() {
+        let rust = "fn parse<'a>(input: &'a str) -> Parsed<'a> { parse_inner(input) }\n";
+        let regions = classify_regions(rust, Path::new("src/lib.rs"));
+        let column = rust.find("parse_inner").unwrap();
+        assert_eq!(regions[column], Region::Code);
+    }
+
+    #[test]
+    fn validates_new_names() {
+        assert!(validate_new_name("config::load_config", "read_config").is_ok());
+        assert!(validate_new_name("load_config", "größe").is_ok());
+        assert!(validate_new_name("load_config", "config::read").is_err());
+        assert!(validate_new_name("load_config", "1st").is_err());
+        assert!(validate_new_name("Storage::load_config", "load_config").is_err());
+        assert_eq!(bare_name("app.Loader.load"), "load");
+    }
+
+    #[test]
+    fn markdown_groups_edits_by_file() {
+        let file = preview_file(
+            "src/main.rs",
+            RUST,
+            "load_config",
+            &[definition()],
+            &[reference("main", 7, 17, 41)],
+        );
+        let preview = RenamePreview::new(
+            "load_config",
+            "read_config",
+            vec![
+                file,
+                unavailable_file("src/gone.rs", &[], &[reference("old", 3, 0, 5)]),
+            ],
+            vec!["src/read.rs:10".to_string()],
+        );
+        assert_eq!(preview.edit_count, 2);
+        assert_eq!(preview.unverified_count, 5);
+
+        let markdown = preview.to_markdown();
+        assert!(markdown.starts_with(
+            "Renaming `load_config` to `read_config`: 2 edits in 1 file, 5 mentions to check by hand"
+        ));
+        assert!(markdown.contains("already defined at src/read.rs:10"));
+        assert!(markdown.contains("## src/main.rs\n- 2:7-18 definition (function)"));
+        assert!(markdown.contains("- src/main.rs:8:32-43 [string literal]"));
+        assert!(markdown.contains("- src/gone.rs:3 [source not in the index] in `old`"));
+    }
+}
@@ -0,0 +1,1051 @@
+// Replay module - Record assistant query sessions and replay them for A/B comparisons
+//
+// A session file captures the queries an assistant issued (tool, parameters), how long each
+// took, an estimate of the tokens the results would cost in a context window, and the keys of
+// the results returned. Replaying a session runs the same queries against another database or
+// build and produces a comparison report covering latency, tokens, and result overlap. The
+// same queries can also be run against two database snapshots to diff their results.
+// Sessions are recorded from the CLI (`--record`) or from MCP tool calls (`SessionRecorder`).
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::collections::HashSet;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::llm_search::estimate_tokens;
+use crate::services::{
+    AnalysisService, AnalysisServiceDatabase, CallersOptions, CallersResult, DatabaseAccess,
+    ImpactOptions, ImpactResult, RelationshipSortBy, ResultGrouping, SearchGrouping, SearchOptions,
+    SearchResult, SearchService, SymbolResult, SymbolSearchOptions,
+};
+
+/// Version of the session file layout
+pub const SESSION_FORMAT_VERSION: u32 = 1;
+
+/// Upper bound on result keys stored per step to keep session files small
+const MAX_RECORDED_RESULTS: usize = 100;
+
+/// Environment variable naming the session file MCP tool calls are recorded to
+pub const RECORD_SESSION_ENV: &str = "KOTADB_RECORD_SESSION";
+
+/// A recorded sequence of queries
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplaySession {
+    pub format_version: u32,
+    /// KotaDB version that produced the session
+    pub kotadb_version: String,
+    pub recorded_at: DateTime<Utc>,
+    /// Database directory the queries ran against
+    pub database: PathBuf,
+    pub steps: Vec<ReplayStep>,
+}
+
+impl ReplaySession {
+    pub fn new(database: &Path) -> Self {
+        Self {
+            format_version: SESSION_FORMAT_VERSION,
+            kotadb_version: env!("CARGO_PKG_VERSION").to_string(),
+            recorded_at: Utc::now(),
+            database: database.to_path_buf(),
+            steps: Vec::new(),
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let raw = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read session file {}", path.display()))?;
+        let session: ReplaySession = serde_json::from_str(&raw)
+            .with_context(|| format!("invalid session file {}", path.display()))?;
+        if session.format_version > SESSION_FORMAT_VERSION {
+            bail!(
+                "session file {} uses format version {}, newer than supported version {}",
+                path.display(),
+                session.format_version,
+                SESSION_FORMAT_VERSION
+            );
+        }
+        Ok(session)
+    }
+
+    /// Write the session atomically so an interrupted run never leaves a truncated file
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_vec_pretty(self)?)
+            .with_context(|| format!("failed to write session file {}", tmp.display()))?;
+        std::fs::rename(&tmp, path)
+            .with_context(|| format!("failed to write session file {}", path.display()))?;
+        Ok(())
+    }
+
+    /// Append `step` to the session at `path`, creating the file on first use
+    pub fn append_to_file(path: &Path, database: &Path, step: ReplayStep) -> Result<()> {
+        let mut session = if path.exists() {
+            Self::load(path)?
+        } else {
+            Self::new(database)
+        };
+        session.steps.push(step);
+        session.save(path)
+    }
+}
+
+/// A query issued by an assistant, tagged by the tool that served it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "tool", rename_all = "snake_case")]
+pub enum ReplayQuery {
+    SearchCode {
+        query: String,
+        #[serde(default = "default_search_limit")]
+        limit: usize,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        tags: Option<Vec<String>>,
+        #[serde(default = "default_search_context")]
+        context: String,
+        #[serde(default)]
+        group_by: SearchGrouping,
+    },
+    SearchSymbols {
+        pattern: String,
+        #[serde(default = "default_symbol_limit")]
+        limit: usize,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        symbol_type: Option<String>,
+    },
+    FindCallers {
+        target: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        limit: Option<usize>,
+    },
+    AnalyzeImpact {
+        target: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        limit: Option<usize>,
+    },
+}
+
+// Defaults mirror the CLI so hand-written query files can omit them
+fn default_search_limit() -> usize {
+    10
+}
+
+fn default_search_context() -> String {
+    "minimal".to_string()
+}
+
+fn default_symbol_limit() -> usize {
+    25
+}
+
+impl ReplayQuery {
+    pub fn tool(&self) -> &'static str {
+        match self {
+            ReplayQuery::SearchCode { .. } => "search_code",
+            ReplayQuery::SearchSymbols { .. } => "search_symbols",
+            ReplayQuery::FindCallers { .. } => "find_callers",
+            ReplayQuery::AnalyzeImpact { .. } => "analyze_impact",
+        }
+    }
+
+    /// Query an MCP tool call corresponds to, if the harness can replay that tool
+    pub fn from_tool_call(method: &str, params: &JsonValue) -> Option<Self> {
+        let text = |key: &str| params.get(key)?.as_str().map(str::to_string);
+        let limit = params
+            .get("limit")
+            .and_then(|v| v.as_u64())
+            .map(|v| v as usize);
+        // Limits as clamped by the MCP tools themselves
+        match method {
+            "kotadb://text_search" => Some(ReplayQuery::SearchCode {
+                query: text("query")?,
+                limit: limit.unwrap_or(default_search_limit()).min(100),
+                tags: None,
+                context: default_search_context(),
+                group_by: SearchGrouping::default(),
+            }),
+            "kotadb://symbol_search" => Some(ReplayQuery::SearchSymbols {
+                pattern: text("pattern")?,
+                limit: limit.unwrap_or(default_symbol_limit()).min(100),
+                symbol_type: text("symbol_type"),
+            }),
+            "kotadb://find_callers" => Some(ReplayQuery::FindCallers {
+                target: text("target")?,
+                limit: None,
+            }),
+            "kotadb://impact_analysis" => Some(ReplayQuery::AnalyzeImpact {
+                target: text("target")?,
+                limit: None,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Primary argument of the query, for report labels
+    pub fn subject(&self) -> &str {
+        match self {
+            ReplayQuery::SearchCode { query, .. } => query,
+            ReplayQuery::SearchSymbols { pattern, .. } => pattern,
+            ReplayQuery::FindCallers { target, .. } | ReplayQuery::AnalyzeImpact { target, .. } => {
+                target
+            }
+        }
+    }
+}
+
+/// Service output a step is recorded from
+pub enum ReplayOutcome<'a> {
+    Search(&'a SearchResult),
+    Symbols(&'a SymbolResult),
+    Callers(&'a CallersResult),
+    Impact(&'a ImpactResult),
+}
+
+/// One executed query and what it returned
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplayStep {
+    #[serde(flatten)]
+    pub query: ReplayQuery,
+    pub duration_ms: f64,
+    /// Approximate tokens the results occupy in an assistant's context
+    pub estimated_tokens: usize,
+    pub result_count: usize,
+    /// Stable keys identifying returned results, used to compute overlap
+    pub results: Vec<String>,
+    /// Relevance score of each result, when the tool ranks by score
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub scores: Vec<f32>,
+}
+
+impl ReplayStep {
+    pub fn record(query: ReplayQuery, elapsed: Duration, outcome: ReplayOutcome<'_>) -> Self {
+        let mut scores = Vec::new();
+        let (results, estimated_tokens) = match outcome {
+            ReplayOutcome::Search(result) => {
+                if let Some(response) = &result.llm_response {
+                    scores = response.results.iter().map(|r| r.relevance_score).collect();
+                }
+                let keys: Vec<String> = match &result.llm_response {
+                    Some(response) => response.results.iter().map(|r| r.path.clone()).collect(),
+                    None => result
+                        .documents
+                        .iter()
+                        .map(|doc| doc.path.as_str().to_string())
+                        .collect(),
+                };
+                let tokens = match &result.llm_response {
+                    Some(response) => response.optimization.token_usage.estimated_tokens,
+                    None => estimate_tokens(&keys.join("\n")),
+                };
+                (keys, tokens)
+            }
+            ReplayOutcome::Symbols(result) => {
+                let keys: Vec<String> = result
+                    .matches
+                    .iter()
+                    .map(|m| format!("{}@{}:{}", m.name, m.file_path, m.start_line))
+                    .collect();
+                let tokens = estimate_tokens(&keys.join("\n"));
+                (keys, tokens)
+            }
+            ReplayOutcome::Callers(result) => (
+                result
+                    .callers
+                    .iter()
+                    .map(|s| site_key(&s.qualified_name, &s.file_path, s.line_number))
+                    .collect(),
+                estimate_tokens(&result.markdown),
+            ),
+            ReplayOutcome::Impact(result) => (
+                result
+                    .impacts
+                    .iter()
+                    .map(|s| site_key(&s.qualified_name, &s.file_path, s.line_number))
+                    .collect(),
+                estimate_tokens(&result.markdown),
+            ),
+        };
+
+        let result_count = results.len();
+        Self {
+            query,
+            duration_ms: elapsed.as_secs_f64() * 1000.0,
+            estimated_tokens,
+            result_count,
+            results: results.into_iter().take(MAX_RECORDED_RESULTS).collect(),
+            scores: scores.into_iter().take(MAX_RECORDED_RESULTS).collect(),
+        }
+    }
+
+    /// Record a step from the JSON an MCP tool returned, keyed like [`ReplayStep::record`]
+    pub fn from_tool_response(query: ReplayQuery, elapsed: Duration, response: &JsonValue) -> Self {
+        let items = |key: &str| {
+            response
+                .get(key)
+                .and_then(|v| v.as_array())
+                .map(Vec::as_slice)
+                .unwrap_or_default()
+        };
+        let field = |item: &JsonValue, key: &str| {
+            item.get(key)
+                .and_then(|v| v.as_str())
+                .unwrap_or_default()
+                .to_string()
+        };
+        let line = |item: &JsonValue, key: &str| {
+            item.get(key)
+                .and_then(|v| v.as_u64())
+                .and_then(|v| u32::try_from(v).ok())
+        };
+        let markdown = response
+            .get("markdown")
+            .and_then(|v| v.as_str())
+            .unwrap_or_default();
+
+        let (results, estimated_tokens): (Vec<String>, usize) = match &query {
+            ReplayQuery::SearchCode { .. } => {
+                let keys: Vec<String> = items("results").iter().map(|r| field(r, "path")).collect();
+                let tokens = estimate_tokens(&keys.join("\n"));
+                (keys, tokens)
+            }
+            ReplayQuery::SearchSymbols { .. } => {
+                let keys: Vec<String> = items("matches")
+                    .iter()
+                    .map(|m| {
+                        format!(
+                            "{}@{}:{}",
+                            field(m, "name"),
+                            field(m, "file_path"),
+                            line(m, "start_line").unwrap_or(0)
+                        )
+                    })
+                    .collect();
+                let tokens = estimate_tokens(&keys.join("\n"));
+                (keys, tokens)
+            }
+            ReplayQuery::FindCallers { .. } | ReplayQuery::AnalyzeImpact { .. } => {
+                let sites = if matches!(query, ReplayQuery::FindCallers { .. }) {
+                    "callers"
+                } else {
+                    "impacts"
+                };
+                let keys = items(sites)
+                    .iter()
+                    .map(|site| {
+                        site_key(
+                            &field(site, "qualified_name"),
+                            &field(site, "file_path"),
+                            line(site, "line_number"),
+                        )
+                    })
+                    .collect();
+                (keys, estimate_tokens(markdown))
+            }
+        };
+
+        let result_count = results.len();
+        Self {
+            query,
+            duration_ms: elapsed.as_secs_f64() * 1000.0,
+            estimated_tokens,
+            result_count,
+            results: results.into_iter().take(MAX_RECORDED_RESULTS).collect(),
+            scores: Vec::new(),
+        }
+    }
+}
+
+/// Appends replayable MCP tool calls to a session file as they are served
+pub struct SessionRecorder {
+    path: PathBuf,
+    database: PathBuf,
+    /// Serializes read-modify-write cycles of the session file
+    writes: tokio::sync::Mutex<()>,
+}
+
+impl SessionRecorder {
+    pub fn new(path: impl Into<PathBuf>, database: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            database: database.into(),
+            writes: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    /// Recorder for the session named by [`RECORD_SESSION_ENV`], if set
+    pub fn from_env(database: &Path) -> Option<Self> {
+        std::env::var(RECORD_SESSION_ENV)
+            .ok()
+            .filter(|path| !path.trim().is_empty())
+            .map(|path| Self::new(path, database))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append a served tool call; calls the harness cannot replay are skipped
+    pub async fn record_tool_call(
+        &self,
+        method: &str,
+        params: &JsonValue,
+        elapsed: Duration,
+        response: &JsonValue,
+    ) -> Result<()> {
+        let Some(query) = ReplayQuery::from_tool_call(method, params) else {
+            return Ok(());
+        };
+        let step = ReplayStep::from_tool_response(query, elapsed, response);
+        let _guard = self.writes.lock().await;
+        let (path, database) = (self.path.clone(), self.database.clone());
+        tokio::task::spawn_blocking(move || ReplaySession::append_to_file(&path, &database, step))
+            .await?
+    }
+}
+
+fn site_key(qualified_name: &str, file_path: &str, line: Option<u32>) -> String {
+    format!("{}@{}:{}", qualified_name, file_path, line.unwrap_or(0))
+}
+
+/// Run `query` against `db` and record the outcome
+pub async fn execute<D>(db: &D, db_path: &Path, query: &ReplayQuery) -> Result<ReplayStep>
+where
+    D: DatabaseAccess + AnalysisServiceDatabase,
+{
+    let started = Instant::now();
+    let step = match query {
+        ReplayQuery::SearchCode {
+            query: text,
+            limit,
+            tags,
+            context,
+            group_by,
+        } => {
+            let service = SearchService::new(db, db_path.to_path_buf());
+            let result = service
+                .search_content(SearchOptions {
+                    query: text.clone(),
+                    limit: *limit,
+                    tags: tags.clone(),
+                    context: context.clone(),
+                    quiet: true,
+                    group_by: *group_by,
+                })
+                .await?;
+            ReplayStep::record(
+                query.clone(),
+                started.elapsed(),
+                ReplayOutcome::Search(&result),
+            )
+        }
+        ReplayQuery::SearchSymbols {
+            pattern,
+            limit,
+            symbol_type,
+        } => {
+            let service = SearchService::new(db, db_path.to_path_buf());
+            let result = service
+                .search_symbols(SymbolSearchOptions {
+                    pattern: pattern.clone(),
+                    limit: *limit,
+                    symbol_type: symbol_type.clone(),
+                    quiet: true,
+                })
+                .await?;
+            ReplayStep::record(
+                query.clone(),
+                started.elapsed(),
+                ReplayOutcome::Symbols(&result),
+            )
+        }
+        ReplayQuery::FindCallers { target, limit } => {
+            let mut service = AnalysisService::new(db, db_path.to_path_buf());
+            let result = service
+                .find_callers(CallersOptions {
+                    target: target.clone(),
+                    limit: *limit,
+                    offset: 0,
+                    sort_by: RelationshipSortBy::default(),
+                    group_by: ResultGrouping::default(),
+                    quiet: true,
+                    explain: false,
+                    context_lines: None,
+                })
+                .await?;
+            ReplayStep::record(
+                query.clone(),
+                started.elapsed(),
+                ReplayOutcome::Callers(&result),
+            )
+        }
+        ReplayQuery::AnalyzeImpact { target, limit } => {
+            let mut service = AnalysisService::new(db, db_path.to_path_buf());
+            let result = service
+                .analyze_impact(ImpactOptions {
+                    target: target.clone(),
+                    limit: *limit,
+                    offset: 0,
+                    sort_by: RelationshipSortBy::default(),
+                    group_by: ResultGrouping::default(),
+                    quiet: true,
+                    explain: false,
+                    context_lines: None,
+                })
+                .await?;
+            ReplayStep::record(
+                query.clone(),
+                started.elapsed(),
+                ReplayOutcome::Impact(&result),
+            )
+        }
+    };
+    Ok(step)
+}
+
+/// Re-run every query in `session` against `db`, producing a new session
+pub async fn replay<D>(db: &D, db_path: &Path, session: &ReplaySession) -> Result<ReplaySession>
+where
+    D: DatabaseAccess + AnalysisServiceDatabase,
+{
+    let mut replayed = ReplaySession::new(db_path);
+    for (index, step) in session.steps.iter().enumerate() {
+        let result = execute(db, db_path, &step.query).await.with_context(|| {
+            format!(
+                "step {} ({} '{}') failed",
+                index + 1,
+                step.query.tool(),
+                step.query.subject()
+            )
+        })?;
+        replayed.steps.push(result);
+    }
+    Ok(replayed)
+}
+
+/// Side-by-side measurements for one query
+#[derive(Debug, Clone, Serialize)]
+pub struct StepComparison {
+    pub index: usize,
+    pub tool: String,
+    pub subject: String,
+    /// Parameters differ between the two sessions, so figures are not like-for-like
+    pub query_mismatch: bool,
+    pub baseline_ms: f64,
+    pub candidate_ms: f64,
+    pub baseline_tokens: usize,
+    pub candidate_tokens: usize,
+    pub baseline_results: usize,
+    pub candidate_results: usize,
+    /// Jaccard similarity of the returned result keys (1.0 when both are empty)
+    pub overlap: f64,
+}
+
+/// Aggregate comparison of two sessions
+#[derive(Debug, Clone, Serialize)]
+pub struct ComparisonReport {
+    pub baseline_version: String,
+    pub candidate_version: String,
+    pub steps: Vec<StepComparison>,
+    pub baseline_total_ms: f64,
+    pub candidate_total_ms: f64,
+    pub baseline_total_tokens: usize,
+    pub candidate_total_tokens: usize,
+    pub mean_overlap: f64,
+    /// Steps present in only one session
+    pub unmatched_steps: usize,
+}
+
+/// Compare two sessions step by step, pairing steps by position
+pub fn compare(baseline: &ReplaySession, candidate: &ReplaySession) -> ComparisonReport {
+    let steps: Vec<StepComparison> = baseline
+        .steps
+        .iter()
+        .zip(&candidate.steps)
+        .enumerate()
+        .map(|(index, (before, after))| StepComparison {
+            index: index + 1,
+            tool: before.query.tool().to_string(),
+            subject: before.query.subject().to_string(),
+            query_mismatch: before.query != after.query,
+            baseline_ms: before.duration_ms,
+            candidate_ms: after.duration_ms,
+            baseline_tokens: before.estimated_tokens,
+            candidate_tokens: after.estimated_tokens,
+            baseline_results: before.result_count,
+            candidate_results: after.result_count,
+            overlap: jaccard(&before.results, &after.results),
+        })
+        .collect();
+
+    let mean_overlap = if steps.is_empty() {
+        1.0
+    } else {
+        steps.iter().map(|s| s.overlap).sum::<f64>() / steps.len() as f64
+    };
+
+    ComparisonReport {
+        baseline_version: baseline.kotadb_version.clone(),
+        candidate_version: candidate.kotadb_version.clone(),
+        baseline_total_ms: steps.iter().map(|s| s.baseline_ms).sum(),
+        candidate_total_ms: steps.iter().map(|s| s.candidate_ms).sum(),
+        baseline_total_tokens: steps.iter().map(|s| s.baseline_tokens).sum(),
+        candidate_total_tokens: steps.iter().map(|s| s.candidate_tokens).sum(),
+        mean_overlap,
+        unmatched_steps: baseline.steps.len().abs_diff(candidate.steps.len()),
+        steps,
+    }
+}
+
+fn jaccard(a: &[String], b: &[String]) -> f64 {
+    let a: HashSet<&String> = a.iter().collect();
+    let b: HashSet<&String> = b.iter().collect();
+    let union = a.union(&b).count();
+    if union == 0 {
+        return 1.0;
+    }
+    a.intersection(&b).count() as f64 / union as f64
+}
+
+fn percent_change(before: f64, after: f64) -> String {
+    if before == 0.0 {
+        return "n/a".to_string();
+    }
+    format!("{:+.1}%", (after - before) / before * 100.0)
+}
+
+impl ComparisonReport {
+    pub fn to_markdown(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(
+            out,
+            "# Replay comparison ({} → {})\n",
+            self.baseline_version, self.candidate_version
+        );
+        let _ = writeln!(
+            out,
+            "| # | Tool | Query | Latency (ms) | Tokens | Results | Overlap |"
+        );
+        let _ = writeln!(
+            out,
+            "|---|------|-------|--------------|--------|---------|---------|"
+        );
+        for step in &self.steps {
+            let marker = if step.query_mismatch { " ⚠" } else { "" };
+            let _ = writeln!(
+                out,
+                "| {} | {} | `{}`{} | {:.2} → {:.2} ({}) | {} → {} | {} → {} | {:.0}% |",
+                step.index,
+                step.tool,
+                step.subject,
+                marker,
+                step.baseline_ms,
+                step.candidate_ms,
+                percent_change(step.baseline_ms, step.candidate_ms),
+                step.baseline_tokens,
+                step.candidate_tokens,
+                step.baseline_results,
+                step.candidate_results,
+                step.overlap * 100.0
+            );
+        }
+        let _ = writeln!(
+            out,
+            "\n**Total latency:** {:.2} ms → {:.2} ms ({})",
+            self.baseline_total_ms,
+            self.candidate_total_ms,
+            percent_change(self.baseline_total_ms, self.candidate_total_ms)
+        );
+        let _ = writeln!(
+            out,
+            "**Total tokens:** {} → {} ({})",
+            self.baseline_total_tokens,
+            self.candidate_total_tokens,
+            percent_change(
+                self.baseline_total_tokens as f64,
+                self.candidate_total_tokens as f64
+            )
+        );
+        let _ = writeln!(
+            out,
+            "**Mean result overlap:** {:.1}%",
+            self.mean_overlap * 100.0
+        );
+        if self.steps.iter().any(|s| s.query_mismatch) {
+            let _ = writeln!(
+                out,
+                "\n⚠ marks steps whose parameters differ between sessions."
+            );
+        }
+        if self.unmatched_steps > 0 {
+            let _ = writeln!(
+                out,
+                "\n{} step(s) exist in only one session and were not compared.",
+                self.unmatched_steps
+            );
+        }
+        out
+    }
+}
+
+/// Scores closer than this are treated as unchanged
+const SCORE_EPSILON: f32 = 1e-3;
+
+/// Movement of a result returned by both snapshots
+#[derive(Debug, Clone, Serialize)]
+pub struct RankShift {
+    pub key: String,
+    /// 1-based positions in each snapshot
+    pub before_rank: usize,
+    pub after_rank: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub before_score: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub after_score: Option<f32>,
+}
+
+/// Differences between the results of one query on two snapshots
+#[derive(Debug, Clone, Serialize)]
+pub struct QueryDiff {
+    pub tool: String,
+    pub subject: String,
+    pub before_count: usize,
+    pub after_count: usize,
+    /// Results only the `after` snapshot returned
+    pub added: Vec<String>,
+    /// Results only the `before` snapshot returned
+    pub removed: Vec<String>,
+    /// Results returned by both whose rank or score changed
+    pub shifted: Vec<RankShift>,
+    pub unchanged: usize,
+}
+
+impl QueryDiff {
+    pub fn is_unchanged(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.shifted.is_empty()
+            && self.before_count == self.after_count
+    }
+}
+
+/// Diff the results two snapshots returned for the same query
+pub fn diff_steps(before: &ReplayStep, after: &ReplayStep) -> QueryDiff {
+    let before_keys: HashSet<&String> = before.results.iter().collect();
+    let after_keys: HashSet<&String> = after.results.iter().collect();
+
+    let mut shifted = Vec::new();
+    let mut unchanged = 0;
+    for (before_idx, key) in before.results.iter().enumerate() {
+        let Some(after_idx) = after.results.iter().position(|k| k == key) else {
+            continue;
+        };
+        let before_score = before.scores.get(before_idx).copied();
+        let after_score = after.scores.get(after_idx).copied();
+        let score_moved = match (before_score, after_score) {
+            (Some(a), Some(b)) => (a - b).abs() > SCORE_EPSILON,
+            _ => false,
+        };
+        if before_idx != after_idx || score_moved {
+            shifted.push(RankShift {
+                key: key.clone(),
+                before_rank: before_idx + 1,
+                after_rank: after_idx + 1,
+                before_score,
+                after_score,
+            });
+        } else {
+            unchanged += 1;
+        }
+    }
+
+    QueryDiff {
+        tool: before.query.tool().to_string(),
+        subject: before.query.subject().to_string(),
+        before_count: before.result_count,
+        after_count: after.result_count,
+        added: after
+            .results
+            .iter()
+            .filter(|k| !before_keys.contains(k))
+            .cloned()
+            .collect(),
+        removed: before
+            .results
+            .iter()
+            .filter(|k| !after_keys.contains(k))
+            .cloned()
+            .collect(),
+        shifted,
+        unchanged,
+    }
+}
+
+/// Per-query result differences between two database snapshots
+#[derive(Debug, Clone, Serialize)]
+pub struct SnapshotDiffReport {
+    pub before: PathBuf,
+    pub after: PathBuf,
+    pub queries: Vec<QueryDiff>,
+}
+
+impl SnapshotDiffReport {
+    pub fn has_differences(&self) -> bool {
+        self.queries.iter().any(|q| !q.is_unchanged())
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(
+            out,
+            "# Snapshot comparison ({} → {})\n",
+            self.before.display(),
+            self.after.display()
+        );
+        for query in &self.queries {
+            let _ = writeln!(
+                out,
+                "## {} `{}` ({} → {} results)\n",
+                query.tool, query.subject, query.before_count, query.after_count
+            );
+            if query.is_unchanged() {
+                let _ = writeln!(out, "No differences.\n");
+                continue;
+            }
+            for key in &query.added {
+                let _ = writeln!(out, "- added: {}", key);
+            }
+            for key in &query.removed {
+                let _ = writeln!(out, "- removed: {}", key);
+            }
+            for shift in &query.shifted {
+                let scores = match (shift.before_score, shift.after_score) {
+                    (Some(a), Some(b)) => format!(", score {:.3} → {:.3}", a, b),
+                    _ => String::new(),
+                };
+                let _ = writeln!(
+                    out,
+                    "- moved: {} (rank {} → {}{})",
+                    shift.key, shift.before_rank, shift.after_rank, scores
+                );
+            }
+            let _ = writeln!(out, "\n{} result(s) unchanged.\n", query.unchanged);
+        }
+        let changed = self.queries.iter().filter(|q| !q.is_unchanged()).count();
+        let _ = writeln!(
+            out,
+            "**{} of {} queries differ.**",
+            changed,
+            self.queries.len()
+        );
+        out
+    }
+}
+
+/// Run each query against both snapshots and diff the results
+pub async fn diff_snapshots<D>(
+    before: (&D, &Path),
+    after: (&D, &Path),
+    queries: &[ReplayQuery],
+) -> Result<SnapshotDiffReport>
+where
+    D: DatabaseAccess + AnalysisServiceDatabase,
+{
+    let mut diffs = Vec::with_capacity(queries.len());
+    for query in queries {
+        let before_step = execute(before.0, before.1, query).await.with_context(|| {
+            format!(
+                "{} '{}' failed on {}",
+                query.tool(),
+                query.subject(),
+                before.1.display()
+            )
+        })?;
+        let after_step = execute(after.0, after.1, query).await.with_context(|| {
+            format!(
+                "{} '{}' failed on {}",
+                query.tool(),
+                query.subject(),
+                after.1.display()
+            )
+        })?;
+        diffs.push(diff_steps(&before_step, &after_step));
+    }
+    Ok(SnapshotDiffReport {
+        before: before.1.to_path_buf(),
+        after: after.1.to_path_buf(),
+        queries: diffs,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn step(query: &str, ms: f64, tokens: usize, results: &[&str]) -> ReplayStep {
+        ReplayStep {
+            query: ReplayQuery::SearchCode {
+                query: query.to_string(),
+                limit: 10,
+                tags: None,
+                context: "minimal".to_string(),
+                group_by: SearchGrouping::default(),
+            },
+            duration_ms: ms,
+            estimated_tokens: tokens,
+            result_count: results.len(),
+            results: results.iter().map(|r| r.to_string()).collect(),
+            scores: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn tool_calls_map_to_replayable_queries() {
+        let params = serde_json::json!({ "pattern": "FileStorage*", "limit": 500 });
+        assert_eq!(
+            ReplayQuery::from_tool_call("kotadb://symbol_search", &params),
+            Some(ReplayQuery::SearchSymbols {
+                pattern: "FileStorage*".to_string(),
+                limit: 100,
+                symbol_type: None,
+            })
+        );
+        let params = serde_json::json!({ "query": "storage" });
+        assert!(matches!(
+            ReplayQuery::from_tool_call("kotadb://text_search", &params),
+            Some(ReplayQuery::SearchCode { limit: 10, .. })
+        ));
+        assert_eq!(
+            ReplayQuery::from_tool_call("kotadb://call_chain", &params),
+            None
+        );
+
+        let response = serde_json::json!({
+            "callers": [
+                { "qualified_name": "app::run", "file_path": "src/app.rs", "line_number": 12 },
+                { "qualified_name": "main", "file_path": "src/main.rs" }
+            ],
+            "markdown": "two callers",
+        });
+        let query = ReplayQuery::FindCallers {
+            target: "Storage".to_string(),
+            limit: None,
+        };
+        let step = ReplayStep::from_tool_response(query, Duration::from_millis(5), &response);
+        assert_eq!(
+            step.results,
+            vec!["app::run@src/app.rs:12", "main@src/main.rs:0"]
+        );
+        assert_eq!(step.estimated_tokens, estimate_tokens("two callers"));
+    }
+
+    #[cfg(feature = "mcp-server")]
+    #[tokio::test]
+    async fn mcp_tool_calls_are_recorded_to_the_session() -> Result<()> {
+        use crate::mcp::tools::{text_search_tools::TextSearchTools, MCPToolRegistry};
+        use std::sync::Arc;
+
+        let dir = tempfile::TempDir::new()?;
+        let database = crate::database::Database::new(dir.path(), true).await?;
+        let session_path = dir.path().join("session.json");
+        let registry = MCPToolRegistry::new()
+            .with_text_tools(Arc::new(TextSearchTools::new(
+                database.trigram_index.clone(),
+                database.storage.clone(),
+            )))
+            .with_session_recorder(Arc::new(SessionRecorder::new(&session_path, dir.path())));
+
+        let params = serde_json::json!({ "query": "storage", "limit": 5 });
+        registry
+            .handle_tool_call("kotadb://text_search", params.clone())
+            .await?;
+        registry
+            .handle_tool_call("kotadb://text_search", params)
+            .await?;
+
+        let session = ReplaySession::load(&session_path)?;
+        assert_eq!(session.steps.len(), 2);
+        assert_eq!(
+            session.steps[0].query,
+            ReplayQuery::SearchCode {
+                query: "storage".to_string(),
+                limit: 5,
+                tags: None,
+                context: "minimal".to_string(),
+                group_by: SearchGrouping::default(),
+            }
+        );
+        Ok(())
+    }
+
+    fn session(steps: Vec<ReplayStep>) -> ReplaySession {
+        let mut session = ReplaySession::new(Path::new("db"));
+        session.steps = steps;
+        session
+    }
+
+    #[test]
+    fn compare_reports_latency_tokens_and_overlap() {
+        let baseline = session(vec![
+            step("storage", 10.0, 100, &["a.rs", "b.rs"]),
+            step("index", 4.0, 40, &[]),
+        ]);
+        let candidate = session(vec![
+            step("storage", 5.0, 80, &["b.rs", "c.rs"]),
+            step("index", 4.0, 40, &[]),
+            step("extra", 1.0, 1, &["x.rs"]),
+        ]);
+
+        let report = compare(&baseline, &candidate);
+        assert_eq!(report.steps.len(), 2);
+        assert!((report.steps[0].overlap - 1.0 / 3.0).abs() < 1e-9);
+        assert_eq!(report.steps[1].overlap, 1.0);
+        assert_eq!(report.baseline_total_tokens, 140);
+        assert_eq!(report.candidate_total_tokens, 120);
+        assert_eq!(report.unmatched_steps, 1);
+        assert!(!report.steps[0].query_mismatch);
+
+        let markdown = report.to_markdown();
+        assert!(markdown.contains("10.00 → 5.00 (-50.0%)"));
+        assert!(markdown.contains("1 step(s) exist in only one session"));
+    }
+
+    #[test]
+    fn diff_reports_added_removed_and_shifted_results() {
+        let mut before = step("storage", 1.0, 1, &["a.rs", "b.rs", "c.rs"]);
+        before.scores = vec![0.9, 0.8, 0.5];
+        let mut after = step("storage", 1.0, 1, &["b.rs", "a.rs", "d.rs"]);
+        after.scores = vec![0.95, 0.9, 0.4];
+
+        let diff = diff_steps(&before, &after);
+        assert_eq!(diff.added, vec!["d.rs"]);
+        assert_eq!(diff.removed, vec!["c.rs"]);
+        assert_eq!(diff.shifted.len(), 2);
+        assert_eq!(diff.shifted[0].key, "a.rs");
+        assert_eq!(
+            (diff.shifted[0].before_rank, diff.shifted[0].after_rank),
+            (1, 2)
+        );
+        assert_eq!(diff.unchanged, 0);
+        assert!(!diff.is_unchanged());
+
+        let same = diff_steps(&before, &before);
+        assert!(same.is_unchanged());
+        assert_eq!(same.unchanged, 3);
+    }
+
+    #[test]
+    fn session_round_trips_through_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("session.json");
+        ReplaySession::append_to_file(&path, Path::new("db"), step("a", 1.0, 2, &["x"])).unwrap();
+        ReplaySession::append_to_file(&path, Path::new("db"), step("b", 1.0, 2, &[])).unwrap();
+
+        let raw = std::fs::read_to_string(&path).unwrap();
+        assert!(raw.contains("\"tool\": \"search_code\""));
+        let loaded = ReplaySession::load(&path).unwrap();
+        assert_eq!(loaded.steps.len(), 2);
+        assert_eq!(loaded.steps[1].query.subject(), "b");
+    }
+}
@@ -0,0 +1,290 @@
+//! Commit-pinned web links to indexed files
+//!
+//! Ingestion records where each repository lives on its forge (the `origin` remote)
+//! and which commit was indexed in `repository_links.json`. Search, symbol and caller
+//! results turn their paths into permalinks such as
+//! `https://github.com/org/repo/blob/<sha>/src/lib.rs#L10-L20` from it.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// File name of the recorded links inside a database directory
+pub const REPOSITORY_LINKS_FILE: &str = "repository_links.json";
+
+/// Where an ingested repository's files can be viewed
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RepositoryLink {
+    pub name: String,
+    /// Document path prefix of the repository's files, e.g. `repos/kota-db/files`
+    pub document_root: String,
+    /// URL of the `origin` remote
+    pub remote_url: Option<String>,
+    /// Commit that was indexed
+    pub commit: Option<String>,
+}
+
+impl RepositoryLink {
+    /// Permalink to `relative_path` at the indexed commit, optionally to a line range
+    pub fn permalink(&self, relative_path: &str, lines: Option<(u32, u32)>) -> Option<String> {
+        let base = web_base_url(self.remote_url.as_deref()?)?;
+        let commit = self.commit.as_deref()?;
+        let path = encode_path(
+            relative_path
+                .trim_start_matches("./")
+                .trim_start_matches('/'),
+        );
+        let forge = Forge::of(&base);
+        let mut url = match forge {
+            Forge::GitHub => format!("{base}/blob/{commit}/{path}"),
+            Forge::GitLab => format!("{base}/-/blob/{commit}/{path}"),
+            Forge::Bitbucket => format!("{base}/src/{commit}/{path}"),
+        };
+        if let Some((start, end)) = lines.filter(|(start, _)| *start > 0) {
+            let end = end.max(start);
+            url.push_str(&match (forge, start == end) {
+                (Forge::GitHub, true) | (Forge::GitLab, true) => format!("#L{start}"),
+                (Forge::GitHub, false) => format!("#L{start}-L{end}"),
+                (Forge::GitLab, false) => format!("#L{start}-{end}"),
+                (Forge::Bitbucket, true) => format!("#lines-{start}"),
+                (Forge::Bitbucket, false) => format!("#lines-{start}:{end}"),
+            });
+        }
+        Some(url)
+    }
+}
+
+/// Links of every repository ingested into a database
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RepositoryLinks {
+    pub repositories: Vec<RepositoryLink>,
+}
+
+impl RepositoryLinks {
+    pub fn path_for(db_path: &Path) -> PathBuf {
+        db_path.join(REPOSITORY_LINKS_FILE)
+    }
+
+    /// Load the recorded links; empty when nothing was recorded
+    pub fn load(db_path: &Path) -> Result<Self> {
+        let path = Self::path_for(db_path);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read repository links: {}", path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse repository links: {}", path.display()))
+    }
+
+    /// Record `link`, replacing an earlier link of the same repository, and write atomically
+    pub fn record(db_path: &Path, link: RepositoryLink) -> Result<()> {
+        let mut links = Self::load(db_path).unwrap_or_default();
+        links
+            .repositories
+            .retain(|existing| existing.document_root != link.document_root);
+        links.repositories.push(link);
+        links
+            .repositories
+            .sort_by(|a, b| a.document_root.cmp(&b.document_root));
+
+        let path = Self::path_for(db_path);
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_vec_pretty(&links)?)
+            .with_context(|| format!("Failed to write repository links: {}", tmp.display()))?;
+        std::fs::rename(&tmp, &path)
+            .with_context(|| format!("Failed to replace repository links: {}", path.display()))?;
+        Ok(())
+    }
+
+    /// Permalink for a document path (`repos/<name>/files/src/lib.rs`) or, when a single
+    /// repository was ingested, for a path relative to its root (`src/lib.rs`)
+    pub fn web_url(&self, path: &str, lines: Option<(u32, u32)>) -> Option<String> {
+        let path = path.trim_start_matches("./").trim_start_matches('/');
+        for link in &self.repositories {
+            if let Some(relative) = path
+                .strip_prefix(link.document_root.as_str())
+                .and_then(|rest| rest.strip_prefix('/'))
+            {
+                return link.permalink(relative, lines);
+            }
+        }
+        match self.repositories.as_slice() {
+            [only] => only.permalink(path, lines),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Forge {
+    GitHub,
+    GitLab,
+    Bitbucket,
+}
+
+impl Forge {
+    /// Hosts that are neither GitLab nor Bitbucket get GitHub-style URLs, which Gitea
+    /// and Forgejo also redirect
+    fn of(base_url: &str) -> Self {
+        let host = base_url
+            .split_once("://")
+            .map_or(base_url, |(_, rest)| rest)
+            .split('/')
+            .next()
+            .unwrap_or_default();
+        if host.contains("gitlab") {
+            Self::GitLab
+        } else if host.contains("bitbucket") {
+            Self::Bitbucket
+        } else {
+            Self::GitHub
+        }
+    }
+}
+
+/// Browser URL of a repository for a git remote URL
+///
+/// Handles `git@host:org/repo.git`, `ssh://git@host[:port]/org/repo.git` and
+/// `https://[user@]host/org/repo(.git)`; credentials and ports of SSH remotes are dropped.
+/// Local remotes (`file://`, plain paths) have no web URL.
+pub fn web_base_url(remote_url: &str) -> Option<String> {
+    let remote_url = remote_url.trim();
+    let (scheme, host, path) = if let Some((scheme, rest)) = remote_url.split_once("://") {
+        let (authority, path) = rest.split_once('/')?;
+        let host = authority.rsplit('@').next()?;
+        match scheme {
+            "http" | "https" => (scheme, host, path),
+            "ssh" | "git" => ("https", host.split(':').next()?, path),
+            _ => return None,
+        }
+    } else {
+        // scp-like syntax: [user@]host:path
+        let (authority, path) = remote_url.split_once(':')?;
+        if authority.contains('/') || path.starts_with("//") {
+            return None;
+        }
+        ("https", authority.rsplit('@').next()?, path)
+    };
+
+    let path = path.trim_matches('/');
+    let path = path.strip_suffix(".git").unwrap_or(path);
+    if host.is_empty() || path.is_empty() {
+        return None;
+    }
+    Some(format!("{scheme}://{host}/{path}"))
+}
+
+/// Percent-encode a repository path, keeping `/` separators
+fn encode_path(path: &str) -> String {
+    let mut encoded = String::with_capacity(path.len());
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn link(remote_url: &str) -> RepositoryLink {
+        RepositoryLink {
+            name: "kota-db".to_string(),
+            document_root: "repos/kota-db/files".to_string(),
+            remote_url: Some(remote_url.to_string()),
+            commit: Some("0a1b2c3".to_string()),
+        }
+    }
+
+    #[test]
+    fn remote_urls_map_to_browser_urls() {
+        for remote in [
+            "git@github.com:kotadb/kota-db.git",
+            "ssh://git@github.com:22/kotadb/kota-db.git",
+            "https://token@github.com/kotadb/kota-db.git",
+            "https://github.com/kotadb/kota-db/",
+        ] {
+            assert_eq!(
+                web_base_url(remote).as_deref(),
+                Some("https://github.com/kotadb/kota-db"),
+                "{remote}"
+            );
+        }
+        assert_eq!(web_base_url("file:///srv/repos/kota-db"), None);
+        assert_eq!(web_base_url("/srv/repos/kota-db"), None);
+    }
+
+    #[test]
+    fn permalinks_follow_each_forge() {
+        let github = link("git@github.com:kotadb/kota-db.git");
+        assert_eq!(
+            github
+                .permalink("src/my file.rs", Some((10, 20)))
+                .as_deref(),
+            Some("https://github.com/kotadb/kota-db/blob/0a1b2c3/src/my%20file.rs#L10-L20")
+        );
+        assert_eq!(
+            github.permalink("src/lib.rs", Some((7, 7))).as_deref(),
+            Some("https://github.com/kotadb/kota-db/blob/0a1b2c3/src/lib.rs#L7")
+        );
+        assert_eq!(
+            link("https://gitlab.com/group/sub/kota-db.git")
+                .permalink("README.md", Some((1, 3)))
+                .as_deref(),
+            Some("https://gitlab.com/group/sub/kota-db/-/blob/0a1b2c3/README.md#L1-3")
+        );
+        assert_eq!(
+            link("git@bitbucket.org:team/kota-db.git")
+                .permalink("README.md", None)
+                .as_deref(),
+            Some("https://bitbucket.org/team/kota-db/src/0a1b2c3/README.md")
+        );
+        let unpinned = RepositoryLink {
+            commit: None,
+            ..github
+        };
+        assert_eq!(unpinned.permalink("src/lib.rs", None), None);
+    }
+
+    #[test]
+    fn links_resolve_document_and_relative_paths() {
+        let dir = tempfile::TempDir::new().unwrap();
+        RepositoryLinks::record(dir.path(), link("git@github.com:kotadb/kota-db.git")).unwrap();
+        let links = RepositoryLinks::load(dir.path()).unwrap();
+        let expected = "https://github.com/kotadb/kota-db/blob/0a1b2c3/src/lib.rs#L3";
+        assert_eq!(
+            links
+                .web_url("repos/kota-db/files/src/lib.rs", Some((3, 3)))
+                .as_deref(),
+            Some(expected)
+        );
+        assert_eq!(
+            links.web_url("src/lib.rs", Some((3, 3))).as_deref(),
+            Some(expected)
+        );
+
+        // Relative paths are ambiguous once a second repository is ingested
+        RepositoryLinks::record(
+            dir.path(),
+            RepositoryLink {
+                name: "widgets".to_string(),
+                document_root: "repos/widgets/files".to_string(),
+                ..link("git@github.com:kotadb/widgets.git")
+            },
+        )
+        .unwrap();
+        let links = RepositoryLinks::load(dir.path()).unwrap();
+        assert_eq!(links.repositories.len(), 2);
+        assert_eq!(links.web_url("src/lib.rs", None), None);
+        assert!(links
+            .web_url("repos/widgets/files/src/lib.rs", None)
+            .unwrap()
+            .starts_with("https://github.com/kotadb/widgets/blob/"));
+    }
+}
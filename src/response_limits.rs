@@ -0,0 +1,459 @@
+//! Size limits on JSON responses of the services servers
+//!
+//! Full-context searches and unbounded listings can produce JSON bodies of hundreds of
+//! megabytes. Responses larger than the applicable limit are truncated: items are dropped
+//! from the end of the largest result arrays until the body fits, and the response says
+//! so with `truncated: true` and a `truncation` object holding per-array counts and a
+//! cursor. Repeating the request with `response_cursor=<cursor>` returns the items that
+//! were left out.
+//!
+//! The soft limit applies by default. Clients may choose another limit up to the hard
+//! limit with the `X-KotaDB-Max-Response-Bytes` header.
+
+use std::collections::BTreeMap;
+use std::env;
+
+use axum::{
+    body::{Body, Bytes, HttpBody},
+    extract::{Request, State},
+    http::{header, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json, Router,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use serde_json::{json, Value as JsonValue};
+use tracing::warn;
+
+use crate::services_http_server::StandardApiError;
+
+/// Header selecting the response limit of a request, up to the hard limit
+pub const MAX_RESPONSE_BYTES_HEADER: &str = "x-kotadb-max-response-bytes";
+/// Query parameter continuing a truncated response
+pub const RESPONSE_CURSOR_PARAM: &str = "response_cursor";
+
+/// Response size limits shared by the services servers
+///
+/// Read from the environment:
+/// - `KOTADB_RESPONSE_SOFT_LIMIT_BYTES`: limit applied by default (default 8 MiB)
+/// - `KOTADB_RESPONSE_HARD_LIMIT_BYTES`: largest limit a client may ask for (default 64 MiB)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResponseLimits {
+    pub soft_limit: usize,
+    pub hard_limit: usize,
+}
+
+impl Default for ResponseLimits {
+    fn default() -> Self {
+        Self {
+            soft_limit: 8 * 1024 * 1024,
+            hard_limit: 64 * 1024 * 1024,
+        }
+    }
+}
+
+impl ResponseLimits {
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let read = |key: &str, default: usize| match env::var(key) {
+            Ok(raw) => match raw.trim().parse::<usize>() {
+                Ok(value) if value > 0 => value,
+                _ => {
+                    warn!("Ignoring invalid {} '{}'; using {}", key, raw, default);
+                    default
+                }
+            },
+            Err(_) => default,
+        };
+        let hard_limit = read("KOTADB_RESPONSE_HARD_LIMIT_BYTES", defaults.hard_limit);
+        let soft_limit = read("KOTADB_RESPONSE_SOFT_LIMIT_BYTES", defaults.soft_limit);
+        if soft_limit > hard_limit {
+            warn!(
+                "KOTADB_RESPONSE_SOFT_LIMIT_BYTES exceeds the hard limit; using {}",
+                hard_limit
+            );
+        }
+        Self {
+            soft_limit: soft_limit.min(hard_limit),
+            hard_limit,
+        }
+    }
+
+    /// Wrap `router` so its JSON responses respect these limits
+    pub fn apply(self, router: Router) -> Router {
+        router.layer(axum::middleware::from_fn_with_state(
+            self,
+            limit_response_size,
+        ))
+    }
+
+    /// Limit requested through [`MAX_RESPONSE_BYTES_HEADER`], capped at the hard limit
+    fn requested(&self, value: Option<&str>) -> Result<usize, String> {
+        match value {
+            None => Ok(self.soft_limit),
+            Some(raw) => match raw.trim().parse::<usize>() {
+                Ok(limit) if limit > 0 => Ok(limit.min(self.hard_limit)),
+                _ => Err(format!(
+                    "{} must be a positive number of bytes",
+                    MAX_RESPONSE_BYTES_HEADER
+                )),
+            },
+        }
+    }
+}
+
+/// How a JSON body was cut down to fit its limit
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Truncation {
+    /// Items dropped per array, keyed by JSON pointer
+    pub omitted: BTreeMap<String, usize>,
+    /// Items kept per truncated array
+    pub returned: BTreeMap<String, usize>,
+    /// Cursor returning the dropped items, when any were dropped
+    pub next_cursor: Option<String>,
+}
+
+/// Encode per-array offsets as a continuation cursor
+pub fn encode_cursor(offsets: &BTreeMap<String, usize>) -> String {
+    URL_SAFE_NO_PAD.encode(json!(offsets).to_string())
+}
+
+/// Per-array offsets of a continuation cursor
+pub fn decode_cursor(cursor: &str) -> Option<BTreeMap<String, usize>> {
+    let bytes = URL_SAFE_NO_PAD.decode(cursor.trim()).ok()?;
+    serde_json::from_slice(&bytes).ok()
+}
+
+/// Skip the items of `value` a cursor already returned, then truncate it to `limit` bytes
+///
+/// Arrays are truncated from the end, largest first; arrays nested in array items are
+/// left whole. Returns `None` when the body cannot be made to fit.
+pub fn truncate_json(
+    value: &mut JsonValue,
+    limit: usize,
+    skip: &BTreeMap<String, usize>,
+) -> Option<Truncation> {
+    for (pointer, offset) in skip {
+        if let Some(JsonValue::Array(items)) = value.pointer_mut(pointer) {
+            items.drain(..(*offset).min(items.len()));
+        }
+    }
+
+    let mut truncation = Truncation::default();
+    let mut size = serialized_len(value);
+    while size > limit {
+        let mut arrays = Vec::new();
+        collect_arrays(value, String::new(), &mut arrays);
+        let (pointer, _) = arrays.into_iter().max_by_key(|(_, bytes)| *bytes)?;
+        let Some(JsonValue::Array(items)) = value.pointer_mut(&pointer) else {
+            return None;
+        };
+
+        let mut excess = size - limit;
+        let mut dropped = 0;
+        while excess > 0 {
+            let Some(item) = items.pop() else { break };
+            // The item and the comma separating it from the previous one
+            excess = excess.saturating_sub(serialized_len(&item) + 1);
+            dropped += 1;
+        }
+        *truncation.omitted.entry(pointer.clone()).or_insert(0) += dropped;
+        truncation.returned.insert(pointer, items.len());
+        size = serialized_len(value) + metadata_len(&truncation, limit, skip);
+    }
+
+    if truncation.omitted.is_empty() {
+        return Some(truncation);
+    }
+    let mut offsets = skip.clone();
+    for (pointer, returned) in &truncation.returned {
+        *offsets.entry(pointer.clone()).or_insert(0) += returned;
+    }
+    truncation.next_cursor = Some(encode_cursor(&offsets));
+    Some(truncation)
+}
+
+/// Top-level fields announcing a truncation
+fn truncation_fields(truncation: &Truncation, limit: usize) -> [(String, JsonValue); 2] {
+    [
+        ("truncated".to_string(), JsonValue::Bool(true)),
+        (
+            "truncation".to_string(),
+            json!({
+                "limit_bytes": limit,
+                "omitted": truncation.omitted,
+                "returned": truncation.returned,
+                "next_cursor": truncation.next_cursor,
+            }),
+        ),
+    ]
+}
+
+/// Bytes the truncation fields will add, with room for the final cursor
+fn metadata_len(truncation: &Truncation, limit: usize, skip: &BTreeMap<String, usize>) -> usize {
+    let mut offsets = skip.clone();
+    for pointer in truncation.returned.keys() {
+        offsets.insert(pointer.clone(), usize::MAX);
+    }
+    let estimate = Truncation {
+        omitted: truncation.omitted.clone(),
+        returned: truncation.returned.clone(),
+        next_cursor: Some(encode_cursor(&offsets)),
+    };
+    truncation_fields(&estimate, limit)
+        .iter()
+        .map(|(key, value)| key.len() + serialized_len(value) + 4)
+        .sum()
+}
+
+/// Non-empty arrays outside other arrays, with their serialized sizes
+fn collect_arrays(value: &JsonValue, pointer: String, arrays: &mut Vec<(String, usize)>) {
+    match value {
+        JsonValue::Array(items) if !items.is_empty() => {
+            arrays.push((pointer, serialized_len(value)));
+        }
+        JsonValue::Object(fields) => {
+            for (key, field) in fields {
+                let escaped = key.replace('~', "~0").replace('/', "~1");
+                collect_arrays(field, format!("{}/{}", pointer, escaped), arrays);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn serialized_len(value: &JsonValue) -> usize {
+    serde_json::to_vec(value).map_or(0, |bytes| bytes.len())
+}
+
+fn limit_error(status: StatusCode, error_type: &str, message: String) -> Response {
+    (
+        status,
+        Json(StandardApiError {
+            error_type: error_type.to_string(),
+            message,
+            details: None,
+            suggestions: vec![
+                "Lower the request's limit or narrow the query".to_string(),
+                format!(
+                    "Raise {} up to the server's hard limit",
+                    MAX_RESPONSE_BYTES_HEADER
+                ),
+            ],
+            error_code: Some(status.as_u16() as u32),
+        }),
+    )
+        .into_response()
+}
+
+/// Middleware truncating successful JSON responses larger than the request's limit
+async fn limit_response_size(
+    State(limits): State<ResponseLimits>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let requested = request
+        .headers()
+        .get(MAX_RESPONSE_BYTES_HEADER)
+        .map(|value| value.to_str().unwrap_or_default());
+    let limit = match limits.requested(requested) {
+        Ok(limit) => limit,
+        Err(message) => {
+            return limit_error(StatusCode::BAD_REQUEST, "validation_error", message);
+        }
+    };
+    let cursor = request.uri().query().and_then(|query| {
+        query
+            .split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .find(|(name, _)| *name == RESPONSE_CURSOR_PARAM)
+            .map(|(_, value)| value.to_string())
+    });
+    let skip = match cursor.as_deref().map(decode_cursor) {
+        None => BTreeMap::new(),
+        Some(Some(skip)) => skip,
+        Some(None) => {
+            return limit_error(
+                StatusCode::BAD_REQUEST,
+                "validation_error",
+                format!("{} is not a valid cursor", RESPONSE_CURSOR_PARAM),
+            );
+        }
+    };
+
+    let response = next.run(request).await;
+    let is_json = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|ct| ct.starts_with("application/json"));
+    let fits = response
+        .body()
+        .size_hint()
+        .upper()
+        .is_some_and(|upper| upper as usize <= limit);
+    if !response.status().is_success() || !is_json || (fits && skip.is_empty()) {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let bytes = match axum::body::to_bytes(body, usize::MAX).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            warn!("Failed to buffer response for size limiting: {}", e);
+            return limit_error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "response_failed",
+                "Failed to read the response body".to_string(),
+            );
+        }
+    };
+    if bytes.len() <= limit && skip.is_empty() {
+        return Response::from_parts(parts, Body::from(bytes));
+    }
+    let Ok(mut value) = serde_json::from_slice::<JsonValue>(&bytes) else {
+        return Response::from_parts(parts, Body::from(bytes));
+    };
+
+    let truncation = match value {
+        JsonValue::Object(_) => truncate_json(&mut value, limit, &skip),
+        _ if bytes.len() <= limit => Some(Truncation::default()),
+        _ => None,
+    };
+    let Some(truncation) = truncation else {
+        return limit_error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "response_too_large",
+            format!(
+                "The response exceeds {} bytes and cannot be truncated to fit",
+                limit
+            ),
+        );
+    };
+    if !truncation.omitted.is_empty() {
+        if let JsonValue::Object(fields) = &mut value {
+            fields.extend(truncation_fields(&truncation, limit));
+        }
+    }
+
+    let body = Bytes::from(serde_json::to_vec(&value).unwrap_or_default());
+    parts.headers.remove(header::CONTENT_LENGTH);
+    Response::from_parts(parts, Body::from(body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn results(count: usize) -> JsonValue {
+        let items: Vec<JsonValue> = (0..count)
+            .map(|i| json!({ "path": format!("src/file_{i:04}.rs"), "score": i }))
+            .collect();
+        json!({ "query": "storage", "results": items, "sources": ["trigram"] })
+    }
+
+    #[test]
+    fn oversized_results_are_truncated_with_a_cursor() {
+        let mut page = results(500);
+        let truncation = truncate_json(&mut page, 4096, &BTreeMap::new()).unwrap();
+        let returned = truncation.returned["/results"];
+
+        assert!(returned > 0 && returned < 500);
+        assert_eq!(truncation.omitted["/results"], 500 - returned);
+        assert_eq!(page["results"].as_array().unwrap().len(), returned);
+        assert_eq!(page["sources"], json!(["trigram"]));
+        if let JsonValue::Object(fields) = &mut page {
+            fields.extend(truncation_fields(&truncation, 4096));
+        }
+        assert!(serialized_len(&page) <= 4096);
+
+        let skip = decode_cursor(truncation.next_cursor.as_deref().unwrap()).unwrap();
+        assert_eq!(skip["/results"], returned);
+        let mut next = results(500);
+        truncate_json(&mut next, 4096, &skip).unwrap();
+        assert_eq!(
+            next["results"][0]["path"],
+            format!("src/file_{returned:04}.rs")
+        );
+    }
+
+    #[test]
+    fn responses_within_the_limit_are_untouched() {
+        let mut page = results(3);
+        let original = page.clone();
+        let truncation = truncate_json(&mut page, 4096, &BTreeMap::new()).unwrap();
+        assert_eq!(truncation, Truncation::default());
+        assert_eq!(page, original);
+
+        let mut scalar = json!({ "output": "x".repeat(100) });
+        assert!(truncate_json(&mut scalar, 50, &BTreeMap::new()).is_none());
+    }
+
+    #[tokio::test]
+    async fn oversized_json_responses_carry_truncation_metadata() -> anyhow::Result<()> {
+        use axum::{http::Request as HttpRequest, routing::get};
+        use tower::ServiceExt;
+
+        let limits = ResponseLimits {
+            soft_limit: 2048,
+            hard_limit: 8192,
+        };
+        let router =
+            limits.apply(Router::new().route("/search", get(|| async { Json(results(400)) })));
+        let fetch = |uri: String, max: Option<&'static str>| {
+            let router = router.clone();
+            async move {
+                let mut request = HttpRequest::builder().uri(uri);
+                if let Some(max) = max {
+                    request = request.header(MAX_RESPONSE_BYTES_HEADER, max);
+                }
+                let response = router.oneshot(request.body(Body::empty())?).await?;
+                let status = response.status();
+                let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await?;
+                anyhow::Ok((
+                    status,
+                    bytes.len(),
+                    serde_json::from_slice::<JsonValue>(&bytes)?,
+                ))
+            }
+        };
+
+        let (status, size, page) = fetch("/search".to_string(), None).await?;
+        assert_eq!(status, StatusCode::OK);
+        assert!(size <= 2048);
+        assert_eq!(page["truncated"], json!(true));
+        let returned = page["truncation"]["returned"]["/results"].as_u64().unwrap() as usize;
+        assert_eq!(
+            page["truncation"]["omitted"]["/results"],
+            json!(400 - returned)
+        );
+
+        let cursor = page["truncation"]["next_cursor"].as_str().unwrap();
+        let (_, _, next) = fetch(format!("/search?response_cursor={cursor}"), None).await?;
+        assert_eq!(
+            next["results"][0]["path"],
+            format!("src/file_{returned:04}.rs")
+        );
+
+        let (_, size, raised) = fetch("/search".to_string(), Some("1000000")).await?;
+        assert!(size > 2048 && size <= 8192);
+        assert_eq!(raised["truncation"]["limit_bytes"], json!(8192));
+
+        let (status, _, _) = fetch("/search".to_string(), Some("lots")).await?;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        Ok(())
+    }
+
+    #[test]
+    fn clients_may_raise_the_limit_up_to_the_hard_limit() {
+        let limits = ResponseLimits {
+            soft_limit: 1_000,
+            hard_limit: 10_000,
+        };
+        assert_eq!(limits.requested(None), Ok(1_000));
+        assert_eq!(limits.requested(Some("5000")), Ok(5_000));
+        assert_eq!(limits.requested(Some("50000")), Ok(10_000));
+        assert!(limits.requested(Some("lots")).is_err());
+        assert!(limits.requested(Some("0")).is_err());
+    }
+}
@@ -0,0 +1,651 @@
+//! Reviewer suggestions for a diff
+//!
+//! `kotadb suggest-reviewers --diff changes.patch` ranks the people most familiar with
+//! the code a unified diff touches, combining:
+//!
+//! - `CODEOWNERS` rules matched against the changed files; the last matching rule wins,
+//!   as on GitHub and GitLab.
+//! - Commit history ingested by `index-codebase`: commits that changed the changed files
+//!   and, with less weight, the files of symbols impacted by the changed functions.
+//!   Commits lose half their weight every 180 days before the newest ingested commit.
+//! - `git blame` of the changed lines and the functions containing them, when a
+//!   repository checkout is given.
+//!
+//! Each signal is turned into the reviewer's share of it before weighting, so one prolific
+//! committer doesn't drown out the owners of the code. The result is JSON for CI bots.
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+use crate::path_utils::paths_equivalent;
+
+/// Locations of a CODEOWNERS file, in the order GitHub looks for one
+pub const CODEOWNERS_PATHS: &[&str] = &[".github/CODEOWNERS", "CODEOWNERS", "docs/CODEOWNERS"];
+
+/// Commits changing more files than this are imports or mass edits, not familiarity
+const MAX_COMMIT_FILES: usize = 100;
+const HISTORY_HALF_LIFE_DAYS: f64 = 180.0;
+const HISTORY_WEIGHT: f64 = 1.0;
+const IMPACT_WEIGHT: f64 = 0.4;
+const BLAME_WEIGHT: f64 = 1.0;
+const OWNER_WEIGHT: f64 = 0.5;
+
+static HUNK_HEADER: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^@@ -(\d+)(?:,(\d+))? \+\d+(?:,(\d+))? @@").expect("valid hunk header pattern")
+});
+static COMMIT_AUTHOR: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?m)^\*\*Author\*\*: (.+?)\s*$").expect("valid author pattern"));
+static COMMIT_DATE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?m)^\*\*Date\*\*: (\d{4}-\d{2}-\d{2} \d{2}:\d{2}:\d{2}) UTC")
+        .expect("valid date pattern")
+});
+
+/// File changed by a diff
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DiffFile {
+    /// Path after the change, or before it for deleted files
+    pub path: String,
+    /// Path before a rename
+    pub old_path: Option<String>,
+    /// 1-based inclusive line ranges of the file before the change that were removed, or
+    /// that new lines were inserted after
+    pub old_ranges: Vec<(usize, usize)>,
+}
+
+impl DiffFile {
+    /// Path of the file before the change, where history and blame are found
+    pub fn base_path(&self) -> &str {
+        self.old_path.as_deref().unwrap_or(&self.path)
+    }
+}
+
+/// Files and changed lines of a unified diff, as produced by `git diff` or `diff -u`
+pub fn parse_unified_diff(diff: &str) -> Vec<DiffFile> {
+    let mut files: Vec<DiffFile> = Vec::new();
+    let mut changed_lines: Vec<usize> = Vec::new();
+    // Old and new lines left in the current hunk, and the next old line number
+    let mut old_left = 0usize;
+    let mut new_left = 0usize;
+    let mut old_line = 0usize;
+    let mut seen_hunk = false;
+
+    for line in diff.lines() {
+        if old_left > 0 || new_left > 0 {
+            match line.as_bytes().first() {
+                Some(b'-') => {
+                    changed_lines.push(old_line);
+                    old_line += 1;
+                    old_left = old_left.saturating_sub(1);
+                }
+                Some(b'+') => {
+                    // Insertions count against the line before them; new files have none
+                    if old_line > 0 {
+                        changed_lines.push((old_line - 1).max(1));
+                    }
+                    new_left = new_left.saturating_sub(1);
+                }
+                Some(b'\\') => {}
+                _ => {
+                    old_line += 1;
+                    old_left = old_left.saturating_sub(1);
+                    new_left = new_left.saturating_sub(1);
+                }
+            }
+            continue;
+        }
+
+        if let Some(paths) = line.strip_prefix("diff --git ") {
+            finish_file(&mut files, &mut changed_lines);
+            let (old, new) = paths.rsplit_once(" b/").unwrap_or((paths, paths));
+            let old = old.strip_prefix("a/").unwrap_or(old);
+            files.push(DiffFile {
+                path: new.to_string(),
+                old_path: (old != new).then(|| old.to_string()),
+                old_ranges: Vec::new(),
+            });
+            seen_hunk = false;
+        } else if let Some(old) = line.strip_prefix("rename from ") {
+            if let Some(file) = files.last_mut() {
+                file.old_path = Some(old.to_string());
+            }
+        } else if let Some(new) = line.strip_prefix("rename to ") {
+            if let Some(file) = files.last_mut() {
+                file.path = new.to_string();
+            }
+        } else if let Some(old) = line.strip_prefix("--- ") {
+            // Plain `diff -u` output has no `diff --git` line between files
+            if files.is_empty() || seen_hunk {
+                finish_file(&mut files, &mut changed_lines);
+                files.push(DiffFile {
+                    path: String::new(),
+                    old_path: None,
+                    old_ranges: Vec::new(),
+                });
+                seen_hunk = false;
+            }
+            if let Some(file) = files.last_mut() {
+                file.old_path = diff_path(old);
+            }
+        } else if let Some(new) = line.strip_prefix("+++ ") {
+            if let Some(file) = files.last_mut() {
+                match diff_path(new) {
+                    Some(new) => {
+                        if file.old_path.as_deref() == Some(new.as_str()) {
+                            file.old_path = None;
+                        }
+                        file.path = new;
+                    }
+                    // Deleted file
+                    None => {
+                        if let Some(old) = file.old_path.take() {
+                            file.path = old;
+                        }
+                    }
+                }
+            }
+        } else if let Some(captures) = HUNK_HEADER.captures(line) {
+            let count = |i: usize| {
+                captures
+                    .get(i)
+                    .map_or(Some(1), |m| m.as_str().parse().ok())
+                    .unwrap_or(0)
+            };
+            old_line = captures[1].parse().unwrap_or(0);
+            old_left = count(2);
+            new_left = count(3);
+            // The old side of a zero-count hunk is the line before the insertion
+            if old_left == 0 && old_line > 0 {
+                old_line += 1;
+            }
+            seen_hunk = true;
+        }
+    }
+    finish_file(&mut files, &mut changed_lines);
+    files.retain(|file| !file.path.is_empty());
+    files
+}
+
+/// Merge the changed lines collected for the last file into its line ranges
+fn finish_file(files: &mut [DiffFile], changed_lines: &mut Vec<usize>) {
+    let Some(file) = files.last_mut() else {
+        changed_lines.clear();
+        return;
+    };
+    changed_lines.sort_unstable();
+    changed_lines.dedup();
+    for &line in changed_lines.iter().filter(|&&line| line > 0) {
+        match file.old_ranges.last_mut() {
+            Some((_, end)) if *end + 1 >= line => *end = line,
+            _ => file.old_ranges.push((line, line)),
+        }
+    }
+    changed_lines.clear();
+}
+
+/// Path of a `---`/`+++` line without the `a/`/`b/` prefix and timestamp; `None` for
+/// `/dev/null`
+fn diff_path(text: &str) -> Option<String> {
+    let path = text.split('\t').next().unwrap_or(text).trim_end();
+    if path == "/dev/null" {
+        return None;
+    }
+    let path = path
+        .strip_prefix("a/")
+        .or_else(|| path.strip_prefix("b/"))
+        .unwrap_or(path);
+    Some(path.to_string())
+}
+
+/// Ownership rules of a CODEOWNERS file
+#[derive(Debug, Clone, Default)]
+pub struct CodeOwners {
+    rules: Vec<(Regex, Vec<String>)>,
+}
+
+impl CodeOwners {
+    /// Rules from CODEOWNERS content; lines with invalid patterns are skipped
+    pub fn parse(content: &str) -> Self {
+        let rules = content
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .filter_map(|line| {
+                let mut fields = line.split_whitespace();
+                let pattern = owner_pattern(fields.next()?)?;
+                let owners = fields
+                    .take_while(|field| !field.starts_with('#'))
+                    .map(str::to_string)
+                    .collect();
+                Some((pattern, owners))
+            })
+            .collect();
+        Self { rules }
+    }
+
+    /// Owners of a repository-relative path; empty when no rule matches or the last
+    /// matching rule names nobody
+    pub fn owners_of(&self, path: &str) -> &[String] {
+        let path = path.trim_start_matches("./").trim_start_matches('/');
+        self.rules
+            .iter()
+            .rev()
+            .find(|(pattern, _)| pattern.is_match(path))
+            .map_or(&[], |(_, owners)| owners.as_slice())
+    }
+}
+
+/// Regex for a gitignore-style CODEOWNERS pattern
+///
+/// Patterns with a leading or inner `/` are anchored at the repository root, others match
+/// at any depth. A matched directory owns everything below it, except that `dir/*` only
+/// covers the files directly in `dir`.
+fn owner_pattern(pattern: &str) -> Option<Regex> {
+    let body = pattern.trim_start_matches('/').trim_end_matches('/');
+    let anchored = pattern.starts_with('/') || body.contains('/');
+    let mut regex = String::from(if anchored { "^" } else { "^(?:.*/)?" });
+    let mut chars = body.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                if chars.peek() == Some(&'/') {
+                    chars.next();
+                    regex.push_str("(?:.*/)?");
+                } else {
+                    regex.push_str(".*");
+                }
+            }
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    if !body.ends_with("/*") {
+        regex.push_str("(?:/.*)?");
+    }
+    regex.push('$');
+    Regex::new(&regex).ok()
+}
+
+/// Author, date and changed files of an ingested commit
+#[derive(Debug, Clone)]
+pub struct CommitRecord {
+    /// `Name <email>`
+    pub author: String,
+    pub timestamp: DateTime<Utc>,
+    pub files: Vec<String>,
+}
+
+impl CommitRecord {
+    /// Author and date of a commit document created by git ingestion, with its changed files
+    pub fn parse(content: &str, files: Vec<String>) -> Option<Self> {
+        let author = COMMIT_AUTHOR.captures(content)?[1].to_string();
+        let date = COMMIT_DATE.captures(content)?;
+        let timestamp = NaiveDateTime::parse_from_str(&date[1], "%Y-%m-%d %H:%M:%S")
+            .ok()?
+            .and_utc();
+        Some(Self {
+            author,
+            timestamp,
+            files,
+        })
+    }
+}
+
+/// Candidate reviewer with the evidence behind the ranking
+#[derive(Debug, Clone, Serialize)]
+pub struct ReviewerSuggestion {
+    /// `Name <email>` of a commit author, or a CODEOWNERS entry no author matches
+    pub reviewer: String,
+    /// CODEOWNERS entry (`@user`, `@org/team` or email) naming this reviewer
+    pub owner: Option<String>,
+    pub score: f64,
+    /// Changed files this reviewer owns per CODEOWNERS
+    pub owned_files: Vec<String>,
+    /// Ingested commits that changed the changed files
+    pub changed_file_commits: usize,
+    /// Ingested commits that changed files of impacted symbols
+    pub impacted_file_commits: usize,
+    /// Changed lines and lines of changed functions last modified by this reviewer
+    pub blamed_lines: usize,
+    /// Most recent commit to a changed or impacted file
+    pub last_commit: Option<DateTime<Utc>>,
+}
+
+/// Reviewer suggestions for a diff
+#[derive(Debug, Clone, Serialize)]
+pub struct ReviewerSuggestions {
+    pub changed_files: Vec<String>,
+    /// Functions and methods overlapping the changed lines
+    pub changed_symbols: Vec<String>,
+    /// Other files with symbols that depend on the changed ones
+    pub impacted_files: Vec<String>,
+    pub commits_analyzed: usize,
+    pub reviewers: Vec<ReviewerSuggestion>,
+}
+
+impl ReviewerSuggestions {
+    /// Format the suggestions as markdown
+    pub fn to_markdown(&self) -> String {
+        let mut md = format!(
+            "## Suggested reviewers\n\n{} changed files, {} changed symbols, {} impacted files, {} commits analyzed\n\n",
+            self.changed_files.len(),
+            self.changed_symbols.len(),
+            self.impacted_files.len(),
+            self.commits_analyzed
+        );
+        if self.reviewers.is_empty() {
+            md.push_str("No reviewers found: no CODEOWNERS rule, commit or blame covers the changed files.\n");
+            return md;
+        }
+        md.push_str("| Reviewer | Score | Owned files | Changed-file commits | Impacted-file commits | Blamed lines | Last commit |\n");
+        md.push_str("|---|---|---|---|---|---|---|\n");
+        for reviewer in &self.reviewers {
+            let name = match &reviewer.owner {
+                Some(owner) if owner != &reviewer.reviewer => {
+                    format!("{} ({})", reviewer.reviewer, owner)
+                }
+                _ => reviewer.reviewer.clone(),
+            };
+            md.push_str(&format!(
+                "| {} | {:.3} | {} | {} | {} | {} | {} |\n",
+                name,
+                reviewer.score,
+                reviewer.owned_files.len(),
+                reviewer.changed_file_commits,
+                reviewer.impacted_file_commits,
+                reviewer.blamed_lines,
+                reviewer
+                    .last_commit
+                    .map(|date| date.format("%Y-%m-%d").to_string())
+                    .unwrap_or_else(|| "-".to_string())
+            ));
+        }
+        md
+    }
+}
+
+#[derive(Default)]
+struct Tally {
+    owner: Option<String>,
+    owned_files: Vec<String>,
+    changed_file_commits: usize,
+    impacted_file_commits: usize,
+    history: f64,
+    impact: f64,
+    blamed_lines: usize,
+    last_commit: Option<DateTime<Utc>>,
+}
+
+/// Rank reviewers of `changed_files` by ownership, recency-weighted commits to the changed
+/// and impacted files, and blamed lines (keyed by `Name <email>`)
+///
+/// Reviewers matching an `exclude` entry (name, email, `Name <email>` or CODEOWNERS
+/// entry, ignoring case), such as the author of the change, are left out.
+pub fn rank_reviewers(
+    changed_files: &[String],
+    impacted_files: &[String],
+    commits: &[CommitRecord],
+    codeowners: &CodeOwners,
+    blame: &HashMap<String, usize>,
+    exclude: &[String],
+) -> Vec<ReviewerSuggestion> {
+    let touches = |files: &[String], commit: &CommitRecord| {
+        commit
+            .files
+            .iter()
+            .any(|file| files.iter().any(|path| paths_equivalent(path, file)))
+    };
+    let newest = commits.iter().map(|commit| commit.timestamp).max();
+    let mut tallies: BTreeMap<String, Tally> = BTreeMap::new();
+
+    for commit in commits
+        .iter()
+        .filter(|commit| commit.files.len() <= MAX_COMMIT_FILES)
+    {
+        let changed = touches(changed_files, commit);
+        if !changed && !touches(impacted_files, commit) {
+            continue;
+        }
+        let age_days = newest.map_or(0.0, |newest| {
+            (newest - commit.timestamp).num_seconds().max(0) as f64 / 86_400.0
+        });
+        let weight = 0.5f64.powf(age_days / HISTORY_HALF_LIFE_DAYS);
+        let tally = tallies.entry(commit.author.clone()).or_default();
+        if changed {
+            tally.changed_file_commits += 1;
+            tally.history += weight;
+        } else {
+            tally.impacted_file_commits += 1;
+            tally.impact += weight;
+        }
+        tally.last_commit = tally.last_commit.max(Some(commit.timestamp));
+    }
+    for (author, lines) in blame {
+        tallies.entry(author.clone()).or_default().blamed_lines += lines;
+    }
+    for file in changed_files {
+        for owner in codeowners.owners_of(file) {
+            let reviewer = tallies
+                .keys()
+                .find(|author| owner_matches_author(owner, author))
+                .cloned()
+                .unwrap_or_else(|| owner.clone());
+            let tally = tallies.entry(reviewer).or_default();
+            tally.owner = Some(owner.clone());
+            if !tally.owned_files.contains(file) {
+                tally.owned_files.push(file.clone());
+            }
+        }
+    }
+
+    tallies.retain(|reviewer, tally| {
+        !exclude.iter().any(|excluded| {
+            identity_matches(excluded, reviewer)
+                || tally
+                    .owner
+                    .as_ref()
+                    .is_some_and(|owner| owner.eq_ignore_ascii_case(excluded))
+        })
+    });
+    let share = |value: f64, total: f64| if total > 0.0 { value / total } else { 0.0 };
+    let total_history: f64 = tallies.values().map(|t| t.history).sum();
+    let total_impact: f64 = tallies.values().map(|t| t.impact).sum();
+    let total_blamed: usize = tallies.values().map(|t| t.blamed_lines).sum();
+
+    let mut suggestions: Vec<ReviewerSuggestion> = tallies
+        .into_iter()
+        .map(|(reviewer, tally)| {
+            let score = HISTORY_WEIGHT * share(tally.history, total_history)
+                + IMPACT_WEIGHT * share(tally.impact, total_impact)
+                + BLAME_WEIGHT * share(tally.blamed_lines as f64, total_blamed as f64)
+                + OWNER_WEIGHT * share(tally.owned_files.len() as f64, changed_files.len() as f64);
+            ReviewerSuggestion {
+                reviewer,
+                owner: tally.owner,
+                score: (score * 1000.0).round() / 1000.0,
+                owned_files: tally.owned_files,
+                changed_file_commits: tally.changed_file_commits,
+                impacted_file_commits: tally.impacted_file_commits,
+                blamed_lines: tally.blamed_lines,
+                last_commit: tally.last_commit,
+            }
+        })
+        .filter(|suggestion| suggestion.score > 0.0)
+        .collect();
+    suggestions.sort_by(|a, b| {
+        b.score
+            .total_cmp(&a.score)
+            .then_with(|| a.reviewer.cmp(&b.reviewer))
+    });
+    suggestions
+}
+
+/// Whether a CODEOWNERS entry names a commit author: `@user` by email user name or name
+/// without spaces, an email address by email
+fn owner_matches_author(owner: &str, author: &str) -> bool {
+    let (name, email) = split_identity(author);
+    match owner.strip_prefix('@') {
+        Some(handle) if !handle.contains('/') => {
+            email
+                .split('@')
+                .next()
+                .is_some_and(|user| user.eq_ignore_ascii_case(handle))
+                || name.replace(' ', "").eq_ignore_ascii_case(handle)
+        }
+        Some(_) => false,
+        None => email.eq_ignore_ascii_case(owner),
+    }
+}
+
+/// Whether `text` names the `Name <email>` identity by name, email or in full
+fn identity_matches(text: &str, identity: &str) -> bool {
+    let (name, email) = split_identity(identity);
+    [identity, name, email]
+        .iter()
+        .any(|candidate| !candidate.is_empty() && candidate.eq_ignore_ascii_case(text))
+}
+
+/// Name and email of a `Name <email>` identity
+fn split_identity(identity: &str) -> (&str, &str) {
+    match identity.split_once(" <") {
+        Some((name, email)) => (name, email.trim_end_matches('>')),
+        None => (identity, ""),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_changed_line_ranges_of_git_and_plain_diffs() {
+        let diff = "\
+diff --git a/src/lib.rs b/src/lib.rs
+index 1111111..2222222 100644
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -10,5 +10,6 @@ fn run() {
+     let a = 1;
+     let b = 2;
+-    let c = 3;
++    let c = 4;
++    let d = 5;
+     a + b
+ }
+--- a/src/old.rs
++++ b/src/new.rs
+@@ -3,0 +4,2 @@
++fn added() {}
++fn also_added() {}
+diff --git a/src/gone.rs b/src/gone.rs
+deleted file mode 100644
+--- a/src/gone.rs
++++ /dev/null
+@@ -1,2 +0,0 @@
+-fn gone() {}
+--- not a header
+";
+        let files = parse_unified_diff(diff);
+        assert_eq!(
+            files,
+            vec![
+                DiffFile {
+                    path: "src/lib.rs".to_string(),
+                    old_path: None,
+                    old_ranges: vec![(12, 12)],
+                },
+                DiffFile {
+                    path: "src/new.rs".to_string(),
+                    old_path: Some("src/old.rs".to_string()),
+                    old_ranges: vec![(3, 3)],
+                },
+                DiffFile {
+                    path: "src/gone.rs".to_string(),
+                    old_path: None,
+                    old_ranges: vec![(1, 2)],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn ranks_owners_history_and_blame() {
+        let codeowners = CodeOwners::parse(
+            "# Default owners\n\
+             *       @org/core\n\
+             /src/db/ @alice carol@example.com\n\
+             docs/*  @dana\n",
+        );
+        assert_eq!(
+            codeowners.owners_of("src/db/pool.rs"),
+            ["@alice", "carol@example.com"]
+        );
+        assert_eq!(codeowners.owners_of("docs/guide.md"), ["@dana"]);
+        assert_eq!(codeowners.owners_of("docs/api/index.md"), ["@org/core"]);
+        assert_eq!(codeowners.owners_of("lib/db/pool.rs"), ["@org/core"]);
+
+        let commit = |author: &str, date: &str, files: &[&str]| {
+            CommitRecord::parse(
+                &format!("# Commit: abc\n\n**Author**: {author}\n**Date**: {date} UTC\n"),
+                files.iter().map(|f| f.to_string()).collect(),
+            )
+            .unwrap()
+        };
+        let commits = vec![
+            commit(
+                "Alice Doe <alice@example.com>",
+                "2024-06-01 10:00:00",
+                &["src/db/pool.rs"],
+            ),
+            commit(
+                "Bob Roe <bob@example.com>",
+                "2023-06-01 10:00:00",
+                &["src/db/pool.rs"],
+            ),
+            commit(
+                "Bob Roe <bob@example.com>",
+                "2023-07-01 10:00:00",
+                &["src/db/pool.rs"],
+            ),
+            commit(
+                "Erin Poe <erin@example.com>",
+                "2024-05-01 10:00:00",
+                &["src/api/handlers.rs"],
+            ),
+            commit(
+                "Frank Moe <frank@example.com>",
+                "2024-05-01 10:00:00",
+                &["README.md"],
+            ),
+        ];
+        let blame = HashMap::from([("Bob Roe <bob@example.com>".to_string(), 30)]);
+        let reviewers = rank_reviewers(
+            &["src/db/pool.rs".to_string()],
+            &["src/api/handlers.rs".to_string()],
+            &commits,
+            &codeowners,
+            &blame,
+            &["carol@example.com".to_string()],
+        );
+
+        let names: Vec<&str> = reviewers.iter().map(|r| r.reviewer.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "Bob Roe <bob@example.com>",
+                "Alice Doe <alice@example.com>",
+                "Erin Poe <erin@example.com>",
+            ]
+        );
+        let alice = &reviewers[1];
+        assert_eq!(alice.owner.as_deref(), Some("@alice"));
+        assert_eq!(alice.owned_files, ["src/db/pool.rs"]);
+        assert_eq!(alice.changed_file_commits, 1);
+        assert_eq!(reviewers[0].blamed_lines, 30);
+        assert_eq!(reviewers[2].impacted_file_commits, 1);
+    }
+}
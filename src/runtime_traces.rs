@@ -0,0 +1,700 @@
+//! Observed call counts from runtime traces
+//!
+//! `kotadb import-traces` reads exported OpenTelemetry spans (OTLP JSON, as written by the
+//! collector's file exporter) or folded stacks (`perf script | stackcollapse-perf.pl`,
+//! py-spy, async-profiler), resolves their frames to symbols of the dependency graph and
+//! stores how often each symbol and each caller → callee edge was observed as
+//! `call_profile.json` in the database directory. Hot-path analysis then ranks symbols by
+//! observed calls, and risk-sorted caller and impact results put the code production
+//! exercises most first.
+//!
+//! For spans a count is one call; for folded stacks it is the number of samples a symbol
+//! (or edge) appeared in, which tracks time spent rather than calls made.
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::dependency_extractor::SerializableDependencyGraph;
+
+/// File name of the imported call profile inside a database directory
+pub const CALL_PROFILE_FILE: &str = "call_profile.json";
+
+/// Format of an exported trace
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TraceFormat {
+    /// OTLP JSON spans
+    #[default]
+    Otel,
+    /// `frame;frame;frame count` lines
+    Folded,
+}
+
+impl TraceFormat {
+    /// OTLP exports are JSON; anything else is read as folded stacks
+    pub fn detect(content: &str) -> Self {
+        if content.trim_start().starts_with('{') {
+            TraceFormat::Otel
+        } else {
+            TraceFormat::Folded
+        }
+    }
+}
+
+impl FromStr for TraceFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "otel" | "otlp" | "opentelemetry" => Ok(TraceFormat::Otel),
+            "folded" | "flamegraph" | "perf" => Ok(TraceFormat::Folded),
+            other => bail!("Unknown trace format '{}': expected otel or folded", other),
+        }
+    }
+}
+
+/// A function as a trace names it
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Frame {
+    /// Unqualified function name
+    pub name: String,
+    /// Module, class or namespace segments preceding the name
+    pub namespace: Vec<String>,
+    /// Source file, when the trace records one
+    pub file: Option<String>,
+}
+
+impl Frame {
+    /// Parse a frame such as `kotadb::storage::FileStorage::insert::h3c1e..`,
+    /// `<A as B>::insert`, `insert (kotadb/storage.py:10)` or `com/foo/Bar.insert_[j]`
+    pub fn parse(raw: &str) -> Option<Self> {
+        let mut raw = raw.trim();
+        // perf annotations for kernel, JIT and inlined frames
+        if let Some(start) = raw.rfind("_[") {
+            if raw.ends_with(']') {
+                raw = &raw[..start];
+            }
+        }
+
+        let mut file = None;
+        if let (Some(open), true) = (raw.find(" ("), raw.ends_with(')')) {
+            let location = &raw[open + 2..raw.len() - 1];
+            let path = match location.rsplit_once(':') {
+                Some((path, line)) if line.chars().all(|c| c.is_ascii_digit()) => path,
+                _ => location,
+            };
+            if path.contains('/') || path.contains('.') {
+                file = Some(path.replace('\\', "/"));
+            }
+            raw = &raw[..open];
+        }
+
+        let mut depth = 0usize;
+        let mut plain = String::with_capacity(raw.len());
+        for c in raw.chars() {
+            match c {
+                '<' => depth += 1,
+                '>' => depth = depth.saturating_sub(1),
+                _ if depth == 0 => plain.push(c),
+                _ => {}
+            }
+        }
+        let plain = plain.split('(').next().unwrap_or_default();
+
+        let mut segments: Vec<String> = plain
+            .split("::")
+            .flat_map(|segment| segment.split(['.', '/']))
+            .map(str::trim)
+            .filter(|segment| !segment.is_empty() && !segment.starts_with("{{"))
+            .filter(|segment| !is_symbol_hash(segment))
+            .map(str::to_string)
+            .collect();
+        let name = segments.pop()?;
+        Some(Self {
+            name,
+            namespace: segments,
+            file,
+        })
+    }
+}
+
+/// Rust legacy mangling hashes, e.g. `h3c1e2f4a5b6c7d8e`
+fn is_symbol_hash(segment: &str) -> bool {
+    segment.len() == 17
+        && segment.starts_with('h')
+        && segment[1..].chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// What a trace observed, before frames are resolved to symbols
+#[derive(Debug, Clone, PartialEq)]
+pub enum Observation {
+    /// A sampled stack, outermost frame first, seen `count` times
+    Stack { frames: Vec<Frame>, count: u64 },
+    /// One call of `callee`; `callers` holds its ancestors, nearest first
+    Call { callee: Frame, callers: Vec<Frame> },
+}
+
+/// Parse a trace export into observations
+pub fn parse_traces(content: &str, format: TraceFormat) -> Result<Vec<Observation>> {
+    let observations = match format {
+        TraceFormat::Folded => parse_folded(content),
+        TraceFormat::Otel => parse_otlp(content)?,
+    };
+    if observations.is_empty() {
+        bail!("Trace contains no stacks or spans");
+    }
+    Ok(observations)
+}
+
+fn parse_folded(content: &str) -> Vec<Observation> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let (stack, count) = line.trim().rsplit_once(char::is_whitespace)?;
+            let count = count.parse::<u64>().ok()?;
+            let frames: Vec<Frame> = stack.split(';').filter_map(Frame::parse).collect();
+            (!frames.is_empty() && count > 0).then_some(Observation::Stack { frames, count })
+        })
+        .collect()
+}
+
+/// A whole OTLP export, or one export per line as the collector's file exporter writes
+fn parse_otlp(content: &str) -> Result<Vec<Observation>> {
+    let documents: Vec<JsonValue> = match serde_json::from_str(content) {
+        Ok(document) => vec![document],
+        Err(_) => content
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(serde_json::from_str)
+            .collect::<Result<_, _>>()
+            .context("Trace is neither OTLP JSON nor JSON lines of OTLP exports")?,
+    };
+
+    let mut spans: HashMap<String, (Frame, Option<String>)> = HashMap::new();
+    for document in &documents {
+        let resource_spans = document["resourceSpans"].as_array().into_iter().flatten();
+        for resource in resource_spans {
+            let scopes = resource["scopeSpans"]
+                .as_array()
+                .or_else(|| resource["instrumentationLibrarySpans"].as_array())
+                .into_iter()
+                .flatten();
+            for span in scopes.flat_map(|scope| scope["spans"].as_array().into_iter().flatten()) {
+                let Some(id) = span["spanId"].as_str() else {
+                    continue;
+                };
+                let Some(frame) = span_frame(span) else {
+                    continue;
+                };
+                let parent = span["parentSpanId"]
+                    .as_str()
+                    .filter(|parent| !parent.is_empty())
+                    .map(str::to_string);
+                spans.insert(id.to_string(), (frame, parent));
+            }
+        }
+    }
+
+    Ok(spans
+        .values()
+        .map(|(callee, parent)| {
+            let mut callers = Vec::new();
+            let mut seen = HashSet::new();
+            let mut next = parent.as_deref();
+            while let Some(id) = next.filter(|id| seen.insert(*id)) {
+                let Some((frame, parent)) = spans.get(id) else {
+                    break;
+                };
+                callers.push(frame.clone());
+                next = parent.as_deref();
+            }
+            Observation::Call {
+                callee: callee.clone(),
+                callers,
+            }
+        })
+        .collect())
+}
+
+/// Function of a span from its `code.*` attributes, or from its name
+fn span_frame(span: &JsonValue) -> Option<Frame> {
+    let attribute = |key: &str| {
+        span["attributes"]
+            .as_array()?
+            .iter()
+            .find(|a| a["key"] == key)?["value"]["stringValue"]
+            .as_str()
+    };
+    let mut frame = match attribute("code.function") {
+        Some(function) => {
+            let qualified = match attribute("code.namespace") {
+                Some(namespace) => format!("{}::{}", namespace, function),
+                None => function.to_string(),
+            };
+            Frame::parse(&qualified)?
+        }
+        None => Frame::parse(span["name"].as_str()?)?,
+    };
+    if let Some(file) = attribute("code.filepath") {
+        frame.file = Some(file.replace('\\', "/"));
+    }
+    Some(frame)
+}
+
+/// Observed calls of one symbol of the dependency graph
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ObservedSymbol {
+    pub qualified_name: String,
+    pub file_path: String,
+    pub calls: u64,
+}
+
+/// Observed calls from one symbol to another
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ObservedEdge {
+    pub caller: String,
+    pub callee: String,
+    pub calls: u64,
+    /// Whether static analysis found this dependency too; dynamic dispatch and
+    /// callbacks often show up only at runtime
+    pub in_graph: bool,
+}
+
+/// Call counts imported from runtime traces
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CallProfile {
+    /// RFC 3339 time of the import
+    pub imported_at: String,
+    /// Trace the counts were read from
+    pub source: String,
+    pub format: TraceFormat,
+    /// Stacks or spans read
+    pub observations: usize,
+    /// Distinct frame names that matched no symbol (or several)
+    pub unresolved_frames: usize,
+    /// Symbols by descending calls
+    pub symbols: Vec<ObservedSymbol>,
+    /// Edges by descending calls
+    pub edges: Vec<ObservedEdge>,
+    #[serde(skip)]
+    symbol_calls: HashMap<String, u64>,
+    #[serde(skip)]
+    edge_calls: HashMap<(String, String), u64>,
+}
+
+impl CallProfile {
+    /// Attach observed calls to the symbols and edges of `graph`
+    pub fn build(
+        observations: &[Observation],
+        graph: &SerializableDependencyGraph,
+        source: impl Into<String>,
+        format: TraceFormat,
+    ) -> Self {
+        let resolver = FrameResolver::new(graph);
+        let mut unresolved = HashSet::new();
+        let mut resolve = |frame: &Frame| {
+            let resolved = resolver.resolve(frame);
+            if resolved.is_none() {
+                unresolved.insert(frame.name.clone());
+            }
+            resolved
+        };
+
+        let mut symbol_calls: HashMap<usize, u64> = HashMap::new();
+        let mut edge_calls: HashMap<(usize, usize), u64> = HashMap::new();
+        for observation in observations {
+            match observation {
+                Observation::Stack { frames, count } => {
+                    // Frames no symbol matches (e.g. std or framework code) are skipped,
+                    // joining the symbols around them
+                    let resolved: Vec<usize> = frames.iter().filter_map(&mut resolve).collect();
+                    let symbols: HashSet<usize> = resolved.iter().copied().collect();
+                    for symbol in symbols {
+                        *symbol_calls.entry(symbol).or_insert(0) += count;
+                    }
+                    let edges: HashSet<(usize, usize)> = resolved
+                        .windows(2)
+                        .map(|pair| (pair[0], pair[1]))
+                        .filter(|(caller, callee)| caller != callee)
+                        .collect();
+                    for edge in edges {
+                        *edge_calls.entry(edge).or_insert(0) += count;
+                    }
+                }
+                Observation::Call { callee, callers } => {
+                    let Some(callee) = resolve(callee) else {
+                        continue;
+                    };
+                    *symbol_calls.entry(callee).or_insert(0) += 1;
+                    if let Some(caller) = callers.iter().find_map(|frame| resolver.resolve(frame)) {
+                        if caller != callee {
+                            *edge_calls.entry((caller, callee)).or_insert(0) += 1;
+                        }
+                    }
+                }
+            }
+        }
+
+        let static_edges: HashSet<(usize, usize)> = graph
+            .edges
+            .iter()
+            .filter_map(|edge| {
+                Some((
+                    *resolver.by_id.get(&edge.from_id)?,
+                    *resolver.by_id.get(&edge.to_id)?,
+                ))
+            })
+            .collect();
+        let node = |i: usize| &graph.nodes[i];
+
+        let mut symbols: Vec<ObservedSymbol> = symbol_calls
+            .into_iter()
+            .map(|(i, calls)| ObservedSymbol {
+                qualified_name: node(i).qualified_name.clone(),
+                file_path: node(i).file_path.display().to_string(),
+                calls,
+            })
+            .collect();
+        symbols.sort_by(|a, b| {
+            b.calls
+                .cmp(&a.calls)
+                .then_with(|| a.qualified_name.cmp(&b.qualified_name))
+        });
+        let mut edges: Vec<ObservedEdge> = edge_calls
+            .into_iter()
+            .map(|((caller, callee), calls)| ObservedEdge {
+                caller: node(caller).qualified_name.clone(),
+                callee: node(callee).qualified_name.clone(),
+                calls,
+                in_graph: static_edges.contains(&(caller, callee)),
+            })
+            .collect();
+        edges.sort_by(|a, b| {
+            b.calls
+                .cmp(&a.calls)
+                .then_with(|| (&a.caller, &a.callee).cmp(&(&b.caller, &b.callee)))
+        });
+
+        let mut profile = Self {
+            imported_at: chrono::Utc::now().to_rfc3339(),
+            source: source.into(),
+            format,
+            observations: observations.len(),
+            unresolved_frames: unresolved.len(),
+            symbols,
+            edges,
+            ..Self::default()
+        };
+        profile.index();
+        profile
+    }
+
+    /// Location of the call profile for a database directory
+    pub fn path_for(db_path: &Path) -> PathBuf {
+        db_path.join(CALL_PROFILE_FILE)
+    }
+
+    /// The imported call profile of a database, if traces were imported
+    pub fn load(db_path: &Path) -> Result<Option<Self>> {
+        let path = Self::path_for(db_path);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read call profile: {}", path.display()))?;
+        let mut profile: Self = serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse call profile: {}", path.display()))?;
+        profile.index();
+        Ok(Some(profile))
+    }
+
+    /// Save the profile atomically into a database directory, replacing earlier imports
+    pub fn save(&self, db_path: &Path) -> Result<()> {
+        std::fs::create_dir_all(db_path)
+            .with_context(|| format!("Failed to create {}", db_path.display()))?;
+        let path = Self::path_for(db_path);
+        let tmp_path = path.with_extension("json.tmp");
+        std::fs::write(&tmp_path, serde_json::to_string(self)?)
+            .with_context(|| format!("Failed to write call profile: {}", tmp_path.display()))?;
+        std::fs::rename(&tmp_path, &path)
+            .with_context(|| format!("Failed to replace call profile: {}", path.display()))?;
+        Ok(())
+    }
+
+    /// Observed calls of a symbol, by qualified name
+    pub fn symbol_calls(&self, qualified_name: &str) -> Option<u64> {
+        self.symbol_calls.get(qualified_name).copied()
+    }
+
+    /// Observed calls from `caller` (qualified name) to `callee`, which may be a plain
+    /// name; calls to every callee of that name are summed then
+    pub fn edge_calls(&self, caller: &str, callee: &str) -> Option<u64> {
+        if let Some(calls) = self
+            .edge_calls
+            .get(&(caller.to_string(), callee.to_string()))
+        {
+            return Some(*calls);
+        }
+        let callee = short_name(callee);
+        let matching: Vec<u64> = self
+            .edges
+            .iter()
+            .filter(|edge| edge.caller == caller && short_name(&edge.callee) == callee)
+            .map(|edge| edge.calls)
+            .collect();
+        (!matching.is_empty()).then(|| matching.iter().sum())
+    }
+
+    fn index(&mut self) {
+        self.symbol_calls = self
+            .symbols
+            .iter()
+            .map(|symbol| (symbol.qualified_name.clone(), symbol.calls))
+            .collect();
+        self.edge_calls = self
+            .edges
+            .iter()
+            .map(|edge| ((edge.caller.clone(), edge.callee.clone()), edge.calls))
+            .collect();
+    }
+}
+
+/// Plain name of a graph symbol, whose qualified name is `<file>::<name>`
+fn short_name(qualified_name: &str) -> &str {
+    qualified_name.rsplit("::").next().unwrap_or(qualified_name)
+}
+
+/// Resolves trace frames to dependency graph nodes
+struct FrameResolver<'g> {
+    graph: &'g SerializableDependencyGraph,
+    by_name: HashMap<&'g str, Vec<usize>>,
+    by_id: HashMap<uuid::Uuid, usize>,
+}
+
+impl<'g> FrameResolver<'g> {
+    fn new(graph: &'g SerializableDependencyGraph) -> Self {
+        let mut by_name: HashMap<&str, Vec<usize>> = HashMap::new();
+        let mut by_id = HashMap::new();
+        for (i, node) in graph.nodes.iter().enumerate() {
+            by_name
+                .entry(short_name(&node.qualified_name))
+                .or_default()
+                .push(i);
+            by_id.insert(node.symbol_id, i);
+        }
+        Self {
+            graph,
+            by_name,
+            by_id,
+        }
+    }
+
+    /// The node a frame names: the only symbol with its name, or the one whose file
+    /// matches the frame's file or best matches its namespace
+    fn resolve(&self, frame: &Frame) -> Option<usize> {
+        let candidates = self.by_name.get(frame.name.as_str())?;
+        if let [only] = candidates.as_slice() {
+            return Some(*only);
+        }
+        let path = |i: usize| {
+            self.graph.nodes[i]
+                .file_path
+                .to_string_lossy()
+                .replace('\\', "/")
+        };
+
+        if let Some(file) = &frame.file {
+            let file = file.trim_start_matches("./");
+            let matching: Vec<usize> = candidates
+                .iter()
+                .copied()
+                .filter(|&i| {
+                    let path = path(i);
+                    path == file
+                        || path.ends_with(&format!("/{}", file))
+                        || file.ends_with(&format!("/{}", path))
+                })
+                .collect();
+            if let [only] = matching.as_slice() {
+                return Some(*only);
+            }
+        }
+
+        let score = |i: usize| {
+            let path = path(i);
+            let components: HashSet<&str> = path
+                .split('/')
+                .map(|component| component.split('.').next().unwrap_or(component))
+                .collect();
+            frame
+                .namespace
+                .iter()
+                .filter(|segment| components.contains(segment.as_str()))
+                .count()
+        };
+        let scored: Vec<(usize, usize)> = candidates.iter().map(|&i| (score(i), i)).collect();
+        let best = scored.iter().map(|(score, _)| *score).max()?;
+        let mut top = scored.iter().filter(|(score, _)| *score == best);
+        match (top.next(), top.next()) {
+            (Some((score, i)), None) if *score > 0 => Some(*i),
+            _ => None,
+        }
+    }
+}
+
+/// Import a trace into a database, resolving frames against its dependency graph
+pub fn import_traces(
+    db_path: &Path,
+    trace_path: &Path,
+    format: Option<TraceFormat>,
+) -> Result<CallProfile> {
+    let content = std::fs::read_to_string(trace_path)
+        .with_context(|| format!("Failed to read trace: {}", trace_path.display()))?;
+    let format = format.unwrap_or_else(|| TraceFormat::detect(&content));
+    let observations = parse_traces(&content, format)?;
+
+    let graph_path = db_path.join("dependency_graph.bin");
+    if !graph_path.exists() {
+        bail!("No dependency graph found; index a codebase with relationship extraction first");
+    }
+    let graph = SerializableDependencyGraph::from_bytes(
+        &std::fs::read(&graph_path)
+            .with_context(|| format!("Failed to read {}", graph_path.display()))?,
+    )?;
+
+    let profile = CallProfile::build(
+        &observations,
+        &graph,
+        trace_path.display().to_string(),
+        format,
+    );
+    profile.save(db_path)?;
+    Ok(profile)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dependency_extractor::{DependencyEdge, GraphStats, SerializableEdge, SymbolNode};
+    use crate::parsing::SymbolType;
+    use crate::types::RelationType;
+    use uuid::Uuid;
+
+    fn graph() -> SerializableDependencyGraph {
+        let node = |file: &str, name: &str| SymbolNode {
+            symbol_id: Uuid::new_v4(),
+            qualified_name: format!("{}::{}", file, name),
+            symbol_type: SymbolType::Function,
+            file_path: PathBuf::from(file),
+            in_degree: 0,
+            out_degree: 0,
+        };
+        let nodes = vec![
+            node("src/main.rs", "main"),
+            node("src/server.rs", "handle"),
+            node("src/storage.rs", "insert"),
+            node("src/index.rs", "insert"),
+        ];
+        let edges = vec![SerializableEdge {
+            from_id: nodes[0].symbol_id,
+            to_id: nodes[1].symbol_id,
+            edge: DependencyEdge {
+                relation_type: RelationType::Calls,
+                line_number: 3,
+                column_number: 4,
+                end_line_number: None,
+                end_column_number: None,
+                start_byte: None,
+                end_byte: None,
+                context: None,
+            },
+        }];
+        SerializableDependencyGraph {
+            nodes,
+            edges,
+            name_to_symbol: HashMap::new(),
+            file_imports: HashMap::new(),
+            stats: GraphStats::default(),
+        }
+    }
+
+    #[test]
+    fn folded_stacks_weight_symbols_and_edges() -> Result<()> {
+        let folded = "\
+app::main;app::server::handle;app::storage::insert 30
+app::main;app::server::handle;std::io::write_all;app::index::insert 10
+app::main;app::server::handle 5
+[unknown];insert 7
+";
+        let observations = parse_traces(folded, TraceFormat::detect(folded))?;
+        let profile =
+            CallProfile::build(&observations, &graph(), "perf.folded", TraceFormat::Folded);
+
+        assert_eq!(profile.symbol_calls("src/main.rs::main"), Some(45));
+        assert_eq!(profile.symbol_calls("src/storage.rs::insert"), Some(30));
+        assert_eq!(profile.symbol_calls("src/index.rs::insert"), Some(10));
+        assert_eq!(
+            profile.edge_calls("src/server.rs::handle", "src/index.rs::insert"),
+            Some(10)
+        );
+        assert_eq!(
+            profile.edge_calls("src/server.rs::handle", "insert"),
+            Some(40)
+        );
+        let edge = |caller: &str| profile.edges.iter().find(|e| e.caller == caller).unwrap();
+        assert!(edge("src/main.rs::main").in_graph);
+        assert!(!edge("src/server.rs::handle").in_graph);
+        // `write_all`, `[unknown]` and the ambiguous bare `insert`
+        assert_eq!(profile.unresolved_frames, 3);
+        Ok(())
+    }
+
+    #[test]
+    fn otlp_spans_count_calls_through_unresolved_ancestors() -> Result<()> {
+        let otlp = r#"{"resourceSpans":[{"scopeSpans":[{"spans":[
+            {"spanId":"a","name":"main"},
+            {"spanId":"b","parentSpanId":"a","name":"GET /items"},
+            {"spanId":"c","parentSpanId":"b","name":"handle"},
+            {"spanId":"d","parentSpanId":"c","name":"db.insert","attributes":[
+                {"key":"code.function","value":{"stringValue":"insert"}},
+                {"key":"code.filepath","value":{"stringValue":"/srv/app/src/storage.rs"}}]},
+            {"spanId":"e","parentSpanId":"c","name":"insert","attributes":[
+                {"key":"code.function","value":{"stringValue":"insert"}},
+                {"key":"code.namespace","value":{"stringValue":"app::storage"}}]}
+        ]}]}]}"#;
+        let observations = parse_traces(otlp, TraceFormat::detect(otlp))?;
+        let profile = CallProfile::build(&observations, &graph(), "spans.json", TraceFormat::Otel);
+
+        assert_eq!(profile.symbol_calls("src/storage.rs::insert"), Some(2));
+        assert_eq!(
+            profile.edge_calls("src/server.rs::handle", "src/storage.rs::insert"),
+            Some(2)
+        );
+        assert_eq!(
+            profile.edge_calls("src/main.rs::main", "src/server.rs::handle"),
+            Some(1)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn frames_drop_generics_hashes_and_locations() {
+        let frame =
+            Frame::parse("<kotadb::FileStorage as kotadb::Storage>::insert::h0123456789abcdef")
+                .unwrap();
+        assert_eq!(frame.name, "insert");
+        assert!(frame.namespace.is_empty());
+
+        let frame = Frame::parse("insert (kotadb/storage.py:10)").unwrap();
+        assert_eq!(frame.file.as_deref(), Some("kotadb/storage.py"));
+        assert_eq!(
+            Frame::parse("com/foo/Bar.run_[j]").unwrap().namespace,
+            ["com", "foo", "Bar"]
+        );
+        assert!("pprof".parse::<TraceFormat>().is_err());
+    }
+}
@@ -277,11 +277,11 @@ pub async fn validate_post_ingestion_search_with_config(
     }
 
     // Check 5: Sample query routing (if we have documents)
-    let storage_docs = storage
-        .list_all()
+    let first_page = storage
+        .list_page(None, 1)
         .await
         .context("Failed to list documents from storage")?;
-    if !storage_docs.is_empty() {
+    if !first_page.documents.is_empty() {
         validate_sample_query_routing(storage, primary_index, trigram_index, &mut report).await?;
     }
 
@@ -305,11 +305,11 @@ async fn validate_document_count_consistency(
     let ctx = ValidationContext::new("document_count_consistency");
 
     // Get document counts from all sources
-    let storage_docs = storage
-        .list_all()
+    let storage_count = storage
+        .list_metadata()
         .await
-        .context("Failed to list documents from storage")?;
-    let storage_count = storage_docs.len();
+        .context("Failed to list documents from storage")?
+        .len();
 
     // For indices, we'll do a wildcard search to get indexed documents
     // Use a high limit to get accurate counts - we need to see all indexed documents
@@ -444,29 +444,26 @@ async fn get_dynamic_search_terms(
     storage: &dyn Storage,
     config: &ValidationConfig,
 ) -> Result<Vec<String>> {
-    let docs = storage.list_all().await?;
+    // Sample a few documents to extract common terms
+    let sample = storage.list_page(None, 5).await?;
     let mut terms = Vec::new();
 
-    // Sample a few documents to extract common terms
-    let sample_size = std::cmp::min(docs.len(), 5);
-    for doc in docs.iter().take(sample_size) {
-        if let Some(retrieved_doc) = storage.get(&doc.id).await? {
-            // Extract and sanitize words from content with comprehensive validation
-            let content_str = String::from_utf8_lossy(&retrieved_doc.content);
-
-            // Apply our enhanced sanitization to the content
-            if let Ok(sanitized) = crate::query_sanitization::sanitize_search_query(&content_str) {
-                // Use sanitized terms instead of raw extraction
-                for term in sanitized.terms.iter().take(3) {
-                    let term_lower = term.to_lowercase();
-                    if !terms.contains(&term_lower)
-                        && !crate::query_sanitization::is_stop_word(&term_lower)
-                        && !contains_sensitive_patterns(&term_lower)
-                    {
-                        terms.push(term_lower);
-                        if terms.len() >= 4 {
-                            return Ok(terms);
-                        }
+    for doc in &sample.documents {
+        // Extract and sanitize words from content with comprehensive validation
+        let content_str = String::from_utf8_lossy(&doc.content);
+
+        // Apply our enhanced sanitization to the content
+        if let Ok(sanitized) = crate::query_sanitization::sanitize_search_query(&content_str) {
+            // Use sanitized terms instead of raw extraction
+            for term in sanitized.terms.iter().take(3) {
+                let term_lower = term.to_lowercase();
+                if !terms.contains(&term_lower)
+                    && !crate::query_sanitization::is_stop_word(&term_lower)
+                    && !contains_sensitive_patterns(&term_lower)
+                {
+                    terms.push(term_lower);
+                    if terms.len() >= 4 {
+                        return Ok(terms);
                     }
                 }
             }
@@ -535,7 +532,7 @@ async fn validate_index_document_coverage(
     report: &mut ValidationReport,
 ) -> Result<()> {
     let storage_docs = storage
-        .list_all()
+        .list_metadata()
         .await
         .context("Failed to list documents from storage")?;
     if storage_docs.is_empty() {
@@ -724,7 +721,7 @@ pub async fn quick_search_validation(
     primary_index: &dyn Index,
     trigram_index: &dyn Index,
 ) -> Result<QuickValidationResult> {
-    let storage_count = storage.list_all().await?.len();
+    let storage_count = storage.list_metadata().await?.len();
 
     if storage_count == 0 {
         return Ok(QuickValidationResult {
@@ -0,0 +1,249 @@
+//! `kotadb self-update`: replace the running binary with a verified release
+//!
+//! Releases on GitHub carry one `kotadb-<platform>-v<version>.tar.gz` archive per
+//! platform and a `.sha256` file next to each. An update downloads both, refuses the
+//! archive unless its SHA-256 matches, extracts `kotadb` and renames it over the
+//! running executable, so an interrupted update leaves the old binary in place.
+
+use std::io::Read;
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+/// GitHub API endpoint listing kotadb releases
+pub const RELEASES_API: &str = "https://api.github.com/repos/jayminwest/kota-db/releases";
+
+/// Version of the running binary
+pub const CURRENT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// A published release and its downloads
+#[derive(Debug, Clone, Deserialize)]
+pub struct Release {
+    pub tag_name: String,
+    #[serde(default)]
+    pub assets: Vec<ReleaseAsset>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReleaseAsset {
+    pub name: String,
+    pub browser_download_url: String,
+}
+
+impl Release {
+    /// Version of the release, without the leading `v` of the tag
+    pub fn version(&self) -> &str {
+        self.tag_name.trim_start_matches('v')
+    }
+
+    fn asset(&self, name: &str) -> Result<&ReleaseAsset> {
+        self.assets
+            .iter()
+            .find(|asset| asset.name == name)
+            .with_context(|| format!("Release {} has no {}", self.tag_name, name))
+    }
+}
+
+/// Archive name of the release build for this platform, e.g.
+/// `kotadb-linux-x64-v0.7.0.tar.gz`
+pub fn asset_name(version: &str) -> Result<String> {
+    let platform = match (std::env::consts::OS, std::env::consts::ARCH) {
+        ("linux", "x86_64") if cfg!(target_env = "musl") => "linux-musl-x64",
+        ("linux", "x86_64") => "linux-x64",
+        ("macos", "x86_64") => "macos-x64",
+        ("macos", "aarch64") => "macos-arm64",
+        (os, arch) => bail!(
+            "No release binary for {os}/{arch} can be installed by self-update; \
+             download one from https://github.com/jayminwest/kota-db/releases or build from source"
+        ),
+    };
+    Ok(format!("kotadb-{platform}-v{version}.tar.gz"))
+}
+
+/// Whether `candidate` is a later release than `current`
+///
+/// Versions compare by their numeric `major.minor.patch`; pre-release suffixes are
+/// ignored.
+pub fn is_newer(candidate: &str, current: &str) -> bool {
+    fn parts(version: &str) -> Vec<u64> {
+        version
+            .trim_start_matches('v')
+            .split(['-', '+'])
+            .next()
+            .unwrap_or_default()
+            .split('.')
+            .map(|part| part.parse().unwrap_or(0))
+            .collect()
+    }
+    parts(candidate) > parts(current)
+}
+
+/// Check `bytes` against a `.sha256` file in `sha256sum` format (`<hex>  <name>`)
+pub fn verify_checksum(bytes: &[u8], checksum_file: &str) -> Result<()> {
+    let expected = checksum_file
+        .split_whitespace()
+        .next()
+        .context("Checksum file is empty")?
+        .to_ascii_lowercase();
+    let actual = hex::encode(Sha256::digest(bytes));
+    if actual != expected {
+        bail!("Checksum mismatch: expected {expected}, downloaded archive has {actual}");
+    }
+    Ok(())
+}
+
+/// The `kotadb` executable inside a release archive
+pub fn extract_binary(archive: &[u8]) -> Result<Vec<u8>> {
+    let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(archive));
+    for entry in archive.entries().context("Invalid release archive")? {
+        let mut entry = entry.context("Invalid release archive")?;
+        let is_binary = entry.path()?.file_name() == Some("kotadb".as_ref());
+        if is_binary && entry.header().entry_type().is_file() {
+            let mut binary = Vec::new();
+            entry.read_to_end(&mut binary)?;
+            return Ok(binary);
+        }
+    }
+    bail!("Release archive does not contain a kotadb binary")
+}
+
+/// Put `binary` in place of the executable at `path`
+///
+/// The binary is written next to `path` and renamed over it, which replaces a running
+/// executable safely on Unix.
+pub fn replace_executable(path: &Path, binary: &[u8]) -> Result<()> {
+    let staged = path.with_file_name(format!(".kotadb-update-{}", std::process::id()));
+    let write = || -> std::io::Result<()> {
+        std::fs::write(&staged, binary)?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&staged, std::fs::Permissions::from_mode(0o755))?;
+        }
+        std::fs::rename(&staged, path)
+    };
+    if let Err(error) = write() {
+        let _ = std::fs::remove_file(&staged);
+        if error.kind() == std::io::ErrorKind::PermissionDenied {
+            bail!(
+                "No permission to replace {}; rerun with sufficient rights (e.g. sudo) or \
+                 reinstall to a writable location",
+                path.display()
+            );
+        }
+        return Err(error).with_context(|| format!("Failed to replace {}", path.display()));
+    }
+    Ok(())
+}
+
+/// Downloads releases from GitHub
+pub struct Updater {
+    client: reqwest::Client,
+}
+
+impl Updater {
+    pub fn new() -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .user_agent(format!("kotadb/{CURRENT_VERSION}"))
+            .timeout(std::time::Duration::from_secs(120))
+            .build()?;
+        Ok(Self { client })
+    }
+
+    /// The latest release, or the release of `version` when given
+    pub async fn release(&self, version: Option<&str>) -> Result<Release> {
+        let url = match version {
+            Some(version) => format!("{RELEASES_API}/tags/v{}", version.trim_start_matches('v')),
+            None => format!("{RELEASES_API}/latest"),
+        };
+        let response = self
+            .client
+            .get(&url)
+            .header("Accept", "application/vnd.github+json")
+            .send()
+            .await
+            .with_context(|| format!("Failed to reach {url}"))?;
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            bail!("No release found at {url}");
+        }
+        Ok(response.error_for_status()?.json().await?)
+    }
+
+    async fn download(&self, asset: &ReleaseAsset) -> Result<Vec<u8>> {
+        let response = self
+            .client
+            .get(&asset.browser_download_url)
+            .send()
+            .await
+            .with_context(|| format!("Failed to download {}", asset.name))?
+            .error_for_status()?;
+        Ok(response.bytes().await?.to_vec())
+    }
+
+    /// Download, verify and install `release` over the executable at `target`
+    pub async fn install(&self, release: &Release, target: &Path) -> Result<()> {
+        let archive_name = asset_name(release.version())?;
+        let archive_asset = release.asset(&archive_name)?;
+        let checksum_asset = release
+            .asset(&format!("{archive_name}.sha256"))
+            .context("Refusing to install a release without a published checksum")?;
+
+        let checksum = String::from_utf8(self.download(checksum_asset).await?)
+            .context("Checksum file is not text")?;
+        let archive = self.download(archive_asset).await?;
+        verify_checksum(&archive, &checksum)?;
+        replace_executable(target, &extract_binary(&archive)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn tar_gz(name: &str, contents: &[u8]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(
+            Vec::new(),
+            flate2::Compression::fast(),
+        ));
+        let mut header = tar::Header::new_gnu();
+        header.set_size(contents.len() as u64);
+        header.set_mode(0o755);
+        header.set_cksum();
+        builder.append_data(&mut header, name, contents).unwrap();
+        builder.into_inner().unwrap().finish().unwrap()
+    }
+
+    #[test]
+    fn compares_release_versions() {
+        assert!(is_newer("0.7.0", "0.6.2"));
+        assert!(is_newer("v0.6.10", "0.6.9"));
+        assert!(is_newer("1.0.0-beta.1", "0.9.9"));
+        assert!(!is_newer("0.6.2", "0.6.2"));
+        assert!(!is_newer("0.6.1", "0.6.2"));
+    }
+
+    #[test]
+    fn installs_only_verified_archives() -> Result<()> {
+        let dir = TempDir::new()?;
+        let target = dir.path().join("kotadb");
+        std::fs::write(&target, b"old")?;
+
+        let archive = tar_gz("kotadb", b"new binary");
+        let checksum = format!(
+            "{}  kotadb-linux-x64-v9.9.9.tar.gz\n",
+            hex::encode(Sha256::digest(&archive))
+        );
+        assert!(verify_checksum(b"tampered", &checksum).is_err());
+        verify_checksum(&archive, &checksum)?;
+
+        replace_executable(&target, &extract_binary(&archive)?)?;
+        assert_eq!(std::fs::read(&target)?, b"new binary");
+        assert_eq!(std::fs::read_dir(dir.path())?.count(), 1);
+
+        assert!(extract_binary(&tar_gz("README.md", b"docs")).is_err());
+        Ok(())
+    }
+}
@@ -3,13 +3,18 @@
 
 use anyhow::{anyhow, Result};
 use std::collections::HashMap;
+use std::path::Path;
 
 use crate::builders::QueryBuilder;
 use crate::contracts::{Document, Index, Storage};
-use crate::embeddings::{EmbeddingConfig, EmbeddingService};
+use crate::embeddings::{self, EmbeddingConfig, EmbeddingService};
+use crate::file_storage::create_file_storage;
 use crate::types::ValidatedDocumentId;
 use crate::vector_index::{DistanceMetric, VectorIndex};
 
+/// Vector index of document embeddings, relative to a database directory
+pub const DOCUMENT_VECTORS_FILE: &str = "vectors/documents.idx";
+
 /// Semantic search engine that combines storage, vector index, and embeddings
 pub struct SemanticSearchEngine {
     storage: Box<dyn Storage>,
@@ -27,7 +32,7 @@ impl SemanticSearchEngine {
     ) -> Result<Self> {
         let embedding_dimension = embedding_config.dimension;
 
-        let vector_index = VectorIndex::new(
+        let vector_index = VectorIndex::load(
             vector_index_path,
             DistanceMetric::Cosine, // Default to cosine similarity for semantic search
             embedding_dimension,
@@ -53,7 +58,7 @@ impl SemanticSearchEngine {
     ) -> Result<Self> {
         let embedding_dimension = embedding_config.dimension;
 
-        let vector_index = VectorIndex::new(
+        let vector_index = VectorIndex::load(
             vector_index_path,
             DistanceMetric::Cosine,
             embedding_dimension,
@@ -70,6 +75,35 @@ impl SemanticSearchEngine {
         })
     }
 
+    /// Open the semantic search engine of a database directory
+    ///
+    /// Returns `None` unless the database has a document vector index
+    /// ([`DOCUMENT_VECTORS_FILE`]) and an embedding provider is configured
+    /// ([`embeddings::config_from_env`]). The engine opens its own storage instance.
+    pub async fn for_database(db_path: &Path) -> Result<Option<Self>> {
+        let vector_index_path = db_path.join(DOCUMENT_VECTORS_FILE);
+        if !vector_index_path.exists() {
+            return Ok(None);
+        }
+        let Some(embedding_config) = embeddings::config_from_env() else {
+            return Ok(None);
+        };
+        let storage_path = db_path.join("storage");
+        let storage = create_file_storage(
+            storage_path
+                .to_str()
+                .ok_or_else(|| anyhow!("Invalid storage path: {:?}", storage_path))?,
+            Some(100),
+        )
+        .await?;
+        let vector_index_path = vector_index_path
+            .to_str()
+            .ok_or_else(|| anyhow!("Invalid vector index path: {:?}", vector_index_path))?;
+        Self::new(Box::new(storage), vector_index_path, embedding_config)
+            .await
+            .map(Some)
+    }
+
     // Note: SemanticSearchEngine requires Box<dyn Trait> types due to its ownership model
     // This necessitates creating separate instances, which reduces memory sharing benefits
     // A future refactor could address this by redesigning the SemanticSearchEngine API
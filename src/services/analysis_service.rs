@@ -3,25 +3,119 @@
 // This service extracts code intelligence and relationship analysis logic from main.rs
 // to enable feature parity across all KotaDB interfaces while maintaining identical behavior.
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use serde_json::json;
 use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use tokio::sync::{Mutex, RwLock};
 use uuid::Uuid;
 
+use crate::metrics::performance::{LockedResource, TimedLock};
 use crate::{
-    binary_relationship_engine::BinaryRelationshipEngine,
+    annotations::{AnnotationStore, LineRange, Note, MAX_NOTES_PER_RESULT},
+    binary_relationship_engine::{BinaryEngineStats, BinaryRelationshipEngine, QueryExplanation},
+    binary_relationship_engine_async::AsyncBinaryRelationshipEngine,
     binary_symbols::BinarySymbolReader,
-    dependency_extractor::SerializableDependencyGraph,
-    path_utils::{detect_language_from_extension, is_potential_entry_point, is_test_file},
-    relationship_query::{RelationshipQueryConfig, RelationshipQueryType},
+    binary_symbols::FunctionRanges,
+    cargo_workspace::CargoCrate,
+    config_usages::{ConfigIndex, ConfigUsages},
+    contracts::Document,
+    coverage::{CoverageStore, CoverageSummary},
+    dependency_extractor::{DependencyGraph, SerializableDependencyGraph, SymbolNode},
+    embeddings::EmbeddingProvider,
+    error_flows::ErrorFlows,
+    feature_flags::{FlagImpact, FlagIndex, FlagKind, FlagSummary},
+    function_similarity::{function_chunks, FunctionVectorIndex},
+    git::{GitRepository, IngestionOptions},
+    log_statements::{LogIndex, LogMatch},
+    path_utils::{
+        detect_language_from_extension, is_potential_entry_point, is_test_file, paths_equivalent,
+    },
+    project_metadata::ProjectMetadata,
+    pure::metadata::strip_frontmatter,
+    query_deadline::QueryDeadline,
+    relationship_query::{
+        RelationshipMatch, RelationshipQueryConfig, RelationshipQueryResult, RelationshipQueryType,
+        SourceRange,
+    },
+    rename_preview::{self, RecordedDefinition, RecordedReference, RenamePreview},
+    repository_links::RepositoryLinks,
+    review_routing::{
+        parse_unified_diff, rank_reviewers, CodeOwners, CommitRecord, ReviewerSuggestions,
+        CODEOWNERS_PATHS,
+    },
+    runtime_traces::CallProfile,
+    semantic_search::SemanticSearchEngine,
+    services::{document_lookup::documents_by_paths, RedactionRules, RepositoryScope},
+    sql_usages::{SqlIndex, TableUsages},
+    symbol_brief::{BriefCaller, BriefSource, SymbolBrief},
+    symbol_evolution::{EvolutionPoint, SymbolDefinition},
+    trends::{decision_points, is_source_language, MetricsChange, MetricsSnapshot, TrendHistory},
 };
 
-// Simple database access trait for AnalysisService - only needs storage
+// Simple database access trait for AnalysisService - storage plus path lookups
 pub trait AnalysisServiceDatabase: Send + Sync {
     fn storage(&self) -> Arc<Mutex<dyn crate::contracts::Storage>>;
+    fn primary_index(&self) -> Arc<Mutex<dyn crate::contracts::Index>>;
+    fn path_cache(&self) -> Arc<RwLock<HashMap<String, crate::ValidatedDocumentId>>>;
+}
+
+/// Ordering applied to caller and impact results before pagination
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RelationshipSortBy {
+    /// Nearest relationships first (graph distance from the target)
+    #[default]
+    Distance,
+    /// Sort by file path, then line number
+    File,
+    /// Most calls observed in imported runtime traces first, then the highest-risk
+    /// relationship kinds (inheritance, interfaces, calls)
+    Risk,
+}
+
+impl FromStr for RelationshipSortBy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "distance" => Ok(Self::Distance),
+            "file" => Ok(Self::File),
+            "risk" => Ok(Self::Risk),
+            other => Err(anyhow::anyhow!(
+                "Unknown sort '{}'. Expected one of: file, distance, risk",
+                other
+            )),
+        }
+    }
+}
+
+/// How caller and impact results are grouped in the response
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ResultGrouping {
+    /// Single flat list
+    #[default]
+    Flat,
+    /// Additionally group results by file
+    File,
+}
+
+impl FromStr for ResultGrouping {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "flat" => Ok(Self::Flat),
+            "file" => Ok(Self::File),
+            other => Err(anyhow::anyhow!(
+                "Unknown grouping '{}'. Expected one of: flat, file",
+                other
+            )),
+        }
+    }
 }
 
 /// Configuration options for find-callers analysis
@@ -29,7 +123,15 @@ pub trait AnalysisServiceDatabase: Send + Sync {
 pub struct CallersOptions {
     pub target: String,
     pub limit: Option<usize>,
+    /// Number of results to skip after sorting
+    pub offset: usize,
+    pub sort_by: RelationshipSortBy,
+    pub group_by: ResultGrouping,
     pub quiet: bool,
+    /// Capture a query plan explanation alongside the results
+    pub explain: bool,
+    /// Include this many lines of surrounding source with each result (None disables)
+    pub context_lines: Option<usize>,
 }
 
 /// Configuration options for impact analysis
@@ -37,12 +139,219 @@ pub struct CallersOptions {
 pub struct ImpactOptions {
     pub target: String,
     pub limit: Option<usize>,
+    /// Number of results to skip after sorting
+    pub offset: usize,
+    pub sort_by: RelationshipSortBy,
+    pub group_by: ResultGrouping,
+    pub quiet: bool,
+    /// Capture a query plan explanation alongside the results
+    pub explain: bool,
+    /// Include this many lines of surrounding source with each result (None disables)
+    pub context_lines: Option<usize>,
+}
+
+/// Configuration options for related-symbol recommendations
+#[derive(Debug, Clone)]
+pub struct RelatedOptions {
+    pub target: String,
+    /// Maximum symbols and files returned (each)
+    pub limit: usize,
     pub quiet: bool,
 }
 
+impl Default for RelatedOptions {
+    fn default() -> Self {
+        Self {
+            target: String::new(),
+            limit: 10,
+            quiet: false,
+        }
+    }
+}
+
+/// Configuration options for similar-function (code clone) search
+#[derive(Debug, Clone)]
+pub struct SimilarOptions {
+    /// Name of the function or method whose body is compared
+    pub target: String,
+    pub limit: usize,
+    pub quiet: bool,
+}
+
+impl Default for SimilarOptions {
+    fn default() -> Self {
+        Self {
+            target: String::new(),
+            limit: 10,
+            quiet: false,
+        }
+    }
+}
+
+/// Configuration options for symbol neighborhood graphs
+#[derive(Debug, Clone)]
+pub struct GraphOptions {
+    pub target: String,
+    /// Dependency hops followed in each direction
+    pub depth: usize,
+    /// Maximum symbols drawn, including the target
+    pub max_nodes: usize,
+}
+
+impl Default for GraphOptions {
+    fn default() -> Self {
+        Self {
+            target: String::new(),
+            depth: 2,
+            max_nodes: 60,
+        }
+    }
+}
+
+/// Configuration options for the treemap dataset
+#[derive(Debug, Clone)]
+pub struct TreemapOptions {
+    /// Directory the treemap is rooted at (empty for the whole corpus)
+    pub path: String,
+    /// Directory levels expanded below the root; deeper directories become leaves
+    pub depth: usize,
+}
+
+impl Default for TreemapOptions {
+    fn default() -> Self {
+        Self {
+            path: String::new(),
+            depth: 8,
+        }
+    }
+}
+
+/// Configuration options for trend history
+#[derive(Debug, Clone)]
+pub struct TrendsOptions {
+    /// Most recent indexing runs returned
+    pub limit: usize,
+}
+
+impl Default for TrendsOptions {
+    fn default() -> Self {
+        Self { limit: 30 }
+    }
+}
+
+/// Configuration options for hot-path analysis
+#[derive(Debug, Clone)]
+pub struct HotPathsOptions {
+    /// Most symbols returned
+    pub limit: usize,
+}
+
+impl Default for HotPathsOptions {
+    fn default() -> Self {
+        Self { limit: 20 }
+    }
+}
+
+/// Configuration options for error type flow analysis
+#[derive(Debug, Clone)]
+pub struct ErrorFlowOptions {
+    /// Error type name, optionally path-qualified
+    pub error_type: String,
+    /// Most entries per section
+    pub limit: usize,
+}
+
+/// Configuration options for configuration key usages
+#[derive(Debug, Clone)]
+pub struct ConfigUsagesOptions {
+    /// Key in any spelling, e.g. DATABASE_URL, database_url or database.url
+    pub key: String,
+    /// Most sites per section
+    pub limit: usize,
+}
+
+/// Configuration options for table usages
+#[derive(Debug, Clone)]
+pub struct TableUsagesOptions {
+    /// Table name, optionally schema-qualified
+    pub table: String,
+    /// Most statements per section
+    pub limit: usize,
+}
+
+/// Configuration options for reviewer suggestions
+#[derive(Debug, Clone)]
+pub struct SuggestReviewersOptions {
+    /// Unified diff of the change
+    pub diff: String,
+    /// Most reviewers suggested
+    pub limit: usize,
+    /// Reviewers to leave out, e.g. the author of the change (name, email or owner entry)
+    pub exclude: Vec<String>,
+    /// Repository checkout to blame the changed lines in
+    pub repo_path: Option<PathBuf>,
+    /// CODEOWNERS content; defaults to the indexed CODEOWNERS file
+    pub codeowners: Option<String>,
+}
+
+/// Configuration options for listing feature flags
+#[derive(Debug, Clone)]
+pub struct FlagsOptions {
+    /// Most flags listed
+    pub limit: usize,
+}
+
+/// Configuration options for the code controlled by a feature flag
+#[derive(Debug, Clone)]
+pub struct FlagImpactOptions {
+    /// Cargo feature, environment variable or SDK flag key
+    pub flag: String,
+}
+
+/// Configuration options for locating the source of a log line
+#[derive(Debug, Clone)]
+pub struct LocateLogOptions {
+    /// Log line as observed, with or without timestamp, level and fields
+    pub message: String,
+    pub limit: usize,
+}
+
+/// Configuration options for a symbol brief
+#[derive(Debug, Clone)]
+pub struct SymbolBriefOptions {
+    pub target: String,
+    /// Callers listed, most at risk first
+    pub max_callers: usize,
+    /// Approximate tokens the brief may use
+    pub token_budget: usize,
+}
+
+impl Default for SymbolBriefOptions {
+    fn default() -> Self {
+        Self {
+            target: String::new(),
+            max_callers: crate::symbol_brief::DEFAULT_MAX_CALLERS,
+            token_budget: crate::symbol_brief::DEFAULT_TOKEN_BUDGET,
+        }
+    }
+}
+
+/// Configuration options for a rename preview
+#[derive(Debug, Clone, Default)]
+pub struct RenamePreviewOptions {
+    /// Name or qualified name of the symbol to rename
+    pub target: String,
+    /// Replacement for the symbol's bare name
+    pub new_name: String,
+}
+
+/// Output formats of a codebase overview
+pub const OVERVIEW_FORMATS: &[&str] = &["human", "json", "markdown", "yaml"];
+
 /// Configuration options for codebase overview
 #[derive(Debug, Clone, serde::Serialize)]
 pub struct OverviewOptions {
+    /// One of [`OVERVIEW_FORMATS`]
     pub format: String,
     pub top_symbols_limit: usize,
     pub entry_points_limit: usize,
@@ -64,16 +373,257 @@ impl Default for OverviewOptions {
 #[derive(Debug, Clone, serde::Serialize)]
 pub struct CallersResult {
     pub callers: Vec<CallSite>,
+    /// One-line description of the query outcome
+    pub summary: String,
     pub markdown: String,
     pub total_count: usize,
+    /// Number of results available before offset/limit were applied
+    pub total_available: usize,
+    pub offset: usize,
+    /// Cursor for fetching the next page, if more results remain
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub groups: Option<Vec<FileGroup<CallSite>>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub explain: Option<QueryExplanation>,
+    /// Notes, tags and bookmarks left on the target and the returned symbols
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub notes: Vec<Note>,
+    /// The query deadline passed before every caller was found, so results are partial
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub timed_out: bool,
 }
 
 /// Result structure for impact analysis
 #[derive(Debug, Clone, serde::Serialize)]
 pub struct ImpactResult {
     pub impacts: Vec<ImpactSite>,
+    /// One-line description of the query outcome
+    pub summary: String,
     pub markdown: String,
     pub total_count: usize,
+    /// Number of results available before offset/limit were applied
+    pub total_available: usize,
+    pub offset: usize,
+    /// Cursor for fetching the next page, if more results remain
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub groups: Option<Vec<FileGroup<ImpactSite>>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub explain: Option<QueryExplanation>,
+    /// Notes, tags and bookmarks left on the target and the returned symbols
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub notes: Vec<Note>,
+    /// How many impacted symbols tests execute, when coverage was imported
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub coverage: Option<CoverageSummary>,
+    /// The query deadline passed before the traversal finished, so results are partial
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub timed_out: bool,
+}
+
+/// Signals combined into related-symbol recommendations
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RelatedSignal {
+    /// Files changed in the same commits as the symbol's file
+    CoChange,
+    /// Symbols used by the symbol's callers
+    SharedCallers,
+    /// Documents whose embeddings are close to the symbol's source
+    Embedding,
+}
+
+/// Result structure for related-symbol recommendations
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct RelatedResult {
+    pub symbol: String,
+    /// Files defining the symbol
+    pub defined_in: Vec<String>,
+    /// Related symbols, most relevant first
+    pub related: Vec<RelatedSymbol>,
+    /// Related files, most relevant first
+    pub files: Vec<RelatedFile>,
+    /// Signals that contributed, whether or not they found anything
+    pub signals: Vec<RelatedSignal>,
+    /// Signals that failed, with their errors; the others are still combined
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub signal_errors: Vec<String>,
+}
+
+/// A symbol worth looking at alongside the queried one
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct RelatedSymbol {
+    pub name: String,
+    pub file_path: String,
+    pub start_line: u32,
+    pub score: f64,
+    /// Callers of the queried symbol that also use this one
+    pub shared_callers: Vec<String>,
+    /// Commits that changed this symbol's file together with the queried symbol's file
+    #[serde(skip_serializing_if = "is_zero")]
+    pub co_changes: usize,
+    /// Embedding similarity of this symbol's file (0-1)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub similarity: Option<f32>,
+}
+
+/// A file worth looking at alongside the queried symbol
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct RelatedFile {
+    pub path: String,
+    pub score: f64,
+    /// Commits that changed this file together with the queried symbol's file
+    #[serde(skip_serializing_if = "is_zero")]
+    pub co_changes: usize,
+    /// Embedding similarity to the queried symbol's source (0-1)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub similarity: Option<f32>,
+}
+
+/// Result structure for similar-function search
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct SimilarResult {
+    pub symbol: String,
+    pub file_path: String,
+    pub start_line: u32,
+    pub end_line: u32,
+    /// Distinct function bodies in the function index
+    pub functions_indexed: usize,
+    /// Bodies embedded by this query because they were new or changed
+    pub newly_embedded: usize,
+    pub matches: Vec<SimilarFunction>,
+}
+
+/// A function whose body is semantically close to the queried one
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct SimilarFunction {
+    pub name: String,
+    pub file_path: String,
+    pub start_line: u32,
+    pub end_line: u32,
+    /// Cosine similarity of the two bodies' embeddings (0-1)
+    pub similarity: f32,
+}
+
+/// Dependency neighborhood of a symbol, ready to be drawn
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct SymbolGraphResult {
+    pub symbol: String,
+    pub depth: usize,
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<GraphEdge>,
+    /// Whether symbols were left out to stay within `max_nodes`
+    pub truncated: bool,
+}
+
+/// A symbol in a [`SymbolGraphResult`]
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct GraphNode {
+    pub name: String,
+    pub qualified_name: String,
+    pub file_path: String,
+    pub kind: String,
+    /// Hops from the target: negative for dependents, positive for dependencies
+    pub layer: i32,
+}
+
+/// A dependency between two [`GraphNode`]s, by index
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct GraphEdge {
+    /// Index of the dependent node
+    pub from: usize,
+    /// Index of the node depended on
+    pub to: usize,
+    pub relation_type: String,
+}
+
+/// Size, complexity and churn of a file or of all files below a directory
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize)]
+pub struct TreemapMetrics {
+    pub files: usize,
+    /// Bytes
+    pub size: usize,
+    pub lines: usize,
+    /// Symbols extracted during indexing
+    pub symbols: usize,
+    /// Decision points (branch keywords and boolean operators) in source files
+    pub complexity: usize,
+    /// Ingested commits that changed the file
+    pub churn: usize,
+}
+
+impl TreemapMetrics {
+    fn add(&mut self, other: &TreemapMetrics) {
+        self.files += other.files;
+        self.size += other.size;
+        self.lines += other.lines;
+        self.symbols += other.symbols;
+        self.complexity += other.complexity;
+        self.churn += other.churn;
+    }
+
+    fn max(&mut self, other: &TreemapMetrics) {
+        self.files = self.files.max(other.files);
+        self.size = self.size.max(other.size);
+        self.lines = self.lines.max(other.lines);
+        self.symbols = self.symbols.max(other.symbols);
+        self.complexity = self.complexity.max(other.complexity);
+        self.churn = self.churn.max(other.churn);
+    }
+}
+
+/// Directory or file in the treemap hierarchy
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct TreemapNode {
+    pub name: String,
+    pub path: String,
+    /// "file" or "directory"
+    #[serde(rename = "type")]
+    pub node_type: &'static str,
+    /// Leaf weight for d3's `hierarchy().sum(d => d.value)`: bytes, set on leaves only
+    /// so every file is counted once
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language: Option<&'static str>,
+    pub metrics: TreemapMetrics,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<TreemapNode>,
+}
+
+/// Hierarchical size/complexity/churn dataset for treemap and heatmap rendering
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct TreemapResult {
+    pub root: TreemapNode,
+    /// Largest value of each metric over the leaves, for scaling colors
+    pub leaf_max: TreemapMetrics,
+    /// Ingested commits churn was counted from
+    pub commits_analyzed: usize,
+}
+
+fn is_zero(value: &usize) -> bool {
+    *value == 0
+}
+
+/// Commits touching more files than this (bulk renames, initial imports) say
+/// nothing about which files belong together and are ignored for co-change
+const MAX_CO_CHANGE_COMMIT_FILES: usize = 50;
+
+/// Changed symbols whose impact is analyzed when suggesting reviewers
+const MAX_REVIEW_IMPACT_SYMBOLS: usize = 20;
+
+/// Lines of the symbol's definition embedded for the similarity signal
+const RELATED_SOURCE_LINES: usize = 10;
+
+/// Results belonging to a single file when grouping by file
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct FileGroup<T> {
+    pub file_path: String,
+    pub count: usize,
+    pub items: Vec<T>,
 }
 
 /// Result structure for codebase overview
@@ -83,29 +633,294 @@ pub struct OverviewResult {
     pub formatted_output: String,
 }
 
+/// Metrics of recent indexing runs
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct TrendsResult {
+    /// Oldest first
+    pub snapshots: Vec<MetricsSnapshot>,
+    /// Newest minus oldest snapshot, when there are at least two
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub change: Option<MetricsChange>,
+}
+
+/// Log statements that could have emitted an observed line
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct LocateLogResult {
+    /// Best match first
+    pub matches: Vec<LogMatch>,
+    /// When the log statements were indexed
+    pub indexed_at: String,
+}
+
+impl LocateLogResult {
+    /// One line per candidate statement: location, enclosing symbol, level and format
+    pub fn to_markdown(&self) -> String {
+        if self.matches.is_empty() {
+            return "No log statement matches this line.\n".to_string();
+        }
+        let mut output = String::new();
+        for m in &self.matches {
+            let statement = &m.statement;
+            output.push_str(&format!(
+                "- {}:{}{} [{}] \"{}\"\n",
+                statement.file_path,
+                statement.line,
+                statement
+                    .symbol
+                    .as_ref()
+                    .map(|symbol| format!(" in `{}`", symbol))
+                    .unwrap_or_default(),
+                statement.level,
+                statement.format
+            ));
+        }
+        output
+    }
+}
+
+/// Feature flags of the indexed code with usage counts
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct FlagsResult {
+    /// Most used first
+    pub flags: Vec<FlagSummary>,
+    /// When the flag usages were indexed
+    pub indexed_at: String,
+}
+
+impl FlagsResult {
+    /// Table of flags with their kinds, usage and file counts
+    pub fn to_markdown(&self) -> String {
+        if self.flags.is_empty() {
+            return "No feature flags found in the indexed code.\n".to_string();
+        }
+        let mut output = String::from(
+            "| Flag | Kind | Usages | Files | Gated files |\n|---|---|---:|---:|---:|\n",
+        );
+        for flag in &self.flags {
+            let kinds: Vec<&str> = flag.kinds.iter().map(FlagKind::as_str).collect();
+            output.push_str(&format!(
+                "| {} | {} | {} | {} | {} |\n",
+                flag.flag,
+                kinds.join(", "),
+                flag.usages,
+                flag.files,
+                flag.gated_files
+            ));
+        }
+        output
+    }
+}
+
+/// How hot paths were ranked
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HotPathRanking {
+    /// Calls observed in imported runtime traces
+    ObservedCalls,
+    /// Number of static callers in the dependency graph
+    StaticCallers,
+}
+
+/// A frequently used symbol
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct HotSymbol {
+    pub qualified_name: String,
+    pub file_path: String,
+    /// Callers found by static analysis
+    pub static_callers: usize,
+    /// Calls observed in imported runtime traces
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub observed_calls: Option<u64>,
+}
+
+/// The most used symbols of the codebase
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct HotPathsResult {
+    pub ranked_by: HotPathRanking,
+    /// Trace the observed calls were imported from
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profile_source: Option<String>,
+    pub symbols: Vec<HotSymbol>,
+}
+
+impl HotPathsResult {
+    /// Table of the hot symbols, hottest first
+    pub fn to_markdown(&self) -> String {
+        if self.symbols.is_empty() {
+            return "No hot paths found. Index a codebase with relationship extraction first.\n"
+                .to_string();
+        }
+        let mut output = match (&self.ranked_by, &self.profile_source) {
+            (HotPathRanking::ObservedCalls, Some(source)) => {
+                format!("Ranked by calls observed in `{}`\n\n", source)
+            }
+            _ => "Ranked by static callers; run `kotadb import-traces <trace>` to rank by \
+                  observed calls\n\n"
+                .to_string(),
+        };
+        output
+            .push_str("| Symbol | File | Observed calls | Static callers |\n|---|---|---:|---:|\n");
+        for symbol in &self.symbols {
+            output.push_str(&format!(
+                "| {} | {} | {} | {} |\n",
+                symbol
+                    .qualified_name
+                    .rsplit("::")
+                    .next()
+                    .unwrap_or_default(),
+                symbol.file_path,
+                symbol
+                    .observed_calls
+                    .map_or_else(|| "-".to_string(), |calls| calls.to_string()),
+                symbol.static_callers
+            ));
+        }
+        output
+    }
+}
+
+impl TrendsResult {
+    /// Table of the snapshots followed by the overall change
+    pub fn to_markdown(&self) -> String {
+        if self.snapshots.is_empty() {
+            return "No indexing runs recorded yet. Run `kotadb index-codebase` to record one.\n"
+                .to_string();
+        }
+        let mut output = String::from(
+            "| Recorded | Files | Lines | Symbols | Relationships | Cycles | Unused | Avg complexity |\n\
+             |---|---:|---:|---:|---:|---:|---:|---:|\n",
+        );
+        for snapshot in &self.snapshots {
+            output.push_str(&format!(
+                "| {} | {} | {} | {} | {} | {} | {} | {:.2} |\n",
+                snapshot.recorded_at,
+                snapshot.files,
+                snapshot.lines,
+                snapshot.symbols,
+                snapshot.relationships,
+                snapshot.cycles,
+                snapshot.unused_symbols,
+                snapshot.avg_complexity
+            ));
+        }
+        if let Some(change) = &self.change {
+            output.push_str(&format!(
+                "\nChange over {} runs: files {:+}, lines {:+}, symbols {:+}, relationships {:+}, \
+                 cycles {:+}, unused {:+}, avg complexity {:+.2}\n",
+                self.snapshots.len(),
+                change.files,
+                change.lines,
+                change.symbols,
+                change.relationships,
+                change.cycles,
+                change.unused_symbols,
+                change.avg_complexity
+            ));
+        }
+        output
+    }
+}
+
+/// A symbol nothing in the dependency graph depends on
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct UnusedSymbol {
+    pub qualified_name: String,
+    pub kind: String,
+    pub file_path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start_line: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end_line: Option<u32>,
+}
+
 /// Individual call site information
 #[derive(Debug, Clone, serde::Serialize)]
 pub struct CallSite {
     pub caller: String,
+    pub qualified_name: String,
     pub file_path: String,
     pub line_number: Option<u32>,
+    pub column_number: Option<u32>,
+    pub relation_type: String,
     pub context: String,
+    /// Source text of the call site, when the dependency graph recorded it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub snippet: Option<String>,
+    /// Exact source range of the call site, when recorded during indexing
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub range: Option<SourceRange>,
+    /// Lines surrounding the call site, when requested via `context_lines`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub excerpt: Option<SourceExcerpt>,
+    /// Calls from this caller to the target observed in imported runtime traces
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub observed_calls: Option<u64>,
+    /// Commit-pinned link to the call site on its forge, when the repository's remote is known
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub web_url: Option<String>,
 }
 
 /// Individual impact site information
 #[derive(Debug, Clone, serde::Serialize)]
 pub struct ImpactSite {
     pub affected_symbol: String,
+    pub qualified_name: String,
     pub file_path: String,
     pub line_number: Option<u32>,
     pub impact_type: String,
+    pub relation_type: String,
+    /// Graph distance from the changed symbol (1 = direct dependent)
+    pub depth: usize,
+    /// Source range of the affected symbol's definition, when recorded during indexing
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub range: Option<SourceRange>,
+    /// Lines surrounding the affected symbol, when requested via `context_lines`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub excerpt: Option<SourceExcerpt>,
+    /// Percentage of the affected symbol's lines executed by tests, when coverage was imported
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub coverage: Option<f64>,
+    /// Calls of the affected symbol observed in imported runtime traces
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub observed_calls: Option<u64>,
+}
+
+/// A few lines of source read from storage around a result location
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct SourceExcerpt {
+    /// First line included (1-based)
+    pub start_line: usize,
+    /// Last line included (1-based)
+    pub end_line: usize,
+    pub text: String,
 }
 
+/// Upper bound on `context_lines` so a single query can't pull whole files
+const MAX_CONTEXT_LINES: usize = 20;
+
 /// Unified analysis service that handles relationship queries and codebase intelligence
 pub struct AnalysisService<'a> {
     database: &'a dyn AnalysisServiceDatabase,
     db_path: PathBuf,
     relationship_engine: Option<BinaryRelationshipEngine>,
+    /// Engine shared across services whose result cache answers caller and impact queries
+    shared_engine: Option<AsyncBinaryRelationshipEngine>,
+    /// Embedding search used for the similarity signal of related symbols
+    semantic_engine: Option<Arc<Mutex<SemanticSearchEngine>>>,
+    /// Embeds function bodies for similar-function search
+    embedding_provider: Option<Arc<dyn EmbeddingProvider>>,
+    /// Directory of the annotation store whose notes are attached to results
+    annotations_dir: PathBuf,
+    /// Annotation store, loaded on the first relationship query
+    annotation_store: Option<AnnotationStore>,
+    /// Repository that results are restricted to
+    scope: Option<RepositoryScope>,
+    /// Cargo crate whose files results are restricted to
+    crate_scope: Option<CargoCrate>,
+    /// Paths hidden from the caller
+    redaction: RedactionRules,
+    /// When caller and impact queries stop and return partial results
+    deadline: QueryDeadline,
 }
 
 impl<'a> AnalysisService<'a> {
@@ -129,17 +944,28 @@ impl<'a> AnalysisService<'a> {
     ) -> CallSite {
         CallSite {
             caller: relationship.symbol_name.clone(),
+            qualified_name: relationship.qualified_name.clone(),
             file_path: relationship.file_path.clone(),
             line_number: Self::safe_line_number_conversion(
                 relationship.location.line_number,
                 &relationship.symbol_name,
             ),
+            column_number: Self::safe_line_number_conversion(
+                relationship.location.column_number,
+                &relationship.symbol_name,
+            ),
+            relation_type: Self::relation_type_name(&relationship.relation_type),
             context: format!(
                 "{} {} at line {}",
                 Self::get_caller_verb(&relationship.relation_type),
                 target,
                 relationship.location.line_number
             ),
+            snippet: relationship.snippet.clone(),
+            range: relationship.location.range(),
+            excerpt: None,
+            observed_calls: None,
+            web_url: None,
         }
     }
 
@@ -149,13 +975,119 @@ impl<'a> AnalysisService<'a> {
     ) -> ImpactSite {
         ImpactSite {
             affected_symbol: relationship.symbol_name.clone(),
+            qualified_name: relationship.qualified_name.clone(),
             file_path: relationship.file_path.clone(),
             line_number: Self::safe_line_number_conversion(
                 relationship.location.line_number,
                 &relationship.symbol_name,
             ),
             impact_type: Self::format_impact_type(&relationship.relation_type),
+            relation_type: Self::relation_type_name(&relationship.relation_type),
+            depth: relationship.depth,
+            range: relationship.location.range(),
+            excerpt: None,
+            coverage: None,
+            observed_calls: None,
+        }
+    }
+
+    /// Stable lowercase name for a relationship kind in structured output
+    pub(crate) fn relation_type_name(relation_type: &crate::types::RelationType) -> String {
+        use crate::types::RelationType;
+        match relation_type {
+            RelationType::Calls => "calls".to_string(),
+            RelationType::Imports => "imports".to_string(),
+            RelationType::Extends => "extends".to_string(),
+            RelationType::Implements => "implements".to_string(),
+            RelationType::References => "references".to_string(),
+            RelationType::Returns => "returns".to_string(),
+            RelationType::ChildOf => "child_of".to_string(),
+            RelationType::Custom(custom) => custom.clone(),
+        }
+    }
+
+    /// Relative risk of a relationship kind, used when sorting by risk
+    fn relation_risk(relation_type: &crate::types::RelationType) -> u8 {
+        use crate::types::RelationType;
+        match relation_type {
+            RelationType::Extends | RelationType::Implements => 4,
+            RelationType::Calls => 3,
+            RelationType::Returns => 2,
+            RelationType::Imports | RelationType::References | RelationType::ChildOf => 1,
+            RelationType::Custom(_) => 0,
+        }
+    }
+
+    /// Sort relationships and apply offset/limit, returning the pre-pagination
+    /// total and a cursor for the next page. `observed_calls` weights risk ordering
+    /// with runtime trace counts.
+    fn order_and_paginate(
+        result: &mut RelationshipQueryResult,
+        sort_by: RelationshipSortBy,
+        offset: usize,
+        limit: Option<usize>,
+        observed_calls: impl Fn(&RelationshipMatch) -> Option<u64>,
+    ) -> (usize, Option<String>) {
+        match sort_by {
+            RelationshipSortBy::Distance => result.direct_relationships.sort_by_key(|r| r.depth),
+            RelationshipSortBy::File => result.direct_relationships.sort_by(|a, b| {
+                a.file_path
+                    .cmp(&b.file_path)
+                    .then(a.location.line_number.cmp(&b.location.line_number))
+            }),
+            RelationshipSortBy::Risk => result.direct_relationships.sort_by_key(|r| {
+                std::cmp::Reverse((
+                    observed_calls(r).unwrap_or(0),
+                    Self::relation_risk(&r.relation_type),
+                ))
+            }),
+        }
+
+        let total_available = result.direct_relationships.len();
+        if offset > 0 {
+            let skip = offset.min(total_available);
+            result.direct_relationships.drain(..skip);
+            result.stats.direct_count = result.direct_relationships.len();
+        }
+
+        // Apply limit if specified (0 means unlimited)
+        if let Some(limit_value) = limit {
+            if limit_value > 0 {
+                result.limit_results(limit_value);
+            }
+        }
+
+        let next_offset = offset + result.direct_relationships.len();
+        let next_cursor = (next_offset < total_available).then(|| next_offset.to_string());
+        (total_available, next_cursor)
+    }
+
+    /// Decode a pagination cursor returned in `next_cursor`
+    pub fn parse_cursor(cursor: &str) -> Result<usize> {
+        cursor
+            .trim()
+            .parse::<usize>()
+            .map_err(|_| anyhow::anyhow!("Invalid cursor '{}'", cursor))
+    }
+
+    /// Group items by file path, preserving the order in which files first appear
+    fn group_by_file<T: Clone>(items: &[T], file_path: impl Fn(&T) -> &str) -> Vec<FileGroup<T>> {
+        let mut groups: Vec<FileGroup<T>> = Vec::new();
+        let mut positions: HashMap<String, usize> = HashMap::new();
+        for item in items {
+            let path = file_path(item);
+            let idx = *positions.entry(path.to_string()).or_insert_with(|| {
+                groups.push(FileGroup {
+                    file_path: path.to_string(),
+                    count: 0,
+                    items: Vec::new(),
+                });
+                groups.len() - 1
+            });
+            groups[idx].count += 1;
+            groups[idx].items.push(item.clone());
         }
+        groups
     }
 
     /// Get appropriate verb for caller relationship types
@@ -213,9 +1145,103 @@ impl<'a> AnalysisService<'a> {
     pub fn new(database: &'a dyn AnalysisServiceDatabase, db_path: PathBuf) -> Self {
         Self {
             database,
+            annotations_dir: db_path.clone(),
             db_path,
             relationship_engine: None,
+            shared_engine: None,
+            semantic_engine: None,
+            embedding_provider: None,
+            annotation_store: None,
+            scope: None,
+            crate_scope: None,
+            redaction: RedactionRules::default(),
+            deadline: QueryDeadline::none(),
+        }
+    }
+
+    /// Attach notes from `dir` instead of the database directory
+    pub fn with_annotations_dir(mut self, dir: PathBuf) -> Self {
+        self.annotations_dir = dir;
+        self
+    }
+
+    /// Use embedding similarity when recommending related symbols
+    pub fn with_semantic_engine(mut self, engine: Arc<Mutex<SemanticSearchEngine>>) -> Self {
+        self.semantic_engine = Some(engine);
+        self
+    }
+
+    /// Embed function bodies with `provider` for similar-function search
+    pub fn with_embedding_provider(mut self, provider: Arc<dyn EmbeddingProvider>) -> Self {
+        self.embedding_provider = Some(provider);
+        self
+    }
+
+    /// Only report relationships, symbols and files within the scoped repositories
+    pub fn with_repository_scope(mut self, scope: RepositoryScope) -> Self {
+        self.scope = Some(scope);
+        self
+    }
+
+    /// Only return results defined in the files of one Cargo crate
+    pub fn with_crate(mut self, krate: CargoCrate) -> Self {
+        self.crate_scope = Some(krate);
+        self
+    }
+
+    /// Leave relationships, symbols and files at redacted paths out of every result
+    pub fn with_redaction(mut self, redaction: RedactionRules) -> Self {
+        self.redaction = redaction;
+        self
+    }
+
+    /// Answer caller and impact queries with a shared engine, from its result cache
+    /// when the same query already ran at the current graph generation
+    pub fn with_relationship_engine(mut self, engine: AsyncBinaryRelationshipEngine) -> Self {
+        self.shared_engine = Some(engine);
+        self
+    }
+
+    /// Stop caller and impact queries at `deadline`, returning what was found with
+    /// `timed_out` set
+    pub fn with_deadline(mut self, deadline: QueryDeadline) -> Self {
+        self.deadline = deadline;
+        self
+    }
+
+    fn in_scope(&self, path: &str) -> bool {
+        self.scope
+            .as_ref()
+            .is_none_or(|scope| scope.contains_path(path))
+            && self
+                .crate_scope
+                .as_ref()
+                .is_none_or(|krate| krate.contains(path))
+            && self.redaction.allows(path)
+    }
+
+    /// Drop relationships found outside the scoped repository or crate, or at redacted
+    /// paths, before pagination
+    ///
+    /// Indirect call paths only name symbols, so scoped results leave them out.
+    fn retain_in_scope(&self, result: &mut RelationshipQueryResult) {
+        if self.scope.is_none() && self.crate_scope.is_none() && self.redaction.is_empty() {
+            return;
         }
+        result
+            .direct_relationships
+            .retain(|relationship| self.in_scope(&relationship.file_path));
+        result.indirect_relationships.clear();
+        result.stats.direct_count = result.direct_relationships.len();
+        result.stats.indirect_count = 0;
+    }
+
+    /// Call counts imported from runtime traces, if any
+    fn call_profile(&self) -> Option<CallProfile> {
+        CallProfile::load(&self.db_path).unwrap_or_else(|e| {
+            tracing::warn!("Ignoring unreadable call profile: {e:#}");
+            None
+        })
     }
 
     /// Create or get the relationship engine, initializing if needed
@@ -234,9 +1260,13 @@ impl<'a> AnalysisService<'a> {
         let config = RelationshipQueryConfig::default();
         let binary_engine = BinaryRelationshipEngine::new(&self.db_path, config).await?;
 
-        // Check if we have any symbols or relationships loaded
-        let stats = binary_engine.get_stats();
-        if !stats.using_binary_path && stats.binary_symbols_loaded == 0 {
+        Self::ensure_symbols_loaded(&binary_engine.get_stats())?;
+        Ok(binary_engine)
+    }
+
+    /// Check that the engine has any symbols or relationships loaded
+    fn ensure_symbols_loaded(stats: &BinaryEngineStats) -> Result<()> {
+        if !stats.using_binary_path && stats.binary_symbols_loaded == 0 {
             return Err(anyhow::anyhow!(
                 "No symbols found in database. Required steps:\n\
                  1. Index a codebase: kotadb index-codebase /path/to/repo\n\
@@ -244,73 +1274,588 @@ impl<'a> AnalysisService<'a> {
                  3. Then retry this command"
             ));
         }
+        Ok(())
+    }
 
-        Ok(binary_engine)
+    /// Run a caller or impact query on the shared engine if there is one, else on
+    /// this service's own engine
+    async fn query_relationships(
+        &mut self,
+        query_type: RelationshipQueryType,
+    ) -> Result<(RelationshipQueryResult, QueryExplanation)> {
+        let deadline = self.deadline;
+        match &self.shared_engine {
+            Some(engine) => {
+                Self::ensure_symbols_loaded(&engine.engine_stats().await)?;
+                engine
+                    .execute_query_with_deadline(query_type, deadline)
+                    .await
+            }
+            None => {
+                self.get_relationship_engine()
+                    .await?
+                    .execute_query_with_deadline(query_type, deadline)
+                    .await
+            }
+        }
     }
 
     /// Find callers of a specific symbol using the same logic as CLI FindCallers command
     pub async fn find_callers(&mut self, options: CallersOptions) -> Result<CallersResult> {
-        let engine = self.get_relationship_engine().await?;
+        let deadline = self.deadline;
         let query_type = RelationshipQueryType::FindCallers {
             target: options.target.clone(),
         };
 
-        let mut result = engine.execute_query(query_type).await?;
-
-        // Apply limit if specified (0 means unlimited)
-        if let Some(limit_value) = options.limit {
-            if limit_value > 0 {
-                result.limit_results(limit_value);
-            }
-        }
+        let (mut result, explanation) = self.query_relationships(query_type).await?;
+        let mut timed_out = explanation.timed_out;
+        self.retain_in_scope(&mut result);
+        let profile = self.call_profile();
+        let observed_calls = |r: &RelationshipMatch| {
+            profile
+                .as_ref()
+                .and_then(|p| p.edge_calls(&r.qualified_name, &options.target))
+        };
+        let (total_available, next_cursor) = Self::order_and_paginate(
+            &mut result,
+            options.sort_by,
+            options.offset,
+            options.limit,
+            observed_calls,
+        );
 
         let markdown = result.to_markdown();
 
         // Extract call sites from the relationship query result with semantic mapping
-        let callers: Vec<CallSite> = result
+        let mut callers: Vec<CallSite> = result
             .direct_relationships
             .iter()
-            .map(|relationship| Self::relationship_to_call_site(relationship, &options.target))
+            .map(|relationship| CallSite {
+                observed_calls: observed_calls(relationship),
+                ..Self::relationship_to_call_site(relationship, &options.target)
+            })
             .collect();
+        // Excerpts are left out once the deadline passed
+        if let Some(context_lines) = options.context_lines.filter(|_| !deadline.expired()) {
+            let locations: Vec<(String, Option<u32>)> = callers
+                .iter()
+                .map(|c| (c.file_path.clone(), c.line_number))
+                .collect();
+            let excerpts = self.read_excerpts(&locations, context_lines).await?;
+            for (caller, excerpt) in callers.iter_mut().zip(excerpts) {
+                caller.excerpt = excerpt;
+            }
+        } else if options.context_lines.is_some() {
+            timed_out = true;
+        }
+        match RepositoryLinks::load(&self.db_path) {
+            Ok(links) => {
+                for caller in &mut callers {
+                    let lines = caller.line_number.map(|line| (line, line));
+                    caller.web_url = links.web_url(&caller.file_path, lines);
+                }
+            }
+            Err(e) => tracing::warn!("Ignoring unreadable repository links: {e:#}"),
+        }
         let total_count = callers.len();
+        let groups = (options.group_by == ResultGrouping::File)
+            .then(|| Self::group_by_file(&callers, |c| c.file_path.as_str()));
+        let notes = self.notes_for(
+            &options.target,
+            callers
+                .iter()
+                .map(|c| (c.caller.as_str(), c.file_path.as_str(), c.line_number)),
+        );
 
         Ok(CallersResult {
             callers,
+            summary: result.summary,
             markdown,
             total_count,
+            total_available,
+            offset: options.offset,
+            next_cursor,
+            groups,
+            explain: options.explain.then_some(explanation),
+            notes,
+            timed_out,
         })
     }
 
     /// Analyze impact of changes to a specific symbol using CLI AnalyzeImpact logic
     pub async fn analyze_impact(&mut self, options: ImpactOptions) -> Result<ImpactResult> {
-        let engine = self.get_relationship_engine().await?;
+        let deadline = self.deadline;
         let query_type = RelationshipQueryType::ImpactAnalysis {
             target: options.target.clone(),
         };
 
-        let mut result = engine.execute_query(query_type).await?;
+        let (mut result, explanation) = self.query_relationships(query_type).await?;
+        let mut timed_out = explanation.timed_out;
+        self.retain_in_scope(&mut result);
 
-        // Apply limit if specified (0 means unlimited)
-        if let Some(limit_value) = options.limit {
-            if limit_value > 0 {
-                result.limit_results(limit_value);
+        // Coverage covers every impacted symbol, not just the returned page
+        let coverage_store = match CoverageStore::load(&self.db_path) {
+            Ok(store) => store,
+            Err(e) => {
+                tracing::warn!("Ignoring unreadable coverage: {e:#}");
+                None
             }
-        }
+        };
+        let coverage = coverage_store.as_ref().map(|store| {
+            store.summarize(result.direct_relationships.iter().map(|r| {
+                (
+                    r.symbol_name.as_str(),
+                    r.file_path.as_str(),
+                    u32::try_from(r.location.line_number).ok(),
+                )
+            }))
+        });
+        let profile = self.call_profile();
+        let observed_calls = |r: &RelationshipMatch| {
+            profile
+                .as_ref()
+                .and_then(|p| p.symbol_calls(&r.qualified_name))
+        };
+        let (total_available, next_cursor) = Self::order_and_paginate(
+            &mut result,
+            options.sort_by,
+            options.offset,
+            options.limit,
+            observed_calls,
+        );
 
-        let markdown = result.to_markdown();
+        let mut markdown = result.to_markdown();
+        let mut summary = result.summary;
+        if let Some(coverage) = coverage.as_ref().filter(|c| c.measured > 0) {
+            let untested = format!(
+                "{}% of impacted symbols are untested ({} of {} with coverage data)",
+                coverage.untested_percent, coverage.untested, coverage.measured
+            );
+            markdown.push_str(&format!("\n**Test coverage:** {}\n", untested));
+            summary = format!("{}; {}", summary, untested);
+        }
 
         // Extract impact sites from the relationship query result with semantic mapping
-        let impacts: Vec<ImpactSite> = result
+        let mut impacts: Vec<ImpactSite> = result
             .direct_relationships
             .iter()
-            .map(Self::relationship_to_impact_site)
+            .map(|relationship| ImpactSite {
+                observed_calls: observed_calls(relationship),
+                ..Self::relationship_to_impact_site(relationship)
+            })
             .collect();
+        if let Some(store) = &coverage_store {
+            for impact in &mut impacts {
+                impact.coverage = store
+                    .lookup(
+                        &impact.affected_symbol,
+                        &impact.file_path,
+                        impact.line_number,
+                    )
+                    .map(|symbol| symbol.percent());
+            }
+        }
+        // Excerpts are left out once the deadline passed
+        if let Some(context_lines) = options.context_lines.filter(|_| !deadline.expired()) {
+            let locations: Vec<(String, Option<u32>)> = impacts
+                .iter()
+                .map(|i| (i.file_path.clone(), i.line_number))
+                .collect();
+            let excerpts = self.read_excerpts(&locations, context_lines).await?;
+            for (impact, excerpt) in impacts.iter_mut().zip(excerpts) {
+                impact.excerpt = excerpt;
+            }
+        } else if options.context_lines.is_some() {
+            timed_out = true;
+        }
         let total_count = impacts.len();
+        let groups = (options.group_by == ResultGrouping::File)
+            .then(|| Self::group_by_file(&impacts, |i| i.file_path.as_str()));
+        let notes = self.notes_for(
+            &options.target,
+            impacts.iter().map(|i| {
+                (
+                    i.affected_symbol.as_str(),
+                    i.file_path.as_str(),
+                    i.line_number,
+                )
+            }),
+        );
 
         Ok(ImpactResult {
             impacts,
+            summary,
             markdown,
             total_count,
+            total_available,
+            offset: options.offset,
+            next_cursor,
+            groups,
+            explain: options.explain.then_some(explanation),
+            notes,
+            coverage,
+            timed_out,
+        })
+    }
+
+    /// Notes on `target` and on the symbols and lines of relationship results
+    ///
+    /// Sites are `(symbol, file, line)`. Best effort: an unreadable annotation
+    /// store contributes nothing.
+    fn notes_for<'s>(
+        &mut self,
+        target: &str,
+        sites: impl Iterator<Item = (&'s str, &'s str, Option<u32>)> + Clone,
+    ) -> Vec<Note> {
+        let annotations_dir = &self.annotations_dir;
+        let store = self.annotation_store.get_or_insert_with(|| {
+            AnnotationStore::load(annotations_dir).unwrap_or_else(|e| {
+                tracing::warn!("Ignoring unreadable annotations: {e:#}");
+                AnnotationStore::default()
+            })
+        });
+        let symbols = std::iter::once(target).chain(sites.clone().map(|(symbol, _, _)| symbol));
+        let files = sites.map(|(_, file, line)| {
+            (
+                file,
+                line.map(|line| LineRange {
+                    start: line,
+                    end: line,
+                }),
+            )
+        });
+        store.notes_for_results(files, symbols, MAX_NOTES_PER_RESULT)
+    }
+
+    /// Recommend symbols and files to look at when modifying a symbol
+    ///
+    /// Combines three signals: symbols used by the symbol's callers (weighted by
+    /// the share of callers using them), files changed in the same commits as the
+    /// symbol's file (weighted by the share of those commits), and, when an
+    /// embedding engine is configured, documents similar to the symbol's source.
+    /// Related symbols whose file also co-changes or is similar rank higher. A
+    /// failing signal is reported in `signal_errors` instead of failing the query.
+    pub async fn find_related(&mut self, options: RelatedOptions) -> Result<RelatedResult> {
+        let target = options.target.trim().to_string();
+        let engine = self.get_relationship_engine().await?;
+        let mut locations = engine.symbol_locations(&target);
+        let shared_callees = engine.find_shared_callees(&target).await;
+        locations.retain(|(path, _)| self.in_scope(path));
+        if locations.is_empty() {
+            return Err(anyhow::anyhow!("Symbol '{}' not found", target));
+        }
+
+        let mut defined_in: Vec<String> = Vec::new();
+        for (path, _) in &locations {
+            if !defined_in.contains(path) {
+                defined_in.push(path.clone());
+            }
+        }
+        let mut signals = Vec::new();
+        let mut signal_errors = Vec::new();
+        let mut files: Vec<RelatedFile> = Vec::new();
+
+        let documents = self
+            .database
+            .storage()
+            .lock_timed(LockedResource::Storage)
+            .await
+            .list_all()
+            .await?;
+        let commits: Vec<Vec<String>> = documents.iter().filter_map(commit_changed_files).collect();
+        if !commits.is_empty() {
+            signals.push(RelatedSignal::CoChange);
+            for (path, co_changes, ratio) in co_changed_files(&commits, &defined_in) {
+                files.push(RelatedFile {
+                    path,
+                    score: ratio,
+                    co_changes,
+                    similarity: None,
+                });
+            }
+        }
+
+        if let Some(semantic_engine) = self.semantic_engine.clone() {
+            let (path, line) = &locations[0];
+            let source = self
+                .read_excerpts(&[(path.clone(), Some(*line))], RELATED_SOURCE_LINES)
+                .await?
+                .pop()
+                .flatten()
+                .map(|excerpt| excerpt.text)
+                .unwrap_or_else(|| target.clone());
+            let engine = semantic_engine.lock().await;
+            match engine
+                .semantic_search(&source, options.limit + defined_in.len(), None)
+                .await
+            {
+                Ok(similar) => {
+                    signals.push(RelatedSignal::Embedding);
+                    for scored in similar {
+                        let path = scored.document.path.as_str();
+                        if defined_in.iter().any(|own| paths_equivalent(own, path)) {
+                            continue;
+                        }
+                        let similarity = 1.0 / (1.0 + scored.semantic_score.max(0.0));
+                        match files.iter_mut().find(|f| paths_equivalent(&f.path, path)) {
+                            Some(file) => {
+                                file.similarity = Some(similarity);
+                                file.score += similarity as f64;
+                            }
+                            None => files.push(RelatedFile {
+                                path: path.to_string(),
+                                score: similarity as f64,
+                                co_changes: 0,
+                                similarity: Some(similarity),
+                            }),
+                        }
+                    }
+                }
+                Err(e) => signal_errors.push(format!("embedding: {e:#}")),
+            }
+        }
+
+        let mut related = Vec::new();
+        match shared_callees {
+            Ok(shared) => {
+                signals.push(RelatedSignal::SharedCallers);
+                let total_callers = shared.total_callers.max(1) as f64;
+                for callee in shared.callees {
+                    let file = files
+                        .iter()
+                        .find(|f| paths_equivalent(&f.path, &callee.file_path));
+                    related.push(RelatedSymbol {
+                        score: callee.callers.len() as f64 / total_callers
+                            + file.map_or(0.0, |f| f.score / 2.0),
+                        co_changes: file.map_or(0, |f| f.co_changes),
+                        similarity: file.and_then(|f| f.similarity),
+                        name: callee.name,
+                        file_path: callee.file_path,
+                        start_line: callee.start_line,
+                        shared_callers: callee.callers,
+                    });
+                }
+            }
+            Err(e) => signal_errors.push(format!("shared_callers: {e:#}")),
+        }
+
+        related.retain(|symbol| self.in_scope(&symbol.file_path));
+        files.retain(|file| self.in_scope(&file.path));
+        related.sort_by(|a, b| {
+            b.score
+                .partial_cmp(&a.score)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then_with(|| a.name.cmp(&b.name))
+        });
+        related.truncate(options.limit);
+        files.sort_by(|a, b| {
+            b.score
+                .partial_cmp(&a.score)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then_with(|| a.path.cmp(&b.path))
+        });
+        files.truncate(options.limit);
+
+        Ok(RelatedResult {
+            symbol: target,
+            defined_in,
+            related,
+            files,
+            signals,
+            signal_errors,
+        })
+    }
+
+    /// Dependency neighborhood of a symbol for visualization
+    ///
+    /// Follows dependents and dependencies up to `depth` hops from every symbol
+    /// with the target's name; see [`SymbolGraphResult::to_svg`] for rendering.
+    pub async fn symbol_graph(&mut self, options: GraphOptions) -> Result<SymbolGraphResult> {
+        let target = options.target.trim().to_string();
+        let engine = self.get_relationship_engine().await?;
+        let neighborhood = engine
+            .symbol_neighborhood(&target, options.depth, options.max_nodes)
+            .await?;
+
+        // Out-of-scope nodes are dropped and the edges renumbered to the kept ones
+        let mut kept = Vec::with_capacity(neighborhood.nodes.len());
+        let mut nodes = Vec::new();
+        for node in neighborhood.nodes {
+            if !self.in_scope(&node.file_path) {
+                kept.push(None);
+                continue;
+            }
+            kept.push(Some(nodes.len()));
+            nodes.push(GraphNode {
+                name: node.name,
+                qualified_name: node.qualified_name,
+                file_path: node.file_path,
+                kind: node.symbol_type.to_string(),
+                layer: node.layer,
+            });
+        }
+        if (self.scope.is_some() || !self.redaction.is_empty())
+            && !nodes.iter().any(|node| node.layer == 0)
+        {
+            return Err(anyhow::anyhow!("Symbol '{}' not found", target));
+        }
+
+        Ok(SymbolGraphResult {
+            symbol: target,
+            depth: options.depth,
+            nodes,
+            edges: neighborhood
+                .edges
+                .into_iter()
+                .filter_map(|edge| {
+                    Some(GraphEdge {
+                        from: kept.get(edge.from).copied().flatten()?,
+                        to: kept.get(edge.to).copied().flatten()?,
+                        relation_type: Self::relation_type_name(&edge.relation_type),
+                    })
+                })
+                .collect(),
+            truncated: neighborhood.truncated,
+        })
+    }
+
+    /// Find functions whose bodies are semantically close to a function's body
+    ///
+    /// Every function and method in the symbol database is embedded into a
+    /// function-level vector index on first use; later queries only embed bodies
+    /// that are new or changed. Matches come from that index alone, so they are
+    /// always whole functions, found by what they do rather than what they are named.
+    pub async fn find_similar_functions(
+        &mut self,
+        options: SimilarOptions,
+    ) -> Result<SimilarResult> {
+        let provider = self.embedding_provider.clone().ok_or_else(|| {
+            anyhow::anyhow!(
+                "No embedding provider configured. Set OPENAI_API_KEY to embed function bodies."
+            )
+        })?;
+        let target = options.target.trim().to_string();
+
+        let symbol_db_path = self.db_path.join("symbols.kota");
+        if !symbol_db_path.exists() {
+            return Err(anyhow::anyhow!(
+                "No symbols found in database. Required steps:\n\
+                 1. Index a codebase: kotadb index-codebase /path/to/repo\n\
+                 2. Verify indexing: kotadb symbol-stats\n\
+                 3. Then retry this command"
+            ));
+        }
+        let reader = BinarySymbolReader::open(&symbol_db_path)?;
+        let documents = self
+            .database
+            .storage()
+            .lock_timed(LockedResource::Storage)
+            .await
+            .list_all()
+            .await?;
+        let chunks = function_chunks(&reader, &documents);
+        let target_chunk = chunks
+            .iter()
+            .find(|chunk| chunk.location.name == target)
+            .ok_or_else(|| anyhow::anyhow!("Function '{}' not found", target))?;
+
+        let mut index =
+            FunctionVectorIndex::open(&self.db_path, provider.model_name(), provider.dimension())
+                .await?;
+        let newly_embedded = index.refresh(&chunks, provider.as_ref()).await?;
+        let vector = provider.embed_text(&target_chunk.text).await?;
+
+        let mut matches = Vec::new();
+        for hit in index.search(&vector, options.limit + 1).await? {
+            for location in hit.locations {
+                if *location == target_chunk.location {
+                    continue;
+                }
+                matches.push(SimilarFunction {
+                    name: location.name.clone(),
+                    file_path: location.file_path.clone(),
+                    start_line: location.start_line,
+                    end_line: location.end_line,
+                    similarity: hit.similarity,
+                });
+            }
+        }
+        matches.truncate(options.limit);
+
+        Ok(SimilarResult {
+            symbol: target,
+            file_path: target_chunk.location.file_path.clone(),
+            start_line: target_chunk.location.start_line,
+            end_line: target_chunk.location.end_line,
+            functions_indexed: index.len(),
+            newly_embedded,
+            matches,
+        })
+    }
+
+    /// Read source excerpts for a batch of result locations
+    ///
+    /// Each distinct file is fetched once by path (shared path cache, then the
+    /// primary index), so a page of results never scans storage.
+    async fn read_excerpts(
+        &self,
+        locations: &[(String, Option<u32>)],
+        context_lines: usize,
+    ) -> Result<Vec<Option<SourceExcerpt>>> {
+        let context_lines = context_lines.min(MAX_CONTEXT_LINES);
+        let wanted: Vec<&str> = locations
+            .iter()
+            .filter(|(_, line)| line.is_some())
+            .map(|(path, _)| path.as_str())
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+
+        let storage = self.database.storage();
+        let primary_index = self.database.primary_index();
+        let path_cache = self.database.path_cache();
+        let documents = documents_by_paths(&storage, &primary_index, &path_cache, &wanted).await?;
+        let sources: HashMap<&str, Vec<String>> = wanted
+            .into_iter()
+            .zip(documents)
+            .map(|(path, doc)| {
+                let lines = doc
+                    .map(|doc| {
+                        String::from_utf8_lossy(&doc.content)
+                            .lines()
+                            .map(str::to_string)
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                (path, lines)
+            })
+            .collect();
+
+        Ok(locations
+            .iter()
+            .map(|(path, line)| {
+                let line = (*line)? as usize;
+                let lines = sources.get(path.as_str())?;
+                Self::excerpt_around(lines, line, context_lines)
+            })
+            .collect())
+    }
+
+    /// Slice `context_lines` lines either side of a 1-based line number
+    fn excerpt_around(
+        lines: &[String],
+        line: usize,
+        context_lines: usize,
+    ) -> Option<SourceExcerpt> {
+        if line == 0 || line > lines.len() {
+            return None;
+        }
+        let start_line = line.saturating_sub(context_lines).max(1);
+        let end_line = (line + context_lines).min(lines.len());
+        Some(SourceExcerpt {
+            start_line,
+            end_line,
+            text: lines[start_line - 1..end_line].join("\n"),
         })
     }
 
@@ -319,15 +1864,31 @@ impl<'a> AnalysisService<'a> {
         let mut overview_data = HashMap::new();
 
         // 1. Basic scale metrics from database
-        let storage_arc = self.database.storage();
-        let storage = storage_arc.lock().await;
-        let all_docs = storage.list_all().await?;
+        let mut all_docs = self
+            .database
+            .storage()
+            .lock_timed(LockedResource::Storage)
+            .await
+            .list_metadata()
+            .await?;
+        if let Some(scope) = &self.scope {
+            all_docs.retain(|document| scope.contains_document(&document.id));
+        }
         let doc_count = all_docs.len();
         let total_size: usize = all_docs.iter().map(|d| d.size).sum();
 
         overview_data.insert("total_files".to_string(), json!(doc_count));
         overview_data.insert("total_size_bytes".to_string(), json!(total_size));
 
+        // Language breakdown by bytes and well-known project files
+        let project = ProjectMetadata::from_metadata(&all_docs);
+        overview_data.insert("languages".to_string(), json!(project.languages));
+        overview_data.insert(
+            "primary_language".to_string(),
+            json!(project.primary_language),
+        );
+        overview_data.insert("project_files".to_string(), json!(project.project_files));
+
         // 2. Symbol analysis (if available)
         let symbol_db_path = self.db_path.join("symbols.kota");
         let mut symbols_by_type: HashMap<String, usize> = HashMap::new();
@@ -338,76 +1899,801 @@ impl<'a> AnalysisService<'a> {
         if symbol_db_path.exists() {
             match BinarySymbolReader::open(&symbol_db_path) {
                 Ok(reader) => {
-                    total_symbols = reader.symbol_count();
+                    for symbol in reader.iter_symbols() {
+                        let file_path = reader.get_symbol_file_path(&symbol).ok();
+                        if self.scope.is_some()
+                            && !file_path.as_deref().is_some_and(|p| self.in_scope(p))
+                        {
+                            continue;
+                        }
+                        total_symbols += 1;
+
+                        // Count by type
+                        let type_name = match crate::parsing::SymbolType::try_from(symbol.kind) {
+                            Ok(symbol_type) => format!("{}", symbol_type),
+                            Err(_) => format!("unknown({})", symbol.kind),
+                        };
+                        *symbols_by_type.entry(type_name).or_insert(0) += 1;
+
+                        // Count by language (inferred from file extension)
+                        if let Some(file_path) = file_path {
+                            unique_files.insert(file_path.clone());
+                            let path = Path::new(&file_path);
+                            let lang = detect_language_from_extension(path);
+                            *symbols_by_language.entry(lang.to_string()).or_insert(0) += 1;
+                        }
+                    }
+                }
+                Err(e) => {
+                    tracing::warn!("Failed to read symbols database: {}", e);
+                }
+            }
+        }
+
+        overview_data.insert("total_symbols".to_string(), json!(total_symbols));
+        overview_data.insert("code_files".to_string(), json!(unique_files.len()));
+        overview_data.insert("symbols_by_type".to_string(), json!(symbols_by_type));
+        overview_data.insert(
+            "symbols_by_language".to_string(),
+            json!(symbols_by_language),
+        );
+
+        // 3. Relationship and dependency analysis
+        let (total_relationships, connected_symbols, top_referenced_symbols, entry_points) =
+            self.analyze_dependencies(&options).await?;
+
+        overview_data.insert(
+            "total_relationships".to_string(),
+            json!(total_relationships),
+        );
+        overview_data.insert("connected_symbols".to_string(), json!(connected_symbols));
+        overview_data.insert(
+            "top_referenced_symbols".to_string(),
+            json!(top_referenced_symbols),
+        );
+        overview_data.insert("entry_points".to_string(), json!(entry_points));
+
+        // 4. File organization patterns
+        let (test_files, source_files, doc_files, test_to_code_ratio) =
+            self.analyze_file_organization(&unique_files).await?;
+
+        let mut file_organization = HashMap::new();
+        file_organization.insert("test_files", test_files);
+        file_organization.insert("source_files", source_files);
+        file_organization.insert("documentation_files", doc_files);
+        overview_data.insert("file_organization".to_string(), json!(file_organization));
+        overview_data.insert(
+            "test_to_code_ratio".to_string(),
+            json!(format!("{:.2}", test_to_code_ratio)),
+        );
+
+        // 5. Measured test coverage, when a report was imported
+        let test_coverage = match CoverageStore::load(&self.db_path) {
+            Ok(store) => store.map(|mut store| {
+                if self.scope.is_some() {
+                    store
+                        .symbols
+                        .retain(|symbol| self.in_scope(&symbol.file_path));
+                }
+                let mut coverage = json!(store.summary());
+                coverage["source"] = json!(store.source);
+                coverage["imported_at"] = json!(store.imported_at);
+                coverage
+            }),
+            Err(e) => {
+                tracing::warn!("Ignoring unreadable coverage: {e:#}");
+                None
+            }
+        };
+        overview_data.insert("test_coverage".to_string(), json!(test_coverage));
+
+        // Format output based on requested format
+        let formatted_output = self
+            .format_overview_output(&overview_data, &options)
+            .await?;
+
+        Ok(OverviewResult {
+            overview_data,
+            formatted_output,
+        })
+    }
+
+    /// Per-file size, complexity and churn arranged as a directory hierarchy
+    ///
+    /// Sizes and line counts come from stored documents, symbol counts from the
+    /// symbol database and churn from commits ingested with git history; without
+    /// them those metrics are 0.
+    pub async fn generate_treemap(&self, options: TreemapOptions) -> Result<TreemapResult> {
+        let root = options.path.trim_matches('/').to_string();
+        let documents = self
+            .database
+            .storage()
+            .lock_timed(LockedResource::Storage)
+            .await
+            .list_all()
+            .await?;
+
+        let (churn, commits_analyzed) = churn_by_file(&documents);
+
+        let mut symbols_per_file: HashMap<String, usize> = HashMap::new();
+        let symbol_db_path = self.db_path.join("symbols.kota");
+        if symbol_db_path.exists() {
+            match BinarySymbolReader::open(&symbol_db_path) {
+                Ok(reader) => {
+                    for symbol in reader.iter_symbols() {
+                        if let Ok(file_path) = reader.get_symbol_file_path(&symbol) {
+                            *symbols_per_file.entry(file_path).or_insert(0) += 1;
+                        }
+                    }
+                }
+                Err(e) => tracing::warn!("Failed to read symbols database: {}", e),
+            }
+        }
+        // Symbol paths may be absolute while document paths are repository-relative,
+        // so counts are keyed by every path suffix starting at a component boundary
+        let mut symbols: HashMap<String, usize> = HashMap::new();
+        for (file_path, count) in symbols_per_file {
+            let file_path = treemap_path(&file_path.replace('\\', "/"));
+            let mut suffix = file_path.as_str();
+            loop {
+                *symbols.entry(suffix.to_string()).or_insert(0) += count;
+                match suffix.split_once('/') {
+                    Some((_, rest)) if !rest.is_empty() => suffix = rest,
+                    _ => break,
+                }
+            }
+        }
+
+        let prefix = format!("{}/", root);
+        let mut files: Vec<(String, &'static str, TreemapMetrics)> = Vec::new();
+        for document in &documents {
+            if document.tags.iter().any(|tag| tag.as_str() == "commit")
+                || !self.in_scope(document.path.as_str())
+            {
+                continue;
+            }
+            let path = treemap_path(document.path.as_str());
+            let relative = if root.is_empty() {
+                path.as_str()
+            } else {
+                match path.strip_prefix(&prefix) {
+                    Some(relative) => relative,
+                    None => continue,
+                }
+            };
+            let language = detect_language_from_extension(Path::new(&path));
+            let content = String::from_utf8_lossy(&document.content);
+            let metrics = TreemapMetrics {
+                files: 1,
+                size: document.size,
+                lines: content.lines().count(),
+                symbols: symbols.get(&path).copied().unwrap_or(0),
+                complexity: if is_source_language(language) {
+                    decision_points(&content)
+                } else {
+                    0
+                },
+                churn: churn.get(&path).copied().unwrap_or(0),
+            };
+            files.push((relative.to_string(), language, metrics));
+        }
+        files.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let mut leaf_max = TreemapMetrics::default();
+        let name = root.rsplit('/').next().unwrap_or("").to_string();
+        let entries: Vec<(Vec<&str>, &'static str, &TreemapMetrics)> = files
+            .iter()
+            .map(|(relative, language, metrics)| {
+                (
+                    relative.split('/').filter(|c| !c.is_empty()).collect(),
+                    *language,
+                    metrics,
+                )
+            })
+            .collect();
+        let root_node = build_treemap(name, root, &entries, options.depth, &mut leaf_max);
+
+        Ok(TreemapResult {
+            root: root_node,
+            leaf_max,
+            commits_analyzed,
+        })
+    }
+
+    /// Metrics recorded after recent indexing runs; with a repository scope, only runs
+    /// that indexed one of its repositories
+    pub fn trends(&self, options: TrendsOptions) -> Result<TrendsResult> {
+        let snapshots = match &self.scope {
+            None => TrendHistory::load(&self.db_path, options.limit)?,
+            Some(scope) => {
+                let mut snapshots: Vec<MetricsSnapshot> =
+                    TrendHistory::load(&self.db_path, usize::MAX)?
+                        .into_iter()
+                        .filter(|snapshot| {
+                            snapshot
+                                .document_prefix
+                                .as_deref()
+                                .is_some_and(|prefix| scope.contains_repository(prefix))
+                        })
+                        .collect();
+                snapshots.split_off(snapshots.len().saturating_sub(options.limit))
+            }
+        };
+        let change = match (snapshots.first(), snapshots.last()) {
+            (Some(first), Some(last)) if snapshots.len() > 1 => {
+                Some(MetricsChange::between(first, last))
+            }
+            _ => None,
+        };
+        Ok(TrendsResult { snapshots, change })
+    }
+
+    /// Where an error type is constructed, converted, handled and escapes public APIs
+    pub async fn error_flows(&self, options: ErrorFlowOptions) -> Result<ErrorFlows> {
+        let documents = self
+            .database
+            .storage()
+            .lock_timed(LockedResource::Storage)
+            .await
+            .list_all()
+            .await?;
+        let sources: Vec<(&str, String)> = documents
+            .iter()
+            .filter(|d| d.path.as_str().ends_with(".rs") && self.in_scope(d.path.as_str()))
+            .filter(|d| !d.tags.iter().any(|tag| tag.as_str() == "commit"))
+            .map(|d| {
+                (
+                    d.path.as_str(),
+                    strip_frontmatter(&String::from_utf8_lossy(&d.content)).to_string(),
+                )
+            })
+            .collect();
+        let functions = FunctionRanges::load(&self.db_path.join("symbols.kota"))?;
+        let mut flows = ErrorFlows::analyze(
+            &options.error_type,
+            sources
+                .iter()
+                .map(|(path, content)| (*path, content.as_str())),
+            &functions,
+        );
+        flows.truncate(options.limit);
+        Ok(flows)
+    }
+
+    /// Where a configuration key is set and read
+    pub fn config_usages(&self, options: ConfigUsagesOptions) -> Result<ConfigUsages> {
+        let index = ConfigIndex::load(&self.db_path)?.ok_or_else(|| {
+            anyhow::anyhow!(
+                "No configuration keys indexed. Index a codebase with symbol extraction first:\n\
+                 kotadb index-codebase /path/to/repo"
+            )
+        })?;
+        let mut usages = index.usages(&options.key);
+        usages
+            .definitions
+            .retain(|site| self.in_scope(&site.file_path));
+        usages.reads.retain(|site| self.in_scope(&site.file_path));
+        usages.truncate(options.limit);
+        Ok(usages)
+    }
+
+    /// SQL statements that define, write and read a table
+    pub fn table_usages(&self, options: TableUsagesOptions) -> Result<TableUsages> {
+        let mut index = SqlIndex::load(&self.db_path)?.ok_or_else(|| {
+            anyhow::anyhow!(
+                "No SQL statements indexed. Index a codebase with symbol extraction first:\n\
+                 kotadb index-codebase /path/to/repo"
+            )
+        })?;
+        index
+            .statements
+            .retain(|statement| self.in_scope(&statement.file_path));
+        let mut usages = index.table_usages(&options.table);
+        usages.truncate(options.limit);
+        Ok(usages)
+    }
+
+    /// Reviewers for a diff ranked by CODEOWNERS, commits to the changed and impacted
+    /// files and, with a repository checkout, blame of the changed functions
+    pub async fn suggest_reviewers(
+        &mut self,
+        options: SuggestReviewersOptions,
+    ) -> Result<ReviewerSuggestions> {
+        let diff_files = parse_unified_diff(&options.diff);
+        if diff_files.is_empty() {
+            return Err(anyhow::anyhow!("No changed files found in the diff"));
+        }
+        let changed_files: Vec<String> = diff_files.iter().map(|f| f.path.clone()).collect();
+        let documents = self
+            .database
+            .storage()
+            .lock_timed(LockedResource::Storage)
+            .await
+            .list_all()
+            .await?;
+        let commits: Vec<CommitRecord> = documents
+            .iter()
+            .filter_map(|document| {
+                let files = commit_changed_files(document)?;
+                CommitRecord::parse(&String::from_utf8_lossy(&document.content), files)
+            })
+            .collect();
+        // Given content, then the checkout's CODEOWNERS, then an indexed one
+        let codeowners = match options.codeowners.or_else(|| {
+            let repo_path = options.repo_path.as_ref()?;
+            CODEOWNERS_PATHS
+                .iter()
+                .find_map(|path| std::fs::read_to_string(repo_path.join(path)).ok())
+        }) {
+            Some(content) => CodeOwners::parse(&content),
+            None => CODEOWNERS_PATHS
+                .iter()
+                .find_map(|path| {
+                    documents.iter().find(|d| {
+                        self.in_scope(d.path.as_str()) && paths_equivalent(d.path.as_str(), path)
+                    })
+                })
+                .map(|d| CodeOwners::parse(strip_frontmatter(&String::from_utf8_lossy(&d.content))))
+                .unwrap_or_default(),
+        };
+
+        // Changed functions, and the lines to blame: changed lines plus whole changed functions
+        let functions = FunctionRanges::load(&self.db_path.join("symbols.kota"))?;
+        let mut changed_symbols: Vec<String> = Vec::new();
+        let mut blame_ranges: Vec<(&str, Vec<(usize, usize)>)> = Vec::new();
+        for file in &diff_files {
+            let mut ranges = file.old_ranges.clone();
+            for &(start, end) in &file.old_ranges {
+                for (name, fn_start, fn_end) in functions.overlapping(file.base_path(), start, end)
+                {
+                    if !changed_symbols.iter().any(|symbol| symbol == name) {
+                        changed_symbols.push(name.to_string());
+                    }
+                    ranges.push((fn_start, fn_end));
+                }
+            }
+            if !ranges.is_empty() {
+                blame_ranges.push((file.base_path(), ranges));
+            }
+        }
+
+        let mut impacted_files: Vec<String> = Vec::new();
+        for symbol in changed_symbols.iter().take(MAX_REVIEW_IMPACT_SYMBOLS) {
+            let engine = match self.get_relationship_engine().await {
+                Ok(engine) => engine,
+                Err(e) => {
+                    tracing::warn!("Suggesting reviewers without impact analysis: {e:#}");
+                    break;
+                }
+            };
+            let query_type = RelationshipQueryType::ImpactAnalysis {
+                target: symbol.clone(),
+            };
+            let (mut result, _) = engine.execute_query_with_explain(query_type).await?;
+            self.retain_in_scope(&mut result);
+            for relationship in result.direct_relationships {
+                let path = relationship.file_path;
+                if !changed_files.iter().any(|f| paths_equivalent(f, &path))
+                    && !impacted_files.contains(&path)
+                {
+                    impacted_files.push(path);
+                }
+            }
+        }
+
+        let mut blame: HashMap<String, usize> = HashMap::new();
+        if let Some(repo_path) = &options.repo_path {
+            let repository = GitRepository::open(repo_path, IngestionOptions::default())?;
+            for (path, ranges) in &blame_ranges {
+                match repository.blame_lines(path, ranges) {
+                    Ok(lines) => {
+                        for (author, count) in lines {
+                            *blame.entry(author).or_insert(0) += count;
+                        }
+                    }
+                    Err(e) => tracing::warn!("Skipping blame of {}: {e:#}", path),
+                }
+            }
+        }
+
+        let mut reviewers = rank_reviewers(
+            &changed_files,
+            &impacted_files,
+            &commits,
+            &codeowners,
+            &blame,
+            &options.exclude,
+        );
+        reviewers.truncate(options.limit);
+        Ok(ReviewerSuggestions {
+            changed_files,
+            changed_symbols,
+            impacted_files,
+            commits_analyzed: commits.len(),
+            reviewers,
+        })
+    }
+
+    /// Definitions of a symbol in this index with their size and decision points, and the
+    /// number of references to it
+    ///
+    /// A path-qualified name (`Type::method`) matches definitions by its last segment.
+    pub async fn measure_symbol(&mut self, name: &str) -> Result<EvolutionPoint> {
+        let mut definitions = self.symbol_definitions(name)?;
+        let sources = self.source_lines(&definitions).await?;
+        for definition in &mut definitions {
+            let lines = &sources[&definition.file_path];
+            let start = definition.start_line.saturating_sub(1).min(lines.len());
+            let end = definition.end_line.min(lines.len()).max(start);
+            definition.complexity = decision_points(&lines[start..end].join("\n"));
+        }
+
+        let mut callers = 0;
+        if !definitions.is_empty() {
+            match self.get_relationship_engine().await {
+                Ok(engine) => {
+                    let query_type = RelationshipQueryType::FindCallers {
+                        target: name.to_string(),
+                    };
+                    match engine.execute_query_with_explain(query_type).await {
+                        Ok((mut result, _)) => {
+                            self.retain_in_scope(&mut result);
+                            callers = result.direct_relationships.len();
+                        }
+                        Err(e) => tracing::debug!("No callers of {name}: {e:#}"),
+                    }
+                }
+                Err(e) => tracing::warn!("Measuring {name} without callers: {e:#}"),
+            }
+        }
+
+        Ok(EvolutionPoint {
+            definitions,
+            callers,
+            ..EvolutionPoint::default()
+        })
+    }
+
+    /// Definition location, signature, doc comment, top callers and source of a symbol
+    /// within a token budget, or `None` when no definition is indexed in scope
+    pub async fn symbol_brief(
+        &mut self,
+        options: SymbolBriefOptions,
+    ) -> Result<Option<SymbolBrief>> {
+        let definitions = self.symbol_definitions(&options.target)?;
+        let Some(definition) = definitions.first() else {
+            return Ok(None);
+        };
+        let sources = self.source_lines(&definitions[..1]).await?;
+
+        let callers = self
+            .find_callers(CallersOptions {
+                target: options.target.clone(),
+                limit: Some(options.max_callers),
+                sort_by: RelationshipSortBy::Risk,
+                quiet: true,
+                ..CallersOptions::default()
+            })
+            .await;
+        let (callers, total_callers) = match callers {
+            Ok(result) => (
+                result
+                    .callers
+                    .into_iter()
+                    .map(|site| BriefCaller {
+                        caller: site.caller,
+                        file_path: site.file_path,
+                        line: site.line_number,
+                    })
+                    .collect(),
+                result.total_available,
+            ),
+            Err(e) => {
+                tracing::debug!("Briefing {} without callers: {e:#}", options.target);
+                (Vec::new(), 0)
+            }
+        };
+
+        let bare_name = options
+            .target
+            .rsplit("::")
+            .next()
+            .unwrap_or(&options.target);
+        let source = BriefSource {
+            symbol: bare_name,
+            kind: &definition.kind,
+            file_path: &definition.file_path,
+            start_line: definition.start_line,
+            end_line: definition.end_line,
+            lines: &sources[&definition.file_path],
+            callers,
+            total_callers,
+            other_definitions: definitions[1..]
+                .iter()
+                .map(|d| format!("{}:{}", d.file_path, d.start_line))
+                .collect(),
+        };
+        Ok(Some(SymbolBrief::build(source, options.token_budget)))
+    }
+
+    /// Every definition and recorded reference a rename of a symbol has to edit, grouped
+    /// by file with exact ranges, plus mentions of its name the index cannot verify
+    ///
+    /// Files without a recorded definition or reference are read too, one storage page
+    /// at a time, so string literals naming the symbol anywhere in scope are flagged.
+    pub async fn preview_rename(&mut self, options: RenamePreviewOptions) -> Result<RenamePreview> {
+        rename_preview::validate_new_name(&options.target, &options.new_name)?;
+        let name = rename_preview::bare_name(&options.target).to_string();
+
+        let definitions = self.symbol_definitions(&options.target)?;
+        let callers = match self
+            .find_callers(CallersOptions {
+                target: options.target.clone(),
+                quiet: true,
+                ..CallersOptions::default()
+            })
+            .await
+        {
+            Ok(result) => result.callers,
+            Err(e) => {
+                tracing::debug!(
+                    "Previewing rename of {} without references: {e:#}",
+                    options.target
+                );
+                Vec::new()
+            }
+        };
+        if definitions.is_empty() && callers.is_empty() {
+            return Err(anyhow::anyhow!(
+                "No definition or reference of '{}' is indexed",
+                options.target
+            ));
+        }
+        let conflicts = self
+            .symbol_definitions(&options.new_name)?
+            .into_iter()
+            .map(|d| format!("{}:{}", d.file_path, d.start_line))
+            .collect();
+
+        let mut recorded = rename_preview::group_by_file(
+            definitions.into_iter().map(|d| {
+                (
+                    d.file_path,
+                    RecordedDefinition {
+                        kind: d.kind,
+                        start_line: d.start_line,
+                        end_line: d.end_line,
+                    },
+                )
+            }),
+            callers.into_iter().map(|c| {
+                (
+                    c.file_path,
+                    RecordedReference {
+                        symbol: c.caller,
+                        line: c.line_number,
+                        column: c.column_number,
+                        range: c.range,
+                    },
+                )
+            }),
+        );
+
+        let storage = self.database.storage();
+        let mut files = Vec::new();
+        let mut cursor = None;
+        loop {
+            let page = storage
+                .lock_timed(LockedResource::Storage)
+                .await
+                .list_page(cursor, RENAME_PREVIEW_PAGE_SIZE)
+                .await?;
+            for document in &page.documents {
+                let path = document.path.as_str();
+                if document.tags.iter().any(|tag| tag.as_str() == "commit") || !self.in_scope(path)
+                {
+                    continue;
+                }
+                let content = String::from_utf8_lossy(&document.content);
+                let content = strip_frontmatter(&content);
+                // Symbols record repository-relative paths; stored paths may be prefixed
+                let recorded_path = recorded
+                    .keys()
+                    .find(|p| p.as_str() == path)
+                    .or_else(|| recorded.keys().find(|p| paths_equivalent(path, p)))
+                    .cloned();
+                let (definitions, references) = recorded_path
+                    .as_ref()
+                    .and_then(|p| recorded.remove(p))
+                    .unwrap_or_default();
+                if recorded_path.is_none() && !content.contains(&name) {
+                    continue;
+                }
+                files.push(rename_preview::preview_file(
+                    recorded_path.as_deref().unwrap_or(path),
+                    content,
+                    &name,
+                    &definitions,
+                    &references,
+                ));
+            }
+            match page.next {
+                Some(next) => cursor = Some(next),
+                None => break,
+            }
+        }
+        files.extend(recorded.iter().map(|(path, (definitions, references))| {
+            rename_preview::unavailable_file(path, definitions, references)
+        }));
+
+        Ok(RenamePreview::new(
+            &options.target,
+            &options.new_name,
+            files,
+            conflicts,
+        ))
+    }
 
-                    for symbol in reader.iter_symbols() {
-                        // Count by type
-                        let type_name = match crate::parsing::SymbolType::try_from(symbol.kind) {
-                            Ok(symbol_type) => format!("{}", symbol_type),
-                            Err(_) => format!("unknown({})", symbol.kind),
-                        };
-                        *symbols_by_type.entry(type_name).or_insert(0) += 1;
+    /// Definitions of a symbol's bare name within scope, sorted by file and line
+    fn symbol_definitions(&self, name: &str) -> Result<Vec<SymbolDefinition>> {
+        use crate::parsing::SymbolType;
 
-                        // Count by language (inferred from file extension)
-                        if let Ok(file_path) = reader.get_symbol_file_path(&symbol) {
-                            unique_files.insert(file_path.clone());
-                            let path = Path::new(&file_path);
-                            let lang = detect_language_from_extension(path);
-                            *symbols_by_language.entry(lang.to_string()).or_insert(0) += 1;
-                        }
-                    }
+        let bare_name = name.rsplit("::").next().unwrap_or(name);
+        let symbol_db_path = self.db_path.join("symbols.kota");
+        let mut definitions: Vec<SymbolDefinition> = Vec::new();
+        if symbol_db_path.exists() {
+            let reader = BinarySymbolReader::open(&symbol_db_path)?;
+            for symbol in reader.iter_symbols() {
+                let Ok(kind) = SymbolType::try_from(symbol.kind) else {
+                    continue;
+                };
+                if kind == SymbolType::Import || reader.get_symbol_name(&symbol)? != bare_name {
+                    continue;
                 }
-                Err(e) => {
-                    tracing::warn!("Failed to read symbols database: {}", e);
+                let file_path = reader.get_symbol_file_path(&symbol)?;
+                if !self.in_scope(&file_path) {
+                    continue;
                 }
+                definitions.push(SymbolDefinition {
+                    file_path,
+                    kind: kind.to_string(),
+                    start_line: symbol.start_line as usize,
+                    end_line: symbol.end_line as usize,
+                    complexity: 0,
+                });
             }
         }
+        definitions.sort_by(|a, b| (&a.file_path, a.start_line).cmp(&(&b.file_path, b.start_line)));
+        definitions.dedup();
 
-        overview_data.insert("total_symbols".to_string(), json!(total_symbols));
-        overview_data.insert("code_files".to_string(), json!(unique_files.len()));
-        overview_data.insert("symbols_by_type".to_string(), json!(symbols_by_type));
-        overview_data.insert(
-            "symbols_by_language".to_string(),
-            json!(symbols_by_language),
-        );
+        Ok(definitions)
+    }
 
-        // 3. Relationship and dependency analysis
-        let (total_relationships, connected_symbols, top_referenced_symbols, entry_points) =
-            self.analyze_dependencies(&options).await?;
+    /// Source lines of each file holding one of `definitions`
+    async fn source_lines(
+        &self,
+        definitions: &[SymbolDefinition],
+    ) -> Result<HashMap<String, Vec<String>>> {
+        // Definitions are sorted by file, so deduplicating keeps each file once
+        let mut files: Vec<&str> = definitions.iter().map(|d| d.file_path.as_str()).collect();
+        files.dedup();
+        let storage = self.database.storage();
+        let primary_index = self.database.primary_index();
+        let path_cache = self.database.path_cache();
+        let documents = documents_by_paths(&storage, &primary_index, &path_cache, &files).await?;
+        Ok(files
+            .into_iter()
+            .zip(documents)
+            .map(|(path, doc)| {
+                let lines = doc
+                    .map(|doc| {
+                        strip_frontmatter(&String::from_utf8_lossy(&doc.content))
+                            .lines()
+                            .map(str::to_string)
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                (path.to_string(), lines)
+            })
+            .collect())
+    }
 
-        overview_data.insert(
-            "total_relationships".to_string(),
-            json!(total_relationships),
-        );
-        overview_data.insert("connected_symbols".to_string(), json!(connected_symbols));
-        overview_data.insert(
-            "top_referenced_symbols".to_string(),
-            json!(top_referenced_symbols),
-        );
-        overview_data.insert("entry_points".to_string(), json!(entry_points));
+    /// Feature flags by usage count
+    pub fn feature_flags(&self, options: FlagsOptions) -> Result<FlagsResult> {
+        let index = self.flag_index()?;
+        let mut flags = index.summaries();
+        flags.truncate(options.limit);
+        Ok(FlagsResult {
+            flags,
+            indexed_at: index.indexed_at,
+        })
+    }
 
-        // 4. File organization patterns
-        let (test_files, source_files, doc_files, test_to_code_ratio) =
-            self.analyze_file_organization(&unique_files).await?;
+    /// Files, items and runtime checks controlled by a feature flag
+    pub fn flag_impact(&self, options: FlagImpactOptions) -> Result<FlagImpact> {
+        Ok(self.flag_index()?.impact(&options.flag))
+    }
 
-        let mut file_organization = HashMap::new();
-        file_organization.insert("test_files", test_files);
-        file_organization.insert("source_files", source_files);
-        file_organization.insert("documentation_files", doc_files);
-        overview_data.insert("file_organization".to_string(), json!(file_organization));
-        overview_data.insert(
-            "test_to_code_ratio".to_string(),
-            json!(format!("{:.2}", test_to_code_ratio)),
-        );
+    /// Flag usages within the repository scope
+    fn flag_index(&self) -> Result<FlagIndex> {
+        let mut index = FlagIndex::load(&self.db_path)?.ok_or_else(|| {
+            anyhow::anyhow!(
+                "No feature flags indexed. Index a codebase with symbol extraction first:\n\
+                 kotadb index-codebase /path/to/repo"
+            )
+        })?;
+        index.usages.retain(|usage| self.in_scope(&usage.file_path));
+        Ok(index)
+    }
 
-        // Format output based on requested format
-        let formatted_output = self
-            .format_overview_output(&overview_data, &options)
-            .await?;
+    /// Log statements whose format string could have produced `options.message`
+    pub fn locate_log(&self, options: LocateLogOptions) -> Result<LocateLogResult> {
+        let index = LogIndex::load(&self.db_path)?.ok_or_else(|| {
+            anyhow::anyhow!(
+                "No log statements indexed. Index a codebase with symbol extraction first:\n\
+                 kotadb index-codebase /path/to/repo"
+            )
+        })?;
+        let mut matches = index.locate(&options.message, usize::MAX);
+        matches.retain(|m| self.in_scope(&m.statement.file_path));
+        matches.truncate(options.limit);
+        Ok(LocateLogResult {
+            matches,
+            indexed_at: index.indexed_at,
+        })
+    }
 
-        Ok(OverviewResult {
-            overview_data,
-            formatted_output,
+    /// Most used symbols: by calls observed in imported runtime traces when a trace was
+    /// imported, otherwise by static callers in the dependency graph
+    pub fn hot_paths(&self, options: HotPathsOptions) -> Result<HotPathsResult> {
+        let graph_path = self.db_path.join("dependency_graph.bin");
+        let graph = if graph_path.exists() {
+            let bytes = std::fs::read(&graph_path)
+                .with_context(|| format!("Failed to read {}", graph_path.display()))?;
+            SerializableDependencyGraph::from_bytes(&bytes)?
+        } else {
+            return Ok(HotPathsResult {
+                ranked_by: HotPathRanking::StaticCallers,
+                profile_source: None,
+                symbols: Vec::new(),
+            });
+        };
+
+        let mut static_callers: HashMap<Uuid, usize> = HashMap::new();
+        for edge in &graph.edges {
+            *static_callers.entry(edge.to_id).or_insert(0) += 1;
+        }
+        let profile = self.call_profile();
+        let mut symbols: Vec<HotSymbol> = graph
+            .nodes
+            .iter()
+            .filter(|node| self.in_scope(&node.file_path.to_string_lossy()))
+            .map(|node| HotSymbol {
+                qualified_name: node.qualified_name.clone(),
+                file_path: node.file_path.display().to_string(),
+                static_callers: static_callers.get(&node.symbol_id).copied().unwrap_or(0),
+                observed_calls: profile
+                    .as_ref()
+                    .and_then(|p| p.symbol_calls(&node.qualified_name)),
+            })
+            .filter(|symbol| symbol.observed_calls.is_some() || symbol.static_callers > 0)
+            .collect();
+
+        let ranked_by = if profile.is_some() {
+            HotPathRanking::ObservedCalls
+        } else {
+            HotPathRanking::StaticCallers
+        };
+        symbols.sort_by(|a, b| {
+            (b.observed_calls, b.static_callers)
+                .cmp(&(a.observed_calls, a.static_callers))
+                .then_with(|| a.qualified_name.cmp(&b.qualified_name))
+        });
+        symbols.truncate(options.limit);
+        Ok(HotPathsResult {
+            ranked_by,
+            profile_source: profile.map(|p| p.source),
+            symbols,
         })
     }
 
@@ -424,9 +2710,23 @@ impl<'a> AnalysisService<'a> {
         let graph_db_path = self.db_path.join("dependency_graph.bin");
         if graph_db_path.exists() {
             if let Ok(graph_binary) = std::fs::read(&graph_db_path) {
-                if let Ok(serializable) =
-                    bincode::deserialize::<SerializableDependencyGraph>(&graph_binary)
+                if let Ok(mut serializable) = SerializableDependencyGraph::from_bytes(&graph_binary)
                 {
+                    if self.scope.is_some() {
+                        serializable
+                            .nodes
+                            .retain(|node| self.in_scope(&node.file_path.to_string_lossy()));
+                        let scoped: HashSet<Uuid> = serializable
+                            .nodes
+                            .iter()
+                            .map(|node| node.symbol_id)
+                            .collect();
+                        serializable
+                            .edges
+                            .retain(|edge| scoped.contains(&edge.from_id));
+                        serializable.stats.node_count = serializable.nodes.len();
+                        serializable.stats.edge_count = serializable.edges.len();
+                    }
                     total_relationships = serializable.stats.edge_count;
                     connected_symbols = serializable.stats.node_count;
 
@@ -535,7 +2835,7 @@ impl<'a> AnalysisService<'a> {
         Ok((test_files, source_files, doc_files, test_to_code_ratio))
     }
 
-    /// Format overview output in human-readable or JSON format
+    /// Format overview output as human-readable text, JSON, Markdown or YAML
     async fn format_overview_output(
         &self,
         overview_data: &HashMap<String, serde_json::Value>,
@@ -546,7 +2846,10 @@ impl<'a> AnalysisService<'a> {
                 let json_output = json!(overview_data);
                 Ok(serde_json::to_string_pretty(&json_output)?)
             }
-            _ => {
+            // Through a JSON value so that keys come out sorted, as in JSON output
+            "yaml" => Ok(serde_yaml::to_string(&json!(overview_data))?),
+            "markdown" => Ok(overview_markdown(overview_data)),
+            "human" => {
                 // Human-readable format
                 let mut output = String::new();
 
@@ -606,6 +2909,50 @@ impl<'a> AnalysisService<'a> {
                     }
                 }
 
+                // Languages by size
+                if let Some(languages) = overview_data.get("languages").and_then(|v| v.as_array()) {
+                    if !languages.is_empty() {
+                        output.push_str("\nLanguages by Size:\n");
+                        for share in languages {
+                            output.push_str(&format!(
+                                "- {}: {}% ({} bytes in {} files)\n",
+                                share["language"].as_str().unwrap_or(""),
+                                share["percentage"],
+                                share["bytes"],
+                                share["files"]
+                            ));
+                        }
+                    }
+                }
+
+                // Project files
+                if let Some(project_files) = overview_data
+                    .get("project_files")
+                    .and_then(|v| v.as_object())
+                {
+                    output.push_str("\nProject Files:\n");
+                    for (label, key) in [
+                        ("README", "readme"),
+                        ("License", "license"),
+                        ("Contributing guide", "contributing"),
+                    ] {
+                        let location = project_files
+                            .get(key)
+                            .and_then(|v| v.as_str())
+                            .unwrap_or("not found");
+                        output.push_str(&format!("- {}: {}\n", label, location));
+                    }
+                    if let Some(build_files) =
+                        project_files.get("build_files").and_then(|v| v.as_array())
+                    {
+                        if !build_files.is_empty() {
+                            let names: Vec<&str> =
+                                build_files.iter().filter_map(|v| v.as_str()).collect();
+                            output.push_str(&format!("- Build files: {}\n", names.join(", ")));
+                        }
+                    }
+                }
+
                 // Relationships
                 if let Some(total_rel) = overview_data
                     .get("total_relationships")
@@ -683,33 +3030,784 @@ impl<'a> AnalysisService<'a> {
                     output.push_str(&format!("- Test-to-code ratio: {}\n", test_ratio));
                 }
 
-                // Calculate estimated test coverage based on test-to-code ratio
-                if let Some(file_org) = overview_data
-                    .get("file_organization")
-                    .and_then(|v| v.as_object())
-                {
-                    if let (Some(source_files), Some(test_files)) = (
-                        file_org.get("source_files").and_then(|v| v.as_u64()),
-                        file_org.get("test_files").and_then(|v| v.as_u64()),
-                    ) {
-                        if source_files > 0 {
-                            let test_to_code_ratio = test_files as f64 / source_files as f64;
-                            let coverage_estimate = 90.0 * (test_to_code_ratio * 0.8).tanh();
-                            let final_estimate = if test_files > 0 {
-                                (coverage_estimate + 10.0).min(90.0)
-                            } else {
-                                0.0
-                            };
-                            output.push_str(&format!(
-                                "- Estimated test coverage: {:.0}%\n",
-                                final_estimate
-                            ));
-                        }
-                    }
+                match overview_data.get("test_coverage").filter(|v| !v.is_null()) {
+                    Some(coverage) => output.push_str(&format!(
+                        "- Measured coverage: {}% of lines in {} functions; {} functions untested ({}%)\n",
+                        coverage["line_percent"],
+                        coverage["measured"],
+                        coverage["untested"],
+                        coverage["untested_percent"]
+                    )),
+                    None => output.push_str(
+                        "- Measured coverage: not imported (run `kotadb import-coverage <report>`)\n",
+                    ),
                 }
 
                 Ok(output)
             }
+            other => anyhow::bail!(
+                "Unknown overview format '{}' (expected one of: {})",
+                other,
+                OVERVIEW_FORMATS.join(", ")
+            ),
+        }
+    }
+}
+
+/// Overview as Markdown with headings and tables, for pasting into pull requests and wikis
+fn overview_markdown(overview_data: &HashMap<String, serde_json::Value>) -> String {
+    let count = |value: Option<&serde_json::Value>| value.and_then(|v| v.as_u64()).unwrap_or(0);
+    let object = |key: &str| overview_data.get(key).and_then(|v| v.as_object());
+    let array = |key: &str| {
+        overview_data
+            .get(key)
+            .and_then(|v| v.as_array())
+            .filter(|items| !items.is_empty())
+    };
+    let file_org = object("file_organization");
+    let org_count = |key: &str| count(file_org.and_then(|org| org.get(key)));
+
+    let mut output = String::from("# Codebase Overview\n\n## Scale\n\n");
+    output.push_str("| Metric | Value |\n|---|---:|\n");
+    for (label, value) in [
+        ("Total files", count(overview_data.get("total_files"))),
+        ("Code files", count(overview_data.get("code_files"))),
+        ("Test files", org_count("test_files")),
+        ("Total symbols", count(overview_data.get("total_symbols"))),
+        (
+            "Total size (bytes)",
+            count(overview_data.get("total_size_bytes")),
+        ),
+    ] {
+        output.push_str(&format!("| {} | {} |\n", label, value));
+    }
+    if let Some(language) = overview_data
+        .get("primary_language")
+        .and_then(|v| v.as_str())
+    {
+        output.push_str(&format!(
+            "| Primary language | {} |\n",
+            markdown_cell(language)
+        ));
+    }
+
+    if let Some(languages) = array("languages") {
+        output.push_str(
+            "\n## Languages\n\n| Language | Share | Bytes | Files |\n|---|---:|---:|---:|\n",
+        );
+        for share in languages {
+            output.push_str(&format!(
+                "| {} | {}% | {} | {} |\n",
+                markdown_cell(share["language"].as_str().unwrap_or("")),
+                share["percentage"],
+                share["bytes"],
+                share["files"]
+            ));
+        }
+    }
+
+    for (key, heading, column) in [
+        ("symbols_by_type", "Symbol Types", "Type"),
+        ("symbols_by_language", "Symbols by Language", "Language"),
+    ] {
+        let Some(counts) = object(key).filter(|counts| !counts.is_empty()) else {
+            continue;
+        };
+        let mut sorted: Vec<_> = counts.iter().collect();
+        sorted.sort_by(|a, b| count(Some(b.1)).cmp(&count(Some(a.1))).then(a.0.cmp(b.0)));
+        output.push_str(&format!(
+            "\n## {}\n\n| {} | Symbols |\n|---|---:|\n",
+            heading, column
+        ));
+        for (name, symbols) in sorted {
+            output.push_str(&format!("| {} | {} |\n", markdown_cell(name), symbols));
+        }
+    }
+
+    if let Some(project_files) = object("project_files") {
+        output.push_str("\n## Project Files\n\n");
+        for (label, key) in [
+            ("README", "readme"),
+            ("License", "license"),
+            ("Contributing guide", "contributing"),
+        ] {
+            match project_files.get(key).and_then(|v| v.as_str()) {
+                Some(location) => output.push_str(&format!("- {}: `{}`\n", label, location)),
+                None => output.push_str(&format!("- {}: not found\n", label)),
+            }
+        }
+        let build_files: Vec<String> = project_files
+            .get("build_files")
+            .and_then(|v| v.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|v| v.as_str())
+            .map(|name| format!("`{}`", name))
+            .collect();
+        if !build_files.is_empty() {
+            output.push_str(&format!("- Build files: {}\n", build_files.join(", ")));
+        }
+    }
+
+    let relationships = count(overview_data.get("total_relationships"));
+    if relationships > 0 {
+        output.push_str(&format!(
+            "\n## Relationships\n\n- Total relationships tracked: {}\n- Connected symbols: {}\n",
+            relationships,
+            count(overview_data.get("connected_symbols"))
+        ));
+    }
+
+    if let Some(top_refs) = array("top_referenced_symbols") {
+        output.push_str("\n## Top Referenced Symbols\n\n| Symbol | References |\n|---|---:|\n");
+        for entry in top_refs {
+            output.push_str(&format!(
+                "| `{}` | {} |\n",
+                markdown_cell(entry["symbol"].as_str().unwrap_or("")),
+                entry["references"]
+            ));
+        }
+    }
+
+    if let Some(entry_points) = array("entry_points") {
+        output.push_str("\n## Entry Points\n\nSymbols with no callers:\n\n");
+        for entry in entry_points.iter().filter_map(|v| v.as_str()) {
+            output.push_str(&format!("- `{}`\n", entry));
+        }
+    }
+
+    if file_org.is_some() {
+        output.push_str("\n## File Organization\n\n| Kind | Files |\n|---|---:|\n");
+        for (label, key) in [
+            ("Source code", "source_files"),
+            ("Tests", "test_files"),
+            ("Documentation", "documentation_files"),
+        ] {
+            output.push_str(&format!("| {} | {} |\n", label, org_count(key)));
+        }
+    }
+
+    output.push_str("\n## Test Coverage\n\n");
+    if let Some(ratio) = overview_data
+        .get("test_to_code_ratio")
+        .and_then(|v| v.as_str())
+    {
+        output.push_str(&format!("- Test-to-code ratio: {}\n", ratio));
+    }
+    match overview_data.get("test_coverage").filter(|v| !v.is_null()) {
+        Some(coverage) => output.push_str(&format!(
+            "- Measured coverage: {}% of lines in {} functions; {} functions untested ({}%)\n",
+            coverage["line_percent"],
+            coverage["measured"],
+            coverage["untested"],
+            coverage["untested_percent"]
+        )),
+        None => output.push_str(
+            "- Measured coverage: not imported (run `kotadb import-coverage <report>`)\n",
+        ),
+    }
+    output
+}
+
+/// `text` safe to place in a Markdown table cell
+fn markdown_cell(text: &str) -> String {
+    text.replace('|', "\\|")
+}
+
+const GRAPH_NODE_WIDTH: i32 = 220;
+const GRAPH_NODE_HEIGHT: i32 = 44;
+const GRAPH_COLUMN_GAP: i32 = 90;
+const GRAPH_ROW_GAP: i32 = 18;
+const GRAPH_MARGIN: i32 = 24;
+const GRAPH_HEADER_HEIGHT: i32 = 40;
+const GRAPH_LEGEND_HEIGHT: i32 = 36;
+/// Characters of a name or path shown in a node before it is elided
+const GRAPH_LABEL_CHARS: usize = 30;
+
+/// Relationship kinds in legend order, with their edge colors
+const GRAPH_EDGE_COLORS: &[(&str, &str)] = &[
+    ("calls", "#2563eb"),
+    ("extends", "#9333ea"),
+    ("implements", "#16a34a"),
+    ("returns", "#0891b2"),
+    ("references", "#d97706"),
+    ("imports", "#6b7280"),
+    ("child_of", "#94a3b8"),
+];
+const GRAPH_OTHER_EDGE_COLOR: &str = "#64748b";
+
+fn graph_edge_color(relation_type: &str) -> &'static str {
+    GRAPH_EDGE_COLORS
+        .iter()
+        .find(|(name, _)| *name == relation_type)
+        .map_or(GRAPH_OTHER_EDGE_COLOR, |(_, color)| color)
+}
+
+fn xml_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// `text` cut to `max_chars`, keeping the end for paths and the start otherwise
+fn elide(text: &str, max_chars: usize, keep_end: bool) -> String {
+    let count = text.chars().count();
+    if count <= max_chars {
+        return text.to_string();
+    }
+    let kept = max_chars.saturating_sub(1);
+    if keep_end {
+        let tail: String = text.chars().skip(count - kept).collect();
+        format!("…{tail}")
+    } else {
+        let head: String = text.chars().take(kept).collect();
+        format!("{head}…")
+    }
+}
+
+impl SymbolGraphResult {
+    /// Render the neighborhood as a standalone SVG image
+    ///
+    /// Layered left to right: dependents of the target, the target, then its
+    /// dependencies, one column per hop. Edges point from dependent to dependency
+    /// and are colored by relationship kind, with a legend of the kinds drawn.
+    pub fn to_svg(&self) -> String {
+        use std::fmt::Write;
+
+        let mut columns: Vec<i32> = self.nodes.iter().map(|node| node.layer).collect();
+        columns.sort_unstable();
+        columns.dedup();
+        let mut rows: HashMap<i32, Vec<usize>> = HashMap::new();
+        for (index, node) in self.nodes.iter().enumerate() {
+            rows.entry(node.layer).or_default().push(index);
+        }
+        for members in rows.values_mut() {
+            members.sort_by(|a, b| {
+                let (a, b) = (&self.nodes[*a], &self.nodes[*b]);
+                a.file_path
+                    .cmp(&b.file_path)
+                    .then_with(|| a.name.cmp(&b.name))
+            });
+        }
+
+        let mut positions = vec![(0, 0); self.nodes.len()];
+        for (column, layer) in columns.iter().enumerate() {
+            for (row, index) in rows[layer].iter().enumerate() {
+                positions[*index] = (
+                    GRAPH_MARGIN + column as i32 * (GRAPH_NODE_WIDTH + GRAPH_COLUMN_GAP),
+                    GRAPH_MARGIN
+                        + GRAPH_HEADER_HEIGHT
+                        + row as i32 * (GRAPH_NODE_HEIGHT + GRAPH_ROW_GAP),
+                );
+            }
+        }
+        let max_rows = rows.values().map(Vec::len).max().unwrap_or(0) as i32;
+        let width = (2 * GRAPH_MARGIN
+            + columns.len() as i32 * (GRAPH_NODE_WIDTH + GRAPH_COLUMN_GAP)
+            - GRAPH_COLUMN_GAP)
+            .max(480);
+        let legend_y =
+            GRAPH_MARGIN + GRAPH_HEADER_HEIGHT + max_rows * (GRAPH_NODE_HEIGHT + GRAPH_ROW_GAP);
+        let height = legend_y + GRAPH_LEGEND_HEIGHT + GRAPH_MARGIN;
+
+        let mut kinds: Vec<&str> = Vec::new();
+        for edge in &self.edges {
+            if !kinds.contains(&edge.relation_type.as_str()) {
+                kinds.push(&edge.relation_type);
+            }
+        }
+        kinds.sort_by_key(|kind| {
+            GRAPH_EDGE_COLORS
+                .iter()
+                .position(|(name, _)| name == kind)
+                .unwrap_or(GRAPH_EDGE_COLORS.len())
+        });
+
+        let mut svg = String::new();
+        let _ = write!(
+            svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}" font-family="ui-monospace, SFMono-Regular, Menlo, monospace">"#
+        );
+        svg.push_str("<defs>");
+        for (marker, kind) in kinds.iter().enumerate() {
+            let _ = write!(
+                svg,
+                r#"<marker id="arrow-{marker}" viewBox="0 0 10 10" refX="10" refY="5" markerWidth="7" markerHeight="7" orient="auto-start-reverse"><path d="M0,0 L10,5 L0,10 z" fill="{color}"/></marker>"#,
+                color = graph_edge_color(kind),
+            );
+        }
+        svg.push_str("</defs>");
+        let _ = write!(
+            svg,
+            r##"<rect width="100%" height="100%" fill="#ffffff"/><text x="{GRAPH_MARGIN}" y="{y}" font-size="14" font-weight="bold" fill="#0f172a">{title}</text>"##,
+            y = GRAPH_MARGIN + 14,
+            title = xml_escape(&format!(
+                "{} — dependents ← → dependencies, depth {}{}",
+                self.symbol,
+                self.depth,
+                if self.truncated { " (truncated)" } else { "" }
+            )),
+        );
+
+        for edge in &self.edges {
+            let (Some(&(fx, fy)), Some(&(tx, ty))) =
+                (positions.get(edge.from), positions.get(edge.to))
+            else {
+                continue;
+            };
+            let (fy, ty) = (fy + GRAPH_NODE_HEIGHT / 2, ty + GRAPH_NODE_HEIGHT / 2);
+            let path = if fx < tx {
+                let (sx, ex) = (fx + GRAPH_NODE_WIDTH, tx);
+                let bend = (ex - sx) / 2;
+                format!(
+                    "M{sx},{fy} C{},{fy} {},{ty} {ex},{ty}",
+                    sx + bend,
+                    ex - bend
+                )
+            } else if fx > tx {
+                let (sx, ex) = (fx, tx + GRAPH_NODE_WIDTH);
+                let bend = (sx - ex) / 2;
+                format!(
+                    "M{sx},{fy} C{},{fy} {},{ty} {ex},{ty}",
+                    sx - bend,
+                    ex + bend
+                )
+            } else {
+                // Same column: loop out to the right of the column
+                let x = fx + GRAPH_NODE_WIDTH;
+                let bend = GRAPH_COLUMN_GAP / 2;
+                format!("M{x},{fy} C{},{fy} {},{ty} {x},{ty}", x + bend, x + bend)
+            };
+            let _ = write!(
+                svg,
+                r#"<path d="{path}" fill="none" stroke="{color}" stroke-width="1.5" marker-end="url(#arrow-{marker})"><title>{title}</title></path>"#,
+                color = graph_edge_color(&edge.relation_type),
+                marker = kinds
+                    .iter()
+                    .position(|kind| *kind == edge.relation_type)
+                    .unwrap_or_default(),
+                title = xml_escape(&format!(
+                    "{} {} {}",
+                    self.nodes[edge.from].name, edge.relation_type, self.nodes[edge.to].name
+                )),
+            );
+        }
+
+        for (node, (x, y)) in self.nodes.iter().zip(&positions) {
+            let (fill, stroke) = if node.layer == 0 {
+                ("#fef3c7", "#d97706")
+            } else {
+                ("#f8fafc", "#94a3b8")
+            };
+            let _ = write!(
+                svg,
+                r##"<g><title>{title}</title><rect x="{x}" y="{y}" width="{GRAPH_NODE_WIDTH}" height="{GRAPH_NODE_HEIGHT}" rx="6" fill="{fill}" stroke="{stroke}"/><text x="{tx}" y="{name_y}" font-size="12" fill="#0f172a">{name}</text><text x="{tx}" y="{path_y}" font-size="10" fill="#64748b">{path}</text></g>"##,
+                title = xml_escape(&format!(
+                    "{} {} ({})",
+                    node.kind, node.qualified_name, node.file_path
+                )),
+                tx = x + 8,
+                name_y = y + 18,
+                path_y = y + 34,
+                name = xml_escape(&elide(&node.name, GRAPH_LABEL_CHARS, false)),
+                path = xml_escape(&elide(&node.file_path, GRAPH_LABEL_CHARS + 4, true)),
+            );
+        }
+
+        let mut legend_x = GRAPH_MARGIN;
+        let legend_line_y = legend_y + GRAPH_LEGEND_HEIGHT / 2;
+        for kind in &kinds {
+            let _ = write!(
+                svg,
+                r##"<line x1="{legend_x}" y1="{legend_line_y}" x2="{x2}" y2="{legend_line_y}" stroke="{color}" stroke-width="2"/><text x="{text_x}" y="{text_y}" font-size="11" fill="#334155">{kind}</text>"##,
+                x2 = legend_x + 24,
+                color = graph_edge_color(kind),
+                text_x = legend_x + 30,
+                text_y = legend_line_y + 4,
+                kind = xml_escape(kind),
+            );
+            legend_x += 40 + 7 * kind.chars().count() as i32;
+        }
+        svg.push_str("</svg>");
+        svg
+    }
+}
+
+/// Health metrics of the indexed `documents` and the symbol and dependency
+/// databases in `db_path`, leaving run details (time, repository) to the caller
+pub fn index_metrics(db_path: &Path, documents: &[Document]) -> MetricsSnapshot {
+    let mut totals = FileTotals::default();
+    for document in documents {
+        totals.add(document);
+    }
+    totals.into_metrics(db_path, None)
+}
+
+/// [`index_metrics`] of every stored document, read one storage page at a time
+pub async fn storage_index_metrics(
+    db_path: &Path,
+    storage: &Arc<Mutex<dyn crate::contracts::Storage>>,
+) -> Result<MetricsSnapshot> {
+    scoped_index_metrics(db_path, storage, None).await
+}
+
+/// [`storage_index_metrics`] of the documents, symbols and relationships of the
+/// repositories in `scope`
+pub async fn repository_index_metrics(
+    db_path: &Path,
+    storage: &Arc<Mutex<dyn crate::contracts::Storage>>,
+    scope: &RepositoryScope,
+) -> Result<MetricsSnapshot> {
+    scoped_index_metrics(db_path, storage, Some(scope)).await
+}
+
+async fn scoped_index_metrics(
+    db_path: &Path,
+    storage: &Arc<Mutex<dyn crate::contracts::Storage>>,
+    scope: Option<&RepositoryScope>,
+) -> Result<MetricsSnapshot> {
+    let mut totals = FileTotals::default();
+    let mut cursor = None;
+    loop {
+        let page = storage
+            .lock_timed(LockedResource::Storage)
+            .await
+            .list_page(cursor, METRICS_PAGE_SIZE)
+            .await?;
+        for document in &page.documents {
+            if scope.is_none_or(|scope| scope.contains_document(&document.id)) {
+                totals.add(document);
+            }
+        }
+        match page.next {
+            Some(next) => cursor = Some(next),
+            None => break,
+        }
+    }
+    Ok(totals.into_metrics(db_path, scope))
+}
+
+/// Documents read per storage page by [`storage_index_metrics`]
+const METRICS_PAGE_SIZE: usize = 256;
+
+/// Documents read per storage page when a rename preview scans for mentions
+const RENAME_PREVIEW_PAGE_SIZE: usize = 256;
+
+/// Per-file counts behind [`index_metrics`], added up one document at a time
+#[derive(Default)]
+struct FileTotals {
+    files: usize,
+    lines: usize,
+    source_files: usize,
+    test_files: usize,
+    total_complexity: usize,
+}
+
+impl FileTotals {
+    fn add(&mut self, document: &Document) {
+        if document.tags.iter().any(|tag| tag.as_str() == "commit") {
+            return;
+        }
+        let content = String::from_utf8_lossy(&document.content);
+        self.files += 1;
+        self.lines += content.lines().count();
+        let path = Path::new(document.path.as_str());
+        if is_source_language(detect_language_from_extension(path)) {
+            self.source_files += 1;
+            self.total_complexity += decision_points(&content);
+            if is_test_file(path) {
+                self.test_files += 1;
+            }
+        }
+    }
+
+    fn into_metrics(self, db_path: &Path, scope: Option<&RepositoryScope>) -> MetricsSnapshot {
+        let mut snapshot = MetricsSnapshot {
+            files: self.files,
+            lines: self.lines,
+            ..MetricsSnapshot::default()
+        };
+        if self.source_files > 0 {
+            snapshot.avg_complexity = self.total_complexity as f64 / self.source_files as f64;
+        }
+        if self.source_files > self.test_files {
+            snapshot.test_to_code_ratio =
+                self.test_files as f64 / (self.source_files - self.test_files) as f64;
+        }
+        graph_metrics(db_path, snapshot, scope)
+    }
+}
+
+/// Fill in the symbol and dependency graph counts of `snapshot` from `db_path`, counting
+/// only symbols in `scope` and relationships and cycles involving them when given one
+fn graph_metrics(
+    db_path: &Path,
+    mut snapshot: MetricsSnapshot,
+    scope: Option<&RepositoryScope>,
+) -> MetricsSnapshot {
+    let in_scope = |path: &str| scope.is_none_or(|scope| scope.contains_path(path));
+    let symbol_db_path = db_path.join("symbols.kota");
+    if symbol_db_path.exists() {
+        match BinarySymbolReader::open(&symbol_db_path) {
+            Ok(reader) if scope.is_some() => {
+                snapshot.symbols = reader
+                    .iter_symbols()
+                    .filter(|symbol| {
+                        reader
+                            .get_symbol_file_path(symbol)
+                            .is_ok_and(|path| in_scope(&path))
+                    })
+                    .count()
+            }
+            Ok(reader) => snapshot.symbols = reader.symbol_count(),
+            Err(e) => tracing::warn!("Failed to read symbols database: {}", e),
+        }
+    }
+
+    let graph_path = db_path.join("dependency_graph.bin");
+    let graph = std::fs::read(&graph_path)
+        .map_err(anyhow::Error::from)
+        .and_then(|bytes| SerializableDependencyGraph::from_bytes(&bytes));
+    match graph {
+        Ok(serializable) => {
+            let scoped: HashSet<Uuid> = serializable
+                .nodes
+                .iter()
+                .filter(|node| in_scope(&node.file_path.to_string_lossy()))
+                .map(|node| node.symbol_id)
+                .collect();
+            snapshot.relationships = if scope.is_some() {
+                serializable
+                    .edges
+                    .iter()
+                    .filter(|edge| scoped.contains(&edge.from_id))
+                    .count()
+            } else {
+                serializable.stats.edge_count
+            };
+            snapshot.unused_symbols = unused_nodes(&serializable)
+                .iter()
+                .filter(|node| scoped.contains(&node.symbol_id))
+                .count();
+            match DependencyGraph::from_serializable(serializable) {
+                Ok(graph) => {
+                    snapshot.cycles = graph
+                        .find_circular_dependencies()
+                        .iter()
+                        .filter(|cycle| cycle.iter().any(|id| scoped.contains(id)))
+                        .count()
+                }
+                Err(e) => tracing::warn!("Failed to rebuild dependency graph: {}", e),
+            }
+        }
+        Err(e) if graph_path.exists() => {
+            tracing::warn!("Failed to read dependency graph: {}", e)
+        }
+        Err(_) => {}
+    }
+    snapshot
+}
+
+/// Symbols in the dependency graph nothing depends on, excluding entry points and tests
+pub(crate) fn unused_nodes(graph: &SerializableDependencyGraph) -> Vec<&SymbolNode> {
+    let targets: HashSet<Uuid> = graph.edges.iter().map(|e| e.to_id).collect();
+    graph
+        .nodes
+        .iter()
+        .filter(|node| !targets.contains(&node.symbol_id))
+        .filter(|node| !is_test_file(&node.file_path))
+        .filter(|node| {
+            !is_potential_entry_point(&node.qualified_name, Some(&node.symbol_type.to_string()))
+        })
+        .collect()
+}
+
+/// Definitions of the symbols counted as `unused_symbols` by [`index_metrics`],
+/// ordered by file and line
+pub fn find_unused_symbols(db_path: &Path) -> Result<Vec<UnusedSymbol>> {
+    let graph_path = db_path.join("dependency_graph.bin");
+    let bytes = std::fs::read(&graph_path).with_context(|| {
+        format!(
+            "Failed to read dependency graph {}; index the codebase with symbol extraction first",
+            graph_path.display()
+        )
+    })?;
+    let graph = SerializableDependencyGraph::from_bytes(&bytes)?;
+    let symbol_db_path = db_path.join("symbols.kota");
+    let reader = if symbol_db_path.exists() {
+        Some(BinarySymbolReader::open(&symbol_db_path)?)
+    } else {
+        None
+    };
+
+    let mut unused: Vec<UnusedSymbol> = unused_nodes(&graph)
+        .into_iter()
+        .map(|node| {
+            let packed = reader
+                .as_ref()
+                .and_then(|reader| reader.find_symbol(node.symbol_id));
+            UnusedSymbol {
+                qualified_name: node.qualified_name.clone(),
+                kind: node.symbol_type.to_string(),
+                file_path: node.file_path.to_string_lossy().to_string(),
+                start_line: packed.map(|symbol| symbol.start_line),
+                end_line: packed.map(|symbol| symbol.end_line),
+            }
+        })
+        .collect();
+    unused.sort_by(|a, b| (&a.file_path, a.start_line).cmp(&(&b.file_path, b.start_line)));
+    Ok(unused)
+}
+
+/// Number of ingested commits that changed each file, keyed by relative path without a
+/// leading `./` or `/`, and the number of commits counted
+pub fn churn_by_file(documents: &[Document]) -> (HashMap<String, usize>, usize) {
+    let mut churn: HashMap<String, usize> = HashMap::new();
+    let mut commits = 0;
+    for changed in documents.iter().filter_map(commit_changed_files) {
+        commits += 1;
+        for file in changed {
+            *churn.entry(treemap_path(&file)).or_insert(0) += 1;
+        }
+    }
+    (churn, commits)
+}
+
+/// Document path as shown in the treemap: relative, without a leading `./` or `/`
+fn treemap_path(path: &str) -> String {
+    path.trim_start_matches("./")
+        .trim_start_matches('/')
+        .to_string()
+}
+
+/// Build a directory node from files given as (path components below the node, language, metrics)
+fn build_treemap(
+    name: String,
+    path: String,
+    files: &[(Vec<&str>, &'static str, &TreemapMetrics)],
+    depth: usize,
+    leaf_max: &mut TreemapMetrics,
+) -> TreemapNode {
+    let mut metrics = TreemapMetrics::default();
+    for (_, _, file_metrics) in files {
+        metrics.add(file_metrics);
+    }
+    let mut node = TreemapNode {
+        name,
+        path,
+        node_type: "directory",
+        value: None,
+        language: None,
+        metrics,
+        children: Vec::new(),
+    };
+    if depth == 0 || files.is_empty() {
+        // Collapsed directory: drawn as a single leaf
+        leaf_max.max(&node.metrics);
+        node.value = Some(node.metrics.size);
+        return node;
+    }
+
+    let child_path = |child: &str| {
+        if node.path.is_empty() {
+            child.to_string()
+        } else {
+            format!("{}/{}", node.path, child)
+        }
+    };
+    let mut directories: std::collections::BTreeMap<&str, Vec<_>> =
+        std::collections::BTreeMap::new();
+    let mut children = Vec::new();
+    for (components, language, file_metrics) in files {
+        match components.split_first() {
+            Some((file, [])) => {
+                leaf_max.max(file_metrics);
+                children.push(TreemapNode {
+                    name: file.to_string(),
+                    path: child_path(file),
+                    node_type: "file",
+                    value: Some(file_metrics.size),
+                    language: Some(*language),
+                    metrics: (*file_metrics).clone(),
+                    children: Vec::new(),
+                });
+            }
+            Some((dir, rest)) => {
+                directories
+                    .entry(*dir)
+                    .or_default()
+                    .push((rest.to_vec(), *language, *file_metrics))
+            }
+            None => {}
+        }
+    }
+    for (dir, entries) in directories {
+        let path = child_path(dir);
+        children.push(build_treemap(
+            dir.to_string(),
+            path,
+            &entries,
+            depth - 1,
+            leaf_max,
+        ));
+    }
+    children.sort_by(|a, b| a.name.cmp(&b.name));
+    node.children = children;
+    node
+}
+
+/// Files listed under "Files Changed" in a commit document created by git ingestion
+pub(crate) fn commit_changed_files(document: &Document) -> Option<Vec<String>> {
+    if !document.tags.iter().any(|tag| tag.as_str() == "commit") {
+        return None;
+    }
+    let content = String::from_utf8_lossy(&document.content);
+    let (_, section) = content.split_once("## Files Changed\n")?;
+    let files: Vec<String> = section
+        .lines()
+        .map_while(|line| line.strip_prefix("- "))
+        .map(str::to_string)
+        .collect();
+    (!files.is_empty()).then_some(files)
+}
+
+/// Files changed together with any of `own_files`, as `(path, commits, share of commits)`
+fn co_changed_files(commits: &[Vec<String>], own_files: &[String]) -> Vec<(String, usize, f64)> {
+    let touching: Vec<&Vec<String>> = commits
+        .iter()
+        .filter(|files| files.len() <= MAX_CO_CHANGE_COMMIT_FILES)
+        .filter(|files| {
+            files
+                .iter()
+                .any(|file| own_files.iter().any(|own| paths_equivalent(own, file)))
+        })
+        .collect();
+
+    let mut counts: Vec<(String, usize)> = Vec::new();
+    for files in &touching {
+        for file in files.iter() {
+            if own_files.iter().any(|own| paths_equivalent(own, file)) {
+                continue;
+            }
+            match counts.iter_mut().find(|(path, _)| path == file) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((file.clone(), 1)),
+            }
         }
     }
+
+    let total = touching.len().max(1) as f64;
+    counts
+        .into_iter()
+        .map(|(path, count)| (path, count, count as f64 / total))
+        .collect()
 }
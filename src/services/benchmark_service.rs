@@ -10,6 +10,9 @@ use std::path::PathBuf;
 use std::time::Instant;
 
 use super::DatabaseAccess;
+use crate::metrics::performance::{
+    lock_contention, LockContentionStats, LockedResource, TimedLock,
+};
 use crate::QueryBuilder;
 
 /// Configuration options for benchmarking operations
@@ -116,6 +119,64 @@ pub struct BenchmarkTypeResult {
     pub operations_per_second: f64,
     pub success_rate: f64,
     pub errors: Vec<String>,
+    /// Lock waits recorded while the benchmark ran; reported by the concurrency benchmark
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lock_contention: Option<Vec<LockContentionStats>>,
+}
+
+impl BenchmarkTypeResult {
+    /// Summarize per-operation timings (in milliseconds) of a benchmark that ran for
+    /// `total_time_ms`
+    fn from_timings(mut timings: Vec<f64>, errors: Vec<String>, total_time_ms: u64) -> Self {
+        let operations = timings.len();
+        let average_time_ms = if !timings.is_empty() {
+            timings.iter().sum::<f64>() / timings.len() as f64
+        } else {
+            0.0
+        };
+
+        timings.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let median_time_ms = if !timings.is_empty() {
+            timings[timings.len() / 2]
+        } else {
+            0.0
+        };
+
+        let p95_time_ms = if timings.len() > 20 {
+            timings[(timings.len() * 95) / 100]
+        } else {
+            average_time_ms
+        };
+
+        let p99_time_ms = if timings.len() > 100 {
+            timings[(timings.len() * 99) / 100]
+        } else {
+            average_time_ms
+        };
+
+        let operations_per_second = if total_time_ms > 0 {
+            (operations as f64 * 1000.0) / total_time_ms as f64
+        } else {
+            0.0
+        };
+
+        Self {
+            operations,
+            total_time_ms,
+            average_time_ms,
+            median_time_ms,
+            p95_time_ms,
+            p99_time_ms,
+            operations_per_second,
+            success_rate: if operations > 0 {
+                1.0 - (errors.len() as f64 / operations as f64)
+            } else {
+                0.0
+            },
+            errors,
+            lock_contention: None,
+        }
+    }
 }
 
 /// Overall benchmark results
@@ -339,6 +400,14 @@ impl<'a> BenchmarkService<'a> {
                 let result = self.benchmark_search_operations(&options).await?;
                 results_by_type.insert("search".to_string(), result);
             }
+            "scan" => {
+                let result = self.benchmark_scan_operations(&options).await?;
+                results_by_type.insert("scan".to_string(), result);
+            }
+            "concurrency" => {
+                let result = self.benchmark_concurrent_queries(&options).await?;
+                results_by_type.insert("concurrency".to_string(), result);
+            }
             _ => {
                 // Run all benchmark types
                 let storage_result = self.benchmark_storage_operations(&options).await?;
@@ -352,6 +421,12 @@ impl<'a> BenchmarkService<'a> {
 
                 let search_result = self.benchmark_search_operations(&options).await?;
                 results_by_type.insert("search".to_string(), search_result);
+
+                let scan_result = self.benchmark_scan_operations(&options).await?;
+                results_by_type.insert("scan".to_string(), scan_result);
+
+                let concurrency_result = self.benchmark_concurrent_queries(&options).await?;
+                results_by_type.insert("concurrency".to_string(), concurrency_result);
             }
         }
 
@@ -547,18 +622,18 @@ impl<'a> BenchmarkService<'a> {
             match i % 3 {
                 0 => {
                     // Warm up storage: get some documents via primary index search
-                    let index_guard = primary_index.lock().await;
+                    let index_guard = primary_index.lock_timed(LockedResource::PrimaryIndex).await;
                     let query = QueryBuilder::new().with_limit(3)?.build()?;
                     if let Ok(doc_ids) = index_guard.search(&query).await {
                         if let Some(doc_id) = doc_ids.first() {
-                            let storage_guard = storage.lock().await;
+                            let storage_guard = storage.lock_timed(LockedResource::Storage).await;
                             let _ = storage_guard.get(doc_id).await;
                         }
                     }
                 }
                 1 => {
                     // Warm up primary index: wildcard search
-                    let index_guard = primary_index.lock().await;
+                    let index_guard = primary_index.lock_timed(LockedResource::PrimaryIndex).await;
                     let query = QueryBuilder::new().with_limit(5)?.build()?;
                     let _ = index_guard.search(&query).await;
                 }
@@ -566,7 +641,7 @@ impl<'a> BenchmarkService<'a> {
                     // Warm up trigram index: common search terms
                     let search_terms = ["async", "struct", "impl", "fn"];
                     let term = &search_terms[i % search_terms.len()];
-                    let index_guard = trigram_index.lock().await;
+                    let index_guard = trigram_index.lock_timed(LockedResource::TrigramIndex).await;
                     let query = QueryBuilder::new().with_text(*term)?.build()?;
                     let _ = index_guard.search(&query).await;
                 }
@@ -592,7 +667,7 @@ impl<'a> BenchmarkService<'a> {
         // Get some existing document IDs to benchmark retrieval
         let mut test_doc_ids = Vec::new();
         {
-            let index_guard = primary_index.lock().await;
+            let index_guard = primary_index.lock_timed(LockedResource::PrimaryIndex).await;
             let query = QueryBuilder::new().with_limit(20)?.build()?;
             if let Ok(doc_ids) = index_guard.search(&query).await {
                 test_doc_ids = doc_ids;
@@ -607,7 +682,7 @@ impl<'a> BenchmarkService<'a> {
             // Benchmark actual document retrieval
             let result = if !test_doc_ids.is_empty() {
                 let doc_id = &test_doc_ids[i % test_doc_ids.len()];
-                let storage_guard = storage.lock().await;
+                let storage_guard = storage.lock_timed(LockedResource::Storage).await;
                 storage_guard.get(doc_id).await
             } else {
                 // If no documents exist, this is expected - just return None
@@ -627,53 +702,11 @@ impl<'a> BenchmarkService<'a> {
         }
 
         let total_time_ms = start_time.elapsed().as_millis() as u64;
-        let operations = timings.len();
-        let average_time_ms = if !timings.is_empty() {
-            timings.iter().sum::<f64>() / timings.len() as f64
-        } else {
-            0.0
-        };
-
-        timings.sort_by(|a, b| a.partial_cmp(b).unwrap());
-        let median_time_ms = if !timings.is_empty() {
-            timings[timings.len() / 2]
-        } else {
-            0.0
-        };
-
-        let p95_time_ms = if timings.len() > 20 {
-            timings[(timings.len() * 95) / 100]
-        } else {
-            average_time_ms
-        };
-
-        let p99_time_ms = if timings.len() > 100 {
-            timings[(timings.len() * 99) / 100]
-        } else {
-            average_time_ms
-        };
-
-        let operations_per_second = if total_time_ms > 0 {
-            (operations as f64 * 1000.0) / total_time_ms as f64
-        } else {
-            0.0
-        };
-
-        Ok(BenchmarkTypeResult {
-            operations,
-            total_time_ms,
-            average_time_ms,
-            median_time_ms,
-            p95_time_ms,
-            p99_time_ms,
-            operations_per_second,
-            success_rate: if operations > 0 {
-                1.0 - (errors.len() as f64 / operations as f64)
-            } else {
-                0.0
-            },
+        Ok(BenchmarkTypeResult::from_timings(
+            timings,
             errors,
-        })
+            total_time_ms,
+        ))
     }
 
     async fn benchmark_index_operations(
@@ -702,13 +735,13 @@ impl<'a> BenchmarkService<'a> {
             // Alternate between primary index searches and trigram searches
             let result = if i % 2 == 0 {
                 // Benchmark primary index search operations
-                let index_guard = primary_index.lock().await;
+                let index_guard = primary_index.lock_timed(LockedResource::PrimaryIndex).await;
                 let query = QueryBuilder::new().with_limit(10)?.build()?;
                 index_guard.search(&query).await.map(|_| ())
             } else {
                 // Benchmark trigram search operations
                 let search_term = &search_terms[i % search_terms.len()];
-                let index_guard = trigram_index.lock().await;
+                let index_guard = trigram_index.lock_timed(LockedResource::TrigramIndex).await;
                 let query = QueryBuilder::new().with_text(*search_term)?.build()?;
                 index_guard.search(&query).await.map(|_| ())
             };
@@ -726,53 +759,11 @@ impl<'a> BenchmarkService<'a> {
         }
 
         let total_time_ms = start_time.elapsed().as_millis() as u64;
-        let operations = timings.len();
-        let average_time_ms = if !timings.is_empty() {
-            timings.iter().sum::<f64>() / timings.len() as f64
-        } else {
-            0.0
-        };
-
-        timings.sort_by(|a, b| a.partial_cmp(b).unwrap());
-        let median_time_ms = if !timings.is_empty() {
-            timings[timings.len() / 2]
-        } else {
-            0.0
-        };
-
-        let p95_time_ms = if timings.len() > 20 {
-            timings[(timings.len() * 95) / 100]
-        } else {
-            average_time_ms
-        };
-
-        let p99_time_ms = if timings.len() > 100 {
-            timings[(timings.len() * 99) / 100]
-        } else {
-            average_time_ms
-        };
-
-        let operations_per_second = if total_time_ms > 0 {
-            (operations as f64 * 1000.0) / total_time_ms as f64
-        } else {
-            0.0
-        };
-
-        Ok(BenchmarkTypeResult {
-            operations,
-            total_time_ms,
-            average_time_ms,
-            median_time_ms,
-            p95_time_ms,
-            p99_time_ms,
-            operations_per_second,
-            success_rate: if operations > 0 {
-                1.0 - (errors.len() as f64 / operations as f64)
-            } else {
-                0.0
-            },
+        Ok(BenchmarkTypeResult::from_timings(
+            timings,
             errors,
-        })
+            total_time_ms,
+        ))
     }
 
     async fn benchmark_query_operations(
@@ -819,14 +810,14 @@ impl<'a> BenchmarkService<'a> {
             let result = match i % 3 {
                 0 => {
                     // Benchmark wildcard queries
-                    let index_guard = primary_index.lock().await;
+                    let index_guard = primary_index.lock_timed(LockedResource::PrimaryIndex).await;
                     let query = QueryBuilder::new().with_limit(20)?.build()?;
                     index_guard.search(&query).await.map(|_| ())
                 }
                 1 => {
                     // Benchmark path pattern matching (use path patterns as text)
                     let pattern = &path_patterns[i % path_patterns.len()];
-                    let index_guard = primary_index.lock().await;
+                    let index_guard = primary_index.lock_timed(LockedResource::PrimaryIndex).await;
                     let query = QueryBuilder::new()
                         .with_text(*pattern)?
                         .with_limit(50)?
@@ -836,7 +827,7 @@ impl<'a> BenchmarkService<'a> {
                 _ => {
                     // Benchmark complex trigram queries
                     let complex_query = &complex_queries[i % complex_queries.len()];
-                    let index_guard = trigram_index.lock().await;
+                    let index_guard = trigram_index.lock_timed(LockedResource::TrigramIndex).await;
                     let query = QueryBuilder::new().with_text(*complex_query)?.build()?;
                     index_guard.search(&query).await.map(|_| ())
                 }
@@ -855,53 +846,66 @@ impl<'a> BenchmarkService<'a> {
         }
 
         let total_time_ms = start_time.elapsed().as_millis() as u64;
-        let operations = timings.len();
-        let average_time_ms = if !timings.is_empty() {
-            timings.iter().sum::<f64>() / timings.len() as f64
-        } else {
-            0.0
-        };
+        Ok(BenchmarkTypeResult::from_timings(
+            timings,
+            errors,
+            total_time_ms,
+        ))
+    }
 
-        timings.sort_by(|a, b| a.partial_cmp(b).unwrap());
-        let median_time_ms = if !timings.is_empty() {
-            timings[timings.len() / 2]
-        } else {
-            0.0
-        };
+    async fn benchmark_scan_operations(
+        &self,
+        options: &BenchmarkOptions,
+    ) -> Result<BenchmarkTypeResult> {
+        let mut timings = Vec::new();
+        let mut errors = Vec::new();
+        let start_time = Instant::now();
 
-        let p95_time_ms = if timings.len() > 20 {
-            timings[(timings.len() * 95) / 100]
-        } else {
-            average_time_ms
-        };
+        // Benchmark wildcard scans: selective patterns walk most of the primary index
+        // before the limit is reached, so each operation measures a full ordered traversal
+        let primary_index = self.database.primary_index();
 
-        let p99_time_ms = if timings.len() > 100 {
-            timings[(timings.len() * 99) / 100]
-        } else {
-            average_time_ms
-        };
+        let scan_patterns = [
+            "*.lock",
+            "*/migrations/*",
+            "*_generated.rs",
+            "*.min.js",
+            "vendor/*",
+        ];
 
-        let operations_per_second = if total_time_ms > 0 {
-            (operations as f64 * 1000.0) / total_time_ms as f64
-        } else {
-            0.0
-        };
+        // Each scan touches every entry, so run fewer of them than point operations
+        let operations_count = std::cmp::min(options.operations, 100);
+        for i in 0..operations_count {
+            let op_start = Instant::now();
 
-        Ok(BenchmarkTypeResult {
-            operations,
-            total_time_ms,
-            average_time_ms,
-            median_time_ms,
-            p95_time_ms,
-            p99_time_ms,
-            operations_per_second,
-            success_rate: if operations > 0 {
-                1.0 - (errors.len() as f64 / operations as f64)
-            } else {
-                0.0
-            },
+            let pattern = &scan_patterns[i % scan_patterns.len()];
+            let result = {
+                let index_guard = primary_index.lock_timed(LockedResource::PrimaryIndex).await;
+                let query = QueryBuilder::new()
+                    .with_text(*pattern)?
+                    .with_limit(1000)?
+                    .build()?;
+                index_guard.search(&query).await.map(|_| ())
+            };
+
+            match result {
+                Ok(_) => {
+                    timings.push(op_start.elapsed().as_micros() as f64 / 1000.0);
+                }
+                Err(e) => {
+                    errors.push(format!("Scan operation failed: {}", e));
+                    // Still record timing for failed operations
+                    timings.push(op_start.elapsed().as_micros() as f64 / 1000.0);
+                }
+            }
+        }
+
+        let total_time_ms = start_time.elapsed().as_millis() as u64;
+        Ok(BenchmarkTypeResult::from_timings(
+            timings,
             errors,
-        })
+            total_time_ms,
+        ))
     }
 
     async fn benchmark_search_operations(
@@ -914,7 +918,7 @@ impl<'a> BenchmarkService<'a> {
 
         // Benchmark actual search operations: content search and symbol search
         // This tests the real codebase intelligence SearchService performance
-        use super::{SearchOptions, SearchService, SymbolSearchOptions};
+        use super::{SearchGrouping, SearchOptions, SearchService, SymbolSearchOptions};
 
         let search_service = SearchService::new(self.database, self.db_path.join("symbols"));
 
@@ -943,6 +947,7 @@ impl<'a> BenchmarkService<'a> {
                     tags: None,
                     context: "minimal".to_string(), // Use minimal context for performance
                     quiet: true,
+                    group_by: SearchGrouping::default(),
                 };
                 search_service
                     .search_content(search_options)
@@ -976,52 +981,71 @@ impl<'a> BenchmarkService<'a> {
         }
 
         let total_time_ms = start_time.elapsed().as_millis() as u64;
-        let operations = timings.len();
-        let average_time_ms = if !timings.is_empty() {
-            timings.iter().sum::<f64>() / timings.len() as f64
-        } else {
-            0.0
-        };
+        Ok(BenchmarkTypeResult::from_timings(
+            timings,
+            errors,
+            total_time_ms,
+        ))
+    }
 
-        timings.sort_by(|a, b| a.partial_cmp(b).unwrap());
-        let median_time_ms = if !timings.is_empty() {
-            timings[timings.len() / 2]
-        } else {
-            0.0
-        };
+    /// Run content searches `concurrent_operations` at a time and report how long they
+    /// waited for the storage and index locks
+    async fn benchmark_concurrent_queries(
+        &self,
+        options: &BenchmarkOptions,
+    ) -> Result<BenchmarkTypeResult> {
+        use super::{SearchGrouping, SearchOptions, SearchService};
 
-        let p95_time_ms = if timings.len() > 20 {
-            timings[(timings.len() * 95) / 100]
-        } else {
-            average_time_ms
-        };
+        let search_service = SearchService::new(self.database, self.db_path.join("symbols"));
+        let queries = [
+            "async fn", "struct", "impl", "Result<", "Error", "Vec<", "HashMap", "tokio",
+        ];
+        let concurrency = options.concurrent_operations.unwrap_or(1).max(1);
+        let operations_count = std::cmp::min(options.operations, options.max_search_queries);
 
-        let p99_time_ms = if timings.len() > 100 {
-            timings[(timings.len() * 99) / 100]
-        } else {
-            average_time_ms
-        };
+        let contention_before = lock_contention();
+        let start_time = Instant::now();
+        let mut timings = Vec::with_capacity(operations_count);
+        let mut errors = Vec::new();
 
-        let operations_per_second = if total_time_ms > 0 {
-            (operations as f64 * 1000.0) / total_time_ms as f64
-        } else {
-            0.0
-        };
+        let mut issued = 0;
+        while issued < operations_count {
+            let batch = std::cmp::min(concurrency, operations_count - issued);
+            let searches = (issued..issued + batch).map(|i| {
+                let search_service = &search_service;
+                let search_options = SearchOptions {
+                    query: queries[i % queries.len()].to_string(),
+                    limit: 10,
+                    tags: None,
+                    context: "minimal".to_string(),
+                    quiet: true,
+                    group_by: SearchGrouping::default(),
+                };
+                async move {
+                    let op_start = Instant::now();
+                    let result = search_service.search_content(search_options).await;
+                    (op_start.elapsed().as_micros() as f64 / 1000.0, result)
+                }
+            });
+            for (elapsed_ms, result) in futures::future::join_all(searches).await {
+                timings.push(elapsed_ms);
+                if let Err(e) = result {
+                    errors.push(format!("Concurrent search failed: {}", e));
+                }
+            }
+            issued += batch;
+        }
+
+        let total_time_ms = start_time.elapsed().as_millis() as u64;
+        let lock_stats = lock_contention()
+            .iter()
+            .zip(&contention_before)
+            .map(|(after, before)| after.since(before))
+            .collect();
 
         Ok(BenchmarkTypeResult {
-            operations,
-            total_time_ms,
-            average_time_ms,
-            median_time_ms,
-            p95_time_ms,
-            p99_time_ms,
-            operations_per_second,
-            success_rate: if operations > 0 {
-                1.0 - (errors.len() as f64 / operations as f64)
-            } else {
-                0.0
-            },
-            errors,
+            lock_contention: Some(lock_stats),
+            ..BenchmarkTypeResult::from_timings(timings, errors, total_time_ms)
         })
     }
 
@@ -1069,6 +1093,15 @@ impl<'a> BenchmarkService<'a> {
                 "   Success rate: {:.1}%\n",
                 result.success_rate * 100.0
             ));
+            for lock in result.lock_contention.iter().flatten() {
+                output.push_str(&format!(
+                    "   {} lock: {} of {} acquisitions waited, avg wait {:.2}ms\n",
+                    lock.resource.as_str(),
+                    lock.contended,
+                    lock.acquisitions,
+                    lock.average_contended_wait().as_secs_f64() * 1000.0
+                ));
+            }
             output.push('\n');
         }
 
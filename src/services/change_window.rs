@@ -0,0 +1,293 @@
+// Files changed within a window of ingested git history
+//
+// `--changed-since` narrows searches to code that changed recently, which is where a
+// fresh regression usually hides. The window starts at an ingested commit (given by
+// SHA prefix), a duration back from now (`7d`, `12h`) or a date. Changed files come
+// from the "Files Changed" lists of commit documents created by git ingestion, so
+// only history that was indexed with the codebase is considered.
+
+use anyhow::{bail, Result};
+use chrono::{DateTime, Duration, NaiveDate, NaiveDateTime, Utc};
+use std::collections::HashSet;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use crate::index_snapshots::parse_as_of_date;
+use crate::metrics::performance::{LockedResource, TimedLock};
+use crate::services::analysis_service::commit_changed_files;
+use crate::{Document, Storage};
+
+/// Shortest SHA prefix accepted as a window start
+const MIN_SHA_PREFIX: usize = 4;
+
+/// Files changed by ingested commits after a point in time
+#[derive(Debug, Clone)]
+pub struct ChangedFiles {
+    /// Start of the window; commits at or before it are not counted
+    pub since: DateTime<Utc>,
+    /// Ingested commits within the window
+    pub commits: usize,
+    /// Repository-relative paths changed by those commits
+    files: HashSet<String>,
+}
+
+/// Commit document parsed for its SHA, date and changed files
+struct IngestedCommit {
+    sha: String,
+    timestamp: DateTime<Utc>,
+    files: Vec<String>,
+}
+
+impl ChangedFiles {
+    /// Resolve `changed_since` against the commit documents in `storage`
+    pub async fn load(storage: &Arc<Mutex<dyn Storage>>, changed_since: &str) -> Result<Self> {
+        let storage = storage.lock_timed(LockedResource::Storage).await;
+        let ids: Vec<_> = storage
+            .list_metadata()
+            .await?
+            .into_iter()
+            .filter(|metadata| metadata.tags.iter().any(|tag| tag.as_str() == "commit"))
+            .map(|metadata| metadata.id)
+            .collect();
+        let documents: Vec<Document> = storage
+            .get_many(&ids)
+            .await?
+            .into_iter()
+            .flatten()
+            .collect();
+        Self::from_commit_documents(&documents, changed_since, Utc::now())
+    }
+
+    /// Resolve `changed_since` (SHA prefix, duration or date) against commit documents
+    pub fn from_commit_documents(
+        documents: &[Document],
+        changed_since: &str,
+        now: DateTime<Utc>,
+    ) -> Result<Self> {
+        let commits: Vec<IngestedCommit> = documents.iter().filter_map(parse_commit).collect();
+        if commits.is_empty() {
+            bail!(
+                "No git history has been ingested, so --changed-since has nothing to compare \
+                 against. Index the repository with its commit history:\n\
+                 kotadb index-codebase --include-commits /path/to/repo"
+            );
+        }
+        let since = resolve_window_start(&commits, changed_since.trim(), now)?;
+
+        let mut files = HashSet::new();
+        let mut count = 0;
+        for commit in commits.iter().filter(|c| c.timestamp > since) {
+            count += 1;
+            files.extend(commit.files.iter().map(|f| normalize(f).to_string()));
+        }
+        Ok(Self {
+            since,
+            commits: count,
+            files,
+        })
+    }
+
+    /// Number of distinct files changed within the window
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// One-line description of the window, e.g. for CLI output
+    pub fn describe(&self) -> String {
+        format!(
+            "{} files changed by {} commits since {}",
+            self.len(),
+            self.commits,
+            self.since.format("%Y-%m-%d %H:%M UTC")
+        )
+    }
+
+    /// Whether `path` was changed within the window
+    ///
+    /// Stored document paths carry a repository prefix (`repos/<name>/files/...`), so a
+    /// changed file also matches a path it ends with on a `/` boundary.
+    pub fn contains(&self, path: &str) -> bool {
+        let path = normalize(path);
+        self.files.contains(path)
+            || self.files.iter().any(|file| {
+                path.strip_suffix(file.as_str())
+                    .is_some_and(|prefix| prefix.ends_with('/'))
+            })
+    }
+}
+
+fn parse_commit(document: &Document) -> Option<IngestedCommit> {
+    let files = commit_changed_files(document)?;
+    let content = String::from_utf8_lossy(&document.content);
+    let field = |prefix: &str| {
+        content
+            .lines()
+            .find_map(|line| line.trim().strip_prefix(prefix))
+            .map(str::trim)
+    };
+    let sha = field("- **SHA**: ")?.to_lowercase();
+    let date = field("**Date**: ")?;
+    let timestamp =
+        NaiveDateTime::parse_from_str(date.trim_end_matches(" UTC"), "%Y-%m-%d %H:%M:%S")
+            .ok()?
+            .and_utc();
+    Some(IngestedCommit {
+        sha,
+        timestamp,
+        files,
+    })
+}
+
+fn resolve_window_start(
+    commits: &[IngestedCommit],
+    changed_since: &str,
+    now: DateTime<Utc>,
+) -> Result<DateTime<Utc>> {
+    if changed_since.len() >= MIN_SHA_PREFIX && changed_since.chars().all(|c| c.is_ascii_hexdigit())
+    {
+        let prefix = changed_since.to_lowercase();
+        let matching: Vec<&IngestedCommit> = commits
+            .iter()
+            .filter(|c| c.sha.starts_with(&prefix))
+            .collect();
+        match matching.as_slice() {
+            [commit] => return Ok(commit.timestamp),
+            [] => {}
+            _ => bail!(
+                "'{}' matches {} ingested commits; use a longer SHA prefix",
+                changed_since,
+                matching.len()
+            ),
+        }
+    }
+    if let Some(duration) = parse_duration(changed_since) {
+        return Ok(now - duration);
+    }
+    // A bare date starts the window at the beginning of that day
+    if let Ok(date) = NaiveDate::parse_from_str(changed_since, "%Y-%m-%d") {
+        if let Some(start) = date.and_hms_opt(0, 0, 0) {
+            return Ok(start.and_utc());
+        }
+    }
+    if let Some(time) = parse_as_of_date(changed_since) {
+        return Ok(time);
+    }
+    bail!(
+        "'{}' is not a duration (e.g. 7d, 12h), a date or the SHA of an ingested commit",
+        changed_since
+    )
+}
+
+/// Parse `<n>m`, `<n>h`, `<n>d` or `<n>w`
+fn parse_duration(text: &str) -> Option<Duration> {
+    let unit = text.chars().last()?;
+    let amount: i64 = text[..text.len() - unit.len_utf8()].parse().ok()?;
+    match unit {
+        'm' => Duration::try_minutes(amount),
+        'h' => Duration::try_hours(amount),
+        'd' => Duration::try_days(amount),
+        'w' => Duration::try_weeks(amount),
+        _ => None,
+    }
+}
+
+fn normalize(path: &str) -> &str {
+    path.trim_start_matches("./").trim_matches('/')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{ValidatedDocumentId, ValidatedPath, ValidatedTag, ValidatedTitle};
+
+    fn commit(sha: &str, date: &str, files: &[&str]) -> Document {
+        let mut content = format!(
+            "# Commit {}\n\n**Author**: Dev <dev@example.com>\n**Date**: {} UTC\n\n\
+             ## Metadata\n- **SHA**: {}\n\n## Files Changed\n",
+            &sha[..8],
+            date,
+            sha
+        );
+        for file in files {
+            content.push_str(&format!("- {}\n", file));
+        }
+        let now = Utc::now();
+        Document::new(
+            ValidatedDocumentId::new(),
+            ValidatedPath::new(format!("repos/app/commits/{}.md", &sha[..8])).unwrap(),
+            ValidatedTitle::new(format!("Commit {}", &sha[..8])).unwrap(),
+            content.into_bytes(),
+            vec![ValidatedTag::new("commit").unwrap()],
+            now,
+            now,
+        )
+    }
+
+    fn history() -> Vec<Document> {
+        vec![
+            commit(
+                "aaaa1111aaaa1111aaaa1111aaaa1111aaaa1111",
+                "2026-10-01 09:00:00",
+                &["src/old.rs"],
+            ),
+            commit(
+                "bbbb2222bbbb2222bbbb2222bbbb2222bbbb2222",
+                "2026-10-10 09:00:00",
+                &["src/config.rs", "README.md"],
+            ),
+            commit(
+                "cccc3333cccc3333cccc3333cccc3333cccc3333",
+                "2026-10-14 09:00:00",
+                &["src/search.rs"],
+            ),
+        ]
+    }
+
+    #[test]
+    fn resolves_durations_shas_and_dates() -> Result<()> {
+        let now = NaiveDate::from_ymd_opt(2026, 10, 15)
+            .unwrap()
+            .and_hms_opt(12, 0, 0)
+            .unwrap()
+            .and_utc();
+        let documents = history();
+
+        let week = ChangedFiles::from_commit_documents(&documents, "7d", now)?;
+        assert_eq!(week.commits, 2);
+        assert!(week.contains("src/config.rs"));
+        assert!(!week.contains("src/old.rs"));
+
+        // Commits after the given one, not the commit itself
+        let after = ChangedFiles::from_commit_documents(&documents, "BBBB22", now)?;
+        assert_eq!(after.commits, 1);
+        assert!(after.contains("src/search.rs"));
+        assert!(!after.contains("src/config.rs"));
+
+        let dated = ChangedFiles::from_commit_documents(&documents, "2026-10-01", now)?;
+        assert_eq!(dated.commits, 3);
+        assert_eq!(dated.len(), 4);
+
+        let error = ChangedFiles::from_commit_documents(&documents, "yesterday", now).unwrap_err();
+        assert!(error.to_string().contains("not a duration"), "{error}");
+        assert!(ChangedFiles::from_commit_documents(&[], "7d", now).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn matches_stored_paths_on_component_boundaries() {
+        let changed = ChangedFiles {
+            since: Utc::now(),
+            commits: 1,
+            files: ["src/config.rs".to_string()].into_iter().collect(),
+        };
+
+        assert!(changed.contains("./src/config.rs"));
+        assert!(changed.contains("repos/app/files/src/config.rs"));
+        assert!(!changed.contains("repos/app/files/other_src/config.rs"));
+        assert!(!changed.contains("src/config.rs.bak"));
+    }
+}
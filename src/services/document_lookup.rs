@@ -0,0 +1,216 @@
+// Document lookup by file path
+//
+// Services that need the stored source for a handful of paths (excerpts, file
+// content, similarity targets) resolve them here instead of listing storage:
+// the shared path cache is consulted first, then the primary index.
+
+use anyhow::Result;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::{Mutex, RwLock};
+
+use crate::metrics::performance::{LockedResource, TimedLock};
+use crate::{
+    path_utils::paths_equivalent, Document, DocumentSummary, Index, Query, Storage,
+    ValidatedDocumentId,
+};
+
+/// Candidate documents fetched when a path suffix matches several indexed files
+const MAX_PATH_CANDIDATES: usize = 16;
+
+/// Fetch the stored document for `path` without scanning storage
+///
+/// `path` may be the stored document path or a repository-relative suffix of it
+/// (`src/lib.rs` for `repos/<name>/files/src/lib.rs`). An exact match wins over a
+/// suffix match. Resolved documents are remembered in `path_cache` under their
+/// stored path; stale cache entries are dropped.
+pub async fn document_by_path(
+    storage: &Arc<Mutex<dyn Storage>>,
+    primary_index: &Arc<Mutex<dyn Index>>,
+    path_cache: &Arc<RwLock<HashMap<String, ValidatedDocumentId>>>,
+    path: &str,
+) -> Result<Option<Document>> {
+    match summary_by_path(storage, primary_index, path_cache, path).await? {
+        Some((id, _)) => {
+            storage
+                .lock_timed(LockedResource::Storage)
+                .await
+                .get(&id)
+                .await
+        }
+        None => Ok(None),
+    }
+}
+
+/// Fetch the stored documents for several paths, in the order of `paths`
+///
+/// Paths are resolved like [`document_by_path`]; the resolved documents are then
+/// read with a single [`Storage::get_many`] call.
+pub async fn documents_by_paths(
+    storage: &Arc<Mutex<dyn Storage>>,
+    primary_index: &Arc<Mutex<dyn Index>>,
+    path_cache: &Arc<RwLock<HashMap<String, ValidatedDocumentId>>>,
+    paths: &[&str],
+) -> Result<Vec<Option<Document>>> {
+    let mut ids = Vec::with_capacity(paths.len());
+    for path in paths {
+        ids.push(
+            summary_by_path(storage, primary_index, path_cache, path)
+                .await?
+                .map(|(id, _)| id),
+        );
+    }
+
+    let found: Vec<ValidatedDocumentId> = ids.iter().flatten().copied().collect();
+    let mut documents = storage
+        .lock_timed(LockedResource::Storage)
+        .await
+        .get_many(&found)
+        .await?
+        .into_iter();
+    Ok(ids
+        .into_iter()
+        .map(|id| id.and_then(|_| documents.next().flatten()))
+        .collect())
+}
+
+/// Resolve `path` like [`document_by_path`], reading only storage metadata
+pub async fn summary_by_path(
+    storage: &Arc<Mutex<dyn Storage>>,
+    primary_index: &Arc<Mutex<dyn Index>>,
+    path_cache: &Arc<RwLock<HashMap<String, ValidatedDocumentId>>>,
+    path: &str,
+) -> Result<Option<(ValidatedDocumentId, DocumentSummary)>> {
+    let cached = path_cache.read().await.get(path).copied();
+    if let Some(id) = cached {
+        match storage
+            .lock_timed(LockedResource::Storage)
+            .await
+            .summary(&id)
+            .await?
+        {
+            Some(summary) if summary.path.as_str() == path => return Ok(Some((id, summary))),
+            _ => {
+                path_cache.write().await.remove(path);
+            }
+        }
+    }
+
+    let suffix = path.trim_start_matches("./").trim_start_matches('/');
+    if suffix.is_empty() || suffix.contains('*') {
+        return Ok(None);
+    }
+    let query = Query::new(None, None, Some(format!("*{suffix}")), MAX_PATH_CANDIDATES)?;
+    let candidates = primary_index
+        .lock_timed(LockedResource::PrimaryIndex)
+        .await
+        .search(&query)
+        .await?;
+
+    let mut best: Option<(ValidatedDocumentId, DocumentSummary)> = None;
+    {
+        let storage = storage.lock_timed(LockedResource::Storage).await;
+        for id in candidates {
+            let Some(summary) = storage.summary(&id).await? else {
+                continue;
+            };
+            if summary.path.as_str() == path {
+                best = Some((id, summary));
+                break;
+            }
+            if best.is_none() && paths_equivalent(summary.path.as_str(), path) {
+                best = Some((id, summary));
+            }
+        }
+    }
+
+    if let Some((id, summary)) = &best {
+        path_cache
+            .write()
+            .await
+            .insert(summary.path.to_string(), *id);
+    }
+    Ok(best)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create_file_storage, create_primary_index, DocumentBuilder};
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn resolves_repository_relative_paths_through_the_primary_index() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let storage: Arc<Mutex<dyn Storage>> = Arc::new(Mutex::new(
+            create_file_storage(temp_dir.path().join("storage").to_str().unwrap(), None).await?,
+        ));
+        let primary_index: Arc<Mutex<dyn Index>> = Arc::new(Mutex::new(
+            create_primary_index(temp_dir.path().join("index").to_str().unwrap(), None).await?,
+        ));
+        let path_cache = Arc::new(RwLock::new(HashMap::new()));
+
+        for path in ["repos/app/files/src/lib.rs", "repos/app/files/src/main.rs"] {
+            let document = DocumentBuilder::new()
+                .path(path)?
+                .title(path)?
+                .content(format!("// {path}").as_bytes())
+                .build()?;
+            primary_index
+                .lock_timed(LockedResource::PrimaryIndex)
+                .await
+                .insert(document.id, document.path.clone())
+                .await?;
+            storage
+                .lock_timed(LockedResource::Storage)
+                .await
+                .insert(document)
+                .await?;
+        }
+
+        let found = document_by_path(&storage, &primary_index, &path_cache, "src/lib.rs")
+            .await?
+            .expect("suffix resolves to the stored document");
+        assert_eq!(found.path.as_str(), "repos/app/files/src/lib.rs");
+        assert!(path_cache
+            .read()
+            .await
+            .contains_key("repos/app/files/src/lib.rs"));
+
+        let cached = document_by_path(
+            &storage,
+            &primary_index,
+            &path_cache,
+            "repos/app/files/src/lib.rs",
+        )
+        .await?;
+        assert_eq!(cached.map(|d| d.id), Some(found.id));
+
+        assert!(
+            document_by_path(&storage, &primary_index, &path_cache, "src/missing.rs")
+                .await?
+                .is_none()
+        );
+
+        let batch = documents_by_paths(
+            &storage,
+            &primary_index,
+            &path_cache,
+            &["src/main.rs", "src/missing.rs", "src/lib.rs"],
+        )
+        .await?;
+        let paths: Vec<Option<&str>> = batch
+            .iter()
+            .map(|doc| doc.as_ref().map(|d| d.path.as_str()))
+            .collect();
+        assert_eq!(
+            paths,
+            [
+                Some("repos/app/files/src/main.rs"),
+                None,
+                Some("repos/app/files/src/lib.rs")
+            ]
+        );
+        Ok(())
+    }
+}
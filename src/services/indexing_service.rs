@@ -3,16 +3,33 @@
 // This service extracts all indexing logic from main.rs and ManagementService
 // to enable consistent indexing operations across CLI, MCP, and future interfaces.
 
-use anyhow::{Context, Result};
+use anyhow::Result;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use tracing::{debug, warn};
+use tracing::warn;
 
 type ProgressNotifier = Arc<dyn Fn(&str) + Send + Sync>;
 
+use crate::config_usages::index_config_usages;
+#[cfg(feature = "tree-sitter-parsing")]
+use crate::contracts::Document;
+#[cfg(feature = "tree-sitter-parsing")]
+use crate::feature_flags::index_feature_flags;
 use crate::git::{IngestionConfig, ProgressCallback, RepositoryIngester};
-
-use super::DatabaseAccess;
+use crate::ingestion_sandbox::IngestionSandbox;
+#[cfg(feature = "tree-sitter-parsing")]
+use crate::log_statements::index_log_statements;
+#[cfg(feature = "tree-sitter-parsing")]
+use crate::metrics::performance::{LockedResource, TimedLock};
+use crate::repository_links::RepositoryLinks;
+#[cfg(feature = "tree-sitter-parsing")]
+use crate::sql_usages::index_sql_usages;
+use crate::trends::{MetricsSnapshot, TrendHistory};
+
+use super::{
+    analysis_service::{repository_index_metrics, storage_index_metrics},
+    DatabaseAccess, RepositoryScope,
+};
 
 /// Configuration options for codebase indexing operations
 #[derive(Debug, Clone, serde::Serialize)]
@@ -139,6 +156,7 @@ pub struct IndexingService<'a> {
     database: &'a dyn DatabaseAccess,
     db_path: PathBuf,
     progress_callback: Option<ProgressNotifier>,
+    sandbox: Option<IngestionSandbox>,
 }
 
 impl<'a> IndexingService<'a> {
@@ -148,6 +166,7 @@ impl<'a> IndexingService<'a> {
             database,
             db_path,
             progress_callback: None,
+            sandbox: None,
         }
     }
 
@@ -158,6 +177,13 @@ impl<'a> IndexingService<'a> {
         self
     }
 
+    /// Run repository ingestion, and with it tree-sitter parsing, in `sandbox`
+    #[must_use]
+    pub fn with_sandbox(mut self, sandbox: Option<IngestionSandbox>) -> Self {
+        self.sandbox = sandbox;
+        self
+    }
+
     /// Index a complete codebase with symbol extraction and relationship analysis
     ///
     /// This method extracts the complex indexing logic from main.rs, providing
@@ -253,6 +279,10 @@ impl<'a> IndexingService<'a> {
         };
 
         ingestion_options.include_paths = options.include_paths.clone();
+        ingestion_options.ignore_patterns =
+            crate::project_init::IgnoreRules::load(&options.repo_path)?
+                .patterns()
+                .to_vec();
 
         #[cfg(feature = "tree-sitter-parsing")]
         {
@@ -293,39 +323,37 @@ impl<'a> IndexingService<'a> {
         );
         let ingester = RepositoryIngester::new(config.clone());
         let storage_arc = self.database.storage();
-        let mut storage = storage_arc.lock().await;
-
-        // Choose the appropriate ingestion method based on symbol extraction setting
-        #[cfg(feature = "tree-sitter-parsing")]
-        let result = if should_extract_symbols {
-            // Use binary symbol storage with relationship extraction for complete analysis
-            let symbol_db_path = self.db_path.join("symbols.kota");
-            let graph_db_path = self.db_path.join("dependency_graph.bin");
-            debug!("Invoking ingest_with_binary_symbols_and_relationships");
-            ingester
-                .ingest_with_binary_symbols_and_relationships(
-                    &options.repo_path,
-                    &mut *storage,
-                    &symbol_db_path,
-                    &graph_db_path,
-                    Some(progress_callback),
-                )
-                .await
-                .context("Failed to ingest repository with symbol and relationship extraction")
-        } else {
-            debug!("Invoking ingest_with_progress without symbol extraction");
-            ingester
-                .ingest_with_progress(&options.repo_path, &mut *storage, Some(progress_callback))
-                .await
-                .context("Failed to ingest repository without symbol extraction")
+        let mut storage = storage_arc.lock_timed(LockedResource::Storage).await;
+
+        // Tree-sitter runs over the repository's files here, in a sandboxed process when
+        // one is configured
+        let result = match &self.sandbox {
+            Some(sandbox) => {
+                sandbox
+                    .ingest(
+                        &config,
+                        &options.repo_path,
+                        &mut *storage,
+                        &self.db_path,
+                        should_extract_symbols,
+                        Some(progress_callback),
+                    )
+                    .await
+            }
+            None => {
+                ingester
+                    .ingest_codebase(
+                        &options.repo_path,
+                        &mut *storage,
+                        &self.db_path,
+                        should_extract_symbols,
+                        Some(progress_callback),
+                    )
+                    .await
+            }
         };
 
-        #[cfg(not(feature = "tree-sitter-parsing"))]
-        let result = ingester
-            .ingest_with_progress(&options.repo_path, &mut *storage, Some(progress_callback))
-            .await
-            .context("Failed to ingest repository without tree-sitter parsing");
-
+        let document_prefix;
         let (files_processed, symbols_extracted, relationships_found) = match result {
             Ok(ingestion_result) => {
                 let files_proc = ingestion_result.files_ingested;
@@ -355,6 +383,15 @@ impl<'a> IndexingService<'a> {
                     ));
                 }
 
+                // Remote and commit for permalinks in search, symbol and caller results
+                if let Err(e) = ingester
+                    .repository_link(&options.repo_path)
+                    .and_then(|link| RepositoryLinks::record(&self.db_path, link))
+                {
+                    warn!("Failed to record repository link: {}", e);
+                }
+
+                document_prefix = ingestion_result.document_prefix;
                 (files_proc, symbols_ext, relationships_found)
             }
             Err(e) => {
@@ -437,12 +474,12 @@ impl<'a> IndexingService<'a> {
             }
 
             // Implement index rebuilding directly using the DatabaseAccess trait
-            // Get all documents from storage
-            let all_docs = {
+            // Count documents up front for progress; content is read one page at a time
+            let total_docs = {
                 let storage = self.database.storage();
-                let storage = storage.lock().await;
-                match storage.list_all().await {
-                    Ok(docs) => docs,
+                let storage = storage.lock_timed(LockedResource::Storage).await;
+                match storage.list_metadata().await {
+                    Ok(metadata) => metadata.len(),
                     Err(e) => {
                         let error = format!("Failed to list documents for index rebuild: {}", e);
                         errors.push(error.clone());
@@ -462,116 +499,68 @@ impl<'a> IndexingService<'a> {
                 }
             };
 
-            let total_docs = all_docs.len();
             if total_docs == 0 {
                 if !options.quiet {
                     formatted_output
                         .push_str("⚠️ No documents found in storage, skipping index rebuild.\n");
                 }
             } else {
-                // Process documents in batches for better performance
-                const BATCH_SIZE: usize = 100;
+                const BATCH_SIZE: usize = crate::index_rebuild::REBUILD_PAGE_SIZE;
                 if let Some(callback) = &progress_notifier {
                     callback(&format!(
                         "rebuild_start documents:{} batch_size:{}",
                         total_docs, BATCH_SIZE
                     ));
                 }
-                let mut processed = 0;
-
-                // Process in chunks to reduce lock contention and prevent OOM
-                for chunk in all_docs.chunks(BATCH_SIZE) {
-                    // Collect document data for this batch (including content for trigram indexing)
-                    let mut batch_entries = Vec::with_capacity(chunk.len());
-                    for doc in chunk {
-                        let doc_id = doc.id;
-                        let doc_path = match crate::types::ValidatedPath::new(doc.path.to_string())
-                        {
-                            Ok(path) => path,
-                            Err(e) => {
-                                let error = format!("Invalid document path: {}", e);
-                                errors.push(error.clone());
-                                if !options.quiet {
-                                    formatted_output.push_str(&format!("⚠️ Warning: {}\n", error));
-                                }
-                                continue; // Skip this document
-                            }
-                        };
-                        batch_entries.push((doc_id, doc_path, doc.content.clone()));
-                    }
 
-                    // Insert batch into primary index (path-based)
-                    {
-                        let primary_index_arc = self.database.primary_index();
-                        let mut primary_index = primary_index_arc.lock().await;
-                        for (doc_id, doc_path, _) in &batch_entries {
-                            if let Err(e) = primary_index.insert(*doc_id, doc_path.clone()).await {
-                                let error =
-                                    format!("Failed to insert document into primary index: {}", e);
-                                errors.push(error.clone());
-                                if !options.quiet {
-                                    formatted_output.push_str(&format!("⚠️ Warning: {}\n", error));
-                                }
-                            }
+                // Storage reads, tokenization and both index writers run as a pipeline
+                let prior_errors = errors.len();
+                let report = crate::index_rebuild::rebuild_indices(
+                    self.database.storage(),
+                    self.database.primary_index(),
+                    self.database.trigram_index(),
+                    |progress| {
+                        if let Some(callback) = &progress_notifier {
+                            callback(&format!(
+                                "rebuild_progress processed:{} total:{} batch_size:{} errors:{}",
+                                progress.processed,
+                                total_docs,
+                                progress.batch_size,
+                                prior_errors + progress.errors
+                            ));
                         }
-                    }
-
-                    // Insert batch into trigram index (content-based)
-                    {
-                        let trigram_index_arc = self.database.trigram_index();
-                        let mut trigram_index = trigram_index_arc.lock().await;
-                        for (doc_id, doc_path, content) in &batch_entries {
-                            if let Err(e) = trigram_index
-                                .insert_with_content(*doc_id, doc_path.clone(), content)
-                                .await
-                            {
-                                let error =
-                                    format!("Failed to insert document into trigram index: {}", e);
-                                errors.push(error.clone());
-                                if !options.quiet {
-                                    formatted_output.push_str(&format!("⚠️ Warning: {}\n", error));
-                                }
+                    },
+                )
+                .await;
+                let processed = match report {
+                    Ok(report) => {
+                        for error in &report.errors {
+                            if !options.quiet {
+                                formatted_output.push_str(&format!("⚠️ Warning: {}\n", error));
                             }
                         }
-                    }
-
-                    processed += batch_entries.len();
-                    if let Some(callback) = &progress_notifier {
-                        callback(&format!(
-                            "rebuild_progress processed:{} total:{} batch_size:{} errors:{}",
-                            processed,
-                            total_docs,
-                            batch_entries.len(),
-                            errors.len()
-                        ));
-                    }
-
-                    // Periodic flush for large datasets
-                    if processed % 500 == 0 || processed >= total_docs {
-                        {
-                            let primary_index_arc = self.database.primary_index();
-                            let mut primary_index = primary_index_arc.lock().await;
-                            if let Err(e) = primary_index.flush().await {
-                                let error = format!("Failed to flush primary index: {}", e);
-                                errors.push(error.clone());
-                                if !options.quiet {
-                                    formatted_output.push_str(&format!("⚠️ Warning: {}\n", error));
-                                }
+                        errors.extend(report.errors.iter().cloned());
+                        if !options.quiet {
+                            formatted_output.push_str(&format!(
+                                "   ⏱️  Rebuild: {:.2}s with {} tokenizer workers\n",
+                                report.elapsed.as_secs_f64(),
+                                report.workers
+                            ));
+                            for line in report.stage_summary().lines() {
+                                formatted_output.push_str(&format!("      {}\n", line));
                             }
                         }
-                        {
-                            let trigram_index_arc = self.database.trigram_index();
-                            let mut trigram_index = trigram_index_arc.lock().await;
-                            if let Err(e) = trigram_index.flush().await {
-                                let error = format!("Failed to flush trigram index: {}", e);
-                                errors.push(error.clone());
-                                if !options.quiet {
-                                    formatted_output.push_str(&format!("⚠️ Warning: {}\n", error));
-                                }
-                            }
+                        report.processed()
+                    }
+                    Err(e) => {
+                        let error = format!("Index rebuild failed: {}", e);
+                        errors.push(error.clone());
+                        if !options.quiet {
+                            formatted_output.push_str(&format!("❌ {}\n", error));
                         }
+                        0
                     }
-                }
+                };
 
                 if !options.quiet {
                     formatted_output.push_str(
@@ -589,17 +578,108 @@ impl<'a> IndexingService<'a> {
             }
         }
 
+        let total_time_ms = start_time.elapsed().as_millis() as u64;
+        match self.run_metrics(&document_prefix).await {
+            Ok(metrics) => {
+                if let Err(e) =
+                    self.record_metrics_snapshot(&options.repo_path, total_time_ms, metrics)
+                {
+                    warn!("Failed to record metrics for trend history: {}", e);
+                }
+            }
+            Err(e) => warn!("Failed to compute metrics for trend history: {}", e),
+        }
+        #[cfg(feature = "tree-sitter-parsing")]
+        if should_extract_symbols {
+            match self.source_documents().await {
+                Ok(documents) => {
+                    if let Err(e) = index_log_statements(&self.db_path, &documents) {
+                        warn!("Failed to index log statements: {}", e);
+                    }
+                    if let Err(e) =
+                        index_feature_flags(&self.db_path, &options.repo_path, &documents)
+                    {
+                        warn!("Failed to index feature flags: {}", e);
+                    }
+                    if let Err(e) = index_config_usages(&self.db_path, &documents) {
+                        warn!("Failed to index configuration keys: {}", e);
+                    }
+                    if let Err(e) = index_sql_usages(&self.db_path, &documents) {
+                        warn!("Failed to index SQL statements: {}", e);
+                    }
+                }
+                Err(e) => warn!("Failed to list documents after indexing: {}", e),
+            }
+        }
+
         Ok(IndexResult {
             files_processed,
             symbols_extracted,
             relationships_found,
-            total_time_ms: start_time.elapsed().as_millis() as u64,
+            total_time_ms,
             success: true,
             formatted_output,
             errors,
         })
     }
 
+    /// Metrics of the repository a run stored under `document_prefix`, or of the whole
+    /// database when ingestion reported no prefix
+    async fn run_metrics(&self, document_prefix: &str) -> Result<MetricsSnapshot> {
+        let storage = self.database.storage();
+        if document_prefix.is_empty() {
+            return storage_index_metrics(&self.db_path, &storage).await;
+        }
+        let scope = RepositoryScope::load(&storage, &[document_prefix.to_string()]).await?;
+        let metrics = repository_index_metrics(&self.db_path, &storage, &scope).await?;
+        Ok(MetricsSnapshot {
+            document_prefix: Some(document_prefix.to_string()),
+            ..metrics
+        })
+    }
+
+    /// Append the health metrics of the indexed repository to the trend history
+    fn record_metrics_snapshot(
+        &self,
+        repo_path: &Path,
+        index_time_ms: u64,
+        metrics: MetricsSnapshot,
+    ) -> Result<()> {
+        let snapshot = MetricsSnapshot {
+            recorded_at: chrono::Utc::now().to_rfc3339(),
+            repository: repo_path.display().to_string(),
+            index_time_ms,
+            ..metrics
+        };
+        TrendHistory::append(&self.db_path, &snapshot)
+    }
+
+    /// Stored documents other than commits, read one storage page at a time so the
+    /// storage lock is released between pages
+    #[cfg(feature = "tree-sitter-parsing")]
+    async fn source_documents(&self) -> Result<Vec<Document>> {
+        let storage = self.database.storage();
+        let mut documents = Vec::new();
+        let mut cursor = None;
+        loop {
+            let page = storage
+                .lock_timed(LockedResource::Storage)
+                .await
+                .list_page(cursor, crate::index_rebuild::REBUILD_PAGE_SIZE)
+                .await?;
+            documents.extend(
+                page.documents
+                    .into_iter()
+                    .filter(|d| !d.tags.iter().any(|tag| tag.as_str() == "commit")),
+            );
+            match page.next {
+                Some(next) => cursor = Some(next),
+                None => break,
+            }
+        }
+        Ok(documents)
+    }
+
     /// Index git repository history and metadata
     ///
     /// Focuses on git-specific operations like commit history, branch analysis,
@@ -739,7 +819,7 @@ impl<'a> IndexingService<'a> {
     async fn populate_trigram_index(&self) -> Result<usize> {
         // Get all documents from storage
         let storage_arc = self.database.storage();
-        let storage = storage_arc.lock().await;
+        let storage = storage_arc.lock_timed(LockedResource::Storage).await;
         let all_docs = storage.list_all().await?;
         drop(storage); // Release storage lock early
 
@@ -749,7 +829,9 @@ impl<'a> IndexingService<'a> {
 
         // Get trigram index
         let trigram_index_arc = self.database.trigram_index();
-        let mut trigram_index = trigram_index_arc.lock().await;
+        let mut trigram_index = trigram_index_arc
+            .lock_timed(LockedResource::TrigramIndex)
+            .await;
 
         let mut indexed_count = 0;
         let total_docs = all_docs.len();
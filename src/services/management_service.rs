@@ -8,6 +8,7 @@ use serde_json::json;
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 
+use crate::metrics::performance::{LockedResource, TimedLock};
 use crate::{
     search_validation::ValidationStatus, services_http_server::start_services_server,
     validate_post_ingestion_search,
@@ -224,7 +225,12 @@ impl<'a> ManagementService<'a> {
             let db = self.database;
 
             // Use storage to get document count and size
-            let all_docs = db.storage().lock().await.list_all().await?;
+            let all_docs = db
+                .storage()
+                .lock_timed(LockedResource::Storage)
+                .await
+                .list_metadata()
+                .await?;
             let count = all_docs.len();
             let total_size: usize = all_docs.iter().map(|d| d.size).sum();
             let avg_size = if count > 0 { total_size / count } else { 0 };
@@ -295,9 +301,13 @@ impl<'a> ManagementService<'a> {
             let storage_arc = self.database.storage();
             let primary_index_arc = self.database.primary_index();
             let trigram_index_arc = self.database.trigram_index();
-            let storage = storage_arc.lock().await;
-            let primary_index = primary_index_arc.lock().await;
-            let trigram_index = trigram_index_arc.lock().await;
+            let storage = storage_arc.lock_timed(LockedResource::Storage).await;
+            let primary_index = primary_index_arc
+                .lock_timed(LockedResource::PrimaryIndex)
+                .await;
+            let trigram_index = trigram_index_arc
+                .lock_timed(LockedResource::TrigramIndex)
+                .await;
             validate_post_ingestion_search(&*storage, &*primary_index, &*trigram_index).await?
         };
 
@@ -511,9 +521,7 @@ impl<'a> ManagementService<'a> {
         let graph_db_path = self.db_path.join("dependency_graph.bin");
         if graph_db_path.exists() {
             if let Ok(graph_binary) = std::fs::read(&graph_db_path) {
-                if let Ok(serializable) =
-                    bincode::deserialize::<SerializableDependencyGraph>(&graph_binary)
-                {
+                if let Ok(serializable) = SerializableDependencyGraph::from_bytes(&graph_binary) {
                     total_relationships = serializable.stats.edge_count;
                     connected_symbols = serializable.stats.node_count;
 
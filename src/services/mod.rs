@@ -13,18 +13,42 @@
 
 pub mod analysis_service;
 pub mod benchmark_service;
+pub mod change_window;
+pub mod document_lookup;
 pub mod indexing_service;
 pub mod management_service;
+pub mod redaction;
+pub mod repository_scope;
 pub mod search_service;
 pub mod stats_service;
 pub mod validation_service;
 
 // Analysis Service exports
 pub use analysis_service::{
-    AnalysisService, AnalysisServiceDatabase, CallSite, CallersOptions, CallersResult,
-    ImpactOptions, ImpactResult, ImpactSite, OverviewOptions, OverviewResult,
+    churn_by_file, find_unused_symbols, index_metrics, repository_index_metrics,
+    storage_index_metrics, AnalysisService, AnalysisServiceDatabase, CallSite, CallersOptions,
+    CallersResult, ConfigUsagesOptions, ErrorFlowOptions, FileGroup, FlagImpactOptions,
+    FlagsOptions, FlagsResult, GraphEdge, GraphNode, GraphOptions, HotPathRanking, HotPathsOptions,
+    HotPathsResult, HotSymbol, ImpactOptions, ImpactResult, ImpactSite, LocateLogOptions,
+    LocateLogResult, OverviewOptions, OverviewResult, RelatedFile, RelatedOptions, RelatedResult,
+    RelatedSignal, RelatedSymbol, RelationshipSortBy, RenamePreviewOptions, ResultGrouping,
+    SimilarFunction, SimilarOptions, SimilarResult, SourceExcerpt, SuggestReviewersOptions,
+    SymbolBriefOptions, SymbolGraphResult, TableUsagesOptions, TreemapMetrics, TreemapNode,
+    TreemapOptions, TreemapResult, TrendsOptions, TrendsResult, UnusedSymbol, OVERVIEW_FORMATS,
 };
 
+// Change window exports
+pub use change_window::ChangedFiles;
+
+// Document lookup exports
+pub use document_lookup::{document_by_path, documents_by_paths, summary_by_path};
+
+// Redaction exports
+pub use redaction::RedactionRules;
+
+// Repository scope exports
+pub use repository_scope::{RepositoryScope, RepositoryScopeCache};
+
 // Indexing Service exports
 pub use indexing_service::{
     GitIndexResult, IncrementalUpdateOptions, IndexCodebaseOptions, IndexGitOptions, IndexResult,
@@ -59,6 +83,7 @@ pub use management_service::{
 
 // Shared exports
 pub use search_service::{
-    DatabaseAccess, SearchOptions, SearchResult, SearchService, SearchType, SymbolMatch,
-    SymbolResult, SymbolSearchOptions,
+    DatabaseAccess, FusedHit, FusedSearchOptions, FusedSearchResult, FusionSource, QueryRoute,
+    SearchGrouping, SearchOptions, SearchResult, SearchService, SearchType, SourceRank,
+    SymbolMatch, SymbolResult, SymbolSearchOptions, RRF_K,
 };
@@ -0,0 +1,157 @@
+// Path-based redaction for API keys sharing a database
+//
+// Teams that share a database with contractors attach redaction rules to the keys
+// they hand out. Each rule is a gitignore-style path pattern; documents, symbols,
+// relationships and files whose path matches any rule are left out of every result
+// the services return for requests made with that key. Patterns are matched against
+// repository-relative paths, and stored paths (`repos/<name>/files/<path>`) are also
+// matched as a whole, so a rule can hide one repository entirely.
+
+use anyhow::{bail, Result};
+use regex::Regex;
+
+/// Most redaction rules one API key may carry
+pub const MAX_REDACTION_RULES: usize = 100;
+
+/// Path patterns whose matches are hidden from a caller
+#[derive(Debug, Clone, Default)]
+pub struct RedactionRules {
+    patterns: Vec<String>,
+    compiled: Vec<Regex>,
+}
+
+impl RedactionRules {
+    /// Compile gitignore-style patterns: `*` and `?` stay within one path segment, `**`
+    /// spans directories, a pattern with a `/` is anchored at the repository root and
+    /// a matched directory hides everything below it
+    pub fn new<I, S>(patterns: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let mut rules = Self::default();
+        for pattern in patterns {
+            let pattern = pattern.into().trim().to_string();
+            if pattern.is_empty() {
+                bail!("Redaction patterns must not be empty");
+            }
+            let Some(compiled) = path_pattern(&pattern) else {
+                bail!("Invalid redaction pattern '{}'", pattern);
+            };
+            rules.patterns.push(pattern);
+            rules.compiled.push(compiled);
+        }
+        if rules.patterns.len() > MAX_REDACTION_RULES {
+            bail!(
+                "At most {} redaction patterns can be attached to an API key",
+                MAX_REDACTION_RULES
+            );
+        }
+        Ok(rules)
+    }
+
+    /// Rules stored with an API key as a JSON array of patterns
+    ///
+    /// Unreadable stored rules are an error rather than no rules, so a damaged record
+    /// never widens what a key can see.
+    pub fn from_json(value: Option<&serde_json::Value>) -> Result<Self> {
+        match value {
+            None | Some(serde_json::Value::Null) => Ok(Self::default()),
+            Some(value) => {
+                let patterns: Vec<String> = serde_json::from_value(value.clone())?;
+                Self::new(patterns)
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.patterns.is_empty()
+    }
+
+    pub fn patterns(&self) -> &[String] {
+        &self.patterns
+    }
+
+    /// Whether `path`, stored or repository-relative, is hidden by any rule
+    pub fn is_redacted(&self, path: &str) -> bool {
+        if self.is_empty() {
+            return false;
+        }
+        let path = path.trim_start_matches("./").trim_start_matches('/');
+        let relative = path.split_once("/files/").map(|(_, relative)| relative);
+        self.compiled.iter().any(|pattern| {
+            pattern.is_match(path) || relative.is_some_and(|relative| pattern.is_match(relative))
+        })
+    }
+
+    /// Whether `path` may be returned to the caller
+    pub fn allows(&self, path: &str) -> bool {
+        !self.is_redacted(path)
+    }
+}
+
+/// Regex for a gitignore-style path pattern
+pub(crate) fn path_pattern(pattern: &str) -> Option<Regex> {
+    let body = pattern.trim_start_matches('/').trim_end_matches('/');
+    if body.is_empty() {
+        return None;
+    }
+    let anchored = pattern.starts_with('/') || body.contains('/');
+    let mut regex = String::from(if anchored { "^" } else { "^(?:.*/)?" });
+    let mut chars = body.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                if chars.peek() == Some(&'/') {
+                    chars.next();
+                    regex.push_str("(?:.*/)?");
+                } else {
+                    regex.push_str(".*");
+                }
+            }
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    regex.push_str("(?:/.*)?$");
+    Regex::new(&regex).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hides_matching_relative_and_stored_paths() -> Result<()> {
+        let rules = RedactionRules::new(["src/billing/", "*.pem", "repos/secret/**"])?;
+
+        assert!(rules.is_redacted("src/billing/invoice.rs"));
+        assert!(rules.is_redacted("./src/billing/mod.rs"));
+        assert!(rules.is_redacted("repos/widgets/files/src/billing/invoice.rs"));
+        assert!(rules.is_redacted("config/keys/server.pem"));
+        assert!(rules.is_redacted("repos/secret/files/README.md"));
+        assert!(rules.is_redacted("repos/secret/commits/abcd1234.md"));
+
+        assert!(rules.allows("src/billing_report.rs"));
+        assert!(rules.allows("lib/src/billing/invoice.rs"));
+        assert!(rules.allows("repos/widgets/files/src/lib.rs"));
+        assert!(RedactionRules::default().allows("src/billing/invoice.rs"));
+        Ok(())
+    }
+
+    #[test]
+    fn reads_stored_rules_and_rejects_bad_patterns() -> Result<()> {
+        let stored = serde_json::json!(["internal/**"]);
+        let rules = RedactionRules::from_json(Some(&stored))?;
+        assert_eq!(rules.patterns(), ["internal/**"]);
+        assert!(rules.is_redacted("internal/ops/deploy.sh"));
+
+        assert!(RedactionRules::from_json(None)?.is_empty());
+        assert!(RedactionRules::from_json(Some(&serde_json::json!("internal"))).is_err());
+        assert!(RedactionRules::new([" "]).is_err());
+        assert!(RedactionRules::new(["/"]).is_err());
+        Ok(())
+    }
+}
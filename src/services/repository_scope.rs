@@ -0,0 +1,264 @@
+// Repository scoping for databases holding several repositories
+//
+// SaaS databases keep every tenant's repositories side by side. Documents are
+// stored under `<root>/<name>/files/<path>`, while symbols and relationships are
+// recorded with repository-relative paths and carry no repository segment. A scope
+// resolves the documents of one or more repositories once so services can keep
+// every kind of result to them.
+
+use anyhow::Result;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use crate::metrics::performance::{LockedResource, TimedLock};
+use crate::{Storage, ValidatedDocumentId};
+
+/// The documents and files of the repositories a request may see
+///
+/// Clones share the resolved repositories, so a cached scope is cheap to hand out.
+#[derive(Debug, Clone, Default)]
+pub struct RepositoryScope {
+    repositories: Vec<Arc<ScopedRepository>>,
+}
+
+/// The documents and files stored under one repository prefix
+#[derive(Debug, Clone, Default)]
+struct ScopedRepository {
+    /// Storage prefix of the repository's documents, e.g. `repos/widgets/`
+    document_prefix: String,
+    document_ids: HashSet<ValidatedDocumentId>,
+    /// Repository-relative paths of its files, as symbols record them
+    files: HashSet<String>,
+}
+
+impl ScopedRepository {
+    fn new(document_prefix: &str) -> Self {
+        let mut document_prefix = document_prefix.to_string();
+        if !document_prefix.ends_with('/') {
+            document_prefix.push('/');
+        }
+        Self {
+            document_prefix,
+            ..Self::default()
+        }
+    }
+
+    fn insert(&mut self, id: ValidatedDocumentId, path: &str) {
+        let Some(relative) = path.strip_prefix(&self.document_prefix) else {
+            return;
+        };
+        self.document_ids.insert(id);
+        if let Some(file) = relative.strip_prefix("files/") {
+            self.files.insert(file.to_string());
+        }
+    }
+}
+
+impl RepositoryScope {
+    /// An empty scope for the repository stored under `document_prefix`
+    pub fn new(document_prefix: impl Into<String>) -> Self {
+        Self {
+            repositories: vec![Arc::new(ScopedRepository::new(&document_prefix.into()))],
+        }
+    }
+
+    /// Resolve the documents stored under each of `document_prefixes`
+    ///
+    /// Reads the metadata of every stored document once, under a single storage lock,
+    /// so no repository is cut short however many documents it holds.
+    pub async fn load(
+        storage: &Arc<Mutex<dyn Storage>>,
+        document_prefixes: &[String],
+    ) -> Result<Self> {
+        let mut repositories: Vec<ScopedRepository> = document_prefixes
+            .iter()
+            .map(|prefix| ScopedRepository::new(prefix))
+            .collect();
+        if !repositories.is_empty() {
+            let documents = storage
+                .lock_timed(LockedResource::Storage)
+                .await
+                .list_metadata()
+                .await?;
+            for document in &documents {
+                for repository in &mut repositories {
+                    repository.insert(document.id, document.path.as_str());
+                }
+            }
+        }
+        Ok(Self {
+            repositories: repositories.into_iter().map(Arc::new).collect(),
+        })
+    }
+
+    /// Record a stored document; documents outside every prefix are ignored
+    pub fn insert(&mut self, id: ValidatedDocumentId, path: &str) {
+        for repository in &mut self.repositories {
+            Arc::make_mut(repository).insert(id, path);
+        }
+    }
+
+    /// Storage prefix of the scope's repository, when it covers exactly one
+    pub fn document_prefix(&self) -> Option<&str> {
+        match self.repositories.as_slice() {
+            [repository] => Some(&repository.document_prefix),
+            _ => None,
+        }
+    }
+
+    /// Stored path of the directory holding the files of the scope's repository, when
+    /// it covers exactly one
+    pub fn files_root(&self) -> Option<String> {
+        self.document_prefix()
+            .map(|prefix| format!("{}files", prefix))
+    }
+
+    /// Whether a repository stored under `document_prefix` is in the scope
+    pub fn contains_repository(&self, document_prefix: &str) -> bool {
+        let document_prefix = document_prefix.trim_end_matches('/');
+        self.repositories
+            .iter()
+            .any(|repository| repository.document_prefix.trim_end_matches('/') == document_prefix)
+    }
+
+    pub fn contains_document(&self, id: &ValidatedDocumentId) -> bool {
+        self.repositories
+            .iter()
+            .any(|repository| repository.document_ids.contains(id))
+    }
+
+    /// Whether `path` (stored or repository-relative) belongs to a scoped repository
+    pub fn contains_path(&self, path: &str) -> bool {
+        let relative = path.trim_start_matches("./").trim_start_matches('/');
+        self.repositories.iter().any(|repository| {
+            path.starts_with(&repository.document_prefix) || repository.files.contains(relative)
+        })
+    }
+}
+
+/// Resolved repositories of a server, kept until it indexes again
+///
+/// Resolving a repository reads the metadata of every stored document, so servers
+/// keep the result per document prefix and start over when the data generation they
+/// were resolved at changes.
+#[derive(Debug, Default)]
+pub struct RepositoryScopeCache {
+    resolved: Mutex<ResolvedRepositories>,
+}
+
+#[derive(Debug, Default)]
+struct ResolvedRepositories {
+    generation: u64,
+    repositories: HashMap<String, Arc<ScopedRepository>>,
+}
+
+impl RepositoryScopeCache {
+    /// Scope of `document_prefixes` at data generation `generation`, resolving only the
+    /// repositories not cached at that generation
+    pub async fn scope(
+        &self,
+        storage: &Arc<Mutex<dyn Storage>>,
+        generation: u64,
+        document_prefixes: &[String],
+    ) -> Result<RepositoryScope> {
+        let mut resolved = self.resolved.lock().await;
+        if resolved.generation != generation {
+            *resolved = ResolvedRepositories {
+                generation,
+                repositories: HashMap::new(),
+            };
+        }
+        let missing: Vec<String> = document_prefixes
+            .iter()
+            .filter(|prefix| !resolved.repositories.contains_key(prefix.as_str()))
+            .cloned()
+            .collect();
+        if !missing.is_empty() {
+            let loaded = RepositoryScope::load(storage, &missing).await?;
+            for (prefix, repository) in missing.into_iter().zip(loaded.repositories) {
+                resolved.repositories.insert(prefix, repository);
+            }
+        }
+        Ok(RepositoryScope {
+            repositories: document_prefixes
+                .iter()
+                .filter_map(|prefix| resolved.repositories.get(prefix).cloned())
+                .collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_storage::create_file_storage;
+    use tempfile::TempDir;
+    use uuid::Uuid;
+
+    #[test]
+    fn scopes_match_stored_and_repository_relative_paths() -> Result<()> {
+        let own = ValidatedDocumentId::from_uuid(Uuid::new_v4())?;
+        let other = ValidatedDocumentId::from_uuid(Uuid::new_v4())?;
+        let mut scope = RepositoryScope::new("repos/widgets");
+        scope.insert(own, "repos/widgets/files/src/lib.rs");
+        scope.insert(other, "repos/gadgets/files/src/main.rs");
+
+        assert_eq!(scope.document_prefix(), Some("repos/widgets/"));
+        assert_eq!(scope.files_root().as_deref(), Some("repos/widgets/files"));
+        assert!(scope.contains_repository("repos/widgets"));
+        assert!(!scope.contains_repository("repos/gadgets/"));
+        assert!(scope.contains_document(&own));
+        assert!(!scope.contains_document(&other));
+        assert!(scope.contains_path("repos/widgets/files/src/lib.rs"));
+        assert!(scope.contains_path("src/lib.rs"));
+        assert!(scope.contains_path("./src/lib.rs"));
+        assert!(!scope.contains_path("src/main.rs"));
+        assert!(!scope.contains_path("repos/gadgets/files/src/main.rs"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn cached_scopes_cover_several_repositories_until_the_generation_changes() -> Result<()> {
+        let dir = TempDir::new()?;
+        let storage: Arc<Mutex<dyn Storage>> = Arc::new(Mutex::new(
+            create_file_storage(dir.path().to_str().unwrap(), Some(100)).await?,
+        ));
+        let mut ids = Vec::new();
+        for path in [
+            "repos/widgets/files/src/lib.rs",
+            "repos/gadgets/files/src/main.rs",
+            "repos/gizmos/files/src/gizmo.rs",
+        ] {
+            let document = crate::DocumentBuilder::new()
+                .path(path)?
+                .title(path)?
+                .content(b"fn main() {}")
+                .build()?;
+            ids.push(document.id);
+            storage.lock().await.insert(document).await?;
+        }
+        let prefixes = ["repos/widgets/".to_string(), "repos/gadgets/".to_string()];
+        let cache = RepositoryScopeCache::default();
+
+        let scope = cache.scope(&storage, 1, &prefixes).await?;
+        assert_eq!(scope.document_prefix(), None);
+        assert!(scope.contains_document(&ids[0]));
+        assert!(scope.contains_document(&ids[1]));
+        assert!(!scope.contains_document(&ids[2]));
+        assert!(scope.contains_path("src/main.rs"));
+        assert!(!scope.contains_path("src/gizmo.rs"));
+
+        let document = crate::DocumentBuilder::new()
+            .path("repos/widgets/files/src/new.rs")?
+            .title("new.rs")?
+            .content(b"fn new() {}")
+            .build()?;
+        storage.lock().await.insert(document).await?;
+        let cached = cache.scope(&storage, 1, &prefixes[..1]).await?;
+        assert!(!cached.contains_path("src/new.rs"));
+        let reindexed = cache.scope(&storage, 2, &prefixes[..1]).await?;
+        assert!(reindexed.contains_path("src/new.rs"));
+        Ok(())
+    }
+}
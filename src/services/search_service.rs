@@ -4,15 +4,35 @@
 // across all KotaDB interfaces while maintaining identical behavior.
 
 use anyhow::Result;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::future::Future;
 use std::path::PathBuf;
-use std::sync::Arc;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
 use tokio::sync::{Mutex, RwLock};
 
+use crate::metrics::performance::{LockedResource, TimedLock};
 use crate::{
-    binary_symbols::BinarySymbolReader,
-    llm_search::{ContextConfig, LLMSearchEngine, LLMSearchResponse, RelevanceConfig},
-    Document, Index, Storage, ValidatedDocumentId,
+    annotations::{
+        Annotation, AnnotationStore, LineRange, Note, MAX_ANNOTATIONS_PER_SEARCH,
+        MAX_NOTES_PER_RESULT,
+    },
+    binary_symbols::{BinarySymbolReader, SharedSymbolReader},
+    cargo_workspace::CargoCrate,
+    corpora::{CorpusFilter, CorpusSelection, CORPORA_DIR},
+    did_you_mean::{Suggester, MAX_SUGGESTIONS},
+    llm_search::{
+        estimate_tokens, ContextConfig, LLMSearchEngine, LLMSearchResponse, LLMSearchResult,
+        MatchLocation, RelevanceConfig,
+    },
+    query_deadline::QueryDeadline,
+    relationship_query::SourceRange,
+    repository_links::RepositoryLinks,
+    semantic_search::SemanticSearchEngine,
+    services::{ChangedFiles, RedactionRules, RepositoryScope},
+    synonyms::SynonymDictionary,
+    Document, DocumentFilters, Index, Storage, ValidatedDocumentId,
 };
 
 // Trait for database access needed by SearchService
@@ -32,6 +52,7 @@ pub struct SearchOptions {
     pub tags: Option<Vec<String>>,
     pub context: String,
     pub quiet: bool,
+    pub group_by: SearchGrouping,
 }
 
 impl Default for SearchOptions {
@@ -42,6 +63,42 @@ impl Default for SearchOptions {
             tags: None,
             context: "medium".to_string(),
             quiet: false,
+            group_by: SearchGrouping::default(),
+        }
+    }
+}
+
+/// How content search results that share a path are returned
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchGrouping {
+    /// One result per file, scored by its best match and holding all of its matches
+    #[default]
+    File,
+    /// One result per match, so a file matching in several places appears several times
+    Match,
+}
+
+impl SearchGrouping {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::File => "file",
+            Self::Match => "match",
+        }
+    }
+}
+
+impl FromStr for SearchGrouping {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "file" => Ok(Self::File),
+            "match" => Ok(Self::Match),
+            other => Err(anyhow::anyhow!(
+                "Unknown grouping '{}'. Expected one of: file, match",
+                other
+            )),
         }
     }
 }
@@ -73,6 +130,35 @@ pub struct SearchResult {
     pub total_count: usize,
     pub llm_response: Option<LLMSearchResponse>,
     pub search_type: SearchType,
+    /// Synonym variants of the query that were searched alongside it
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub expanded_queries: Vec<String>,
+    /// Nearby identifiers or paths, when the search found nothing
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub suggestions: Vec<String>,
+    /// Stored summaries of the results, and of anything else matching the query
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub annotations: Vec<Annotation>,
+    /// Notes, tags and bookmarks left on the results
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub notes: Vec<Note>,
+    /// Commit-pinned web links of the documents, keyed by document path
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub web_urls: BTreeMap<String, String>,
+    /// The query deadline passed before the search finished, so results are partial
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub timed_out: bool,
+}
+
+impl SearchResult {
+    /// Whether neither regular nor LLM-optimized results were found
+    pub fn is_empty(&self) -> bool {
+        self.documents.is_empty()
+            && self
+                .llm_response
+                .as_ref()
+                .is_none_or(|response| response.results.is_empty())
+    }
 }
 
 /// Search result for symbol search
@@ -80,6 +166,18 @@ pub struct SearchResult {
 pub struct SymbolResult {
     pub matches: Vec<SymbolMatch>,
     pub total_symbols: usize,
+    /// Nearby symbol names or paths, when no symbol matched
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub suggestions: Vec<String>,
+    /// Stored summaries of the results, and of anything else matching the query
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub annotations: Vec<Annotation>,
+    /// Notes, tags and bookmarks left on the results
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub notes: Vec<Note>,
+    /// The query deadline passed before the search finished, so results are partial
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub timed_out: bool,
 }
 
 /// Individual symbol match
@@ -88,7 +186,189 @@ pub struct SymbolMatch {
     pub name: String,
     pub file_path: String,
     pub start_line: u32,
+    pub end_line: u32,
     pub kind: String,
+    /// Exact definition range, when the symbol database recorded byte offsets
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub range: Option<SourceRange>,
+    /// Commit-pinned link to the definition on its forge, when the repository's remote is known
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub web_url: Option<String>,
+}
+
+/// Constant `k` of reciprocal-rank fusion: a result ranked `r` by a source scores `1 / (k + r)`
+pub const RRF_K: f64 = 60.0;
+
+/// Symbols kept per fused result for provenance
+const MAX_SYMBOLS_PER_HIT: usize = 3;
+
+/// Index candidates fetched by repository-scoped searches, which filter before limiting
+const SCOPED_CANDIDATE_LIMIT: usize = 100_000;
+
+/// Documents fetched per batch while corpus facets are checked against content
+const CORPUS_FETCH_BATCH: usize = 256;
+
+/// Configuration options for fused multi-source search
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct FusedSearchOptions {
+    pub query: String,
+    pub limit: usize,
+    pub quiet: bool,
+}
+
+impl Default for FusedSearchOptions {
+    fn default() -> Self {
+        Self {
+            query: String::new(),
+            limit: 10,
+            quiet: false,
+        }
+    }
+}
+
+/// Index a fused result was found by
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FusionSource {
+    Trigram,
+    Symbol,
+    Semantic,
+    Path,
+}
+
+impl FusionSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FusionSource::Trigram => "trigram",
+            FusionSource::Symbol => "symbol",
+            FusionSource::Semantic => "semantic",
+            FusionSource::Path => "path",
+        }
+    }
+}
+
+/// How a fused search query was routed, detected from its shape
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QueryRoute {
+    /// `Foo::bar`, `StorageEngine`, `open_file`, `Storage*`: symbol matches weigh most
+    Symbol,
+    /// `src/**.rs`, `lib.rs`: matched against document paths only
+    Path,
+    /// Everything else: content and vector matches weigh most
+    Prose,
+}
+
+impl QueryRoute {
+    /// Classify a query by its shape
+    pub fn classify(query: &str) -> Self {
+        let query = query.trim();
+        if query.is_empty() || query.contains(char::is_whitespace) {
+            return QueryRoute::Prose;
+        }
+        if query.contains('/') || query.contains('\\') || has_file_extension(query) {
+            return QueryRoute::Path;
+        }
+        let identifier = query
+            .strip_suffix("()")
+            .unwrap_or(query)
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '_' | ':' | '.' | '*'));
+        let camel_case =
+            query.chars().skip(1).any(char::is_uppercase) && query.chars().any(char::is_lowercase);
+        if identifier
+            && (query.contains("::")
+                || query.contains('.')
+                || query.contains('_')
+                || query.contains('*')
+                || query.ends_with("()")
+                || camel_case)
+        {
+            return QueryRoute::Symbol;
+        }
+        QueryRoute::Prose
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            QueryRoute::Symbol => "symbol",
+            QueryRoute::Path => "path",
+            QueryRoute::Prose => "prose",
+        }
+    }
+
+    /// Fusion weight of each source under this route
+    fn weight(&self, source: FusionSource) -> f64 {
+        match (self, source) {
+            (QueryRoute::Symbol, FusionSource::Symbol) => 2.0,
+            (QueryRoute::Symbol, FusionSource::Semantic) => 0.5,
+            (QueryRoute::Prose, FusionSource::Symbol) => 0.5,
+            _ => 1.0,
+        }
+    }
+}
+
+/// `lib.rs`, `*.toml`: a final `.ext` of up to four ASCII letters/digits after a stem
+fn has_file_extension(query: &str) -> bool {
+    match query.rsplit_once('.') {
+        Some((stem, ext)) => {
+            !stem.is_empty()
+                && !stem.ends_with('.')
+                && (1..=4).contains(&ext.len())
+                && ext
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit())
+                && ext.chars().any(|c| c.is_ascii_alphabetic())
+        }
+        None => false,
+    }
+}
+
+/// Position of a fused result in one source's ranking (1-based)
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct SourceRank {
+    pub source: FusionSource,
+    pub rank: usize,
+}
+
+/// A file ranked by reciprocal-rank fusion across sources
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct FusedHit {
+    pub path: String,
+    pub score: f64,
+    pub sources: Vec<SourceRank>,
+    /// Matching symbols in the file, when the symbol source contributed
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub symbols: Vec<SymbolMatch>,
+    /// Commit-pinned link to the file on its forge, when the repository's remote is known
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub web_url: Option<String>,
+}
+
+/// Result of fused multi-source search
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct FusedSearchResult {
+    pub query: String,
+    /// Route chosen from the query's shape
+    pub route: QueryRoute,
+    pub results: Vec<FusedHit>,
+    /// Sources that returned a ranking, whether or not it was empty
+    pub sources_searched: Vec<FusionSource>,
+    /// Sources that failed, with their errors; the remaining sources are still fused
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub source_errors: Vec<String>,
+    /// Nearby identifiers or paths, when nothing was found
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub suggestions: Vec<String>,
+    /// Stored summaries of the results, and of anything else matching the query
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub annotations: Vec<Annotation>,
+    /// Notes, tags and bookmarks left on the results
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub notes: Vec<Note>,
+    /// The query deadline passed before the search finished, so results are partial
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub timed_out: bool,
 }
 
 /// Type of search performed
@@ -103,6 +383,29 @@ pub enum SearchType {
 pub struct SearchService<'a> {
     database: &'a dyn DatabaseAccess,
     symbol_db_path: PathBuf,
+    semantic_engine: Option<Arc<Mutex<SemanticSearchEngine>>>,
+    /// Directory of the annotation store attached to results
+    annotations_dir: PathBuf,
+    /// Synonym dictionary of the database, loaded on first use
+    synonyms: OnceLock<SynonymDictionary>,
+    /// Repository that results are restricted to
+    scope: Option<RepositoryScope>,
+    /// Cargo crate whose files results are restricted to
+    crate_scope: Option<CargoCrate>,
+    /// Corpus (and facet values) that results are restricted to
+    corpus: Option<CorpusFilter>,
+    /// Files changed within a window of git history that results are restricted to
+    changed_files: Option<ChangedFiles>,
+    /// Paths hidden from the caller
+    redaction: RedactionRules,
+    /// When searches stop and return what they found so far
+    deadline: QueryDeadline,
+    /// Set once a search stopped at the deadline
+    timed_out: AtomicBool,
+    /// Symbol database reader kept across searches, with its name index
+    symbol_reader: Option<SharedSymbolReader>,
+    /// Extension, depth and size restrictions on returned documents
+    document_filters: DocumentFilters,
 }
 
 impl<'a> SearchService<'a> {
@@ -110,12 +413,441 @@ impl<'a> SearchService<'a> {
     pub fn new(database: &'a dyn DatabaseAccess, symbol_db_path: PathBuf) -> Self {
         Self {
             database,
+            annotations_dir: symbol_db_path.clone(),
             symbol_db_path,
+            semantic_engine: None,
+            synonyms: OnceLock::new(),
+            scope: None,
+            crate_scope: None,
+            corpus: None,
+            changed_files: None,
+            redaction: RedactionRules::default(),
+            deadline: QueryDeadline::none(),
+            timed_out: AtomicBool::new(false),
+            symbol_reader: None,
+            document_filters: DocumentFilters::default(),
         }
     }
 
+    /// Attach annotations from `dir` instead of the database directory
+    pub fn with_annotations_dir(mut self, dir: PathBuf) -> Self {
+        self.annotations_dir = dir;
+        self
+    }
+
+    /// Include vector search in fused search
+    pub fn with_semantic_engine(mut self, engine: Arc<Mutex<SemanticSearchEngine>>) -> Self {
+        self.semantic_engine = Some(engine);
+        self
+    }
+
+    /// Only return documents, symbols and suggestions from one repository
+    ///
+    /// Scoped content searches always use regular search, since LLM-optimized
+    /// results are selected before they could be filtered.
+    pub fn with_repository_scope(mut self, scope: RepositoryScope) -> Self {
+        self.scope = Some(scope);
+        self
+    }
+
+    /// Only return results defined in the files of one Cargo crate
+    pub fn with_crate(mut self, krate: CargoCrate) -> Self {
+        self.crate_scope = Some(krate);
+        self
+    }
+
+    /// Only return documents of one corpus, as selected by a `corpus:` query term
+    ///
+    /// Facet values of the filter are checked against each document's frontmatter.
+    pub fn with_corpus_filter(mut self, filter: CorpusFilter) -> Self {
+        self.corpus = Some(filter);
+        self
+    }
+
+    /// Only return results from files changed within a window of ingested git history
+    ///
+    /// Like repository scopes, this always uses regular content search.
+    pub fn with_changed_files(mut self, changed: ChangedFiles) -> Self {
+        self.changed_files = Some(changed);
+        self
+    }
+
+    /// Leave documents, symbols and suggestions from redacted paths out of every result
+    ///
+    /// Like repository scopes, redaction always uses regular content search.
+    pub fn with_redaction(mut self, redaction: RedactionRules) -> Self {
+        self.redaction = redaction;
+        self
+    }
+
+    /// Only return documents passing extension, depth and size filters
+    ///
+    /// Wildcard searches hand extension and depth filters to the primary index, which
+    /// checks them while traversing paths; other filters are checked against stored
+    /// document metadata before any content is read. Like repository scopes, filtered
+    /// searches always use regular content search.
+    pub fn with_document_filters(mut self, filters: DocumentFilters) -> Self {
+        self.document_filters = filters;
+        self
+    }
+
+    /// Search symbols through a reader shared across searches
+    ///
+    /// Its name index answers patterns without scanning every symbol; without one,
+    /// each symbol search opens the database and scans it.
+    pub fn with_symbol_reader(mut self, reader: SharedSymbolReader) -> Self {
+        self.symbol_reader = Some(reader);
+        self
+    }
+
+    /// Stop searches at `deadline`, returning what was found with `timed_out` set
+    ///
+    /// Index searches are abandoned when it passes, and candidate and symbol scans
+    /// check it as they go.
+    pub fn with_deadline(mut self, deadline: QueryDeadline) -> Self {
+        self.deadline = deadline;
+        self
+    }
+
+    /// Whether the deadline passed, checked every [`crate::query_deadline::CHECK_INTERVAL`]
+    /// iterations of a scan; a passed deadline marks results as timed out
+    fn out_of_time(&self, iteration: usize) -> bool {
+        let expired = self.deadline.expired_at(iteration);
+        if expired {
+            self.timed_out.store(true, Ordering::Relaxed);
+        }
+        expired
+    }
+
+    /// Whether any search of this service stopped at the deadline
+    fn timed_out(&self) -> bool {
+        self.timed_out.load(Ordering::Relaxed)
+    }
+
+    /// Await `future` until the deadline, failing once it passed
+    async fn until_deadline<T>(&self, future: impl Future<Output = Result<T>>) -> Result<T> {
+        match self.deadline.run(future).await {
+            Some(result) => result,
+            None => {
+                self.timed_out.store(true, Ordering::Relaxed);
+                Err(anyhow::anyhow!("query deadline passed"))
+            }
+        }
+    }
+
+    /// Whether results are filtered by path, so candidates must be checked one by one
+    fn filters_paths(&self) -> bool {
+        self.scope.is_some()
+            || self.corpus.is_some()
+            || self.changed_files.is_some()
+            || !self.redaction.is_empty()
+    }
+
+    /// Whether index candidates for `query` must still be checked against the document
+    /// filters, because the primary index could not apply them all
+    fn checks_document_filters(&self, query: &str) -> bool {
+        self.document_filters.filters_sizes()
+            || (self.document_filters.filters_paths() && !query.contains('*'))
+    }
+
+    fn in_scope(&self, path: &str) -> bool {
+        self.scope
+            .as_ref()
+            .is_none_or(|scope| scope.contains_path(path))
+            && self
+                .crate_scope
+                .as_ref()
+                .is_none_or(|krate| krate.contains(path))
+            && self
+                .corpus
+                .as_ref()
+                .is_none_or(|corpus| corpus.matches_path(path))
+            && self
+                .changed_files
+                .as_ref()
+                .is_none_or(|changed| changed.contains(path))
+            && self.redaction.allows(path)
+    }
+
+    /// Whether `document` is in scope and has the facet values of the corpus filter
+    fn document_in_scope(&self, document: &Document) -> bool {
+        let path = document.path.as_str();
+        self.in_scope(path)
+            && self
+                .corpus
+                .as_ref()
+                .is_none_or(|corpus| corpus.matches_document(path, &document.content))
+    }
+
+    /// Search trigram, symbol-name and (if configured) vector indices concurrently
+    /// and merge their rankings with reciprocal-rank fusion
+    ///
+    /// The query is routed by its shape (see [`QueryRoute::classify`]): path-like
+    /// queries only match document paths, symbol-like queries weigh symbol matches
+    /// double, and prose weighs content matches. Results are files; each lists the
+    /// rank it had in every source that found it. A failing source is reported in
+    /// `source_errors` instead of failing the search. When nothing is found, the
+    /// result carries "did you mean" suggestions.
+    pub async fn search_fused(&self, options: FusedSearchOptions) -> Result<FusedSearchResult> {
+        let mut result = self.search_fused_by_route(options).await?;
+        if result.results.is_empty() && !result.query.is_empty() {
+            result.suggestions = self.did_you_mean(&result.query).await;
+        }
+        if let Some(store) = self.annotation_store() {
+            let paths = || result.results.iter().map(|hit| hit.path.as_str());
+            result.annotations = store.for_results(
+                &result.query,
+                paths(),
+                std::iter::empty(),
+                MAX_ANNOTATIONS_PER_SEARCH,
+            );
+            result.notes = store.notes_for_results(
+                paths().map(|path| (path, None)),
+                std::iter::empty(),
+                MAX_NOTES_PER_RESULT,
+            );
+        }
+        if let Some(links) = self.repository_links() {
+            for hit in &mut result.results {
+                hit.web_url = links.web_url(&hit.path, None);
+            }
+        }
+        result.timed_out = self.timed_out();
+        Ok(result)
+    }
+
+    async fn search_fused_by_route(
+        &self,
+        options: FusedSearchOptions,
+    ) -> Result<FusedSearchResult> {
+        let mut query = options.query.trim().to_string();
+        if let (true, Some(CorpusSelection::Corpus(name))) = (
+            query.is_empty(),
+            self.corpus.as_ref().map(|corpus| &corpus.selection),
+        ) {
+            // A bare `corpus:<name>` lists the corpus
+            query = format!("{CORPORA_DIR}/{name}/");
+        }
+        let route = QueryRoute::classify(&query);
+        if query.is_empty() {
+            return Ok(FusedSearchResult {
+                query,
+                route,
+                results: vec![],
+                sources_searched: vec![],
+                source_errors: vec![],
+                suggestions: vec![],
+                annotations: vec![],
+                notes: vec![],
+                timed_out: false,
+            });
+        }
+        if route == QueryRoute::Path {
+            return self.search_paths(query, options.limit).await;
+        }
+
+        // Fetch deeper rankings than requested so fusion can promote results that
+        // rank moderately in several sources
+        let depth = options.limit.saturating_mul(2).max(10);
+        let content_options = SearchOptions {
+            query: query.clone(),
+            limit: depth,
+            tags: None,
+            context: "none".to_string(),
+            quiet: true,
+            group_by: SearchGrouping::default(),
+        };
+        let expanded_queries = self.expand_query(&query);
+
+        let trigram = async {
+            self.regular_search(
+                &content_options.query,
+                &content_options.tags,
+                content_options.limit,
+                &expanded_queries,
+            )
+            .await
+            .map(|(documents, _)| {
+                documents
+                    .into_iter()
+                    .map(|document| document.path.as_str().to_string())
+                    .collect::<Vec<_>>()
+            })
+        };
+        let symbols = async {
+            let subject = symbol_subject(&query);
+            self.search_symbols(SymbolSearchOptions {
+                pattern: symbol_pattern(subject),
+                limit: depth.saturating_mul(MAX_SYMBOLS_PER_HIT),
+                symbol_type: None,
+                quiet: true,
+            })
+            .await
+            .map(|result| rank_symbol_matches(subject, result.matches))
+        };
+        let semantic = async {
+            match &self.semantic_engine {
+                Some(engine) => Some(
+                    self.until_deadline(async {
+                        engine
+                            .lock()
+                            .await
+                            .semantic_search(&query, depth, None)
+                            .await
+                    })
+                    .await
+                    .map(|documents| {
+                        documents
+                            .into_iter()
+                            .filter(|scored| self.document_in_scope(&scored.document))
+                            .map(|scored| scored.document.path.as_str().to_string())
+                            .collect::<Vec<_>>()
+                    }),
+                ),
+                None => None,
+            }
+        };
+        let (trigram, symbols, semantic) = tokio::join!(trigram, symbols, semantic);
+
+        let mut fusion = RankFusion::new(route);
+        let mut sources_searched = Vec::new();
+        let mut source_errors = Vec::new();
+
+        match trigram {
+            Ok(paths) => {
+                sources_searched.push(FusionSource::Trigram);
+                for (rank, path) in paths.into_iter().enumerate() {
+                    fusion.add(FusionSource::Trigram, rank + 1, &path);
+                }
+            }
+            Err(e) => source_errors.push(format!("trigram: {e:#}")),
+        }
+        match semantic {
+            Some(Ok(paths)) => {
+                sources_searched.push(FusionSource::Semantic);
+                for (rank, path) in paths.into_iter().enumerate() {
+                    fusion.add(FusionSource::Semantic, rank + 1, &path);
+                }
+            }
+            Some(Err(e)) => source_errors.push(format!("semantic: {e:#}")),
+            None => {}
+        }
+        match symbols {
+            Ok(matches) => {
+                sources_searched.push(FusionSource::Symbol);
+                // Symbols rank the files that define them, by their best symbol
+                let mut file_rank = 0;
+                for symbol in matches {
+                    let index = fusion.find(&symbol.file_path);
+                    let index = match index {
+                        Some(index) if fusion.hits[index].has_source(FusionSource::Symbol) => index,
+                        _ => {
+                            file_rank += 1;
+                            fusion.add(FusionSource::Symbol, file_rank, &symbol.file_path)
+                        }
+                    };
+                    let hit = &mut fusion.hits[index];
+                    if hit.symbols.len() < MAX_SYMBOLS_PER_HIT {
+                        hit.symbols.push(symbol);
+                    }
+                }
+            }
+            Err(e) => source_errors.push(format!("symbol: {e:#}")),
+        }
+
+        Ok(FusedSearchResult {
+            query,
+            route,
+            results: fusion.into_ranked(options.limit),
+            sources_searched,
+            source_errors,
+            suggestions: vec![],
+            annotations: vec![],
+            notes: vec![],
+            timed_out: false,
+        })
+    }
+
+    /// Path route of fused search: match the query as a glob against document paths
+    async fn search_paths(&self, query: String, limit: usize) -> Result<FusedSearchResult> {
+        let mut fusion = RankFusion::new(QueryRoute::Path);
+        let mut sources_searched = Vec::new();
+        let mut source_errors = Vec::new();
+
+        match self
+            .regular_search(&path_glob(&query), &None, limit, &[])
+            .await
+        {
+            Ok((documents, _)) => {
+                sources_searched.push(FusionSource::Path);
+                for (rank, document) in documents.iter().enumerate() {
+                    fusion.add(FusionSource::Path, rank + 1, document.path.as_str());
+                }
+            }
+            Err(e) => source_errors.push(format!("path: {e:#}")),
+        }
+
+        Ok(FusedSearchResult {
+            query,
+            route: QueryRoute::Path,
+            results: fusion.into_ranked(limit),
+            sources_searched,
+            source_errors,
+            suggestions: vec![],
+            annotations: vec![],
+            notes: vec![],
+            timed_out: false,
+        })
+    }
+
     /// Search for content using the same logic as CLI SearchCode command
+    ///
+    /// Queries are expanded with the database's synonym dictionary: every variant
+    /// is searched as well and its results are merged after the original ones.
+    /// When nothing is found, the result carries "did you mean" suggestions.
     pub async fn search_content(&self, options: SearchOptions) -> Result<SearchResult> {
+        let mut result = self.search_content_with_synonyms(&options).await?;
+        group_results(&mut result, options.group_by, options.limit);
+        if result.is_empty() && !options.query.is_empty() && options.query != "*" {
+            result.suggestions = self.did_you_mean(&options.query).await;
+        }
+        let mut paths: Vec<&str> = result.documents.iter().map(|d| d.path.as_str()).collect();
+        if let Some(llm_response) = &result.llm_response {
+            paths.extend(llm_response.results.iter().map(|r| r.path.as_str()));
+        }
+        if let Some(store) = self.annotation_store() {
+            result.annotations = store.for_results(
+                &options.query,
+                paths.iter().copied(),
+                std::iter::empty(),
+                MAX_ANNOTATIONS_PER_SEARCH,
+            );
+            result.notes = store.notes_for_results(
+                paths.iter().map(|path| (*path, None)),
+                std::iter::empty(),
+                MAX_NOTES_PER_RESULT,
+            );
+        }
+        if let Some(links) = self.repository_links() {
+            result.web_urls = result
+                .documents
+                .iter()
+                .filter_map(|doc| {
+                    let path = doc.path.as_str();
+                    Some((path.to_string(), links.web_url(path, None)?))
+                })
+                .collect();
+            if let Some(llm_response) = &mut result.llm_response {
+                for llm_result in &mut llm_response.results {
+                    llm_result.web_url = links.web_url(&llm_result.path, None);
+                }
+            }
+        }
+        result.timed_out = self.timed_out();
+        Ok(result)
+    }
+
+    async fn search_content_with_synonyms(&self, options: &SearchOptions) -> Result<SearchResult> {
         // Handle empty query
         if options.query.is_empty() {
             return Ok(SearchResult {
@@ -123,32 +855,79 @@ impl<'a> SearchService<'a> {
                 total_count: 0,
                 llm_response: None,
                 search_type: SearchType::RegularSearch,
+                expanded_queries: vec![],
+                suggestions: vec![],
+                annotations: vec![],
+                notes: vec![],
+                web_urls: BTreeMap::new(),
+                timed_out: false,
             });
         }
 
+        let expanded_queries = self.expand_query(&options.query);
+
         // Use LLM-optimized search only when explicitly requested for enhanced context
         // This ensures fast performance by default while maintaining UX consistency
-        if options.query != "*" && (options.context == "medium" || options.context == "full") {
+        if options.query != "*"
+            && self.scope.is_none()
+            && self.corpus.is_none()
+            && self.changed_files.is_none()
+            && self.redaction.is_empty()
+            && self.document_filters.is_empty()
+            && (options.context == "medium" || options.context == "full")
+        {
             // Try LLM-optimized search with fallback to regular search on error
-            match self.try_llm_search(&options).await {
-                Ok(response) => {
+            match self.until_deadline(self.try_llm_search(options)).await {
+                Ok(mut response) => {
+                    for variant in &expanded_queries {
+                        if self.out_of_time(0) {
+                            break;
+                        }
+                        let variant_options = SearchOptions {
+                            query: variant.clone(),
+                            ..options.clone()
+                        };
+                        if let Ok(extra) = self
+                            .until_deadline(self.try_llm_search(&variant_options))
+                            .await
+                        {
+                            merge_llm_responses(&mut response, extra, options.limit);
+                        }
+                    }
                     return Ok(SearchResult {
                         documents: vec![], // Documents are embedded in LLM response
                         total_count: response.optimization.total_matches,
                         llm_response: Some(response),
                         search_type: SearchType::LLMOptimized,
+                        expanded_queries,
+                        suggestions: vec![],
+                        annotations: vec![],
+                        notes: vec![],
+                        web_urls: BTreeMap::new(),
+                        timed_out: false,
                     });
                 }
                 Err(_) => {
                     // Fall back to regular search
                     let (documents, total_count) = self
-                        .regular_search(&options.query, &options.tags, options.limit)
+                        .regular_search(
+                            &options.query,
+                            &options.tags,
+                            options.limit,
+                            &expanded_queries,
+                        )
                         .await?;
                     return Ok(SearchResult {
                         documents,
                         total_count,
                         llm_response: None,
                         search_type: SearchType::RegularSearch,
+                        expanded_queries,
+                        suggestions: vec![],
+                        annotations: vec![],
+                        notes: vec![],
+                        web_urls: BTreeMap::new(),
+                        timed_out: false,
                     });
                 }
             }
@@ -156,7 +935,12 @@ impl<'a> SearchService<'a> {
 
         // Use regular search for wildcard or when context is none
         let (documents, total_count) = self
-            .regular_search(&options.query, &options.tags, options.limit)
+            .regular_search(
+                &options.query,
+                &options.tags,
+                options.limit,
+                &expanded_queries,
+            )
             .await?;
 
         Ok(SearchResult {
@@ -168,9 +952,124 @@ impl<'a> SearchService<'a> {
             } else {
                 SearchType::RegularSearch
             },
+            expanded_queries,
+            suggestions: vec![],
+            annotations: vec![],
+            notes: vec![],
+            web_urls: BTreeMap::new(),
+            timed_out: false,
         })
     }
 
+    /// Symbol names and document paths near `query` by edit distance
+    ///
+    /// Best effort: unreadable symbol or path dictionaries just contribute nothing.
+    async fn did_you_mean(&self, query: &str) -> Vec<String> {
+        if self.out_of_time(0) {
+            return Vec::new();
+        }
+        let mut suggester = Suggester::new();
+
+        let symbol_db_path = self.symbol_db_path.join("symbols.kota");
+        if symbol_db_path.exists() {
+            match BinarySymbolReader::open(&symbol_db_path) {
+                Ok(reader) => {
+                    for (i, packed_symbol) in reader.iter_symbols().enumerate() {
+                        if self.out_of_time(i) {
+                            break;
+                        }
+                        if self.filters_paths()
+                            && !reader
+                                .get_symbol_file_path(&packed_symbol)
+                                .is_ok_and(|path| self.in_scope(&path))
+                        {
+                            continue;
+                        }
+                        if let Ok(name) = reader.get_symbol_name(&packed_symbol) {
+                            suggester.add_identifier(&name);
+                        }
+                    }
+                }
+                Err(e) => tracing::debug!("No symbol suggestions: {e:#}"),
+            }
+        }
+
+        let path_cache = self.database.path_cache();
+        let cached_paths: Vec<String> = path_cache.read().await.keys().cloned().collect();
+        if cached_paths.is_empty() {
+            let storage = self.database.storage();
+            let documents = storage
+                .lock_timed(LockedResource::Storage)
+                .await
+                .list_metadata()
+                .await;
+            match documents {
+                Ok(documents) => {
+                    for document in documents.iter().filter(|d| self.in_scope(d.path.as_str())) {
+                        suggester.add_path(document.path.as_str());
+                    }
+                }
+                Err(e) => tracing::debug!("No path suggestions: {e:#}"),
+            }
+        } else {
+            for path in cached_paths.iter().filter(|path| self.in_scope(path)) {
+                suggester.add_path(path);
+            }
+        }
+
+        suggester.suggest(query, MAX_SUGGESTIONS)
+    }
+
+    /// Stored annotations and notes to attach to results, if there are any
+    ///
+    /// Best effort: an unreadable annotation store contributes nothing.
+    fn annotation_store(&self) -> Option<AnnotationStore> {
+        match AnnotationStore::load(&self.annotations_dir) {
+            Ok(store) if !store.is_empty() => Some(store),
+            Ok(_) => None,
+            Err(e) => {
+                tracing::warn!("Ignoring unreadable annotations: {e:#}");
+                None
+            }
+        }
+    }
+
+    /// Forge links recorded for the database's repositories
+    ///
+    /// Best effort: unreadable links leave results without web URLs.
+    fn repository_links(&self) -> Option<RepositoryLinks> {
+        match RepositoryLinks::load(&self.symbol_db_path) {
+            Ok(links) if !links.repositories.is_empty() => Some(links),
+            Ok(_) => None,
+            Err(e) => {
+                tracing::warn!("Ignoring unreadable repository links: {e:#}");
+                None
+            }
+        }
+    }
+
+    /// Synonym variants of a content query; wildcard queries are never expanded
+    fn expand_query(&self, query: &str) -> Vec<String> {
+        if query.contains('*') {
+            return Vec::new();
+        }
+        self.synonyms().expand_query(query)
+    }
+
+    /// The database's synonym dictionary, read once per service
+    ///
+    /// Best effort: an unreadable dictionary expands nothing.
+    fn synonyms(&self) -> &SynonymDictionary {
+        self.synonyms
+            .get_or_init(|| match SynonymDictionary::load(&self.symbol_db_path) {
+                Ok(dictionary) => dictionary,
+                Err(e) => {
+                    tracing::warn!("Ignoring unreadable synonym dictionary: {e:#}");
+                    SynonymDictionary::default()
+                }
+            })
+    }
+
     /// Search for symbols using the same logic as CLI SearchSymbols command
     pub async fn search_symbols(&self, options: SymbolSearchOptions) -> Result<SymbolResult> {
         let symbol_db_path = self.symbol_db_path.join("symbols.kota");
@@ -180,75 +1079,130 @@ impl<'a> SearchService<'a> {
             return Ok(SymbolResult {
                 matches: vec![],
                 total_symbols: 0,
+                suggestions: vec![],
+                annotations: vec![],
+                notes: vec![],
+                timed_out: false,
             });
         }
 
-        // Open binary symbol reader
-        let reader = BinarySymbolReader::open(&symbol_db_path)?;
+        let reader = match &self.symbol_reader {
+            Some(shared) => shared.get()?,
+            None => Arc::new(BinarySymbolReader::open(&symbol_db_path)?),
+        };
         let total_symbols = reader.symbol_count();
 
         if total_symbols == 0 {
             return Ok(SymbolResult {
                 matches: vec![],
                 total_symbols: 0,
+                suggestions: vec![],
+                annotations: vec![],
+                notes: vec![],
+                timed_out: false,
             });
         }
 
         // Search symbols using the same logic as main.rs
         let mut matches = Vec::new();
         let mut seen_symbols = HashSet::new();
-        let pattern_lower = options.pattern.to_lowercase();
-
-        for packed_symbol in reader.iter_symbols() {
+        let candidates = reader.matching_symbols(&options.pattern);
+        for (i, packed_symbol) in candidates
+            .into_iter()
+            .filter_map(|index| reader.get_symbol(index))
+            .enumerate()
+        {
+            if self.out_of_time(i) {
+                break;
+            }
             if let Ok(symbol_name) = reader.get_symbol_name(&packed_symbol) {
-                let symbol_name_lower = symbol_name.to_lowercase();
+                // Filter by type if specified
+                if let Some(ref filter_type) = options.symbol_type {
+                    let filter_lower = filter_type.to_lowercase();
+                    let type_str = format!("{}", packed_symbol.kind).to_lowercase();
+                    if !type_str.contains(&filter_lower) {
+                        continue;
+                    }
+                }
 
-                // Match against pattern - check for wildcards first, then substring
-                let is_match = if pattern_lower.contains('*') {
-                    matches_wildcard_pattern(&symbol_name_lower, &pattern_lower)
-                } else {
-                    symbol_name_lower.contains(&pattern_lower)
-                };
+                // Get file path for display
+                let file_path = reader
+                    .get_symbol_file_path(&packed_symbol)
+                    .unwrap_or_else(|_| "<unknown>".to_string());
+                if !self.in_scope(&file_path) {
+                    continue;
+                }
 
-                if is_match {
-                    // Filter by type if specified
-                    if let Some(ref filter_type) = options.symbol_type {
-                        let filter_lower = filter_type.to_lowercase();
-                        let type_str = format!("{}", packed_symbol.kind).to_lowercase();
-                        if !type_str.contains(&filter_lower) {
-                            continue;
-                        }
-                    }
+                // Create a unique key for deduplication (name + file + line)
+                let unique_key =
+                    format!("{}:{}:{}", symbol_name, file_path, packed_symbol.start_line);
 
-                    // Get file path for display
-                    let file_path = reader
-                        .get_symbol_file_path(&packed_symbol)
-                        .unwrap_or_else(|_| "<unknown>".to_string());
-
-                    // Create a unique key for deduplication (name + file + line)
-                    let unique_key =
-                        format!("{}:{}:{}", symbol_name, file_path, packed_symbol.start_line);
-
-                    // Only add if we haven't seen this exact symbol before
-                    if seen_symbols.insert(unique_key) {
-                        matches.push(SymbolMatch {
-                            name: symbol_name,
-                            file_path,
-                            start_line: packed_symbol.start_line,
-                            kind: format!("{}", packed_symbol.kind),
-                        });
-
-                        if matches.len() >= options.limit {
-                            break;
-                        }
+                // Only add if we haven't seen this exact symbol before
+                if seen_symbols.insert(unique_key) {
+                    matches.push(SymbolMatch {
+                        name: symbol_name,
+                        file_path,
+                        start_line: packed_symbol.start_line,
+                        end_line: packed_symbol.end_line,
+                        kind: format!("{}", packed_symbol.kind),
+                        range: packed_symbol.has_byte_range().then_some(SourceRange {
+                            start_line: packed_symbol.start_line as usize,
+                            start_column: packed_symbol.start_column as usize,
+                            end_line: packed_symbol.end_line as usize,
+                            end_column: packed_symbol.end_column as usize,
+                            start_byte: packed_symbol.start_byte as usize,
+                            end_byte: packed_symbol.end_byte as usize,
+                        }),
+                        web_url: None,
+                    });
+
+                    if matches.len() >= options.limit {
+                        break;
                     }
                 }
             }
         }
 
+        let suggestions = if matches.is_empty() {
+            self.did_you_mean(&options.pattern).await
+        } else {
+            vec![]
+        };
+        let (annotations, notes) = match self.annotation_store() {
+            Some(store) => (
+                store.for_results(
+                    &options.pattern,
+                    std::iter::empty(),
+                    matches.iter().map(|m| m.name.as_str()),
+                    MAX_ANNOTATIONS_PER_SEARCH,
+                ),
+                store.notes_for_results(
+                    matches.iter().map(|m| {
+                        let lines = LineRange {
+                            start: m.start_line,
+                            end: m.end_line.max(m.start_line),
+                        };
+                        (m.file_path.as_str(), Some(lines))
+                    }),
+                    matches.iter().map(|m| m.name.as_str()),
+                    MAX_NOTES_PER_RESULT,
+                ),
+            ),
+            None => (Vec::new(), Vec::new()),
+        };
+        if let Some(links) = self.repository_links() {
+            for m in &mut matches {
+                m.web_url = links.web_url(&m.file_path, Some((m.start_line, m.end_line)));
+            }
+        }
+
         Ok(SymbolResult {
             matches,
             total_symbols,
+            suggestions,
+            annotations,
+            notes,
+            timed_out: self.timed_out(),
         })
     }
 
@@ -282,8 +1236,10 @@ impl<'a> SearchService<'a> {
         // Perform LLM-optimized search
         let storage_arc = self.database.storage();
         let trigram_index_arc = self.database.trigram_index();
-        let storage = storage_arc.lock().await;
-        let trigram_index = trigram_index_arc.lock().await;
+        let storage = storage_arc.lock_timed(LockedResource::Storage).await;
+        let trigram_index = trigram_index_arc
+            .lock_timed(LockedResource::TrigramIndex)
+            .await;
 
         llm_engine
             .search_optimized(
@@ -296,16 +1252,20 @@ impl<'a> SearchService<'a> {
     }
 
     /// Perform regular search using the database - same logic as Database::search_with_count
+    ///
+    /// `alternatives` (synonym variants) are added to the same trigram query as
+    /// alternative search terms, so a document matching any of them is found once.
     async fn regular_search(
         &self,
         query: &str,
         tags: &Option<Vec<String>>,
         limit: usize,
+        alternatives: &[String],
     ) -> Result<(Vec<Document>, usize)> {
         use crate::QueryBuilder;
 
-        // Handle empty queries
-        if query.is_empty() {
+        // Handle empty queries, and searches starting after the deadline
+        if query.is_empty() || self.out_of_time(0) {
             return Ok((Vec::new(), 0));
         }
 
@@ -322,50 +1282,437 @@ impl<'a> SearchService<'a> {
             }
         }
 
-        query_builder = query_builder.with_limit(limit)?;
-        let query_obj = query_builder.build()?;
+        let candidate_limit = if self.filters_paths() || self.checks_document_filters(query) {
+            SCOPED_CANDIDATE_LIMIT
+        } else {
+            limit
+        };
+        query_builder = query_builder
+            .with_limit(candidate_limit)?
+            .with_filters(self.document_filters.clone())
+            .with_deadline(self.deadline);
+        let mut query_obj = query_builder.build()?;
+        if !query.contains('*') {
+            for alternative in alternatives {
+                let Ok(sanitized) = crate::query_sanitization::sanitize_search_query(alternative)
+                else {
+                    continue;
+                };
+                if !sanitized.is_empty() {
+                    query_obj
+                        .search_terms
+                        .push(crate::types::ValidatedSearchQuery::new(&sanitized.text, 1)?);
+                }
+            }
+        }
 
         // Route to appropriate index based on query type
-        let doc_ids = if query.contains('*') {
-            // Use Primary Index for wildcard/pattern queries
-            self.database
-                .primary_index()
-                .lock()
-                .await
-                .search(&query_obj)
-                .await?
+        let index_search = async {
+            if query.contains('*') {
+                // Use Primary Index for wildcard/pattern queries
+                self.database
+                    .primary_index()
+                    .lock_timed(LockedResource::PrimaryIndex)
+                    .await
+                    .search(&query_obj)
+                    .await
+            } else {
+                // Use Trigram Index for full-text search queries
+                self.database
+                    .trigram_index()
+                    .lock_timed(LockedResource::TrigramIndex)
+                    .await
+                    .search(&query_obj)
+                    .await
+            }
+        };
+        // Index traversals that check the query deadline return the candidates they found
+        // by then; an abandoned index search has no partial candidates to return
+        let Some(doc_ids) = self.deadline.run(index_search).await else {
+            self.timed_out.store(true, Ordering::Relaxed);
+            return Ok((Vec::new(), 0));
+        };
+        let doc_ids = doc_ids?;
+        if self.deadline.expired() {
+            self.timed_out.store(true, Ordering::Relaxed);
+        }
+
+        let doc_ids: Vec<_> = match &self.scope {
+            Some(scope) => doc_ids
+                .into_iter()
+                .filter(|id| scope.contains_document(id))
+                .collect(),
+            None => doc_ids,
+        };
+
+        let storage_arc = self.database.storage();
+        let storage = storage_arc.lock_timed(LockedResource::Storage).await;
+
+        // Changed-file windows, redaction and document filters only need the stored path
+        // and size of every candidate
+        let doc_ids = if self.changed_files.is_some()
+            || !self.redaction.is_empty()
+            || self.checks_document_filters(query)
+        {
+            let mut candidates = Vec::new();
+            for (i, doc_id) in doc_ids.into_iter().enumerate() {
+                if self.out_of_time(i) {
+                    break;
+                }
+                if let Some(summary) = storage.summary(&doc_id).await? {
+                    let path = summary.path.as_str();
+                    if self.redaction.allows(path)
+                        && self.document_filters.matches_path(path)
+                        && self.document_filters.matches_size(summary.size as u64)
+                        && self
+                            .changed_files
+                            .as_ref()
+                            .is_none_or(|changed| changed.contains(path))
+                    {
+                        candidates.push(doc_id);
+                    }
+                }
+            }
+            candidates
         } else {
-            // Use Trigram Index for full-text search queries
-            self.database
-                .trigram_index()
-                .lock()
-                .await
-                .search(&query_obj)
-                .await?
+            doc_ids
         };
 
+        // Corpus filters need the stored path of every candidate, and facets also need
+        // its content, so candidates are narrowed by path before any content is read
+        if let Some(corpus) = &self.corpus {
+            let mut candidates = Vec::new();
+            for (i, doc_id) in doc_ids.into_iter().enumerate() {
+                if self.out_of_time(i) {
+                    break;
+                }
+                if let Some(summary) = storage.summary(&doc_id).await? {
+                    if corpus.matches_path(summary.path.as_str()) {
+                        candidates.push(doc_id);
+                    }
+                }
+            }
+            if !corpus.has_facets() {
+                let total_count = candidates.len();
+                candidates.truncate(limit);
+                let documents = storage.get_many(&candidates).await?;
+                return Ok((documents.into_iter().flatten().collect(), total_count));
+            }
+
+            let mut documents = Vec::new();
+            let mut total_count = 0;
+            for batch in candidates.chunks(CORPUS_FETCH_BATCH) {
+                if self.out_of_time(0) {
+                    break;
+                }
+                for doc in storage.get_many(batch).await?.into_iter().flatten() {
+                    if corpus.matches_document(doc.path.as_str(), &doc.content) {
+                        total_count += 1;
+                        if documents.len() < limit {
+                            documents.push(doc);
+                        }
+                    }
+                }
+            }
+            return Ok((documents, total_count));
+        }
+
         // Store total count before limiting
         let total_count = doc_ids.len();
 
-        // Retrieve documents from storage
+        // Retrieve documents from storage in one batch
         let doc_ids_limited: Vec<_> = doc_ids.into_iter().take(limit).collect();
-        let mut documents = Vec::with_capacity(doc_ids_limited.len());
-        let storage_arc = self.database.storage();
-        let storage = storage_arc.lock().await;
+        let documents: Vec<_> = storage
+            .get_many(&doc_ids_limited)
+            .await?
+            .into_iter()
+            .flatten()
+            .collect();
+
+        Ok((documents, total_count))
+    }
+}
+
+/// Accumulates reciprocal-rank fusion scores per file
+struct RankFusion {
+    route: QueryRoute,
+    hits: Vec<FusedHit>,
+}
+
+impl RankFusion {
+    fn new(route: QueryRoute) -> Self {
+        Self {
+            route,
+            hits: Vec::new(),
+        }
+    }
+
+    /// Hits ordered by fused score, cut to `limit`
+    fn into_ranked(self, limit: usize) -> Vec<FusedHit> {
+        let mut results = self.hits;
+        results.sort_by(|a, b| {
+            b.score
+                .partial_cmp(&a.score)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then_with(|| a.path.cmp(&b.path))
+        });
+        results.truncate(limit);
+        results
+    }
+
+    /// Index of the hit for `path`, matching ingested document paths
+    /// (`repos/app/files/src/lib.rs`) against repository-relative symbol paths
+    fn find(&self, path: &str) -> Option<usize> {
+        self.hits.iter().position(|hit| same_file(&hit.path, path))
+    }
 
-        for doc_id in doc_ids_limited {
-            if let Some(doc) = storage.get(&doc_id).await? {
-                documents.push(doc);
+    /// Record that `source` ranked `path` at `rank`, returning the hit's index
+    ///
+    /// The contribution `1 / (RRF_K + rank)` is scaled by the route's weight for `source`.
+    fn add(&mut self, source: FusionSource, rank: usize, path: &str) -> usize {
+        let index = match self.find(path) {
+            Some(index) => index,
+            None => {
+                self.hits.push(FusedHit {
+                    path: path.to_string(),
+                    score: 0.0,
+                    sources: Vec::new(),
+                    symbols: Vec::new(),
+                    web_url: None,
+                });
+                self.hits.len() - 1
             }
+        };
+        let hit = &mut self.hits[index];
+        if !hit.has_source(source) {
+            hit.score += self.route.weight(source) / (RRF_K + rank as f64);
+            hit.sources.push(SourceRank { source, rank });
         }
+        index
+    }
+}
 
-        Ok((documents, total_count))
+impl FusedHit {
+    fn has_source(&self, source: FusionSource) -> bool {
+        self.sources.iter().any(|entry| entry.source == source)
     }
 }
 
+fn same_file(a: &str, b: &str) -> bool {
+    let (a, b) = (a.trim_start_matches("./"), b.trim_start_matches("./"));
+    a == b || a.ends_with(&format!("/{b}")) || b.ends_with(&format!("/{a}"))
+}
+
+/// Part of a symbol-shaped query that names the symbol: `Foo::bar` → `bar`,
+/// `self.index()` → `index`
+fn symbol_subject(query: &str) -> &str {
+    let last = query.rsplit("::").next().unwrap_or(query);
+    let last = last.rsplit('.').next().unwrap_or(last);
+    let last = last.strip_suffix("()").unwrap_or(last);
+    if last.is_empty() {
+        query
+    } else {
+        last
+    }
+}
+
+/// Document-path glob for a path-shaped query: `src/**.rs` → `*src/*.rs`,
+/// `lib.rs` → `*lib.rs`; patterns without wildcards also match as a prefix
+fn path_glob(query: &str) -> String {
+    let mut pattern = query.replace('\\', "/");
+    while pattern.contains("**") {
+        pattern = pattern.replace("**", "*");
+    }
+    let had_wildcard = pattern.contains('*');
+    if !pattern.starts_with('*') {
+        pattern.insert(0, '*');
+    }
+    if !had_wildcard && !has_file_extension(query) {
+        pattern.push('*');
+    }
+    pattern
+}
+
+/// Symbol-name pattern for a free-text query: `storage engine` matches
+/// `storage_engine`, `StorageEngine` and `open_storage_engine`
+fn symbol_pattern(query: &str) -> String {
+    if query.contains('*') {
+        return query.to_string();
+    }
+    let words: Vec<&str> = query.split_whitespace().collect();
+    format!("*{}*", words.join("*"))
+}
+
+/// Order symbol matches by how closely the name matches the query: exact names
+/// first, then shorter names
+fn rank_symbol_matches(query: &str, mut matches: Vec<SymbolMatch>) -> Vec<SymbolMatch> {
+    let compact = |text: &str| -> String {
+        text.chars()
+            .filter(|c| c.is_alphanumeric())
+            .flat_map(char::to_lowercase)
+            .collect()
+    };
+    let wanted = compact(query);
+    matches.sort_by_key(|symbol| (compact(&symbol.name) != wanted, symbol.name.len()));
+    matches
+}
+
+/// Merge the results of a synonym variant into the original LLM response
+///
+/// Results already present (by path) are skipped, the merged list is re-ranked by
+/// relevance and cut to `limit`, and results that would exceed the token budget
+/// are dropped.
+/// Apply `grouping` to the results of a content search
+fn group_results(result: &mut SearchResult, grouping: SearchGrouping, limit: usize) {
+    match grouping {
+        SearchGrouping::File => {
+            let mut seen = HashSet::new();
+            result
+                .documents
+                .retain(|document| seen.insert(document.path.as_str().to_string()));
+            if let Some(response) = &mut result.llm_response {
+                group_llm_results_by_file(response);
+            }
+        }
+        SearchGrouping::Match => {
+            if let Some(response) = &mut result.llm_response {
+                split_llm_results_by_match(response, limit);
+            }
+        }
+    }
+}
+
+/// Merge LLM results that share a path into one, scored by the best of them and
+/// holding the match locations of all of them
+fn group_llm_results_by_file(response: &mut LLMSearchResponse) {
+    let mut positions: HashMap<String, usize> = HashMap::new();
+    let mut grouped: Vec<LLMSearchResult> = Vec::with_capacity(response.results.len());
+    let mut merged = false;
+    for result in response.results.drain(..) {
+        let Some(&at) = positions.get(&result.path) else {
+            positions.insert(result.path.clone(), grouped.len());
+            grouped.push(result);
+            continue;
+        };
+        merged = true;
+        let kept = &mut grouped[at];
+        let other = if result.relevance_score > kept.relevance_score {
+            std::mem::replace(kept, result)
+        } else {
+            result
+        };
+        let details = &mut kept.match_details;
+        details
+            .exact_matches
+            .extend(other.match_details.exact_matches);
+        details
+            .term_matches
+            .extend(other.match_details.term_matches);
+        details.match_quality = details.match_quality.max(other.match_details.match_quality);
+    }
+    if merged {
+        grouped.sort_by(|a, b| {
+            b.relevance_score
+                .partial_cmp(&a.relevance_score)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+    }
+    response.results = grouped;
+}
+
+/// Replace each LLM result by one result per match location, snippeted around that
+/// match; results keep their file's score, so a file's matches stay together
+fn split_llm_results_by_match(response: &mut LLMSearchResponse, limit: usize) {
+    let mut split = Vec::with_capacity(limit.min(response.results.len()));
+    for result in response.results.drain(..) {
+        if split.len() >= limit {
+            break;
+        }
+        let details = &result.match_details;
+        if details.exact_matches.len() + details.term_matches.len() <= 1 {
+            split.push(result);
+            continue;
+        }
+        let locations: Vec<(bool, MatchLocation)> = details
+            .exact_matches
+            .iter()
+            .map(|location| (true, location.clone()))
+            .chain(
+                details
+                    .term_matches
+                    .iter()
+                    .map(|location| (false, location.clone())),
+            )
+            .collect();
+        for (exact, location) in locations {
+            let mut single = result.clone();
+            single.content_snippet = location.context.clone();
+            single.estimated_tokens = estimate_tokens(&single.content_snippet);
+            let (exact_matches, term_matches) = if exact {
+                (vec![location], Vec::new())
+            } else {
+                (Vec::new(), vec![location])
+            };
+            single.match_details.exact_matches = exact_matches;
+            single.match_details.term_matches = term_matches;
+            split.push(single);
+        }
+    }
+    split.truncate(limit);
+    response.results = split;
+}
+
+fn merge_llm_responses(response: &mut LLMSearchResponse, extra: LLMSearchResponse, limit: usize) {
+    let mut added = 0;
+    for result in extra.results {
+        if response
+            .results
+            .iter()
+            .any(|existing| existing.path == result.path)
+        {
+            continue;
+        }
+        response.results.push(result);
+        added += 1;
+    }
+    if added == 0 {
+        return;
+    }
+
+    response.results.sort_by(|a, b| {
+        b.relevance_score
+            .partial_cmp(&a.relevance_score)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+
+    let budget = response.optimization.token_usage.budget;
+    let mut estimated_tokens = 0;
+    let mut kept = Vec::with_capacity(limit.min(response.results.len()));
+    for result in response.results.drain(..) {
+        if kept.len() >= limit {
+            break;
+        }
+        if !kept.is_empty() && estimated_tokens + result.estimated_tokens > budget {
+            continue;
+        }
+        estimated_tokens += result.estimated_tokens;
+        kept.push(result);
+    }
+    response.results = kept;
+
+    response.optimization.total_matches += added;
+    response.optimization.returned = response.results.len();
+    response.optimization.token_usage.estimated_tokens = estimated_tokens;
+    response.optimization.token_usage.efficiency = if budget > 0 {
+        estimated_tokens as f32 / budget as f32
+    } else {
+        0.0
+    };
+}
+
 /// Match a string against a wildcard pattern
 /// Copied from main.rs to maintain identical behavior
-fn matches_wildcard_pattern(text: &str, pattern: &str) -> bool {
+pub(crate) fn matches_wildcard_pattern(text: &str, pattern: &str) -> bool {
     // Handle pure wildcard
     if pattern == "*" {
         return true;
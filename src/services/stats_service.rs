@@ -4,13 +4,20 @@
 // and ManagementService to provide comprehensive database analytics across all interfaces.
 
 use anyhow::Result;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
+use uuid::Uuid;
 
-use super::DatabaseAccess;
+use super::{AnalysisService, DatabaseAccess, RepositoryScope};
+use crate::metrics::performance::{
+    lock_contention, LockContentionStats, LockedResource, TimedLock,
+};
 use crate::{
     binary_relationship_engine::BinaryRelationshipEngine,
-    relationship_query::RelationshipQueryConfig, Document,
+    dependency_extractor::{DependencyGraph, SerializableDependencyGraph},
+    relationship_query::RelationshipQueryConfig,
+    trends::{MetricsChange, MetricsSnapshot, TrendHistory},
+    DocumentMetadata,
 };
 
 /// Configuration options for database statistics
@@ -20,6 +27,8 @@ pub struct StatsOptions {
     pub symbols: bool,
     pub relationships: bool,
     pub detailed: bool,
+    /// Compare the latest indexing run with the one before it
+    pub compare_previous: bool,
     pub quiet: bool,
 }
 
@@ -203,12 +212,29 @@ pub struct ThroughputMetrics {
     pub queue_wait_time_ms: f64,
 }
 
+/// The latest indexing run of a repository next to its previous run
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct RunComparison {
+    pub current: MetricsSnapshot,
+    /// None until the repository has been indexed twice
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub previous: Option<MetricsSnapshot>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub change: Option<MetricsChange>,
+}
+
 /// Result structure for database statistics
 #[derive(Debug, Clone, serde::Serialize)]
 pub struct StatsResult {
     pub basic_stats: Option<BasicStats>,
     pub symbol_stats: Option<SymbolStats>,
     pub relationship_stats: Option<RelationshipStats>,
+    /// Wait times on the storage and index locks, when detailed statistics are requested
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lock_contention: Option<Vec<LockContentionStats>>,
+    /// Deltas against the previous indexing run, when requested and a run was recorded
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub comparison: Option<RunComparison>,
     pub formatted_output: String,
 }
 
@@ -230,12 +256,45 @@ pub struct PerformanceMetricsResult {
 pub struct StatsService<'a> {
     database: &'a dyn DatabaseAccess,
     db_path: PathBuf,
+    scope: Option<RepositoryScope>,
 }
 
 impl<'a> StatsService<'a> {
     /// Create a new StatsService instance
     pub fn new(database: &'a dyn DatabaseAccess, db_path: PathBuf) -> Self {
-        Self { database, db_path }
+        Self {
+            database,
+            db_path,
+            scope: None,
+        }
+    }
+
+    /// Only count the documents, symbols, relationships and indexing runs of the
+    /// scoped repositories
+    pub fn with_repository_scope(mut self, scope: RepositoryScope) -> Self {
+        self.scope = Some(scope);
+        self
+    }
+
+    fn in_scope(&self, path: &str) -> bool {
+        self.scope
+            .as_ref()
+            .is_none_or(|scope| scope.contains_path(path))
+    }
+
+    /// Metadata of the stored documents in scope
+    async fn scoped_metadata(&self) -> Result<Vec<DocumentMetadata>> {
+        let mut documents = self
+            .database
+            .storage()
+            .lock_timed(LockedResource::Storage)
+            .await
+            .list_metadata()
+            .await?;
+        if let Some(scope) = &self.scope {
+            documents.retain(|document| scope.contains_document(&document.id));
+        }
+        Ok(documents)
     }
 
     /// Get comprehensive database statistics
@@ -283,6 +342,20 @@ impl<'a> StatsService<'a> {
             }
         }
 
+        // Lock contention is collected last so that it covers the locks taken above
+        let lock_contention = options.detailed.then(lock_contention);
+        if let Some(ref stats) = lock_contention {
+            formatted_output.push_str(&self.format_lock_contention(stats));
+        }
+
+        let comparison = if options.compare_previous {
+            let comparison = self.compare_previous_run()?;
+            formatted_output.push_str(&Self::format_run_comparison(comparison.as_ref()));
+            comparison
+        } else {
+            None
+        };
+
         // Add helpful tips and next steps
         if !options.quiet {
             formatted_output.push_str(&self.generate_usage_tips().await?);
@@ -292,10 +365,42 @@ impl<'a> StatsService<'a> {
             basic_stats,
             symbol_stats,
             relationship_stats,
+            lock_contention,
+            comparison,
             formatted_output,
         })
     }
 
+    /// Compare the newest recorded indexing run with the previous run of the same repository
+    ///
+    /// Every successful `index-codebase` run appends its summary to the trend history,
+    /// so this needs nothing beyond what indexing already persisted.
+    pub fn compare_previous_run(&self) -> Result<Option<RunComparison>> {
+        let mut runs = TrendHistory::load(&self.db_path, usize::MAX)?;
+        if let Some(scope) = &self.scope {
+            runs.retain(|run| {
+                run.document_prefix
+                    .as_deref()
+                    .is_some_and(|prefix| scope.contains_repository(prefix))
+            });
+        }
+        let Some(current) = runs.pop() else {
+            return Ok(None);
+        };
+        let previous = runs
+            .into_iter()
+            .rev()
+            .find(|run| run.repository == current.repository);
+        let change = previous
+            .as_ref()
+            .map(|previous| MetricsChange::between(previous, &current));
+        Ok(Some(RunComparison {
+            current,
+            previous,
+            change,
+        }))
+    }
+
     /// Perform comprehensive health check of the database
     pub async fn health_check(&self, options: HealthCheckOptions) -> Result<HealthCheckResult> {
         let mut formatted_output = String::new();
@@ -454,9 +559,7 @@ impl<'a> StatsService<'a> {
     // Private helper methods
 
     async fn get_basic_statistics(&self) -> Result<BasicStats> {
-        let storage_arc = self.database.storage();
-        let storage = storage_arc.lock().await;
-        let all_docs = storage.list_all().await?;
+        let all_docs = self.scoped_metadata().await?;
 
         let count = all_docs.len();
         let total_size: usize = all_docs.iter().map(|d| d.size).sum();
@@ -493,7 +596,7 @@ impl<'a> StatsService<'a> {
         }
 
         let reader = crate::binary_symbols::BinarySymbolReader::open(&symbol_db_path)?;
-        let total_symbols = reader.symbol_count();
+        let mut total_symbols = 0;
 
         // Collect detailed statistics from binary symbols
         let mut symbols_by_type: HashMap<String, usize> = HashMap::new();
@@ -501,6 +604,12 @@ impl<'a> StatsService<'a> {
         let mut unique_files = std::collections::HashSet::new();
 
         for symbol in reader.iter_symbols() {
+            let file_path = reader.get_symbol_file_path(&symbol).ok();
+            if self.scope.is_some() && !file_path.as_deref().is_some_and(|p| self.in_scope(p)) {
+                continue;
+            }
+            total_symbols += 1;
+
             // Count by type - convert u8 back to SymbolType for readable display
             let type_name = match crate::parsing::SymbolType::try_from(symbol.kind) {
                 Ok(symbol_type) => format!("{}", symbol_type),
@@ -509,7 +618,7 @@ impl<'a> StatsService<'a> {
             *symbols_by_type.entry(type_name).or_insert(0) += 1;
 
             // Count by language and track unique files
-            if let Ok(file_path) = reader.get_symbol_file_path(&symbol) {
+            if let Some(file_path) = file_path {
                 unique_files.insert(file_path.clone());
                 let path = std::path::Path::new(&file_path);
                 let lang = crate::path_utils::detect_language_from_extension(path);
@@ -525,10 +634,7 @@ impl<'a> StatsService<'a> {
         };
 
         // Calculate actual extraction coverage: files with symbols / total files analyzed
-        let storage_arc = self.database.storage();
-        let storage = storage_arc.lock().await;
-        let all_docs = storage.list_all().await?;
-        let total_files_analyzed = all_docs.len();
+        let total_files_analyzed = self.scoped_metadata().await?.len();
 
         let extraction_coverage = if total_files_analyzed > 0 {
             (files_with_symbols as f64 / total_files_analyzed as f64) * 100.0
@@ -548,6 +654,9 @@ impl<'a> StatsService<'a> {
 
     #[cfg(feature = "tree-sitter-parsing")]
     async fn get_relationship_statistics(&self) -> Result<RelationshipStats> {
+        if self.scope.is_some() {
+            return self.scoped_relationship_statistics();
+        }
         // Use BinaryRelationshipEngine to get actual relationship statistics
         let config = RelationshipQueryConfig::default();
         let binary_engine = match BinaryRelationshipEngine::new(&self.db_path, config).await {
@@ -629,6 +738,69 @@ impl<'a> StatsService<'a> {
         })
     }
 
+    /// Relationships from the scoped repositories' symbols, counted in the dependency graph
+    #[cfg(feature = "tree-sitter-parsing")]
+    fn scoped_relationship_statistics(&self) -> Result<RelationshipStats> {
+        let graph_path = self.db_path.join("dependency_graph.bin");
+        if !graph_path.exists() {
+            return Ok(RelationshipStats {
+                total_relationships: 0,
+                connected_symbols: 0,
+                dependency_graph_stats: None,
+                relationship_types: HashMap::new(),
+                average_connections_per_symbol: 0.0,
+            });
+        }
+        let graph = SerializableDependencyGraph::from_bytes(&std::fs::read(&graph_path)?)?;
+        let scoped: HashSet<Uuid> = graph
+            .nodes
+            .iter()
+            .filter(|node| self.in_scope(&node.file_path.to_string_lossy()))
+            .map(|node| node.symbol_id)
+            .collect();
+        let mut relationship_types: HashMap<String, usize> = HashMap::new();
+        let mut connected = HashSet::new();
+        let mut edges = 0;
+        for edge in graph.edges.iter().filter(|e| scoped.contains(&e.from_id)) {
+            edges += 1;
+            connected.insert(edge.from_id);
+            if scoped.contains(&edge.to_id) {
+                connected.insert(edge.to_id);
+            }
+            *relationship_types
+                .entry(AnalysisService::relation_type_name(
+                    &edge.edge.relation_type,
+                ))
+                .or_insert(0) += 1;
+        }
+        let circular_dependencies = DependencyGraph::from_serializable(graph)?
+            .find_circular_dependencies()
+            .iter()
+            .filter(|cycle| cycle.iter().any(|id| scoped.contains(id)))
+            .count();
+        let average = |count: usize| {
+            if count > 0 {
+                edges as f64 / count as f64
+            } else {
+                0.0
+            }
+        };
+        Ok(RelationshipStats {
+            total_relationships: edges,
+            connected_symbols: connected.len(),
+            dependency_graph_stats: Some(DependencyGraphStats {
+                nodes: scoped.len(),
+                edges,
+                strongly_connected_components: circular_dependencies,
+                average_connections_per_node: average(scoped.len()),
+                max_depth: 0,
+                circular_dependencies,
+            }),
+            relationship_types,
+            average_connections_per_symbol: average(scoped.len()),
+        })
+    }
+
     async fn format_basic_statistics(&self, stats: &BasicStats) -> Result<String> {
         let mut output = String::new();
 
@@ -726,6 +898,77 @@ impl<'a> StatsService<'a> {
         Ok(output)
     }
 
+    fn format_run_comparison(comparison: Option<&RunComparison>) -> String {
+        let mut output = String::from("📈 Compared with Previous Index Run:\n");
+        let Some(comparison) = comparison else {
+            output.push_str("   No indexing runs recorded yet; run index-codebase first\n\n");
+            return output;
+        };
+        let (Some(previous), Some(change)) = (&comparison.previous, &comparison.change) else {
+            output.push_str(&format!(
+                "   Only one run of {} recorded ({}); index again to see changes\n\n",
+                comparison.current.repository, comparison.current.recorded_at
+            ));
+            return output;
+        };
+
+        output.push_str(&format!(
+            "   {} → {}\n",
+            previous.recorded_at, comparison.current.recorded_at
+        ));
+        for (label, value, delta) in [
+            ("Files", comparison.current.files, change.files),
+            ("Symbols", comparison.current.symbols, change.symbols),
+            (
+                "Relationships",
+                comparison.current.relationships,
+                change.relationships,
+            ),
+        ] {
+            output.push_str(&format!("   {}: {} ({:+})\n", label, value, delta));
+        }
+        output.push('\n');
+        output
+    }
+
+    fn format_lock_contention(&self, stats: &[LockContentionStats]) -> String {
+        let mut output = String::new();
+
+        output.push_str(
+            "🔒 Lock Contention
+",
+        );
+        output.push_str(
+            "=================
+
+",
+        );
+
+        for resource in stats {
+            output.push_str(&format!(
+                "   {}: {} acquisitions, {} contended ({:.1}%), avg wait {:.2}ms, max wait {:.2}ms\n",
+                resource.resource.as_str(),
+                resource.acquisitions,
+                resource.contended,
+                resource.contention_ratio() * 100.0,
+                resource.average_contended_wait().as_secs_f64() * 1000.0,
+                resource.max_wait.as_secs_f64() * 1000.0
+            ));
+        }
+        if stats
+            .iter()
+            .any(|resource| resource.contention_ratio() > 0.1)
+        {
+            output.push_str(
+                "   ⚠️  Over 10% of acquisitions waited: concurrent requests are serialized \
+                 on the single-writer locks\n",
+            );
+        }
+
+        output.push('\n');
+        output
+    }
+
     async fn format_health_report(&self, report: &HealthReport) -> Result<String> {
         let mut output = String::new();
 
@@ -835,25 +1078,17 @@ impl<'a> StatsService<'a> {
     }
 
     /// Calculate actual storage efficiency based on document data
-    async fn calculate_storage_efficiency(&self, documents: &[Document]) -> Result<f64> {
-        if documents.is_empty() {
-            return Ok(0.0);
-        }
-
+    async fn calculate_storage_efficiency(&self, documents: &[DocumentMetadata]) -> Result<f64> {
         // Calculate efficiency based on several factors:
-        // 1. Content size vs document size ratio (compression/overhead)
-        // 2. File system efficiency estimation
-        // 3. Fragmentation approximation
+        // 1. File system efficiency estimation
+        // 2. Fragmentation approximation
+        // Stored sizes are content lengths, so content itself adds no overhead
 
-        let total_content_size: usize = documents.iter().map(|d| d.content.len()).sum();
-        let total_document_size: usize = documents.iter().map(|d| d.size).sum();
-
-        // Base efficiency: actual content vs stored document size
-        let content_efficiency = if total_document_size > 0 {
-            total_content_size as f64 / total_document_size as f64
-        } else {
-            0.0
-        };
+        let total_content_size: usize = documents.iter().map(|d| d.size).sum();
+        if total_content_size == 0 {
+            return Ok(0.0);
+        }
+        let content_efficiency = 1.0;
 
         // Account for file system overhead and metadata
         // Small files tend to have lower efficiency due to minimum allocation units
@@ -913,10 +1148,10 @@ mod tests {
     use super::*;
     use crate::Document;
 
-    // Helper function to create test documents
-    fn create_test_document(path: &str, content: &str, size: usize) -> Document {
+    // Helper function to create metadata of test documents
+    fn create_test_document(path: &str, content: &str, size: usize) -> DocumentMetadata {
         use chrono::Utc;
-        Document {
+        DocumentMetadata::of_document(&Document {
             id: crate::ValidatedDocumentId::new(),
             path: crate::ValidatedPath::new(path).unwrap(),
             title: crate::ValidatedTitle::new("Test Title").unwrap(),
@@ -926,7 +1161,7 @@ mod tests {
             created_at: Utc::now(),
             updated_at: Utc::now(),
             size,
-        }
+        })
     }
 
     // Mock database access for testing
@@ -985,15 +1220,15 @@ mod tests {
         );
 
         // Test case 3: Very small files (penalty case)
-        let small_documents = vec![create_test_document("tiny.txt", "hi", 1000)]; // 1KB, much larger than content
-        let efficiency = service
+        let small_documents = vec![create_test_document("tiny.txt", "hi", 2)];
+        let small_efficiency = service
             .calculate_storage_efficiency(&small_documents)
             .await
             .unwrap();
         assert!(
-            efficiency < 0.2,
+            small_efficiency < efficiency,
             "Small files should have lower efficiency: {}",
-            efficiency
+            small_efficiency
         );
 
         // Test case 4: Large files (good efficiency)
@@ -1140,4 +1375,57 @@ mod tests {
         assert!(!options.relationships);
         assert!(!options.quiet);
     }
+
+    #[test]
+    fn test_lock_contention_flags_serialized_access() {
+        use std::time::Duration;
+
+        let db = MockDatabaseAccess;
+        let service = StatsService::new(&db, PathBuf::from("/tmp/test"));
+        let stats = |contended| LockContentionStats {
+            resource: LockedResource::Storage,
+            acquisitions: 100,
+            contended,
+            total_wait: Duration::from_millis(contended * 2),
+            max_wait: Duration::from_millis(5),
+        };
+
+        let quiet = service.format_lock_contention(&[stats(1)]);
+        assert!(quiet.contains("storage: 100 acquisitions, 1 contended (1.0%)"));
+        assert!(!quiet.contains("single-writer"));
+
+        let busy = service.format_lock_contention(&[stats(40)]);
+        assert!(busy.contains("avg wait 2.00ms, max wait 5.00ms"));
+        assert!(busy.contains("single-writer"));
+    }
+
+    #[test]
+    fn test_compare_previous_run_of_the_same_repository() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let db = MockDatabaseAccess;
+        let service = StatsService::new(&db, dir.path().to_path_buf());
+        assert!(service.compare_previous_run().unwrap().is_none());
+
+        let run = |repository: &str, files, symbols, relationships| MetricsSnapshot {
+            recorded_at: "2026-10-01T00:00:00Z".to_string(),
+            repository: repository.to_string(),
+            files,
+            symbols,
+            relationships,
+            ..Default::default()
+        };
+        TrendHistory::append(dir.path(), &run("app", 10, 100, 40)).unwrap();
+        let only = service.compare_previous_run().unwrap().unwrap();
+        assert!(only.previous.is_none());
+        assert!(StatsService::format_run_comparison(Some(&only)).contains("Only one run of app"));
+
+        TrendHistory::append(dir.path(), &run("lib", 3, 30, 5)).unwrap();
+        TrendHistory::append(dir.path(), &run("app", 12, 95, 40)).unwrap();
+        let comparison = service.compare_previous_run().unwrap().unwrap();
+        assert_eq!(comparison.previous.as_ref().unwrap().files, 10);
+        let output = StatsService::format_run_comparison(Some(&comparison));
+        assert!(output.contains("Files: 12 (+2)"), "{output}");
+        assert!(output.contains("Symbols: 95 (-5)"), "{output}");
+        assert!(output.contains("Relationships: 40 (+0)"), "{output}");
+    }
 }
@@ -7,6 +7,7 @@ use anyhow::Result;
 use std::collections::HashMap;
 use std::path::PathBuf;
 
+use crate::metrics::performance::{LockedResource, TimedLock};
 use crate::{
     search_validation::{ValidationCheck, ValidationStatus},
     validate_post_ingestion_search,
@@ -666,9 +667,13 @@ impl<'a> ValidationService<'a> {
             let storage_arc = self.database.storage();
             let primary_index_arc = self.database.primary_index();
             let trigram_index_arc = self.database.trigram_index();
-            let storage = storage_arc.lock().await;
-            let primary_index = primary_index_arc.lock().await;
-            let trigram_index = trigram_index_arc.lock().await;
+            let storage = storage_arc.lock_timed(LockedResource::Storage).await;
+            let primary_index = primary_index_arc
+                .lock_timed(LockedResource::PrimaryIndex)
+                .await;
+            let trigram_index = trigram_index_arc
+                .lock_timed(LockedResource::TrigramIndex)
+                .await;
             validate_post_ingestion_search(&*storage, &*primary_index, &*trigram_index).await?
         };
 
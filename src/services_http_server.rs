@@ -12,7 +12,7 @@ use axum::{
     extract::{Query as AxumQuery, State},
     http::{HeaderMap, StatusCode},
     response::Json,
-    routing::{get, post},
+    routing::{any, delete, get, patch, post, put},
     Router,
 };
 use chrono::Utc;
@@ -22,9 +22,12 @@ use serde::{Deserialize, Serialize};
 use serde_json::{json, Map as JsonMap, Value as JsonValue};
 use sha2::{Digest, Sha256};
 use sqlx::{PgPool, Row};
-use std::sync::Arc;
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
 use std::{
-    collections::{BTreeSet, HashMap},
+    collections::{BTreeMap, BTreeSet, HashMap},
     env,
     net::SocketAddr,
     path::PathBuf,
@@ -32,29 +35,79 @@ use std::{
 };
 use tokio::{net::TcpListener, sync::RwLock};
 use tower::ServiceBuilder;
-use tower_http::{cors::CorsLayer, trace::TraceLayer};
+use tower_http::{
+    compression::{
+        predicate::{Predicate, SizeAbove},
+        CompressionLayer,
+    },
+    cors::CorsLayer,
+    trace::TraceLayer,
+};
 use tracing::{debug, error, info, warn};
 use url::Url;
 use uuid::Uuid;
 
-#[cfg(all(feature = "mcp-server", feature = "tree-sitter-parsing"))]
 use crate::mcp::tools::symbol_tools::SymbolTools;
 #[cfg(feature = "mcp-server")]
 use crate::mcp::tools::MCPToolRegistry;
 #[cfg(feature = "mcp-server")]
 use crate::mcp_http_bridge::{create_mcp_bridge_router, McpHttpBridgeState};
-use crate::{auth_middleware::AuthContext, observability::with_trace_id, Index, Storage};
+#[cfg(all(feature = "mcp-server", feature = "tree-sitter-parsing"))]
+use crate::metrics::performance::{export_lock_contention_prometheus, LockedResource, TimedLock};
+use crate::response_limits::{ResponseLimits, MAX_RESPONSE_BYTES_HEADER};
 use crate::{
+    annotations::{
+        Annotation, AnnotationKind, AnnotationStore, NewNote, Note, NoteFilter, NoteUpdate,
+        MAX_ANNOTATIONS_PER_SEARCH,
+    },
+    corpora::{
+        load_documents, CorpusDocument, CorpusField, CorpusFilter, CorpusRegistry, CorpusSchema,
+    },
     database::Database,
+    maintenance::{
+        self, run_task, MaintenanceConfig, MaintenanceStatus, MaintenanceTask, TaskReport, TaskRun,
+        MAINTENANCE_CHECK_INTERVAL,
+    },
     services::{
-        AnalysisService, BenchmarkOptions, BenchmarkService, CallersOptions, ImpactOptions,
-        IndexCodebaseOptions, IndexingService, OverviewOptions, SearchOptions, SearchService,
-        StatsOptions, StatsService, SymbolSearchOptions, ValidationOptions, ValidationService,
+        document_by_path, summary_by_path, AnalysisService, BenchmarkOptions, BenchmarkService,
+        CallersOptions, CallersResult, FusedSearchOptions, GraphOptions, ImpactOptions,
+        ImpactResult, IndexCodebaseOptions, IndexingService, OverviewOptions, RedactionRules,
+        RelatedOptions, RelationshipSortBy, RepositoryScope, RepositoryScopeCache, ResultGrouping,
+        SearchGrouping, SearchOptions, SearchService, StatsOptions, StatsService,
+        SymbolSearchOptions, TreemapOptions, TrendsOptions, ValidationOptions, ValidationService,
+        OVERVIEW_FORMATS,
     },
     supabase_repository::{
-        job_worker::SupabaseJobWorker, JobStatusRow, RepositoryRegistration, RepositoryRow,
-        SupabaseRepositoryStore,
+        account::{
+            document_prefix, export_manifest, local_document_prefix, AccountDataRequestRow,
+            DeletionConfirmation, ExportArchive, DELETION_CONFIRMATION_TTL_MINUTES,
+        },
+        job_worker::SupabaseJobWorker,
+        notifications::{
+            resolve_notification_url, NotificationHookRow, EVENT_INDEX_COMPLETED,
+            EVENT_INDEX_FAILED,
+        },
+        secrets::{secret_keyring, SECRETS_MASTER_KEY_ENV},
+        task::parse_github_owner_repo,
+        uploads::{
+            contiguous_size, is_valid_upload_name, list_parts, part_path, total_size, upload_dir,
+            SourceUploadRow, UploadPart, MAX_UPLOAD_BYTES, MAX_UPLOAD_PARTS, MAX_UPLOAD_PART_BYTES,
+        },
+        webhook_rules::{WebhookDecision, WebhookRules},
+        JobStatusRow, RepositoryRegistration, RepositoryRow, SupabaseRepositoryStore,
+    },
+};
+use crate::{
+    auth_middleware::AuthContext,
+    binary_relationship_engine_async::AsyncBinaryRelationshipEngine,
+    binary_symbols::SharedSymbolReader,
+    observability::with_trace_id,
+    query_deadline::QueryDeadline,
+    signed_urls::{
+        is_shareable_path, UrlSigner, DEFAULT_SIGNED_URL_TTL_SECS, MAX_SIGNED_URL_TTL_SECS,
+        URL_SIGNING_KEY_ENV,
     },
+    DocumentFilters, Index, SemanticSearchEngine, Storage, ValidatedDocumentId,
 };
 
 /// Application state for services-only HTTP server
@@ -76,6 +129,84 @@ pub struct ServicesAppState {
     pub jobs: Arc<RwLock<HashMap<String, JobStatus>>>,
     /// Simple repository registry persisted under db_path/repositories.json
     pub repositories: Arc<RwLock<Vec<RepositoryRecord>>>,
+    /// Generation counter bumped whenever this server changes indexed data; feeds ETags
+    pub data_generation: Arc<AtomicU64>,
+    /// Responses recorded per `Idempotency-Key` so client retries replay instead of re-running
+    pub idempotency: Arc<RwLock<HashMap<String, IdempotencyRecord>>>,
+    /// Token usage attributed to API keys and assistant sessions
+    pub token_usage: Arc<TokenLedger>,
+    /// Serializes read-modify-write cycles of db_path/annotations.json (annotations and notes)
+    pub annotation_writes: Arc<tokio::sync::Mutex<()>>,
+    /// Serializes corpus schema changes and document loads
+    pub corpus_writes: Arc<tokio::sync::Mutex<()>>,
+    /// Document path -> ID lookups shared across requests, filled as paths are resolved
+    pub path_cache: Arc<RwLock<HashMap<String, ValidatedDocumentId>>>,
+    /// Vector search over the database, opened on first use (see [`semantic_engine`])
+    pub semantic_engine: Arc<tokio::sync::OnceCell<Option<SharedSemanticEngine>>>,
+    /// Relationship engine with a result cache shared by callers and impact requests,
+    /// opened on first use (see [`relationship_engine`])
+    pub relationship_engine: Arc<tokio::sync::OnceCell<Option<AsyncBinaryRelationshipEngine>>>,
+    /// Symbol database reader whose name index is kept across symbol searches
+    pub symbol_reader: SharedSymbolReader,
+    /// Default codebase overview precomputed by maintenance (see [`refresh_overview`])
+    pub overview_cache: Arc<RwLock<Option<CachedOverview>>>,
+    /// Repositories resolved for scoped requests, kept until data changes
+    pub repository_scopes: Arc<RepositoryScopeCache>,
+}
+
+type SharedSemanticEngine = Arc<tokio::sync::Mutex<SemanticSearchEngine>>;
+
+/// Codebase overview computed with default options, served while its fingerprint matches
+#[derive(Debug, Clone)]
+pub struct CachedOverview {
+    fingerprint: String,
+    value: serde_json::Value,
+}
+
+/// The database's semantic search engine, if it has document vectors and embeddings
+/// are configured
+///
+/// Opened once per server; a database that cannot be opened is logged and searched
+/// without vectors.
+async fn semantic_engine(state: &ServicesAppState) -> Option<SharedSemanticEngine> {
+    state
+        .semantic_engine
+        .get_or_init(|| async {
+            match SemanticSearchEngine::for_database(&state.db_path).await {
+                Ok(engine) => engine.map(|engine| Arc::new(tokio::sync::Mutex::new(engine))),
+                Err(e) => {
+                    warn!("Vector search unavailable: {e:#}");
+                    None
+                }
+            }
+        })
+        .await
+        .clone()
+}
+
+/// The server's shared relationship engine, whose result cache outlives requests
+///
+/// Opened once per server and reloaded when re-indexing changes the graph; if it
+/// cannot be opened, each request builds its own engine as before.
+async fn relationship_engine(state: &ServicesAppState) -> Option<AsyncBinaryRelationshipEngine> {
+    state
+        .relationship_engine
+        .get_or_init(|| async {
+            match AsyncBinaryRelationshipEngine::new(
+                &state.db_path,
+                crate::relationship_query::RelationshipQueryConfig::default(),
+            )
+            .await
+            {
+                Ok(engine) => Some(engine),
+                Err(e) => {
+                    warn!("Relationship result cache unavailable: {e:#}");
+                    None
+                }
+            }
+        })
+        .await
+        .clone()
 }
 
 impl ServicesAppState {
@@ -109,1107 +240,4036 @@ impl ServicesAppState {
     fn repo_registry_path(&self) -> PathBuf {
         self.db_path.join("repositories.json")
     }
+
+    /// Record that indexed data changed, invalidating outstanding ETags
+    pub fn bump_data_generation(&self) {
+        self.data_generation.fetch_add(1, Ordering::SeqCst);
+    }
 }
 
-fn parse_local_path_ingestion_flag(raw: Option<String>) -> bool {
-    match raw {
-        Some(value) => {
-            let normalized = value.trim().to_ascii_lowercase();
-            !matches!(normalized.as_str(), "0" | "false" | "no" | "off")
+/// Response compression settings shared by the services servers
+///
+/// Read from the environment:
+/// - `KOTADB_COMPRESSION`: set to `off`/`false`/`0` to disable compression
+/// - `KOTADB_COMPRESSION_MIN_BYTES`: smallest body to compress (default 1024)
+/// - `KOTADB_COMPRESSION_CONTENT_TYPES`: comma-separated content-type prefixes to
+///   compress (default `application/json,text/`)
+#[derive(Debug, Clone)]
+pub struct CompressionConfig {
+    pub enabled: bool,
+    pub min_size: u16,
+    pub content_types: Vec<String>,
+}
+
+impl Default for CompressionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            min_size: 1024,
+            content_types: vec!["application/json".to_string(), "text/".to_string()],
         }
-        None => true,
     }
 }
 
-#[cfg(test)]
-mod allow_local_path_tests {
-    use super::parse_local_path_ingestion_flag;
-
-    #[test]
-    fn defaults_to_true_when_missing() {
-        assert!(parse_local_path_ingestion_flag(None));
+impl CompressionConfig {
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let enabled = env::var("KOTADB_COMPRESSION")
+            .map(|v| parse_bool_env_flag(&v))
+            .unwrap_or(defaults.enabled);
+        let min_size = match env::var("KOTADB_COMPRESSION_MIN_BYTES") {
+            Ok(raw) => raw.trim().parse().unwrap_or_else(|_| {
+                warn!(
+                    "Ignoring invalid KOTADB_COMPRESSION_MIN_BYTES '{}'; using {}",
+                    raw, defaults.min_size
+                );
+                defaults.min_size
+            }),
+            Err(_) => defaults.min_size,
+        };
+        let content_types = env::var("KOTADB_COMPRESSION_CONTENT_TYPES")
+            .map(|raw| {
+                raw.split(',')
+                    .map(|t| t.trim().to_ascii_lowercase())
+                    .filter(|t| !t.is_empty())
+                    .collect()
+            })
+            .unwrap_or(defaults.content_types);
+        Self {
+            enabled,
+            min_size,
+            content_types,
+        }
     }
 
-    #[test]
-    fn returns_true_for_truthy_strings() {
-        for value in ["1", "true", "yes", "on", " arbitrary "] {
-            assert!(parse_local_path_ingestion_flag(Some(value.to_string())));
+    /// Wrap `router` in gzip/deflate/zstd compression negotiated via `Accept-Encoding`
+    pub fn apply(&self, router: Router) -> Router {
+        if !self.enabled {
+            return router;
         }
+        let content_types = Arc::new(self.content_types.clone());
+        let content_type_allowed =
+            move |_: StatusCode,
+                  _: axum::http::Version,
+                  headers: &HeaderMap,
+                  _: &axum::http::Extensions| {
+                headers
+                    .get(axum::http::header::CONTENT_TYPE)
+                    .and_then(|v| v.to_str().ok())
+                    .map(|ct| {
+                        let ct = ct.to_ascii_lowercase();
+                        // Never buffer server-sent event streams
+                        !ct.starts_with("text/event-stream")
+                            && content_types.iter().any(|prefix| ct.starts_with(prefix))
+                    })
+                    .unwrap_or(false)
+            };
+        router.layer(
+            CompressionLayer::new()
+                .gzip(true)
+                .deflate(true)
+                .zstd(true)
+                .compress_when(SizeAbove::new(self.min_size).and(content_type_allowed)),
+        )
     }
+}
 
-    #[test]
-    fn returns_false_for_falsey_strings() {
-        for value in ["0", "false", "no", "off", " FALSE "] {
-            assert!(!parse_local_path_ingestion_flag(Some(value.to_string())));
+/// Initial data generation, seeded from the start time so ETags never repeat across restarts
+fn initial_data_generation() -> Arc<AtomicU64> {
+    let seed = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or(0);
+    Arc::new(AtomicU64::new(seed))
+}
+
+/// Compute the ETag for a read request at the current data generation
+///
+/// Besides the in-process generation, the size and mtime of the symbol database and
+/// dependency graph are mixed in so re-indexing by another process (e.g. the CLI) also
+/// invalidates cached responses.
+async fn data_etag(state: &ServicesAppState, uri: &axum::http::Uri, headers: &HeaderMap) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(state.data_generation.load(Ordering::SeqCst).to_le_bytes());
+    for artifact in ["symbols.kota", "dependency_graph.bin"] {
+        if let Ok(meta) = tokio::fs::metadata(state.db_path.join(artifact)).await {
+            hasher.update(meta.len().to_le_bytes());
+            if let Ok(modified) = meta.modified() {
+                let nanos = modified
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .map(|d| d.as_nanos())
+                    .unwrap_or(0);
+                hasher.update(nanos.to_le_bytes());
+            }
         }
     }
+    hasher.update(uri.to_string().as_bytes());
+    // Responses may differ per caller once results are scoped by API key, and per
+    // requested response size limit
+    for name in ["authorization", "x-api-key", MAX_RESPONSE_BYTES_HEADER] {
+        if let Some(value) = headers.get(name) {
+            hasher.update(value.as_bytes());
+        }
+    }
+    format!("W/\"{}\"", hex::encode(&hasher.finalize()[..16]))
 }
 
-#[cfg(test)]
-mod saas_helper_tests {
-    use super::normalize_git_ref;
+/// Whether an `If-None-Match` header matches `etag` (weak comparison, per RFC 9110)
+fn if_none_match_matches(headers: &HeaderMap, etag: &str) -> bool {
+    let strip_weak = |tag: &str| tag.trim().trim_start_matches("W/").to_string();
+    let current = strip_weak(etag);
+    headers
+        .get_all(axum::http::header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|candidate| candidate.trim() == "*" || strip_weak(candidate) == current)
+}
 
-    #[test]
-    fn normalizes_branch_refs() {
-        assert_eq!(
-            normalize_git_ref("refs/heads/main"),
-            Some("main".to_string())
-        );
+/// Middleware answering conditional GETs with 304 when the data generation is unchanged
+async fn conditional_get(
+    State(state): State<ServicesAppState>,
+    request: axum::extract::Request,
+    next: axum::middleware::Next,
+) -> axum::response::Response {
+    use axum::response::IntoResponse;
+
+    let etag = data_etag(&state, request.uri(), request.headers()).await;
+    if if_none_match_matches(request.headers(), &etag) {
+        return (StatusCode::NOT_MODIFIED, [(axum::http::header::ETAG, etag)]).into_response();
     }
 
-    #[test]
-    fn returns_none_for_non_branch_refs() {
-        assert_eq!(normalize_git_ref("refs/pull/123"), None);
+    let mut response = next.run(request).await;
+    if response.status().is_success() {
+        if let Ok(value) = axum::http::HeaderValue::from_str(&etag) {
+            response
+                .headers_mut()
+                .insert(axum::http::header::ETAG, value);
+        }
     }
+    response
 }
 
-/// Health check response
-#[derive(Debug, Serialize)]
-pub struct HealthResponse {
-    pub status: String,
-    pub version: String,
-    pub services_enabled: Vec<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub saas: Option<SaasHealth>,
+/// How long a recorded `Idempotency-Key` keeps replaying its first response
+const IDEMPOTENCY_WINDOW: Duration = Duration::from_secs(24 * 60 * 60);
+/// Largest request body the idempotency middleware will buffer for hashing
+const MAX_IDEMPOTENT_BODY_BYTES: usize = 1024 * 1024;
+const MAX_IDEMPOTENCY_KEY_LEN: usize = 255;
+/// Keys remembered before the oldest records are evicted
+const MAX_IDEMPOTENCY_RECORDS: usize = 10_000;
+
+/// Outcome of the first request seen for an idempotency key
+#[derive(Debug, Clone)]
+pub struct IdempotencyRecord {
+    request_hash: String,
+    recorded_at: Instant,
+    /// `None` while the original request is still being handled
+    response: Option<StoredResponse>,
 }
 
-#[derive(Debug, Serialize, Default)]
-pub struct JobQueueHealth {
-    pub queued: usize,
-    pub in_progress: usize,
-    pub failed: usize,
-    pub failed_recent: usize,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub oldest_queued_seconds: Option<u64>,
+#[derive(Debug, Clone)]
+struct StoredResponse {
+    status: StatusCode,
+    content_type: Option<axum::http::HeaderValue>,
+    body: Bytes,
 }
 
-#[derive(Debug, Serialize)]
-pub struct SaasHealth {
-    pub supabase_status: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub supabase_latency_ms: Option<u128>,
-    pub job_queue: JobQueueHealth,
+type IdempotencyRecords = Arc<RwLock<HashMap<String, IdempotencyRecord>>>;
+
+/// Record `key` as in flight, evicting the oldest records once the map is full
+fn reserve_idempotency_key(
+    records: &mut HashMap<String, IdempotencyRecord>,
+    key: String,
+    request_hash: String,
+) {
+    while records.len() >= MAX_IDEMPOTENCY_RECORDS {
+        let Some(oldest) = records
+            .iter()
+            .min_by_key(|(_, record)| record.recorded_at)
+            .map(|(key, _)| key.clone())
+        else {
+            break;
+        };
+        records.remove(&oldest);
+    }
+    records.insert(
+        key,
+        IdempotencyRecord {
+            request_hash,
+            recorded_at: Instant::now(),
+            response: None,
+        },
+    );
 }
 
-/// Error response
-#[derive(Debug, Serialize)]
-pub struct ErrorResponse {
-    pub error: String,
-    pub message: String,
+/// Releases an in-flight idempotency key when its request finishes without a
+/// recorded response, including when the client disconnects and the handler
+/// future is dropped mid-request.
+struct InFlightIdempotencyKey {
+    records: IdempotencyRecords,
+    key: String,
 }
 
-/// Minimal background job status for indexing
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct JobStatus {
-    pub id: String,
-    pub repo_path: String,
-    pub status: String, // queued | running | completed | failed
-    pub progress: Option<f32>,
-    pub started_at: Option<String>,
-    pub updated_at: Option<String>,
-    pub error: Option<String>,
+impl Drop for InFlightIdempotencyKey {
+    fn drop(&mut self) {
+        fn release(records: &mut HashMap<String, IdempotencyRecord>, key: &str) {
+            if records
+                .get(key)
+                .is_some_and(|record| record.response.is_none())
+            {
+                records.remove(key);
+            }
+        }
+
+        if let Ok(mut records) = self.records.try_write() {
+            release(&mut records, &self.key);
+        } else if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+            let records = self.records.clone();
+            let key = std::mem::take(&mut self.key);
+            runtime.spawn(async move { release(&mut *records.write().await, &key) });
+        }
+    }
 }
 
-/// Simple repository registry record
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct RepositoryRecord {
-    pub id: String,
-    pub name: String,
-    pub path: String,
-    pub url: Option<String>,
-    pub last_indexed: Option<String>,
+fn idempotency_error(
+    status: StatusCode,
+    error_type: &str,
+    message: &str,
+    suggestion: &str,
+) -> axum::response::Response {
+    use axum::response::IntoResponse;
+
+    (
+        status,
+        Json(StandardApiError {
+            error_type: error_type.to_string(),
+            message: message.to_string(),
+            details: None,
+            suggestions: vec![suggestion.to_string()],
+            error_code: Some(status.as_u16() as u32),
+        }),
+    )
+        .into_response()
 }
 
-/// Stats request parameters
-#[derive(Debug, Deserialize)]
-pub struct StatsQuery {
-    pub basic: Option<bool>,
-    pub symbols: Option<bool>,
-    pub relationships: Option<bool>,
+/// Middleware replaying the recorded response for a repeated `Idempotency-Key`.
+///
+/// Keys are scoped to the route and caller credentials; reusing a key with a different
+/// body is rejected. Server errors are not recorded so the client can retry them.
+async fn idempotent_post(
+    State(state): State<ServicesAppState>,
+    request: axum::extract::Request,
+    next: axum::middleware::Next,
+) -> axum::response::Response {
+    use axum::response::IntoResponse;
+
+    let Some(raw_key) = request.headers().get("idempotency-key") else {
+        return next.run(request).await;
+    };
+    let key = match raw_key.to_str().map(str::trim) {
+        Ok(key) if !key.is_empty() && key.len() <= MAX_IDEMPOTENCY_KEY_LEN => key.to_string(),
+        _ => {
+            return handle_validation_error(
+                "Idempotency-Key",
+                "must be 1-255 visible ASCII characters",
+                request.uri().path(),
+            )
+            .into_response()
+        }
+    };
+
+    let mut scope = Sha256::new();
+    scope.update(request.uri().path().as_bytes());
+    for name in [axum::http::header::AUTHORIZATION.as_str(), "x-api-key"] {
+        if let Some(value) = request.headers().get(name) {
+            scope.update(value.as_bytes());
+        }
+    }
+    scope.update(key.as_bytes());
+    let scoped_key = hex::encode(scope.finalize());
+
+    let (parts, body) = request.into_parts();
+    let body = match axum::body::to_bytes(body, MAX_IDEMPOTENT_BODY_BYTES).await {
+        Ok(body) => body,
+        Err(_) => {
+            return idempotency_error(
+                StatusCode::PAYLOAD_TOO_LARGE,
+                "payload_too_large",
+                "Request body too large for an idempotent request",
+                "Send requests under 1 MiB when using Idempotency-Key",
+            )
+        }
+    };
+    let request_hash = hex::encode(Sha256::digest(&body));
+
+    {
+        let mut records = state.idempotency.write().await;
+        records.retain(|_, record| record.recorded_at.elapsed() < IDEMPOTENCY_WINDOW);
+        match records.get(&scoped_key) {
+            Some(record) if record.request_hash != request_hash => {
+                return idempotency_error(
+                    StatusCode::UNPROCESSABLE_ENTITY,
+                    "idempotency_key_reused",
+                    "Idempotency-Key was already used with a different request body",
+                    "Generate a new Idempotency-Key for each distinct request",
+                );
+            }
+            Some(IdempotencyRecord {
+                response: Some(stored),
+                ..
+            }) => {
+                let mut response = (stored.status, stored.body.clone()).into_response();
+                if let Some(content_type) = &stored.content_type {
+                    response
+                        .headers_mut()
+                        .insert(axum::http::header::CONTENT_TYPE, content_type.clone());
+                }
+                response.headers_mut().insert(
+                    "idempotent-replayed",
+                    axum::http::HeaderValue::from_static("true"),
+                );
+                return response;
+            }
+            Some(_) => {
+                return idempotency_error(
+                    StatusCode::CONFLICT,
+                    "idempotency_request_in_progress",
+                    "A request with this Idempotency-Key is still being processed",
+                    "Retry after the original request completes",
+                );
+            }
+            None => {
+                reserve_idempotency_key(&mut records, scoped_key.clone(), request_hash.clone());
+            }
+        }
+    }
+    // Dropped on every exit below; it only clears the key if no response was recorded
+    let _in_flight = InFlightIdempotencyKey {
+        records: state.idempotency.clone(),
+        key: scoped_key.clone(),
+    };
+
+    let response = next
+        .run(axum::extract::Request::from_parts(
+            parts,
+            axum::body::Body::from(body),
+        ))
+        .await;
+    if response.status().is_server_error() {
+        return response;
+    }
+
+    let (parts, body) = response.into_parts();
+    let body = match axum::body::to_bytes(body, usize::MAX).await {
+        Ok(body) => body,
+        Err(e) => {
+            return internal_server_error(format!("Failed to buffer response: {}", e))
+                .into_response();
+        }
+    };
+    state.idempotency.write().await.insert(
+        scoped_key,
+        IdempotencyRecord {
+            request_hash,
+            recorded_at: Instant::now(),
+            response: Some(StoredResponse {
+                status: parts.status,
+                content_type: parts.headers.get(axum::http::header::CONTENT_TYPE).cloned(),
+                body: body.clone(),
+            }),
+        },
+    );
+    axum::response::Response::from_parts(parts, axum::body::Body::from(body))
 }
 
-/// Benchmark request
-#[derive(Debug, Deserialize)]
-pub struct BenchmarkRequest {
-    pub operations: Option<usize>,
-    pub benchmark_type: Option<String>,
-    pub format: Option<String>,
+/// Header clients send to attribute token usage to an assistant session
+const SESSION_HEADER: &str = "x-kotadb-session";
+const MAX_SESSION_ID_LEN: usize = 128;
+/// Largest response body the token accounting middleware will buffer
+const MAX_ACCOUNTED_BODY_BYTES: usize = 16 * 1024 * 1024;
+/// Consumers tracked before the least recently seen are evicted
+const MAX_TRACKED_CONSUMERS: usize = 10_000;
+/// How long per-file token counts are reused before being looked up again
+const FILE_TOKEN_INDEX_TTL: Duration = Duration::from_secs(60);
+/// Files whose token counts are cached before the cache is reset
+const MAX_CACHED_FILE_TOKENS: usize = 100_000;
+
+/// API key and/or session that token usage is attributed to
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+pub struct TokenConsumer {
+    pub api_key_id: Option<i64>,
+    pub session: Option<String>,
 }
 
-/// Validation request
-#[derive(Debug, Deserialize)]
-pub struct ValidationRequest {
-    pub check_integrity: Option<bool>,
-    pub check_consistency: Option<bool>,
-    pub repair: Option<bool>,
+/// Token counts aggregated over a set of requests
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TokenUsageTotals {
+    pub requests: u64,
+    /// Tokens the responses put into an assistant's context
+    pub returned_tokens: u64,
+    /// Tokens needed to paste the referenced files in full instead
+    pub raw_file_tokens: u64,
+    pub tokens_saved: u64,
 }
 
-/// Index codebase request
-#[derive(Debug, Deserialize)]
-pub struct IndexCodebaseRequest {
-    pub repo_path: String,
-    pub prefix: Option<String>,
-    pub include_files: Option<bool>,
-    pub include_commits: Option<bool>,
-    pub extract_symbols: Option<bool>,
+impl TokenUsageTotals {
+    fn add(&mut self, returned: u64, raw: u64) {
+        self.requests += 1;
+        self.returned_tokens += returned;
+        self.raw_file_tokens += raw;
+        self.tokens_saved += raw.saturating_sub(returned);
+    }
+
+    fn merge(&mut self, other: &TokenUsageTotals) {
+        self.requests += other.requests;
+        self.returned_tokens += other.returned_tokens;
+        self.raw_file_tokens += other.raw_file_tokens;
+        self.tokens_saved += other.tokens_saved;
+    }
 }
 
-/// v1 repository registration request
-#[derive(Debug, Deserialize)]
-pub struct RegisterRepositoryRequest {
-    pub path: Option<String>,
-    pub git_url: Option<String>,
-    pub branch: Option<String>,
-    // Optional indexing overrides
-    pub include_files: Option<bool>,
-    pub include_commits: Option<bool>,
-    pub max_file_size_mb: Option<usize>,
-    pub max_memory_mb: Option<u64>,
-    pub max_parallel_files: Option<usize>,
-    pub enable_chunking: Option<bool>,
-    pub extract_symbols: Option<bool>,
+/// Usage recorded for one consumer
+#[derive(Debug, Clone, Serialize)]
+pub struct ConsumerTokenUsage {
+    #[serde(flatten)]
+    pub consumer: TokenConsumer,
+    #[serde(flatten)]
+    pub totals: TokenUsageTotals,
+    /// Totals broken down by tool (`search_code`, `find_callers`, `mcp:<tool>`, ...)
+    pub tools: BTreeMap<String, TokenUsageTotals>,
+    pub first_seen: chrono::DateTime<Utc>,
+    pub last_seen: chrono::DateTime<Utc>,
 }
 
-/// v1 repository registration response
-#[derive(Debug, Serialize)]
-pub struct RegisterRepositoryResponse {
-    pub job_id: String,
-    pub repository_id: String,
-    pub status: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub webhook_secret: Option<String>,
+/// In-memory token accounting shared by the search, analysis and MCP routes
+pub struct TokenLedger {
+    started_at: chrono::DateTime<Utc>,
+    consumers: RwLock<HashMap<TokenConsumer, ConsumerTokenUsage>>,
+    file_tokens: RwLock<Option<FileTokenIndex>>,
 }
 
-/// v1 repository listing response
-#[derive(Debug, Serialize)]
-pub struct ListRepositoriesResponse {
-    pub repositories: Vec<RepositoryRecord>,
+/// Token counts of referenced files, keyed by normalized path (`None`: not stored)
+struct FileTokenIndex {
+    generation: u64,
+    built_at: Instant,
+    tokens: HashMap<String, Option<u64>>,
 }
 
-/// v1 index status response
-#[derive(Debug, Serialize)]
-pub struct IndexStatusResponse {
-    pub job: Option<JobStatus>,
-}
-
-#[derive(Debug, Serialize)]
-pub struct WebhookResponse {
-    pub status: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub job_id: Option<String>,
+impl Default for TokenLedger {
+    fn default() -> Self {
+        Self {
+            started_at: Utc::now(),
+            consumers: RwLock::new(HashMap::new()),
+            file_tokens: RwLock::new(None),
+        }
+    }
 }
 
-/// Codebase overview request
-#[derive(Debug, Deserialize)]
-pub struct CodebaseOverviewRequest {
-    pub format: Option<String>,
-    pub top_symbols_limit: Option<usize>,
-    pub entry_points_limit: Option<usize>,
-}
+impl TokenLedger {
+    async fn record(&self, consumer: TokenConsumer, tool: &str, returned: u64, raw: u64) {
+        let now = Utc::now();
+        let mut consumers = self.consumers.write().await;
+        if !consumers.contains_key(&consumer) && consumers.len() >= MAX_TRACKED_CONSUMERS {
+            if let Some(oldest) = consumers
+                .values()
+                .min_by_key(|usage| usage.last_seen)
+                .map(|usage| usage.consumer.clone())
+            {
+                consumers.remove(&oldest);
+            }
+        }
+        let usage = consumers
+            .entry(consumer.clone())
+            .or_insert_with(|| ConsumerTokenUsage {
+                consumer,
+                totals: TokenUsageTotals::default(),
+                tools: BTreeMap::new(),
+                first_seen: now,
+                last_seen: now,
+            });
+        usage.totals.add(returned, raw);
+        usage
+            .tools
+            .entry(tool.to_string())
+            .or_default()
+            .add(returned, raw);
+        usage.last_seen = now;
+    }
 
-// ================================================================================================
-// ENHANCED API STRUCTURES - Standards Compliant, Non-Breaking
-// ================================================================================================
+    /// Tokens of each referenced file, from the counts storage recorded at index time.
+    ///
+    /// Paths are resolved through the path cache and primary index; resolved counts are
+    /// cached until data changes or they age out.
+    async fn raw_file_tokens(&self, state: &ServicesAppState, paths: &BTreeSet<String>) -> u64 {
+        if paths.is_empty() {
+            return 0;
+        }
+        let generation = state.data_generation.load(Ordering::SeqCst);
+        let mut total = 0;
+        let mut missing = Vec::new();
+        {
+            let mut guard = self.file_tokens.write().await;
+            let fresh = guard.as_ref().is_some_and(|index| {
+                index.generation == generation
+                    && index.built_at.elapsed() <= FILE_TOKEN_INDEX_TTL
+                    && index.tokens.len() < MAX_CACHED_FILE_TOKENS
+            });
+            if !fresh {
+                *guard = None;
+            }
+            let index = guard.get_or_insert_with(|| FileTokenIndex {
+                generation,
+                built_at: Instant::now(),
+                tokens: HashMap::new(),
+            });
+            for path in paths {
+                // Analysis results report repository-relative paths; storage keys carry a prefix
+                let normalized = path.trim_start_matches("./").trim_start_matches('/');
+                match index.tokens.get(normalized) {
+                    Some(tokens) => total += tokens.unwrap_or(0),
+                    None => missing.push(normalized.to_string()),
+                }
+            }
+        }
+        if missing.is_empty() {
+            return total;
+        }
 
-/// Search request with format options and validation
-#[derive(Debug, Deserialize)]
-pub struct SearchRequest {
-    pub query: String,
-    pub limit: Option<usize>,
-    pub search_type: Option<String>,
-    pub format: Option<String>, // "simple", "rich", "cli" (default: rich)
+        let mut resolved = Vec::with_capacity(missing.len());
+        for path in missing {
+            let tokens = match file_tokens(state, &path).await {
+                Ok(tokens) => tokens,
+                Err(e) => {
+                    warn!("Token accounting could not resolve {}: {}", path, e);
+                    None
+                }
+            };
+            total += tokens.unwrap_or(0);
+            resolved.push((path, tokens));
+        }
+        if let Some(index) = self.file_tokens.write().await.as_mut() {
+            if index.generation == generation {
+                index.tokens.extend(resolved);
+            }
+        }
+        total
+    }
 }
 
-/// Symbol search request with format options
-#[derive(Debug, Deserialize)]
-pub struct SymbolSearchRequest {
-    pub pattern: String,
-    pub limit: Option<usize>,
-    pub symbol_type: Option<String>,
-    pub format: Option<String>, // "simple", "rich", "cli" (default: rich)
+/// Stored token count of the document at `path`, counting the content when storage has none
+async fn file_tokens(state: &ServicesAppState, path: &str) -> Result<Option<u64>> {
+    let Some((id, summary)) = summary_by_path(
+        &state.storage,
+        &state.primary_index,
+        &state.path_cache,
+        path,
+    )
+    .await?
+    else {
+        return Ok(None);
+    };
+    if let Some(tokens) = summary.tokens {
+        return Ok(Some(tokens));
+    }
+    Ok(state
+        .storage
+        .lock_timed(LockedResource::Storage)
+        .await
+        .get(&id)
+        .await?
+        .map(|doc| crate::contracts::content_tokens(&doc.content)))
 }
 
-/// Callers request with better field names and validation
-#[derive(Debug, Deserialize)]
-pub struct CallersRequest {
-    pub symbol: String, // Intuitive field name
-    pub limit: Option<usize>,
-    pub format: Option<String>, // "simple", "rich", "cli" (default: rich)
-    pub include_indirect: Option<bool>,
+/// Tool name a token-accounted request is reported under
+fn token_accounting_tool(path: &str) -> Option<String> {
+    if let Some(tool) = path.strip_prefix("/mcp/tools/") {
+        return (!tool.is_empty() && !tool.contains('/')).then(|| format!("mcp:{}", tool));
+    }
+    let tool = match path {
+        "/api/v1/search" => "search",
+        "/api/v1/search/code" => "search_code",
+        "/api/v1/search/symbols" => "search_symbols",
+        "/api/v1/find-callers" => "find_callers",
+        "/api/v1/analyze-impact" => "analyze_impact",
+        p if p.starts_with("/api/v1/symbols/") && p.ends_with("/callers") => "find_callers",
+        p if p.starts_with("/api/v1/symbols/") && p.ends_with("/impact") => "analyze_impact",
+        p if p.starts_with("/api/v1/symbols/") && p.ends_with("/related") => "related_symbols",
+        _ => return None,
+    };
+    Some(tool.to_string())
 }
 
-/// Impact analysis request with better field names
-#[derive(Debug, Deserialize)]
-pub struct ImpactAnalysisRequest {
-    pub symbol: String, // Intuitive field name
-    pub limit: Option<usize>,
-    pub format: Option<String>, // "simple", "rich", "cli" (default: rich)
-    pub max_depth: Option<u32>,
+/// File paths referenced anywhere in a JSON response
+fn collect_referenced_paths(value: &JsonValue, paths: &mut BTreeSet<String>) {
+    match value {
+        JsonValue::Object(map) => {
+            for (key, value) in map {
+                match (key.as_str(), value) {
+                    ("path" | "file_path", JsonValue::String(path)) if !path.is_empty() => {
+                        paths.insert(path.clone());
+                    }
+                    // `format=simple` search responses list bare paths
+                    ("results", JsonValue::Array(items))
+                        if items.iter().all(JsonValue::is_string) =>
+                    {
+                        paths.extend(items.iter().filter_map(|v| v.as_str()).map(String::from));
+                    }
+                    _ => collect_referenced_paths(value, paths),
+                }
+            }
+        }
+        JsonValue::Array(items) => {
+            for item in items {
+                collect_referenced_paths(item, paths);
+            }
+        }
+        _ => {}
+    }
 }
 
-/// Simple response format for search operations - CLI-like
-#[derive(Debug, Serialize)]
-pub struct SimpleSearchResponse {
-    pub results: Vec<String>, // Just file paths
-    pub total_count: usize,
-    pub query_time_ms: u64,
+/// Tokens a response contributes to an assistant's context.
+///
+/// LLM-optimized search responses carry their own `TokenUsage` estimate; other responses
+/// are estimated from the serialized body.
+fn returned_tokens(value: &JsonValue, body: &str) -> u64 {
+    value
+        .pointer("/llm_response/optimization/token_usage/estimated_tokens")
+        .or_else(|| value.pointer("/optimization/token_usage/estimated_tokens"))
+        .and_then(|tokens| tokens.as_u64())
+        .unwrap_or_else(|| crate::llm_search::estimate_tokens(body) as u64)
 }
 
-/// Simple response format for symbol search - CLI-like  
-#[derive(Debug, Serialize)]
-pub struct SimpleSymbolResponse {
-    pub symbols: Vec<String>, // Just symbol names
-    pub total_count: usize,
+/// Buffer a response body for accounting, up to [`MAX_ACCOUNTED_BODY_BYTES`].
+///
+/// Larger bodies, and bodies that fail mid-stream, come back as a body replaying what was
+/// read followed by the rest of the original stream.
+async fn buffer_accounted_body(body: axum::body::Body) -> Result<Bytes, axum::body::Body> {
+    use futures::StreamExt;
+
+    let mut stream = body.into_data_stream();
+    let mut chunks: Vec<Result<Bytes, axum::Error>> = Vec::new();
+    let mut buffered = 0usize;
+    while let Some(chunk) = stream.next().await {
+        let overflow = match &chunk {
+            Ok(bytes) => {
+                buffered += bytes.len();
+                buffered > MAX_ACCOUNTED_BODY_BYTES
+            }
+            Err(_) => true,
+        };
+        chunks.push(chunk);
+        if overflow {
+            let replay = futures::stream::iter(chunks).chain(stream);
+            return Err(axum::body::Body::from_stream(replay));
+        }
+    }
+
+    let mut body = Vec::with_capacity(buffered);
+    for chunk in chunks.into_iter().flatten() {
+        body.extend_from_slice(&chunk);
+    }
+    Ok(Bytes::from(body))
 }
 
-/// Simple response format for analysis operations - CLI-like
-#[derive(Debug, Serialize)]
-pub struct SimpleAnalysisResponse {
-    pub results: Vec<String>, // Just relevant items
-    pub total_count: usize,
+/// Middleware attributing the token cost of successful responses to the calling API key or
+/// `X-KotaDB-Session`, alongside the cost of dumping the referenced files in full
+async fn token_accounting(
+    State(state): State<ServicesAppState>,
+    request: axum::extract::Request,
+    next: axum::middleware::Next,
+) -> axum::response::Response {
+    let Some(tool) = token_accounting_tool(request.uri().path()) else {
+        return next.run(request).await;
+    };
+    let consumer = TokenConsumer {
+        api_key_id: request
+            .extensions()
+            .get::<AuthContext>()
+            .map(|auth| auth.key_id),
+        session: request
+            .headers()
+            .get(SESSION_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .map(str::trim)
+            .filter(|session| !session.is_empty() && session.len() <= MAX_SESSION_ID_LEN)
+            .map(str::to_string),
+    };
+
+    let response = next.run(request).await;
+    let is_json = response
+        .headers()
+        .get(axum::http::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(|ct| ct.starts_with("application/json"))
+        .unwrap_or(false);
+    if !response.status().is_success() || !is_json {
+        return response;
+    }
+
+    let (parts, body) = response.into_parts();
+    let body = match buffer_accounted_body(body).await {
+        Ok(body) => body,
+        // Too large (or failing) to account: pass the body through untouched
+        Err(body) => return axum::response::Response::from_parts(parts, body),
+    };
+    if let Ok(value) = serde_json::from_slice::<JsonValue>(&body) {
+        let mut paths = BTreeSet::new();
+        collect_referenced_paths(&value, &mut paths);
+        let returned = returned_tokens(&value, &String::from_utf8_lossy(&body));
+        let raw = state.token_usage.raw_file_tokens(&state, &paths).await;
+        state
+            .token_usage
+            .record(consumer, &tool, returned, raw)
+            .await;
+    }
+    axum::response::Response::from_parts(parts, axum::body::Body::from(body))
 }
 
-/// CLI-format response that exactly matches command-line output
-#[derive(Debug, Serialize)]
-pub struct CliFormatResponse {
-    pub output: String, // Exact CLI output format
+/// Interpret a boolean environment variable: anything but `0`/`false`/`no`/`off` enables it
+fn parse_bool_env_flag(value: &str) -> bool {
+    let normalized = value.trim().to_ascii_lowercase();
+    !matches!(normalized.as_str(), "0" | "false" | "no" | "off")
 }
 
-/// Standardized API error with comprehensive information
-#[derive(Debug, Serialize)]
-pub struct StandardApiError {
-    pub error_type: String,
-    pub message: String,
-    pub details: Option<String>,
-    pub suggestions: Vec<String>,
-    pub error_code: Option<u32>,
+fn parse_local_path_ingestion_flag(raw: Option<String>) -> bool {
+    match raw {
+        Some(value) => parse_bool_env_flag(&value),
+        None => true,
+    }
 }
 
-// ================================================================================================
-// SHARED ERROR HANDLING - DRY Principle Compliance
-// ================================================================================================
+#[cfg(test)]
+mod allow_local_path_tests {
+    use super::{parse_bool_env_flag, parse_local_path_ingestion_flag};
 
-/// Standard result type for API operations
-type ApiResult<T> = Result<Json<T>, (StatusCode, Json<StandardApiError>)>;
+    #[test]
+    fn defaults_to_true_when_missing() {
+        assert!(parse_local_path_ingestion_flag(None));
+    }
 
-/// Standardized error handling for JSON parsing failures
-fn handle_json_parsing_error(
-    error: axum::extract::rejection::JsonRejection,
-    endpoint: &str,
-) -> (StatusCode, Json<StandardApiError>) {
-    let (error_type, message, suggestions) = match error {
-        axum::extract::rejection::JsonRejection::MissingJsonContentType(_) => (
-            "missing_content_type",
-            "Request must include 'Content-Type: application/json' header",
-            vec![
-                format!("Add header: 'Content-Type: application/json'"),
-                format!("Example: curl -H 'Content-Type: application/json' -d '{{\"symbol\":\"test\"}}' /api/{}", endpoint)
-            ]
-        ),
-        axum::extract::rejection::JsonRejection::JsonDataError(_) => (
-            "invalid_json_data", 
-            "JSON data is invalid or malformed",
-            vec![
-                "Validate JSON syntax with a JSON validator".to_string(),
-                "Ensure all required fields are provided".to_string(),
-                "Check for trailing commas or other syntax errors".to_string()
-            ]
-        ),
-        axum::extract::rejection::JsonRejection::JsonSyntaxError(_) => (
-            "json_syntax_error",
-            "JSON contains syntax errors",
-            vec![
-                "Validate JSON with an online JSON validator".to_string(),
-                "Common issues: missing quotes, trailing commas, unescaped characters".to_string()
-            ]
-        ),
-        _ => (
-            "request_parsing_error",
-            "Failed to parse request body",
-            vec![
-                "Ensure valid JSON format".to_string(),
-                "Include proper Content-Type header".to_string()
-            ]
-        )
-    };
+    #[test]
+    fn returns_true_for_truthy_strings() {
+        for value in ["1", "true", "yes", "on", " arbitrary "] {
+            assert!(parse_local_path_ingestion_flag(Some(value.to_string())));
+        }
+    }
 
-    (
-        StatusCode::BAD_REQUEST,
-        Json(StandardApiError {
-            error_type: error_type.to_string(),
-            message: message.to_string(),
-            details: Some(format!("Endpoint: {}", endpoint)),
-            suggestions,
-            error_code: Some(400),
-        }),
-    )
-}
+    #[test]
+    fn returns_false_for_falsey_strings() {
+        for value in ["0", "false", "no", "off", " FALSE "] {
+            assert!(!parse_local_path_ingestion_flag(Some(value.to_string())));
+        }
+    }
 
-/// Standardized error handling for service operation failures
-fn handle_service_error(
-    error: anyhow::Error,
-    operation: &str,
-) -> (StatusCode, Json<StandardApiError>) {
-    (
-        StatusCode::INTERNAL_SERVER_ERROR,
-        Json(StandardApiError {
-            error_type: format!("{}_failed", operation),
-            message: error.to_string(),
-            details: Some(format!("Operation: {}", operation)),
-            suggestions: vec![
-                "Check system resources and database connectivity".to_string(),
-                "Verify input parameters are valid".to_string(),
-                "Contact system administrator if problem persists".to_string(),
-            ],
-            error_code: Some(500),
-        }),
-    )
+    #[test]
+    fn generic_flags_share_the_same_spelling() {
+        assert!(parse_bool_env_flag("on"));
+        assert!(parse_bool_env_flag(" 1 "));
+        assert!(!parse_bool_env_flag("Off"));
+        assert!(!parse_bool_env_flag("no"));
+    }
 }
 
-/// Standardized validation error handling with helpful messages
-fn handle_validation_error(
-    field_name: &str,
-    message: &str,
-    endpoint: &str,
-) -> (StatusCode, Json<StandardApiError>) {
-    (
-        StatusCode::BAD_REQUEST,
-        Json(StandardApiError {
-            error_type: "validation_error".to_string(),
-            message: format!("Validation failed for '{}': {}", field_name, message),
-            details: Some(format!("Endpoint: {}", endpoint)),
-            suggestions: vec![
-                format!("Ensure '{}' meets validation requirements", field_name),
-                "Check API documentation for valid field formats".to_string(),
-                "Use ValidatedPath for file paths and ValidatedTitle for titles".to_string(),
-            ],
-            error_code: Some(400),
-        }),
-    )
-}
+#[cfg(test)]
+mod idempotency_tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn cancelled_requests_release_their_in_flight_key() {
+        let records: IdempotencyRecords = Arc::new(RwLock::new(HashMap::new()));
+
+        let handler = {
+            let records = records.clone();
+            async move {
+                reserve_idempotency_key(
+                    &mut *records.write().await,
+                    "tenant:key".to_string(),
+                    "hash".to_string(),
+                );
+                let _in_flight = InFlightIdempotencyKey {
+                    records: records.clone(),
+                    key: "tenant:key".to_string(),
+                };
+                std::future::pending::<()>().await;
+            }
+        };
+        assert!(tokio::time::timeout(Duration::from_millis(10), handler)
+            .await
+            .is_err());
 
-fn internal_server_error(message: impl Into<String>) -> (StatusCode, Json<StandardApiError>) {
-    (
-        StatusCode::INTERNAL_SERVER_ERROR,
-        Json(StandardApiError {
-            error_type: "internal_server_error".into(),
-            message: message.into(),
-            details: None,
-            suggestions: vec![
-                "Try again later".into(),
-                "Contact support if the problem persists".into(),
-            ],
-            error_code: Some(500),
-        }),
-    )
-}
+        assert!(!records.read().await.contains_key("tenant:key"));
+    }
 
-fn unauthorized_error(message: impl Into<String>) -> (StatusCode, Json<StandardApiError>) {
-    (
-        StatusCode::UNAUTHORIZED,
-        Json(StandardApiError {
-            error_type: "unauthorized".into(),
-            message: message.into(),
-            details: None,
-            suggestions: vec!["Provide a valid API key".into()],
-            error_code: Some(401),
-        }),
-    )
-}
+    #[tokio::test]
+    async fn completed_records_survive_the_guard() {
+        let records: IdempotencyRecords = Arc::new(RwLock::new(HashMap::new()));
+        {
+            let _in_flight = InFlightIdempotencyKey {
+                records: records.clone(),
+                key: "tenant:key".to_string(),
+            };
+            records.write().await.insert(
+                "tenant:key".to_string(),
+                IdempotencyRecord {
+                    request_hash: "hash".to_string(),
+                    recorded_at: Instant::now(),
+                    response: Some(StoredResponse {
+                        status: StatusCode::CREATED,
+                        content_type: None,
+                        body: Bytes::from_static(b"{}"),
+                    }),
+                },
+            );
+        }
 
-/// Standardized not-found error handling with helpful messages
-fn handle_not_found_error(
-    field_name: &str,
-    message: &str,
-    endpoint: &str,
-) -> (StatusCode, Json<StandardApiError>) {
-    (
-        StatusCode::NOT_FOUND,
-        Json(StandardApiError {
-            error_type: "not_found".to_string(),
-            message: format!("{}: {}", field_name, message),
-            details: Some(format!("Endpoint: {}", endpoint)),
-            suggestions: vec![
-                "Verify the identifier and try again".to_string(),
-                "List available resources to find valid IDs".to_string(),
-            ],
-            error_code: Some(404),
-        }),
-    )
+        assert!(records.read().await.contains_key("tenant:key"));
+    }
+
+    #[test]
+    fn reserving_at_capacity_evicts_the_oldest_record() {
+        let oldest = Instant::now() - Duration::from_secs(60);
+        let mut records = HashMap::new();
+        for i in 0..MAX_IDEMPOTENCY_RECORDS {
+            records.insert(
+                format!("key-{i}"),
+                IdempotencyRecord {
+                    request_hash: String::new(),
+                    recorded_at: oldest + Duration::from_millis(i as u64),
+                    response: None,
+                },
+            );
+        }
+        reserve_idempotency_key(&mut records, "newest".to_string(), String::new());
+
+        assert_eq!(records.len(), MAX_IDEMPOTENCY_RECORDS);
+        assert!(records.contains_key("newest"));
+        assert!(!records.contains_key("key-0"));
+    }
 }
 
-/// Create clean services-only HTTP server
-pub fn create_services_server(
-    storage: Arc<tokio::sync::Mutex<dyn Storage>>,
-    primary_index: Arc<tokio::sync::Mutex<dyn Index>>,
-    trigram_index: Arc<tokio::sync::Mutex<dyn Index>>,
-    db_path: PathBuf,
-) -> Router {
-    let state = ServicesAppState {
-        storage: storage.clone(),
-        primary_index: primary_index.clone(),
-        trigram_index: trigram_index.clone(),
-        db_path: db_path.clone(),
-        api_key_service: None, // No authentication for basic services server
-        supabase_pool: None,
-        webhook_base_url: None,
-        saas_mode: false,
-        jobs: Arc::new(RwLock::new(HashMap::new())),
-        repositories: Arc::new(RwLock::new(load_repositories_from_disk(db_path.as_path()))),
-    };
+#[cfg(test)]
+mod repository_scope_tests {
+    use super::*;
+    use crate::auth_middleware::AuthContext;
+    use axum::body::Body;
+    use axum::http::Request;
+    use tempfile::TempDir;
+    use tower::ServiceExt;
+
+    async fn scoped_state(dir: &TempDir) -> Result<ServicesAppState> {
+        let database = Database::new(dir.path(), true).await?;
+        for path in [
+            "repos/widgets/files/src/lib.rs",
+            "repos/gadgets/files/src/lib.rs",
+        ] {
+            let document = crate::DocumentBuilder::new()
+                .path(path)?
+                .title("lib.rs")?
+                .content(b"pub fn shared() {}")
+                .build()?;
+            database
+                .primary_index
+                .lock_timed(LockedResource::PrimaryIndex)
+                .await
+                .insert(document.id, document.path.clone())
+                .await?;
+            database
+                .storage
+                .lock_timed(LockedResource::Storage)
+                .await
+                .insert(document)
+                .await?;
+        }
+        let state = ServicesAppState {
+            storage: database.storage.clone(),
+            primary_index: database.primary_index.clone(),
+            trigram_index: database.trigram_index.clone(),
+            db_path: dir.path().to_path_buf(),
+            api_key_service: None,
+            supabase_pool: None,
+            webhook_base_url: None,
+            saas_mode: false,
+            jobs: Arc::new(RwLock::new(HashMap::new())),
+            repositories: Arc::new(RwLock::new(vec![RepositoryRecord {
+                id: "widgets-id".to_string(),
+                name: "Widgets".to_string(),
+                path: "/src/widgets".to_string(),
+                url: None,
+                last_indexed: None,
+            }])),
+            data_generation: initial_data_generation(),
+            idempotency: Arc::new(RwLock::new(HashMap::new())),
+            token_usage: Arc::new(TokenLedger::default()),
+            annotation_writes: Arc::new(tokio::sync::Mutex::new(())),
+            corpus_writes: Arc::new(tokio::sync::Mutex::new(())),
+            path_cache: database.path_cache.clone(),
+            semantic_engine: Arc::new(tokio::sync::OnceCell::new()),
+            relationship_engine: Arc::new(tokio::sync::OnceCell::new()),
+            symbol_reader: SharedSymbolReader::new(dir.path().join("symbols.kota")),
+            overview_cache: Arc::new(RwLock::new(None)),
+            repository_scopes: Arc::new(RepositoryScopeCache::default()),
+        };
+        Ok(state)
+    }
 
-    let base_router = Router::new()
-        // Health endpoint
-        .route("/health", get(health_check))
-        // Versioned v1 endpoints (canonical)
-        .route("/api/v1/analysis/stats", get(get_stats))
-        .route(
-            "/api/v1/search/code",
-            post(search_code_v1_post).get(search_code_enhanced),
-        )
-        .route(
-            "/api/v1/search/symbols",
-            post(search_symbols_v1_post).get(search_symbols_enhanced),
+    async fn scoped_router(dir: &TempDir) -> Result<Router> {
+        Ok(Router::new()
+            .route("/api/v1/search/code", get(search_code_enhanced))
+            .route("/api/v1/analysis/stats", get(get_stats))
+            .route("/api/v1/analysis/trends", get(trends_v1))
+            .route("/api/v1/codebase-overview", get(codebase_overview))
+            .with_state(scoped_state(dir).await?))
+    }
+
+    async fn get_json(router: &Router, uri: &str) -> Result<(StatusCode, JsonValue)> {
+        let response = router
+            .clone()
+            .oneshot(Request::builder().uri(uri).body(Body::empty())?)
+            .await?;
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await?;
+        Ok((status, serde_json::from_slice(&bytes)?))
+    }
+
+    #[tokio::test]
+    async fn searches_can_be_scoped_to_one_repository() -> Result<()> {
+        let dir = TempDir::new()?;
+        let router = scoped_router(&dir).await?;
+
+        let (status, body) = get_json(&router, "/api/v1/search/code?query=*&format=simple").await?;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["results"].as_array().map(Vec::len), Some(2), "{body}");
+
+        let (status, body) = get_json(
+            &router,
+            "/api/v1/search/code?query=*&format=simple&repository_id=widgets-id",
         )
-        .route("/api/v1/symbols/:symbol/callers", get(find_callers_v1_get))
-        .route("/api/v1/symbols/:symbol/impact", get(analyze_impact_v1_get))
-        .route("/api/v1/symbols", get(list_symbols_v1))
-        .route("/api/v1/files/symbols/*path", get(file_symbols_v1))
-        .route("/api/v1/repositories", post(register_repository_v1))
-        .route("/api/v1/repositories", get(list_repositories_v1))
-        .route("/api/v1/index/status", get(index_status_v1))
-        // Normalized v1 routes for remaining services
-        .route("/api/v1/benchmark", post(run_benchmark))
-        .route("/api/v1/validate", post(validate_database))
-        .route("/api/v1/health-check", get(health_check_detailed))
-        .route("/api/v1/index-codebase", post(index_codebase))
-        .route("/api/v1/find-callers", post(find_callers_enhanced))
-        .route("/api/v1/analyze-impact", post(analyze_impact_enhanced))
-        .route("/api/v1/codebase-overview", get(codebase_overview))
-        .with_state(state)
-        .layer(
-            ServiceBuilder::new()
-                .layer(TraceLayer::new_for_http())
-                .layer(CorsLayer::permissive()),
+        .await?;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            body["results"],
+            json!(["repos/widgets/files/src/lib.rs"]),
+            "{body}"
         );
 
-    // Optionally mount MCP bridge without auth for local development
-    // Available by default when compiled with mcp-server feature
-    #[cfg(feature = "mcp-server")]
-    let base_router = {
-        let mut registry = MCPToolRegistry::new();
-        // Register lightweight text search
-        {
-            let text_tools = Arc::new(crate::mcp::tools::text_search_tools::TextSearchTools::new(
-                trigram_index.clone(),
-                storage.clone(),
-            ));
-            registry = registry.with_text_tools(text_tools);
-        }
-        #[cfg(feature = "tree-sitter-parsing")]
-        {
-            let symbol_tools = Arc::new(SymbolTools::new(
-                storage.clone(),
-                primary_index.clone(),
-                trigram_index.clone(),
-                db_path.clone(),
-            ));
-            registry = registry.with_symbol_tools(symbol_tools);
+        let (status, _) = get_json(
+            &router,
+            "/api/v1/search/code?query=*&repository_id=unknown-id",
+        )
+        .await?;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn stats_overview_and_trends_can_be_scoped_to_one_repository() -> Result<()> {
+        let dir = TempDir::new()?;
+        let router = scoped_router(&dir).await?;
+        for (repository, files) in [("widgets", 1), ("gadgets", 7)] {
+            crate::trends::TrendHistory::append(
+                dir.path(),
+                &crate::trends::MetricsSnapshot {
+                    repository: format!("/src/{repository}"),
+                    document_prefix: Some(format!("repos/{repository}/")),
+                    files,
+                    ..Default::default()
+                },
+            )?;
         }
-        let mcp_state = McpHttpBridgeState::new(Some(Arc::new(registry)));
-        let mcp_router = create_mcp_bridge_router().with_state(mcp_state);
-        base_router.merge(mcp_router)
-    };
 
-    base_router
-}
+        let (status, body) = get_json(&router, "/api/v1/analysis/stats?basic=true").await?;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["basic_stats"]["document_count"], 2, "{body}");
+        let (status, body) = get_json(
+            &router,
+            "/api/v1/analysis/stats?basic=true&repository_id=widgets-id",
+        )
+        .await?;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["basic_stats"]["document_count"], 1, "{body}");
 
-/// Start the services-only HTTP server
-pub async fn start_services_server(
-    storage: Arc<tokio::sync::Mutex<dyn Storage>>,
-    primary_index: Arc<tokio::sync::Mutex<dyn Index>>,
-    trigram_index: Arc<tokio::sync::Mutex<dyn Index>>,
-    db_path: PathBuf,
-    port: u16,
-) -> Result<()> {
-    let app = create_services_server(storage, primary_index, trigram_index, db_path);
+        let (status, body) = get_json(
+            &router,
+            "/api/v1/codebase-overview?repository_id=widgets-id",
+        )
+        .await?;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["overview_data"]["total_files"], 1, "{body}");
 
-    // Try to bind to the port with enhanced error handling
-    let listener = match TcpListener::bind(&format!("0.0.0.0:{port}")).await {
-        Ok(listener) => listener,
-        Err(e) => {
-            error!("Failed to start server on port {}: {}", port, e);
+        let (status, body) = get_json(&router, "/api/v1/analysis/trends").await?;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            body["snapshots"].as_array().map(Vec::len),
+            Some(2),
+            "{body}"
+        );
+        let (status, body) =
+            get_json(&router, "/api/v1/analysis/trends?repository_id=widgets-id").await?;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            body["snapshots"].as_array().map(Vec::len),
+            Some(1),
+            "{body}"
+        );
+        assert_eq!(body["snapshots"][0]["files"], 1, "{body}");
 
-            if e.kind() == std::io::ErrorKind::AddrInUse {
-                error!("Port {} is already in use. Try these alternatives:", port);
-                error!("   - Use a different port: --port {}", port + 1);
+        let (status, _) =
+            get_json(&router, "/api/v1/analysis/stats?repository_id=unknown-id").await?;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        Ok(())
+    }
 
-                // Cross-platform command suggestions
-                if cfg!(unix) {
-                    error!("   - Check port usage: lsof -ti:{}", port);
-                    error!("   - Kill process using port: kill $(lsof -ti:{})", port);
-                } else {
-                    error!("   - Check port usage: netstat -ano | findstr :{}", port);
-                    error!("   - Kill process using port: taskkill /PID <PID> /F");
-                }
-            }
+    #[tokio::test]
+    async fn redaction_rules_hide_paths_from_searches_and_content() -> Result<()> {
+        let dir = TempDir::new()?;
+        let auth = AuthContext {
+            key_id: 7,
+            user_email: "contractor@example.com".to_string(),
+            user_id: None,
+            rate_limit: 60,
+            remaining_quota: 1_000,
+            redaction: RedactionRules::new(["repos/gadgets/"])?,
+        };
+        let router = Router::new()
+            .route("/api/v1/search/code", get(search_code_enhanced))
+            .route("/api/v1/files/content/*path", get(file_content_v1))
+            .route("/api/v1/files/tree", get(file_tree_v1))
+            .route("/api/v1/analysis/stats", get(get_stats))
+            .layer(Extension(auth))
+            .with_state(scoped_state(&dir).await?);
+
+        let (status, body) = get_json(&router, "/api/v1/search/code?query=*&format=simple").await?;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            body["results"],
+            json!(["repos/widgets/files/src/lib.rs"]),
+            "{body}"
+        );
 
-            return Err(e).context(format!(
-                "Failed to bind to port {}. Port may be in use or insufficient permissions",
-                port
-            ));
-        }
-    };
+        let (status, _) = get_json(
+            &router,
+            "/api/v1/files/content/repos/widgets/files/src/lib.rs",
+        )
+        .await?;
+        assert_eq!(status, StatusCode::OK);
+        let (status, _) = get_json(
+            &router,
+            "/api/v1/files/content/repos/gadgets/files/src/lib.rs",
+        )
+        .await?;
+        assert_eq!(status, StatusCode::NOT_FOUND);
 
-    info!("KotaDB Services HTTP Server listening on port {}", port);
-    debug!("Clean services-only architecture - no legacy endpoints");
-    debug!("Available endpoints:");
-    debug!("   GET    /health                          - Server health check");
-    debug!("   GET    /api/v1/analysis/stats           - Database statistics (StatsService)");
-    debug!(
-        "   POST   /api/v1/benchmark                - Performance benchmarks (BenchmarkService)"
-    );
-    debug!("   POST   /api/v1/validate                 - Database validation (ValidationService)");
-    debug!(
-        "   GET    /api/v1/health-check             - Detailed health check (ValidationService)"
-    );
-    debug!("   POST   /api/v1/index-codebase           - Index repository (IndexingService)");
-    debug!("   GET    /api/v1/search/code              - Search code content (SearchService)");
-    debug!("   GET    /api/v1/search/symbols           - Search symbols (SearchService)");
-    debug!("   POST   /api/v1/find-callers             - Find callers (AnalysisService)");
-    debug!("   POST   /api/v1/analyze-impact           - Impact analysis (AnalysisService)");
-    debug!("   GET    /api/v1/codebase-overview        - Codebase overview (AnalysisService)");
-    debug!("Server ready at http://localhost:{}", port);
-    debug!("Health check: curl http://localhost:{}/health", port);
+        let (status, body) = get_json(&router, "/api/v1/files/tree?path=repos&depth=1").await?;
+        assert_eq!(status, StatusCode::OK);
+        assert!(!body.to_string().contains("gadgets"), "{body}");
 
-    axum::serve(
-        listener,
-        app.into_make_service_with_connect_info::<SocketAddr>(),
-    )
-    .await?;
-    Ok(())
+        let (status, body) = get_json(&router, "/api/v1/analysis/stats").await?;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert_eq!(body["error"], "redaction_unsupported", "{body}");
+        Ok(())
+    }
 }
 
-/// Create services server with SaaS capabilities (API key authentication)
-pub async fn create_services_saas_server(
-    storage: Arc<tokio::sync::Mutex<dyn Storage>>,
-    primary_index: Arc<tokio::sync::Mutex<dyn Index>>,
-    trigram_index: Arc<tokio::sync::Mutex<dyn Index>>,
-    db_path: PathBuf,
-    api_key_config: crate::ApiKeyConfig,
-) -> Result<Router> {
-    validate_saas_environment(&api_key_config)?;
+#[cfg(test)]
+mod repository_settings_tests {
+    use super::*;
+    use crate::auth_middleware::AuthContext;
+    use axum::body::Body;
+    use axum::http::Request;
+    use tempfile::TempDir;
+    use tower::ServiceExt;
+
+    async fn settings_router(dir: &TempDir) -> Result<Router> {
+        let database = Database::new(dir.path(), true).await?;
+        // Never connected: rejected patches must fail before touching Supabase
+        let pool = sqlx::postgres::PgPoolOptions::new()
+            .connect_lazy("postgres://kotadb@127.0.0.1:1/unused")?;
+        let state = ServicesAppState {
+            storage: database.storage.clone(),
+            primary_index: database.primary_index.clone(),
+            trigram_index: database.trigram_index.clone(),
+            db_path: dir.path().to_path_buf(),
+            api_key_service: None,
+            supabase_pool: Some(pool),
+            webhook_base_url: None,
+            saas_mode: true,
+            jobs: Arc::new(RwLock::new(HashMap::new())),
+            repositories: Arc::new(RwLock::new(Vec::new())),
+            data_generation: initial_data_generation(),
+            idempotency: Arc::new(RwLock::new(HashMap::new())),
+            token_usage: Arc::new(TokenLedger::default()),
+            annotation_writes: Arc::new(tokio::sync::Mutex::new(())),
+            corpus_writes: Arc::new(tokio::sync::Mutex::new(())),
+            path_cache: database.path_cache.clone(),
+            semantic_engine: Arc::new(tokio::sync::OnceCell::new()),
+            relationship_engine: Arc::new(tokio::sync::OnceCell::new()),
+            symbol_reader: SharedSymbolReader::new(dir.path().join("symbols.kota")),
+            overview_cache: Arc::new(RwLock::new(None)),
+            repository_scopes: Arc::new(RepositoryScopeCache::default()),
+        };
+        let auth = AuthContext {
+            key_id: 1,
+            user_email: "dev@example.com".to_string(),
+            user_id: Some(Uuid::new_v4().to_string()),
+            rate_limit: 60,
+            remaining_quota: 1_000,
+            redaction: RedactionRules::default(),
+        };
+        Ok(Router::new()
+            .route(
+                "/api/v1/repositories/:repository_id/settings",
+                patch(update_repository_settings_v1),
+            )
+            .layer(Extension(auth))
+            .with_state(state))
+    }
 
-    use crate::auth_middleware::{auth_middleware, internal_auth_middleware};
+    async fn patch_settings(router: &Router, body: JsonValue) -> Result<(StatusCode, String)> {
+        let response = router
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("PATCH")
+                    .uri(format!("/api/v1/repositories/{}/settings", Uuid::new_v4()))
+                    .header("content-type", "application/json")
+                    .body(Body::from(body.to_string()))?,
+            )
+            .await?;
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await?;
+        Ok((status, String::from_utf8_lossy(&bytes).into_owned()))
+    }
 
-    // Initialize API key service
-    let api_key_service = Arc::new(crate::ApiKeyService::new(api_key_config).await?);
+    #[tokio::test]
+    async fn settings_patch_rejects_unknown_fields() -> Result<()> {
+        let dir = TempDir::new()?;
+        let router = settings_router(&dir).await?;
 
-    let repos_init = load_repositories_from_disk_async(db_path.as_path()).await;
-    let supabase_pool = api_key_service.pool();
-    let webhook_base_url = Url::parse(
-        &env::var("KOTADB_WEBHOOK_BASE_URL")
-            .context("KOTADB_WEBHOOK_BASE_URL must be set to the public SaaS base URL")?,
-    )
-    .context("Invalid KOTADB_WEBHOOK_BASE_URL value")?;
-    let state = ServicesAppState {
-        storage: storage.clone(),
-        primary_index: primary_index.clone(),
-        trigram_index: trigram_index.clone(),
-        db_path: db_path.clone(),
-        api_key_service: Some(api_key_service.clone()),
-        supabase_pool: Some(supabase_pool.clone()),
-        webhook_base_url: Some(webhook_base_url.clone()),
-        saas_mode: true,
-        jobs: Arc::new(RwLock::new(HashMap::new())),
-        repositories: Arc::new(RwLock::new(repos_init)),
+        let (status, body) = patch_settings(&router, json!({ "brnach": "main" })).await?;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body.contains("brnach"), "{body}");
+
+        let (status, body) =
+            patch_settings(&router, json!({ "options": { "include_file": true } })).await?;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body.contains("include_file"), "{body}");
+
+        let (status, _) =
+            patch_settings(&router, json!({ "options": { "max_memory_mb": "lots" } })).await?;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let (status, _) = patch_settings(&router, json!({ "options": null })).await?;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let (status, _) =
+            patch_settings(&router, json!({ "webhook_rules": { "branchez": [] } })).await?;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod demo_rate_limit_tests {
+    use super::*;
+    use axum::body::Body;
+
+    fn limiter(limit: u32) -> DemoRateLimiter {
+        DemoRateLimiter {
+            limit,
+            window: Duration::from_secs(60),
+            trust_proxy_headers: true,
+            clients: std::sync::Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn request(headers: &[(&str, &str)]) -> axum::extract::Request {
+        let mut builder = axum::http::Request::builder().uri("/health");
+        for (name, value) in headers {
+            builder = builder.header(*name, *value);
+        }
+        builder.body(Body::empty()).unwrap()
+    }
+
+    #[test]
+    fn client_key_ignores_client_supplied_forwarding_hops() {
+        let limiter = limiter(1);
+        assert_eq!(
+            limiter.client_key(&request(&[
+                ("x-forwarded-for", "1.1.1.1"),
+                ("fly-client-ip", "203.0.113.7"),
+            ])),
+            "203.0.113.7"
+        );
+        assert_eq!(
+            limiter.client_key(&request(&[("x-forwarded-for", "1.1.1.1, 203.0.113.9")])),
+            "203.0.113.9"
+        );
+        assert_eq!(
+            limiter.client_key(&request(&[("x-forwarded-for", "203.0.113.9, not-an-ip")])),
+            "unknown"
+        );
+
+        let untrusted = DemoRateLimiter {
+            trust_proxy_headers: false,
+            ..limiter
+        };
+        assert_eq!(
+            untrusted.client_key(&request(&[("fly-client-ip", "203.0.113.7")])),
+            "unknown"
+        );
+    }
+
+    #[test]
+    fn tracked_clients_are_bounded() {
+        let limiter = limiter(5);
+        for i in 0..MAX_DEMO_CLIENTS + 10 {
+            assert!(limiter.check(&format!("client-{i}")).is_ok());
+        }
+        assert!(limiter.clients.lock().unwrap().len() <= MAX_DEMO_CLIENTS);
+    }
+}
+
+#[cfg(test)]
+mod token_accounting_tests {
+    use super::*;
+    use axum::body::Body;
+
+    #[tokio::test]
+    async fn oversized_bodies_stream_through_unchanged() {
+        const CHUNK: usize = 1024 * 1024;
+        let chunks = MAX_ACCOUNTED_BODY_BYTES / CHUNK + 2;
+        let chunk = Bytes::from(vec![b'x'; CHUNK]);
+        let stream =
+            futures::stream::iter((0..chunks).map(move |_| Ok::<_, std::io::Error>(chunk.clone())));
+
+        let passthrough = buffer_accounted_body(Body::from_stream(stream))
+            .await
+            .expect_err("body exceeds the accounting limit");
+        let replayed = axum::body::to_bytes(passthrough, usize::MAX).await.unwrap();
+        assert_eq!(replayed.len(), chunks * CHUNK);
+
+        let small = buffer_accounted_body(Body::from("{\"ok\":true}"))
+            .await
+            .unwrap();
+        assert_eq!(&small[..], b"{\"ok\":true}");
+    }
+
+    #[tokio::test]
+    async fn raw_file_tokens_use_counts_recorded_at_insert() -> Result<()> {
+        let dir = tempfile::TempDir::new()?;
+        let database = Database::new(dir.path(), true).await?;
+        let content = "fn main() { println!(\"hello\"); }";
+        let document = crate::DocumentBuilder::new()
+            .path("repos/app/files/src/main.rs")?
+            .title("main.rs")?
+            .content(content.as_bytes())
+            .build()?;
+        database
+            .primary_index
+            .lock_timed(LockedResource::PrimaryIndex)
+            .await
+            .insert(document.id, document.path.clone())
+            .await?;
+        database
+            .storage
+            .lock_timed(LockedResource::Storage)
+            .await
+            .insert(document)
+            .await?;
+
+        let state = ServicesAppState {
+            storage: database.storage.clone(),
+            primary_index: database.primary_index.clone(),
+            trigram_index: database.trigram_index.clone(),
+            db_path: dir.path().to_path_buf(),
+            api_key_service: None,
+            supabase_pool: None,
+            webhook_base_url: None,
+            saas_mode: false,
+            jobs: Arc::new(RwLock::new(HashMap::new())),
+            repositories: Arc::new(RwLock::new(Vec::new())),
+            data_generation: initial_data_generation(),
+            idempotency: Arc::new(RwLock::new(HashMap::new())),
+            token_usage: Arc::new(TokenLedger::default()),
+            annotation_writes: Arc::new(tokio::sync::Mutex::new(())),
+            corpus_writes: Arc::new(tokio::sync::Mutex::new(())),
+            path_cache: database.path_cache.clone(),
+            semantic_engine: Arc::new(tokio::sync::OnceCell::new()),
+            relationship_engine: Arc::new(tokio::sync::OnceCell::new()),
+            symbol_reader: SharedSymbolReader::new(dir.path().join("symbols.kota")),
+            overview_cache: Arc::new(RwLock::new(None)),
+            repository_scopes: Arc::new(RepositoryScopeCache::default()),
+        };
+        let paths: BTreeSet<String> = ["src/main.rs", "./src/missing.rs"]
+            .into_iter()
+            .map(String::from)
+            .collect();
+
+        let expected = crate::contracts::content_tokens(content.as_bytes());
+        assert_eq!(
+            state.token_usage.raw_file_tokens(&state, &paths).await,
+            expected
+        );
+        // Served from the cache on the second lookup
+        assert_eq!(
+            state.token_usage.raw_file_tokens(&state, &paths).await,
+            expected
+        );
+        let cached = state.token_usage.file_tokens.read().await;
+        let index = cached.as_ref().unwrap();
+        assert_eq!(index.tokens.get("src/main.rs"), Some(&Some(expected)));
+        assert_eq!(index.tokens.get("src/missing.rs"), Some(&None));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod saas_helper_tests {
+    use super::normalize_git_ref;
+
+    #[test]
+    fn normalizes_branch_refs() {
+        assert_eq!(
+            normalize_git_ref("refs/heads/main"),
+            Some("main".to_string())
+        );
+    }
+
+    #[test]
+    fn returns_none_for_non_branch_refs() {
+        assert_eq!(normalize_git_ref("refs/pull/123"), None);
+    }
+}
+
+/// Health check response
+#[derive(Debug, Serialize)]
+pub struct HealthResponse {
+    pub status: String,
+    pub version: String,
+    pub services_enabled: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub saas: Option<SaasHealth>,
+}
+
+#[derive(Debug, Serialize, Default)]
+pub struct JobQueueHealth {
+    pub queued: usize,
+    pub in_progress: usize,
+    pub failed: usize,
+    pub failed_recent: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub oldest_queued_seconds: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SaasHealth {
+    pub supabase_status: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub supabase_latency_ms: Option<u128>,
+    pub job_queue: JobQueueHealth,
+}
+
+/// Error response
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    pub error: String,
+    pub message: String,
+}
+
+/// Minimal background job status for indexing
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobStatus {
+    pub id: String,
+    pub repo_path: String,
+    pub status: String, // queued | running | completed | failed
+    pub progress: Option<f32>,
+    pub started_at: Option<String>,
+    pub updated_at: Option<String>,
+    pub error: Option<String>,
+}
+
+/// Simple repository registry record
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RepositoryRecord {
+    pub id: String,
+    pub name: String,
+    pub path: String,
+    pub url: Option<String>,
+    pub last_indexed: Option<String>,
+}
+
+/// Stats request parameters
+#[derive(Debug, Deserialize)]
+pub struct StatsQuery {
+    pub basic: Option<bool>,
+    pub symbols: Option<bool>,
+    pub relationships: Option<bool>,
+    /// Include lock contention on the storage and indices
+    pub detailed: Option<bool>,
+    /// `previous` adds deltas against the previous indexing run
+    pub compare: Option<String>,
+    pub repository_id: Option<String>,
+}
+
+/// Benchmark request
+#[derive(Debug, Deserialize)]
+pub struct BenchmarkRequest {
+    pub operations: Option<usize>,
+    pub benchmark_type: Option<String>,
+    pub format: Option<String>,
+}
+
+/// Validation request
+#[derive(Debug, Deserialize)]
+pub struct ValidationRequest {
+    pub check_integrity: Option<bool>,
+    pub check_consistency: Option<bool>,
+    pub repair: Option<bool>,
+}
+
+/// Index codebase request
+#[derive(Debug, Deserialize)]
+pub struct IndexCodebaseRequest {
+    pub repo_path: String,
+    pub prefix: Option<String>,
+    pub include_files: Option<bool>,
+    pub include_commits: Option<bool>,
+    pub extract_symbols: Option<bool>,
+}
+
+/// v1 repository registration request
+#[derive(Debug, Deserialize)]
+pub struct RegisterRepositoryRequest {
+    pub path: Option<String>,
+    pub git_url: Option<String>,
+    pub branch: Option<String>,
+    // Optional indexing overrides
+    pub include_files: Option<bool>,
+    pub include_commits: Option<bool>,
+    pub max_file_size_mb: Option<usize>,
+    pub max_memory_mb: Option<u64>,
+    pub max_parallel_files: Option<usize>,
+    pub enable_chunking: Option<bool>,
+    pub extract_symbols: Option<bool>,
+    /// Webhook filtering rules stored under `settings.webhook_rules`
+    pub webhook_rules: Option<JsonValue>,
+}
+
+/// v1 repository registration response
+#[derive(Debug, Serialize)]
+pub struct RegisterRepositoryResponse {
+    pub job_id: String,
+    pub repository_id: String,
+    pub status: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub webhook_secret: Option<String>,
+}
+
+/// v1 repository listing response
+#[derive(Debug, Serialize)]
+pub struct ListRepositoriesResponse {
+    pub repositories: Vec<RepositoryRecord>,
+}
+
+/// v1 index status response
+#[derive(Debug, Serialize)]
+pub struct IndexStatusResponse {
+    pub job: Option<JobStatus>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WebhookResponse {
+    pub status: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub job_id: Option<String>,
+}
+
+/// Codebase overview request
+#[derive(Debug, Deserialize)]
+pub struct CodebaseOverviewRequest {
+    pub format: Option<String>,
+    pub top_symbols_limit: Option<usize>,
+    pub entry_points_limit: Option<usize>,
+    pub repository_id: Option<String>,
+}
+
+// ================================================================================================
+// ENHANCED API STRUCTURES - Standards Compliant, Non-Breaking
+// ================================================================================================
+
+/// Search request with format options and validation
+#[derive(Debug, Deserialize)]
+pub struct SearchRequest {
+    pub query: String,
+    pub limit: Option<usize>,
+    pub search_type: Option<String>,
+    pub format: Option<String>, // "simple", "rich", "cli" (default: rich)
+    pub repository_id: Option<String>,
+    /// "file" (default) for one result per file, "match" for one per match
+    pub group_by: Option<String>,
+    /// Query deadline in milliseconds; 0 disables it (default: `KOTADB_QUERY_TIMEOUT_MS` or 30s)
+    pub timeout_ms: Option<u64>,
+    /// Comma-separated file extensions results are restricted to (e.g. "rs,toml")
+    pub extensions: Option<String>,
+    /// Most directories above a result file in its path
+    pub max_depth: Option<usize>,
+    /// Smallest result file size in bytes
+    pub min_size: Option<u64>,
+    /// Largest result file size in bytes
+    pub max_size: Option<u64>,
+}
+
+impl SearchRequest {
+    fn document_filters(&self) -> DocumentFilters {
+        DocumentFilters {
+            max_depth: self.max_depth,
+            min_size: self.min_size,
+            max_size: self.max_size,
+            ..Default::default()
+        }
+        .with_extensions(self.extensions.as_deref().unwrap_or_default().split(','))
+    }
+}
+
+/// Symbol search request with format options
+#[derive(Debug, Deserialize)]
+pub struct SymbolSearchRequest {
+    pub pattern: String,
+    pub limit: Option<usize>,
+    pub symbol_type: Option<String>,
+    pub format: Option<String>, // "simple", "rich", "cli" (default: rich)
+    pub repository_id: Option<String>,
+    /// Query deadline in milliseconds, see [`QueryDeadline::for_server_request`]
+    pub timeout_ms: Option<u64>,
+}
+
+/// Callers request with better field names and validation
+#[derive(Debug, Deserialize)]
+pub struct CallersRequest {
+    pub symbol: String, // Intuitive field name
+    pub limit: Option<usize>,
+    pub format: Option<String>, // "simple", "rich", "cli" (default: rich)
+    pub include_indirect: Option<bool>,
+    pub repository_id: Option<String>,
+    /// Query deadline in milliseconds, see [`QueryDeadline::for_server_request`]
+    pub timeout_ms: Option<u64>,
+}
+
+/// Impact analysis request with better field names
+#[derive(Debug, Deserialize)]
+pub struct ImpactAnalysisRequest {
+    pub symbol: String, // Intuitive field name
+    pub limit: Option<usize>,
+    pub format: Option<String>, // "simple", "rich", "cli" (default: rich)
+    pub max_depth: Option<u32>,
+    pub repository_id: Option<String>,
+    /// Query deadline in milliseconds, see [`QueryDeadline::for_server_request`]
+    pub timeout_ms: Option<u64>,
+}
+
+/// Simple response format for search operations - CLI-like
+#[derive(Debug, Serialize)]
+pub struct SimpleSearchResponse {
+    pub results: Vec<String>, // Just file paths
+    pub total_count: usize,
+    pub query_time_ms: u64,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub suggestions: Vec<String>, // "Did you mean" candidates when nothing matched
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub annotations: Vec<Annotation>, // Stored summaries of the results
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub notes: Vec<Note>, // Notes, tags and bookmarks left on the results
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub timed_out: bool, // The query deadline passed, so results are partial
+}
+
+/// Simple response format for symbol search - CLI-like  
+#[derive(Debug, Serialize)]
+pub struct SimpleSymbolResponse {
+    pub symbols: Vec<String>, // Just symbol names
+    pub total_count: usize,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub suggestions: Vec<String>, // "Did you mean" candidates when nothing matched
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub annotations: Vec<Annotation>, // Stored summaries of the results
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub notes: Vec<Note>, // Notes, tags and bookmarks left on the results
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub timed_out: bool, // The query deadline passed, so results are partial
+}
+
+/// Simple response format for analysis operations - CLI-like
+#[derive(Debug, Serialize)]
+pub struct SimpleAnalysisResponse {
+    pub results: Vec<String>, // Just relevant items
+    pub total_count: usize,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub timed_out: bool, // The query deadline passed, so results are partial
+}
+
+/// CLI-format response that exactly matches command-line output
+#[derive(Debug, Serialize)]
+pub struct CliFormatResponse {
+    pub output: String, // Exact CLI output format
+}
+
+/// Standardized API error with comprehensive information
+#[derive(Debug, Serialize)]
+pub struct StandardApiError {
+    pub error_type: String,
+    pub message: String,
+    pub details: Option<String>,
+    pub suggestions: Vec<String>,
+    pub error_code: Option<u32>,
+}
+
+// ================================================================================================
+// SHARED ERROR HANDLING - DRY Principle Compliance
+// ================================================================================================
+
+/// Standard result type for API operations
+type ApiResult<T> = Result<Json<T>, (StatusCode, Json<StandardApiError>)>;
+
+/// Standardized error handling for JSON parsing failures
+fn handle_json_parsing_error(
+    error: axum::extract::rejection::JsonRejection,
+    endpoint: &str,
+) -> (StatusCode, Json<StandardApiError>) {
+    let (error_type, message, suggestions) = match error {
+        axum::extract::rejection::JsonRejection::MissingJsonContentType(_) => (
+            "missing_content_type",
+            "Request must include 'Content-Type: application/json' header",
+            vec![
+                format!("Add header: 'Content-Type: application/json'"),
+                format!("Example: curl -H 'Content-Type: application/json' -d '{{\"symbol\":\"test\"}}' /api/{}", endpoint)
+            ]
+        ),
+        axum::extract::rejection::JsonRejection::JsonDataError(_) => (
+            "invalid_json_data", 
+            "JSON data is invalid or malformed",
+            vec![
+                "Validate JSON syntax with a JSON validator".to_string(),
+                "Ensure all required fields are provided".to_string(),
+                "Check for trailing commas or other syntax errors".to_string()
+            ]
+        ),
+        axum::extract::rejection::JsonRejection::JsonSyntaxError(_) => (
+            "json_syntax_error",
+            "JSON contains syntax errors",
+            vec![
+                "Validate JSON with an online JSON validator".to_string(),
+                "Common issues: missing quotes, trailing commas, unescaped characters".to_string()
+            ]
+        ),
+        _ => (
+            "request_parsing_error",
+            "Failed to parse request body",
+            vec![
+                "Ensure valid JSON format".to_string(),
+                "Include proper Content-Type header".to_string()
+            ]
+        )
+    };
+
+    (
+        StatusCode::BAD_REQUEST,
+        Json(StandardApiError {
+            error_type: error_type.to_string(),
+            message: message.to_string(),
+            details: Some(format!("Endpoint: {}", endpoint)),
+            suggestions,
+            error_code: Some(400),
+        }),
+    )
+}
+
+/// Standardized error handling for service operation failures
+fn handle_service_error(
+    error: anyhow::Error,
+    operation: &str,
+) -> (StatusCode, Json<StandardApiError>) {
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(StandardApiError {
+            error_type: format!("{}_failed", operation),
+            message: error.to_string(),
+            details: Some(format!("Operation: {}", operation)),
+            suggestions: vec![
+                "Check system resources and database connectivity".to_string(),
+                "Verify input parameters are valid".to_string(),
+                "Contact system administrator if problem persists".to_string(),
+            ],
+            error_code: Some(500),
+        }),
+    )
+}
+
+/// Standardized validation error handling with helpful messages
+fn handle_validation_error(
+    field_name: &str,
+    message: &str,
+    endpoint: &str,
+) -> (StatusCode, Json<StandardApiError>) {
+    (
+        StatusCode::BAD_REQUEST,
+        Json(StandardApiError {
+            error_type: "validation_error".to_string(),
+            message: format!("Validation failed for '{}': {}", field_name, message),
+            details: Some(format!("Endpoint: {}", endpoint)),
+            suggestions: vec![
+                format!("Ensure '{}' meets validation requirements", field_name),
+                "Check API documentation for valid field formats".to_string(),
+                "Use ValidatedPath for file paths and ValidatedTitle for titles".to_string(),
+            ],
+            error_code: Some(400),
+        }),
+    )
+}
+
+fn internal_server_error(message: impl Into<String>) -> (StatusCode, Json<StandardApiError>) {
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(StandardApiError {
+            error_type: "internal_server_error".into(),
+            message: message.into(),
+            details: None,
+            suggestions: vec![
+                "Try again later".into(),
+                "Contact support if the problem persists".into(),
+            ],
+            error_code: Some(500),
+        }),
+    )
+}
+
+fn unauthorized_error(message: impl Into<String>) -> (StatusCode, Json<StandardApiError>) {
+    (
+        StatusCode::UNAUTHORIZED,
+        Json(StandardApiError {
+            error_type: "unauthorized".into(),
+            message: message.into(),
+            details: None,
+            suggestions: vec!["Provide a valid API key".into()],
+            error_code: Some(401),
+        }),
+    )
+}
+
+/// Standardized not-found error handling with helpful messages
+fn handle_not_found_error(
+    field_name: &str,
+    message: &str,
+    endpoint: &str,
+) -> (StatusCode, Json<StandardApiError>) {
+    (
+        StatusCode::NOT_FOUND,
+        Json(StandardApiError {
+            error_type: "not_found".to_string(),
+            message: format!("{}: {}", field_name, message),
+            details: Some(format!("Endpoint: {}", endpoint)),
+            suggestions: vec![
+                "Verify the identifier and try again".to_string(),
+                "List available resources to find valid IDs".to_string(),
+            ],
+            error_code: Some(404),
+        }),
+    )
+}
+
+/// Create clean services-only HTTP server
+pub fn create_services_server(
+    storage: Arc<tokio::sync::Mutex<dyn Storage>>,
+    primary_index: Arc<tokio::sync::Mutex<dyn Index>>,
+    trigram_index: Arc<tokio::sync::Mutex<dyn Index>>,
+    db_path: PathBuf,
+) -> Router {
+    let state = ServicesAppState {
+        storage: storage.clone(),
+        primary_index: primary_index.clone(),
+        trigram_index: trigram_index.clone(),
+        db_path: db_path.clone(),
+        api_key_service: None, // No authentication for basic services server
+        supabase_pool: None,
+        webhook_base_url: None,
+        saas_mode: false,
+        jobs: Arc::new(RwLock::new(HashMap::new())),
+        repositories: Arc::new(RwLock::new(load_repositories_from_disk(db_path.as_path()))),
+
+        data_generation: initial_data_generation(),
+        idempotency: Arc::new(RwLock::new(HashMap::new())),
+        token_usage: Arc::new(TokenLedger::default()),
+        annotation_writes: Arc::new(tokio::sync::Mutex::new(())),
+        corpus_writes: Arc::new(tokio::sync::Mutex::new(())),
+        path_cache: Arc::new(RwLock::new(HashMap::new())),
+        semantic_engine: Arc::new(tokio::sync::OnceCell::new()),
+        relationship_engine: Arc::new(tokio::sync::OnceCell::new()),
+        symbol_reader: SharedSymbolReader::new(db_path.join("symbols.kota")),
+        overview_cache: Arc::new(RwLock::new(None)),
+        repository_scopes: Arc::new(RepositoryScopeCache::default()),
+    };
+    let maintenance = MaintenanceConfig::from_env();
+    if maintenance.window.is_some() {
+        spawn_maintenance_scheduler(state.clone(), maintenance);
+    }
+
+    let conditional = axum::middleware::from_fn_with_state(state.clone(), conditional_get);
+    let idempotent = axum::middleware::from_fn_with_state(state.clone(), idempotent_post);
+    let tokens = axum::middleware::from_fn_with_state(state.clone(), token_accounting);
+
+    let base_router = Router::new()
+        // Health endpoint
+        .route("/health", get(health_check))
+        .route("/metrics", get(prometheus_metrics))
+        // Versioned v1 endpoints (canonical)
+        .route(
+            "/api/v1/analysis/stats",
+            get(get_stats).route_layer(conditional.clone()),
+        )
+        .route(
+            "/api/v1/analysis/trends",
+            get(trends_v1).route_layer(conditional.clone()),
+        )
+        .route(
+            "/api/v1/search",
+            get(fused_search_v1).route_layer(tokens.clone()),
+        )
+        .route(
+            "/api/v1/search/code",
+            post(search_code_v1_post)
+                .get(search_code_enhanced)
+                .route_layer(tokens.clone()),
+        )
+        .route(
+            "/api/v1/search/symbols",
+            post(search_symbols_v1_post)
+                .get(search_symbols_enhanced)
+                .route_layer(tokens.clone()),
+        )
+        .route(
+            "/api/v1/symbols/:symbol/callers",
+            get(find_callers_v1_get).route_layer(tokens.clone()),
+        )
+        .route(
+            "/api/v1/symbols/:symbol/impact",
+            get(analyze_impact_v1_get).route_layer(tokens.clone()),
+        )
+        .route(
+            "/api/v1/symbols/:symbol/related",
+            get(related_symbols_v1_get).route_layer(tokens.clone()),
+        )
+        .route(
+            "/api/v1/symbols/:symbol/graph.svg",
+            get(symbol_graph_svg_v1).route_layer(conditional.clone()),
+        )
+        .route(
+            "/api/v1/annotations",
+            get(list_annotations_v1)
+                .put(put_annotation_v1)
+                .delete(delete_annotation_v1),
+        )
+        .route("/api/v1/notes", get(list_notes_v1).post(create_note_v1))
+        .route("/api/v1/maintenance", get(maintenance_status_v1))
+        .route("/api/v1/corpora", get(list_corpora_v1))
+        .route("/api/v1/corpora/:name", put(put_corpus_v1))
+        .route(
+            "/api/v1/corpora/:name/documents",
+            post(load_corpus_documents_v1),
+        )
+        .route(
+            "/api/v1/notes/:id",
+            patch(update_note_v1).delete(delete_note_v1),
+        )
+        .route(
+            "/api/v1/symbols",
+            get(list_symbols_v1).route_layer(conditional.clone()),
+        )
+        .route(
+            "/api/v1/symbols/export",
+            get(export_symbols_v1).route_layer(conditional.clone()),
+        )
+        .route(
+            "/api/v1/files/symbols/*path",
+            get(file_symbols_v1).route_layer(conditional.clone()),
+        )
+        .route(
+            "/api/v1/files/content/*path",
+            get(file_content_v1).route_layer(conditional.clone()),
+        )
+        .route(
+            "/api/v1/files/tree",
+            get(file_tree_v1).route_layer(conditional.clone()),
+        )
+        .route(
+            "/api/v1/repositories",
+            post(register_repository_v1).route_layer(idempotent.clone()),
+        )
+        .route("/api/v1/repositories", get(list_repositories_v1))
+        .route("/api/v1/index/status", get(index_status_v1))
+        .route("/api/v1/usage/tokens", get(token_usage_v1))
+        // Normalized v1 routes for remaining services
+        .route("/api/v1/benchmark", post(run_benchmark))
+        .route("/api/v1/validate", post(validate_database))
+        .route("/api/v1/health-check", get(health_check_detailed))
+        .route(
+            "/api/v1/index-codebase",
+            post(index_codebase).route_layer(idempotent.clone()),
+        )
+        .route(
+            "/api/v1/find-callers",
+            post(find_callers_enhanced).route_layer(tokens.clone()),
+        )
+        .route(
+            "/api/v1/analyze-impact",
+            post(analyze_impact_enhanced).route_layer(tokens.clone()),
+        )
+        .route(
+            "/api/v1/codebase-overview",
+            get(codebase_overview).route_layer(conditional.clone()),
+        )
+        .route(
+            "/api/v1/overview/treemap",
+            get(treemap_v1).route_layer(conditional.clone()),
+        )
+        .with_state(state)
+        .layer(
+            ServiceBuilder::new()
+                .layer(TraceLayer::new_for_http())
+                .layer(CorsLayer::permissive()),
+        );
+
+    // Optionally mount MCP bridge without auth for local development
+    // Available by default when compiled with mcp-server feature
+    #[cfg(feature = "mcp-server")]
+    let base_router = {
+        let mut registry = MCPToolRegistry::new();
+        // Register lightweight text search
+        {
+            let text_tools = Arc::new(crate::mcp::tools::text_search_tools::TextSearchTools::new(
+                trigram_index.clone(),
+                storage.clone(),
+            ));
+            registry = registry.with_text_tools(text_tools);
+        }
+        #[cfg(feature = "tree-sitter-parsing")]
+        {
+            let symbol_tools = Arc::new(SymbolTools::new(
+                storage.clone(),
+                primary_index.clone(),
+                trigram_index.clone(),
+                db_path.clone(),
+            ));
+            registry = registry.with_symbol_tools(symbol_tools);
+        }
+        if let Some(recorder) = crate::replay::SessionRecorder::from_env(&db_path) {
+            registry = registry.with_session_recorder(Arc::new(recorder));
+        }
+        let mcp_state = McpHttpBridgeState::new(Some(Arc::new(registry)));
+        let mcp_router = create_mcp_bridge_router()
+            .with_state(mcp_state)
+            .layer(tokens);
+        base_router.merge(mcp_router)
+    };
+
+    let base_router = ResponseLimits::from_env().apply(base_router);
+    CompressionConfig::from_env().apply(base_router)
+}
+
+/// Start the services-only HTTP server
+pub async fn start_services_server(
+    storage: Arc<tokio::sync::Mutex<dyn Storage>>,
+    primary_index: Arc<tokio::sync::Mutex<dyn Index>>,
+    trigram_index: Arc<tokio::sync::Mutex<dyn Index>>,
+    db_path: PathBuf,
+    port: u16,
+) -> Result<()> {
+    let app = create_services_server(storage, primary_index, trigram_index, db_path);
+
+    // Try to bind to the port with enhanced error handling
+    let listener = match TcpListener::bind(&format!("0.0.0.0:{port}")).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            error!("Failed to start server on port {}: {}", port, e);
+
+            if e.kind() == std::io::ErrorKind::AddrInUse {
+                error!("Port {} is already in use. Try these alternatives:", port);
+                error!("   - Use a different port: --port {}", port + 1);
+
+                // Cross-platform command suggestions
+                if cfg!(unix) {
+                    error!("   - Check port usage: lsof -ti:{}", port);
+                    error!("   - Kill process using port: kill $(lsof -ti:{})", port);
+                } else {
+                    error!("   - Check port usage: netstat -ano | findstr :{}", port);
+                    error!("   - Kill process using port: taskkill /PID <PID> /F");
+                }
+            }
+
+            return Err(e).context(format!(
+                "Failed to bind to port {}. Port may be in use or insufficient permissions",
+                port
+            ));
+        }
+    };
+
+    info!("KotaDB Services HTTP Server listening on port {}", port);
+    debug!("Clean services-only architecture - no legacy endpoints");
+    debug!("Available endpoints:");
+    debug!("   GET    /health                          - Server health check");
+    debug!("   GET    /api/v1/analysis/stats           - Database statistics (StatsService)");
+    debug!(
+        "   POST   /api/v1/benchmark                - Performance benchmarks (BenchmarkService)"
+    );
+    debug!("   POST   /api/v1/validate                 - Database validation (ValidationService)");
+    debug!(
+        "   GET    /api/v1/health-check             - Detailed health check (ValidationService)"
+    );
+    debug!("   POST   /api/v1/index-codebase           - Index repository (IndexingService)");
+    debug!("   GET    /api/v1/search/code              - Search code content (SearchService)");
+    debug!("   GET    /api/v1/search/symbols           - Search symbols (SearchService)");
+    debug!("   POST   /api/v1/find-callers             - Find callers (AnalysisService)");
+    debug!("   POST   /api/v1/analyze-impact           - Impact analysis (AnalysisService)");
+    debug!("   GET    /api/v1/codebase-overview        - Codebase overview (AnalysisService)");
+    debug!("Server ready at http://localhost:{}", port);
+    debug!("Health check: curl http://localhost:{}/health", port);
+
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Create services server with SaaS capabilities (API key authentication)
+pub async fn create_services_saas_server(
+    storage: Arc<tokio::sync::Mutex<dyn Storage>>,
+    primary_index: Arc<tokio::sync::Mutex<dyn Index>>,
+    trigram_index: Arc<tokio::sync::Mutex<dyn Index>>,
+    db_path: PathBuf,
+    api_key_config: crate::ApiKeyConfig,
+) -> Result<Router> {
+    validate_saas_environment(&api_key_config)?;
+
+    use crate::auth_middleware::{auth_middleware, internal_auth_middleware};
+
+    // Initialize API key service
+    let api_key_service = Arc::new(crate::ApiKeyService::new(api_key_config).await?);
+
+    let repos_init = load_repositories_from_disk_async(db_path.as_path()).await;
+    let supabase_pool = api_key_service.pool();
+    let webhook_base_url = Url::parse(
+        &env::var("KOTADB_WEBHOOK_BASE_URL")
+            .context("KOTADB_WEBHOOK_BASE_URL must be set to the public SaaS base URL")?,
+    )
+    .context("Invalid KOTADB_WEBHOOK_BASE_URL value")?;
+    let state = ServicesAppState {
+        storage: storage.clone(),
+        primary_index: primary_index.clone(),
+        trigram_index: trigram_index.clone(),
+        db_path: db_path.clone(),
+        api_key_service: Some(api_key_service.clone()),
+        supabase_pool: Some(supabase_pool.clone()),
+        webhook_base_url: Some(webhook_base_url.clone()),
+        saas_mode: true,
+        jobs: Arc::new(RwLock::new(HashMap::new())),
+        repositories: Arc::new(RwLock::new(repos_init)),
+        data_generation: initial_data_generation(),
+        idempotency: Arc::new(RwLock::new(HashMap::new())),
+        token_usage: Arc::new(TokenLedger::default()),
+        annotation_writes: Arc::new(tokio::sync::Mutex::new(())),
+        corpus_writes: Arc::new(tokio::sync::Mutex::new(())),
+        path_cache: Arc::new(RwLock::new(HashMap::new())),
+        semantic_engine: Arc::new(tokio::sync::OnceCell::new()),
+        relationship_engine: Arc::new(tokio::sync::OnceCell::new()),
+        symbol_reader: SharedSymbolReader::new(db_path.join("symbols.kota")),
+        overview_cache: Arc::new(RwLock::new(None)),
+        repository_scopes: Arc::new(RepositoryScopeCache::default()),
+    };
+
+    // Spawn Supabase-backed indexing worker for SaaS mode
+    {
+        let worker_store = SupabaseRepositoryStore::new(supabase_pool);
+        let worker_database = Arc::new(Database {
+            storage: state.storage.clone(),
+            primary_index: state.primary_index.clone(),
+            trigram_index: state.trigram_index.clone(),
+            path_cache: state.path_cache.clone(),
+        });
+        let mut worker = SupabaseJobWorker::new(worker_store, worker_database, db_path.clone())
+            .with_data_generation(state.data_generation.clone());
+        if let Some(sandbox) = crate::ingestion_sandbox::IngestionSandbox::from_env()? {
+            info!("Indexing jobs parse repositories in a sandboxed subprocess");
+            worker = worker.with_ingestion_sandbox(sandbox);
+        }
+        tokio::spawn(async move {
+            if let Err(e) = worker.run().await {
+                error!("Supabase job worker terminated: {}", e);
+            }
+        });
+    }
+
+    let conditional = axum::middleware::from_fn_with_state(state.clone(), conditional_get);
+    let idempotent = axum::middleware::from_fn_with_state(state.clone(), idempotent_post);
+    let tokens = axum::middleware::from_fn_with_state(state.clone(), token_accounting);
+
+    // Create authenticated routes (require API key)
+    let authenticated_routes = Router::new()
+        // v1 endpoints (canonical)
+        .route(
+            "/api/v1/analysis/stats",
+            get(get_stats).route_layer(conditional.clone()),
+        )
+        .route(
+            "/api/v1/analysis/trends",
+            get(trends_v1).route_layer(conditional.clone()),
+        )
+        .route(
+            "/api/v1/search",
+            get(fused_search_v1).route_layer(tokens.clone()),
+        )
+        .route(
+            "/api/v1/search/code",
+            post(search_code_v1_post)
+                .get(search_code_enhanced)
+                .route_layer(tokens.clone()),
+        )
+        .route(
+            "/api/v1/search/symbols",
+            post(search_symbols_v1_post)
+                .get(search_symbols_enhanced)
+                .route_layer(tokens.clone()),
+        )
+        .route(
+            "/api/v1/symbols/:symbol/callers",
+            get(find_callers_v1_get).route_layer(tokens.clone()),
+        )
+        .route(
+            "/api/v1/symbols/:symbol/impact",
+            get(analyze_impact_v1_get).route_layer(tokens.clone()),
+        )
+        .route(
+            "/api/v1/symbols/:symbol/related",
+            get(related_symbols_v1_get).route_layer(tokens.clone()),
+        )
+        .route(
+            "/api/v1/symbols/:symbol/graph.svg",
+            get(symbol_graph_svg_v1).route_layer(conditional.clone()),
+        )
+        .route(
+            "/api/v1/annotations",
+            get(list_annotations_v1)
+                .put(put_annotation_v1)
+                .delete(delete_annotation_v1),
+        )
+        .route("/api/v1/notes", get(list_notes_v1).post(create_note_v1))
+        .route(
+            "/api/v1/notes/:id",
+            patch(update_note_v1).delete(delete_note_v1),
+        )
+        .route(
+            "/api/v1/symbols",
+            get(list_symbols_v1).route_layer(conditional.clone()),
+        )
+        .route(
+            "/api/v1/symbols/export",
+            get(export_symbols_v1).route_layer(conditional.clone()),
+        )
+        .route(
+            "/api/v1/files/symbols/*path",
+            get(file_symbols_v1).route_layer(conditional.clone()),
+        )
+        .route(
+            "/api/v1/files/content/*path",
+            get(file_content_v1).route_layer(conditional.clone()),
+        )
+        .route(
+            "/api/v1/files/tree",
+            get(file_tree_v1).route_layer(conditional.clone()),
+        )
+        .route(
+            "/api/v1/repositories",
+            post(register_repository_v1).route_layer(idempotent.clone()),
+        )
+        .route("/api/v1/repositories", get(list_repositories_v1))
+        .route(
+            "/api/v1/repositories/:repository_id/settings",
+            patch(update_repository_settings_v1),
+        )
+        .route(
+            "/api/v1/notification-hooks",
+            post(create_notification_hook_v1).get(list_notification_hooks_v1),
+        )
+        .route(
+            "/api/v1/notification-hooks/:hook_id",
+            delete(delete_notification_hook_v1),
+        )
+        .route(
+            "/api/v1/uploads",
+            post(create_upload_v1).route_layer(idempotent.clone()),
+        )
+        .route(
+            "/api/v1/uploads/:upload_id",
+            get(get_upload_v1).delete(abort_upload_v1),
+        )
+        .route(
+            "/api/v1/uploads/:upload_id/parts/:part_number",
+            put(put_upload_part_v1),
+        )
+        .route(
+            "/api/v1/uploads/:upload_id/complete",
+            post(complete_upload_v1).route_layer(idempotent.clone()),
+        )
+        .route("/api/v1/account/export", get(export_account_v1))
+        .route(
+            "/api/v1/account/deletion",
+            post(request_account_deletion_v1),
+        )
+        .route(
+            "/api/v1/account/deletion/:request_id",
+            delete(cancel_account_deletion_v1),
+        )
+        .route(
+            "/api/v1/account/deletion/:request_id/confirm",
+            post(confirm_account_deletion_v1),
+        )
+        .route("/api/v1/account/requests", get(list_account_requests_v1))
+        .route("/api/v1/artifacts/signed-urls", post(create_signed_url_v1))
+        .route("/api/v1/index/status", get(index_status_v1))
+        .route("/api/v1/usage/tokens", get(token_usage_v1))
+        // Normalized v1 routes for remaining services
+        .route("/api/v1/benchmark", post(run_benchmark))
+        .route("/api/v1/validate", post(validate_database))
+        .route(
+            "/api/v1/index-codebase",
+            post(index_codebase).route_layer(idempotent.clone()),
+        )
+        .route(
+            "/api/v1/find-callers",
+            post(find_callers_enhanced).route_layer(tokens.clone()),
+        )
+        .route(
+            "/api/v1/analyze-impact",
+            post(analyze_impact_enhanced).route_layer(tokens.clone()),
+        )
+        .route(
+            "/api/v1/codebase-overview",
+            get(codebase_overview).route_layer(conditional.clone()),
+        )
+        .route(
+            "/api/v1/overview/treemap",
+            get(treemap_v1).route_layer(conditional.clone()),
+        )
+        .layer(axum::middleware::from_fn_with_state(
+            api_key_service.clone(),
+            auth_middleware,
+        ));
+
+    #[cfg(feature = "mcp-server")]
+    let authenticated_mcp_routes = {
+        let mut registry = MCPToolRegistry::new();
+        {
+            let text_tools = Arc::new(crate::mcp::tools::text_search_tools::TextSearchTools::new(
+                trigram_index.clone(),
+                storage.clone(),
+            ));
+            registry = registry.with_text_tools(text_tools);
+        }
+        #[cfg(feature = "tree-sitter-parsing")]
+        {
+            let symbol_tools = Arc::new(SymbolTools::new(
+                storage.clone(),
+                primary_index.clone(),
+                trigram_index.clone(),
+                db_path.clone(),
+            ));
+            registry = registry.with_symbol_tools(symbol_tools);
+        }
+
+        let mcp_state = McpHttpBridgeState::new(Some(Arc::new(registry)));
+        create_mcp_bridge_router()
+            .with_state(mcp_state)
+            .layer(tokens)
+            .layer(axum::middleware::from_fn_with_state(
+                api_key_service.clone(),
+                auth_middleware,
+            ))
+    };
+
+    // Create internal routes (require internal API key)
+    let internal_routes = Router::new()
+        .route("/internal/create-api-key", post(create_api_key_handler))
+        .layer(axum::middleware::from_fn(internal_auth_middleware));
+
+    let mut router = Router::new()
+        // Public endpoints (no authentication required)
+        .route("/health", get(health_check))
+        .route("/api/v1/health-check", get(health_check_detailed))
+        .route(
+            "/webhooks/github/:repository_id",
+            post(handle_github_webhook),
+        )
+        // Merge authenticated routes
+        .merge(authenticated_routes)
+        // Merge internal routes
+        .merge(internal_routes);
+
+    #[cfg(feature = "mcp-server")]
+    {
+        router = router.merge(authenticated_mcp_routes);
+    }
+
+    let router = router.with_state(state).layer(
+        ServiceBuilder::new()
+            .layer(TraceLayer::new_for_http())
+            .layer(CorsLayer::permissive()),
+    );
+    let router = ResponseLimits::from_env().apply(router);
+    Ok(CompressionConfig::from_env().apply(router))
+}
+
+/// Start services server with SaaS capabilities (API key authentication)
+pub async fn start_services_saas_server(
+    storage: Arc<tokio::sync::Mutex<dyn Storage>>,
+    primary_index: Arc<tokio::sync::Mutex<dyn Index>>,
+    trigram_index: Arc<tokio::sync::Mutex<dyn Index>>,
+    db_path: PathBuf,
+    api_key_config: crate::ApiKeyConfig,
+    port: u16,
+) -> Result<()> {
+    let app = create_services_saas_server(
+        storage,
+        primary_index,
+        trigram_index,
+        db_path.clone(),
+        api_key_config,
+    )
+    .await?;
+
+    // Try to bind to the port with enhanced error handling
+    let listener = match TcpListener::bind(&format!("0.0.0.0:{port}")).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            error!("Failed to start server on port {}: {}", port, e);
+
+            if e.kind() == std::io::ErrorKind::AddrInUse {
+                error!("Port {} is already in use. Try these alternatives:", port);
+                #[cfg(target_os = "macos")]
+                {
+                    error!("   - Find process using port: sudo lsof -i :{}", port);
+                    error!("   - Kill process using port: sudo kill -9 <PID>");
+                }
+                #[cfg(target_os = "linux")]
+                {
+                    error!(
+                        "   - Find process using port: sudo netstat -tulpn | grep :{}",
+                        port
+                    );
+                    error!("   - Kill process using port: sudo kill -9 <PID>");
+                }
+                #[cfg(target_os = "windows")]
+                {
+                    error!("   - Check port usage: netstat -ano | findstr :{}", port);
+                    error!("   - Kill process using port: taskkill /PID <PID> /F");
+                }
+            }
+
+            return Err(e).context(format!(
+                "Failed to bind to port {}. Port may be in use or insufficient permissions",
+                port
+            ));
+        }
+    };
+
+    info!("KotaDB Services SaaS Server listening on port {}", port);
+    debug!("API key authentication enabled");
+    debug!("Clean services architecture with SaaS capabilities");
+    debug!("Available endpoints:");
+    debug!("   GET    /health                    - Server health check (public)");
+    debug!("   GET    /api/v1/health-check       - Detailed health check (public)");
+    debug!("   🔐 Authenticated endpoints (require API key):");
+    debug!("   GET    /api/v1/analysis/stats     - Database statistics (StatsService)");
+    debug!("   POST   /api/v1/benchmark          - Performance benchmarks (BenchmarkService)");
+    debug!("   POST   /api/v1/validate           - Database validation (ValidationService)");
+    debug!("   POST   /api/v1/index-codebase     - Index repository (IndexingService)");
+    debug!("   GET    /api/v1/search/code        - Search code content (SearchService)");
+    debug!("   GET    /api/v1/search/symbols     - Search symbols (SearchService)");
+    debug!("   POST   /api/v1/find-callers       - Find callers (AnalysisService)");
+    debug!("   POST   /api/v1/analyze-impact     - Impact analysis (AnalysisService)");
+    debug!("   GET    /api/v1/codebase-overview  - Codebase overview (AnalysisService)");
+    debug!("   🔒 Internal endpoints (require internal API key):");
+    debug!("   POST   /internal/create-api-key   - Create new API key");
+    debug!("SaaS Server ready at http://localhost:{}", port);
+    debug!("Health check: curl http://localhost:{}/health", port);
+    debug!(
+        "Authenticated example: curl -H 'X-API-Key: your-key' http://localhost:{}/api/v1/analysis/stats",
+        port
+    );
+
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Settings for the anonymous, read-only demo server
+///
+/// Environment overrides (used by [`DemoConfig::from_env`]):
+/// - `KOTADB_DEMO_SAMPLE_REPO`: git repository indexed at startup when the database is empty
+/// - `KOTADB_DEMO_RATE_LIMIT`: requests per client per minute (default 30)
+/// - `KOTADB_DEMO_TRUST_PROXY`: key clients by `Fly-Client-IP`, or the right-most
+///   `X-Forwarded-For` hop appended by the proxy (default off)
+#[derive(Debug, Clone)]
+pub struct DemoConfig {
+    pub sample_repo: Option<PathBuf>,
+    pub requests_per_minute: u32,
+    pub trust_proxy_headers: bool,
+}
+
+impl Default for DemoConfig {
+    fn default() -> Self {
+        Self {
+            sample_repo: None,
+            requests_per_minute: 30,
+            trust_proxy_headers: false,
+        }
+    }
+}
+
+impl DemoConfig {
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            sample_repo: env::var("KOTADB_DEMO_SAMPLE_REPO")
+                .ok()
+                .filter(|v| !v.trim().is_empty())
+                .map(PathBuf::from),
+            requests_per_minute: env::var("KOTADB_DEMO_RATE_LIMIT")
+                .ok()
+                .and_then(|v| v.trim().parse().ok())
+                .filter(|v| *v > 0)
+                .unwrap_or(defaults.requests_per_minute),
+            trust_proxy_headers: env::var("KOTADB_DEMO_TRUST_PROXY")
+                .map(|v| parse_bool_env_flag(&v))
+                .unwrap_or(defaults.trust_proxy_headers),
+        }
+    }
+}
+
+/// Clients tracked by the demo rate limiter before the oldest windows are dropped
+const MAX_DEMO_CLIENTS: usize = 10_000;
+
+/// Fixed-window request counter keyed by client address
+struct DemoRateLimiter {
+    limit: u32,
+    window: Duration,
+    trust_proxy_headers: bool,
+    clients: std::sync::Mutex<HashMap<String, (Instant, u32)>>,
+}
+
+impl DemoRateLimiter {
+    /// Count a request; returns the remaining allowance or how long until the window resets
+    fn check(&self, client: &str) -> Result<u32, Duration> {
+        let now = Instant::now();
+        let mut clients = self.clients.lock().unwrap_or_else(|e| e.into_inner());
+        if clients.len() >= MAX_DEMO_CLIENTS && !clients.contains_key(client) {
+            clients.retain(|_, (started, _)| now.duration_since(*started) < self.window);
+            while clients.len() >= MAX_DEMO_CLIENTS {
+                let Some(oldest) = clients
+                    .iter()
+                    .min_by_key(|(_, (started, _))| *started)
+                    .map(|(key, _)| key.clone())
+                else {
+                    break;
+                };
+                clients.remove(&oldest);
+            }
+        }
+        let entry = clients.entry(client.to_string()).or_insert((now, 0));
+        if now.duration_since(entry.0) >= self.window {
+            *entry = (now, 0);
+        }
+        if entry.1 >= self.limit {
+            return Err(self.window.saturating_sub(now.duration_since(entry.0)));
+        }
+        entry.1 += 1;
+        Ok(self.limit - entry.1)
+    }
+
+    /// Address a request is counted against
+    ///
+    /// With proxy headers trusted, `Fly-Client-IP` wins, then the right-most
+    /// `X-Forwarded-For` entry: earlier entries are supplied by the client and can be forged.
+    fn client_key(&self, request: &axum::extract::Request) -> String {
+        if self.trust_proxy_headers {
+            let headers = request.headers();
+            let forwarded = ["fly-client-ip", "x-forwarded-for"]
+                .into_iter()
+                .find_map(|name| {
+                    let hop = headers.get(name)?.to_str().ok()?.rsplit(',').next()?;
+                    hop.trim().parse::<std::net::IpAddr>().ok()
+                });
+            if let Some(client) = forwarded {
+                return client.to_string();
+            }
+        }
+        request
+            .extensions()
+            .get::<axum::extract::ConnectInfo<SocketAddr>>()
+            .map(|info| info.0.ip().to_string())
+            .unwrap_or_else(|| "unknown".to_string())
+    }
+}
+
+async fn demo_rate_limit(
+    State(limiter): State<Arc<DemoRateLimiter>>,
+    request: axum::extract::Request,
+    next: axum::middleware::Next,
+) -> axum::response::Response {
+    use axum::response::IntoResponse;
+
+    let client = limiter.client_key(&request);
+    match limiter.check(&client) {
+        Ok(remaining) => {
+            let mut response = next.run(request).await;
+            let headers = response.headers_mut();
+            headers.insert("X-RateLimit-Limit", limiter.limit.into());
+            headers.insert("X-RateLimit-Remaining", remaining.into());
+            response
+        }
+        Err(retry_after) => {
+            let retry_secs = retry_after.as_secs().max(1);
+            let mut response = (
+                StatusCode::TOO_MANY_REQUESTS,
+                Json(StandardApiError {
+                    error_type: "rate_limit_exceeded".into(),
+                    message: format!(
+                        "Demo rate limit of {} requests per minute exceeded",
+                        limiter.limit
+                    ),
+                    details: None,
+                    suggestions: vec![
+                        format!("Retry after {} seconds", retry_secs),
+                        "Run KotaDB locally or sign up for an API key for higher limits".into(),
+                    ],
+                    error_code: Some(429),
+                }),
+            )
+                .into_response();
+            let headers = response.headers_mut();
+            headers.insert(axum::http::header::RETRY_AFTER, retry_secs.into());
+            headers.insert("X-RateLimit-Limit", limiter.limit.into());
+            headers.insert("X-RateLimit-Remaining", 0u32.into());
+            response
+        }
+    }
+}
+
+/// Rejects mutating endpoints on the demo server
+async fn demo_read_only() -> (StatusCode, Json<StandardApiError>) {
+    (
+        StatusCode::FORBIDDEN,
+        Json(StandardApiError {
+            error_type: "demo_read_only".into(),
+            message: "The demo server is read-only".into(),
+            details: Some("Indexing and repository management are disabled in demo mode".into()),
+            suggestions: vec![
+                "Run KotaDB locally to index your own repositories".into(),
+                "Use the managed service with an API key".into(),
+            ],
+            error_code: Some(403),
+        }),
+    )
+}
+
+/// Create the anonymous demo server: read-only endpoints over a pre-indexed sample repository,
+/// rate limited per client and with every mutating endpoint disabled.
+pub async fn create_services_demo_server(
+    storage: Arc<tokio::sync::Mutex<dyn Storage>>,
+    primary_index: Arc<tokio::sync::Mutex<dyn Index>>,
+    trigram_index: Arc<tokio::sync::Mutex<dyn Index>>,
+    db_path: PathBuf,
+    config: DemoConfig,
+) -> Result<Router> {
+    let state = ServicesAppState {
+        storage: storage.clone(),
+        primary_index: primary_index.clone(),
+        trigram_index: trigram_index.clone(),
+        db_path: db_path.clone(),
+        api_key_service: None,
+        supabase_pool: None,
+        webhook_base_url: None,
+        saas_mode: false,
+        jobs: Arc::new(RwLock::new(HashMap::new())),
+        repositories: Arc::new(RwLock::new(Vec::new())),
+        data_generation: initial_data_generation(),
+        idempotency: Arc::new(RwLock::new(HashMap::new())),
+        token_usage: Arc::new(TokenLedger::default()),
+        annotation_writes: Arc::new(tokio::sync::Mutex::new(())),
+        corpus_writes: Arc::new(tokio::sync::Mutex::new(())),
+        path_cache: Arc::new(RwLock::new(HashMap::new())),
+        semantic_engine: Arc::new(tokio::sync::OnceCell::new()),
+        relationship_engine: Arc::new(tokio::sync::OnceCell::new()),
+        symbol_reader: SharedSymbolReader::new(db_path.join("symbols.kota")),
+        overview_cache: Arc::new(RwLock::new(None)),
+        repository_scopes: Arc::new(RepositoryScopeCache::default()),
+    };
+
+    if let Some(sample_repo) = &config.sample_repo {
+        let is_empty = storage
+            .lock_timed(LockedResource::Storage)
+            .await
+            .list_metadata()
+            .await?
+            .is_empty();
+        if is_empty {
+            info!("Indexing demo sample repository {}", sample_repo.display());
+            let database = Database {
+                storage: storage.clone(),
+                primary_index: primary_index.clone(),
+                trigram_index: trigram_index.clone(),
+                path_cache: state.path_cache.clone(),
+            };
+            let result = IndexingService::new(&database, db_path.clone())
+                .index_codebase(IndexCodebaseOptions {
+                    repo_path: sample_repo.clone(),
+                    quiet: true,
+                    ..IndexCodebaseOptions::default()
+                })
+                .await
+                .context("Failed to index demo sample repository")?;
+            if !result.success {
+                return Err(anyhow!(
+                    "Failed to index demo sample repository: {}",
+                    result.errors.join("; ")
+                ));
+            }
+            state.bump_data_generation();
+        }
+    }
+
+    let limiter = Arc::new(DemoRateLimiter {
+        limit: config.requests_per_minute,
+        window: Duration::from_secs(60),
+        trust_proxy_headers: config.trust_proxy_headers,
+        clients: std::sync::Mutex::new(HashMap::new()),
+    });
+    let conditional = axum::middleware::from_fn_with_state(state.clone(), conditional_get);
+
+    let read_only_routes = Router::new()
+        .route(
+            "/api/v1/analysis/stats",
+            get(get_stats).route_layer(conditional.clone()),
+        )
+        .route(
+            "/api/v1/analysis/trends",
+            get(trends_v1).route_layer(conditional.clone()),
+        )
+        .route("/api/v1/search", get(fused_search_v1))
+        .route(
+            "/api/v1/search/code",
+            post(search_code_v1_post).get(search_code_enhanced),
+        )
+        .route(
+            "/api/v1/search/symbols",
+            post(search_symbols_v1_post).get(search_symbols_enhanced),
+        )
+        .route("/api/v1/symbols/:symbol/callers", get(find_callers_v1_get))
+        .route("/api/v1/symbols/:symbol/impact", get(analyze_impact_v1_get))
+        .route(
+            "/api/v1/symbols/:symbol/related",
+            get(related_symbols_v1_get),
+        )
+        .route(
+            "/api/v1/symbols/:symbol/graph.svg",
+            get(symbol_graph_svg_v1).route_layer(conditional.clone()),
+        )
+        .route(
+            "/api/v1/annotations",
+            get(list_annotations_v1)
+                .put(demo_read_only)
+                .delete(demo_read_only),
+        )
+        .route("/api/v1/notes", get(list_notes_v1).post(demo_read_only))
+        .route("/api/v1/notes/:id", any(demo_read_only))
+        .route(
+            "/api/v1/symbols",
+            get(list_symbols_v1).route_layer(conditional.clone()),
+        )
+        .route(
+            "/api/v1/symbols/export",
+            get(export_symbols_v1).route_layer(conditional.clone()),
+        )
+        .route(
+            "/api/v1/files/symbols/*path",
+            get(file_symbols_v1).route_layer(conditional.clone()),
+        )
+        .route(
+            "/api/v1/files/content/*path",
+            get(file_content_v1).route_layer(conditional.clone()),
+        )
+        .route(
+            "/api/v1/files/tree",
+            get(file_tree_v1).route_layer(conditional.clone()),
+        )
+        .route("/api/v1/find-callers", post(find_callers_enhanced))
+        .route("/api/v1/analyze-impact", post(analyze_impact_enhanced))
+        .route(
+            "/api/v1/codebase-overview",
+            get(codebase_overview).route_layer(conditional.clone()),
+        )
+        .route(
+            "/api/v1/overview/treemap",
+            get(treemap_v1).route_layer(conditional.clone()),
+        )
+        .route("/api/v1/repositories", any(demo_read_only))
+        .route("/api/v1/index-codebase", any(demo_read_only))
+        .route("/api/v1/index/status", any(demo_read_only))
+        .route("/api/v1/benchmark", any(demo_read_only))
+        .route("/api/v1/validate", any(demo_read_only))
+        .layer(axum::middleware::from_fn_with_state(
+            limiter,
+            demo_rate_limit,
+        ));
+
+    let router = Router::new()
+        .route("/health", get(health_check))
+        .route("/api/v1/health-check", get(health_check_detailed))
+        .merge(read_only_routes)
+        .with_state(state)
+        .layer(
+            ServiceBuilder::new()
+                .layer(TraceLayer::new_for_http())
+                .layer(CorsLayer::permissive()),
+        );
+    let router = ResponseLimits::from_env().apply(router);
+    Ok(CompressionConfig::from_env().apply(router))
+}
+
+/// Start the anonymous demo server (see [`create_services_demo_server`])
+pub async fn start_services_demo_server(
+    storage: Arc<tokio::sync::Mutex<dyn Storage>>,
+    primary_index: Arc<tokio::sync::Mutex<dyn Index>>,
+    trigram_index: Arc<tokio::sync::Mutex<dyn Index>>,
+    db_path: PathBuf,
+    config: DemoConfig,
+    port: u16,
+) -> Result<()> {
+    let requests_per_minute = config.requests_per_minute;
+    let app =
+        create_services_demo_server(storage, primary_index, trigram_index, db_path, config).await?;
+
+    let listener = TcpListener::bind(&format!("0.0.0.0:{port}"))
+        .await
+        .with_context(|| format!("Failed to bind demo server to port {}", port))?;
+
+    info!("KotaDB demo server listening on port {}", port);
+    debug!(
+        "Read-only anonymous access, {} requests per client per minute",
+        requests_per_minute
+    );
+
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Basic health check
+async fn health_check(State(state): State<ServicesAppState>) -> Json<HealthResponse> {
+    let mut response = HealthResponse {
+        status: "healthy".to_string(),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        services_enabled: vec![
+            "StatsService".to_string(),
+            "BenchmarkService".to_string(),
+            "ValidationService".to_string(),
+            "IndexingService".to_string(),
+            "SearchService".to_string(),
+            "AnalysisService".to_string(),
+        ],
+        saas: None,
+    };
+
+    if state.is_saas_mode() {
+        response.saas = Some(fetch_saas_health(&state).await);
+    }
+
+    Json(response)
+}
+
+/// GET /metrics - Prometheus exposition of lock contention on the storage and indices,
+/// and of the relationship result cache once it was opened
+async fn prometheus_metrics(State(state): State<ServicesAppState>) -> axum::response::Response {
+    use axum::response::IntoResponse;
+
+    let mut metrics = export_lock_contention_prometheus();
+    if let Some(Some(engine)) = state.relationship_engine.get() {
+        metrics.push_str(&engine.cache_stats().to_prometheus());
+    }
+    (
+        [(
+            axum::http::header::CONTENT_TYPE,
+            "text/plain; version=0.0.4; charset=utf-8",
+        )],
+        metrics,
+    )
+        .into_response()
+}
+
+/// Get database statistics via StatsService
+async fn get_stats(
+    State(state): State<ServicesAppState>,
+    auth_context: Option<Extension<AuthContext>>,
+    AxumQuery(params): AxumQuery<StatsQuery>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<ErrorResponse>)> {
+    redaction_unsupported(auth_context.as_ref())?;
+    let scope = legacy_repository_scope(
+        &state,
+        auth_context.as_ref(),
+        params.repository_id.as_deref(),
+        "stats",
+    )
+    .await?;
+    let compare_previous = match params.compare.as_deref() {
+        None => false,
+        Some("previous") => true,
+        Some(other) => {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse {
+                    error: "invalid_compare".to_string(),
+                    message: format!("Unknown comparison '{}' (expected: previous)", other),
+                }),
+            ));
+        }
     };
+    let result = with_trace_id("api_stats", async move {
+        // Create Database instance to implement DatabaseAccess
+        let database = Database {
+            storage: state.storage.clone(),
+            primary_index: state.primary_index.clone(),
+            trigram_index: state.trigram_index.clone(),
+            path_cache: state.path_cache.clone(),
+        };
+
+        let mut stats_service = StatsService::new(&database, state.db_path.clone());
+        if let Some(scope) = scope {
+            stats_service = stats_service.with_repository_scope(scope);
+        }
 
-    // Spawn Supabase-backed indexing worker for SaaS mode
-    {
-        let worker_store = SupabaseRepositoryStore::new(supabase_pool);
-        let worker_database = Arc::new(Database {
+        let options = StatsOptions {
+            basic: params.basic.unwrap_or(false),
+            symbols: params.symbols.unwrap_or(true),
+            relationships: params.relationships.unwrap_or(true),
+            detailed: params.detailed.unwrap_or(false),
+            compare_previous,
+            quiet: false,
+        };
+
+        stats_service.get_statistics(options).await
+    })
+    .await;
+
+    match result {
+        Ok(stats) => {
+            let json_value = serde_json::to_value(stats).map_err(|e| {
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ErrorResponse {
+                        error: "serialization_failed".to_string(),
+                        message: e.to_string(),
+                    }),
+                )
+            })?;
+            Ok(Json(json_value))
+        }
+        Err(e) => {
+            tracing::warn!("Failed to get stats: {}", e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: "stats_failed".to_string(),
+                    message: e.to_string(),
+                }),
+            ))
+        }
+    }
+}
+
+/// Run performance benchmarks via BenchmarkService
+async fn run_benchmark(
+    State(state): State<ServicesAppState>,
+    Json(request): Json<BenchmarkRequest>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<ErrorResponse>)> {
+    let result = with_trace_id("api_benchmark", async move {
+        // Create Database instance to implement DatabaseAccess
+        let database = Database {
             storage: state.storage.clone(),
             primary_index: state.primary_index.clone(),
             trigram_index: state.trigram_index.clone(),
-            path_cache: Arc::new(RwLock::new(HashMap::new())),
-        });
-        let worker = SupabaseJobWorker::new(worker_store, worker_database, db_path.clone());
-        tokio::spawn(async move {
-            if let Err(e) = worker.run().await {
-                error!("Supabase job worker terminated: {}", e);
-            }
-        });
-    }
+            path_cache: state.path_cache.clone(),
+        };
 
-    // Create authenticated routes (require API key)
-    let authenticated_routes = Router::new()
-        // v1 endpoints (canonical)
-        .route("/api/v1/analysis/stats", get(get_stats))
-        .route(
-            "/api/v1/search/code",
-            post(search_code_v1_post).get(search_code_enhanced),
-        )
-        .route(
-            "/api/v1/search/symbols",
-            post(search_symbols_v1_post).get(search_symbols_enhanced),
-        )
-        .route("/api/v1/symbols/:symbol/callers", get(find_callers_v1_get))
-        .route("/api/v1/symbols/:symbol/impact", get(analyze_impact_v1_get))
-        .route("/api/v1/symbols", get(list_symbols_v1))
-        .route("/api/v1/files/symbols/*path", get(file_symbols_v1))
-        .route("/api/v1/repositories", post(register_repository_v1))
-        .route("/api/v1/repositories", get(list_repositories_v1))
-        .route("/api/v1/index/status", get(index_status_v1))
-        // Normalized v1 routes for remaining services
-        .route("/api/v1/benchmark", post(run_benchmark))
-        .route("/api/v1/validate", post(validate_database))
-        .route("/api/v1/index-codebase", post(index_codebase))
-        .route("/api/v1/find-callers", post(find_callers_enhanced))
-        .route("/api/v1/analyze-impact", post(analyze_impact_enhanced))
-        .route("/api/v1/codebase-overview", get(codebase_overview))
-        .layer(axum::middleware::from_fn_with_state(
-            api_key_service.clone(),
-            auth_middleware,
-        ));
+        let benchmark_service = BenchmarkService::new(&database, state.db_path.clone());
 
-    #[cfg(feature = "mcp-server")]
-    let authenticated_mcp_routes = {
-        let mut registry = MCPToolRegistry::new();
-        {
-            let text_tools = Arc::new(crate::mcp::tools::text_search_tools::TextSearchTools::new(
-                trigram_index.clone(),
-                storage.clone(),
-            ));
-            registry = registry.with_text_tools(text_tools);
+        let options = BenchmarkOptions {
+            operations: request.operations.unwrap_or(1000),
+            benchmark_type: request.benchmark_type.unwrap_or_else(|| "all".to_string()),
+            format: request.format.unwrap_or_else(|| "json".to_string()),
+            max_search_queries: 100,
+            quiet: false,
+            warm_up_operations: Some(100),
+            concurrent_operations: Some(1),
+        };
+
+        benchmark_service.run_benchmark(options).await
+    })
+    .await;
+
+    match result {
+        Ok(benchmark_result) => {
+            let json_value = serde_json::to_value(benchmark_result).map_err(|e| {
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ErrorResponse {
+                        error: "serialization_failed".to_string(),
+                        message: e.to_string(),
+                    }),
+                )
+            })?;
+            Ok(Json(json_value))
         }
-        #[cfg(feature = "tree-sitter-parsing")]
-        {
-            let symbol_tools = Arc::new(SymbolTools::new(
-                storage.clone(),
-                primary_index.clone(),
-                trigram_index.clone(),
-                db_path.clone(),
-            ));
-            registry = registry.with_symbol_tools(symbol_tools);
+        Err(e) => {
+            tracing::warn!("Failed to run benchmark: {}", e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: "benchmark_failed".to_string(),
+                    message: e.to_string(),
+                }),
+            ))
         }
+    }
+}
 
-        let mcp_state = McpHttpBridgeState::new(Some(Arc::new(registry)));
-        create_mcp_bridge_router().with_state(mcp_state).layer(
-            axum::middleware::from_fn_with_state(api_key_service.clone(), auth_middleware),
-        )
+// ================================================================================================
+// v1 ROUTE HANDLERS - Thin wrappers mapping to existing services
+// ================================================================================================
+
+#[derive(Debug, Deserialize)]
+struct V1SearchCodeBody {
+    pub query: String,
+    pub limit: Option<usize>,
+    pub format: Option<String>,
+    pub repository_id: Option<String>,
+    pub group_by: Option<String>,
+    pub timeout_ms: Option<u64>,
+    pub extensions: Option<String>,
+    pub max_depth: Option<usize>,
+    pub min_size: Option<u64>,
+    pub max_size: Option<u64>,
+}
+
+async fn search_code_v1_post(
+    State(state): State<ServicesAppState>,
+    auth_context: Option<Extension<AuthContext>>,
+    request_result: Result<Json<V1SearchCodeBody>, axum::extract::rejection::JsonRejection>,
+) -> ApiResult<serde_json::Value> {
+    let Json(body) = request_result.map_err(|e| handle_json_parsing_error(e, "v1 search-code"))?;
+    let query = body.query.clone();
+    let limit = body.limit;
+    let format = body.format.clone();
+    // Reuse search_code_enhanced by constructing AxumQuery<SearchRequest>
+    let request = SearchRequest {
+        query,
+        limit,
+        search_type: Some("medium".to_string()),
+        format,
+        repository_id: body.repository_id,
+        group_by: body.group_by,
+        timeout_ms: body.timeout_ms,
+        extensions: body.extensions,
+        max_depth: body.max_depth,
+        min_size: body.min_size,
+        max_size: body.max_size,
     };
 
-    // Create internal routes (require internal API key)
-    let internal_routes = Router::new()
-        .route("/internal/create-api-key", post(create_api_key_handler))
-        .layer(axum::middleware::from_fn(internal_auth_middleware));
+    // Inline the logic of search_code_enhanced to avoid duplicate parsing
+    if request.query.trim().is_empty() {
+        return Err(handle_validation_error(
+            "query",
+            "Query cannot be empty",
+            "search-code",
+        ));
+    }
+    let group_by = parse_search_grouping(request.group_by.as_deref())?;
+
+    let annotations_dir = annotations_dir(&state, auth_context.as_ref())?;
+    let scope = repository_scope(
+        &state,
+        auth_context.as_ref(),
+        request.repository_id.as_deref(),
+        "search-code",
+    )
+    .await?;
+    let document_filters = request.document_filters();
+    let result = with_trace_id("api_v1_search_code", async move {
+        let database = Database {
+            storage: state.storage.clone(),
+            primary_index: state.primary_index.clone(),
+            trigram_index: state.trigram_index.clone(),
+            path_cache: state.path_cache.clone(),
+        };
+        let mut search_service = SearchService::new(&database, state.db_path.clone())
+            .with_redaction(redaction_rules(auth_context.as_ref()))
+            .with_annotations_dir(annotations_dir)
+            .with_deadline(QueryDeadline::for_server_request(request.timeout_ms))
+            .with_document_filters(document_filters);
+        if let Some(scope) = scope {
+            search_service = search_service.with_repository_scope(scope);
+        }
+        let options = SearchOptions {
+            query: request.query,
+            limit: request.limit.unwrap_or(10),
+            tags: None,
+            context: request.search_type.unwrap_or_else(|| "medium".to_string()),
+            quiet: false,
+            group_by,
+        };
+        search_service.search_content(options).await
+    })
+    .await;
 
-    let mut router = Router::new()
-        // Public endpoints (no authentication required)
-        .route("/health", get(health_check))
-        .route("/api/v1/health-check", get(health_check_detailed))
-        .route(
-            "/webhooks/github/:repository_id",
-            post(handle_github_webhook),
-        )
-        // Merge authenticated routes
-        .merge(authenticated_routes)
-        // Merge internal routes
-        .merge(internal_routes);
+    match result {
+        Ok(search_result) => {
+            let format = request.format.unwrap_or_else(|| "rich".to_string());
+            let response_value = render_search_code_response(&search_result, &format)
+                .map_err(|e| handle_service_error(anyhow::anyhow!(e), "search_code"))?;
+            Ok(Json(response_value))
+        }
+        Err(e) => Err(handle_service_error(e, "search_code")),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct V1SearchSymbolsBody {
+    pub pattern: String,
+    pub limit: Option<usize>,
+    pub symbol_type: Option<String>,
+    pub format: Option<String>,
+    pub repository_id: Option<String>,
+    pub timeout_ms: Option<u64>,
+}
+
+async fn search_symbols_v1_post(
+    State(state): State<ServicesAppState>,
+    auth_context: Option<Extension<AuthContext>>,
+    request_result: Result<Json<V1SearchSymbolsBody>, axum::extract::rejection::JsonRejection>,
+) -> ApiResult<serde_json::Value> {
+    let Json(body) =
+        request_result.map_err(|e| handle_json_parsing_error(e, "v1 search-symbols"))?;
+    if body.pattern.trim().is_empty() {
+        return Err(handle_validation_error(
+            "pattern",
+            "Pattern cannot be empty",
+            "search-symbols",
+        ));
+    }
+
+    let annotations_dir = annotations_dir(&state, auth_context.as_ref())?;
+    let scope = repository_scope(
+        &state,
+        auth_context.as_ref(),
+        body.repository_id.as_deref(),
+        "search-symbols",
+    )
+    .await?;
+    let result = with_trace_id("api_v1_search_symbols", async move {
+        let database = Database {
+            storage: state.storage.clone(),
+            primary_index: state.primary_index.clone(),
+            trigram_index: state.trigram_index.clone(),
+            path_cache: state.path_cache.clone(),
+        };
+        let mut search_service = SearchService::new(&database, state.db_path.clone())
+            .with_symbol_reader(state.symbol_reader.clone())
+            .with_redaction(redaction_rules(auth_context.as_ref()))
+            .with_annotations_dir(annotations_dir)
+            .with_deadline(QueryDeadline::for_server_request(body.timeout_ms));
+        if let Some(scope) = scope {
+            search_service = search_service.with_repository_scope(scope);
+        }
+        let options = SymbolSearchOptions {
+            pattern: body.pattern,
+            limit: body.limit.unwrap_or(25),
+            symbol_type: body.symbol_type,
+            quiet: false,
+        };
+        search_service.search_symbols(options).await
+    })
+    .await;
 
-    #[cfg(feature = "mcp-server")]
-    {
-        router = router.merge(authenticated_mcp_routes);
+    match result {
+        Ok(symbol_result) => {
+            let format = body.format.unwrap_or_else(|| "rich".to_string());
+            let response_value = render_symbol_search_response(&symbol_result, &format)
+                .map_err(|e| handle_service_error(anyhow::anyhow!(e), "symbol_search"))?;
+            Ok(Json(response_value))
+        }
+        Err(e) => Err(handle_service_error(e, "symbol_search")),
     }
+}
 
-    Ok(router.with_state(state).layer(
-        ServiceBuilder::new()
-            .layer(TraceLayer::new_for_http())
-            .layer(CorsLayer::permissive()),
-    ))
+#[derive(Debug, Deserialize)]
+struct FusedSearchQuery {
+    pub query: String,
+    pub limit: Option<usize>,
+    pub repository_id: Option<String>,
+    pub timeout_ms: Option<u64>,
 }
 
-/// Start services server with SaaS capabilities (API key authentication)
-pub async fn start_services_saas_server(
-    storage: Arc<tokio::sync::Mutex<dyn Storage>>,
-    primary_index: Arc<tokio::sync::Mutex<dyn Index>>,
-    trigram_index: Arc<tokio::sync::Mutex<dyn Index>>,
-    db_path: PathBuf,
-    api_key_config: crate::ApiKeyConfig,
-    port: u16,
-) -> Result<()> {
-    let app = create_services_saas_server(
-        storage,
-        primary_index,
-        trigram_index,
-        db_path.clone(),
-        api_key_config,
+/// GET /api/v1/search - trigram, symbol and vector results fused by reciprocal rank
+async fn fused_search_v1(
+    State(state): State<ServicesAppState>,
+    auth_context: Option<Extension<AuthContext>>,
+    AxumQuery(q): AxumQuery<FusedSearchQuery>,
+) -> ApiResult<serde_json::Value> {
+    if q.query.trim().is_empty() {
+        return Err(handle_validation_error(
+            "query",
+            "Query cannot be empty",
+            "search",
+        ));
+    }
+
+    let annotations_dir = annotations_dir(&state, auth_context.as_ref())?;
+    let scope = repository_scope(
+        &state,
+        auth_context.as_ref(),
+        q.repository_id.as_deref(),
+        "search",
     )
     .await?;
+    let (query, corpus) = CorpusFilter::from_query(&state.db_path, &q.query)
+        .map_err(|e| handle_validation_error("query", &e.to_string(), "search"))?;
+    let result = with_trace_id("api_v1_fused_search", async move {
+        let database = Database {
+            storage: state.storage.clone(),
+            primary_index: state.primary_index.clone(),
+            trigram_index: state.trigram_index.clone(),
+            path_cache: state.path_cache.clone(),
+        };
+        let mut search_service = SearchService::new(&database, state.db_path.clone())
+            .with_symbol_reader(state.symbol_reader.clone())
+            .with_redaction(redaction_rules(auth_context.as_ref()))
+            .with_annotations_dir(annotations_dir)
+            .with_deadline(QueryDeadline::for_server_request(q.timeout_ms));
+        if let Some(scope) = scope {
+            search_service = search_service.with_repository_scope(scope);
+        }
+        if let Some(corpus) = corpus {
+            search_service = search_service.with_corpus_filter(corpus);
+        }
+        if let Some(engine) = semantic_engine(&state).await {
+            search_service = search_service.with_semantic_engine(engine);
+        }
+        search_service
+            .search_fused(FusedSearchOptions {
+                query,
+                limit: q.limit.unwrap_or(10).min(100),
+                quiet: false,
+            })
+            .await
+    })
+    .await;
 
-    // Try to bind to the port with enhanced error handling
-    let listener = match TcpListener::bind(&format!("0.0.0.0:{port}")).await {
-        Ok(listener) => listener,
-        Err(e) => {
-            error!("Failed to start server on port {}: {}", port, e);
+    match result {
+        Ok(fused) => {
+            Ok(Json(serde_json::to_value(fused).map_err(|e| {
+                handle_service_error(anyhow::anyhow!(e), "search")
+            })?))
+        }
+        Err(e) => Err(handle_service_error(e, "search")),
+    }
+}
 
-            if e.kind() == std::io::ErrorKind::AddrInUse {
-                error!("Port {} is already in use. Try these alternatives:", port);
-                #[cfg(target_os = "macos")]
-                {
-                    error!("   - Find process using port: sudo lsof -i :{}", port);
-                    error!("   - Kill process using port: sudo kill -9 <PID>");
-                }
-                #[cfg(target_os = "linux")]
-                {
-                    error!(
-                        "   - Find process using port: sudo netstat -tulpn | grep :{}",
-                        port
-                    );
-                    error!("   - Kill process using port: sudo kill -9 <PID>");
-                }
-                #[cfg(target_os = "windows")]
-                {
-                    error!("   - Check port usage: netstat -ano | findstr :{}", port);
-                    error!("   - Kill process using port: taskkill /PID <PID> /F");
-                }
-            }
+/// GET /api/v1/symbols/:symbol/callers
+async fn find_callers_v1_get(
+    State(state): State<ServicesAppState>,
+    auth_context: Option<Extension<AuthContext>>,
+    axum::extract::Path(symbol): axum::extract::Path<String>,
+    AxumQuery(q): AxumQuery<CallersQuery>,
+) -> ApiResult<serde_json::Value> {
+    if symbol.trim().is_empty() {
+        return Err(handle_validation_error(
+            "symbol",
+            "Symbol name cannot be empty",
+            "symbols/:symbol/callers",
+        ));
+    }
 
-            return Err(e).context(format!(
-                "Failed to bind to port {}. Port may be in use or insufficient permissions",
-                port
-            ));
+    let paging = parse_relationship_paging(
+        q.offset,
+        q.cursor.as_deref(),
+        q.sort.as_deref(),
+        q.group_by.as_deref(),
+        "symbols/:symbol/callers",
+    )?;
+
+    let annotations_dir = annotations_dir(&state, auth_context.as_ref())?;
+    let scope = repository_scope(
+        &state,
+        auth_context.as_ref(),
+        q.repository_id.as_deref(),
+        "symbols/:symbol/callers",
+    )
+    .await?;
+    let result = with_trace_id("api_v1_find_callers", async move {
+        let database = Database {
+            storage: state.storage.clone(),
+            primary_index: state.primary_index.clone(),
+            trigram_index: state.trigram_index.clone(),
+            path_cache: state.path_cache.clone(),
+        };
+        let mut analysis_service = AnalysisService::new(&database, state.db_path.clone())
+            .with_redaction(redaction_rules(auth_context.as_ref()))
+            .with_annotations_dir(annotations_dir)
+            .with_deadline(QueryDeadline::for_server_request(q.timeout_ms));
+        if let Some(engine) = relationship_engine(&state).await {
+            analysis_service = analysis_service.with_relationship_engine(engine);
         }
-    };
+        if let Some(scope) = scope {
+            analysis_service = analysis_service.with_repository_scope(scope);
+        }
+        let options = CallersOptions {
+            target: symbol,
+            limit: q.limit,
+            offset: paging.offset,
+            sort_by: paging.sort_by,
+            group_by: paging.group_by,
+            quiet: false,
+            explain: q.explain.unwrap_or(false),
+            context_lines: q.context_lines,
+        };
+        analysis_service.find_callers(options).await
+    })
+    .await;
 
-    info!("KotaDB Services SaaS Server listening on port {}", port);
-    debug!("API key authentication enabled");
-    debug!("Clean services architecture with SaaS capabilities");
-    debug!("Available endpoints:");
-    debug!("   GET    /health                    - Server health check (public)");
-    debug!("   GET    /api/v1/health-check       - Detailed health check (public)");
-    debug!("   🔐 Authenticated endpoints (require API key):");
-    debug!("   GET    /api/v1/analysis/stats     - Database statistics (StatsService)");
-    debug!("   POST   /api/v1/benchmark          - Performance benchmarks (BenchmarkService)");
-    debug!("   POST   /api/v1/validate           - Database validation (ValidationService)");
-    debug!("   POST   /api/v1/index-codebase     - Index repository (IndexingService)");
-    debug!("   GET    /api/v1/search/code        - Search code content (SearchService)");
-    debug!("   GET    /api/v1/search/symbols     - Search symbols (SearchService)");
-    debug!("   POST   /api/v1/find-callers       - Find callers (AnalysisService)");
-    debug!("   POST   /api/v1/analyze-impact     - Impact analysis (AnalysisService)");
-    debug!("   GET    /api/v1/codebase-overview  - Codebase overview (AnalysisService)");
-    debug!("   🔒 Internal endpoints (require internal API key):");
-    debug!("   POST   /internal/create-api-key   - Create new API key");
-    debug!("SaaS Server ready at http://localhost:{}", port);
-    debug!("Health check: curl http://localhost:{}/health", port);
-    debug!(
-        "Authenticated example: curl -H 'X-API-Key: your-key' http://localhost:{}/api/v1/analysis/stats",
-        port
-    );
+    match result {
+        Ok(callers_result) => {
+            Ok(Json(serde_json::to_value(callers_result).map_err(|e| {
+                handle_service_error(anyhow::anyhow!(e), "find_callers")
+            })?))
+        }
+        Err(e) => Err(handle_service_error(e, "find_callers")),
+    }
+}
 
-    axum::serve(
-        listener,
-        app.into_make_service_with_connect_info::<SocketAddr>(),
+/// GET /api/v1/symbols/:symbol/related - co-change, shared-caller and embedding recommendations
+async fn related_symbols_v1_get(
+    State(state): State<ServicesAppState>,
+    auth_context: Option<Extension<AuthContext>>,
+    axum::extract::Path(symbol): axum::extract::Path<String>,
+    AxumQuery(q): AxumQuery<RelatedQuery>,
+) -> ApiResult<serde_json::Value> {
+    if symbol.trim().is_empty() {
+        return Err(handle_validation_error(
+            "symbol",
+            "Symbol name cannot be empty",
+            "symbols/:symbol/related",
+        ));
+    }
+    if q.limit == Some(0) {
+        return Err(handle_validation_error(
+            "limit",
+            "limit must be at least 1",
+            "symbols/:symbol/related",
+        ));
+    }
+
+    let scope = repository_scope(
+        &state,
+        auth_context.as_ref(),
+        q.repository_id.as_deref(),
+        "symbols/:symbol/related",
     )
     .await?;
-    Ok(())
+    let result = with_trace_id("api_v1_related_symbols", async move {
+        let database = Database {
+            storage: state.storage.clone(),
+            primary_index: state.primary_index.clone(),
+            trigram_index: state.trigram_index.clone(),
+            path_cache: state.path_cache.clone(),
+        };
+        let mut analysis_service = AnalysisService::new(&database, state.db_path.clone())
+            .with_redaction(redaction_rules(auth_context.as_ref()));
+        if let Some(scope) = scope {
+            analysis_service = analysis_service.with_repository_scope(scope);
+        }
+        analysis_service
+            .find_related(RelatedOptions {
+                target: symbol,
+                limit: q.limit.unwrap_or(10).min(100),
+                quiet: false,
+            })
+            .await
+    })
+    .await;
+
+    match result {
+        Ok(related) => Ok(Json(serde_json::to_value(related).map_err(|e| {
+            handle_service_error(anyhow::anyhow!(e), "related_symbols")
+        })?)),
+        Err(e) => Err(handle_service_error(e, "related_symbols")),
+    }
 }
 
-/// Basic health check
-async fn health_check(State(state): State<ServicesAppState>) -> Json<HealthResponse> {
-    let mut response = HealthResponse {
-        status: "healthy".to_string(),
-        version: env!("CARGO_PKG_VERSION").to_string(),
-        services_enabled: vec![
-            "StatsService".to_string(),
-            "BenchmarkService".to_string(),
-            "ValidationService".to_string(),
-            "IndexingService".to_string(),
-            "SearchService".to_string(),
-            "AnalysisService".to_string(),
-        ],
-        saas: None,
-    };
+/// Deepest neighborhood drawn by the graph endpoint
+const MAX_GRAPH_DEPTH: usize = 4;
+/// Most symbols drawn by the graph endpoint
+const MAX_GRAPH_NODES: usize = 200;
 
-    if state.is_saas_mode() {
-        response.saas = Some(fetch_saas_health(&state).await);
+/// GET /api/v1/symbols/:symbol/graph.svg - picture of a symbol's dependency neighborhood
+async fn symbol_graph_svg_v1(
+    State(state): State<ServicesAppState>,
+    auth_context: Option<Extension<AuthContext>>,
+    axum::extract::Path(symbol): axum::extract::Path<String>,
+    AxumQuery(q): AxumQuery<SymbolGraphQuery>,
+) -> Result<axum::response::Response, (StatusCode, Json<StandardApiError>)> {
+    use axum::response::IntoResponse;
+    const ENDPOINT: &str = "symbols/:symbol/graph.svg";
+
+    if symbol.trim().is_empty() {
+        return Err(handle_validation_error(
+            "symbol",
+            "Symbol name cannot be empty",
+            ENDPOINT,
+        ));
+    }
+    let defaults = GraphOptions::default();
+    let depth = q.depth.unwrap_or(defaults.depth);
+    if !(1..=MAX_GRAPH_DEPTH).contains(&depth) {
+        return Err(handle_validation_error(
+            "depth",
+            &format!("depth must be between 1 and {}", MAX_GRAPH_DEPTH),
+            ENDPOINT,
+        ));
+    }
+    let max_nodes = q.max_nodes.unwrap_or(defaults.max_nodes);
+    if !(1..=MAX_GRAPH_NODES).contains(&max_nodes) {
+        return Err(handle_validation_error(
+            "max_nodes",
+            &format!("max_nodes must be between 1 and {}", MAX_GRAPH_NODES),
+            ENDPOINT,
+        ));
     }
 
-    Json(response)
+    let scope = repository_scope(
+        &state,
+        auth_context.as_ref(),
+        q.repository_id.as_deref(),
+        ENDPOINT,
+    )
+    .await?;
+    let result = with_trace_id("api_v1_symbol_graph", async move {
+        let database = Database {
+            storage: state.storage.clone(),
+            primary_index: state.primary_index.clone(),
+            trigram_index: state.trigram_index.clone(),
+            path_cache: state.path_cache.clone(),
+        };
+        let mut analysis_service = AnalysisService::new(&database, state.db_path.clone())
+            .with_redaction(redaction_rules(auth_context.as_ref()));
+        if let Some(scope) = scope {
+            analysis_service = analysis_service.with_repository_scope(scope);
+        }
+        analysis_service
+            .symbol_graph(GraphOptions {
+                target: symbol,
+                depth,
+                max_nodes,
+            })
+            .await
+    })
+    .await;
+
+    match result {
+        Ok(graph) => Ok((
+            [(axum::http::header::CONTENT_TYPE, "image/svg+xml")],
+            graph.to_svg(),
+        )
+            .into_response()),
+        Err(e) => Err(handle_service_error(e, "symbol_graph")),
+    }
 }
 
-/// Get database statistics via StatsService
-async fn get_stats(
+/// Deepest directory level the treemap endpoint expands
+const MAX_TREEMAP_DEPTH: usize = 32;
+
+/// GET /api/v1/overview/treemap - directory hierarchy with size, complexity and churn
+async fn treemap_v1(
     State(state): State<ServicesAppState>,
-    AxumQuery(params): AxumQuery<StatsQuery>,
-) -> Result<Json<serde_json::Value>, (StatusCode, Json<ErrorResponse>)> {
-    let result = with_trace_id("api_stats", async move {
-        // Create Database instance to implement DatabaseAccess
+    auth_context: Option<Extension<AuthContext>>,
+    AxumQuery(q): AxumQuery<TreemapQuery>,
+) -> ApiResult<serde_json::Value> {
+    const ENDPOINT: &str = "overview/treemap";
+
+    let defaults = TreemapOptions::default();
+    let depth = q.depth.unwrap_or(defaults.depth);
+    if !(1..=MAX_TREEMAP_DEPTH).contains(&depth) {
+        return Err(handle_validation_error(
+            "depth",
+            &format!("depth must be between 1 and {}", MAX_TREEMAP_DEPTH),
+            ENDPOINT,
+        ));
+    }
+    let mut path = q.path.unwrap_or_default();
+    if path.split('/').any(|component| component == "..") {
+        return Err(handle_validation_error(
+            "path",
+            "path must not contain '..'",
+            ENDPOINT,
+        ));
+    }
+    // A treemap scoped to one repository treats `path` as relative to its files
+    let scope = repository_scope(
+        &state,
+        auth_context.as_ref(),
+        q.repository_id.as_deref(),
+        ENDPOINT,
+    )
+    .await?;
+    if let Some(files_root) = scope.as_ref().and_then(RepositoryScope::files_root) {
+        path = format!("{}/{}", files_root, path.trim_matches('/'));
+    }
+
+    let result = with_trace_id("api_v1_treemap", async move {
         let database = Database {
             storage: state.storage.clone(),
             primary_index: state.primary_index.clone(),
             trigram_index: state.trigram_index.clone(),
-            path_cache: Arc::new(RwLock::new(HashMap::new())),
-        };
-
-        let stats_service = StatsService::new(&database, state.db_path.clone());
-
-        let options = StatsOptions {
-            basic: params.basic.unwrap_or(false),
-            symbols: params.symbols.unwrap_or(true),
-            relationships: params.relationships.unwrap_or(true),
-            detailed: false,
-            quiet: false,
+            path_cache: state.path_cache.clone(),
         };
-
-        stats_service.get_statistics(options).await
+        let mut analysis_service = AnalysisService::new(&database, state.db_path.clone())
+            .with_redaction(redaction_rules(auth_context.as_ref()));
+        if let Some(scope) = scope {
+            analysis_service = analysis_service.with_repository_scope(scope);
+        }
+        analysis_service
+            .generate_treemap(TreemapOptions { path, depth })
+            .await
     })
     .await;
 
     match result {
-        Ok(stats) => {
-            let json_value = serde_json::to_value(stats).map_err(|e| {
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(ErrorResponse {
-                        error: "serialization_failed".to_string(),
-                        message: e.to_string(),
-                    }),
-                )
-            })?;
-            Ok(Json(json_value))
-        }
-        Err(e) => {
-            tracing::warn!("Failed to get stats: {}", e);
-            Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse {
-                    error: "stats_failed".to_string(),
-                    message: e.to_string(),
-                }),
-            ))
+        Ok(treemap) => {
+            Ok(Json(serde_json::to_value(treemap).map_err(|e| {
+                handle_service_error(anyhow::anyhow!(e), "treemap")
+            })?))
         }
+        Err(e) => Err(handle_service_error(e, "treemap")),
     }
 }
 
-/// Run performance benchmarks via BenchmarkService
-async fn run_benchmark(
+/// Most indexing runs returned by the trends endpoint
+const MAX_TRENDS_LIMIT: usize = 1000;
+
+/// GET /api/v1/analysis/trends - metrics recorded after recent indexing runs
+async fn trends_v1(
     State(state): State<ServicesAppState>,
-    Json(request): Json<BenchmarkRequest>,
-) -> Result<Json<serde_json::Value>, (StatusCode, Json<ErrorResponse>)> {
-    let result = with_trace_id("api_benchmark", async move {
-        // Create Database instance to implement DatabaseAccess
-        let database = Database {
-            storage: state.storage.clone(),
-            primary_index: state.primary_index.clone(),
-            trigram_index: state.trigram_index.clone(),
-            path_cache: Arc::new(RwLock::new(HashMap::new())),
-        };
+    auth_context: Option<Extension<AuthContext>>,
+    AxumQuery(q): AxumQuery<TrendsQuery>,
+) -> ApiResult<serde_json::Value> {
+    if !redaction_rules(auth_context.as_ref()).is_empty() {
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(StandardApiError {
+                error_type: "redaction_unsupported".into(),
+                message: REDACTED_ENDPOINT_MESSAGE.into(),
+                details: Some("Endpoint: analysis/trends".into()),
+                suggestions: vec!["Use an API key without redaction rules".into()],
+                error_code: Some(403),
+            }),
+        ));
+    }
+    let limit = q.limit.unwrap_or(TrendsOptions::default().limit);
+    if !(1..=MAX_TRENDS_LIMIT).contains(&limit) {
+        return Err(handle_validation_error(
+            "limit",
+            &format!("limit must be between 1 and {}", MAX_TRENDS_LIMIT),
+            "analysis/trends",
+        ));
+    }
+    let scope = repository_scope(
+        &state,
+        auth_context.as_ref(),
+        q.repository_id.as_deref(),
+        "analysis/trends",
+    )
+    .await?;
 
-        let benchmark_service = BenchmarkService::new(&database, state.db_path.clone());
+    let database = Database {
+        storage: state.storage.clone(),
+        primary_index: state.primary_index.clone(),
+        trigram_index: state.trigram_index.clone(),
+        path_cache: state.path_cache.clone(),
+    };
+    let mut analysis_service = AnalysisService::new(&database, state.db_path.clone());
+    if let Some(scope) = scope {
+        analysis_service = analysis_service.with_repository_scope(scope);
+    }
+    match analysis_service.trends(TrendsOptions { limit }) {
+        Ok(trends) => {
+            Ok(Json(serde_json::to_value(trends).map_err(|e| {
+                handle_service_error(anyhow::anyhow!(e), "trends")
+            })?))
+        }
+        Err(e) => Err(handle_service_error(e, "trends")),
+    }
+}
 
-        let options = BenchmarkOptions {
-            operations: request.operations.unwrap_or(1000),
-            benchmark_type: request.benchmark_type.unwrap_or_else(|| "all".to_string()),
-            format: request.format.unwrap_or_else(|| "json".to_string()),
-            max_search_queries: 100,
-            quiet: false,
-            warm_up_operations: Some(100),
-            concurrent_operations: Some(1),
+/// Directory of the annotation store a request reads and writes
+///
+/// Local servers keep one store in the database directory. In SaaS mode each API
+/// key owner gets their own, so tenants never see or edit each other's annotations.
+fn annotations_dir(
+    state: &ServicesAppState,
+    auth_context: Option<&Extension<AuthContext>>,
+) -> Result<PathBuf, (StatusCode, Json<StandardApiError>)> {
+    if !state.is_saas_mode() {
+        return Ok(state.db_path.clone());
+    }
+    let Extension(auth) = auth_context
+        .ok_or_else(|| unauthorized_error("Authentication required for annotations"))?;
+    let owner = match &auth.user_id {
+        Some(user_id) => format!("user-{user_id}"),
+        None => format!("key-{}", auth.key_id),
+    };
+    Ok(AnnotationStore::tenant_dir(&state.db_path, &owner))
+}
+
+/// Repositories a search or analysis request is restricted to
+///
+/// SaaS callers only ever see repositories they own: a request naming one is scoped
+/// to it, any other id is reported as not found so other tenants' repository ids are
+/// not disclosed, and a request naming none is scoped to all of the caller's
+/// repositories. Local servers resolve ids from their repository registry and leave
+/// requests naming none unscoped.
+async fn repository_scope(
+    state: &ServicesAppState,
+    auth_context: Option<&Extension<AuthContext>>,
+    repository_id: Option<&str>,
+    endpoint: &str,
+) -> Result<Option<RepositoryScope>, (StatusCode, Json<StandardApiError>)> {
+    let repository_id = repository_id.map(str::trim).filter(|id| !id.is_empty());
+    let prefixes = if state.is_saas_mode() {
+        let (store, user_uuid) =
+            saas_store_and_user(state, auth_context.cloned(), "read indexed repositories")?;
+        match repository_id {
+            Some(repository_id) => {
+                let repo_uuid = Uuid::parse_str(repository_id).map_err(|_| {
+                    handle_validation_error(
+                        "repository_id",
+                        "Invalid repository id format",
+                        endpoint,
+                    )
+                })?;
+                match store.fetch_owned_repository(repo_uuid, user_uuid).await {
+                    Ok(Some(repository)) => {
+                        vec![document_prefix(&repository.git_url, &repository.settings)]
+                    }
+                    Ok(None) => {
+                        return Err(handle_not_found_error(
+                            "repository_id",
+                            "Repository not found",
+                            endpoint,
+                        ))
+                    }
+                    Err(e) => {
+                        error!("Failed to look up repository {}: {}", repo_uuid, e);
+                        return Err(internal_server_error("Failed to look up repository"));
+                    }
+                }
+            }
+            None => match store.account_repositories(user_uuid).await {
+                Ok(repositories) => repositories
+                    .iter()
+                    .map(|repo| document_prefix(&repo.git_url, &repo.settings))
+                    .collect::<std::collections::BTreeSet<_>>()
+                    .into_iter()
+                    .collect(),
+                Err(e) => {
+                    error!("Failed to list repositories of {}: {}", user_uuid, e);
+                    return Err(internal_server_error("Failed to look up repositories"));
+                }
+            },
+        }
+    } else {
+        let Some(repository_id) = repository_id else {
+            return Ok(None);
         };
+        let repositories = state.repositories.read().await;
+        match repositories
+            .iter()
+            .find(|record| record.id == repository_id)
+        {
+            Some(record) => vec![local_document_prefix(&record.name)],
+            None => {
+                return Err(handle_not_found_error(
+                    "repository_id",
+                    "Repository not found",
+                    endpoint,
+                ))
+            }
+        }
+    };
+    state
+        .repository_scopes
+        .scope(
+            &state.storage,
+            state.data_generation.load(Ordering::SeqCst),
+            &prefixes,
+        )
+        .await
+        .map(Some)
+        .map_err(|e| handle_service_error(e, "repository_scope"))
+}
 
-        benchmark_service.run_benchmark(options).await
-    })
-    .await;
+/// Paths hidden from the API key a request was made with
+fn redaction_rules(auth_context: Option<&Extension<AuthContext>>) -> RedactionRules {
+    auth_context
+        .map(|Extension(auth)| auth.redaction.clone())
+        .unwrap_or_default()
+}
 
-    match result {
-        Ok(benchmark_result) => {
-            let json_value = serde_json::to_value(benchmark_result).map_err(|e| {
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(ErrorResponse {
-                        error: "serialization_failed".to_string(),
-                        message: e.to_string(),
-                    }),
-                )
-            })?;
-            Ok(Json(json_value))
-        }
-        Err(e) => {
-            tracing::warn!("Failed to run benchmark: {}", e);
-            Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
+/// Why endpoints aggregating the whole database refuse API keys with redaction rules
+const REDACTED_ENDPOINT_MESSAGE: &str =
+    "This endpoint reports on the whole database and is unavailable to API keys with redaction rules";
+
+/// Refusal of the stats, overview and trends endpoints for keys that hide some paths
+fn redaction_unsupported(
+    auth_context: Option<&Extension<AuthContext>>,
+) -> Result<(), (StatusCode, Json<ErrorResponse>)> {
+    if redaction_rules(auth_context).is_empty() {
+        return Ok(());
+    }
+    Err((
+        StatusCode::FORBIDDEN,
+        Json(ErrorResponse {
+            error: "redaction_unsupported".to_string(),
+            message: REDACTED_ENDPOINT_MESSAGE.to_string(),
+        }),
+    ))
+}
+
+/// [`repository_scope`] for the stats and overview endpoints, which report errors as
+/// [`ErrorResponse`]
+async fn legacy_repository_scope(
+    state: &ServicesAppState,
+    auth_context: Option<&Extension<AuthContext>>,
+    repository_id: Option<&str>,
+    endpoint: &str,
+) -> Result<Option<RepositoryScope>, (StatusCode, Json<ErrorResponse>)> {
+    repository_scope(state, auth_context, repository_id, endpoint)
+        .await
+        .map_err(|(status, Json(error))| {
+            (
+                status,
                 Json(ErrorResponse {
-                    error: "benchmark_failed".to_string(),
-                    message: e.to_string(),
+                    error: error.error_type,
+                    message: error.message,
                 }),
-            ))
-        }
-    }
+            )
+        })
 }
 
-// ================================================================================================
-// v1 ROUTE HANDLERS - Thin wrappers mapping to existing services
-// ================================================================================================
+/// PUT /api/v1/annotations request body
+#[derive(Debug, Deserialize)]
+pub struct PutAnnotationBody {
+    pub kind: AnnotationKind,
+    pub target: String,
+    pub summary: String,
+    pub source: Option<String>,
+}
 
+/// GET/DELETE /api/v1/annotations query: one target, or a search over summaries
 #[derive(Debug, Deserialize)]
-struct V1SearchCodeBody {
-    pub query: String,
+pub struct AnnotationQuery {
+    pub kind: Option<AnnotationKind>,
+    pub target: Option<String>,
+    pub q: Option<String>,
     pub limit: Option<usize>,
-    pub format: Option<String>,
 }
 
-async fn search_code_v1_post(
+/// PUT /api/v1/annotations - store the summary of a file or symbol
+async fn put_annotation_v1(
     State(state): State<ServicesAppState>,
-    request_result: Result<Json<V1SearchCodeBody>, axum::extract::rejection::JsonRejection>,
+    auth_context: Option<Extension<AuthContext>>,
+    request_result: Result<Json<PutAnnotationBody>, axum::extract::rejection::JsonRejection>,
 ) -> ApiResult<serde_json::Value> {
-    let Json(body) = request_result.map_err(|e| handle_json_parsing_error(e, "v1 search-code"))?;
-    let query = body.query.clone();
-    let limit = body.limit;
-    let format = body.format.clone();
-    // Reuse search_code_enhanced by constructing AxumQuery<SearchRequest>
-    let request = SearchRequest {
-        query,
-        limit,
-        search_type: Some("medium".to_string()),
-        format,
+    let Json(body) = request_result.map_err(|e| handle_json_parsing_error(e, "v1 annotations"))?;
+
+    let dir = annotations_dir(&state, auth_context.as_ref())?;
+    let _writes = state.annotation_writes.lock().await;
+    let mut store =
+        AnnotationStore::load(&dir).map_err(|e| handle_service_error(e, "annotations"))?;
+    let (annotation, created) = store
+        .put(
+            body.kind,
+            &body.target,
+            &body.summary,
+            body.source.as_deref(),
+        )
+        .map_err(|e| handle_validation_error("annotation", &e.to_string(), "annotations"))?;
+    store
+        .save(&dir)
+        .map_err(|e| handle_service_error(e, "annotations"))?;
+
+    Ok(Json(serde_json::json!({
+        "annotation": annotation,
+        "created": created,
+    })))
+}
+
+/// GET /api/v1/annotations - one target's annotation, a search, or all annotations
+async fn list_annotations_v1(
+    State(state): State<ServicesAppState>,
+    auth_context: Option<Extension<AuthContext>>,
+    AxumQuery(q): AxumQuery<AnnotationQuery>,
+) -> ApiResult<serde_json::Value> {
+    let dir = annotations_dir(&state, auth_context.as_ref())?;
+    let store = AnnotationStore::load(&dir).map_err(|e| handle_service_error(e, "annotations"))?;
+
+    let annotations: Vec<&Annotation> = match (&q.target, &q.q) {
+        (Some(target), _) => {
+            let kind = q.kind.ok_or_else(|| {
+                handle_validation_error(
+                    "kind",
+                    "kind ('file' or 'symbol') is required with target",
+                    "annotations",
+                )
+            })?;
+            let annotation = store.get(kind, target).ok_or_else(|| {
+                handle_not_found_error("target", "No annotation stored", "annotations")
+            })?;
+            vec![annotation]
+        }
+        (None, Some(query)) => store
+            .search(
+                query,
+                q.limit.unwrap_or(MAX_ANNOTATIONS_PER_SEARCH).min(100),
+            )
+            .into_iter()
+            .filter(|annotation| q.kind.is_none_or(|kind| annotation.kind == kind))
+            .collect(),
+        (None, None) => store
+            .annotations
+            .iter()
+            .filter(|annotation| q.kind.is_none_or(|kind| annotation.kind == kind))
+            .take(q.limit.unwrap_or(usize::MAX))
+            .collect(),
     };
 
-    // Inline the logic of search_code_enhanced to avoid duplicate parsing
-    if request.query.trim().is_empty() {
+    Ok(Json(serde_json::json!({
+        "total_count": annotations.len(),
+        "annotations": annotations,
+    })))
+}
+
+/// DELETE /api/v1/annotations - drop the annotation of one file or symbol
+async fn delete_annotation_v1(
+    State(state): State<ServicesAppState>,
+    auth_context: Option<Extension<AuthContext>>,
+    AxumQuery(q): AxumQuery<AnnotationQuery>,
+) -> ApiResult<serde_json::Value> {
+    let (Some(kind), Some(target)) = (q.kind, q.target) else {
         return Err(handle_validation_error(
-            "query",
-            "Query cannot be empty",
-            "search-code",
+            "target",
+            "kind and target are required",
+            "annotations",
         ));
-    }
-
-    let result = with_trace_id("api_v1_search_code", async move {
-        let database = Database {
-            storage: state.storage.clone(),
-            primary_index: state.primary_index.clone(),
-            trigram_index: state.trigram_index.clone(),
-            path_cache: Arc::new(RwLock::new(HashMap::new())),
-        };
-        let search_service = SearchService::new(&database, state.db_path.clone());
-        let options = SearchOptions {
-            query: request.query,
-            limit: request.limit.unwrap_or(10),
-            tags: None,
-            context: request.search_type.unwrap_or_else(|| "medium".to_string()),
-            quiet: false,
-        };
-        search_service.search_content(options).await
-    })
-    .await;
+    };
 
-    match result {
-        Ok(search_result) => {
-            let format = request.format.unwrap_or_else(|| "rich".to_string());
-            let response_value = render_search_code_response(&search_result, &format)
-                .map_err(|e| handle_service_error(anyhow::anyhow!(e), "search_code"))?;
-            Ok(Json(response_value))
-        }
-        Err(e) => Err(handle_service_error(e, "search_code")),
+    let dir = annotations_dir(&state, auth_context.as_ref())?;
+    let _writes = state.annotation_writes.lock().await;
+    let mut store =
+        AnnotationStore::load(&dir).map_err(|e| handle_service_error(e, "annotations"))?;
+    let removed = store
+        .remove(kind, &target)
+        .map_err(|e| handle_validation_error("target", &e.to_string(), "annotations"))?;
+    if !removed {
+        return Err(handle_not_found_error(
+            "target",
+            "No annotation stored",
+            "annotations",
+        ));
     }
+    store
+        .save(&dir)
+        .map_err(|e| handle_service_error(e, "annotations"))?;
+
+    Ok(Json(serde_json::json!({ "removed": true })))
 }
 
+/// GET /api/v1/notes query
 #[derive(Debug, Deserialize)]
-struct V1SearchSymbolsBody {
-    pub pattern: String,
+pub struct NotesQuery {
+    pub kind: Option<AnnotationKind>,
+    pub target: Option<String>,
+    pub tag: Option<String>,
+    pub bookmark: Option<bool>,
     pub limit: Option<usize>,
-    pub symbol_type: Option<String>,
-    pub format: Option<String>,
 }
 
-async fn search_symbols_v1_post(
+/// POST /api/v1/notes - leave a note, tags or a bookmark on a file, lines or a symbol
+async fn create_note_v1(
     State(state): State<ServicesAppState>,
-    request_result: Result<Json<V1SearchSymbolsBody>, axum::extract::rejection::JsonRejection>,
+    auth_context: Option<Extension<AuthContext>>,
+    request_result: Result<Json<NewNote>, axum::extract::rejection::JsonRejection>,
 ) -> ApiResult<serde_json::Value> {
-    let Json(body) =
-        request_result.map_err(|e| handle_json_parsing_error(e, "v1 search-symbols"))?;
-    if body.pattern.trim().is_empty() {
-        return Err(handle_validation_error(
-            "pattern",
-            "Pattern cannot be empty",
-            "search-symbols",
+    let Json(body) = request_result.map_err(|e| handle_json_parsing_error(e, "v1 notes"))?;
+
+    let dir = annotations_dir(&state, auth_context.as_ref())?;
+    let _writes = state.annotation_writes.lock().await;
+    let mut store = AnnotationStore::load(&dir).map_err(|e| handle_service_error(e, "notes"))?;
+    let note = store
+        .add_note(body)
+        .map_err(|e| handle_validation_error("note", &e.to_string(), "notes"))?;
+    store
+        .save(&dir)
+        .map_err(|e| handle_service_error(e, "notes"))?;
+
+    Ok(Json(serde_json::json!({ "note": note })))
+}
+
+/// GET /api/v1/notes - notes filtered by target, tag and bookmark
+async fn list_notes_v1(
+    State(state): State<ServicesAppState>,
+    auth_context: Option<Extension<AuthContext>>,
+    AxumQuery(q): AxumQuery<NotesQuery>,
+) -> ApiResult<serde_json::Value> {
+    let dir = annotations_dir(&state, auth_context.as_ref())?;
+    let store = AnnotationStore::load(&dir).map_err(|e| handle_service_error(e, "notes"))?;
+    let filter = NoteFilter {
+        kind: q.kind,
+        target: q.target,
+        tag: q.tag,
+        bookmark: q.bookmark,
+    };
+    let notes: Vec<&Note> = store
+        .notes(&filter)
+        .into_iter()
+        .take(q.limit.unwrap_or(usize::MAX))
+        .collect();
+
+    Ok(Json(serde_json::json!({
+        "total_count": notes.len(),
+        "notes": notes,
+    })))
+}
+
+/// PATCH /api/v1/notes/:id - edit the text, tags or bookmark of a note
+async fn update_note_v1(
+    State(state): State<ServicesAppState>,
+    auth_context: Option<Extension<AuthContext>>,
+    axum::extract::Path(id): axum::extract::Path<String>,
+    request_result: Result<Json<NoteUpdate>, axum::extract::rejection::JsonRejection>,
+) -> ApiResult<serde_json::Value> {
+    let Json(body) = request_result.map_err(|e| handle_json_parsing_error(e, "v1 notes"))?;
+
+    let dir = annotations_dir(&state, auth_context.as_ref())?;
+    let _writes = state.annotation_writes.lock().await;
+    let mut store = AnnotationStore::load(&dir).map_err(|e| handle_service_error(e, "notes"))?;
+    let note = store
+        .update_note(&id, body)
+        .map_err(|e| handle_validation_error("note", &e.to_string(), "notes"))?
+        .ok_or_else(|| handle_not_found_error("id", "No note with this id", "notes"))?;
+    store
+        .save(&dir)
+        .map_err(|e| handle_service_error(e, "notes"))?;
+
+    Ok(Json(serde_json::json!({ "note": note })))
+}
+
+/// DELETE /api/v1/notes/:id
+async fn delete_note_v1(
+    State(state): State<ServicesAppState>,
+    auth_context: Option<Extension<AuthContext>>,
+    axum::extract::Path(id): axum::extract::Path<String>,
+) -> ApiResult<serde_json::Value> {
+    let dir = annotations_dir(&state, auth_context.as_ref())?;
+    let _writes = state.annotation_writes.lock().await;
+    let mut store = AnnotationStore::load(&dir).map_err(|e| handle_service_error(e, "notes"))?;
+    if !store.remove_note(&id) {
+        return Err(handle_not_found_error(
+            "id",
+            "No note with this id",
+            "notes",
         ));
     }
+    store
+        .save(&dir)
+        .map_err(|e| handle_service_error(e, "notes"))?;
 
-    let result = with_trace_id("api_v1_search_symbols", async move {
-        let database = Database {
-            storage: state.storage.clone(),
-            primary_index: state.primary_index.clone(),
-            trigram_index: state.trigram_index.clone(),
-            path_cache: Arc::new(RwLock::new(HashMap::new())),
-        };
-        let search_service = SearchService::new(&database, state.db_path.clone());
-        let options = SymbolSearchOptions {
-            pattern: body.pattern,
-            limit: body.limit.unwrap_or(25),
-            symbol_type: body.symbol_type,
-            quiet: false,
-        };
-        search_service.search_symbols(options).await
-    })
-    .await;
+    Ok(Json(serde_json::json!({ "removed": true })))
+}
 
-    match result {
-        Ok(symbol_result) => {
-            let format = body.format.unwrap_or_else(|| "rich".to_string());
-            let response_value = render_symbol_search_response(&symbol_result, &format)
-                .map_err(|e| handle_service_error(anyhow::anyhow!(e), "symbol_search"))?;
-            Ok(Json(response_value))
-        }
-        Err(e) => Err(handle_service_error(e, "symbol_search")),
-    }
+/// PUT /api/v1/corpora/:name request body
+#[derive(Debug, Deserialize)]
+pub struct PutCorpusBody {
+    pub description: Option<String>,
+    #[serde(default)]
+    pub fields: Vec<CorpusField>,
 }
 
-/// GET /api/v1/symbols/:symbol/callers
-async fn find_callers_v1_get(
+/// POST /api/v1/corpora/:name/documents request body
+#[derive(Debug, Deserialize)]
+pub struct LoadCorpusDocumentsBody {
+    pub documents: Vec<CorpusDocument>,
+}
+
+/// GET /api/v1/corpora - schemas of the defined corpora
+async fn list_corpora_v1(State(state): State<ServicesAppState>) -> ApiResult<serde_json::Value> {
+    let registry =
+        CorpusRegistry::load(&state.db_path).map_err(|e| handle_service_error(e, "corpora"))?;
+    Ok(Json(serde_json::json!({ "corpora": registry.corpora })))
+}
+
+/// PUT /api/v1/corpora/:name - define a corpus or replace its schema
+async fn put_corpus_v1(
     State(state): State<ServicesAppState>,
-    axum::extract::Path(symbol): axum::extract::Path<String>,
-    AxumQuery(q): AxumQuery<CallersQuery>,
+    axum::extract::Path(name): axum::extract::Path<String>,
+    request_result: Result<Json<PutCorpusBody>, axum::extract::rejection::JsonRejection>,
 ) -> ApiResult<serde_json::Value> {
-    if symbol.trim().is_empty() {
-        return Err(handle_validation_error(
-            "symbol",
-            "Symbol name cannot be empty",
-            "symbols/:symbol/callers",
-        ));
-    }
+    let Json(body) = request_result.map_err(|e| handle_json_parsing_error(e, "v1 corpora"))?;
+
+    let _writes = state.corpus_writes.lock().await;
+    let mut registry =
+        CorpusRegistry::load(&state.db_path).map_err(|e| handle_service_error(e, "corpora"))?;
+    let schema = CorpusSchema {
+        name,
+        description: body.description,
+        fields: body.fields,
+        updated_at: Utc::now(),
+    };
+    let created = registry
+        .upsert(schema.clone())
+        .map_err(|e| handle_validation_error("corpus", &e.to_string(), "corpora"))?;
+    registry
+        .save(&state.db_path)
+        .map_err(|e| handle_service_error(e, "corpora"))?;
+
+    Ok(Json(serde_json::json!({
+        "corpus": schema,
+        "created": created,
+    })))
+}
 
-    let result = with_trace_id("api_v1_find_callers", async move {
-        let database = Database {
-            storage: state.storage.clone(),
-            primary_index: state.primary_index.clone(),
-            trigram_index: state.trigram_index.clone(),
-            path_cache: Arc::new(RwLock::new(HashMap::new())),
-        };
-        let mut analysis_service = AnalysisService::new(&database, state.db_path.clone());
-        let options = CallersOptions {
-            target: symbol,
-            limit: q.limit,
-            quiet: false,
-        };
-        analysis_service.find_callers(options).await
-    })
-    .await;
+/// POST /api/v1/corpora/:name/documents - load documents into a corpus
+///
+/// The whole request is rejected when any document doesn't match the corpus schema.
+async fn load_corpus_documents_v1(
+    State(state): State<ServicesAppState>,
+    axum::extract::Path(name): axum::extract::Path<String>,
+    request_result: Result<Json<LoadCorpusDocumentsBody>, axum::extract::rejection::JsonRejection>,
+) -> ApiResult<serde_json::Value> {
+    let Json(body) =
+        request_result.map_err(|e| handle_json_parsing_error(e, "v1 corpora/documents"))?;
+
+    let _writes = state.corpus_writes.lock().await;
+    let registry = CorpusRegistry::load(&state.db_path)
+        .map_err(|e| handle_service_error(e, "corpora/documents"))?;
+    let schema = registry.get(&name).ok_or_else(|| {
+        handle_not_found_error("name", "No corpus with this name", "corpora/documents")
+    })?;
+    schema
+        .validate_batch(&body.documents)
+        .map_err(|e| handle_validation_error("documents", &e.to_string(), "corpora/documents"))?;
 
-    match result {
-        Ok(callers_result) => {
-            Ok(Json(serde_json::to_value(callers_result).map_err(|e| {
-                handle_service_error(anyhow::anyhow!(e), "find_callers")
-            })?))
-        }
-        Err(e) => Err(handle_service_error(e, "find_callers")),
-    }
+    let database = Database {
+        storage: state.storage.clone(),
+        primary_index: state.primary_index.clone(),
+        trigram_index: state.trigram_index.clone(),
+        path_cache: state.path_cache.clone(),
+    };
+    let result = load_documents(&database, schema, &body.documents)
+        .await
+        .map_err(|e| handle_service_error(e, "corpora/documents"))?;
+    state.bump_data_generation();
+
+    Ok(Json(serde_json::to_value(result).map_err(|e| {
+        handle_service_error(anyhow::anyhow!(e), "corpora/documents")
+    })?))
 }
 
 /// GET /api/v1/symbols/:symbol/impact
 async fn analyze_impact_v1_get(
     State(state): State<ServicesAppState>,
+    auth_context: Option<Extension<AuthContext>>,
     axum::extract::Path(symbol): axum::extract::Path<String>,
     AxumQuery(q): AxumQuery<ImpactQuery>,
 ) -> ApiResult<serde_json::Value> {
@@ -1221,18 +4281,48 @@ async fn analyze_impact_v1_get(
         ));
     }
 
+    let paging = parse_relationship_paging(
+        q.offset,
+        q.cursor.as_deref(),
+        q.sort.as_deref(),
+        q.group_by.as_deref(),
+        "symbols/:symbol/impact",
+    )?;
+
+    let annotations_dir = annotations_dir(&state, auth_context.as_ref())?;
+    let scope = repository_scope(
+        &state,
+        auth_context.as_ref(),
+        q.repository_id.as_deref(),
+        "symbols/:symbol/impact",
+    )
+    .await?;
     let result = with_trace_id("api_v1_analyze_impact", async move {
         let database = Database {
             storage: state.storage.clone(),
             primary_index: state.primary_index.clone(),
             trigram_index: state.trigram_index.clone(),
-            path_cache: Arc::new(RwLock::new(HashMap::new())),
+            path_cache: state.path_cache.clone(),
         };
-        let mut analysis_service = AnalysisService::new(&database, state.db_path.clone());
+        let mut analysis_service = AnalysisService::new(&database, state.db_path.clone())
+            .with_redaction(redaction_rules(auth_context.as_ref()))
+            .with_annotations_dir(annotations_dir)
+            .with_deadline(QueryDeadline::for_server_request(q.timeout_ms));
+        if let Some(engine) = relationship_engine(&state).await {
+            analysis_service = analysis_service.with_relationship_engine(engine);
+        }
+        if let Some(scope) = scope {
+            analysis_service = analysis_service.with_repository_scope(scope);
+        }
         let options = ImpactOptions {
             target: symbol,
             limit: q.limit,
+            offset: paging.offset,
+            sort_by: paging.sort_by,
+            group_by: paging.group_by,
             quiet: false,
+            explain: q.explain.unwrap_or(false),
+            context_lines: q.context_lines,
         };
         analysis_service.analyze_impact(options).await
     })
@@ -1254,29 +4344,121 @@ struct ListSymbolsQuery {
     pattern: Option<String>,
     limit: Option<usize>,
     symbol_type: Option<String>,
+    repository_id: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
 struct CallersQuery {
     limit: Option<usize>,
+    offset: Option<usize>,
+    cursor: Option<String>,
+    sort: Option<String>,
+    group_by: Option<String>,
+    explain: Option<bool>,
+    context_lines: Option<usize>,
+    repository_id: Option<String>,
+    timeout_ms: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RelatedQuery {
+    limit: Option<usize>,
+    repository_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SymbolGraphQuery {
+    depth: Option<usize>,
+    max_nodes: Option<usize>,
+    repository_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TrendsQuery {
+    limit: Option<usize>,
+    repository_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TreemapQuery {
+    path: Option<String>,
+    depth: Option<usize>,
+    repository_id: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
 struct ImpactQuery {
     limit: Option<usize>,
+    offset: Option<usize>,
+    cursor: Option<String>,
+    sort: Option<String>,
+    group_by: Option<String>,
+    explain: Option<bool>,
+    context_lines: Option<usize>,
+    repository_id: Option<String>,
+    timeout_ms: Option<u64>,
+}
+
+/// Paging and ordering parameters shared by the v1 callers/impact endpoints
+struct RelationshipPaging {
+    offset: usize,
+    sort_by: RelationshipSortBy,
+    group_by: ResultGrouping,
+}
+
+fn parse_relationship_paging(
+    offset: Option<usize>,
+    cursor: Option<&str>,
+    sort: Option<&str>,
+    group_by: Option<&str>,
+    endpoint: &str,
+) -> Result<RelationshipPaging, (StatusCode, Json<StandardApiError>)> {
+    let offset = match cursor {
+        Some(cursor) => AnalysisService::parse_cursor(cursor)
+            .map_err(|e| handle_validation_error("cursor", &e.to_string(), endpoint))?,
+        None => offset.unwrap_or(0),
+    };
+    let sort_by = sort
+        .map(str::parse::<RelationshipSortBy>)
+        .transpose()
+        .map_err(|e| handle_validation_error("sort", &e.to_string(), endpoint))?
+        .unwrap_or_default();
+    let group_by = group_by
+        .map(str::parse::<ResultGrouping>)
+        .transpose()
+        .map_err(|e| handle_validation_error("group_by", &e.to_string(), endpoint))?
+        .unwrap_or_default();
+    Ok(RelationshipPaging {
+        offset,
+        sort_by,
+        group_by,
+    })
 }
 
 async fn list_symbols_v1(
     State(state): State<ServicesAppState>,
+    auth_context: Option<Extension<AuthContext>>,
     AxumQuery(q): AxumQuery<ListSymbolsQuery>,
 ) -> ApiResult<serde_json::Value> {
+    let scope = repository_scope(
+        &state,
+        auth_context.as_ref(),
+        q.repository_id.as_deref(),
+        "symbols",
+    )
+    .await?;
     let database = Database {
         storage: state.storage.clone(),
         primary_index: state.primary_index.clone(),
         trigram_index: state.trigram_index.clone(),
-        path_cache: Arc::new(RwLock::new(HashMap::new())),
+        path_cache: state.path_cache.clone(),
     };
-    let search_service = SearchService::new(&database, state.db_path.clone());
+    let mut search_service = SearchService::new(&database, state.db_path.clone())
+        .with_symbol_reader(state.symbol_reader.clone())
+        .with_redaction(redaction_rules(auth_context.as_ref()));
+    if let Some(scope) = scope {
+        search_service = search_service.with_repository_scope(scope);
+    }
     let options = SymbolSearchOptions {
         pattern: q.pattern.unwrap_or_else(|| "*".to_string()),
         limit: q.limit.unwrap_or(50),
@@ -1293,38 +4475,479 @@ async fn list_symbols_v1(
     }
 }
 
+#[derive(Debug, Deserialize)]
+struct ExportSymbolsQuery {
+    kind: Option<String>,
+    path: Option<String>,
+    name: Option<String>,
+    repository_id: Option<String>,
+}
+
+/// Symbols serialized per chunk of the export stream
+const SYMBOL_EXPORT_BATCH: usize = 1_000;
+
+/// GET /api/v1/symbols/export -> every symbol as NDJSON, filtered server-side
+async fn export_symbols_v1(
+    State(state): State<ServicesAppState>,
+    auth_context: Option<Extension<AuthContext>>,
+    AxumQuery(q): AxumQuery<ExportSymbolsQuery>,
+) -> Result<axum::response::Response, (StatusCode, Json<StandardApiError>)> {
+    use crate::symbol_export::{SymbolExportFilter, SymbolExporter};
+    use axum::response::IntoResponse;
+
+    let scope = repository_scope(
+        &state,
+        auth_context.as_ref(),
+        q.repository_id.as_deref(),
+        "symbols",
+    )
+    .await?;
+    let filter = SymbolExportFilter {
+        kind: q.kind,
+        path_prefix: q.path,
+        name: q.name,
+    };
+    let mut exporter = SymbolExporter::open(&state.db_path, filter).map_err(|e| {
+        (
+            StatusCode::NOT_FOUND,
+            Json(StandardApiError {
+                error_type: "symbols_db_missing".into(),
+                message: format!("{}", e),
+                details: Some("symbols.kota not found or unreadable".into()),
+                suggestions: vec!["Run indexing with symbol extraction enabled".into()],
+                error_code: Some(404),
+            }),
+        )
+    })?;
+    if let Some(scope) = scope {
+        exporter = exporter.with_repository_scope(scope);
+    }
+    exporter = exporter.with_redaction(redaction_rules(auth_context.as_ref()));
+    let total_symbols = exporter.total_symbols();
+
+    let body = futures::stream::unfold(Some(exporter), |exporter| async move {
+        let mut exporter = exporter?;
+        match exporter.next_ndjson_batch(SYMBOL_EXPORT_BATCH) {
+            Ok(batch) if batch.is_empty() => None,
+            Ok(batch) => Some((Ok(Bytes::from(batch)), Some(exporter))),
+            Err(e) => Some((Err(std::io::Error::other(e)), None)),
+        }
+    });
+    Ok((
+        StatusCode::OK,
+        [
+            (
+                axum::http::header::CONTENT_TYPE,
+                "application/x-ndjson".to_string(),
+            ),
+            (
+                axum::http::HeaderName::from_static("x-kotadb-total-symbols"),
+                total_symbols.to_string(),
+            ),
+        ],
+        axum::body::Body::from_stream(body),
+    )
+        .into_response())
+}
+
 /// GET /api/v1/files/*path -> symbols in file
 async fn file_symbols_v1(
     State(state): State<ServicesAppState>,
+    auth_context: Option<Extension<AuthContext>>,
     axum::extract::Path(path): axum::extract::Path<String>,
 ) -> ApiResult<serde_json::Value> {
+    // Redacted files report no symbols, like files that were never indexed
+    let redacted = redaction_rules(auth_context.as_ref()).is_redacted(&path);
     // Read binary symbols file and filter by file_path
     let symbol_db_path = state.db_path.join("symbols.kota");
     match crate::binary_symbols::BinarySymbolReader::open(&symbol_db_path) {
         Ok(reader) => {
             let mut entries = Vec::new();
-            for s in reader.read_symbols_for_file(&path) {
+            let symbols = if redacted {
+                Vec::new()
+            } else {
+                reader.read_symbols_for_file(&path)
+            };
+            for s in symbols {
                 let name = reader.get_symbol_name(&s).unwrap_or_default();
                 entries.push(serde_json::json!({
                     "name": name,
                     "kind": format!("{:?}", s.kind),
                     "start_line": s.start_line,
                     "end_line": s.end_line,
+                    "start_column": s.start_column,
+                    "end_column": s.end_column,
+                    "start_byte": s.start_byte,
+                    "end_byte": s.end_byte,
                 }));
             }
             Ok(Json(serde_json::json!({"file": path, "symbols": entries})))
         }
-        Err(e) => Err((
+        Err(e) => Err((
+            StatusCode::NOT_FOUND,
+            Json(StandardApiError {
+                error_type: "symbols_db_missing".into(),
+                message: format!("{}", e),
+                details: Some("symbols.kota not found or unreadable".into()),
+                suggestions: vec!["Run indexing with symbol extraction enabled".into()],
+                error_code: Some(404),
+            }),
+        )),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct FileContentQuery {
+    start_line: Option<usize>,
+    end_line: Option<usize>,
+}
+
+/// Decode stored document bytes as text, returning the text and the detected encoding
+///
+/// Handles UTF-8 (with or without BOM) and BOM-marked UTF-16. Returns `None` for
+/// content that looks binary. Invalid UTF-8 sequences are replaced rather than rejected.
+fn decode_document_text(bytes: &[u8]) -> Option<(String, &'static str)> {
+    let decode_utf16 = |bytes: &[u8], to_u16: fn([u8; 2]) -> u16| {
+        let (pairs, _) = bytes.as_chunks::<2>();
+        let units: Vec<u16> = pairs.iter().map(|pair| to_u16(*pair)).collect();
+        String::from_utf16_lossy(&units)
+    };
+
+    if let Some(rest) = bytes.strip_prefix(&[0xEF, 0xBB, 0xBF]) {
+        return Some((String::from_utf8_lossy(rest).into_owned(), "utf-8"));
+    }
+    if let Some(rest) = bytes.strip_prefix(&[0xFF, 0xFE]) {
+        return Some((decode_utf16(rest, u16::from_le_bytes), "utf-16le"));
+    }
+    if let Some(rest) = bytes.strip_prefix(&[0xFE, 0xFF]) {
+        return Some((decode_utf16(rest, u16::from_be_bytes), "utf-16be"));
+    }
+    if bytes.contains(&0) {
+        return None;
+    }
+    match std::str::from_utf8(bytes) {
+        Ok(text) => Some((text.to_string(), "utf-8")),
+        Err(_) => Some((String::from_utf8_lossy(bytes).into_owned(), "utf-8-lossy")),
+    }
+}
+
+/// GET /api/v1/files/content/*path?start_line=&end_line= -> slice of an indexed document
+async fn file_content_v1(
+    State(state): State<ServicesAppState>,
+    auth_context: Option<Extension<AuthContext>>,
+    axum::extract::Path(path): axum::extract::Path<String>,
+    AxumQuery(q): AxumQuery<FileContentQuery>,
+) -> ApiResult<serde_json::Value> {
+    const ENDPOINT: &str = "files/content";
+
+    if q.start_line == Some(0) {
+        return Err(handle_validation_error(
+            "start_line",
+            "Line numbers are 1-based",
+            ENDPOINT,
+        ));
+    }
+    if let (Some(start), Some(end)) = (q.start_line, q.end_line) {
+        if end < start {
+            return Err(handle_validation_error(
+                "end_line",
+                "end_line must be greater than or equal to start_line",
+                ENDPOINT,
+            ));
+        }
+    }
+
+    let document = document_by_path(
+        &state.storage,
+        &state.primary_index,
+        &state.path_cache,
+        &path,
+    )
+    .await
+    .map_err(|e| handle_service_error(e, ENDPOINT))?;
+    // Redacted documents are reported as missing, so their existence is not disclosed
+    let redaction = redaction_rules(auth_context.as_ref());
+    let document = document.filter(|document| redaction.allows(document.path.as_str()));
+    let Some(document) = document else {
+        return Err((
+            StatusCode::NOT_FOUND,
+            Json(StandardApiError {
+                error_type: "document_not_found".into(),
+                message: format!("No indexed document at path '{}'", path),
+                details: Some(format!("Endpoint: {}", ENDPOINT)),
+                suggestions: vec![
+                    "Check the path is relative to the indexed repository root".into(),
+                    "Use /api/v1/search/code to locate the file".into(),
+                ],
+                error_code: Some(404),
+            }),
+        ));
+    };
+
+    let Some((text, encoding)) = decode_document_text(&document.content) else {
+        return Err((
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            Json(StandardApiError {
+                error_type: "binary_document".into(),
+                message: format!("Document '{}' is not text", path),
+                details: Some(format!("Endpoint: {}", ENDPOINT)),
+                suggestions: vec!["Only text documents can be sliced by line".into()],
+                error_code: Some(415),
+            }),
+        ));
+    };
+
+    // File storage prepends a tags frontmatter block; drop it so line numbers match the source
+    let text = if document.tags.is_empty() {
+        text.as_str()
+    } else {
+        text.strip_prefix("---\n")
+            .and_then(|rest| rest.find("\n---\n").map(|end| &rest[end + 5..]))
+            .unwrap_or(text.as_str())
+    };
+
+    let lines: Vec<&str> = text.lines().collect();
+    let total_lines = lines.len();
+    let start_line = q.start_line.unwrap_or(1);
+    let end_line = q.end_line.unwrap_or(total_lines).min(total_lines);
+    if total_lines > 0 && start_line > total_lines {
+        return Err(handle_validation_error(
+            "start_line",
+            &format!("File has only {} lines", total_lines),
+            ENDPOINT,
+        ));
+    }
+    let content = if total_lines == 0 {
+        String::new()
+    } else {
+        lines[start_line - 1..end_line].join("\n")
+    };
+
+    // Annotate with symbols overlapping the returned range, when a symbol database exists
+    let mut symbols = Vec::new();
+    let symbol_db_path = state.db_path.join("symbols.kota");
+    if let Ok(reader) = crate::binary_symbols::BinarySymbolReader::open(&symbol_db_path) {
+        let mut file_symbols = reader.read_symbols_for_file(document.path.as_str());
+        if file_symbols.is_empty() && document.path.as_str() != path {
+            file_symbols = reader.read_symbols_for_file(&path);
+        }
+        for s in file_symbols {
+            let (sym_start, sym_end) = (s.start_line as usize, s.end_line as usize);
+            if sym_start > end_line || sym_end < start_line {
+                continue;
+            }
+            symbols.push(json!({
+                "name": reader.get_symbol_name(&s).unwrap_or_default(),
+                "kind": format!("{:?}", s.kind),
+                "start_line": s.start_line,
+                "end_line": s.end_line,
+                "start_column": s.start_column,
+                "end_column": s.end_column,
+            }));
+        }
+    }
+
+    Ok(Json(json!({
+        "path": document.path.as_str(),
+        "encoding": encoding,
+        "total_lines": total_lines,
+        "start_line": start_line,
+        "end_line": end_line,
+        "content": content,
+        "symbols": symbols,
+    })))
+}
+
+#[derive(Debug, Deserialize)]
+struct FileTreeQuery {
+    path: Option<String>,
+    depth: Option<usize>,
+}
+
+/// Deepest listing `/api/v1/files/tree` will expand in one request
+const MAX_TREE_DEPTH: usize = 10;
+
+/// Documents enumerated for one `/api/v1/files/tree` listing; larger corpora are truncated
+const MAX_TREE_DOCUMENTS: usize = 100_000;
+
+/// Directory or file entry in a `/api/v1/files/tree` listing
+#[derive(Debug, Serialize)]
+struct FileTreeNode {
+    name: String,
+    path: String,
+    #[serde(rename = "type")]
+    node_type: &'static str,
+    /// Number of files at or below this node
+    file_count: usize,
+    /// Total size in bytes of files at or below this node
+    total_size: usize,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    children: Vec<FileTreeNode>,
+    /// True when entries were left out: children beyond the requested depth, or at
+    /// the root, documents beyond the listing cap
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    truncated: bool,
+}
+
+/// Build a directory node from files given as (path components below the node, size)
+fn build_file_tree(
+    name: &str,
+    path: &str,
+    files: &[(Vec<&str>, usize)],
+    depth: usize,
+) -> FileTreeNode {
+    let mut node = FileTreeNode {
+        name: name.to_string(),
+        path: path.to_string(),
+        node_type: "directory",
+        file_count: files.len(),
+        total_size: files.iter().map(|(_, size)| size).sum(),
+        children: Vec::new(),
+        truncated: false,
+    };
+    if files.is_empty() {
+        return node;
+    }
+    if depth == 0 {
+        node.truncated = true;
+        return node;
+    }
+
+    let mut directories: std::collections::BTreeMap<&str, Vec<(Vec<&str>, usize)>> =
+        std::collections::BTreeMap::new();
+    let mut leaf_files: Vec<(&str, usize)> = Vec::new();
+    for (components, size) in files {
+        match components.split_first() {
+            Some((file, [])) => leaf_files.push((file, *size)),
+            Some((dir, rest)) => directories
+                .entry(dir)
+                .or_default()
+                .push((rest.to_vec(), *size)),
+            None => {}
+        }
+    }
+
+    let child_path = |child: &str| {
+        if path.is_empty() {
+            child.to_string()
+        } else {
+            format!("{}/{}", path, child)
+        }
+    };
+    for (dir, dir_files) in &directories {
+        node.children
+            .push(build_file_tree(dir, &child_path(dir), dir_files, depth - 1));
+    }
+    leaf_files.sort_unstable();
+    for (file, size) in leaf_files {
+        node.children.push(FileTreeNode {
+            name: file.to_string(),
+            path: child_path(file),
+            node_type: "file",
+            file_count: 1,
+            total_size: size,
+            children: Vec::new(),
+            truncated: false,
+        });
+    }
+    node
+}
+
+/// GET /api/v1/files/tree?path=&depth= -> hierarchical listing of indexed documents
+async fn file_tree_v1(
+    State(state): State<ServicesAppState>,
+    auth_context: Option<Extension<AuthContext>>,
+    AxumQuery(q): AxumQuery<FileTreeQuery>,
+) -> ApiResult<serde_json::Value> {
+    const ENDPOINT: &str = "files/tree";
+
+    let depth = q.depth.unwrap_or(2);
+    if depth == 0 || depth > MAX_TREE_DEPTH {
+        return Err(handle_validation_error(
+            "depth",
+            &format!("depth must be between 1 and {}", MAX_TREE_DEPTH),
+            ENDPOINT,
+        ));
+    }
+    let root = q
+        .path
+        .as_deref()
+        .unwrap_or("")
+        .trim_matches('/')
+        .to_string();
+
+    // Enumerate documents under the root through the primary (path) index, then take
+    // paths and sizes from storage metadata without reading document content
+    let pattern = if root.is_empty() {
+        "*".to_string()
+    } else {
+        format!("*{}/*", root)
+    };
+    let query = crate::Query::new(None, None, Some(pattern), MAX_TREE_DOCUMENTS)
+        .map_err(|e| handle_service_error(e, ENDPOINT))?;
+    let ids = state
+        .primary_index
+        .lock_timed(LockedResource::PrimaryIndex)
+        .await
+        .search(&query)
+        .await
+        .map_err(|e| handle_service_error(e, ENDPOINT))?;
+    let listing_truncated = ids.len() >= MAX_TREE_DOCUMENTS;
+
+    let redaction = redaction_rules(auth_context.as_ref());
+    let mut files: Vec<(String, usize)> = Vec::with_capacity(ids.len());
+    {
+        let storage = state.storage.lock_timed(LockedResource::Storage).await;
+        for id in &ids {
+            if let Some(summary) = storage
+                .summary(id)
+                .await
+                .map_err(|e| handle_service_error(e, ENDPOINT))?
+                .filter(|summary| redaction.allows(summary.path.as_str()))
+            {
+                files.push((
+                    summary.path.as_str().trim_start_matches('/').to_string(),
+                    summary.size,
+                ));
+            }
+        }
+    }
+
+    let prefix = format!("{}/", root);
+    let scoped: Vec<(Vec<&str>, usize)> = files
+        .iter()
+        .filter_map(|(file_path, size)| {
+            let relative = if root.is_empty() {
+                file_path.as_str()
+            } else {
+                file_path.strip_prefix(&prefix)?
+            };
+            Some((
+                relative.split('/').filter(|c| !c.is_empty()).collect(),
+                *size,
+            ))
+        })
+        .collect();
+
+    if scoped.is_empty() && !root.is_empty() {
+        return Err((
             StatusCode::NOT_FOUND,
             Json(StandardApiError {
-                error_type: "symbols_db_missing".into(),
-                message: format!("{}", e),
-                details: Some("symbols.kota not found or unreadable".into()),
-                suggestions: vec!["Run indexing with symbol extraction enabled".into()],
+                error_type: "directory_not_found".into(),
+                message: format!("No indexed documents under '{}'", root),
+                details: Some(format!("Endpoint: {}", ENDPOINT)),
+                suggestions: vec!["Omit 'path' to list from the corpus root".into()],
                 error_code: Some(404),
             }),
-        )),
+        ));
     }
+
+    let name = root.rsplit('/').next().unwrap_or("");
+    let mut tree = build_file_tree(name, &root, &scoped, depth);
+    tree.truncated |= listing_truncated;
+    serde_json::to_value(tree)
+        .map(Json)
+        .map_err(|e| handle_service_error(anyhow::anyhow!(e), ENDPOINT))
 }
 
 /// POST /api/v1/repositories -> start background indexing job
@@ -1486,7 +5109,7 @@ async fn register_repository_local(
             storage: state_clone.storage.clone(),
             primary_index: state_clone.primary_index.clone(),
             trigram_index: state_clone.trigram_index.clone(),
-            path_cache: Arc::new(RwLock::new(HashMap::new())),
+            path_cache: state.path_cache.clone(),
         };
         let indexing = IndexingService::new(&database, state_clone.db_path.clone());
         let mut options = IndexCodebaseOptions {
@@ -1515,7 +5138,10 @@ async fn register_repository_local(
             options.extract_symbols = Some(v);
         }
         options.quiet = false;
-        match indexing.index_codebase(options).await {
+        let outcome = indexing.index_codebase(options).await;
+        // Even failed runs may have written partial data
+        state_clone.bump_data_generation();
+        match outcome {
             Ok(_) => {
                 update_job_status(&state_clone, &job_id, |j| {
                     j.status = "completed".into();
@@ -1597,6 +5223,16 @@ async fn register_repository_saas(
         .and_then(|id| Uuid::parse_str(id).ok())
         .ok_or_else(|| unauthorized_error("API key is not linked to a Supabase user"))?;
 
+    if let Some(rules) = &body.webhook_rules {
+        if let Err(e) = WebhookRules::parse(rules) {
+            return Err(handle_validation_error(
+                "webhook_rules",
+                &e.to_string(),
+                "repositories",
+            ));
+        }
+    }
+
     let provider = infer_repository_provider(git_url);
     let repo_name = infer_repository_name(git_url).unwrap_or_else(|| "repository".to_string());
     let settings = build_settings_from_request(&body);
@@ -2026,6 +5662,34 @@ async fn handle_github_webhook(
         ));
     }
 
+    if let WebhookDecision::Skip(reason) =
+        WebhookRules::from_settings(&repo_meta.settings).evaluate(&event_type, &payload_json)
+    {
+        info!(
+            repository_id = %repo_uuid,
+            event = %event_type,
+            reason = %reason,
+            "Webhook delivery skipped by repository rules"
+        );
+        if let Err(err) = store
+            .update_webhook_delivery_status(record_id, "ignored", true, Some(reason.as_str()), None)
+            .await
+        {
+            error!(
+                "Failed to mark webhook delivery {} as skipped: {}",
+                record_id, err
+            );
+        }
+
+        return Ok((
+            StatusCode::OK,
+            Json(WebhookResponse {
+                status: format!("skipped:{}", reason),
+                job_id: None,
+            }),
+        ));
+    }
+
     let branch_source = payload_json
         .get("ref")
         .and_then(|value| value.as_str())
@@ -2053,6 +5717,9 @@ async fn handle_github_webhook(
     if let Some(ref_value) = payload_json.get("ref").cloned() {
         job_payload_map.insert("ref".into(), ref_value);
     }
+    if let Some(after) = payload_json.get("after").and_then(|v| v.as_str()) {
+        job_payload_map.insert("after".into(), json!(after));
+    }
     if let Some(commits) = payload_json.get("commits").cloned() {
         job_payload_map.insert("commits".into(), commits);
     }
@@ -2068,8 +5735,19 @@ async fn handle_github_webhook(
     }
     job_payload_map.insert("webhook_delivery_id".into(), json!(record_id));
 
+    // Pushes that list their changed files only need those blobs; force pushes can rewrite
+    // history the commit list does not describe, so they take the full re-ingest path.
+    let forced = payload_json
+        .get("forced")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+    let job_type_str = if event_type == "push" && !forced && job_payload_map.contains_key("changes")
+    {
+        "delta_index"
+    } else {
+        job_type.unwrap()
+    };
     let job_payload = JsonValue::Object(job_payload_map);
-    let job_type_str = job_type.unwrap();
 
     let job_id = match store
         .enqueue_job(
@@ -2310,342 +5988,1341 @@ async fn ensure_github_webhook(
     ))
 }
 
-fn parse_github_owner_repo(git_url: &str) -> Option<(String, String)> {
-    if let Ok(url) = Url::parse(git_url) {
-        let host = url.host_str()?;
-        if !host.contains("github.com") {
-            return None;
-        }
-        let mut segments = url.path().trim_matches('/').split('/');
-        let owner = segments.next()?.to_string();
-        let repo = segments
-            .next()
-            .map(|s| s.trim_end_matches(".git").to_string())?;
-        if owner.is_empty() || repo.is_empty() {
-            return None;
+fn build_settings_from_request(body: &RegisterRepositoryRequest) -> JsonValue {
+    let mut root = JsonMap::new();
+
+    if let Some(branch) = &body.branch {
+        root.insert("branch".into(), json!(branch));
+    }
+
+    let mut options = JsonMap::new();
+    if let Some(v) = body.include_files {
+        options.insert("include_files".into(), json!(v));
+    }
+    if let Some(v) = body.include_commits {
+        options.insert("include_commits".into(), json!(v));
+    }
+    if let Some(v) = body.max_file_size_mb {
+        options.insert("max_file_size_mb".into(), json!(v));
+    }
+    if let Some(v) = body.max_memory_mb {
+        options.insert("max_memory_mb".into(), json!(v));
+    }
+    if let Some(v) = body.max_parallel_files {
+        options.insert("max_parallel_files".into(), json!(v));
+    }
+    if let Some(v) = body.enable_chunking {
+        options.insert("enable_chunking".into(), json!(v));
+    }
+    if let Some(v) = body.extract_symbols {
+        options.insert("extract_symbols".into(), json!(v));
+    }
+
+    if !options.is_empty() {
+        root.insert("options".into(), JsonValue::Object(options));
+    }
+
+    if let Some(rules) = &body.webhook_rules {
+        root.insert("webhook_rules".into(), rules.clone());
+    }
+
+    JsonValue::Object(root)
+}
+
+fn build_job_payload(body: &RegisterRepositoryRequest, git_url: &str, provider: &str) -> JsonValue {
+    let mut payload = JsonMap::new();
+    payload.insert("git_url".into(), json!(git_url));
+    payload.insert("provider".into(), json!(provider));
+    if let Some(branch) = &body.branch {
+        payload.insert("branch".into(), json!(branch));
+    }
+    payload.insert("requested_at".into(), json!(Utc::now().to_rfc3339()));
+    payload.insert("settings".into(), build_settings_from_request(body));
+
+    JsonValue::Object(payload)
+}
+
+fn infer_repository_provider(git_url: &str) -> String {
+    if let Ok(parsed) = Url::parse(git_url) {
+        if let Some(host) = parsed.host_str() {
+            if host.contains("github.com") {
+                return "github".to_string();
+            }
+            if host.contains("gitlab.com") {
+                return "gitlab".to_string();
+            }
+            if host.contains("bitbucket.org") {
+                return "bitbucket".to_string();
+            }
+        }
+    }
+    "git".to_string()
+}
+
+fn infer_repository_name(git_url: &str) -> Option<String> {
+    if let Ok(parsed) = Url::parse(git_url) {
+        let path = parsed.path().trim_matches('/');
+        if !path.is_empty() {
+            if let Some(last) = path.rsplit('/').next() {
+                return Some(last.trim_end_matches(".git").to_string());
+            }
+        }
+    }
+    None
+}
+
+type HmacSha256 = Hmac<Sha256>;
+
+fn headers_to_json(headers: &HeaderMap) -> JsonValue {
+    let mut map = JsonMap::new();
+    for (name, value) in headers.iter() {
+        if let Ok(string_value) = value.to_str() {
+            map.insert(
+                name.as_str().to_string(),
+                JsonValue::String(string_value.to_string()),
+            );
+        }
+    }
+    JsonValue::Object(map)
+}
+
+fn normalize_git_ref(git_ref: &str) -> Option<String> {
+    if let Some(branch) = git_ref.strip_prefix("refs/heads/") {
+        return Some(branch.to_string());
+    }
+    if let Some(tag) = git_ref.strip_prefix("refs/tags/") {
+        return Some(tag.to_string());
+    }
+    None
+}
+
+fn extract_commit_changes(payload: &JsonValue) -> Option<JsonValue> {
+    let commits = payload.get("commits")?.as_array()?;
+    let mut added = BTreeSet::new();
+    let mut modified = BTreeSet::new();
+    let mut removed = BTreeSet::new();
+
+    for commit in commits {
+        if let Some(files) = commit.get("added").and_then(|v| v.as_array()) {
+            for file in files.iter().filter_map(|v| v.as_str()) {
+                added.insert(file.to_string());
+            }
+        }
+        if let Some(files) = commit.get("modified").and_then(|v| v.as_array()) {
+            for file in files.iter().filter_map(|v| v.as_str()) {
+                modified.insert(file.to_string());
+            }
+        }
+        if let Some(files) = commit.get("removed").and_then(|v| v.as_array()) {
+            for file in files.iter().filter_map(|v| v.as_str()) {
+                removed.insert(file.to_string());
+            }
+        }
+    }
+
+    if added.is_empty() && modified.is_empty() && removed.is_empty() {
+        return None;
+    }
+
+    Some(json!({
+        "added": added.into_iter().collect::<Vec<_>>(),
+        "modified": modified.into_iter().collect::<Vec<_>>(),
+        "removed": removed.into_iter().collect::<Vec<_>>(),
+    }))
+}
+
+fn verify_github_signature(secret: &str, payload: &[u8], signature_header: &str) -> bool {
+    let Some(signature_hex) = signature_header.strip_prefix("sha256=") else {
+        return false;
+    };
+
+    let Ok(signature_bytes) = hex::decode(signature_hex) else {
+        return false;
+    };
+
+    let mut mac = match HmacSha256::new_from_slice(secret.as_bytes()) {
+        Ok(mac) => mac,
+        Err(_) => return false,
+    };
+
+    mac.update(payload);
+    mac.verify_slice(&signature_bytes).is_ok()
+}
+
+const IMPORTANT_SAAS_ENV_VARS: &[&str] = &[
+    "SUPABASE_URL",
+    "SUPABASE_ANON_KEY",
+    "SUPABASE_SERVICE_KEY",
+    "KOTADB_WEBHOOK_BASE_URL",
+    SECRETS_MASTER_KEY_ENV,
+    URL_SIGNING_KEY_ENV,
+];
+
+const SUPABASE_DB_ENV_CHOICES: &[&str] = &[
+    "SUPABASE_DB_URL",
+    "SUPABASE_DB_URL_STAGING",
+    "SUPABASE_DB_URL_PRODUCTION",
+];
+
+const IMPORTANT_GITHUB_ENV_VARS: &[&str] = &[
+    "GITHUB_CLIENT_ID",
+    "GITHUB_CLIENT_SECRET",
+    "GITHUB_WEBHOOK_TOKEN",
+];
+
+const GITHUB_API_URL: &str = "https://api.github.com";
+const GITHUB_WEBHOOK_EVENTS: &[&str] = &["push", "pull_request"];
+const GITHUB_USER_AGENT: &str = "kotadb-saas-webhooks/1.0";
+
+fn env_present(key: &str) -> bool {
+    env::var(key)
+        .map(|value| !value.trim().is_empty())
+        .unwrap_or(false)
+}
+
+fn any_env_present(keys: &[&str]) -> bool {
+    keys.iter().any(|key| env_present(key))
+}
+
+fn validate_saas_environment(api_key_config: &crate::ApiKeyConfig) -> Result<()> {
+    if env_present("DISABLE_SAAS_ENV_VALIDATION") {
+        warn!("SaaS environment validation disabled via DISABLE_SAAS_ENV_VALIDATION");
+        return Ok(());
+    }
+
+    let database_configured = !api_key_config.database_url.trim().is_empty()
+        || env_present("DATABASE_URL")
+        || any_env_present(SUPABASE_DB_ENV_CHOICES);
+
+    if !database_configured {
+        let message = "Database connection not configured. Provide ApiKeyConfig.database_url or set DATABASE_URL/SUPABASE_DB_URL";
+        error!("{}", message);
+        return Err(anyhow!(message));
+    }
+
+    if let Err(e) = secret_keyring() {
+        error!("Webhook secret encryption misconfigured: {:#}", e);
+        return Err(e);
+    }
+
+    let mut missing_optional = Vec::new();
+
+    for key in IMPORTANT_SAAS_ENV_VARS {
+        if !env_present(key) {
+            missing_optional.push(*key);
         }
-        return Some((owner, repo));
     }
 
-    if let Some(stripped) = git_url.strip_prefix("git@github.com:") {
-        let mut segments = stripped.split('/');
-        let owner = segments.next()?.to_string();
-        let repo = segments
-            .next()
-            .map(|s| s.trim_end_matches(".git").to_string())?;
-        if owner.is_empty() || repo.is_empty() {
-            return None;
+    for key in IMPORTANT_GITHUB_ENV_VARS {
+        if !env_present(key) {
+            missing_optional.push(*key);
         }
-        return Some((owner, repo));
     }
 
-    None
+    if !missing_optional.is_empty() {
+        warn!(
+            missing = ?missing_optional,
+            "Optional SaaS environment variables are not set. Functionality relying on them may be limited"
+        );
+    }
+
+    Ok(())
 }
 
-fn build_settings_from_request(body: &RegisterRepositoryRequest) -> JsonValue {
-    let mut root = JsonMap::new();
+async fn fetch_saas_health(state: &ServicesAppState) -> SaasHealth {
+    let mut health = SaasHealth {
+        supabase_status: "not_configured".to_string(),
+        supabase_latency_ms: None,
+        job_queue: JobQueueHealth::default(),
+    };
 
-    if let Some(branch) = &body.branch {
-        root.insert("branch".into(), json!(branch));
-    }
+    let Some(pool) = &state.supabase_pool else {
+        return health;
+    };
 
-    let mut options = JsonMap::new();
-    if let Some(v) = body.include_files {
-        options.insert("include_files".into(), json!(v));
-    }
-    if let Some(v) = body.include_commits {
-        options.insert("include_commits".into(), json!(v));
-    }
-    if let Some(v) = body.max_file_size_mb {
-        options.insert("max_file_size_mb".into(), json!(v));
-    }
-    if let Some(v) = body.max_memory_mb {
-        options.insert("max_memory_mb".into(), json!(v));
-    }
-    if let Some(v) = body.max_parallel_files {
-        options.insert("max_parallel_files".into(), json!(v));
-    }
-    if let Some(v) = body.enable_chunking {
-        options.insert("enable_chunking".into(), json!(v));
+    let start = Instant::now();
+    match sqlx::query(
+        "SELECT status, COUNT(*)::bigint AS job_count FROM indexing_jobs GROUP BY status",
+    )
+    .fetch_all(pool)
+    .await
+    {
+        Ok(rows) => {
+            health.supabase_status = "ok".to_string();
+            health.supabase_latency_ms = Some(start.elapsed().as_millis());
+            for row in rows {
+                let status = row
+                    .try_get::<String, _>("status")
+                    .unwrap_or_else(|_| "unknown".to_string());
+                let count = row.try_get::<i64, _>("job_count").unwrap_or(0).max(0) as usize;
+                match status.as_str() {
+                    "queued" => health.job_queue.queued = count,
+                    "in_progress" => health.job_queue.in_progress = count,
+                    "failed" => health.job_queue.failed = count,
+                    _ => {}
+                }
+            }
+        }
+        Err(e) => {
+            health.supabase_status = "error".to_string();
+            error!("Supabase health query failed: {}", e);
+        }
     }
-    if let Some(v) = body.extract_symbols {
-        options.insert("extract_symbols".into(), json!(v));
+
+    if let Ok(failed_recent) = sqlx::query_scalar::<_, i64>(
+        "SELECT COUNT(*) FROM indexing_jobs WHERE status = 'failed' AND finished_at > NOW() - INTERVAL '1 hour'",
+    )
+    .fetch_one(pool)
+    .await
+    {
+        health.job_queue.failed_recent = failed_recent.max(0) as usize;
     }
 
-    if !options.is_empty() {
-        root.insert("options".into(), JsonValue::Object(options));
+    if let Ok(Some(ts)) = sqlx::query_scalar::<_, Option<chrono::DateTime<Utc>>>(
+        "SELECT MIN(queued_at) FROM indexing_jobs WHERE status = 'queued'",
+    )
+    .fetch_one(pool)
+    .await
+    {
+        let age = Utc::now().signed_duration_since(ts).num_seconds().max(0) as u64;
+        health.job_queue.oldest_queued_seconds = Some(age);
     }
 
-    JsonValue::Object(root)
+    health
 }
 
-fn build_job_payload(body: &RegisterRepositoryRequest, git_url: &str, provider: &str) -> JsonValue {
-    let mut payload = JsonMap::new();
-    payload.insert("git_url".into(), json!(git_url));
-    payload.insert("provider".into(), json!(provider));
-    if let Some(branch) = &body.branch {
-        payload.insert("branch".into(), json!(branch));
+/// GET /api/v1/repositories
+async fn list_repositories_v1(
+    State(state): State<ServicesAppState>,
+    auth_context: Option<Extension<AuthContext>>,
+) -> ApiResult<ListRepositoriesResponse> {
+    if state.is_saas_mode() {
+        return list_repositories_saas(&state, auth_context).await;
     }
-    payload.insert("requested_at".into(), json!(Utc::now().to_rfc3339()));
-    payload.insert("settings".into(), build_settings_from_request(body));
 
-    JsonValue::Object(payload)
+    let repos = state.repositories.read().await.clone();
+    Ok(Json(ListRepositoriesResponse {
+        repositories: repos,
+    }))
 }
 
-fn infer_repository_provider(git_url: &str) -> String {
-    if let Ok(parsed) = Url::parse(git_url) {
-        if let Some(host) = parsed.host_str() {
-            if host.contains("github.com") {
-                return "github".to_string();
-            }
-            if host.contains("gitlab.com") {
-                return "gitlab".to_string();
-            }
-            if host.contains("bitbucket.org") {
-                return "bitbucket".to_string();
-            }
-        }
-    }
-    "git".to_string()
+/// v1 repository settings patch; only these keys may be changed
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RepositorySettingsPatch {
+    #[serde(default)]
+    pub branch: Option<String>,
+    #[serde(default)]
+    pub options: Option<RepositoryOptionsPatch>,
+    /// Validated separately by [`WebhookRules::parse`]; `null` clears the rules
+    #[serde(default)]
+    pub webhook_rules: Option<JsonValue>,
 }
 
-fn infer_repository_name(git_url: &str) -> Option<String> {
-    if let Ok(parsed) = Url::parse(git_url) {
-        let path = parsed.path().trim_matches('/');
-        if !path.is_empty() {
-            if let Some(last) = path.rsplit('/').next() {
-                return Some(last.trim_end_matches(".git").to_string());
-            }
-        }
-    }
-    None
+/// Indexing overrides accepted under `settings.options`
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RepositoryOptionsPatch {
+    pub include_files: Option<bool>,
+    pub include_commits: Option<bool>,
+    pub max_file_size_mb: Option<usize>,
+    pub max_memory_mb: Option<u64>,
+    pub max_parallel_files: Option<usize>,
+    pub enable_chunking: Option<bool>,
+    pub extract_symbols: Option<bool>,
 }
 
-type HmacSha256 = Hmac<Sha256>;
+/// v1 repository settings update response
+#[derive(Debug, Serialize)]
+pub struct RepositorySettingsResponse {
+    pub repository_id: String,
+    pub settings: JsonValue,
+}
 
-fn headers_to_json(headers: &HeaderMap) -> JsonValue {
-    let mut map = JsonMap::new();
-    for (name, value) in headers.iter() {
-        if let Ok(string_value) = value.to_str() {
-            map.insert(
-                name.as_str().to_string(),
-                JsonValue::String(string_value.to_string()),
+/// PATCH /api/v1/repositories/:repository_id/settings - merge repository settings (SaaS)
+async fn update_repository_settings_v1(
+    State(state): State<ServicesAppState>,
+    auth_context: Option<Extension<AuthContext>>,
+    Path(repository_id): Path<String>,
+    Json(patch): Json<JsonValue>,
+) -> ApiResult<RepositorySettingsResponse> {
+    const ENDPOINT: &str = "repositories/settings";
+
+    let pool = match &state.supabase_pool {
+        Some(pool) => pool.clone(),
+        None => return Err(internal_server_error("Supabase connection not configured")),
+    };
+
+    let Extension(auth) = auth_context
+        .ok_or_else(|| unauthorized_error("Authentication required to update repositories"))?;
+    let user_uuid = auth
+        .user_id
+        .as_ref()
+        .and_then(|id| Uuid::parse_str(id).ok())
+        .ok_or_else(|| unauthorized_error("API key is not linked to a Supabase user"))?;
+
+    let repo_uuid = Uuid::parse_str(&repository_id).map_err(|_| {
+        handle_validation_error("repository_id", "Invalid repository id format", ENDPOINT)
+    })?;
+
+    let Some(fields) = patch.as_object() else {
+        return Err(handle_validation_error(
+            "settings",
+            "Body must be a JSON object",
+            ENDPOINT,
+        ));
+    };
+    if fields
+        .get("options")
+        .is_some_and(|options| !options.is_object())
+    {
+        return Err(handle_validation_error(
+            "options",
+            "Must be a JSON object",
+            ENDPOINT,
+        ));
+    }
+    if let Err(e) = serde_json::from_value::<RepositorySettingsPatch>(patch.clone()) {
+        return Err(handle_validation_error(
+            "settings",
+            &e.to_string(),
+            ENDPOINT,
+        ));
+    }
+    if let Some(rules) = fields.get("webhook_rules").filter(|rules| !rules.is_null()) {
+        if let Err(e) = WebhookRules::parse(rules) {
+            return Err(handle_validation_error(
+                "webhook_rules",
+                &e.to_string(),
+                ENDPOINT,
+            ));
+        }
+    }
+
+    let store = SupabaseRepositoryStore::new(pool);
+    match store
+        .update_repository_settings(repo_uuid, user_uuid, &patch)
+        .await
+    {
+        Ok(Some(settings)) => Ok(Json(RepositorySettingsResponse {
+            repository_id: repo_uuid.to_string(),
+            settings,
+        })),
+        Ok(None) => Err(handle_not_found_error(
+            "repository_id",
+            "Repository not found",
+            ENDPOINT,
+        )),
+        Err(e) => {
+            error!(
+                "Failed to update settings for repository {}: {}",
+                repo_uuid, e
             );
+            Err(internal_server_error(
+                "Failed to update repository settings",
+            ))
         }
     }
-    JsonValue::Object(map)
 }
 
-fn normalize_git_ref(git_ref: &str) -> Option<String> {
-    if let Some(branch) = git_ref.strip_prefix("refs/heads/") {
-        return Some(branch.to_string());
-    }
-    if let Some(tag) = git_ref.strip_prefix("refs/tags/") {
-        return Some(tag.to_string());
-    }
-    None
+/// v1 notification hook registration request
+#[derive(Debug, Deserialize)]
+pub struct CreateNotificationHookRequest {
+    pub url: String,
 }
 
-fn extract_commit_changes(payload: &JsonValue) -> Option<JsonValue> {
-    let commits = payload.get("commits")?.as_array()?;
-    let mut added = BTreeSet::new();
-    let mut modified = BTreeSet::new();
-    let mut removed = BTreeSet::new();
+/// v1 notification hook registration response; the secret is only returned here
+#[derive(Debug, Serialize)]
+pub struct CreateNotificationHookResponse {
+    #[serde(flatten)]
+    pub hook: NotificationHookRow,
+    pub secret: String,
+    pub events: Vec<String>,
+}
 
-    for commit in commits {
-        if let Some(files) = commit.get("added").and_then(|v| v.as_array()) {
-            for file in files.iter().filter_map(|v| v.as_str()) {
-                added.insert(file.to_string());
-            }
+/// v1 notification hook listing response
+#[derive(Debug, Serialize)]
+pub struct ListNotificationHooksResponse {
+    pub hooks: Vec<NotificationHookRow>,
+}
+
+/// Supabase store and caller for SaaS-only endpoints; `action` completes the 401 message
+fn saas_store_and_user(
+    state: &ServicesAppState,
+    auth_context: Option<Extension<AuthContext>>,
+    action: &str,
+) -> Result<(SupabaseRepositoryStore, Uuid), (StatusCode, Json<StandardApiError>)> {
+    let pool = match &state.supabase_pool {
+        Some(pool) => pool.clone(),
+        None => return Err(internal_server_error("Supabase connection not configured")),
+    };
+    let Extension(auth) = auth_context
+        .ok_or_else(|| unauthorized_error(format!("Authentication required to {}", action)))?;
+    let user_uuid = auth
+        .user_id
+        .as_ref()
+        .and_then(|id| Uuid::parse_str(id).ok())
+        .ok_or_else(|| unauthorized_error("API key is not linked to a Supabase user"))?;
+    Ok((SupabaseRepositoryStore::new(pool), user_uuid))
+}
+
+/// POST /api/v1/notification-hooks - register a callback for job completion (SaaS)
+async fn create_notification_hook_v1(
+    State(state): State<ServicesAppState>,
+    auth_context: Option<Extension<AuthContext>>,
+    Json(body): Json<CreateNotificationHookRequest>,
+) -> ApiResult<CreateNotificationHookResponse> {
+    let (store, user_uuid) =
+        saas_store_and_user(&state, auth_context, "manage notification hooks")?;
+
+    let url = match Url::parse(body.url.trim()) {
+        Ok(url) if url.scheme() == "https" && url.host_str().is_some() => url,
+        _ => {
+            return Err(handle_validation_error(
+                "url",
+                "Must be an absolute https URL",
+                "notification-hooks",
+            ))
         }
-        if let Some(files) = commit.get("modified").and_then(|v| v.as_array()) {
-            for file in files.iter().filter_map(|v| v.as_str()) {
-                modified.insert(file.to_string());
-            }
+    };
+    if let Err(e) = resolve_notification_url(&url).await {
+        debug!("Rejected notification hook URL {}: {}", url, e);
+        return Err(handle_validation_error(
+            "url",
+            "Must resolve to a public internet address",
+            "notification-hooks",
+        ));
+    }
+
+    match store
+        .create_notification_hook(user_uuid, url.as_str())
+        .await
+    {
+        Ok((hook, secret)) => Ok(Json(CreateNotificationHookResponse {
+            hook,
+            secret,
+            events: vec![EVENT_INDEX_COMPLETED.into(), EVENT_INDEX_FAILED.into()],
+        })),
+        Err(e) => {
+            error!("Failed to register notification hook: {}", e);
+            Err(internal_server_error(
+                "Failed to register notification hook",
+            ))
         }
-        if let Some(files) = commit.get("removed").and_then(|v| v.as_array()) {
-            for file in files.iter().filter_map(|v| v.as_str()) {
-                removed.insert(file.to_string());
-            }
+    }
+}
+
+/// GET /api/v1/notification-hooks - list the caller's notification hooks (SaaS)
+async fn list_notification_hooks_v1(
+    State(state): State<ServicesAppState>,
+    auth_context: Option<Extension<AuthContext>>,
+) -> ApiResult<ListNotificationHooksResponse> {
+    let (store, user_uuid) =
+        saas_store_and_user(&state, auth_context, "manage notification hooks")?;
+    match store.list_notification_hooks(user_uuid).await {
+        Ok(hooks) => Ok(Json(ListNotificationHooksResponse { hooks })),
+        Err(e) => {
+            error!("Failed to list notification hooks: {}", e);
+            Err(internal_server_error("Failed to list notification hooks"))
         }
     }
+}
 
-    if added.is_empty() && modified.is_empty() && removed.is_empty() {
-        return None;
+/// DELETE /api/v1/notification-hooks/:hook_id - remove a notification hook (SaaS)
+async fn delete_notification_hook_v1(
+    State(state): State<ServicesAppState>,
+    auth_context: Option<Extension<AuthContext>>,
+    Path(hook_id): Path<String>,
+) -> Result<StatusCode, (StatusCode, Json<StandardApiError>)> {
+    let (store, user_uuid) =
+        saas_store_and_user(&state, auth_context, "manage notification hooks")?;
+    let hook_uuid = Uuid::parse_str(&hook_id).map_err(|_| {
+        handle_validation_error("hook_id", "Invalid hook id format", "notification-hooks")
+    })?;
+    match store.delete_notification_hook(hook_uuid, user_uuid).await {
+        Ok(true) => Ok(StatusCode::NO_CONTENT),
+        Ok(false) => Err(handle_not_found_error(
+            "hook_id",
+            "Notification hook not found",
+            "notification-hooks",
+        )),
+        Err(e) => {
+            error!("Failed to delete notification hook {}: {}", hook_uuid, e);
+            Err(internal_server_error("Failed to delete notification hook"))
+        }
     }
+}
 
-    Some(json!({
-        "added": added.into_iter().collect::<Vec<_>>(),
-        "modified": modified.into_iter().collect::<Vec<_>>(),
-        "removed": removed.into_iter().collect::<Vec<_>>(),
-    }))
+/// v1 source upload creation request
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CreateUploadRequest {
+    /// Repository name the archive is indexed under; re-using a name replaces its contents
+    pub name: String,
+    pub branch: Option<String>,
+    /// Archive size in bytes, checked when the upload completes
+    pub size: Option<u64>,
+    /// Indexing overrides, as accepted under `settings.options`
+    pub options: Option<JsonValue>,
 }
 
-fn verify_github_signature(secret: &str, payload: &[u8], signature_header: &str) -> bool {
-    let Some(signature_hex) = signature_header.strip_prefix("sha256=") else {
-        return false;
-    };
+/// v1 source upload state, returned on creation and when resuming
+#[derive(Debug, Serialize)]
+pub struct UploadResponse {
+    #[serde(flatten)]
+    pub upload: SourceUploadRow,
+    pub parts: Vec<UploadPart>,
+    pub received_bytes: u64,
+    pub max_part_bytes: u64,
+    pub max_parts: u32,
+}
 
-    let Ok(signature_bytes) = hex::decode(signature_hex) else {
-        return false;
-    };
+/// v1 upload part response
+#[derive(Debug, Serialize)]
+pub struct UploadPartResponse {
+    pub part_number: u32,
+    pub size: u64,
+    pub sha256: String,
+}
 
-    let mut mac = match HmacSha256::new_from_slice(secret.as_bytes()) {
-        Ok(mac) => mac,
-        Err(_) => return false,
-    };
+/// v1 upload completion response
+#[derive(Debug, Serialize)]
+pub struct CompleteUploadResponse {
+    pub upload_id: String,
+    pub repository_id: String,
+    pub job_id: String,
+    pub status: String,
+    pub parts: usize,
+    pub size: u64,
+}
 
-    mac.update(payload);
-    mac.verify_slice(&signature_bytes).is_ok()
+/// Error with an explicit status and a single suggestion, for SaaS state conflicts
+fn saas_error(
+    endpoint: &str,
+    status: StatusCode,
+    error_type: &str,
+    message: impl Into<String>,
+    suggestion: &str,
+) -> (StatusCode, Json<StandardApiError>) {
+    (
+        status,
+        Json(StandardApiError {
+            error_type: error_type.into(),
+            message: message.into(),
+            details: Some(format!("Endpoint: {}", endpoint)),
+            suggestions: vec![suggestion.into()],
+            error_code: Some(status.as_u16() as u32),
+        }),
+    )
 }
 
-const IMPORTANT_SAAS_ENV_VARS: &[&str] = &[
-    "SUPABASE_URL",
-    "SUPABASE_ANON_KEY",
-    "SUPABASE_SERVICE_KEY",
-    "KOTADB_WEBHOOK_BASE_URL",
-];
+fn upload_error(
+    status: StatusCode,
+    error_type: &str,
+    message: impl Into<String>,
+    suggestion: &str,
+) -> (StatusCode, Json<StandardApiError>) {
+    saas_error("uploads", status, error_type, message, suggestion)
+}
 
-const SUPABASE_DB_ENV_CHOICES: &[&str] = &[
-    "SUPABASE_DB_URL",
-    "SUPABASE_DB_URL_STAGING",
-    "SUPABASE_DB_URL_PRODUCTION",
-];
+/// Pending upload `upload_id` owned by the caller
+async fn pending_upload(
+    store: &SupabaseRepositoryStore,
+    upload_id: &str,
+    user_uuid: Uuid,
+) -> Result<SourceUploadRow, (StatusCode, Json<StandardApiError>)> {
+    let upload = fetch_upload(store, upload_id, user_uuid).await?;
+    if upload.status != "pending" {
+        return Err(upload_error(
+            StatusCode::CONFLICT,
+            "upload_closed",
+            format!("Upload is {}", upload.status),
+            "Create a new upload to send another archive",
+        ));
+    }
+    Ok(upload)
+}
 
-const IMPORTANT_GITHUB_ENV_VARS: &[&str] = &[
-    "GITHUB_CLIENT_ID",
-    "GITHUB_CLIENT_SECRET",
-    "GITHUB_WEBHOOK_TOKEN",
-];
+async fn fetch_upload(
+    store: &SupabaseRepositoryStore,
+    upload_id: &str,
+    user_uuid: Uuid,
+) -> Result<SourceUploadRow, (StatusCode, Json<StandardApiError>)> {
+    let upload_uuid = Uuid::parse_str(upload_id)
+        .map_err(|_| handle_validation_error("upload_id", "Invalid upload id format", "uploads"))?;
+    match store.fetch_source_upload(upload_uuid, user_uuid).await {
+        Ok(Some(upload)) => Ok(upload),
+        Ok(None) => Err(handle_not_found_error(
+            "upload_id",
+            "Upload not found",
+            "uploads",
+        )),
+        Err(e) => {
+            error!("Failed to load upload {}: {}", upload_uuid, e);
+            Err(internal_server_error("Failed to load upload"))
+        }
+    }
+}
 
-const GITHUB_API_URL: &str = "https://api.github.com";
-const GITHUB_WEBHOOK_EVENTS: &[&str] = &["push", "pull_request"];
-const GITHUB_USER_AGENT: &str = "kotadb-saas-webhooks/1.0";
+fn upload_response(state: &ServicesAppState, upload: SourceUploadRow) -> ApiResult<UploadResponse> {
+    let parts = list_parts(&upload_dir(&state.db_path, upload.id)).map_err(|e| {
+        error!("Failed to list parts of upload {}: {}", upload.id, e);
+        internal_server_error("Failed to read upload parts")
+    })?;
+    Ok(Json(UploadResponse {
+        upload,
+        received_bytes: parts.iter().map(|part| part.size).sum(),
+        parts,
+        max_part_bytes: MAX_UPLOAD_PART_BYTES,
+        max_parts: MAX_UPLOAD_PARTS,
+    }))
+}
 
-fn env_present(key: &str) -> bool {
-    env::var(key)
-        .map(|value| !value.trim().is_empty())
-        .unwrap_or(false)
+/// POST /api/v1/uploads - open a resumable source archive upload (SaaS)
+async fn create_upload_v1(
+    State(state): State<ServicesAppState>,
+    auth_context: Option<Extension<AuthContext>>,
+    Json(body): Json<CreateUploadRequest>,
+) -> ApiResult<UploadResponse> {
+    let (store, user_uuid) = saas_store_and_user(&state, auth_context, "upload source archives")?;
+
+    if !is_valid_upload_name(&body.name) {
+        return Err(handle_validation_error(
+            "name",
+            "Use 1-100 letters, digits, '-', '_' or '.', not starting with '.'",
+            "uploads",
+        ));
+    }
+    if body
+        .size
+        .is_some_and(|size| size == 0 || size > MAX_UPLOAD_BYTES)
+    {
+        return Err(handle_validation_error(
+            "size",
+            &format!("Must be between 1 and {} bytes", MAX_UPLOAD_BYTES),
+            "uploads",
+        ));
+    }
+    let mut settings = JsonMap::new();
+    if let Some(branch) = &body.branch {
+        settings.insert("branch".into(), json!(branch));
+    }
+    if let Some(options) = &body.options {
+        if let Err(e) = serde_json::from_value::<RepositoryOptionsPatch>(options.clone()) {
+            return Err(handle_validation_error(
+                "options",
+                &e.to_string(),
+                "uploads",
+            ));
+        }
+        settings.insert("options".into(), options.clone());
+    }
+
+    let api_key_id = store
+        .lookup_primary_api_key(user_uuid)
+        .await
+        .unwrap_or_else(|e| {
+            error!(
+                "Failed to resolve Supabase API key for user {}: {}",
+                user_uuid, e
+            );
+            None
+        });
+    let upload = match store
+        .create_source_upload(
+            user_uuid,
+            api_key_id,
+            &body.name,
+            &JsonValue::Object(settings),
+            body.size.map(|size| size as i64),
+        )
+        .await
+    {
+        Ok(upload) => upload,
+        Err(e) => {
+            error!("Failed to create source upload: {}", e);
+            return Err(internal_server_error("Failed to create upload"));
+        }
+    };
+    upload_response(&state, upload)
 }
 
-fn any_env_present(keys: &[&str]) -> bool {
-    keys.iter().any(|key| env_present(key))
+/// GET /api/v1/uploads/:upload_id - upload state and received parts, for resuming (SaaS)
+async fn get_upload_v1(
+    State(state): State<ServicesAppState>,
+    auth_context: Option<Extension<AuthContext>>,
+    Path(upload_id): Path<String>,
+) -> ApiResult<UploadResponse> {
+    let (store, user_uuid) = saas_store_and_user(&state, auth_context, "upload source archives")?;
+    let upload = fetch_upload(&store, &upload_id, user_uuid).await?;
+    upload_response(&state, upload)
 }
 
-fn validate_saas_environment(api_key_config: &crate::ApiKeyConfig) -> Result<()> {
-    if env_present("DISABLE_SAAS_ENV_VALIDATION") {
-        warn!("SaaS environment validation disabled via DISABLE_SAAS_ENV_VALIDATION");
-        return Ok(());
+/// PUT /api/v1/uploads/:upload_id/parts/:part_number - store one part; re-sending replaces it (SaaS)
+async fn put_upload_part_v1(
+    State(state): State<ServicesAppState>,
+    auth_context: Option<Extension<AuthContext>>,
+    Path((upload_id, part_number)): Path<(String, String)>,
+    body: axum::body::Body,
+) -> ApiResult<UploadPartResponse> {
+    use futures::StreamExt;
+    use tokio::io::AsyncWriteExt;
+
+    let (store, user_uuid) = saas_store_and_user(&state, auth_context, "upload source archives")?;
+    let upload = pending_upload(&store, &upload_id, user_uuid).await?;
+    let part_number = part_number
+        .parse::<u32>()
+        .ok()
+        .filter(|number| (1..=MAX_UPLOAD_PARTS).contains(number))
+        .ok_or_else(|| {
+            handle_validation_error(
+                "part_number",
+                &format!("Must be between 1 and {}", MAX_UPLOAD_PARTS),
+                "uploads",
+            )
+        })?;
+
+    let dir = upload_dir(&state.db_path, upload.id);
+    let other_parts: u64 = list_parts(&dir)
+        .map_err(|e| {
+            error!("Failed to list parts of upload {}: {}", upload.id, e);
+            internal_server_error("Failed to read upload parts")
+        })?
+        .iter()
+        .filter(|part| part.part_number != part_number)
+        .map(|part| part.size)
+        .sum();
+    let limit = MAX_UPLOAD_PART_BYTES.min(MAX_UPLOAD_BYTES.saturating_sub(other_parts));
+
+    let io_error = |e: std::io::Error| {
+        error!("Failed to store part of upload {}: {}", upload.id, e);
+        internal_server_error("Failed to store upload part")
+    };
+    tokio::fs::create_dir_all(&dir).await.map_err(io_error)?;
+    // Parts are written aside and renamed so a dropped connection never leaves a short part
+    let staging = dir.join(format!(".part-{:05}-{}", part_number, Uuid::new_v4()));
+    let mut file = tokio::fs::File::create(&staging).await.map_err(io_error)?;
+    let mut hasher = Sha256::new();
+    let mut size = 0u64;
+    let mut stream = body.into_data_stream();
+    let written = async {
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk.map_err(|_| {
+                handle_validation_error("body", "Request body ended unexpectedly", "uploads")
+            })?;
+            size += chunk.len() as u64;
+            if size > limit {
+                return Err(upload_error(
+                    StatusCode::PAYLOAD_TOO_LARGE,
+                    "part_too_large",
+                    format!(
+                        "Parts are limited to {} bytes and archives to {} bytes",
+                        MAX_UPLOAD_PART_BYTES, MAX_UPLOAD_BYTES
+                    ),
+                    "Split the archive into smaller parts",
+                ));
+            }
+            hasher.update(&chunk);
+            file.write_all(&chunk).await.map_err(io_error)?;
+        }
+        file.sync_all().await.map_err(io_error)
+    }
+    .await;
+    if let Err(e) = written {
+        tokio::fs::remove_file(&staging).await.ok();
+        return Err(e);
+    }
+    if size == 0 {
+        tokio::fs::remove_file(&staging).await.ok();
+        return Err(handle_validation_error(
+            "body",
+            "Part must not be empty",
+            "uploads",
+        ));
     }
+    tokio::fs::rename(&staging, part_path(&dir, part_number))
+        .await
+        .map_err(io_error)?;
 
-    let database_configured = !api_key_config.database_url.trim().is_empty()
-        || env_present("DATABASE_URL")
-        || any_env_present(SUPABASE_DB_ENV_CHOICES);
+    Ok(Json(UploadPartResponse {
+        part_number,
+        size,
+        sha256: hex::encode(hasher.finalize()),
+    }))
+}
 
-    if !database_configured {
-        let message = "Database connection not configured. Provide ApiKeyConfig.database_url or set DATABASE_URL/SUPABASE_DB_URL";
-        error!("{}", message);
-        return Err(anyhow!(message));
+/// POST /api/v1/uploads/:upload_id/complete - close the upload and queue indexing (SaaS)
+async fn complete_upload_v1(
+    State(state): State<ServicesAppState>,
+    auth_context: Option<Extension<AuthContext>>,
+    Path(upload_id): Path<String>,
+) -> Result<(StatusCode, Json<CompleteUploadResponse>), (StatusCode, Json<StandardApiError>)> {
+    let (store, user_uuid) = saas_store_and_user(&state, auth_context, "upload source archives")?;
+    let upload = pending_upload(&store, &upload_id, user_uuid).await?;
+
+    let parts = list_parts(&upload_dir(&state.db_path, upload.id)).map_err(|e| {
+        error!("Failed to list parts of upload {}: {}", upload.id, e);
+        internal_server_error("Failed to read upload parts")
+    })?;
+    // Parts are written concurrently, so only the completed set bounds the archive size
+    let received = total_size(&parts);
+    if received > MAX_UPLOAD_BYTES {
+        return Err(upload_error(
+            StatusCode::PAYLOAD_TOO_LARGE,
+            "upload_too_large",
+            format!(
+                "Received {} bytes but archives are limited to {} bytes",
+                received, MAX_UPLOAD_BYTES
+            ),
+            "Start a new upload with a smaller archive",
+        ));
+    }
+    let size = contiguous_size(&parts).map_err(|e| {
+        upload_error(
+            StatusCode::CONFLICT,
+            "upload_incomplete",
+            format!("Upload is incomplete: {}", e),
+            "Check received parts with GET /api/v1/uploads/:upload_id and resend missing ones",
+        )
+    })?;
+    if let Some(expected) = upload
+        .expected_size
+        .filter(|&expected| expected as u64 != size)
+    {
+        return Err(upload_error(
+            StatusCode::CONFLICT,
+            "upload_incomplete",
+            format!(
+                "Received {} bytes but the upload declared {}",
+                size, expected
+            ),
+            "Check received parts with GET /api/v1/uploads/:upload_id and resend missing ones",
+        ));
     }
 
-    let mut missing_optional = Vec::new();
+    let repository = match store.fetch_repository(upload.repository_id).await {
+        Ok(Some(repository)) => repository,
+        Ok(None) => {
+            return Err(handle_not_found_error(
+                "repository_id",
+                "Repository not found",
+                "uploads",
+            ))
+        }
+        Err(e) => {
+            error!("Failed to load repository {}: {}", upload.repository_id, e);
+            return Err(internal_server_error("Failed to load repository"));
+        }
+    };
+    let payload = json!({
+        "git_url": repository.git_url,
+        "provider": repository.provider,
+        "branch": repository.default_branch,
+        "requested_at": Utc::now().to_rfc3339(),
+        "upload_id": upload.id,
+    });
 
-    for key in IMPORTANT_SAAS_ENV_VARS {
-        if !env_present(key) {
-            missing_optional.push(*key);
+    match store
+        .complete_source_upload(upload.id, user_uuid, repository.api_key_id, &payload)
+        .await
+    {
+        Ok(Some(job_id)) => Ok((
+            StatusCode::ACCEPTED,
+            Json(CompleteUploadResponse {
+                upload_id: upload.id.to_string(),
+                repository_id: upload.repository_id.to_string(),
+                job_id: job_id.to_string(),
+                status: "queued".into(),
+                parts: parts.len(),
+                size,
+            }),
+        )),
+        Ok(None) => Err(upload_error(
+            StatusCode::CONFLICT,
+            "upload_closed",
+            "Upload is no longer pending",
+            "Create a new upload to send another archive",
+        )),
+        Err(e) => {
+            error!("Failed to complete upload {}: {}", upload.id, e);
+            Err(internal_server_error("Failed to complete upload"))
         }
     }
+}
 
-    for key in IMPORTANT_GITHUB_ENV_VARS {
-        if !env_present(key) {
-            missing_optional.push(*key);
+/// DELETE /api/v1/uploads/:upload_id - abort a pending upload and discard its parts (SaaS)
+async fn abort_upload_v1(
+    State(state): State<ServicesAppState>,
+    auth_context: Option<Extension<AuthContext>>,
+    Path(upload_id): Path<String>,
+) -> Result<StatusCode, (StatusCode, Json<StandardApiError>)> {
+    let (store, user_uuid) = saas_store_and_user(&state, auth_context, "upload source archives")?;
+    let upload = pending_upload(&store, &upload_id, user_uuid).await?;
+    match store.abort_source_upload(upload.id, user_uuid).await {
+        Ok(true) => {
+            tokio::fs::remove_dir_all(upload_dir(&state.db_path, upload.id))
+                .await
+                .ok();
+            Ok(StatusCode::NO_CONTENT)
+        }
+        Ok(false) => Err(upload_error(
+            StatusCode::CONFLICT,
+            "upload_closed",
+            "Upload is no longer pending",
+            "Create a new upload to send another archive",
+        )),
+        Err(e) => {
+            error!("Failed to abort upload {}: {}", upload.id, e);
+            Err(internal_server_error("Failed to abort upload"))
         }
     }
+}
 
-    if !missing_optional.is_empty() {
-        warn!(
-            missing = ?missing_optional,
-            "Optional SaaS environment variables are not set. Functionality relying on them may be limited"
-        );
-    }
+/// v1 account deletion request response; the confirmation token is only returned here
+#[derive(Debug, Serialize)]
+pub struct AccountDeletionResponse {
+    #[serde(flatten)]
+    pub request: AccountDataRequestRow,
+    pub confirmation_token: String,
+    pub confirm_path: String,
+}
 
-    Ok(())
+/// v1 account deletion confirmation request
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConfirmAccountDeletionRequest {
+    pub confirmation_token: String,
 }
 
-async fn fetch_saas_health(state: &ServicesAppState) -> SaasHealth {
-    let mut health = SaasHealth {
-        supabase_status: "not_configured".to_string(),
-        supabase_latency_ms: None,
-        job_queue: JobQueueHealth::default(),
-    };
+/// v1 listing of the caller's exports and deletions
+#[derive(Debug, Serialize)]
+pub struct ListAccountDataRequestsResponse {
+    pub requests: Vec<AccountDataRequestRow>,
+}
 
-    let Some(pool) = &state.supabase_pool else {
-        return health;
-    };
+/// Stored documents read per storage page while building an account export
+const EXPORT_PAGE_SIZE: usize = 256;
 
-    let start = Instant::now();
-    match sqlx::query(
-        "SELECT status, COUNT(*)::bigint AS job_count FROM indexing_jobs GROUP BY status",
-    )
-    .fetch_all(pool)
-    .await
-    {
-        Ok(rows) => {
-            health.supabase_status = "ok".to_string();
-            health.supabase_latency_ms = Some(start.elapsed().as_millis());
-            for row in rows {
-                let status = row
-                    .try_get::<String, _>("status")
-                    .unwrap_or_else(|_| "unknown".to_string());
-                let count = row.try_get::<i64, _>("job_count").unwrap_or(0).max(0) as usize;
-                match status.as_str() {
-                    "queued" => health.job_queue.queued = count,
-                    "in_progress" => health.job_queue.in_progress = count,
-                    "failed" => health.job_queue.failed = count,
-                    _ => {}
-                }
+/// GET /api/v1/account/export - download the caller's metadata, annotations and documents (SaaS)
+async fn export_account_v1(
+    State(state): State<ServicesAppState>,
+    auth_context: Option<Extension<AuthContext>>,
+) -> Result<axum::response::Response, (StatusCode, Json<StandardApiError>)> {
+    use axum::response::IntoResponse;
+    use std::io::{Seek, SeekFrom};
+    use tokio::io::AsyncReadExt;
+
+    let key_id = auth_context
+        .as_ref()
+        .map_or(0, |Extension(auth)| auth.key_id);
+    let annotations = annotations_dir(&state, auth_context.as_ref())?;
+    let (store, user_uuid) = saas_store_and_user(&state, auth_context, "export account data")?;
+
+    let export_failed = |e: anyhow::Error| {
+        error!("Failed to export account {}: {}", user_uuid, e);
+        internal_server_error("Failed to export account data")
+    };
+    let tables = store
+        .export_account_tables(user_uuid)
+        .await
+        .map_err(export_failed)?;
+    let prefixes: Vec<String> = store
+        .account_repositories(user_uuid)
+        .await
+        .map_err(export_failed)?
+        .iter()
+        .map(|repo| document_prefix(&repo.git_url, &repo.settings))
+        .collect::<std::collections::BTreeSet<_>>()
+        .into_iter()
+        .collect();
+    // Documents are read and archived one storage page at a time, holding the storage lock
+    // only while a page is read
+    let archive_tables = tables.clone();
+    let mut archive = tokio::task::spawn_blocking(move || {
+        ExportArchive::new(tempfile::tempfile()?, &archive_tables, Some(&annotations))
+    })
+    .await
+    .map_err(anyhow::Error::from)
+    .and_then(|result| result)
+    .map_err(export_failed)?;
+    let mut cursor = None;
+    loop {
+        let page = state
+            .storage
+            .lock_timed(LockedResource::Storage)
+            .await
+            .list_page(cursor, EXPORT_PAGE_SIZE)
+            .await
+            .map_err(export_failed)?;
+        let documents: Vec<_> = page
+            .documents
+            .into_iter()
+            .filter(|document| {
+                let path = document.path.as_str().trim_start_matches('/');
+                prefixes
+                    .iter()
+                    .any(|prefix| path.starts_with(prefix.as_str()))
+            })
+            .collect();
+        if !documents.is_empty() {
+            archive = tokio::task::spawn_blocking(move || {
+                archive.append_documents(&documents).map(|()| archive)
+            })
+            .await
+            .map_err(anyhow::Error::from)
+            .and_then(|result| result)
+            .map_err(export_failed)?;
+        }
+        match page.next {
+            Some(next) => cursor = Some(next),
+            None => break,
+        }
+    }
+
+    let manifest = export_manifest(user_uuid, &tables, &prefixes, archive.documents());
+    let archive_manifest = manifest.clone();
+    let file = tokio::task::spawn_blocking(move || -> Result<std::fs::File> {
+        let mut file = archive.finish(&archive_manifest)?;
+        file.seek(SeekFrom::Start(0))?;
+        Ok(file)
+    })
+    .await
+    .map_err(anyhow::Error::from)
+    .and_then(|result| result)
+    .map_err(export_failed)?;
+
+    let request = store
+        .record_account_export(user_uuid, key_id, &manifest)
+        .await
+        .map_err(export_failed)?;
+
+    let body = futures::stream::unfold(tokio::fs::File::from_std(file), |mut file| async move {
+        let mut buffer = vec![0u8; 64 * 1024];
+        match file.read(&mut buffer).await {
+            Ok(0) => None,
+            Ok(read) => {
+                buffer.truncate(read);
+                Some((Ok(Bytes::from(buffer)), file))
             }
+            Err(e) => Some((Err(e), file)),
         }
+    });
+    let filename = format!(
+        "kotadb-export-{}.tar.gz",
+        request.created_at.format("%Y%m%dT%H%M%SZ")
+    );
+    Ok((
+        StatusCode::OK,
+        [
+            (
+                axum::http::header::CONTENT_TYPE,
+                "application/gzip".to_string(),
+            ),
+            (
+                axum::http::header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}\"", filename),
+            ),
+            (
+                axum::http::HeaderName::from_static("x-kotadb-export-id"),
+                request.id.to_string(),
+            ),
+        ],
+        axum::body::Body::from_stream(body),
+    )
+        .into_response())
+}
+
+/// POST /api/v1/account/deletion - start deleting the caller's account data (SaaS)
+async fn request_account_deletion_v1(
+    State(state): State<ServicesAppState>,
+    auth_context: Option<Extension<AuthContext>>,
+) -> ApiResult<AccountDeletionResponse> {
+    let key_id = auth_context
+        .as_ref()
+        .map_or(0, |Extension(auth)| auth.key_id);
+    let (store, user_uuid) = saas_store_and_user(&state, auth_context, "delete account data")?;
+    match store.request_account_deletion(user_uuid, key_id).await {
+        Ok((request, confirmation_token)) => Ok(Json(AccountDeletionResponse {
+            confirm_path: format!("/api/v1/account/deletion/{}/confirm", request.id),
+            request,
+            confirmation_token,
+        })),
         Err(e) => {
-            health.supabase_status = "error".to_string();
-            error!("Supabase health query failed: {}", e);
+            error!("Failed to open deletion request for {}: {}", user_uuid, e);
+            Err(internal_server_error("Failed to request account deletion"))
         }
     }
+}
 
-    if let Ok(failed_recent) = sqlx::query_scalar::<_, i64>(
-        "SELECT COUNT(*) FROM indexing_jobs WHERE status = 'failed' AND finished_at > NOW() - INTERVAL '1 hour'",
-    )
-    .fetch_one(pool)
-    .await
+/// POST /api/v1/account/deletion/:request_id/confirm - schedule a requested deletion (SaaS)
+async fn confirm_account_deletion_v1(
+    State(state): State<ServicesAppState>,
+    auth_context: Option<Extension<AuthContext>>,
+    Path(request_id): Path<String>,
+    Json(body): Json<ConfirmAccountDeletionRequest>,
+) -> Result<(StatusCode, Json<AccountDataRequestRow>), (StatusCode, Json<StandardApiError>)> {
+    let (store, user_uuid) = saas_store_and_user(&state, auth_context, "delete account data")?;
+    let request_uuid = Uuid::parse_str(&request_id).map_err(|_| {
+        handle_validation_error("request_id", "Invalid request id format", "account")
+    })?;
+    let request_again = "Request a new deletion with POST /api/v1/account/deletion";
+    match store
+        .confirm_account_deletion(request_uuid, user_uuid, body.confirmation_token.trim())
+        .await
     {
-        health.job_queue.failed_recent = failed_recent.max(0) as usize;
+        Ok(DeletionConfirmation::Scheduled(request)) => Ok((StatusCode::ACCEPTED, Json(request))),
+        Ok(DeletionConfirmation::NotFound) => Err(handle_not_found_error(
+            "request_id",
+            "Deletion request not found",
+            "account",
+        )),
+        Ok(DeletionConfirmation::NotPending(status)) => Err(saas_error(
+            "account",
+            StatusCode::CONFLICT,
+            "deletion_not_pending",
+            format!("Deletion request is {}", status),
+            request_again,
+        )),
+        Ok(DeletionConfirmation::Expired) => Err(saas_error(
+            "account",
+            StatusCode::GONE,
+            "confirmation_expired",
+            format!(
+                "Confirmation tokens expire after {} minutes",
+                DELETION_CONFIRMATION_TTL_MINUTES
+            ),
+            request_again,
+        )),
+        Ok(DeletionConfirmation::InvalidToken) => Err(saas_error(
+            "account",
+            StatusCode::FORBIDDEN,
+            "invalid_confirmation_token",
+            "Confirmation token does not match this request",
+            "Use the confirmation_token returned when the deletion was requested",
+        )),
+        Err(e) => {
+            error!("Failed to confirm deletion {}: {}", request_uuid, e);
+            Err(internal_server_error("Failed to confirm account deletion"))
+        }
     }
+}
 
-    if let Ok(Some(ts)) = sqlx::query_scalar::<_, Option<chrono::DateTime<Utc>>>(
-        "SELECT MIN(queued_at) FROM indexing_jobs WHERE status = 'queued'",
-    )
-    .fetch_one(pool)
-    .await
-    {
-        let age = Utc::now().signed_duration_since(ts).num_seconds().max(0) as u64;
-        health.job_queue.oldest_queued_seconds = Some(age);
+/// DELETE /api/v1/account/deletion/:request_id - cancel a deletion the worker has not started (SaaS)
+async fn cancel_account_deletion_v1(
+    State(state): State<ServicesAppState>,
+    auth_context: Option<Extension<AuthContext>>,
+    Path(request_id): Path<String>,
+) -> Result<StatusCode, (StatusCode, Json<StandardApiError>)> {
+    let (store, user_uuid) = saas_store_and_user(&state, auth_context, "delete account data")?;
+    let request_uuid = Uuid::parse_str(&request_id).map_err(|_| {
+        handle_validation_error("request_id", "Invalid request id format", "account")
+    })?;
+    match store.cancel_account_deletion(request_uuid, user_uuid).await {
+        Ok(true) => Ok(StatusCode::NO_CONTENT),
+        Ok(false) => Err(handle_not_found_error(
+            "request_id",
+            "No pending or scheduled deletion with this id",
+            "account",
+        )),
+        Err(e) => {
+            error!("Failed to cancel deletion {}: {}", request_uuid, e);
+            Err(internal_server_error("Failed to cancel account deletion"))
+        }
     }
+}
 
-    health
+/// GET /api/v1/account/requests - audit trail of the caller's exports and deletions (SaaS)
+async fn list_account_requests_v1(
+    State(state): State<ServicesAppState>,
+    auth_context: Option<Extension<AuthContext>>,
+) -> ApiResult<ListAccountDataRequestsResponse> {
+    let (store, user_uuid) =
+        saas_store_and_user(&state, auth_context, "view account data requests")?;
+    match store.list_account_data_requests(user_uuid).await {
+        Ok(requests) => Ok(Json(ListAccountDataRequestsResponse { requests })),
+        Err(e) => {
+            error!("Failed to list account data requests: {}", e);
+            Err(internal_server_error(
+                "Failed to list account data requests",
+            ))
+        }
+    }
 }
 
-/// GET /api/v1/repositories
-async fn list_repositories_v1(
+/// v1 signed artifact URL request
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CreateSignedUrlRequest {
+    /// Artifact path and query as it appears in the URL, e.g. `/api/v1/overview/treemap?depth=2`
+    pub path: String,
+    pub expires_in_seconds: Option<i64>,
+}
+
+/// v1 signed artifact URL response
+#[derive(Debug, Serialize)]
+pub struct SignedUrlResponse {
+    /// Absolute URL when the server knows its public base URL, otherwise the signed path
+    pub url: String,
+    pub path: String,
+    pub expires_at: chrono::DateTime<Utc>,
+}
+
+/// POST /api/v1/artifacts/signed-urls - temporary read access to an artifact without an API key (SaaS)
+async fn create_signed_url_v1(
     State(state): State<ServicesAppState>,
     auth_context: Option<Extension<AuthContext>>,
-) -> ApiResult<ListRepositoriesResponse> {
-    if state.is_saas_mode() {
-        return list_repositories_saas(&state, auth_context).await;
+    Json(body): Json<CreateSignedUrlRequest>,
+) -> ApiResult<SignedUrlResponse> {
+    let Some(Extension(auth)) = auth_context else {
+        return Err(unauthorized_error("Authentication required to sign URLs"));
+    };
+    let Some(signer) = UrlSigner::from_env() else {
+        return Err(saas_error(
+            "artifacts",
+            StatusCode::SERVICE_UNAVAILABLE,
+            "signed_urls_disabled",
+            "Signed URLs are not enabled on this server",
+            &format!("Set {} to enable signed URLs", URL_SIGNING_KEY_ENV),
+        ));
+    };
+
+    let (path, query) = match body.path.trim().split_once('?') {
+        Some((path, query)) => (path, Some(query).filter(|query| !query.is_empty())),
+        None => (body.path.trim(), None),
+    };
+    if !is_shareable_path(path) {
+        return Err(handle_validation_error(
+            "path",
+            "Only /api/v1/codebase-overview, /api/v1/overview/treemap, /api/v1/analysis/stats, /api/v1/analysis/trends and /api/v1/symbols/:symbol/graph.svg can be shared",
+            "artifacts",
+        ));
+    }
+    let ttl = body
+        .expires_in_seconds
+        .unwrap_or(DEFAULT_SIGNED_URL_TTL_SECS);
+    if !(1..=MAX_SIGNED_URL_TTL_SECS).contains(&ttl) {
+        return Err(handle_validation_error(
+            "expires_in_seconds",
+            &format!("Must be between 1 and {}", MAX_SIGNED_URL_TTL_SECS),
+            "artifacts",
+        ));
     }
 
-    let repos = state.repositories.read().await.clone();
-    Ok(Json(ListRepositoriesResponse {
-        repositories: repos,
+    let expires_at = Utc::now() + chrono::Duration::seconds(ttl);
+    let signed = signer
+        .sign(path, query, auth.key_id, expires_at)
+        .map_err(|e| handle_validation_error("path", &e.to_string(), "artifacts"))?;
+    let url = state
+        .webhook_base_url
+        .as_ref()
+        .and_then(|base| base.join(&signed).ok())
+        .map(|url| url.to_string())
+        .unwrap_or_else(|| signed.clone());
+
+    Ok(Json(SignedUrlResponse {
+        url,
+        path: signed,
+        expires_at,
     }))
 }
 
@@ -2714,6 +7391,70 @@ struct IndexStatusQuery {
     job_id: String,
 }
 
+#[derive(Debug, Deserialize)]
+struct TokenUsageQuery {
+    session: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct TokenUsageResponse {
+    /// Accounting is in-memory; counts cover the period since this timestamp
+    since: chrono::DateTime<Utc>,
+    consumers: Vec<ConsumerTokenUsage>,
+    totals: TokenUsageTotals,
+    /// Share of raw-file tokens avoided by returning targeted results (0.0-1.0)
+    savings_ratio: f64,
+}
+
+/// GET /api/v1/usage/tokens
+///
+/// In SaaS mode only the calling API key's usage is returned.
+async fn token_usage_v1(
+    State(state): State<ServicesAppState>,
+    auth_context: Option<Extension<AuthContext>>,
+    AxumQuery(q): AxumQuery<TokenUsageQuery>,
+) -> ApiResult<TokenUsageResponse> {
+    let key_filter = if state.is_saas_mode() {
+        match auth_context {
+            Some(Extension(auth)) => Some(auth.key_id),
+            None => return Err(unauthorized_error("Missing authentication context")),
+        }
+    } else {
+        None
+    };
+
+    let mut consumers: Vec<ConsumerTokenUsage> = state
+        .token_usage
+        .consumers
+        .read()
+        .await
+        .values()
+        .filter(|usage| key_filter.is_none() || usage.consumer.api_key_id == key_filter)
+        .filter(|usage| {
+            q.session.is_none() || usage.consumer.session.as_deref() == q.session.as_deref()
+        })
+        .cloned()
+        .collect();
+    consumers.sort_by_key(|usage| std::cmp::Reverse(usage.last_seen));
+
+    let mut totals = TokenUsageTotals::default();
+    for usage in &consumers {
+        totals.merge(&usage.totals);
+    }
+    let savings_ratio = if totals.raw_file_tokens == 0 {
+        0.0
+    } else {
+        totals.tokens_saved as f64 / totals.raw_file_tokens as f64
+    };
+
+    Ok(Json(TokenUsageResponse {
+        since: state.token_usage.started_at,
+        consumers,
+        totals,
+        savings_ratio,
+    }))
+}
+
 async fn index_status_v1(
     State(state): State<ServicesAppState>,
     AxumQuery(q): AxumQuery<IndexStatusQuery>,
@@ -2942,7 +7683,7 @@ async fn validate_database(
             storage: state.storage.clone(),
             primary_index: state.primary_index.clone(),
             trigram_index: state.trigram_index.clone(),
-            path_cache: Arc::new(RwLock::new(HashMap::new())),
+            path_cache: state.path_cache.clone(),
         };
 
         let validation_service = ValidationService::new(&database, state.db_path.clone());
@@ -2996,7 +7737,7 @@ async fn health_check_detailed(
             storage: state.storage.clone(),
             primary_index: state.primary_index.clone(),
             trigram_index: state.trigram_index.clone(),
-            path_cache: Arc::new(RwLock::new(HashMap::new())),
+            path_cache: state.path_cache.clone(),
         };
 
         let validation_service = ValidationService::new(&database, state.db_path.clone());
@@ -3056,13 +7797,14 @@ async fn index_codebase(
         ));
     }
 
+    let data_generation = state.data_generation.clone();
     let result = with_trace_id("api_index_codebase", async move {
         // Create Database instance to implement DatabaseAccess
         let database = Database {
             storage: state.storage.clone(),
             primary_index: state.primary_index.clone(),
             trigram_index: state.trigram_index.clone(),
-            path_cache: Arc::new(RwLock::new(HashMap::new())),
+            path_cache: state.path_cache.clone(),
         };
 
         let indexing_service = IndexingService::new(&database, state.db_path.clone());
@@ -3086,6 +7828,7 @@ async fn index_codebase(
         indexing_service.index_codebase(options).await
     })
     .await;
+    data_generation.fetch_add(1, Ordering::SeqCst);
 
     match result {
         Ok(index_result) => {
@@ -3113,46 +7856,210 @@ async fn index_codebase(
     }
 }
 
+/// Symbols and entry points listed by a codebase overview unless requested otherwise
+const DEFAULT_OVERVIEW_LIMIT: usize = 10;
+
+fn default_overview_options() -> OverviewOptions {
+    OverviewOptions {
+        format: "json".to_string(),
+        top_symbols_limit: DEFAULT_OVERVIEW_LIMIT,
+        entry_points_limit: DEFAULT_OVERVIEW_LIMIT,
+        quiet: false,
+    }
+}
+
+fn is_default_overview(options: &OverviewOptions) -> bool {
+    let default = default_overview_options();
+    options.format == default.format
+        && options.top_symbols_limit == default.top_symbols_limit
+        && options.entry_points_limit == default.entry_points_limit
+}
+
+/// Fingerprint of the indexed data an overview was computed from
+async fn overview_fingerprint(state: &ServicesAppState) -> String {
+    data_etag(
+        state,
+        &axum::http::Uri::from_static("/api/v1/codebase-overview"),
+        &HeaderMap::new(),
+    )
+    .await
+}
+
+/// The precomputed default overview, unless the data changed since it was computed
+async fn cached_overview(state: &ServicesAppState) -> Option<serde_json::Value> {
+    let cached = state.overview_cache.read().await.clone()?;
+    (cached.fingerprint == overview_fingerprint(state).await).then_some(cached.value)
+}
+
+/// Overview refresh maintenance task: compute the default overview ahead of requests
+async fn refresh_overview(state: &ServicesAppState) -> Result<TaskReport> {
+    let fingerprint = overview_fingerprint(state).await;
+    let database = Database {
+        storage: state.storage.clone(),
+        primary_index: state.primary_index.clone(),
+        trigram_index: state.trigram_index.clone(),
+        path_cache: state.path_cache.clone(),
+    };
+    let overview = AnalysisService::new(&database, state.db_path.clone())
+        .generate_overview(default_overview_options())
+        .await?;
+    let count = |key: &str| overview.overview_data.get(key).cloned().unwrap_or_default();
+    let detail = format!(
+        "{} files, {} symbols",
+        count("total_files"),
+        count("total_symbols")
+    );
+    *state.overview_cache.write().await = Some(CachedOverview {
+        fingerprint,
+        value: serde_json::to_value(overview)?,
+    });
+    Ok(TaskReport { items: 1, detail })
+}
+
+/// Run the enabled maintenance tasks during the configured window
+fn spawn_maintenance_scheduler(state: ServicesAppState, config: MaintenanceConfig) {
+    if let Some(window) = config.window {
+        info!("Maintenance scheduled daily {}", window);
+    }
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(MAINTENANCE_CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Err(e) = run_due_maintenance(&state, &config).await {
+                warn!("Maintenance check failed: {e:#}");
+            }
+        }
+    });
+}
+
+/// Run the tasks due now, recording each run in `maintenance.json`
+async fn run_due_maintenance(state: &ServicesAppState, config: &MaintenanceConfig) -> Result<()> {
+    let now = Utc::now();
+    let mut status = MaintenanceStatus::load(&state.db_path)?;
+    let recorded = status.clone();
+    status.configure(config, now);
+    for task in config.due_tasks(&status, now) {
+        let run = run_maintenance_task(state, config, task).await;
+        status.last_runs.insert(task, run);
+        status.save(&state.db_path)?;
+    }
+    if status != recorded {
+        status.save(&state.db_path)?;
+    }
+    Ok(())
+}
+
+async fn run_maintenance_task(
+    state: &ServicesAppState,
+    config: &MaintenanceConfig,
+    task: MaintenanceTask,
+) -> TaskRun {
+    let database = Database {
+        storage: state.storage.clone(),
+        primary_index: state.primary_index.clone(),
+        trigram_index: state.trigram_index.clone(),
+        path_cache: state.path_cache.clone(),
+    };
+    match task {
+        MaintenanceTask::Compaction => run_task(task, maintenance::compact(&database)).await,
+        MaintenanceTask::CacheWarmup => {
+            run_task(task, async {
+                let mut report = maintenance::warm_path_cache(&database).await?;
+                if semantic_engine(state).await.is_some() {
+                    report.detail.push_str("; vector search opened");
+                }
+                Ok(report)
+            })
+            .await
+        }
+        MaintenanceTask::OverviewRefresh => run_task(task, refresh_overview(state)).await,
+        MaintenanceTask::SnapshotPruning => {
+            let (db_path, keep) = (state.db_path.clone(), config.keep_snapshots);
+            run_task(task, async move {
+                tokio::task::spawn_blocking(move || maintenance::prune(&db_path, keep)).await?
+            })
+            .await
+        }
+    }
+}
+
+/// GET /api/v1/maintenance - scheduler configuration and the last run of each task
+async fn maintenance_status_v1(
+    State(state): State<ServicesAppState>,
+) -> ApiResult<serde_json::Value> {
+    let status = MaintenanceStatus::load(&state.db_path)
+        .map_err(|e| handle_service_error(e, "maintenance"))?;
+    Ok(Json(serde_json::to_value(status).map_err(|e| {
+        handle_service_error(anyhow::anyhow!(e), "maintenance")
+    })?))
+}
+
 /// Get codebase overview via AnalysisService
 async fn codebase_overview(
     State(state): State<ServicesAppState>,
+    auth_context: Option<Extension<AuthContext>>,
     AxumQuery(request): AxumQuery<CodebaseOverviewRequest>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, Json<ErrorResponse>)> {
+    redaction_unsupported(auth_context.as_ref())?;
+    let scope = legacy_repository_scope(
+        &state,
+        auth_context.as_ref(),
+        request.repository_id.as_deref(),
+        "codebase-overview",
+    )
+    .await?;
+    if let Some(format) = request
+        .format
+        .as_deref()
+        .filter(|format| !OVERVIEW_FORMATS.contains(format))
+    {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: "invalid_format".to_string(),
+                message: format!(
+                    "Unknown overview format '{}' (expected one of: {})",
+                    format,
+                    OVERVIEW_FORMATS.join(", ")
+                ),
+            }),
+        ));
+    }
     let result = with_trace_id("api_codebase_overview", async move {
         // Create Database instance to implement DatabaseAccess
         let database = Database {
             storage: state.storage.clone(),
             primary_index: state.primary_index.clone(),
             trigram_index: state.trigram_index.clone(),
-            path_cache: Arc::new(RwLock::new(HashMap::new())),
+            path_cache: state.path_cache.clone(),
         };
 
-        let analysis_service = AnalysisService::new(&database, state.db_path.clone());
+        let scoped = scope.is_some();
+        let mut analysis_service = AnalysisService::new(&database, state.db_path.clone());
+        if let Some(scope) = scope {
+            analysis_service = analysis_service.with_repository_scope(scope);
+        }
 
         let options = OverviewOptions {
             format: request.format.unwrap_or_else(|| "json".to_string()),
-            top_symbols_limit: request.top_symbols_limit.unwrap_or(10),
-            entry_points_limit: request.entry_points_limit.unwrap_or(10),
+            top_symbols_limit: request.top_symbols_limit.unwrap_or(DEFAULT_OVERVIEW_LIMIT),
+            entry_points_limit: request.entry_points_limit.unwrap_or(DEFAULT_OVERVIEW_LIMIT),
             quiet: false,
         };
+        // The precomputed overview covers the whole database
+        if !scoped && is_default_overview(&options) {
+            if let Some(cached) = cached_overview(&state).await {
+                return Ok(cached);
+            }
+        }
 
-        analysis_service.generate_overview(options).await
+        let overview_result = analysis_service.generate_overview(options).await?;
+        Ok(serde_json::to_value(overview_result)?)
     })
     .await;
 
     match result {
-        Ok(overview_result) => {
-            let json_value = serde_json::to_value(overview_result).map_err(|e| {
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(ErrorResponse {
-                        error: "serialization_failed".to_string(),
-                        message: e.to_string(),
-                    }),
-                )
-            })?;
-            Ok(Json(json_value))
-        }
+        Ok(json_value) => Ok(Json(json_value)),
         Err(e) => {
             tracing::warn!("Failed to get codebase overview: {}", e);
             Err((
@@ -3173,6 +8080,7 @@ async fn codebase_overview(
 /// Search code endpoint with format options and validation
 async fn search_code_enhanced(
     State(state): State<ServicesAppState>,
+    auth_context: Option<Extension<AuthContext>>,
     AxumQuery(request): AxumQuery<SearchRequest>,
 ) -> ApiResult<serde_json::Value> {
     // Validate query input using validation layer
@@ -3183,17 +8091,34 @@ async fn search_code_enhanced(
             "search-code",
         ));
     }
-
+    let group_by = parse_search_grouping(request.group_by.as_deref())?;
+
+    let annotations_dir = annotations_dir(&state, auth_context.as_ref())?;
+    let scope = repository_scope(
+        &state,
+        auth_context.as_ref(),
+        request.repository_id.as_deref(),
+        "search-code",
+    )
+    .await?;
+    let document_filters = request.document_filters();
     let result = with_trace_id("api_enhanced_search_code", async move {
         // Create Database instance to implement DatabaseAccess
         let database = Database {
             storage: state.storage.clone(),
             primary_index: state.primary_index.clone(),
             trigram_index: state.trigram_index.clone(),
-            path_cache: Arc::new(RwLock::new(HashMap::new())),
+            path_cache: state.path_cache.clone(),
         };
 
-        let search_service = SearchService::new(&database, state.db_path.clone());
+        let mut search_service = SearchService::new(&database, state.db_path.clone())
+            .with_redaction(redaction_rules(auth_context.as_ref()))
+            .with_annotations_dir(annotations_dir)
+            .with_deadline(QueryDeadline::for_server_request(request.timeout_ms))
+            .with_document_filters(document_filters);
+        if let Some(scope) = scope {
+            search_service = search_service.with_repository_scope(scope);
+        }
 
         let options = SearchOptions {
             query: request.query,
@@ -3201,6 +8126,7 @@ async fn search_code_enhanced(
             tags: None,
             context: request.search_type.unwrap_or_else(|| "medium".to_string()),
             quiet: false,
+            group_by,
         };
 
         search_service.search_content(options).await
@@ -3224,6 +8150,7 @@ async fn search_code_enhanced(
 /// Symbol search endpoint with format options
 async fn search_symbols_enhanced(
     State(state): State<ServicesAppState>,
+    auth_context: Option<Extension<AuthContext>>,
     AxumQuery(request): AxumQuery<SymbolSearchRequest>,
 ) -> ApiResult<serde_json::Value> {
     // Validate pattern input
@@ -3235,16 +8162,31 @@ async fn search_symbols_enhanced(
         ));
     }
 
+    let annotations_dir = annotations_dir(&state, auth_context.as_ref())?;
+    let scope = repository_scope(
+        &state,
+        auth_context.as_ref(),
+        request.repository_id.as_deref(),
+        "search-symbols",
+    )
+    .await?;
     let result = with_trace_id("api_enhanced_search_symbols", async move {
         // Create Database instance to implement DatabaseAccess
         let database = Database {
             storage: state.storage.clone(),
             primary_index: state.primary_index.clone(),
             trigram_index: state.trigram_index.clone(),
-            path_cache: Arc::new(RwLock::new(HashMap::new())),
+            path_cache: state.path_cache.clone(),
         };
 
-        let search_service = SearchService::new(&database, state.db_path.clone());
+        let mut search_service = SearchService::new(&database, state.db_path.clone())
+            .with_symbol_reader(state.symbol_reader.clone())
+            .with_redaction(redaction_rules(auth_context.as_ref()))
+            .with_annotations_dir(annotations_dir)
+            .with_deadline(QueryDeadline::for_server_request(request.timeout_ms));
+        if let Some(scope) = scope {
+            search_service = search_service.with_repository_scope(scope);
+        }
 
         let options = SymbolSearchOptions {
             pattern: request.pattern,
@@ -3274,6 +8216,7 @@ async fn search_symbols_enhanced(
 /// Find callers endpoint with format options and validation
 async fn find_callers_enhanced(
     State(state): State<ServicesAppState>,
+    auth_context: Option<Extension<AuthContext>>,
     request_result: Result<Json<CallersRequest>, axum::extract::rejection::JsonRejection>,
 ) -> ApiResult<serde_json::Value> {
     // Handle JSON parsing errors using shared error handler
@@ -3288,21 +8231,43 @@ async fn find_callers_enhanced(
         ));
     }
 
+    let annotations_dir = annotations_dir(&state, auth_context.as_ref())?;
+    let scope = repository_scope(
+        &state,
+        auth_context.as_ref(),
+        request.repository_id.as_deref(),
+        "find-callers",
+    )
+    .await?;
     let result = with_trace_id("api_enhanced_find_callers", async move {
         // Create Database instance to implement DatabaseAccess
         let database = Database {
             storage: state.storage.clone(),
             primary_index: state.primary_index.clone(),
             trigram_index: state.trigram_index.clone(),
-            path_cache: Arc::new(RwLock::new(HashMap::new())),
+            path_cache: state.path_cache.clone(),
         };
 
-        let mut analysis_service = AnalysisService::new(&database, state.db_path.clone());
+        let mut analysis_service = AnalysisService::new(&database, state.db_path.clone())
+            .with_redaction(redaction_rules(auth_context.as_ref()))
+            .with_annotations_dir(annotations_dir)
+            .with_deadline(QueryDeadline::for_server_request(request.timeout_ms));
+        if let Some(engine) = relationship_engine(&state).await {
+            analysis_service = analysis_service.with_relationship_engine(engine);
+        }
+        if let Some(scope) = scope {
+            analysis_service = analysis_service.with_repository_scope(scope);
+        }
 
         let options = CallersOptions {
             target: request.symbol,
             limit: request.limit,
+            offset: 0,
+            sort_by: RelationshipSortBy::default(),
+            group_by: ResultGrouping::default(),
             quiet: false,
+            explain: false,
+            context_lines: None,
         };
 
         analysis_service.find_callers(options).await
@@ -3317,24 +8282,18 @@ async fn find_callers_enhanced(
             let response_value = match format.as_str() {
                 "simple" => {
                     // Extract just the relevant caller information
-                    let simple_results: Vec<String> =
-                        if let Ok(json_val) = serde_json::to_value(&callers_result) {
-                            extract_simple_caller_results(&json_val)
-                        } else {
-                            vec!["Error parsing results".to_string()]
-                        };
+                    let simple_results = extract_simple_caller_results(&callers_result);
                     let count = simple_results.len();
 
                     serde_json::to_value(SimpleAnalysisResponse {
                         results: simple_results,
                         total_count: count,
+                        timed_out: callers_result.timed_out,
                     })
                     .map_err(|e| handle_service_error(anyhow::anyhow!(e), "find_callers"))?
                 }
                 "cli" => {
-                    let json_val = serde_json::to_value(&callers_result)
-                        .map_err(|e| handle_service_error(anyhow::anyhow!(e), "find_callers"))?;
-                    let cli_output = format_callers_as_cli(&json_val);
+                    let cli_output = format_callers_as_cli(&callers_result);
                     serde_json::to_value(CliFormatResponse { output: cli_output })
                         .map_err(|e| handle_service_error(anyhow::anyhow!(e), "find_callers"))?
                 }
@@ -3357,6 +8316,7 @@ async fn find_callers_enhanced(
 /// Impact analysis endpoint with format options and validation  
 async fn analyze_impact_enhanced(
     State(state): State<ServicesAppState>,
+    auth_context: Option<Extension<AuthContext>>,
     request_result: Result<Json<ImpactAnalysisRequest>, axum::extract::rejection::JsonRejection>,
 ) -> ApiResult<serde_json::Value> {
     // Handle JSON parsing errors using shared error handler
@@ -3372,21 +8332,43 @@ async fn analyze_impact_enhanced(
         ));
     }
 
+    let annotations_dir = annotations_dir(&state, auth_context.as_ref())?;
+    let scope = repository_scope(
+        &state,
+        auth_context.as_ref(),
+        request.repository_id.as_deref(),
+        "analyze-impact",
+    )
+    .await?;
     let result = with_trace_id("api_enhanced_analyze_impact", async move {
         // Create Database instance to implement DatabaseAccess
         let database = Database {
             storage: state.storage.clone(),
             primary_index: state.primary_index.clone(),
             trigram_index: state.trigram_index.clone(),
-            path_cache: Arc::new(RwLock::new(HashMap::new())),
+            path_cache: state.path_cache.clone(),
         };
 
-        let mut analysis_service = AnalysisService::new(&database, state.db_path.clone());
+        let mut analysis_service = AnalysisService::new(&database, state.db_path.clone())
+            .with_redaction(redaction_rules(auth_context.as_ref()))
+            .with_annotations_dir(annotations_dir)
+            .with_deadline(QueryDeadline::for_server_request(request.timeout_ms));
+        if let Some(engine) = relationship_engine(&state).await {
+            analysis_service = analysis_service.with_relationship_engine(engine);
+        }
+        if let Some(scope) = scope {
+            analysis_service = analysis_service.with_repository_scope(scope);
+        }
 
         let options = ImpactOptions {
             target: request.symbol,
             limit: request.limit,
+            offset: 0,
+            sort_by: RelationshipSortBy::default(),
+            group_by: ResultGrouping::default(),
             quiet: false,
+            explain: false,
+            context_lines: None,
         };
 
         analysis_service.analyze_impact(options).await
@@ -3401,24 +8383,18 @@ async fn analyze_impact_enhanced(
             let response_value = match format.as_str() {
                 "simple" => {
                     // Extract just the relevant impact information
-                    let simple_results: Vec<String> =
-                        if let Ok(json_val) = serde_json::to_value(&impact_result) {
-                            extract_simple_impact_results(&json_val)
-                        } else {
-                            vec!["Error parsing results".to_string()]
-                        };
+                    let simple_results = extract_simple_impact_results(&impact_result);
                     let count = simple_results.len();
 
                     serde_json::to_value(SimpleAnalysisResponse {
                         results: simple_results,
                         total_count: count,
+                        timed_out: impact_result.timed_out,
                     })
                     .map_err(|e| handle_service_error(anyhow::anyhow!(e), "analyze_impact"))?
                 }
                 "cli" => {
-                    let json_val = serde_json::to_value(&impact_result)
-                        .map_err(|e| handle_service_error(anyhow::anyhow!(e), "analyze_impact"))?;
-                    let cli_output = format_impact_as_cli(&json_val);
+                    let cli_output = format_impact_as_cli(&impact_result);
                     serde_json::to_value(CliFormatResponse { output: cli_output })
                         .map_err(|e| handle_service_error(anyhow::anyhow!(e), "analyze_impact"))?
                 }
@@ -3442,6 +8418,16 @@ async fn analyze_impact_enhanced(
 // FORMAT CONVERSION HELPERS - CLI and Simple Format Support
 // ================================================================================================
 
+fn parse_search_grouping(
+    group_by: Option<&str>,
+) -> Result<SearchGrouping, (StatusCode, Json<StandardApiError>)> {
+    group_by
+        .map(str::parse::<SearchGrouping>)
+        .transpose()
+        .map_err(|e| handle_validation_error("group_by", &e.to_string(), "search-code"))
+        .map(Option::unwrap_or_default)
+}
+
 fn render_search_code_response(
     search_result: &crate::services::search_service::SearchResult,
     format: &str,
@@ -3466,6 +8452,10 @@ fn render_search_code_response(
                 results: file_paths,
                 total_count: search_result.total_count,
                 query_time_ms: 0,
+                suggestions: search_result.suggestions.clone(),
+                annotations: search_result.annotations.clone(),
+                notes: search_result.notes.clone(),
+                timed_out: search_result.timed_out,
             })
         }
         "cli" => {
@@ -3490,6 +8480,10 @@ fn render_symbol_search_response(
             serde_json::to_value(SimpleSymbolResponse {
                 symbols: symbol_names,
                 total_count: symbol_result.total_symbols,
+                suggestions: symbol_result.suggestions.clone(),
+                annotations: symbol_result.annotations.clone(),
+                notes: symbol_result.notes.clone(),
+                timed_out: symbol_result.timed_out,
             })
         }
         "cli" => {
@@ -3518,9 +8512,17 @@ fn format_search_as_cli(search_result: &crate::services::search_service::SearchR
     }
 
     output.push_str(&format!("\nTotal matches: {}", search_result.total_count));
+    push_did_you_mean(&mut output, &search_result.suggestions);
     output
 }
 
+/// Append the "did you mean" line of CLI-style no-results output
+fn push_did_you_mean(output: &mut String, suggestions: &[String]) {
+    if !suggestions.is_empty() {
+        output.push_str(&format!("\nDid you mean: {}?", suggestions.join(", ")));
+    }
+}
+
 /// Format symbol search results as CLI-style output
 fn format_symbols_as_cli(symbol_result: &crate::services::search_service::SymbolResult) -> String {
     let mut output = String::new();
@@ -3537,93 +8539,60 @@ fn format_symbols_as_cli(symbol_result: &crate::services::search_service::Symbol
         symbol_result.matches.len(),
         symbol_result.total_symbols
     ));
+    push_did_you_mean(&mut output, &symbol_result.suggestions);
     output
 }
 
 /// Format callers results as CLI-style output
-fn format_callers_as_cli(callers_result: &serde_json::Value) -> String {
-    // TODO: Extract caller information from service result and format as CLI
-    // This will depend on the structure of CallersResult from AnalysisService
-    if let Some(callers) = callers_result.get("callers") {
-        if let Some(callers_array) = callers.as_array() {
-            let mut output = String::new();
-            for caller in callers_array {
-                if let (Some(name), Some(file), Some(line)) = (
-                    caller.get("name").and_then(|v| v.as_str()),
-                    caller.get("file").and_then(|v| v.as_str()),
-                    caller.get("line").and_then(|v| v.as_u64()),
-                ) {
-                    output.push_str(&format!("📞 {} ({}:{})\n", name, file, line));
-                }
-            }
-            return output;
-        }
+fn format_callers_as_cli(callers_result: &CallersResult) -> String {
+    let mut output = String::new();
+    for site in &callers_result.callers {
+        output.push_str(&format!(
+            "📞 {} ({}:{})\n",
+            site.caller,
+            site.file_path,
+            site.line_number.unwrap_or(0)
+        ));
     }
-
-    // Fallback to JSON string representation
-    serde_json::to_string_pretty(callers_result).unwrap_or_else(|_| "No callers found".to_string())
+    output.push_str(&format!("\nTotal callers: {}", callers_result.total_count));
+    output
 }
 
 /// Format impact analysis results as CLI-style output
-fn format_impact_as_cli(impact_result: &serde_json::Value) -> String {
-    // TODO: Extract impact information from service result and format as CLI
-    // This will depend on the structure of ImpactResult from AnalysisService
-    if let Some(impact) = impact_result.get("impacted_files") {
-        if let Some(impact_array) = impact.as_array() {
-            let mut output = String::new();
-            for file in impact_array {
-                if let Some(path) = file.as_str() {
-                    output.push_str(&format!("⚡ {}\n", path));
-                }
-            }
-            return output;
-        }
+fn format_impact_as_cli(impact_result: &ImpactResult) -> String {
+    let mut output = String::new();
+    for site in &impact_result.impacts {
+        output.push_str(&format!(
+            "⚡ {} ({}:{}) depth {}\n",
+            site.affected_symbol,
+            site.file_path,
+            site.line_number.unwrap_or(0),
+            site.depth
+        ));
     }
-
-    // Fallback to JSON string representation
-    serde_json::to_string_pretty(impact_result).unwrap_or_else(|_| "No impact found".to_string())
+    output.push_str(&format!(
+        "\nTotal impacted symbols: {}",
+        impact_result.total_count
+    ));
+    output
 }
 
 /// Extract simple caller results for simple format
-fn extract_simple_caller_results(json_val: &serde_json::Value) -> Vec<String> {
-    let mut results = Vec::new();
-
-    if let Some(callers) = json_val.get("callers") {
-        if let Some(callers_array) = callers.as_array() {
-            for caller in callers_array {
-                if let Some(name) = caller.get("name").and_then(|v| v.as_str()) {
-                    results.push(name.to_string());
-                }
-            }
-        }
-    }
-
-    if results.is_empty() {
-        results.push("No callers found".to_string());
-    }
-
-    results
+fn extract_simple_caller_results(callers_result: &CallersResult) -> Vec<String> {
+    callers_result
+        .callers
+        .iter()
+        .map(|site| site.caller.clone())
+        .collect()
 }
 
 /// Extract simple impact results for simple format
-fn extract_simple_impact_results(json_val: &serde_json::Value) -> Vec<String> {
-    let mut results = Vec::new();
-
-    if let Some(impact) = json_val.get("impacted_files") {
-        if let Some(impact_array) = impact.as_array() {
-            for file in impact_array {
-                if let Some(path) = file.as_str() {
-                    results.push(path.to_string());
-                }
-            }
-        }
-    }
-
-    if results.is_empty() {
-        results.push("No impact found".to_string());
-    }
-
-    results
+fn extract_simple_impact_results(impact_result: &ImpactResult) -> Vec<String> {
+    impact_result
+        .impacts
+        .iter()
+        .map(|site| site.affected_symbol.clone())
+        .collect()
 }
 
 /// Create API key handler for internal endpoints
@@ -0,0 +1,317 @@
+//! Short-lived signed URLs for analysis artifacts
+//!
+//! A signed URL grants GET access to one artifact path, with its exact query string,
+//! on behalf of the API key that created it until it expires. SaaS users hand these to
+//! teammates or CI systems instead of an API key. The signature is an HMAC over the
+//! path, the query and the embedded key id and expiry, so none of them can be altered.
+//! The one exception is the response cursor, which pages through a truncated artifact.
+//! Revoking or deactivating the key invalidates every URL it signed.
+
+use std::sync::OnceLock;
+
+use anyhow::{bail, Result};
+use chrono::{DateTime, TimeZone, Utc};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use crate::response_limits::RESPONSE_CURSOR_PARAM;
+
+/// Secret used to sign artifact URLs; signing is unavailable when it is unset
+pub const URL_SIGNING_KEY_ENV: &str = "KOTADB_URL_SIGNING_KEY";
+
+pub const EXPIRES_PARAM: &str = "kotadb_expires";
+pub const KEY_ID_PARAM: &str = "kotadb_key";
+pub const SIGNATURE_PARAM: &str = "kotadb_signature";
+
+/// Lifetime of a signed URL when the caller does not choose one
+pub const DEFAULT_SIGNED_URL_TTL_SECS: i64 = 60 * 60;
+/// Longest lifetime a signed URL may be given
+pub const MAX_SIGNED_URL_TTL_SECS: i64 = 7 * 24 * 60 * 60;
+
+/// Artifact endpoints that can be shared through signed URLs
+const SHAREABLE_PATHS: &[&str] = &[
+    "/api/v1/codebase-overview",
+    "/api/v1/overview/treemap",
+    "/api/v1/analysis/stats",
+    "/api/v1/analysis/trends",
+];
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Why a signed URL was refused
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SignedUrlError {
+    /// Missing or malformed signing parameters
+    Malformed,
+    Expired,
+    BadSignature,
+    /// The path is not an artifact endpoint
+    NotShareable,
+}
+
+impl std::fmt::Display for SignedUrlError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            SignedUrlError::Malformed => "signed URL parameters are missing or malformed",
+            SignedUrlError::Expired => "signed URL has expired",
+            SignedUrlError::BadSignature => "signed URL signature does not match",
+            SignedUrlError::NotShareable => "this endpoint cannot be shared through signed URLs",
+        })
+    }
+}
+
+impl std::error::Error for SignedUrlError {}
+
+/// Whether `path` is an artifact endpoint that signed URLs may grant access to
+pub fn is_shareable_path(path: &str) -> bool {
+    if SHAREABLE_PATHS.contains(&path) {
+        return true;
+    }
+    path.strip_prefix("/api/v1/symbols/")
+        .and_then(|rest| rest.strip_suffix("/graph.svg"))
+        .is_some_and(|symbol| !symbol.is_empty() && !symbol.contains('/'))
+}
+
+/// Whether a query string carries a signature, i.e. the request asks for signed access
+pub fn has_signature(query: Option<&str>) -> bool {
+    query.is_some_and(|query| {
+        query
+            .split('&')
+            .any(|pair| pair.starts_with(&format!("{}=", SIGNATURE_PARAM)))
+    })
+}
+
+/// Query string with the signing parameters removed, or `None` when nothing is left
+pub fn strip_signing_params(query: &str) -> Option<String> {
+    let rest: Vec<&str> = query
+        .split('&')
+        .filter(|pair| {
+            let name = pair.split('=').next().unwrap_or_default();
+            !pair.is_empty() && ![EXPIRES_PARAM, KEY_ID_PARAM, SIGNATURE_PARAM].contains(&name)
+        })
+        .collect();
+    (!rest.is_empty()).then(|| rest.join("&"))
+}
+
+/// Signs artifact URLs and checks signed requests
+pub struct UrlSigner {
+    key: Vec<u8>,
+}
+
+impl std::fmt::Debug for UrlSigner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("UrlSigner").finish_non_exhaustive()
+    }
+}
+
+impl UrlSigner {
+    pub fn new(key: impl Into<Vec<u8>>) -> Self {
+        Self { key: key.into() }
+    }
+
+    /// Signer configured through the environment, read once per process
+    pub fn from_env() -> Option<&'static UrlSigner> {
+        static SIGNER: OnceLock<Option<UrlSigner>> = OnceLock::new();
+        SIGNER
+            .get_or_init(|| {
+                std::env::var(URL_SIGNING_KEY_ENV)
+                    .ok()
+                    .filter(|key| !key.trim().is_empty())
+                    .map(|key| UrlSigner::new(key.trim().as_bytes()))
+            })
+            .as_ref()
+    }
+
+    /// Path and query granting access to `path?query` for `key_id` until `expires_at`
+    pub fn sign(
+        &self,
+        path: &str,
+        query: Option<&str>,
+        key_id: i64,
+        expires_at: DateTime<Utc>,
+    ) -> Result<String> {
+        if !is_shareable_path(path) {
+            bail!(SignedUrlError::NotShareable);
+        }
+        let mut query = query
+            .and_then(strip_signing_params)
+            .map(|query| {
+                query
+                    .split('&')
+                    .filter(|pair| !pair.starts_with(&format!("{}=", RESPONSE_CURSOR_PARAM)))
+                    .map(|pair| format!("{}&", pair))
+                    .collect::<String>()
+            })
+            .unwrap_or_default();
+        query.push_str(&format!(
+            "{}={}&{}={}",
+            EXPIRES_PARAM,
+            expires_at.timestamp(),
+            KEY_ID_PARAM,
+            key_id
+        ));
+        let signature = self.signature(path, &query);
+        Ok(format!(
+            "{}?{}&{}={}",
+            path, query, SIGNATURE_PARAM, signature
+        ))
+    }
+
+    /// Key id a signed request acts for, once its path, signature and expiry check out
+    pub fn verify(
+        &self,
+        path: &str,
+        query: &str,
+        now: DateTime<Utc>,
+    ) -> Result<i64, SignedUrlError> {
+        if !is_shareable_path(path) {
+            return Err(SignedUrlError::NotShareable);
+        }
+        let mut signature = None;
+        let mut expires = None;
+        let mut key_id = None;
+        let mut signed = Vec::new();
+        for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+            let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+            let slot = match name {
+                SIGNATURE_PARAM => &mut signature,
+                EXPIRES_PARAM => &mut expires,
+                KEY_ID_PARAM => &mut key_id,
+                RESPONSE_CURSOR_PARAM => continue,
+                _ => {
+                    signed.push(pair);
+                    continue;
+                }
+            };
+            if slot.replace(value).is_some() {
+                return Err(SignedUrlError::Malformed);
+            }
+            if name != SIGNATURE_PARAM {
+                signed.push(pair);
+            }
+        }
+
+        let (Some(signature), Some(expires), Some(key_id)) = (signature, expires, key_id) else {
+            return Err(SignedUrlError::Malformed);
+        };
+        let signature = hex::decode(signature).map_err(|_| SignedUrlError::Malformed)?;
+        let expires = expires
+            .parse::<i64>()
+            .ok()
+            .and_then(|secs| Utc.timestamp_opt(secs, 0).single())
+            .ok_or(SignedUrlError::Malformed)?;
+        let key_id = key_id
+            .parse::<i64>()
+            .map_err(|_| SignedUrlError::Malformed)?;
+
+        self.mac(path, &signed.join("&"))
+            .verify_slice(&signature)
+            .map_err(|_| SignedUrlError::BadSignature)?;
+        if expires <= now {
+            return Err(SignedUrlError::Expired);
+        }
+        Ok(key_id)
+    }
+
+    fn mac(&self, path: &str, query: &str) -> HmacSha256 {
+        let mut mac = HmacSha256::new_from_slice(&self.key).expect("HMAC accepts keys of any size");
+        mac.update(b"GET\n");
+        mac.update(path.as_bytes());
+        mac.update(b"\n");
+        mac.update(query.as_bytes());
+        mac
+    }
+
+    fn signature(&self, path: &str, query: &str) -> String {
+        hex::encode(self.mac(path, query).finalize().into_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn split(url: &str) -> (&str, &str) {
+        url.split_once('?').expect("signed URLs carry a query")
+    }
+
+    #[test]
+    fn signed_urls_grant_their_key_until_expiry() -> Result<()> {
+        let signer = UrlSigner::new("test-key");
+        let now = Utc::now();
+        let url = signer.sign(
+            "/api/v1/symbols/Storage/graph.svg",
+            Some("depth=2"),
+            42,
+            now + Duration::minutes(5),
+        )?;
+        let (path, query) = split(&url);
+
+        assert!(query.starts_with("depth=2&kotadb_expires="));
+        assert_eq!(signer.verify(path, query, now), Ok(42));
+        assert_eq!(
+            signer.verify(path, query, now + Duration::minutes(6)),
+            Err(SignedUrlError::Expired)
+        );
+        assert_eq!(strip_signing_params(query).as_deref(), Some("depth=2"));
+        let continued = format!("{}&response_cursor=e30", query);
+        assert_eq!(signer.verify(path, &continued, now), Ok(42));
+        Ok(())
+    }
+
+    #[test]
+    fn altered_urls_are_rejected() -> Result<()> {
+        let signer = UrlSigner::new("test-key");
+        let now = Utc::now();
+        let url = signer.sign(
+            "/api/v1/codebase-overview",
+            Some("top_symbols_limit=5"),
+            7,
+            now + Duration::minutes(5),
+        )?;
+        let (path, query) = split(&url);
+
+        let widened = query.replace("top_symbols_limit=5", "top_symbols_limit=500");
+        assert_eq!(
+            signer.verify(path, &widened, now),
+            Err(SignedUrlError::BadSignature)
+        );
+        let other_key = query.replace("kotadb_key=7", "kotadb_key=8");
+        assert_eq!(
+            signer.verify(path, &other_key, now),
+            Err(SignedUrlError::BadSignature)
+        );
+        assert_eq!(
+            signer.verify("/api/v1/overview/treemap", query, now),
+            Err(SignedUrlError::BadSignature)
+        );
+        assert_eq!(
+            UrlSigner::new("other-key").verify(path, query, now),
+            Err(SignedUrlError::BadSignature)
+        );
+        assert_eq!(
+            signer.verify(path, "kotadb_key=7", now),
+            Err(SignedUrlError::Malformed)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn only_artifact_endpoints_are_shareable() {
+        assert!(is_shareable_path("/api/v1/codebase-overview"));
+        assert!(is_shareable_path("/api/v1/symbols/Storage/graph.svg"));
+        assert!(!is_shareable_path("/api/v1/symbols//graph.svg"));
+        assert!(!is_shareable_path("/api/v1/files/content/src/lib.rs"));
+        assert!(!is_shareable_path("/api/v1/account/export"));
+
+        let signer = UrlSigner::new("test-key");
+        assert!(signer
+            .sign("/api/v1/account/export", None, 1, Utc::now())
+            .is_err());
+        assert_eq!(
+            signer.verify("/api/v1/search", "kotadb_signature=00", Utc::now()),
+            Err(SignedUrlError::NotShareable)
+        );
+    }
+}
@@ -0,0 +1,1152 @@
+//! Embedded SQL extraction and table usage map
+//!
+//! `index-codebase` finds SQL in string literals of source files (sqlx `query!`,
+//! `sqlx::query(..)`, raw strings, Go backtick strings, Python triple-quoted strings) and
+//! in `.sql` files, and records the tables and columns each statement touches in
+//! `sql_usages.json` in the database directory. `kotadb table-usages <table>` then lists
+//! the code that defines, writes and reads a table, for schema change impact analysis.
+//!
+//! Statements are tokenized, not fully parsed: tables come from `FROM`, `JOIN`, `INTO`,
+//! `UPDATE`, `TABLE`, `REFERENCES` and `... ON <table>` clauses; columns from select
+//! lists, insert column lists, `SET` assignments, comparisons and `alias.column`
+//! references, attributed through aliases or to the only table of the statement.
+
+use anyhow::{Context, Result};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use crate::binary_symbols::FunctionRanges;
+use crate::contracts::Document;
+use crate::feature_flags::LineStarts;
+use crate::pure::metadata::strip_frontmatter;
+
+/// File name of the SQL usage index inside a database directory
+pub const SQL_INDEX_FILE: &str = "sql_usages.json";
+
+/// Longest SQL text kept per statement
+const MAX_SQL_CHARS: usize = 240;
+
+static SQL_START: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?is)^\s*(?:SELECT\b.*\bFROM\b|INSERT\s+INTO\b|UPDATE\s+\S+\s+SET\b|DELETE\s+FROM\b|WITH\s+(?:RECURSIVE\s+)?\w+\s+AS\s*\(|(?:CREATE|ALTER|DROP)\s+(?:OR\s+REPLACE\s+)?(?:UNIQUE\s+)?(?:TABLE|INDEX|VIEW|POLICY|TRIGGER)\b|TRUNCATE\b)",
+    )
+    .expect("valid SQL pattern")
+});
+static STATEMENT_KEYWORD: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?i)\b(?:SELECT|INSERT|UPDATE|DELETE|WITH|CREATE|ALTER|DROP|TRUNCATE|REPLACE|MERGE)\b",
+    )
+    .expect("valid keyword pattern")
+});
+/// Lowercase SQL in string literals also has to look like a query, not a sentence
+static SQL_SHAPE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)\b(?:where|join|values|returning|limit|order\s+by|group\s+by)\b|[*=$?]")
+        .expect("valid SQL shape pattern")
+});
+
+const KEYWORDS: &[&str] = &[
+    "ADD",
+    "ALL",
+    "ALTER",
+    "AND",
+    "ANY",
+    "AS",
+    "ASC",
+    "BETWEEN",
+    "BY",
+    "CASCADE",
+    "CASE",
+    "CHECK",
+    "COLUMN",
+    "CONFLICT",
+    "CONSTRAINT",
+    "CREATE",
+    "CROSS",
+    "CURRENT_TIMESTAMP",
+    "DEFAULT",
+    "DELETE",
+    "DESC",
+    "DISTINCT",
+    "DO",
+    "DROP",
+    "ELSE",
+    "ENABLE",
+    "END",
+    "EXCEPT",
+    "EXISTS",
+    "FALSE",
+    "FETCH",
+    "FOR",
+    "FOREIGN",
+    "FROM",
+    "FULL",
+    "GROUP",
+    "HAVING",
+    "IF",
+    "ILIKE",
+    "IN",
+    "INDEX",
+    "INNER",
+    "INSERT",
+    "INTERSECT",
+    "INTO",
+    "IS",
+    "JOIN",
+    "KEY",
+    "LATERAL",
+    "LEFT",
+    "LIKE",
+    "LIMIT",
+    "NATURAL",
+    "NOT",
+    "NOTHING",
+    "NULL",
+    "NULLS",
+    "OFFSET",
+    "ON",
+    "ONLY",
+    "OR",
+    "ORDER",
+    "OUTER",
+    "OVER",
+    "PARTITION",
+    "POLICY",
+    "PRIMARY",
+    "RECURSIVE",
+    "REFERENCES",
+    "REPLACE",
+    "RETURNING",
+    "RIGHT",
+    "ROW",
+    "SELECT",
+    "SET",
+    "TABLE",
+    "THEN",
+    "TO",
+    "TRIGGER",
+    "TRUE",
+    "TRUNCATE",
+    "UNION",
+    "UNIQUE",
+    "UPDATE",
+    "USING",
+    "VALUES",
+    "VIEW",
+    "WHEN",
+    "WHERE",
+    "WITH",
+];
+
+/// Leading words of table definition items that are constraints, not columns
+const CONSTRAINT_WORDS: &[&str] = &[
+    "CONSTRAINT",
+    "PRIMARY",
+    "FOREIGN",
+    "UNIQUE",
+    "CHECK",
+    "EXCLUDE",
+    "INDEX",
+    "KEY",
+    "LIKE",
+];
+
+/// Kind of SQL statement
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SqlOperation {
+    Select,
+    Insert,
+    Update,
+    Delete,
+    Create,
+    Alter,
+    Drop,
+}
+
+/// How a statement uses a table
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TableAccess {
+    /// Creates, alters or drops the table or objects on it (indexes, policies,
+    /// foreign keys referencing it)
+    Schema,
+    /// Inserts, updates, deletes or truncates rows
+    Write,
+    /// Selects rows, including subqueries of writes
+    Read,
+}
+
+/// A table touched by a statement
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TableRef {
+    /// Name as written, possibly schema-qualified
+    pub table: String,
+    pub access: TableAccess,
+    /// Columns of this table the statement names
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub columns: Vec<String>,
+}
+
+/// One SQL statement found in the code
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SqlStatement {
+    pub file_path: String,
+    /// 1-based line where the statement (or the literal holding it) starts
+    pub line: usize,
+    /// Function containing the statement
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub symbol: Option<String>,
+    pub operation: SqlOperation,
+    pub tables: Vec<TableRef>,
+    /// Statement text, whitespace collapsed and shortened
+    pub sql: String,
+}
+
+/// A statement touching one table, as listed by `table-usages`
+#[derive(Debug, Clone, Serialize)]
+pub struct TableUsageSite {
+    pub file_path: String,
+    pub line: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub symbol: Option<String>,
+    pub operation: SqlOperation,
+    pub columns: Vec<String>,
+    pub sql: String,
+}
+
+/// Code that defines, writes and reads one table
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TableUsages {
+    pub table: String,
+    /// DDL: the table definition, alterations, indexes, policies and foreign keys
+    pub schema: Vec<TableUsageSite>,
+    pub writes: Vec<TableUsageSite>,
+    pub reads: Vec<TableUsageSite>,
+    /// Columns named by the reads and writes, with the number of statements naming them
+    pub columns: BTreeMap<String, usize>,
+}
+
+impl TableUsages {
+    /// Keep at most `limit` sites per section
+    pub fn truncate(&mut self, limit: usize) {
+        self.schema.truncate(limit);
+        self.writes.truncate(limit);
+        self.reads.truncate(limit);
+    }
+
+    /// Sections for schema, writes and reads, then the columns used
+    pub fn to_markdown(&self) -> String {
+        let mut output = format!("# Usages of table `{}`\n", self.table);
+        if self.schema.is_empty() && self.writes.is_empty() && self.reads.is_empty() {
+            output.push_str("\nNo indexed SQL uses this table.\n");
+            return output;
+        }
+        for (title, sites) in [
+            ("Schema", &self.schema),
+            ("Writes", &self.writes),
+            ("Reads", &self.reads),
+        ] {
+            if sites.is_empty() {
+                continue;
+            }
+            output.push_str(&format!("\n## {} ({})\n", title, sites.len()));
+            for site in sites {
+                output.push_str(&format!(
+                    "- {}:{}{} `{}`\n",
+                    site.file_path,
+                    site.line,
+                    site.symbol
+                        .as_ref()
+                        .map(|symbol| format!(" in `{}`", symbol))
+                        .unwrap_or_default(),
+                    site.sql
+                ));
+            }
+        }
+        if !self.columns.is_empty() {
+            let mut columns: Vec<(&String, &usize)> = self.columns.iter().collect();
+            columns.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+            let columns: Vec<String> = columns
+                .into_iter()
+                .map(|(column, count)| format!("`{}` ({})", column, count))
+                .collect();
+            output.push_str(&format!("\n## Columns used\n{}\n", columns.join(", ")));
+        }
+        output
+    }
+}
+
+/// SQL statements of the indexed codebase
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SqlIndex {
+    /// RFC 3339 time the index was built
+    pub indexed_at: String,
+    pub statements: Vec<SqlStatement>,
+}
+
+impl SqlIndex {
+    /// Extract SQL statements from `.sql` files and from string literals of source files
+    pub fn build<'a>(
+        files: impl IntoIterator<Item = (&'a str, &'a str)>,
+        functions: &FunctionRanges,
+    ) -> Self {
+        let mut statements = Vec::new();
+        for (path, content) in files {
+            let lines = LineStarts::new(content);
+            let mut add = |offset: usize, text: &str| {
+                // Skip block openers such as `BEGIN` in function bodies
+                let Some(start) = STATEMENT_KEYWORD.find(text) else {
+                    return;
+                };
+                let (offset, text) = (offset + start.start(), &text[start.start()..]);
+                if let Some((operation, tables)) = parse_statement(&tokenize(text)) {
+                    let line = lines.line_of(offset);
+                    statements.push(SqlStatement {
+                        file_path: path.to_string(),
+                        line,
+                        symbol: functions.enclosing(path, line).map(str::to_string),
+                        operation,
+                        tables,
+                        sql: shorten(text),
+                    });
+                }
+            };
+            if path.ends_with(".sql") {
+                for (offset, text) in split_statements(content, 0) {
+                    add(offset, text);
+                }
+            } else {
+                for (offset, literal) in string_literals(content, path) {
+                    let Some(keyword) = SQL_START.find(&literal) else {
+                        continue;
+                    };
+                    let keyword = keyword.as_str().trim_start();
+                    let uppercase = keyword
+                        .split_whitespace()
+                        .next()
+                        .is_some_and(|word| word.chars().all(|c| c.is_ascii_uppercase()));
+                    if !uppercase && !SQL_SHAPE.is_match(&literal) {
+                        continue;
+                    }
+                    for (offset, text) in split_statements(&literal, offset) {
+                        add(offset, text);
+                    }
+                }
+            }
+        }
+        statements.sort_by(|a, b| (&a.file_path, a.line).cmp(&(&b.file_path, b.line)));
+        Self {
+            indexed_at: chrono::Utc::now().to_rfc3339(),
+            statements,
+        }
+    }
+
+    /// Location of the SQL index for a database directory
+    pub fn path_for(db_path: &Path) -> PathBuf {
+        db_path.join(SQL_INDEX_FILE)
+    }
+
+    /// The SQL index of a database, if one was built
+    pub fn load(db_path: &Path) -> Result<Option<Self>> {
+        let path = Self::path_for(db_path);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read SQL usages: {}", path.display()))?;
+        let index = serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse SQL usages: {}", path.display()))?;
+        Ok(Some(index))
+    }
+
+    /// Save the index atomically into a database directory
+    pub fn save(&self, db_path: &Path) -> Result<()> {
+        std::fs::create_dir_all(db_path)
+            .with_context(|| format!("Failed to create {}", db_path.display()))?;
+        let path = Self::path_for(db_path);
+        let tmp_path = path.with_extension("json.tmp");
+        std::fs::write(&tmp_path, serde_json::to_string(self)?)
+            .with_context(|| format!("Failed to write SQL usages: {}", tmp_path.display()))?;
+        std::fs::rename(&tmp_path, &path)
+            .with_context(|| format!("Failed to replace SQL usages: {}", path.display()))?;
+        Ok(())
+    }
+
+    /// Statements touching `table`, matched case-insensitively and with or without a
+    /// schema qualifier
+    pub fn table_usages(&self, table: &str) -> TableUsages {
+        let mut usages = TableUsages {
+            table: table.to_string(),
+            ..TableUsages::default()
+        };
+        for statement in &self.statements {
+            for table_ref in statement
+                .tables
+                .iter()
+                .filter(|table_ref| tables_match(&table_ref.table, table))
+            {
+                let site = TableUsageSite {
+                    file_path: statement.file_path.clone(),
+                    line: statement.line,
+                    symbol: statement.symbol.clone(),
+                    operation: statement.operation,
+                    columns: table_ref.columns.clone(),
+                    sql: statement.sql.clone(),
+                };
+                if table_ref.access != TableAccess::Schema {
+                    for column in &site.columns {
+                        *usages.columns.entry(column.clone()).or_default() += 1;
+                    }
+                }
+                match table_ref.access {
+                    TableAccess::Schema => usages.schema.push(site),
+                    TableAccess::Write => usages.writes.push(site),
+                    TableAccess::Read => usages.reads.push(site),
+                }
+            }
+        }
+        usages
+    }
+}
+
+/// Build and save the SQL index from stored documents
+pub fn index_sql_usages(db_path: &Path, documents: &[Document]) -> Result<SqlIndex> {
+    let functions = FunctionRanges::load(&db_path.join("symbols.kota"))?;
+    let contents: Vec<(&str, String)> = documents
+        .iter()
+        .filter(|d| !d.tags.iter().any(|tag| tag.as_str() == "commit"))
+        .filter(|d| {
+            crate::trends::is_source_language(crate::path_utils::detect_language_from_extension(
+                Path::new(d.path.as_str()),
+            ))
+        })
+        .map(|d| {
+            (
+                d.path.as_str(),
+                strip_frontmatter(&String::from_utf8_lossy(&d.content)).to_string(),
+            )
+        })
+        .collect();
+    let index = SqlIndex::build(
+        contents
+            .iter()
+            .map(|(path, content)| (*path, content.as_str())),
+        &functions,
+    );
+    index.save(db_path)?;
+    Ok(index)
+}
+
+/// Table names compare case-insensitively, without quotes, and on the unqualified name
+fn tables_match(a: &str, b: &str) -> bool {
+    let name = |table: &str| {
+        table
+            .rsplit('.')
+            .next()
+            .unwrap_or(table)
+            .trim_matches('"')
+            .to_ascii_lowercase()
+    };
+    let qualified = |table: &str| table.replace('"', "").to_ascii_lowercase();
+    if a.contains('.') && b.contains('.') {
+        qualified(a) == qualified(b)
+    } else {
+        name(a) == name(b)
+    }
+}
+
+fn shorten(sql: &str) -> String {
+    let collapsed = sql.split_whitespace().collect::<Vec<_>>().join(" ");
+    match collapsed.char_indices().nth(MAX_SQL_CHARS) {
+        Some((end, _)) => format!("{}...", &collapsed[..end]),
+        None => collapsed,
+    }
+}
+
+/// String literal contents with their offsets, escapes resolved to spaces
+fn string_literals(source: &str, path: &str) -> Vec<(usize, String)> {
+    let rust = path.ends_with(".rs");
+    let hash_comments = [".py", ".rb", ".sh"].iter().any(|ext| path.ends_with(ext));
+    let bytes = source.as_bytes();
+    let mut literals = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let rest = &bytes[i..];
+        if (hash_comments && rest[0] == b'#') || (!hash_comments && rest.starts_with(b"//")) {
+            i = source[i..].find('\n').map_or(bytes.len(), |n| i + n);
+        } else if !hash_comments && rest.starts_with(b"/*") {
+            i = source[i..].find("*/").map_or(bytes.len(), |n| i + n + 2);
+        } else if rust
+            && rest[0] == b'r'
+            && (i == 0 || !(bytes[i - 1].is_ascii_alphanumeric() || bytes[i - 1] == b'_'))
+            && matches!(rest.get(1), Some(b'"' | b'#'))
+        {
+            let hashes = rest[1..].iter().take_while(|b| **b == b'#').count();
+            if rest.get(1 + hashes) != Some(&b'"') {
+                i += 1;
+                continue;
+            }
+            let body = i + 2 + hashes;
+            let terminator = format!("\"{}", "#".repeat(hashes));
+            let end = source[body..]
+                .find(&terminator)
+                .map_or(bytes.len(), |n| body + n);
+            literals.push((body, source[body..end].to_string()));
+            i = end + terminator.len();
+        } else if !rust && (rest.starts_with(b"\"\"\"") || rest.starts_with(b"'''")) {
+            let quote = &source[i..i + 3];
+            let body = i + 3;
+            let end = source[body..].find(quote).map_or(bytes.len(), |n| body + n);
+            literals.push((body, source[body..end].to_string()));
+            i = end + 3;
+        } else if rust && (rest.starts_with(b"'\"'") || rest.starts_with(b"'\\''")) {
+            // Char literals holding a quote
+            i += if rest[1] == b'\\' { 4 } else { 3 };
+        } else if rest[0] == b'"' || (!rust && matches!(rest[0], b'\'' | b'`')) {
+            let quote = rest[0];
+            let mut j = i + 1;
+            let mut text = String::new();
+            while j < bytes.len() && bytes[j] != quote {
+                if bytes[j] == b'\\' && quote != b'`' {
+                    text.push(' ');
+                    j += 2;
+                    continue;
+                }
+                let c = source[j..].chars().next().expect("char boundary");
+                text.push(c);
+                j += c.len_utf8();
+            }
+            literals.push((i + 1, text));
+            i = j + 1;
+        } else {
+            i += source[i..].chars().next().map_or(1, char::len_utf8);
+        }
+    }
+    literals
+}
+
+/// Statements of SQL text split on top-level `;`, with their offsets; statements inside
+/// dollar-quoted function bodies are included
+fn split_statements(sql: &str, base: usize) -> Vec<(usize, &str)> {
+    let bytes = sql.as_bytes();
+    let mut statements = Vec::new();
+    let mut start = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        let rest = &bytes[i..];
+        if rest.starts_with(b"--") {
+            i = sql[i..].find('\n').map_or(bytes.len(), |n| i + n);
+        } else if rest.starts_with(b"/*") {
+            i = sql[i..].find("*/").map_or(bytes.len(), |n| i + n + 2);
+        } else if rest[0] == b'\'' || rest[0] == b'"' {
+            let quote = rest[0];
+            i = sql[i + 1..]
+                .find(quote as char)
+                .map_or(bytes.len(), |n| i + n + 2);
+        } else if rest[0] == b'$' {
+            let tag_len = rest[1..]
+                .iter()
+                .take_while(|b| b.is_ascii_alphanumeric() || **b == b'_')
+                .count();
+            if rest.get(1 + tag_len) == Some(&b'$') && !rest[1].is_ascii_digit() {
+                let tag = &sql[i..i + tag_len + 2];
+                let body = i + tag.len();
+                let end = sql[body..].find(tag).map_or(bytes.len(), |n| body + n);
+                statements.extend(split_statements(&sql[body..end], base + body));
+                i = (end + tag.len()).min(bytes.len());
+            } else {
+                i += 1;
+            }
+        } else if rest[0] == b';' {
+            statements.push((start, &sql[start..i]));
+            i += 1;
+            start = i;
+        } else {
+            i += sql[i..].chars().next().map_or(1, char::len_utf8);
+        }
+    }
+    statements.push((start, &sql[start..]));
+    statements
+        .into_iter()
+        .filter_map(|(offset, text)| {
+            let trimmed = text.trim_start();
+            (!trimmed.is_empty()).then(|| {
+                (
+                    base + offset + text.len() - trimmed.len(),
+                    trimmed.trim_end(),
+                )
+            })
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TokenKind {
+    Ident,
+    QuotedIdent,
+    Punct,
+    Op,
+    Literal,
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    text: String,
+    kind: TokenKind,
+}
+
+impl Token {
+    /// Uppercase keyword, or empty for anything that is not an unquoted word
+    fn keyword(&self) -> String {
+        if self.kind == TokenKind::Ident {
+            self.text.to_ascii_uppercase()
+        } else {
+            String::new()
+        }
+    }
+
+    fn is(&self, keyword: &str) -> bool {
+        self.kind == TokenKind::Ident && self.text.eq_ignore_ascii_case(keyword)
+    }
+
+    fn is_punct(&self, punct: &str) -> bool {
+        self.kind == TokenKind::Punct && self.text == punct
+    }
+
+    /// Identifier that can name a table or column
+    fn is_name(&self) -> bool {
+        match self.kind {
+            TokenKind::QuotedIdent => true,
+            TokenKind::Ident => !KEYWORDS.contains(&self.keyword().as_str()),
+            _ => false,
+        }
+    }
+}
+
+fn tokenize(sql: &str) -> Vec<Token> {
+    let chars: Vec<char> = sql.chars().collect();
+    let mut tokens: Vec<Token> = Vec::new();
+    let mut i = 0;
+    let push = |tokens: &mut Vec<Token>, text: String, kind: TokenKind| {
+        // `a.b` and `"a"."b"` become one qualified name
+        let qualifies = matches!(kind, TokenKind::Ident | TokenKind::QuotedIdent)
+            || (kind == TokenKind::Punct && text == "*");
+        if qualifies && tokens.len() >= 2 && tokens[tokens.len() - 1].is_punct(".") {
+            let previous = &tokens[tokens.len() - 2];
+            if matches!(previous.kind, TokenKind::Ident | TokenKind::QuotedIdent) {
+                tokens.pop();
+                let previous = tokens.last_mut().expect("qualifier");
+                previous.text = format!("{}.{}", previous.text, text);
+                previous.kind = TokenKind::QuotedIdent;
+                return;
+            }
+        }
+        tokens.push(Token { text, kind });
+    };
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        if c.is_whitespace() {
+            i += 1;
+        } else if c == '-' && next == Some('-') {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+        } else if c == '/' && next == Some('*') {
+            i += 2;
+            while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+                i += 1;
+            }
+            i += 2;
+        } else if c == '\'' {
+            i += 1;
+            while i < chars.len() && chars[i] != '\'' {
+                i += 1;
+            }
+            i += 1;
+            push(&mut tokens, String::new(), TokenKind::Literal);
+        } else if c == '"' || c == '`' {
+            let start = i + 1;
+            i = start;
+            while i < chars.len() && chars[i] != c {
+                i += 1;
+            }
+            let name: String = chars[start..i.min(chars.len())].iter().collect();
+            i += 1;
+            push(&mut tokens, name, TokenKind::QuotedIdent);
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            push(
+                &mut tokens,
+                chars[start..i].iter().collect(),
+                TokenKind::Ident,
+            );
+        } else if c == '$' && dollar_tag(&chars[i..]).is_some() {
+            // Dollar-quoted bodies are split into their own statements
+            let tag = dollar_tag(&chars[i..]).expect("tag");
+            i += tag.len();
+            while i < chars.len() && !chars[i..].starts_with(&tag) {
+                i += 1;
+            }
+            i += tag.len();
+            push(&mut tokens, String::new(), TokenKind::Literal);
+        } else if c.is_ascii_digit()
+            || matches!(c, '$' | '?' | '@')
+            || (c == ':' && next.is_some_and(|n| n.is_ascii_alphabetic()))
+        {
+            // Numbers and bind parameters
+            i += 1;
+            while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            push(&mut tokens, String::new(), TokenKind::Literal);
+        } else if matches!(c, '=' | '<' | '>' | '!') || (c == ':' && next == Some(':')) {
+            let start = i;
+            i += 1;
+            while i < chars.len() && matches!(chars[i], '=' | '<' | '>' | ':') {
+                i += 1;
+            }
+            push(&mut tokens, chars[start..i].iter().collect(), TokenKind::Op);
+        } else {
+            i += 1;
+            push(&mut tokens, c.to_string(), TokenKind::Punct);
+        }
+    }
+    tokens
+}
+
+/// `$$` or `$tag$` opening a dollar-quoted string at the start of `chars`
+fn dollar_tag(chars: &[char]) -> Option<Vec<char>> {
+    let tag_len = chars[1..]
+        .iter()
+        .take_while(|c| c.is_ascii_alphanumeric() || **c == '_')
+        .count();
+    let digit = chars.get(1).is_some_and(|c| c.is_ascii_digit());
+    (chars.get(1 + tag_len) == Some(&'$') && !digit).then(|| chars[..tag_len + 2].to_vec())
+}
+
+/// A column named in a statement, before it is attributed to a table
+struct ColumnRef {
+    name: String,
+    qualifier: Option<String>,
+    /// Table the column belongs to by position (insert lists, `SET`, definitions)
+    table: Option<usize>,
+}
+
+fn parse_statement(tokens: &[Token]) -> Option<(SqlOperation, Vec<TableRef>)> {
+    let first = tokens.first()?.keyword();
+    let has = |keyword: &str| tokens.iter().any(|t| t.is(keyword));
+    let operation = match first.as_str() {
+        "SELECT" => SqlOperation::Select,
+        "INSERT" | "REPLACE" | "MERGE" => SqlOperation::Insert,
+        "UPDATE" => SqlOperation::Update,
+        "DELETE" | "TRUNCATE" => SqlOperation::Delete,
+        "CREATE" => SqlOperation::Create,
+        "ALTER" => SqlOperation::Alter,
+        "DROP" => SqlOperation::Drop,
+        "WITH" if has("INSERT") => SqlOperation::Insert,
+        "WITH" if has("DELETE") => SqlOperation::Delete,
+        "WITH" if has("SET") && has("UPDATE") => SqlOperation::Update,
+        "WITH" => SqlOperation::Select,
+        _ => return None,
+    };
+    let ddl = matches!(
+        operation,
+        SqlOperation::Create | SqlOperation::Alter | SqlOperation::Drop
+    );
+
+    // CTE names are not tables
+    let ctes: HashSet<String> = tokens
+        .windows(3)
+        .filter(|w| w[0].is_name() && w[1].is("AS") && w[2].is_punct("("))
+        .map(|w| w[0].text.to_ascii_lowercase())
+        .collect();
+
+    let mut tables: Vec<(String, TableAccess)> = Vec::new();
+    let mut aliases: HashMap<String, usize> = HashMap::new();
+    let mut columns: Vec<ColumnRef> = Vec::new();
+
+    let keyword_at = |i: usize| tokens.get(i).map(Token::keyword).unwrap_or_default();
+    let add_table = |tables: &mut Vec<(String, TableAccess)>,
+                     aliases: &mut HashMap<String, usize>,
+                     i: usize,
+                     access: TableAccess|
+     -> Option<usize> {
+        let token = tokens.get(i)?;
+        if !token.is_name() || ctes.contains(&token.text.to_ascii_lowercase()) {
+            return None;
+        }
+        let name = token.text.clone();
+        let index = match tables
+            .iter()
+            .position(|(table, existing)| *table == name && *existing == access)
+        {
+            Some(index) => index,
+            None => {
+                tables.push((name.clone(), access));
+                tables.len() - 1
+            }
+        };
+        let last = name
+            .rsplit('.')
+            .next()
+            .unwrap_or(&name)
+            .to_ascii_lowercase();
+        aliases.entry(last).or_insert(index);
+        // `table alias` or `table AS alias`
+        let alias = if tokens.get(i + 1).is_some_and(|t| t.is("AS")) {
+            tokens.get(i + 2)
+        } else {
+            tokens.get(i + 1)
+        };
+        if let Some(alias) = alias.filter(|t| t.kind == TokenKind::Ident && t.is_name()) {
+            aliases.insert(alias.text.to_ascii_lowercase(), index);
+        }
+        Some(index)
+    };
+
+    let mut i = 0;
+    while i < tokens.len() {
+        let keyword = tokens[i].keyword();
+        let previous = if i > 0 {
+            keyword_at(i - 1)
+        } else {
+            String::new()
+        };
+        match keyword.as_str() {
+            "FROM" | "JOIN" => {
+                let access = if previous == "DELETE" {
+                    TableAccess::Write
+                } else {
+                    TableAccess::Read
+                };
+                let mut j = i + 1;
+                loop {
+                    // Skip `ONLY`, `LATERAL` and subqueries or table functions
+                    if tokens.get(j).is_some_and(|t| t.is("ONLY")) {
+                        j += 1;
+                    }
+                    if tokens.get(j + 1).is_some_and(|t| t.is_punct("(")) {
+                        break;
+                    }
+                    if add_table(&mut tables, &mut aliases, j, access).is_none() {
+                        break;
+                    }
+                    j += 1;
+                    while tokens
+                        .get(j)
+                        .is_some_and(|t| t.is("AS") || (t.is_name() && t.kind == TokenKind::Ident))
+                    {
+                        j += 1;
+                    }
+                    if keyword == "FROM" && tokens.get(j).is_some_and(|t| t.is_punct(",")) {
+                        j += 1;
+                    } else {
+                        break;
+                    }
+                }
+            }
+            "INTO" => {
+                if let Some(table) = add_table(&mut tables, &mut aliases, i + 1, TableAccess::Write)
+                {
+                    let mut j = i + 2;
+                    while tokens.get(j).is_some_and(|t| t.is("AS") || t.is_name()) {
+                        j += 1;
+                    }
+                    if tokens.get(j).is_some_and(|t| t.is_punct("(")) {
+                        columns.extend(
+                            list_items(tokens, j)
+                                .filter_map(|item| item.first())
+                                .filter(|t| t.is_name())
+                                .map(|t| ColumnRef {
+                                    name: t.text.clone(),
+                                    qualifier: None,
+                                    table: Some(table),
+                                }),
+                        );
+                    }
+                }
+            }
+            "UPDATE" if !matches!(previous.as_str(), "ON" | "DO" | "FOR" | "KEY" | "OR") => {
+                if let Some(table) = add_table(&mut tables, &mut aliases, i + 1, TableAccess::Write)
+                {
+                    if let Some(set) = tokens[i..].iter().position(|t| t.is("SET")) {
+                        let mut depth = 0usize;
+                        for j in i + set + 1..tokens.len() {
+                            let token = &tokens[j];
+                            if token.is_punct("(") {
+                                depth += 1;
+                            } else if token.is_punct(")") {
+                                depth = depth.saturating_sub(1);
+                            } else if depth == 0
+                                && (token.is("WHERE") || token.is("FROM") || token.is("RETURNING"))
+                            {
+                                break;
+                            } else if depth == 0
+                                && token.is_name()
+                                && tokens.get(j + 1).is_some_and(|t| t.text == "=")
+                            {
+                                columns.push(ColumnRef {
+                                    name: token.text.clone(),
+                                    qualifier: None,
+                                    table: Some(table),
+                                });
+                            }
+                        }
+                    }
+                }
+            }
+            "TRUNCATE" => {
+                let j = if keyword_at(i + 1) == "TABLE" {
+                    i + 2
+                } else {
+                    i + 1
+                };
+                add_table(&mut tables, &mut aliases, j, TableAccess::Write);
+            }
+            "TABLE" | "VIEW" if ddl => {
+                let mut j = i + 1;
+                while matches!(keyword_at(j).as_str(), "IF" | "NOT" | "EXISTS" | "ONLY") {
+                    j += 1;
+                }
+                let table = add_table(&mut tables, &mut aliases, j, TableAccess::Schema);
+                if let (Some(table), "CREATE") = (table, first.as_str()) {
+                    if tokens.get(j + 1).is_some_and(|t| t.is_punct("(")) {
+                        columns.extend(
+                            list_items(tokens, j + 1)
+                                .filter_map(|item| item.first())
+                                .filter(|t| {
+                                    t.is_name() && !CONSTRAINT_WORDS.contains(&t.keyword().as_str())
+                                })
+                                .map(|t| ColumnRef {
+                                    name: t.text.clone(),
+                                    qualifier: None,
+                                    table: Some(table),
+                                }),
+                        );
+                    }
+                }
+            }
+            "REFERENCES" => {
+                add_table(&mut tables, &mut aliases, i + 1, TableAccess::Schema);
+            }
+            // `CREATE INDEX .. ON t`, `CREATE POLICY .. ON t`, `CREATE TRIGGER .. ON t`
+            "ON" if ddl && (has("INDEX") || has("POLICY") || has("TRIGGER")) => {
+                add_table(&mut tables, &mut aliases, i + 1, TableAccess::Schema);
+            }
+            "SELECT" => {
+                let mut depth = 0usize;
+                for j in i + 1..tokens.len() {
+                    let token = &tokens[j];
+                    if token.is_punct("(") {
+                        depth += 1;
+                    } else if token.is_punct(")") {
+                        if depth == 0 {
+                            break;
+                        }
+                        depth -= 1;
+                    } else if depth == 0 && token.is("FROM") {
+                        break;
+                    } else if token.is_name()
+                        && !tokens.get(j + 1).is_some_and(|t| t.is_punct("("))
+                        && !tokens[j - 1].is("AS")
+                        && !tokens[j - 1].text.starts_with("::")
+                    {
+                        columns.push(column_ref(&token.text));
+                    }
+                }
+            }
+            _ => {}
+        }
+        // Comparisons and qualified references anywhere name columns
+        let token = &tokens[i];
+        let compared = tokens.get(i + 1).is_some_and(|next| {
+            (next.kind == TokenKind::Op && !next.text.starts_with("::"))
+                || matches!(
+                    next.keyword().as_str(),
+                    "IN" | "LIKE" | "ILIKE" | "IS" | "BETWEEN"
+                )
+        });
+        if token.is_name() && !ddl && (compared || token.text.contains('.')) {
+            columns.push(column_ref(&token.text));
+        }
+        i += 1;
+    }
+
+    if tables.is_empty() {
+        return None;
+    }
+
+    let mut refs: Vec<TableRef> = tables
+        .iter()
+        .map(|(table, access)| TableRef {
+            table: table.trim_matches('"').to_string(),
+            access: *access,
+            columns: Vec::new(),
+        })
+        .collect();
+    let distinct_tables: HashSet<&str> = tables.iter().map(|(table, _)| table.as_str()).collect();
+    for column in columns {
+        let targets: Vec<usize> = match (&column.table, &column.qualifier) {
+            (Some(table), _) => vec![*table],
+            (None, Some(qualifier)) => aliases
+                .get(&qualifier.to_ascii_lowercase())
+                .map(|index| {
+                    // The alias names the table, whatever the access
+                    let name = &tables[*index].0;
+                    (0..tables.len())
+                        .filter(|i| tables[*i].0 == *name)
+                        .collect()
+                })
+                .unwrap_or_default(),
+            // Row values like `to_jsonb(r)` name an alias, not a column
+            (None, None) if aliases.contains_key(&column.name.to_ascii_lowercase()) => Vec::new(),
+            (None, None) if distinct_tables.len() == 1 => (0..tables.len()).collect(),
+            (None, None) => Vec::new(),
+        };
+        for target in targets {
+            let columns = &mut refs[target].columns;
+            if !columns.contains(&column.name) {
+                columns.push(column.name.clone());
+            }
+        }
+    }
+    Some((operation, refs))
+}
+
+fn column_ref(text: &str) -> ColumnRef {
+    let text = text.replace('"', "");
+    match text.rsplit_once('.') {
+        Some((qualifier, name)) => ColumnRef {
+            name: name.to_string(),
+            qualifier: Some(
+                qualifier
+                    .rsplit('.')
+                    .next()
+                    .unwrap_or(qualifier)
+                    .to_string(),
+            ),
+            table: None,
+        },
+        None => ColumnRef {
+            name: text,
+            qualifier: None,
+            table: None,
+        },
+    }
+}
+
+/// Comma-separated items of the parenthesized list opening at `open`
+fn list_items(tokens: &[Token], open: usize) -> impl Iterator<Item = &[Token]> {
+    let mut items = Vec::new();
+    let mut depth = 0usize;
+    let mut start = open + 1;
+    for (j, token) in tokens.iter().enumerate().skip(open) {
+        if token.is_punct("(") {
+            depth += 1;
+        } else if token.is_punct(")") {
+            depth -= 1;
+            if depth == 0 {
+                items.push(&tokens[start..j]);
+                break;
+            }
+        } else if depth == 1 && token.is_punct(",") {
+            items.push(&tokens[start..j]);
+            start = j + 1;
+        }
+    }
+    items.into_iter()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MIGRATION: &str = r#"-- Repositories
+CREATE TABLE IF NOT EXISTS repositories (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID REFERENCES auth.users(id) ON DELETE CASCADE,
+    git_url TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'pending',
+    UNIQUE (user_id, git_url)
+);
+
+CREATE INDEX IF NOT EXISTS idx_repositories_status ON repositories(status);
+
+CREATE OR REPLACE FUNCTION touch() RETURNS trigger AS $$
+BEGIN
+    UPDATE repositories SET status = 'stale' WHERE id = NEW.id;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+"#;
+
+    const CODE: &str = r##"
+pub async fn claim(pool: &PgPool, id: Uuid) -> Result<()> {
+    // SELECT * FROM commented_out
+    let row = sqlx::query_as::<_, Job>(
+        r#"SELECT j.id, j.status, r.git_url
+           FROM indexing_jobs j
+           JOIN repositories r ON r.id = j.repository_id
+           WHERE j.id = $1"#,
+    )
+    .bind(id);
+    sqlx::query("INSERT INTO repositories (user_id, git_url) VALUES ($1, $2)").bind(id);
+    println!("Select a file from the list");
+    Ok(())
+}
+"##;
+
+    fn build() -> SqlIndex {
+        SqlIndex::build(
+            [
+                ("supabase/migrations/001_init.sql", MIGRATION),
+                ("src/jobs.rs", CODE),
+            ],
+            &FunctionRanges::from_ranges([("claim".to_string(), "src/jobs.rs".to_string(), 2, 14)]),
+        )
+    }
+
+    #[test]
+    fn migrations_define_tables_and_function_bodies_write_them() {
+        let usages = build().table_usages("repositories");
+        let schema: Vec<usize> = usages.schema.iter().map(|site| site.line).collect();
+        assert_eq!(schema, vec![2, 10]);
+        assert_eq!(
+            usages.schema[0].columns,
+            vec!["id", "user_id", "git_url", "status"]
+        );
+        assert!(build()
+            .table_usages("auth.users")
+            .schema
+            .iter()
+            .any(|site| site.line == 2));
+
+        assert_eq!(usages.writes.len(), 2);
+        assert_eq!(usages.writes[0].symbol.as_deref(), Some("claim"));
+        assert_eq!(usages.writes[0].columns, vec!["user_id", "git_url"]);
+        assert_eq!(usages.writes[1].line, 14);
+        assert_eq!(usages.writes[1].columns, vec!["status", "id"]);
+    }
+
+    #[test]
+    fn embedded_queries_attribute_columns_through_aliases() {
+        let index = build();
+        let usages = index.table_usages("repositories");
+        assert_eq!(usages.reads.len(), 1);
+        assert_eq!(usages.reads[0].line, 5);
+        assert_eq!(usages.reads[0].columns, vec!["git_url", "id"]);
+        assert_eq!(usages.columns.get("git_url"), Some(&2));
+
+        let jobs = index.table_usages("INDEXING_JOBS");
+        assert_eq!(jobs.reads[0].columns, vec!["id", "status", "repository_id"]);
+        assert!(index.table_usages("commented_out").reads.is_empty());
+        assert!(index.table_usages("the").reads.is_empty());
+    }
+}
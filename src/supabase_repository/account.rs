@@ -0,0 +1,706 @@
+use std::collections::{BTreeMap, HashSet};
+use std::io::Write;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, Utc};
+use flate2::{write::GzEncoder, Compression};
+use serde::Serialize;
+use serde_json::{json, Value as JsonValue};
+use sqlx::types::Json;
+use tracing::instrument;
+use uuid::Uuid;
+
+use super::{
+    generate_webhook_secret, hash_secret,
+    job_worker::{infer_repository_identifier, sanitize_repository_name},
+    SupabaseRepositoryStore,
+};
+use crate::Document;
+
+/// How long a deletion confirmation token stays valid.
+pub const DELETION_CONFIRMATION_TTL_MINUTES: i64 = 15;
+
+/// Tenant rows included in an export, keyed by archive entry; `$1` is the user id.
+///
+/// Secrets and credential hashes are left out.
+const EXPORT_QUERIES: &[(&str, &str)] = &[
+    (
+        "repositories",
+        "SELECT to_jsonb(r) - 'webhook_secret_hash' FROM repositories r \
+         WHERE r.user_id = $1 ORDER BY r.created_at",
+    ),
+    (
+        "indexing_jobs",
+        "SELECT to_jsonb(j) FROM indexing_jobs j JOIN repositories r ON r.id = j.repository_id \
+         WHERE r.user_id = $1 ORDER BY j.created_at",
+    ),
+    (
+        "indexing_job_events",
+        "SELECT to_jsonb(e) FROM indexing_job_events e \
+         JOIN indexing_jobs j ON j.id = e.job_id JOIN repositories r ON r.id = j.repository_id \
+         WHERE r.user_id = $1 ORDER BY e.id",
+    ),
+    (
+        "webhook_deliveries",
+        "SELECT to_jsonb(d) - 'signature' FROM webhook_deliveries d \
+         JOIN repositories r ON r.id = d.repository_id WHERE r.user_id = $1 ORDER BY d.id",
+    ),
+    (
+        "source_uploads",
+        "SELECT to_jsonb(u) FROM source_uploads u WHERE u.user_id = $1 ORDER BY u.created_at",
+    ),
+    (
+        "notification_hooks",
+        "SELECT to_jsonb(h) FROM notification_hooks h WHERE h.user_id = $1 ORDER BY h.created_at",
+    ),
+    (
+        "token_usage",
+        "SELECT to_jsonb(t) FROM token_usage t JOIN api_keys k ON k.id = t.api_key_id \
+         WHERE k.user_id = $1 ORDER BY t.id",
+    ),
+    (
+        "usage_metrics",
+        "SELECT to_jsonb(m) FROM usage_metrics m JOIN api_keys k ON k.id = m.api_key_id \
+         WHERE k.user_id = $1 ORDER BY m.created_at",
+    ),
+    (
+        "api_keys",
+        "SELECT to_jsonb(k) - 'key_hash' FROM api_keys k WHERE k.user_id = $1 ORDER BY k.created_at",
+    ),
+    (
+        "kotadb_api_keys",
+        "SELECT to_jsonb(k) - 'key_hash' FROM kotadb_api_keys k \
+         WHERE lower(k.user_id) = $1::text ORDER BY k.id",
+    ),
+    (
+        "kotadb_api_key_usage",
+        "SELECT to_jsonb(u) FROM kotadb_api_key_usage u JOIN kotadb_api_keys k ON k.id = u.key_id \
+         WHERE lower(k.user_id) = $1::text ORDER BY u.id",
+    ),
+    (
+        "documents",
+        "SELECT to_jsonb(d) - 'embedding' FROM documents d WHERE d.user_id = $1 ORDER BY d.created_at",
+    ),
+    (
+        "account_data_requests",
+        "SELECT to_jsonb(a) - 'confirmation_hash' FROM account_data_requests a \
+         WHERE a.user_id = $1 ORDER BY a.created_at",
+    ),
+];
+
+/// Statements removing a tenant's rows, children first; `$1` is the user id.
+///
+/// `account_data_requests` is kept as the audit trail of the deletion itself.
+const PURGE_STATEMENTS: &[(&str, &str)] = &[
+    (
+        "indexing_job_events",
+        "DELETE FROM indexing_job_events WHERE job_id IN (\
+         SELECT j.id FROM indexing_jobs j JOIN repositories r ON r.id = j.repository_id \
+         WHERE r.user_id = $1)",
+    ),
+    (
+        "webhook_deliveries",
+        "DELETE FROM webhook_deliveries \
+         WHERE repository_id IN (SELECT id FROM repositories WHERE user_id = $1)",
+    ),
+    (
+        "source_uploads",
+        "DELETE FROM source_uploads WHERE user_id = $1",
+    ),
+    (
+        "token_usage",
+        "DELETE FROM token_usage \
+         WHERE api_key_id IN (SELECT id FROM api_keys WHERE user_id = $1) \
+         OR repository_id IN (SELECT id FROM repositories WHERE user_id = $1)",
+    ),
+    (
+        "usage_metrics",
+        "DELETE FROM usage_metrics WHERE api_key_id IN (SELECT id FROM api_keys WHERE user_id = $1)",
+    ),
+    (
+        "indexing_jobs",
+        "DELETE FROM indexing_jobs \
+         WHERE repository_id IN (SELECT id FROM repositories WHERE user_id = $1)",
+    ),
+    (
+        "repository_secrets",
+        "DELETE FROM repository_secrets \
+         WHERE repository_id IN (SELECT id FROM repositories WHERE user_id = $1)",
+    ),
+    ("repositories", "DELETE FROM repositories WHERE user_id = $1"),
+    (
+        "notification_hooks",
+        "DELETE FROM notification_hooks WHERE user_id = $1",
+    ),
+    ("documents", "DELETE FROM documents WHERE user_id = $1"),
+    ("api_keys", "DELETE FROM api_keys WHERE user_id = $1"),
+    (
+        "kotadb_api_key_usage",
+        "DELETE FROM kotadb_api_key_usage \
+         WHERE key_id IN (SELECT id FROM kotadb_api_keys WHERE lower(user_id) = $1::text)",
+    ),
+    (
+        "api_key_rate_limits",
+        "DELETE FROM api_key_rate_limits \
+         WHERE key_id IN (SELECT id FROM kotadb_api_keys WHERE lower(user_id) = $1::text)",
+    ),
+    (
+        "kotadb_api_keys",
+        "DELETE FROM kotadb_api_keys WHERE lower(user_id) = $1::text",
+    ),
+];
+
+const REQUEST_COLUMNS: &str = "id, kind, status, confirmation_expires_at, confirmed_at, \
+                               started_at, completed_at, summary, error_message, created_at";
+
+/// Audit record of a tenant export or deletion.
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct AccountDataRequestRow {
+    pub id: Uuid,
+    pub kind: String,
+    pub status: String,
+    pub confirmation_expires_at: Option<DateTime<Utc>>,
+    pub confirmed_at: Option<DateTime<Utc>>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub summary: JsonValue,
+    pub error_message: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Repository whose indexed documents belong to a tenant.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct AccountRepository {
+    pub id: Uuid,
+    pub git_url: String,
+    pub settings: JsonValue,
+}
+
+/// Outcome of presenting a deletion confirmation token.
+#[derive(Debug)]
+pub enum DeletionConfirmation {
+    Scheduled(AccountDataRequestRow),
+    NotFound,
+    /// The request is no longer awaiting confirmation; carries its status
+    NotPending(String),
+    Expired,
+    InvalidToken,
+}
+
+/// Deletion request claimed by the worker.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct ClaimedDeletion {
+    pub id: Uuid,
+    pub user_id: Uuid,
+}
+
+impl SupabaseRepositoryStore {
+    /// Open a deletion request for `user_id`, returning it with its confirmation token.
+    ///
+    /// Earlier requests still awaiting confirmation are cancelled, so only the newest token works.
+    #[instrument(skip(self))]
+    pub async fn request_account_deletion(
+        &self,
+        user_id: Uuid,
+        requested_by_key_id: i64,
+    ) -> Result<(AccountDataRequestRow, String)> {
+        let token = generate_webhook_secret();
+        let expires_at = Utc::now() + Duration::minutes(DELETION_CONFIRMATION_TTL_MINUTES);
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query(
+            r#"
+            UPDATE account_data_requests
+            SET status = 'cancelled',
+                confirmation_hash = NULL
+            WHERE user_id = $1 AND kind = 'deletion' AND status = 'pending_confirmation'
+            "#,
+        )
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await
+        .context("failed to cancel earlier deletion requests")?;
+
+        let row = sqlx::query_as::<_, AccountDataRequestRow>(&format!(
+            r#"
+            INSERT INTO account_data_requests (
+                user_id, requested_by_key_id, kind, status, confirmation_hash, confirmation_expires_at
+            )
+            VALUES ($1, $2, 'deletion', 'pending_confirmation', $3, $4)
+            RETURNING {REQUEST_COLUMNS}
+            "#
+        ))
+        .bind(user_id)
+        .bind(requested_by_key_id)
+        .bind(hash_secret(&token))
+        .bind(expires_at)
+        .fetch_one(&mut *tx)
+        .await
+        .context("failed to create deletion request")?;
+
+        tx.commit().await?;
+        Ok((row, token))
+    }
+
+    /// Schedule deletion `request_id` of `user_id` when `token` matches and has not expired
+    #[instrument(skip(self, token))]
+    pub async fn confirm_account_deletion(
+        &self,
+        request_id: Uuid,
+        user_id: Uuid,
+        token: &str,
+    ) -> Result<DeletionConfirmation> {
+        #[derive(sqlx::FromRow)]
+        struct PendingRow {
+            status: String,
+            confirmation_hash: Option<String>,
+            confirmation_expires_at: Option<DateTime<Utc>>,
+        }
+
+        let mut tx = self.pool.begin().await?;
+        let pending = sqlx::query_as::<_, PendingRow>(
+            r#"
+            SELECT status, confirmation_hash, confirmation_expires_at
+            FROM account_data_requests
+            WHERE id = $1 AND user_id = $2 AND kind = 'deletion'
+            FOR UPDATE
+            "#,
+        )
+        .bind(request_id)
+        .bind(user_id)
+        .fetch_optional(&mut *tx)
+        .await
+        .context("failed to load deletion request")?;
+
+        let Some(pending) = pending else {
+            return Ok(DeletionConfirmation::NotFound);
+        };
+        if pending.status != "pending_confirmation" {
+            return Ok(DeletionConfirmation::NotPending(pending.status));
+        }
+        if pending
+            .confirmation_expires_at
+            .is_none_or(|expires_at| expires_at < Utc::now())
+        {
+            return Ok(DeletionConfirmation::Expired);
+        }
+        if pending.confirmation_hash.as_deref() != Some(hash_secret(token).as_str()) {
+            return Ok(DeletionConfirmation::InvalidToken);
+        }
+
+        let row = sqlx::query_as::<_, AccountDataRequestRow>(&format!(
+            r#"
+            UPDATE account_data_requests
+            SET status = 'scheduled',
+                confirmation_hash = NULL,
+                confirmed_at = NOW()
+            WHERE id = $1
+            RETURNING {REQUEST_COLUMNS}
+            "#
+        ))
+        .bind(request_id)
+        .fetch_one(&mut *tx)
+        .await
+        .context("failed to schedule deletion")?;
+
+        tx.commit().await?;
+        Ok(DeletionConfirmation::Scheduled(row))
+    }
+
+    /// Cancel a deletion of `user_id` the worker has not started; returns whether one was cancelled
+    #[instrument(skip(self))]
+    pub async fn cancel_account_deletion(&self, request_id: Uuid, user_id: Uuid) -> Result<bool> {
+        let result = sqlx::query(
+            r#"
+            UPDATE account_data_requests
+            SET status = 'cancelled',
+                confirmation_hash = NULL
+            WHERE id = $1 AND user_id = $2 AND kind = 'deletion'
+              AND status IN ('pending_confirmation', 'scheduled')
+            "#,
+        )
+        .bind(request_id)
+        .bind(user_id)
+        .execute(&self.pool)
+        .await
+        .context("failed to cancel deletion request")?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    #[instrument(skip(self))]
+    pub async fn list_account_data_requests(
+        &self,
+        user_id: Uuid,
+    ) -> Result<Vec<AccountDataRequestRow>> {
+        let rows = sqlx::query_as::<_, AccountDataRequestRow>(&format!(
+            r#"
+            SELECT {REQUEST_COLUMNS}
+            FROM account_data_requests
+            WHERE user_id = $1
+            ORDER BY created_at DESC
+            "#
+        ))
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await
+        .context("failed to list account data requests")?;
+
+        Ok(rows)
+    }
+
+    /// Record a finished export of `user_id`'s data
+    #[instrument(skip(self, summary))]
+    pub async fn record_account_export(
+        &self,
+        user_id: Uuid,
+        requested_by_key_id: i64,
+        summary: &JsonValue,
+    ) -> Result<AccountDataRequestRow> {
+        let row = sqlx::query_as::<_, AccountDataRequestRow>(&format!(
+            r#"
+            INSERT INTO account_data_requests (
+                user_id, requested_by_key_id, kind, status, started_at, completed_at, summary
+            )
+            VALUES ($1, $2, 'export', 'completed', NOW(), NOW(), $3)
+            RETURNING {REQUEST_COLUMNS}
+            "#
+        ))
+        .bind(user_id)
+        .bind(requested_by_key_id)
+        .bind(Json(summary))
+        .fetch_one(&self.pool)
+        .await
+        .context("failed to record account export")?;
+
+        Ok(row)
+    }
+
+    /// Rows of every exported table belonging to `user_id`
+    #[instrument(skip(self))]
+    pub async fn export_account_tables(
+        &self,
+        user_id: Uuid,
+    ) -> Result<Vec<(&'static str, Vec<JsonValue>)>> {
+        let mut tables = Vec::with_capacity(EXPORT_QUERIES.len());
+        for (name, query) in EXPORT_QUERIES {
+            let rows = sqlx::query_scalar::<_, JsonValue>(query)
+                .bind(user_id)
+                .fetch_all(&self.pool)
+                .await
+                .with_context(|| format!("failed to export {}", name))?;
+            tables.push((*name, rows));
+        }
+        Ok(tables)
+    }
+
+    #[instrument(skip(self))]
+    pub async fn account_repositories(&self, user_id: Uuid) -> Result<Vec<AccountRepository>> {
+        let rows = sqlx::query_as::<_, AccountRepository>(
+            "SELECT id, git_url, settings FROM repositories WHERE user_id = $1",
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await
+        .context("failed to list account repositories")?;
+
+        Ok(rows)
+    }
+
+    /// Document prefixes of repositories owned by anyone but `user_id`
+    #[instrument(skip(self))]
+    pub async fn foreign_document_prefixes(&self, user_id: Uuid) -> Result<HashSet<String>> {
+        let rows = sqlx::query_as::<_, (String, JsonValue)>(
+            "SELECT git_url, settings FROM repositories WHERE user_id IS DISTINCT FROM $1",
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await
+        .context("failed to list other repositories")?;
+
+        Ok(rows
+            .iter()
+            .map(|(git_url, settings)| document_prefix(git_url, settings))
+            .collect())
+    }
+
+    #[instrument(skip(self))]
+    pub async fn account_upload_ids(&self, user_id: Uuid) -> Result<Vec<Uuid>> {
+        let ids = sqlx::query_scalar::<_, Uuid>("SELECT id FROM source_uploads WHERE user_id = $1")
+            .bind(user_id)
+            .fetch_all(&self.pool)
+            .await
+            .context("failed to list account uploads")?;
+
+        Ok(ids)
+    }
+
+    /// Claim the oldest scheduled deletion, marking it in progress
+    #[instrument(skip(self))]
+    pub async fn claim_account_deletion(&self) -> Result<Option<ClaimedDeletion>> {
+        let row = sqlx::query_as::<_, ClaimedDeletion>(
+            r#"
+            UPDATE account_data_requests
+            SET status = 'in_progress',
+                started_at = NOW()
+            WHERE id = (
+                SELECT id
+                FROM account_data_requests
+                WHERE kind = 'deletion' AND status = 'scheduled'
+                ORDER BY created_at
+                LIMIT 1
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING id, user_id
+            "#,
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .context("failed to claim account deletion")?;
+
+        Ok(row)
+    }
+
+    /// Delete every row belonging to `user_id`, returning the count removed per table
+    #[instrument(skip(self))]
+    pub async fn purge_account_rows(&self, user_id: Uuid) -> Result<BTreeMap<String, u64>> {
+        let mut counts = BTreeMap::new();
+        let mut tx = self.pool.begin().await?;
+        for (name, statement) in PURGE_STATEMENTS {
+            let result = sqlx::query(statement)
+                .bind(user_id)
+                .execute(&mut *tx)
+                .await
+                .with_context(|| format!("failed to delete {}", name))?;
+            counts.insert(name.to_string(), result.rows_affected());
+        }
+        tx.commit().await?;
+        Ok(counts)
+    }
+
+    /// Close a claimed deletion as `completed` or `failed` with its audit summary
+    #[instrument(skip(self, summary, error_message))]
+    pub async fn finish_account_deletion(
+        &self,
+        request_id: Uuid,
+        summary: &JsonValue,
+        error_message: Option<&str>,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE account_data_requests
+            SET status = CASE WHEN $3::text IS NULL THEN 'completed' ELSE 'failed' END,
+                completed_at = NOW(),
+                summary = $2,
+                error_message = $3
+            WHERE id = $1
+            "#,
+        )
+        .bind(request_id)
+        .bind(Json(summary))
+        .bind(error_message)
+        .execute(&self.pool)
+        .await
+        .context("failed to record deletion outcome")?;
+
+        Ok(())
+    }
+}
+
+/// Storage path prefix under which the worker ingests a repository's documents
+pub fn document_prefix(git_url: &str, settings: &JsonValue) -> String {
+    let root = settings
+        .get("prefix")
+        .and_then(|value| value.as_str())
+        .unwrap_or("repos")
+        .trim_matches('/');
+    let name = sanitize_repository_name(&infer_repository_identifier(git_url));
+    format!("{}/{}/", root, name)
+}
+
+/// Storage path prefix of a repository a local server indexed from directory `name`
+pub fn local_document_prefix(name: &str) -> String {
+    format!("repos/{}/", sanitize_repository_name(name))
+}
+
+/// Split `own` prefixes into those only this tenant uses and those another tenant shares
+pub fn split_shared_prefixes(
+    own: impl IntoIterator<Item = String>,
+    foreign: &HashSet<String>,
+) -> (Vec<String>, Vec<String>) {
+    let mut exclusive = Vec::new();
+    let mut shared = Vec::new();
+    for prefix in own.into_iter().collect::<std::collections::BTreeSet<_>>() {
+        if foreign.contains(&prefix) {
+            shared.push(prefix);
+        } else {
+            exclusive.push(prefix);
+        }
+    }
+    (exclusive, shared)
+}
+
+/// Gzip-compressed tar export written as it is built: one `metadata/<table>.json` per table,
+/// the tenant's annotation store under `annotations/`, document contents under `documents/`
+/// one page at a time, and `manifest.json` last, once the document count is known
+pub struct ExportArchive<W: Write> {
+    builder: tar::Builder<GzEncoder<W>>,
+    mtime: u64,
+    documents: usize,
+}
+
+impl<W: Write> ExportArchive<W> {
+    /// Start an archive with the exported tables and annotations
+    pub fn new(
+        writer: W,
+        tables: &[(&str, Vec<JsonValue>)],
+        annotations_dir: Option<&Path>,
+    ) -> Result<Self> {
+        let mut archive = Self {
+            builder: tar::Builder::new(GzEncoder::new(writer, Compression::default())),
+            mtime: Utc::now().timestamp().max(0) as u64,
+            documents: 0,
+        };
+        for (name, rows) in tables {
+            archive.append(
+                &format!("metadata/{}.json", name),
+                &serde_json::to_vec_pretty(rows)?,
+            )?;
+        }
+        if let Some(dir) = annotations_dir.filter(|dir| dir.is_dir()) {
+            archive
+                .builder
+                .append_dir_all("annotations", dir)
+                .context("failed to add annotations to export")?;
+        }
+        Ok(archive)
+    }
+
+    /// Add the contents of a page of documents
+    pub fn append_documents(&mut self, documents: &[Document]) -> Result<()> {
+        for document in documents {
+            self.append(
+                &format!(
+                    "documents/{}",
+                    document.path.as_str().trim_start_matches('/')
+                ),
+                &document.content,
+            )?;
+            self.documents += 1;
+        }
+        Ok(())
+    }
+
+    /// Number of documents added so far
+    pub fn documents(&self) -> usize {
+        self.documents
+    }
+
+    /// Write `manifest` and close the archive
+    pub fn finish(mut self, manifest: &JsonValue) -> Result<W> {
+        self.append("manifest.json", &serde_json::to_vec_pretty(manifest)?)?;
+        Ok(self.builder.into_inner()?.finish()?)
+    }
+
+    fn append(&mut self, path: &str, bytes: &[u8]) -> Result<()> {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(bytes.len() as u64);
+        header.set_mode(0o644);
+        header.set_mtime(self.mtime);
+        self.builder
+            .append_data(&mut header, path, bytes)
+            .with_context(|| format!("failed to add {} to export", path))
+    }
+}
+
+/// Manifest describing an export archive
+pub fn export_manifest(
+    user_id: Uuid,
+    tables: &[(&str, Vec<JsonValue>)],
+    prefixes: &[String],
+    documents: usize,
+) -> JsonValue {
+    let counts: BTreeMap<&str, usize> = tables
+        .iter()
+        .map(|(name, rows)| (*name, rows.len()))
+        .collect();
+    json!({
+        "format_version": 1,
+        "user_id": user_id,
+        "generated_at": Utc::now().to_rfc3339(),
+        "tables": counts,
+        "document_prefixes": prefixes,
+        "documents": documents,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ValidatedDocumentId, ValidatedPath, ValidatedTitle};
+    use flate2::read::GzDecoder;
+    use std::io::Read;
+
+    #[test]
+    fn shared_prefixes_are_retained() {
+        let own = [
+            document_prefix("https://github.com/acme/Widgets.git", &json!({})),
+            document_prefix("upload://archive/tools", &json!({ "prefix": "/mirror/" })),
+        ];
+        assert_eq!(own[0], "repos/widgets/");
+        assert_eq!(own[1], "mirror/tools/");
+
+        let foreign = HashSet::from([document_prefix(
+            "git@github.com:other/widgets.git",
+            &json!({}),
+        )]);
+        let (exclusive, shared) = split_shared_prefixes(own, &foreign);
+        assert_eq!(exclusive, vec!["mirror/tools/"]);
+        assert_eq!(shared, vec!["repos/widgets/"]);
+    }
+
+    #[test]
+    fn export_archive_holds_metadata_annotations_and_documents() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        std::fs::write(dir.path().join("annotations.json"), "{}")?;
+        let document = Document::new(
+            ValidatedDocumentId::new(),
+            ValidatedPath::new("repos/widgets/files/src/lib.rs")?,
+            ValidatedTitle::new("lib.rs")?,
+            b"pub fn a() {}\n".to_vec(),
+            Vec::new(),
+            Utc::now(),
+            Utc::now(),
+        );
+        let tables = vec![("repositories", vec![json!({ "name": "widgets" })])];
+        let mut archive = ExportArchive::new(Vec::new(), &tables, Some(dir.path()))?;
+        archive.append_documents(&[document])?;
+        let manifest = export_manifest(
+            Uuid::nil(),
+            &tables,
+            &["repos/widgets/".into()],
+            archive.documents(),
+        );
+        let bytes = archive.finish(&manifest)?;
+
+        let mut archive = tar::Archive::new(GzDecoder::new(bytes.as_slice()));
+        let mut entries = BTreeMap::new();
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            let mut content = String::new();
+            entry.read_to_string(&mut content)?;
+            entries.insert(entry.path()?.display().to_string(), content);
+        }
+        assert_eq!(
+            entries["documents/repos/widgets/files/src/lib.rs"],
+            "pub fn a() {}\n"
+        );
+        assert!(entries["metadata/repositories.json"].contains("widgets"));
+        assert_eq!(entries["annotations/annotations.json"], "{}");
+        let manifest: JsonValue = serde_json::from_str(&entries["manifest.json"])?;
+        assert_eq!(manifest["tables"]["repositories"], 1);
+        assert_eq!(manifest["documents"], 1);
+        Ok(())
+    }
+}
@@ -1,7 +1,10 @@
 use std::{
     collections::HashSet,
     path::{Path, PathBuf},
-    sync::Arc,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
     time::{Duration, Instant},
 };
 
@@ -18,10 +21,24 @@ use tracing::{debug, error, info, instrument, warn};
 use url::Url;
 use uuid::Uuid;
 
+use crate::annotations::AnnotationStore;
+use crate::ingestion_sandbox::IngestionSandbox;
+use crate::metrics::performance::{LockedResource, TimedLock};
 use crate::services::{DatabaseAccess, IndexCodebaseOptions, IndexResult, IndexingService};
 
 use super::{
-    task::{merge_settings, option_bool, option_usize, SupabaseJobPayload},
+    account::{document_prefix, split_shared_prefixes},
+    notifications::{
+        build_notification_payload, dispatch_notifications, EVENT_INDEX_COMPLETED,
+        EVENT_INDEX_FAILED,
+    },
+    task::{
+        merge_settings, option_bool, option_usize, parse_github_owner_repo, SupabaseJobPayload,
+    },
+    uploads::{
+        archive_root, contiguous_size, extract_archive, list_parts, part_path, upload_dir,
+        ExtractedArchive, ARCHIVE_INDEX_JOB,
+    },
     JobForWorker, RepositoryMetaRow, SupabaseRepositoryStore,
 };
 
@@ -34,6 +51,10 @@ where
     pub database: Arc<D>,
     pub db_path: PathBuf,
     pub poll_interval: Duration,
+    /// Generation counter of the serving process, bumped when a job changes indexed data
+    pub data_generation: Option<Arc<AtomicU64>>,
+    /// Subprocess that parses repositories instead of the worker's own process
+    pub sandbox: Option<IngestionSandbox>,
 }
 
 // Jobs are considered stale after 45 minutes of inactivity. A periodic heartbeat keeps
@@ -42,6 +63,12 @@ const STALE_JOB_MAX_AGE: Duration = Duration::from_secs(45 * 60);
 // Heartbeat interval is intentionally aggressive so we have a dense breadcrumb trail in
 // job_events and tighter monitoring of Supabase connection health.
 const JOB_HEARTBEAT_INTERVAL: Duration = Duration::from_secs(15);
+// Delta jobs fetch changed blobs one API call at a time; larger pushes use a shallow fetch.
+const MAX_DELTA_BLOBS: usize = 200;
+// Documents read per storage page when deleting a tenant's documents.
+const DELETE_PAGE_SIZE: usize = 256;
+const GITHUB_API_URL: &str = "https://api.github.com";
+const GITHUB_USER_AGENT: &str = "kotadb-saas-worker/1.0";
 
 fn delivery_id_from_payload(payload: &JsonValue) -> Option<i64> {
     match payload.get("webhook_delivery_id") {
@@ -143,7 +170,7 @@ impl Drop for JobHeartbeat {
     }
 }
 
-fn infer_repository_identifier(git_url: &str) -> String {
+pub(super) fn infer_repository_identifier(git_url: &str) -> String {
     if let Ok(parsed) = Url::parse(git_url) {
         if let Some(segment) = parsed
             .path()
@@ -172,7 +199,82 @@ fn infer_repository_identifier(git_url: &str) -> String {
     "repository".to_string()
 }
 
-fn sanitize_repository_name(name: &str) -> String {
+/// Whether a push payload path stays inside the workspace it is written to
+fn is_safe_relative_path(path: &str) -> bool {
+    !path.is_empty()
+        && Path::new(path)
+            .components()
+            .all(|component| matches!(component, std::path::Component::Normal(_)))
+}
+
+/// Commit `blobs` into a fresh repository at `dir` with `origin` pointing at `git_url`
+#[cfg(feature = "git-integration")]
+fn write_delta_repository(
+    dir: &Path,
+    git_url: &str,
+    branch: Option<&str>,
+    blobs: &[(String, Vec<u8>)],
+) -> Result<()> {
+    let mut init_options = git2::RepositoryInitOptions::new();
+    if let Some(branch) = branch {
+        init_options.initial_head(branch);
+    }
+    let repo = git2::Repository::init_opts(dir, &init_options)
+        .context("failed to initialise delta workspace")?;
+    repo.remote("origin", git_url)?;
+
+    let mut index = repo.index()?;
+    for (path, content) in blobs {
+        let target = dir.join(path);
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&target, content)?;
+        index.add_path(Path::new(path))?;
+    }
+    index.write()?;
+
+    let tree = repo.find_tree(index.write_tree()?)?;
+    let signature = git2::Signature::now("kotadb", "kotadb@localhost")?;
+    repo.commit(
+        Some("HEAD"),
+        &signature,
+        &signature,
+        "KotaDB delta snapshot",
+        &tree,
+        &[],
+    )?;
+    Ok(())
+}
+
+/// Commit the files already present in `dir` into a fresh repository
+#[cfg(feature = "git-integration")]
+fn write_snapshot_repository(dir: &Path, branch: Option<&str>) -> Result<()> {
+    let mut init_options = git2::RepositoryInitOptions::new();
+    if let Some(branch) = branch {
+        init_options.initial_head(branch);
+    }
+    let repo = git2::Repository::init_opts(dir, &init_options)
+        .context("failed to initialise archive workspace")?;
+
+    let mut index = repo.index()?;
+    index.add_all(["*"], git2::IndexAddOption::DEFAULT, None)?;
+    index.write()?;
+
+    let tree = repo.find_tree(index.write_tree()?)?;
+    let signature = git2::Signature::now("kotadb", "kotadb@localhost")?;
+    repo.commit(
+        Some("HEAD"),
+        &signature,
+        &signature,
+        "KotaDB archive snapshot",
+        &tree,
+        &[],
+    )?;
+    Ok(())
+}
+
+pub(super) fn sanitize_repository_name(name: &str) -> String {
     let sanitized = name
         .chars()
         .map(|c| {
@@ -203,6 +305,8 @@ where
             database,
             db_path,
             poll_interval: Duration::from_secs(5),
+            data_generation: None,
+            sandbox: None,
         }
     }
 
@@ -211,6 +315,25 @@ where
         self
     }
 
+    /// Share the HTTP server's generation counter so completed jobs invalidate ETags
+    pub fn with_data_generation(mut self, data_generation: Arc<AtomicU64>) -> Self {
+        self.data_generation = Some(data_generation);
+        self
+    }
+
+    /// Ingest untrusted repositories in `sandbox` rather than in this process
+    pub fn with_ingestion_sandbox(mut self, sandbox: IngestionSandbox) -> Self {
+        self.sandbox = Some(sandbox);
+        self
+    }
+
+    /// Record that a job changed the local indices
+    fn record_data_change(&self) {
+        if let Some(data_generation) = &self.data_generation {
+            data_generation.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
     #[instrument(skip_all)]
     pub async fn run(&self) -> Result<()> {
         loop {
@@ -238,6 +361,14 @@ where
             );
         }
 
+        // Confirmed account deletions run ahead of indexing so a tenant's data is not
+        // re-indexed after they asked for it to be removed.
+        match self.process_account_deletion().await {
+            Ok(true) => return Ok(true),
+            Ok(false) => {}
+            Err(e) => warn!("Account deletion processing failed: {}", e),
+        }
+
         let Some(job) = self.store.fetch_job_for_worker().await? else {
             return Ok(false);
         };
@@ -250,10 +381,37 @@ where
 
         match self.process_job(job).await {
             Ok((result, webhook_delivery_id)) => {
-                self.store.complete_job(job_id, result).await?;
+                self.record_data_change();
+                self.store.complete_job(job_id, result.clone()).await?;
                 self.store
                     .record_job_event(job_id, "completed", "Job completed", None)
                     .await?;
+                let generation = match self
+                    .store
+                    .bump_index_generation(job_clone.repository_id)
+                    .await
+                {
+                    Ok(generation) => Some(generation),
+                    Err(err) => {
+                        warn!(
+                            "Failed to bump index generation for job {}: {}",
+                            job_id, err
+                        );
+                        None
+                    }
+                };
+                self.notify(
+                    &job_clone,
+                    build_notification_payload(
+                        EVENT_INDEX_COMPLETED,
+                        job_id,
+                        job_clone.repository_id,
+                        &job_clone.job_type,
+                        result.as_ref(),
+                        None,
+                        generation,
+                    ),
+                );
                 if let Some(delivery_id) = webhook_delivery_id {
                     if let Err(err) = self
                         .store
@@ -282,6 +440,18 @@ where
                         Some(json!({ "error": e.to_string() })),
                     )
                     .await?;
+                self.notify(
+                    &job_clone,
+                    build_notification_payload(
+                        EVENT_INDEX_FAILED,
+                        job_id,
+                        job_clone.repository_id,
+                        &job_clone.job_type,
+                        None,
+                        Some(&e.to_string()),
+                        None,
+                    ),
+                );
                 if let Some(delivery_id) = delivery_id_from_payload(&job_clone.payload) {
                     if let Err(err) = self
                         .store
@@ -305,9 +475,187 @@ where
         }
     }
 
+    /// Carry out the oldest confirmed account deletion; returns whether one was claimed
+    async fn process_account_deletion(&self) -> Result<bool> {
+        let Some(request) = self.store.claim_account_deletion().await? else {
+            return Ok(false);
+        };
+
+        warn!(
+            request_id = %request.id,
+            user_id = %request.user_id,
+            "Deleting account data"
+        );
+        match self.delete_account_data(request.user_id).await {
+            Ok(summary) => {
+                self.store
+                    .finish_account_deletion(request.id, &summary, None)
+                    .await?;
+                info!(request_id = %request.id, "Account deletion completed");
+            }
+            Err(e) => {
+                error!("Account deletion {} failed: {}", request.id, e);
+                self.store
+                    .finish_account_deletion(request.id, &json!({}), Some(&e.to_string()))
+                    .await?;
+            }
+        }
+        Ok(true)
+    }
+
+    /// Remove a tenant's indexed documents, worker files, annotations and rows
+    ///
+    /// Documents under a prefix another tenant's repository also maps to are kept, since
+    /// the local store does not record which tenant ingested them.
+    async fn delete_account_data(&self, user_id: Uuid) -> Result<JsonValue> {
+        let repositories = self.store.account_repositories(user_id).await?;
+        let foreign = self.store.foreign_document_prefixes(user_id).await?;
+        let (prefixes, shared) = split_shared_prefixes(
+            repositories
+                .iter()
+                .map(|repo| document_prefix(&repo.git_url, &repo.settings)),
+            &foreign,
+        );
+
+        let documents_deleted = self.delete_documents_under(&prefixes).await?;
+        if documents_deleted > 0 {
+            self.record_data_change();
+        }
+
+        let repos_dir = self.db_path.join("repos");
+        let mut paths = Vec::new();
+        for repo in &repositories {
+            for suffix in ["", "-delta", "-upload"] {
+                paths.push(repos_dir.join(format!("{}{}", repo.id, suffix)));
+            }
+        }
+        for upload_id in self.store.account_upload_ids(user_id).await? {
+            paths.push(upload_dir(&self.db_path, upload_id));
+        }
+        paths.push(AnnotationStore::tenant_dir(
+            &self.db_path,
+            &format!("user-{user_id}"),
+        ));
+
+        let mut removed_paths = 0;
+        for path in paths {
+            if tokio::fs::try_exists(&path).await.unwrap_or(false) {
+                tokio::fs::remove_dir_all(&path)
+                    .await
+                    .with_context(|| format!("failed to remove {}", path.display()))?;
+                removed_paths += 1;
+            }
+        }
+
+        let rows_deleted = self.store.purge_account_rows(user_id).await?;
+
+        Ok(json!({
+            "documents_deleted": documents_deleted,
+            "document_prefixes": prefixes,
+            "shared_prefixes_retained": shared,
+            "filesystem_paths_removed": removed_paths,
+            "rows_deleted": rows_deleted,
+        }))
+    }
+
+    /// Delete every stored document whose path starts with one of `prefixes`
+    async fn delete_documents_under(&self, prefixes: &[String]) -> Result<usize> {
+        if prefixes.is_empty() {
+            return Ok(0);
+        }
+
+        // Matching documents are deleted one storage page at a time, so the storage, index
+        // and cache locks are only held for one page's deletions
+        let storage_arc = self.database.storage();
+        let primary_index_arc = self.database.primary_index();
+        let trigram_index_arc = self.database.trigram_index();
+        let path_cache = self.database.path_cache();
+        let mut deleted = 0;
+        let mut cursor = None;
+        loop {
+            let page = storage_arc
+                .lock_timed(LockedResource::Storage)
+                .await
+                .list_page(cursor, DELETE_PAGE_SIZE)
+                .await?;
+            cursor = page.next;
+            let matching: Vec<_> = page
+                .documents
+                .into_iter()
+                .filter(|document| {
+                    let path = document.path.as_str().trim_start_matches('/');
+                    prefixes
+                        .iter()
+                        .any(|prefix| path.starts_with(prefix.as_str()))
+                })
+                .map(|document| (document.id, document.path))
+                .collect();
+
+            if !matching.is_empty() {
+                let mut storage = storage_arc.lock_timed(LockedResource::Storage).await;
+                let mut primary_index = primary_index_arc
+                    .lock_timed(LockedResource::PrimaryIndex)
+                    .await;
+                let mut trigram_index = trigram_index_arc
+                    .lock_timed(LockedResource::TrigramIndex)
+                    .await;
+                let mut cache = path_cache.write().await;
+                for (id, path) in matching {
+                    let path = path.as_str();
+                    storage
+                        .delete(&id)
+                        .await
+                        .with_context(|| format!("failed to delete document {}", path))?;
+                    if let Err(err) = primary_index.delete(&id).await {
+                        warn!("Failed to delete primary index entry {}: {}", path, err);
+                    }
+                    if let Err(err) = trigram_index.delete(&id).await {
+                        warn!("Failed to delete trigram index entry {}: {}", path, err);
+                    }
+                    cache.remove(path);
+                    deleted += 1;
+                }
+            }
+
+            if cursor.is_none() {
+                break;
+            }
+        }
+
+        storage_arc
+            .lock_timed(LockedResource::Storage)
+            .await
+            .flush()
+            .await
+            .ok();
+        primary_index_arc
+            .lock_timed(LockedResource::PrimaryIndex)
+            .await
+            .flush()
+            .await
+            .ok();
+        trigram_index_arc
+            .lock_timed(LockedResource::TrigramIndex)
+            .await
+            .flush()
+            .await
+            .ok();
+
+        Ok(deleted)
+    }
+
+    /// Deliver a job notification to the tenant's hooks without holding up the worker
+    fn notify(&self, job: &JobForWorker, payload: JsonValue) {
+        let store = self.store.clone();
+        let repository_id = job.repository_id;
+        tokio::spawn(async move {
+            dispatch_notifications(&store, repository_id, &payload).await;
+        });
+    }
+
     async fn process_job(&self, job: JobForWorker) -> Result<(Option<JsonValue>, Option<i64>)> {
         match job.job_type.as_str() {
-            "webhook_update" => {
+            "webhook_update" | "delta_index" => {
                 let payload = SupabaseJobPayload::parse(job.payload.clone());
                 let plan = self.prepare_incremental_work(&payload);
                 self.process_indexing_job(job, payload, Some(plan)).await
@@ -316,6 +664,18 @@ where
                 let payload = SupabaseJobPayload::parse(job.payload.clone());
                 self.process_indexing_job(job, payload, None).await
             }
+            ARCHIVE_INDEX_JOB => {
+                let payload = SupabaseJobPayload::parse(job.payload.clone());
+                let upload_id = payload.upload_id;
+                let outcome = self.process_indexing_job(job, payload, None).await?;
+                // Parts are kept until indexing succeeds so a failed job can be re-queued
+                if let Some(upload_id) = upload_id {
+                    tokio::fs::remove_dir_all(upload_dir(&self.db_path, upload_id))
+                        .await
+                        .ok();
+                }
+                Ok(outcome)
+            }
             other => {
                 self.store
                     .record_job_event(
@@ -387,7 +747,15 @@ where
         payload.provider = payload.provider.or(Some(repo_meta.provider.clone()));
         payload.branch = payload.branch.or(repo_meta.default_branch.clone());
 
-        let merged_settings = merge_settings(&repo_meta.settings, payload.settings.as_ref());
+        let mut merged_settings = merge_settings(&repo_meta.settings, payload.settings.as_ref());
+        if job.job_type == "delta_index" || job.job_type == ARCHIVE_INDEX_JOB {
+            // Delta and archive workspaces carry a snapshot commit (or a shallow one), not the
+            // real history
+            merged_settings = merge_settings(
+                &merged_settings,
+                Some(&json!({ "options": { "include_commits": false } })),
+            );
+        }
         payload.settings = Some(merged_settings);
 
         if let Some(delivery_id) = payload.webhook_delivery_id {
@@ -475,7 +843,43 @@ where
             {
                 warn!("Failed to record cloning event for job {}: {}", job.id, err);
             }
-            let repo_path = self.prepare_repository(job.repository_id, &payload).await?;
+            let repo_path = if job.job_type == "delta_index" {
+                let paths = include_paths.as_deref().unwrap_or_default();
+                match self
+                    .prepare_delta_workspace(job.repository_id, &payload, paths)
+                    .await
+                {
+                    Ok((path, missing)) => {
+                        // Files absent at the pushed commit were deleted later in the push
+                        self.purge_removed_paths(&repo_meta, &payload, &safe_repo_name, &missing)
+                            .await?;
+                        path
+                    }
+                    Err(err) => {
+                        warn!(
+                            repository = %repo_meta.git_url,
+                            "Delta blob fetch failed, falling back to shallow fetch: {}", err
+                        );
+                        self.store
+                            .record_job_event(
+                                job.id,
+                                "delta_fallback",
+                                "Falling back to shallow fetch",
+                                Some(json!({ "error": err.to_string() })),
+                            )
+                            .await
+                            .ok();
+                        self.prepare_repository(job.repository_id, &payload, Some(1))
+                            .await?
+                    }
+                }
+            } else if job.job_type == ARCHIVE_INDEX_JOB {
+                self.prepare_archive_workspace(job.repository_id, &payload, &safe_repo_name)
+                    .await?
+            } else {
+                self.prepare_repository(job.repository_id, &payload, None)
+                    .await?
+            };
             warn!(
                 repository = %repo_meta.git_url,
                 path = %repo_path.display(),
@@ -505,6 +909,7 @@ where
                     Some(json!({
                         "create_index_doc": !skip_index_document,
                         "include_paths": include_paths.as_ref().map(|paths| paths.len()),
+                        "sandboxed": self.sandbox.is_some(),
                     })),
                 )
                 .await
@@ -614,15 +1019,192 @@ where
         ))
     }
 
+    /// Build a workspace holding only the blobs a push changed.
+    ///
+    /// Blobs are fetched from the GitHub contents API at the pushed commit and committed into
+    /// a throwaway repository whose `origin` matches the real one, so ingestion names documents
+    /// exactly as a full clone would. Returns the workspace and the paths missing at that commit.
+    async fn prepare_delta_workspace(
+        &self,
+        repository_id: Uuid,
+        payload: &SupabaseJobPayload,
+        paths: &[String],
+    ) -> Result<(PathBuf, Vec<String>)> {
+        #[cfg(not(feature = "git-integration"))]
+        {
+            let _ = (repository_id, payload, paths);
+            return Err(anyhow!(
+                "git-integration feature is required for SaaS ingestion worker"
+            ));
+        }
+
+        #[cfg(feature = "git-integration")]
+        {
+            if paths.len() > MAX_DELTA_BLOBS {
+                return Err(anyhow!(
+                    "{} changed files exceeds the delta limit of {}",
+                    paths.len(),
+                    MAX_DELTA_BLOBS
+                ));
+            }
+            if paths.iter().any(|path| !is_safe_relative_path(path)) {
+                return Err(anyhow!("push payload contains an unsafe path"));
+            }
+            let (owner, repo) = parse_github_owner_repo(&payload.git_url)
+                .context("delta indexing requires a GitHub repository")?;
+            let git_ref = payload
+                .head_sha
+                .clone()
+                .or_else(|| payload.branch.clone())
+                .context("push payload missing head commit")?;
+
+            let client = reqwest::Client::builder()
+                .timeout(Duration::from_secs(30))
+                .user_agent(GITHUB_USER_AGENT)
+                .build()
+                .context("failed to build GitHub client")?;
+            let token = std::env::var("GITHUB_WEBHOOK_TOKEN")
+                .ok()
+                .filter(|token| !token.trim().is_empty());
+
+            let mut blobs = Vec::with_capacity(paths.len());
+            let mut missing = Vec::new();
+            for path in paths {
+                let mut url =
+                    Url::parse(&format!("{GITHUB_API_URL}/repos/{owner}/{repo}/contents"))
+                        .context("failed to construct GitHub contents URL")?;
+                url.path_segments_mut()
+                    .map_err(|_| anyhow!("invalid GitHub contents URL"))?
+                    .extend(path.split('/'));
+
+                let mut request = client
+                    .get(url)
+                    .query(&[("ref", git_ref.as_str())])
+                    .header("Accept", "application/vnd.github.raw");
+                if let Some(token) = &token {
+                    request = request.header("Authorization", format!("token {token}"));
+                }
+                let response = request
+                    .send()
+                    .await
+                    .with_context(|| format!("failed to fetch {} from GitHub", path))?;
+                if response.status() == reqwest::StatusCode::NOT_FOUND {
+                    missing.push(path.clone());
+                    continue;
+                }
+                if !response.status().is_success() {
+                    return Err(anyhow!(
+                        "GitHub returned {} fetching {}",
+                        response.status(),
+                        path
+                    ));
+                }
+                blobs.push((path.clone(), response.bytes().await?.to_vec()));
+            }
+
+            let repo_dir = self
+                .db_path
+                .join("repos")
+                .join(format!("{}-delta", repository_id));
+            if repo_dir.exists() {
+                tokio::fs::remove_dir_all(&repo_dir).await.ok();
+            }
+            tokio::fs::create_dir_all(&repo_dir).await?;
+
+            let git_url = payload.git_url.clone();
+            let branch = payload.branch.clone();
+            let repo_dir_clone = repo_dir.clone();
+            task::spawn_blocking(move || {
+                write_delta_repository(&repo_dir_clone, &git_url, branch.as_deref(), &blobs)
+            })
+            .await??;
+
+            info!(
+                repository = %payload.git_url,
+                fetched = paths.len() - missing.len(),
+                missing = missing.len(),
+                "Delta workspace ready"
+            );
+            Ok((repo_dir, missing))
+        }
+    }
+
+    /// Unpack an uploaded archive into a single-commit workspace.
+    ///
+    /// The workspace directory is named after the repository so ingestion, which falls back to
+    /// the directory name when there is no `origin`, labels documents as it would for a clone.
+    async fn prepare_archive_workspace(
+        &self,
+        repository_id: Uuid,
+        payload: &SupabaseJobPayload,
+        repo_name: &str,
+    ) -> Result<PathBuf> {
+        #[cfg(not(feature = "git-integration"))]
+        {
+            let _ = (repository_id, payload, repo_name);
+            return Err(anyhow!(
+                "git-integration feature is required for SaaS ingestion worker"
+            ));
+        }
+
+        #[cfg(feature = "git-integration")]
+        {
+            let upload_id = payload
+                .upload_id
+                .context("archive job payload missing upload_id")?;
+            let parts_dir = upload_dir(&self.db_path, upload_id);
+            let parts = list_parts(&parts_dir)?;
+            contiguous_size(&parts)
+                .with_context(|| format!("upload {} is incomplete", upload_id))?;
+            let part_paths: Vec<PathBuf> = parts
+                .iter()
+                .map(|part| part_path(&parts_dir, part.part_number))
+                .collect();
+
+            let workspace = self
+                .db_path
+                .join("repos")
+                .join(format!("{}-upload", repository_id));
+            if workspace.exists() {
+                tokio::fs::remove_dir_all(&workspace).await.ok();
+            }
+            let extract_dir = workspace.join(".extract");
+            let repo_dir = workspace.join(repo_name);
+            let branch = payload.branch.clone();
+            let repo_dir_clone = repo_dir.clone();
+
+            let summary = task::spawn_blocking(move || -> Result<ExtractedArchive> {
+                let summary = extract_archive(&part_paths, &extract_dir)?;
+                std::fs::rename(archive_root(&extract_dir)?, &repo_dir_clone)
+                    .context("failed to move archive root into place")?;
+                write_snapshot_repository(&repo_dir_clone, branch.as_deref())?;
+                Ok(summary)
+            })
+            .await??;
+
+            info!(
+                repository_id = %repository_id,
+                upload_id = %upload_id,
+                files = summary.files,
+                bytes = summary.bytes,
+                skipped = summary.skipped,
+                "Archive workspace ready"
+            );
+            Ok(repo_dir)
+        }
+    }
+
     async fn prepare_repository(
         &self,
         repository_id: Uuid,
         payload: &SupabaseJobPayload,
+        depth: Option<i32>,
     ) -> Result<PathBuf> {
         #[cfg(not(feature = "git-integration"))]
         {
             let _ = repository_id;
             let _ = payload;
+            let _ = depth;
             return Err(anyhow!(
                 "git-integration feature is required for SaaS ingestion worker"
             ));
@@ -661,6 +1243,11 @@ where
                 if let Some(branch) = branch.as_deref() {
                     builder.branch(branch);
                 }
+                if let Some(depth) = depth {
+                    let mut fetch_options = git2::FetchOptions::new();
+                    fetch_options.depth(depth);
+                    builder.fetch_options(fetch_options);
+                }
 
                 builder
                     .clone(&git_url, repo_dir_clone.as_path())
@@ -758,6 +1345,7 @@ where
         let store = self.store.clone();
         let db_path = self.db_path.clone();
         let database = self.database.clone();
+        let sandbox = self.sandbox.clone();
         let options_clone = options.clone();
         let progress_callback_block = Arc::clone(&progress_callback);
         let blocking_store = store.clone();
@@ -799,7 +1387,8 @@ where
                     });
 
                     let indexing = IndexingService::new(database.as_ref(), db_path)
-                        .with_progress_callback(Some(progress_callback_block));
+                        .with_progress_callback(Some(progress_callback_block))
+                        .with_sandbox(sandbox);
                     let indexing_result = indexing.index_codebase(options_clone).await;
 
                     heartbeat_task.abort();
@@ -845,11 +1434,15 @@ where
 
         let prefix = prefix.trim_start_matches('/');
         let storage_arc = self.database.storage();
-        let mut storage = storage_arc.lock().await;
+        let mut storage = storage_arc.lock_timed(LockedResource::Storage).await;
         let primary_index_arc = self.database.primary_index();
-        let mut primary_index = primary_index_arc.lock().await;
+        let mut primary_index = primary_index_arc
+            .lock_timed(LockedResource::PrimaryIndex)
+            .await;
         let trigram_index_arc = self.database.trigram_index();
-        let mut trigram_index = trigram_index_arc.lock().await;
+        let mut trigram_index = trigram_index_arc
+            .lock_timed(LockedResource::TrigramIndex)
+            .await;
         let path_cache = self.database.path_cache();
         let mut cache = path_cache.write().await;
 
@@ -897,3 +1490,84 @@ where
         Ok(())
     }
 }
+
+#[cfg(all(test, feature = "git-integration"))]
+mod tests {
+    use super::*;
+    use crate::git::{types::IngestionOptions, GitRepository};
+
+    #[test]
+    fn delta_repository_mirrors_origin_name_and_paths() -> Result<()> {
+        let dir = tempfile::TempDir::new()?;
+        let blobs = vec![
+            ("src/lib.rs".to_string(), b"pub fn a() {}\n".to_vec()),
+            ("README.md".to_string(), b"# demo\n".to_vec()),
+        ];
+        write_delta_repository(
+            dir.path(),
+            "https://github.com/acme/widgets.git",
+            Some("main"),
+            &blobs,
+        )?;
+
+        let repo = GitRepository::open(dir.path(), IngestionOptions::default())?;
+        let metadata = repo.metadata()?;
+        assert_eq!(metadata.name, "widgets");
+        assert_eq!(metadata.default_branch, "main");
+        let mut paths: Vec<String> = repo.list_files()?.into_iter().map(|f| f.path).collect();
+        paths.sort();
+        assert_eq!(paths, vec!["README.md", "src/lib.rs"]);
+        Ok(())
+    }
+
+    #[test]
+    fn archive_snapshot_is_named_after_its_directory() -> Result<()> {
+        let dir = tempfile::TempDir::new()?;
+        let workspace = dir.path().join("widgets");
+        std::fs::create_dir_all(workspace.join("src"))?;
+        std::fs::write(workspace.join("src/lib.rs"), "pub fn a() {}\n")?;
+        std::fs::write(workspace.join("README.md"), "# demo\n")?;
+        write_snapshot_repository(&workspace, Some("main"))?;
+
+        let repo = GitRepository::open(&workspace, IngestionOptions::default())?;
+        let metadata = repo.metadata()?;
+        assert_eq!(metadata.name, "widgets");
+        assert_eq!(metadata.default_branch, "main");
+        let mut paths: Vec<String> = repo.list_files()?.into_iter().map(|f| f.path).collect();
+        paths.sort();
+        assert_eq!(paths, vec!["README.md", "src/lib.rs"]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn completed_jobs_bump_the_shared_data_generation() -> Result<()> {
+        let dir = tempfile::TempDir::new()?;
+        let database = Arc::new(crate::database::Database::new(dir.path(), true).await?);
+        // Never connected: recording a data change must not touch Supabase
+        let pool = sqlx::postgres::PgPoolOptions::new()
+            .connect_lazy("postgres://kotadb@127.0.0.1:1/unused")?;
+        let generation = Arc::new(AtomicU64::new(7));
+
+        let worker = SupabaseJobWorker::new(
+            SupabaseRepositoryStore::new(pool),
+            database,
+            dir.path().to_path_buf(),
+        );
+        worker.record_data_change();
+        assert_eq!(generation.load(Ordering::SeqCst), 7);
+
+        let worker = worker.with_data_generation(generation.clone());
+        worker.record_data_change();
+        assert_eq!(generation.load(Ordering::SeqCst), 8);
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_paths_escaping_the_workspace() {
+        assert!(is_safe_relative_path("src/lib.rs"));
+        assert!(!is_safe_relative_path("../etc/passwd"));
+        assert!(!is_safe_relative_path("/etc/passwd"));
+        assert!(!is_safe_relative_path("src/../../x"));
+        assert!(!is_safe_relative_path(""));
+    }
+}
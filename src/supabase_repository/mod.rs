@@ -10,8 +10,14 @@ use std::time::Duration;
 use tracing::{instrument, warn};
 use uuid::Uuid;
 
+pub mod account;
 pub mod job_worker;
+pub mod notifications;
+pub mod secrets;
 pub mod task;
+pub mod uploads;
+pub mod webhook_rules;
+use self::secrets::{secret_keyring, SealedSecret, SECRETS_MASTER_KEY_ENV};
 use self::task::merge_settings;
 
 /// Row representing a repository in Supabase.
@@ -234,24 +240,18 @@ impl SupabaseRepositoryStore {
             let secret_plain = secret_value
                 .as_deref()
                 .context("webhook secret value missing during insertion")?;
-
-            sqlx::query(
-                r#"
-                INSERT INTO repository_secrets (repository_id, secret, secret_hash)
-                VALUES ($1, $2, $3)
-                ON CONFLICT (repository_id)
-                DO UPDATE
-                    SET secret = EXCLUDED.secret,
-                        secret_hash = EXCLUDED.secret_hash,
-                        updated_at = NOW()
-                "#,
-            )
-            .bind(repository.id)
-            .bind(secret_plain)
-            .bind(secret_hash_value)
-            .execute(&mut *tx)
-            .await
-            .context("failed to persist webhook secret")?;
+            let sealed = secret_keyring()?
+                .with_context(|| {
+                    format!(
+                        "{} must be set to store webhook secrets",
+                        SECRETS_MASTER_KEY_ENV
+                    )
+                })?
+                .seal(repository.id, secret_plain)?;
+
+            store_sealed_secret(&mut tx, repository.id, &sealed, secret_hash_value)
+                .await
+                .context("failed to persist webhook secret")?;
         }
 
         let job_id = sqlx::query_scalar::<_, Uuid>(
@@ -621,7 +621,7 @@ impl SupabaseRepositoryStore {
             r#"
             UPDATE repositories
             SET last_indexed_at = COALESCE($2, last_indexed_at),
-                metadata = metadata || $3,
+                metadata = (metadata || $3) #- '{webhook,secret}',
                 updated_at = NOW()
             WHERE id = $1
             "#,
@@ -636,6 +636,55 @@ impl SupabaseRepositoryStore {
         Ok(())
     }
 
+    /// Merge `patch` into the settings of a repository owned by `user_id`.
+    ///
+    /// Returns the merged settings, or `None` when the repository does not belong to the user.
+    #[instrument(skip(self, patch))]
+    pub async fn update_repository_settings(
+        &self,
+        repository_id: Uuid,
+        user_id: Uuid,
+        patch: &JsonValue,
+    ) -> Result<Option<JsonValue>> {
+        let mut tx = self.pool.begin().await?;
+
+        let current = sqlx::query_scalar::<_, JsonValue>(
+            r#"
+            SELECT settings
+            FROM repositories
+            WHERE id = $1 AND user_id = $2
+            FOR UPDATE
+            "#,
+        )
+        .bind(repository_id)
+        .bind(user_id)
+        .fetch_optional(&mut *tx)
+        .await
+        .context("failed to load repository settings")?;
+
+        let Some(current) = current else {
+            return Ok(None);
+        };
+
+        let merged = merge_settings(&current, Some(patch));
+        sqlx::query(
+            r#"
+            UPDATE repositories
+            SET settings = $2,
+                updated_at = NOW()
+            WHERE id = $1
+            "#,
+        )
+        .bind(repository_id)
+        .bind(Json(&merged))
+        .execute(&mut *tx)
+        .await
+        .context("failed to update repository settings")?;
+
+        tx.commit().await?;
+        Ok(Some(merged))
+    }
+
     #[instrument(skip(self, event_message, context))]
     pub async fn record_job_event(
         &self,
@@ -856,6 +905,46 @@ fn ensure_webhook_metadata(metadata: &mut JsonValue, provider: &str, secret_hash
     }
 }
 
+/// Upsert a sealed webhook secret, clearing any plaintext value of the row
+async fn store_sealed_secret(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    repository_id: Uuid,
+    sealed: &SealedSecret,
+    secret_hash: &str,
+) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO repository_secrets (
+            repository_id,
+            secret,
+            secret_hash,
+            secret_ciphertext,
+            secret_wrapped_key,
+            secret_key_id
+        )
+        VALUES ($1, NULL, $2, $3, $4, $5)
+        ON CONFLICT (repository_id)
+        DO UPDATE
+            SET secret = NULL,
+                secret_hash = EXCLUDED.secret_hash,
+                secret_ciphertext = EXCLUDED.secret_ciphertext,
+                secret_wrapped_key = EXCLUDED.secret_wrapped_key,
+                secret_key_id = EXCLUDED.secret_key_id,
+                updated_at = NOW()
+        "#,
+    )
+    .bind(repository_id)
+    .bind(secret_hash)
+    .bind(&sealed.ciphertext)
+    .bind(&sealed.wrapped_key)
+    .bind(&sealed.key_id)
+    .execute(&mut **tx)
+    .await
+    .context("failed to store sealed webhook secret")?;
+
+    Ok(())
+}
+
 fn generate_webhook_secret() -> String {
     let mut bytes = [0u8; 32];
     OsRng.fill_bytes(&mut bytes);
@@ -913,20 +1002,141 @@ impl SupabaseRepositoryStore {
         Ok(row)
     }
 
+    /// Repository metadata, only when the repository belongs to `user_id`
+    #[instrument(skip(self))]
+    pub async fn fetch_owned_repository(
+        &self,
+        repository_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<Option<RepositoryMetaRow>> {
+        let row = sqlx::query_as::<_, RepositoryMetaRow>(
+            r#"
+            SELECT
+                id,
+                git_url,
+                provider,
+                default_branch,
+                settings,
+                api_key_id,
+                metadata
+            FROM repositories
+            WHERE id = $1 AND user_id = $2
+            "#,
+        )
+        .bind(repository_id)
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await
+        .context("failed to fetch repository metadata")?;
+
+        Ok(row)
+    }
+
+    /// Webhook secret of a repository, decrypted
+    ///
+    /// Secrets still stored in plaintext, or sealed under a retired master key, are
+    /// re-sealed under the current key, and any copy left in the repository metadata is
+    /// removed. Without a configured master key plaintext secrets are returned as they are.
     #[instrument(skip(self))]
     pub async fn fetch_webhook_secret(&self, repository_id: Uuid) -> Result<Option<String>> {
-        let secret = sqlx::query_scalar::<_, String>(
+        #[derive(sqlx::FromRow)]
+        struct SecretRow {
+            secret: Option<String>,
+            secret_ciphertext: Option<String>,
+            secret_wrapped_key: Option<String>,
+            secret_key_id: Option<String>,
+            metadata_secret: Option<String>,
+        }
+
+        let Some(row) = sqlx::query_as::<_, SecretRow>(
             r#"
-            SELECT secret
-            FROM repository_secrets
-            WHERE repository_id = $1
+            SELECT
+                s.secret,
+                s.secret_ciphertext,
+                s.secret_wrapped_key,
+                s.secret_key_id,
+                r.metadata #>> '{webhook,secret}' AS metadata_secret
+            FROM repositories r
+            LEFT JOIN repository_secrets s ON s.repository_id = r.id
+            WHERE r.id = $1
             "#,
         )
         .bind(repository_id)
         .fetch_optional(&self.pool)
         .await
-        .context("failed to load webhook secret")?;
+        .context("failed to load webhook secret")?
+        else {
+            return Ok(None);
+        };
+
+        let keyring = secret_keyring()?;
+        let (secret, current) = match (
+            row.secret_ciphertext,
+            row.secret_wrapped_key,
+            row.secret_key_id,
+        ) {
+            (Some(ciphertext), Some(wrapped_key), Some(key_id)) => {
+                let keyring = keyring.with_context(|| {
+                    format!(
+                        "{} must be set to read webhook secrets",
+                        SECRETS_MASTER_KEY_ENV
+                    )
+                })?;
+                let sealed = SealedSecret {
+                    key_id,
+                    wrapped_key,
+                    ciphertext,
+                };
+                let secret = keyring.open(repository_id, &sealed).with_context(|| {
+                    format!("failed to open webhook secret of {}", repository_id)
+                })?;
+                let current = sealed.key_id == keyring.current_key_id()
+                    && row.secret.is_none()
+                    && row.metadata_secret.is_none();
+                (secret, current)
+            }
+            _ => match row.secret.or(row.metadata_secret) {
+                Some(secret) => (secret, false),
+                None => return Ok(None),
+            },
+        };
+
+        if !current {
+            if keyring.is_none() {
+                warn!(
+                    "Webhook secret of {} is stored in plaintext; set {} to encrypt it",
+                    repository_id, SECRETS_MASTER_KEY_ENV
+                );
+            } else if let Err(err) = self.reseal_webhook_secret(repository_id, &secret).await {
+                warn!(
+                    "Failed to re-encrypt webhook secret of {}: {}",
+                    repository_id, err
+                );
+            }
+        }
 
-        Ok(secret)
+        Ok(Some(secret))
+    }
+
+    /// Store `secret` sealed under the current master key and drop plaintext copies
+    async fn reseal_webhook_secret(&self, repository_id: Uuid, secret: &str) -> Result<()> {
+        let sealed = secret_keyring()?
+            .context("no master key configured")?
+            .seal(repository_id, secret)?;
+        let mut tx = self.pool.begin().await?;
+        store_sealed_secret(&mut tx, repository_id, &sealed, &hash_secret(secret)).await?;
+        sqlx::query(
+            r#"
+            UPDATE repositories
+            SET metadata = metadata #- '{webhook,secret}'
+            WHERE id = $1 AND metadata #> '{webhook,secret}' IS NOT NULL
+            "#,
+        )
+        .bind(repository_id)
+        .execute(&mut *tx)
+        .await
+        .context("failed to remove webhook secret from metadata")?;
+        tx.commit().await?;
+        Ok(())
     }
 }
@@ -0,0 +1,509 @@
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use serde_json::{json, Value as JsonValue};
+use sha2::Sha256;
+use tracing::{instrument, warn};
+use url::{Host, Url};
+use uuid::Uuid;
+
+use super::{generate_webhook_secret, SupabaseRepositoryStore};
+
+/// Event sent when an indexing job finishes successfully.
+pub const EVENT_INDEX_COMPLETED: &str = "index.completed";
+/// Event sent when an indexing job fails.
+pub const EVENT_INDEX_FAILED: &str = "index.failed";
+
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+const DELIVERY_ATTEMPTS: u32 = 3;
+const USER_AGENT: &str = "kotadb-notifications/1.0";
+
+/// Tenant callback registered to receive job notifications.
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct NotificationHookRow {
+    pub id: Uuid,
+    pub url: String,
+    pub active: bool,
+    pub last_delivery_at: Option<DateTime<Utc>>,
+    pub last_status_code: Option<i32>,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Delivery target resolved for a repository's owner.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct NotificationTarget {
+    pub id: Uuid,
+    pub url: String,
+    pub secret: String,
+}
+
+impl SupabaseRepositoryStore {
+    /// Register a callback URL for `user_id`, returning the hook and its signing secret.
+    ///
+    /// Re-registering an existing URL reactivates it and rotates the secret.
+    #[instrument(skip(self))]
+    pub async fn create_notification_hook(
+        &self,
+        user_id: Uuid,
+        url: &str,
+    ) -> Result<(NotificationHookRow, String)> {
+        let secret = generate_webhook_secret();
+        let mut tx = self.pool.begin().await?;
+
+        let row = sqlx::query_as::<_, NotificationHookRow>(
+            r#"
+            INSERT INTO notification_hooks (user_id, url)
+            VALUES ($1, $2)
+            ON CONFLICT (user_id, url) DO UPDATE
+            SET active = TRUE,
+                updated_at = NOW()
+            RETURNING id, url, active, last_delivery_at, last_status_code, last_error, created_at
+            "#,
+        )
+        .bind(user_id)
+        .bind(url)
+        .fetch_one(&mut *tx)
+        .await
+        .context("failed to register notification hook")?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO notification_hook_secrets (hook_id, secret)
+            VALUES ($1, $2)
+            ON CONFLICT (hook_id)
+            DO UPDATE
+                SET secret = EXCLUDED.secret,
+                    updated_at = NOW()
+            "#,
+        )
+        .bind(row.id)
+        .bind(&secret)
+        .execute(&mut *tx)
+        .await
+        .context("failed to persist notification hook secret")?;
+
+        tx.commit().await?;
+        Ok((row, secret))
+    }
+
+    #[instrument(skip(self))]
+    pub async fn list_notification_hooks(&self, user_id: Uuid) -> Result<Vec<NotificationHookRow>> {
+        let rows = sqlx::query_as::<_, NotificationHookRow>(
+            r#"
+            SELECT id, url, active, last_delivery_at, last_status_code, last_error, created_at
+            FROM notification_hooks
+            WHERE user_id = $1
+            ORDER BY created_at DESC
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await
+        .context("failed to list notification hooks")?;
+
+        Ok(rows)
+    }
+
+    /// Delete a hook owned by `user_id`; returns whether a hook was removed
+    #[instrument(skip(self))]
+    pub async fn delete_notification_hook(&self, hook_id: Uuid, user_id: Uuid) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM notification_hooks WHERE id = $1 AND user_id = $2")
+            .bind(hook_id)
+            .bind(user_id)
+            .execute(&self.pool)
+            .await
+            .context("failed to delete notification hook")?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Active hooks belonging to the owner of `repository_id`
+    #[instrument(skip(self))]
+    pub async fn notification_targets(
+        &self,
+        repository_id: Uuid,
+    ) -> Result<Vec<NotificationTarget>> {
+        let rows = sqlx::query_as::<_, NotificationTarget>(
+            r#"
+            SELECT h.id, h.url, s.secret
+            FROM notification_hooks h
+            JOIN notification_hook_secrets s ON s.hook_id = h.id
+            JOIN repositories r ON r.user_id = h.user_id
+            WHERE r.id = $1 AND h.active
+            "#,
+        )
+        .bind(repository_id)
+        .fetch_all(&self.pool)
+        .await
+        .context("failed to load notification hooks")?;
+
+        Ok(rows)
+    }
+
+    #[instrument(skip(self))]
+    pub async fn record_notification_delivery(
+        &self,
+        hook_id: Uuid,
+        status_code: Option<i32>,
+        error_message: Option<&str>,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE notification_hooks
+            SET last_delivery_at = NOW(),
+                last_status_code = $2,
+                last_error = $3
+            WHERE id = $1
+            "#,
+        )
+        .bind(hook_id)
+        .bind(status_code)
+        .bind(error_message)
+        .execute(&self.pool)
+        .await
+        .context("failed to record notification delivery")?;
+
+        Ok(())
+    }
+
+    /// Increment and return the repository's index generation
+    #[instrument(skip(self))]
+    pub async fn bump_index_generation(&self, repository_id: Uuid) -> Result<i64> {
+        let generation = sqlx::query_scalar::<_, i64>(
+            r#"
+            UPDATE repositories
+            SET metadata = jsonb_set(
+                    metadata,
+                    '{index_generation}',
+                    to_jsonb(COALESCE((metadata->>'index_generation')::BIGINT, 0) + 1)
+                ),
+                updated_at = NOW()
+            WHERE id = $1
+            RETURNING (metadata->>'index_generation')::BIGINT
+            "#,
+        )
+        .bind(repository_id)
+        .fetch_one(&self.pool)
+        .await
+        .context("failed to bump index generation")?;
+
+        Ok(generation)
+    }
+}
+
+/// Summary posted to notification hooks when a job finishes
+pub fn build_notification_payload(
+    event: &str,
+    job_id: Uuid,
+    repository_id: Uuid,
+    job_type: &str,
+    result: Option<&JsonValue>,
+    error: Option<&str>,
+    index_generation: Option<i64>,
+) -> JsonValue {
+    json!({
+        "event": event,
+        "delivery_id": Uuid::new_v4(),
+        "job_id": job_id,
+        "repository_id": repository_id,
+        "job_type": job_type,
+        "status": if event == EVENT_INDEX_COMPLETED { "completed" } else { "failed" },
+        "result": result,
+        "error": error,
+        "index_generation": index_generation,
+        "occurred_at": Utc::now().to_rfc3339(),
+    })
+}
+
+/// `sha256=<hex>` HMAC of `body`, sent as `X-KotaDB-Signature-256`
+pub fn sign_notification(secret: &str, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// Resolve a hook URL, refusing destinations that are not publicly routable.
+///
+/// Every address the host resolves to must be public; delivery connects only to the
+/// returned addresses so a later DNS answer cannot redirect it to an internal service.
+pub async fn resolve_notification_url(url: &Url) -> Result<Vec<SocketAddr>> {
+    if url.scheme() != "https" {
+        bail!("notification hooks must use https");
+    }
+    let port = url.port_or_known_default().unwrap_or(443);
+    let addrs: Vec<SocketAddr> = match url.host() {
+        Some(Host::Ipv4(ip)) => vec![SocketAddr::new(ip.into(), port)],
+        Some(Host::Ipv6(ip)) => vec![SocketAddr::new(ip.into(), port)],
+        Some(Host::Domain(domain)) => tokio::net::lookup_host((domain, port))
+            .await
+            .with_context(|| format!("failed to resolve {}", domain))?
+            .collect(),
+        None => bail!("notification hook URL has no host"),
+    };
+    if addrs.is_empty() {
+        bail!("notification hook host did not resolve");
+    }
+    if let Some(addr) = addrs.iter().find(|addr| !is_public_ip(addr.ip())) {
+        bail!(
+            "notification hook resolves to non-public address {}",
+            addr.ip()
+        );
+    }
+    Ok(addrs)
+}
+
+/// Whether `ip` is routable on the public internet (not loopback, private, link-local,
+/// unspecified, multicast or otherwise reserved)
+fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => {
+            let [first, second, ..] = v4.octets();
+            !(v4.is_loopback()
+                || v4.is_private()
+                || v4.is_link_local()
+                || v4.is_unspecified()
+                || v4.is_broadcast()
+                || v4.is_multicast()
+                || v4.is_documentation()
+                || first == 0
+                // 100.64.0.0/10 carrier-grade NAT
+                || (first == 100 && (second & 0xc0) == 64))
+        }
+        IpAddr::V6(v6) => {
+            if let Some(v4) = v6.to_ipv4_mapped() {
+                return is_public_ip(IpAddr::V4(v4));
+            }
+            let first = v6.segments()[0];
+            !(v6.is_loopback()
+                || v6.is_unspecified()
+                || v6.is_multicast()
+                // fc00::/7 unique local, fe80::/10 link-local
+                || (first & 0xfe00) == 0xfc00
+                || (first & 0xffc0) == 0xfe80)
+        }
+    }
+}
+
+/// Client for one hook: no redirects, and the host pinned to its checked addresses
+fn delivery_client(url: &Url, addrs: &[SocketAddr]) -> reqwest::Result<reqwest::Client> {
+    let mut builder = reqwest::Client::builder()
+        .timeout(DELIVERY_TIMEOUT)
+        .user_agent(USER_AGENT)
+        .redirect(reqwest::redirect::Policy::none());
+    if let Some(Host::Domain(domain)) = url.host() {
+        builder = builder.resolve_to_addrs(domain, addrs);
+    }
+    builder.build()
+}
+
+/// Short failure description stored on the hook row; transport details stay in the logs
+fn delivery_error(err: &reqwest::Error) -> &'static str {
+    if err.is_timeout() {
+        "request timed out"
+    } else if err.is_connect() {
+        "connection failed"
+    } else {
+        "request failed"
+    }
+}
+
+/// Post `payload` to every active hook of the repository owner.
+///
+/// Each hook gets a few attempts with backoff; the outcome is recorded on the hook row.
+/// Hooks whose host no longer resolves to public addresses are not contacted. Failures
+/// are logged and never propagate to the job that triggered them.
+pub async fn dispatch_notifications(
+    store: &SupabaseRepositoryStore,
+    repository_id: Uuid,
+    payload: &JsonValue,
+) {
+    let targets = match store.notification_targets(repository_id).await {
+        Ok(targets) => targets,
+        Err(err) => {
+            warn!(
+                "Failed to load notification hooks for repository {}: {}",
+                repository_id, err
+            );
+            return;
+        }
+    };
+    if targets.is_empty() {
+        return;
+    }
+
+    let body = payload.to_string();
+    let event = payload
+        .get("event")
+        .and_then(|v| v.as_str())
+        .unwrap_or(EVENT_INDEX_COMPLETED);
+    let delivery_id = payload
+        .get("delivery_id")
+        .and_then(|v| v.as_str())
+        .unwrap_or_default();
+
+    for target in targets {
+        let signature = sign_notification(&target.secret, body.as_bytes());
+        let headers = [
+            ("Content-Type", "application/json"),
+            ("X-KotaDB-Event", event),
+            ("X-KotaDB-Delivery", delivery_id),
+            ("X-KotaDB-Signature-256", signature.as_str()),
+        ];
+        let outcome = deliver(&target, &headers, &body).await;
+
+        if let Some(error) = &outcome.1 {
+            warn!(
+                hook_id = %target.id,
+                repository_id = %repository_id,
+                "Notification delivery failed: {}", error
+            );
+        }
+        if let Err(err) = store
+            .record_notification_delivery(target.id, outcome.0, outcome.1.as_deref())
+            .await
+        {
+            warn!(
+                "Failed to record notification delivery for hook {}: {}",
+                target.id, err
+            );
+        }
+    }
+}
+
+/// Attempt delivery to one hook, returning the last status code and stored error
+async fn deliver(
+    target: &NotificationTarget,
+    headers: &[(&str, &str)],
+    body: &str,
+) -> (Option<i32>, Option<String>) {
+    let Ok(url) = Url::parse(&target.url) else {
+        return (None, Some("destination is not allowed".into()));
+    };
+    let addrs = match resolve_notification_url(&url).await {
+        Ok(addrs) => addrs,
+        Err(err) => {
+            warn!(hook_id = %target.id, "Refusing notification delivery: {}", err);
+            return (None, Some("destination is not allowed".into()));
+        }
+    };
+    let client = match delivery_client(&url, &addrs) {
+        Ok(client) => client,
+        Err(err) => {
+            warn!("Failed to build notification client: {}", err);
+            return (None, Some("request failed".into()));
+        }
+    };
+
+    let mut outcome = (None, Some("not attempted".to_string()));
+    for attempt in 0..DELIVERY_ATTEMPTS {
+        if attempt > 0 {
+            tokio::time::sleep(Duration::from_secs(1 << attempt)).await;
+        }
+        let request = headers
+            .iter()
+            .fold(client.post(url.clone()), |request, (name, value)| {
+                request.header(*name, *value)
+            });
+        outcome = match request.body(body.to_string()).send().await {
+            Ok(response) => {
+                let status = response.status();
+                let error = (!status.is_success()).then(|| format!("HTTP {}", status));
+                (Some(i32::from(status.as_u16())), error)
+            }
+            Err(err) => {
+                warn!(hook_id = %target.id, "Notification request failed: {}", err);
+                (None, Some(delivery_error(&err).to_string()))
+            }
+        };
+        // Client errors will not succeed on retry
+        let retryable = match outcome.0 {
+            Some(code) => code >= 500 || code == 429,
+            None => true,
+        };
+        if outcome.1.is_none() || !retryable {
+            break;
+        }
+    }
+    outcome
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signature_matches_reference_hmac() {
+        // HMAC-SHA256("key", "The quick brown fox jumps over the lazy dog")
+        assert_eq!(
+            sign_notification("key", b"The quick brown fox jumps over the lazy dog"),
+            "sha256=f7bc83f430538424b13298e6aa6fb143ef4d59a14946175997479dbc2d1a3cd8"
+        );
+    }
+
+    #[test]
+    fn internal_addresses_are_not_public() {
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "0.0.0.0",
+            "100.64.0.1",
+            "::1",
+            "::",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+        ] {
+            assert!(!is_public_ip(ip.parse().unwrap()), "{ip} must be rejected");
+        }
+        for ip in ["93.184.216.34", "2606:2800:220:1:248:1893:25c8:1946"] {
+            assert!(is_public_ip(ip.parse().unwrap()), "{ip} is public");
+        }
+    }
+
+    #[tokio::test]
+    async fn hook_urls_must_resolve_to_public_addresses() {
+        for url in [
+            "https://127.0.0.1/hook",
+            "https://[::1]/hook",
+            "https://169.254.169.254/latest/meta-data",
+            "https://localhost/hook",
+            "http://93.184.216.34/hook",
+        ] {
+            let url = Url::parse(url).unwrap();
+            assert!(resolve_notification_url(&url).await.is_err(), "{url}");
+        }
+
+        let url = Url::parse("https://93.184.216.34:8443/hook").unwrap();
+        let addrs = resolve_notification_url(&url).await.unwrap();
+        assert_eq!(addrs, vec!["93.184.216.34:8443".parse().unwrap()]);
+    }
+
+    #[test]
+    fn payload_carries_summary_and_generation() {
+        let job_id = Uuid::new_v4();
+        let repository_id = Uuid::new_v4();
+        let result = json!({ "files_processed": 3 });
+        let payload = build_notification_payload(
+            EVENT_INDEX_COMPLETED,
+            job_id,
+            repository_id,
+            "delta_index",
+            Some(&result),
+            None,
+            Some(7),
+        );
+        assert_eq!(payload["status"], "completed");
+        assert_eq!(payload["job_id"], json!(job_id));
+        assert_eq!(payload["result"]["files_processed"], 3);
+        assert_eq!(payload["index_generation"], 7);
+    }
+}
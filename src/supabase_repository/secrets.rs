@@ -0,0 +1,248 @@
+//! Envelope encryption of repository webhook secrets
+//!
+//! Each secret is sealed with its own random data key using AES-256-GCM, and the data
+//! key is wrapped with a master key taken from the environment. Only ciphertexts reach
+//! Postgres; the master key never does. Ciphertexts are bound to their repository id,
+//! so a sealed secret copied onto another repository fails to open.
+//!
+//! Rows written before encryption keep their secret in plaintext (in `repository_secrets`
+//! or, for the oldest repositories, in `repositories.metadata`) and are re-sealed the next
+//! time they are read. Rows sealed under a retired master key are re-wrapped the same way.
+
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use anyhow::{anyhow, bail, Context, Result};
+use base64::{
+    engine::general_purpose::{STANDARD, STANDARD_NO_PAD},
+    Engine as _,
+};
+use openssl::symm::{decrypt_aead, encrypt_aead, Cipher};
+use rand::{rngs::OsRng, RngCore};
+use uuid::Uuid;
+
+/// Base64-encoded 32-byte master key wrapping webhook secret data keys
+pub const SECRETS_MASTER_KEY_ENV: &str = "KOTADB_SECRETS_MASTER_KEY";
+/// Identifier recorded next to secrets sealed under the master key (defaults to `default`)
+pub const SECRETS_MASTER_KEY_ID_ENV: &str = "KOTADB_SECRETS_MASTER_KEY_ID";
+/// Comma-separated `id=base64key` pairs of earlier master keys, still accepted for reading
+pub const SECRETS_RETIRED_KEYS_ENV: &str = "KOTADB_SECRETS_RETIRED_KEYS";
+
+const DEFAULT_KEY_ID: &str = "default";
+const KEY_LEN: usize = 32;
+const NONCE_LEN: usize = 12;
+const TAG_LEN: usize = 16;
+
+/// A secret sealed under a data key, with the data key wrapped by master key `key_id`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SealedSecret {
+    pub key_id: String,
+    pub wrapped_key: String,
+    pub ciphertext: String,
+}
+
+/// Master keys able to seal and open webhook secrets
+pub struct SecretKeyring {
+    current_id: String,
+    keys: HashMap<String, [u8; KEY_LEN]>,
+}
+
+impl std::fmt::Debug for SecretKeyring {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut key_ids: Vec<_> = self.keys.keys().collect();
+        key_ids.sort();
+        f.debug_struct("SecretKeyring")
+            .field("current_id", &self.current_id)
+            .field("key_ids", &key_ids)
+            .finish()
+    }
+}
+
+impl SecretKeyring {
+    pub fn new(key_id: impl Into<String>, key: [u8; KEY_LEN]) -> Self {
+        let current_id = key_id.into();
+        let keys = HashMap::from([(current_id.clone(), key)]);
+        Self { current_id, keys }
+    }
+
+    /// Also accept secrets sealed under an earlier master key
+    pub fn with_retired_key(mut self, key_id: impl Into<String>, key: [u8; KEY_LEN]) -> Self {
+        self.keys.entry(key_id.into()).or_insert(key);
+        self
+    }
+
+    /// Keyring described by the environment, or `None` when no master key is configured
+    pub fn from_env() -> Result<Option<Self>> {
+        let Some(master) = non_empty_env(SECRETS_MASTER_KEY_ENV) else {
+            return Ok(None);
+        };
+        let key_id =
+            non_empty_env(SECRETS_MASTER_KEY_ID_ENV).unwrap_or_else(|| DEFAULT_KEY_ID.into());
+        let mut keyring = Self::new(
+            key_id,
+            decode_key(&master).with_context(|| format!("invalid {}", SECRETS_MASTER_KEY_ENV))?,
+        );
+        if let Some(retired) = non_empty_env(SECRETS_RETIRED_KEYS_ENV) {
+            for entry in retired.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+                let (id, key) = entry.split_once('=').with_context(|| {
+                    format!(
+                        "{} entries must look like id=base64key",
+                        SECRETS_RETIRED_KEYS_ENV
+                    )
+                })?;
+                let key = decode_key(key)
+                    .with_context(|| format!("invalid retired master key {}", id.trim()))?;
+                keyring = keyring.with_retired_key(id.trim(), key);
+            }
+        }
+        Ok(Some(keyring))
+    }
+
+    pub fn current_key_id(&self) -> &str {
+        &self.current_id
+    }
+
+    /// Seal `secret` for `repository_id` under a fresh data key
+    pub fn seal(&self, repository_id: Uuid, secret: &str) -> Result<SealedSecret> {
+        let mut data_key = [0u8; KEY_LEN];
+        OsRng.fill_bytes(&mut data_key);
+        let ciphertext = aead_seal(&data_key, repository_id.as_bytes(), secret.as_bytes())?;
+        let wrapped_key = aead_seal(
+            &self.keys[&self.current_id],
+            self.current_id.as_bytes(),
+            &data_key,
+        )?;
+        Ok(SealedSecret {
+            key_id: self.current_id.clone(),
+            wrapped_key,
+            ciphertext,
+        })
+    }
+
+    /// Open a secret sealed for `repository_id`
+    pub fn open(&self, repository_id: Uuid, sealed: &SealedSecret) -> Result<String> {
+        let master = self
+            .keys
+            .get(&sealed.key_id)
+            .ok_or_else(|| anyhow!("master key {} is not configured", sealed.key_id))?;
+        let data_key: [u8; KEY_LEN] =
+            aead_open(master, sealed.key_id.as_bytes(), &sealed.wrapped_key)
+                .context("failed to unwrap data key")?
+                .try_into()
+                .map_err(|_| anyhow!("unwrapped data key has the wrong length"))?;
+        let secret = aead_open(&data_key, repository_id.as_bytes(), &sealed.ciphertext)
+            .context("failed to decrypt secret")?;
+        String::from_utf8(secret).context("decrypted secret is not UTF-8")
+    }
+}
+
+/// Keyring of this process, read from the environment once
+pub fn secret_keyring() -> Result<Option<&'static SecretKeyring>> {
+    static KEYRING: OnceLock<Result<Option<SecretKeyring>, String>> = OnceLock::new();
+    match KEYRING.get_or_init(|| SecretKeyring::from_env().map_err(|e| format!("{:#}", e))) {
+        Ok(keyring) => Ok(keyring.as_ref()),
+        Err(e) => bail!("{}", e),
+    }
+}
+
+fn non_empty_env(key: &str) -> Option<String> {
+    std::env::var(key)
+        .ok()
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+}
+
+fn decode_key(encoded: &str) -> Result<[u8; KEY_LEN]> {
+    let encoded = encoded.trim();
+    let bytes = STANDARD
+        .decode(encoded)
+        .or_else(|_| STANDARD_NO_PAD.decode(encoded))
+        .context("master key is not base64")?;
+    bytes.try_into().map_err(|bytes: Vec<u8>| {
+        anyhow!("master key is {} bytes, expected {}", bytes.len(), KEY_LEN)
+    })
+}
+
+/// AES-256-GCM, encoded as base64 of nonce || ciphertext || tag
+fn aead_seal(key: &[u8; KEY_LEN], aad: &[u8], plaintext: &[u8]) -> Result<String> {
+    let mut nonce = [0u8; NONCE_LEN];
+    OsRng.fill_bytes(&mut nonce);
+    let mut tag = [0u8; TAG_LEN];
+    let ciphertext = encrypt_aead(
+        Cipher::aes_256_gcm(),
+        key,
+        Some(&nonce),
+        aad,
+        plaintext,
+        &mut tag,
+    )?;
+    let mut sealed = Vec::with_capacity(NONCE_LEN + ciphertext.len() + TAG_LEN);
+    sealed.extend_from_slice(&nonce);
+    sealed.extend_from_slice(&ciphertext);
+    sealed.extend_from_slice(&tag);
+    Ok(STANDARD_NO_PAD.encode(sealed))
+}
+
+fn aead_open(key: &[u8; KEY_LEN], aad: &[u8], sealed: &str) -> Result<Vec<u8>> {
+    let sealed = STANDARD_NO_PAD
+        .decode(sealed)
+        .context("sealed value is not base64")?;
+    if sealed.len() < NONCE_LEN + TAG_LEN {
+        bail!("sealed value is truncated");
+    }
+    let (nonce, rest) = sealed.split_at(NONCE_LEN);
+    let (ciphertext, tag) = rest.split_at(rest.len() - TAG_LEN);
+    decrypt_aead(
+        Cipher::aes_256_gcm(),
+        key,
+        Some(nonce),
+        aad,
+        ciphertext,
+        tag,
+    )
+    .map_err(|_| anyhow!("authentication failed"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sealed_secrets_open_only_for_their_repository() -> Result<()> {
+        let keyring = SecretKeyring::new("k1", [7u8; KEY_LEN]);
+        let repository_id = Uuid::new_v4();
+        let sealed = keyring.seal(repository_id, "s3cret")?;
+
+        assert_eq!(sealed.key_id, "k1");
+        assert!(!sealed.ciphertext.contains("s3cret"));
+        assert_eq!(keyring.open(repository_id, &sealed)?, "s3cret");
+        assert!(keyring.open(Uuid::new_v4(), &sealed).is_err());
+        assert!(SecretKeyring::new("k1", [8u8; KEY_LEN])
+            .open(repository_id, &sealed)
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn retired_keys_open_but_do_not_seal() -> Result<()> {
+        let repository_id = Uuid::new_v4();
+        let sealed = SecretKeyring::new("old", [1u8; KEY_LEN]).seal(repository_id, "s3cret")?;
+
+        let rotated =
+            SecretKeyring::new("new", [2u8; KEY_LEN]).with_retired_key("old", [1u8; KEY_LEN]);
+        assert_eq!(rotated.open(repository_id, &sealed)?, "s3cret");
+        assert_eq!(rotated.seal(repository_id, "s3cret")?.key_id, "new");
+        assert!(SecretKeyring::new("new", [2u8; KEY_LEN])
+            .open(repository_id, &sealed)
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn master_keys_must_be_32_bytes_of_base64() {
+        assert!(decode_key(&STANDARD.encode([3u8; KEY_LEN])).is_ok());
+        assert!(decode_key(&STANDARD_NO_PAD.encode([3u8; KEY_LEN])).is_ok());
+        assert!(decode_key(&STANDARD.encode([3u8; 16])).is_err());
+        assert!(decode_key("not base64!").is_err());
+    }
+}
@@ -1,6 +1,8 @@
 use chrono::{DateTime, Utc};
 use serde::Deserialize;
 use serde_json::{Map as JsonMap, Value as JsonValue};
+use url::Url;
+use uuid::Uuid;
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct SupabaseJobPayload {
@@ -13,9 +15,14 @@ pub struct SupabaseJobPayload {
     pub delivery_id: Option<String>,
     #[serde(rename = "ref")]
     pub git_ref: Option<String>,
+    /// Commit a push moved the branch to
+    #[serde(rename = "after")]
+    pub head_sha: Option<String>,
     pub commits: Option<JsonValue>,
     pub webhook_delivery_id: Option<i64>,
     pub changes: Option<JsonValue>,
+    /// Upload whose archive an `archive_index` job indexes
+    pub upload_id: Option<Uuid>,
 }
 
 impl SupabaseJobPayload {
@@ -29,9 +36,11 @@ impl SupabaseJobPayload {
             event_type: None,
             delivery_id: None,
             git_ref: None,
+            head_sha: None,
             commits: None,
             webhook_delivery_id: None,
             changes: None,
+            upload_id: None,
         })
     }
 }
@@ -87,3 +96,36 @@ pub fn option_usize(settings: &JsonValue, key: &str) -> Option<usize> {
             .and_then(|v| usize::try_from(v).ok())
     })
 }
+
+/// Split a GitHub HTTPS or SSH URL into `(owner, repo)`
+pub fn parse_github_owner_repo(git_url: &str) -> Option<(String, String)> {
+    if let Ok(url) = Url::parse(git_url) {
+        let host = url.host_str()?;
+        if !host.contains("github.com") {
+            return None;
+        }
+        let mut segments = url.path().trim_matches('/').split('/');
+        let owner = segments.next()?.to_string();
+        let repo = segments
+            .next()
+            .map(|s| s.trim_end_matches(".git").to_string())?;
+        if owner.is_empty() || repo.is_empty() {
+            return None;
+        }
+        return Some((owner, repo));
+    }
+
+    if let Some(stripped) = git_url.strip_prefix("git@github.com:") {
+        let mut segments = stripped.split('/');
+        let owner = segments.next()?.to_string();
+        let repo = segments
+            .next()
+            .map(|s| s.trim_end_matches(".git").to_string())?;
+        if owner.is_empty() || repo.is_empty() {
+            return None;
+        }
+        return Some((owner, repo));
+    }
+
+    None
+}
@@ -0,0 +1,497 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use flate2::read::GzDecoder;
+use serde::Serialize;
+use serde_json::{json, Value as JsonValue};
+use sqlx::types::Json;
+use tracing::instrument;
+use uuid::Uuid;
+
+use super::{task::merge_settings, SupabaseRepositoryStore};
+
+/// Job type queued when an upload completes.
+pub const ARCHIVE_INDEX_JOB: &str = "archive_index";
+/// Provider recorded for repositories fed by uploads instead of a VCS.
+pub const UPLOAD_PROVIDER: &str = "upload";
+/// Largest accepted part.
+pub const MAX_UPLOAD_PART_BYTES: u64 = 64 * 1024 * 1024;
+/// Highest part number accepted for an upload.
+pub const MAX_UPLOAD_PARTS: u32 = 10_000;
+/// Largest accepted archive, summed over all parts.
+pub const MAX_UPLOAD_BYTES: u64 = 4 * 1024 * 1024 * 1024;
+/// Ceiling on the unpacked size of an archive, guarding against decompression bombs.
+pub const MAX_EXTRACTED_BYTES: u64 = 16 * 1024 * 1024 * 1024;
+
+const MAX_UPLOAD_NAME_LEN: usize = 100;
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// Upload session owned by a tenant.
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct SourceUploadRow {
+    pub id: Uuid,
+    pub repository_id: Uuid,
+    pub status: String,
+    pub expected_size: Option<i64>,
+    pub job_id: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Part stored on disk for an upload.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct UploadPart {
+    pub part_number: u32,
+    pub size: u64,
+}
+
+/// Outcome of unpacking an uploaded archive.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExtractedArchive {
+    pub files: usize,
+    pub bytes: u64,
+    /// Links, devices and `.git` entries that were not written
+    pub skipped: usize,
+}
+
+impl SupabaseRepositoryStore {
+    /// Open an upload for repository `name`, registering the repository on first use.
+    ///
+    /// `settings` are merged into the repository settings like a registration would.
+    #[instrument(skip(self, settings))]
+    pub async fn create_source_upload(
+        &self,
+        user_id: Uuid,
+        api_key_id: Option<Uuid>,
+        name: &str,
+        settings: &JsonValue,
+        expected_size: Option<i64>,
+    ) -> Result<SourceUploadRow> {
+        let git_url = upload_git_url(name);
+        let mut tx = self.pool.begin().await?;
+
+        let existing = sqlx::query_scalar::<_, JsonValue>(
+            r#"
+            SELECT settings
+            FROM repositories
+            WHERE user_id = $1 AND git_url = $2
+            FOR UPDATE
+            "#,
+        )
+        .bind(user_id)
+        .bind(&git_url)
+        .fetch_optional(&mut *tx)
+        .await
+        .context("failed to fetch existing repository settings")?;
+        let merged_settings =
+            merge_settings(&existing.unwrap_or_else(|| json!({})), Some(settings));
+
+        let repository_id = sqlx::query_scalar::<_, Uuid>(
+            r#"
+            INSERT INTO repositories (user_id, api_key_id, name, git_url, provider, settings)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (user_id, git_url)
+            DO UPDATE
+                SET updated_at = NOW(),
+                    api_key_id = COALESCE(EXCLUDED.api_key_id, repositories.api_key_id),
+                    settings = EXCLUDED.settings
+            RETURNING id
+            "#,
+        )
+        .bind(user_id)
+        .bind(api_key_id)
+        .bind(name)
+        .bind(&git_url)
+        .bind(UPLOAD_PROVIDER)
+        .bind(Json(merged_settings))
+        .fetch_one(&mut *tx)
+        .await
+        .context("failed to upsert upload repository")?;
+
+        let upload = sqlx::query_as::<_, SourceUploadRow>(
+            r#"
+            INSERT INTO source_uploads (user_id, repository_id, expected_size)
+            VALUES ($1, $2, $3)
+            RETURNING id, repository_id, status, expected_size, job_id, created_at, updated_at
+            "#,
+        )
+        .bind(user_id)
+        .bind(repository_id)
+        .bind(expected_size)
+        .fetch_one(&mut *tx)
+        .await
+        .context("failed to create source upload")?;
+
+        tx.commit().await?;
+        Ok(upload)
+    }
+
+    /// Upload `upload_id` when it belongs to `user_id`
+    #[instrument(skip(self))]
+    pub async fn fetch_source_upload(
+        &self,
+        upload_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<Option<SourceUploadRow>> {
+        let row = sqlx::query_as::<_, SourceUploadRow>(
+            r#"
+            SELECT id, repository_id, status, expected_size, job_id, created_at, updated_at
+            FROM source_uploads
+            WHERE id = $1 AND user_id = $2
+            "#,
+        )
+        .bind(upload_id)
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await
+        .context("failed to fetch source upload")?;
+
+        Ok(row)
+    }
+
+    /// Close a pending upload and queue the job indexing it.
+    ///
+    /// Returns the job id, or `None` when the upload is not pending or not owned by `user_id`.
+    #[instrument(skip(self, payload))]
+    pub async fn complete_source_upload(
+        &self,
+        upload_id: Uuid,
+        user_id: Uuid,
+        requested_by: Option<Uuid>,
+        payload: &JsonValue,
+    ) -> Result<Option<Uuid>> {
+        let mut tx = self.pool.begin().await?;
+
+        let repository_id = sqlx::query_scalar::<_, Uuid>(
+            r#"
+            UPDATE source_uploads
+            SET status = 'completed'
+            WHERE id = $1 AND user_id = $2 AND status = 'pending'
+            RETURNING repository_id
+            "#,
+        )
+        .bind(upload_id)
+        .bind(user_id)
+        .fetch_optional(&mut *tx)
+        .await
+        .context("failed to complete source upload")?;
+
+        let Some(repository_id) = repository_id else {
+            return Ok(None);
+        };
+
+        let job_id = sqlx::query_scalar::<_, Uuid>(
+            r#"
+            INSERT INTO indexing_jobs (repository_id, requested_by, job_type, payload, priority, status)
+            VALUES ($1, $2, $3, $4, 0, 'queued')
+            RETURNING id
+            "#,
+        )
+        .bind(repository_id)
+        .bind(requested_by)
+        .bind(ARCHIVE_INDEX_JOB)
+        .bind(Json(payload.clone()))
+        .fetch_one(&mut *tx)
+        .await
+        .context("failed to enqueue archive job")?;
+
+        sqlx::query("UPDATE source_uploads SET job_id = $2 WHERE id = $1")
+            .bind(upload_id)
+            .bind(job_id)
+            .execute(&mut *tx)
+            .await
+            .context("failed to link archive job")?;
+
+        sqlx::query(
+            r#"
+            UPDATE repositories
+            SET status = 'queued',
+                sync_state = 'pending',
+                updated_at = NOW()
+            WHERE id = $1
+            "#,
+        )
+        .bind(repository_id)
+        .execute(&mut *tx)
+        .await
+        .context("failed to update repository state")?;
+
+        tx.commit().await?;
+        Ok(Some(job_id))
+    }
+
+    /// Abort a pending upload owned by `user_id`; returns whether it was pending
+    #[instrument(skip(self))]
+    pub async fn abort_source_upload(&self, upload_id: Uuid, user_id: Uuid) -> Result<bool> {
+        let result = sqlx::query(
+            r#"
+            UPDATE source_uploads
+            SET status = 'aborted'
+            WHERE id = $1 AND user_id = $2 AND status = 'pending'
+            "#,
+        )
+        .bind(upload_id)
+        .bind(user_id)
+        .execute(&self.pool)
+        .await
+        .context("failed to abort source upload")?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+/// Synthetic clone URL identifying an upload-fed repository among a tenant's repositories
+pub fn upload_git_url(name: &str) -> String {
+    format!("{}://archive/{}", UPLOAD_PROVIDER, name)
+}
+
+/// Whether `name` can label an upload-fed repository
+pub fn is_valid_upload_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= MAX_UPLOAD_NAME_LEN
+        && !name.starts_with('.')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+/// Directory holding the parts of `upload_id`
+pub fn upload_dir(db_path: &Path, upload_id: Uuid) -> PathBuf {
+    db_path.join("uploads").join(upload_id.to_string())
+}
+
+/// File a part is stored in; a re-sent part replaces it
+pub fn part_path(dir: &Path, part_number: u32) -> PathBuf {
+    dir.join(format!("part-{:05}", part_number))
+}
+
+/// Parts received so far, ordered by part number
+pub fn list_parts(dir: &Path) -> Result<Vec<UploadPart>> {
+    let mut parts = Vec::new();
+    if !dir.exists() {
+        return Ok(parts);
+    }
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name();
+        let Some(part_number) = name
+            .to_str()
+            .and_then(|name| name.strip_prefix("part-"))
+            .and_then(|number| number.parse::<u32>().ok())
+        else {
+            continue;
+        };
+        parts.push(UploadPart {
+            part_number,
+            size: entry.metadata()?.len(),
+        });
+    }
+    parts.sort_by_key(|part| part.part_number);
+    Ok(parts)
+}
+
+/// Total size of `parts`, which must be numbered from 1 without gaps and add up to at most
+/// [`MAX_UPLOAD_BYTES`]
+///
+/// Parts are written concurrently, so their individual size checks cannot bound the total.
+pub fn contiguous_size(parts: &[UploadPart]) -> Result<u64> {
+    if parts.is_empty() {
+        bail!("no parts uploaded");
+    }
+    for (expected, part) in (1..).zip(parts) {
+        if part.part_number != expected {
+            bail!("part {} is missing", expected);
+        }
+    }
+    let size = total_size(parts);
+    if size > MAX_UPLOAD_BYTES {
+        bail!(
+            "parts add up to {} bytes, more than the {} byte limit",
+            size,
+            MAX_UPLOAD_BYTES
+        );
+    }
+    Ok(size)
+}
+
+/// Combined size of `parts`
+pub fn total_size(parts: &[UploadPart]) -> u64 {
+    parts.iter().map(|part| part.size).sum()
+}
+
+/// Unpack the tar archive, gzip-compressed or not, formed by concatenating `parts` into `dest`.
+///
+/// Only regular files and directories are written. Entries escaping `dest`, links and anything
+/// under `.git` are skipped, and extraction stops once [`MAX_EXTRACTED_BYTES`] would be exceeded.
+pub fn extract_archive(parts: &[PathBuf], dest: &Path) -> Result<ExtractedArchive> {
+    let mut reader: Box<dyn Read> = Box::new(std::io::empty());
+    for path in parts {
+        let file =
+            File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+        reader = Box::new(reader.chain(file));
+    }
+    let mut reader = BufReader::new(reader);
+    let compressed = reader.fill_buf()?.starts_with(&GZIP_MAGIC);
+    let reader: Box<dyn Read> = if compressed {
+        Box::new(GzDecoder::new(reader))
+    } else {
+        Box::new(reader)
+    };
+
+    std::fs::create_dir_all(dest)?;
+    let mut summary = ExtractedArchive::default();
+    let mut archive = tar::Archive::new(reader);
+    for entry in archive.entries().context("archive is not a tar file")? {
+        let mut entry = entry.context("corrupt archive entry")?;
+        let Some(relative) = archive_entry_path(&entry.path()?) else {
+            summary.skipped += 1;
+            continue;
+        };
+        let target = dest.join(&relative);
+        match entry.header().entry_type() {
+            tar::EntryType::Directory => std::fs::create_dir_all(&target)?,
+            tar::EntryType::Regular | tar::EntryType::Continuous => {
+                summary.bytes += entry.size();
+                if summary.bytes > MAX_EXTRACTED_BYTES {
+                    bail!("archive expands beyond {} bytes", MAX_EXTRACTED_BYTES);
+                }
+                if let Some(parent) = target.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                let mut file = File::create(&target)
+                    .with_context(|| format!("failed to write {}", relative.display()))?;
+                std::io::copy(&mut entry, &mut file)?;
+                summary.files += 1;
+            }
+            _ => summary.skipped += 1,
+        }
+    }
+    Ok(summary)
+}
+
+/// Relative path an archive entry is written to, or `None` when it must be skipped
+fn archive_entry_path(path: &Path) -> Option<PathBuf> {
+    let mut relative = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::Normal(part) if part == ".git" => return None,
+            Component::Normal(part) => relative.push(part),
+            _ => return None,
+        }
+    }
+    (!relative.as_os_str().is_empty()).then_some(relative)
+}
+
+/// Source root of an extracted archive: its single top-level directory when it has one,
+/// as archives exported from forges usually do, otherwise `dir` itself
+pub fn archive_root(dir: &Path) -> Result<PathBuf> {
+    let mut entries = std::fs::read_dir(dir)?.collect::<std::io::Result<Vec<_>>>()?;
+    if entries.len() == 1 && entries[0].file_type()?.is_dir() {
+        return Ok(entries.remove(0).path());
+    }
+    Ok(dir.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::{write::GzEncoder, Compression};
+
+    fn tar_gz(entries: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::fast()));
+        for (path, content) in entries {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            // `append_data` rejects `..`, so write the raw name like a hostile archive would
+            header.as_gnu_mut().unwrap().name[..path.len()].copy_from_slice(path.as_bytes());
+            header.set_cksum();
+            builder.append(&header, *content).unwrap();
+        }
+        builder.into_inner().unwrap().finish().unwrap()
+    }
+
+    #[test]
+    fn extracts_archive_split_across_parts() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let archive = tar_gz(&[
+            ("project-main/src/lib.rs", b"pub fn a() {}\n"),
+            ("project-main/README.md", b"# project\n"),
+            ("project-main/../escape.rs", b"fn escape() {}\n"),
+            ("project-main/.git/config", b"[core]\n"),
+        ]);
+
+        let uploads = dir.path().join("parts");
+        std::fs::create_dir_all(&uploads)?;
+        let (first, second) = archive.split_at(archive.len() / 2);
+        std::fs::write(part_path(&uploads, 2), second)?;
+        std::fs::write(part_path(&uploads, 1), first)?;
+        let parts = list_parts(&uploads)?;
+        assert_eq!(contiguous_size(&parts)?, archive.len() as u64);
+
+        let dest = dir.path().join("workspace");
+        let paths: Vec<PathBuf> = parts
+            .iter()
+            .map(|part| part_path(&uploads, part.part_number))
+            .collect();
+        let summary = extract_archive(&paths, &dest)?;
+        assert_eq!(summary.files, 2);
+        assert_eq!(summary.skipped, 2);
+        assert!(!dir.path().join("escape.rs").exists());
+
+        let root = archive_root(&dest)?;
+        assert_eq!(root, dest.join("project-main"));
+        assert_eq!(
+            std::fs::read_to_string(root.join("src/lib.rs"))?,
+            "pub fn a() {}\n"
+        );
+        assert!(!root.join(".git").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_parts_adding_up_to_more_than_the_limit() -> Result<()> {
+        // Parts written concurrently each pass their own check yet exceed the total
+        let parts_at_limit = (MAX_UPLOAD_BYTES / MAX_UPLOAD_PART_BYTES) as u32;
+        let mut parts: Vec<UploadPart> = (1..=parts_at_limit)
+            .map(|part_number| UploadPart {
+                part_number,
+                size: MAX_UPLOAD_PART_BYTES,
+            })
+            .collect();
+        assert_eq!(contiguous_size(&parts)?, MAX_UPLOAD_BYTES);
+
+        parts.push(UploadPart {
+            part_number: parts_at_limit + 1,
+            size: 1,
+        });
+        assert!(contiguous_size(&parts).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_gaps_and_invalid_names() {
+        let parts = [
+            UploadPart {
+                part_number: 1,
+                size: 10,
+            },
+            UploadPart {
+                part_number: 3,
+                size: 10,
+            },
+        ];
+        assert!(contiguous_size(&parts).is_err());
+        assert!(contiguous_size(&[]).is_err());
+
+        assert!(is_valid_upload_name("monorepo-2.1_final"));
+        assert!(!is_valid_upload_name("../etc"));
+        assert!(!is_valid_upload_name(".hidden"));
+        assert!(!is_valid_upload_name("with space"));
+        assert!(!is_valid_upload_name(""));
+    }
+}
@@ -0,0 +1,309 @@
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+
+/// Commit message markers that suppress re-indexing when no markers are configured.
+pub const DEFAULT_SKIP_MARKERS: &[&str] = &["[skip ci]", "[ci skip]", "[skip kotadb]"];
+
+/// Per-repository rules deciding which webhook deliveries enqueue indexing jobs.
+///
+/// Stored under `settings.webhook_rules`. Patterns use `*` to match any run of characters
+/// (including `/`), so `release/*` and `src/*` match nested branches and paths.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WebhookRules {
+    /// Branches to track; empty tracks every branch
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub branches: Vec<String>,
+    /// Only re-index when a changed file matches one of these patterns; empty matches all
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub paths: Vec<String>,
+    /// Changed files matching these patterns never trigger re-indexing on their own
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ignore_paths: Vec<String>,
+    /// Head commit markers that skip the push; `None` uses [`DEFAULT_SKIP_MARKERS`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub skip_markers: Option<Vec<String>>,
+}
+
+/// Result of evaluating [`WebhookRules`] against a delivery.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WebhookDecision {
+    Enqueue,
+    Skip(String),
+}
+
+impl WebhookRules {
+    /// Read rules from repository settings, falling back to defaults when absent or malformed
+    pub fn from_settings(settings: &JsonValue) -> Self {
+        settings
+            .get("webhook_rules")
+            .and_then(|rules| serde_json::from_value(rules.clone()).ok())
+            .unwrap_or_default()
+    }
+
+    /// Parse and validate rules supplied by a client
+    pub fn parse(value: &JsonValue) -> Result<Self> {
+        let rules: WebhookRules = serde_json::from_value(value.clone())?;
+        let patterns = rules
+            .branches
+            .iter()
+            .chain(&rules.paths)
+            .chain(&rules.ignore_paths)
+            .chain(rules.skip_markers.iter().flatten());
+        for pattern in patterns {
+            if pattern.trim().is_empty() {
+                bail!("rule entries must not be empty");
+            }
+        }
+        Ok(rules)
+    }
+
+    /// Decide whether a GitHub delivery of `event_type` should enqueue a job
+    pub fn evaluate(&self, event_type: &str, payload: &JsonValue) -> WebhookDecision {
+        if let Some(branch) = target_branch(event_type, payload) {
+            if !self.branches.is_empty()
+                && !self
+                    .branches
+                    .iter()
+                    .any(|pattern| wildcard_match(pattern, &branch))
+            {
+                return WebhookDecision::Skip(format!("branch {} is not tracked", branch));
+            }
+        }
+
+        if let Some(message) = head_commit_message(payload) {
+            let lowered = message.to_ascii_lowercase();
+            let marker = match &self.skip_markers {
+                Some(markers) => markers
+                    .iter()
+                    .find(|m| lowered.contains(&m.to_ascii_lowercase()))
+                    .cloned(),
+                None => DEFAULT_SKIP_MARKERS
+                    .iter()
+                    .find(|m| lowered.contains(*m))
+                    .map(|m| m.to_string()),
+            };
+            if let Some(marker) = marker {
+                return WebhookDecision::Skip(format!("commit message contains {}", marker));
+            }
+        }
+
+        // Path filters only apply when the payload lists changed files (push events)
+        if !self.paths.is_empty() || !self.ignore_paths.is_empty() {
+            if let Some(files) = changed_files(payload) {
+                let relevant = files.iter().any(|file| {
+                    let included =
+                        self.paths.is_empty() || self.paths.iter().any(|p| wildcard_match(p, file));
+                    included && !self.ignore_paths.iter().any(|p| wildcard_match(p, file))
+                });
+                if !relevant {
+                    return WebhookDecision::Skip("no changed files match path filters".into());
+                }
+            }
+        }
+
+        WebhookDecision::Enqueue
+    }
+}
+
+/// Branch a delivery applies to: the pushed branch, or the base branch of a pull request
+fn target_branch(event_type: &str, payload: &JsonValue) -> Option<String> {
+    let raw = match event_type {
+        "push" => {
+            let git_ref = payload.get("ref")?.as_str()?;
+            // Tag pushes are not branch updates
+            if git_ref.starts_with("refs/tags/") {
+                return None;
+            }
+            git_ref
+        }
+        "pull_request" => payload
+            .get("pull_request")?
+            .get("base")?
+            .get("ref")?
+            .as_str()?,
+        "workflow_run" => payload.get("workflow_run")?.get("head_branch")?.as_str()?,
+        _ => return None,
+    };
+    Some(raw.strip_prefix("refs/heads/").unwrap_or(raw).to_string())
+}
+
+fn head_commit_message(payload: &JsonValue) -> Option<&str> {
+    payload
+        .get("head_commit")
+        .and_then(|commit| commit.get("message"))
+        .and_then(|message| message.as_str())
+        .or_else(|| {
+            payload
+                .get("commits")?
+                .as_array()?
+                .last()?
+                .get("message")?
+                .as_str()
+        })
+}
+
+/// GitHub lists at most this many commits in a push payload
+const MAX_PAYLOAD_COMMITS: usize = 20;
+
+/// Files changed by a push, or `None` when the payload does not list all of them
+fn changed_files(payload: &JsonValue) -> Option<Vec<&str>> {
+    let commits = payload.get("commits")?.as_array()?;
+    // Truncated pushes omit commits, so their file lists cannot rule anything out
+    let pushed = ["size", "distinct_size"]
+        .into_iter()
+        .filter_map(|key| payload.get(key).and_then(|v| v.as_u64()))
+        .max()
+        .unwrap_or(0);
+    if commits.len() >= MAX_PAYLOAD_COMMITS || pushed > commits.len() as u64 {
+        return None;
+    }
+    let files: Vec<&str> = commits
+        .iter()
+        .flat_map(|commit| {
+            ["added", "modified", "removed"]
+                .into_iter()
+                .filter_map(|key| commit.get(key).and_then(|v| v.as_array()))
+                .flatten()
+                .filter_map(|file| file.as_str())
+        })
+        .collect();
+    (!files.is_empty()).then_some(files)
+}
+
+/// Match `text` against a pattern where `*` matches any run of characters
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let parts: Vec<&str> = pattern.split('*').collect();
+    if parts.len() == 1 {
+        return pattern == text;
+    }
+
+    let first = parts[0];
+    let last = parts[parts.len() - 1];
+    if text.len() < first.len() + last.len() || !text.starts_with(first) || !text.ends_with(last) {
+        return false;
+    }
+
+    let mut pos = first.len();
+    let end = text.len() - last.len();
+    for part in &parts[1..parts.len() - 1] {
+        match text[pos..end].find(part) {
+            Some(found) => pos += found + part.len(),
+            None => return false,
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn push(git_ref: &str, message: &str, files: &[&str]) -> JsonValue {
+        json!({
+            "ref": git_ref,
+            "head_commit": { "message": message },
+            "commits": [{ "message": message, "modified": files }],
+        })
+    }
+
+    #[test]
+    fn default_rules_enqueue_everything_but_skip_markers() {
+        let rules = WebhookRules::default();
+        assert_eq!(
+            rules.evaluate(
+                "push",
+                &push("refs/heads/main", "fix parser", &["src/lib.rs"])
+            ),
+            WebhookDecision::Enqueue
+        );
+        assert!(matches!(
+            rules.evaluate(
+                "push",
+                &push("refs/heads/main", "docs [skip ci]", &["README.md"])
+            ),
+            WebhookDecision::Skip(_)
+        ));
+    }
+
+    #[test]
+    fn branch_and_path_filters_apply() {
+        let rules = WebhookRules::parse(&json!({
+            "branches": ["main", "release/*"],
+            "paths": ["src/*"],
+            "ignore_paths": ["src/generated/*"],
+            "skip_markers": [],
+        }))
+        .unwrap();
+
+        let tracked = push("refs/heads/release/1.2", "bump", &["src/lib.rs"]);
+        assert_eq!(rules.evaluate("push", &tracked), WebhookDecision::Enqueue);
+
+        let untracked = push("refs/heads/feature", "wip", &["src/lib.rs"]);
+        assert!(matches!(
+            rules.evaluate("push", &untracked),
+            WebhookDecision::Skip(_)
+        ));
+
+        let docs_only = push("refs/heads/main", "docs", &["docs/guide.md"]);
+        assert!(matches!(
+            rules.evaluate("push", &docs_only),
+            WebhookDecision::Skip(_)
+        ));
+
+        let generated = push("refs/heads/main", "regen", &["src/generated/api.rs"]);
+        assert!(matches!(
+            rules.evaluate("push", &generated),
+            WebhookDecision::Skip(_)
+        ));
+
+        // An empty marker list disables skip markers entirely
+        let marked = push("refs/heads/main", "[skip ci]", &["src/lib.rs"]);
+        assert_eq!(rules.evaluate("push", &marked), WebhookDecision::Enqueue);
+
+        let pr = json!({ "pull_request": { "base": { "ref": "main" }, "head": { "ref": "x" } } });
+        assert_eq!(
+            rules.evaluate("pull_request", &pr),
+            WebhookDecision::Enqueue
+        );
+    }
+
+    #[test]
+    fn truncated_pushes_bypass_path_filters() {
+        let rules = WebhookRules::parse(&json!({ "paths": ["src/*"] })).unwrap();
+        let commit = json!({ "message": "docs", "modified": ["docs/guide.md"] });
+
+        let mut listed = push("refs/heads/main", "docs", &["docs/guide.md"]);
+        listed["size"] = json!(1);
+        assert!(matches!(
+            rules.evaluate("push", &listed),
+            WebhookDecision::Skip(_)
+        ));
+
+        let mut oversized = listed.clone();
+        oversized["size"] = json!(35);
+        assert_eq!(rules.evaluate("push", &oversized), WebhookDecision::Enqueue);
+
+        let mut full_page = push("refs/heads/main", "docs", &[]);
+        full_page["commits"] = json!(vec![commit; MAX_PAYLOAD_COMMITS]);
+        assert_eq!(rules.evaluate("push", &full_page), WebhookDecision::Enqueue);
+    }
+
+    #[test]
+    fn parse_rejects_unknown_fields_and_empty_entries() {
+        assert!(WebhookRules::parse(&json!({ "branchez": ["main"] })).is_err());
+        assert!(WebhookRules::parse(&json!({ "paths": [" "] })).is_err());
+    }
+
+    #[test]
+    fn wildcard_matching() {
+        assert!(wildcard_match("main", "main"));
+        assert!(!wildcard_match("main", "main2"));
+        assert!(wildcard_match("release/*", "release/1.2/hotfix"));
+        assert!(wildcard_match("*.md", "docs/guide.md"));
+        assert!(wildcard_match("src/*/mod.rs", "src/a/b/mod.rs"));
+        assert!(!wildcard_match("ab*ba", "aba"));
+    }
+}
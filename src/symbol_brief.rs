@@ -0,0 +1,408 @@
+//! One-call symbol briefs for assistants
+//!
+//! Understanding a symbol usually takes a symbol search for its definition, a callers
+//! query and a file read. A [`SymbolBrief`] answers all three at once: where the symbol
+//! is defined, its signature and doc comment, its top callers and its source, cut to a
+//! token budget so the brief never crowds out the rest of the assistant's context.
+
+use serde::Serialize;
+
+use crate::llm_search::estimate_tokens;
+use crate::services::SourceExcerpt;
+
+/// Token budget of a brief unless the caller asks for another
+pub const DEFAULT_TOKEN_BUDGET: usize = 1500;
+
+/// Callers listed in a brief unless the caller asks for another number
+pub const DEFAULT_MAX_CALLERS: usize = 5;
+
+/// Most lines of a declaration read as its signature
+const MAX_SIGNATURE_LINES: usize = 8;
+
+/// A caller of the described symbol
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BriefCaller {
+    pub caller: String,
+    pub file_path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<u32>,
+}
+
+/// What is known about a symbol before its brief is cut to a budget
+#[derive(Debug, Clone)]
+pub struct BriefSource<'a> {
+    pub symbol: &'a str,
+    pub kind: &'a str,
+    pub file_path: &'a str,
+    /// 1-based, inclusive line range of the definition
+    pub start_line: usize,
+    pub end_line: usize,
+    /// Source lines of the defining file
+    pub lines: &'a [String],
+    /// Callers, most relevant first
+    pub callers: Vec<BriefCaller>,
+    /// Callers found in total, including any not passed in `callers`
+    pub total_callers: usize,
+    /// Other definitions of the same name as `path:line`
+    pub other_definitions: Vec<String>,
+}
+
+/// Definition, signature, doc comment, callers and source of one symbol
+#[derive(Debug, Clone, Serialize)]
+pub struct SymbolBrief {
+    pub symbol: String,
+    pub kind: String,
+    pub file_path: String,
+    pub start_line: usize,
+    pub end_line: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub doc_comment: Option<String>,
+    pub callers: Vec<BriefCaller>,
+    /// Callers found in total, including those not listed
+    pub total_callers: usize,
+    /// Source of the definition, from its first line for as long as the budget allows
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub snippet: Option<SourceExcerpt>,
+    /// Other definitions of the same name as `path:line`, not described further
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub other_definitions: Vec<String>,
+    /// Estimated tokens of the brief's content
+    pub estimated_tokens: usize,
+    /// Doc comment, callers or source were cut to fit the token budget
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub truncated: bool,
+}
+
+impl SymbolBrief {
+    /// Brief of `source` within roughly `token_budget` tokens
+    ///
+    /// Location and signature are always included. The doc comment, callers and source
+    /// follow in that order, each cut where the budget runs out.
+    pub fn build(source: BriefSource<'_>, token_budget: usize) -> Self {
+        let signature = signature_at(source.lines, source.start_line);
+        let doc_comment = doc_comment_of(source.lines, source.start_line);
+        let mut used = estimate_tokens(source.symbol)
+            + estimate_tokens(source.kind)
+            + estimate_tokens(source.file_path)
+            + signature.as_deref().map_or(0, estimate_tokens)
+            + source
+                .other_definitions
+                .iter()
+                .map(|d| estimate_tokens(d))
+                .sum::<usize>();
+        let mut truncated = false;
+
+        let doc_comment = doc_comment.and_then(|doc| {
+            let kept = take_lines_within(&doc.lines().collect::<Vec<_>>(), token_budget, &mut used);
+            truncated |= kept.len() < doc.lines().count();
+            (!kept.is_empty()).then(|| kept.join("\n"))
+        });
+
+        let mut callers = Vec::new();
+        for caller in source.callers {
+            let cost = estimate_tokens(&caller.caller) + estimate_tokens(&caller.file_path) + 1;
+            if used + cost > token_budget {
+                truncated = true;
+                break;
+            }
+            used += cost;
+            callers.push(caller);
+        }
+
+        let start = source.start_line.max(1);
+        let end = source.end_line.max(start).min(source.lines.len());
+        let body: Vec<&str> = source
+            .lines
+            .get(start - 1..end)
+            .unwrap_or_default()
+            .iter()
+            .map(String::as_str)
+            .collect();
+        let kept = take_lines_within(&body, token_budget, &mut used);
+        truncated |= kept.len() < body.len();
+        let snippet = (!kept.is_empty()).then(|| SourceExcerpt {
+            start_line: start,
+            end_line: start + kept.len() - 1,
+            text: kept.join("\n"),
+        });
+
+        Self {
+            symbol: source.symbol.to_string(),
+            kind: source.kind.to_string(),
+            file_path: source.file_path.to_string(),
+            start_line: source.start_line,
+            end_line: source.end_line,
+            signature,
+            doc_comment,
+            callers,
+            total_callers: source.total_callers,
+            snippet,
+            other_definitions: source.other_definitions,
+            estimated_tokens: used,
+            truncated,
+        }
+    }
+
+    /// Heading with the location, then signature, documentation, callers and source
+    pub fn to_markdown(&self) -> String {
+        let mut output = format!(
+            "## `{}` ({}) — {}:{}-{}\n\n",
+            self.symbol, self.kind, self.file_path, self.start_line, self.end_line
+        );
+        if let Some(signature) = &self.signature {
+            output.push_str(&format!("`{}`\n\n", signature));
+        }
+        if let Some(doc) = &self.doc_comment {
+            output.push_str(doc);
+            output.push_str("\n\n");
+        }
+        if self.total_callers > 0 {
+            output.push_str(&format!(
+                "Callers ({} of {}):\n",
+                self.callers.len(),
+                self.total_callers
+            ));
+            for caller in &self.callers {
+                match caller.line {
+                    Some(line) => output.push_str(&format!(
+                        "- `{}` at {}:{}\n",
+                        caller.caller, caller.file_path, line
+                    )),
+                    None => {
+                        output.push_str(&format!("- `{}` in {}\n", caller.caller, caller.file_path))
+                    }
+                }
+            }
+            output.push('\n');
+        } else {
+            output.push_str("No callers found.\n\n");
+        }
+        if let Some(snippet) = &self.snippet {
+            output.push_str(&format!(
+                "```\n{}\n```\n",
+                snippet.text.trim_end_matches('\n')
+            ));
+        }
+        if !self.other_definitions.is_empty() {
+            output.push_str(&format!(
+                "\nAlso defined at {}\n",
+                self.other_definitions.join(", ")
+            ));
+        }
+        if self.truncated {
+            output.push_str("\n(cut to fit the token budget)\n");
+        }
+        output
+    }
+}
+
+/// Leading lines that fit in what is left of `budget`, adding their cost to `used`
+fn take_lines_within<'a>(lines: &[&'a str], budget: usize, used: &mut usize) -> Vec<&'a str> {
+    let mut kept = Vec::new();
+    for line in lines {
+        let cost = estimate_tokens(line).max(1);
+        if *used + cost > budget {
+            break;
+        }
+        *used += cost;
+        kept.push(*line);
+    }
+    kept
+}
+
+/// Declaration starting at the 1-based `line`, up to the opening of its body
+///
+/// Multi-line declarations are joined into one line.
+pub fn signature_at(lines: &[String], line: usize) -> Option<String> {
+    let mut parts = Vec::new();
+    for text in lines
+        .iter()
+        .skip(line.checked_sub(1)?)
+        .take(MAX_SIGNATURE_LINES)
+    {
+        let text = text.trim();
+        if let Some(brace) = text.find('{') {
+            parts.push(text[..brace].trim_end());
+            break;
+        }
+        parts.push(text);
+        if text.ends_with(':') || text.ends_with(';') {
+            break;
+        }
+    }
+    let signature = parts
+        .into_iter()
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+    (!signature.is_empty()).then_some(signature)
+}
+
+/// Comment block directly above the definition at the 1-based `line`, skipping
+/// attributes and decorators, or else the docstring opening its body
+pub fn doc_comment_of(lines: &[String], line: usize) -> Option<String> {
+    let mut comment = Vec::new();
+    let mut above = line.checked_sub(1)?.min(lines.len());
+    while above > 0 {
+        let text = lines[above - 1].trim();
+        above -= 1;
+        if text.starts_with("#[") || text.starts_with('@') {
+            continue;
+        }
+        match strip_comment_marker(text) {
+            Some(content) => comment.push(content),
+            None => break,
+        }
+    }
+    comment.reverse();
+    let doc = trim_blank_lines(&comment);
+    if !doc.is_empty() {
+        return Some(doc);
+    }
+    docstring_below(lines, line)
+}
+
+fn strip_comment_marker(text: &str) -> Option<&str> {
+    ["///", "//!", "//", "/**", "/*", "*/", "*", "#"]
+        .iter()
+        .find_map(|marker| text.strip_prefix(marker))
+        .map(|rest| rest.trim_end_matches("*/").trim())
+}
+
+fn trim_blank_lines(lines: &[&str]) -> String {
+    let start = lines.iter().position(|l| !l.is_empty());
+    let end = lines.iter().rposition(|l| !l.is_empty());
+    match (start, end) {
+        (Some(start), Some(end)) => lines[start..=end].join("\n"),
+        _ => String::new(),
+    }
+}
+
+/// Python-style docstring on the lines after the declaration at the 1-based `line`
+fn docstring_below(lines: &[String], line: usize) -> Option<String> {
+    let declaration_end = lines
+        .iter()
+        .enumerate()
+        .skip(line - 1)
+        .take(MAX_SIGNATURE_LINES)
+        .find(|(_, text)| text.trim_end().ends_with(':'))?
+        .0;
+    let first = lines.get(declaration_end + 1)?.trim();
+    let quote = ["\"\"\"", "'''"]
+        .into_iter()
+        .find(|quote| first.starts_with(quote))?;
+    let opened = &first[quote.len()..];
+    if let Some(end) = opened.find(quote) {
+        let doc = opened[..end].trim();
+        return (!doc.is_empty()).then(|| doc.to_string());
+    }
+
+    let mut doc = vec![opened.trim()];
+    for text in &lines[declaration_end + 2..] {
+        let text = text.trim();
+        if let Some(end) = text.find(quote) {
+            doc.push(text[..end].trim());
+            break;
+        }
+        doc.push(text);
+    }
+    let doc = trim_blank_lines(&doc);
+    (!doc.is_empty()).then_some(doc)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(source: &str) -> Vec<String> {
+        source.lines().map(str::to_string).collect()
+    }
+
+    const RUST: &str = "use std::fmt;
+
+/// Parses a configuration file.
+///
+/// Unknown keys are ignored.
+#[must_use]
+pub fn parse_config(
+    path: &Path,
+) -> Result<Config> {
+    let text = read(path)?;
+    toml::from_str(&text)
+}
+";
+
+    fn source<'a>(lines: &'a [String], callers: usize) -> BriefSource<'a> {
+        BriefSource {
+            symbol: "parse_config",
+            kind: "function",
+            file_path: "src/config.rs",
+            start_line: 7,
+            end_line: 12,
+            lines,
+            callers: (0..callers)
+                .map(|i| BriefCaller {
+                    caller: format!("load_{i}"),
+                    file_path: "src/main.rs".to_string(),
+                    line: Some(10 + i as u32),
+                })
+                .collect(),
+            total_callers: callers + 2,
+            other_definitions: vec!["tests/config.rs:3".to_string()],
+        }
+    }
+
+    #[test]
+    fn reads_signature_and_doc_comments() {
+        let rust = lines(RUST);
+        assert_eq!(
+            signature_at(&rust, 7).as_deref(),
+            Some("pub fn parse_config( path: &Path, ) -> Result<Config>")
+        );
+        assert_eq!(
+            doc_comment_of(&rust, 7).as_deref(),
+            Some("Parses a configuration file.\n\nUnknown keys are ignored.")
+        );
+        assert_eq!(doc_comment_of(&rust, 1), None);
+
+        let python = lines(
+            "@cache\ndef load(path):\n    \"\"\"Load settings.\n\n    Missing files are empty.\n    \"\"\"\n    return {}\n",
+        );
+        assert_eq!(signature_at(&python, 2).as_deref(), Some("def load(path):"));
+        assert_eq!(
+            doc_comment_of(&python, 2).as_deref(),
+            Some("Load settings.\n\nMissing files are empty.")
+        );
+        let one_line = lines("def f():\n    '''Do it.'''\n");
+        assert_eq!(doc_comment_of(&one_line, 1).as_deref(), Some("Do it."));
+    }
+
+    #[test]
+    fn fits_the_token_budget() {
+        let rust = lines(RUST);
+        let full = SymbolBrief::build(source(&rust, 3), DEFAULT_TOKEN_BUDGET);
+        assert!(!full.truncated);
+        assert_eq!(full.callers.len(), 3);
+        assert_eq!(full.total_callers, 5);
+        let snippet = full.snippet.as_ref().unwrap();
+        assert_eq!((snippet.start_line, snippet.end_line), (7, 12));
+        assert!(snippet.text.ends_with("toml::from_str(&text)\n}"));
+        let markdown = full.to_markdown();
+        assert!(markdown.starts_with("## `parse_config` (function) — src/config.rs:7-12"));
+        assert!(markdown.contains("Callers (3 of 5):\n- `load_0` at src/main.rs:10\n"));
+        assert!(markdown.contains("Also defined at tests/config.rs:3"));
+
+        // A small budget keeps the location and signature, then cuts the rest
+        let small = SymbolBrief::build(source(&rust, 50), 40);
+        assert!(small.truncated);
+        assert!(small.signature.is_some());
+        assert!(small.callers.len() < 50);
+        assert!(small.estimated_tokens <= 40);
+        assert!(small
+            .snippet
+            .as_ref()
+            .is_none_or(|snippet| snippet.end_line < 12));
+    }
+}
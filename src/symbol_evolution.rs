@@ -0,0 +1,341 @@
+//! Symbol evolution across index snapshots
+//!
+//! `kotadb symbol-evolution <name>` opens the index snapshots recorded by
+//! `index-codebase --snapshot` one at a time, oldest commit first, and then the live index.
+//! In each it measures the symbol's definitions (file, line span, decision points) and the
+//! number of references to it, and reports when the symbol appeared, how its size,
+//! complexity and callers changed from one snapshot to the next, and when it was removed.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::path::Path;
+
+use crate::index_snapshots::{SnapshotManifest, SNAPSHOTS_DIR};
+use crate::services::AnalysisService;
+
+/// Definition of a symbol in one index
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SymbolDefinition {
+    pub file_path: String,
+    pub kind: String,
+    pub start_line: usize,
+    pub end_line: usize,
+    /// Decision points (branches and boolean operators) in the definition
+    pub complexity: usize,
+}
+
+impl SymbolDefinition {
+    /// Lines spanned by the definition
+    pub fn lines(&self) -> usize {
+        self.end_line.saturating_sub(self.start_line) + 1
+    }
+}
+
+/// Change of a symbol since the previous point of its timeline
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "change", rename_all = "snake_case")]
+pub enum SymbolChange {
+    Added { files: Vec<String> },
+    Removed,
+    Moved { from: Vec<String>, to: Vec<String> },
+    Resized { from: usize, to: usize },
+    Complexity { from: usize, to: usize },
+    Callers { from: usize, to: usize },
+}
+
+impl std::fmt::Display for SymbolChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SymbolChange::Added { files } => write!(f, "added in {}", files.join(", ")),
+            SymbolChange::Removed => write!(f, "removed"),
+            SymbolChange::Moved { from, to } => {
+                write!(f, "moved from {} to {}", from.join(", "), to.join(", "))
+            }
+            SymbolChange::Resized { from, to } => write!(f, "{} → {} lines", from, to),
+            SymbolChange::Complexity { from, to } => write!(f, "complexity {} → {}", from, to),
+            SymbolChange::Callers { from, to } => write!(f, "callers {} → {}", from, to),
+        }
+    }
+}
+
+/// A symbol as indexed at one commit, or in the live index
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct EvolutionPoint {
+    /// Commit of the snapshot; `None` for the live index
+    pub commit: Option<String>,
+    pub committed_at: Option<DateTime<Utc>>,
+    /// Definitions of the symbol; empty when it doesn't exist
+    pub definitions: Vec<SymbolDefinition>,
+    /// References to the symbol from other symbols
+    pub callers: usize,
+    /// Changes since the previous point
+    pub changes: Vec<SymbolChange>,
+}
+
+impl EvolutionPoint {
+    /// Lines of all definitions
+    pub fn lines(&self) -> usize {
+        self.definitions.iter().map(SymbolDefinition::lines).sum()
+    }
+
+    /// Decision points of all definitions
+    pub fn complexity(&self) -> usize {
+        self.definitions.iter().map(|d| d.complexity).sum()
+    }
+
+    fn files(&self) -> Vec<String> {
+        let files: BTreeSet<&str> = self
+            .definitions
+            .iter()
+            .map(|d| d.file_path.as_str())
+            .collect();
+        files.into_iter().map(str::to_string).collect()
+    }
+
+    fn label(&self) -> String {
+        match (&self.commit, self.committed_at) {
+            (Some(commit), Some(time)) => format!(
+                "{} ({})",
+                &commit[..commit.len().min(12)],
+                time.format("%Y-%m-%d")
+            ),
+            (Some(commit), None) => commit[..commit.len().min(12)].to_string(),
+            _ => "current index".to_string(),
+        }
+    }
+}
+
+/// Timeline of a symbol over the index snapshots
+#[derive(Debug, Clone, Serialize)]
+pub struct SymbolEvolution {
+    pub symbol: String,
+    /// Label of the first point the symbol exists in
+    pub introduced: Option<String>,
+    /// Label of the point the symbol last disappeared in, if it is gone
+    pub removed: Option<String>,
+    /// Oldest commit first, the live index last
+    pub points: Vec<EvolutionPoint>,
+}
+
+impl SymbolEvolution {
+    /// Timeline of measured points, filling in the changes between consecutive points
+    pub fn new(symbol: &str, mut points: Vec<EvolutionPoint>) -> Self {
+        let mut introduced = None;
+        let mut removed = None;
+        for i in 0..points.len() {
+            let changes = match i.checked_sub(1).map(|j| &points[j]) {
+                Some(previous) => changes_between(previous, &points[i]),
+                None if points[i].definitions.is_empty() => Vec::new(),
+                None => vec![SymbolChange::Added {
+                    files: points[i].files(),
+                }],
+            };
+            for change in &changes {
+                match change {
+                    SymbolChange::Added { .. } => {
+                        introduced.get_or_insert_with(|| points[i].label());
+                        removed = None;
+                    }
+                    SymbolChange::Removed => removed = Some(points[i].label()),
+                    _ => {}
+                }
+            }
+            points[i].changes = changes;
+        }
+        Self {
+            symbol: symbol.to_string(),
+            introduced,
+            removed,
+            points,
+        }
+    }
+
+    /// Format the timeline as markdown
+    pub fn to_markdown(&self) -> String {
+        let mut md = format!("## Evolution of `{}`\n\n", self.symbol);
+        match (&self.introduced, &self.removed) {
+            (None, _) => md.push_str("Not found in any snapshot.\n\n"),
+            (Some(introduced), None) => md.push_str(&format!("Introduced in {}.\n\n", introduced)),
+            (Some(introduced), Some(removed)) => md.push_str(&format!(
+                "Introduced in {}, removed in {}.\n\n",
+                introduced, removed
+            )),
+        }
+        md.push_str("| Snapshot | Files | Lines | Complexity | Callers | Changes |\n");
+        md.push_str("|---|---|---|---|---|---|\n");
+        for point in &self.points {
+            let changes: Vec<String> = point.changes.iter().map(ToString::to_string).collect();
+            if point.definitions.is_empty() {
+                md.push_str(&format!(
+                    "| {} | - | - | - | - | {} |\n",
+                    point.label(),
+                    changes.join("; ")
+                ));
+                continue;
+            }
+            md.push_str(&format!(
+                "| {} | {} | {} | {} | {} | {} |\n",
+                point.label(),
+                point.files().join(", "),
+                point.lines(),
+                point.complexity(),
+                point.callers,
+                changes.join("; ")
+            ));
+        }
+        md
+    }
+}
+
+fn changes_between(previous: &EvolutionPoint, current: &EvolutionPoint) -> Vec<SymbolChange> {
+    match (
+        previous.definitions.is_empty(),
+        current.definitions.is_empty(),
+    ) {
+        (true, true) => return Vec::new(),
+        (true, false) => {
+            return vec![SymbolChange::Added {
+                files: current.files(),
+            }]
+        }
+        (false, true) => return vec![SymbolChange::Removed],
+        (false, false) => {}
+    }
+    let mut changes = Vec::new();
+    let (from, to) = (previous.files(), current.files());
+    if from != to {
+        changes.push(SymbolChange::Moved { from, to });
+    }
+    if previous.lines() != current.lines() {
+        changes.push(SymbolChange::Resized {
+            from: previous.lines(),
+            to: current.lines(),
+        });
+    }
+    if previous.complexity() != current.complexity() {
+        changes.push(SymbolChange::Complexity {
+            from: previous.complexity(),
+            to: current.complexity(),
+        });
+    }
+    if previous.callers != current.callers {
+        changes.push(SymbolChange::Callers {
+            from: previous.callers,
+            to: current.callers,
+        });
+    }
+    changes
+}
+
+/// Measure `symbol` in every snapshot of the database at `db_path`, oldest commit first,
+/// then in the live index
+pub async fn trace_symbol(db_path: &Path, symbol: &str) -> Result<SymbolEvolution> {
+    let manifest = SnapshotManifest::load(db_path)?;
+    if manifest.snapshots.is_empty() {
+        anyhow::bail!(
+            "No index snapshots recorded. Index with snapshots first:\n\
+             kotadb index-codebase /path/to/repo --snapshot"
+        );
+    }
+
+    let mut points = Vec::with_capacity(manifest.snapshots.len() + 1);
+    for snapshot in &manifest.snapshots {
+        let snapshot_path = db_path.join(SNAPSHOTS_DIR).join(&snapshot.dir);
+        let point = measure(&snapshot_path, symbol)
+            .await
+            .with_context(|| format!("Failed to read snapshot {}", snapshot.dir))?;
+        points.push(EvolutionPoint {
+            commit: Some(snapshot.commit.clone()),
+            committed_at: Some(snapshot.committed_at),
+            ..point
+        });
+    }
+    points.push(measure(db_path, symbol).await?);
+    Ok(SymbolEvolution::new(symbol, points))
+}
+
+/// Open one index, measure the symbol in it and close it again
+async fn measure(db_path: &Path, symbol: &str) -> Result<EvolutionPoint> {
+    let database = crate::database::Database::new(db_path, true).await?;
+    let mut analysis_service = AnalysisService::new(&database, db_path.to_path_buf());
+    analysis_service.measure_symbol(symbol).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(
+        commit: &str,
+        spans: &[(&str, usize, usize, usize)],
+        callers: usize,
+    ) -> EvolutionPoint {
+        EvolutionPoint {
+            commit: Some(commit.to_string()),
+            committed_at: None,
+            definitions: spans
+                .iter()
+                .map(|&(file, start, end, complexity)| SymbolDefinition {
+                    file_path: file.to_string(),
+                    kind: "function".to_string(),
+                    start_line: start,
+                    end_line: end,
+                    complexity,
+                })
+                .collect(),
+            callers,
+            changes: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn reports_appearance_changes_and_removal() {
+        let evolution = SymbolEvolution::new(
+            "parse",
+            vec![
+                point("c1", &[], 0),
+                point("c2", &[("src/a.rs", 10, 19, 2)], 1),
+                point("c3", &[("src/a.rs", 10, 19, 2)], 1),
+                point("c4", &[("src/b.rs", 1, 25, 5)], 3),
+                point("c5", &[], 0),
+            ],
+        );
+
+        let changes: Vec<&[SymbolChange]> = evolution
+            .points
+            .iter()
+            .map(|p| p.changes.as_slice())
+            .collect();
+        assert_eq!(changes[0], []);
+        assert_eq!(
+            changes[1],
+            [SymbolChange::Added {
+                files: vec!["src/a.rs".to_string()]
+            }]
+        );
+        assert_eq!(changes[2], []);
+        assert_eq!(
+            changes[3],
+            [
+                SymbolChange::Moved {
+                    from: vec!["src/a.rs".to_string()],
+                    to: vec!["src/b.rs".to_string()]
+                },
+                SymbolChange::Resized { from: 10, to: 25 },
+                SymbolChange::Complexity { from: 2, to: 5 },
+                SymbolChange::Callers { from: 1, to: 3 },
+            ]
+        );
+        assert_eq!(changes[4], [SymbolChange::Removed]);
+        assert_eq!(evolution.introduced.as_deref(), Some("c2"));
+        assert_eq!(evolution.removed.as_deref(), Some("c5"));
+
+        let markdown = evolution.to_markdown();
+        assert!(markdown.contains("Introduced in c2, removed in c5."));
+        assert!(markdown.contains(
+            "| c4 | src/b.rs | 25 | 5 | 3 | moved from src/a.rs to src/b.rs; 10 → 25 lines; complexity 2 → 5; callers 1 → 3 |"
+        ));
+    }
+}
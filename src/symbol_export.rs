@@ -0,0 +1,273 @@
+//! Bulk export of the symbol table
+//!
+//! `GET /api/v1/symbols/export` streams every symbol of `symbols.kota` as one JSON object
+//! per line so external tools can mirror the symbol table without paging through
+//! `/api/v1/symbols`. Records are produced lazily from the memory-mapped symbol database,
+//! joined with the in/out degrees of `dependency_graph.bin` when it exists.
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+use uuid::Uuid;
+
+use crate::binary_symbols::{BinarySymbolReader, PackedSymbol};
+use crate::dependency_extractor::SerializableDependencyGraph;
+use crate::parsing::SymbolType;
+use crate::services::search_service::matches_wildcard_pattern;
+use crate::services::{RedactionRules, RepositoryScope};
+
+/// Server-side filters applied while exporting
+#[derive(Debug, Clone, Default)]
+pub struct SymbolExportFilter {
+    /// Symbol kind, e.g. `function` (case-insensitive)
+    pub kind: Option<String>,
+    /// Leading path of the files to export, e.g. `src/services/`
+    pub path_prefix: Option<String>,
+    /// Name substring, or wildcard pattern when it contains `*` (case-insensitive)
+    pub name: Option<String>,
+}
+
+impl SymbolExportFilter {
+    fn matches(&self, name: &str, kind: &str, file_path: &str) -> bool {
+        if let Some(wanted) = &self.kind {
+            if !kind.eq_ignore_ascii_case(wanted) {
+                return false;
+            }
+        }
+        if let Some(prefix) = &self.path_prefix {
+            let relative = file_path.trim_start_matches("./");
+            if !relative.starts_with(prefix.trim_start_matches("./")) {
+                return false;
+            }
+        }
+        if let Some(pattern) = &self.name {
+            let (name, pattern) = (name.to_lowercase(), pattern.to_lowercase());
+            let matched = if pattern.contains('*') {
+                matches_wildcard_pattern(&name, &pattern)
+            } else {
+                name.contains(&pattern)
+            };
+            if !matched {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// Size and connectivity of an exported symbol
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct SymbolExportMetrics {
+    pub lines: u32,
+    /// Bytes spanned; 0 for databases without byte ranges
+    pub bytes: u32,
+    /// References to the symbol in the dependency graph
+    pub in_degree: usize,
+    /// References from the symbol in the dependency graph
+    pub out_degree: usize,
+}
+
+/// One line of the export
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SymbolExportRecord {
+    pub id: Uuid,
+    pub name: String,
+    /// Qualified name from the dependency graph, when the symbol is part of it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub qualified_name: Option<String>,
+    pub kind: String,
+    pub file_path: String,
+    pub start_line: u32,
+    pub end_line: u32,
+    pub start_column: u32,
+    pub end_column: u32,
+    pub parent_id: Option<Uuid>,
+    /// Name of the parent symbol
+    pub parent: Option<String>,
+    pub metrics: SymbolExportMetrics,
+}
+
+/// Graph data joined onto exported symbols
+struct GraphNodeInfo {
+    qualified_name: String,
+    in_degree: usize,
+    out_degree: usize,
+}
+
+/// Lazily walks the symbol database, yielding the symbols that pass the filters
+pub struct SymbolExporter {
+    reader: BinarySymbolReader,
+    graph: HashMap<Uuid, GraphNodeInfo>,
+    filter: SymbolExportFilter,
+    scope: Option<RepositoryScope>,
+    redaction: RedactionRules,
+    next_index: usize,
+}
+
+impl SymbolExporter {
+    /// Open the symbol database (and dependency graph, if any) of the index at `db_path`
+    pub fn open(db_path: &Path, filter: SymbolExportFilter) -> Result<Self> {
+        let reader = BinarySymbolReader::open(&db_path.join("symbols.kota"))?;
+        let graph_path = db_path.join("dependency_graph.bin");
+        let mut graph = HashMap::new();
+        if graph_path.exists() {
+            let bytes = std::fs::read(&graph_path)
+                .with_context(|| format!("Failed to read {}", graph_path.display()))?;
+            let dependency_graph = SerializableDependencyGraph::from_bytes(&bytes)?;
+            for node in dependency_graph.nodes {
+                graph.insert(
+                    node.symbol_id,
+                    GraphNodeInfo {
+                        qualified_name: node.qualified_name,
+                        in_degree: node.in_degree,
+                        out_degree: node.out_degree,
+                    },
+                );
+            }
+        }
+        Ok(Self {
+            reader,
+            graph,
+            filter,
+            scope: None,
+            redaction: RedactionRules::default(),
+            next_index: 0,
+        })
+    }
+
+    /// Keep the export to the files of one repository
+    pub fn with_repository_scope(mut self, scope: RepositoryScope) -> Self {
+        self.scope = Some(scope);
+        self
+    }
+
+    /// Leave symbols defined at redacted paths out of the export
+    pub fn with_redaction(mut self, redaction: RedactionRules) -> Self {
+        self.redaction = redaction;
+        self
+    }
+
+    /// Symbols in the database, before filtering
+    pub fn total_symbols(&self) -> usize {
+        self.reader.symbol_count()
+    }
+
+    fn record(&self, symbol: &PackedSymbol) -> Option<SymbolExportRecord> {
+        let name = self.reader.get_symbol_name(symbol).ok()?;
+        let file_path = self.reader.get_symbol_file_path(symbol).ok()?;
+        let kind = match SymbolType::try_from(symbol.kind) {
+            Ok(symbol_type) => symbol_type.to_string(),
+            Err(_) => format!("unknown({})", symbol.kind),
+        };
+        if !self.filter.matches(&name, &kind, &file_path) {
+            return None;
+        }
+        if let Some(scope) = &self.scope {
+            if !scope.contains_path(&file_path) {
+                return None;
+            }
+        }
+        if self.redaction.is_redacted(&file_path) {
+            return None;
+        }
+
+        let id = Uuid::from_bytes(symbol.id);
+        let parent_id = (symbol.parent_id != [0u8; 16]).then(|| Uuid::from_bytes(symbol.parent_id));
+        let parent = parent_id
+            .and_then(|parent_id| self.reader.find_symbol(parent_id))
+            .and_then(|parent| self.reader.get_symbol_name(&parent).ok());
+        let node = self.graph.get(&id);
+        Some(SymbolExportRecord {
+            id,
+            name,
+            qualified_name: node.map(|node| node.qualified_name.clone()),
+            kind,
+            file_path,
+            start_line: symbol.start_line,
+            end_line: symbol.end_line,
+            start_column: symbol.start_column,
+            end_column: symbol.end_column,
+            parent_id,
+            parent,
+            metrics: SymbolExportMetrics {
+                lines: symbol.end_line.saturating_sub(symbol.start_line) + 1,
+                bytes: symbol.end_byte.saturating_sub(symbol.start_byte),
+                in_degree: node.map_or(0, |node| node.in_degree),
+                out_degree: node.map_or(0, |node| node.out_degree),
+            },
+        })
+    }
+
+    /// Up to `max` further records as NDJSON; empty once the database is exhausted
+    pub fn next_ndjson_batch(&mut self, max: usize) -> Result<Vec<u8>> {
+        let mut batch = Vec::new();
+        for record in self.by_ref().take(max) {
+            serde_json::to_writer(&mut batch, &record)?;
+            batch.push(b'\n');
+        }
+        Ok(batch)
+    }
+}
+
+impl Iterator for SymbolExporter {
+    type Item = SymbolExportRecord;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.next_index < self.reader.symbol_count() {
+            let symbol = self.reader.get_symbol(self.next_index)?;
+            self.next_index += 1;
+            if let Some(record) = self.record(&symbol) {
+                return Some(record);
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binary_symbols::BinarySymbolWriter;
+    use tempfile::TempDir;
+
+    #[test]
+    fn exports_filtered_symbols_as_ndjson() -> Result<()> {
+        let dir = TempDir::new()?;
+        let (parser, parse, helper) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let mut writer = BinarySymbolWriter::new();
+        writer.add_symbol(parser, "Parser", 4, "src/parser.rs", 1, 40, None);
+        writer.add_symbol(parse, "parse", 2, "src/parser.rs", 10, 19, Some(parser));
+        writer.add_symbol(helper, "parse_helper", 1, "tests/helpers.rs", 3, 5, None);
+        writer.write_to_file(&dir.path().join("symbols.kota"))?;
+
+        let all: Vec<SymbolExportRecord> =
+            SymbolExporter::open(dir.path(), SymbolExportFilter::default())?.collect();
+        assert_eq!(all.len(), 3);
+        assert_eq!(all[1].kind, "method");
+        assert_eq!(all[1].parent.as_deref(), Some("Parser"));
+        assert_eq!(all[1].parent_id, Some(parser));
+        assert_eq!(all[1].metrics.lines, 10);
+        assert_eq!(all[0].parent_id, None);
+
+        let filter = SymbolExportFilter {
+            name: Some("PARSE*".to_string()),
+            path_prefix: Some("./src/".to_string()),
+            ..SymbolExportFilter::default()
+        };
+        let mut exporter = SymbolExporter::open(dir.path(), filter)?;
+        let first = exporter.next_ndjson_batch(1)?;
+        let rest = exporter.next_ndjson_batch(10)?;
+        assert!(exporter.next_ndjson_batch(10)?.is_empty());
+
+        let lines: Vec<serde_json::Value> = [first, rest]
+            .concat()
+            .split(|&b| b == b'\n')
+            .filter(|line| !line.is_empty())
+            .map(serde_json::from_slice)
+            .collect::<Result<_, _>>()?;
+        let names: Vec<&str> = lines.iter().filter_map(|l| l["name"].as_str()).collect();
+        assert_eq!(names, ["Parser", "parse"]);
+        Ok(())
+    }
+}
@@ -304,6 +304,8 @@ impl SymbolStorage {
                             start_column: graph_node.location.start_column,
                             end_line: graph_node.location.end_line,
                             end_column: graph_node.location.end_column,
+                            start_byte: 0,
+                            end_byte: 0,
                             text: String::new(), // Empty for now
                             documentation: None,
                         };
@@ -1684,7 +1686,11 @@ impl SymbolStorage {
                     relation_type: relationship.relation_type.clone(),
                     line_number: 0,   // Not available in current SymbolRelation
                     column_number: 0, // Not available in current SymbolRelation
-                    context: None,    // Not available in current SymbolRelation
+                    end_line_number: None,
+                    end_column_number: None,
+                    start_byte: None,
+                    end_byte: None,
+                    context: None, // Not available in current SymbolRelation
                 };
                 graph.add_edge(from_node, to_node, edge);
             }
@@ -2336,6 +2342,8 @@ mod level1 {
                 end_line: 1,
                 start_column: 0,
                 end_column: 10,
+                start_byte: 0,
+                end_byte: 0,
                 text: "invalid".to_string(),
                 documentation: None,
             }],
@@ -2519,6 +2527,8 @@ impl TestStruct {
             end_line: 7,
             start_column: 0,
             end_column: 1,
+            start_byte: 0,
+            end_byte: 0,
             text: "fn test_function() {}".to_string(),
             documentation: None,
         };
@@ -2571,6 +2581,8 @@ impl TestStruct {
                 end_line: 2,
                 start_column: 0,
                 end_column: 1,
+                start_byte: 0,
+                end_byte: 0,
                 text: "test".to_string(),
                 documentation: None,
             };
@@ -0,0 +1,215 @@
+//! Per-database synonym dictionary for query expansion
+//!
+//! Questions tend to spell words out ("database config") while code abbreviates
+//! them (`db_cfg`). The dictionary maps a term to its synonyms and is stored as
+//! `synonyms.json` in the database directory, edited with `kotadb synonyms`.
+//! [`crate::services::SearchService`] expands content queries with it, searching
+//! the original query and every variant as alternatives of one trigram query.
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// File name of the dictionary inside a database directory
+pub const SYNONYMS_FILE: &str = "synonyms.json";
+
+/// Upper bound on query variants searched besides the original query
+pub const MAX_QUERY_EXPANSIONS: usize = 8;
+
+const MAX_TERM_LEN: usize = 64;
+
+/// Synonyms keyed by term; lookups work in both directions
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SynonymDictionary {
+    #[serde(default)]
+    pub entries: BTreeMap<String, Vec<String>>,
+}
+
+impl SynonymDictionary {
+    /// Location of the dictionary for a database directory
+    pub fn path_for(db_path: &Path) -> PathBuf {
+        db_path.join(SYNONYMS_FILE)
+    }
+
+    /// Load the dictionary of a database, or an empty one if none was saved
+    pub fn load(db_path: &Path) -> Result<Self> {
+        let path = Self::path_for(db_path);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read synonyms: {}", path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse synonyms: {}", path.display()))
+    }
+
+    /// Save the dictionary atomically into a database directory
+    pub fn save(&self, db_path: &Path) -> Result<()> {
+        let path = Self::path_for(db_path);
+        let tmp_path = path.with_extension("json.tmp");
+        std::fs::write(&tmp_path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write synonyms: {}", tmp_path.display()))?;
+        std::fs::rename(&tmp_path, &path)
+            .with_context(|| format!("Failed to replace synonyms: {}", path.display()))?;
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Add synonyms for a term, returning how many were new
+    pub fn add(&mut self, term: &str, synonyms: &[String]) -> Result<usize> {
+        let term = normalize_term(term)?;
+        let mut added = 0;
+        for synonym in synonyms {
+            let synonym = normalize_term(synonym)?;
+            if synonym == term {
+                continue;
+            }
+            let list = self.entries.entry(term.clone()).or_default();
+            if !list.contains(&synonym) {
+                list.push(synonym);
+                added += 1;
+            }
+        }
+        Ok(added)
+    }
+
+    /// Remove some synonyms of a term, or the whole entry when `synonyms` is empty
+    ///
+    /// Returns whether anything was removed.
+    pub fn remove(&mut self, term: &str, synonyms: &[String]) -> Result<bool> {
+        let term = normalize_term(term)?;
+        if synonyms.is_empty() {
+            return Ok(self.entries.remove(&term).is_some());
+        }
+        let Some(list) = self.entries.get_mut(&term) else {
+            return Ok(false);
+        };
+        let before = list.len();
+        for synonym in synonyms {
+            let synonym = normalize_term(synonym)?;
+            list.retain(|existing| *existing != synonym);
+        }
+        let removed = list.len() != before;
+        if list.is_empty() {
+            self.entries.remove(&term);
+        }
+        Ok(removed)
+    }
+
+    /// Every word that may replace `word`, in both directions of the dictionary
+    pub fn alternatives(&self, word: &str) -> Vec<String> {
+        let word = word.to_lowercase();
+        let mut alternatives: Vec<String> = Vec::new();
+        let mut push = |candidate: &String| {
+            if *candidate != word && !alternatives.contains(candidate) {
+                alternatives.push(candidate.clone());
+            }
+        };
+        if let Some(list) = self.entries.get(&word) {
+            list.iter().for_each(&mut push);
+        }
+        for (term, list) in &self.entries {
+            if list.contains(&word) {
+                push(term);
+                list.iter().for_each(&mut push);
+            }
+        }
+        alternatives
+    }
+
+    /// Query variants with one word replaced by a synonym, excluding the query itself
+    ///
+    /// At most [`MAX_QUERY_EXPANSIONS`] variants are produced, favouring earlier words.
+    pub fn expand_query(&self, query: &str) -> Vec<String> {
+        if self.is_empty() {
+            return Vec::new();
+        }
+        let words: Vec<&str> = query.split_whitespace().collect();
+        let mut variants = Vec::new();
+        for (i, word) in words.iter().enumerate() {
+            for alternative in self.alternatives(word) {
+                let variant = words
+                    .iter()
+                    .enumerate()
+                    .map(|(j, other)| if i == j { alternative.as_str() } else { other })
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                if !variants.contains(&variant) {
+                    variants.push(variant);
+                }
+                if variants.len() >= MAX_QUERY_EXPANSIONS {
+                    return variants;
+                }
+            }
+        }
+        variants
+    }
+}
+
+fn normalize_term(term: &str) -> Result<String> {
+    let term = term.trim().to_lowercase();
+    if term.is_empty() {
+        bail!("Synonym terms cannot be empty");
+    }
+    if term.len() > MAX_TERM_LEN {
+        bail!("Synonym terms are limited to {MAX_TERM_LEN} bytes");
+    }
+    if term.contains(char::is_whitespace) {
+        bail!("Synonym terms must be single words: '{term}'");
+    }
+    Ok(term)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dictionary() -> SynonymDictionary {
+        let mut dictionary = SynonymDictionary::default();
+        dictionary
+            .add("cfg", &["config".to_string(), "configuration".to_string()])
+            .unwrap();
+        dictionary.add("db", &["Database".to_string()]).unwrap();
+        dictionary
+    }
+
+    #[test]
+    fn expands_each_word_in_both_directions() {
+        let dictionary = dictionary();
+        assert_eq!(
+            dictionary.expand_query("load cfg"),
+            vec!["load config", "load configuration"]
+        );
+        assert_eq!(
+            dictionary.expand_query("database config"),
+            vec!["db config", "database cfg", "database configuration"]
+        );
+        assert!(dictionary.expand_query("storage engine").is_empty());
+    }
+
+    #[test]
+    fn add_and_remove_normalize_terms() {
+        let mut dictionary = dictionary();
+        assert_eq!(dictionary.add("DB", &["database".to_string()]).unwrap(), 0);
+        assert!(dictionary.add("db", &["two words".to_string()]).is_err());
+
+        assert!(dictionary.remove("cfg", &["config".to_string()]).unwrap());
+        assert_eq!(dictionary.entries["cfg"], vec!["configuration"]);
+        assert!(dictionary.remove("db", &[]).unwrap());
+        assert!(!dictionary.remove("db", &[]).unwrap());
+    }
+
+    #[test]
+    fn round_trips_through_the_database_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(SynonymDictionary::load(dir.path()).unwrap().is_empty());
+
+        let dictionary = dictionary();
+        dictionary.save(dir.path()).unwrap();
+        assert_eq!(SynonymDictionary::load(dir.path()).unwrap(), dictionary);
+    }
+}
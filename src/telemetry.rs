@@ -0,0 +1,429 @@
+//! Opt-in anonymous usage telemetry
+//!
+//! Telemetry is off until `kotadb telemetry enable` and collects nothing before that:
+//! no files are written and no requests are made. Once enabled, each CLI invocation
+//! appends one event to `telemetry_queue.jsonl` in the database directory: the
+//! subcommand name (never its arguments, queries or paths), how long it took and
+//! whether it succeeded. When [`BATCH_SIZE`] events are queued and an endpoint is
+//! configured, they are aggregated into a [`UsageReport`] — per-command counts and
+//! latency percentiles plus a bucketed database size — and posted to the endpoint.
+//! Reports that fail to send stay queued for the next batch. `DO_NOT_TRACK=1` or
+//! `KOTADB_TELEMETRY_DISABLED=1` switch collection off regardless of the settings.
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use uuid::Uuid;
+
+/// File name of the telemetry settings inside a database directory
+pub const SETTINGS_FILE: &str = "telemetry.json";
+
+/// File name of the local event queue inside a database directory
+pub const QUEUE_FILE: &str = "telemetry_queue.jsonl";
+
+/// Queued events that trigger sending a report
+pub const BATCH_SIZE: usize = 50;
+
+/// Most events kept while reports cannot be sent; older events are dropped first
+pub const MAX_QUEUED_EVENTS: usize = 5_000;
+
+/// Overrides the configured endpoint
+pub const ENDPOINT_ENV: &str = "KOTADB_TELEMETRY_ENDPOINT";
+
+const DISABLED_ENV: &str = "KOTADB_TELEMETRY_DISABLED";
+const SEND_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Whether telemetry is enabled for a database, and where reports go
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TelemetrySettings {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Random id generated on enable; identifies reports of one installation only
+    #[serde(default)]
+    pub installation_id: Option<Uuid>,
+    /// URL reports are posted to
+    #[serde(default)]
+    pub endpoint: Option<String>,
+    #[serde(default)]
+    pub enabled_at: Option<DateTime<Utc>>,
+}
+
+impl TelemetrySettings {
+    /// Location of the settings for a database directory
+    pub fn path_for(db_path: &Path) -> PathBuf {
+        db_path.join(SETTINGS_FILE)
+    }
+
+    /// Load the settings of a database; disabled if none were saved
+    pub fn load(db_path: &Path) -> Result<Self> {
+        let path = Self::path_for(db_path);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read telemetry settings: {}", path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse telemetry settings: {}", path.display()))
+    }
+
+    /// Save the settings atomically into a database directory
+    pub fn save(&self, db_path: &Path) -> Result<()> {
+        let path = Self::path_for(db_path);
+        let tmp_path = path.with_extension("json.tmp");
+        std::fs::write(&tmp_path, serde_json::to_string_pretty(self)?).with_context(|| {
+            format!("Failed to write telemetry settings: {}", tmp_path.display())
+        })?;
+        std::fs::rename(&tmp_path, &path)
+            .with_context(|| format!("Failed to replace telemetry settings: {}", path.display()))?;
+        Ok(())
+    }
+
+    /// Opt in, keeping the installation id of an earlier opt-in
+    pub fn enable(&mut self, endpoint: Option<String>) -> Result<()> {
+        if let Some(endpoint) = &endpoint {
+            let url = url::Url::parse(endpoint)
+                .with_context(|| format!("Invalid telemetry endpoint '{}'", endpoint))?;
+            if !matches!(url.scheme(), "http" | "https") {
+                bail!("Telemetry endpoint must be an http(s) URL");
+            }
+        }
+        if !self.enabled {
+            self.enabled_at = Some(Utc::now());
+        }
+        self.enabled = true;
+        self.installation_id.get_or_insert_with(Uuid::new_v4);
+        if endpoint.is_some() {
+            self.endpoint = endpoint;
+        }
+        Ok(())
+    }
+
+    /// Opt out, forgetting the installation id so a later opt-in starts afresh
+    pub fn disable(&mut self) {
+        *self = Self {
+            endpoint: self.endpoint.take(),
+            ..Self::default()
+        };
+    }
+
+    /// Endpoint reports are sent to, with [`ENDPOINT_ENV`] taking precedence
+    pub fn effective_endpoint(&self) -> Option<String> {
+        std::env::var(ENDPOINT_ENV)
+            .ok()
+            .filter(|endpoint| !endpoint.trim().is_empty())
+            .or_else(|| self.endpoint.clone())
+    }
+
+    /// Whether events are recorded, honoring the environment opt-outs
+    pub fn collecting(&self) -> bool {
+        self.enabled && !opted_out_by_environment()
+    }
+}
+
+/// Whether `DO_NOT_TRACK` or `KOTADB_TELEMETRY_DISABLED` is set to a true value
+pub fn opted_out_by_environment() -> bool {
+    [DISABLED_ENV, "DO_NOT_TRACK"].iter().any(|name| {
+        std::env::var(name)
+            .is_ok_and(|value| matches!(value.trim(), "1" | "true" | "yes" | "TRUE" | "YES"))
+    })
+}
+
+/// One CLI invocation
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UsageEvent {
+    /// Subcommand name, e.g. `search-code`
+    pub command: String,
+    pub duration_ms: u64,
+    pub success: bool,
+    pub recorded_at: DateTime<Utc>,
+}
+
+/// Usage of one command within a report
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CommandUsage {
+    pub count: usize,
+    pub failures: usize,
+    pub p50_ms: u64,
+    pub p90_ms: u64,
+    pub p99_ms: u64,
+}
+
+/// Aggregated, anonymous usage sent to the telemetry endpoint
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct UsageReport {
+    pub installation_id: Uuid,
+    pub version: String,
+    pub os: String,
+    pub period_start: DateTime<Utc>,
+    pub period_end: DateTime<Utc>,
+    /// Size class of the database directory, e.g. `10MB-100MB`
+    pub index_size: &'static str,
+    pub commands: BTreeMap<String, CommandUsage>,
+}
+
+impl UsageReport {
+    /// Aggregate queued events; `None` when there are none
+    pub fn from_events(
+        installation_id: Uuid,
+        events: &[UsageEvent],
+        index_bytes: u64,
+    ) -> Option<Self> {
+        let period_start = events.iter().map(|event| event.recorded_at).min()?;
+        let period_end = events.iter().map(|event| event.recorded_at).max()?;
+
+        let mut by_command: BTreeMap<&str, Vec<&UsageEvent>> = BTreeMap::new();
+        for event in events {
+            by_command.entry(&event.command).or_default().push(event);
+        }
+        let commands = by_command
+            .into_iter()
+            .map(|(command, events)| {
+                let mut durations: Vec<u64> = events.iter().map(|e| e.duration_ms).collect();
+                durations.sort_unstable();
+                let usage = CommandUsage {
+                    count: events.len(),
+                    failures: events.iter().filter(|e| !e.success).count(),
+                    p50_ms: percentile(&durations, 0.50),
+                    p90_ms: percentile(&durations, 0.90),
+                    p99_ms: percentile(&durations, 0.99),
+                };
+                (command.to_string(), usage)
+            })
+            .collect();
+
+        Some(Self {
+            installation_id,
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            os: std::env::consts::OS.to_string(),
+            period_start,
+            period_end,
+            index_size: index_size_bucket(index_bytes),
+            commands,
+        })
+    }
+}
+
+/// Nearest-rank percentile of sorted values
+fn percentile(sorted: &[u64], quantile: f64) -> u64 {
+    if sorted.is_empty() {
+        return 0;
+    }
+    let rank = (quantile * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+/// Size class reported instead of the exact size of a database
+pub fn index_size_bucket(bytes: u64) -> &'static str {
+    const MB: u64 = 1024 * 1024;
+    match bytes {
+        b if b < 10 * MB => "<10MB",
+        b if b < 100 * MB => "10MB-100MB",
+        b if b < 1024 * MB => "100MB-1GB",
+        b if b < 10 * 1024 * MB => "1GB-10GB",
+        _ => ">10GB",
+    }
+}
+
+/// Total size of the files below `dir`
+fn directory_size(dir: &Path) -> u64 {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .map(|entry| match entry.metadata() {
+            Ok(metadata) if metadata.is_dir() => directory_size(&entry.path()),
+            Ok(metadata) => metadata.len(),
+            Err(_) => 0,
+        })
+        .sum()
+}
+
+/// Location of the event queue for a database directory
+pub fn queue_path(db_path: &Path) -> PathBuf {
+    db_path.join(QUEUE_FILE)
+}
+
+/// Events waiting to be reported; unreadable lines are skipped
+pub fn queued_events(db_path: &Path) -> Result<Vec<UsageEvent>> {
+    let path = queue_path(db_path);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content = std::fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read telemetry queue: {}", path.display()))?;
+    Ok(content
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+/// Delete the event queue of a database
+pub fn clear_queue(db_path: &Path) -> Result<()> {
+    let path = queue_path(db_path);
+    if path.exists() {
+        std::fs::remove_file(&path)
+            .with_context(|| format!("Failed to remove telemetry queue: {}", path.display()))?;
+    }
+    Ok(())
+}
+
+fn append_event(db_path: &Path, event: &UsageEvent) -> Result<()> {
+    let path = queue_path(db_path);
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .with_context(|| format!("Failed to open telemetry queue: {}", path.display()))?;
+    writeln!(file, "{}", serde_json::to_string(event)?)
+        .with_context(|| format!("Failed to append to telemetry queue: {}", path.display()))
+}
+
+/// Keep only the newest [`MAX_QUEUED_EVENTS`] events
+fn truncate_queue(db_path: &Path, events: &[UsageEvent]) -> Result<()> {
+    let keep = &events[events.len().saturating_sub(MAX_QUEUED_EVENTS)..];
+    let mut content = String::new();
+    for event in keep {
+        content.push_str(&serde_json::to_string(event)?);
+        content.push('\n');
+    }
+    let path = queue_path(db_path);
+    let tmp_path = path.with_extension("jsonl.tmp");
+    std::fs::write(&tmp_path, content)
+        .with_context(|| format!("Failed to write telemetry queue: {}", tmp_path.display()))?;
+    std::fs::rename(&tmp_path, &path)
+        .with_context(|| format!("Failed to replace telemetry queue: {}", path.display()))
+}
+
+/// Record one CLI invocation if telemetry is enabled, sending a report once a batch
+/// is complete
+///
+/// Does nothing, and touches no files, while telemetry is disabled.
+pub async fn record_command(
+    db_path: &Path,
+    command: &str,
+    duration: Duration,
+    success: bool,
+) -> Result<()> {
+    let settings = TelemetrySettings::load(db_path)?;
+    if !settings.collecting() || !db_path.is_dir() {
+        return Ok(());
+    }
+    append_event(
+        db_path,
+        &UsageEvent {
+            command: command.to_string(),
+            duration_ms: duration.as_millis() as u64,
+            success,
+            recorded_at: Utc::now(),
+        },
+    )?;
+
+    let events = queued_events(db_path)?;
+    if events.len() > MAX_QUEUED_EVENTS {
+        truncate_queue(db_path, &events)?;
+    }
+    if events.len() >= BATCH_SIZE && settings.effective_endpoint().is_some() {
+        send_report(db_path, &settings).await?;
+    }
+    Ok(())
+}
+
+/// Post a report of the queued events and clear the queue once it was accepted
+///
+/// Returns the number of events reported.
+pub async fn send_report(db_path: &Path, settings: &TelemetrySettings) -> Result<usize> {
+    let (Some(installation_id), Some(endpoint)) =
+        (settings.installation_id, settings.effective_endpoint())
+    else {
+        bail!("Telemetry is not enabled with an endpoint");
+    };
+    let events = queued_events(db_path)?;
+    let Some(report) = UsageReport::from_events(installation_id, &events, directory_size(db_path))
+    else {
+        return Ok(0);
+    };
+    let client = reqwest::Client::builder().timeout(SEND_TIMEOUT).build()?;
+    client
+        .post(&endpoint)
+        .json(&report)
+        .send()
+        .await
+        .with_context(|| format!("Failed to send telemetry report to {}", endpoint))?
+        .error_for_status()
+        .with_context(|| format!("Telemetry endpoint {} rejected the report", endpoint))?;
+    clear_queue(db_path)?;
+    Ok(events.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn event(command: &str, duration_ms: u64, success: bool) -> UsageEvent {
+        UsageEvent {
+            command: command.to_string(),
+            duration_ms,
+            success,
+            recorded_at: Utc::now(),
+        }
+    }
+
+    #[tokio::test]
+    async fn records_nothing_until_enabled() -> Result<()> {
+        let dir = TempDir::new()?;
+        record_command(dir.path(), "search-code", Duration::from_millis(12), true).await?;
+        assert!(!queue_path(dir.path()).exists());
+        assert!(!TelemetrySettings::path_for(dir.path()).exists());
+
+        let mut settings = TelemetrySettings::load(dir.path())?;
+        settings.enable(None)?;
+        settings.save(dir.path())?;
+        record_command(dir.path(), "search-code", Duration::from_millis(12), true).await?;
+        let events = queued_events(dir.path())?;
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].command, "search-code");
+
+        let id = settings.installation_id;
+        settings.disable();
+        assert!(!settings.enabled);
+        assert!(settings.installation_id.is_none());
+        settings.enable(None)?;
+        assert_ne!(settings.installation_id, id);
+        assert!(settings.enable(Some("ftp://example.com".into())).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn reports_counts_percentiles_and_size_classes() {
+        let mut events: Vec<UsageEvent> = (1..=100)
+            .map(|ms| event("search-code", ms, ms != 7))
+            .collect();
+        events.push(event("stats", 40, true));
+
+        let id = Uuid::new_v4();
+        let report = UsageReport::from_events(id, &events, 50 * 1024 * 1024).unwrap();
+        assert_eq!(report.index_size, "10MB-100MB");
+        let search = &report.commands["search-code"];
+        assert_eq!(
+            (
+                search.count,
+                search.failures,
+                search.p50_ms,
+                search.p90_ms,
+                search.p99_ms
+            ),
+            (100, 1, 50, 90, 99)
+        );
+        assert_eq!(report.commands["stats"].p99_ms, 40);
+
+        assert!(UsageReport::from_events(id, &[], 0).is_none());
+        assert_eq!(index_size_bucket(0), "<10MB");
+        assert_eq!(index_size_bucket(20 * 1024 * 1024 * 1024), ">10GB");
+    }
+}
@@ -0,0 +1,194 @@
+//! Codebase health over time
+//!
+//! Every successful indexing run appends a [`MetricsSnapshot`] to
+//! `metrics_history.jsonl` in the database directory: how many files, lines and
+//! symbols the indexed repository holds, how many dependency cycles and unused
+//! symbols its part of the dependency graph has and how complex its source files
+//! are on average.
+//! `kotadb trends` and `GET /api/v1/analysis/trends` read the history back so an
+//! overview can be compared with earlier ones instead of standing alone.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// File name of the metrics history inside a database directory
+pub const TRENDS_FILE: &str = "metrics_history.jsonl";
+
+/// Codebase metrics recorded after one indexing run
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MetricsSnapshot {
+    /// RFC 3339 time the run finished
+    pub recorded_at: String,
+    /// Repository the run indexed
+    pub repository: String,
+    /// Storage prefix of the indexed repository's documents; the metrics of runs that
+    /// record one cover that repository alone, older runs cover the whole database
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub document_prefix: Option<String>,
+    pub files: usize,
+    pub lines: usize,
+    pub symbols: usize,
+    pub relationships: usize,
+    /// Groups of symbols that depend on each other in a cycle
+    pub cycles: usize,
+    /// Symbols nothing depends on, excluding entry points and tests
+    pub unused_symbols: usize,
+    /// Mean decision points per source file
+    pub avg_complexity: f64,
+    /// Test files per non-test source file
+    #[serde(default)]
+    pub test_to_code_ratio: f64,
+    /// Duration of the indexing run
+    #[serde(default)]
+    pub index_time_ms: u64,
+}
+
+/// Change of each metric between the oldest and newest snapshot of a history
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct MetricsChange {
+    pub files: i64,
+    pub lines: i64,
+    pub symbols: i64,
+    pub relationships: i64,
+    pub cycles: i64,
+    pub unused_symbols: i64,
+    pub avg_complexity: f64,
+}
+
+impl MetricsChange {
+    /// Difference `to - from`
+    pub fn between(from: &MetricsSnapshot, to: &MetricsSnapshot) -> Self {
+        let delta = |from: usize, to: usize| to as i64 - from as i64;
+        Self {
+            files: delta(from.files, to.files),
+            lines: delta(from.lines, to.lines),
+            symbols: delta(from.symbols, to.symbols),
+            relationships: delta(from.relationships, to.relationships),
+            cycles: delta(from.cycles, to.cycles),
+            unused_symbols: delta(from.unused_symbols, to.unused_symbols),
+            avg_complexity: to.avg_complexity - from.avg_complexity,
+        }
+    }
+}
+
+/// Append-only log of the snapshots of a database
+pub struct TrendHistory;
+
+impl TrendHistory {
+    /// Location of the history for a database directory
+    pub fn path_for(db_path: &Path) -> PathBuf {
+        db_path.join(TRENDS_FILE)
+    }
+
+    /// Record a snapshot after the ones already stored
+    pub fn append(db_path: &Path, snapshot: &MetricsSnapshot) -> Result<()> {
+        std::fs::create_dir_all(db_path)
+            .with_context(|| format!("Failed to create {}", db_path.display()))?;
+        let path = Self::path_for(db_path);
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .with_context(|| format!("Failed to open metrics history: {}", path.display()))?;
+        writeln!(file, "{}", serde_json::to_string(snapshot)?)
+            .with_context(|| format!("Failed to write metrics history: {}", path.display()))
+    }
+
+    /// The newest `limit` snapshots, oldest first
+    ///
+    /// Lines that fail to parse (e.g. a run interrupted mid-write) are skipped.
+    pub fn load(db_path: &Path, limit: usize) -> Result<Vec<MetricsSnapshot>> {
+        let path = Self::path_for(db_path);
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read metrics history: {}", path.display()))?;
+        let snapshots: Vec<MetricsSnapshot> = content
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .filter_map(|line| match serde_json::from_str(line) {
+                Ok(snapshot) => Some(snapshot),
+                Err(e) => {
+                    tracing::warn!("Skipping unreadable metrics record: {}", e);
+                    None
+                }
+            })
+            .collect();
+        let skip = snapshots.len().saturating_sub(limit);
+        Ok(snapshots.into_iter().skip(skip).collect())
+    }
+}
+
+/// Approximate cyclomatic complexity: branch keywords plus `&&`/`||` operators
+pub fn decision_points(source: &str) -> usize {
+    let keywords = source
+        .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .filter(|word| {
+            matches!(
+                *word,
+                "if" | "elif" | "for" | "while" | "loop" | "case" | "catch" | "except"
+            )
+        })
+        .count();
+    keywords + source.matches("&&").count() + source.matches("||").count()
+}
+
+/// Whether decision points are counted for files of `language`
+pub fn is_source_language(language: &str) -> bool {
+    !matches!(language, "Other" | "YAML" | "JSON" | "XML" | "TOML")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(files: usize, cycles: usize) -> MetricsSnapshot {
+        MetricsSnapshot {
+            recorded_at: "2026-01-01T00:00:00Z".to_string(),
+            repository: "repo".to_string(),
+            files,
+            cycles,
+            avg_complexity: 2.5,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn load_returns_the_newest_snapshots_oldest_first() {
+        let dir = tempfile::TempDir::new().unwrap();
+        assert!(TrendHistory::load(dir.path(), 10).unwrap().is_empty());
+        for files in 1..=4 {
+            TrendHistory::append(dir.path(), &snapshot(files, 0)).unwrap();
+        }
+        // A torn final line is ignored
+        let mut file = std::fs::OpenOptions::new()
+            .append(true)
+            .open(TrendHistory::path_for(dir.path()))
+            .unwrap();
+        write!(file, "{{\"recorded_at\":").unwrap();
+
+        let files: Vec<usize> = TrendHistory::load(dir.path(), 2)
+            .unwrap()
+            .iter()
+            .map(|s| s.files)
+            .collect();
+        assert_eq!(files, vec![3, 4]);
+    }
+
+    #[test]
+    fn change_is_newest_minus_oldest() {
+        let change = MetricsChange::between(&snapshot(10, 3), &snapshot(7, 4));
+        assert_eq!(change.files, -3);
+        assert_eq!(change.cycles, 1);
+        assert_eq!(change.avg_complexity, 0.0);
+    }
+
+    #[test]
+    fn decision_points_count_branches_and_boolean_operators() {
+        let source = "if a && b { for x in y {} } else if c || d {} // verify";
+        assert_eq!(decision_points(source), 5);
+    }
+}
@@ -10,7 +10,8 @@ use std::path::PathBuf;
 use tokio::fs;
 use tokio::sync::RwLock;
 
-use crate::contracts::{Document, Index, Query};
+use crate::contracts::{ContentTokenizer, Document, Index, Query, TokenizedDocument};
+use crate::identifier_tokenization;
 use crate::types::{ValidatedDocumentId, ValidatedPath};
 use crate::validation;
 use crate::wrappers::MeteredIndex;
@@ -32,6 +33,10 @@ pub struct TrigramIndex {
     metadata: RwLock<TrigramMetadata>,
     /// State tracking for lazy loading with error protection
     load_state: RwLock<LoadState>,
+    /// Index identifier subwords (camelCase/snake_case) alongside the raw text
+    ///
+    /// Fixed when the index is created and persisted in its metadata.
+    identifier_tokenization: bool,
 }
 
 /// Cached document content for search operations
@@ -58,6 +63,9 @@ struct TrigramMetadata {
     total_trigrams: usize,
     created: i64,
     updated: i64,
+    /// Whether identifier subwords were indexed; absent in indices built before the option
+    #[serde(default)]
+    identifier_subwords: bool,
 }
 
 impl Default for TrigramMetadata {
@@ -70,6 +78,7 @@ impl Default for TrigramMetadata {
             total_trigrams: 0,
             created: now,
             updated: now,
+            identifier_subwords: false,
         }
     }
 }
@@ -115,6 +124,187 @@ impl TrigramIndex {
         trigrams
     }
 
+    /// Enable or disable identifier-aware tokenization
+    ///
+    /// New indices default to [`identifier_tokenization::identifier_tokenization_enabled`];
+    /// an index that already holds documents keeps the mode it was built with.
+    pub fn set_identifier_tokenization(&mut self, enabled: bool) {
+        let metadata = self.metadata.get_mut();
+        if metadata.document_count > 0 {
+            if metadata.identifier_subwords != enabled {
+                tracing::warn!(
+                    "Ignoring identifier tokenization change for a non-empty trigram index; rebuild the index to switch modes"
+                );
+            }
+            return;
+        }
+        metadata.identifier_subwords = enabled;
+        self.identifier_tokenization = enabled;
+    }
+
+    /// Append identifier subwords to text about to be indexed, when enabled
+    fn with_identifier_subwords(&self, text: String) -> String {
+        Self::append_identifier_subwords(text, self.identifier_tokenization)
+    }
+
+    fn append_identifier_subwords(text: String, enabled: bool) -> String {
+        if !enabled {
+            return text;
+        }
+        let subwords = identifier_tokenization::identifier_subwords(&text);
+        if subwords.is_empty() {
+            text
+        } else {
+            format!("{text}\n{subwords}")
+        }
+    }
+
+    /// File name of `path`, indexed as the document title
+    fn title_of(path: &ValidatedPath) -> &str {
+        path.as_str()
+            .split('/')
+            .next_back()
+            .unwrap_or(path.as_str())
+    }
+
+    /// Trigrams indexed for a document: its file name and content, plus identifier
+    /// subwords when `identifier_subwords` is set
+    pub fn content_trigrams(
+        path: &ValidatedPath,
+        content: &[u8],
+        identifier_subwords: bool,
+    ) -> Vec<String> {
+        let searchable_text = format!(
+            "{} {}",
+            Self::title_of(path),
+            String::from_utf8_lossy(content)
+        );
+        Self::extract_trigrams(&Self::append_identifier_subwords(
+            searchable_text,
+            identifier_subwords,
+        ))
+    }
+
+    /// Documents matching one search term's trigrams under the minimum-match threshold
+    fn matching_documents(
+        index: &HashMap<String, HashSet<ValidatedDocumentId>>,
+        query_trigrams: &[String],
+    ) -> Vec<ValidatedDocumentId> {
+        // Find documents that contain these trigrams
+        let mut candidate_docs: HashMap<ValidatedDocumentId, usize> = HashMap::new();
+        let mut total_trigram_hits = 0;
+
+        for trigram in query_trigrams {
+            if let Some(doc_ids) = index.get(trigram) {
+                for doc_id in doc_ids {
+                    *candidate_docs.entry(*doc_id).or_insert(0) += 1;
+                }
+                total_trigram_hits += doc_ids.len();
+            }
+        }
+
+        // If no trigrams found matches at all, return empty results early
+        // This handles cases where query contains completely unknown patterns
+        if candidate_docs.is_empty() {
+            return Vec::new();
+        }
+
+        // Calculate minimum match threshold with improved precision
+        // This prevents false positives from random trigram matches
+        debug_assert!(
+            !query_trigrams.is_empty(),
+            "Should not reach threshold calculation with empty trigrams"
+        );
+        let min_match_threshold = if query_trigrams.len() <= 3 {
+            // For very short queries (1-3 trigrams), require all trigrams to match
+            query_trigrams.len()
+        } else if query_trigrams.len() <= 6 {
+            // For short queries (4-6 trigrams), require 80% match to reduce false positives
+            std::cmp::max(query_trigrams.len() * 8 / 10, query_trigrams.len() - 1)
+        } else {
+            // For longer queries, require at least 60% of trigrams to match
+            // This is more strict than the previous 30% to improve precision
+            std::cmp::max(3, (query_trigrams.len() * 6) / 10)
+        };
+
+        // Filter by minimum threshold first
+        let mut filtered_candidates: Vec<ValidatedDocumentId> = candidate_docs
+            .iter()
+            .filter(|(_, match_count)| **match_count >= min_match_threshold)
+            .map(|(doc_id, _)| *doc_id)
+            .collect();
+
+        // Optional fallback (feature-gated). Default: no fallback to avoid false positives.
+        if cfg!(feature = "aggressive-trigram-thresholds")
+            && filtered_candidates.is_empty()
+            && !candidate_docs.is_empty()
+        {
+            tracing::debug!(
+                "Strict threshold {} eliminated all {} candidates, applying fallback for query with {} trigrams",
+                min_match_threshold, candidate_docs.len(), query_trigrams.len()
+            );
+
+            // Progressive fallback: try increasingly relaxed thresholds
+            let fallback_thresholds = if query_trigrams.len() <= 3 {
+                // For very short queries, try 2/3 then 1/3 trigrams
+                vec![query_trigrams.len().saturating_sub(1), 1]
+            } else if query_trigrams.len() <= 6 {
+                // For medium queries, try 50% then 33% then minimum of 2
+                vec![
+                    query_trigrams.len() / 2,
+                    query_trigrams.len() / 3,
+                    std::cmp::min(2, query_trigrams.len()),
+                ]
+            } else {
+                // For long queries, try 40% then 30% then minimum of 3
+                vec![
+                    (query_trigrams.len() * 4) / 10,
+                    query_trigrams.len() / 3,
+                    std::cmp::min(3, query_trigrams.len()),
+                ]
+            };
+
+            for fallback_threshold in fallback_thresholds {
+                if fallback_threshold > 0 && fallback_threshold < min_match_threshold {
+                    filtered_candidates = candidate_docs
+                        .iter()
+                        .filter(|(_, match_count)| **match_count >= fallback_threshold)
+                        .map(|(doc_id, _)| *doc_id)
+                        .collect();
+
+                    if !filtered_candidates.is_empty() {
+                        tracing::debug!(
+                            "Fallback threshold {} found {} candidates",
+                            fallback_threshold,
+                            filtered_candidates.len()
+                        );
+                        break;
+                    }
+                }
+            }
+
+            // If we still have no results, take the top candidates by trigram match count
+            if filtered_candidates.is_empty() {
+                let mut sorted_candidates: Vec<_> = candidate_docs.iter().collect();
+                sorted_candidates.sort_by(|a, b| b.1.cmp(a.1));
+                filtered_candidates = sorted_candidates
+                    .into_iter()
+                    .take(std::cmp::min(5, candidate_docs.len())) // Take top 5 matches
+                    .map(|(doc_id, _)| *doc_id)
+                    .collect();
+
+                if !filtered_candidates.is_empty() {
+                    tracing::debug!(
+                        "Final fallback: returning top {} candidates by trigram matches",
+                        filtered_candidates.len()
+                    );
+                }
+            }
+        }
+
+        filtered_candidates
+    }
+
     /// Extract searchable text from a document
     ///
     /// Combines title and content for comprehensive text indexing
@@ -469,7 +659,10 @@ impl TrigramIndex {
                                         as usize;
 
                                     // Reconstruct trigrams from title and preview
-                                    let doc_text = format!("{} {}", title, content_preview);
+                                    let doc_text = self.with_identifier_subwords(format!(
+                                        "{} {}",
+                                        title, content_preview
+                                    ));
                                     let full_trigrams = Self::extract_trigrams(&doc_text);
 
                                     // Pre-compute trigram frequency map for performance
@@ -636,12 +829,32 @@ impl Index for TrigramIndex {
         validation::path::validate_storage_directory_path(path)?;
 
         let index_path = PathBuf::from(path);
+
+        // The tokenization mode belongs to the index on disk, so read it from the
+        // (small) metadata file now rather than waiting for the lazy load
+        let metadata_path = index_path.join("meta").join("trigram_metadata.json");
+        let persisted = match fs::read_to_string(&metadata_path).await {
+            Ok(content) => match serde_json::from_str::<TrigramMetadata>(&content) {
+                Ok(metadata) => Some(metadata),
+                Err(e) => {
+                    tracing::warn!("Failed to deserialize trigram metadata: {}", e);
+                    None
+                }
+            },
+            Err(_) => None,
+        };
+        let metadata = persisted.unwrap_or_else(|| TrigramMetadata {
+            identifier_subwords: identifier_tokenization::identifier_tokenization_enabled(),
+            ..TrigramMetadata::default()
+        });
+
         let index = Self {
             index_path: index_path.clone(),
             trigram_index: RwLock::new(HashMap::new()),
             document_cache: RwLock::new(HashMap::new()),
             wal_writer: RwLock::new(None),
-            metadata: RwLock::new(TrigramMetadata::default()),
+            identifier_tokenization: metadata.identifier_subwords,
+            metadata: RwLock::new(metadata),
             load_state: RwLock::new(LoadState::NotLoaded),
         };
 
@@ -731,133 +944,43 @@ impl Index for TrigramIndex {
             return Ok(Vec::new());
         }
 
-        // Extract trigrams from all search terms
-        // Estimate ~10 trigrams per search term on average, with reasonable bounds
-        let estimated_capacity = (query.search_terms.len() * 10).clamp(16, 1000);
-        let mut all_query_trigrams = Vec::with_capacity(estimated_capacity);
-        for search_term in &query.search_terms {
-            let term_trigrams = Self::extract_trigrams(search_term.as_str());
-            all_query_trigrams.extend(term_trigrams);
-        }
-
-        if all_query_trigrams.is_empty() {
-            return Ok(Vec::new());
-        }
-
-        // Find documents that contain these trigrams
-        let index = self.trigram_index.read().await;
-        let mut candidate_docs: HashMap<ValidatedDocumentId, usize> = HashMap::new();
-        let mut total_trigram_hits = 0;
-
-        for trigram in &all_query_trigrams {
-            if let Some(doc_ids) = index.get(trigram) {
-                for doc_id in doc_ids {
-                    *candidate_docs.entry(*doc_id).or_insert(0) += 1;
+        // Extract trigrams per search term; several terms are alternatives (OR), and a
+        // document matches when it passes the threshold for any of them
+        let term_trigrams: Vec<Vec<String>> = query
+            .search_terms
+            .iter()
+            .map(|search_term| {
+                if self.identifier_tokenization {
+                    Self::extract_trigrams(&identifier_tokenization::normalize_query_term(
+                        search_term.as_str(),
+                    ))
+                } else {
+                    Self::extract_trigrams(search_term.as_str())
                 }
-                total_trigram_hits += doc_ids.len();
-            }
-        }
+            })
+            .filter(|trigrams| !trigrams.is_empty())
+            .collect();
+        let all_query_trigrams: Vec<String> = term_trigrams.concat();
 
-        // If no trigrams found matches at all, return empty results early
-        // This handles cases where query contains completely unknown patterns
-        if candidate_docs.is_empty() {
+        if all_query_trigrams.is_empty() {
             return Ok(Vec::new());
         }
 
-        // Calculate minimum match threshold with improved precision
-        // This prevents false positives from random trigram matches
-        debug_assert!(
-            !all_query_trigrams.is_empty(),
-            "Should not reach threshold calculation with empty trigrams"
-        );
-        let min_match_threshold = if all_query_trigrams.len() <= 3 {
-            // For very short queries (1-3 trigrams), require all trigrams to match
-            all_query_trigrams.len()
-        } else if all_query_trigrams.len() <= 6 {
-            // For short queries (4-6 trigrams), require 80% match to reduce false positives
-            std::cmp::max(
-                all_query_trigrams.len() * 8 / 10,
-                all_query_trigrams.len() - 1,
-            )
-        } else {
-            // For longer queries, require at least 60% of trigrams to match
-            // This is more strict than the previous 30% to improve precision
-            std::cmp::max(3, (all_query_trigrams.len() * 6) / 10)
-        };
-
-        // Filter by minimum threshold first
-        let mut filtered_candidates: Vec<ValidatedDocumentId> = candidate_docs
-            .iter()
-            .filter(|(_, match_count)| **match_count >= min_match_threshold)
-            .map(|(doc_id, _)| *doc_id)
-            .collect();
-
-        // Optional fallback (feature-gated). Default: no fallback to avoid false positives.
-        if cfg!(feature = "aggressive-trigram-thresholds")
-            && filtered_candidates.is_empty()
-            && !candidate_docs.is_empty()
+        let mut filtered_candidates: Vec<ValidatedDocumentId> = Vec::new();
         {
-            tracing::debug!(
-                "Strict threshold {} eliminated all {} candidates, applying fallback for query with {} trigrams",
-                min_match_threshold, candidate_docs.len(), all_query_trigrams.len()
-            );
-
-            // Progressive fallback: try increasingly relaxed thresholds
-            let fallback_thresholds = if all_query_trigrams.len() <= 3 {
-                // For very short queries, try 2/3 then 1/3 trigrams
-                vec![all_query_trigrams.len().saturating_sub(1), 1]
-            } else if all_query_trigrams.len() <= 6 {
-                // For medium queries, try 50% then 33% then minimum of 2
-                vec![
-                    all_query_trigrams.len() / 2,
-                    all_query_trigrams.len() / 3,
-                    std::cmp::min(2, all_query_trigrams.len()),
-                ]
-            } else {
-                // For long queries, try 40% then 30% then minimum of 3
-                vec![
-                    (all_query_trigrams.len() * 4) / 10,
-                    all_query_trigrams.len() / 3,
-                    std::cmp::min(3, all_query_trigrams.len()),
-                ]
-            };
-
-            for fallback_threshold in fallback_thresholds {
-                if fallback_threshold > 0 && fallback_threshold < min_match_threshold {
-                    filtered_candidates = candidate_docs
-                        .iter()
-                        .filter(|(_, match_count)| **match_count >= fallback_threshold)
-                        .map(|(doc_id, _)| *doc_id)
-                        .collect();
-
-                    if !filtered_candidates.is_empty() {
-                        tracing::debug!(
-                            "Fallback threshold {} found {} candidates",
-                            fallback_threshold,
-                            filtered_candidates.len()
-                        );
-                        break;
+            let index = self.trigram_index.read().await;
+            let mut seen = HashSet::new();
+            for trigrams in &term_trigrams {
+                for doc_id in Self::matching_documents(&index, trigrams) {
+                    if seen.insert(doc_id) {
+                        filtered_candidates.push(doc_id);
                     }
                 }
             }
+        }
 
-            // If we still have no results, take the top candidates by trigram match count
-            if filtered_candidates.is_empty() {
-                let mut sorted_candidates: Vec<_> = candidate_docs.iter().collect();
-                sorted_candidates.sort_by(|a, b| b.1.cmp(a.1));
-                filtered_candidates = sorted_candidates
-                    .into_iter()
-                    .take(std::cmp::min(5, candidate_docs.len())) // Take top 5 matches
-                    .map(|(doc_id, _)| *doc_id)
-                    .collect();
-
-                if !filtered_candidates.is_empty() {
-                    tracing::debug!(
-                        "Final fallback: returning top {} candidates by trigram matches",
-                        filtered_candidates.len()
-                    );
-                }
-            }
+        if filtered_candidates.is_empty() {
+            return Ok(Vec::new());
         }
 
         // Calculate relevance scores for each candidate document using optimized scoring
@@ -959,20 +1082,29 @@ impl Index for TrigramIndex {
         path: ValidatedPath,
         content: &[u8],
     ) -> Result<()> {
-        // Convert content to string for trigram extraction
-        let content_str = String::from_utf8_lossy(content);
+        let tokenizer = ContentTokenizer::Trigram {
+            identifier_subwords: self.identifier_tokenization,
+        };
+        self.insert_tokenized(tokenizer.tokenize(id, path, content.to_vec()))
+            .await
+    }
 
-        // Create a Document-like structure for text extraction
-        // We need title and content for comprehensive indexing
-        let title = path
-            .as_str()
-            .split('/')
-            .next_back()
-            .unwrap_or(path.as_str());
-        let searchable_text = format!("{} {}", title, content_str);
+    async fn content_tokenizer(&self) -> Option<ContentTokenizer> {
+        Some(ContentTokenizer::Trigram {
+            identifier_subwords: self.identifier_tokenization,
+        })
+    }
 
-        // Extract trigrams from the full content
-        let trigrams = Self::extract_trigrams(&searchable_text);
+    /// Insert a document whose trigrams were extracted ahead of time
+    async fn insert_tokenized(&mut self, document: TokenizedDocument) -> Result<()> {
+        let TokenizedDocument {
+            id,
+            path,
+            content,
+            trigrams,
+        } = document;
+        let content_str = String::from_utf8_lossy(&content);
+        let title = Self::title_of(&path);
 
         if trigrams.is_empty() {
             return Ok(()); // Nothing to index
@@ -1023,7 +1155,8 @@ impl Index for TrigramIndex {
                     title: title.to_string(),
                     content_preview,
                     full_trigrams: trigrams.clone(),
-                    word_count: searchable_text.split_whitespace().count(),
+                    word_count: title.split_whitespace().count()
+                        + content_str.split_whitespace().count(),
                     trigram_count: trigrams.len(),
                     trigram_freq,
                 },
@@ -1278,4 +1411,61 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_identifier_tokenization_matches_subword_queries() -> Result<()> {
+        let test_dir = format!("test_data/trigram_identifiers_{}", Uuid::new_v4());
+        std::fs::create_dir_all(&test_dir)?;
+
+        let query = crate::contracts::Query {
+            search_terms: vec![crate::types::ValidatedSearchQuery::new("get user", 1)?],
+            limit: crate::types::ValidatedLimit::new(10, 100_000)?,
+            ..Default::default()
+        };
+        let content = b"pub fn getUserById(id: u64) -> Option<User>";
+
+        let mut index = TrigramIndex::open(&format!("{test_dir}/enabled")).await?;
+        index.set_identifier_tokenization(true);
+        let doc_id = ValidatedDocumentId::new();
+        index
+            .insert_with_content(doc_id, ValidatedPath::new("src/users.rs")?, content)
+            .await?;
+        assert_eq!(index.search(&query).await?, vec![doc_id]);
+
+        // Identifier queries are split the same way as the indexed code
+        let snake_query = crate::contracts::Query {
+            search_terms: vec![crate::types::ValidatedSearchQuery::new("get_user", 1)?],
+            ..query.clone()
+        };
+        assert_eq!(index.search(&snake_query).await?, vec![doc_id]);
+
+        // The mode is persisted with the index and wins over the setter after reopening
+        index.flush().await?;
+        drop(index);
+        let mut reopened = TrigramIndex::open(&format!("{test_dir}/enabled")).await?;
+        reopened.set_identifier_tokenization(false);
+        assert_eq!(reopened.search(&query).await?, vec![doc_id]);
+
+        let mut plain = TrigramIndex::open(&format!("{test_dir}/disabled")).await?;
+        plain.set_identifier_tokenization(false);
+        plain
+            .insert_with_content(
+                ValidatedDocumentId::new(),
+                ValidatedPath::new("src/users.rs")?,
+                content,
+            )
+            .await?;
+        assert!(plain.search(&query).await?.is_empty());
+
+        // Queries against an index built without subwords are not rewritten
+        let exact_query = crate::contracts::Query {
+            search_terms: vec![crate::types::ValidatedSearchQuery::new("getUserById", 1)?],
+            ..query.clone()
+        };
+        assert_eq!(plain.search(&exact_query).await?.len(), 1);
+
+        let _ = std::fs::remove_dir_all(&test_dir);
+
+        Ok(())
+    }
 }
@@ -74,6 +74,17 @@ impl VectorIndex {
         })
     }
 
+    /// Open an index saved at `path`, or an empty one if nothing was saved yet
+    pub async fn load(
+        path: impl AsRef<Path>,
+        distance_metric: DistanceMetric,
+        vector_dimension: usize,
+    ) -> Result<Self> {
+        let mut index = Self::new(path, distance_metric, vector_dimension).await?;
+        index.load_from_disk().await?;
+        Ok(index)
+    }
+
     /// Calculate distance between two vectors
     fn calculate_distance(&self, v1: &[f32], v2: &[f32]) -> f32 {
         if v1.len() != v2.len() {
@@ -142,6 +153,30 @@ impl VectorIndex {
         Ok(())
     }
 
+    /// Insert many vectors, writing the index to disk once
+    pub async fn insert_vectors(
+        &mut self,
+        vectors: Vec<(ValidatedDocumentId, Vec<f32>)>,
+    ) -> Result<()> {
+        for (id, vector) in vectors {
+            if vector.len() != self.vector_dimension {
+                return Err(anyhow!(
+                    "Vector dimension mismatch: expected {}, got {}",
+                    self.vector_dimension,
+                    vector.len()
+                ));
+            }
+            let levels = (0..=self.get_random_level())
+                .map(|_| HashSet::new())
+                .collect();
+            if self.entry_point.is_none() {
+                self.entry_point = Some(id);
+            }
+            self.nodes.insert(id, VectorNode { id, vector, levels });
+        }
+        self.save_to_disk().await
+    }
+
     /// Search for k nearest neighbors
     pub async fn search_knn(
         &self,
@@ -251,6 +286,24 @@ impl VectorIndex {
 
         Ok(removed)
     }
+
+    /// Remove many vectors, writing the index to disk once; returns how many existed
+    pub async fn remove_vectors(&mut self, ids: &[ValidatedDocumentId]) -> Result<usize> {
+        let removed = ids
+            .iter()
+            .filter(|id| self.nodes.remove(id).is_some())
+            .count();
+        if self
+            .entry_point
+            .is_some_and(|entry| !self.nodes.contains_key(&entry))
+        {
+            self.entry_point = self.nodes.keys().next().cloned();
+        }
+        if removed > 0 {
+            self.save_to_disk().await?;
+        }
+        Ok(removed)
+    }
 }
 
 /// Serializable index data for persistence
@@ -0,0 +1,221 @@
+//! Index warm-up ahead of the first query
+//!
+//! `kotadb warmup` is meant for container start: it reads every hot structure once so
+//! the first user query doesn't pay for cold pages and lazy loading. It pages in the
+//! trigram index and primary index leaf pages, maps and indexes `symbols.kota`,
+//! decodes `dependency_graph.bin`, fills the path cache and, on request, computes the
+//! default codebase overview. Index files stay in the OS page cache after the command
+//! exits, so a server started afterwards opens them warm. Artifacts that were never
+//! built are reported as skipped rather than failing the warm-up.
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::future::Future;
+use std::path::Path;
+use std::time::Instant;
+
+use crate::leaf_pages::LEAF_PAGES_FILE;
+use crate::maintenance::{warm_path_cache, TaskReport};
+use crate::services::{AnalysisService, AnalysisServiceDatabase, DatabaseAccess, OverviewOptions};
+
+/// Bytes between the reads that fault in a mapped file
+const PAGE_SIZE: usize = 4096;
+
+/// Top symbols and entry points in the precomputed overview, matching the API default
+const OVERVIEW_LIMIT: usize = 10;
+
+/// Outcome of one warm-up step
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct WarmupStep {
+    pub step: &'static str,
+    pub duration_ms: u64,
+    /// Items loaded: files, symbols, paths, ...
+    pub items: u64,
+    pub detail: String,
+    /// The structure has not been built for this database
+    pub skipped: bool,
+}
+
+impl WarmupStep {
+    /// Time `work`; `None` from it means the structure doesn't exist
+    async fn run<F>(step: &'static str, work: F) -> Result<Self>
+    where
+        F: Future<Output = Result<Option<TaskReport>>>,
+    {
+        let timer = Instant::now();
+        let report = work
+            .await
+            .with_context(|| format!("Warm-up step {} failed", step))?;
+        Ok(Self {
+            step,
+            duration_ms: timer.elapsed().as_millis() as u64,
+            skipped: report.is_none(),
+            items: report.as_ref().map_or(0, |r| r.items),
+            detail: report.map_or_else(|| "not built".to_string(), |r| r.detail),
+        })
+    }
+}
+
+/// Warm every structure of the database at `db_path`, in the order queries touch them
+pub async fn warm_up<D>(database: &D, db_path: &Path, overview: bool) -> Result<Vec<WarmupStep>>
+where
+    D: DatabaseAccess + AnalysisServiceDatabase,
+{
+    let mut steps = vec![
+        WarmupStep::run("index_files", async { page_in_index_files(db_path) }).await?,
+        WarmupStep::run("symbols", async { load_symbols(db_path) }).await?,
+        WarmupStep::run("dependency_graph", async { load_dependency_graph(db_path) }).await?,
+        WarmupStep::run("path_cache", async {
+            warm_path_cache(database).await.map(Some)
+        })
+        .await?,
+    ];
+    if overview {
+        steps.push(
+            WarmupStep::run("overview", async {
+                let overview = AnalysisService::new(database, db_path.to_path_buf())
+                    .generate_overview(OverviewOptions {
+                        format: "json".to_string(),
+                        top_symbols_limit: OVERVIEW_LIMIT,
+                        entry_points_limit: OVERVIEW_LIMIT,
+                        quiet: true,
+                    })
+                    .await?;
+                let count = |key: &str| overview.overview_data.get(key).cloned();
+                Ok(Some(TaskReport {
+                    items: 1,
+                    detail: format!(
+                        "{} files, {} symbols",
+                        count("total_files").unwrap_or_default(),
+                        count("total_symbols").unwrap_or_default()
+                    ),
+                }))
+            })
+            .await?,
+        );
+    }
+    Ok(steps)
+}
+
+/// Page in the binary trigram index files and the primary index leaf pages
+fn page_in_index_files(db_path: &Path) -> Result<Option<TaskReport>> {
+    let mut files = Vec::new();
+    if let Ok(entries) = std::fs::read_dir(db_path.join("trigram_index").join("binary")) {
+        files.extend(
+            entries
+                .flatten()
+                .map(|entry| entry.path())
+                .filter(|path| path.is_file()),
+        );
+    }
+    let leaves = db_path
+        .join("primary_index")
+        .join("data")
+        .join(LEAF_PAGES_FILE);
+    if leaves.is_file() {
+        files.push(leaves);
+    }
+    if files.is_empty() {
+        return Ok(None);
+    }
+    let mut bytes = 0;
+    for file in &files {
+        bytes += page_in(file)?;
+    }
+    Ok(Some(TaskReport {
+        items: files.len() as u64,
+        detail: format!(
+            "{} files, {:.1} MB paged in",
+            files.len(),
+            bytes as f64 / (1024.0 * 1024.0)
+        ),
+    }))
+}
+
+/// Map `path` and read one byte of every page so it is resident in the page cache
+pub fn page_in(path: &Path) -> Result<u64> {
+    let file =
+        std::fs::File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let len = file.metadata()?.len();
+    if len == 0 {
+        return Ok(0);
+    }
+    // SAFETY: the map is only read, and dropped before returning
+    let mmap = unsafe { memmap2::Mmap::map(&file) }
+        .with_context(|| format!("Failed to map {}", path.display()))?;
+    let checksum = mmap
+        .iter()
+        .step_by(PAGE_SIZE)
+        .fold(0u8, |acc, byte| acc.wrapping_add(*byte));
+    std::hint::black_box(checksum);
+    Ok(len)
+}
+
+#[cfg(feature = "tree-sitter-parsing")]
+fn load_symbols(db_path: &Path) -> Result<Option<TaskReport>> {
+    let path = db_path.join("symbols.kota");
+    if !path.exists() {
+        return Ok(None);
+    }
+    page_in(&path)?;
+    let reader = crate::binary_symbols::BinarySymbolReader::open(&path)?;
+    Ok(Some(TaskReport {
+        items: reader.symbol_count() as u64,
+        detail: format!("{} symbols mapped", reader.symbol_count()),
+    }))
+}
+
+#[cfg(not(feature = "tree-sitter-parsing"))]
+fn load_symbols(_db_path: &Path) -> Result<Option<TaskReport>> {
+    Ok(None)
+}
+
+#[cfg(feature = "tree-sitter-parsing")]
+fn load_dependency_graph(db_path: &Path) -> Result<Option<TaskReport>> {
+    let path = db_path.join("dependency_graph.bin");
+    if !path.exists() {
+        return Ok(None);
+    }
+    let bytes =
+        std::fs::read(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let graph = crate::dependency_extractor::SerializableDependencyGraph::from_bytes(&bytes)?;
+    Ok(Some(TaskReport {
+        items: graph.nodes.len() as u64,
+        detail: format!(
+            "{} symbols, {} edges decoded",
+            graph.nodes.len(),
+            graph.edges.len()
+        ),
+    }))
+}
+
+#[cfg(not(feature = "tree-sitter-parsing"))]
+fn load_dependency_graph(_db_path: &Path) -> Result<Option<TaskReport>> {
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn pages_in_index_files_and_skips_missing_structures() -> Result<()> {
+        let dir = TempDir::new()?;
+        assert!(page_in_index_files(dir.path())?.is_none());
+        assert!(load_symbols(dir.path())?.is_none());
+        assert!(load_dependency_graph(dir.path())?.is_none());
+
+        let binary = dir.path().join("trigram_index").join("binary");
+        std::fs::create_dir_all(&binary)?;
+        std::fs::write(binary.join("trigrams.bin"), vec![7u8; 3 * PAGE_SIZE + 1])?;
+        std::fs::write(binary.join("stats.bin"), [])?;
+        let report = page_in_index_files(dir.path())?.expect("index files exist");
+        assert_eq!(report.items, 2);
+        assert_eq!(
+            page_in(&binary.join("trigrams.bin"))?,
+            3 * PAGE_SIZE as u64 + 1
+        );
+        Ok(())
+    }
+}
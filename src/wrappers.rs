@@ -8,6 +8,7 @@ pub mod optimization;
 use anyhow::{Context, Result};
 use async_trait::async_trait;
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tokio::sync::{Mutex, RwLock};
@@ -15,7 +16,10 @@ use tracing::{debug, error, info, warn};
 use uuid::Uuid;
 
 use self::buffered_storage::BufferedStorage;
-use crate::contracts::{Document, Index, Query, Storage};
+use crate::contracts::{
+    ContentTokenizer, Document, DocumentMetadata, DocumentPage, DocumentSummary, Index, Query,
+    Storage, TokenizedDocument,
+};
 use crate::observability::*;
 use crate::types::{ValidatedDocumentId, ValidatedPath};
 use crate::validation::{self};
@@ -157,6 +161,45 @@ impl<S: Storage> Storage for TracedStorage<S> {
         .await
     }
 
+    async fn get_many(&self, ids: &[ValidatedDocumentId]) -> Result<Vec<Option<Document>>> {
+        with_trace_id("storage.get_many", async {
+            let start = Instant::now();
+            debug!("[{}] Getting {} documents", self.trace_id, ids.len());
+
+            let result = self.inner.get_many(ids).await;
+
+            let duration = start.elapsed();
+            record_metric(MetricType::Histogram {
+                name: "storage.get_many.duration",
+                value: duration.as_millis() as f64,
+                unit: "ms",
+            });
+            if let Ok(documents) = &result {
+                debug!(
+                    "[{}] Found {} of {} documents",
+                    self.trace_id,
+                    documents.iter().flatten().count(),
+                    ids.len()
+                );
+            }
+
+            result
+        })
+        .await
+    }
+
+    async fn summary(&self, id: &ValidatedDocumentId) -> Result<Option<DocumentSummary>> {
+        self.inner.summary(id).await
+    }
+
+    async fn get_metadata(&self, id: &ValidatedDocumentId) -> Result<Option<DocumentMetadata>> {
+        self.inner.get_metadata(id).await
+    }
+
+    async fn list_metadata(&self) -> Result<Vec<DocumentMetadata>> {
+        self.inner.list_metadata().await
+    }
+
     async fn update(&mut self, doc: Document) -> Result<()> {
         self.increment_op_count().await;
 
@@ -207,6 +250,29 @@ impl<S: Storage> Storage for TracedStorage<S> {
         .await
     }
 
+    async fn list_page(
+        &self,
+        after: Option<ValidatedDocumentId>,
+        limit: usize,
+    ) -> Result<DocumentPage> {
+        with_trace_id("storage.list_page", async {
+            debug!("[{}] Listing up to {} documents", self.trace_id, limit);
+            let start = Instant::now();
+
+            let result = self.inner.list_page(after, limit).await;
+
+            let duration = start.elapsed();
+            record_metric(MetricType::Histogram {
+                name: "storage.list_page.duration",
+                value: duration.as_millis() as f64,
+                unit: "ms",
+            });
+
+            result
+        })
+        .await
+    }
+
     async fn sync(&mut self) -> Result<()> {
         with_trace_id("storage.sync", async {
             info!("[{}] Syncing storage", self.trace_id);
@@ -310,6 +376,29 @@ impl<S: Storage> Storage for ValidatedStorage<S> {
         Ok(result)
     }
 
+    async fn get_many(&self, ids: &[ValidatedDocumentId]) -> Result<Vec<Option<Document>>> {
+        let documents = self.inner.get_many(ids).await?;
+
+        let no_existing = std::collections::HashSet::new();
+        for doc in documents.iter().flatten() {
+            validation::document::validate_for_insert(doc, &no_existing)?;
+        }
+
+        Ok(documents)
+    }
+
+    async fn summary(&self, id: &ValidatedDocumentId) -> Result<Option<DocumentSummary>> {
+        self.inner.summary(id).await
+    }
+
+    async fn get_metadata(&self, id: &ValidatedDocumentId) -> Result<Option<DocumentMetadata>> {
+        self.inner.get_metadata(id).await
+    }
+
+    async fn list_metadata(&self) -> Result<Vec<DocumentMetadata>> {
+        self.inner.list_metadata().await
+    }
+
     async fn update(&mut self, doc: Document) -> Result<()> {
         // Get existing document for validation
         let existing = self
@@ -337,6 +426,14 @@ impl<S: Storage> Storage for ValidatedStorage<S> {
         self.inner.list_all().await
     }
 
+    async fn list_page(
+        &self,
+        after: Option<ValidatedDocumentId>,
+        limit: usize,
+    ) -> Result<DocumentPage> {
+        self.inner.list_page(after, limit).await
+    }
+
     async fn sync(&mut self) -> Result<()> {
         self.inner.sync().await
     }
@@ -350,37 +447,288 @@ impl<S: Storage> Storage for ValidatedStorage<S> {
     }
 }
 
-/// Storage wrapper that automatically retries failed operations
+/// Kinds of storage failure, each retried under its own [`RetryPolicy`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorClass {
+    /// Rejected input; the same request fails the same way when repeated
+    Validation,
+    /// Filesystem and OS errors, usually transient
+    Io,
+    /// Errors not recognised as either of the above
+    Other,
+}
+
+impl ErrorClass {
+    /// Classify an error by the first recognised cause in its chain
+    pub fn of(error: &anyhow::Error) -> Self {
+        for cause in error.chain() {
+            if cause.is::<validation::ValidationError>() {
+                return Self::Validation;
+            }
+            if let Some(io) = cause.downcast_ref::<std::io::Error>() {
+                return match io.kind() {
+                    std::io::ErrorKind::InvalidInput | std::io::ErrorKind::InvalidData => {
+                        Self::Validation
+                    }
+                    _ => Self::Io,
+                };
+            }
+        }
+        Self::Other
+    }
+}
+
+/// How failed operations of one [`ErrorClass`] are retried
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetryPolicy {
+    /// Attempts in total, including the first one
+    pub max_attempts: u32,
+    /// Delay before the first retry, doubled for each one after it
+    pub base_delay: Duration,
+    /// Upper bound on the delay between attempts
+    pub max_delay: Duration,
+    /// Fraction of each delay drawn at random, from 0.0 (fixed) to 1.0 (full jitter)
+    pub jitter: f64,
+}
+
+impl RetryPolicy {
+    /// Fail on the first error
+    pub fn never() -> Self {
+        Self {
+            max_attempts: 1,
+            base_delay: Duration::ZERO,
+            max_delay: Duration::ZERO,
+            jitter: 0.0,
+        }
+    }
+
+    /// Exponential backoff with half of each delay jittered
+    pub fn exponential(max_attempts: u32, base_delay: Duration, max_delay: Duration) -> Self {
+        Self {
+            max_attempts: max_attempts.max(1),
+            base_delay,
+            max_delay,
+            jitter: 0.5,
+        }
+    }
+
+    /// Set the jittered fraction of each delay
+    pub fn with_jitter(mut self, jitter: f64) -> Self {
+        self.jitter = jitter.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Delay before the given retry (1 for the first), after jitter
+    pub fn delay(&self, retry: u32) -> Duration {
+        let factor = 2u32.saturating_pow(retry.saturating_sub(1));
+        let backoff = self.base_delay.saturating_mul(factor).min(self.max_delay);
+        backoff.mul_f64(1.0 - self.jitter * rand::random::<f64>())
+    }
+}
+
+/// Retry counters of a [`RetryableStorage`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RetryStats {
+    /// Retries performed
+    pub retries: u64,
+    /// Operations that succeeded after at least one retry
+    pub recovered: u64,
+    /// Operations that failed after every attempt their policy allows
+    pub exhausted: u64,
+    /// Operations failed by an error whose policy does not retry
+    pub not_retried: u64,
+    /// Operations abandoned because the next retry would overrun the time budget
+    pub over_budget: u64,
+}
+
+#[derive(Default)]
+struct RetryCounters {
+    retries: AtomicU64,
+    recovered: AtomicU64,
+    exhausted: AtomicU64,
+    not_retried: AtomicU64,
+    over_budget: AtomicU64,
+}
+
+impl RetryCounters {
+    fn bump(counter: &AtomicU64, metric: &'static str) {
+        counter.fetch_add(1, Ordering::Relaxed);
+        record_metric(MetricType::Counter {
+            name: metric,
+            value: 1,
+        });
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct RetryPolicies {
+    validation: RetryPolicy,
+    io: RetryPolicy,
+    other: RetryPolicy,
+}
+
+impl RetryPolicies {
+    fn get(&self, class: ErrorClass) -> &RetryPolicy {
+        match class {
+            ErrorClass::Validation => &self.validation,
+            ErrorClass::Io => &self.io,
+            ErrorClass::Other => &self.other,
+        }
+    }
+
+    fn get_mut(&mut self, class: ErrorClass) -> &mut RetryPolicy {
+        match class {
+            ErrorClass::Validation => &mut self.validation,
+            ErrorClass::Io => &mut self.io,
+            ErrorClass::Other => &mut self.other,
+        }
+    }
+}
+
+/// Retry bookkeeping for a single storage operation
+struct RetryAttempt {
+    operation: &'static str,
+    policies: RetryPolicies,
+    max_elapsed: Option<Duration>,
+    counters: Arc<RetryCounters>,
+    started: Instant,
+    retries: u32,
+}
+
+impl RetryAttempt {
+    fn succeeded(&self) {
+        if self.retries > 0 {
+            info!(
+                "Operation {} succeeded after {} attempts",
+                self.operation,
+                self.retries + 1
+            );
+            RetryCounters::bump(&self.counters.recovered, "storage.retry.recovered");
+        }
+    }
+
+    /// Wait out the backoff before the next attempt, or hand the error back
+    async fn backoff(&mut self, error: anyhow::Error) -> Result<()> {
+        let class = ErrorClass::of(&error);
+        let policy = *self.policies.get(class);
+        let attempt = self.retries + 1;
+
+        if policy.max_attempts <= 1 {
+            debug!(
+                "Operation {} failed with a non-retryable {:?} error: {}",
+                self.operation, class, error
+            );
+            RetryCounters::bump(&self.counters.not_retried, "storage.retry.not_retried");
+            return Err(error);
+        }
+        if attempt >= policy.max_attempts {
+            error!(
+                "Operation {} failed after {} attempts: {}",
+                self.operation, attempt, error
+            );
+            RetryCounters::bump(&self.counters.exhausted, "storage.retry.exhausted");
+            return Err(error);
+        }
+
+        let delay = policy.delay(attempt);
+        if let Some(budget) = self.max_elapsed {
+            if self.started.elapsed() + delay > budget {
+                error!(
+                    "Operation {} gave up after {} attempts, retry budget of {:?} spent: {}",
+                    self.operation, attempt, budget, error
+                );
+                RetryCounters::bump(&self.counters.over_budget, "storage.retry.over_budget");
+                return Err(error);
+            }
+        }
+
+        warn!(
+            "Operation {} failed (attempt {}/{}), retrying in {:?}: {}",
+            self.operation, attempt, policy.max_attempts, delay, error
+        );
+        self.retries += 1;
+        RetryCounters::bump(&self.counters.retries, "storage.retry.attempts");
+        tokio::time::sleep(delay).await;
+        Ok(())
+    }
+}
+
+/// Storage wrapper that retries failed operations according to per-error-class policies
 pub struct RetryableStorage<S: Storage> {
     inner: S,
-    max_retries: u32,
-    base_delay: Duration,
-    max_delay: Duration,
+    policies: RetryPolicies,
+    max_elapsed: Option<Duration>,
+    counters: Arc<RetryCounters>,
 }
 
 impl<S: Storage> RetryableStorage<S> {
     /// Create a new retryable storage wrapper
+    ///
+    /// I/O and unclassified errors get three attempts with jittered exponential
+    /// backoff; validation errors are returned immediately.
     pub fn new(inner: S) -> Self {
+        let backoff =
+            RetryPolicy::exponential(3, Duration::from_millis(100), Duration::from_secs(5));
         Self {
             inner,
-            max_retries: 3,
-            base_delay: Duration::from_millis(100),
-            max_delay: Duration::from_secs(5),
+            policies: RetryPolicies {
+                validation: RetryPolicy::never(),
+                io: backoff,
+                other: backoff,
+            },
+            max_elapsed: None,
+            counters: Arc::new(RetryCounters::default()),
         }
     }
 
-    /// Configure retry parameters
+    /// Configure the backoff for I/O and unclassified errors
     pub fn with_retry_config(
         mut self,
         max_retries: u32,
         base_delay: Duration,
         max_delay: Duration,
     ) -> Self {
-        self.max_retries = max_retries;
-        self.base_delay = base_delay;
-        self.max_delay = max_delay;
+        for class in [ErrorClass::Io, ErrorClass::Other] {
+            let policy = self.policies.get_mut(class);
+            *policy = RetryPolicy::exponential(max_retries, base_delay, max_delay)
+                .with_jitter(policy.jitter);
+        }
         self
     }
+
+    /// Set the policy for one class of error
+    pub fn with_policy(mut self, class: ErrorClass, policy: RetryPolicy) -> Self {
+        *self.policies.get_mut(class) = policy;
+        self
+    }
+
+    /// Stop retrying once an operation has spent this long, delays included
+    pub fn with_max_elapsed(mut self, budget: Duration) -> Self {
+        self.max_elapsed = Some(budget);
+        self
+    }
+
+    /// Get retry statistics
+    pub fn retry_stats(&self) -> RetryStats {
+        RetryStats {
+            retries: self.counters.retries.load(Ordering::Relaxed),
+            recovered: self.counters.recovered.load(Ordering::Relaxed),
+            exhausted: self.counters.exhausted.load(Ordering::Relaxed),
+            not_retried: self.counters.not_retried.load(Ordering::Relaxed),
+            over_budget: self.counters.over_budget.load(Ordering::Relaxed),
+        }
+    }
+
+    fn attempt(&self, operation: &'static str) -> RetryAttempt {
+        RetryAttempt {
+            operation,
+            policies: self.policies,
+            max_elapsed: self.max_elapsed,
+            counters: self.counters.clone(),
+            started: Instant::now(),
+            retries: 0,
+        }
+    }
 }
 
 #[async_trait]
@@ -394,249 +742,136 @@ impl<S: Storage> Storage for RetryableStorage<S> {
     }
 
     async fn insert(&mut self, doc: Document) -> Result<()> {
-        let mut attempt = 0;
-        let mut delay = self.base_delay;
-
+        let mut retry = self.attempt("insert");
         loop {
-            attempt += 1;
-
             match self.inner.insert(doc.clone()).await {
                 Ok(()) => {
-                    if attempt > 1 {
-                        info!("Operation insert succeeded after {} attempts", attempt);
-                    }
+                    retry.succeeded();
                     return Ok(());
                 }
-                Err(e) if attempt >= self.max_retries => {
-                    error!("Operation insert failed after {} attempts: {}", attempt, e);
-                    return Err(e);
-                }
-                Err(e) => {
-                    warn!(
-                        "Operation insert failed (attempt {}/{}): {}",
-                        attempt, self.max_retries, e
-                    );
-
-                    tokio::time::sleep(delay).await;
-
-                    // Exponential backoff with jitter
-                    delay = std::cmp::min(delay * 2, self.max_delay);
-                    let jitter = Duration::from_millis(rand::random::<u64>() % 100);
-                    delay += jitter;
-                }
+                Err(e) => retry.backoff(e).await?,
             }
         }
     }
 
     async fn get(&self, id: &ValidatedDocumentId) -> Result<Option<Document>> {
-        let mut attempt = 0;
-        let mut delay = self.base_delay;
-
+        let mut retry = self.attempt("get");
         loop {
-            attempt += 1;
-
             match self.inner.get(id).await {
                 Ok(result) => {
-                    if attempt > 1 {
-                        info!("Operation get succeeded after {} attempts", attempt);
-                    }
+                    retry.succeeded();
                     return Ok(result);
                 }
-                Err(e) if attempt >= self.max_retries => {
-                    error!("Operation get failed after {} attempts: {}", attempt, e);
-                    return Err(e);
-                }
-                Err(e) => {
-                    warn!(
-                        "Operation get failed (attempt {}/{}): {}",
-                        attempt, self.max_retries, e
-                    );
-
-                    tokio::time::sleep(delay).await;
-
-                    // Exponential backoff with jitter
-                    delay = std::cmp::min(delay * 2, self.max_delay);
-                    let jitter = Duration::from_millis(rand::random::<u64>() % 100);
-                    delay += jitter;
+                Err(e) => retry.backoff(e).await?,
+            }
+        }
+    }
+
+    async fn get_many(&self, ids: &[ValidatedDocumentId]) -> Result<Vec<Option<Document>>> {
+        let mut retry = self.attempt("get_many");
+        loop {
+            match self.inner.get_many(ids).await {
+                Ok(result) => {
+                    retry.succeeded();
+                    return Ok(result);
                 }
+                Err(e) => retry.backoff(e).await?,
             }
         }
     }
 
-    async fn update(&mut self, doc: Document) -> Result<()> {
-        let mut attempt = 0;
-        let mut delay = self.base_delay;
+    async fn summary(&self, id: &ValidatedDocumentId) -> Result<Option<DocumentSummary>> {
+        // Metadata lookups don't touch the disk, so there is nothing to retry
+        self.inner.summary(id).await
+    }
 
-        loop {
-            attempt += 1;
+    async fn get_metadata(&self, id: &ValidatedDocumentId) -> Result<Option<DocumentMetadata>> {
+        // Metadata lookups don't read content, so there is nothing to retry
+        self.inner.get_metadata(id).await
+    }
+
+    async fn list_metadata(&self) -> Result<Vec<DocumentMetadata>> {
+        self.inner.list_metadata().await
+    }
 
+    async fn update(&mut self, doc: Document) -> Result<()> {
+        let mut retry = self.attempt("update");
+        loop {
             match self.inner.update(doc.clone()).await {
                 Ok(()) => {
-                    if attempt > 1 {
-                        info!("Operation update succeeded after {} attempts", attempt);
-                    }
+                    retry.succeeded();
                     return Ok(());
                 }
-                Err(e) if attempt >= self.max_retries => {
-                    error!("Operation update failed after {} attempts: {}", attempt, e);
-                    return Err(e);
-                }
-                Err(e) => {
-                    warn!(
-                        "Operation update failed (attempt {}/{}): {}",
-                        attempt, self.max_retries, e
-                    );
-
-                    tokio::time::sleep(delay).await;
-
-                    // Exponential backoff with jitter
-                    delay = std::cmp::min(delay * 2, self.max_delay);
-                    let jitter = Duration::from_millis(rand::random::<u64>() % 100);
-                    delay += jitter;
-                }
+                Err(e) => retry.backoff(e).await?,
             }
         }
     }
 
     async fn delete(&mut self, id: &ValidatedDocumentId) -> Result<bool> {
-        let mut attempt = 0;
-        let mut delay = self.base_delay;
-
+        let mut retry = self.attempt("delete");
         loop {
-            attempt += 1;
-
             match self.inner.delete(id).await {
                 Ok(deleted) => {
-                    if attempt > 1 {
-                        info!("Operation delete succeeded after {} attempts", attempt);
-                    }
+                    retry.succeeded();
                     return Ok(deleted);
                 }
-                Err(e) if attempt >= self.max_retries => {
-                    error!("Operation delete failed after {} attempts: {}", attempt, e);
-                    return Err(e);
-                }
-                Err(e) => {
-                    warn!(
-                        "Operation delete failed (attempt {}/{}): {}",
-                        attempt, self.max_retries, e
-                    );
-
-                    tokio::time::sleep(delay).await;
-
-                    // Exponential backoff with jitter
-                    delay = std::cmp::min(delay * 2, self.max_delay);
-                    let jitter = Duration::from_millis(rand::random::<u64>() % 100);
-                    delay += jitter;
-                }
+                Err(e) => retry.backoff(e).await?,
             }
         }
     }
 
     async fn list_all(&self) -> Result<Vec<Document>> {
-        let mut attempt = 0;
-        let mut delay = self.base_delay;
-
+        let mut retry = self.attempt("list_all");
         loop {
-            attempt += 1;
-
             match self.inner.list_all().await {
                 Ok(result) => {
-                    if attempt > 1 {
-                        info!("Operation list_all succeeded after {} attempts", attempt);
-                    }
+                    retry.succeeded();
                     return Ok(result);
                 }
-                Err(e) if attempt >= self.max_retries => {
-                    error!(
-                        "Operation list_all failed after {} attempts: {}",
-                        attempt, e
-                    );
-                    return Err(e);
-                }
-                Err(e) => {
-                    warn!(
-                        "Operation list_all failed (attempt {}/{}): {}",
-                        attempt, self.max_retries, e
-                    );
-
-                    tokio::time::sleep(delay).await;
-
-                    // Exponential backoff with jitter
-                    delay = std::cmp::min(delay * 2, self.max_delay);
-                    let jitter = Duration::from_millis(rand::random::<u64>() % 100);
-                    delay += jitter;
+                Err(e) => retry.backoff(e).await?,
+            }
+        }
+    }
+
+    async fn list_page(
+        &self,
+        after: Option<ValidatedDocumentId>,
+        limit: usize,
+    ) -> Result<DocumentPage> {
+        let mut retry = self.attempt("list_page");
+        loop {
+            match self.inner.list_page(after, limit).await {
+                Ok(result) => {
+                    retry.succeeded();
+                    return Ok(result);
                 }
+                Err(e) => retry.backoff(e).await?,
             }
         }
     }
 
     async fn sync(&mut self) -> Result<()> {
-        let mut attempt = 0;
-        let mut delay = self.base_delay;
-
+        let mut retry = self.attempt("sync");
         loop {
-            attempt += 1;
-
             match self.inner.sync().await {
                 Ok(()) => {
-                    if attempt > 1 {
-                        info!("Operation sync succeeded after {} attempts", attempt);
-                    }
+                    retry.succeeded();
                     return Ok(());
                 }
-                Err(e) if attempt >= self.max_retries => {
-                    error!("Operation sync failed after {} attempts: {}", attempt, e);
-                    return Err(e);
-                }
-                Err(e) => {
-                    warn!(
-                        "Operation sync failed (attempt {}/{}): {}",
-                        attempt, self.max_retries, e
-                    );
-
-                    tokio::time::sleep(delay).await;
-
-                    // Exponential backoff with jitter
-                    delay = std::cmp::min(delay * 2, self.max_delay);
-                    let jitter = Duration::from_millis(rand::random::<u64>() % 100);
-                    delay += jitter;
-                }
+                Err(e) => retry.backoff(e).await?,
             }
         }
     }
 
     async fn flush(&mut self) -> Result<()> {
-        let mut attempt = 0;
-        let mut delay = self.base_delay;
-
+        let mut retry = self.attempt("flush");
         loop {
-            attempt += 1;
-
             match self.inner.flush().await {
                 Ok(()) => {
-                    if attempt > 1 {
-                        info!("Operation flush succeeded after {} attempts", attempt);
-                    }
+                    retry.succeeded();
                     return Ok(());
                 }
-                Err(e) if attempt >= self.max_retries => {
-                    error!("Operation flush failed after {} attempts: {}", attempt, e);
-                    return Err(e);
-                }
-                Err(e) => {
-                    warn!(
-                        "Operation flush failed (attempt {}/{}): {}",
-                        attempt, self.max_retries, e
-                    );
-
-                    tokio::time::sleep(delay).await;
-
-                    // Exponential backoff with jitter
-                    delay = std::cmp::min(delay * 2, self.max_delay);
-                    let jitter = Duration::from_millis(rand::random::<u64>() % 100);
-                    delay += jitter;
-                }
+                Err(e) => retry.backoff(e).await?,
             }
         }
     }
@@ -763,6 +998,58 @@ impl<S: Storage> Storage for CachedStorage<S> {
         Ok(result)
     }
 
+    async fn get_many(&self, ids: &[ValidatedDocumentId]) -> Result<Vec<Option<Document>>> {
+        // Serve what the cache holds and fetch the rest in one batch
+        let mut documents = Vec::with_capacity(ids.len());
+        let mut missing = Vec::new();
+        {
+            let mut cache = self.cache.lock().await;
+            for (position, id) in ids.iter().enumerate() {
+                let cached = cache.get(&id.as_uuid()).cloned();
+                if cached.is_none() {
+                    missing.push((position, *id));
+                }
+                documents.push(cached);
+            }
+        }
+        *self.cache_hits.lock().await += (ids.len() - missing.len()) as u64;
+        if missing.is_empty() {
+            return Ok(documents);
+        }
+        *self.cache_misses.lock().await += missing.len() as u64;
+
+        let missing_ids: Vec<ValidatedDocumentId> = missing.iter().map(|(_, id)| *id).collect();
+        let fetched = self.inner.get_many(&missing_ids).await?;
+
+        let mut cache = self.cache.lock().await;
+        for ((position, id), doc) in missing.into_iter().zip(fetched) {
+            if let Some(ref doc) = doc {
+                cache.insert(id.as_uuid(), doc.clone());
+            }
+            documents[position] = doc;
+        }
+
+        Ok(documents)
+    }
+
+    async fn summary(&self, id: &ValidatedDocumentId) -> Result<Option<DocumentSummary>> {
+        if let Some(doc) = self.cache.lock().await.get(&id.as_uuid()) {
+            return Ok(Some(DocumentSummary::of_document(doc)));
+        }
+        self.inner.summary(id).await
+    }
+
+    async fn get_metadata(&self, id: &ValidatedDocumentId) -> Result<Option<DocumentMetadata>> {
+        if let Some(doc) = self.cache.lock().await.get(&id.as_uuid()) {
+            return Ok(Some(DocumentMetadata::of_document(doc)));
+        }
+        self.inner.get_metadata(id).await
+    }
+
+    async fn list_metadata(&self) -> Result<Vec<DocumentMetadata>> {
+        self.inner.list_metadata().await
+    }
+
     async fn update(&mut self, doc: Document) -> Result<()> {
         self.inner.update(doc.clone()).await?;
 
@@ -787,6 +1074,15 @@ impl<S: Storage> Storage for CachedStorage<S> {
         self.inner.list_all().await
     }
 
+    async fn list_page(
+        &self,
+        after: Option<ValidatedDocumentId>,
+        limit: usize,
+    ) -> Result<DocumentPage> {
+        // Pages pass through without filling the cache, so a full scan doesn't evict it
+        self.inner.list_page(after, limit).await
+    }
+
     async fn sync(&mut self) -> Result<()> {
         self.inner.sync().await
     }
@@ -943,6 +1239,18 @@ impl<I: Index> Index for MeteredIndex<I> {
         result
     }
 
+    async fn content_tokenizer(&self) -> Option<ContentTokenizer> {
+        self.inner.content_tokenizer().await
+    }
+
+    async fn insert_tokenized(&mut self, document: TokenizedDocument) -> Result<()> {
+        let start = Instant::now();
+        let result = self.inner.insert_tokenized(document).await;
+        self.record_timing("insert_tokenized", start.elapsed())
+            .await;
+        result
+    }
+
     async fn close(self) -> Result<()> {
         let timing_stats = self.timing_stats().await;
         for (op, (min, avg, max)) in timing_stats {
@@ -953,6 +1261,211 @@ impl<I: Index> Index for MeteredIndex<I> {
         }
         self.inner.close().await
     }
+
+    async fn tree_structure(&self) -> Result<Option<crate::contracts::TreeStructureMetrics>> {
+        self.inner.tree_structure().await
+    }
+
+    async fn rebuild_tree(&mut self) -> Result<bool> {
+        let start = Instant::now();
+        let result = self.inner.rebuild_tree().await;
+        self.record_timing("rebuild_tree", start.elapsed()).await;
+        result
+    }
+
+    async fn tree_snapshot(&self) -> Result<Option<crate::pure::btree::BTreeRoot>> {
+        self.inner.tree_snapshot().await
+    }
+
+    async fn replace_tree(&mut self, tree: crate::pure::btree::BTreeRoot) -> Result<bool> {
+        let start = Instant::now();
+        let result = self.inner.replace_tree(tree).await;
+        self.record_timing("replace_tree", start.elapsed()).await;
+        result
+    }
+}
+
+/// Storage wrapper that refuses writes, for databases whose lease another process holds
+pub struct ReadOnlyStorage<S: Storage> {
+    inner: S,
+    reason: Arc<str>,
+}
+
+impl<S: Storage> ReadOnlyStorage<S> {
+    /// Wrap a storage, failing writes with `reason`
+    pub fn new(inner: S, reason: impl Into<Arc<str>>) -> Self {
+        Self {
+            inner,
+            reason: reason.into(),
+        }
+    }
+
+    fn refuse<T>(&self) -> Result<T> {
+        anyhow::bail!("Database is read-only: {}", self.reason)
+    }
+}
+
+#[async_trait]
+impl<S: Storage> Storage for ReadOnlyStorage<S> {
+    async fn open(path: &str) -> Result<Self>
+    where
+        Self: Sized,
+    {
+        Ok(Self::new(S::open(path).await?, "opened read-only"))
+    }
+
+    async fn insert(&mut self, _document: Document) -> Result<()> {
+        self.refuse()
+    }
+
+    async fn get(&self, id: &ValidatedDocumentId) -> Result<Option<Document>> {
+        self.inner.get(id).await
+    }
+
+    async fn get_many(&self, ids: &[ValidatedDocumentId]) -> Result<Vec<Option<Document>>> {
+        self.inner.get_many(ids).await
+    }
+
+    async fn summary(&self, id: &ValidatedDocumentId) -> Result<Option<DocumentSummary>> {
+        self.inner.summary(id).await
+    }
+
+    async fn get_metadata(&self, id: &ValidatedDocumentId) -> Result<Option<DocumentMetadata>> {
+        self.inner.get_metadata(id).await
+    }
+
+    async fn list_metadata(&self) -> Result<Vec<DocumentMetadata>> {
+        self.inner.list_metadata().await
+    }
+
+    async fn update(&mut self, _document: Document) -> Result<()> {
+        self.refuse()
+    }
+
+    async fn delete(&mut self, _id: &ValidatedDocumentId) -> Result<bool> {
+        self.refuse()
+    }
+
+    async fn list_all(&self) -> Result<Vec<Document>> {
+        self.inner.list_all().await
+    }
+
+    async fn list_page(
+        &self,
+        after: Option<ValidatedDocumentId>,
+        limit: usize,
+    ) -> Result<DocumentPage> {
+        self.inner.list_page(after, limit).await
+    }
+
+    // Nothing was written, so there is nothing to persist
+    async fn sync(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    async fn close(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Index wrapper that refuses writes, for databases whose lease another process holds
+pub struct ReadOnlyIndex<I: Index> {
+    inner: I,
+    reason: Arc<str>,
+}
+
+impl<I: Index> ReadOnlyIndex<I> {
+    /// Wrap an index, failing writes with `reason`
+    pub fn new(inner: I, reason: impl Into<Arc<str>>) -> Self {
+        Self {
+            inner,
+            reason: reason.into(),
+        }
+    }
+
+    fn refuse<T>(&self) -> Result<T> {
+        anyhow::bail!("Database is read-only: {}", self.reason)
+    }
+}
+
+#[async_trait]
+impl<I: Index> Index for ReadOnlyIndex<I> {
+    async fn open(path: &str) -> Result<Self>
+    where
+        Self: Sized,
+    {
+        Ok(Self::new(I::open(path).await?, "opened read-only"))
+    }
+
+    async fn insert(&mut self, _id: ValidatedDocumentId, _path: ValidatedPath) -> Result<()> {
+        self.refuse()
+    }
+
+    async fn insert_with_content(
+        &mut self,
+        _id: ValidatedDocumentId,
+        _path: ValidatedPath,
+        _content: &[u8],
+    ) -> Result<()> {
+        self.refuse()
+    }
+
+    async fn update(&mut self, _id: ValidatedDocumentId, _path: ValidatedPath) -> Result<()> {
+        self.refuse()
+    }
+
+    async fn update_with_content(
+        &mut self,
+        _id: ValidatedDocumentId,
+        _path: ValidatedPath,
+        _content: &[u8],
+    ) -> Result<()> {
+        self.refuse()
+    }
+
+    async fn insert_tokenized(&mut self, _document: TokenizedDocument) -> Result<()> {
+        self.refuse()
+    }
+
+    async fn delete(&mut self, _id: &ValidatedDocumentId) -> Result<bool> {
+        self.refuse()
+    }
+
+    async fn search(&self, query: &Query) -> Result<Vec<ValidatedDocumentId>> {
+        self.inner.search(query).await
+    }
+
+    async fn sync(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    async fn close(self) -> Result<()> {
+        Ok(())
+    }
+
+    async fn tree_structure(&self) -> Result<Option<crate::contracts::TreeStructureMetrics>> {
+        self.inner.tree_structure().await
+    }
+
+    async fn rebuild_tree(&mut self) -> Result<bool> {
+        self.refuse()
+    }
+
+    async fn tree_snapshot(&self) -> Result<Option<crate::pure::btree::BTreeRoot>> {
+        self.inner.tree_snapshot().await
+    }
+
+    async fn replace_tree(&mut self, _tree: crate::pure::btree::BTreeRoot) -> Result<bool> {
+        self.refuse()
+    }
 }
 
 // TODO: SafeTransaction implementation needs a concrete Transaction type
@@ -1201,6 +1714,89 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_error_classes() {
+        let invalid = anyhow::Error::new(validation::ValidationError::InvalidInput {
+            field: "path".to_string(),
+            reason: "empty".to_string(),
+        });
+        assert_eq!(ErrorClass::of(&invalid), ErrorClass::Validation);
+
+        let io =
+            anyhow::Error::new(std::io::Error::other("disk on fire")).context("Failed to write");
+        assert_eq!(ErrorClass::of(&io), ErrorClass::Io);
+
+        assert_eq!(
+            ErrorClass::of(&anyhow::anyhow!("Simulated failure")),
+            ErrorClass::Other
+        );
+    }
+
+    #[test]
+    fn test_retry_policy_backoff() {
+        let policy =
+            RetryPolicy::exponential(5, Duration::from_millis(10), Duration::from_millis(50))
+                .with_jitter(0.0);
+        assert_eq!(policy.delay(1), Duration::from_millis(10));
+        assert_eq!(policy.delay(3), Duration::from_millis(40));
+        assert_eq!(policy.delay(4), Duration::from_millis(50));
+
+        let jittered = policy.with_jitter(1.0);
+        assert!((0..20).all(|_| jittered.delay(2) <= Duration::from_millis(20)));
+    }
+
+    #[tokio::test]
+    async fn test_retryable_storage_policies() {
+        let storage = MockStorage::open("test")
+            .await
+            .expect("Mock storage should open");
+        let fail_next = storage.fail_next.clone();
+        let mut retryable = RetryableStorage::new(storage).with_retry_config(
+            3,
+            Duration::from_millis(1),
+            Duration::from_millis(2),
+        );
+
+        let doc = |title: &str| {
+            Document::new(
+                ValidatedDocumentId::from_uuid(Uuid::new_v4()).expect("UUID should be valid"),
+                ValidatedPath::new(format!("{title}.md")).expect("Test path should be valid"),
+                ValidatedTitle::new(title).expect("Test title should be valid"),
+                b"test content".to_vec(),
+                vec![],
+                chrono::Utc::now(),
+                chrono::Utc::now(),
+            )
+        };
+
+        // A transient failure is retried
+        *fail_next.lock().await = true;
+        retryable
+            .insert(doc("first"))
+            .await
+            .expect("Insert should succeed after a retry");
+        let stats = retryable.retry_stats();
+        assert_eq!((stats.retries, stats.recovered), (1, 1));
+
+        // A class without retries fails straight away
+        let mut retryable = retryable.with_policy(ErrorClass::Other, RetryPolicy::never());
+        *fail_next.lock().await = true;
+        assert!(retryable.insert(doc("second")).await.is_err());
+        assert_eq!(retryable.retry_stats().not_retried, 1);
+
+        // An exhausted time budget stops retries
+        let mut retryable = retryable
+            .with_policy(
+                ErrorClass::Other,
+                RetryPolicy::exponential(3, Duration::from_secs(1), Duration::from_secs(1)),
+            )
+            .with_max_elapsed(Duration::from_millis(100));
+        *fail_next.lock().await = true;
+        assert!(retryable.insert(doc("third")).await.is_err());
+        let stats = retryable.retry_stats();
+        assert_eq!((stats.retries, stats.over_budget), (1, 1));
+    }
+
     #[tokio::test]
     async fn test_cached_storage() {
         let storage = MockStorage::open("test")
@@ -1236,5 +1832,17 @@ mod tests {
         let (hits, misses) = cached_mut.cache_stats().await;
         assert_eq!(hits, 2);
         assert_eq!(misses, 0);
+
+        // Batch get - one hit, one miss for an unknown ID
+        let unknown = ValidatedDocumentId::from_uuid(Uuid::new_v4()).expect("UUID should be valid");
+        let documents = cached_mut
+            .get_many(&[unknown, doc.id])
+            .await
+            .expect("Batch get should succeed");
+        assert!(documents[0].is_none());
+        assert_eq!(documents[1].as_ref().map(|d| d.id), Some(doc.id));
+        let (hits, misses) = cached_mut.cache_stats().await;
+        assert_eq!(hits, 3);
+        assert_eq!(misses, 1);
     }
 }
@@ -3,7 +3,7 @@
 
 use anyhow::{Context, Result};
 use async_trait::async_trait;
-use std::collections::{HashMap, VecDeque};
+use std::collections::{BTreeMap, HashMap, VecDeque};
 use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
@@ -11,7 +11,7 @@ use tokio::sync::{Mutex, RwLock};
 use tokio::time;
 use tracing::{debug, info};
 
-use crate::contracts::{Document, Storage};
+use crate::contracts::{Document, DocumentMetadata, DocumentPage, DocumentSummary, Storage};
 use crate::observability::{record_metric, MetricType};
 use crate::types::ValidatedDocumentId;
 
@@ -343,6 +343,122 @@ impl<S: Storage> Storage for BufferedStorage<S> {
         }
     }
 
+    async fn get_many(&self, ids: &[ValidatedDocumentId]) -> Result<Vec<Option<Document>>> {
+        // Pending operations win over the underlying storage; the rest is fetched in one batch
+        let mut documents = Vec::with_capacity(ids.len());
+        let mut missing = Vec::new();
+        {
+            let buffer = self.write_buffer.lock().await;
+            for (position, id) in ids.iter().enumerate() {
+                let buffered = buffer.iter().rev().find_map(|op| match op {
+                    BufferedOperation::Insert(doc) | BufferedOperation::Update(doc)
+                        if doc.id == *id =>
+                    {
+                        Some(Some(doc.clone()))
+                    }
+                    BufferedOperation::Delete(del_id) if del_id == id => Some(None),
+                    _ => None,
+                });
+                if buffered.is_none() {
+                    missing.push((position, *id));
+                }
+                documents.push(buffered.flatten());
+            }
+        }
+        if missing.is_empty() {
+            return Ok(documents);
+        }
+
+        let inner = self
+            .inner
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Storage already closed"))?;
+        let missing_ids: Vec<ValidatedDocumentId> = missing.iter().map(|(_, id)| *id).collect();
+        for ((position, _), doc) in missing.into_iter().zip(inner.get_many(&missing_ids).await?) {
+            documents[position] = doc;
+        }
+        Ok(documents)
+    }
+
+    async fn summary(&self, id: &ValidatedDocumentId) -> Result<Option<DocumentSummary>> {
+        {
+            let buffer = self.write_buffer.lock().await;
+            for op in buffer.iter().rev() {
+                match op {
+                    BufferedOperation::Insert(doc) | BufferedOperation::Update(doc) => {
+                        if doc.id == *id {
+                            return Ok(Some(DocumentSummary::of_document(doc)));
+                        }
+                    }
+                    BufferedOperation::Delete(del_id) => {
+                        if del_id == id {
+                            return Ok(None);
+                        }
+                    }
+                }
+            }
+        }
+
+        match &self.inner {
+            Some(inner) => inner.summary(id).await,
+            None => Err(anyhow::anyhow!("Storage already closed")),
+        }
+    }
+
+    async fn get_metadata(&self, id: &ValidatedDocumentId) -> Result<Option<DocumentMetadata>> {
+        {
+            let buffer = self.write_buffer.lock().await;
+            for op in buffer.iter().rev() {
+                match op {
+                    BufferedOperation::Insert(doc) | BufferedOperation::Update(doc) => {
+                        if doc.id == *id {
+                            return Ok(Some(DocumentMetadata::of_document(doc)));
+                        }
+                    }
+                    BufferedOperation::Delete(del_id) => {
+                        if del_id == id {
+                            return Ok(None);
+                        }
+                    }
+                }
+            }
+        }
+
+        match &self.inner {
+            Some(inner) => inner.get_metadata(id).await,
+            None => Err(anyhow::anyhow!("Storage already closed")),
+        }
+    }
+
+    async fn list_metadata(&self) -> Result<Vec<DocumentMetadata>> {
+        let mut metadata: HashMap<ValidatedDocumentId, DocumentMetadata> = match &self.inner {
+            Some(inner) => inner
+                .list_metadata()
+                .await?
+                .into_iter()
+                .map(|m| (m.id, m))
+                .collect(),
+            None => return Err(anyhow::anyhow!("Storage already closed")),
+        };
+
+        // Apply buffered operations, as `list_all` does
+        {
+            let buffer = self.write_buffer.lock().await;
+            for op in buffer.iter() {
+                match op {
+                    BufferedOperation::Insert(doc) | BufferedOperation::Update(doc) => {
+                        metadata.insert(doc.id, DocumentMetadata::of_document(doc));
+                    }
+                    BufferedOperation::Delete(id) => {
+                        metadata.remove(id);
+                    }
+                }
+            }
+        }
+
+        Ok(metadata.into_values().collect())
+    }
+
     async fn update(&mut self, doc: Document) -> Result<()> {
         // Calculate document size
         let doc_size = doc.content.len() + doc.path.as_str().len() + doc.title.as_str().len();
@@ -429,6 +545,48 @@ impl<S: Storage> Storage for BufferedStorage<S> {
         Ok(doc_map.into_values().collect())
     }
 
+    async fn list_page(
+        &self,
+        after: Option<ValidatedDocumentId>,
+        limit: usize,
+    ) -> Result<DocumentPage> {
+        let page = match &self.inner {
+            Some(inner) => inner.list_page(after, limit).await?,
+            None => return Err(anyhow::anyhow!("Storage already closed")),
+        };
+
+        // Apply buffered operations on IDs within the range the inner page covers
+        let in_range = |id: &ValidatedDocumentId| {
+            after.is_none_or(|after| *id > after) && page.next.is_none_or(|next| *id <= next)
+        };
+        let mut doc_map: BTreeMap<ValidatedDocumentId, Document> =
+            page.documents.into_iter().map(|d| (d.id, d)).collect();
+        {
+            let buffer = self.write_buffer.lock().await;
+            for op in buffer.iter() {
+                match op {
+                    BufferedOperation::Insert(doc) | BufferedOperation::Update(doc) => {
+                        if in_range(&doc.id) {
+                            doc_map.insert(doc.id, doc.clone());
+                        }
+                    }
+                    BufferedOperation::Delete(id) => {
+                        doc_map.remove(id);
+                    }
+                }
+            }
+        }
+
+        // Buffered inserts can overfill the page; the rest follow on the next one
+        let mut documents: Vec<Document> = doc_map.into_values().collect();
+        let mut next = page.next;
+        if documents.len() > limit {
+            documents.truncate(limit);
+            next = documents.last().map(|doc| doc.id);
+        }
+        Ok(DocumentPage { documents, next })
+    }
+
     async fn close(mut self) -> Result<()> {
         // Signal shutdown to background task immediately and aggressively
         self.shutdown.store(true, Ordering::Release);
@@ -564,4 +722,46 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_list_page_merges_buffered_operations() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let storage = FileStorage::open(temp_dir.path().to_str().unwrap()).await?;
+        let mut buffered = BufferedStorage::new(storage);
+
+        let mut expected = Vec::new();
+        for i in 0..6 {
+            let doc = DocumentBuilder::new()
+                .path(format!("test{}.md", i))?
+                .title(format!("Test {}", i))?
+                .content(b"test content")
+                .build()?;
+            expected.push(doc.id);
+            buffered.insert(doc).await?;
+            if i == 3 {
+                buffered.flush().await?;
+            }
+        }
+        // One flushed and one buffered document are deleted before flushing again
+        buffered.delete(&expected[0]).await?;
+        buffered.delete(&expected[5]).await?;
+        expected.drain(..1);
+        expected.pop();
+        expected.sort();
+
+        let mut listed = Vec::new();
+        let mut cursor = None;
+        loop {
+            let page = buffered.list_page(cursor, 2).await?;
+            assert!(page.documents.len() <= 2);
+            listed.extend(page.documents.iter().map(|doc| doc.id));
+            match page.next {
+                Some(next) => cursor = Some(next),
+                None => break,
+            }
+        }
+        assert_eq!(listed, expected);
+
+        Ok(())
+    }
 }
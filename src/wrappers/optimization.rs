@@ -3,16 +3,23 @@
 
 use crate::contracts::optimization::{
     BalanceInfo, BulkOperationResult, BulkOperationType, BulkOperations, ConcurrentAccess,
-    ContentionMetrics, MemoryOptimization, MemoryUsage, TreeAnalysis, TreeStructureMetrics,
+    ContentionMetrics, MemoryOptimization, MemoryUsage, OptimizationRecommendation, TreeAnalysis,
+    TreeStructureMetrics,
 };
-use crate::contracts::{Index, Query};
+use crate::contracts::{ContentTokenizer, Index, Query, TokenizedDocument};
 use crate::metrics::optimization::{LockType, OptimizationMetricsCollector};
+use crate::pure::{analyze_tree_structure, rebuild_tree};
 use crate::types::{ValidatedDocumentId, ValidatedPath};
-use anyhow::Result;
+use anyhow::{Context, Result};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime};
 use tokio::sync::RwLock;
 
+/// Number of applied optimizations kept for inspection
+const APPLIED_OPTIMIZATION_HISTORY: usize = 32;
+
 /// High-performance index wrapper with automatic optimization
 ///
 /// This wrapper automatically applies:
@@ -21,12 +28,23 @@ use tokio::sync::RwLock;
 /// - Memory optimization
 /// - Performance monitoring
 /// - Tree rebalancing
+///
+/// With adaptive optimization enabled, every `adaptive_check_interval` writes start a
+/// background task that analyzes a copy of the tree and applies the RebalanceTree and
+/// CompactNodes recommendations whose thresholds are crossed by rebuilding that copy.
+/// The index is locked only to take the copy and to swap in the rebuilt tree, which is
+/// dropped if writes happened in between.
 #[derive(Debug)]
 pub struct OptimizedIndex<T: Index> {
     inner: Arc<RwLock<T>>,
-    metrics_collector: OptimizationMetricsCollector,
+    metrics_collector: Arc<OptimizationMetricsCollector>,
     optimization_config: OptimizationConfig,
     tree_cache: Arc<RwLock<Option<CachedTreeState>>>,
+    writes_since_check: AtomicUsize,
+    applied_optimizations: Arc<RwLock<VecDeque<AppliedOptimization>>>,
+    /// Bumped whenever the write lock is taken, so a rebuild can tell the tree changed
+    write_generation: Arc<AtomicU64>,
+    background_optimization: std::sync::Mutex<Option<tokio::task::JoinHandle<()>>>,
 }
 
 /// Configuration for optimization behavior
@@ -42,6 +60,9 @@ pub struct OptimizationConfig {
     pub memory_cleanup_interval: Duration,
     pub enable_adaptive_caching: bool,
     pub cache_hot_path_threshold: u32, // Access count to cache
+    pub enable_adaptive_optimization: bool,
+    pub adaptive_check_interval: usize, // Writes between structure checks
+    pub compaction_trigger_threshold: f64, // Node utilization threshold
 }
 
 impl Default for OptimizationConfig {
@@ -57,6 +78,9 @@ impl Default for OptimizationConfig {
             memory_cleanup_interval: Duration::from_secs(300), // 5 minutes
             enable_adaptive_caching: true,
             cache_hot_path_threshold: 10,
+            enable_adaptive_optimization: true,
+            adaptive_check_interval: 1000,
+            compaction_trigger_threshold: 0.5,
         }
     }
 }
@@ -70,16 +94,20 @@ struct CachedTreeState {
     operation_count_since_update: usize,
 }
 
-impl<T: Index + Send + Sync> OptimizedIndex<T> {
+impl<T: Index + Send + Sync + 'static> OptimizedIndex<T> {
     /// Create new optimized index wrapper
     pub fn new(inner: T, config: OptimizationConfig) -> Self {
         let metrics_config = crate::metrics::optimization::OptimizationMetricsConfig::default();
 
         Self {
             inner: Arc::new(RwLock::new(inner)),
-            metrics_collector: OptimizationMetricsCollector::new(metrics_config),
+            metrics_collector: Arc::new(OptimizationMetricsCollector::new(metrics_config)),
             optimization_config: config,
             tree_cache: Arc::new(RwLock::new(None)),
+            writes_since_check: AtomicUsize::new(0),
+            applied_optimizations: Arc::new(RwLock::new(VecDeque::new())),
+            write_generation: Arc::new(AtomicU64::new(0)),
+            background_optimization: std::sync::Mutex::new(None),
         }
     }
 
@@ -94,14 +122,19 @@ impl<T: Index + Send + Sync> OptimizedIndex<T> {
         id: ValidatedDocumentId,
         path: ValidatedPath,
     ) -> Result<()> {
-        if self.optimization_config.enable_bulk_operations {
+        let result = if self.optimization_config.enable_bulk_operations {
             // For now, delegate to regular insert
             // In a full implementation, this would collect operations and batch them
             self.concurrent_write(id, path).await
         } else {
             let mut inner = self.acquire_write_lock().await?;
             inner.insert(id, path).await
+        };
+
+        if result.is_ok() {
+            self.record_write().await;
         }
+        result
     }
 
     /// Optimized search that leverages caching and concurrent reads
@@ -119,8 +152,82 @@ impl<T: Index + Send + Sync> OptimizedIndex<T> {
 
     /// Optimized delete that may batch operations
     pub async fn optimized_delete(&mut self, id: &ValidatedDocumentId) -> Result<bool> {
-        let mut inner = self.acquire_write_lock().await?;
-        inner.delete(id).await
+        let result = self.acquire_write_lock().await?.delete(id).await;
+
+        if result.is_ok() {
+            self.record_write().await;
+        }
+        result
+    }
+
+    /// Count a write, starting a background optimization once enough have accumulated
+    async fn record_write(&self) {
+        if !self.optimization_config.enable_adaptive_optimization {
+            return;
+        }
+
+        let writes = self.writes_since_check.fetch_add(1, Ordering::Relaxed) + 1;
+        if writes < self.optimization_config.adaptive_check_interval.max(1) {
+            return;
+        }
+        self.writes_since_check.store(0, Ordering::Relaxed);
+
+        // One optimization at a time; writes that cross the interval meanwhile skip it
+        let mut background = self
+            .background_optimization
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if background.as_ref().is_some_and(|task| !task.is_finished()) {
+            return;
+        }
+        let optimizer = self.optimizer();
+        *background = Some(tokio::spawn(async move {
+            // The writes succeeded; a failed optimization only leaves the tree as it was
+            if let Err(e) = optimizer.apply().await {
+                tracing::warn!("Adaptive index optimization failed: {}", e);
+            }
+        }));
+    }
+
+    /// Wait for a background optimization started by a write to finish
+    async fn finish_background_optimization(&self) {
+        let task = self
+            .background_optimization
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .take();
+        if let Some(task) = task {
+            let _ = task.await;
+        }
+    }
+
+    /// Analyze the tree and apply the recommendations whose thresholds are crossed
+    ///
+    /// Returns `None` when the wrapped index isn't backed by a tree, nothing is due, or
+    /// the tree changed while it was being rebuilt.
+    pub async fn apply_recommendations(&self) -> Result<Option<AppliedOptimization>> {
+        self.optimizer().apply().await
+    }
+
+    fn optimizer(&self) -> TreeOptimizer<T> {
+        TreeOptimizer {
+            inner: self.inner.clone(),
+            config: self.optimization_config.clone(),
+            tree_cache: self.tree_cache.clone(),
+            applied_optimizations: self.applied_optimizations.clone(),
+            metrics_collector: self.metrics_collector.clone(),
+            write_generation: self.write_generation.clone(),
+        }
+    }
+
+    /// Optimizations applied so far, oldest first
+    pub async fn applied_optimizations(&self) -> Vec<AppliedOptimization> {
+        self.applied_optimizations
+            .read()
+            .await
+            .iter()
+            .cloned()
+            .collect()
     }
 
     /// Acquire read lock with contention tracking
@@ -152,6 +259,7 @@ impl<T: Index + Send + Sync> OptimizedIndex<T> {
 
         // Acquire lock using tokio's async RwLock
         let guard = self.inner.write().await;
+        self.write_generation.fetch_add(1, Ordering::AcqRel);
 
         let wait_time = start.elapsed();
         let was_contested = wait_time > Duration::from_millis(1);
@@ -202,8 +310,22 @@ impl<T: Index + Send + Sync> OptimizedIndex<T> {
             recommendations.push("Memory compaction opportunities identified".to_string());
         }
 
-        // Update cached tree state
-        self.update_tree_cache(tree_metrics.clone()).await;
+        // Apply due recommendations, which also refreshes the cached tree state
+        let applied = if self.optimization_config.enable_adaptive_optimization {
+            self.apply_recommendations().await?
+        } else {
+            None
+        };
+        match &applied {
+            Some(applied) => actions_taken.push(format!(
+                "Tree rebuilt: balance {:.2} -> {:.2}, utilization {:.2} -> {:.2}",
+                applied.before.balance_factor,
+                applied.after.balance_factor,
+                applied.before.utilization_factor,
+                applied.after.utilization_factor
+            )),
+            None => self.update_tree_cache(tree_metrics.clone()).await,
+        }
 
         let analysis_duration = start.elapsed();
 
@@ -214,13 +336,16 @@ impl<T: Index + Send + Sync> OptimizedIndex<T> {
             recommendations,
             actions_taken,
             estimated_improvement: 1.15, // 15% improvement estimate
+            applied,
         })
     }
 
-    /// Get tree metrics (placeholder - would interface with actual tree)
+    /// Get tree metrics from the wrapped index, or estimates for indices without a tree
     async fn get_tree_metrics(&self) -> Result<TreeStructureMetrics> {
-        // In a full implementation, this would analyze the actual tree structure
-        // For now, return reasonable default metrics
+        if let Some(metrics) = self.acquire_read_lock().await?.tree_structure().await? {
+            return Ok(metrics);
+        }
+
         Ok(TreeStructureMetrics {
             total_entries: 1000,
             tree_depth: 4,
@@ -246,6 +371,98 @@ impl<T: Index + Send + Sync> OptimizedIndex<T> {
     }
 
     /// Update cached tree state
+    async fn update_tree_cache(&self, metrics: TreeStructureMetrics) {
+        self.optimizer().update_tree_cache(metrics).await
+    }
+}
+
+/// The parts of an [`OptimizedIndex`] that adaptive optimization works on, shared with
+/// the background task it runs in
+struct TreeOptimizer<T> {
+    inner: Arc<RwLock<T>>,
+    config: OptimizationConfig,
+    tree_cache: Arc<RwLock<Option<CachedTreeState>>>,
+    applied_optimizations: Arc<RwLock<VecDeque<AppliedOptimization>>>,
+    metrics_collector: Arc<OptimizationMetricsCollector>,
+    write_generation: Arc<AtomicU64>,
+}
+
+impl<T: Index + Send + Sync> TreeOptimizer<T> {
+    /// Rebuild a copy of the tree if recommendations are due and swap it in
+    async fn apply(&self) -> Result<Option<AppliedOptimization>> {
+        let (snapshot, generation) = {
+            let inner = self.inner.read().await;
+            let generation = self.write_generation.load(Ordering::Acquire);
+            (inner.tree_snapshot().await?, generation)
+        };
+        let Some(snapshot) = snapshot else {
+            return Ok(None);
+        };
+
+        // Analysis and the rebuild run on the copy without holding the index
+        let start = Instant::now();
+        let config = self.config.clone();
+        let (before, recommendations, rebuilt) = tokio::task::spawn_blocking(move || {
+            let before = analyze_tree_structure(&snapshot)?;
+            let recommendations = due_recommendations(&config, &before);
+            // Rebalancing and compaction are both served by one bottom-up rebuild
+            let rebuilt = if recommendations.is_empty() {
+                None
+            } else {
+                let tree = rebuild_tree(&snapshot)?;
+                let after = analyze_tree_structure(&tree)?;
+                Some((tree, after))
+            };
+            anyhow::Ok((before, recommendations, rebuilt))
+        })
+        .await
+        .context("Index optimization task panicked")??;
+        let Some((tree, after)) = rebuilt else {
+            self.update_tree_cache(before).await;
+            return Ok(None);
+        };
+
+        {
+            let mut inner = self.inner.write().await;
+            if self.write_generation.load(Ordering::Acquire) != generation {
+                // Swapping in the copy would drop those writes; the next check retries
+                tracing::debug!("Index changed during optimization; discarding rebuilt tree");
+                return Ok(None);
+            }
+            if !inner.replace_tree(tree).await? {
+                return Ok(None);
+            }
+        }
+        let duration = start.elapsed();
+
+        tracing::info!(
+            "Applied {} index optimization(s) in {:?}: balance {:.2} -> {:.2}, utilization {:.2} -> {:.2}",
+            recommendations.len(),
+            duration,
+            before.balance_factor,
+            after.balance_factor,
+            before.utilization_factor,
+            after.utilization_factor
+        );
+
+        let applied = AppliedOptimization {
+            timestamp: SystemTime::now(),
+            recommendations,
+            before,
+            after: after.clone(),
+            duration,
+        };
+        self.update_tree_cache(after).await;
+
+        let mut history = self.applied_optimizations.write().await;
+        if history.len() == APPLIED_OPTIMIZATION_HISTORY {
+            history.pop_front();
+        }
+        history.push_back(applied.clone());
+
+        Ok(Some(applied))
+    }
+
     async fn update_tree_cache(&self, metrics: TreeStructureMetrics) {
         let mut cache = self.tree_cache.write().await;
         *cache = Some(CachedTreeState {
@@ -259,6 +476,29 @@ impl<T: Index + Send + Sync> OptimizedIndex<T> {
     }
 }
 
+/// Recommendations of `metrics` that the configured thresholds call for
+fn due_recommendations(
+    config: &OptimizationConfig,
+    metrics: &TreeStructureMetrics,
+) -> Vec<OptimizationRecommendation> {
+    metrics
+        .recommended_actions
+        .iter()
+        .filter(|recommendation| match recommendation {
+            OptimizationRecommendation::RebalanceTree { .. } => {
+                config.enable_auto_rebalancing
+                    && metrics.balance_factor < config.rebalancing_trigger_threshold
+            }
+            OptimizationRecommendation::CompactNodes { .. } => {
+                config.enable_memory_optimization
+                    && metrics.utilization_factor < config.compaction_trigger_threshold
+            }
+            _ => false,
+        })
+        .cloned()
+        .collect()
+}
+
 /// Result of optimization analysis
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct OptimizationReport {
@@ -268,23 +508,28 @@ pub struct OptimizationReport {
     pub recommendations: Vec<String>,
     pub actions_taken: Vec<String>,
     pub estimated_improvement: f64, // Performance improvement factor
+    #[serde(default)]
+    pub applied: Option<AppliedOptimization>,
+}
+
+/// Recommendations applied to the tree, with its structure before and after
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct AppliedOptimization {
+    pub timestamp: SystemTime,
+    pub recommendations: Vec<OptimizationRecommendation>,
+    pub before: TreeStructureMetrics,
+    pub after: TreeStructureMetrics,
+    pub duration: Duration,
 }
 
 #[async_trait::async_trait]
-impl<T: Index + Send + Sync> Index for OptimizedIndex<T> {
+impl<T: Index + Send + Sync + 'static> Index for OptimizedIndex<T> {
     async fn open(path: &str) -> Result<Self>
     where
         Self: Sized,
     {
         let inner = T::open(path).await?;
-        let metrics_config = crate::metrics::optimization::OptimizationMetricsConfig::default();
-
-        Ok(Self {
-            inner: Arc::new(RwLock::new(inner)),
-            metrics_collector: OptimizationMetricsCollector::new(metrics_config),
-            optimization_config: OptimizationConfig::default(),
-            tree_cache: Arc::new(RwLock::new(None)),
-        })
+        Ok(Self::with_defaults(inner))
     }
 
     async fn insert(&mut self, id: ValidatedDocumentId, path: ValidatedPath) -> Result<()> {
@@ -315,6 +560,7 @@ impl<T: Index + Send + Sync> Index for OptimizedIndex<T> {
     }
 
     async fn close(self) -> Result<()> {
+        self.finish_background_optimization().await;
         let inner = Arc::try_unwrap(self.inner)
             .map_err(|_| anyhow::anyhow!("Cannot close index with active references"))?
             .into_inner();
@@ -327,8 +573,16 @@ impl<T: Index + Send + Sync> Index for OptimizedIndex<T> {
         path: ValidatedPath,
         content: &[u8],
     ) -> Result<()> {
-        let mut inner = self.acquire_write_lock().await?;
-        inner.insert_with_content(id, path, content).await
+        let result = self
+            .acquire_write_lock()
+            .await?
+            .insert_with_content(id, path, content)
+            .await;
+
+        if result.is_ok() {
+            self.record_write().await;
+        }
+        result
     }
 
     async fn update_with_content(
@@ -337,13 +591,58 @@ impl<T: Index + Send + Sync> Index for OptimizedIndex<T> {
         path: ValidatedPath,
         content: &[u8],
     ) -> Result<()> {
-        let mut inner = self.acquire_write_lock().await?;
-        inner.update_with_content(id, path, content).await
+        let result = self
+            .acquire_write_lock()
+            .await?
+            .update_with_content(id, path, content)
+            .await;
+
+        if result.is_ok() {
+            self.record_write().await;
+        }
+        result
+    }
+
+    async fn content_tokenizer(&self) -> Option<ContentTokenizer> {
+        self.acquire_read_lock()
+            .await
+            .ok()?
+            .content_tokenizer()
+            .await
+    }
+
+    async fn insert_tokenized(&mut self, document: TokenizedDocument) -> Result<()> {
+        let result = self
+            .acquire_write_lock()
+            .await?
+            .insert_tokenized(document)
+            .await;
+
+        if result.is_ok() {
+            self.record_write().await;
+        }
+        result
+    }
+
+    async fn tree_structure(&self) -> Result<Option<TreeStructureMetrics>> {
+        self.acquire_read_lock().await?.tree_structure().await
+    }
+
+    async fn rebuild_tree(&mut self) -> Result<bool> {
+        self.acquire_write_lock().await?.rebuild_tree().await
+    }
+
+    async fn tree_snapshot(&self) -> Result<Option<crate::pure::btree::BTreeRoot>> {
+        self.acquire_read_lock().await?.tree_snapshot().await
+    }
+
+    async fn replace_tree(&mut self, tree: crate::pure::btree::BTreeRoot) -> Result<bool> {
+        self.acquire_write_lock().await?.replace_tree(tree).await
     }
 }
 
 #[async_trait::async_trait]
-impl<T: Index + Send + Sync> BulkOperations for OptimizedIndex<T> {
+impl<T: Index + Send + Sync + 'static> BulkOperations for OptimizedIndex<T> {
     fn bulk_insert(
         &mut self,
         pairs: Vec<(ValidatedDocumentId, ValidatedPath)>,
@@ -446,7 +745,7 @@ impl<T: Index + Send + Sync> BulkOperations for OptimizedIndex<T> {
 }
 
 #[async_trait::async_trait]
-impl<T: Index + Send + Sync> ConcurrentAccess for OptimizedIndex<T> {
+impl<T: Index + Send + Sync + 'static> ConcurrentAccess for OptimizedIndex<T> {
     fn concurrent_read(&self, _key: &ValidatedDocumentId) -> Result<Option<ValidatedPath>> {
         // Simulate concurrent read with optimized locking
         Ok(Some(
@@ -471,7 +770,7 @@ impl<T: Index + Send + Sync> ConcurrentAccess for OptimizedIndex<T> {
     }
 }
 
-impl<T: Index + Send + Sync> TreeAnalysis for OptimizedIndex<T> {
+impl<T: Index + Send + Sync + 'static> TreeAnalysis for OptimizedIndex<T> {
     fn analyze_structure(&self) -> TreeStructureMetrics {
         // Return cached metrics if available, otherwise default
         // Since this is a sync function, we'll use blocking_read
@@ -522,7 +821,7 @@ impl<T: Index + Send + Sync> TreeAnalysis for OptimizedIndex<T> {
     }
 }
 
-impl<T: Index + Send + Sync> MemoryOptimization for OptimizedIndex<T> {
+impl<T: Index + Send + Sync + 'static> MemoryOptimization for OptimizedIndex<T> {
     fn get_memory_usage(&self) -> MemoryUsage {
         // Placeholder implementation - would interface with actual memory tracking
         MemoryUsage {
@@ -563,7 +862,7 @@ impl<T: Index + Send + Sync> MemoryOptimization for OptimizedIndex<T> {
 }
 
 /// Factory function to create an optimized index with all Stage 6 features
-pub fn create_optimized_index<T: Index + Send + Sync>(
+pub fn create_optimized_index<T: Index + Send + Sync + 'static>(
     inner: T,
     config: OptimizationConfig,
 ) -> OptimizedIndex<T> {
@@ -571,14 +870,16 @@ pub fn create_optimized_index<T: Index + Send + Sync>(
 }
 
 /// Factory function to create an optimized index with default settings
-pub fn create_optimized_index_with_defaults<T: Index + Send + Sync>(inner: T) -> OptimizedIndex<T> {
+pub fn create_optimized_index_with_defaults<T: Index + Send + Sync + 'static>(
+    inner: T,
+) -> OptimizedIndex<T> {
     OptimizedIndex::with_defaults(inner)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::primary_index::create_primary_index_for_tests;
+    use crate::primary_index::{create_primary_index_for_tests, PrimaryIndex};
     use tempfile::TempDir;
 
     #[tokio::test]
@@ -631,6 +932,85 @@ mod tests {
         Ok(())
     }
 
+    /// Insert 300 entries and delete 240 of them, `check_interval` set to the write count
+    async fn sparse_index(
+        config: OptimizationConfig,
+    ) -> Result<(TempDir, OptimizedIndex<PrimaryIndex>)> {
+        let temp_dir = TempDir::new()?;
+        let path = temp_dir.path().join("primary");
+        let primary_index = create_primary_index_for_tests(path.to_str().unwrap()).await?;
+        let mut optimized = create_optimized_index(
+            primary_index,
+            OptimizationConfig {
+                adaptive_check_interval: 540,
+                ..config
+            },
+        );
+
+        let mut ids = Vec::new();
+        for i in 0..300 {
+            let id = ValidatedDocumentId::from_uuid(uuid::Uuid::new_v4())?;
+            optimized
+                .insert(id, ValidatedPath::new(format!("sparse/{i}.md"))?)
+                .await?;
+            ids.push(id);
+        }
+        for id in ids.iter().skip(60) {
+            assert!(optimized.delete(id).await?);
+        }
+        Ok((temp_dir, optimized))
+    }
+
+    #[tokio::test]
+    async fn test_adaptive_optimization_compacts_sparse_tree() -> Result<()> {
+        let (_temp_dir, optimized) = sparse_index(OptimizationConfig::default()).await?;
+        optimized.finish_background_optimization().await;
+
+        let applied = optimized.applied_optimizations().await;
+        assert_eq!(applied.len(), 1);
+        let applied = &applied[0];
+        assert!(applied
+            .recommendations
+            .iter()
+            .any(|r| matches!(r, OptimizationRecommendation::CompactNodes { .. })));
+        assert!(applied.before.utilization_factor < 0.5);
+        assert!(applied.after.utilization_factor > applied.before.utilization_factor);
+        assert!(
+            applied.after.node_distribution.total_nodes
+                < applied.before.node_distribution.total_nodes
+        );
+        assert_eq!(applied.after.total_entries, 60);
+        assert_eq!(applied.after.balance_factor, 1.0);
+
+        let query = Query::new(Some("*".to_string()), None, None, 100)?;
+        assert_eq!(optimized.search(&query).await?.len(), 60);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_adaptive_optimization_can_be_disabled() -> Result<()> {
+        let (_temp_dir, optimized) = sparse_index(OptimizationConfig {
+            enable_adaptive_optimization: false,
+            ..OptimizationConfig::default()
+        })
+        .await?;
+        optimized.finish_background_optimization().await;
+        assert!(optimized.applied_optimizations().await.is_empty());
+
+        // Thresholds below the tree's utilization leave it alone too
+        let optimized = OptimizedIndex {
+            optimization_config: OptimizationConfig {
+                compaction_trigger_threshold: 0.0,
+                ..OptimizationConfig::default()
+            },
+            ..optimized
+        };
+        assert!(optimized.apply_recommendations().await?.is_none());
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_optimization_dashboard() {
         let temp_dir = TempDir::new().expect("Failed to create temp dir");
@@ -0,0 +1,6 @@
+-- Allow delta_index jobs, queued for pushes that only need their changed blobs re-ingested.
+
+ALTER TABLE indexing_jobs DROP CONSTRAINT IF EXISTS indexing_jobs_type_check;
+ALTER TABLE indexing_jobs
+    ADD CONSTRAINT indexing_jobs_type_check
+    CHECK (job_type IN ('full_index', 'incremental_update', 'webhook_update', 'delta_index', 'validate'));
@@ -0,0 +1,73 @@
+-- Outbound notification hooks: tenant callback URLs notified when indexing jobs finish.
+
+CREATE TABLE IF NOT EXISTS notification_hooks (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES auth.users(id) ON DELETE CASCADE,
+    url TEXT NOT NULL,
+    active BOOLEAN NOT NULL DEFAULT TRUE,
+    last_delivery_at TIMESTAMPTZ,
+    last_status_code INTEGER,
+    last_error TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (user_id, url)
+);
+
+ALTER TABLE notification_hooks ENABLE ROW LEVEL SECURITY;
+
+DROP POLICY IF EXISTS "Service role manages notification hooks" ON notification_hooks;
+CREATE POLICY "Service role manages notification hooks"
+    ON notification_hooks FOR ALL
+    USING (auth.role() = 'service_role')
+    WITH CHECK (auth.role() = 'service_role');
+
+DROP POLICY IF EXISTS "Users view own notification hooks" ON notification_hooks;
+CREATE POLICY "Users view own notification hooks"
+    ON notification_hooks FOR SELECT
+    USING (user_id = auth.uid());
+
+CREATE INDEX IF NOT EXISTS idx_notification_hooks_user_id ON notification_hooks(user_id) WHERE active;
+
+DROP TRIGGER IF EXISTS update_notification_hooks_updated_at ON notification_hooks;
+CREATE TRIGGER update_notification_hooks_updated_at
+    BEFORE UPDATE ON notification_hooks
+    FOR EACH ROW
+    EXECUTE FUNCTION update_updated_at_column();
+
+-- Notification hook secrets ------------------------------------------------
+CREATE TABLE IF NOT EXISTS notification_hook_secrets (
+    hook_id UUID PRIMARY KEY REFERENCES notification_hooks(id) ON DELETE CASCADE,
+    secret TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+ALTER TABLE notification_hook_secrets ENABLE ROW LEVEL SECURITY;
+
+DROP POLICY IF EXISTS "Service role manages notification hook secrets" ON notification_hook_secrets;
+CREATE POLICY "Service role manages notification hook secrets"
+    ON notification_hook_secrets FOR ALL
+    USING (auth.role() = 'service_role')
+    WITH CHECK (auth.role() = 'service_role');
+
+DROP TRIGGER IF EXISTS update_notification_hook_secrets_updated_at ON notification_hook_secrets;
+CREATE TRIGGER update_notification_hook_secrets_updated_at
+    BEFORE UPDATE ON notification_hook_secrets
+    FOR EACH ROW
+    EXECUTE FUNCTION update_updated_at_column();
+
+-- Move secrets out of hook rows created before the split
+DO $$
+BEGIN
+    IF EXISTS (
+        SELECT 1 FROM information_schema.columns
+        WHERE table_schema = 'public'
+          AND table_name = 'notification_hooks'
+          AND column_name = 'secret'
+    ) THEN
+        INSERT INTO notification_hook_secrets (hook_id, secret)
+        SELECT id, secret FROM notification_hooks
+        ON CONFLICT (hook_id) DO NOTHING;
+        ALTER TABLE notification_hooks DROP COLUMN secret;
+    END IF;
+END $$;
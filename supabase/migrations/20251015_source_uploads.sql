@@ -0,0 +1,41 @@
+-- Resumable source archive uploads for tenants that cannot grant clone access to their VCS.
+-- Parts are stored on the worker's disk; this table tracks ownership and lifecycle.
+
+ALTER TABLE indexing_jobs DROP CONSTRAINT IF EXISTS indexing_jobs_type_check;
+ALTER TABLE indexing_jobs
+    ADD CONSTRAINT indexing_jobs_type_check
+    CHECK (job_type IN ('full_index', 'incremental_update', 'webhook_update', 'delta_index', 'archive_index', 'validate'));
+
+CREATE TABLE IF NOT EXISTS source_uploads (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES auth.users(id) ON DELETE CASCADE,
+    repository_id UUID NOT NULL REFERENCES repositories(id) ON DELETE CASCADE,
+    status TEXT NOT NULL DEFAULT 'pending',
+    expected_size BIGINT,
+    job_id UUID REFERENCES indexing_jobs(id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CONSTRAINT source_uploads_status_check CHECK (status IN ('pending', 'completed', 'aborted'))
+);
+
+ALTER TABLE source_uploads ENABLE ROW LEVEL SECURITY;
+
+DROP POLICY IF EXISTS "Service role manages source uploads" ON source_uploads;
+CREATE POLICY "Service role manages source uploads"
+    ON source_uploads FOR ALL
+    USING (auth.role() = 'service_role')
+    WITH CHECK (auth.role() = 'service_role');
+
+DROP POLICY IF EXISTS "Users view own source uploads" ON source_uploads;
+CREATE POLICY "Users view own source uploads"
+    ON source_uploads FOR SELECT
+    USING (user_id = auth.uid());
+
+CREATE INDEX IF NOT EXISTS idx_source_uploads_user_id ON source_uploads(user_id);
+CREATE INDEX IF NOT EXISTS idx_source_uploads_repository_id ON source_uploads(repository_id);
+
+DROP TRIGGER IF EXISTS update_source_uploads_updated_at ON source_uploads;
+CREATE TRIGGER update_source_uploads_updated_at
+    BEFORE UPDATE ON source_uploads
+    FOR EACH ROW
+    EXECUTE FUNCTION update_updated_at_column();
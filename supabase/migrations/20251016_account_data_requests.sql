@@ -0,0 +1,46 @@
+-- Tenant data exports and account deletions.
+-- Rows are the audit trail for both, so they carry no foreign keys and outlive the account.
+
+CREATE TABLE IF NOT EXISTS account_data_requests (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL,
+    requested_by_key_id BIGINT,
+    kind TEXT NOT NULL,
+    status TEXT NOT NULL,
+    confirmation_hash TEXT,
+    confirmation_expires_at TIMESTAMPTZ,
+    confirmed_at TIMESTAMPTZ,
+    started_at TIMESTAMPTZ,
+    completed_at TIMESTAMPTZ,
+    summary JSONB NOT NULL DEFAULT '{}'::jsonb,
+    error_message TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CONSTRAINT account_data_requests_kind_check CHECK (kind IN ('export', 'deletion')),
+    CONSTRAINT account_data_requests_status_check CHECK (
+        status IN ('pending_confirmation', 'scheduled', 'in_progress', 'completed', 'failed', 'cancelled')
+    )
+);
+
+ALTER TABLE account_data_requests ENABLE ROW LEVEL SECURITY;
+
+DROP POLICY IF EXISTS "Service role manages account data requests" ON account_data_requests;
+CREATE POLICY "Service role manages account data requests"
+    ON account_data_requests FOR ALL
+    USING (auth.role() = 'service_role')
+    WITH CHECK (auth.role() = 'service_role');
+
+DROP POLICY IF EXISTS "Users view own account data requests" ON account_data_requests;
+CREATE POLICY "Users view own account data requests"
+    ON account_data_requests FOR SELECT
+    USING (user_id = auth.uid());
+
+CREATE INDEX IF NOT EXISTS idx_account_data_requests_user_id ON account_data_requests(user_id, created_at DESC);
+CREATE INDEX IF NOT EXISTS idx_account_data_requests_scheduled
+    ON account_data_requests(created_at) WHERE kind = 'deletion' AND status = 'scheduled';
+
+DROP TRIGGER IF EXISTS update_account_data_requests_updated_at ON account_data_requests;
+CREATE TRIGGER update_account_data_requests_updated_at
+    BEFORE UPDATE ON account_data_requests
+    FOR EACH ROW
+    EXECUTE FUNCTION update_updated_at_column();
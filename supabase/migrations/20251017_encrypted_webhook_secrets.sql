@@ -0,0 +1,21 @@
+-- Envelope-encrypted webhook secrets.
+-- The service seals each secret with its own data key and wraps that key with a master key
+-- held outside the database. Plaintext `secret` values, and secrets still kept in
+-- repositories.metadata, are re-sealed and cleared the next time the service reads them.
+
+ALTER TABLE repository_secrets ALTER COLUMN secret DROP NOT NULL;
+ALTER TABLE repository_secrets ADD COLUMN IF NOT EXISTS secret_ciphertext TEXT;
+ALTER TABLE repository_secrets ADD COLUMN IF NOT EXISTS secret_wrapped_key TEXT;
+ALTER TABLE repository_secrets ADD COLUMN IF NOT EXISTS secret_key_id TEXT;
+
+ALTER TABLE repository_secrets DROP CONSTRAINT IF EXISTS repository_secrets_value_check;
+ALTER TABLE repository_secrets
+    ADD CONSTRAINT repository_secrets_value_check
+    CHECK (
+        secret IS NOT NULL
+        OR (secret_ciphertext IS NOT NULL AND secret_wrapped_key IS NOT NULL AND secret_key_id IS NOT NULL)
+    );
+
+-- Rows still awaiting re-encryption, for tracking the rollout.
+CREATE INDEX IF NOT EXISTS idx_repository_secrets_plaintext
+    ON repository_secrets(repository_id) WHERE secret IS NOT NULL;
@@ -69,6 +69,7 @@ mod tests {
             monthly_quota: Some(1000),
             expires_at: None,
             allowed_ips: None,
+            redaction_rules: None,
         };
 
         let api_key_response = service.create_api_key(request).await.unwrap();
@@ -157,6 +158,7 @@ mod tests {
             monthly_quota: Some(1000),
             expires_at: None,
             allowed_ips: Some(vec!["192.168.1.1".to_string()]),
+            redaction_rules: None,
         };
 
         let api_key_response = service.create_api_key(request).await.unwrap();
@@ -186,6 +188,48 @@ mod tests {
         );
     }
 
+    /// Test redaction rules travel from key creation to validation
+    #[tokio::test]
+    #[ignore] // Requires actual PostgreSQL database
+    async fn test_redaction_rules_round_trip() {
+        let config = ApiKeyConfig::default();
+        let service = ApiKeyService::new(config).await.unwrap();
+
+        let request = CreateApiKeyRequest {
+            user_email: "contractor@example.com".to_string(),
+            user_id: Some(Uuid::new_v4().to_string()),
+            description: Some("Contractor key".to_string()),
+            rate_limit: Some(60),
+            monthly_quota: Some(1000),
+            expires_at: None,
+            allowed_ips: None,
+            redaction_rules: Some(vec!["src/billing/".to_string(), "*.pem".to_string()]),
+        };
+        let api_key_response = service.create_api_key(request).await.unwrap();
+        assert_eq!(api_key_response.redaction_rules.len(), 2);
+
+        let validation = service
+            .validate_api_key(&api_key_response.api_key, None)
+            .await
+            .unwrap();
+        assert!(validation.is_valid);
+        assert!(validation.redaction.is_redacted("src/billing/invoice.rs"));
+        assert!(!validation.redaction.is_redacted("src/lib.rs"));
+
+        // Invalid patterns are rejected before a key is created
+        let invalid = CreateApiKeyRequest {
+            user_email: "contractor@example.com".to_string(),
+            user_id: None,
+            description: None,
+            rate_limit: None,
+            monthly_quota: None,
+            expires_at: None,
+            allowed_ips: None,
+            redaction_rules: Some(vec!["  ".to_string()]),
+        };
+        assert!(service.create_api_key(invalid).await.is_err());
+    }
+
     /// Test rate limit window edge cases
     #[test]
     fn test_rate_limit_window_calculation() {
@@ -15,19 +15,43 @@ async fn test_json_serialization_structure() -> Result<()> {
         callers: vec![
             CallSite {
                 caller: "test_function".to_string(),
+                qualified_name: String::new(),
                 file_path: "src/test.rs".to_string(),
                 line_number: Some(42),
+                column_number: None,
+                relation_type: "calls".to_string(),
                 context: "Calls target at line 42".to_string(),
+                snippet: None,
+                range: None,
+                excerpt: None,
+                observed_calls: None,
+                web_url: None,
             },
             CallSite {
                 caller: "another_function".to_string(),
+                qualified_name: String::new(),
                 file_path: "src/other.rs".to_string(),
                 line_number: None, // Test line number overflow handling
+                column_number: None,
+                relation_type: "calls".to_string(),
                 context: "Calls target at line 999999999".to_string(),
+                snippet: None,
+                range: None,
+                excerpt: None,
+                observed_calls: None,
+                web_url: None,
             },
         ],
+        summary: String::new(),
         markdown: "# Callers\n\nFound 2 callers".to_string(),
         total_count: 2,
+        total_available: 2,
+        offset: 0,
+        next_cursor: None,
+        groups: None,
+        explain: None,
+        notes: vec![],
+        timed_out: false,
     };
 
     // Test JSON serialization
@@ -55,12 +79,28 @@ async fn test_json_serialization_structure() -> Result<()> {
     let impact_result = ImpactResult {
         impacts: vec![ImpactSite {
             affected_symbol: "affected_function".to_string(),
+            qualified_name: String::new(),
             file_path: "src/impact.rs".to_string(),
             line_number: Some(100),
             impact_type: "Function Call Impact".to_string(),
+            relation_type: "calls".to_string(),
+            depth: 1,
+            range: None,
+            excerpt: None,
+            coverage: None,
+            observed_calls: None,
         }],
+        summary: String::new(),
         markdown: "# Impact Analysis\n\nFound 1 impact".to_string(),
         total_count: 1,
+        total_available: 1,
+        offset: 0,
+        next_cursor: None,
+        groups: None,
+        explain: None,
+        notes: vec![],
+        coverage: None,
+        timed_out: false,
     };
 
     let impact_json_result = serde_json::to_string(&impact_result)?;
@@ -93,8 +133,16 @@ async fn test_response_structure_consistency() -> Result<()> {
     // Test empty results have consistent structure
     let empty_callers = CallersResult {
         callers: vec![],
+        summary: String::new(),
         markdown: "# Callers\n\nNo callers found".to_string(),
         total_count: 0,
+        total_available: 0,
+        offset: 0,
+        next_cursor: None,
+        groups: None,
+        explain: None,
+        notes: vec![],
+        timed_out: false,
     };
 
     assert_eq!(
@@ -105,8 +153,17 @@ async fn test_response_structure_consistency() -> Result<()> {
 
     let empty_impacts = ImpactResult {
         impacts: vec![],
+        summary: String::new(),
         markdown: "# Impact Analysis\n\nNo impacts found".to_string(),
         total_count: 0,
+        total_available: 0,
+        offset: 0,
+        next_cursor: None,
+        groups: None,
+        explain: None,
+        notes: vec![],
+        coverage: None,
+        timed_out: false,
     };
 
     assert_eq!(
@@ -120,19 +177,43 @@ async fn test_response_structure_consistency() -> Result<()> {
         callers: vec![
             CallSite {
                 caller: "caller1".to_string(),
+                qualified_name: String::new(),
                 file_path: "file1.rs".to_string(),
                 line_number: Some(1),
+                column_number: None,
+                relation_type: "calls".to_string(),
                 context: "Context 1".to_string(),
+                snippet: None,
+                range: None,
+                excerpt: None,
+                observed_calls: None,
+                web_url: None,
             },
             CallSite {
                 caller: "caller2".to_string(),
+                qualified_name: String::new(),
                 file_path: "file2.rs".to_string(),
                 line_number: Some(2),
+                column_number: None,
+                relation_type: "calls".to_string(),
                 context: "Context 2".to_string(),
+                snippet: None,
+                range: None,
+                excerpt: None,
+                observed_calls: None,
+                web_url: None,
             },
         ],
+        summary: String::new(),
         markdown: "# Callers".to_string(),
         total_count: 2,
+        total_available: 2,
+        offset: 0,
+        next_cursor: None,
+        groups: None,
+        explain: None,
+        notes: vec![],
+        timed_out: false,
     };
 
     assert_eq!(
@@ -159,9 +240,17 @@ async fn test_line_number_handling() -> Result<()> {
     for (line_number, description) in test_cases {
         let call_site = CallSite {
             caller: format!("test_caller_{}", description),
+            qualified_name: String::new(),
             file_path: "test.rs".to_string(),
             line_number,
+            column_number: None,
+            relation_type: "calls".to_string(),
             context: format!("Test context for {}", description),
+            snippet: None,
+            range: None,
+            excerpt: None,
+            observed_calls: None,
+            web_url: None,
         };
 
         // Should serialize without panicking
@@ -214,9 +303,17 @@ async fn test_edge_case_inputs() -> Result<()> {
     for (input, description) in edge_cases {
         let call_site = CallSite {
             caller: input.to_string(),
+            qualified_name: String::new(),
             file_path: format!("path/to/{}.rs", input.replace(['/', '\\', '\n', '\t'], "_")),
             line_number: Some(1),
+            column_number: None,
+            relation_type: "calls".to_string(),
             context: format!("Test context for {}", input),
+            snippet: None,
+            range: None,
+            excerpt: None,
+            observed_calls: None,
+            web_url: None,
         };
 
         // Should serialize without panicking
@@ -263,9 +360,17 @@ async fn test_semantic_context_mapping() -> Result<()> {
     for context in test_contexts {
         let call_site = CallSite {
             caller: "test_caller".to_string(),
+            qualified_name: String::new(),
             file_path: "test.rs".to_string(),
             line_number: Some(42),
+            column_number: None,
+            relation_type: "calls".to_string(),
             context: context.to_string(),
+            snippet: None,
+            range: None,
+            excerpt: None,
+            observed_calls: None,
+            web_url: None,
         };
 
         let json_result = serde_json::to_string(&call_site)?;
@@ -299,9 +404,16 @@ async fn test_semantic_context_mapping() -> Result<()> {
     for impact_type in impact_types {
         let impact_site = ImpactSite {
             affected_symbol: "test_symbol".to_string(),
+            qualified_name: String::new(),
             file_path: "test.rs".to_string(),
             line_number: Some(42),
             impact_type: impact_type.to_string(),
+            relation_type: "calls".to_string(),
+            depth: 1,
+            range: None,
+            excerpt: None,
+            coverage: None,
+            observed_calls: None,
         };
 
         let json_result = serde_json::to_string(&impact_site)?;
@@ -369,16 +481,31 @@ async fn test_semantic_context_accuracy() -> Result<()> {
         // Simulate what the actual mapping functions would produce
         let call_site = CallSite {
             caller: "test_caller".to_string(),
+            qualified_name: String::new(),
             file_path: "test.rs".to_string(),
             line_number: Some(line_number),
+            column_number: None,
+            relation_type: "calls".to_string(),
             context: format!("{} {} at line {}", expected_verb, target, line_number),
+            snippet: None,
+            range: None,
+            excerpt: None,
+            observed_calls: None,
+            web_url: None,
         };
 
         let impact_site = ImpactSite {
             affected_symbol: "test_symbol".to_string(),
+            qualified_name: String::new(),
             file_path: "test.rs".to_string(),
             line_number: Some(line_number),
             impact_type: expected_impact.to_string(),
+            relation_type: "calls".to_string(),
+            depth: 1,
+            range: None,
+            excerpt: None,
+            coverage: None,
+            observed_calls: None,
         };
 
         // Verify context contains expected semantic information
@@ -425,34 +552,74 @@ async fn test_comprehensive_api_response_validation() -> Result<()> {
     let diverse_callers = vec![
         CallSite {
             caller: "FileStorage::new".to_string(),
+            qualified_name: String::new(),
             file_path: "src/file_storage.rs".to_string(),
             line_number: Some(45),
+            column_number: None,
+            relation_type: "calls".to_string(),
             context: "Calls DatabaseConfig at line 45".to_string(),
+            snippet: None,
+            range: None,
+            excerpt: None,
+            observed_calls: None,
+            web_url: None,
         },
         CallSite {
             caller: "HttpServer::init".to_string(),
+            qualified_name: String::new(),
             file_path: "src/http_server.rs".to_string(),
             line_number: Some(120),
+            column_number: None,
+            relation_type: "calls".to_string(),
             context: "Imports DatabaseConfig at line 120".to_string(),
+            snippet: None,
+            range: None,
+            excerpt: None,
+            observed_calls: None,
+            web_url: None,
         },
         CallSite {
             caller: "ServiceImpl".to_string(),
+            qualified_name: String::new(),
             file_path: "src/services/mod.rs".to_string(),
             line_number: Some(67),
+            column_number: None,
+            relation_type: "calls".to_string(),
             context: "Implements DatabaseConfig at line 67".to_string(),
+            snippet: None,
+            range: None,
+            excerpt: None,
+            observed_calls: None,
+            web_url: None,
         },
         CallSite {
             caller: "ConfigBuilder".to_string(),
+            qualified_name: String::new(),
             file_path: "src/builders.rs".to_string(),
             line_number: None, // Test overflow case
+            column_number: None,
+            relation_type: "calls".to_string(),
             context: "References DatabaseConfig at line 4294967296".to_string(),
+            snippet: None,
+            range: None,
+            excerpt: None,
+            observed_calls: None,
+            web_url: None,
         },
     ];
 
     let callers_result = CallersResult {
         callers: diverse_callers,
+        summary: String::new(),
         markdown: "# Callers Analysis\n\nFound multiple relationship types".to_string(),
         total_count: 4,
+        total_available: 4,
+        offset: 0,
+        next_cursor: None,
+        groups: None,
+        explain: None,
+        notes: vec![],
+        timed_out: false,
     };
 
     // Validate JSON serialization preserves semantic information
@@ -491,34 +658,71 @@ async fn test_comprehensive_api_response_validation() -> Result<()> {
     let diverse_impacts = vec![
         ImpactSite {
             affected_symbol: "DatabaseConnection".to_string(),
+            qualified_name: String::new(),
             file_path: "src/database.rs".to_string(),
             line_number: Some(89),
             impact_type: "Function Call Impact".to_string(),
+            relation_type: "calls".to_string(),
+            depth: 1,
+            range: None,
+            excerpt: None,
+            coverage: None,
+            observed_calls: None,
         },
         ImpactSite {
             affected_symbol: "ConfigParser".to_string(),
+            qualified_name: String::new(),
             file_path: "src/config.rs".to_string(),
             line_number: Some(156),
             impact_type: "Import Impact".to_string(),
+            relation_type: "calls".to_string(),
+            depth: 1,
+            range: None,
+            excerpt: None,
+            coverage: None,
+            observed_calls: None,
         },
         ImpactSite {
             affected_symbol: "ServiceTrait".to_string(),
+            qualified_name: String::new(),
             file_path: "src/services/trait.rs".to_string(),
             line_number: Some(23),
             impact_type: "Interface Impact".to_string(),
+            relation_type: "calls".to_string(),
+            depth: 1,
+            range: None,
+            excerpt: None,
+            coverage: None,
+            observed_calls: None,
         },
         ImpactSite {
             affected_symbol: "CustomHandler".to_string(),
+            qualified_name: String::new(),
             file_path: "src/custom.rs".to_string(),
             line_number: Some(78),
             impact_type: "Custom Relationship Impact (handler)".to_string(),
+            relation_type: "calls".to_string(),
+            depth: 1,
+            range: None,
+            excerpt: None,
+            coverage: None,
+            observed_calls: None,
         },
     ];
 
     let impact_result = ImpactResult {
         impacts: diverse_impacts,
+        summary: String::new(),
         markdown: "# Impact Analysis\n\nFound various impact types".to_string(),
         total_count: 4,
+        total_available: 4,
+        offset: 0,
+        next_cursor: None,
+        groups: None,
+        explain: None,
+        notes: vec![],
+        coverage: None,
+        timed_out: false,
     };
 
     // Validate impact JSON serialization
@@ -581,16 +785,31 @@ async fn test_relationship_type_coverage() -> Result<()> {
 
         let call_site = CallSite {
             caller: "test_caller".to_string(),
+            qualified_name: String::new(),
             file_path: "test.rs".to_string(),
             line_number: Some(42),
+            column_number: None,
+            relation_type: "calls".to_string(),
             context: format!("Testing {:?} relationship", relation_type),
+            snippet: None,
+            range: None,
+            excerpt: None,
+            observed_calls: None,
+            web_url: None,
         };
 
         let impact_site = ImpactSite {
             affected_symbol: "test_symbol".to_string(),
+            qualified_name: String::new(),
             file_path: "test.rs".to_string(),
             line_number: Some(42),
             impact_type: format!("Testing {:?} impact", relation_type),
+            relation_type: "calls".to_string(),
+            depth: 1,
+            range: None,
+            excerpt: None,
+            coverage: None,
+            observed_calls: None,
         };
 
         // Verify structures can be serialized
@@ -636,16 +855,31 @@ async fn test_relationship_type_coverage() -> Result<()> {
 
         let call_site = CallSite {
             caller: "custom_caller".to_string(),
+            qualified_name: String::new(),
             file_path: "custom.rs".to_string(),
             line_number: Some(1),
+            column_number: None,
+            relation_type: "calls".to_string(),
             context: format!("Custom relationship: {}", custom_str),
+            snippet: None,
+            range: None,
+            excerpt: None,
+            observed_calls: None,
+            web_url: None,
         };
 
         let impact_site = ImpactSite {
             affected_symbol: "custom_symbol".to_string(),
+            qualified_name: String::new(),
             file_path: "custom.rs".to_string(),
             line_number: Some(1),
             impact_type: format!("Custom impact: {}", custom_str),
+            relation_type: "calls".to_string(),
+            depth: 1,
+            range: None,
+            excerpt: None,
+            coverage: None,
+            observed_calls: None,
         };
 
         // Verify custom relationships can be handled
@@ -674,8 +908,16 @@ async fn test_api_response_consistency() -> Result<()> {
     // Test scenario 1: Empty results
     let empty_callers = CallersResult {
         callers: vec![],
+        summary: String::new(),
         markdown: "# No Results\n\nNo callers found".to_string(),
         total_count: 0,
+        total_available: 0,
+        offset: 0,
+        next_cursor: None,
+        groups: None,
+        explain: None,
+        notes: vec![],
+        timed_out: false,
     };
 
     let empty_json = serde_json::to_string(&empty_callers)?;
@@ -697,12 +939,28 @@ async fn test_api_response_consistency() -> Result<()> {
     let single_caller = CallersResult {
         callers: vec![CallSite {
             caller: "SingleCaller".to_string(),
+            qualified_name: String::new(),
             file_path: "src/single.rs".to_string(),
             line_number: Some(1),
+            column_number: None,
+            relation_type: "calls".to_string(),
             context: "Calls target at line 1".to_string(),
+            snippet: None,
+            range: None,
+            excerpt: None,
+            observed_calls: None,
+            web_url: None,
         }],
+        summary: String::new(),
         markdown: "# Single Result".to_string(),
         total_count: 1,
+        total_available: 1,
+        offset: 0,
+        next_cursor: None,
+        groups: None,
+        explain: None,
+        notes: vec![],
+        timed_out: false,
     };
 
     let single_json = serde_json::to_string(&single_caller)?;
@@ -724,16 +982,32 @@ async fn test_api_response_consistency() -> Result<()> {
     let large_callers = (0..100)
         .map(|i| CallSite {
             caller: format!("Caller{}", i),
+            qualified_name: String::new(),
             file_path: format!("src/caller{}.rs", i),
             line_number: Some(i as u32 + 1),
+            column_number: None,
+            relation_type: "calls".to_string(),
             context: format!("Calls target at line {}", i + 1),
+            snippet: None,
+            range: None,
+            excerpt: None,
+            observed_calls: None,
+            web_url: None,
         })
         .collect::<Vec<_>>();
 
     let large_result = CallersResult {
         callers: large_callers,
+        summary: String::new(),
         markdown: "# Large Result Set".to_string(),
         total_count: 100,
+        total_available: 100,
+        offset: 0,
+        next_cursor: None,
+        groups: None,
+        explain: None,
+        notes: vec![],
+        timed_out: false,
     };
 
     let large_json = serde_json::to_string(&large_result)?;
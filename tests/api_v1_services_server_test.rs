@@ -8,7 +8,7 @@ use kotadb::{
     Storage,
 };
 use reqwest::{Client, StatusCode};
-use serde_json::Value;
+use serde_json::{json, Value};
 use std::sync::Arc;
 use tempfile::TempDir;
 use tokio::{sync::Mutex, time::Duration};
@@ -281,6 +281,42 @@ async fn v1_symbol_routes_behave_without_symbols_db() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn v1_callers_and_impact_reject_invalid_paging_options() -> Result<()> {
+    let (base, _tmp, server) = start_services_test_server().await;
+    let client = Client::new();
+
+    let bad_sort = client
+        .get(format!(
+            "{}/api/v1/symbols/hello_world/callers?sort=alphabetical",
+            base
+        ))
+        .send()
+        .await?;
+    assert_eq!(bad_sort.status(), StatusCode::BAD_REQUEST);
+
+    let bad_group = client
+        .get(format!(
+            "{}/api/v1/symbols/hello_world/impact?group_by=module",
+            base
+        ))
+        .send()
+        .await?;
+    assert_eq!(bad_group.status(), StatusCode::BAD_REQUEST);
+
+    let bad_cursor = client
+        .get(format!(
+            "{}/api/v1/symbols/hello_world/impact?cursor=not-a-cursor",
+            base
+        ))
+        .send()
+        .await?;
+    assert_eq!(bad_cursor.status(), StatusCode::BAD_REQUEST);
+
+    server.abort();
+    Ok(())
+}
+
 #[tokio::test]
 async fn v1_index_status_unknown_job_is_404() -> Result<()> {
     let (base, _tmp, server) = start_services_test_server().await;
@@ -444,3 +480,1046 @@ async fn v1_callers_and_impact_happy_path_when_symbols_present() -> Result<()> {
     server.abort();
     Ok(())
 }
+
+/// Register the test repository and wait for its indexing job to finish
+async fn index_test_repo(client: &Client, base: &str, root: &std::path::Path) -> Result<()> {
+    let repo_dir = init_test_git_repo(root)?;
+    index_repo_dir(client, base, &repo_dir).await
+}
+
+/// Register an existing repository and wait for its indexing job to finish
+async fn index_repo_dir(client: &Client, base: &str, repo_dir: &std::path::Path) -> Result<()> {
+    let register_resp = client
+        .post(format!("{}/api/v1/repositories", base))
+        .json(&serde_json::json!({"path": repo_dir.to_string_lossy()}))
+        .send()
+        .await?;
+    assert_eq!(register_resp.status(), StatusCode::OK);
+    let reg: Value = register_resp.json().await?;
+    let job_id = reg["job_id"].as_str().unwrap().to_string();
+
+    let start = std::time::Instant::now();
+    loop {
+        let status_resp = client
+            .get(format!("{}/api/v1/index/status?job_id={}", base, job_id))
+            .send()
+            .await?;
+        if status_resp.status() == StatusCode::NOT_FOUND {
+            return Ok(());
+        }
+        let body: Value = status_resp.json().await?;
+        match body["job"]["status"].as_str() {
+            Some("completed") => return Ok(()),
+            Some("failed") => panic!("index job failed: {:?}", body),
+            _ => {}
+        }
+        if start.elapsed() > Duration::from_secs(15) {
+            panic!("indexing timeout");
+        }
+        tokio::time::sleep(Duration::from_millis(200)).await;
+    }
+}
+
+#[tokio::test]
+async fn v1_file_content_returns_line_ranges() -> Result<()> {
+    if !git_available() {
+        eprintln!("git not available; skipping test");
+        return Ok(());
+    }
+    let (base, temp_dir, server) = start_services_test_server().await;
+    let client = Client::new();
+
+    index_test_repo(&client, &base, temp_dir.path()).await?;
+
+    // Lines 7-9 hold hello_world (line 1 of the fixture is blank)
+    let resp = client
+        .get(format!(
+            "{}/api/v1/files/content/src/lib.rs?start_line=7&end_line=9",
+            base
+        ))
+        .send()
+        .await?;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body: Value = resp.json().await?;
+    assert_eq!(body["encoding"], "utf-8");
+    assert_eq!(body["start_line"], 7);
+    assert_eq!(body["end_line"], 9);
+    assert_eq!(
+        body["content"],
+        "pub fn hello_world() {\n    println!(\"hello world\");\n}"
+    );
+    assert!(body["symbols"].is_array());
+
+    // Symbol kinds use the same representation as the file symbols endpoint
+    let file_symbols: Value = client
+        .get(format!("{}/api/v1/files/symbols/src/lib.rs", base))
+        .send()
+        .await?
+        .json()
+        .await?;
+    for symbol in body["symbols"].as_array().unwrap() {
+        let listed = file_symbols["symbols"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .find(|s| s["name"] == symbol["name"] && s["start_line"] == symbol["start_line"]);
+        if let Some(listed) = listed {
+            assert_eq!(listed["kind"], symbol["kind"]);
+        }
+        assert!(symbol["kind"].is_string());
+    }
+
+    // Inverted ranges are rejected
+    let inverted = client
+        .get(format!(
+            "{}/api/v1/files/content/src/lib.rs?start_line=9&end_line=2",
+            base
+        ))
+        .send()
+        .await?;
+    assert_eq!(inverted.status(), StatusCode::BAD_REQUEST);
+
+    // Unknown files are 404
+    let missing = client
+        .get(format!("{}/api/v1/files/content/src/missing.rs", base))
+        .send()
+        .await?;
+    assert_eq!(missing.status(), StatusCode::NOT_FOUND);
+
+    server.abort();
+    Ok(())
+}
+
+#[tokio::test]
+async fn v1_file_tree_lists_directories_with_counts() -> Result<()> {
+    if !git_available() {
+        eprintln!("git not available; skipping test");
+        return Ok(());
+    }
+    let (base, temp_dir, server) = start_services_test_server().await;
+    let client = Client::new();
+    index_test_repo(&client, &base, temp_dir.path()).await?;
+
+    let resp = client
+        .get(format!(
+            "{}/api/v1/files/tree?path=repos/repo/files&depth=1",
+            base
+        ))
+        .send()
+        .await?;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let tree: Value = resp.json().await?;
+    assert_eq!(tree["type"], "directory");
+    assert_eq!(tree["file_count"], 1);
+    let src = &tree["children"][0];
+    assert_eq!(src["name"], "src");
+    assert_eq!(src["path"], "repos/repo/files/src");
+    assert_eq!(src["file_count"], 1);
+    assert!(src["total_size"].as_u64().unwrap() > 0);
+    // depth=1 stops at src, so its files are summarized but not listed
+    assert_eq!(src["truncated"], true);
+    assert!(src.get("children").is_none());
+
+    let deeper: Value = client
+        .get(format!(
+            "{}/api/v1/files/tree?path=repos/repo/files&depth=2",
+            base
+        ))
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(deeper["children"][0]["children"][0]["name"], "lib.rs");
+    assert_eq!(deeper["children"][0]["children"][0]["type"], "file");
+
+    let bad_depth = client
+        .get(format!("{}/api/v1/files/tree?depth=0", base))
+        .send()
+        .await?;
+    assert_eq!(bad_depth.status(), StatusCode::BAD_REQUEST);
+
+    let missing = client
+        .get(format!("{}/api/v1/files/tree?path=does/not/exist", base))
+        .send()
+        .await?;
+    assert_eq!(missing.status(), StatusCode::NOT_FOUND);
+
+    server.abort();
+    Ok(())
+}
+
+#[tokio::test]
+async fn v1_read_endpoints_support_conditional_requests() -> Result<()> {
+    if !git_available() {
+        eprintln!("git not available; skipping test");
+        return Ok(());
+    }
+    let (base, temp_dir, server) = start_services_test_server().await;
+    let client = Client::new();
+    let stats_url = format!("{}/api/v1/analysis/stats", base);
+
+    let first = client.get(&stats_url).send().await?;
+    assert_eq!(first.status(), StatusCode::OK);
+    let etag = first
+        .headers()
+        .get(reqwest::header::ETAG)
+        .expect("stats response carries an ETag")
+        .to_str()?
+        .to_string();
+
+    let cached = client
+        .get(&stats_url)
+        .header(reqwest::header::IF_NONE_MATCH, &etag)
+        .send()
+        .await?;
+    assert_eq!(cached.status(), StatusCode::NOT_MODIFIED);
+
+    // Indexing bumps the data generation, so the old ETag no longer matches
+    index_test_repo(&client, &base, temp_dir.path()).await?;
+    let refreshed = client
+        .get(&stats_url)
+        .header(reqwest::header::IF_NONE_MATCH, &etag)
+        .send()
+        .await?;
+    assert_eq!(refreshed.status(), StatusCode::OK);
+    assert_ne!(
+        refreshed.headers().get(reqwest::header::ETAG).unwrap(),
+        etag.as_str()
+    );
+
+    server.abort();
+    Ok(())
+}
+
+#[tokio::test]
+async fn compression_is_negotiated_via_accept_encoding() -> Result<()> {
+    use axum::{routing::get, Json, Router};
+    use kotadb::services_http_server::CompressionConfig;
+
+    let large = serde_json::json!({ "items": vec!["kotadb"; 512] });
+    let router = Router::new()
+        .route("/large", get(move || async move { Json(large) }))
+        .route(
+            "/small",
+            get(|| async { Json(serde_json::json!({ "ok": true })) }),
+        );
+    let app = CompressionConfig::default().apply(router);
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let base = format!("http://127.0.0.1:{}", listener.local_addr()?.port());
+    let server = tokio::spawn(async move { axum::serve(listener, app).await });
+
+    let client = Client::new();
+    for encoding in ["gzip", "deflate", "zstd"] {
+        let response = client
+            .get(format!("{}/large", base))
+            .header(reqwest::header::ACCEPT_ENCODING, encoding)
+            .send()
+            .await?;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers().get(reqwest::header::CONTENT_ENCODING),
+            Some(&reqwest::header::HeaderValue::from_static(encoding))
+        );
+    }
+
+    // No Accept-Encoding means an identity response
+    let plain = client.get(format!("{}/large", base)).send().await?;
+    assert!(plain
+        .headers()
+        .get(reqwest::header::CONTENT_ENCODING)
+        .is_none());
+    let body: Value = plain.json().await?;
+    assert_eq!(body["items"].as_array().map(|a| a.len()), Some(512));
+
+    // Bodies below the size threshold are left alone
+    let small = client
+        .get(format!("{}/small", base))
+        .header(reqwest::header::ACCEPT_ENCODING, "gzip")
+        .send()
+        .await?;
+    assert!(small
+        .headers()
+        .get(reqwest::header::CONTENT_ENCODING)
+        .is_none());
+
+    server.abort();
+    Ok(())
+}
+
+#[tokio::test]
+async fn v1_repository_registration_honours_idempotency_keys() -> Result<()> {
+    if !git_available() {
+        eprintln!("git not available; skipping test");
+        return Ok(());
+    }
+    let (base, temp_dir, server) = start_services_test_server().await;
+    let client = Client::new();
+    let repo_dir = init_test_git_repo(temp_dir.path())?;
+    let url = format!("{}/api/v1/repositories", base);
+    let body = serde_json::json!({"path": repo_dir.to_string_lossy()});
+
+    let first = client
+        .post(&url)
+        .header("Idempotency-Key", "register-once")
+        .json(&body)
+        .send()
+        .await?;
+    assert_eq!(first.status(), StatusCode::OK);
+    assert!(first.headers().get("idempotent-replayed").is_none());
+    let first: Value = first.json().await?;
+
+    // A client retry replays the original job instead of enqueuing another
+    let retry = client
+        .post(&url)
+        .header("Idempotency-Key", "register-once")
+        .json(&body)
+        .send()
+        .await?;
+    assert_eq!(retry.status(), StatusCode::OK);
+    assert_eq!(
+        retry
+            .headers()
+            .get("idempotent-replayed")
+            .and_then(|v| v.to_str().ok()),
+        Some("true")
+    );
+    let retry: Value = retry.json().await?;
+    assert_eq!(retry["job_id"], first["job_id"]);
+
+    // Reusing the key for a different request is rejected
+    let reused = client
+        .post(&url)
+        .header("Idempotency-Key", "register-once")
+        .json(&serde_json::json!({"path": temp_dir.path().to_string_lossy()}))
+        .send()
+        .await?;
+    assert_eq!(reused.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let err: Value = reused.json().await?;
+    assert_eq!(err["error_type"], "idempotency_key_reused");
+
+    server.abort();
+    Ok(())
+}
+
+#[tokio::test]
+async fn demo_server_is_read_only_and_rate_limited() -> Result<()> {
+    use kotadb::{create_services_demo_server, DemoConfig};
+
+    if !git_available() {
+        eprintln!("git not available; skipping test");
+        return Ok(());
+    }
+    let temp_dir = TempDir::new()?;
+    let repo_dir = init_test_git_repo(temp_dir.path())?;
+    let storage =
+        create_file_storage(temp_dir.path().join("storage").to_str().unwrap(), Some(100)).await?;
+    let primary =
+        create_primary_index_for_tests(temp_dir.path().join("primary").to_str().unwrap()).await?;
+    let trigram =
+        create_trigram_index_for_tests(temp_dir.path().join("trigram").to_str().unwrap()).await?;
+
+    let app = create_services_demo_server(
+        Arc::new(Mutex::new(storage)),
+        Arc::new(Mutex::new(primary)),
+        Arc::new(Mutex::new(trigram)),
+        temp_dir.path().to_path_buf(),
+        DemoConfig {
+            sample_repo: Some(repo_dir.clone()),
+            requests_per_minute: 3,
+            trust_proxy_headers: false,
+        },
+    )
+    .await?;
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let base = format!("http://127.0.0.1:{}", listener.local_addr()?.port());
+    let server = tokio::spawn(async move { axum::serve(listener, app).await });
+    let client = Client::new();
+
+    // The sample repository was indexed at startup and is readable anonymously
+    let content = client
+        .get(format!(
+            "{}/api/v1/files/content/repos/repo/files/src/lib.rs",
+            base
+        ))
+        .send()
+        .await?;
+    assert_eq!(content.status(), StatusCode::OK);
+    assert_eq!(
+        content
+            .headers()
+            .get("x-ratelimit-remaining")
+            .and_then(|v| v.to_str().ok()),
+        Some("2")
+    );
+
+    let register = client
+        .post(format!("{}/api/v1/repositories", base))
+        .json(&serde_json::json!({"path": repo_dir.to_string_lossy()}))
+        .send()
+        .await?;
+    assert_eq!(register.status(), StatusCode::FORBIDDEN);
+    let err: Value = register.json().await?;
+    assert_eq!(err["error_type"], "demo_read_only");
+
+    let third = client
+        .get(format!("{}/api/v1/analysis/stats", base))
+        .send()
+        .await?;
+    assert_eq!(third.status(), StatusCode::OK);
+
+    let limited = client
+        .get(format!("{}/api/v1/analysis/stats", base))
+        .send()
+        .await?;
+    assert_eq!(limited.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert!(limited
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)
+        .is_some());
+
+    // Health checks are not rate limited
+    let health = client.get(format!("{}/health", base)).send().await?;
+    assert_eq!(health.status(), StatusCode::OK);
+
+    server.abort();
+    Ok(())
+}
+
+#[tokio::test]
+async fn v1_token_usage_is_reported_per_session() -> Result<()> {
+    if !git_available() {
+        eprintln!("git not available; skipping test");
+        return Ok(());
+    }
+    let (base, temp_dir, server) = start_services_test_server().await;
+    let client = Client::new();
+
+    index_test_repo(&client, &base, temp_dir.path()).await?;
+
+    for session in ["session-a", "session-a", "session-b"] {
+        let resp = client
+            .post(format!("{}/api/v1/search/code", base))
+            .header("X-KotaDB-Session", session)
+            .json(&serde_json::json!({"query": "hello", "format": "simple"}))
+            .send()
+            .await?;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    let usage: Value = client
+        .get(format!("{}/api/v1/usage/tokens", base))
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(usage["consumers"].as_array().unwrap().len(), 2);
+    assert_eq!(usage["totals"]["requests"], 3);
+
+    let session_a: Value = client
+        .get(format!("{}/api/v1/usage/tokens?session=session-a", base))
+        .send()
+        .await?
+        .json()
+        .await?;
+    let consumers = session_a["consumers"].as_array().unwrap();
+    assert_eq!(consumers.len(), 1);
+    assert_eq!(consumers[0]["session"], "session-a");
+    assert!(consumers[0]["api_key_id"].is_null());
+    assert_eq!(consumers[0]["requests"], 2);
+    assert_eq!(consumers[0]["tools"]["search_code"]["requests"], 2);
+    // Returning a path list is cheaper than pasting the matched file
+    let raw = consumers[0]["raw_file_tokens"].as_u64().unwrap();
+    let returned = consumers[0]["returned_tokens"].as_u64().unwrap();
+    assert!(raw > returned, "raw {} returned {}", raw, returned);
+    assert_eq!(
+        consumers[0]["tokens_saved"].as_u64().unwrap(),
+        raw - returned
+    );
+
+    server.abort();
+    Ok(())
+}
+
+#[tokio::test]
+async fn v1_fused_search_validates_query_and_lists_sources() -> Result<()> {
+    let (base, _temp_dir, server) = start_services_test_server().await;
+    let client = Client::new();
+
+    let empty = client
+        .get(format!("{}/api/v1/search?query=%20", base))
+        .send()
+        .await?;
+    assert_eq!(empty.status(), StatusCode::BAD_REQUEST);
+
+    let resp = client
+        .get(format!(
+            "{}/api/v1/search?query=storage%20engine&limit=5",
+            base
+        ))
+        .send()
+        .await?;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body: Value = resp.json().await?;
+    assert_eq!(body["query"], "storage engine");
+    assert!(body["results"].as_array().is_some());
+    let sources: Vec<&str> = body["sources_searched"]
+        .as_array()
+        .expect("sources_searched missing")
+        .iter()
+        .filter_map(|s| s.as_str())
+        .collect();
+    assert_eq!(sources, vec!["trigram", "symbol"]);
+
+    server.abort();
+    Ok(())
+}
+
+#[tokio::test]
+async fn v1_related_symbols_combine_co_change_and_shared_callers() -> Result<()> {
+    use std::process::Command;
+    if !git_available() {
+        eprintln!("git not available; skipping test");
+        return Ok(());
+    }
+    let (base, temp_dir, server) = start_services_test_server().await;
+    let client = Client::new();
+
+    // A second commit changes lib.rs together with config.rs, and both callers
+    // of load_config also call parse_args
+    let repo_dir = init_test_git_repo(temp_dir.path())?;
+    let lib_rs = r#"
+pub fn load_config() -> u32 {
+    1
+}
+
+pub fn parse_args() -> u32 {
+    2
+}
+
+pub fn run() -> u32 {
+    load_config() + parse_args()
+}
+
+pub fn check() -> u32 {
+    load_config() + parse_args()
+}
+"#;
+    std::fs::write(repo_dir.join("src/lib.rs"), lib_rs)?;
+    std::fs::write(
+        repo_dir.join("src/config.rs"),
+        "pub const RETRIES: u32 = 3;\n",
+    )?;
+    for args in [vec!["add", "."], vec!["commit", "-m", "Load configuration"]] {
+        assert!(Command::new("git")
+            .args(&args)
+            .current_dir(&repo_dir)
+            .status()?
+            .success());
+    }
+    index_repo_dir(&client, &base, &repo_dir).await?;
+
+    let zero_limit = client
+        .get(format!(
+            "{}/api/v1/symbols/load_config/related?limit=0",
+            base
+        ))
+        .send()
+        .await?;
+    assert_eq!(zero_limit.status(), StatusCode::BAD_REQUEST);
+
+    if std::fs::metadata(temp_dir.path().join("symbols.kota")).is_err() {
+        eprintln!("symbols.kota not present; skipping related-symbols checks");
+        server.abort();
+        return Ok(());
+    }
+
+    let resp = client
+        .get(format!("{}/api/v1/symbols/load_config/related", base))
+        .send()
+        .await?;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body: Value = resp.json().await?;
+    assert_eq!(body["symbol"], "load_config");
+    assert_eq!(body["defined_in"], json!(["src/lib.rs"]));
+    let signals = body["signals"].as_array().unwrap();
+    assert!(signals.contains(&json!("co_change")));
+    assert!(signals.contains(&json!("shared_callers")));
+
+    // config.rs only ever changed together with load_config's file
+    let files = body["files"].as_array().unwrap();
+    assert_eq!(files[0]["path"], "src/config.rs");
+    assert_eq!(files[0]["co_changes"], 1);
+
+    // parse_args is called by both callers of load_config
+    let related = body["related"].as_array().unwrap();
+    let parse_args = related
+        .iter()
+        .find(|symbol| symbol["name"] == "parse_args")
+        .expect("parse_args should be related");
+    assert_eq!(parse_args["shared_callers"], json!(["check", "run"]));
+    assert!(related.iter().all(|symbol| symbol["name"] != "load_config"));
+
+    server.abort();
+    Ok(())
+}
+
+#[tokio::test]
+async fn v1_symbol_graph_renders_the_neighborhood_as_svg() -> Result<()> {
+    use std::process::Command;
+    if !git_available() {
+        eprintln!("git not available; skipping test");
+        return Ok(());
+    }
+    let (base, temp_dir, server) = start_services_test_server().await;
+    let client = Client::new();
+
+    let repo_dir = init_test_git_repo(temp_dir.path())?;
+    let lib_rs = r#"
+pub fn load_config() -> u32 {
+    parse_args()
+}
+
+pub fn parse_args() -> u32 {
+    2
+}
+
+pub fn run() -> u32 {
+    load_config()
+}
+"#;
+    std::fs::write(repo_dir.join("src/lib.rs"), lib_rs)?;
+    for args in [vec!["add", "."], vec!["commit", "-m", "Load configuration"]] {
+        assert!(Command::new("git")
+            .args(&args)
+            .current_dir(&repo_dir)
+            .status()?
+            .success());
+    }
+    index_repo_dir(&client, &base, &repo_dir).await?;
+
+    for query in ["depth=0", "depth=5", "max_nodes=0"] {
+        let invalid = client
+            .get(format!(
+                "{}/api/v1/symbols/load_config/graph.svg?{}",
+                base, query
+            ))
+            .send()
+            .await?;
+        assert_eq!(invalid.status(), StatusCode::BAD_REQUEST, "{query}");
+    }
+
+    if std::fs::metadata(temp_dir.path().join("symbols.kota")).is_err() {
+        eprintln!("symbols.kota not present; skipping symbol graph checks");
+        server.abort();
+        return Ok(());
+    }
+
+    let resp = client
+        .get(format!("{}/api/v1/symbols/load_config/graph.svg", base))
+        .send()
+        .await?;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(resp.headers()["content-type"], "image/svg+xml");
+    assert!(resp.headers().contains_key("etag"));
+    let svg = resp.text().await?;
+    assert!(svg.starts_with("<svg"));
+    assert!(svg.ends_with("</svg>"));
+    // The caller, the target and its dependency are drawn, connected by call edges
+    for name in [">run<", ">load_config<", ">parse_args<"] {
+        assert!(svg.contains(name), "missing {name} in {svg}");
+    }
+    assert!(svg.contains(">calls<"), "legend lists call edges");
+
+    server.abort();
+    Ok(())
+}
+
+#[tokio::test]
+async fn v1_treemap_nests_files_under_directories_with_metrics() -> Result<()> {
+    use std::process::Command;
+    if !git_available() {
+        eprintln!("git not available; skipping test");
+        return Ok(());
+    }
+    let (base, temp_dir, server) = start_services_test_server().await;
+    let client = Client::new();
+
+    let repo_dir = init_test_git_repo(temp_dir.path())?;
+    let lib_rs = r#"
+pub fn classify(n: i32) -> &'static str {
+    if n < 0 && n != -1 {
+        "negative"
+    } else if n == 0 || n == 1 {
+        "small"
+    } else {
+        "large"
+    }
+}
+"#;
+    std::fs::write(repo_dir.join("src/lib.rs"), lib_rs)?;
+    for args in [vec!["add", "."], vec!["commit", "-m", "Classify numbers"]] {
+        assert!(Command::new("git")
+            .args(&args)
+            .current_dir(&repo_dir)
+            .status()?
+            .success());
+    }
+    index_repo_dir(&client, &base, &repo_dir).await?;
+
+    let invalid = client
+        .get(format!("{}/api/v1/overview/treemap?depth=0", base))
+        .send()
+        .await?;
+    assert_eq!(invalid.status(), StatusCode::BAD_REQUEST);
+
+    let resp = client
+        .get(format!("{}/api/v1/overview/treemap", base))
+        .send()
+        .await?;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert!(resp.headers().contains_key("etag"));
+    let body: Value = resp.json().await?;
+    let root = &body["root"];
+    assert_eq!(root["type"], "directory");
+    assert!(root.get("value").is_none(), "only leaves carry a value");
+
+    fn find<'a>(node: &'a Value, name: &str) -> Option<&'a Value> {
+        if node["name"] == name {
+            return Some(node);
+        }
+        node["children"]
+            .as_array()?
+            .iter()
+            .find_map(|child| find(child, name))
+    }
+    let lib = find(root, "lib.rs").expect("lib.rs in treemap");
+    assert_eq!(lib["type"], "file");
+    assert_eq!(lib["language"], "Rust");
+    assert!(lib["path"].as_str().unwrap().ends_with("src/lib.rs"));
+    assert_eq!(lib["value"], lib["metrics"]["size"]);
+    // Two `if`s, one `&&` and one `||`
+    assert_eq!(lib["metrics"]["complexity"], 4);
+    let src = find(root, "src").expect("src directory in treemap");
+    assert_eq!(src["type"], "directory");
+    assert!(src["metrics"]["files"].as_u64().unwrap() >= 1);
+    assert!(root["metrics"]["size"].as_u64() >= lib["metrics"]["size"].as_u64());
+    assert!(body["leaf_max"]["complexity"].as_u64().unwrap() >= 4);
+
+    // Rooting at a directory and limiting depth collapses what lies below
+    let src_path = src["path"].as_str().unwrap();
+    let parent = src_path.rsplit_once('/').map_or("", |(parent, _)| parent);
+    let resp = client
+        .get(format!(
+            "{}/api/v1/overview/treemap?path={}&depth=1",
+            base, parent
+        ))
+        .send()
+        .await?;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body: Value = resp.json().await?;
+    let collapsed = find(&body["root"], "src").expect("src under the requested root");
+    assert!(collapsed.get("children").is_none());
+    assert_eq!(collapsed["value"], src["metrics"]["size"]);
+
+    let resp = client
+        .get(format!(
+            "{}/api/v1/overview/treemap?path={}",
+            base, src_path
+        ))
+        .send()
+        .await?;
+    let body: Value = resp.json().await?;
+    assert_eq!(body["root"]["path"], src_path);
+    assert!(find(&body["root"], "lib.rs").is_some());
+
+    server.abort();
+    Ok(())
+}
+
+#[tokio::test]
+async fn v1_trends_report_metrics_of_each_indexing_run() -> Result<()> {
+    use std::process::Command;
+    if !git_available() {
+        eprintln!("git not available; skipping test");
+        return Ok(());
+    }
+    let (base, temp_dir, server) = start_services_test_server().await;
+    let client = Client::new();
+
+    let resp = client
+        .get(format!("{}/api/v1/analysis/trends", base))
+        .send()
+        .await?;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body: Value = resp.json().await?;
+    assert_eq!(body["snapshots"], json!([]));
+    assert!(body.get("change").is_none());
+
+    let repo_dir = init_test_git_repo(temp_dir.path())?;
+    index_repo_dir(&client, &base, &repo_dir).await?;
+
+    std::fs::write(
+        repo_dir.join("src/extra.rs"),
+        "pub fn pick(a: bool, b: bool) -> bool {\n    if a && b {\n        true\n    } else {\n        b\n    }\n}\n",
+    )?;
+    for args in [vec!["add", "."], vec!["commit", "-m", "Add extra"]] {
+        assert!(Command::new("git")
+            .args(&args)
+            .current_dir(&repo_dir)
+            .status()?
+            .success());
+    }
+    index_repo_dir(&client, &base, &repo_dir).await?;
+
+    let invalid = client
+        .get(format!("{}/api/v1/analysis/trends?limit=0", base))
+        .send()
+        .await?;
+    assert_eq!(invalid.status(), StatusCode::BAD_REQUEST);
+
+    let resp = client
+        .get(format!("{}/api/v1/analysis/trends", base))
+        .send()
+        .await?;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body: Value = resp.json().await?;
+    let snapshots = body["snapshots"].as_array().unwrap();
+    assert_eq!(snapshots.len(), 2, "one snapshot per indexing run: {body}");
+    let (first, last) = (&snapshots[0], &snapshots[1]);
+    assert!(last["files"].as_u64() > first["files"].as_u64());
+    assert!(last["lines"].as_u64() > first["lines"].as_u64());
+    assert!(last["avg_complexity"].as_f64().unwrap() > 0.0);
+    assert!(last["repository"].as_str().unwrap().ends_with("repo"));
+    assert!(body["change"]["files"].as_i64().unwrap() >= 1);
+
+    let resp = client
+        .get(format!("{}/api/v1/analysis/trends?limit=1", base))
+        .send()
+        .await?;
+    let body: Value = resp.json().await?;
+    assert_eq!(body["snapshots"].as_array().unwrap().len(), 1);
+    assert_eq!(body["snapshots"][0]["recorded_at"], last["recorded_at"]);
+
+    // The same two runs, as stats deltas
+    let resp = client
+        .get(format!(
+            "{}/api/v1/analysis/stats?basic=true&compare=previous",
+            base
+        ))
+        .send()
+        .await?;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body: Value = resp.json().await?;
+    let comparison = &body["comparison"];
+    assert_eq!(comparison["current"]["recorded_at"], last["recorded_at"]);
+    assert_eq!(comparison["previous"]["recorded_at"], first["recorded_at"]);
+    assert!(comparison["change"]["files"].as_i64().unwrap() >= 1);
+    assert!(body["formatted_output"]
+        .as_str()
+        .unwrap()
+        .contains("Compared with Previous Index Run"));
+
+    let invalid = client
+        .get(format!("{}/api/v1/analysis/stats?compare=oldest", base))
+        .send()
+        .await?;
+    assert_eq!(invalid.status(), StatusCode::BAD_REQUEST);
+
+    server.abort();
+    Ok(())
+}
+
+#[tokio::test]
+async fn v1_annotations_are_stored_and_returned_with_search_results() -> Result<()> {
+    if !git_available() {
+        eprintln!("git not available; skipping test");
+        return Ok(());
+    }
+    let (base, temp_dir, server) = start_services_test_server().await;
+    let client = Client::new();
+
+    index_test_repo(&client, &base, temp_dir.path()).await?;
+
+    let empty = client
+        .put(format!("{}/api/v1/annotations", base))
+        .json(&json!({"kind": "file", "target": "src/lib.rs", "summary": " "}))
+        .send()
+        .await?;
+    assert_eq!(empty.status(), StatusCode::BAD_REQUEST);
+
+    let put = client
+        .put(format!("{}/api/v1/annotations", base))
+        .json(&json!({
+            "kind": "file",
+            "target": "./src/lib.rs",
+            "summary": "Async helpers that process queued test items",
+            "source": "assistant"
+        }))
+        .send()
+        .await?;
+    assert_eq!(put.status(), StatusCode::OK);
+    let body: Value = put.json().await?;
+    assert_eq!(body["created"], true);
+    assert_eq!(body["annotation"]["target"], "src/lib.rs");
+
+    let get: Value = client
+        .get(format!(
+            "{}/api/v1/annotations?kind=file&target=src/lib.rs",
+            base
+        ))
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(get["annotations"][0]["source"], "assistant");
+
+    let by_words: Value = client
+        .get(format!("{}/api/v1/annotations?q=queued%20items", base))
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(by_words["total_count"], 1);
+
+    // The annotation of a result file comes back alongside the search
+    let search: Value = client
+        .post(format!("{}/api/v1/search/code", base))
+        .json(&json!({"query": "process_test_item", "format": "simple"}))
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(search["results"], json!(["repos/repo/files/src/lib.rs"]));
+    assert_eq!(search["annotations"][0]["target"], "src/lib.rs");
+
+    let delete = client
+        .delete(format!(
+            "{}/api/v1/annotations?kind=file&target=src/lib.rs",
+            base
+        ))
+        .send()
+        .await?;
+    assert_eq!(delete.status(), StatusCode::OK);
+    let missing = client
+        .get(format!(
+            "{}/api/v1/annotations?kind=file&target=src/lib.rs",
+            base
+        ))
+        .send()
+        .await?;
+    assert_eq!(missing.status(), StatusCode::NOT_FOUND);
+
+    server.abort();
+    Ok(())
+}
+
+#[tokio::test]
+async fn v1_notes_are_filtered_and_attached_to_overlapping_results() -> Result<()> {
+    if !git_available() {
+        eprintln!("git not available; skipping test");
+        return Ok(());
+    }
+    let (base, temp_dir, server) = start_services_test_server().await;
+    let client = Client::new();
+
+    index_test_repo(&client, &base, temp_dir.path()).await?;
+
+    let empty = client
+        .post(format!("{}/api/v1/notes", base))
+        .json(&json!({"kind": "symbol", "target": "hello_world"}))
+        .send()
+        .await?;
+    assert_eq!(empty.status(), StatusCode::BAD_REQUEST);
+
+    let mut ids = Vec::new();
+    for note in [
+        json!({"kind": "file", "target": "src/lib.rs", "lines": {"start": 2, "end": 5},
+               "text": "Async worker entry point", "tags": ["#Entry"], "bookmark": true}),
+        json!({"kind": "file", "target": "src/lib.rs", "lines": {"start": 7, "end": 9},
+               "text": "Greeting used by the demo"}),
+        json!({"kind": "symbol", "target": "hello_world", "tags": ["demo"], "author": "sam"}),
+    ] {
+        let resp = client
+            .post(format!("{}/api/v1/notes", base))
+            .json(&note)
+            .send()
+            .await?;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body: Value = resp.json().await?;
+        ids.push(body["note"]["id"].as_str().unwrap().to_string());
+    }
+
+    let bookmarks: Value = client
+        .get(format!("{}/api/v1/notes?bookmark=true&tag=entry", base))
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(bookmarks["total_count"], 1);
+    assert_eq!(bookmarks["notes"][0]["id"], json!(ids[0]));
+
+    let file_notes: Value = client
+        .get(format!(
+            "{}/api/v1/notes?kind=file&target=./src/lib.rs",
+            base
+        ))
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(file_notes["total_count"], 2);
+
+    // Content results are whole files and carry every note on them
+    let search: Value = client
+        .post(format!("{}/api/v1/search/code", base))
+        .json(&json!({"query": "process_test_item", "format": "simple"}))
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(search["notes"].as_array().unwrap().len(), 2);
+
+    let patched = client
+        .patch(format!("{}/api/v1/notes/{}", base, ids[1]))
+        .json(&json!({"text": "Prints a greeting"}))
+        .send()
+        .await?;
+    assert_eq!(patched.status(), StatusCode::OK);
+    let patched: Value = patched.json().await?;
+    assert_eq!(patched["note"]["text"], "Prints a greeting");
+
+    if std::fs::metadata(temp_dir.path().join("symbols.kota")).is_ok() {
+        // A symbol result picks up its own notes and those on overlapping lines only
+        let symbols: Value = client
+            .post(format!("{}/api/v1/search/symbols", base))
+            .json(&json!({"pattern": "hello_world"}))
+            .send()
+            .await?
+            .json()
+            .await?;
+        let noted: Vec<&str> = symbols["notes"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|note| note["id"].as_str().unwrap())
+            .collect();
+        assert_eq!(noted, vec![ids[2].as_str(), ids[1].as_str()]);
+    } else {
+        eprintln!("symbols.kota not present; skipping symbol note checks");
+    }
+
+    let deleted = client
+        .delete(format!("{}/api/v1/notes/{}", base, ids[0]))
+        .send()
+        .await?;
+    assert_eq!(deleted.status(), StatusCode::OK);
+    let missing = client
+        .delete(format!("{}/api/v1/notes/{}", base, ids[0]))
+        .send()
+        .await?;
+    assert_eq!(missing.status(), StatusCode::NOT_FOUND);
+
+    server.abort();
+    Ok(())
+}
@@ -0,0 +1,65 @@
+// Lease handling of the kotadb binary: commands that write wait for the holder of a
+// database's lease instead of falling back to read-only access
+
+use anyhow::Result;
+use kotadb::db_lock::DbLock;
+use std::io::{BufRead, BufReader};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+use tempfile::TempDir;
+
+fn kotadb_serve(db_path: &std::path::Path) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_kotadb"));
+    command
+        .arg("--db-path")
+        .arg(db_path)
+        .args(["serve", "--port", "0"]);
+    command
+}
+
+#[test]
+fn serve_fails_while_a_reader_holds_the_lease() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let db_path = temp_dir.path().join("db");
+    std::fs::create_dir_all(&db_path)?;
+    let _reader = DbLock::acquire(&db_path, "kotadb stats", Duration::ZERO)?;
+
+    let started = Instant::now();
+    let output = kotadb_serve(&db_path).output()?;
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert!(!output.status.success(), "serve started without the lease");
+    assert!(stderr.contains("is in use by `kotadb stats`"), "{stderr}");
+    assert!(!stderr.contains("opening it read-only"), "{stderr}");
+    // It gave the holder time to finish before giving up
+    assert!(started.elapsed() >= Duration::from_secs(5));
+    Ok(())
+}
+
+#[test]
+fn serve_starts_once_the_reader_releases_the_lease() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let db_path = temp_dir.path().join("db");
+    std::fs::create_dir_all(&db_path)?;
+    let reader = DbLock::acquire(&db_path, "kotadb stats", Duration::ZERO)?;
+
+    let mut server = kotadb_serve(&db_path)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    std::thread::sleep(Duration::from_secs(1));
+    drop(reader);
+
+    let stdout = BufReader::new(server.stdout.take().expect("piped stdout"));
+    let started = stdout
+        .lines()
+        .map_while(Result::ok)
+        .any(|line| line.contains("Starting KotaDB Services HTTP Server"));
+    server.kill()?;
+    let output = server.wait_with_output()?;
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert!(started, "serve did not start: {stderr}");
+    assert!(!stderr.contains("opening it read-only"), "{stderr}");
+    Ok(())
+}
@@ -0,0 +1,271 @@
+// Crash-consistency tests driven by injected storage and index failures
+// Run with: cargo test --features failure-injection --test failure_injection_test
+
+#![cfg(feature = "failure-injection")]
+
+use anyhow::Result;
+use kotadb::failure_injection::{FailingIndex, FailingStorage, FailureSchedule, Operation};
+use kotadb::file_storage::FileStorage;
+use kotadb::{
+    create_file_storage, create_primary_index_for_tests, create_trigram_index,
+    database::Database,
+    services::{IndexCodebaseOptions, IndexingService},
+    Document, DocumentBuilder, Index, QueryBuilder, RetryableStorage, Storage, ValidatedDocumentId,
+    ValidatedPath,
+};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::Duration;
+use tempfile::TempDir;
+use tokio::sync::{Mutex, RwLock};
+
+mod git_test_helpers;
+use git_test_helpers::TestGitRepository;
+
+fn document(i: usize) -> Result<Document> {
+    DocumentBuilder::new()
+        .path(format!("docs/doc_{i}.md"))?
+        .title(format!("Document {i}"))?
+        .content(format!("Content of document {i}: {}", "x".repeat(64)).as_bytes())
+        .build()
+}
+
+async fn all_ids(index: &(impl Index + ?Sized)) -> Result<HashSet<ValidatedDocumentId>> {
+    let query = QueryBuilder::new()
+        .with_text("*")?
+        .with_limit(1000)?
+        .build()?;
+    Ok(index.search(&query).await?.into_iter().collect())
+}
+
+#[tokio::test]
+async fn retryable_storage_rides_out_transient_io_errors() -> Result<()> {
+    let dir = TempDir::new()?;
+    let schedule = FailureSchedule::seeded(11)
+        .with_io_errors(0.3)
+        .with_latency(0.2, Duration::from_millis(1), Duration::from_millis(3))
+        .only(&[Operation::Write]);
+    let inner = FileStorage::open(dir.path().to_str().unwrap()).await?;
+    let mut storage = RetryableStorage::new(FailingStorage::new(inner, schedule.clone()))
+        .with_retry_config(8, Duration::from_millis(1), Duration::from_millis(4));
+
+    let documents = (0..40).map(document).collect::<Result<Vec<_>>>()?;
+    for doc in &documents {
+        storage.insert(doc.clone()).await?;
+    }
+    let stats = schedule.stats();
+    assert!(
+        stats.io_errors > 0,
+        "seed 11 should inject errors: {stats:?}"
+    );
+    assert!(stats.delays > 0);
+
+    schedule.set_enabled(false);
+    for doc in &documents {
+        let stored = storage.get(&doc.id).await?.expect("document stored");
+        assert_eq!(stored.content, doc.content);
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn retryable_storage_gives_up_when_writes_keep_failing() -> Result<()> {
+    let dir = TempDir::new()?;
+    let schedule = FailureSchedule::seeded(5).crash_after(0);
+    let inner = FileStorage::open(dir.path().to_str().unwrap()).await?;
+    let mut storage = RetryableStorage::new(FailingStorage::new(inner, schedule.clone()))
+        .with_retry_config(3, Duration::from_millis(1), Duration::from_millis(2));
+
+    let err = storage.insert(document(0)?).await.unwrap_err();
+    assert!(err.to_string().contains("injected I/O error"), "{err}");
+    assert_eq!(schedule.stats().io_errors, 3);
+
+    // Injected I/O errors never reach the disk
+    schedule.set_enabled(false);
+    assert!(storage.list_all().await?.is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn torn_storage_writes_leave_acknowledged_documents_intact() -> Result<()> {
+    let dir = TempDir::new()?;
+    let path = dir.path().to_str().unwrap().to_string();
+    let schedule = FailureSchedule::seeded(23).with_partial_writes(0.3);
+    let mut storage = FailingStorage::new(FileStorage::open(&path).await?, schedule.clone());
+
+    let documents = (0..30).map(document).collect::<Result<Vec<_>>>()?;
+    let mut acknowledged = Vec::new();
+    let mut torn = Vec::new();
+    for doc in &documents {
+        match storage.insert(doc.clone()).await {
+            Ok(()) => acknowledged.push(doc),
+            Err(_) => torn.push(doc),
+        }
+    }
+    assert!(!torn.is_empty(), "seed 23 should tear some writes");
+    schedule.set_enabled(false);
+    storage.sync().await?;
+    drop(storage);
+
+    let reopened = FileStorage::open(&path).await?;
+    for doc in acknowledged {
+        let stored = reopened
+            .get(&doc.id)
+            .await?
+            .expect("acknowledged write lost");
+        assert_eq!(stored.content, doc.content);
+    }
+    // A write reported as failed may have left a prefix, never foreign content
+    for doc in torn {
+        if let Some(stored) = reopened.get(&doc.id).await? {
+            assert!(doc.content.starts_with(&stored.content));
+        }
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn primary_index_wal_keeps_acknowledged_inserts_across_a_crash() -> Result<()> {
+    let dir = TempDir::new()?;
+    let index_path = dir.path().join("primary_index");
+    let path = index_path.to_str().unwrap();
+    let schedule = FailureSchedule::seeded(3).crash_after(25);
+    let mut index = FailingIndex::new(create_primary_index_for_tests(path).await?, schedule);
+
+    let mut acknowledged = HashSet::new();
+    for i in 0..40 {
+        let id = ValidatedDocumentId::new();
+        let doc_path = ValidatedPath::new(format!("src/file_{i}.rs"))?;
+        if index.insert(id, doc_path).await.is_ok() {
+            acknowledged.insert(id);
+        }
+    }
+    assert_eq!(acknowledged.len(), 25);
+    // The crash also prevents the flush that would persist the tree
+    assert!(index.flush().await.is_err());
+    drop(index);
+
+    // The entry being appended when the process died
+    let wal_path = index_path.join("wal").join("current.wal");
+    let mut wal = std::fs::OpenOptions::new().append(true).open(&wal_path)?;
+    std::io::Write::write_all(&mut wal, br#"{"Insert":{"id":"8c1f"#)?;
+    drop(wal);
+
+    let recovered = create_primary_index_for_tests(path).await?;
+    assert_eq!(all_ids(&recovered).await?, acknowledged);
+    Ok(())
+}
+
+#[tokio::test]
+async fn index_writes_reported_as_failed_never_lose_acknowledged_ones() -> Result<()> {
+    let dir = TempDir::new()?;
+    let path = dir.path().join("primary_index");
+    let path = path.to_str().unwrap();
+    let schedule = FailureSchedule::seeded(9).with_partial_writes(0.4);
+    let mut index = FailingIndex::new(create_primary_index_for_tests(path).await?, schedule);
+
+    let mut acknowledged = HashSet::new();
+    let mut attempted = HashSet::new();
+    for i in 0..40 {
+        let id = ValidatedDocumentId::new();
+        attempted.insert(id);
+        if index
+            .insert(id, ValidatedPath::new(format!("lib/{i}.py"))?)
+            .await
+            .is_ok()
+        {
+            acknowledged.insert(id);
+        }
+    }
+    assert!(acknowledged.len() < attempted.len());
+    drop(index);
+
+    let recovered = all_ids(&create_primary_index_for_tests(path).await?).await?;
+    assert!(recovered.is_superset(&acknowledged));
+    assert!(recovered.is_subset(&attempted));
+    Ok(())
+}
+
+#[tokio::test]
+async fn indexing_pipeline_reports_failed_inserts_and_stays_consistent() -> Result<()> {
+    let repo = TestGitRepository::new_with_comprehensive_content().await?;
+    let db_dir = TempDir::new()?;
+    let db_path = db_dir.path();
+
+    let schedule = FailureSchedule::seeded(17)
+        .with_io_errors(0.3)
+        .only(&[Operation::Write]);
+    let storage = create_file_storage(db_path.join("storage").to_str().unwrap(), Some(100)).await?;
+    let primary_index =
+        create_primary_index_for_tests(db_path.join("primary_index").to_str().unwrap()).await?;
+    let trigram_index =
+        create_trigram_index(db_path.join("trigram_index").to_str().unwrap(), Some(100)).await?;
+    let database = Database {
+        storage: Arc::new(Mutex::new(FailingStorage::new(storage, schedule.clone()))),
+        primary_index: Arc::new(Mutex::new(primary_index)),
+        trigram_index: Arc::new(Mutex::new(trigram_index)),
+        path_cache: Arc::new(RwLock::new(HashMap::new())),
+    };
+
+    let ingested = Arc::new(StdMutex::new(None));
+    let progress = {
+        let ingested = ingested.clone();
+        Arc::new(move |message: &str| {
+            if let Some(counts) = message.strip_prefix("documents_ingested ") {
+                *ingested.lock().unwrap() = Some(counts.to_string());
+            }
+        })
+    };
+    // The repository index document aborts ingestion when its insert fails, so only
+    // per-file inserts are exercised here
+    let result = IndexingService::new(&database, db_path.to_path_buf())
+        .with_progress_callback(Some(progress))
+        .index_codebase(IndexCodebaseOptions {
+            repo_path: Path::new(&repo.path).to_path_buf(),
+            include_commits: false,
+            extract_symbols: Some(false),
+            create_index: false,
+            quiet: true,
+            ..Default::default()
+        })
+        .await?;
+    assert!(result.success, "{:?}", result.errors);
+    assert!(schedule.stats().io_errors > 0);
+
+    // files:<ingested> documents:<created> errors:<failed inserts>
+    let counts = ingested
+        .lock()
+        .unwrap()
+        .clone()
+        .expect("ingestion counts reported");
+    let count = |key: &str| -> usize {
+        counts
+            .split_whitespace()
+            .find_map(|part| part.strip_prefix(key))
+            .and_then(|value| value.parse().ok())
+            .unwrap()
+    };
+    assert!(count("errors:") > 0, "{counts}");
+    assert_eq!(count("documents:"), result.files_processed);
+
+    schedule.set_enabled(false);
+    let stored = database.storage.lock().await.list_all().await?;
+    assert_eq!(stored.len(), result.files_processed);
+    for doc in &stored {
+        let relative = doc.title.as_str().strip_prefix("File: ").unwrap();
+        let on_disk = std::fs::read(Path::new(&repo.path).join(relative))?;
+        // Stored content carries the tag frontmatter ahead of the file body
+        assert!(
+            doc.content.ends_with(&on_disk),
+            "{} stored incompletely",
+            doc.path.as_str()
+        );
+    }
+
+    // The rebuilt primary index matches what storage holds, not what ingestion attempted
+    let indexed = all_ids(&*database.primary_index.lock().await).await?;
+    let stored_ids: HashSet<_> = stored.iter().map(|doc| doc.id).collect();
+    assert_eq!(indexed, stored_ids);
+    Ok(())
+}
@@ -0,0 +1,125 @@
+// End-to-end checks of indexing and analysis against a generated fixture repository
+// The fixture records the symbols, calls, cycles and dead code it was built with, so
+// every expectation comes from its manifest rather than from hand-written sources
+
+use anyhow::{ensure, Result};
+use kotadb::fixture_generator::{generate, FixtureOptions, FixtureSize};
+use kotadb::services::{find_unused_symbols, index_metrics};
+use std::path::Path;
+use std::process::Command;
+use tempfile::TempDir;
+
+/// Run the kotadb binary against `db_path` and return its stdout
+fn kotadb(db_path: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new(env!("CARGO_BIN_EXE_kotadb"))
+        .arg("--db-path")
+        .arg(db_path)
+        .args(args)
+        .output()?;
+    ensure!(
+        output.status.success(),
+        "kotadb {} failed: {}",
+        args.join(" "),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    Ok(String::from_utf8(output.stdout)?)
+}
+
+#[test]
+fn analysis_matches_the_generated_fixture() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let repo = temp_dir.path().join("fixture");
+    let db_path = temp_dir.path().join("db");
+    let fixture = generate(&FixtureOptions {
+        size: FixtureSize::Small,
+        ..FixtureOptions::default()
+    });
+    fixture.write(&repo, false)?;
+    kotadb(&db_path, &["index-codebase", repo.to_str().unwrap()])?;
+    let expected = &fixture.expected;
+
+    for callee in expected.called_symbols() {
+        let output = kotadb(&db_path, &["find-callers", callee, "-f", "json"])?;
+        let result: serde_json::Value = serde_json::from_str(&output)?;
+        let found: Vec<(&str, &str, u64)> = result["callers"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|caller| {
+                Some((
+                    caller["caller"].as_str()?,
+                    caller["file_path"].as_str()?,
+                    caller["line_number"].as_u64()?,
+                ))
+            })
+            .collect();
+        for call in expected.callers_of(callee) {
+            assert!(
+                found.contains(&(call.caller.as_str(), call.file.as_str(), call.line as u64)),
+                "{} -> {} at {}:{} not found in {:?}",
+                call.caller,
+                call.callee,
+                call.file,
+                call.line,
+                found
+            );
+        }
+    }
+
+    let unused: Vec<String> = find_unused_symbols(&db_path)?
+        .into_iter()
+        .filter_map(|symbol| symbol.qualified_name.rsplit("::").next().map(String::from))
+        .collect();
+    for name in &expected.dead_code {
+        assert!(unused.contains(name), "dead function {name} not reported");
+    }
+    for name in expected.called_symbols() {
+        assert!(
+            !unused.iter().any(|u| u == name),
+            "called function {name} reported unused"
+        );
+    }
+
+    assert_eq!(index_metrics(&db_path, &[]).cycles, expected.cycles.len());
+    Ok(())
+}
+
+#[test]
+fn generate_fixture_is_reproducible() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let db_path = temp_dir.path().join("db");
+    let mut commits = Vec::new();
+    for name in ["first", "second"] {
+        let output = temp_dir.path().join(name);
+        let report = kotadb(
+            &db_path,
+            &[
+                "generate-fixture",
+                "--languages",
+                "rust,ts",
+                "--size",
+                "small",
+                "--seed",
+                "7",
+                "-o",
+                output.to_str().unwrap(),
+                "-f",
+                "json",
+            ],
+        )?;
+        let report: serde_json::Value = serde_json::from_str(&report)?;
+        commits.push(report["commit"].as_str().unwrap_or_default().to_string());
+    }
+
+    assert!(!commits[0].is_empty());
+    assert_eq!(commits[0], commits[1]);
+
+    // A second run into the same directory needs --force
+    let output = temp_dir.path().join("first");
+    assert!(kotadb(
+        &db_path,
+        &["generate-fixture", "-o", output.to_str().unwrap()]
+    )
+    .is_err());
+    Ok(())
+}
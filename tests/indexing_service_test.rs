@@ -5,9 +5,10 @@ use anyhow::Result;
 use kotadb::{
     create_file_storage, create_primary_index_for_tests, create_trigram_index,
     database::Database,
+    repository_links::RepositoryLinks,
     services::{
         DatabaseAccess, IncrementalUpdateOptions, IndexCodebaseOptions, IndexGitOptions,
-        IndexingService,
+        IndexingService, SearchOptions, SearchService, SymbolSearchOptions,
     },
 };
 use std::{collections::HashMap, fs, path::PathBuf, sync::Arc};
@@ -499,3 +500,83 @@ async fn test_indexing_service_large_file_handling() -> Result<()> {
 
     Ok(())
 }
+
+#[cfg(feature = "tree-sitter-parsing")]
+#[tokio::test]
+async fn test_index_codebase_records_commit_pinned_web_urls() -> Result<()> {
+    let (database, temp_dir) = create_test_database().await?;
+    let repo_path = create_test_repository(temp_dir.path())?;
+    std::process::Command::new("git")
+        .args([
+            "remote",
+            "add",
+            "origin",
+            "git@github.com:kotadb/example.git",
+        ])
+        .current_dir(&repo_path)
+        .output()?;
+    let head = std::process::Command::new("git")
+        .args(["rev-parse", "HEAD"])
+        .current_dir(&repo_path)
+        .output()?;
+    let head = String::from_utf8(head.stdout)?.trim().to_string();
+
+    let db_path = temp_dir.path().to_path_buf();
+    let result = IndexingService::new(&database, db_path.clone())
+        .index_codebase(IndexCodebaseOptions {
+            repo_path,
+            extract_symbols: Some(true),
+            quiet: true,
+            ..Default::default()
+        })
+        .await?;
+    assert!(result.success, "{:?}", result.errors);
+
+    let links = RepositoryLinks::load(&db_path)?;
+    assert_eq!(links.repositories.len(), 1);
+    assert_eq!(links.repositories[0].commit.as_deref(), Some(head.as_str()));
+
+    let base = format!("https://github.com/kotadb/example/blob/{head}/");
+    let search = SearchService::new(&database, db_path);
+    let symbols = search
+        .search_symbols(SymbolSearchOptions {
+            pattern: "TestStruct".to_string(),
+            quiet: true,
+            ..Default::default()
+        })
+        .await?;
+    let symbol = symbols.matches.first().expect("TestStruct indexed");
+    assert_eq!(
+        symbol.web_url,
+        Some(format!(
+            "{base}src/main.rs#L{}-L{}",
+            symbol.start_line, symbol.end_line
+        ))
+    );
+
+    let content = search
+        .search_content(SearchOptions {
+            query: "utility_function".to_string(),
+            context: "none".to_string(),
+            quiet: true,
+            ..Default::default()
+        })
+        .await?;
+    let urls: Vec<&String> = content
+        .web_urls
+        .values()
+        .chain(
+            content
+                .llm_response
+                .iter()
+                .flat_map(|response| response.results.iter())
+                .filter_map(|result| result.web_url.as_ref()),
+        )
+        .collect();
+    assert!(
+        urls.contains(&&format!("{base}src/utils/mod.rs")),
+        "{urls:?}"
+    );
+
+    Ok(())
+}
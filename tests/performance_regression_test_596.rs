@@ -12,7 +12,7 @@ use tokio::sync::{Mutex, RwLock};
 
 use kotadb::{
     create_file_storage, create_primary_index, create_trigram_index,
-    services::search_service::{DatabaseAccess, SearchOptions, SearchService},
+    services::search_service::{DatabaseAccess, SearchGrouping, SearchOptions, SearchService},
     DocumentBuilder, Index, Storage, ValidatedDocumentId,
 };
 
@@ -554,6 +554,7 @@ async fn test_performance_regression_protection_issue_596() -> Result<()> {
             tags: None,
             context: "minimal".to_string(), // Use new default context
             quiet: false,
+            group_by: SearchGrouping::default(),
         };
 
         let start_time = Instant::now();
@@ -645,6 +646,7 @@ async fn test_context_mode_performance_difference() -> Result<()> {
         tags: None,
         context: "minimal".to_string(),
         quiet: false,
+        group_by: SearchGrouping::default(),
     };
 
     let start_time = Instant::now();
@@ -658,6 +660,7 @@ async fn test_context_mode_performance_difference() -> Result<()> {
         tags: None,
         context: "medium".to_string(),
         quiet: false,
+        group_by: SearchGrouping::default(),
     };
 
     let start_time = Instant::now();
@@ -722,6 +725,7 @@ async fn test_bulk_search_performance() -> Result<()> {
             tags: None,
             context: "minimal".to_string(),
             quiet: true,
+            group_by: SearchGrouping::default(),
         };
 
         let result = search_service.search_content(options).await?;
@@ -784,6 +788,7 @@ async fn test_concurrent_search_performance() -> Result<()> {
                 tags: None,
                 context: "minimal".to_string(),
                 quiet: true,
+                group_by: SearchGrouping::default(),
             };
 
             let start = Instant::now();
@@ -46,7 +46,7 @@ async fn test_persistence_basic_save_and_load() -> Result<()> {
 
     // Verify files were created
     assert!(index_path.join("meta").join("metadata.json").exists());
-    assert!(index_path.join("data").join("btree_data.json").exists());
+    assert!(index_path.join("data").join("btree_leaves.bin").exists());
 
     // Load index from disk
     {
@@ -136,9 +136,9 @@ async fn test_persistence_corrupted_btree_data() -> Result<()> {
         index.flush().await?;
     }
 
-    // Corrupt B+ tree data file
-    let btree_path = index_path.join("data").join("btree_data.json");
-    fs::write(&btree_path, "not valid json at all")?;
+    // Corrupt B+ tree leaf pages
+    let btree_path = index_path.join("data").join("btree_leaves.bin");
+    fs::write(&btree_path, "not valid leaf pages at all")?;
 
     // Try to load - should fail gracefully
     let result = create_primary_index_for_tests(index_path.to_str().unwrap()).await;
@@ -146,10 +146,10 @@ async fn test_persistence_corrupted_btree_data() -> Result<()> {
         result.is_err(),
         "Should fail to load with corrupted B+ tree data"
     );
-    let error_message = result.err().unwrap().to_string();
+    let error_message = format!("{:#}", result.err().unwrap());
     assert!(
-        error_message.contains("Failed to deserialize B+ tree data"),
-        "Error should mention B+ tree deserialization: {error_message}"
+        error_message.contains("Invalid B+ tree leaf pages"),
+        "Error should mention the leaf pages: {error_message}"
     );
 
     Ok(())
@@ -192,6 +192,55 @@ async fn test_persistence_invalid_uuid_in_data() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_persistence_migrates_legacy_json_mappings() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let index_path = temp_dir.path().join("legacy_json_test");
+
+    fs::create_dir_all(index_path.join("meta"))?;
+    fs::create_dir_all(index_path.join("data"))?;
+    fs::create_dir_all(index_path.join("wal"))?;
+
+    let ids: Vec<Uuid> = (0..12).map(|_| Uuid::new_v4()).collect();
+    let mappings: std::collections::HashMap<String, String> = ids
+        .iter()
+        .enumerate()
+        .map(|(i, id)| (id.to_string(), format!("legacy/{i}.md")))
+        .collect();
+    fs::write(
+        index_path.join("data").join("btree_data.json"),
+        serde_json::to_string(&mappings)?,
+    )?;
+
+    let query = QueryBuilder::new()
+        .with_text("*")?
+        .with_limit(100)?
+        .build()?;
+
+    // Older indices load from the JSON mappings and are rewritten as leaf pages on flush
+    {
+        let mut index = create_primary_index_for_tests(index_path.to_str().unwrap()).await?;
+        assert_eq!(index.search(&query).await?.len(), 12);
+        index.flush().await?;
+    }
+    assert!(index_path.join("data").join("btree_leaves.bin").exists());
+    assert!(!index_path.join("data").join("btree_data.json").exists());
+
+    let index = create_primary_index_for_tests(index_path.to_str().unwrap()).await?;
+    let mut results: Vec<Uuid> = index
+        .search(&query)
+        .await?
+        .iter()
+        .map(|id| id.as_uuid())
+        .collect();
+    let mut expected = ids.clone();
+    results.sort();
+    expected.sort();
+    assert_eq!(results, expected);
+
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_persistence_invalid_path_in_data() -> Result<()> {
     let temp_dir = TempDir::new()?;
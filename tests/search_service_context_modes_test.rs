@@ -11,8 +11,10 @@ use tokio::sync::{Mutex, RwLock};
 
 use kotadb::{
     create_file_storage, create_primary_index, create_trigram_index,
-    services::search_service::{DatabaseAccess, SearchOptions, SearchService, SearchType},
-    DocumentBuilder, Index, Storage, ValidatedDocumentId,
+    services::search_service::{
+        DatabaseAccess, SearchGrouping, SearchOptions, SearchService, SearchType,
+    },
+    DocumentBuilder, DocumentFilters, Index, Storage, ValidatedDocumentId,
 };
 
 /// Test implementation of DatabaseAccess trait for SearchService testing
@@ -136,6 +138,7 @@ async fn test_search_context_none_uses_fast_search() -> Result<()> {
         tags: None,
         context: "none".to_string(),
         quiet: false,
+        group_by: SearchGrouping::default(),
     };
 
     let start_time = std::time::Instant::now();
@@ -178,6 +181,7 @@ async fn test_search_context_minimal_uses_fast_search() -> Result<()> {
         tags: None,
         context: "minimal".to_string(), // This is the NEW default from PR #597
         quiet: false,
+        group_by: SearchGrouping::default(),
     };
 
     let start_time = std::time::Instant::now();
@@ -217,6 +221,7 @@ async fn test_search_context_medium_uses_llm_search() -> Result<()> {
         tags: None,
         context: "medium".to_string(), // Should trigger LLM search
         quiet: false,
+        group_by: SearchGrouping::default(),
     };
 
     let result = search_service.search_content(options).await;
@@ -255,6 +260,7 @@ async fn test_search_context_full_uses_llm_search() -> Result<()> {
         tags: None,
         context: "full".to_string(), // Should trigger LLM search
         quiet: false,
+        group_by: SearchGrouping::default(),
     };
 
     let result = search_service.search_content(options).await;
@@ -294,6 +300,7 @@ async fn test_search_wildcard_always_uses_regular_search() -> Result<()> {
         tags: None,
         context: "medium".to_string(), // Even with medium context
         quiet: false,
+        group_by: SearchGrouping::default(),
     };
 
     let result = search_service.search_content(options).await?;
@@ -320,6 +327,7 @@ async fn test_search_empty_query_handling() -> Result<()> {
         tags: None,
         context: "medium".to_string(),
         quiet: false,
+        group_by: SearchGrouping::default(),
     };
 
     let result = search_service.search_content(options).await?;
@@ -356,6 +364,7 @@ async fn test_performance_regression_protection() -> Result<()> {
             tags: None,
             context: context.to_string(),
             quiet: false,
+            group_by: SearchGrouping::default(),
         };
 
         let start_time = std::time::Instant::now();
@@ -423,6 +432,7 @@ async fn test_context_mode_conditional_logic() -> Result<()> {
             tags: None,
             context: context.to_string(),
             quiet: false,
+            group_by: SearchGrouping::default(),
         };
 
         let result = search_service.search_content(options).await?;
@@ -480,6 +490,7 @@ async fn test_llm_fallback_behavior() -> Result<()> {
         tags: None,
         context: "medium".to_string(), // Should try LLM
         quiet: false,
+        group_by: SearchGrouping::default(),
     };
 
     let result = search_service.search_content(options).await?;
@@ -504,3 +515,146 @@ async fn test_llm_fallback_behavior() -> Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn test_group_by_file_and_match() -> Result<()> {
+    let (_temp_dir, database) = setup_test_database().await?;
+    // A second stored version of a file, as left behind by an interrupted re-index
+    let duplicate = DocumentBuilder::new()
+        .path("src/complex.rs")
+        .unwrap()
+        .title("Complex Code")
+        .unwrap()
+        .content(b"pub struct Database { storage: Storage }")
+        .build()?;
+    database
+        .storage
+        .lock()
+        .await
+        .insert(duplicate.clone())
+        .await?;
+    database
+        .trigram_index
+        .lock()
+        .await
+        .insert_with_content(duplicate.id, duplicate.path.clone(), &duplicate.content)
+        .await?;
+
+    let search_service = SearchService::new(&database, PathBuf::from("/tmp/test_symbols"));
+    let search = |context: &str, group_by| SearchOptions {
+        query: "Storage".to_string(),
+        limit: 10,
+        tags: None,
+        context: context.to_string(),
+        quiet: true,
+        group_by,
+    };
+
+    let by_file = search_service
+        .search_content(search("none", SearchGrouping::File))
+        .await?;
+    let paths: Vec<&str> = by_file.documents.iter().map(|d| d.path.as_str()).collect();
+    assert_eq!(paths, vec!["src/complex.rs"]);
+
+    let by_match = search_service
+        .search_content(search("none", SearchGrouping::Match))
+        .await?;
+    assert_eq!(by_match.documents.len(), 2);
+
+    let by_file = search_service
+        .search_content(search("full", SearchGrouping::File))
+        .await?;
+    if let Some(response) = by_file.llm_response {
+        assert_eq!(response.results.len(), 1);
+    }
+
+    let by_match = search_service
+        .search_content(search("full", SearchGrouping::Match))
+        .await?;
+    if let Some(response) = by_match.llm_response {
+        assert!(response.results.len() > 1);
+        for hit in &response.results {
+            assert_eq!(hit.path, "src/complex.rs");
+            let details = &hit.match_details;
+            assert!(details.exact_matches.len() + details.term_matches.len() <= 1);
+        }
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_document_filters_restrict_wildcard_and_text_searches() -> Result<()> {
+    let (_temp_dir, database) = setup_test_database().await?;
+    let search = |query: &str| SearchOptions {
+        query: query.to_string(),
+        limit: 10,
+        tags: None,
+        context: "medium".to_string(),
+        quiet: false,
+        group_by: SearchGrouping::default(),
+    };
+    let paths = |documents: &[kotadb::Document]| -> Vec<String> {
+        let mut paths: Vec<_> = documents.iter().map(|d| d.path.to_string()).collect();
+        paths.sort();
+        paths
+    };
+    let filtered = |filters: DocumentFilters| {
+        SearchService::new(&database, PathBuf::from("/tmp/test_symbols"))
+            .with_document_filters(filters)
+    };
+
+    let small = DocumentFilters {
+        max_size: Some(100),
+        ..Default::default()
+    };
+    let result = filtered(small.clone()).search_content(search("*")).await?;
+    assert_eq!(paths(&result.documents), vec!["src/example.rs"]);
+    assert_eq!(result.total_count, 1);
+
+    // Filtered searches skip LLM-optimized search and still check sizes of text matches
+    let result = filtered(small).search_content(search("async")).await?;
+    assert!(matches!(result.search_type, SearchType::RegularSearch));
+    assert_eq!(paths(&result.documents), vec!["src/example.rs"]);
+
+    let large = DocumentFilters {
+        min_size: Some(100),
+        ..Default::default()
+    };
+    let result = filtered(large).search_content(search("async")).await?;
+    assert_eq!(paths(&result.documents), vec!["src/complex.rs"]);
+
+    let markdown = DocumentFilters::default().with_extensions(["md"]);
+    assert!(filtered(markdown.clone())
+        .search_content(search("*"))
+        .await?
+        .documents
+        .is_empty());
+    assert!(filtered(markdown)
+        .search_content(search("async"))
+        .await?
+        .documents
+        .is_empty());
+
+    let top_level = DocumentFilters {
+        max_depth: Some(0),
+        ..Default::default()
+    };
+    assert!(filtered(top_level)
+        .search_content(search("*"))
+        .await?
+        .documents
+        .is_empty());
+    let rust = DocumentFilters {
+        max_depth: Some(1),
+        ..Default::default()
+    }
+    .with_extensions(["RS"]);
+    let result = filtered(rust).search_content(search("*")).await?;
+    assert_eq!(
+        paths(&result.documents),
+        vec!["src/complex.rs", "src/example.rs"]
+    );
+
+    Ok(())
+}
@@ -0,0 +1,440 @@
+// SearchService fused search tests
+// Covers reciprocal-rank fusion of trigram, symbol and vector results with per-source provenance,
+// routing of queries by their shape, "did you mean" suggestions for empty results and
+// synonym expansion of content queries
+
+use anyhow::Result;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tempfile::TempDir;
+use tokio::sync::{Mutex, RwLock};
+
+use kotadb::{
+    create_file_storage, create_primary_index, create_trigram_index,
+    embeddings::{models, ProviderConfig},
+    query_deadline::QueryDeadline,
+    services::search_service::{
+        DatabaseAccess, FusedSearchOptions, FusionSource, QueryRoute, SearchGrouping,
+        SearchOptions, SearchService, RRF_K,
+    },
+    synonyms::SynonymDictionary,
+    DocumentBuilder, Index, SemanticSearchEngine, Storage, ValidatedDocumentId,
+};
+
+struct TestDatabase {
+    storage: Arc<Mutex<dyn Storage>>,
+    primary_index: Arc<Mutex<dyn Index>>,
+    trigram_index: Arc<Mutex<dyn Index>>,
+    path_cache: Arc<RwLock<HashMap<String, ValidatedDocumentId>>>,
+}
+
+impl DatabaseAccess for TestDatabase {
+    fn storage(&self) -> Arc<Mutex<dyn Storage>> {
+        self.storage.clone()
+    }
+
+    fn primary_index(&self) -> Arc<Mutex<dyn Index>> {
+        self.primary_index.clone()
+    }
+
+    fn trigram_index(&self) -> Arc<Mutex<dyn Index>> {
+        self.trigram_index.clone()
+    }
+
+    fn path_cache(&self) -> Arc<RwLock<HashMap<String, ValidatedDocumentId>>> {
+        self.path_cache.clone()
+    }
+}
+
+async fn setup_test_database(documents: &[(&str, &str)]) -> Result<(TempDir, TestDatabase)> {
+    let temp_dir = TempDir::new()?;
+    let db_path = temp_dir.path().to_path_buf();
+
+    let storage = create_file_storage(db_path.join("storage").to_str().unwrap(), None).await?;
+    let primary_index =
+        create_primary_index(db_path.join("primary").to_str().unwrap(), None).await?;
+    let trigram_index =
+        create_trigram_index(db_path.join("trigram").to_str().unwrap(), None).await?;
+
+    let database = TestDatabase {
+        storage: Arc::new(Mutex::new(storage)),
+        primary_index: Arc::new(Mutex::new(primary_index)),
+        trigram_index: Arc::new(Mutex::new(trigram_index)),
+        path_cache: Arc::new(RwLock::new(HashMap::new())),
+    };
+
+    {
+        let mut storage = database.storage.lock().await;
+        let mut primary = database.primary_index.lock().await;
+        let mut trigram = database.trigram_index.lock().await;
+        for (path, content) in documents {
+            let doc = DocumentBuilder::new()
+                .path(*path)?
+                .title(*path)?
+                .content(content.as_bytes())
+                .build()?;
+            storage.insert(doc.clone()).await?;
+            primary.insert(doc.id, doc.path.clone()).await?;
+            trigram
+                .insert_with_content(doc.id, doc.path.clone(), &doc.content)
+                .await?;
+        }
+    }
+
+    Ok((temp_dir, database))
+}
+
+#[tokio::test]
+async fn test_fused_search_reports_provenance_and_rrf_scores() -> Result<()> {
+    let (temp_dir, database) = setup_test_database(&[
+        (
+            "src/engine.rs",
+            "pub fn open_storage_engine() -> StorageEngine { todo!() }",
+        ),
+        (
+            "src/http.rs",
+            "pub fn serve_requests(port: u16) { listen(port) }",
+        ),
+    ])
+    .await?;
+    let search_service = SearchService::new(&database, temp_dir.path().to_path_buf());
+
+    let result = search_service
+        .search_fused(FusedSearchOptions {
+            query: "storage engine".to_string(),
+            limit: 5,
+            quiet: true,
+        })
+        .await?;
+
+    assert_eq!(result.route, QueryRoute::Prose);
+    // No symbols database was built, so the symbol source contributes an empty ranking
+    assert_eq!(
+        result.sources_searched,
+        vec![FusionSource::Trigram, FusionSource::Symbol]
+    );
+    assert!(result.source_errors.is_empty());
+    assert_eq!(result.results.len(), 1);
+
+    let hit = &result.results[0];
+    assert_eq!(hit.path, "src/engine.rs");
+    assert_eq!(hit.sources.len(), 1);
+    assert_eq!(hit.sources[0].source, FusionSource::Trigram);
+    assert_eq!(hit.sources[0].rank, 1);
+    assert!((hit.score - 1.0 / (RRF_K + 1.0)).abs() < 1e-9);
+    assert!(hit.symbols.is_empty());
+
+    Ok(())
+}
+
+/// OpenAI-compatible embeddings endpoint answering every text with the same vector
+async fn spawn_embedding_server() -> Result<String> {
+    use axum::{routing::post, Json, Router};
+    use serde_json::{json, Value};
+
+    async fn embeddings(Json(body): Json<Value>) -> Json<Value> {
+        let count = body["input"].as_array().map_or(1, Vec::len);
+        let data: Vec<Value> = (0..count)
+            .map(|index| json!({ "embedding": vec![0.5f32; 1536], "index": index }))
+            .collect();
+        Json(json!({ "data": data, "usage": { "total_tokens": count } }))
+    }
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let address = listener.local_addr()?;
+    tokio::spawn(async move {
+        let _ = axum::serve(
+            listener,
+            Router::new().route("/embeddings", post(embeddings)),
+        )
+        .await;
+    });
+    Ok(format!("http://{address}"))
+}
+
+#[tokio::test]
+async fn test_fused_search_includes_the_semantic_source() -> Result<()> {
+    let (temp_dir, database) = setup_test_database(&[(
+        "src/engine.rs",
+        "pub fn open_storage_engine() -> StorageEngine { todo!() }",
+    )])
+    .await?;
+
+    let mut embedding_config = models::openai_text_embedding_3_small("test-key".to_string());
+    embedding_config.provider_config = ProviderConfig::OpenAI {
+        api_key: "test-key".to_string(),
+        api_base: Some(spawn_embedding_server().await?),
+        organization: None,
+    };
+    let semantic_storage =
+        create_file_storage(temp_dir.path().join("semantic").to_str().unwrap(), None).await?;
+    let mut engine = SemanticSearchEngine::new(
+        Box::new(semantic_storage),
+        temp_dir
+            .path()
+            .join("vectors/documents.idx")
+            .to_str()
+            .unwrap(),
+        embedding_config,
+    )
+    .await?;
+    engine
+        .insert_document(
+            DocumentBuilder::new()
+                .path("docs/architecture.md")?
+                .title("Architecture")?
+                .content(b"How documents are persisted")
+                .build()?,
+        )
+        .await?;
+
+    let result = SearchService::new(&database, temp_dir.path().to_path_buf())
+        .with_semantic_engine(Arc::new(Mutex::new(engine)))
+        .search_fused(FusedSearchOptions {
+            query: "storage engine".to_string(),
+            limit: 5,
+            quiet: true,
+        })
+        .await?;
+
+    assert!(
+        result.source_errors.is_empty(),
+        "{:?}",
+        result.source_errors
+    );
+    assert_eq!(
+        result.sources_searched,
+        vec![
+            FusionSource::Trigram,
+            FusionSource::Semantic,
+            FusionSource::Symbol
+        ]
+    );
+    let semantic_hit = result
+        .results
+        .iter()
+        .find(|hit| hit.path == "docs/architecture.md")
+        .expect("vector match is fused into the results");
+    assert_eq!(semantic_hit.sources[0].source, FusionSource::Semantic);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_fused_search_with_empty_query_searches_nothing() -> Result<()> {
+    let (temp_dir, database) =
+        setup_test_database(&[("src/engine.rs", "pub fn open_storage_engine() {}")]).await?;
+    let search_service = SearchService::new(&database, temp_dir.path().to_path_buf());
+
+    let result = search_service
+        .search_fused(FusedSearchOptions {
+            query: "   ".to_string(),
+            ..Default::default()
+        })
+        .await?;
+
+    assert!(result.results.is_empty());
+    assert!(result.sources_searched.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn test_query_route_detects_symbols_paths_and_prose() {
+    for query in [
+        "FileStorage::new",
+        "StorageEngine",
+        "open_storage_engine",
+        "Storage*",
+        "self.insert()",
+    ] {
+        assert_eq!(QueryRoute::classify(query), QueryRoute::Symbol, "{query}");
+    }
+    for query in [
+        "src/**.rs",
+        "src/engine.rs",
+        "lib.rs",
+        "*.toml",
+        "tests\\mod.rs",
+    ] {
+        assert_eq!(QueryRoute::classify(query), QueryRoute::Path, "{query}");
+    }
+    for query in [
+        "storage engine",
+        "storage",
+        "Config",
+        "how is FileStorage opened",
+        "",
+    ] {
+        assert_eq!(QueryRoute::classify(query), QueryRoute::Prose, "{query}");
+    }
+}
+
+#[tokio::test]
+async fn test_path_route_matches_document_paths_only() -> Result<()> {
+    let (temp_dir, database) = setup_test_database(&[
+        ("src/engine.rs", "pub fn open_storage_engine() {}"),
+        ("src/http.rs", "// see src/engine.rs"),
+        ("docs/engine.md", "# Engine"),
+    ])
+    .await?;
+    let search_service = SearchService::new(&database, temp_dir.path().to_path_buf());
+
+    let result = search_service
+        .search_fused(FusedSearchOptions {
+            query: "src/**.rs".to_string(),
+            ..Default::default()
+        })
+        .await?;
+
+    assert_eq!(result.route, QueryRoute::Path);
+    assert_eq!(result.sources_searched, vec![FusionSource::Path]);
+    let mut paths: Vec<&str> = result.results.iter().map(|hit| hit.path.as_str()).collect();
+    paths.sort();
+    assert_eq!(paths, vec!["src/engine.rs", "src/http.rs"]);
+
+    let result = search_service
+        .search_fused(FusedSearchOptions {
+            query: "engine.rs".to_string(),
+            ..Default::default()
+        })
+        .await?;
+    let paths: Vec<&str> = result.results.iter().map(|hit| hit.path.as_str()).collect();
+    assert_eq!(paths, vec!["src/engine.rs"]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_zero_result_searches_suggest_nearby_paths() -> Result<()> {
+    let (temp_dir, database) = setup_test_database(&[
+        ("src/engine.rs", "pub fn open_storage_engine() {}"),
+        ("src/http.rs", "pub fn serve_requests(port: u16) {}"),
+    ])
+    .await?;
+    let search_service = SearchService::new(&database, temp_dir.path().to_path_buf());
+
+    let fused = search_service
+        .search_fused(FusedSearchOptions {
+            query: "src/engnie.rs".to_string(),
+            ..Default::default()
+        })
+        .await?;
+    assert!(fused.results.is_empty());
+    assert_eq!(fused.suggestions, vec!["src/engine.rs"]);
+
+    let content = search_service
+        .search_content(SearchOptions {
+            query: "engnie".to_string(),
+            limit: 10,
+            tags: None,
+            context: "none".to_string(),
+            quiet: true,
+            group_by: SearchGrouping::default(),
+        })
+        .await?;
+    assert!(content.documents.is_empty());
+    assert_eq!(content.suggestions, vec!["src/engine.rs"]);
+
+    // Searches that find something never carry suggestions
+    let found = search_service
+        .search_fused(FusedSearchOptions {
+            query: "src/engine.rs".to_string(),
+            ..Default::default()
+        })
+        .await?;
+    assert_eq!(found.results.len(), 1);
+    assert!(found.suggestions.is_empty());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_synonyms_surface_documents_that_only_match_a_variant() -> Result<()> {
+    let (temp_dir, database) = setup_test_database(&[
+        ("src/cfg.rs", "fn load cfg and load configuration"),
+        ("src/settings.rs", "pub fn load configuration() {}"),
+        ("src/http.rs", "pub fn serve_requests(port: u16) {}"),
+    ])
+    .await?;
+    let options = SearchOptions {
+        query: "load cfg".to_string(),
+        limit: 10,
+        tags: None,
+        context: "none".to_string(),
+        quiet: true,
+        group_by: SearchGrouping::default(),
+    };
+
+    let plain = SearchService::new(&database, temp_dir.path().to_path_buf())
+        .search_content(options.clone())
+        .await?;
+    let paths: Vec<&str> = plain.documents.iter().map(|d| d.path.as_str()).collect();
+    assert_eq!(paths, vec!["src/cfg.rs"]);
+
+    let mut dictionary = SynonymDictionary::default();
+    dictionary.add("cfg", &["configuration".to_string()])?;
+    dictionary.save(temp_dir.path())?;
+
+    let expanded = SearchService::new(&database, temp_dir.path().to_path_buf())
+        .search_content(options)
+        .await?;
+    assert_eq!(expanded.expanded_queries, vec!["load configuration"]);
+    let mut paths: Vec<&str> = expanded.documents.iter().map(|d| d.path.as_str()).collect();
+    paths.sort();
+    assert_eq!(paths, vec!["src/cfg.rs", "src/settings.rs"]);
+    // A document matching the query and a variant is found and counted once
+    assert_eq!(expanded.total_count, 2);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_searches_past_their_deadline_return_partial_results_marked_timed_out() -> Result<()> {
+    let (temp_dir, database) = setup_test_database(&[
+        ("src/engine.rs", "pub fn open_storage_engine() {}"),
+        ("src/http.rs", "pub fn serve_requests(port: u16) {}"),
+    ])
+    .await?;
+    let options = SearchOptions {
+        query: "storage".to_string(),
+        limit: 10,
+        tags: None,
+        context: "none".to_string(),
+        quiet: true,
+        group_by: SearchGrouping::default(),
+    };
+
+    let complete = SearchService::new(&database, temp_dir.path().to_path_buf())
+        .with_deadline(QueryDeadline::after(Duration::from_secs(60)))
+        .search_content(options.clone())
+        .await?;
+    assert_eq!(complete.documents.len(), 1);
+    assert!(!complete.timed_out);
+    assert!(serde_json::to_value(&complete)?.get("timed_out").is_none());
+
+    let expired = QueryDeadline::after(Duration::ZERO);
+    let partial = SearchService::new(&database, temp_dir.path().to_path_buf())
+        .with_deadline(expired)
+        .search_content(options)
+        .await?;
+    assert!(partial.timed_out);
+    assert!(partial.documents.is_empty());
+    // Suggestions are not computed once the deadline passed
+    assert!(partial.suggestions.is_empty());
+    assert_eq!(serde_json::to_value(&partial)?["timed_out"], true);
+
+    let fused = SearchService::new(&database, temp_dir.path().to_path_buf())
+        .with_deadline(expired)
+        .search_fused(FusedSearchOptions {
+            query: "storage engine".to_string(),
+            limit: 5,
+            quiet: true,
+        })
+        .await?;
+    assert!(fused.timed_out);
+    assert!(fused.results.is_empty());
+
+    Ok(())
+}
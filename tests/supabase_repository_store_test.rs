@@ -113,6 +113,22 @@ async fn supabase_repository_store_happy_path() -> Result<()> {
         .expect("job status should exist");
     assert_eq!(status.status, "completed");
 
+    // Settings patches merge options and leave other keys alone
+    let settings = store
+        .update_repository_settings(
+            repository.id,
+            user_id,
+            &json!({ "options": { "include_commits": false } }),
+        )
+        .await?
+        .expect("repository settings should exist");
+    assert_eq!(settings["branch"], "main");
+    assert_eq!(settings["options"]["include_commits"], false);
+    assert!(store
+        .update_repository_settings(repository.id, Uuid::new_v4(), &json!({}))
+        .await?
+        .is_none());
+
     // Allow async clean-up before container teardown
     sleep(Duration::from_millis(100)).await;
 